@@ -28,7 +28,9 @@ use vulkano::instance::Instance;
 use vulkano::instance::InstanceExtensions;
 use vulkano::pipeline::ComputePipeline;
 use vulkano::sync::now;
+use vulkano::sync::AccessFlagBits;
 use vulkano::sync::GpuFuture;
+use vulkano::sync::PipelineStages;
 
 use std::sync::Arc;
 
@@ -125,7 +127,7 @@ void main() {
     // descriptor sets that each contain the buffer you want to run the shader on.
     let set = Arc::new(simple_descriptor_set!(pipeline.clone(), 0, {
         data: data_buffer.clone()
-    }));
+    }).unwrap());
 
     // In order to execute our operation, we have to build a command buffer.
     let command_buffer = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap()
@@ -137,6 +139,12 @@ void main() {
         // clonable anyway). In this example we would avoid cloning them since this is the last
         // time we use them, but in a real code you would probably need to clone them.
         .dispatch([1024, 1, 1], pipeline.clone(), set.clone(), ()).unwrap()
+        // The buffer is going to be read by the CPU, so the writes of the shader must be made
+        // visible to the host once the command buffer has finished executing.
+        .compute_write_buffer_barrier(data_buffer.clone(),
+                                      PipelineStages { host: true, .. PipelineStages::none() },
+                                      AccessFlagBits { host_read: true, .. AccessFlagBits::none() })
+        .unwrap()
         // Finish building the command buffer by calling `build`.
         .build().unwrap();
 
@@ -0,0 +1,175 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+// This example shows how to display the result of a compute shader without using any graphics
+// pipeline. The compute shader writes a gradient directly into the images of the swapchain, which
+// are then presented.
+//
+// If the device has a queue family that supports compute operations and presenting but not
+// graphics operations, this family is used. Vulkano transitions the swapchain images to the
+// layout required for presenting with stages that are valid on such a queue.
+
+#[macro_use]
+extern crate vulkano;
+#[macro_use]
+extern crate vulkano_shader_derive;
+extern crate winit;
+extern crate vulkano_win;
+
+use vulkano_win::VkSurfaceBuild;
+
+use vulkano::command_buffer::AutoCommandBufferBuilder;
+use vulkano::device::Device;
+use vulkano::format::Format;
+use vulkano::image::ImageUsage;
+use vulkano::instance::Instance;
+use vulkano::pipeline::ComputePipeline;
+use vulkano::swapchain;
+use vulkano::swapchain::PresentMode;
+use vulkano::swapchain::Swapchain;
+use vulkano::sync::now;
+use vulkano::sync::GpuFuture;
+
+use std::sync::Arc;
+
+fn main() {
+    let instance = {
+        let extensions = vulkano_win::required_extensions();
+        Instance::new(None, &extensions, None).expect("failed to create Vulkan instance")
+    };
+
+    let physical = vulkano::instance::PhysicalDevice::enumerate(&instance)
+                            .next().expect("no device available");
+    println!("Using device: {} (type: {:?})", physical.name(), physical.ty());
+
+    let events_loop = winit::EventsLoop::new();
+    let window = winit::WindowBuilder::new().build_vk_surface(&events_loop, instance.clone()).unwrap();
+
+    // We need a queue family that can execute compute shaders and present on the window. A family
+    // without graphics support is preferred, in order to show that it isn't needed.
+    let can_present = |q: &vulkano::instance::QueueFamily| {
+        q.supports_compute() && q.supports_surface(window.surface()).unwrap_or(false)
+    };
+    let queue = physical.queue_families()
+        .find(|q| can_present(q) && !q.supports_graphics())
+        .or_else(|| physical.queue_families().find(|q| can_present(q)))
+        .expect("couldn't find a compute queue family that can present");
+    println!("Using queue family {} (graphics: {})", queue.id(), queue.supports_graphics());
+
+    let (device, mut queues) = {
+        let device_ext = vulkano::device::DeviceExtensions {
+            khr_swapchain: true,
+            .. vulkano::device::DeviceExtensions::none()
+        };
+
+        Device::new(&physical, physical.supported_features(), &device_ext,
+                    [(queue, 0.5)].iter().cloned()).expect("failed to create device")
+    };
+
+    let queue = queues.next().unwrap();
+
+    let (swapchain, images) = {
+        let caps = window.surface().capabilities(physical)
+                         .expect("failed to get surface capabilities");
+        assert!(caps.supported_usage_flags.storage,
+                "the swapchain images can't be used as storage images");
+
+        let dimensions = caps.current_extent.unwrap_or([1280, 1024]);
+        let alpha = caps.default_composite_alpha();
+        let transform = caps.default_transform();
+
+        // The compute shader writes to an `rgba8` image, so the swapchain must use a matching
+        // format.
+        let format = caps.supported_formats.iter()
+            .map(|&(format, _)| format)
+            .find(|&format| format == Format::R8G8B8A8Unorm)
+            .expect("the surface doesn't support the R8G8B8A8Unorm format");
+
+        // The images are only written by the compute shader, they are never used as color
+        // attachments.
+        let usage = ImageUsage {
+            storage: true,
+            .. ImageUsage::none()
+        };
+
+        Swapchain::new(device.clone(), window.surface().clone(), caps.min_image_count, format,
+                       dimensions, 1, usage, &queue, transform, alpha, PresentMode::Fifo, true,
+                       None).expect("failed to create swapchain")
+    };
+
+    mod cs {
+        #[derive(VulkanoShader)]
+        #[ty = "compute"]
+        #[src = "
+#version 450
+
+layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+layout(set = 0, binding = 0, rgba8) uniform writeonly image2D img;
+
+void main() {
+    ivec2 size = imageSize(img);
+    ivec2 pos = ivec2(gl_GlobalInvocationID.xy);
+    if (pos.x >= size.x || pos.y >= size.y) {
+        return;
+    }
+
+    vec2 uv = vec2(pos) / vec2(size);
+    imageStore(img, pos, vec4(uv, 1.0 - uv.x, 1.0));
+}
+"]
+        struct Dummy;
+    }
+
+    let shader = cs::Shader::load(&device).expect("failed to create shader module");
+    let pipeline = Arc::new(ComputePipeline::new(device.clone(), &shader.main_entry_point(), &())
+                                .expect("failed to create compute pipeline"));
+
+    // One descriptor set per swapchain image.
+    let sets = images.iter().map(|image| {
+        Arc::new(simple_descriptor_set!(pipeline.clone(), 0, {
+            img: image.clone()
+        }).unwrap())
+    }).collect::<Vec<_>>();
+
+    let mut previous_frame_end = Box::new(now(device.clone())) as Box<GpuFuture>;
+
+    loop {
+        previous_frame_end.cleanup_finished();
+
+        let (image_num, acquire_future) = swapchain::acquire_next_image(swapchain.clone(),
+                                                                        None).unwrap();
+
+        let dimensions = images[image_num].dimensions();
+        let groups = [(dimensions[0] + 7) / 8, (dimensions[1] + 7) / 8, 1];
+
+        let command_buffer = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap()
+            .dispatch(groups, pipeline.clone(), sets[image_num].clone(), ())
+            .unwrap()
+            .build().unwrap();
+
+        // The image is written in the `General` layout. Presenting it adds a transition to the
+        // layout required by the presentation engine, using only compute and transfer stages if
+        // the queue doesn't support graphics.
+        let future = previous_frame_end.join(acquire_future)
+            .then_execute(queue.clone(), command_buffer).unwrap()
+            .then_swapchain_present(queue.clone(), swapchain.clone(), image_num)
+            .then_signal_fence_and_flush().unwrap();
+        previous_frame_end = Box::new(future) as Box<_>;
+
+        let mut done = false;
+        events_loop.poll_events(|ev| {
+            match ev {
+                winit::Event::WindowEvent { event: winit::WindowEvent::Closed, .. } => done = true,
+                _ => ()
+            }
+        });
+        if done { return; }
+    }
+}
@@ -13,6 +13,7 @@ use vulkano::device::{Device, DeviceExtensions};
 use vulkano::format::Format;
 use vulkano::image::ImmutableImage;
 use vulkano::image::Dimensions;
+use vulkano::image::MipmapsCount;
 use vulkano::instance;
 use vulkano::instance::{Instance, InstanceExtensions, PhysicalDevice};
 use vulkano::instance::debug::{DebugCallback, MessageTypes};
@@ -99,7 +100,10 @@ fn main() {
     // Create an image in order to generate some additional logging:
     let pixel_format = Format::R8G8B8A8Uint;
     let dimensions = Dimensions::Dim2d { width: 4096, height: 4096 };
-    ImmutableImage::new(device.clone(), dimensions, pixel_format, Some(queue.family())).unwrap();
+    unsafe {
+        ImmutableImage::uninitialized(device.clone(), dimensions, pixel_format, MipmapsCount::One,
+                                      Some(queue.family())).unwrap();
+    }
 
     // (At this point you should see a bunch of messages printed to the terminal window - have fun debugging!)
 }
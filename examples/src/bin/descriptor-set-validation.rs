@@ -0,0 +1,197 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+// This example measures the cost of validating the descriptor sets of a draw, with and without
+// the cache that the `AutoCommandBufferBuilder` uses.
+//
+// The same descriptor set, which contains several uniform buffers, is checked against the same
+// pipeline many times in a row. Without the cache, every descriptor of the set is compared with
+// the descriptor of the pipeline layout each time. With the cache, only the first check does this
+// work and the following ones look up the previous result. Finally, draws that use the set are
+// recorded in order to compare the validation with the total cost of a draw.
+//
+// Run it in release mode in order to get meaningful timings.
+
+#[macro_use]
+extern crate vulkano;
+#[macro_use]
+extern crate vulkano_shader_derive;
+extern crate time;
+
+use vulkano::buffer::BufferUsage;
+use vulkano::buffer::CpuAccessibleBuffer;
+use vulkano::command_buffer::AutoCommandBufferBuilder;
+use vulkano::command_buffer::DynamicState;
+use vulkano::command_buffer::validity::DescriptorSetsValidityCache;
+use vulkano::command_buffer::validity::check_descriptor_sets_validity;
+use vulkano::device::Device;
+use vulkano::device::DeviceExtensions;
+use vulkano::format::Format;
+use vulkano::framebuffer::Framebuffer;
+use vulkano::framebuffer::Subpass;
+use vulkano::image::AttachmentImage;
+use vulkano::instance::Features;
+use vulkano::instance::Instance;
+use vulkano::instance::InstanceExtensions;
+use vulkano::pipeline::GraphicsPipeline;
+use vulkano::pipeline::viewport::Viewport;
+
+use std::iter;
+use std::sync::Arc;
+
+const NUM_CHECKS: u32 = 100000;
+
+fn main() {
+    let instance = Instance::new(None, &InstanceExtensions::none(), None)
+        .expect("failed to create Vulkan instance");
+
+    let physical = vulkano::instance::PhysicalDevice::enumerate(&instance)
+        .next().expect("no device available");
+
+    let queue = physical.queue_families().find(|&q| q.supports_graphics())
+        .expect("couldn't find a graphical queue family");
+
+    let (device, mut queues) = {
+        Device::new(&physical, &Features::none(), &DeviceExtensions::none(),
+                    [(queue, 0.5)].iter().cloned()).expect("failed to create device")
+    };
+
+    let queue = queues.next().unwrap();
+
+    #[derive(Debug, Clone)]
+    struct Vertex { position: [f32; 2] }
+    impl_vertex!(Vertex, position);
+
+    let vertex_buffer = CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::all(),
+                                                       Some(queue.family()), [
+        Vertex { position: [-0.5, -0.25] },
+        Vertex { position: [0.0, 0.5] },
+        Vertex { position: [0.25, -0.1] }
+    ].iter().cloned()).expect("failed to create buffer");
+
+    let uniform_buffer = CpuAccessibleBuffer::from_data(device.clone(), BufferUsage::all(),
+                                                        Some(queue.family()),
+                                                        [0.25f32, 0.25, 0.25, 0.25])
+        .expect("failed to create buffer");
+
+    mod vs {
+        #[derive(VulkanoShader)]
+        #[ty = "vertex"]
+        #[src = "
+#version 450
+
+layout(location = 0) in vec2 position;
+
+void main() {
+    gl_Position = vec4(position, 0.0, 1.0);
+}
+"]
+        struct Dummy;
+    }
+
+    mod fs {
+        #[derive(VulkanoShader)]
+        #[ty = "fragment"]
+        #[src = "
+#version 450
+
+layout(set = 0, binding = 0) uniform Red { vec4 color; } red;
+layout(set = 0, binding = 1) uniform Green { vec4 color; } green;
+layout(set = 0, binding = 2) uniform Blue { vec4 color; } blue;
+layout(set = 0, binding = 3) uniform Alpha { vec4 color; } alpha;
+
+layout(location = 0) out vec4 f_color;
+
+void main() {
+    f_color = red.color + green.color + blue.color + alpha.color;
+}
+"]
+        struct Dummy;
+    }
+
+    let vs = vs::Shader::load(&device).expect("failed to create shader module");
+    let fs = fs::Shader::load(&device).expect("failed to create shader module");
+
+    let render_pass = Arc::new(single_pass_renderpass!(device.clone(),
+        attachments: {
+            color: {
+                load: Clear,
+                store: Store,
+                format: Format::R8G8B8A8Unorm,
+                samples: 1,
+            }
+        },
+        pass: {
+            color: [color],
+            depth_stencil: {}
+        }
+    ).unwrap());
+
+    let image = AttachmentImage::new(device.clone(), [64, 64], Format::R8G8B8A8Unorm).unwrap();
+    let framebuffer = Arc::new(Framebuffer::start(render_pass.clone())
+        .add(image.clone()).unwrap()
+        .build().unwrap());
+
+    let pipeline = Arc::new(GraphicsPipeline::start()
+        .vertex_input_single_buffer::<Vertex>()
+        .vertex_shader(vs.main_entry_point(), ())
+        .triangle_list()
+        .viewports(iter::once(Viewport {
+            origin: [0.0, 0.0],
+            depth_range: 0.0 .. 1.0,
+            dimensions: [64.0, 64.0],
+        }))
+        .fragment_shader(fs.main_entry_point(), ())
+        .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+        .build(device.clone())
+        .unwrap());
+
+    let set = Arc::new(simple_descriptor_set!(pipeline.clone(), 0, {
+        red: uniform_buffer.clone(),
+        green: uniform_buffer.clone(),
+        blue: uniform_buffer.clone(),
+        alpha: uniform_buffer.clone(),
+    }).unwrap());
+
+    // Validation without the cache.
+    let start = time::precise_time_ns();
+    for _ in 0 .. NUM_CHECKS {
+        check_descriptor_sets_validity(&pipeline, &set).unwrap();
+    }
+    let uncached_ns = time::precise_time_ns() - start;
+
+    // Validation with the cache.
+    let mut cache = DescriptorSetsValidityCache::new();
+    let start = time::precise_time_ns();
+    for _ in 0 .. NUM_CHECKS {
+        cache.check(&pipeline, &set).unwrap();
+    }
+    let cached_ns = time::precise_time_ns() - start;
+    assert_eq!(cache.misses(), 1);
+
+    // Recording draws, which validate the set with the cache of the builder.
+    let start = time::precise_time_ns();
+    let mut builder = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap()
+        .begin_render_pass(framebuffer.clone(), false, vec![[0.0, 0.0, 0.0, 1.0].into()])
+        .unwrap();
+    for _ in 0 .. NUM_CHECKS {
+        builder = builder.draw(pipeline.clone(), DynamicState::none(), vertex_buffer.clone(),
+                               set.clone(), ())
+            .unwrap();
+    }
+    let _ = builder.end_render_pass().unwrap().build().unwrap();
+    let draws_ns = time::precise_time_ns() - start;
+
+    println!("{} checks without cache: {} ns per check", NUM_CHECKS,
+             uncached_ns / NUM_CHECKS as u64);
+    println!("{} checks with cache: {} ns per check", NUM_CHECKS,
+             cached_ns / NUM_CHECKS as u64);
+    println!("{} draws with a descriptor set: {} ns per draw", NUM_CHECKS,
+             draws_ns / NUM_CHECKS as u64);
+}
@@ -0,0 +1,289 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+// This example demonstrates how to let the GPU generate its own draw commands, with the
+// `VK_NV_device_generated_commands` extension.
+//
+// A compute shader writes 100 *sequences* in a buffer. Each sequence contains the position of a
+// triangle, which is passed to the vertex shader through push constants, followed by the
+// parameters of a draw command. The buffer is then executed with `execute_generated_commands`,
+// which draws the 100 triangles on a 10x10 grid without the CPU ever seeing their positions.
+//
+// The example exits early if the device doesn't support the extension.
+
+#[macro_use]
+extern crate vulkano;
+#[macro_use]
+extern crate vulkano_shader_derive;
+
+use vulkano::buffer::BufferAccess;
+use vulkano::buffer::BufferUsage;
+use vulkano::buffer::CpuAccessibleBuffer;
+use vulkano::buffer::DeviceLocalBuffer;
+use vulkano::command_buffer::AutoCommandBufferBuilder;
+use vulkano::command_buffer::DynamicState;
+use vulkano::command_buffer::indirect_commands::IndirectCommandsLayout;
+use vulkano::command_buffer::indirect_commands::IndirectCommandsToken;
+use vulkano::descriptor::descriptor::ShaderStages;
+use vulkano::device::Device;
+use vulkano::device::DeviceExtensions;
+use vulkano::format::Format;
+use vulkano::framebuffer::Framebuffer;
+use vulkano::framebuffer::Subpass;
+use vulkano::image::AttachmentImage;
+use vulkano::image::ImageUsage;
+use vulkano::instance::Features;
+use vulkano::instance::Instance;
+use vulkano::instance::InstanceExtensions;
+use vulkano::pipeline::ComputePipeline;
+use vulkano::pipeline::GraphicsPipeline;
+use vulkano::pipeline::viewport::Viewport;
+use vulkano::sync::GpuFuture;
+use vulkano::sync::now;
+
+use std::iter;
+use std::sync::Arc;
+
+// Number of sequences generated by the compute shader.
+const NUM_DRAWS: u32 = 100;
+
+// Size in bytes of a sequence: the position of the triangle, then a `DrawIndirectCommand`.
+const SEQUENCE_STRIDE: u32 = 24;
+
+fn main() {
+    let instance = Instance::new(None, &InstanceExtensions::none(), None)
+        .expect("failed to create Vulkan instance");
+
+    let physical = vulkano::instance::PhysicalDevice::enumerate(&instance)
+        .next().expect("no device available");
+
+    if !DeviceExtensions::supported_by_device(&physical).nv_device_generated_commands {
+        println!("The device doesn't support VK_NV_device_generated_commands");
+        return;
+    }
+
+    let queue = physical.queue_families().find(|&q| q.supports_graphics())
+        .expect("couldn't find a graphical queue family");
+
+    let (device, mut queues) = {
+        let extensions = DeviceExtensions {
+            nv_device_generated_commands: true,
+            .. DeviceExtensions::none()
+        };
+        Device::new(&physical, &Features::none(), &extensions, [(queue, 0.5)].iter().cloned())
+            .expect("failed to create device")
+    };
+
+    let queue = queues.next().unwrap();
+
+    mod cs {
+        #[derive(VulkanoShader)]
+        #[ty = "compute"]
+        #[src = "
+#version 450
+
+layout(local_size_x = 64, local_size_y = 1, local_size_z = 1) in;
+
+struct Sequence {
+    vec2 offset;
+    uint vertex_count;
+    uint instance_count;
+    uint first_vertex;
+    uint first_instance;
+};
+
+layout(set = 0, binding = 0) buffer Sequences {
+    Sequence sequences[];
+} sequences;
+
+void main() {
+    uint idx = gl_GlobalInvocationID.x;
+    if (idx >= 100) {
+        return;
+    }
+
+    sequences.sequences[idx].offset = vec2(float(idx % 10), float(idx / 10)) * 0.2 - 1.0;
+    sequences.sequences[idx].vertex_count = 3;
+    sequences.sequences[idx].instance_count = 1;
+    sequences.sequences[idx].first_vertex = 0;
+    sequences.sequences[idx].first_instance = 0;
+}"
+]
+        struct Dummy;
+    }
+
+    mod vs {
+        #[derive(VulkanoShader)]
+        #[ty = "vertex"]
+        #[src = "
+#version 450
+
+layout(location = 0) in vec2 position;
+
+layout(push_constant) uniform PushConstants {
+    vec2 offset;
+} pc;
+
+void main() {
+    gl_Position = vec4(pc.offset + position, 0.0, 1.0);
+}
+"]
+        struct Dummy;
+    }
+
+    mod fs {
+        #[derive(VulkanoShader)]
+        #[ty = "fragment"]
+        #[src = "
+#version 450
+
+layout(location = 0) out vec4 f_color;
+
+void main() {
+    f_color = vec4(1.0, 0.0, 0.0, 1.0);
+}
+"]
+        struct Dummy;
+    }
+
+    let cs = cs::Shader::load(&device).expect("failed to create shader module");
+    let vs = vs::Shader::load(&device).expect("failed to create shader module");
+    let fs = fs::Shader::load(&device).expect("failed to create shader module");
+
+    let compute_pipeline = Arc::new(ComputePipeline::new(device.clone(), &cs.main_entry_point(),
+                                                         &())
+        .expect("failed to create compute pipeline"));
+
+    let render_pass = Arc::new(single_pass_renderpass!(device.clone(),
+        attachments: {
+            color: {
+                load: Clear,
+                store: Store,
+                format: Format::R8G8B8A8Unorm,
+                samples: 1,
+            }
+        },
+        pass: {
+            color: [color],
+            depth_stencil: {}
+        }
+    ).unwrap());
+
+    #[derive(Debug, Clone)]
+    struct Vertex { position: [f32; 2] }
+    impl_vertex!(Vertex, position);
+
+    // Each triangle covers the top-left half of its cell of the grid.
+    let vertex_buffer = CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::all(),
+                                                       Some(queue.family()), [
+        Vertex { position: [0.0, 0.0] },
+        Vertex { position: [0.18, 0.0] },
+        Vertex { position: [0.0, 0.18] }
+    ].iter().cloned()).expect("failed to create buffer");
+
+    // Pipelines must opt in to be used with generated commands.
+    let pipeline = Arc::new(GraphicsPipeline::start()
+        .vertex_input_single_buffer::<Vertex>()
+        .vertex_shader(vs.main_entry_point(), ())
+        .triangle_list()
+        .viewports(iter::once(Viewport {
+            origin: [0.0, 0.0],
+            depth_range: 0.0 .. 1.0,
+            dimensions: [100.0, 100.0],
+        }))
+        .fragment_shader(fs.main_entry_point(), ())
+        .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+        .indirect_bindable()
+        .build(device.clone())
+        .unwrap());
+
+    // Describes the content of a sequence: the push constants at offset 0, then the draw.
+    let layout = IndirectCommandsLayout::start()
+        .stream(SEQUENCE_STRIDE)
+        .token(0, 0, IndirectCommandsToken::PushConstant {
+            stages: ShaderStages { vertex: true, .. ShaderStages::none() },
+            offset: 0,
+            size: 8,
+        })
+        .token(0, 8, IndirectCommandsToken::Draw)
+        .build(pipeline.clone())
+        .expect("failed to create indirect commands layout");
+
+    // The buffer that the compute shader writes the sequences to.
+    let sequences = DeviceLocalBuffer::<[[u32; 6]]>::array(device.clone(), NUM_DRAWS as usize,
+                                                           BufferUsage {
+                                                               storage_buffer: true,
+                                                               indirect_buffer: true,
+                                                               .. BufferUsage::none()
+                                                           },
+                                                           Some(queue.family()))
+        .expect("failed to create buffer");
+
+    // Scratch memory used by the implementation while executing the sequences.
+    let preprocess_size = layout.preprocess_requirements(&pipeline, NUM_DRAWS).size;
+    let preprocess = DeviceLocalBuffer::<[u8]>::array(device.clone(), preprocess_size,
+                                                      BufferUsage::indirect_buffer(),
+                                                      Some(queue.family()))
+        .expect("failed to create buffer");
+
+    let set = Arc::new(simple_descriptor_set!(compute_pipeline.clone(), 0, {
+        sequences: sequences.clone()
+    }).unwrap());
+
+    let image = AttachmentImage::with_usage(device.clone(), [100, 100], Format::R8G8B8A8Unorm,
+                                            ImageUsage {
+                                                transfer_source: true,
+                                                color_attachment: true,
+                                                .. ImageUsage::none()
+                                            }).unwrap();
+    let framebuffer = Arc::new(Framebuffer::start(render_pass.clone())
+        .add(image.clone()).unwrap()
+        .build().unwrap());
+
+    let output = CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::all(),
+                                                Some(queue.family()),
+                                                (0 .. 100 * 100).map(|_| [0u8; 4]))
+        .expect("failed to create buffer");
+
+    // The barrier between the compute shader writing the sequences and the generated commands
+    // reading them is added automatically.
+    let streams = vec![Box::new(sequences.clone()) as Box<BufferAccess + Send + Sync>];
+    let command_buffer = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap()
+        .dispatch([(NUM_DRAWS + 63) / 64, 1, 1], compute_pipeline.clone(), set.clone(), ())
+        .unwrap()
+        .begin_render_pass(framebuffer.clone(), false, vec![[0.0, 0.0, 0.0, 1.0].into()])
+        .unwrap()
+        .execute_generated_commands(pipeline.clone(), DynamicState::none(),
+                                    vertex_buffer.clone(), layout.clone(), streams, NUM_DRAWS,
+                                    preprocess.clone(), (), ())
+        .unwrap()
+        .end_render_pass()
+        .unwrap()
+        .copy_image_to_buffer(image.clone(), output.clone())
+        .unwrap()
+        .build().unwrap();
+
+    let future = now(device.clone())
+        .then_execute(queue.clone(), command_buffer).unwrap()
+        .then_signal_fence_and_flush().unwrap();
+    future.wait(None).unwrap();
+
+    // Every cell of the grid must contain a triangle.
+    let content = output.read().unwrap();
+    let mut drawn = 0;
+    for y in 0 .. 10 {
+        for x in 0 .. 10 {
+            if content[(y * 10 + 2) * 100 + x * 10 + 2] == [255, 0, 0, 255] {
+                drawn += 1;
+            }
+        }
+    }
+
+    println!("{} triangles drawn out of {}", drawn, NUM_DRAWS);
+}
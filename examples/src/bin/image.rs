@@ -41,7 +41,7 @@ fn main() {
     let window = winit::WindowBuilder::new().build_vk_surface(&events_loop, instance.clone()).unwrap();
 
     let queue = physical.queue_families().find(|&q| q.supports_graphics() &&
-                                                   window.surface().is_supported(q).unwrap_or(false))
+                                                   q.supports_surface(window.surface()).unwrap_or(false))
                                                 .expect("couldn't find a graphical queue family");
 
     let device_ext = vulkano::device::DeviceExtensions {
@@ -100,24 +100,24 @@ fn main() {
         ).unwrap()
     );
 
-    let texture = vulkano::image::immutable::ImmutableImage::new(device.clone(), vulkano::image::Dimensions::Dim2d { width: 93, height: 93 },
-                                                                 vulkano::format::R8G8B8A8Unorm, Some(queue.family())).unwrap();
-
-
-    let pixel_buffer = {
+    let (texture, tex_future) = {
         let image = image::load_from_memory_with_format(include_bytes!("image_img.png"),
                                                         image::ImageFormat::PNG).unwrap().to_rgba();
         let image_data = image.into_raw().clone();
 
         let image_data_chunks = image_data.chunks(4).map(|c| [c[0], c[1], c[2], c[3]]);
 
-        // TODO: staging buffer instead
-        vulkano::buffer::cpu_access::CpuAccessibleBuffer::<[[u8; 4]]>
-            ::from_iter(device.clone(), vulkano::buffer::BufferUsage::all(),
-                        Some(queue.family()), image_data_chunks)
-                        .expect("failed to create buffer")
+        vulkano::image::immutable::ImmutableImage::from_iter(
+            image_data_chunks,
+            vulkano::image::Dimensions::Dim2d { width: 93, height: 93 },
+            vulkano::format::R8G8B8A8Unorm,
+            Some(queue.family()),
+            queue.clone()).unwrap()
     };
 
+    // The texture can't be put in a descriptor set before its upload has finished.
+    tex_future.then_signal_fence_and_flush().unwrap().wait(None).unwrap();
+
 
     let sampler = vulkano::sampler::Sampler::new(device.clone(), vulkano::sampler::Filter::Linear,
                                                  vulkano::sampler::Filter::Linear, vulkano::sampler::MipmapMode::Nearest,
@@ -142,7 +142,7 @@ fn main() {
 
     let set = Arc::new(simple_descriptor_set!(pipeline.clone(), 0, {
         tex: (texture.clone(), sampler.clone())
-    }));
+    }).unwrap());
 
     let framebuffers = images.iter().map(|image| {
         Arc::new(vulkano::framebuffer::Framebuffer::start(renderpass.clone())
@@ -158,9 +158,6 @@ fn main() {
 
         let cb = vulkano::command_buffer::AutoCommandBufferBuilder::new(device.clone(), queue.family())
             .unwrap()
-            .copy_buffer_to_image(pixel_buffer.clone(), texture.clone())
-            .unwrap()
-            //.clear_color_image(&texture, [0.0, 1.0, 0.0, 1.0])
             .begin_render_pass(
                 framebuffers[image_num].clone(), false,
                 vec![[0.0, 0.0, 1.0, 1.0].into()]).unwrap()
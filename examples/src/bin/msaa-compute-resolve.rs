@@ -0,0 +1,203 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+// This example shows how to access the individual samples of a multisampled image from a compute
+// shader.
+//
+// A first compute shader writes a different color to each of the 4 samples of every pixel of a
+// multisampled storage image. A second compute shader then averages the samples of each pixel
+// into a regular image, which is what a resolve operation does. The result is copied to a buffer
+// and checked on the CPU.
+//
+// Accessing multisampled storage images requires the `shader_storage_image_multisample` feature.
+
+#[macro_use]
+extern crate vulkano;
+#[macro_use]
+extern crate vulkano_shader_derive;
+
+use vulkano::buffer::BufferUsage;
+use vulkano::buffer::CpuAccessibleBuffer;
+use vulkano::command_buffer::AutoCommandBufferBuilder;
+use vulkano::device::Device;
+use vulkano::device::DeviceExtensions;
+use vulkano::format::Format;
+use vulkano::image::Dimensions;
+use vulkano::image::ImageCreationError;
+use vulkano::image::ImageLayout;
+use vulkano::image::StorageImage;
+use vulkano::instance::Features;
+use vulkano::instance::Instance;
+use vulkano::instance::InstanceExtensions;
+use vulkano::pipeline::ComputePipeline;
+use vulkano::sync::now;
+use vulkano::sync::AccessFlagBits;
+use vulkano::sync::GpuFuture;
+use vulkano::sync::PipelineStages;
+
+use std::sync::Arc;
+
+const WIDTH: u32 = 64;
+const HEIGHT: u32 = 64;
+const SAMPLES: u32 = 4;
+
+fn main() {
+    let instance = Instance::new(None, &InstanceExtensions::none(), None)
+        .expect("failed to create Vulkan instance");
+
+    let physical = vulkano::instance::PhysicalDevice::enumerate(&instance)
+        .next().expect("no device available");
+
+    let features = Features {
+        shader_storage_image_multisample: true,
+        .. Features::none()
+    };
+    if !physical.supported_features().superset_of(&features) {
+        println!("The device doesn't support multisampled storage images");
+        return;
+    }
+
+    let queue = physical.queue_families().find(|&q| q.supports_compute()).unwrap();
+
+    let (device, mut queues) = {
+        Device::new(&physical, &features, &DeviceExtensions::none(),
+                    [(queue, 0.5)].iter().cloned()).expect("failed to create device")
+    };
+
+    let queue = queues.next().unwrap();
+
+    let multisampled = match StorageImage::multisampled(device.clone(), [WIDTH, HEIGHT], SAMPLES,
+                                                        Format::R8G8B8A8Unorm,
+                                                        Some(queue.family())) {
+        Ok(img) => img,
+        Err(ImageCreationError::UnsupportedSamplesCount { .. }) => {
+            println!("The device doesn't support {} samples for storage images", SAMPLES);
+            return;
+        },
+        Err(err) => panic!("failed to create the multisampled image: {}", err),
+    };
+
+    let resolved = {
+        let dimensions = Dimensions::Dim2d { width: WIDTH, height: HEIGHT };
+        StorageImage::new(device.clone(), dimensions, Format::R8G8B8A8Unorm, Some(queue.family()))
+            .expect("failed to create the resolved image")
+    };
+
+    // The `image2DMS` type gives access to the samples of the image. Each sample gets a red
+    // component that depends on its index, so that averaging them gives a known value.
+    mod fill {
+        #[derive(VulkanoShader)]
+        #[ty = "compute"]
+        #[src = "
+#version 450
+
+layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+layout(set = 0, binding = 0, rgba8) uniform writeonly image2DMS img;
+
+void main() {
+    ivec2 pos = ivec2(gl_GlobalInvocationID.xy);
+    for (int s = 0; s < 4; s++) {
+        imageStore(img, pos, s, vec4(float(s * 64) / 255.0, float(pos.x) / 255.0,
+                                     float(pos.y) / 255.0, 1.0));
+    }
+}
+"]
+        struct Dummy;
+    }
+
+    mod resolve {
+        #[derive(VulkanoShader)]
+        #[ty = "compute"]
+        #[src = "
+#version 450
+
+layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+layout(set = 0, binding = 0, rgba8) uniform readonly image2DMS src;
+layout(set = 0, binding = 1, rgba8) uniform writeonly image2D dest;
+
+void main() {
+    ivec2 pos = ivec2(gl_GlobalInvocationID.xy);
+    vec4 sum = vec4(0.0);
+    for (int s = 0; s < 4; s++) {
+        sum += imageLoad(src, pos, s);
+    }
+    imageStore(dest, pos, sum / 4.0);
+}
+"]
+        struct Dummy;
+    }
+
+    let fill_shader = fill::Shader::load(&device).expect("failed to create shader module");
+    let fill_pipeline = Arc::new(ComputePipeline::new(device.clone(),
+                                                      &fill_shader.main_entry_point(), &())
+                                     .expect("failed to create compute pipeline"));
+
+    let resolve_shader = resolve::Shader::load(&device).expect("failed to create shader module");
+    let resolve_pipeline = Arc::new(ComputePipeline::new(device.clone(),
+                                                         &resolve_shader.main_entry_point(), &())
+                                        .expect("failed to create compute pipeline"));
+
+    // The descriptors of the shaders are multisampled, which is checked against the number of
+    // samples of the images when building the sets.
+    let fill_set = Arc::new(simple_descriptor_set!(fill_pipeline.clone(), 0, {
+        img: multisampled.clone()
+    }).unwrap());
+
+    let resolve_set = Arc::new(simple_descriptor_set!(resolve_pipeline.clone(), 0, {
+        src: multisampled.clone(),
+        dest: resolved.clone()
+    }).unwrap());
+
+    let buffer = CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::all(),
+                                                Some(queue.family()),
+                                                (0 .. WIDTH * HEIGHT * 4).map(|_| 0u8))
+        .expect("failed to create buffer");
+
+    let compute_stage = PipelineStages { compute_shader: true, .. PipelineStages::none() };
+    let shader_read = AccessFlagBits { shader_read: true, .. AccessFlagBits::none() };
+    let transfer_stage = PipelineStages { transfer: true, .. PipelineStages::none() };
+    let transfer_read = AccessFlagBits { transfer_read: true, .. AccessFlagBits::none() };
+
+    let command_buffer = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap()
+        .dispatch_elements(fill_pipeline.clone(), [WIDTH, HEIGHT, 1], fill_set.clone(), ())
+        .unwrap()
+        .compute_write_image_barrier(multisampled.clone(), ImageLayout::General, compute_stage,
+                                     shader_read)
+        .unwrap()
+        .dispatch_elements(resolve_pipeline.clone(), [WIDTH, HEIGHT, 1], resolve_set.clone(), ())
+        .unwrap()
+        .compute_write_image_barrier(resolved.clone(), ImageLayout::TransferSrcOptimal,
+                                     transfer_stage, transfer_read)
+        .unwrap()
+        .copy_image_to_buffer(resolved.clone(), buffer.clone())
+        .unwrap()
+        .host_read_barrier(buffer.clone())
+        .unwrap()
+        .build().unwrap();
+
+    let future = now(device.clone())
+        .then_execute(queue.clone(), command_buffer).unwrap()
+        .then_signal_fence_and_flush().unwrap();
+
+    future.wait(None).unwrap();
+
+    // The red components of the samples are 0, 64, 128 and 192, whose average is 96. The other
+    // components are the same for all the samples of a pixel.
+    let content = buffer.read().expect("failed to lock buffer for reading");
+    for y in 0 .. HEIGHT {
+        for x in 0 .. WIDTH {
+            let offset = ((y * WIDTH + x) * 4) as usize;
+            assert_eq!(&content[offset .. offset + 4], &[96, x as u8, y as u8, 255]);
+        }
+    }
+
+    println!("Success");
+}
@@ -0,0 +1,229 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+// This example renders to two windows with the same device. The first window is redrawn at each
+// frame, and the second one only every third frame.
+//
+// The images of the windows that are redrawn during a frame are presented with a single present
+// command thanks to `present_multiple`. Each swapchain keeps its own bookkeeping, so
+// `wait_for_latency` paces each window independently, and a window that is out of date is
+// recreated without disturbing the other one.
+
+#![allow(dead_code)]
+
+#[macro_use]
+extern crate vulkano;
+extern crate winit;
+extern crate vulkano_win;
+
+use vulkano_win::VkSurfaceBuild;
+use vulkano_win::Window;
+
+use vulkano::command_buffer::AutoCommandBufferBuilder;
+use vulkano::command_buffer::submit::SubmitPresentError;
+use vulkano::device::Device;
+use vulkano::device::DeviceExtensions;
+use vulkano::framebuffer::Framebuffer;
+use vulkano::framebuffer::FramebufferAbstract;
+use vulkano::framebuffer::RenderPassAbstract;
+use vulkano::image::SwapchainImage;
+use vulkano::instance::Instance;
+use vulkano::instance::PhysicalDevice;
+use vulkano::swapchain;
+use vulkano::swapchain::AcquireError;
+use vulkano::swapchain::PresentMode;
+use vulkano::swapchain::Swapchain;
+use vulkano::sync::now;
+use vulkano::sync::GpuFuture;
+
+use std::sync::Arc;
+
+// A window, its swapchain and what is needed to draw on it.
+struct WindowState {
+    // Kept alive for as long as the swapchain exists.
+    window: Window,
+    swapchain: Arc<Swapchain>,
+    render_pass: Arc<RenderPassAbstract + Send + Sync>,
+    framebuffers: Vec<Arc<FramebufferAbstract + Send + Sync>>,
+    color: [f32; 4],
+    // The window is redrawn once every `interval` frames.
+    interval: u32,
+}
+
+impl WindowState {
+    // Recreates the swapchain after it has become out of date. Does nothing while the window is
+    // minimized.
+    fn recreate(&mut self) {
+        if let Some((swapchain, images)) = self.swapchain.recreate_for_surface(false).unwrap() {
+            self.framebuffers = framebuffers(&self.render_pass, &images);
+            self.swapchain = swapchain;
+        }
+    }
+}
+
+fn framebuffers(render_pass: &Arc<RenderPassAbstract + Send + Sync>,
+                images: &[Arc<SwapchainImage>])
+                -> Vec<Arc<FramebufferAbstract + Send + Sync>> {
+    images.iter().map(|image| {
+        Arc::new(Framebuffer::start(render_pass.clone())
+            .add(image.clone()).unwrap()
+            .build().unwrap()) as Arc<FramebufferAbstract + Send + Sync>
+    }).collect()
+}
+
+fn main() {
+    let instance = {
+        let extensions = vulkano_win::required_extensions();
+        Instance::new(None, &extensions, None).expect("failed to create Vulkan instance")
+    };
+
+    let physical = PhysicalDevice::enumerate(&instance).next().expect("no device available");
+    println!("Using device: {} (type: {:?})", physical.name(), physical.ty());
+
+    let events_loop = winit::EventsLoop::new();
+    let windows = vec![
+        winit::WindowBuilder::new().with_title("Every frame")
+            .build_vk_surface(&events_loop, instance.clone()).unwrap(),
+        winit::WindowBuilder::new().with_title("Every third frame")
+            .build_vk_surface(&events_loop, instance.clone()).unwrap(),
+    ];
+
+    // The same queue presents to both windows.
+    let queue = physical.queue_families().find(|&q| {
+        q.supports_graphics() &&
+            windows.iter().all(|w| q.supports_surface(w.surface()).unwrap_or(false))
+    }).expect("couldn't find a queue family that can present to both windows");
+
+    let (device, mut queues) = {
+        let device_ext = DeviceExtensions {
+            khr_swapchain: true,
+            .. DeviceExtensions::none()
+        };
+
+        Device::new(&physical, physical.supported_features(), &device_ext,
+                    [(queue, 0.5)].iter().cloned()).expect("failed to create device")
+    };
+
+    let queue = queues.next().unwrap();
+
+    let colors = [[1.0, 0.0, 0.0, 1.0], [0.0, 0.0, 1.0, 1.0]];
+    let intervals = [1, 3];
+
+    let windows = windows.into_iter().zip(colors.iter().zip(intervals.iter()));
+    let mut windows = windows.map(|(window, (&color, &interval))| {
+        let caps = window.surface().capabilities(physical)
+                         .expect("failed to get surface capabilities");
+
+        let (swapchain, images) = Swapchain::new(device.clone(), window.surface().clone(),
+                                                 caps.min_image_count, caps.supported_formats[0].0,
+                                                 caps.current_extent.unwrap_or([640, 480]), 1,
+                                                 caps.supported_usage_flags, &queue,
+                                                 caps.default_transform(),
+                                                 caps.default_composite_alpha(),
+                                                 PresentMode::Fifo, true, None)
+            .expect("failed to create swapchain");
+
+        let render_pass = Arc::new(single_pass_renderpass!(device.clone(),
+            attachments: {
+                color: {
+                    load: Clear,
+                    store: Store,
+                    format: swapchain.format(),
+                    samples: 1,
+                }
+            },
+            pass: {
+                color: [color],
+                depth_stencil: {}
+            }
+        ).unwrap()) as Arc<RenderPassAbstract + Send + Sync>;
+
+        WindowState {
+            framebuffers: framebuffers(&render_pass, &images),
+            window: window,
+            swapchain: swapchain,
+            render_pass: render_pass,
+            color: color,
+            interval: interval,
+        }
+    }).collect::<Vec<_>>();
+
+    let mut previous_frame_end = Box::new(now(device.clone())) as Box<GpuFuture>;
+
+    for frame in 0 .. {
+        previous_frame_end.cleanup_finished();
+
+        // Draws on the windows that need to be redrawn during this frame.
+        let mut future = previous_frame_end;
+        let mut presented = Vec::new();
+        for (index, window) in windows.iter_mut().enumerate() {
+            if frame % window.interval != 0 {
+                continue;
+            }
+
+            // Each window has at most two presents in flight, independently of the other one.
+            window.swapchain.wait_for_latency(2, None).unwrap();
+
+            let (image_num, acquire_future) =
+                match swapchain::acquire_next_image(window.swapchain.clone(), None) {
+                    Ok(r) => r,
+                    Err(AcquireError::OutOfDate) => {
+                        window.recreate();
+                        continue;
+                    },
+                    Err(err) => panic!("{:?}", err),
+                };
+
+            let command_buffer = AutoCommandBufferBuilder::new(device.clone(), queue.family())
+                .unwrap()
+                .begin_render_pass(window.framebuffers[image_num].clone(), false,
+                                   vec![window.color.into()])
+                .unwrap()
+                .end_render_pass()
+                .unwrap()
+                .build().unwrap();
+
+            future = Box::new(future.join(acquire_future)
+                .then_execute(queue.clone(), command_buffer).unwrap()) as Box<_>;
+            presented.push((index, image_num));
+        }
+
+        if presented.is_empty() {
+            previous_frame_end = future;
+        } else {
+            // One present command for all the windows that have been redrawn.
+            let images = presented.iter()
+                .map(|&(index, image_num)| (windows[index].swapchain.clone(), image_num))
+                .collect::<Vec<_>>();
+            let present = swapchain::present_multiple(future, queue.clone(), images);
+            present.flush().unwrap();
+
+            // A window that is out of date doesn't prevent the other ones from being presented.
+            for (&(index, _), result) in presented.iter().zip(present.results().unwrap()) {
+                match result {
+                    Ok(()) => (),
+                    Err(SubmitPresentError::OutOfDate) => windows[index].recreate(),
+                    Err(err) => panic!("{:?}", err),
+                }
+            }
+
+            let future = present.then_signal_fence_and_flush().unwrap();
+            previous_frame_end = Box::new(future) as Box<_>;
+        }
+
+        let mut done = false;
+        events_loop.poll_events(|ev| {
+            match ev {
+                winit::Event::WindowEvent { event: winit::WindowEvent::Closed, .. } => done = true,
+                _ => ()
+            }
+        });
+        if done { return; }
+    }
+}
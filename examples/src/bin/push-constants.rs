@@ -0,0 +1,202 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+// This example shows how to draw with a pipeline whose shaders only use push constants.
+//
+// Since none of the shaders declares a descriptor, the layout of the pipeline doesn't have any
+// descriptor set and `()` is passed as the collection of sets when drawing. Both shaders declare
+// the same push constants block, from which the vertex shader reads an offset and the fragment
+// shader reads a color.
+//
+// The image is copied to a buffer and checked on the CPU.
+
+#[macro_use]
+extern crate vulkano;
+#[macro_use]
+extern crate vulkano_shader_derive;
+
+use vulkano::buffer::BufferUsage;
+use vulkano::buffer::CpuAccessibleBuffer;
+use vulkano::command_buffer::AutoCommandBufferBuilder;
+use vulkano::command_buffer::DynamicState;
+use vulkano::descriptor::PipelineLayoutAbstract;
+use vulkano::device::Device;
+use vulkano::device::DeviceExtensions;
+use vulkano::format::Format;
+use vulkano::framebuffer::Framebuffer;
+use vulkano::framebuffer::Subpass;
+use vulkano::image::AttachmentImage;
+use vulkano::image::ImageUsage;
+use vulkano::instance::Features;
+use vulkano::instance::Instance;
+use vulkano::instance::InstanceExtensions;
+use vulkano::pipeline::GraphicsPipeline;
+use vulkano::pipeline::viewport::Viewport;
+use vulkano::sync::now;
+use vulkano::sync::GpuFuture;
+
+use std::iter;
+use std::sync::Arc;
+
+fn main() {
+    let instance = Instance::new(None, &InstanceExtensions::none(), None)
+        .expect("failed to create Vulkan instance");
+
+    let physical = vulkano::instance::PhysicalDevice::enumerate(&instance)
+        .next().expect("no device available");
+
+    let queue = physical.queue_families().find(|&q| q.supports_graphics())
+        .expect("couldn't find a graphical queue family");
+
+    let (device, mut queues) = {
+        Device::new(&physical, &Features::none(), &DeviceExtensions::none(),
+                    [(queue, 0.5)].iter().cloned()).expect("failed to create device")
+    };
+
+    let queue = queues.next().unwrap();
+
+    #[derive(Debug, Clone)]
+    struct Vertex { position: [f32; 2] }
+    impl_vertex!(Vertex, position);
+
+    // A quad that covers the left half of the image once it is moved by the offset.
+    let vertex_buffer = CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::all(),
+                                                       Some(queue.family()), [
+        Vertex { position: [0.0, -1.0] },
+        Vertex { position: [0.0, 1.0] },
+        Vertex { position: [1.0, -1.0] },
+        Vertex { position: [1.0, -1.0] },
+        Vertex { position: [0.0, 1.0] },
+        Vertex { position: [1.0, 1.0] }
+    ].iter().cloned()).expect("failed to create buffer");
+
+    mod vs {
+        #[derive(VulkanoShader)]
+        #[ty = "vertex"]
+        #[src = "
+#version 450
+
+layout(push_constant) uniform PushConstants {
+    vec4 color;
+    vec2 offset;
+} pc;
+
+layout(location = 0) in vec2 position;
+
+void main() {
+    gl_Position = vec4(position + pc.offset, 0.0, 1.0);
+}
+"]
+        struct Dummy;
+    }
+
+    mod fs {
+        #[derive(VulkanoShader)]
+        #[ty = "fragment"]
+        #[src = "
+#version 450
+
+layout(push_constant) uniform PushConstants {
+    vec4 color;
+    vec2 offset;
+} pc;
+
+layout(location = 0) out vec4 f_color;
+
+void main() {
+    f_color = pc.color;
+}
+"]
+        struct Dummy;
+    }
+
+    let vs = vs::Shader::load(&device).expect("failed to create shader module");
+    let fs = fs::Shader::load(&device).expect("failed to create shader module");
+
+    let render_pass = Arc::new(single_pass_renderpass!(device.clone(),
+        attachments: {
+            color: {
+                load: Clear,
+                store: Store,
+                format: Format::R8G8B8A8Unorm,
+                samples: 1,
+            }
+        },
+        pass: {
+            color: [color],
+            depth_stencil: {}
+        }
+    ).unwrap());
+
+    let image = AttachmentImage::with_usage(device.clone(), [64, 64], Format::R8G8B8A8Unorm,
+                                            ImageUsage {
+                                                transfer_source: true,
+                                                color_attachment: true,
+                                                .. ImageUsage::none()
+                                            }).unwrap();
+    let framebuffer = Arc::new(Framebuffer::start(render_pass.clone())
+        .add(image.clone()).unwrap()
+        .build().unwrap());
+
+    let pipeline = Arc::new(GraphicsPipeline::start()
+        .vertex_input_single_buffer::<Vertex>()
+        .vertex_shader(vs.main_entry_point(), ())
+        .triangle_list()
+        .viewports(iter::once(Viewport {
+            origin: [0.0, 0.0],
+            depth_range: 0.0 .. 1.0,
+            dimensions: [64.0, 64.0],
+        }))
+        .fragment_shader(fs.main_entry_point(), ())
+        .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+        .build(device.clone())
+        .unwrap());
+
+    // Neither shader declares a descriptor, therefore the pipeline has no descriptor set.
+    assert_eq!(pipeline.num_sets(), 0);
+
+    let output = CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::all(),
+                                                Some(queue.family()),
+                                                (0 .. 64 * 64).map(|_| [0u8; 4]))
+        .expect("failed to create buffer");
+
+    let push_constants = vs::ty::PushConstants {
+        color: [1.0, 0.0, 0.0, 1.0],
+        offset: [-1.0, 0.0],
+    };
+
+    let command_buffer = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap()
+        .begin_render_pass(framebuffer.clone(), false, vec![[0.0, 0.0, 1.0, 1.0].into()])
+        .unwrap()
+        .draw(pipeline.clone(), DynamicState::none(), vertex_buffer.clone(), (), push_constants)
+        .unwrap()
+        .end_render_pass()
+        .unwrap()
+        .copy_image_to_buffer(image.clone(), output.clone())
+        .unwrap()
+        .host_read_barrier(output.clone())
+        .unwrap()
+        .build().unwrap();
+
+    let future = now(device.clone())
+        .then_execute(queue.clone(), command_buffer).unwrap()
+        .then_signal_fence_and_flush().unwrap();
+    future.wait(None).unwrap();
+
+    // The left half is red and the right half keeps the blue clear color.
+    let content = output.read().expect("failed to lock buffer for reading");
+    for y in 0 .. 64 {
+        for x in 0 .. 64 {
+            let expected = if x < 32 { [255, 0, 0, 255] } else { [0, 0, 255, 255] };
+            assert_eq!(content[y * 64 + x], expected);
+        }
+    }
+
+    println!("Success");
+}
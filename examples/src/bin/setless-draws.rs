@@ -0,0 +1,220 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+// This example measures the cost of recording draw commands with a pipeline that doesn't have
+// any descriptor set, compared to a pipeline that needs one.
+//
+// Both pipelines draw a triangle with a color. The first one receives the color through push
+// constants and is drawn with `()` as its collection of descriptor sets, which records neither a
+// descriptor set binding nor any validation work for the sets. The second one reads the color
+// from a uniform buffer and its descriptor set is bound at each draw.
+//
+// Run it in release mode in order to get meaningful timings.
+
+#[macro_use]
+extern crate vulkano;
+#[macro_use]
+extern crate vulkano_shader_derive;
+extern crate time;
+
+use vulkano::buffer::BufferUsage;
+use vulkano::buffer::CpuAccessibleBuffer;
+use vulkano::command_buffer::AutoCommandBufferBuilder;
+use vulkano::command_buffer::DynamicState;
+use vulkano::device::Device;
+use vulkano::device::DeviceExtensions;
+use vulkano::format::Format;
+use vulkano::framebuffer::Framebuffer;
+use vulkano::framebuffer::Subpass;
+use vulkano::image::AttachmentImage;
+use vulkano::instance::Features;
+use vulkano::instance::Instance;
+use vulkano::instance::InstanceExtensions;
+use vulkano::pipeline::GraphicsPipeline;
+use vulkano::pipeline::viewport::Viewport;
+
+use std::iter;
+use std::sync::Arc;
+
+const NUM_DRAWS: u32 = 100000;
+
+fn main() {
+    let instance = Instance::new(None, &InstanceExtensions::none(), None)
+        .expect("failed to create Vulkan instance");
+
+    let physical = vulkano::instance::PhysicalDevice::enumerate(&instance)
+        .next().expect("no device available");
+
+    let queue = physical.queue_families().find(|&q| q.supports_graphics())
+        .expect("couldn't find a graphical queue family");
+
+    let (device, mut queues) = {
+        Device::new(&physical, &Features::none(), &DeviceExtensions::none(),
+                    [(queue, 0.5)].iter().cloned()).expect("failed to create device")
+    };
+
+    let queue = queues.next().unwrap();
+
+    #[derive(Debug, Clone)]
+    struct Vertex { position: [f32; 2] }
+    impl_vertex!(Vertex, position);
+
+    let vertex_buffer = CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::all(),
+                                                       Some(queue.family()), [
+        Vertex { position: [-0.5, -0.25] },
+        Vertex { position: [0.0, 0.5] },
+        Vertex { position: [0.25, -0.1] }
+    ].iter().cloned()).expect("failed to create buffer");
+
+    let uniform_buffer = CpuAccessibleBuffer::from_data(device.clone(), BufferUsage::all(),
+                                                        Some(queue.family()),
+                                                        [1.0f32, 0.0, 0.0, 1.0])
+        .expect("failed to create buffer");
+
+    mod vs {
+        #[derive(VulkanoShader)]
+        #[ty = "vertex"]
+        #[src = "
+#version 450
+
+layout(location = 0) in vec2 position;
+
+void main() {
+    gl_Position = vec4(position, 0.0, 1.0);
+}
+"]
+        struct Dummy;
+    }
+
+    mod push_constants_fs {
+        #[derive(VulkanoShader)]
+        #[ty = "fragment"]
+        #[src = "
+#version 450
+
+layout(push_constant) uniform PushConstants {
+    vec4 color;
+} pc;
+
+layout(location = 0) out vec4 f_color;
+
+void main() {
+    f_color = pc.color;
+}
+"]
+        struct Dummy;
+    }
+
+    mod uniform_fs {
+        #[derive(VulkanoShader)]
+        #[ty = "fragment"]
+        #[src = "
+#version 450
+
+layout(set = 0, binding = 0) uniform Data {
+    vec4 color;
+} data;
+
+layout(location = 0) out vec4 f_color;
+
+void main() {
+    f_color = data.color;
+}
+"]
+        struct Dummy;
+    }
+
+    let vs = vs::Shader::load(&device).expect("failed to create shader module");
+    let push_constants_fs = push_constants_fs::Shader::load(&device)
+        .expect("failed to create shader module");
+    let uniform_fs = uniform_fs::Shader::load(&device).expect("failed to create shader module");
+
+    let render_pass = Arc::new(single_pass_renderpass!(device.clone(),
+        attachments: {
+            color: {
+                load: Clear,
+                store: Store,
+                format: Format::R8G8B8A8Unorm,
+                samples: 1,
+            }
+        },
+        pass: {
+            color: [color],
+            depth_stencil: {}
+        }
+    ).unwrap());
+
+    let image = AttachmentImage::new(device.clone(), [64, 64], Format::R8G8B8A8Unorm).unwrap();
+    let framebuffer = Arc::new(Framebuffer::start(render_pass.clone())
+        .add(image.clone()).unwrap()
+        .build().unwrap());
+
+    let viewport = Viewport {
+        origin: [0.0, 0.0],
+        depth_range: 0.0 .. 1.0,
+        dimensions: [64.0, 64.0],
+    };
+
+    let setless_pipeline = Arc::new(GraphicsPipeline::start()
+        .vertex_input_single_buffer::<Vertex>()
+        .vertex_shader(vs.main_entry_point(), ())
+        .triangle_list()
+        .viewports(iter::once(viewport.clone()))
+        .fragment_shader(push_constants_fs.main_entry_point(), ())
+        .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+        .build(device.clone())
+        .unwrap());
+
+    let uniform_pipeline = Arc::new(GraphicsPipeline::start()
+        .vertex_input_single_buffer::<Vertex>()
+        .vertex_shader(vs.main_entry_point(), ())
+        .triangle_list()
+        .viewports(iter::once(viewport))
+        .fragment_shader(uniform_fs.main_entry_point(), ())
+        .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+        .build(device.clone())
+        .unwrap());
+
+    let set = Arc::new(simple_descriptor_set!(uniform_pipeline.clone(), 0, {
+        data: uniform_buffer.clone()
+    }).unwrap());
+
+    let push_constants = push_constants_fs::ty::PushConstants { color: [1.0, 0.0, 0.0, 1.0] };
+
+    // Recording with `()`.
+    let start = time::precise_time_ns();
+    let mut builder = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap()
+        .begin_render_pass(framebuffer.clone(), false, vec![[0.0, 0.0, 0.0, 1.0].into()])
+        .unwrap();
+    for _ in 0 .. NUM_DRAWS {
+        builder = builder.draw(setless_pipeline.clone(), DynamicState::none(),
+                               vertex_buffer.clone(), (), push_constants)
+            .unwrap();
+    }
+    let _ = builder.end_render_pass().unwrap().build().unwrap();
+    let setless_ns = time::precise_time_ns() - start;
+
+    // Recording with a descriptor set.
+    let start = time::precise_time_ns();
+    let mut builder = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap()
+        .begin_render_pass(framebuffer.clone(), false, vec![[0.0, 0.0, 0.0, 1.0].into()])
+        .unwrap();
+    for _ in 0 .. NUM_DRAWS {
+        builder = builder.draw(uniform_pipeline.clone(), DynamicState::none(),
+                               vertex_buffer.clone(), set.clone(), ())
+            .unwrap();
+    }
+    let _ = builder.end_render_pass().unwrap().build().unwrap();
+    let uniform_ns = time::precise_time_ns() - start;
+
+    println!("{} draws without descriptor set: {} ns per draw", NUM_DRAWS,
+             setless_ns / NUM_DRAWS as u64);
+    println!("{} draws with a descriptor set: {} ns per draw", NUM_DRAWS,
+             uniform_ns / NUM_DRAWS as u64);
+}
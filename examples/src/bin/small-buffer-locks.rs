@@ -0,0 +1,92 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+// This example measures the cost of locking many small CPU-accessible buffers from the CPU.
+//
+// The host-visible memory pool maps each of its chunks of memory once, when the chunk is
+// allocated, and all the buffers allocated in a chunk share this mapping. Locking a buffer
+// therefore doesn't call `vkMapMemory`, only `vkInvalidateMappedMemoryRanges` and
+// `vkFlushMappedMemoryRanges` if the memory isn't host-coherent.
+//
+// Each frame writes to then reads from 1000 buffers that contain a single `u32`. For comparison,
+// the same values are written and read through a single buffer that contains all of them.
+//
+// Run it in release mode in order to get meaningful timings.
+
+extern crate vulkano;
+extern crate time;
+
+use vulkano::buffer::BufferUsage;
+use vulkano::buffer::CpuAccessibleBuffer;
+use vulkano::device::Device;
+use vulkano::device::DeviceExtensions;
+use vulkano::instance::Features;
+use vulkano::instance::Instance;
+use vulkano::instance::InstanceExtensions;
+
+const NUM_BUFFERS: u32 = 1000;
+const NUM_FRAMES: u32 = 100;
+
+fn main() {
+    let instance = Instance::new(None, &InstanceExtensions::none(), None)
+        .expect("failed to create Vulkan instance");
+
+    let physical = vulkano::instance::PhysicalDevice::enumerate(&instance)
+        .next().expect("no device available");
+
+    let queue = physical.queue_families().next().expect("couldn't find a queue family");
+
+    let (device, mut queues) = {
+        Device::new(&physical, &Features::none(), &DeviceExtensions::none(),
+                    [(queue, 0.5)].iter().cloned()).expect("failed to create device")
+    };
+
+    let queue = queues.next().unwrap();
+
+    let small_buffers = (0 .. NUM_BUFFERS)
+        .map(|n| {
+            CpuAccessibleBuffer::from_data(device.clone(), BufferUsage::all(),
+                                           Some(queue.family()), n)
+                .expect("failed to create buffer")
+        })
+        .collect::<Vec<_>>();
+
+    let big_buffer = CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::all(),
+                                                    Some(queue.family()), 0 .. NUM_BUFFERS)
+        .expect("failed to create buffer");
+
+    // Locking each small buffer.
+    let start = time::precise_time_ns();
+    for frame in 0 .. NUM_FRAMES {
+        for buffer in small_buffers.iter() {
+            *buffer.write().unwrap() += frame;
+        }
+        for (n, buffer) in small_buffers.iter().enumerate() {
+            assert!(*buffer.read().unwrap() >= n as u32);
+        }
+    }
+    let small_ns = time::precise_time_ns() - start;
+
+    // Locking the single buffer once per frame for each access.
+    let start = time::precise_time_ns();
+    for frame in 0 .. NUM_FRAMES {
+        for value in big_buffer.write().unwrap().iter_mut() {
+            *value += frame;
+        }
+        for (n, value) in big_buffer.read().unwrap().iter().enumerate() {
+            assert!(*value >= n as u32);
+        }
+    }
+    let big_ns = time::precise_time_ns() - start;
+
+    println!("{} small buffers: {} ns per frame, {} ns per lock", NUM_BUFFERS,
+             small_ns / NUM_FRAMES as u64, small_ns / (2 * NUM_BUFFERS * NUM_FRAMES) as u64);
+    println!("Single buffer of {} values: {} ns per frame", NUM_BUFFERS,
+             big_ns / NUM_FRAMES as u64);
+}
@@ -41,7 +41,7 @@ fn main() {
     let window = winit::WindowBuilder::new().build_vk_surface(&events_loop, instance.clone()).unwrap();
 
     let queue = physical.queue_families().find(|&q| q.supports_graphics() &&
-                                                   window.surface().is_supported(q).unwrap_or(false))
+                                                   q.supports_surface(window.surface()).unwrap_or(false))
                                                 .expect("couldn't find a graphical queue family");
 
     let device_ext = vulkano::device::DeviceExtensions {
@@ -140,7 +140,7 @@ fn main() {
 
     let set = Arc::new(simple_descriptor_set!(pipeline.clone(), 0, {
         uniforms: uniform_buffer.clone()
-    }));
+    }).unwrap());
 
     let framebuffers = images.iter().map(|image| {
         Arc::new(vulkano::framebuffer::Framebuffer::start(renderpass.clone())
@@ -0,0 +1,155 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+// This example shows how to draw on a transparent window, so that the desktop shows through.
+//
+// Whether the compositor takes the alpha channel of the swapchain images into account is
+// controlled by the composite alpha mode of the swapchain. Not all platforms support a
+// transparent mode, so we pass a list of preferences to `choose_composite_alpha` and adapt the
+// colors we write depending on what we get.
+
+#[macro_use]
+extern crate vulkano;
+extern crate winit;
+extern crate vulkano_win;
+
+use vulkano_win::VkSurfaceBuild;
+
+use vulkano::command_buffer::AutoCommandBufferBuilder;
+use vulkano::device::Device;
+use vulkano::framebuffer::Framebuffer;
+use vulkano::instance::Instance;
+use vulkano::swapchain;
+use vulkano::swapchain::CompositeAlpha;
+use vulkano::swapchain::PresentMode;
+use vulkano::swapchain::Swapchain;
+use vulkano::sync::now;
+use vulkano::sync::GpuFuture;
+
+use std::sync::Arc;
+
+fn main() {
+    let extensions = vulkano_win::required_extensions();
+    let instance = Instance::new(None, &extensions, None).expect("failed to create instance");
+
+    let physical = vulkano::instance::PhysicalDevice::enumerate(&instance)
+                            .next().expect("no device available");
+
+    // The window itself must be transparent as well, otherwise the compositor ignores the alpha
+    // channel no matter what the swapchain says.
+    let events_loop = winit::EventsLoop::new();
+    let window = winit::WindowBuilder::new()
+        .with_transparency(true)
+        .with_decorations(false)
+        .build_vk_surface(&events_loop, instance.clone())
+        .unwrap();
+
+    let queue = physical.queue_families().find(|&q| {
+        q.supports_graphics() && q.supports_surface(window.surface()).unwrap_or(false)
+    }).expect("couldn't find a graphical queue family");
+
+    let (device, mut queues) = {
+        let device_ext = vulkano::device::DeviceExtensions {
+            khr_swapchain: true,
+            .. vulkano::device::DeviceExtensions::none()
+        };
+
+        Device::new(&physical, physical.supported_features(), &device_ext,
+                    [(queue, 0.5)].iter().cloned()).expect("failed to create device")
+    };
+    let queue = queues.next().unwrap();
+
+    let (swapchain, images) = {
+        let caps = window.surface().capabilities(physical)
+                         .expect("failed to get surface capabilities");
+
+        // We prefer `PostMultiplied`, where the compositor multiplies the colors by the alpha
+        // value itself. If the surface doesn't support it, we fall back to `PreMultiplied` and
+        // do the multiplication ourselves. If neither is supported the window can't be
+        // transparent on this platform.
+        let alpha = match caps.choose_composite_alpha(vec![CompositeAlpha::PostMultiplied,
+                                                           CompositeAlpha::PreMultiplied]) {
+            Ok(alpha) => alpha,
+            Err(err) => {
+                println!("{}; the window will be opaque", err);
+                caps.default_composite_alpha()
+            },
+        };
+
+        let dimensions = caps.current_extent.unwrap_or([1280, 1024]);
+        let (format, _) = caps.default_format();
+
+        Swapchain::new(device.clone(), window.surface().clone(), caps.min_image_count, format,
+                       dimensions, 1, caps.supported_usage_flags, &queue,
+                       caps.default_transform(), alpha, PresentMode::Fifo, true,
+                       None).expect("failed to create swapchain")
+    };
+
+    println!("Using composite alpha {:?}", swapchain.composite_alpha());
+
+    // Half-transparent orange. The color must be premultiplied if the compositor expects it.
+    let rgb = [1.0, 0.5, 0.0];
+    let alpha = 0.5;
+    let clear_color = if swapchain.composite_alpha() == CompositeAlpha::PreMultiplied {
+        [rgb[0] * alpha, rgb[1] * alpha, rgb[2] * alpha, alpha]
+    } else {
+        [rgb[0], rgb[1], rgb[2], alpha]
+    };
+
+    let render_pass = Arc::new(single_pass_renderpass!(device.clone(),
+        attachments: {
+            color: {
+                load: Clear,
+                store: Store,
+                format: swapchain.format(),
+                samples: 1,
+            }
+        },
+        pass: {
+            color: [color],
+            depth_stencil: {}
+        }
+    ).unwrap());
+
+    let framebuffers = images.iter().map(|image| {
+        Arc::new(Framebuffer::start(render_pass.clone())
+            .add(image.clone()).unwrap()
+            .build().unwrap())
+    }).collect::<Vec<_>>();
+
+    let mut previous_frame_end = Box::new(now(device.clone())) as Box<GpuFuture>;
+
+    loop {
+        previous_frame_end.cleanup_finished();
+
+        let (image_num, acquire_future) = swapchain::acquire_next_image(swapchain.clone(),
+                                                                        None).unwrap();
+
+        let command_buffer = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap()
+            .begin_render_pass(framebuffers[image_num].clone(), false,
+                               vec![clear_color.into()]).unwrap()
+            .end_render_pass().unwrap()
+            .build().unwrap();
+
+        let future = previous_frame_end.join(acquire_future)
+            .then_execute(queue.clone(), command_buffer).unwrap()
+            .then_swapchain_present(queue.clone(), swapchain.clone(), image_num)
+            .then_signal_fence_and_flush().unwrap();
+        previous_frame_end = Box::new(future) as Box<_>;
+
+        let mut done = false;
+        events_loop.poll_events(|ev| {
+            match ev {
+                winit::Event::WindowEvent { event: winit::WindowEvent::Closed, .. } => done = true,
+                _ => ()
+            }
+        });
+        if done { return; }
+    }
+}
@@ -49,7 +49,6 @@ use vulkano::pipeline::GraphicsPipeline;
 use vulkano::pipeline::viewport::Viewport;
 use vulkano::swapchain;
 use vulkano::swapchain::PresentMode;
-use vulkano::swapchain::SurfaceTransform;
 use vulkano::swapchain::Swapchain;
 use vulkano::sync::now;
 use vulkano::sync::GpuFuture;
@@ -117,7 +116,7 @@ fn main() {
     // We have to choose which queues to use early on, because we will need this info very soon.
     let queue = physical.queue_families().find(|&q| {
         // We take the first queue that supports drawing to our window.
-        q.supports_graphics() && window.surface().is_supported(q).unwrap_or(false)
+        q.supports_graphics() && q.supports_surface(window.surface()).unwrap_or(false)
     }).expect("couldn't find a graphical queue family");
 
     // Now initializing the device. This is probably the most important object of Vulkan.
@@ -170,7 +169,12 @@ fn main() {
 
         // The alpha mode indicates how the alpha value of the final image will behave. For example
         // you can choose whether the window will be opaque or transparent.
-        let alpha = caps.supported_composite_alpha.iter().next().unwrap();
+        let alpha = caps.default_composite_alpha();
+
+        // The transform is applied by the presentation engine to the image before showing it.
+        // On mobile platforms you may want to use `caps.current_transform` instead and rotate
+        // your rendering yourself.
+        let transform = caps.default_transform();
 
         // Choosing the internal format that the images will have.
         let format = caps.supported_formats[0].0;
@@ -178,7 +182,7 @@ fn main() {
         // Please take a look at the docs for the meaning of the parameters we didn't mention.
         Swapchain::new(device.clone(), window.surface().clone(), caps.min_image_count, format,
                        dimensions, 1, caps.supported_usage_flags, &queue,
-                       SurfaceTransform::Identity, alpha, PresentMode::Fifo, true,
+                       transform, alpha, PresentMode::Fifo, true,
                        None).expect("failed to create swapchain")
     };
 
@@ -57,6 +57,9 @@ pub type DisplayKHR = u64;
 pub type DisplayModeKHR = u64;
 pub type DebugReportCallbackEXT = u64;
 pub type DescriptorUpdateTemplateKHR = u64;
+pub type ShaderEXT = u64;
+pub type PrivateDataSlotEXT = u64;
+pub type IndirectCommandsLayoutNV = u64;
 
 pub const LOD_CLAMP_NONE: f32 = 1000.0;
 pub const REMAINING_MIP_LEVELS: u32 = 0xffffffff;
@@ -166,6 +169,7 @@ pub const STRUCTURE_TYPE_MIR_SURFACE_CREATE_INFO_KHR: u32 = 1000007000;
 pub const STRUCTURE_TYPE_ANDROID_SURFACE_CREATE_INFO_KHR: u32 = 1000008000;
 pub const STRUCTURE_TYPE_WIN32_SURFACE_CREATE_INFO_KHR: u32 = 1000009000;
 pub const STRUCTURE_TYPE_DEBUG_REPORT_CREATE_INFO_EXT: u32 = 1000011000;
+pub const STRUCTURE_TYPE_QUEUE_FAMILY_VIDEO_PROPERTIES_KHR: u32 = 1000023012;
 pub const STRUCTURE_TYPE_IOS_SURFACE_CREATE_INFO_MVK: u32 = 1000000000 + (52 * 1000);
 pub const STRUCTURE_TYPE_MACOS_SURFACE_CREATE_INFO_MVK: u32 = 1000000000 + (53 * 1000);
 pub const STRUCTURE_TYPE_PHYSICAL_DEVICE_FEATURES_2_KHR: u32 = 1000059000;
@@ -180,6 +184,59 @@ pub const STRUCTURE_TYPE_PHYSICAL_DEVICE_SPARSE_IMAGE_FORMAT_INFO_2_KHR: u32 = 1
 pub const STRUCTURE_TYPE_VI_SURFACE_CREATE_INFO_NN: u32 = 1000062000;
 pub const STRUCTURE_TYPE_PHYSICAL_DEVICE_PUSH_DESCRIPTOR_PROPERTIES_KHR: u32 = 1000080000;
 pub const STRUCTURE_TYPE_DESCRIPTOR_UPDATE_TEMPLATE_CREATE_INFO_KHR: u32 = 1000085000;
+pub const STRUCTURE_TYPE_PHYSICAL_DEVICE_SUBGROUP_PROPERTIES: u32 = 1000094000;
+pub const STRUCTURE_TYPE_PHYSICAL_DEVICE_DEPTH_CLIP_ENABLE_FEATURES_EXT: u32 = 1000102000;
+pub const STRUCTURE_TYPE_PIPELINE_RASTERIZATION_DEPTH_CLIP_STATE_CREATE_INFO_EXT: u32 = 1000102001;
+pub const STRUCTURE_TYPE_PHYSICAL_DEVICE_SURFACE_INFO_2_KHR: u32 = 1000119000;
+pub const STRUCTURE_TYPE_SURFACE_CAPABILITIES_2_KHR: u32 = 1000119001;
+pub const STRUCTURE_TYPE_DEBUG_UTILS_OBJECT_NAME_INFO_EXT: u32 = 1000128000;
+pub const STRUCTURE_TYPE_DEBUG_UTILS_LABEL_EXT: u32 = 1000128002;
+pub const STRUCTURE_TYPE_PIPELINE_CREATION_FEEDBACK_CREATE_INFO_EXT: u32 = 1000192000;
+pub const STRUCTURE_TYPE_PHYSICAL_DEVICE_TIMELINE_SEMAPHORE_FEATURES_KHR: u32 = 1000207000;
+pub const STRUCTURE_TYPE_PHYSICAL_DEVICE_TIMELINE_SEMAPHORE_PROPERTIES_KHR: u32 = 1000207001;
+pub const STRUCTURE_TYPE_SEMAPHORE_TYPE_CREATE_INFO_KHR: u32 = 1000207002;
+pub const STRUCTURE_TYPE_TIMELINE_SEMAPHORE_SUBMIT_INFO_KHR: u32 = 1000207003;
+pub const STRUCTURE_TYPE_SEMAPHORE_WAIT_INFO_KHR: u32 = 1000207004;
+pub const STRUCTURE_TYPE_SEMAPHORE_SIGNAL_INFO_KHR: u32 = 1000207005;
+pub const STRUCTURE_TYPE_PHYSICAL_DEVICE_SUBGROUP_SIZE_CONTROL_PROPERTIES_EXT: u32 = 1000225000;
+pub const STRUCTURE_TYPE_PIPELINE_SHADER_STAGE_REQUIRED_SUBGROUP_SIZE_CREATE_INFO_EXT: u32 = 1000225001;
+pub const STRUCTURE_TYPE_PHYSICAL_DEVICE_SUBGROUP_SIZE_CONTROL_FEATURES_EXT: u32 = 1000225002;
+pub const STRUCTURE_TYPE_SURFACE_PROTECTED_CAPABILITIES_KHR: u32 = 1000239000;
+pub const STRUCTURE_TYPE_PHYSICAL_DEVICE_EXTENDED_DYNAMIC_STATE_FEATURES_EXT: u32 = 1000267000;
+pub const STRUCTURE_TYPE_PHYSICAL_DEVICE_SWAPCHAIN_MAINTENANCE_1_FEATURES_EXT: u32 = 1000275000;
+pub const STRUCTURE_TYPE_SWAPCHAIN_PRESENT_FENCE_INFO_EXT: u32 = 1000275001;
+pub const STRUCTURE_TYPE_PHYSICAL_DEVICE_PRIVATE_DATA_FEATURES_EXT: u32 = 1000295000;
+pub const STRUCTURE_TYPE_DEVICE_PRIVATE_DATA_CREATE_INFO_EXT: u32 = 1000295001;
+pub const STRUCTURE_TYPE_PRIVATE_DATA_SLOT_CREATE_INFO_EXT: u32 = 1000295002;
+pub const STRUCTURE_TYPE_MEMORY_BARRIER_2_KHR: u32 = 1000314000;
+pub const STRUCTURE_TYPE_BUFFER_MEMORY_BARRIER_2_KHR: u32 = 1000314001;
+pub const STRUCTURE_TYPE_IMAGE_MEMORY_BARRIER_2_KHR: u32 = 1000314002;
+pub const STRUCTURE_TYPE_DEPENDENCY_INFO_KHR: u32 = 1000314003;
+pub const STRUCTURE_TYPE_SUBMIT_INFO_2_KHR: u32 = 1000314004;
+pub const STRUCTURE_TYPE_SEMAPHORE_SUBMIT_INFO_KHR: u32 = 1000314005;
+pub const STRUCTURE_TYPE_COMMAND_BUFFER_SUBMIT_INFO_KHR: u32 = 1000314006;
+pub const STRUCTURE_TYPE_PHYSICAL_DEVICE_SYNCHRONIZATION_2_FEATURES_KHR: u32 = 1000314007;
+pub const STRUCTURE_TYPE_VERTEX_INPUT_BINDING_DESCRIPTION_2_EXT: u32 = 1000352001;
+pub const STRUCTURE_TYPE_VERTEX_INPUT_ATTRIBUTE_DESCRIPTION_2_EXT: u32 = 1000352002;
+pub const STRUCTURE_TYPE_PHYSICAL_DEVICE_IMAGE_VIEW_MIN_LOD_FEATURES_EXT: u32 = 1000391000;
+pub const STRUCTURE_TYPE_IMAGE_VIEW_MIN_LOD_CREATE_INFO_EXT: u32 = 1000391001;
+pub const STRUCTURE_TYPE_MEMORY_REQUIREMENTS_2_KHR: u32 = 1000146003;
+pub const STRUCTURE_TYPE_PHYSICAL_DEVICE_DEVICE_GENERATED_COMMANDS_PROPERTIES_NV: u32 = 1000277000;
+pub const STRUCTURE_TYPE_INDIRECT_COMMANDS_LAYOUT_TOKEN_NV: u32 = 1000277003;
+pub const STRUCTURE_TYPE_INDIRECT_COMMANDS_LAYOUT_CREATE_INFO_NV: u32 = 1000277004;
+pub const STRUCTURE_TYPE_GENERATED_COMMANDS_INFO_NV: u32 = 1000277005;
+pub const STRUCTURE_TYPE_GENERATED_COMMANDS_MEMORY_REQUIREMENTS_INFO_NV: u32 = 1000277006;
+pub const STRUCTURE_TYPE_PHYSICAL_DEVICE_DEVICE_GENERATED_COMMANDS_FEATURES_NV: u32 = 1000277007;
+pub const STRUCTURE_TYPE_RENDERING_INFO_KHR: u32 = 1000044000;
+pub const STRUCTURE_TYPE_RENDERING_ATTACHMENT_INFO_KHR: u32 = 1000044001;
+pub const STRUCTURE_TYPE_PHYSICAL_DEVICE_DYNAMIC_RENDERING_FEATURES_KHR: u32 = 1000044003;
+pub const STRUCTURE_TYPE_PHYSICAL_DEVICE_MAINTENANCE_5_FEATURES_KHR: u32 = 1000470000;
+pub const STRUCTURE_TYPE_PHYSICAL_DEVICE_SHADER_OBJECT_FEATURES_EXT: u32 = 1000482000;
+pub const STRUCTURE_TYPE_PHYSICAL_DEVICE_SHADER_OBJECT_PROPERTIES_EXT: u32 = 1000482001;
+pub const STRUCTURE_TYPE_SHADER_CREATE_INFO_EXT: u32 = 1000482002;
+pub const STRUCTURE_TYPE_PHYSICAL_DEVICE_COOPERATIVE_MATRIX_FEATURES_KHR: u32 = 1000506000;
+pub const STRUCTURE_TYPE_COOPERATIVE_MATRIX_PROPERTIES_KHR: u32 = 1000506001;
+pub const STRUCTURE_TYPE_PHYSICAL_DEVICE_COOPERATIVE_MATRIX_PROPERTIES_KHR: u32 = 1000506002;
 
 pub type SystemAllocationScope = u32;
 pub const SYSTEM_ALLOCATION_SCOPE_COMMAND: u32 = 0;
@@ -414,6 +471,7 @@ pub const IMAGE_LAYOUT_TRANSFER_SRC_OPTIMAL: u32 = 6;
 pub const IMAGE_LAYOUT_TRANSFER_DST_OPTIMAL: u32 = 7;
 pub const IMAGE_LAYOUT_PREINITIALIZED: u32 = 8;
 pub const IMAGE_LAYOUT_PRESENT_SRC_KHR: u32 = 1000001002;
+pub const IMAGE_LAYOUT_SHARED_PRESENT_KHR: u32 = 1000111000;
 
 pub type ImageViewType = u32;
 pub const IMAGE_VIEW_TYPE_1D: u32 = 0;
@@ -535,6 +593,10 @@ pub const DYNAMIC_STATE_DEPTH_BOUNDS: u32 = 5;
 pub const DYNAMIC_STATE_STENCIL_COMPARE_MASK: u32 = 6;
 pub const DYNAMIC_STATE_STENCIL_WRITE_MASK: u32 = 7;
 pub const DYNAMIC_STATE_STENCIL_REFERENCE: u32 = 8;
+pub const DYNAMIC_STATE_DEPTH_TEST_ENABLE_EXT: u32 = 1000267006;
+pub const DYNAMIC_STATE_DEPTH_WRITE_ENABLE_EXT: u32 = 1000267007;
+pub const DYNAMIC_STATE_DEPTH_COMPARE_OP_EXT: u32 = 1000267008;
+pub const DYNAMIC_STATE_STENCIL_TEST_ENABLE_EXT: u32 = 1000267010;
 
 pub type Filter = u32;
 pub const FILTER_NEAREST: u32 = 0;
@@ -656,8 +718,21 @@ pub const QUEUE_GRAPHICS_BIT: u32 = 0x00000001;
 pub const QUEUE_COMPUTE_BIT: u32 = 0x00000002;
 pub const QUEUE_TRANSFER_BIT: u32 = 0x00000004;
 pub const QUEUE_SPARSE_BINDING_BIT: u32 = 0x00000008;
+pub const QUEUE_PROTECTED_BIT: u32 = 0x00000010;
+pub const QUEUE_VIDEO_DECODE_BIT_KHR: u32 = 0x00000020;
+pub const QUEUE_VIDEO_ENCODE_BIT_KHR: u32 = 0x00000040;
+pub const QUEUE_OPTICAL_FLOW_BIT_NV: u32 = 0x00000100;
 pub type QueueFlags = Flags;
 
+pub type VideoCodecOperationFlagBitsKHR = u32;
+pub const VIDEO_CODEC_OPERATION_DECODE_H264_BIT_KHR: u32 = 0x00000001;
+pub const VIDEO_CODEC_OPERATION_DECODE_H265_BIT_KHR: u32 = 0x00000002;
+pub const VIDEO_CODEC_OPERATION_DECODE_AV1_BIT_KHR: u32 = 0x00000004;
+pub const VIDEO_CODEC_OPERATION_ENCODE_H264_BIT_KHR: u32 = 0x00010000;
+pub const VIDEO_CODEC_OPERATION_ENCODE_H265_BIT_KHR: u32 = 0x00020000;
+pub const VIDEO_CODEC_OPERATION_ENCODE_AV1_BIT_KHR: u32 = 0x00040000;
+pub type VideoCodecOperationFlagsKHR = Flags;
+
 
 pub type MemoryPropertyFlagBits = u32;
 pub const MEMORY_PROPERTY_DEVICE_LOCAL_BIT: u32 = 0x00000001;
@@ -693,7 +768,9 @@ pub const PIPELINE_STAGE_BOTTOM_OF_PIPE_BIT: u32 = 0x00002000;
 pub const PIPELINE_STAGE_HOST_BIT: u32 = 0x00004000;
 pub const PIPELINE_STAGE_ALL_GRAPHICS_BIT: u32 = 0x00008000;
 pub const PIPELINE_STAGE_ALL_COMMANDS_BIT: u32 = 0x00010000;
+pub const PIPELINE_STAGE_COMMAND_PREPROCESS_BIT_NV: u32 = 0x00020000;
 pub type PipelineStageFlags = Flags;
+pub type PipelineStageFlags2KHR = u64;
 pub type MemoryMapFlags = Flags;
 
 
@@ -776,7 +853,11 @@ pub type PipelineCreateFlagBits = u32;
 pub const PIPELINE_CREATE_DISABLE_OPTIMIZATION_BIT: u32 = 0x00000001;
 pub const PIPELINE_CREATE_ALLOW_DERIVATIVES_BIT: u32 = 0x00000002;
 pub const PIPELINE_CREATE_DERIVATIVE_BIT: u32 = 0x00000004;
+pub const PIPELINE_CREATE_INDIRECT_BINDABLE_BIT_NV: u32 = 0x00040000;
 pub type PipelineCreateFlags = Flags;
+pub type PipelineShaderStageCreateFlagBits = u32;
+pub const PIPELINE_SHADER_STAGE_CREATE_ALLOW_VARYING_SUBGROUP_SIZE_BIT_EXT: u32 = 0x00000001;
+pub const PIPELINE_SHADER_STAGE_CREATE_REQUIRE_FULL_SUBGROUPS_BIT_EXT: u32 = 0x00000002;
 pub type PipelineShaderStageCreateFlags = Flags;
 
 
@@ -816,6 +897,36 @@ pub type ColorComponentFlags = Flags;
 pub type PipelineDynamicStateCreateFlags = Flags;
 pub type PipelineLayoutCreateFlags = Flags;
 pub type ShaderStageFlags = Flags;
+pub type SubgroupFeatureFlagBits = u32;
+pub const SUBGROUP_FEATURE_BASIC_BIT: u32 = 0x00000001;
+pub const SUBGROUP_FEATURE_VOTE_BIT: u32 = 0x00000002;
+pub const SUBGROUP_FEATURE_ARITHMETIC_BIT: u32 = 0x00000004;
+pub const SUBGROUP_FEATURE_BALLOT_BIT: u32 = 0x00000008;
+pub const SUBGROUP_FEATURE_SHUFFLE_BIT: u32 = 0x00000010;
+pub const SUBGROUP_FEATURE_SHUFFLE_RELATIVE_BIT: u32 = 0x00000020;
+pub const SUBGROUP_FEATURE_CLUSTERED_BIT: u32 = 0x00000040;
+pub const SUBGROUP_FEATURE_QUAD_BIT: u32 = 0x00000080;
+pub type SubgroupFeatureFlags = Flags;
+
+pub type ComponentTypeKHR = u32;
+pub const COMPONENT_TYPE_FLOAT16_KHR: u32 = 0;
+pub const COMPONENT_TYPE_FLOAT32_KHR: u32 = 1;
+pub const COMPONENT_TYPE_FLOAT64_KHR: u32 = 2;
+pub const COMPONENT_TYPE_SINT8_KHR: u32 = 3;
+pub const COMPONENT_TYPE_SINT16_KHR: u32 = 4;
+pub const COMPONENT_TYPE_SINT32_KHR: u32 = 5;
+pub const COMPONENT_TYPE_SINT64_KHR: u32 = 6;
+pub const COMPONENT_TYPE_UINT8_KHR: u32 = 7;
+pub const COMPONENT_TYPE_UINT16_KHR: u32 = 8;
+pub const COMPONENT_TYPE_UINT32_KHR: u32 = 9;
+pub const COMPONENT_TYPE_UINT64_KHR: u32 = 10;
+
+pub type ScopeKHR = u32;
+pub const SCOPE_DEVICE_KHR: u32 = 1;
+pub const SCOPE_WORKGROUP_KHR: u32 = 2;
+pub const SCOPE_SUBGROUP_KHR: u32 = 3;
+pub const SCOPE_QUEUE_FAMILY_KHR: u32 = 5;
+
 pub type SamplerCreateFlags = Flags;
 pub type DescriptorSetLayoutCreateFlags = Flags;
 
@@ -852,6 +963,8 @@ pub const ACCESS_HOST_READ_BIT: u32 = 0x00002000;
 pub const ACCESS_HOST_WRITE_BIT: u32 = 0x00004000;
 pub const ACCESS_MEMORY_READ_BIT: u32 = 0x00008000;
 pub const ACCESS_MEMORY_WRITE_BIT: u32 = 0x00010000;
+pub const ACCESS_COMMAND_PREPROCESS_READ_BIT_NV: u32 = 0x00020000;
+pub const ACCESS_COMMAND_PREPROCESS_WRITE_BIT_NV: u32 = 0x00040000;
 pub type AccessFlags = Flags;
 
 
@@ -874,6 +987,60 @@ pub type CommandPoolResetFlags = Flags;
 pub type CommandPoolTrimFlagsKHR = Flags;
 
 
+pub type SemaphoreTypeKHR = u32;
+pub const SEMAPHORE_TYPE_BINARY_KHR: u32 = 0;
+pub const SEMAPHORE_TYPE_TIMELINE_KHR: u32 = 1;
+
+pub type SemaphoreWaitFlagBitsKHR = u32;
+pub const SEMAPHORE_WAIT_ANY_BIT_KHR: u32 = 0x00000001;
+pub type SemaphoreWaitFlagsKHR = Flags;
+
+pub type ShaderCodeTypeEXT = u32;
+pub const SHADER_CODE_TYPE_BINARY_EXT: u32 = 0;
+pub const SHADER_CODE_TYPE_SPIRV_EXT: u32 = 1;
+
+pub type ShaderCreateFlagBitsEXT = u32;
+pub const SHADER_CREATE_LINK_STAGE_BIT_EXT: u32 = 0x00000001;
+pub type ShaderCreateFlagsEXT = Flags;
+
+pub type RenderingFlagBitsKHR = u32;
+pub const RENDERING_CONTENTS_SECONDARY_COMMAND_BUFFERS_BIT_KHR: u32 = 0x00000001;
+pub const RENDERING_SUSPENDING_BIT_KHR: u32 = 0x00000002;
+pub const RENDERING_RESUMING_BIT_KHR: u32 = 0x00000004;
+pub type RenderingFlagsKHR = Flags;
+
+pub type ResolveModeFlagBitsKHR = u32;
+pub const RESOLVE_MODE_NONE_KHR: u32 = 0;
+pub const RESOLVE_MODE_SAMPLE_ZERO_BIT_KHR: u32 = 0x00000001;
+pub const RESOLVE_MODE_AVERAGE_BIT_KHR: u32 = 0x00000002;
+pub const RESOLVE_MODE_MIN_BIT_KHR: u32 = 0x00000004;
+pub const RESOLVE_MODE_MAX_BIT_KHR: u32 = 0x00000008;
+
+pub type SubmitFlagBitsKHR = u32;
+pub const SUBMIT_PROTECTED_BIT_KHR: u32 = 0x00000001;
+pub type SubmitFlagsKHR = Flags;
+
+pub type IndirectCommandsTokenTypeNV = u32;
+pub const INDIRECT_COMMANDS_TOKEN_TYPE_SHADER_GROUP_NV: u32 = 0;
+pub const INDIRECT_COMMANDS_TOKEN_TYPE_STATE_FLAGS_NV: u32 = 1;
+pub const INDIRECT_COMMANDS_TOKEN_TYPE_INDEX_BUFFER_NV: u32 = 2;
+pub const INDIRECT_COMMANDS_TOKEN_TYPE_VERTEX_BUFFER_NV: u32 = 3;
+pub const INDIRECT_COMMANDS_TOKEN_TYPE_PUSH_CONSTANT_NV: u32 = 4;
+pub const INDIRECT_COMMANDS_TOKEN_TYPE_DRAW_INDEXED_NV: u32 = 5;
+pub const INDIRECT_COMMANDS_TOKEN_TYPE_DRAW_NV: u32 = 6;
+pub const INDIRECT_COMMANDS_TOKEN_TYPE_DRAW_TASKS_NV: u32 = 7;
+
+pub type IndirectCommandsLayoutUsageFlagBitsNV = u32;
+pub const INDIRECT_COMMANDS_LAYOUT_USAGE_EXPLICIT_PREPROCESS_BIT_NV: u32 = 0x00000001;
+pub const INDIRECT_COMMANDS_LAYOUT_USAGE_INDEXED_SEQUENCES_BIT_NV: u32 = 0x00000002;
+pub const INDIRECT_COMMANDS_LAYOUT_USAGE_UNORDERED_SEQUENCES_BIT_NV: u32 = 0x00000004;
+pub type IndirectCommandsLayoutUsageFlagsNV = Flags;
+
+pub type IndirectStateFlagBitsNV = u32;
+pub const INDIRECT_STATE_FLAG_FRONTFACE_BIT_NV: u32 = 0x00000001;
+pub type IndirectStateFlagsNV = Flags;
+
+
 pub type CommandBufferUsageFlagBits = u32;
 pub const COMMAND_BUFFER_USAGE_ONE_TIME_SUBMIT_BIT: u32 = 0x00000001;
 pub const COMMAND_BUFFER_USAGE_RENDER_PASS_CONTINUE_BIT: u32 = 0x00000002;
@@ -938,6 +1105,8 @@ pub const PRESENT_MODE_IMMEDIATE_KHR: u32 = 0;
 pub const PRESENT_MODE_MAILBOX_KHR: u32 = 1;
 pub const PRESENT_MODE_FIFO_KHR: u32 = 2;
 pub const PRESENT_MODE_FIFO_RELAXED_KHR: u32 = 3;
+pub const PRESENT_MODE_SHARED_DEMAND_REFRESH_KHR: u32 = 1000111000;
+pub const PRESENT_MODE_SHARED_CONTINUOUS_REFRESH_KHR: u32 = 1000111001;
 
 pub type SurfaceTransformFlagBitsKHR = u32;
 pub const SURFACE_TRANSFORM_IDENTITY_BIT_KHR: u32 = 0x00000001;
@@ -958,6 +1127,24 @@ pub const COMPOSITE_ALPHA_POST_MULTIPLIED_BIT_KHR: u32 = 0x00000004;
 pub const COMPOSITE_ALPHA_INHERIT_BIT_KHR: u32 = 0x00000008;
 pub type CompositeAlphaFlagsKHR = Flags;
 
+pub type PipelineCreationFeedbackFlagBitsEXT = u32;
+pub const PIPELINE_CREATION_FEEDBACK_VALID_BIT_EXT: u32 = 0x00000001;
+pub const PIPELINE_CREATION_FEEDBACK_APPLICATION_PIPELINE_CACHE_HIT_BIT_EXT: u32 = 0x00000002;
+pub const PIPELINE_CREATION_FEEDBACK_BASE_PIPELINE_ACCELERATION_BIT_EXT: u32 = 0x00000004;
+pub type PipelineCreationFeedbackFlagsEXT = Flags;
+
+pub type ObjectType = u32;
+pub const OBJECT_TYPE_UNKNOWN: u32 = 0;
+pub const OBJECT_TYPE_SEMAPHORE: u32 = 5;
+pub const OBJECT_TYPE_FENCE: u32 = 7;
+pub const OBJECT_TYPE_DEVICE_MEMORY: u32 = 8;
+pub const OBJECT_TYPE_BUFFER: u32 = 9;
+pub const OBJECT_TYPE_IMAGE: u32 = 10;
+pub const OBJECT_TYPE_PIPELINE: u32 = 19;
+pub const OBJECT_TYPE_SWAPCHAIN_KHR: u32 = 1000001000;
+
+pub type PrivateDataSlotCreateFlagsEXT = Flags;
+
 pub type DebugReportObjectTypeEXT = u32;
 pub const DEBUG_REPORT_OBJECT_TYPE_UNKNOWN_EXT: u32 = 0;
 pub const DEBUG_REPORT_OBJECT_TYPE_INSTANCE_EXT: u32 = 1;
@@ -1581,6 +1768,21 @@ pub struct ShaderModuleCreateInfo {
     pub pCode: *const u32,
 }
 
+#[repr(C)]
+pub struct PipelineCreationFeedbackEXT {
+    pub flags: PipelineCreationFeedbackFlagsEXT,
+    pub duration: u64,
+}
+
+#[repr(C)]
+pub struct PipelineCreationFeedbackCreateInfoEXT {
+    pub sType: StructureType,
+    pub pNext: *const c_void,
+    pub pPipelineCreationFeedback: *mut PipelineCreationFeedbackEXT,
+    pub pipelineStageCreationFeedbackCount: u32,
+    pub pPipelineStageCreationFeedbacks: *mut PipelineCreationFeedbackEXT,
+}
+
 #[repr(C)]
 pub struct PipelineCacheCreateInfo {
     pub sType: StructureType,
@@ -2232,6 +2434,27 @@ pub struct SurfaceCapabilitiesKHR {
     pub supportedUsageFlags: ImageUsageFlags,
 }
 
+#[repr(C)]
+pub struct PhysicalDeviceSurfaceInfo2KHR {
+    pub sType: StructureType,
+    pub pNext: *const c_void,
+    pub surface: SurfaceKHR,
+}
+
+#[repr(C)]
+pub struct SurfaceCapabilities2KHR {
+    pub sType: StructureType,
+    pub pNext: *mut c_void,
+    pub surfaceCapabilities: SurfaceCapabilitiesKHR,
+}
+
+#[repr(C)]
+pub struct SurfaceProtectedCapabilitiesKHR {
+    pub sType: StructureType,
+    pub pNext: *mut c_void,
+    pub supportsProtected: Bool32,
+}
+
 #[repr(C)]
 pub struct SurfaceFormatKHR {
     pub format: Format,
@@ -2427,6 +2650,23 @@ pub struct DebugReportCallbackCreateInfoEXT {
     pub pUserData: *mut c_void,
 }
 
+#[repr(C)]
+pub struct DebugUtilsLabelEXT {
+    pub sType: StructureType,
+    pub pNext: *const c_void,
+    pub pLabelName: *const c_char,
+    pub color: [f32; 4],
+}
+
+#[repr(C)]
+pub struct DebugUtilsObjectNameInfoEXT {
+    pub sType: StructureType,
+    pub pNext: *const c_void,
+    pub objectType: ObjectType,
+    pub objectHandle: u64,
+    pub pObjectName: *const c_char,
+}
+
 #[repr(C)]
 pub struct IOSSurfaceCreateInfoMVK {
 	pub sType: StructureType,
@@ -2486,6 +2726,16 @@ pub struct PhysicalDeviceProperties2KHR {
     pub properties: PhysicalDeviceProperties,
 }
 
+#[repr(C)]
+pub struct PhysicalDeviceSubgroupProperties {
+    pub sType: StructureType,
+    pub pNext: *mut c_void,
+    pub subgroupSize: u32,
+    pub supportedStages: ShaderStageFlags,
+    pub supportedOperations: SubgroupFeatureFlags,
+    pub quadOperationsInAllStages: Bool32,
+}
+
 #[repr(C)]
 pub struct FormatProperties2KHR {
     pub sType: StructureType,
@@ -2584,6 +2834,405 @@ pub struct DescriptorUpdateTemplateCreateInfoKHR {
     pub set: u32,
 }
 
+#[repr(C)]
+pub struct PhysicalDeviceTimelineSemaphoreFeaturesKHR {
+    pub sType: StructureType,
+    pub pNext: *mut c_void,
+    pub timelineSemaphore: Bool32,
+}
+
+#[repr(C)]
+pub struct PhysicalDeviceTimelineSemaphorePropertiesKHR {
+    pub sType: StructureType,
+    pub pNext: *mut c_void,
+    pub maxTimelineSemaphoreValueDifference: u64,
+}
+
+#[repr(C)]
+pub struct SemaphoreTypeCreateInfoKHR {
+    pub sType: StructureType,
+    pub pNext: *const c_void,
+    pub semaphoreType: SemaphoreTypeKHR,
+    pub initialValue: u64,
+}
+
+#[repr(C)]
+pub struct SemaphoreWaitInfoKHR {
+    pub sType: StructureType,
+    pub pNext: *const c_void,
+    pub flags: SemaphoreWaitFlagsKHR,
+    pub semaphoreCount: u32,
+    pub pSemaphores: *const Semaphore,
+    pub pValues: *const u64,
+}
+
+#[repr(C)]
+pub struct SemaphoreSignalInfoKHR {
+    pub sType: StructureType,
+    pub pNext: *const c_void,
+    pub semaphore: Semaphore,
+    pub value: u64,
+}
+
+#[repr(C)]
+pub struct PhysicalDeviceSynchronization2FeaturesKHR {
+    pub sType: StructureType,
+    pub pNext: *mut c_void,
+    pub synchronization2: Bool32,
+}
+
+#[repr(C)]
+pub struct SemaphoreSubmitInfoKHR {
+    pub sType: StructureType,
+    pub pNext: *const c_void,
+    pub semaphore: Semaphore,
+    pub value: u64,
+    pub stageMask: PipelineStageFlags2KHR,
+    pub deviceIndex: u32,
+}
+
+#[repr(C)]
+pub struct CommandBufferSubmitInfoKHR {
+    pub sType: StructureType,
+    pub pNext: *const c_void,
+    pub commandBuffer: CommandBuffer,
+    pub deviceMask: u32,
+}
+
+#[repr(C)]
+pub struct SubmitInfo2KHR {
+    pub sType: StructureType,
+    pub pNext: *const c_void,
+    pub flags: SubmitFlagsKHR,
+    pub waitSemaphoreInfoCount: u32,
+    pub pWaitSemaphoreInfos: *const SemaphoreSubmitInfoKHR,
+    pub commandBufferInfoCount: u32,
+    pub pCommandBufferInfos: *const CommandBufferSubmitInfoKHR,
+    pub signalSemaphoreInfoCount: u32,
+    pub pSignalSemaphoreInfos: *const SemaphoreSubmitInfoKHR,
+}
+
+#[repr(C)]
+pub struct PhysicalDeviceSubgroupSizeControlFeaturesEXT {
+    pub sType: StructureType,
+    pub pNext: *mut c_void,
+    pub subgroupSizeControl: Bool32,
+    pub computeFullSubgroups: Bool32,
+}
+
+#[repr(C)]
+pub struct PhysicalDeviceSubgroupSizeControlPropertiesEXT {
+    pub sType: StructureType,
+    pub pNext: *mut c_void,
+    pub minSubgroupSize: u32,
+    pub maxSubgroupSize: u32,
+    pub maxComputeWorkgroupSubgroups: u32,
+    pub requiredSubgroupSizeStages: ShaderStageFlags,
+}
+
+#[repr(C)]
+pub struct PipelineShaderStageRequiredSubgroupSizeCreateInfoEXT {
+    pub sType: StructureType,
+    pub pNext: *mut c_void,
+    pub requiredSubgroupSize: u32,
+}
+
+#[repr(C)]
+pub struct PhysicalDeviceDepthClipEnableFeaturesEXT {
+    pub sType: StructureType,
+    pub pNext: *mut c_void,
+    pub depthClipEnable: Bool32,
+}
+
+#[repr(C)]
+pub struct QueueFamilyVideoPropertiesKHR {
+    pub sType: StructureType,
+    pub pNext: *mut c_void,
+    pub videoCodecOperations: VideoCodecOperationFlagsKHR,
+}
+
+#[repr(C)]
+pub struct PhysicalDeviceCooperativeMatrixFeaturesKHR {
+    pub sType: StructureType,
+    pub pNext: *mut c_void,
+    pub cooperativeMatrix: Bool32,
+    pub cooperativeMatrixRobustBufferAccess: Bool32,
+}
+
+#[repr(C)]
+pub struct PhysicalDeviceCooperativeMatrixPropertiesKHR {
+    pub sType: StructureType,
+    pub pNext: *mut c_void,
+    pub cooperativeMatrixSupportedStages: ShaderStageFlags,
+}
+
+#[repr(C)]
+pub struct CooperativeMatrixPropertiesKHR {
+    pub sType: StructureType,
+    pub pNext: *mut c_void,
+    pub MSize: u32,
+    pub NSize: u32,
+    pub KSize: u32,
+    pub AType: ComponentTypeKHR,
+    pub BType: ComponentTypeKHR,
+    pub CType: ComponentTypeKHR,
+    pub ResultType: ComponentTypeKHR,
+    pub saturatingAccumulation: Bool32,
+    pub scope: ScopeKHR,
+}
+
+#[repr(C)]
+pub struct PipelineRasterizationDepthClipStateCreateInfoEXT {
+    pub sType: StructureType,
+    pub pNext: *const c_void,
+    pub flags: Flags,
+    pub depthClipEnable: Bool32,
+}
+
+#[repr(C)]
+pub struct PhysicalDeviceExtendedDynamicStateFeaturesEXT {
+    pub sType: StructureType,
+    pub pNext: *mut c_void,
+    pub extendedDynamicState: Bool32,
+}
+
+#[repr(C)]
+pub struct PhysicalDeviceSwapchainMaintenance1FeaturesEXT {
+    pub sType: StructureType,
+    pub pNext: *mut c_void,
+    pub swapchainMaintenance1: Bool32,
+}
+
+#[repr(C)]
+pub struct SwapchainPresentFenceInfoEXT {
+    pub sType: StructureType,
+    pub pNext: *const c_void,
+    pub swapchainCount: u32,
+    pub pFences: *const Fence,
+}
+
+#[repr(C)]
+pub struct PhysicalDeviceDynamicRenderingFeaturesKHR {
+    pub sType: StructureType,
+    pub pNext: *mut c_void,
+    pub dynamicRendering: Bool32,
+}
+
+#[repr(C)]
+pub struct PhysicalDeviceMaintenance5FeaturesKHR {
+    pub sType: StructureType,
+    pub pNext: *mut c_void,
+    pub maintenance5: Bool32,
+}
+
+#[repr(C)]
+pub struct RenderingAttachmentInfoKHR {
+    pub sType: StructureType,
+    pub pNext: *const c_void,
+    pub imageView: ImageView,
+    pub imageLayout: ImageLayout,
+    pub resolveMode: ResolveModeFlagBitsKHR,
+    pub resolveImageView: ImageView,
+    pub resolveImageLayout: ImageLayout,
+    pub loadOp: AttachmentLoadOp,
+    pub storeOp: AttachmentStoreOp,
+    pub clearValue: ClearValue,
+}
+
+#[repr(C)]
+pub struct RenderingInfoKHR {
+    pub sType: StructureType,
+    pub pNext: *const c_void,
+    pub flags: RenderingFlagsKHR,
+    pub renderArea: Rect2D,
+    pub layerCount: u32,
+    pub viewMask: u32,
+    pub colorAttachmentCount: u32,
+    pub pColorAttachments: *const RenderingAttachmentInfoKHR,
+    pub pDepthAttachment: *const RenderingAttachmentInfoKHR,
+    pub pStencilAttachment: *const RenderingAttachmentInfoKHR,
+}
+
+#[repr(C)]
+pub struct PhysicalDeviceShaderObjectFeaturesEXT {
+    pub sType: StructureType,
+    pub pNext: *mut c_void,
+    pub shaderObject: Bool32,
+}
+
+#[repr(C)]
+pub struct ShaderCreateInfoEXT {
+    pub sType: StructureType,
+    pub pNext: *const c_void,
+    pub flags: ShaderCreateFlagsEXT,
+    pub stage: ShaderStageFlagBits,
+    pub nextStage: ShaderStageFlags,
+    pub codeType: ShaderCodeTypeEXT,
+    pub codeSize: usize,
+    pub pCode: *const c_void,
+    pub pName: *const c_char,
+    pub setLayoutCount: u32,
+    pub pSetLayouts: *const DescriptorSetLayout,
+    pub pushConstantRangeCount: u32,
+    pub pPushConstantRanges: *const PushConstantRange,
+    pub pSpecializationInfo: *const SpecializationInfo,
+}
+
+#[repr(C)]
+pub struct PhysicalDevicePrivateDataFeaturesEXT {
+    pub sType: StructureType,
+    pub pNext: *mut c_void,
+    pub privateData: Bool32,
+}
+
+#[repr(C)]
+pub struct PrivateDataSlotCreateInfoEXT {
+    pub sType: StructureType,
+    pub pNext: *const c_void,
+    pub flags: PrivateDataSlotCreateFlagsEXT,
+}
+
+#[repr(C)]
+pub struct PhysicalDeviceImageViewMinLodFeaturesEXT {
+    pub sType: StructureType,
+    pub pNext: *mut c_void,
+    pub minLod: Bool32,
+}
+
+#[repr(C)]
+pub struct ImageViewMinLodCreateInfoEXT {
+    pub sType: StructureType,
+    pub pNext: *const c_void,
+    pub minLod: f32,
+}
+
+#[repr(C)]
+pub struct VertexInputBindingDescription2EXT {
+    pub sType: StructureType,
+    pub pNext: *mut c_void,
+    pub binding: u32,
+    pub stride: u32,
+    pub inputRate: VertexInputRate,
+    pub divisor: u32,
+}
+
+#[repr(C)]
+pub struct VertexInputAttributeDescription2EXT {
+    pub sType: StructureType,
+    pub pNext: *mut c_void,
+    pub location: u32,
+    pub binding: u32,
+    pub format: Format,
+    pub offset: u32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct ColorBlendEquationEXT {
+    pub srcColorBlendFactor: BlendFactor,
+    pub dstColorBlendFactor: BlendFactor,
+    pub colorBlendOp: BlendOp,
+    pub srcAlphaBlendFactor: BlendFactor,
+    pub dstAlphaBlendFactor: BlendFactor,
+    pub alphaBlendOp: BlendOp,
+}
+
+#[repr(C)]
+pub struct MemoryRequirements2KHR {
+    pub sType: StructureType,
+    pub pNext: *mut c_void,
+    pub memoryRequirements: MemoryRequirements,
+}
+
+#[repr(C)]
+pub struct PhysicalDeviceDeviceGeneratedCommandsFeaturesNV {
+    pub sType: StructureType,
+    pub pNext: *mut c_void,
+    pub deviceGeneratedCommands: Bool32,
+}
+
+#[repr(C)]
+pub struct PhysicalDeviceDeviceGeneratedCommandsPropertiesNV {
+    pub sType: StructureType,
+    pub pNext: *mut c_void,
+    pub maxGraphicsShaderGroupCount: u32,
+    pub maxIndirectSequenceCount: u32,
+    pub maxIndirectCommandsTokenCount: u32,
+    pub maxIndirectCommandsStreamCount: u32,
+    pub maxIndirectCommandsTokenOffset: u32,
+    pub maxIndirectCommandsStreamStride: u32,
+    pub minSequencesCountBufferOffsetAlignment: u32,
+    pub minSequencesIndexBufferOffsetAlignment: u32,
+    pub minIndirectCommandsBufferOffsetAlignment: u32,
+}
+
+#[repr(C)]
+pub struct IndirectCommandsLayoutTokenNV {
+    pub sType: StructureType,
+    pub pNext: *const c_void,
+    pub tokenType: IndirectCommandsTokenTypeNV,
+    pub stream: u32,
+    pub offset: u32,
+    pub vertexBindingUnit: u32,
+    pub vertexDynamicStride: Bool32,
+    pub pushconstantPipelineLayout: PipelineLayout,
+    pub pushconstantShaderStageFlags: ShaderStageFlags,
+    pub pushconstantOffset: u32,
+    pub pushconstantSize: u32,
+    pub indirectStateFlags: IndirectStateFlagsNV,
+    pub indexTypeCount: u32,
+    pub pIndexTypes: *const IndexType,
+    pub pIndexTypeValues: *const u32,
+}
+
+#[repr(C)]
+pub struct IndirectCommandsLayoutCreateInfoNV {
+    pub sType: StructureType,
+    pub pNext: *const c_void,
+    pub flags: IndirectCommandsLayoutUsageFlagsNV,
+    pub pipelineBindPoint: PipelineBindPoint,
+    pub tokenCount: u32,
+    pub pTokens: *const IndirectCommandsLayoutTokenNV,
+    pub streamCount: u32,
+    pub pStreamStrides: *const u32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct IndirectCommandsStreamNV {
+    pub buffer: Buffer,
+    pub offset: DeviceSize,
+}
+
+#[repr(C)]
+pub struct GeneratedCommandsInfoNV {
+    pub sType: StructureType,
+    pub pNext: *const c_void,
+    pub pipelineBindPoint: PipelineBindPoint,
+    pub pipeline: Pipeline,
+    pub indirectCommandsLayout: IndirectCommandsLayoutNV,
+    pub streamCount: u32,
+    pub pStreams: *const IndirectCommandsStreamNV,
+    pub sequencesCount: u32,
+    pub preprocessBuffer: Buffer,
+    pub preprocessOffset: DeviceSize,
+    pub preprocessSize: DeviceSize,
+    pub sequencesCountBuffer: Buffer,
+    pub sequencesCountOffset: DeviceSize,
+    pub sequencesIndexBuffer: Buffer,
+    pub sequencesIndexOffset: DeviceSize,
+}
+
+#[repr(C)]
+pub struct GeneratedCommandsMemoryRequirementsInfoNV {
+    pub sType: StructureType,
+    pub pNext: *const c_void,
+    pub pipelineBindPoint: PipelineBindPoint,
+    pub pipeline: Pipeline,
+    pub indirectCommandsLayout: IndirectCommandsLayoutNV,
+    pub maxSequencesCount: u32,
+}
+
 
 macro_rules! ptrs {
     ($struct_name:ident, { $($name:ident => ($($param_n:ident: $param_ty:ty),*) -> $ret:ty,)+ }) => (
@@ -2675,6 +3324,7 @@ ptrs!(InstancePointers, {
     CreateDisplayPlaneSurfaceKHR => (instance: Instance, pCreateInfo: *const DisplaySurfaceCreateInfoKHR, pAllocator: *const AllocationCallbacks, pSurface: *mut SurfaceKHR) -> Result,
     GetPhysicalDeviceSurfaceSupportKHR => (physicalDevice: PhysicalDevice, queueFamilyIndex: u32, surface: SurfaceKHR, pSupported: *mut Bool32) -> Result,
     GetPhysicalDeviceSurfaceCapabilitiesKHR => (physicalDevice: PhysicalDevice, surface: SurfaceKHR, pSurfaceCapabilities: *mut SurfaceCapabilitiesKHR) -> Result,
+    GetPhysicalDeviceSurfaceCapabilities2KHR => (physicalDevice: PhysicalDevice, pSurfaceInfo: *const PhysicalDeviceSurfaceInfo2KHR, pSurfaceCapabilities: *mut SurfaceCapabilities2KHR) -> Result,
     GetPhysicalDeviceSurfaceFormatsKHR => (physicalDevice: PhysicalDevice, surface: SurfaceKHR, pSurfaceFormatCount: *mut u32, pSurfaceFormats: *mut SurfaceFormatKHR) -> Result,
     GetPhysicalDeviceSurfacePresentModesKHR => (physicalDevice: PhysicalDevice, surface: SurfaceKHR, pPresentModeCount: *mut u32, pPresentModes: *mut PresentModeKHR) -> Result,
     CreateDebugReportCallbackEXT => (instance: Instance, pCreateInfo: *const DebugReportCallbackCreateInfoEXT, pAllocator: *const AllocationCallbacks, pCallback: *mut DebugReportCallbackEXT) -> Result,
@@ -2696,6 +3346,7 @@ ptrs!(InstancePointers, {
     GetPhysicalDeviceQueueFamilyProperties2KHR => (physicalDevice: PhysicalDevice, pQueueFamilyPropertiesCount: *mut u32, pQueueFamilyProperties: *mut QueueFamilyProperties2KHR) -> (),
     GetPhysicalDeviceMemoryProperties2KHR => (physicalDevice: PhysicalDevice, pMemoryProperties: *mut PhysicalDeviceMemoryProperties2KHR) -> (),
     GetPhysicalDeviceSparseImageFormatProperties2KHR => (physicalDevice: PhysicalDevice, pFormatInfo: *const PhysicalDeviceSparseImageFormatInfo2KHR, pPropertyCount: *mut u32, pProperties: *mut SparseImageFormatProperties2KHR) -> (),
+    GetPhysicalDeviceCooperativeMatrixPropertiesKHR => (physicalDevice: PhysicalDevice, pPropertyCount: *mut u32, pProperties: *mut CooperativeMatrixPropertiesKHR) -> Result,
 });
 
 ptrs!(DevicePointers, {
@@ -2831,4 +3482,51 @@ ptrs!(DevicePointers, {
     DestroyDescriptorUpdateTemplateKHR => (device: Device, descriptorUpdateTemplate: DescriptorUpdateTemplateKHR, pAllocator: *const AllocationCallbacks) -> (),
     UpdateDescriptorSetWithTemplateKHR => (device: Device, descriptorSet: DescriptorSet, descriptorUpdateTemplate: DescriptorUpdateTemplateKHR, pData: *const c_void) -> (),
     CmdPushDescriptorSetWithTemplateKHR => (commandBuffer: CommandBuffer, descriptorUpdateTemplate: DescriptorUpdateTemplateKHR, layout: PipelineLayout, set: u32, pData: *const c_void) -> (),
+    GetSemaphoreCounterValueKHR => (device: Device, semaphore: Semaphore, pValue: *mut u64) -> Result,
+    WaitSemaphoresKHR => (device: Device, pWaitInfo: *const SemaphoreWaitInfoKHR, timeout: u64) -> Result,
+    SignalSemaphoreKHR => (device: Device, pSignalInfo: *const SemaphoreSignalInfoKHR) -> Result,
+    QueueSubmit2KHR => (queue: Queue, submitCount: u32, pSubmits: *const SubmitInfo2KHR, fence: Fence) -> Result,
+    GetSwapchainStatusKHR => (device: Device, swapchain: SwapchainKHR) -> Result,
+    CreatePrivateDataSlotEXT => (device: Device, pCreateInfo: *const PrivateDataSlotCreateInfoEXT, pAllocator: *const AllocationCallbacks, pPrivateDataSlot: *mut PrivateDataSlotEXT) -> Result,
+    DestroyPrivateDataSlotEXT => (device: Device, privateDataSlot: PrivateDataSlotEXT, pAllocator: *const AllocationCallbacks) -> (),
+    SetPrivateDataEXT => (device: Device, objectType: ObjectType, objectHandle: u64, privateDataSlot: PrivateDataSlotEXT, data: u64) -> Result,
+    GetPrivateDataEXT => (device: Device, objectType: ObjectType, objectHandle: u64, privateDataSlot: PrivateDataSlotEXT, pData: *mut u64) -> (),
+    CreateShadersEXT => (device: Device, createInfoCount: u32, pCreateInfos: *const ShaderCreateInfoEXT, pAllocator: *const AllocationCallbacks, pShaders: *mut ShaderEXT) -> Result,
+    DestroyShaderEXT => (device: Device, shader: ShaderEXT, pAllocator: *const AllocationCallbacks) -> (),
+    CmdBindShadersEXT => (commandBuffer: CommandBuffer, stageCount: u32, pStages: *const ShaderStageFlagBits, pShaders: *const ShaderEXT) -> (),
+    CmdSetViewportWithCountEXT => (commandBuffer: CommandBuffer, viewportCount: u32, pViewports: *const Viewport) -> (),
+    CmdSetScissorWithCountEXT => (commandBuffer: CommandBuffer, scissorCount: u32, pScissors: *const Rect2D) -> (),
+    CmdSetRasterizerDiscardEnableEXT => (commandBuffer: CommandBuffer, rasterizerDiscardEnable: Bool32) -> (),
+    CmdSetCullModeEXT => (commandBuffer: CommandBuffer, cullMode: CullModeFlags) -> (),
+    CmdSetFrontFaceEXT => (commandBuffer: CommandBuffer, frontFace: FrontFace) -> (),
+    CmdSetDepthClampEnableEXT => (commandBuffer: CommandBuffer, depthClampEnable: Bool32) -> (),
+    CmdSetPolygonModeEXT => (commandBuffer: CommandBuffer, polygonMode: PolygonMode) -> (),
+    CmdSetDepthBiasEnableEXT => (commandBuffer: CommandBuffer, depthBiasEnable: Bool32) -> (),
+    CmdSetDepthTestEnableEXT => (commandBuffer: CommandBuffer, depthTestEnable: Bool32) -> (),
+    CmdSetDepthWriteEnableEXT => (commandBuffer: CommandBuffer, depthWriteEnable: Bool32) -> (),
+    CmdSetDepthCompareOpEXT => (commandBuffer: CommandBuffer, depthCompareOp: CompareOp) -> (),
+    CmdSetDepthBoundsTestEnableEXT => (commandBuffer: CommandBuffer, depthBoundsTestEnable: Bool32) -> (),
+    CmdSetStencilTestEnableEXT => (commandBuffer: CommandBuffer, stencilTestEnable: Bool32) -> (),
+    CmdSetPrimitiveTopologyEXT => (commandBuffer: CommandBuffer, primitiveTopology: PrimitiveTopology) -> (),
+    CmdSetPrimitiveRestartEnableEXT => (commandBuffer: CommandBuffer, primitiveRestartEnable: Bool32) -> (),
+    CmdSetVertexInputEXT => (commandBuffer: CommandBuffer, vertexBindingDescriptionCount: u32, pVertexBindingDescriptions: *const VertexInputBindingDescription2EXT, vertexAttributeDescriptionCount: u32, pVertexAttributeDescriptions: *const VertexInputAttributeDescription2EXT) -> (),
+    CmdSetRasterizationSamplesEXT => (commandBuffer: CommandBuffer, rasterizationSamples: SampleCountFlagBits) -> (),
+    CmdSetSampleMaskEXT => (commandBuffer: CommandBuffer, samples: SampleCountFlagBits, pSampleMask: *const SampleMask) -> (),
+    CmdSetAlphaToCoverageEnableEXT => (commandBuffer: CommandBuffer, alphaToCoverageEnable: Bool32) -> (),
+    CmdSetAlphaToOneEnableEXT => (commandBuffer: CommandBuffer, alphaToOneEnable: Bool32) -> (),
+    CmdSetLogicOpEnableEXT => (commandBuffer: CommandBuffer, logicOpEnable: Bool32) -> (),
+    CmdBeginRenderingKHR => (commandBuffer: CommandBuffer, pRenderingInfo: *const RenderingInfoKHR) -> (),
+    CmdEndRenderingKHR => (commandBuffer: CommandBuffer) -> (),
+    CmdSetColorBlendEnableEXT => (commandBuffer: CommandBuffer, firstAttachment: u32, attachmentCount: u32, pColorBlendEnables: *const Bool32) -> (),
+    CmdSetColorBlendEquationEXT => (commandBuffer: CommandBuffer, firstAttachment: u32, attachmentCount: u32, pColorBlendEquations: *const ColorBlendEquationEXT) -> (),
+    CmdSetColorWriteMaskEXT => (commandBuffer: CommandBuffer, firstAttachment: u32, attachmentCount: u32, pColorWriteMasks: *const ColorComponentFlags) -> (),
+    CmdBeginDebugUtilsLabelEXT => (commandBuffer: CommandBuffer, pLabelInfo: *const DebugUtilsLabelEXT) -> (),
+    CmdEndDebugUtilsLabelEXT => (commandBuffer: CommandBuffer) -> (),
+    CmdInsertDebugUtilsLabelEXT => (commandBuffer: CommandBuffer, pLabelInfo: *const DebugUtilsLabelEXT) -> (),
+    SetDebugUtilsObjectNameEXT => (device: Device, pNameInfo: *const DebugUtilsObjectNameInfoEXT) -> Result,
+    CreateIndirectCommandsLayoutNV => (device: Device, pCreateInfo: *const IndirectCommandsLayoutCreateInfoNV, pAllocator: *const AllocationCallbacks, pIndirectCommandsLayout: *mut IndirectCommandsLayoutNV) -> Result,
+    DestroyIndirectCommandsLayoutNV => (device: Device, indirectCommandsLayout: IndirectCommandsLayoutNV, pAllocator: *const AllocationCallbacks) -> (),
+    GetGeneratedCommandsMemoryRequirementsNV => (device: Device, pInfo: *const GeneratedCommandsMemoryRequirementsInfoNV, pMemoryRequirements: *mut MemoryRequirements2KHR) -> (),
+    CmdPreprocessGeneratedCommandsNV => (commandBuffer: CommandBuffer, pGeneratedCommandsInfo: *const GeneratedCommandsInfoNV) -> (),
+    CmdExecuteGeneratedCommandsNV => (commandBuffer: CommandBuffer, isPreprocessed: Bool32, pGeneratedCommandsInfo: *const GeneratedCommandsInfoNV) -> (),
 });
@@ -21,7 +21,7 @@ pub fn write_descriptor_sets(doc: &parse::Spirv) -> String {
         name: String,
         set: u32,
         binding: u32,
-        desc_ty: String,
+        desc: String,
         array_count: u64,
         readonly: bool,
     }
@@ -63,7 +63,7 @@ pub fn write_descriptor_sets(doc: &parse::Spirv) -> String {
             .expect(&format!("Uniform `{}` is missing a binding", name));
 
         // Find informations about the kind of binding for this descriptor.
-        let (desc_ty, readonly, array_count) = descriptor_infos(doc, pointed_ty, false)
+        let (desc, readonly, array_count) = descriptor_infos(doc, pointed_ty, false)
             .expect(&format!("Couldn't find relevant type for uniform `{}` (type {}, maybe \
                               unimplemented)",
                              name,
@@ -71,7 +71,7 @@ pub fn write_descriptor_sets(doc: &parse::Spirv) -> String {
 
         descriptors.push(Descriptor {
                              name: name,
-                             desc_ty: desc_ty,
+                             desc: desc,
                              set: descriptor_set,
                              binding: binding,
                              array_count: array_count,
@@ -103,15 +103,10 @@ pub fn write_descriptor_sets(doc: &parse::Spirv) -> String {
         .iter()
         .map(|d| {
             format!(
-                "({set}, {binding}) => Some(DescriptorDesc {{
-            ty: {desc_ty},
-            array_count: {array_count},
-            stages: self.0.clone(),
-            readonly: {readonly},
-        }}),",
+                "({set}, {binding}) => Some({desc}.array({array_count}).readonly({readonly})),",
                 set = d.set,
                 binding = d.binding,
-                desc_ty = d.desc_ty,
+                desc = d.desc,
                 array_count = d.array_count,
                 readonly = if d.readonly { "true" } else { "false" }
             )
@@ -120,17 +115,20 @@ pub fn write_descriptor_sets(doc: &parse::Spirv) -> String {
         .collect::<Vec<_>>()
         .concat();
 
-    let num_sets = 1 + descriptors.iter().fold(0, |s, d| cmp::max(s, d.set));
+    // A shader without any descriptor, for example one that only uses push constants, doesn't
+    // have any descriptor set.
+    let num_sets = descriptors.iter().map(|d| d.set + 1).max().unwrap_or(0);
 
     // Writing the body of the `num_bindings_in_set` method.
     let num_bindings_in_set_body = {
         (0 .. num_sets)
             .map(|set| {
-                     let num = 1 +
-                         descriptors
-                             .iter()
-                             .filter(|d| d.set == set)
-                             .fold(0, |s, d| cmp::max(s, d.binding));
+                     let num = descriptors
+                         .iter()
+                         .filter(|d| d.set == set)
+                         .map(|d| d.binding + 1)
+                         .max()
+                         .unwrap_or(0);
                      format!("{set} => Some({num}),", set = set, num = num)
                  })
             .collect::<Vec<_>>()
@@ -251,8 +249,10 @@ fn pointer_variable_ty(doc: &parse::Spirv, variable: u32) -> u32 {
         .unwrap()
 }
 
-/// Returns a `DescriptorDescTy` constructor, a bool indicating whether the descriptor is
-/// read-only, and the number of array elements.
+/// Returns an expression that builds the `DescriptorDesc` with the `DescriptorDesc` helpers, a
+/// bool indicating whether the descriptor is read-only, and the number of array elements.
+///
+/// The expression uses `self.0` as the shader stages.
 ///
 /// See also section 14.5.2 of the Vulkan specs: Descriptor Set Interface
 fn descriptor_infos(doc: &parse::Spirv, pointed_ty: u32, force_combined_image_sampled: bool)
@@ -283,11 +283,9 @@ fn descriptor_infos(doc: &parse::Spirv, pointed_ty: u32, force_combined_image_sa
                 // Determine whether there's a NonWritable decoration.
                 //let non_writable = false;       // TODO: tricky because the decoration is on struct members
 
-                let desc = format!("DescriptorDescTy::Buffer(DescriptorBufferDesc {{
-                    dynamic: Some(false),
-                    storage: {},
-                    content: DescriptorBufferContentDesc::F32,      // FIXME: wrong
-                }})", if is_ssbo { "true" } else { "false "});
+                // FIXME: the content of the buffer is always F32, which is wrong
+                let desc = format!("DescriptorDesc::{}(self.0.clone())",
+                                   if is_ssbo { "storage_buffer" } else { "uniform_buffer" });
 
                 Some((desc, true, 1))
             },
@@ -314,27 +312,29 @@ fn descriptor_infos(doc: &parse::Spirv, pointed_ty: u32, force_combined_image_sa
                             else { false }, "If Dim is SubpassData, Image Format must be Unknown");
                     assert!(!sampled, "If Dim is SubpassData, Sampled must be 2");
 
-                    let desc = format!("DescriptorDescTy::InputAttachment {{
-                                            multisampled: {},
-                                            array_layers: {}
-                                        }}", ms, arrayed);
+                    let desc = format!("DescriptorDesc::input_attachment(self.0.clone(), {})
+                                            .array_layers({})", ms, arrayed);
 
                     Some((desc, true, 1))
 
                 } else if let &enums::Dim::DimBuffer = dim {
                     // We are a texel buffer.
-                    let desc = format!("DescriptorDescTy::TexelBuffer {{
-                        storage: {},
-                        format: None,       // TODO: specify format if known
-                    }}", !sampled);
+                    // TODO: specify format if known
+                    let desc = format!("DescriptorDesc::texel_buffer(self.0.clone(), {}, None)",
+                                       !sampled);
 
                     Some((desc, true, 1))
 
                 } else {
                     // We are a sampled or storage image.
-                    let sampled = if sampled { "true" } else { "false" };
-                    let ty = if force_combined_image_sampled { "CombinedImageSampler" }
-                             else { "Image" };
+                    // TODO: specify format if known
+                    let (ctor, format) = if force_combined_image_sampled {
+                        ("combined_image_sampler", "")
+                    } else if sampled {
+                        ("sampled_image", "")
+                    } else {
+                        ("storage_image", ", None")
+                    };
                     let dim = match *dim {
                         enums::Dim::Dim1D => "DescriptorImageDescDimensions::OneDimensional",
                         enums::Dim::Dim2D => "DescriptorImageDescDimensions::TwoDimensional",
@@ -344,13 +344,9 @@ fn descriptor_infos(doc: &parse::Spirv, pointed_ty: u32, force_combined_image_sa
                         _ => unreachable!()
                     };
 
-                    let desc = format!("DescriptorDescTy::{}(DescriptorImageDesc {{
-                        sampled: {},
-                        dimensions: {},
-                        format: None,       // TODO: specify format if known
-                        multisampled: {},
-                        array_layers: {},
-                    }})", ty, sampled, dim, ms, arrayed);
+                    let desc = format!("DescriptorDesc::{}(self.0.clone(), {}{})
+                                            .multisampled({})
+                                            .array_layers({})", ctor, dim, format, ms, arrayed);
 
                     Some((desc, true, 1))
                 }
@@ -363,7 +359,7 @@ fn descriptor_infos(doc: &parse::Spirv, pointed_ty: u32, force_combined_image_sa
             },
 
             &parse::Instruction::TypeSampler { result_id } if result_id == pointed_ty => {
-                let desc = format!("DescriptorDescTy::Sampler");
+                let desc = format!("DescriptorDesc::sampler(self.0.clone())");
                 Some((desc, true, 1))
             },
 
@@ -16,14 +16,14 @@ use location_decoration;
 use name_from_id;
 
 pub fn write_entry_point(doc: &parse::Spirv, instruction: &parse::Instruction) -> (String, String) {
-    let (execution, ep_name, interface) = match instruction {
+    let (execution, ep_id, ep_name, interface) = match instruction {
         &parse::Instruction::EntryPoint {
             ref execution,
+            id,
             ref name,
             ref interface,
-            ..
         } => {
-            (execution, name, interface)
+            (execution, id, name, interface)
         },
         _ => unreachable!(),
     };
@@ -110,15 +110,43 @@ pub fn write_entry_point(doc: &parse::Spirv, instruction: &parse::Instruction) -
         },
 
         enums::ExecutionModel::ExecutionModelGLCompute => {
+            let (size, specialization_ids) = compute_local_size(doc, ep_id);
             (format!("::vulkano::pipeline::shader::ComputeShaderEntryPoint<(), Layout>"),
              format!("compute_shader_entry_point(::std::ffi::CStr::from_ptr(NAME.as_ptr() as \
                       *const _), Layout(ShaderStages {{ compute: true, .. ShaderStages::none() \
-                      }}))"))
+                      }})).with_local_size(::vulkano::pipeline::shader::ComputeShaderLocalSize \
+                      {{ size: {:?}, specialization_ids: {:?} }})",
+                     size,
+                     specialization_ids))
         },
 
         enums::ExecutionModel::ExecutionModelKernel => panic!("Kernels are not supported"),
     };
 
+    // The stages that can be the last one before rasterization report whether they output points
+    // and write their size, so that pipelines drawing points can be checked.
+    let outputs_points = match *execution {
+        enums::ExecutionModel::ExecutionModelVertex => Some(false),
+        enums::ExecutionModel::ExecutionModelTessellationEvaluation => {
+            Some(has_execution_mode(doc, ep_id, enums::ExecutionMode::ExecutionModePointMode))
+        },
+        enums::ExecutionModel::ExecutionModelGeometry => {
+            Some(has_execution_mode(doc, ep_id, enums::ExecutionMode::ExecutionModeOutputPoints))
+        },
+        _ => None,
+    };
+
+    let f_call = match outputs_points {
+        Some(outputs_points) => {
+            format!("{}.with_point_size_output(::vulkano::pipeline::shader::PointSizeOutput {{ \
+                     outputs_points: {}, writes_point_size: {} }})",
+                    f_call,
+                    outputs_points,
+                    writes_point_size(doc, interface))
+        },
+        None => f_call,
+    };
+
     let entry_point = format!(
         r#"
     /// Returns a logical struct describing the entry point named `{ep_name}`.
@@ -146,6 +174,235 @@ pub fn write_entry_point(doc: &parse::Spirv, instruction: &parse::Instruction) -
     (interface_structs, entry_point)
 }
 
+/// Returns the workgroup size of a compute entry point, and for each dimension the id of the
+/// specialization constant that overrides it, if any.
+fn compute_local_size(doc: &parse::Spirv, ep_id: u32) -> ([u32; 3], [Option<u32>; 3]) {
+    let mut size = [1, 1, 1];
+    let mut specialization_ids = [None, None, None];
+
+    for instruction in doc.instructions.iter() {
+        match instruction {
+            &parse::Instruction::ExecutionMode {
+                target_id,
+                mode: enums::ExecutionMode::ExecutionModeLocalSize,
+                ref optional_literals,
+            } if target_id == ep_id => {
+                for (dim, &value) in optional_literals.iter().take(3).enumerate() {
+                    size[dim] = value;
+                }
+            },
+            _ => (),
+        }
+    }
+
+    // A constant decorated with the `WorkgroupSize` built-in takes precedence over the
+    // `LocalSize` execution mode. This is how the size is declared when it depends on
+    // specialization constants.
+    let mut workgroup_size_id = None;
+    for instruction in doc.instructions.iter() {
+        match instruction {
+            &parse::Instruction::Decorate {
+                target_id,
+                decoration: enums::Decoration::DecorationBuiltIn,
+                ref params,
+            } => {
+                match enums::BuiltIn::from_num(params[0]) {
+                    Ok(enums::BuiltIn::BuiltInWorkgroupSize) => {
+                        workgroup_size_id = Some(target_id);
+                    },
+                    _ => (),
+                }
+            },
+            _ => (),
+        }
+    }
+
+    let workgroup_size_id = match workgroup_size_id {
+        Some(id) => id,
+        None => return (size, specialization_ids),
+    };
+
+    let constituents = doc.instructions
+        .iter()
+        .filter_map(|i| match i {
+                        &parse::Instruction::ConstantComposite {
+                            result_id,
+                            ref constituents,
+                            ..
+                        } if result_id == workgroup_size_id => Some(constituents),
+                        &parse::Instruction::SpecConstantComposite {
+                            result_id,
+                            ref constituents,
+                            ..
+                        } if result_id == workgroup_size_id => Some(constituents),
+                        _ => None,
+                    })
+        .next();
+
+    let constituents = match constituents {
+        Some(c) => c,
+        None => return (size, specialization_ids),
+    };
+
+    for (dim, &constituent) in constituents.iter().take(3).enumerate() {
+        for instruction in doc.instructions.iter() {
+            match instruction {
+                &parse::Instruction::Constant { result_id, ref data, .. }
+                    if result_id == constituent => {
+                    size[dim] = data[0];
+                },
+                &parse::Instruction::SpecConstant { result_id, ref data, .. }
+                    if result_id == constituent => {
+                    size[dim] = data[0];
+                    specialization_ids[dim] = spec_id_decoration(doc, result_id);
+                },
+                _ => (),
+            }
+        }
+    }
+
+    (size, specialization_ids)
+}
+
+/// Returns true if the entry point has the given execution mode.
+fn has_execution_mode(doc: &parse::Spirv, ep_id: u32, mode: enums::ExecutionMode) -> bool {
+    doc.instructions.iter().any(|i| match i {
+        &parse::Instruction::ExecutionMode { target_id, mode: ref m, .. } => {
+            target_id == ep_id && *m == mode
+        },
+        _ => false,
+    })
+}
+
+/// Returns true if the entry point writes the `PointSize` built-in to one of its outputs.
+///
+/// The `gl_PerVertex` block always declares a `PointSize` member, so we look for an actual access
+/// to that member instead of relying on the decorations alone.
+fn writes_point_size(doc: &parse::Spirv, interface: &[u32]) -> bool {
+    for &var_id in interface.iter() {
+        let pointer_type_id = doc.instructions
+            .iter()
+            .filter_map(|i| match i {
+                            &parse::Instruction::Variable {
+                                result_type_id,
+                                result_id,
+                                storage_class: enums::StorageClass::StorageClassOutput,
+                                ..
+                            } if result_id == var_id => Some(result_type_id),
+                            _ => None,
+                        })
+            .next();
+
+        let pointer_type_id = match pointer_type_id {
+            Some(id) => id,
+            None => continue,
+        };
+
+        // The variable itself is the built-in.
+        if is_point_size_decorated(doc, var_id, None) {
+            let written = doc.instructions.iter().any(|i| match i {
+                &parse::Instruction::Store { pointer_id, .. } => pointer_id == var_id,
+                _ => false,
+            });
+
+            if written {
+                return true;
+            }
+
+            continue;
+        }
+
+        // The variable is a block, and one of its members is the built-in.
+        let struct_id = doc.instructions
+            .iter()
+            .filter_map(|i| match i {
+                            &parse::Instruction::TypePointer { result_id, type_id, .. }
+                                if result_id == pointer_type_id => Some(type_id),
+                            _ => None,
+                        })
+            .next();
+
+        let struct_id = match struct_id {
+            Some(id) => id,
+            None => continue,
+        };
+
+        let written = doc.instructions.iter().any(|i| match i {
+            &parse::Instruction::AccessChain { base_id, ref indexes, .. }
+                if base_id == var_id && !indexes.is_empty() => {
+                match constant_value(doc, indexes[0]) {
+                    Some(member) => is_point_size_decorated(doc, struct_id, Some(member)),
+                    None => false,
+                }
+            },
+            _ => false,
+        });
+
+        if written {
+            return true;
+        }
+    }
+
+    false
+}
+
+/// Returns true if `id` (or its member `member`) is decorated with the `PointSize` built-in.
+fn is_point_size_decorated(doc: &parse::Spirv, id: u32, member: Option<u32>) -> bool {
+    doc.instructions.iter().any(|i| {
+        let params = match (i, member) {
+            (&parse::Instruction::Decorate {
+                 target_id,
+                 decoration: enums::Decoration::DecorationBuiltIn,
+                 ref params,
+             },
+             None) if target_id == id => params,
+            (&parse::Instruction::MemberDecorate {
+                 target_id,
+                 member: m,
+                 decoration: enums::Decoration::DecorationBuiltIn,
+                 ref params,
+             },
+             Some(member)) if target_id == id && m == member => params,
+            _ => return false,
+        };
+
+        match enums::BuiltIn::from_num(params[0]) {
+            Ok(enums::BuiltIn::BuiltInPointSize) => true,
+            _ => false,
+        }
+    })
+}
+
+/// Returns the value of a scalar constant.
+fn constant_value(doc: &parse::Spirv, id: u32) -> Option<u32> {
+    doc.instructions
+        .iter()
+        .filter_map(|i| match i {
+                        &parse::Instruction::Constant { result_id, ref data, .. }
+                            if result_id == id => data.get(0).cloned(),
+                        _ => None,
+                    })
+        .next()
+}
+
+/// Returns the `SpecId` decoration of a specialization constant.
+fn spec_id_decoration(doc: &parse::Spirv, id: u32) -> Option<u32> {
+    for instruction in doc.instructions.iter() {
+        match instruction {
+            &parse::Instruction::Decorate {
+                target_id,
+                decoration: enums::Decoration::DecorationSpecId,
+                ref params,
+            } if target_id == id => {
+                return Some(params[0]);
+            },
+            _ => (),
+        }
+    }
+
+    None
+}
+
 fn write_interface_structs(doc: &parse::Spirv, capitalized_ep_name: &str, interface: &[u32],
                            ignore_first_array_in: bool, ignore_first_array_out: bool)
                            -> String {
@@ -291,3 +548,73 @@ fn write_interface_struct(struct_name: &str, attributes: &[(u32, String, (String
         len = attributes.len()
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use glsl_to_spirv;
+    use parse;
+
+    use super::compute_local_size;
+
+    // Compiles a compute shader and returns the workgroup size of its `main` entry point.
+    fn local_size(src: &str) -> ([u32; 3], [Option<u32>; 3]) {
+        let mut data = Vec::new();
+        glsl_to_spirv::compile(src, glsl_to_spirv::ShaderType::Compute)
+            .unwrap()
+            .read_to_end(&mut data)
+            .unwrap();
+        let doc = parse::parse_spirv(&data).unwrap();
+
+        let ep_id = doc.instructions
+            .iter()
+            .filter_map(|i| match i {
+                            &parse::Instruction::EntryPoint { id, .. } => Some(id),
+                            _ => None,
+                        })
+            .next()
+            .unwrap();
+
+        compute_local_size(&doc, ep_id)
+    }
+
+    #[test]
+    fn literal_local_size() {
+        let size = local_size("
+            #version 450
+            layout(local_size_x = 8, local_size_y = 4) in;
+            void main() {}
+        ");
+        assert_eq!(size, ([8, 4, 1], [None, None, None]));
+    }
+
+    #[test]
+    fn default_local_size() {
+        let size = local_size("
+            #version 450
+            void main() {}
+        ");
+        assert_eq!(size, ([1, 1, 1], [None, None, None]));
+    }
+
+    #[test]
+    fn specialized_local_size() {
+        let size = local_size("
+            #version 450
+            layout(local_size_x_id = 3, local_size_z = 2) in;
+            void main() {}
+        ");
+        assert_eq!(size, ([1, 1, 2], [Some(3), None, None]));
+    }
+
+    #[test]
+    fn specialized_local_size_default() {
+        let size = local_size("
+            #version 450
+            layout(local_size_x = 16, local_size_y_id = 1) in;
+            void main() {}
+        ");
+        assert_eq!(size, ([16, 1, 1], [None, Some(1), None]));
+    }
+}
@@ -274,19 +274,22 @@ fn format_from_id(doc: &parse::Spirv, searched: u32, ignore_first_array: bool) -
             } if result_id == searched => {
                 assert!(!ignore_first_array);
                 let (format, sz) = format_from_id(doc, component_id, false);
-                assert!(format.starts_with("R32"));
+                assert!(format.starts_with("R32") || format.starts_with("R64"));
                 assert_eq!(sz, 1);
+                let bits = format[1 .. 3].to_owned();
                 let format = if count == 1 {
                     format
                 } else if count == 2 {
-                    format!("R32G32{}", &format[3 ..])
+                    format!("R{0}G{0}{1}", bits, &format[3 ..])
                 } else if count == 3 {
-                    format!("R32G32B32{}", &format[3 ..])
+                    format!("R{0}G{0}B{0}{1}", bits, &format[3 ..])
                 } else if count == 4 {
-                    format!("R32G32B32A32{}", &format[3 ..])
+                    format!("R{0}G{0}B{0}A{0}{1}", bits, &format[3 ..])
                 } else {
                     panic!("Found vector type with more than 4 elements")
                 };
+                // 64-bit vectors with three or four components consume two locations.
+                let sz = if bits == "64" && count >= 3 { 2 } else { sz };
                 return (format, sz);
             },
             &parse::Instruction::TypeMatrix {
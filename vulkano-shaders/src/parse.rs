@@ -108,6 +108,11 @@ pub enum Instruction {
         name: String,
         interface: Vec<u32>,
     },
+    ExecutionMode {
+        target_id: u32,
+        mode: ExecutionMode,
+        optional_literals: Vec<u32>,
+    },
     Capability(Capability),
     TypeVoid { result_id: u32 },
     TypeBool { result_id: u32 },
@@ -161,6 +166,21 @@ pub enum Instruction {
         result_id: u32,
         data: Vec<u32>,
     },
+    ConstantComposite {
+        result_type_id: u32,
+        result_id: u32,
+        constituents: Vec<u32>,
+    },
+    SpecConstant {
+        result_type_id: u32,
+        result_id: u32,
+        data: Vec<u32>,
+    },
+    SpecConstantComposite {
+        result_type_id: u32,
+        result_id: u32,
+        constituents: Vec<u32>,
+    },
     FunctionEnd,
     Variable {
         result_type_id: u32,
@@ -168,6 +188,16 @@ pub enum Instruction {
         storage_class: StorageClass,
         initializer: Option<u32>,
     },
+    Store {
+        pointer_id: u32,
+        object_id: u32,
+    },
+    AccessChain {
+        result_type_id: u32,
+        result_id: u32,
+        base_id: u32,
+        indexes: Vec<u32>,
+    },
     Decorate {
         target_id: u32,
         decoration: Decoration,
@@ -227,6 +257,16 @@ fn decode_instruction(opcode: u16, operands: &[u32]) -> Result<Instruction, Pars
                    interface: r.to_owned(),
                }
            },
+           16 => match ExecutionMode::from_num(operands[1]) {
+               Ok(mode) => Instruction::ExecutionMode {
+                   target_id: operands[0],
+                   mode: mode,
+                   optional_literals: operands[2 ..].to_owned(),
+               },
+               // Execution modes that we don't know about, for example those added by
+               // extensions, don't prevent the rest of the module from being parsed.
+               Err(_) => Instruction::Unknown(opcode, operands.to_owned()),
+           },
            17 => Instruction::Capability(Capability::from_num(operands[0])?),
            19 => Instruction::TypeVoid { result_id: operands[0] },
            20 => Instruction::TypeBool { result_id: operands[0] },
@@ -306,6 +346,21 @@ fn decode_instruction(opcode: u16, operands: &[u32]) -> Result<Instruction, Pars
                result_id: operands[1],
                data: operands[2 ..].to_owned(),
            },
+           44 => Instruction::ConstantComposite {
+               result_type_id: operands[0],
+               result_id: operands[1],
+               constituents: operands[2 ..].to_owned(),
+           },
+           50 => Instruction::SpecConstant {
+               result_type_id: operands[0],
+               result_id: operands[1],
+               data: operands[2 ..].to_owned(),
+           },
+           51 => Instruction::SpecConstantComposite {
+               result_type_id: operands[0],
+               result_id: operands[1],
+               constituents: operands[2 ..].to_owned(),
+           },
            56 => Instruction::FunctionEnd,
            59 => Instruction::Variable {
                result_type_id: operands[0],
@@ -313,6 +368,16 @@ fn decode_instruction(opcode: u16, operands: &[u32]) -> Result<Instruction, Pars
                storage_class: StorageClass::from_num(operands[2])?,
                initializer: operands.get(3).map(|&v| v),
            },
+           62 => Instruction::Store {
+               pointer_id: operands[0],
+               object_id: operands[1],
+           },
+           65 | 66 => Instruction::AccessChain {
+               result_type_id: operands[0],
+               result_id: operands[1],
+               base_id: operands[2],
+               indexes: operands[3 ..].to_owned(),
+           },
            71 => Instruction::Decorate {
                target_id: operands[0],
                decoration: Decoration::from_num(operands[1])?,
@@ -359,4 +424,25 @@ mod test {
         let data = include_bytes!("../tests/frag.spv");
         println!("{:#?}", parse::parse_spirv(data).unwrap());
     }
+
+    #[test]
+    fn unknown_execution_mode() {
+        // Header, then `OpExecutionMode %1 LocalSize 8 1 1` and `OpExecutionMode %1 0xffff`.
+        let words = [0x07230203, 0x00010000, 0, 2, 0,
+                     (6 << 16) | 16, 1, 17, 8, 1, 1,
+                     (3 << 16) | 16, 1, 0xffff];
+        let doc = parse::parse_u32s(&words).unwrap();
+
+        assert_eq!(doc.instructions.len(), 2);
+        match doc.instructions[0] {
+            parse::Instruction::ExecutionMode { target_id: 1, ref optional_literals, .. } => {
+                assert_eq!(optional_literals, &[8, 1, 1]);
+            },
+            _ => panic!(),
+        }
+        match doc.instructions[1] {
+            parse::Instruction::Unknown(16, ref operands) => assert_eq!(operands, &[1, 0xffff]),
+            _ => panic!(),
+        }
+    }
 }
@@ -15,6 +15,10 @@
 //! Each access from the CPU or from the GPU locks the whole buffer for either reading or writing.
 //! You can read the buffer multiple times simultaneously. Trying to read and write simultaneously,
 //! or write and write simultaneously will block.
+//!
+//! Buffers of arrays can also be locked by the CPU for a range of elements only, with
+//! `read_range()` and `write_range()`. Multiple threads can then write to disjoint parts of the
+//! buffer at the same time. The GPU still locks the whole buffer.
 
 use smallvec::SmallVec;
 use std::error;
@@ -23,10 +27,12 @@ use std::marker::PhantomData;
 use std::mem;
 use std::ops::Deref;
 use std::ops::DerefMut;
+use std::ops::Range;
 use std::ptr;
 use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::sync::Mutex;
 use std::sync::RwLock;
 use std::sync::RwLockReadGuard;
 use std::sync::RwLockWriteGuard;
@@ -58,6 +64,11 @@ use sync::Sharing;
 use OomError;
 
 /// Buffer whose content is accessible by the CPU.
+///
+/// The memory of the buffer is usually a part of a bigger chunk allocated by a memory pool. The
+/// chunk is mapped once and stays mapped, so locking the buffer never maps or unmaps memory. The
+/// locks point to the content of the buffer at its offset within the chunk, and only this range
+/// is flushed or invalidated if the memory isn't host-coherent.
 #[derive(Debug)]
 pub struct CpuAccessibleBuffer<T: ?Sized, A = StdMemoryPoolAlloc> {
     // Inner content.
@@ -72,6 +83,12 @@ pub struct CpuAccessibleBuffer<T: ?Sized, A = StdMemoryPoolAlloc> {
     // tries to submit this buffer for the GPU, this `RwLock` is briefly locked and modified.
     access: RwLock<CurrentGpuAccess>,
 
+    // Ranges of the memory currently locked by the CPU with `read()`, `read_range()` or
+    // `write_range()`, as returned by `cpu_range()`, and whether the lock is exclusive. Every
+    // entry is accompanied by a read guard on `access`. Always locked after `access` in order to
+    // avoid deadlocks.
+    cpu_ranges: Mutex<Vec<(Range<usize>, bool)>>,
+
     // Queue families allowed to access this buffer.
     queue_families: SmallVec<[u32; 4]>,
 
@@ -242,6 +259,7 @@ impl<T: ?Sized> CpuAccessibleBuffer<T> {
                         access: RwLock::new(CurrentGpuAccess::NonExclusive {
                             num: AtomicUsize::new(0)
                         }),
+                        cpu_ranges: Mutex::new(Vec::new()),
                         queue_families: queue_families,
                         marker: PhantomData,
                     }))
@@ -278,6 +296,10 @@ impl<T: ?Sized, A> CpuAccessibleBuffer<T, A>
     /// After this function successfully locks the buffer, any attempt to submit a command buffer
     /// that uses it in exclusive mode will fail. You can still submit this buffer for non-exlusive
     /// accesses (ie. reads).
+    ///
+    /// If the content of the buffer was written by the GPU, the command buffer that wrote it must
+    /// have called `host_read_barrier()` (or an equivalent barrier) for the new content to be
+    /// visible here.
     #[inline]
     pub fn read(&self) -> Result<ReadLock<T>, ReadLockError> {
         let lock = match self.access.try_read() {
@@ -292,11 +314,18 @@ impl<T: ?Sized, A> CpuAccessibleBuffer<T, A>
             return Err(ReadLockError::GpuWriteLocked);
         }
 
+        let locked = self.cpu_range(0 .. self.inner.size());
+        let cpu_range = match CpuRangeLock::new(&self.cpu_ranges, locked, false) {
+            Some(r) => r,
+            None => return Err(ReadLockError::CpuWriteLocked),
+        };
+
         let offset = self.memory.offset();
         let range = offset .. offset + self.inner.size();
 
         Ok(ReadLock {
-               inner: unsafe { self.memory.mapped_memory().unwrap().read_write(range) },
+               inner: unsafe { self.memory.mapped_memory().unwrap().read(range) },
+               cpu_range: cpu_range,
                lock: lock,
            })
     }
@@ -309,8 +338,58 @@ impl<T: ?Sized, A> CpuAccessibleBuffer<T, A>
     ///
     /// After this function successfully locks the buffer, any attempt to submit a command buffer
     /// that uses it and any attempt to call `read()` will return an error.
+    ///
+    /// This is the same as `read_write()`. Use `write_only()` instead if you overwrite the
+    /// content without reading it.
     #[inline]
     pub fn write(&self) -> Result<WriteLock<T>, WriteLockError> {
+        self.read_write()
+    }
+
+    /// Locks the buffer in order to read and modify its content from the CPU.
+    ///
+    /// This works the same way as `write()`. If the memory of the buffer isn't host-coherent,
+    /// its caches are invalidated when locking so that the content written by the GPU is visible,
+    /// and flushed when the lock is dropped so that the modifications are visible to the GPU.
+    #[inline]
+    pub fn read_write(&self) -> Result<WriteLock<T>, WriteLockError> {
+        self.write_impl(true)
+    }
+
+    /// Locks the buffer in order to overwrite its content from the CPU.
+    ///
+    /// This works the same way as `read_write()`, except that the caches of the memory are not
+    /// invalidated when locking, which saves some work on memory that isn't host-coherent. The
+    /// modifications are still flushed when the lock is dropped.
+    ///
+    /// Reading through the returned lock is allowed, but may return outdated values instead of
+    /// what the GPU last wrote. Only use this function if you overwrite the content entirely,
+    /// or if you don't care about the previous content.
+    #[inline]
+    pub fn write_only(&self) -> Result<WriteLock<T>, WriteLockError> {
+        self.write_impl(false)
+    }
+
+    // Returns the range of the memory to register in `cpu_ranges` when locking the bytes of the
+    // buffer within `bytes`.
+    //
+    // If the memory isn't host-coherent, locking invalidates and flushes whole atoms of
+    // `non_coherent_atom_size` bytes. Invalidating an atom discards the writes that another lock
+    // made to it and hasn't flushed yet, therefore two locks that share an atom must conflict even
+    // if their bytes don't overlap.
+    fn cpu_range(&self, bytes: Range<usize>) -> Range<usize> {
+        let offset = self.memory.offset();
+        let range = offset + bytes.start .. offset + bytes.end;
+        let memory = self.memory.mapped_memory().unwrap();
+
+        if memory.as_ref().memory_type().is_host_coherent() {
+            range
+        } else {
+            memory.atom_aligned_range(range)
+        }
+    }
+
+    fn write_impl(&self, invalidate: bool) -> Result<WriteLock<T>, WriteLockError> {
         let lock = match self.access.try_write() {
             Ok(l) => l,
             // TODO: if a user simultaneously calls .read() or .write(), and the function is
@@ -327,12 +406,129 @@ impl<T: ?Sized, A> CpuAccessibleBuffer<T, A>
 
         let offset = self.memory.offset();
         let range = offset .. offset + self.inner.size();
+        let memory = self.memory.mapped_memory().unwrap();
 
         Ok(WriteLock {
+               inner: unsafe {
+                   if invalidate {
+                       memory.read_write(range)
+                   } else {
+                       memory.write_only(range)
+                   }
+               },
+               lock: lock,
+           })
+    }
+}
+
+impl<T, A> CpuAccessibleBuffer<[T], A>
+    where T: Content + 'static,
+          A: MemoryPoolAlloc,
+{
+    /// Locks the elements of the buffer within `range` in order to read them from the CPU.
+    ///
+    /// This works the same way as `read()`, except that only the given range is locked. Writing
+    /// other elements of the buffer with `write_range()` at the same time is allowed. If the
+    /// memory of the buffer isn't host-coherent, the elements that share an atom of
+    /// `non_coherent_atom_size` bytes with `range` are locked as well.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `range` is out of the bounds of the buffer.
+    ///
+    pub fn read_range(&self, range: Range<usize>) -> Result<ReadLock<[T]>, ReadLockError> {
+        let bytes = self.range_to_bytes(range);
+
+        let lock = match self.access.try_read() {
+            Ok(l) => l,
+            Err(_) => return Err(ReadLockError::CpuWriteLocked),
+        };
+
+        if let CurrentGpuAccess::Exclusive { .. } = *lock {
+            return Err(ReadLockError::GpuWriteLocked);
+        }
+
+        let cpu_range = match CpuRangeLock::new(&self.cpu_ranges, self.cpu_range(bytes.clone()),
+                                                false) {
+            Some(r) => r,
+            None => return Err(ReadLockError::CpuWriteLocked),
+        };
+
+        let offset = self.memory.offset();
+        let range = offset + bytes.start .. offset + bytes.end;
+
+        Ok(ReadLock {
+               inner: unsafe { self.memory.mapped_memory().unwrap().read(range) },
+               cpu_range: cpu_range,
+               lock: lock,
+           })
+    }
+
+    /// Locks the elements of the buffer within `range` in order to write them from the CPU.
+    ///
+    /// This works the same way as `write()`, except that only the given range is locked. Reading
+    /// or writing other elements of the buffer with `read_range()` or `write_range()` at the same
+    /// time is allowed. Because the GPU always locks the whole buffer, this function returns
+    /// `GpuLocked` if the buffer is in use by the GPU, even if it doesn't overlap `range`.
+    ///
+    /// If the memory of the buffer isn't host-coherent, the elements that share an atom of
+    /// `non_coherent_atom_size` bytes with `range` are locked as well, as locking them separately
+    /// could discard writes that haven't been flushed yet.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `range` is out of the bounds of the buffer.
+    ///
+    pub fn write_range(&self, range: Range<usize>) -> Result<WriteRangeLock<[T]>, WriteLockError> {
+        let bytes = self.range_to_bytes(range);
+        let locked = self.cpu_range(bytes.clone());
+
+        let lock = match self.access.try_read() {
+            Ok(l) => l,
+            Err(_) => return Err(WriteLockError::CpuLocked),
+        };
+
+        let cpu_range = {
+            // We must hold `cpu_ranges` while checking the GPU accesses, otherwise the GPU could
+            // lock the buffer between the check and the registration of our range.
+            let mut cpu_ranges = self.cpu_ranges.lock().unwrap();
+
+            match *lock {
+                CurrentGpuAccess::NonExclusive { ref num } if num.load(Ordering::SeqCst) == 0 => (),
+                _ => return Err(WriteLockError::GpuLocked),
+            }
+
+            if ranges_conflict(&cpu_ranges, &locked, true) {
+                return Err(WriteLockError::CpuLocked);
+            }
+
+            cpu_ranges.push((locked.clone(), true));
+            CpuRangeLock {
+                ranges: &self.cpu_ranges,
+                range: locked,
+                exclusive: true,
+            }
+        };
+
+        let offset = self.memory.offset();
+        let range = offset + bytes.start .. offset + bytes.end;
+
+        Ok(WriteRangeLock {
                inner: unsafe { self.memory.mapped_memory().unwrap().read_write(range) },
+               cpu_range: cpu_range,
                lock: lock,
            })
     }
+
+    // Checks that `range` is within the buffer and turns it into a range of bytes.
+    #[inline]
+    fn range_to_bytes(&self, range: Range<usize>) -> Range<usize> {
+        let elem_size = mem::size_of::<T>();
+        assert!(range.start <= range.end);
+        let end = range.end.checked_mul(elem_size).expect("range out of the bounds of the buffer");
+        assert!(end <= self.inner.size());
+        range.start * elem_size .. end
+    }
 }
 
 unsafe impl<T: ?Sized, A> BufferAccess for CpuAccessibleBuffer<T, A>
@@ -351,11 +547,6 @@ unsafe impl<T: ?Sized, A> BufferAccess for CpuAccessibleBuffer<T, A>
         self.inner.size()
     }
 
-    #[inline]
-    fn conflict_key(&self, self_offset: usize, self_size: usize) -> u64 {
-        self.inner.key()
-    }
-
     #[inline]
     fn try_gpu_lock(&self, exclusive_access: bool, _: &Queue) -> Result<(), AccessError> {
         if exclusive_access {
@@ -378,6 +569,12 @@ unsafe impl<T: ?Sized, A> BufferAccess for CpuAccessibleBuffer<T, A>
                 Err(_) => return Err(AccessError::AlreadyInUse),
             };
 
+            // Ranges locked by `write_range()` only hold a read lock on `access`.
+            let cpu_ranges = self.cpu_ranges.lock().unwrap();
+            if cpu_ranges.iter().any(|&(_, exclusive)| exclusive) {
+                return Err(AccessError::AlreadyInUse);
+            }
+
             match *lock {
                 CurrentGpuAccess::Exclusive { .. } => return Err(AccessError::AlreadyInUse),
                 CurrentGpuAccess::NonExclusive { ref num } => {
@@ -470,6 +667,7 @@ unsafe impl<T: ?Sized, A> DeviceOwned for CpuAccessibleBuffer<T, A> {
 /// this buffer's content or tries to submit a GPU command that uses this buffer, it will block.
 pub struct ReadLock<'a, T: ?Sized + 'a> {
     inner: MemCpuAccess<'a, T>,
+    cpu_range: CpuRangeLock<'a>,
     lock: RwLockReadGuard<'a, CurrentGpuAccess>,
 }
 
@@ -481,6 +679,7 @@ impl<'a, T: ?Sized + 'a> ReadLock<'a, T> {
     {
         ReadLock {
             inner: self.inner.map(|ptr| unsafe { f(&mut *ptr) as *mut _ }),
+            cpu_range: self.cpu_range,
             lock: self.lock,
         }
     }
@@ -563,6 +762,77 @@ impl<'a, T: ?Sized + 'a> DerefMut for WriteLock<'a, T> {
     }
 }
 
+/// Object that can be used to write a range of elements of a `CpuAccessibleBuffer`.
+///
+/// Note that this object holds a rwlock read guard on the buffer. Other ranges of the buffer can be
+/// accessed by the CPU in the meantime, but the GPU can't use the buffer.
+pub struct WriteRangeLock<'a, T: ?Sized + 'a> {
+    inner: MemCpuAccess<'a, T>,
+    cpu_range: CpuRangeLock<'a>,
+    lock: RwLockReadGuard<'a, CurrentGpuAccess>,
+}
+
+impl<'a, T: ?Sized + 'a> Deref for WriteRangeLock<'a, T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        self.inner.deref()
+    }
+}
+
+impl<'a, T: ?Sized + 'a> DerefMut for WriteRangeLock<'a, T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        self.inner.deref_mut()
+    }
+}
+
+// Entry of `cpu_ranges` that is removed when dropped.
+struct CpuRangeLock<'a> {
+    ranges: &'a Mutex<Vec<(Range<usize>, bool)>>,
+    range: Range<usize>,
+    exclusive: bool,
+}
+
+impl<'a> CpuRangeLock<'a> {
+    // Registers `range` in `ranges`. Returns `None` if it conflicts with an existing entry.
+    fn new(ranges: &'a Mutex<Vec<(Range<usize>, bool)>>, range: Range<usize>, exclusive: bool)
+           -> Option<CpuRangeLock<'a>> {
+        let mut list = ranges.lock().unwrap();
+        if ranges_conflict(&list, &range, exclusive) {
+            return None;
+        }
+
+        list.push((range.clone(), exclusive));
+        Some(CpuRangeLock {
+                 ranges: ranges,
+                 range: range,
+                 exclusive: exclusive,
+             })
+    }
+}
+
+impl<'a> Drop for CpuRangeLock<'a> {
+    #[inline]
+    fn drop(&mut self) {
+        let mut list = self.ranges.lock().unwrap();
+        let pos = list.iter()
+            .position(|&(ref r, e)| *r == self.range && e == self.exclusive)
+            .unwrap();
+        list.swap_remove(pos);
+    }
+}
+
+// Returns true if locking `range` conflicts with one of the existing locks. Two locks conflict
+// if they overlap and at least one of them is exclusive.
+fn ranges_conflict(locks: &[(Range<usize>, bool)], range: &Range<usize>, exclusive: bool)
+                   -> bool {
+    locks.iter().any(|&(ref r, e)| {
+        (exclusive || e) && r.start < range.end && range.start < r.end
+    })
+}
+
 /// Error when attempting to CPU-write a buffer.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum WriteLockError {
@@ -595,7 +865,17 @@ impl fmt::Display for WriteLockError {
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+    use std::sync::Barrier;
+    use std::thread;
+    use std::usize;
+
     use buffer::{BufferUsage, CpuAccessibleBuffer};
+    use buffer::cpu_access::ReadLockError;
+    use buffer::cpu_access::WriteLockError;
+    use buffer::cpu_access::ranges_conflict;
+    use memory::MappedDeviceMemory;
+    use memory::pool::MemoryPoolAlloc;
 
     #[test]
     fn create_empty_buffer() {
@@ -608,4 +888,169 @@ mod tests {
                                                Some(queue.family()),
                                                EMPTY.iter());
     }
+
+    #[test]
+    fn ranges_conflict_rules() {
+        let locks = [(0 .. 16, false), (32 .. 48, true)];
+        assert!(!ranges_conflict(&locks, &(16 .. 32), true));
+        assert!(!ranges_conflict(&locks, &(8 .. 16), false));
+        assert!(ranges_conflict(&locks, &(8 .. 16), true));
+        assert!(ranges_conflict(&locks, &(40 .. 44), false));
+        assert!(!ranges_conflict(&locks, &(48 .. 64), true));
+    }
+
+    #[test]
+    fn small_buffers_share_mapping() {
+        let (device, queue) = gfx_dev_and_queue!();
+        let buffers = (0 .. 16u32)
+            .map(|n| {
+                     CpuAccessibleBuffer::from_data(device.clone(),
+                                                    BufferUsage::all(),
+                                                    Some(queue.family()),
+                                                    n)
+                         .unwrap()
+                 })
+            .collect::<Vec<_>>();
+
+        let mapping = |buffer: &CpuAccessibleBuffer<u32>| {
+            buffer.memory.mapped_memory().unwrap() as *const MappedDeviceMemory
+        };
+        assert!(buffers.iter().all(|b| mapping(b) == mapping(&buffers[0])));
+
+        for buffer in buffers.iter() {
+            *buffer.write().unwrap() += 100;
+        }
+        for (n, buffer) in buffers.iter().enumerate() {
+            assert_eq!(*buffer.read().unwrap(), n as u32 + 100);
+        }
+    }
+
+    #[test]
+    fn write_only_then_read() {
+        let (device, queue) = gfx_dev_and_queue!();
+        let buffer = CpuAccessibleBuffer::from_iter(device,
+                                                    BufferUsage::all(),
+                                                    Some(queue.family()),
+                                                    (0 .. 16).map(|_| 0u32))
+            .unwrap();
+
+        {
+            let mut content = buffer.write_only().unwrap();
+            for (n, value) in content.iter_mut().enumerate() {
+                *value = n as u32;
+            }
+            assert_eq!(buffer.read().err(), Some(ReadLockError::CpuWriteLocked));
+        }
+
+        {
+            let mut content = buffer.read_write().unwrap();
+            content[3] += 10;
+        }
+
+        let content = buffer.read().unwrap();
+        assert_eq!(content[2], 2);
+        assert_eq!(content[3], 13);
+    }
+
+    #[test]
+    fn overlapping_ranges() {
+        let (device, queue) = gfx_dev_and_queue!();
+        let buffer = CpuAccessibleBuffer::from_iter(device,
+                                                    BufferUsage::all(),
+                                                    Some(queue.family()),
+                                                    (0 .. 256).map(|_| 0u32))
+            .unwrap();
+
+        // The ranges are 256 bytes apart, which is the maximum `non_coherent_atom_size`.
+        let _write = buffer.write_range(0 .. 16).unwrap();
+        assert_eq!(buffer.read_range(8 .. 24).err(), Some(ReadLockError::CpuWriteLocked));
+        assert_eq!(buffer.write_range(15 .. 16).err(), Some(WriteLockError::CpuLocked));
+        assert_eq!(buffer.read().err(), Some(ReadLockError::CpuWriteLocked));
+        assert_eq!(buffer.write().err(), Some(WriteLockError::CpuLocked));
+        assert!(buffer.read_range(64 .. 128).is_ok());
+    }
+
+    #[test]
+    #[should_panic]
+    fn range_overflow() {
+        let (device, queue) = gfx_dev_and_queue!();
+        let buffer = CpuAccessibleBuffer::from_iter(device,
+                                                    BufferUsage::all(),
+                                                    Some(queue.family()),
+                                                    (0 .. 16).map(|_| 0u32))
+            .unwrap();
+
+        // Without overflow checks, the end in bytes would wrap around to 4.
+        let _ = buffer.read_range(0 .. usize::MAX / 4 + 1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn reversed_range() {
+        let (device, queue) = gfx_dev_and_queue!();
+        let buffer = CpuAccessibleBuffer::from_iter(device,
+                                                    BufferUsage::all(),
+                                                    Some(queue.family()),
+                                                    (0 .. 16).map(|_| 0u32))
+            .unwrap();
+
+        let _ = buffer.write_range(8 .. 4);
+    }
+
+    #[test]
+    fn write_ranges_sharing_atom() {
+        let (device, queue) = gfx_dev_and_queue!();
+        let buffer = CpuAccessibleBuffer::from_iter(device.clone(),
+                                                    BufferUsage::all(),
+                                                    Some(queue.family()),
+                                                    (0 .. 256).map(|_| 0u8))
+            .unwrap();
+
+        let memory = buffer.memory.mapped_memory().unwrap();
+        let coherent = memory.as_ref().memory_type().is_host_coherent();
+        let atom_size = device.physical_device().limits().non_coherent_atom_size();
+
+        // The two ranges don't overlap, but share an atom unless the atom size is 1.
+        let _write = buffer.write_range(0 .. 1).unwrap();
+        let result = buffer.write_range(1 .. 2);
+        if coherent || atom_size == 1 {
+            assert!(result.is_ok());
+        } else {
+            assert_eq!(result.err(), Some(WriteLockError::CpuLocked));
+        }
+    }
+
+    #[test]
+    fn concurrent_disjoint_write_ranges() {
+        let (device, queue) = gfx_dev_and_queue!();
+        let buffer = CpuAccessibleBuffer::from_iter(device,
+                                                    BufferUsage::all(),
+                                                    Some(queue.family()),
+                                                    (0 .. 1024).map(|_| 0u32))
+            .unwrap();
+
+        // Both threads hold their lock at the same time before writing.
+        let barrier = Arc::new(Barrier::new(2));
+        let threads = (0 .. 2)
+            .map(|n| {
+                let buffer = buffer.clone();
+                let barrier = barrier.clone();
+                thread::spawn(move || {
+                    let mut lock = buffer.write_range(n * 512 .. (n + 1) * 512).unwrap();
+                    barrier.wait();
+                    for val in lock.iter_mut() {
+                        *val = n as u32 + 1;
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        let content = buffer.read().unwrap();
+        assert!(content[.. 512].iter().all(|&v| v == 1));
+        assert!(content[512 ..].iter().all(|&v| v == 2));
+    }
 }
@@ -29,6 +29,7 @@ use device::Device;
 use device::DeviceOwned;
 use device::Queue;
 use instance::QueueFamily;
+use memory::CpuAccess;
 use memory::pool::AllocLayout;
 use memory::pool::MemoryPool;
 use memory::pool::MemoryPoolAlloc;
@@ -101,6 +102,10 @@ struct ActualBuffer<A>
     capacity: usize,
 }
 
+// Value of `num_gpu_accesses` while the CPU is writing to a subbuffer. GPU-locking the
+// subbuffer fails in the meantime.
+const CPU_WRITE_LOCK: usize = !0;
+
 // Access pattern of one subbuffer.
 #[derive(Debug)]
 struct ActualBufferSubbuffer {
@@ -219,6 +224,10 @@ impl<T, A> CpuBufferPool<T, A>
     ///
     /// > **Note**: You can think of it like a `Vec`. If you insert an element and the `Vec` is not
     /// > large enough, a new chunk of memory is automatically allocated.
+    ///
+    /// If the memory of the pool isn't host-coherent, the range containing `data` is flushed
+    /// before the subbuffer is returned. There is therefore no need to flush it before using the
+    /// subbuffer in a command buffer.
     pub fn next(&self, data: T) -> CpuBufferPoolSubbuffer<T, A> {
         let mut mutex = self.current_buffer.lock().unwrap();
 
@@ -252,6 +261,48 @@ impl<T, A> CpuBufferPool<T, A>
         self.try_next_impl(&mut mutex, data).ok()
     }
 
+    // Tries to lock a subbuffer from the current buffer and writes `data` in it.
+    fn try_next_impl(&self, cur_buf_mutex: &mut MutexGuard<Option<Arc<ActualBuffer<A>>>>, data: T)
+                     -> Result<CpuBufferPoolSubbuffer<T, A>, T> {
+        let subbuffer = match self.try_lock_impl(cur_buf_mutex) {
+            Some(s) => s,
+            None => return Err(data),
+        };
+
+        // Write `data` in the memory.
+        unsafe {
+            *subbuffer.access_at(0) = data;
+        }
+
+        Ok(subbuffer)
+    }
+}
+
+impl<T: ?Sized, A> CpuBufferPool<T, A>
+    where A: MemoryPool
+{
+    // Grants access to a new subbuffer without writing anything in it. A new buffer is allocated
+    // if no subbuffer is available.
+    pub(crate) fn next_uninit(&self) -> Result<CpuBufferPoolSubbuffer<T, A>, OomError> {
+        let mut mutex = self.current_buffer.lock().unwrap();
+
+        if let Some(subbuffer) = self.try_lock_impl(&mut mutex) {
+            return Ok(subbuffer);
+        }
+
+        let next_capacity = match *mutex {
+            Some(ref b) => b.capacity * 2,
+            None => 3,
+        };
+
+        self.reset_buf(&mut mutex, next_capacity)?;
+
+        match self.try_lock_impl(&mut mutex) {
+            Some(subbuffer) => Ok(subbuffer),
+            None => unreachable!(),
+        }
+    }
+
     // Creates a new buffer and sets it as current.
     fn reset_buf(&self, cur_buf_mutex: &mut MutexGuard<Option<Arc<ActualBuffer<A>>>>,
                  capacity: usize)
@@ -321,12 +372,12 @@ impl<T, A> CpuBufferPool<T, A>
     }
 
     // Tries to lock a subbuffer from the current buffer.
-    fn try_next_impl(&self, cur_buf_mutex: &mut MutexGuard<Option<Arc<ActualBuffer<A>>>>, data: T)
-                     -> Result<CpuBufferPoolSubbuffer<T, A>, T> {
-        // Grab the current buffer. Return `Err` if the pool wasn't "initialized" yet.
+    fn try_lock_impl(&self, cur_buf_mutex: &mut MutexGuard<Option<Arc<ActualBuffer<A>>>>)
+                     -> Option<CpuBufferPoolSubbuffer<T, A>> {
+        // Grab the current buffer. Return `None` if the pool wasn't "initialized" yet.
         let current_buffer = match cur_buf_mutex.clone() {
             Some(b) => b,
-            None => return Err(data),
+            None => return None,
         };
 
         // Grab the next subbuffer to use.
@@ -347,7 +398,7 @@ impl<T, A> CpuBufferPool<T, A>
             .num_cpu_accesses
             .compare_and_swap(0, 1, Ordering::SeqCst) != 0
         {
-            return Err(data);
+            return None;
         }
 
         // Reset num_gpu_accesses.
@@ -355,23 +406,12 @@ impl<T, A> CpuBufferPool<T, A>
             .num_gpu_accesses
             .store(0, Ordering::SeqCst);
 
-        // Write `data` in the memory.
-        unsafe {
-            let range = (next_subbuffer * self.one_size) .. ((next_subbuffer + 1) * self.one_size);
-            let mut mapping = current_buffer
-                .memory
-                .mapped_memory()
-                .unwrap()
-                .read_write(range);
-            *mapping = data;
-        }
-
-        Ok(CpuBufferPoolSubbuffer {
-               buffer: current_buffer,
-               subbuffer_index: next_subbuffer,
-               size: self.one_size,
-               marker: PhantomData,
-           })
+        Some(CpuBufferPoolSubbuffer {
+                 buffer: current_buffer,
+                 subbuffer_index: next_subbuffer,
+                 size: self.one_size,
+                 marker: PhantomData,
+             })
     }
 }
 
@@ -421,6 +461,54 @@ impl<T: ?Sized, A> Clone for CpuBufferPoolSubbuffer<T, A>
     }
 }
 
+impl<T: ?Sized, A> CpuBufferPoolSubbuffer<T, A>
+    where A: MemoryPool
+{
+    // Grants CPU access to an object of type `U` located `offset` bytes after the start of the
+    // subbuffer.
+    //
+    // The caller must ensure that the object is within the subbuffer and that the GPU isn't
+    // accessing this range of memory.
+    //
+    // If the memory isn't host-coherent, the range of the object is flushed when the returned
+    // `CpuAccess` is destroyed, so that the writes are visible to the GPU.
+    pub(crate) unsafe fn access_at<U>(&self, offset: usize) -> CpuAccess<U> {
+        debug_assert!(offset + mem::size_of::<U>() <= self.size);
+        let start = self.buffer.memory.offset() + self.subbuffer_index * self.size + offset;
+        self.buffer
+            .memory
+            .mapped_memory()
+            .unwrap()
+            .read_write(start .. start + mem::size_of::<U>())
+    }
+
+    // Writes `value` at `offset` bytes after the start of the subbuffer, if the GPU isn't
+    // accessing the subbuffer. Returns the value back if the subbuffer is GPU-locked.
+    //
+    // The subbuffer is locked during the write, so that it can't be submitted concurrently.
+    //
+    // # Panic
+    //
+    // - Panics if the object isn't within the subbuffer.
+    //
+    pub(crate) fn try_write_at<U>(&self, offset: usize, value: U) -> Result<(), U> {
+        assert!(offset + mem::size_of::<U>() <= self.size);
+
+        let in_use = &self.buffer.subbuffers[self.subbuffer_index].num_gpu_accesses;
+        if in_use.compare_and_swap(0, CPU_WRITE_LOCK, Ordering::SeqCst) != 0 {
+            return Err(value);
+        }
+
+        unsafe {
+            // The write is flushed when the `CpuAccess` is destroyed, at the end of the statement.
+            *self.access_at(offset) = value;
+        }
+
+        in_use.store(0, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
 unsafe impl<T: ?Sized, A> BufferAccess for CpuBufferPoolSubbuffer<T, A>
     where A: MemoryPool
 {
@@ -437,11 +525,6 @@ unsafe impl<T: ?Sized, A> BufferAccess for CpuBufferPoolSubbuffer<T, A>
         self.size
     }
 
-    #[inline]
-    fn conflict_key(&self, self_offset: usize, self_size: usize) -> u64 {
-        self.buffer.inner.key() + self.subbuffer_index as u64
-    }
-
     #[inline]
     fn try_gpu_lock(&self, _: bool, _: &Queue) -> Result<(), AccessError> {
         let in_use = &self.buffer.subbuffers[self.subbuffer_index].num_gpu_accesses;
@@ -494,8 +577,15 @@ unsafe impl<T: ?Sized, A> DeviceOwned for CpuBufferPoolSubbuffer<T, A>
 
 #[cfg(test)]
 mod tests {
+    use buffer::BufferUsage;
+    use buffer::CpuAccessibleBuffer;
     use buffer::CpuBufferPool;
+    use command_buffer::AutoCommandBufferBuilder;
+    use command_buffer::CommandBuffer;
+    use memory::pool::MemoryPoolAlloc;
+    use std::iter;
     use std::mem;
+    use sync::GpuFuture;
 
     #[test]
     fn basic_create() {
@@ -551,4 +641,40 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn data_visible_at_memory_offset() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        // The first pool occupies the start of the memory block, so that the buffer of the
+        // second one is bound at a non-zero offset.
+        let first = CpuBufferPool::new(device.clone(), BufferUsage::all(), Some(queue.family()));
+        let _first_sub = first.next([1u32; 4]);
+
+        let pool = CpuBufferPool::new(device.clone(), BufferUsage::all(), Some(queue.family()));
+        let _ = pool.next([2u32; 4]);
+        let sub = pool.next([0x12345678u32, 3, 5, 7]);
+        assert_ne!(sub.buffer.memory.offset(), 0);
+
+        let dest = CpuAccessibleBuffer::from_data(device.clone(),
+                                                  BufferUsage::all(),
+                                                  iter::once(queue.family()),
+                                                  [0u32; 4])
+            .unwrap();
+
+        AutoCommandBufferBuilder::new(device.clone(), queue.family())
+            .unwrap()
+            .copy_buffer(sub, dest.clone())
+            .unwrap()
+            .build()
+            .unwrap()
+            .execute(queue.clone())
+            .unwrap()
+            .then_signal_fence_and_flush()
+            .unwrap()
+            .wait(None)
+            .unwrap();
+
+        assert_eq!(*dest.read().unwrap(), [0x12345678u32, 3, 5, 7]);
+    }
 }
@@ -29,6 +29,7 @@ use buffer::traits::TypedBufferAccess;
 use device::Device;
 use device::DeviceOwned;
 use device::Queue;
+use instance::MemoryType;
 use instance::QueueFamily;
 use memory::pool::AllocLayout;
 use memory::pool::MemoryPool;
@@ -38,6 +39,7 @@ use sync::AccessError;
 use sync::Sharing;
 
 use OomError;
+use VulkanObject;
 
 /// Buffer whose content is in device-local memory.
 ///
@@ -82,6 +84,31 @@ impl<T> DeviceLocalBuffer<T> {
     {
         unsafe { DeviceLocalBuffer::raw(device, mem::size_of::<T>(), usage, queue_families) }
     }
+
+    /// Builds a new buffer whose memory is allocated from the given memory type.
+    ///
+    /// Contrary to `new`, the memory type isn't automatically chosen. Returns an error if the
+    /// memory type isn't device-local, or if the buffer can't be put in memory of this type.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the memory type doesn't belong to the physical device of `device`.
+    ///
+    // TODO: unsafe because uninitialized data
+    #[inline]
+    pub fn with_memory_type<'a, I>(device: Arc<Device>, usage: BufferUsage, queue_families: I,
+                                   memory_type: MemoryType)
+                                   -> Result<Arc<DeviceLocalBuffer<T>>, BufferCreationError>
+        where I: IntoIterator<Item = QueueFamily<'a>>
+    {
+        unsafe {
+            DeviceLocalBuffer::raw_with_memory_type(device,
+                                                    mem::size_of::<T>(),
+                                                    usage,
+                                                    queue_families,
+                                                    memory_type)
+        }
+    }
 }
 
 impl<T> DeviceLocalBuffer<[T]> {
@@ -94,6 +121,32 @@ impl<T> DeviceLocalBuffer<[T]> {
     {
         unsafe { DeviceLocalBuffer::raw(device, len * mem::size_of::<T>(), usage, queue_families) }
     }
+
+    /// Builds a new buffer whose memory is allocated from the given memory type. Can be used for
+    /// arrays.
+    ///
+    /// Returns an error if the memory type isn't device-local, or if the buffer can't be put in
+    /// memory of this type.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the memory type doesn't belong to the physical device of `device`.
+    ///
+    // TODO: unsafe because uninitialized data
+    #[inline]
+    pub fn array_with_memory_type<'a, I>(device: Arc<Device>, len: usize, usage: BufferUsage,
+                                         queue_families: I, memory_type: MemoryType)
+                                         -> Result<Arc<DeviceLocalBuffer<[T]>>, BufferCreationError>
+        where I: IntoIterator<Item = QueueFamily<'a>>
+    {
+        unsafe {
+            DeviceLocalBuffer::raw_with_memory_type(device,
+                                                    len * mem::size_of::<T>(),
+                                                    usage,
+                                                    queue_families,
+                                                    memory_type)
+        }
+    }
 }
 
 impl<T: ?Sized> DeviceLocalBuffer<T> {
@@ -107,6 +160,48 @@ impl<T: ?Sized> DeviceLocalBuffer<T> {
                              queue_families: I)
                              -> Result<Arc<DeviceLocalBuffer<T>>, OomError>
         where I: IntoIterator<Item = QueueFamily<'a>>
+    {
+        match DeviceLocalBuffer::raw_impl(device, size, usage, queue_families, None) {
+            Ok(b) => Ok(b),
+            Err(BufferCreationError::OomError(err)) => Err(err),
+            Err(_) => unreachable!(),        // We don't use sparse binding and choose the memory
+            // type ourselves, therefore the other errors can't happen
+        }
+    }
+
+    /// Builds a new buffer without checking the size, whose memory is allocated from the given
+    /// memory type.
+    ///
+    /// Returns `MemoryTypeNotDeviceLocal` if the memory type isn't device-local, and
+    /// `MemoryTypeNotAllowed` if the buffer can't be put in memory of this type.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the memory type doesn't belong to the physical device of `device`.
+    ///
+    /// # Safety
+    ///
+    /// You must ensure that the size that you pass is correct for `T`.
+    ///
+    pub unsafe fn raw_with_memory_type<'a, I>(device: Arc<Device>, size: usize,
+                                              usage: BufferUsage, queue_families: I,
+                                              memory_type: MemoryType)
+                                              -> Result<Arc<DeviceLocalBuffer<T>>,
+                                                        BufferCreationError>
+        where I: IntoIterator<Item = QueueFamily<'a>>
+    {
+        assert_eq!(memory_type.physical_device().internal_object(),
+                   device.physical_device().internal_object());
+        if !memory_type.is_device_local() {
+            return Err(BufferCreationError::MemoryTypeNotDeviceLocal);
+        }
+        DeviceLocalBuffer::raw_impl(device, size, usage, queue_families, Some(memory_type))
+    }
+
+    unsafe fn raw_impl<'a, I>(device: Arc<Device>, size: usize, usage: BufferUsage,
+                              queue_families: I, memory_type: Option<MemoryType>)
+                              -> Result<Arc<DeviceLocalBuffer<T>>, BufferCreationError>
+        where I: IntoIterator<Item = QueueFamily<'a>>
     {
         let queue_families = queue_families
             .into_iter()
@@ -120,25 +215,28 @@ impl<T: ?Sized> DeviceLocalBuffer<T> {
                 Sharing::Exclusive
             };
 
-            match UnsafeBuffer::new(device.clone(), size, usage, sharing, SparseLevel::none()) {
-                Ok(b) => b,
-                Err(BufferCreationError::OomError(err)) => return Err(err),
-                Err(_) => unreachable!(),        // We don't use sparse binding, therefore the other
-                // errors can't happen
-            }
+            UnsafeBuffer::new(device.clone(), size, usage, sharing, SparseLevel::none())?
         };
 
-        let mem_ty = {
-            let device_local = device
-                .physical_device()
-                .memory_types()
-                .filter(|t| (mem_reqs.memory_type_bits & (1 << t.id())) != 0)
-                .filter(|t| t.is_device_local());
-            let any = device
-                .physical_device()
-                .memory_types()
-                .filter(|t| (mem_reqs.memory_type_bits & (1 << t.id())) != 0);
-            device_local.chain(any).next().unwrap()
+        let mem_ty = match memory_type {
+            Some(ty) => {
+                if (mem_reqs.memory_type_bits & (1 << ty.id())) == 0 {
+                    return Err(BufferCreationError::MemoryTypeNotAllowed);
+                }
+                ty
+            },
+            None => {
+                let device_local = device
+                    .physical_device()
+                    .memory_types()
+                    .filter(|t| (mem_reqs.memory_type_bits & (1 << t.id())) != 0)
+                    .filter(|t| t.is_device_local());
+                let any = device
+                    .physical_device()
+                    .memory_types()
+                    .filter(|t| (mem_reqs.memory_type_bits & (1 << t.id())) != 0);
+                device_local.chain(any).next().unwrap()
+            },
         };
 
         let mem = MemoryPool::alloc(&Device::standard_pool(&device),
@@ -199,11 +297,6 @@ unsafe impl<T: ?Sized, A> BufferAccess for DeviceLocalBuffer<T, A>
         self.inner.size()
     }
 
-    #[inline]
-    fn conflict_key(&self, self_offset: usize, self_size: usize) -> u64 {
-        self.inner.key()
-    }
-
     #[inline]
     fn try_gpu_lock(&self, exclusive: bool, _: &Queue) -> Result<(), AccessError> {
         let mut lock = self.gpu_lock.lock().unwrap();
@@ -269,3 +362,84 @@ unsafe impl<T: ?Sized, A> TypedBufferAccess for DeviceLocalBuffer<T, A>
 {
     type Content = T;
 }
+
+#[cfg(test)]
+mod tests {
+    use buffer::BufferUsage;
+    use buffer::device_local::DeviceLocalBuffer;
+    use buffer::sys::BufferCreationError;
+    use buffer::sys::SparseLevel;
+    use buffer::sys::UnsafeBuffer;
+    use memory::pool::MemoryPoolAlloc;
+    use std::iter::Empty;
+    use std::mem;
+    use sync::Sharing;
+
+    #[test]
+    fn fixed_memory_type() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let (_, mem_reqs) = unsafe {
+            UnsafeBuffer::new(device.clone(),
+                              mem::size_of::<[u32; 4]>(),
+                              BufferUsage::all(),
+                              Sharing::Exclusive::<Empty<u32>>,
+                              SparseLevel::none())
+                .unwrap()
+        };
+
+        for memory_type in device.physical_device().memory_types() {
+            let allowed = (mem_reqs.memory_type_bits & (1 << memory_type.id())) != 0;
+
+            match DeviceLocalBuffer::<[u32; 4]>::with_memory_type(device.clone(),
+                                                                  BufferUsage::all(),
+                                                                  Some(queue.family()),
+                                                                  memory_type) {
+                Ok(buffer) => {
+                    assert!(allowed && memory_type.is_device_local());
+                    assert_eq!(buffer.memory.memory().memory_type().id(), memory_type.id());
+                },
+                Err(BufferCreationError::MemoryTypeNotDeviceLocal) => {
+                    assert!(!memory_type.is_device_local());
+                },
+                Err(BufferCreationError::MemoryTypeNotAllowed) => {
+                    assert!(!allowed && memory_type.is_device_local());
+                },
+                Err(_) => panic!(),
+            }
+        }
+    }
+
+    #[test]
+    fn array_fixed_memory_type() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let (_, mem_reqs) = unsafe {
+            UnsafeBuffer::new(device.clone(),
+                              16 * mem::size_of::<u32>(),
+                              BufferUsage::all(),
+                              Sharing::Exclusive::<Empty<u32>>,
+                              SparseLevel::none())
+                .unwrap()
+        };
+
+        for memory_type in device.physical_device().memory_types() {
+            let allowed = (mem_reqs.memory_type_bits & (1 << memory_type.id())) != 0;
+
+            match DeviceLocalBuffer::<[u32]>::array_with_memory_type(device.clone(),
+                                                                     16,
+                                                                     BufferUsage::all(),
+                                                                     Some(queue.family()),
+                                                                     memory_type) {
+                Ok(_) => assert!(allowed && memory_type.is_device_local()),
+                Err(BufferCreationError::MemoryTypeNotDeviceLocal) => {
+                    assert!(!memory_type.is_device_local());
+                },
+                Err(BufferCreationError::MemoryTypeNotAllowed) => {
+                    assert!(!allowed && memory_type.is_device_local());
+                },
+                Err(_) => panic!(),
+            }
+        }
+    }
+}
@@ -0,0 +1,571 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::iter;
+use std::mem;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use buffer::BufferUsage;
+use buffer::cpu_pool::CpuBufferPool;
+use buffer::cpu_pool::CpuBufferPoolSubbuffer;
+use buffer::traits::BufferAccess;
+use buffer::traits::BufferInner;
+use buffer::traits::TypedBufferAccess;
+use device::Device;
+use device::DeviceOwned;
+use device::Queue;
+use memory::pool::MemoryPool;
+use memory::pool::StdMemoryPool;
+use sync::AccessError;
+
+use OomError;
+
+/// Pool of uniform buffer data meant to be accessed with dynamic offsets.
+///
+/// When drawing many objects that each have their own uniform data, it is more efficient to put
+/// all the data in the same buffer and to bind it once with a dynamic uniform buffer descriptor,
+/// than to create one buffer and one descriptor set per object. Each object then selects its data
+/// by passing a different dynamic offset when drawing.
+///
+/// Dynamic offsets must be multiples of the `min_uniform_buffer_offset_alignment` limit of the
+/// device. The `DynamicUniformBuffer` takes care of this by putting its elements at a stride
+/// that is a multiple of this limit.
+///
+/// Elements are written in *chunks* of `chunk_len` elements. Each call to `push` writes to the
+/// current chunk and returns it alongside the offset of the element within it, until the chunk is
+/// full or until you call `finish_chunk`. You are expected to call `finish_chunk` once per frame,
+/// so that all the objects of a frame share the same chunk and therefore the same descriptor set.
+///
+/// # Example
+///
+/// ```ignore
+/// let buffer = DynamicUniformBuffer::<vs::ty::Data>::new(device.clone(), 128);
+///
+/// // At each frame:
+/// let pushed = objects.iter().map(|o| buffer.push(o.data()).unwrap()).collect::<Vec<_>>();
+/// buffer.finish_chunk();
+///
+/// // All the objects share the same chunk, as long as there are less than 128 of them.
+/// let set = simple_descriptor_set!(pipeline.clone(), 0, { data: pushed[0].0.clone() })?;
+/// let set = Arc::new(set);
+///
+/// for (object, &(_, offset)) in objects.iter().zip(pushed.iter()) {
+///     builder = builder.draw(pipeline.clone(), DynamicState::none(), object.vertices(),
+///                            DynamicOffsets::new(set.clone(), iter::once(offset)), ())?;
+/// }
+/// ```
+pub struct DynamicUniformBuffer<T, A = Arc<StdMemoryPool>>
+    where A: MemoryPool
+{
+    // Pool from which the chunks are allocated.
+    pool: CpuBufferPool<[T], A>,
+
+    // Distance in bytes between two elements of a chunk.
+    stride: usize,
+
+    // Number of elements in a chunk.
+    chunk_len: usize,
+
+    // Chunk that is being filled, and number of elements already written in it.
+    current: Mutex<Option<(DynamicUniformChunk<T, A>, usize)>>,
+}
+
+impl<T> DynamicUniformBuffer<T> {
+    /// Builds a new `DynamicUniformBuffer` whose chunks can hold `chunk_len` elements.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `chunk_len` is 0.
+    /// - Panics if `T` is zero-sized.
+    ///
+    pub fn new(device: Arc<Device>, chunk_len: usize) -> DynamicUniformBuffer<T> {
+        assert!(chunk_len >= 1);
+        assert_ne!(mem::size_of::<T>(), 0);
+
+        let alignment = device
+            .physical_device()
+            .limits()
+            .min_uniform_buffer_offset_alignment();
+        let stride = aligned_stride(mem::size_of::<T>(), alignment);
+
+        let pool = unsafe {
+            CpuBufferPool::raw(device, stride * chunk_len, BufferUsage::uniform_buffer(),
+                               iter::empty())
+        };
+
+        DynamicUniformBuffer {
+            pool: pool,
+            stride: stride,
+            chunk_len: chunk_len,
+            current: Mutex::new(None),
+        }
+    }
+}
+
+impl<T, A> DynamicUniformBuffer<T, A>
+    where A: MemoryPool
+{
+    /// Returns the distance in bytes between two elements, which is the size of `T` rounded up
+    /// to the `min_uniform_buffer_offset_alignment` limit.
+    #[inline]
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    /// Returns the number of elements that a chunk can hold.
+    #[inline]
+    pub fn chunk_len(&self) -> usize {
+        self.chunk_len
+    }
+
+    /// Writes `value` in the current chunk, and returns the chunk and the dynamic offset to use
+    /// in order to access `value` from a shader.
+    ///
+    /// If the current chunk is full or if `finish_chunk` has been called, a new chunk is grabbed
+    /// from the pool first.
+    ///
+    /// > **Note**: The current chunk is also replaced with a new one if a command buffer that
+    /// > uses it has been submitted, since the GPU may be reading it. Values pushed after the
+    /// > submission therefore never alter what the GPU reads, but they don't share the same
+    /// > chunk as the values pushed before.
+    pub fn push(&self, value: T) -> Result<(DynamicUniformChunk<T, A>, u32), OomError> {
+        let mut current = self.current.lock().unwrap();
+
+        let value = match *current {
+            Some((ref chunk, ref mut len)) if *len < self.chunk_len => {
+                let offset = *len * self.stride;
+                match chunk.inner.try_write_at(offset, value) {
+                    Ok(()) => {
+                        *len += 1;
+                        return Ok((chunk.clone(), offset as u32));
+                    },
+                    // The GPU may be reading the chunk.
+                    Err(value) => value,
+                }
+            },
+            _ => value,
+        };
+
+        let chunk = DynamicUniformChunk { inner: Arc::new(self.pool.next_uninit()?) };
+        match chunk.inner.try_write_at(0, value) {
+            Ok(()) => (),
+            Err(_) => unreachable!(), // The chunk is new and can't be GPU-locked.
+        }
+
+        *current = Some((chunk.clone(), 1));
+        Ok((chunk, 0))
+    }
+
+    /// Stops writing to the current chunk. The next call to `push` will use a new chunk.
+    #[inline]
+    pub fn finish_chunk(&self) {
+        *self.current.lock().unwrap() = None;
+    }
+}
+
+unsafe impl<T, A> DeviceOwned for DynamicUniformBuffer<T, A>
+    where A: MemoryPool
+{
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        self.pool.device()
+    }
+}
+
+/// A chunk of a `DynamicUniformBuffer`.
+///
+/// When used as a buffer, the chunk represents one element at the start of the chunk. This is
+/// the range that the dynamic offsets returned by `DynamicUniformBuffer::push` are relative to,
+/// and is what must be written in a dynamic uniform buffer descriptor.
+pub struct DynamicUniformChunk<T, A = Arc<StdMemoryPool>>
+    where A: MemoryPool
+{
+    inner: Arc<CpuBufferPoolSubbuffer<[T], A>>,
+}
+
+impl<T, A> Clone for DynamicUniformChunk<T, A>
+    where A: MemoryPool
+{
+    #[inline]
+    fn clone(&self) -> DynamicUniformChunk<T, A> {
+        DynamicUniformChunk { inner: self.inner.clone() }
+    }
+}
+
+unsafe impl<T, A> BufferAccess for DynamicUniformChunk<T, A>
+    where A: MemoryPool
+{
+    #[inline]
+    fn inner(&self) -> BufferInner {
+        self.inner.inner()
+    }
+
+    #[inline]
+    fn size(&self) -> usize {
+        mem::size_of::<T>()
+    }
+
+    #[inline]
+    fn try_gpu_lock(&self, exclusive_access: bool, queue: &Queue) -> Result<(), AccessError> {
+        self.inner.try_gpu_lock(exclusive_access, queue)
+    }
+
+    #[inline]
+    unsafe fn increase_gpu_lock(&self) {
+        self.inner.increase_gpu_lock()
+    }
+
+    #[inline]
+    unsafe fn unlock(&self) {
+        self.inner.unlock()
+    }
+}
+
+unsafe impl<T, A> TypedBufferAccess for DynamicUniformChunk<T, A>
+    where A: MemoryPool
+{
+    type Content = T;
+}
+
+unsafe impl<T, A> DeviceOwned for DynamicUniformChunk<T, A>
+    where A: MemoryPool
+{
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        self.inner.device()
+    }
+}
+
+// Rounds `size` up to a multiple of `alignment`.
+#[inline]
+fn aligned_stride(size: usize, alignment: usize) -> usize {
+    if alignment <= 1 {
+        return size;
+    }
+
+    (size + alignment - 1) / alignment * alignment
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CStr;
+    use std::iter;
+    use std::sync::Arc;
+
+    use buffer::BufferAccess;
+    use buffer::BufferUsage;
+    use buffer::CpuAccessibleBuffer;
+    use buffer::DynamicUniformBuffer;
+    use command_buffer::AutoCommandBufferBuilder;
+    use command_buffer::DynamicState;
+    use command_buffer::read_image_region;
+    use descriptor::descriptor::DescriptorBufferContentDesc;
+    use descriptor::descriptor::DescriptorBufferDesc;
+    use descriptor::descriptor::DescriptorDesc;
+    use descriptor::descriptor::DescriptorDescTy;
+    use descriptor::descriptor::ShaderStages;
+    use descriptor::descriptor_set::DynamicOffsets;
+    use descriptor::descriptor_set::SimpleDescriptorSetBufferExt;
+    use descriptor::descriptor_set::SimpleDescriptorSetBuilder;
+    use descriptor::pipeline_layout::EmptyPipelineDesc;
+    use descriptor::pipeline_layout::PipelineLayoutDesc;
+    use descriptor::pipeline_layout::PipelineLayoutDescNames;
+    use descriptor::pipeline_layout::PipelineLayoutDescPcRange;
+    use format::ClearValue;
+    use format::Format;
+    use framebuffer::Framebuffer;
+    use framebuffer::Subpass;
+    use image::AttachmentImage;
+    use image::ImageUsage;
+    use pipeline::GraphicsPipeline;
+    use pipeline::shader::EmptyShaderInterfaceDef;
+    use pipeline::shader::ShaderModule;
+    use pipeline::vertex::Vertex;
+    use pipeline::vertex::VertexMemberInfo;
+    use pipeline::viewport::Viewport;
+    use sync::now;
+    use tests::FULL_SCREEN_VS;
+    use super::aligned_stride;
+
+    #[test]
+    fn stride() {
+        assert_eq!(aligned_stride(16, 256), 256);
+        assert_eq!(aligned_stride(256, 256), 256);
+        assert_eq!(aligned_stride(257, 256), 512);
+        assert_eq!(aligned_stride(12, 1), 12);
+        assert_eq!(aligned_stride(12, 0), 12);
+    }
+
+    #[test]
+    fn hundred_colors_in_one_chunk() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let buffer = DynamicUniformBuffer::<[f32; 4]>::new(device.clone(), 100);
+        let alignment = device
+            .physical_device()
+            .limits()
+            .min_uniform_buffer_offset_alignment();
+        assert_eq!(buffer.stride() % alignment, 0);
+
+        let mut pushed = Vec::new();
+        for i in 0 .. 100 {
+            let color = [i as f32 / 100.0, 1.0 - i as f32 / 100.0, 0.5, 1.0];
+            pushed.push((buffer.push(color).unwrap(), color));
+        }
+
+        let first = (pushed[0].0).0.clone();
+        for (i, &((ref chunk, offset), color)) in pushed.iter().enumerate() {
+            // All the objects share the same chunk and therefore the same descriptor.
+            assert_eq!(chunk.inner().buffer.key(), first.inner().buffer.key());
+            assert_eq!(chunk.inner().offset, first.inner().offset);
+            assert_eq!(offset as usize, i * buffer.stride());
+            assert_eq!(offset as usize % alignment, 0);
+            assert_eq!(unsafe { *chunk.inner.access_at::<[f32; 4]>(offset as usize) }, color);
+        }
+
+        // Going above the length of a chunk or finishing it switches to a new chunk.
+        let (next, offset) = buffer.push([0.0; 4]).unwrap();
+        assert_eq!(offset, 0);
+        assert!(next.inner().buffer.key() != first.inner().buffer.key() ||
+                    next.inner().offset != first.inner().offset);
+
+        buffer.finish_chunk();
+        let (_, offset) = buffer.push([0.0; 4]).unwrap();
+        assert_eq!(offset, 0);
+    }
+
+    #[test]
+    fn draw_hundred_objects() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        // Object `i` has the color `(i, 255 - i, 0, 255)`, and is drawn on the pixel `(i, 0)`.
+        let buffer = DynamicUniformBuffer::<[f32; 4]>::new(device.clone(), 100);
+        let pushed = (0 .. 100)
+            .map(|i| {
+                     let color = [i as f32 / 255.0, (255 - i) as f32 / 255.0, 0.0, 1.0];
+                     buffer.push(color).unwrap()
+                 })
+            .collect::<Vec<_>>();
+
+        let vs = unsafe { ShaderModule::new(device.clone(), &FULL_SCREEN_VS) }.unwrap();
+        let fs = unsafe { ShaderModule::new(device.clone(), &UNIFORM_COLOR_FS) }.unwrap();
+        let (vs_main, fs_main) = unsafe {
+            let name = CStr::from_bytes_with_nul_unchecked(b"main\0");
+            (vs.vertex_shader_entry_point::<(), _, _, _>(name,
+                                                         EmptyShaderInterfaceDef,
+                                                         EmptyShaderInterfaceDef,
+                                                         EmptyPipelineDesc),
+             fs.fragment_shader_entry_point::<(), _, _, _>(name,
+                                                           EmptyShaderInterfaceDef,
+                                                           EmptyShaderInterfaceDef,
+                                                           DynamicUniformDesc))
+        };
+
+        let render_pass = Arc::new(single_pass_renderpass!(device.clone(),
+            attachments: {
+                color: { load: Clear, store: Store, format: Format::R8G8B8A8Unorm, samples: 1, }
+            },
+            pass: { color: [color], depth_stencil: {} }
+        ).unwrap());
+
+        let pipeline = Arc::new(GraphicsPipeline::start()
+                                    .vertex_input_single_buffer::<NoVertex>()
+                                    .vertex_shader(vs_main, ())
+                                    .triangle_list()
+                                    .viewports_dynamic_scissors_irrelevant(1)
+                                    .fragment_shader(fs_main, ())
+                                    .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+                                    .build(device.clone())
+                                    .unwrap());
+
+        let usage = ImageUsage {
+            transfer_source: true,
+            color_attachment: true,
+            ..ImageUsage::none()
+        };
+        let image = AttachmentImage::with_usage(device.clone(), [100, 1], Format::R8G8B8A8Unorm,
+                                                usage)
+            .unwrap();
+        let framebuffer = Arc::new(Framebuffer::start(render_pass.clone())
+                                       .add(image.clone())
+                                       .unwrap()
+                                       .build()
+                                       .unwrap());
+
+        let vertices = CpuAccessibleBuffer::from_iter(device.clone(),
+                                                      BufferUsage::all(),
+                                                      Some(queue.family()),
+                                                      (0 .. 3).map(|_| NoVertex { _dummy: 0 }))
+            .unwrap();
+
+        // All the objects share the same chunk, and therefore the same descriptor set.
+        let set = {
+            let builder = SimpleDescriptorSetBuilder::new(pipeline.clone(), 0);
+            Arc::new(SimpleDescriptorSetBufferExt::add_me(pushed[0].0.clone(), builder, "data")
+                         .unwrap()
+                         .build().unwrap())
+        };
+
+        let mut builder = AutoCommandBufferBuilder::new(device.clone(), queue.family())
+            .unwrap()
+            .begin_render_pass(framebuffer.clone(),
+                               false,
+                               vec![ClearValue::Float([0.0, 0.0, 0.0, 0.0])])
+            .unwrap();
+        for (i, &(ref chunk, offset)) in pushed.iter().enumerate() {
+            assert_eq!(chunk.inner().offset, pushed[0].0.inner().offset);
+
+            let dynamic = DynamicState {
+                viewports: Some(vec![Viewport {
+                                         origin: [i as f32, 0.0],
+                                         dimensions: [1.0, 1.0],
+                                         depth_range: 0.0 .. 1.0,
+                                     }]),
+                ..DynamicState::none()
+            };
+            builder = builder
+                .draw(pipeline.clone(),
+                      dynamic,
+                      vertices.clone(),
+                      DynamicOffsets::new(set.clone(), iter::once(offset)),
+                      ())
+                .unwrap();
+        }
+        let cb = builder.end_render_pass().unwrap().build().unwrap();
+
+        let draw = now(device.clone()).then_execute(queue.clone(), cb).unwrap();
+        let texels: Vec<[u8; 4]> =
+            read_image_region(draw, queue.clone(), image.clone(), [0, 0, 0], [100, 1, 1], 0, 0)
+                .unwrap();
+        for (i, texel) in texels.into_iter().enumerate() {
+            assert_eq!(texel, [i as u8, 255 - i as u8, 0, 255]);
+        }
+    }
+
+    #[test]
+    fn push_after_submission_uses_new_chunk() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let buffer = DynamicUniformBuffer::<[f32; 4]>::new(device.clone(), 100);
+        let (first, _) = buffer.push([1.0; 4]).unwrap();
+
+        // Simulates a submission that reads the chunk.
+        first.try_gpu_lock(false, &queue).unwrap();
+
+        let (second, offset) = buffer.push([2.0; 4]).unwrap();
+        assert_eq!(offset, 0);
+        assert!(second.inner().buffer.key() != first.inner().buffer.key() ||
+                    second.inner().offset != first.inner().offset);
+        assert_eq!(unsafe { *first.inner.access_at::<[f32; 4]>(0) }, [1.0; 4]);
+
+        // The new chunk is the one that is now being filled.
+        let (third, offset) = buffer.push([3.0; 4]).unwrap();
+        assert_eq!(offset as usize, buffer.stride());
+        assert_eq!(third.inner().offset, second.inner().offset);
+
+        unsafe {
+            first.unlock();
+        }
+    }
+
+    // Vertex type of a vertex buffer that isn't read by the vertex shader.
+    #[derive(Debug, Copy, Clone)]
+    struct NoVertex {
+        _dummy: u32,
+    }
+
+    unsafe impl Vertex for NoVertex {
+        #[inline]
+        fn member(_: &str) -> Option<VertexMemberInfo> {
+            None
+        }
+    }
+
+    // Pipeline layout with a dynamic uniform buffer named `data`.
+    #[derive(Debug, Copy, Clone)]
+    struct DynamicUniformDesc;
+
+    unsafe impl PipelineLayoutDesc for DynamicUniformDesc {
+        fn num_sets(&self) -> usize {
+            1
+        }
+
+        fn num_bindings_in_set(&self, set: usize) -> Option<usize> {
+            if set == 0 { Some(1) } else { None }
+        }
+
+        fn descriptor(&self, set: usize, binding: usize) -> Option<DescriptorDesc> {
+            if set != 0 || binding != 0 {
+                return None;
+            }
+
+            Some(DescriptorDesc {
+                     ty: DescriptorDescTy::Buffer(DescriptorBufferDesc {
+                                                      dynamic: Some(true),
+                                                      storage: false,
+                                                      content: DescriptorBufferContentDesc::F32,
+                                                  }),
+                     array_count: 1,
+                     stages: ShaderStages {
+                         fragment: true,
+                         ..ShaderStages::none()
+                     },
+                     readonly: true,
+                 })
+        }
+
+        fn num_push_constants_ranges(&self) -> usize {
+            0
+        }
+
+        fn push_constants_range(&self, _: usize) -> Option<PipelineLayoutDescPcRange> {
+            None
+        }
+    }
+
+    unsafe impl PipelineLayoutDescNames for DynamicUniformDesc {
+        fn descriptor_by_name(&self, name: &str) -> Option<(usize, usize)> {
+            if name == "data" { Some((0, 0)) } else { None }
+        }
+    }
+
+    /*
+        SPIR-V 1.0 module, assembled by hand.
+
+        layout(set = 0, binding = 0) uniform Data { vec4 color; } data;
+        layout(location = 0) out vec4 f_color;
+
+        void main() {
+            f_color = data.color;
+        }
+    */
+    const UNIFORM_COLOR_FS: [u8; 412] = [3, 2, 35, 7, 0, 0, 1, 0, 0, 0, 0, 0, 17, 0, 0, 0, 0, 0, 0,
+                                         0, 17, 0, 2, 0, 1, 0, 0, 0, 14, 0, 3, 0, 0, 0, 0, 0, 1, 0,
+                                         0, 0, 15, 0, 6, 0, 4, 0, 0, 0, 1, 0, 0, 0, 109, 97, 105,
+                                         110, 0, 0, 0, 0, 2, 0, 0, 0, 16, 0, 3, 0, 1, 0, 0, 0, 7, 0,
+                                         0, 0, 71, 0, 4, 0, 2, 0, 0, 0, 30, 0, 0, 0, 0, 0, 0, 0, 71,
+                                         0, 3, 0, 3, 0, 0, 0, 2, 0, 0, 0, 72, 0, 5, 0, 3, 0, 0, 0,
+                                         0, 0, 0, 0, 35, 0, 0, 0, 0, 0, 0, 0, 71, 0, 4, 0, 4, 0, 0,
+                                         0, 34, 0, 0, 0, 0, 0, 0, 0, 71, 0, 4, 0, 4, 0, 0, 0, 33, 0,
+                                         0, 0, 0, 0, 0, 0, 19, 0, 2, 0, 5, 0, 0, 0, 33, 0, 3, 0, 6,
+                                         0, 0, 0, 5, 0, 0, 0, 22, 0, 3, 0, 7, 0, 0, 0, 32, 0, 0, 0,
+                                         23, 0, 4, 0, 8, 0, 0, 0, 7, 0, 0, 0, 4, 0, 0, 0, 21, 0, 4,
+                                         0, 9, 0, 0, 0, 32, 0, 0, 0, 1, 0, 0, 0, 30, 0, 3, 0, 3, 0,
+                                         0, 0, 8, 0, 0, 0, 32, 0, 4, 0, 10, 0, 0, 0, 2, 0, 0, 0, 3,
+                                         0, 0, 0, 32, 0, 4, 0, 11, 0, 0, 0, 2, 0, 0, 0, 8, 0, 0, 0,
+                                         32, 0, 4, 0, 12, 0, 0, 0, 3, 0, 0, 0, 8, 0, 0, 0, 59, 0, 4,
+                                         0, 10, 0, 0, 0, 4, 0, 0, 0, 2, 0, 0, 0, 59, 0, 4, 0, 12, 0,
+                                         0, 0, 2, 0, 0, 0, 3, 0, 0, 0, 43, 0, 4, 0, 9, 0, 0, 0, 13,
+                                         0, 0, 0, 0, 0, 0, 0, 54, 0, 5, 0, 5, 0, 0, 0, 1, 0, 0, 0,
+                                         0, 0, 0, 0, 6, 0, 0, 0, 248, 0, 2, 0, 14, 0, 0, 0, 65, 0,
+                                         5, 0, 11, 0, 0, 0, 15, 0, 0, 0, 4, 0, 0, 0, 13, 0, 0, 0,
+                                         61, 0, 4, 0, 8, 0, 0, 0, 16, 0, 0, 0, 15, 0, 0, 0, 62, 0,
+                                         3, 0, 2, 0, 0, 0, 16, 0, 0, 0, 253, 0, 1, 0, 56, 0, 1, 0];
+}
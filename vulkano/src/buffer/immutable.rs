@@ -15,6 +15,10 @@
 //! copying from another buffer). Any subsequent command buffer **must** only read from the buffer,
 //! or a panic will happen.
 //!
+//! Until the initial upload has finished executing, the buffer can't be put in a descriptor set
+//! and can't be used by a command buffer, except after the upload in the same command buffer.
+//! Trying to do so returns an `UninitializedResource` error.
+//!
 //! The buffer will be stored in device-local memory if possible
 //!
 
@@ -328,8 +332,8 @@ unsafe impl<T: ?Sized, A> BufferAccess for ImmutableBuffer<T, A> {
     }
 
     #[inline]
-    fn conflict_key(&self, self_offset: usize, self_size: usize) -> u64 {
-        self.inner.key()
+    fn initialized(&self) -> bool {
+        self.initialized.load(Ordering::Relaxed)
     }
 
     #[inline]
@@ -383,11 +387,6 @@ unsafe impl<T: ?Sized, A> BufferAccess for ImmutableBufferInitialization<T, A> {
         self.buffer.size()
     }
 
-    #[inline]
-    fn conflict_key(&self, self_offset: usize, self_size: usize) -> u64 {
-        self.buffer.inner.key()
-    }
-
     #[inline]
     fn try_gpu_lock(&self, exclusive_access: bool, queue: &Queue) -> Result<(), AccessError> {
         if self.buffer.initialized.load(Ordering::Relaxed) {
@@ -440,6 +439,8 @@ mod tests {
     use buffer::immutable::ImmutableBuffer;
     use command_buffer::AutoCommandBufferBuilder;
     use command_buffer::CommandBuffer;
+    use command_buffer::CopyBufferError;
+    use command_buffer::synced::SyncCommandBufferBuilderError;
     use std::iter;
     use sync::GpuFuture;
 
@@ -531,7 +532,6 @@ mod tests {
     }
 
     #[test]
-    #[should_panic] // TODO: check Result error instead of panicking
     fn read_uninitialized_forbidden() {
         let (device, queue) = gfx_dev_and_queue!();
 
@@ -548,16 +548,15 @@ mod tests {
                                                  0)
             .unwrap();
 
-        let _ = AutoCommandBufferBuilder::new(device.clone(), queue.family())
+        let result = AutoCommandBufferBuilder::new(device.clone(), queue.family())
             .unwrap()
-            .copy_buffer(src, buffer)
-            .unwrap()
-            .build()
-            .unwrap()
-            .execute(queue.clone())
-            .unwrap()
-            .then_signal_fence_and_flush()
-            .unwrap();
+            .copy_buffer(src, buffer);
+
+        match result {
+            Err(CopyBufferError::SyncCommandBufferBuilderError(
+                SyncCommandBufferBuilderError::UninitializedResource)) => (),
+            _ => panic!(),
+        }
     }
 
     #[test]
@@ -592,8 +591,7 @@ mod tests {
     }
 
     #[test]
-    #[ignore] // TODO: doesn't work because the submit sync layer isn't properly implemented
-    fn init_then_read_same_future() {
+    fn init_then_read_other_cb_forbidden() {
         let (device, queue) = gfx_dev_and_queue!();
 
         let (buffer, init) = unsafe {
@@ -609,25 +607,60 @@ mod tests {
                                                  0)
             .unwrap();
 
-        let cb1 = AutoCommandBufferBuilder::new(device.clone(), queue.family())
+        let _cb1 = AutoCommandBufferBuilder::new(device.clone(), queue.family())
             .unwrap()
             .copy_buffer(src.clone(), init)
             .unwrap()
             .build()
             .unwrap();
 
-        let cb2 = AutoCommandBufferBuilder::new(device.clone(), queue.family())
+        // The upload hasn't been executed yet.
+        let result = AutoCommandBufferBuilder::new(device.clone(), queue.family())
             .unwrap()
-            .copy_buffer(buffer, src.clone())
+            .copy_buffer(buffer, src.clone());
+
+        match result {
+            Err(CopyBufferError::SyncCommandBufferBuilderError(
+                SyncCommandBufferBuilderError::UninitializedResource)) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn init_then_read_after_upload_finished() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let (buffer, init) = unsafe {
+            ImmutableBuffer::<u32>::uninitialized(device.clone(),
+                                                  BufferUsage::all(),
+                                                  iter::once(queue.family()))
+                .unwrap()
+        };
+
+        let src = CpuAccessibleBuffer::from_data(device.clone(),
+                                                 BufferUsage::all(),
+                                                 iter::once(queue.family()),
+                                                 0)
+            .unwrap();
+
+        AutoCommandBufferBuilder::new(device.clone(), queue.family())
+            .unwrap()
+            .copy_buffer(src.clone(), init)
             .unwrap()
             .build()
+            .unwrap()
+            .execute(queue.clone())
+            .unwrap()
+            .then_signal_fence_and_flush()
+            .unwrap()
+            .wait(None)
             .unwrap();
 
-        let _ = cb1.execute(queue.clone())
+        let _ = AutoCommandBufferBuilder::new(device.clone(), queue.family())
             .unwrap()
-            .then_execute(queue.clone(), cb2)
+            .copy_buffer(buffer, src.clone())
             .unwrap()
-            .then_signal_fence_and_flush()
+            .build()
             .unwrap();
     }
 
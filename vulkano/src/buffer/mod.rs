@@ -33,6 +33,9 @@
 //! `CpuAccessibleBuffer`. If you have data that is very rarely modified, you are encouraged to
 //! use an `ImmutableBuffer` or a `DeviceLocalBuffer` instead.
 //!
+//! Uploads to a `DeviceLocalBuffer` or to an image go through a host-visible buffer. If you upload
+//! data often, a `StagingArena` recycles these buffers instead of creating new ones every time.
+//!
 //! If you just want to get started, you can use the `CpuAccessibleBuffer` everywhere, as it is
 //! the most flexible type of buffer.
 //!
@@ -65,8 +68,11 @@
 pub use self::cpu_access::CpuAccessibleBuffer;
 pub use self::cpu_pool::CpuBufferPool;
 pub use self::device_local::DeviceLocalBuffer;
+pub use self::dynamic_uniform::DynamicUniformBuffer;
 pub use self::immutable::ImmutableBuffer;
 pub use self::slice::BufferSlice;
+pub use self::staging::StagingArena;
+pub use self::staging::StagingBuffer;
 pub use self::sys::BufferCreationError;
 pub use self::traits::BufferAccess;
 pub use self::traits::BufferInner;
@@ -78,7 +84,9 @@ pub use self::view::BufferViewRef;
 pub mod cpu_access;
 pub mod cpu_pool;
 pub mod device_local;
+pub mod dynamic_uniform;
 pub mod immutable;
+pub mod staging;
 pub mod sys;
 pub mod view;
 
@@ -0,0 +1,521 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Recycling of the staging buffers used to upload data to the GPU.
+//!
+//! Uploading data to a device-local buffer or to an image requires copying it to a host-visible
+//! buffer first. Creating a new `CpuAccessibleBuffer` for each upload allocates and frees memory
+//! all the time, which is a measurable overhead for code that uploads constantly, such as texture
+//! streaming. A `StagingArena` keeps the buffers that are no longer in use and hands them out
+//! again for the next uploads.
+//!
+//! # Example
+//!
+//! ```ignore
+//! let arena = StagingArena::new(device.clone());
+//!
+//! // For each tile of the texture that has been streamed in:
+//! let staging = arena.upload_iter(tile.pixels().iter().cloned())?;
+//! builder = builder.copy_buffer_to_image_dimensions(staging, texture.clone(), tile.offset(),
+//!                                                   tile.dimensions(), 0, 1, 0)?;
+//! ```
+//!
+//! # Size classes
+//!
+//! Buffers are sorted by size class. The size of a class is a power of two, and an upload gets a
+//! buffer of the smallest class that can hold it. The smallest class is 256 bytes.
+//!
+//! # Recycling
+//!
+//! A `StagingBuffer` returns its memory to the arena when it is destroyed. Since command buffers
+//! keep the buffers they use alive, this happens once the command buffer that performs the
+//! upload has been destroyed, which in turn happens once the future of its submission has
+//! completed and has been cleaned up.
+//!
+//! The arena keeps the buffers that have been returned to it, up to a total size that is 64 MiB
+//! by default and that can be chosen with `with_max_free_size`. The buffers that would exceed
+//! this size are freed instead of being kept. Call `trim` to free all the buffers that are kept,
+//! for example after a loading screen.
+
+use std::cmp;
+use std::collections::HashMap;
+use std::iter;
+use std::marker::PhantomData;
+use std::mem;
+use std::ptr;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+
+use buffer::BufferUsage;
+use buffer::sys::BufferCreationError;
+use buffer::sys::SparseLevel;
+use buffer::sys::UnsafeBuffer;
+use buffer::traits::BufferAccess;
+use buffer::traits::BufferInner;
+use buffer::traits::TypedBufferAccess;
+use device::Device;
+use device::DeviceOwned;
+use device::Queue;
+use memory::Content;
+use memory::pool::AllocLayout;
+use memory::pool::MemoryPool;
+use memory::pool::MemoryPoolAlloc;
+use memory::pool::StdMemoryPool;
+use sync::AccessError;
+use sync::Sharing;
+
+use OomError;
+
+// Size in bytes of the smallest size class. Must be a power of two.
+const MIN_CLASS_SIZE: usize = 256;
+
+// Default maximum total size in bytes of the buffers that an arena keeps for reuse.
+const DEFAULT_MAX_FREE_SIZE: usize = 64 * 1024 * 1024;
+
+/// Pool of host-visible buffers that are recycled between uploads.
+///
+/// See [the module-level documentation](index.html) for more information.
+///
+/// # Arc-like
+///
+/// The `StagingArena` struct internally contains an `Arc`. You can clone the `StagingArena` for
+/// a cheap cost, and all the clones will share the same buffers.
+pub struct StagingArena<A = Arc<StdMemoryPool>>
+    where A: MemoryPool
+{
+    inner: Arc<ArenaInner<A>>,
+}
+
+struct ArenaInner<A>
+    where A: MemoryPool
+{
+    // The device of the arena.
+    device: Arc<Device>,
+
+    // The memory pool to use for allocations.
+    pool: A,
+
+    // Buffers that aren't in use.
+    free: Mutex<FreeBlocks<A>>,
+
+    // Maximum value of `FreeBlocks::size`.
+    max_free_size: usize,
+}
+
+// Buffers of an arena that aren't in use.
+struct FreeBlocks<A>
+    where A: MemoryPool
+{
+    // The buffers, by size class.
+    blocks: HashMap<u32, Vec<Block<A>>>,
+
+    // Total size in bytes of the buffers.
+    size: usize,
+}
+
+// A buffer of the arena and its memory.
+struct Block<A>
+    where A: MemoryPool
+{
+    inner: UnsafeBuffer,
+    memory: A::Alloc,
+    class: u32,
+}
+
+/// A buffer obtained from a `StagingArena`.
+///
+/// When this object is destroyed, the buffer is given back to the arena.
+pub struct StagingBuffer<T: ?Sized, A = Arc<StdMemoryPool>>
+    where A: MemoryPool
+{
+    // Always `Some`, except during the destructor.
+    block: Option<Block<A>>,
+
+    arena: Arc<ArenaInner<A>>,
+
+    // Size in bytes of the content. The buffer itself can be larger.
+    size: usize,
+
+    // Number of times this buffer has been GPU-locked.
+    num_gpu_accesses: AtomicUsize,
+
+    // Necessary to make it compile.
+    marker: PhantomData<Box<T>>,
+}
+
+impl StagingArena {
+    /// Builds a new empty arena.
+    ///
+    /// The buffers can only be used as transfer sources and with exclusive queue family accesses.
+    #[inline]
+    pub fn new(device: Arc<Device>) -> StagingArena {
+        StagingArena::with_max_free_size(device, DEFAULT_MAX_FREE_SIZE)
+    }
+
+    /// Same as `new`, but the buffers that are kept for reuse never total more than
+    /// `max_free_size` bytes.
+    pub fn with_max_free_size(device: Arc<Device>, max_free_size: usize) -> StagingArena {
+        let pool = Device::standard_pool(&device);
+
+        StagingArena {
+            inner: Arc::new(ArenaInner {
+                                device: device,
+                                pool: pool,
+                                free: Mutex::new(FreeBlocks {
+                                                     blocks: HashMap::new(),
+                                                     size: 0,
+                                                 }),
+                                max_free_size: max_free_size,
+                            }),
+        }
+    }
+}
+
+impl<A> StagingArena<A>
+    where A: MemoryPool
+{
+    /// Grabs a buffer from the arena and writes `data` in it.
+    pub fn upload<T>(&self, data: T) -> Result<StagingBuffer<T, A>, BufferCreationError>
+        where T: Content + 'static
+    {
+        unsafe {
+            let buffer = self.next(mem::size_of::<T>())?;
+            ptr::write(&mut *buffer.mapping::<T>(), data);
+            Ok(buffer)
+        }
+    }
+
+    /// Grabs a buffer from the arena and writes the elements of `data` in it.
+    pub fn upload_iter<T, I>(&self, data: I) -> Result<StagingBuffer<[T], A>, BufferCreationError>
+        where I: ExactSizeIterator<Item = T>,
+              T: Content + 'static
+    {
+        unsafe {
+            let size = match data.len().checked_mul(mem::size_of::<T>()) {
+                Some(s) => s,
+                None => return Err(OomError::OutOfDeviceMemory.into()),
+            };
+
+            let buffer = self.next(size)?;
+
+            {
+                let mut mapping = buffer.mapping::<[T]>();
+                for (o, i) in mapping.iter_mut().zip(data) {
+                    ptr::write(o, i);
+                }
+            }
+
+            Ok(buffer)
+        }
+    }
+
+    /// Returns the number of buffers that are waiting in the arena to be reused.
+    pub fn num_free_buffers(&self) -> usize {
+        let free = self.inner.free.lock().unwrap();
+        free.blocks.values().map(|blocks| blocks.len()).sum()
+    }
+
+    /// Returns the total size in bytes of the buffers that are waiting in the arena to be reused.
+    #[inline]
+    pub fn free_size(&self) -> usize {
+        self.inner.free.lock().unwrap().size
+    }
+
+    /// Frees the buffers that aren't in use.
+    ///
+    /// The buffers that are still in use are not affected and are returned to the arena as
+    /// usual once they are destroyed.
+    pub fn trim(&self) {
+        let mut free = self.inner.free.lock().unwrap();
+        free.blocks.clear();
+        free.size = 0;
+    }
+
+    // Grabs a buffer that can hold `size` bytes, reusing a free one if possible. Its content is
+    // uninitialized.
+    fn next<T: ?Sized>(&self, size: usize) -> Result<StagingBuffer<T, A>, BufferCreationError> {
+        let class = match size_class(size) {
+            Some(c) => c,
+            None => return Err(OomError::OutOfDeviceMemory.into()),
+        };
+
+        let reused = {
+            let mut free = self.inner.free.lock().unwrap();
+            let block = free.blocks.get_mut(&class).and_then(|blocks| blocks.pop());
+            if block.is_some() {
+                free.size -= 1 << class;
+            }
+            block
+        };
+
+        let block = match reused {
+            Some(block) => block,
+            None => self.inner.new_block(class)?,
+        };
+
+        Ok(StagingBuffer {
+               block: Some(block),
+               arena: self.inner.clone(),
+               size: size,
+               num_gpu_accesses: AtomicUsize::new(0),
+               marker: PhantomData,
+           })
+    }
+}
+
+impl<A> ArenaInner<A>
+    where A: MemoryPool
+{
+    // Creates a new buffer for the given size class.
+    fn new_block(&self, class: u32) -> Result<Block<A>, BufferCreationError> {
+        unsafe {
+            let (buffer, mem_reqs) = {
+                let sharing: Sharing<iter::Empty<u32>> = Sharing::Exclusive;
+                UnsafeBuffer::new(self.device.clone(),
+                                  1 << class,
+                                  BufferUsage::transfer_source(),
+                                  sharing,
+                                  SparseLevel::none())?
+            };
+
+            let mem_ty = self.device
+                .physical_device()
+                .memory_types()
+                .filter(|t| (mem_reqs.memory_type_bits & (1 << t.id())) != 0)
+                .filter(|t| t.is_host_visible())
+                .next()
+                .unwrap(); // Vk specs guarantee that this can't fail
+
+            let mem = MemoryPool::alloc(&self.pool,
+                                        mem_ty,
+                                        mem_reqs.size,
+                                        mem_reqs.alignment,
+                                        AllocLayout::Linear)?;
+            debug_assert!((mem.offset() % mem_reqs.alignment) == 0);
+            debug_assert!(mem.mapped_memory().is_some());
+            buffer.bind_memory(mem.memory(), mem.offset())?;
+
+            Ok(Block {
+                   inner: buffer,
+                   memory: mem,
+                   class: class,
+               })
+        }
+    }
+}
+
+// Returns the log2 of the size of the smallest class that can hold `size` bytes, or `None` on
+// overflow.
+fn size_class(size: usize) -> Option<u32> {
+    cmp::max(size, MIN_CLASS_SIZE)
+        .checked_next_power_of_two()
+        .map(|s| s.trailing_zeros())
+}
+
+impl<A> Clone for StagingArena<A>
+    where A: MemoryPool
+{
+    #[inline]
+    fn clone(&self) -> StagingArena<A> {
+        StagingArena { inner: self.inner.clone() }
+    }
+}
+
+unsafe impl<A> DeviceOwned for StagingArena<A>
+    where A: MemoryPool
+{
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        &self.inner.device
+    }
+}
+
+impl<T: ?Sized, A> StagingBuffer<T, A>
+    where A: MemoryPool
+{
+    // Grants CPU access to the content of the buffer.
+    //
+    // The caller must ensure that the GPU isn't accessing the buffer.
+    unsafe fn mapping<U: ?Sized>(&self) -> ::memory::CpuAccess<U>
+        where U: Content
+    {
+        let memory = &self.block.as_ref().unwrap().memory;
+        let offset = memory.offset();
+        memory
+            .mapped_memory()
+            .unwrap()
+            .read_write(offset .. offset + self.size)
+    }
+}
+
+unsafe impl<T: ?Sized, A> BufferAccess for StagingBuffer<T, A>
+    where A: MemoryPool
+{
+    #[inline]
+    fn inner(&self) -> BufferInner {
+        BufferInner {
+            buffer: &self.block.as_ref().unwrap().inner,
+            offset: 0,
+        }
+    }
+
+    #[inline]
+    fn size(&self) -> usize {
+        self.size
+    }
+
+    #[inline]
+    fn try_gpu_lock(&self, _: bool, _: &Queue) -> Result<(), AccessError> {
+        if self.num_gpu_accesses.compare_and_swap(0, 1, Ordering::SeqCst) != 0 {
+            return Err(AccessError::AlreadyInUse);
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    unsafe fn increase_gpu_lock(&self) {
+        let num_usages = self.num_gpu_accesses.fetch_add(1, Ordering::SeqCst);
+        debug_assert!(num_usages >= 1);
+    }
+
+    #[inline]
+    unsafe fn unlock(&self) {
+        let was_in_use = self.num_gpu_accesses.fetch_sub(1, Ordering::SeqCst);
+        debug_assert!(was_in_use >= 1);
+    }
+}
+
+impl<T: ?Sized, A> Drop for StagingBuffer<T, A>
+    where A: MemoryPool
+{
+    fn drop(&mut self) {
+        let block = self.block.take().unwrap();
+        let block_size = 1 << block.class;
+
+        let mut free = self.arena.free.lock().unwrap();
+        if free.size + block_size > self.arena.max_free_size {
+            // The block is freed when it goes out of scope.
+            return;
+        }
+
+        free.size += block_size;
+        free.blocks.entry(block.class).or_insert_with(Vec::new).push(block);
+    }
+}
+
+unsafe impl<T: ?Sized, A> TypedBufferAccess for StagingBuffer<T, A>
+    where A: MemoryPool
+{
+    type Content = T;
+}
+
+unsafe impl<T: ?Sized, A> DeviceOwned for StagingBuffer<T, A>
+    where A: MemoryPool
+{
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        &self.arena.device
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buffer::BufferAccess;
+    use buffer::staging::StagingArena;
+    use buffer::staging::size_class;
+
+    use VulkanObject;
+
+    #[test]
+    fn size_classes() {
+        assert_eq!(size_class(0), Some(8));
+        assert_eq!(size_class(1), Some(8));
+        assert_eq!(size_class(256), Some(8));
+        assert_eq!(size_class(257), Some(9));
+        assert_eq!(size_class(1 << 20), Some(20));
+        assert_eq!(size_class(!0), None);
+    }
+
+    #[test]
+    fn buffers_are_reused() {
+        let (device, _) = gfx_dev_and_queue!();
+        let arena = StagingArena::new(device);
+
+        let buffer = arena.upload_iter(0 .. 300u32).unwrap();
+        assert_eq!(buffer.size(), 1200);
+        assert_eq!(arena.num_free_buffers(), 0);
+        drop(buffer);
+        assert_eq!(arena.num_free_buffers(), 1);
+
+        // Same size class as the previous buffer.
+        let buffer = arena.upload_iter(0 .. 400u32).unwrap();
+        assert_eq!(arena.num_free_buffers(), 0);
+
+        // Different size class.
+        let other = arena.upload(5u8).unwrap();
+        drop(buffer);
+        drop(other);
+        assert_eq!(arena.num_free_buffers(), 2);
+
+        arena.trim();
+        assert_eq!(arena.num_free_buffers(), 0);
+    }
+
+    #[test]
+    fn reused_buffer_content() {
+        let (device, _) = gfx_dev_and_queue!();
+        let arena = StagingArena::new(device);
+
+        let buffer = arena.upload_iter(0 .. 300u32).unwrap();
+        let handle = buffer.inner().buffer.internal_object();
+        drop(buffer);
+
+        // The same buffer is handed out again, with the new content and size.
+        let buffer = arena.upload_iter((0 .. 200u32).map(|n| n * 2)).unwrap();
+        assert_eq!(buffer.inner().buffer.internal_object(), handle);
+        assert_eq!(buffer.size(), 800);
+        unsafe {
+            let mapping = buffer.mapping::<[u32]>();
+            assert_eq!(mapping.len(), 200);
+            assert!(mapping.iter().enumerate().all(|(n, &v)| v == n as u32 * 2));
+        }
+    }
+
+    #[test]
+    fn max_free_size() {
+        let (device, _) = gfx_dev_and_queue!();
+        let arena = StagingArena::with_max_free_size(device, 4096);
+
+        // Buffers of 256, 1024, 2048 and 4096 bytes.
+        let buffers = (arena.upload([0u8; 256]).unwrap(),
+                       arena.upload([0u8; 1024]).unwrap(),
+                       arena.upload([0u8; 2048]).unwrap(),
+                       arena.upload([0u8; 4096]).unwrap());
+
+        drop(buffers.0);
+        drop(buffers.1);
+        drop(buffers.2);
+        assert_eq!(arena.num_free_buffers(), 3);
+        assert_eq!(arena.free_size(), 256 + 1024 + 2048);
+
+        // Keeping this buffer would exceed the limit, so it is freed instead.
+        drop(buffers.3);
+        assert_eq!(arena.num_free_buffers(), 3);
+        assert_eq!(arena.free_size(), 256 + 1024 + 2048);
+
+        // Reusing a buffer makes room for another one.
+        let buffer = arena.upload([0u8; 2048]).unwrap();
+        assert_eq!(arena.free_size(), 256 + 1024);
+        drop(buffer);
+        assert_eq!(arena.free_size(), 256 + 1024 + 2048);
+    }
+}
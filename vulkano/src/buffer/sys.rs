@@ -116,7 +116,8 @@ impl UnsafeBuffer {
             check_errors(vk.CreateBuffer(device.internal_object(),
                                          &infos,
                                          ptr::null(),
-                                         &mut output))?;
+                                         &mut output),
+                         "vkCreateBuffer")?;
             output
         };
 
@@ -137,17 +138,17 @@ impl UnsafeBuffer {
             let limits = device.physical_device().limits();
             if usage.uniform_texel_buffer || usage.storage_texel_buffer {
                 output.alignment = align(output.alignment,
-                                         limits.min_texel_buffer_offset_alignment() as usize);
+                                         limits.min_texel_buffer_offset_alignment());
             }
 
             if usage.storage_buffer {
                 output.alignment = align(output.alignment,
-                                         limits.min_storage_buffer_offset_alignment() as usize);
+                                         limits.min_storage_buffer_offset_alignment());
             }
 
             if usage.uniform_buffer {
                 output.alignment = align(output.alignment,
-                                         limits.min_uniform_buffer_offset_alignment() as usize);
+                                         limits.min_uniform_buffer_offset_alignment());
             }
 
             output
@@ -181,20 +182,21 @@ impl UnsafeBuffer {
         {
             let limits = self.device().physical_device().limits();
             if self.usage_uniform_texel_buffer() || self.usage_storage_texel_buffer() {
-                debug_assert!(offset % limits.min_texel_buffer_offset_alignment() as usize == 0);
+                debug_assert!(offset % limits.min_texel_buffer_offset_alignment() == 0);
             }
             if self.usage_storage_buffer() {
-                debug_assert!(offset % limits.min_storage_buffer_offset_alignment() as usize == 0);
+                debug_assert!(offset % limits.min_storage_buffer_offset_alignment() == 0);
             }
             if self.usage_uniform_buffer() {
-                debug_assert!(offset % limits.min_uniform_buffer_offset_alignment() as usize == 0);
+                debug_assert!(offset % limits.min_uniform_buffer_offset_alignment() == 0);
             }
         }
 
         check_errors(vk.BindBufferMemory(self.device.internal_object(),
                                          self.buffer,
                                          memory.internal_object(),
-                                         offset as vk::DeviceSize))?;
+                                         offset as vk::DeviceSize),
+                     "vkBindBufferMemory")?;
         Ok(())
     }
 
@@ -333,6 +335,10 @@ pub enum BufferCreationError {
     SparseResidencyBufferFeatureNotEnabled,
     /// Sparse aliasing was requested but the corresponding feature wasn't enabled.
     SparseResidencyAliasedFeatureNotEnabled,
+    /// The memory type that was requested isn't in the memory types supported by the buffer.
+    MemoryTypeNotAllowed,
+    /// The memory type that was requested doesn't have the device-local property.
+    MemoryTypeNotDeviceLocal,
 }
 
 impl error::Error for BufferCreationError {
@@ -349,6 +355,13 @@ impl error::Error for BufferCreationError {
             BufferCreationError::SparseResidencyAliasedFeatureNotEnabled => {
                 "sparse aliasing was requested but the corresponding feature wasn't enabled"
             },
+            BufferCreationError::MemoryTypeNotAllowed => {
+                "the memory type that was requested isn't in the memory types supported by the \
+                 buffer"
+            },
+            BufferCreationError::MemoryTypeNotDeviceLocal => {
+                "the memory type that was requested doesn't have the device-local property"
+            },
         }
     }
 
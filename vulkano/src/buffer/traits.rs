@@ -18,7 +18,6 @@ use memory::Content;
 use sync::AccessError;
 
 use SafeDeref;
-use VulkanObject;
 
 /// Trait for objects that represent a way for the GPU to have access to a buffer or a slice of a
 /// buffer.
@@ -94,29 +93,27 @@ pub unsafe trait BufferAccess: DeviceOwned {
     ///
     /// If this function returns `false`, this means that we are allowed to access the offset/size
     /// of `self` at the same time as the offset/size of `other` without causing a data race.
+    ///
+    /// The default implementation compares the underlying `UnsafeBuffer` objects returned by
+    /// `inner()` and the ranges of bytes within them. Two different objects that wrap around the
+    /// same buffer, such as a buffer and a slice of it, are therefore correctly detected as
+    /// conflicting.
     fn conflicts_buffer(&self, self_offset: usize, self_size: usize, other: &BufferAccess,
                         other_offset: usize, other_size: usize)
                         -> bool {
-        // TODO: should we really provide a default implementation?
-
         debug_assert!(self_size <= self.size());
 
-        if self.inner().buffer.internal_object() != other.inner().buffer.internal_object() {
-            return false;
-        }
-
-        let self_offset = self_offset + self.inner().offset;
-        let other_offset = other_offset + other.inner().offset;
+        let self_inner = self.inner();
+        let other_inner = other.inner();
 
-        if self_offset < other_offset && self_offset + self_size <= other_offset {
+        if self_inner.buffer.key() != other_inner.buffer.key() {
             return false;
         }
 
-        if other_offset < self_offset && other_offset + other_size <= self_offset {
-            return false;
-        }
+        let self_offset = self_offset + self_inner.offset;
+        let other_offset = other_offset + other_inner.offset;
 
-        true
+        self_offset < other_offset + other_size && other_offset < self_offset + self_size
     }
 
     /// Returns true if an access to `self` (as defined by `self_offset` and `self_size`)
@@ -125,15 +122,15 @@ pub unsafe trait BufferAccess: DeviceOwned {
     ///
     /// If this function returns `false`, this means that we are allowed to access the offset/size
     /// of `self` at the same time as the offset/size of `other` without causing a data race.
+    ///
+    /// The default implementation always returns `false`, as buffers and images never share
+    /// memory unless you implement these traits yourself.
+    #[inline]
     fn conflicts_image(&self, self_offset: usize, self_size: usize, other: &ImageAccess,
                        other_first_layer: u32, other_num_layers: u32, other_first_mipmap: u32,
                        other_num_mipmaps: u32)
                        -> bool {
-        let other_key = other.conflict_key(other_first_layer,
-                                           other_num_layers,
-                                           other_first_mipmap,
-                                           other_num_mipmaps);
-        self.conflict_key(self_offset, self_size) == other_key
+        false
     }
 
     /// Returns a key that uniquely identifies the range given by offset/size.
@@ -147,9 +144,12 @@ pub unsafe trait BufferAccess: DeviceOwned {
     /// Since it is possible to accidentally return the same key for memory ranges that don't
     /// overlap, the `conflicts_buffer` or `conflicts_image` function should always be called to
     /// verify whether they actually overlap.
+    ///
+    /// The default implementation returns the key of the underlying `UnsafeBuffer`, which is the
+    /// same for all the ranges of a buffer.
+    #[inline]
     fn conflict_key(&self, self_offset: usize, self_size: usize) -> u64 {
-        // FIXME: remove implementation
-        unimplemented!()
+        self.inner().buffer.key()
     }
 
     /// Shortcut for `conflicts_buffer` that compares the whole buffer to another.
@@ -176,6 +176,18 @@ pub unsafe trait BufferAccess: DeviceOwned {
         self.conflict_key(0, self.size())
     }
 
+    /// Returns false if the content of the buffer hasn't been initialized yet.
+    ///
+    /// An uninitialized buffer can't be put in a descriptor set, and a command buffer can only
+    /// use it after a previous command of the same command buffer has used it. This is how
+    /// `ImmutableBuffer` prevents reading its content before the initial upload has finished.
+    ///
+    /// The default implementation returns true.
+    #[inline]
+    fn initialized(&self) -> bool {
+        true
+    }
+
     /// Locks the resource for usage on the GPU. Returns an error if the lock can't be acquired.
     ///
     /// This function exists to prevent the user from causing a data race by reading and writing
@@ -240,6 +252,11 @@ unsafe impl<T> BufferAccess for T
         (**self).conflict_key(self_offset, self_size)
     }
 
+    #[inline]
+    fn initialized(&self) -> bool {
+        (**self).initialized()
+    }
+
     #[inline]
     fn try_gpu_lock(&self, exclusive_access: bool, queue: &Queue) -> Result<(), AccessError> {
         (**self).try_gpu_lock(exclusive_access, queue)
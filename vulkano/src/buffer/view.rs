@@ -161,7 +161,8 @@ impl<F, B> BufferView<F, B>
             check_errors(vk.CreateBufferView(device.internal_object(),
                                              &infos,
                                              ptr::null(),
-                                             &mut output))?;
+                                             &mut output),
+                         "vkCreateBufferView")?;
             (output, format_props)
         };
 
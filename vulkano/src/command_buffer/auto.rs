@@ -11,21 +11,30 @@ use std::error;
 use std::fmt;
 use std::iter;
 use std::mem;
+use std::ops::Range;
 use std::slice;
 use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::time::Instant;
 
 use OomError;
+use VulkanObject;
 use buffer::BufferAccess;
+use buffer::CpuAccessibleBuffer;
 use buffer::TypedBufferAccess;
+use buffer::cpu_access::ReadLockError;
 use command_buffer::CommandBuffer;
 use command_buffer::CommandBufferExecError;
 use command_buffer::DrawIndirectCommand;
 use command_buffer::DynamicState;
 use command_buffer::StateCacher;
 use command_buffer::StateCacherOutcome;
+use command_buffer::indirect_commands::IndirectCommandsLayout;
 use command_buffer::pool::CommandPoolBuilderAlloc;
 use command_buffer::pool::standard::StandardCommandPoolAlloc;
 use command_buffer::pool::standard::StandardCommandPoolBuilder;
+use command_buffer::synced::DebugLabelScope;
 use command_buffer::synced::SyncCommandBuffer;
 use command_buffer::synced::SyncCommandBufferBuilder;
 use command_buffer::synced::SyncCommandBufferBuilderError;
@@ -37,19 +46,35 @@ use command_buffer::sys::UnsafeCommandBufferBuilderImageAspect;
 use command_buffer::validity::*;
 use descriptor::descriptor_set::DescriptorSetsCollection;
 use descriptor::pipeline_layout::PipelineLayoutAbstract;
+use descriptor::pipeline_layout::PipelineLayoutPushConstantsCompatible;
 use device::Device;
 use device::DeviceOwned;
 use device::Queue;
+use format::ClearValue;
 use framebuffer::FramebufferAbstract;
+use framebuffer::IncompatibleRenderPassAttachmentError;
+use framebuffer::IncompatibleRenderPassError;
+use framebuffer::RenderPassAbstract;
 use framebuffer::RenderPassDescClearValues;
 use framebuffer::SubpassContents;
+use framebuffer::ensure_image_view_compatible;
+use framebuffer::ensure_render_pass_compatible;
 use image::ImageAccess;
 use image::ImageLayout;
+use image::ImageViewAccess;
 use instance::QueueFamily;
+use instrumentation::CommandBufferBuildStats;
+use instrumentation::SubmissionObserver;
 use pipeline::ComputePipelineAbstract;
 use pipeline::GraphicsPipelineAbstract;
+use pipeline::depth_stencil::DynamicStencilValue;
+use pipeline::depth_stencil::StencilFaceFlags;
 use pipeline::input_assembly::Index;
+use pipeline::shader_object::ShaderObject;
+use pipeline::shader_object::ShaderObjectStage;
+use pipeline::shader_object::ShaderObjectState;
 use pipeline::vertex::VertexSource;
+use query::UnsafeQueryPool;
 use sync::AccessCheckError;
 use sync::AccessFlagBits;
 use sync::GpuFuture;
@@ -65,36 +90,106 @@ use sync::PipelineStages;
 pub struct AutoCommandBufferBuilder<P = StandardCommandPoolBuilder> {
     inner: SyncCommandBufferBuilder<P>,
     state_cacher: StateCacher,
+    // Results of the validation of the descriptor sets that have been used so far.
+    descriptor_sets_cache: DescriptorSetsValidityCache,
     // Contains the number of subpasses remaining in the current render pass, or `None` if we're
     // outside a render pass. If this is `Some(0)`, the user must call `end_render_pass`. If this
     // is `Some(1)` or more, the user must call `next_subpass`.
     subpasses_remaining: Option<usize>,
+    // The render pass of the current render pass instance and the index of the current subpass,
+    // used to check that the pipelines passed to the draw commands are compatible with them.
+    render_pass: Option<(Arc<RenderPassAbstract + Send + Sync>, u32)>,
+    // True if we are between `begin_rendering` and `end_rendering`.
+    rendering: bool,
     // True if we are a secondary command buffer.
     secondary_cb: bool,
+    // True if a vertex shader object is bound and no graphics pipeline has been bound since.
+    graphics_shaders_bound: bool,
+    // How the command buffer is going to be submitted.
+    flags: Flags,
+    // Observer registered on the device when the builder was created, alongside the queue family
+    // and the time at which the building started.
+    observer: Option<(Arc<SubmissionObserver>, u32, Instant)>,
+    // Identifies this builder for the resources that track what has been recorded in it, such as
+    // the regions of an `UpdatableImage` that are sampled. Destroyed when the builder is built.
+    token: Arc<()>,
 }
 
 impl AutoCommandBufferBuilder<StandardCommandPoolBuilder> {
+    /// Starts building a primary command buffer.
+    ///
+    /// The final command buffer can be submitted multiple times, but not while a previous
+    /// submission is still in use by the GPU.
+    #[inline]
     pub fn new(device: Arc<Device>, queue_family: QueueFamily)
                -> Result<AutoCommandBufferBuilder<StandardCommandPoolBuilder>, OomError> {
+        AutoCommandBufferBuilder::with_flags(device, queue_family, Flags::None)
+    }
+
+    /// Starts building a primary command buffer that can only be submitted once.
+    ///
+    /// This lets the implementation optimize the command buffer for a single use. Trying to
+    /// submit it a second time returns `CommandBufferExecError::OneTimeSubmitAlreadySubmitted`.
+    ///
+    /// Once the execution of the command buffer has finished, the buffers and images that it
+    /// only needed while executing (for example the source of a copy) are released, even if the
+    /// command buffer itself is kept alive. Pipelines, framebuffers and descriptor sets are only
+    /// released when the command buffer is destroyed.
+    #[inline]
+    pub fn primary_one_time_submit(device: Arc<Device>, queue_family: QueueFamily)
+        -> Result<AutoCommandBufferBuilder<StandardCommandPoolBuilder>, OomError> {
+        AutoCommandBufferBuilder::with_flags(device, queue_family, Flags::OneTimeSubmit)
+    }
+
+    /// Starts building a primary command buffer that can be submitted multiple times, including
+    /// while a previous submission is still in use by the GPU.
+    ///
+    /// This may be slower to execute than the other kinds of command buffers.
+    #[inline]
+    pub fn primary_simultaneous_use(device: Arc<Device>, queue_family: QueueFamily)
+        -> Result<AutoCommandBufferBuilder<StandardCommandPoolBuilder>, OomError> {
+        AutoCommandBufferBuilder::with_flags(device, queue_family, Flags::SimultaneousUse)
+    }
+
+    fn with_flags(device: Arc<Device>, queue_family: QueueFamily, flags: Flags)
+                  -> Result<AutoCommandBufferBuilder<StandardCommandPoolBuilder>, OomError> {
         unsafe {
             let pool = Device::standard_command_pool(&device, queue_family);
-            let inner = SyncCommandBufferBuilder::new(&pool, Kind::primary(), Flags::None);
+            let inner = SyncCommandBufferBuilder::new(&pool, Kind::primary(), flags);
             let state_cacher = StateCacher::new();
 
+            let observer = device.submission_observer().map(|observer| {
+                observer.command_buffer_build_start(queue_family.id());
+                (observer, queue_family.id(), Instant::now())
+            });
+
             Ok(AutoCommandBufferBuilder {
                    inner: inner?,
                    state_cacher: state_cacher,
+                   descriptor_sets_cache: DescriptorSetsValidityCache::new(),
                    subpasses_remaining: None,
+                   render_pass: None,
+                   rendering: false,
                    secondary_cb: false,
+                   graphics_shaders_bound: false,
+                   flags: flags,
+                   observer: observer,
+                   token: Arc::new(()),
                })
         }
     }
 }
 
 impl<P> AutoCommandBufferBuilder<P> {
+    // Returns an object that is unique to this builder and that is destroyed when it is built.
+    #[inline]
+    pub(crate) fn token(&self) -> &Arc<()> {
+        &self.token
+    }
+
     #[inline]
     fn ensure_outside_render_pass(&self) -> Result<(), AutoCommandBufferBuilderContextError> {
-        if self.subpasses_remaining.is_none() {
+        if self.subpasses_remaining.is_none() && !self.rendering {
             Ok(())
         } else {
             Err(AutoCommandBufferBuilderContextError::ForbiddenInsideRenderPass)
@@ -110,9 +205,77 @@ impl<P> AutoCommandBufferBuilder<P> {
         }
     }
 
+    // Checks that the pipeline can be used in the current subpass.
+    #[inline]
+    fn ensure_pipeline_compatible<Gp>(&self, pipeline: &Gp)
+                                      -> Result<(), IncompatibleRenderPassError>
+        where Gp: ?Sized + GraphicsPipelineAbstract
+    {
+        let &(ref render_pass, subpass) = match self.render_pass {
+            Some(ref rp) => rp,
+            None => return Ok(()),
+        };
+
+        if pipeline.subpass_index() != subpass {
+            return Err(IncompatibleRenderPassError::SubpassIndexMismatch {
+                           expected: pipeline.subpass_index(),
+                           obtained: subpass,
+                       });
+        }
+
+        ensure_render_pass_compatible(pipeline, &**render_pass)
+    }
+
+    /// Starts capturing the commands of the command buffer, alongside the pipeline barriers and
+    /// layout transitions that the builder inserts automatically. The capture can be retrieved
+    /// with `AutoCommandBuffer::capture` once the command buffer is built.
+    ///
+    /// The capture is a compact text that doesn't change from one run to the next, which makes
+    /// it possible to check that the synchronization of a sequence of commands doesn't regress.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if commands have already been added to the builder.
+    ///
+    #[inline]
+    pub fn enable_capture(mut self) -> Self {
+        self.inner.enable_capture();
+        self
+    }
+
     /// Builds the command buffer.
     #[inline]
-    pub fn build(self) -> Result<AutoCommandBuffer<P::Alloc>, BuildError>
+    pub fn build(mut self) -> Result<AutoCommandBuffer<P::Alloc>, BuildError>
+        where P: CommandPoolBuilderAlloc
+    {
+        // The observer is notified even if the build fails, so that each call to
+        // `command_buffer_build_start` has a matching `command_buffer_build_end`.
+        let observer = match self.observer.take() {
+            Some((observer, queue_family, start)) => {
+                Some((observer, queue_family, start, self.inner.num_commands(),
+                      self.descriptor_sets_cache.hits(), self.descriptor_sets_cache.misses()))
+            },
+            None => None,
+        };
+
+        let result = self.build_impl();
+
+        if let Some((observer, queue_family, start, num_commands, hits, misses)) = observer {
+            observer.command_buffer_build_end(&CommandBufferBuildStats {
+                                                  queue_family: queue_family,
+                                                  num_commands: num_commands,
+                                                  descriptor_sets_validation_hits: hits,
+                                                  descriptor_sets_validation_misses: misses,
+                                                  duration: start.elapsed(),
+                                                  succeeded: result.is_ok(),
+                                              });
+        }
+
+        result
+    }
+
+    // Implementation of `build`, without the notifications to the observer.
+    fn build_impl(self) -> Result<AutoCommandBuffer<P::Alloc>, BuildError>
         where P: CommandPoolBuilderAlloc
     {
         if self.secondary_cb {
@@ -120,7 +283,39 @@ impl<P> AutoCommandBufferBuilder<P> {
         }
 
         self.ensure_outside_render_pass()?;
-        Ok(AutoCommandBuffer { inner: self.inner.build()? })
+
+        let submit_state = match self.flags {
+            Flags::None => SubmitState::ExclusiveUse { in_use: AtomicBool::new(false) },
+            Flags::SimultaneousUse => SubmitState::Concurrent,
+            Flags::OneTimeSubmit => {
+                SubmitState::OneTime { already_submitted: AtomicBool::new(false) }
+            },
+        };
+
+        let inner = self.inner.build()?;
+
+        Ok(AutoCommandBuffer {
+               inner: inner,
+               submit_state: submit_state,
+           })
+    }
+
+    /// Adds a command that opens a debug label with the given name and color.
+    ///
+    /// The commands that are added until the label is closed with `end_debug_label` are grouped
+    /// under this label in debuggers and profilers. Labels can be nested. Any label that is still
+    /// open when the command buffer is built is closed at the end of the command buffer.
+    ///
+    /// Requires the `ext_debug_utils` extension to be enabled on the instance. A color of
+    /// `[0.0; 4]` means that the label has no color.
+    #[inline]
+    pub fn begin_debug_label(mut self, name: &str, color: [f32; 4])
+                             -> Result<Self, DebugLabelError> {
+        let name = check_debug_label(self.device(), name)?;
+        unsafe {
+            self.inner.begin_debug_utils_label(name, color);
+        }
+        Ok(self)
     }
 
     /// Adds a command that enters a render pass.
@@ -129,11 +324,41 @@ impl<P> AutoCommandBufferBuilder<P> {
     /// you're inside the first subpass of the render pass. If `secondary` is false, you will only
     /// be able to add inline draw commands and not secondary command buffers.
     ///
-    /// You must call this before you can add draw commands.
+    /// You must call this before you can add draw commands. Returns an error if an attachment of
+    /// the framebuffer doesn't match the render pass description of the framebuffer. The draw
+    /// commands then check that their pipeline is compatible with the current subpass.
     #[inline]
-    pub fn begin_render_pass<F, C>(mut self, framebuffer: F, secondary: bool, clear_values: C)
+    pub fn begin_render_pass<F, C>(self, framebuffer: F, secondary: bool, clear_values: C)
                                    -> Result<Self, BeginRenderPassError>
         where F: FramebufferAbstract + RenderPassDescClearValues<C> + Send + Sync + 'static
+    {
+        let dimensions = framebuffer.dimensions();
+        self.begin_render_pass_with_area(framebuffer,
+                                         secondary,
+                                         clear_values,
+                                         [0, 0],
+                                         [dimensions[0], dimensions[1]])
+    }
+
+    /// Adds a command that enters a render pass, and only renders to the area of `extent` pixels
+    /// starting at `offset`.
+    ///
+    /// Same as `begin_render_pass`, except that the load and store operations of the attachments
+    /// only apply to the render area. Drawing outside of the area has undefined results. Using a
+    /// tight render area with the `DontCare` load operation avoids loading and storing pixels
+    /// that the render pass doesn't touch, for example when updating a region of a shadow atlas.
+    ///
+    /// The area must be within the framebuffer. If it doesn't cover the whole framebuffer, the
+    /// attachments must not have the `Undefined` initial layout, as this would discard their
+    /// content outside of the area. See `check_render_area` for more details.
+    ///
+    /// For best performance, the offset and extent should be multiples of the `granularity()` of
+    /// the render pass.
+    pub fn begin_render_pass_with_area<F, C>(mut self, framebuffer: F, secondary: bool,
+                                             clear_values: C, offset: [u32; 2],
+                                             extent: [u32; 2])
+                                             -> Result<Self, BeginRenderPassError>
+        where F: FramebufferAbstract + RenderPassDescClearValues<C> + Send + Sync + 'static
     {
         unsafe {
             if self.secondary_cb {
@@ -141,16 +366,121 @@ impl<P> AutoCommandBufferBuilder<P> {
             }
 
             self.ensure_outside_render_pass()?;
+            check_render_area(&framebuffer, offset, extent)?;
+
+            for (num, view) in framebuffer.attachments().into_iter().enumerate() {
+                ensure_image_view_compatible(&framebuffer, num, view)?;
+            }
 
             let clear_values = framebuffer.convert_clear_values(clear_values);
-            let clear_values = clear_values.collect::<Vec<_>>().into_iter(); // TODO: necessary for Send + Sync ; needs an API rework of convert_clear_values
+            // TODO: collecting is necessary for Send + Sync ; needs an API rework of
+            //       convert_clear_values
+            let clear_values = clear_values.collect::<Vec<_>>();
+            check_clear_values(&framebuffer, &clear_values)?;
+            let clear_values = clear_values.into_iter();
             let contents = if secondary { SubpassContents::SecondaryCommandBuffers }
                            else { SubpassContents::Inline };
             let num_subpasses = framebuffer.num_subpasses();
             debug_assert_ne!(num_subpasses, 0);
+            let framebuffer = Arc::new(framebuffer);
             self.inner
-                .begin_render_pass(framebuffer, contents, clear_values)?;
+                .begin_render_pass_with_area(framebuffer.clone(), contents, clear_values, offset,
+                                             extent)?;
             self.subpasses_remaining = Some(num_subpasses - 1);
+            self.render_pass = Some((framebuffer as Arc<_>, 0));
+            Ok(self)
+        }
+    }
+
+    /// Adds a command that begins rendering to `color_attachments` without a render pass, in the
+    /// area of `extent` pixels starting at `offset`.
+    ///
+    /// Each attachment is cleared with its clear value, or keeps its content if the value is
+    /// `ClearValue::None`. The content of the attachments is always stored when calling
+    /// `end_rendering`.
+    ///
+    /// Pipelines are always created for a render pass, so the only draw command that is allowed
+    /// until `end_rendering` is `draw_with_shaders`. Requires the `khr_dynamic_rendering`
+    /// extension. See `check_begin_rendering` for the other requirements.
+    pub fn begin_rendering<V>(mut self, color_attachments: Vec<(V, ClearValue)>,
+                              offset: [u32; 2], extent: [u32; 2])
+                              -> Result<Self, BeginRenderingError>
+        where V: ImageViewAccess + Send + Sync + 'static
+    {
+        unsafe {
+            if self.secondary_cb {
+                return Err(AutoCommandBufferBuilderContextError::ForbiddenInSecondary.into());
+            }
+
+            self.ensure_outside_render_pass()?;
+            check_begin_rendering(self.device(), &color_attachments, offset, extent)?;
+
+            self.inner.begin_rendering(color_attachments, offset, extent)?;
+            self.rendering = true;
+            Ok(self)
+        }
+    }
+
+    /// Makes sure that the writes performed to `buffer` by the previous dispatch commands are
+    /// visible to the commands that come next and that use the buffer with the given stages and
+    /// access.
+    ///
+    /// Buffers accessed through descriptor sets are not yet tracked automatically, so you must
+    /// call this function between a `dispatch` that writes to a buffer and a draw (or any other
+    /// command) that reads from it. For example, for a buffer that is filled by a compute shader
+    /// then used as a vertex buffer, pass the `vertex_input` stage and the
+    /// `vertex_attribute_read` access.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `stages` is empty or if `access` is not compatible with `stages`.
+    ///
+    #[inline]
+    pub fn compute_write_buffer_barrier<B>(mut self, buffer: B, stages: PipelineStages,
+                                           access: AccessFlagBits)
+                                           -> Result<Self, ComputeWriteBarrierError>
+        where B: BufferAccess + Clone + Send + Sync + 'static
+    {
+        unsafe {
+            self.ensure_outside_render_pass()?;
+            assert_ne!(stages, PipelineStages::none());
+            assert!(access.is_compatible_with(&stages));
+
+            self.inner.declare_buffer_usage(buffer.clone(), true, compute_write_stages(),
+                                            compute_write_access())?;
+            self.inner.declare_buffer_usage(buffer, false, stages, access)?;
+            Ok(self)
+        }
+    }
+
+    /// Same as `compute_write_buffer_barrier`, but for an image.
+    ///
+    /// The compute shader is assumed to have written to the image in the `General` layout, as
+    /// required for storage images. If `layout` is different, the image is transitioned to it.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `stages` is empty or if `access` is not compatible with `stages`.
+    /// - Panics if `layout` is `Undefined` or `Preinitialized`.
+    ///
+    #[inline]
+    pub fn compute_write_image_barrier<I>(mut self, image: I, layout: ImageLayout,
+                                          stages: PipelineStages, access: AccessFlagBits)
+                                          -> Result<Self, ComputeWriteBarrierError>
+        where I: ImageAccess + Clone + Send + Sync + 'static
+    {
+        unsafe {
+            self.ensure_outside_render_pass()?;
+            assert_ne!(stages, PipelineStages::none());
+            assert!(access.is_compatible_with(&stages));
+            assert_ne!(layout, ImageLayout::Undefined);
+            assert_ne!(layout, ImageLayout::Preinitialized);
+
+            self.inner.declare_image_usage(image.clone(), ImageLayout::General, true,
+                                           compute_write_stages(), compute_write_access())?;
+            // Changing the layout of the image is a write operation.
+            let exclusive = layout != ImageLayout::General;
+            self.inner.declare_image_usage(image, layout, exclusive, stages, access)?;
             Ok(self)
         }
     }
@@ -194,8 +524,9 @@ impl<P> AutoCommandBufferBuilder<P> {
     {
         unsafe {
             self.ensure_outside_render_pass()?;
+            check_copy_buffer_to_image(self.device(), &src, &dest, offset, size, first_layer,
+                                       num_layers, mipmap)?;
 
-            // TODO: check validity
             // TODO: hastily implemented
 
             let copy = UnsafeCommandBufferBuilderBufferImageCopy {
@@ -225,6 +556,131 @@ impl<P> AutoCommandBufferBuilder<P> {
         }
     }
 
+    /// Adds a command that copies from an image to a buffer.
+    pub fn copy_image_to_buffer<S, D>(self, src: S, dest: D)
+                                      -> Result<Self, CopyImageToBufferError>
+        where S: ImageAccess + Send + Sync + 'static,
+              D: BufferAccess + Send + Sync + 'static
+    {
+        self.ensure_outside_render_pass()?;
+
+        let dims = src.dimensions().width_height_depth();
+        self.copy_image_to_buffer_dimensions(src, dest, [0, 0, 0], dims, 0, 1, 0)
+    }
+
+    /// Adds a command that copies a region of an image to a buffer.
+    ///
+    /// The texels of the region are tightly packed in the buffer.
+    pub fn copy_image_to_buffer_dimensions<S, D>(
+        mut self, src: S, dest: D, offset: [u32; 3], size: [u32; 3], first_layer: u32,
+        num_layers: u32, mipmap: u32) -> Result<Self, CopyImageToBufferError>
+        where S: ImageAccess + Send + Sync + 'static,
+              D: BufferAccess + Send + Sync + 'static
+    {
+        unsafe {
+            self.ensure_outside_render_pass()?;
+            check_copy_image_to_buffer(self.device(), &src, &dest, offset, size, first_layer,
+                                       num_layers, mipmap)?;
+
+            let copy = UnsafeCommandBufferBuilderBufferImageCopy {
+                buffer_offset: 0,
+                buffer_row_length: 0,
+                buffer_image_height: 0,
+                // `check_copy_image_to_buffer` has checked that the image has a color aspect.
+                image_aspect: UnsafeCommandBufferBuilderImageAspect {
+                    color: true,
+                    depth: false,
+                    stencil: false,
+                },
+                image_mip_level: mipmap,
+                image_base_array_layer: first_layer,
+                image_layer_count: num_layers,
+                image_offset: [offset[0] as i32, offset[1] as i32, offset[2] as i32],
+                image_extent: size,
+            };
+
+            self.inner.copy_image_to_buffer(src, ImageLayout::TransferSrcOptimal, dest,
+                                            iter::once(copy))?;
+            Ok(self)
+        }
+    }
+
+    /// Binds shader objects to the stages they were created for.
+    ///
+    /// If `shaders` contains at least one graphics shader, then all the graphics stages for which
+    /// no shader is provided are unbound. This makes it possible to switch for example from a
+    /// vertex/geometry/fragment combination to a vertex/fragment one.
+    ///
+    /// Binding shader objects replaces the currently bound pipeline of the same bind point.
+    /// Calling `draw`, `draw_indexed` or `draw_indirect` afterwards binds a pipeline again.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if two shaders of `shaders` were created for the same stage.
+    /// - Panics if one of the shaders wasn't created with the same device as this command buffer.
+    ///
+    #[inline]
+    pub fn bind_shaders<L, I>(mut self, shaders: I) -> Self
+        where I: IntoIterator<Item = Arc<ShaderObject<L>>>,
+              L: Send + Sync + 'static
+    {
+        unsafe {
+            let shaders = shaders.into_iter().collect::<Vec<_>>();
+
+            for (num, shader) in shaders.iter().enumerate() {
+                assert_eq!(shader.device().internal_object(), self.device().internal_object());
+                assert!(shaders[.. num].iter().all(|s| s.stage() != shader.stage()));
+            }
+
+            let mut unbound = Vec::new();
+            if shaders.iter().any(|s| s.stage().is_graphics()) {
+                let features = self.device().enabled_features();
+                let candidates = [
+                    (ShaderObjectStage::Vertex, true),
+                    (ShaderObjectStage::TessellationControl, features.tessellation_shader),
+                    (ShaderObjectStage::TessellationEvaluation, features.tessellation_shader),
+                    (ShaderObjectStage::Geometry, features.geometry_shader),
+                    (ShaderObjectStage::Fragment, true),
+                ];
+
+                for &(stage, supported) in candidates.iter() {
+                    if supported && shaders.iter().all(|s| s.stage() != stage) {
+                        unbound.push(stage);
+                    }
+                }
+
+                self.graphics_shaders_bound = shaders
+                    .iter()
+                    .any(|s| s.stage() == ShaderObjectStage::Vertex);
+            }
+
+            // The pipelines that the state cacher remembers are no longer bound.
+            self.state_cacher.invalidate();
+            self.inner.bind_shaders(shaders, unbound);
+            self
+        }
+    }
+
+    /// Opens a debug label and returns an object that closes it when it is destroyed.
+    ///
+    /// This is the same as `begin_debug_label` followed by `end_debug_label`, except that the
+    /// label is always closed, even in case of an early return. The scope doesn't borrow the
+    /// builder, so you can keep adding commands while it is alive:
+    ///
+    /// ```ignore
+    /// let _scope = builder.debug_scope("ShadowPass")?;
+    /// builder = builder.draw(...)?;
+    /// ```
+    ///
+    /// Scopes must be destroyed in the reverse order of their creation, which is what happens
+    /// when they are stored in local variables. If the scope is still alive when the command
+    /// buffer is built, the label is closed at the end of the command buffer.
+    #[inline]
+    pub fn debug_scope(&mut self, name: &str) -> Result<DebugLabelScope<P>, DebugLabelError> {
+        let name = check_debug_label(self.device(), name)?;
+        unsafe { Ok(self.inner.debug_utils_label_scope(name, [0.0; 4])) }
+    }
+
     #[inline]
     pub fn dispatch<Cp, S, Pc>(mut self, dimensions: [u32; 3], pipeline: Cp, sets: S, constants: Pc)
                                -> Result<Self, DispatchError>
@@ -234,7 +690,7 @@ impl<P> AutoCommandBufferBuilder<P> {
         unsafe {
             self.ensure_outside_render_pass()?;
             check_push_constants_validity(&pipeline, &constants)?;
-            check_descriptor_sets_validity(&pipeline, &sets)?;
+            self.descriptor_sets_cache.check(&pipeline, &sets)?;
             check_dispatch(pipeline.device(), dimensions)?;
 
             if let StateCacherOutcome::NeedChange =
@@ -251,6 +707,26 @@ impl<P> AutoCommandBufferBuilder<P> {
         }
     }
 
+    /// Same as `dispatch`, except that the number of workgroups is computed from the number of
+    /// elements to process in each dimension.
+    ///
+    /// The workgroup size of `pipeline` is taken into account, and the number of workgroups is
+    /// rounded up so that each element gets an invocation. Use `[num_elements, 1, 1]` for a
+    /// one-dimensional dispatch. The shader must ignore the invocations of the last workgroups
+    /// that are past the end of the elements. An error is returned if the workgroup size of
+    /// `pipeline` is unknown.
+    #[inline]
+    pub fn dispatch_elements<Cp, S, Pc>(self, pipeline: Cp, elements: [u32; 3], sets: S,
+                                        constants: Pc)
+                                        -> Result<Self, DispatchError>
+        where Cp: ComputePipelineAbstract + Send + Sync + 'static + Clone, // TODO: meh for Clone
+              S: DescriptorSetsCollection
+    {
+        let dimensions =
+            check_dispatch_elements(pipeline.device(), pipeline.local_size(), elements)?;
+        self.dispatch(dimensions, pipeline, sets, constants)
+    }
+
     #[inline]
     pub fn draw<V, Gp, S, Pc>(mut self, pipeline: Gp, dynamic: DynamicState, vertices: V, sets: S,
                               constants: Pc) -> Result<Self, DrawError>
@@ -258,18 +734,18 @@ impl<P> AutoCommandBufferBuilder<P> {
               S: DescriptorSetsCollection
     {
         unsafe {
-            // TODO: must check that pipeline is compatible with render pass
-
             self.ensure_inside_render_pass()?;
+            self.ensure_pipeline_compatible(&pipeline)?;
             check_dynamic_state_validity(&pipeline, &dynamic)?;
             check_push_constants_validity(&pipeline, &constants)?;
-            check_descriptor_sets_validity(&pipeline, &sets)?;
+            self.descriptor_sets_cache.check(&pipeline, &sets)?;
             let vb_infos = check_vertex_buffers(&pipeline, vertices)?;
 
             if let StateCacherOutcome::NeedChange =
                 self.state_cacher.bind_graphics_pipeline(&pipeline)
             {
                 self.inner.bind_pipeline_graphics(pipeline.clone());
+                self.graphics_shaders_bound = false;
             }
 
             push_constants(&mut self.inner, pipeline.clone(), constants);
@@ -283,9 +759,10 @@ impl<P> AutoCommandBufferBuilder<P> {
         }
     }
 
+    /// Draws all the indices of `index_buffer`.
     #[inline]
     pub fn draw_indexed<V, Gp, S, Pc, Ib, I>(
-        mut self, pipeline: Gp, dynamic: DynamicState, vertices: V, index_buffer: Ib, sets: S,
+        self, pipeline: Gp, dynamic: DynamicState, vertices: V, index_buffer: Ib, sets: S,
         constants: Pc)
         -> Result<Self, DrawIndexedError>
         where Gp: GraphicsPipelineAbstract + VertexSource<V> + Send + Sync + 'static + Clone, // TODO: meh for Clone
@@ -293,61 +770,208 @@ impl<P> AutoCommandBufferBuilder<P> {
               Ib: BufferAccess + TypedBufferAccess<Content = [I]> + Send + Sync + 'static,
               I: Index + 'static
     {
+        let index_count = index_buffer.len() as u32;
+        self.draw_indexed_range(pipeline, dynamic, vertices, index_buffer, 0, index_count, sets,
+                                constants)
+    }
+
+    /// Draws `index_count` indices of `index_buffer`, starting with the index at `first_index`.
+    ///
+    /// Returns an error if the range of indices goes past the end of the index buffer. The
+    /// indices themselves aren't checked against the number of vertices in `vertices`, as this
+    /// would require reading them. Use `draw_indexed_strict` for that.
+    #[inline]
+    pub fn draw_indexed_range<V, Gp, S, Pc, Ib, I>(
+        self, pipeline: Gp, dynamic: DynamicState, vertices: V, index_buffer: Ib,
+        first_index: u32, index_count: u32, sets: S, constants: Pc)
+        -> Result<Self, DrawIndexedError>
+        where Gp: GraphicsPipelineAbstract + VertexSource<V> + Send + Sync + 'static + Clone, // TODO: meh for Clone
+              S: DescriptorSetsCollection,
+              Ib: BufferAccess + TypedBufferAccess<Content = [I]> + Send + Sync + 'static,
+              I: Index + 'static
+    {
+        unsafe {
+            self.draw_indexed_impl(pipeline, dynamic, vertices, index_buffer, first_index,
+                                   index_count, sets, constants, true, None)
+        }
+    }
+
+    /// Same as `draw_indexed_range`, but doesn't check that the range of indices fits in the
+    /// index buffer.
+    ///
+    /// # Safety
+    ///
+    /// `first_index + index_count` must not be superior to the number of indices in
+    /// `index_buffer`.
+    #[inline]
+    pub unsafe fn draw_indexed_range_unchecked<V, Gp, S, Pc, Ib, I>(
+        self, pipeline: Gp, dynamic: DynamicState, vertices: V, index_buffer: Ib,
+        first_index: u32, index_count: u32, sets: S, constants: Pc)
+        -> Result<Self, DrawIndexedError>
+        where Gp: GraphicsPipelineAbstract + VertexSource<V> + Send + Sync + 'static + Clone, // TODO: meh for Clone
+              S: DescriptorSetsCollection,
+              Ib: BufferAccess + TypedBufferAccess<Content = [I]> + Send + Sync + 'static,
+              I: Index + 'static
+    {
+        self.draw_indexed_impl(pipeline, dynamic, vertices, index_buffer, first_index,
+                               index_count, sets, constants, false, None)
+    }
+
+    /// Same as `draw_indexed_range`, but also checks that every index that is drawn refers to a
+    /// vertex that exists in `vertices`.
+    ///
+    /// The indices are read from the CPU, which means that the index buffer must not be locked
+    /// for writing. This is more expensive than the other checks and is meant to be used while
+    /// debugging.
+    #[inline]
+    pub fn draw_indexed_strict<V, Gp, S, Pc, I>(
+        self, pipeline: Gp, dynamic: DynamicState, vertices: V,
+        index_buffer: Arc<CpuAccessibleBuffer<[I]>>, first_index: u32, index_count: u32,
+        sets: S, constants: Pc)
+        -> Result<Self, DrawIndexedError>
+        where Gp: GraphicsPipelineAbstract + VertexSource<V> + Send + Sync + 'static + Clone, // TODO: meh for Clone
+              S: DescriptorSetsCollection,
+              I: Index + Send + Sync + 'static
+    {
+        let buffer = index_buffer.clone();
+        let indices = buffer.read()?;
+
         unsafe {
-            // TODO: must check that pipeline is compatible with render pass
+            self.draw_indexed_impl(pipeline, dynamic, vertices, index_buffer, first_index,
+                                   index_count, sets, constants, true, Some(&indices[..]))
+        }
+    }
+
+    // Implementation of the `draw_indexed` functions. If `check_range` is true, checks that the
+    // range of indices fits in the index buffer. If `indices` is `Some`, it must contain the
+    // content of the index buffer and the indices of the range are checked against the number
+    // of vertices.
+    unsafe fn draw_indexed_impl<V, Gp, S, Pc, Ib, I>(
+        mut self, pipeline: Gp, dynamic: DynamicState, vertices: V, index_buffer: Ib,
+        first_index: u32, index_count: u32, sets: S, constants: Pc, check_range: bool,
+        indices: Option<&[I]>)
+        -> Result<Self, DrawIndexedError>
+        where Gp: GraphicsPipelineAbstract + VertexSource<V> + Send + Sync + 'static + Clone, // TODO: meh for Clone
+              S: DescriptorSetsCollection,
+              Ib: BufferAccess + TypedBufferAccess<Content = [I]> + Send + Sync + 'static,
+              I: Index + 'static
+    {
+        self.ensure_inside_render_pass()?;
+        self.ensure_pipeline_compatible(&pipeline)?;
+        let ib_infos = check_index_buffer(self.device(), &index_buffer)?;
+        if check_range {
+            check_index_range(ib_infos.num_indices, first_index, index_count)?;
+        }
+        check_dynamic_state_validity(&pipeline, &dynamic)?;
+        check_push_constants_validity(&pipeline, &constants)?;
+        self.descriptor_sets_cache.check(&pipeline, &sets)?;
+        let vb_infos = check_vertex_buffers(&pipeline, vertices)?;
+        if let Some(indices) = indices {
+            let range = first_index as usize .. (first_index + index_count) as usize;
+            check_indices_vertex_range(&indices[range], 0, vb_infos.vertex_count)?;
+        }
+
+        if let StateCacherOutcome::NeedChange =
+            self.state_cacher.bind_graphics_pipeline(&pipeline)
+        {
+            self.inner.bind_pipeline_graphics(pipeline.clone());
+            self.graphics_shaders_bound = false;
+        }
 
+        self.inner.bind_index_buffer(index_buffer, I::ty())?;
+        push_constants(&mut self.inner, pipeline.clone(), constants);
+        set_state(&mut self.inner, dynamic);
+        descriptor_sets(&mut self.inner, true, pipeline.clone(), sets)?;
+        vertex_buffers(&mut self.inner, vb_infos.vertex_buffers)?;
+
+        self.inner.draw_indexed(index_count, 1, first_index, 0, 0);
+        Ok(self)
+    }
+
+    #[inline]
+    pub fn draw_indirect<V, Gp, S, Pc, Ib>(mut self, pipeline: Gp, dynamic: DynamicState,
+                                           vertices: V, indirect_buffer: Ib, sets: S, constants: Pc)
+                                           -> Result<Self, DrawIndirectError>
+        where Gp: GraphicsPipelineAbstract + VertexSource<V> + Send + Sync + 'static + Clone, // TODO: meh for Clone
+              S: DescriptorSetsCollection,
+              Ib: BufferAccess
+                      + TypedBufferAccess<Content = [DrawIndirectCommand]>
+                      + Send
+                      + Sync
+                      + 'static
+    {
+        unsafe {
             self.ensure_inside_render_pass()?;
-            let ib_infos = check_index_buffer(self.device(), &index_buffer)?;
+            self.ensure_pipeline_compatible(&pipeline)?;
             check_dynamic_state_validity(&pipeline, &dynamic)?;
             check_push_constants_validity(&pipeline, &constants)?;
-            check_descriptor_sets_validity(&pipeline, &sets)?;
+            self.descriptor_sets_cache.check(&pipeline, &sets)?;
             let vb_infos = check_vertex_buffers(&pipeline, vertices)?;
 
+            let draw_count = indirect_buffer.len() as u32;
+
             if let StateCacherOutcome::NeedChange =
                 self.state_cacher.bind_graphics_pipeline(&pipeline)
             {
                 self.inner.bind_pipeline_graphics(pipeline.clone());
+                self.graphics_shaders_bound = false;
             }
 
-            self.inner.bind_index_buffer(index_buffer, I::ty())?;
             push_constants(&mut self.inner, pipeline.clone(), constants);
             set_state(&mut self.inner, dynamic);
             descriptor_sets(&mut self.inner, true, pipeline.clone(), sets)?;
             vertex_buffers(&mut self.inner, vb_infos.vertex_buffers)?;
-            // TODO: how to handle an index out of range of the vertex buffers?
 
-            self.inner.draw_indexed(ib_infos.num_indices as u32, 1, 0, 0, 0);
+            self.inner.draw_indirect(indirect_buffer,
+                                     draw_count,
+                                     mem::size_of::<DrawIndirectCommand>() as u32)?;
             Ok(self)
         }
     }
 
+    /// Executes `sequences_count` sequences of commands generated by the GPU.
+    ///
+    /// The pipeline must have been built with `GraphicsPipelineBuilder::indirect_bindable`.
+    ///
+    /// The content of the sequences is described by `layout`, and `streams` must contain one
+    /// buffer for each stream of the layout. `preprocess` is used as scratch memory by the
+    /// implementation, and its size must be at least what
+    /// `IndirectCommandsLayout::preprocess_requirements` returns for the same pipeline and
+    /// number of sequences.
+    ///
+    /// The pipeline, the dynamic state, the vertex buffers, the descriptor sets and the push
+    /// constants are bound before the sequences are executed, like for `draw_indirect`.
+    /// The tokens of the sequences then override them.
     #[inline]
-    pub fn draw_indirect<V, Gp, S, Pc, Ib>(mut self, pipeline: Gp, dynamic: DynamicState,
-                                           vertices: V, indirect_buffer: Ib, sets: S, constants: Pc)
-                                           -> Result<Self, DrawIndirectError>
+    pub fn execute_generated_commands<V, Gp, L, S, Pc, Pb>(
+        mut self, pipeline: Gp, dynamic: DynamicState, vertices: V,
+        layout: Arc<IndirectCommandsLayout<L>>, streams: Vec<Box<BufferAccess + Send + Sync>>,
+        sequences_count: u32, preprocess: Pb, sets: S, constants: Pc)
+        -> Result<Self, ExecuteGeneratedCommandsError>
         where Gp: GraphicsPipelineAbstract + VertexSource<V> + Send + Sync + 'static + Clone, // TODO: meh for Clone
+              L: Send + Sync + 'static,
               S: DescriptorSetsCollection,
-              Ib: BufferAccess
-                      + TypedBufferAccess<Content = [DrawIndirectCommand]>
-                      + Send
-                      + Sync
-                      + 'static
+              Pb: BufferAccess + Send + Sync + 'static
     {
         unsafe {
-            // TODO: must check that pipeline is compatible with render pass
-
             self.ensure_inside_render_pass()?;
+            self.ensure_pipeline_compatible(&pipeline)?;
             check_dynamic_state_validity(&pipeline, &dynamic)?;
             check_push_constants_validity(&pipeline, &constants)?;
-            check_descriptor_sets_validity(&pipeline, &sets)?;
+            self.descriptor_sets_cache.check(&pipeline, &sets)?;
             let vb_infos = check_vertex_buffers(&pipeline, vertices)?;
-
-            let draw_count = indirect_buffer.len() as u32;
+            check_generated_commands(self.device(),
+                                     &pipeline,
+                                     &layout,
+                                     &streams,
+                                     sequences_count,
+                                     &preprocess)?;
 
             if let StateCacherOutcome::NeedChange =
                 self.state_cacher.bind_graphics_pipeline(&pipeline)
             {
                 self.inner.bind_pipeline_graphics(pipeline.clone());
+                self.graphics_shaders_bound = false;
             }
 
             push_constants(&mut self.inner, pipeline.clone(), constants);
@@ -355,13 +979,70 @@ impl<P> AutoCommandBufferBuilder<P> {
             descriptor_sets(&mut self.inner, true, pipeline.clone(), sets)?;
             vertex_buffers(&mut self.inner, vb_infos.vertex_buffers)?;
 
-            self.inner.draw_indirect(indirect_buffer,
-                                     draw_count,
-                                     mem::size_of::<DrawIndirectCommand>() as u32)?;
+            // The generated commands may change any of the states that they bind, so the
+            // cached states can no longer be trusted.
+            self.state_cacher.invalidate();
+
+            self.inner.execute_generated_commands(pipeline,
+                                                  layout,
+                                                  streams,
+                                                  sequences_count,
+                                                  preprocess)?;
+            Ok(self)
+        }
+    }
+
+    /// Draws with the shader objects previously bound with `bind_shaders`.
+    ///
+    /// Must be called between `begin_rendering` and `end_rendering`, as shader objects can't be
+    /// used inside a render pass.
+    ///
+    /// Contrary to `draw`, no pipeline is involved. The whole fixed-function state is provided
+    /// by `state` and is set before drawing. `layout` must be compatible with the layouts the
+    /// bound shader objects were created with, and is used to bind `sets` and `constants`.
+    ///
+    /// The vertex buffers are bound in the order of `vertices`, starting from binding 0,
+    /// and must match the bindings described in `state`.
+    #[inline]
+    pub fn draw_with_shaders<Pl, S, Pc>(mut self, layout: Pl, state: ShaderObjectState,
+                                        vertices: Vec<Box<BufferAccess + Send + Sync>>,
+                                        vertex_count: u32, instance_count: u32, sets: S,
+                                        constants: Pc)
+                                        -> Result<Self, DrawWithShadersError>
+        where Pl: PipelineLayoutAbstract + PipelineLayoutPushConstantsCompatible<Pc> + Send + Sync + Clone + 'static,
+              S: DescriptorSetsCollection
+    {
+        unsafe {
+            if !self.rendering {
+                return Err(AutoCommandBufferBuilderContextError::ForbiddenOutsideRenderPass.into());
+            }
+            if !self.graphics_shaders_bound {
+                return Err(AutoCommandBufferBuilderContextError::NoShadersBound.into());
+            }
+
+            check_push_constants_validity(&layout, &constants)?;
+            self.descriptor_sets_cache.check(&layout, &sets)?;
+
+            push_constants(&mut self.inner, layout.clone(), constants);
+            self.inner.set_shader_object_state(state);
+            descriptor_sets(&mut self.inner, true, layout, sets)?;
+            vertex_buffers(&mut self.inner, vertices)?;
+
+            self.inner.draw(vertex_count, instance_count, 0, 0);
             Ok(self)
         }
     }
 
+    /// Adds a command that closes the latest debug label opened with `begin_debug_label`.
+    #[inline]
+    pub fn end_debug_label(mut self) -> Result<Self, DebugLabelError> {
+        check_end_debug_label(self.device(), self.inner.num_open_debug_labels())?;
+        unsafe {
+            self.inner.end_debug_utils_label();
+        }
+        Ok(self)
+    }
+
     /// Adds a command that ends the current render pass.
     ///
     /// This must be called after you went through all the subpasses and before you can build
@@ -385,6 +1066,21 @@ impl<P> AutoCommandBufferBuilder<P> {
 
             self.inner.end_render_pass();
             self.subpasses_remaining = None;
+            self.render_pass = None;
+            Ok(self)
+        }
+    }
+
+    /// Adds a command that ends the rendering started with `begin_rendering`.
+    #[inline]
+    pub fn end_rendering(mut self) -> Result<Self, AutoCommandBufferBuilderContextError> {
+        unsafe {
+            if !self.rendering {
+                return Err(AutoCommandBufferBuilderContextError::ForbiddenOutsideRenderPass);
+            }
+
+            self.inner.end_rendering();
+            self.rendering = false;
             Ok(self)
         }
     }
@@ -405,12 +1101,57 @@ impl<P> AutoCommandBufferBuilder<P> {
     {
         unsafe {
             self.ensure_outside_render_pass()?;
-            check_fill_buffer(self.device(), &buffer)?;
-            self.inner.fill_buffer(buffer, data);
+            check_fill_buffer(self.device(), &buffer)?;
+            self.inner.fill_buffer(buffer, data);
+            Ok(self)
+        }
+    }
+
+    /// Makes sure that the writes performed to `buffer` by the previous commands of this command
+    /// buffer are visible to the host once the command buffer has finished executing.
+    ///
+    /// Waiting for a fence is not enough for the CPU to see the data written by the GPU, as the
+    /// writes must also be made available to the `host` stage. Call this function at the end of a
+    /// command buffer that produces data that you want to read from the CPU, for example after
+    /// copying an image or a buffer to a `CpuAccessibleBuffer`.
+    ///
+    /// For buffers written by a compute shader, use `compute_write_buffer_barrier` with the
+    /// `host` stage and the `host_read` access instead.
+    #[inline]
+    pub fn host_read_barrier<B>(mut self, buffer: B) -> Result<Self, HostReadBarrierError>
+        where B: BufferAccess + Send + Sync + 'static
+    {
+        unsafe {
+            self.ensure_outside_render_pass()?;
+
+            let stages = PipelineStages {
+                host: true,
+                ..PipelineStages::none()
+            };
+            let access = AccessFlagBits {
+                host_read: true,
+                ..AccessFlagBits::none()
+            };
+
+            self.inner.declare_buffer_usage(buffer, false, stages, access)?;
             Ok(self)
         }
     }
 
+    /// Adds a debug label with the given name and color that marks a single point of the
+    /// command buffer.
+    ///
+    /// Requires the `ext_debug_utils` extension to be enabled on the instance.
+    #[inline]
+    pub fn insert_debug_label(mut self, name: &str, color: [f32; 4])
+                              -> Result<Self, DebugLabelError> {
+        let name = check_debug_label(self.device(), name)?;
+        unsafe {
+            self.inner.insert_debug_utils_label(name, color);
+        }
+        Ok(self)
+    }
+
     /// Adds a command that jumps to the next subpass of the current render pass.
     #[inline]
     pub fn next_subpass(mut self, secondary: bool)
@@ -432,6 +1173,10 @@ impl<P> AutoCommandBufferBuilder<P> {
                 }
             }
 
+            if let Some((_, ref mut subpass)) = self.render_pass {
+                *subpass += 1;
+            }
+
             let contents = if secondary { SubpassContents::SecondaryCommandBuffers }
                            else { SubpassContents::Inline };
             self.inner.next_subpass(contents);
@@ -439,6 +1184,21 @@ impl<P> AutoCommandBufferBuilder<P> {
         }
     }
 
+    /// Adds a command that resets the given range of queries of a query pool.
+    ///
+    /// Queries must be reset before they are used, for example before writing a timestamp with
+    /// `write_timestamp`.
+    #[inline]
+    pub fn reset_query_pool(mut self, pool: Arc<UnsafeQueryPool>, queries: Range<u32>)
+                            -> Result<Self, ResetQueryPoolError> {
+        unsafe {
+            self.ensure_outside_render_pass()?;
+            check_reset_query_pool(self.device(), &pool, queries.clone())?;
+            self.inner.reset_query_pool(pool, queries);
+            Ok(self)
+        }
+    }
+
     /// Adds a command that writes data to a buffer.
     ///
     /// If `data` is larger than the buffer, only the part of `data` that fits is written. If the
@@ -464,6 +1224,24 @@ impl<P> AutoCommandBufferBuilder<P> {
             Ok(self)
         }
     }
+
+    /// Adds a command that writes a timestamp to a query of a timestamp query pool once all the
+    /// previous commands have reached the given stage.
+    ///
+    /// `stage` must contain exactly one stage. The query must have been reset with
+    /// `reset_query_pool` beforehand. The value is written in units of
+    /// `timestamp_period` nanoseconds, and only the number of bits returned by
+    /// `QueueFamily::timestamp_valid_bits` are meaningful.
+    #[inline]
+    pub fn write_timestamp(mut self, pool: Arc<UnsafeQueryPool>, query: u32,
+                           stage: PipelineStages)
+                           -> Result<Self, WriteTimestampError> {
+        unsafe {
+            check_write_timestamp(self.device(), &pool, query, stage)?;
+            self.inner.write_timestamp(pool, query, stage);
+            Ok(self)
+        }
+    }
 }
 
 unsafe impl<P> DeviceOwned for AutoCommandBufferBuilder<P> {
@@ -473,6 +1251,24 @@ unsafe impl<P> DeviceOwned for AutoCommandBufferBuilder<P> {
     }
 }
 
+// Stages used by a compute shader that writes to a resource.
+#[inline]
+fn compute_write_stages() -> PipelineStages {
+    PipelineStages {
+        compute_shader: true,
+        ..PipelineStages::none()
+    }
+}
+
+// Access used by a compute shader that writes to a resource.
+#[inline]
+fn compute_write_access() -> AccessFlagBits {
+    AccessFlagBits {
+        shader_write: true,
+        ..AccessFlagBits::none()
+    }
+}
+
 // Shortcut function to set the push constants.
 unsafe fn push_constants<P, Pl, Pc>(dest: &mut SyncCommandBufferBuilder<P>, pipeline: Pl,
                                     push_constants: Pc)
@@ -512,6 +1308,54 @@ unsafe fn set_state<P>(dest: &mut SyncCommandBufferBuilder<P>, dynamic: DynamicS
     if let Some(ref scissors) = dynamic.scissors {
         dest.set_scissor(0, scissors.iter().cloned().collect::<Vec<_>>().into_iter()); // TODO: don't collect
     }
+
+    if let Some(value) = dynamic.stencil_compare_mask {
+        stencil_state(value, |face, v| dest.set_stencil_compare_mask(face, v));
+    }
+
+    if let Some(value) = dynamic.stencil_write_mask {
+        stencil_state(value, |face, v| dest.set_stencil_write_mask(face, v));
+    }
+
+    if let Some(value) = dynamic.stencil_reference {
+        stencil_state(value, |face, v| dest.set_stencil_reference(face, v));
+    }
+
+    if let Some(enable) = dynamic.depth_test_enable {
+        dest.set_depth_test_enable(enable);
+    }
+
+    if let Some(enable) = dynamic.depth_write_enable {
+        dest.set_depth_write_enable(enable);
+    }
+
+    if let Some(compare) = dynamic.depth_compare_op {
+        dest.set_depth_compare_op(compare);
+    }
+
+    if let Some(enable) = dynamic.stencil_test_enable {
+        dest.set_stencil_test_enable(enable);
+    }
+
+    if let Some(ref range) = dynamic.depth_bounds {
+        dest.set_depth_bounds(range.start, range.end);
+    }
+
+    if let Some(bias) = dynamic.depth_bias {
+        dest.set_depth_bias(bias.constant_factor, bias.clamp, bias.slope_factor);
+    }
+}
+
+// Calls `set` once if both faces use the same value, or once per face otherwise.
+fn stencil_state<F>(value: DynamicStencilValue, mut set: F)
+    where F: FnMut(StencilFaceFlags, u32)
+{
+    if value.front == value.back {
+        set(StencilFaceFlags::FrontAndBack, value.front);
+    } else {
+        set(StencilFaceFlags::Front, value.front);
+        set(StencilFaceFlags::Back, value.back);
+    }
 }
 
 // Shortcut function to bind vertex buffers.
@@ -533,18 +1377,81 @@ unsafe fn descriptor_sets<P, Pl, S>(dest: &mut SyncCommandBufferBuilder<P>, gfx:
     where Pl: PipelineLayoutAbstract + Send + Sync + Clone + 'static,
           S: DescriptorSetsCollection
 {
+    let dynamic_offsets = sets.dynamic_offsets();
+    let sets = sets.into_vec();
+
+    // Pipelines that only use push constants don't need any bind command.
+    if sets.is_empty() {
+        return Ok(());
+    }
+
     let mut sets_binder = dest.bind_descriptor_sets();
 
-    for set in sets.into_vec() {
+    for set in sets {
         sets_binder.add(set);
     }
 
-    sets_binder.submit(gfx, pipeline.clone(), 0, iter::empty())?;
+    sets_binder.submit(gfx, pipeline.clone(), 0, dynamic_offsets.into_iter())?;
     Ok(())
 }
 
 pub struct AutoCommandBuffer<P = StandardCommandPoolAlloc> {
     inner: SyncCommandBuffer<P>,
+    // Whether the command buffer can be submitted, depending on the flags it was created with.
+    submit_state: SubmitState,
+}
+
+// Whether an `AutoCommandBuffer` can be submitted.
+#[derive(Debug)]
+enum SubmitState {
+    // The command buffer was created with `Flags::None`. `in_use` is true between a submission
+    // and the moment the GPU has finished executing it.
+    ExclusiveUse { in_use: AtomicBool },
+    // The command buffer was created with `Flags::SimultaneousUse`.
+    Concurrent,
+    // The command buffer was created with `Flags::OneTimeSubmit`.
+    OneTime { already_submitted: AtomicBool },
+}
+
+impl<P> AutoCommandBuffer<P> {
+    /// Returns true if the command buffer was created with `primary_simultaneous_use`, in which
+    /// case it can be submitted again while a previous submission is still executing.
+    #[inline]
+    pub fn simultaneous_use(&self) -> bool {
+        match self.submit_state {
+            SubmitState::Concurrent => true,
+            _ => false,
+        }
+    }
+
+    /// Returns the capture of the commands and of the pipeline barriers of the command buffer,
+    /// or `None` if `enable_capture` wasn't called on the builder.
+    ///
+    /// See `SyncCommandBuffer::capture` for the format.
+    #[inline]
+    pub fn capture(&self) -> Option<&str> {
+        self.inner.capture()
+    }
+
+    /// Returns the number of commands that were recorded in the command buffer, not counting the
+    /// pipeline barriers that were inserted automatically.
+    ///
+    /// Commands that only bind a state that is already bound aren't recorded, and therefore not
+    /// counted.
+    #[inline]
+    pub fn recorded_command_count(&self) -> usize {
+        self.inner.recorded_command_count()
+    }
+
+    /// Returns the number of distinct buffers and images that the command buffer keeps alive
+    /// and synchronizes.
+    ///
+    /// The buffers and images that are only accessed through descriptor sets aren't counted.
+    /// For a one-time-submit command buffer, this drops once its execution has finished.
+    #[inline]
+    pub fn resource_reference_count(&self) -> usize {
+        self.inner.resource_reference_count()
+    }
 }
 
 unsafe impl<P> CommandBuffer for AutoCommandBuffer<P> {
@@ -558,7 +1465,52 @@ unsafe impl<P> CommandBuffer for AutoCommandBuffer<P> {
     #[inline]
     fn prepare_submit(&self, future: &GpuFuture, queue: &Queue)
                       -> Result<(), CommandBufferExecError> {
-        self.inner.prepare_submit(future, queue)
+        match self.submit_state {
+            SubmitState::OneTime { ref already_submitted } => {
+                if already_submitted.swap(true, Ordering::SeqCst) {
+                    return Err(CommandBufferExecError::OneTimeSubmitAlreadySubmitted);
+                }
+            },
+            SubmitState::ExclusiveUse { ref in_use } => {
+                if in_use.swap(true, Ordering::SeqCst) {
+                    return Err(CommandBufferExecError::ExclusiveAlreadyInUse);
+                }
+            },
+            SubmitState::Concurrent => (),
+        };
+
+        let err = match self.inner.prepare_submit(future, queue) {
+            Ok(()) => return Ok(()),
+            Err(err) => err,
+        };
+
+        // The command buffer isn't going to be submitted after all.
+        match self.submit_state {
+            SubmitState::OneTime { ref already_submitted } => {
+                already_submitted.store(false, Ordering::SeqCst);
+            },
+            SubmitState::ExclusiveUse { ref in_use } => {
+                in_use.store(false, Ordering::SeqCst);
+            },
+            SubmitState::Concurrent => (),
+        };
+
+        Err(err)
+    }
+
+    #[inline]
+    unsafe fn unlock(&self) {
+        self.inner.unlock();
+
+        match self.submit_state {
+            SubmitState::ExclusiveUse { ref in_use } => {
+                let was_in_use = in_use.swap(false, Ordering::SeqCst);
+                debug_assert!(was_in_use);
+            },
+            // The command buffer can't be submitted again.
+            SubmitState::OneTime { .. } => self.inner.release_execution_resources(),
+            SubmitState::Concurrent => (),
+        }
     }
 
     #[inline]
@@ -639,6 +1591,20 @@ err_gen!(BuildError {
 });
 
 err_gen!(BeginRenderPassError {
+    AutoCommandBufferBuilderContextError,
+    IncompatibleRenderPassAttachmentError,
+    CheckClearValuesError,
+    CheckRenderAreaError,
+    SyncCommandBufferBuilderError
+});
+
+err_gen!(BeginRenderingError {
+    AutoCommandBufferBuilderContextError,
+    CheckBeginRenderingError,
+    SyncCommandBufferBuilderError
+});
+
+err_gen!(ComputeWriteBarrierError {
     AutoCommandBufferBuilderContextError,
     SyncCommandBufferBuilderError
 });
@@ -651,6 +1617,13 @@ err_gen!(CopyBufferError {
 
 err_gen!(CopyBufferToImageError {
     AutoCommandBufferBuilderContextError,
+    CheckCopyBufferToImageError,
+    SyncCommandBufferBuilderError
+});
+
+err_gen!(CopyImageToBufferError {
+    AutoCommandBufferBuilderContextError,
+    CheckCopyImageToBufferError,
     SyncCommandBufferBuilderError
 });
 
@@ -659,6 +1632,10 @@ err_gen!(FillBufferError {
     CheckFillBufferError
 });
 
+err_gen!(DebugLabelError {
+    CheckDebugLabelError
+});
+
 err_gen!(DispatchError {
     AutoCommandBufferBuilderContextError,
     CheckPushConstantsValidityError,
@@ -669,6 +1646,7 @@ err_gen!(DispatchError {
 
 err_gen!(DrawError {
     AutoCommandBufferBuilderContextError,
+    IncompatibleRenderPassError,
     CheckDynamicStateValidityError,
     CheckPushConstantsValidityError,
     CheckDescriptorSetsValidityError,
@@ -678,28 +1656,64 @@ err_gen!(DrawError {
 
 err_gen!(DrawIndexedError {
     AutoCommandBufferBuilderContextError,
+    IncompatibleRenderPassError,
     CheckDynamicStateValidityError,
     CheckPushConstantsValidityError,
     CheckDescriptorSetsValidityError,
     CheckVertexBufferError,
     CheckIndexBufferError,
+    CheckDrawRangeError,
+    ReadLockError,
     SyncCommandBufferBuilderError
 });
 
 err_gen!(DrawIndirectError {
     AutoCommandBufferBuilderContextError,
+    IncompatibleRenderPassError,
+    CheckDynamicStateValidityError,
+    CheckPushConstantsValidityError,
+    CheckDescriptorSetsValidityError,
+    CheckVertexBufferError,
+    SyncCommandBufferBuilderError
+});
+
+err_gen!(DrawWithShadersError {
+    AutoCommandBufferBuilderContextError,
+    CheckPushConstantsValidityError,
+    CheckDescriptorSetsValidityError,
+    SyncCommandBufferBuilderError
+});
+
+err_gen!(ExecuteGeneratedCommandsError {
+    AutoCommandBufferBuilderContextError,
+    IncompatibleRenderPassError,
     CheckDynamicStateValidityError,
     CheckPushConstantsValidityError,
     CheckDescriptorSetsValidityError,
     CheckVertexBufferError,
+    CheckGeneratedCommandsError,
+    SyncCommandBufferBuilderError
+});
+
+err_gen!(HostReadBarrierError {
+    AutoCommandBufferBuilderContextError,
     SyncCommandBufferBuilderError
 });
 
+err_gen!(ResetQueryPoolError {
+    AutoCommandBufferBuilderContextError,
+    CheckQueryError
+});
+
 err_gen!(UpdateBufferError {
     AutoCommandBufferBuilderContextError,
     CheckUpdateBufferError
 });
 
+err_gen!(WriteTimestampError {
+    CheckQueryError
+});
+
 #[derive(Debug, Copy, Clone)]
 pub enum AutoCommandBufferBuilderContextError {
     /// Operation forbidden in a secondary command buffer.
@@ -711,6 +1725,8 @@ pub enum AutoCommandBufferBuilderContextError {
     /// Tried to end a render pass with subpasses remaining, or tried to go to next subpass with no
     /// subpass remaining.
     NumSubpassesMismatch,
+    /// Tried to draw with shader objects while no vertex shader object is bound.
+    NoShadersBound,
 }
 
 impl error::Error for AutoCommandBufferBuilderContextError {
@@ -730,6 +1746,9 @@ impl error::Error for AutoCommandBufferBuilderContextError {
                 "tried to end a render pass with subpasses remaining, or tried to go to next \
                  subpass with no subpass remaining"
             },
+            AutoCommandBufferBuilderContextError::NoShadersBound => {
+                "tried to draw with shader objects while no vertex shader object is bound"
+            },
         }
     }
 }
@@ -740,3 +1759,615 @@ impl fmt::Display for AutoCommandBufferBuilderContextError {
         write!(fmt, "{}", error::Error::description(self))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CStr;
+    use std::iter;
+    use std::sync::Arc;
+
+    use buffer::BufferUsage;
+    use buffer::CpuAccessibleBuffer;
+    use command_buffer::AutoCommandBufferBuilder;
+    use command_buffer::CommandBuffer;
+    use command_buffer::CommandBufferExecError;
+    use command_buffer::DebugLabelError;
+    use command_buffer::DynamicState;
+    use command_buffer::validity::CheckClearValuesError;
+    use command_buffer::validity::CheckDebugLabelError;
+    use descriptor::pipeline_layout::EmptyPipelineDesc;
+    use descriptor::pipeline_layout::PipelineLayoutAbstract;
+    use device::Device;
+    use device::DeviceExtensions;
+    use device::DeviceOwned;
+    use features::Features;
+    use format::ClearValue;
+    use format::Format;
+    use framebuffer::AttachmentKind;
+    use framebuffer::Framebuffer;
+    use framebuffer::FramebufferAbstract;
+    use framebuffer::FramebufferSys;
+    use framebuffer::IncompatibleRenderPassAttachmentError;
+    use framebuffer::IncompatibleRenderPassError;
+    use framebuffer::LayoutAttachmentDescription;
+    use framebuffer::LayoutPassDependencyDescription;
+    use framebuffer::LayoutPassDescription;
+    use framebuffer::RenderPassAbstract;
+    use framebuffer::RenderPassDesc;
+    use framebuffer::RenderPassDescClearValues;
+    use framebuffer::RenderPassSys;
+    use framebuffer::Subpass;
+    use image::AttachmentImage;
+    use image::ImageViewAccess;
+    use instance::Instance;
+    use instance::InstanceExtensions;
+    use instance::PhysicalDevice;
+    use pipeline::GraphicsPipeline;
+    use pipeline::depth_stencil::DynamicStencilValue;
+    use pipeline::depth_stencil::StencilFaceFlags;
+    use pipeline::shader::EmptyShaderInterfaceDef;
+    use pipeline::shader::ShaderModule;
+    use pipeline::vertex::SingleBufferDefinition;
+    use pipeline::vertex::Vertex;
+    use pipeline::vertex::VertexMemberInfo;
+    use pipeline::viewport::Viewport;
+    use sync::GpuFuture;
+    use tests::BASIC_FS;
+    use tests::FULL_SCREEN_VS;
+    use super::BeginRenderPassError;
+    use super::DrawError;
+    use super::stencil_state;
+
+    // Vertex of the buffers passed to the draw commands. The vertex shader doesn't read any
+    // attribute.
+    #[derive(Debug, Copy, Clone)]
+    struct Word(u32);
+
+    unsafe impl Vertex for Word {
+        #[inline]
+        fn member(_: &str) -> Option<VertexMemberInfo> {
+            None
+        }
+    }
+
+    // Builds a pipeline that draws a full-screen triangle in `subpass`.
+    fn pipeline<Rp>(device: &Arc<Device>, subpass: Subpass<Rp>)
+                    -> Arc<GraphicsPipeline<SingleBufferDefinition<Word>,
+                                            Box<PipelineLayoutAbstract + Send + Sync>,
+                                            Rp>>
+        where Rp: RenderPassAbstract
+    {
+        let vs = unsafe { ShaderModule::new(device.clone(), &FULL_SCREEN_VS) }.unwrap();
+        let fs = unsafe { ShaderModule::new(device.clone(), &BASIC_FS) }.unwrap();
+        let (vs_main, fs_main) = unsafe {
+            let name = CStr::from_bytes_with_nul_unchecked(b"main\0");
+            (vs.vertex_shader_entry_point::<(), _, _, _>(name,
+                                                         EmptyShaderInterfaceDef,
+                                                         EmptyShaderInterfaceDef,
+                                                         EmptyPipelineDesc),
+             fs.fragment_shader_entry_point::<(), _, _, _>(name,
+                                                           EmptyShaderInterfaceDef,
+                                                           EmptyShaderInterfaceDef,
+                                                           EmptyPipelineDesc))
+        };
+
+        let viewport = Viewport {
+            origin: [0.0, 0.0],
+            dimensions: [32.0, 32.0],
+            depth_range: 0.0 .. 1.0,
+        };
+        Arc::new(GraphicsPipeline::start()
+                     .vertex_input_single_buffer::<Word>()
+                     .vertex_shader(vs_main, ())
+                     .triangle_list()
+                     .viewports(iter::once(viewport))
+                     .fragment_shader(fs_main, ())
+                     .render_pass(subpass)
+                     .build(device.clone())
+                     .unwrap())
+    }
+
+    // Framebuffer that describes itself with the attachments of another render pass than the one
+    // it was created with.
+    struct OtherDescFramebuffer {
+        framebuffer: Arc<FramebufferAbstract + Send + Sync>,
+        render_pass: Arc<RenderPassAbstract + Send + Sync>,
+    }
+
+    unsafe impl DeviceOwned for OtherDescFramebuffer {
+        fn device(&self) -> &Arc<Device> {
+            self.framebuffer.device()
+        }
+    }
+
+    unsafe impl RenderPassDesc for OtherDescFramebuffer {
+        fn num_attachments(&self) -> usize {
+            self.render_pass.num_attachments()
+        }
+
+        fn attachment_desc(&self, num: usize) -> Option<LayoutAttachmentDescription> {
+            self.render_pass.attachment_desc(num)
+        }
+
+        fn num_subpasses(&self) -> usize {
+            self.render_pass.num_subpasses()
+        }
+
+        fn subpass_desc(&self, num: usize) -> Option<LayoutPassDescription> {
+            self.render_pass.subpass_desc(num)
+        }
+
+        fn num_dependencies(&self) -> usize {
+            self.render_pass.num_dependencies()
+        }
+
+        fn dependency_desc(&self, num: usize) -> Option<LayoutPassDependencyDescription> {
+            self.render_pass.dependency_desc(num)
+        }
+    }
+
+    unsafe impl RenderPassDescClearValues<Vec<ClearValue>> for OtherDescFramebuffer {
+        fn convert_clear_values(&self, values: Vec<ClearValue>)
+                                -> Box<Iterator<Item = ClearValue>> {
+            Box::new(values.into_iter())
+        }
+    }
+
+    unsafe impl RenderPassAbstract for OtherDescFramebuffer {
+        fn inner(&self) -> RenderPassSys {
+            RenderPassAbstract::inner(&self.framebuffer)
+        }
+    }
+
+    unsafe impl FramebufferAbstract for OtherDescFramebuffer {
+        fn inner(&self) -> FramebufferSys {
+            FramebufferAbstract::inner(&self.framebuffer)
+        }
+
+        fn dimensions(&self) -> [u32; 3] {
+            self.framebuffer.dimensions()
+        }
+
+        fn attachments(&self) -> Vec<&ImageViewAccess> {
+            FramebufferAbstract::attachments(&self.framebuffer)
+        }
+    }
+
+    #[test]
+    fn one_time_submit_twice() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let cb = AutoCommandBufferBuilder::primary_one_time_submit(device, queue.family())
+            .unwrap()
+            .build()
+            .unwrap();
+        let cb = Arc::new(cb);
+
+        cb.clone()
+            .execute(queue.clone())
+            .unwrap()
+            .then_signal_fence_and_flush()
+            .unwrap()
+            .wait(None)
+            .unwrap();
+
+        match cb.execute(queue) {
+            Err(CommandBufferExecError::OneTimeSubmitAlreadySubmitted) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn one_time_submit_releases_resources() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let source = CpuAccessibleBuffer::from_data(device.clone(), BufferUsage::all(),
+                                                    iter::once(queue.family()), 5u32)
+            .unwrap();
+        let destination = CpuAccessibleBuffer::from_data(device.clone(), BufferUsage::all(),
+                                                         iter::once(queue.family()), 0u32)
+            .unwrap();
+
+        let cb = AutoCommandBufferBuilder::primary_one_time_submit(device, queue.family())
+            .unwrap()
+            .copy_buffer(source.clone(), destination.clone())
+            .unwrap()
+            .build()
+            .unwrap();
+        let cb = Arc::new(cb);
+        assert!(Arc::strong_count(&source) >= 2);
+
+        cb.clone()
+            .execute(queue)
+            .unwrap()
+            .then_signal_fence_and_flush()
+            .unwrap()
+            .wait(None)
+            .unwrap();
+
+        // The command buffer is still alive, but no longer holds the buffers.
+        assert_eq!(Arc::strong_count(&source), 1);
+        assert_eq!(Arc::strong_count(&destination), 1);
+        assert_eq!(*destination.read().unwrap(), 5);
+        drop(cb);
+    }
+
+    #[test]
+    fn statistics() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let source = CpuAccessibleBuffer::from_data(device.clone(), BufferUsage::all(),
+                                                    iter::once(queue.family()), 5u32)
+            .unwrap();
+        let destination = CpuAccessibleBuffer::from_data(device.clone(), BufferUsage::all(),
+                                                         iter::once(queue.family()), 0u32)
+            .unwrap();
+
+        let cb = AutoCommandBufferBuilder::new(device, queue.family())
+            .unwrap()
+            .fill_buffer(destination.clone(), 0)
+            .unwrap()
+            .copy_buffer(source.clone(), destination.clone())
+            .unwrap()
+            .build()
+            .unwrap();
+
+        assert_eq!(cb.recorded_command_count(), 2);
+        assert_eq!(cb.resource_reference_count(), 2);
+    }
+
+    #[test]
+    fn debug_labels_require_extension() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let mut builder = AutoCommandBufferBuilder::new(device, queue.family()).unwrap();
+        match builder.debug_scope("Scope") {
+            Err(DebugLabelError::CheckDebugLabelError(err)) => {
+                assert_eq!(err, CheckDebugLabelError::ExtensionNotEnabled)
+            },
+            _ => panic!(),
+        }
+        match builder.begin_debug_label("Label", [1.0; 4]) {
+            Err(DebugLabelError::CheckDebugLabelError(err)) => {
+                assert_eq!(err, CheckDebugLabelError::ExtensionNotEnabled)
+            },
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn debug_scopes_are_balanced() {
+        let extensions = InstanceExtensions {
+            ext_debug_utils: true,
+            ..InstanceExtensions::none()
+        };
+        let instance = match Instance::new(None, &extensions, None) {
+            Ok(i) => i,
+            Err(_) => return,
+        };
+        let physical = match PhysicalDevice::enumerate(&instance).next() {
+            Some(p) => p,
+            None => return,
+        };
+        let family = match physical.queue_families().find(|q| q.supports_graphics()) {
+            Some(q) => q,
+            None => return,
+        };
+        let (device, mut queues) = Device::new(&physical, &Features::none(),
+                                               &DeviceExtensions::none(),
+                                               [(family, 0.5)].iter().cloned())
+            .unwrap();
+        let queue = queues.next().unwrap();
+
+        let buffer = CpuAccessibleBuffer::from_data(device.clone(), BufferUsage::all(),
+                                                    iter::once(queue.family()), 0u32)
+            .unwrap();
+
+        let mut builder = AutoCommandBufferBuilder::new(device, queue.family())
+            .unwrap()
+            .enable_capture();
+        {
+            let _outer = builder.debug_scope("Outer").unwrap();
+            {
+                let _inner = builder.debug_scope("Inner").unwrap();
+                builder = builder.fill_buffer(buffer.clone(), 1).unwrap();
+            }
+            builder = builder.fill_buffer(buffer.clone(), 2).unwrap();
+        }
+        let _unclosed = builder.debug_scope("Unclosed").unwrap();
+        let cb = builder.build().unwrap();
+
+        let commands = cb.capture()
+            .unwrap()
+            .lines()
+            .filter_map(|line| line.split_whitespace().next())
+            .filter(|name| name.ends_with("_debug_utils_label") || *name == "fill_buffer")
+            .collect::<Vec<_>>();
+        assert_eq!(commands,
+                   ["begin_debug_utils_label",
+                    "begin_debug_utils_label",
+                    "fill_buffer",
+                    "end_debug_utils_label",
+                    "fill_buffer",
+                    "end_debug_utils_label",
+                    "begin_debug_utils_label",
+                    "end_debug_utils_label"]);
+    }
+
+    #[test]
+    fn host_read_barrier_after_copy() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let source = CpuAccessibleBuffer::from_data(device.clone(), BufferUsage::all(),
+                                                    iter::once(queue.family()), 5u32)
+            .unwrap();
+        let destination = CpuAccessibleBuffer::from_data(device.clone(), BufferUsage::all(),
+                                                         iter::once(queue.family()), 0u32)
+            .unwrap();
+
+        let cb = AutoCommandBufferBuilder::new(device, queue.family())
+            .unwrap()
+            .enable_capture()
+            .copy_buffer(source.clone(), destination.clone())
+            .unwrap()
+            .host_read_barrier(destination.clone())
+            .unwrap()
+            .build()
+            .unwrap();
+
+        // The write of the copy is made available to the host stage.
+        {
+            let capture = cb.capture().unwrap();
+            assert!(capture.contains("pipeline_barrier transfer -> host"));
+            assert!(capture.contains("transfer_write -> host_read"));
+        }
+
+        cb.execute(queue)
+            .unwrap()
+            .then_signal_fence_and_flush()
+            .unwrap()
+            .wait(None)
+            .unwrap();
+        assert_eq!(*destination.read().unwrap(), 5);
+    }
+
+    #[test]
+    fn begin_render_pass_wrong_clear_values() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let rp = Arc::new(single_pass_renderpass!(device.clone(),
+            attachments: {
+                color: { load: Clear, store: Store, format: Format::R8G8B8A8Unorm, samples: 1, }
+            },
+            pass: { color: [color], depth_stencil: {} }
+        ).unwrap());
+        let image = AttachmentImage::new(device.clone(), [32, 32], Format::R8G8B8A8Unorm)
+            .unwrap();
+        let framebuffer = Arc::new(Framebuffer::start(rp).add(image).unwrap().build().unwrap());
+
+        let builder = || AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap();
+
+        match builder().begin_render_pass(framebuffer.clone(), false, Vec::<ClearValue>::new()) {
+            Err(BeginRenderPassError::CheckClearValuesError(err)) => {
+                assert_eq!(err,
+                           CheckClearValuesError::WrongNumberOfClearValues {
+                               expected: 1,
+                               obtained: 0,
+                           })
+            },
+            _ => panic!(),
+        }
+
+        let values = vec![ClearValue::Uint([0; 4])];
+        match builder().begin_render_pass(framebuffer.clone(), false, values) {
+            Err(BeginRenderPassError::CheckClearValuesError(err)) => {
+                assert_eq!(err, CheckClearValuesError::InvalidClearValue { attachment: 0 })
+            },
+            _ => panic!(),
+        }
+
+        let values = vec![ClearValue::Float([0.0, 0.0, 0.0, 1.0])];
+        builder().begin_render_pass(framebuffer, false, values).unwrap();
+    }
+
+    #[test]
+    fn begin_render_pass_incompatible_attachment() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let render_pass = |format: Format| {
+            Arc::new(single_pass_renderpass!(device.clone(),
+                attachments: {
+                    color: { load: Clear, store: Store, format: format, samples: 1, }
+                },
+                pass: { color: [color], depth_stencil: {} }
+            ).unwrap())
+        };
+        let image = AttachmentImage::new(device.clone(), [32, 32], Format::R8G8B8A8Unorm)
+            .unwrap();
+        let framebuffer = Framebuffer::start(render_pass(Format::R8G8B8A8Unorm))
+            .add(image)
+            .unwrap()
+            .build()
+            .unwrap();
+        let framebuffer = OtherDescFramebuffer {
+            framebuffer: Arc::new(framebuffer),
+            render_pass: render_pass(Format::B8G8R8A8Unorm),
+        };
+
+        let builder = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap();
+        let values = vec![ClearValue::Float([0.0, 0.0, 0.0, 1.0])];
+        let err = match builder.begin_render_pass(framebuffer, false, values) {
+            Err(BeginRenderPassError::IncompatibleRenderPassAttachmentError(err)) => err,
+            _ => panic!(),
+        };
+        match err {
+            IncompatibleRenderPassAttachmentError::FormatMismatch { expected, obtained } => {
+                assert_eq!(expected, Format::B8G8R8A8Unorm);
+                assert_eq!(obtained, Format::R8G8B8A8Unorm);
+            },
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn draw_incompatible_pipeline() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let render_pass = |format: Format| {
+            Arc::new(single_pass_renderpass!(device.clone(),
+                attachments: {
+                    color: { load: Clear, store: Store, format: format, samples: 1, }
+                },
+                pass: { color: [color], depth_stencil: {} }
+            ).unwrap())
+        };
+        let begin = |format: Format| {
+            let image = AttachmentImage::new(device.clone(), [32, 32], format).unwrap();
+            let framebuffer = Framebuffer::start(render_pass(format))
+                .add(image)
+                .unwrap()
+                .build()
+                .unwrap();
+            AutoCommandBufferBuilder::new(device.clone(), queue.family())
+                .unwrap()
+                .begin_render_pass(Arc::new(framebuffer),
+                                   false,
+                                   vec![ClearValue::Float([0.0, 0.0, 0.0, 1.0])])
+                .unwrap()
+        };
+
+        let pipeline = pipeline(&device,
+                                Subpass::from(render_pass(Format::R8G8B8A8Unorm), 0).unwrap());
+        let vertices = CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::all(),
+                                                      iter::once(queue.family()),
+                                                      (0 .. 3).map(Word))
+            .unwrap();
+
+        let result = begin(Format::B8G8R8A8Unorm)
+            .draw(pipeline.clone(), DynamicState::none(), vertices.clone(), (), ());
+        match result {
+            Err(DrawError::IncompatibleRenderPassError(err)) => {
+                assert_eq!(err,
+                           IncompatibleRenderPassError::FormatMismatch {
+                               subpass: 0,
+                               kind: AttachmentKind::Color,
+                               index: 0,
+                               expected: Format::R8G8B8A8Unorm,
+                               obtained: Format::B8G8R8A8Unorm,
+                           })
+            },
+            _ => panic!(),
+        }
+
+        // Another render pass with the same attachments is compatible.
+        begin(Format::R8G8B8A8Unorm)
+            .draw(pipeline, DynamicState::none(), vertices, (), ())
+            .unwrap()
+            .end_render_pass()
+            .unwrap();
+    }
+
+    #[test]
+    fn draw_pipeline_of_other_subpass() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let render_pass = Arc::new(ordered_passes_renderpass!(device.clone(),
+            attachments: {
+                a: { load: Clear, store: Store, format: Format::R8G8B8A8Unorm, samples: 1, },
+                b: { load: Clear, store: Store, format: Format::R8G8B8A8Unorm, samples: 1, }
+            },
+            passes: [
+                { color: [a], depth_stencil: {}, input: [] },
+                { color: [b], depth_stencil: {}, input: [] }
+            ]
+        ).unwrap());
+        let image = || {
+            AttachmentImage::new(device.clone(), [32, 32], Format::R8G8B8A8Unorm).unwrap()
+        };
+        let framebuffer = Arc::new(Framebuffer::start(render_pass.clone())
+                                       .add(image())
+                                       .unwrap()
+                                       .add(image())
+                                       .unwrap()
+                                       .build()
+                                       .unwrap());
+        let begin = || {
+            let values = vec![ClearValue::Float([0.0, 0.0, 0.0, 1.0]); 2];
+            AutoCommandBufferBuilder::new(device.clone(), queue.family())
+                .unwrap()
+                .begin_render_pass(framebuffer.clone(), false, values)
+                .unwrap()
+        };
+
+        let pipeline = pipeline(&device, Subpass::from(render_pass, 1).unwrap());
+        let vertices = CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::all(),
+                                                      iter::once(queue.family()),
+                                                      (0 .. 3).map(Word))
+            .unwrap();
+
+        match begin().draw(pipeline.clone(), DynamicState::none(), vertices.clone(), (), ()) {
+            Err(DrawError::IncompatibleRenderPassError(err)) => {
+                assert_eq!(err,
+                           IncompatibleRenderPassError::SubpassIndexMismatch {
+                               expected: 1,
+                               obtained: 0,
+                           })
+            },
+            _ => panic!(),
+        }
+
+        begin()
+            .next_subpass(false)
+            .unwrap()
+            .draw(pipeline, DynamicState::none(), vertices, (), ())
+            .unwrap()
+            .end_render_pass()
+            .unwrap();
+    }
+
+    #[test]
+    fn exclusive_reuse_after_finished() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let cb = AutoCommandBufferBuilder::new(device, queue.family())
+            .unwrap()
+            .build()
+            .unwrap();
+        let cb = Arc::new(cb);
+
+        let future = cb.clone().execute(queue.clone()).unwrap();
+        match cb.clone().execute(queue.clone()) {
+            Err(CommandBufferExecError::ExclusiveAlreadyInUse) => (),
+            _ => panic!(),
+        }
+
+        future.then_signal_fence_and_flush().unwrap().wait(None).unwrap();
+        cb.execute(queue).unwrap();
+    }
+
+    #[test]
+    fn simultaneous_use() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let cb = AutoCommandBufferBuilder::primary_simultaneous_use(device, queue.family())
+            .unwrap()
+            .build()
+            .unwrap();
+        let cb = Arc::new(cb);
+
+        let _first = cb.clone().execute(queue.clone()).unwrap();
+        let _second = cb.execute(queue).unwrap();
+    }
+
+    #[test]
+    fn stencil_state_faces() {
+        let mut calls = Vec::new();
+        stencil_state(DynamicStencilValue::both(3), |face, v| calls.push((face, v)));
+        assert_eq!(calls, vec![(StencilFaceFlags::FrontAndBack, 3)]);
+
+        let mut calls = Vec::new();
+        let value = DynamicStencilValue { front: 1, back: 2 };
+        stencil_state(value, |face, v| calls.push((face, v)));
+        assert_eq!(calls, vec![(StencilFaceFlags::Front, 1), (StencilFaceFlags::Back, 2)]);
+    }
+}
@@ -0,0 +1,1043 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Textual capture of the commands and pipeline barriers recorded by a
+//! `SyncCommandBufferBuilder`.
+//!
+//! The capture is meant to be compared between two runs, therefore it doesn't contain anything
+//! that depends on the driver or on the order in which the objects were created. Buffers and
+//! images are named `buf0`, `buf1`, `img0`, and so on, in the order in which the command buffer
+//! first uses them.
+
+use fnv::FnvHashMap;
+
+use buffer::BufferAccess;
+use command_buffer::sys::UnsafeCommandBufferBuilderPipelineBarrier;
+use image::ImageAccess;
+use sync::AccessFlagBits;
+use sync::PipelineStages;
+use vk;
+use VulkanObject;
+
+/// Accumulates the text of a capture while a command buffer is being built.
+pub struct CommandCapture {
+    // Identifiers of the buffers and images that have been used so far.
+    buffers: FnvHashMap<vk::Buffer, usize>,
+    images: FnvHashMap<vk::Image, usize>,
+
+    // Identifiers of the resources used by each command, indexed by the position of the command
+    // in the builder.
+    command_resources: Vec<Vec<String>>,
+
+    // The capture itself.
+    text: String,
+}
+
+impl CommandCapture {
+    /// Builds an empty capture.
+    #[inline]
+    pub fn new() -> CommandCapture {
+        CommandCapture {
+            buffers: FnvHashMap::default(),
+            images: FnvHashMap::default(),
+            command_resources: Vec::new(),
+            text: String::new(),
+        }
+    }
+
+    /// Registers that the command at position `command_id` uses `buffer`.
+    #[inline]
+    pub fn buffer_use(&mut self, command_id: usize, buffer: &BufferAccess) {
+        let id = self.buffer_id(buffer.inner().buffer.internal_object());
+        self.resources_of(command_id).push(id);
+    }
+
+    /// Registers that the command at position `command_id` uses `image`.
+    #[inline]
+    pub fn image_use(&mut self, command_id: usize, image: &ImageAccess) {
+        let id = self.image_id(image.inner().image.internal_object());
+        self.resources_of(command_id).push(id);
+    }
+
+    /// Adds a line for the command at position `command_id`, which is being sent to the Vulkan
+    /// command buffer.
+    pub fn command(&mut self, command_id: usize, name: &str) {
+        self.text.push_str(name);
+        if let Some(resources) = self.command_resources.get(command_id) {
+            for resource in resources {
+                self.text.push(' ');
+                self.text.push_str(resource);
+            }
+        }
+        self.text.push('\n');
+    }
+
+    /// Adds the lines of a pipeline barrier that is being sent to the Vulkan command buffer.
+    /// Does nothing if the barrier is empty.
+    pub fn pipeline_barrier(&mut self, barrier: &UnsafeCommandBufferBuilderPipelineBarrier) {
+        if barrier.is_empty() {
+            return;
+        }
+
+        let by_region = if (barrier.dependency_flags & vk::DEPENDENCY_BY_REGION_BIT) != 0 {
+            " by_region"
+        } else {
+            ""
+        };
+        self.text.push_str(&format!("pipeline_barrier {} -> {}{}\n",
+                                    stage_names(barrier.src_stage_mask),
+                                    stage_names(barrier.dst_stage_mask),
+                                    by_region));
+
+        // The order of the barriers depends on the order of the resources in a hash map, so we
+        // sort them.
+        let mut lines = Vec::new();
+
+        for b in barrier.memory_barriers.iter() {
+            lines.push(format!("memory {} -> {}",
+                               access_names(b.srcAccessMask),
+                               access_names(b.dstAccessMask)));
+        }
+
+        for b in barrier.buffer_barriers.iter() {
+            let id = self.buffer_id(b.buffer);
+            // A barrier that covers the end of the buffer has a size of `WHOLE_SIZE`.
+            let end = if b.size == vk::WHOLE_SIZE {
+                "end".to_owned()
+            } else {
+                (b.offset + b.size).to_string()
+            };
+            lines.push(format!("buffer {} {}..{} {} -> {}{}",
+                               id,
+                               b.offset,
+                               end,
+                               access_names(b.srcAccessMask),
+                               access_names(b.dstAccessMask),
+                               queue_transfer(b.srcQueueFamilyIndex, b.dstQueueFamilyIndex)));
+        }
+
+        for b in barrier.image_barriers.iter() {
+            let id = self.image_id(b.image);
+            let range = &b.subresourceRange;
+            lines.push(format!("image {} mipmaps {}..{} layers {}..{} {} -> {} {} -> {}{}",
+                               id,
+                               range.baseMipLevel,
+                               range.baseMipLevel + range.levelCount,
+                               range.baseArrayLayer,
+                               range.baseArrayLayer + range.layerCount,
+                               layout_name(b.oldLayout),
+                               layout_name(b.newLayout),
+                               access_names(b.srcAccessMask),
+                               access_names(b.dstAccessMask),
+                               queue_transfer(b.srcQueueFamilyIndex, b.dstQueueFamilyIndex)));
+        }
+
+        lines.sort();
+        for line in lines {
+            self.text.push_str("    ");
+            self.text.push_str(&line);
+            self.text.push('\n');
+        }
+    }
+
+    /// Returns the text of the capture.
+    #[inline]
+    pub fn into_text(self) -> String {
+        self.text
+    }
+
+    fn resources_of(&mut self, command_id: usize) -> &mut Vec<String> {
+        while self.command_resources.len() <= command_id {
+            self.command_resources.push(Vec::new());
+        }
+        &mut self.command_resources[command_id]
+    }
+
+    fn buffer_id(&mut self, buffer: vk::Buffer) -> String {
+        let next = self.buffers.len();
+        format!("buf{}", *self.buffers.entry(buffer).or_insert(next))
+    }
+
+    fn image_id(&mut self, image: vk::Image) -> String {
+        let next = self.images.len();
+        format!("img{}", *self.images.entry(image).or_insert(next))
+    }
+}
+
+fn stage_names(bits: vk::PipelineStageFlags) -> String {
+    join_names(PipelineStages::names_from_vk(bits))
+}
+
+fn access_names(bits: vk::AccessFlags) -> String {
+    join_names(AccessFlagBits::names_from_vk(bits))
+}
+
+fn join_names(names: Vec<&'static str>) -> String {
+    if names.is_empty() {
+        "none".to_owned()
+    } else {
+        names.join("|")
+    }
+}
+
+fn queue_transfer(src: u32, dest: u32) -> String {
+    if src == vk::QUEUE_FAMILY_IGNORED && dest == vk::QUEUE_FAMILY_IGNORED {
+        String::new()
+    } else {
+        format!(" queue {} -> {}", src, dest)
+    }
+}
+
+fn layout_name(layout: vk::ImageLayout) -> &'static str {
+    match layout {
+        vk::IMAGE_LAYOUT_UNDEFINED => "Undefined",
+        vk::IMAGE_LAYOUT_GENERAL => "General",
+        vk::IMAGE_LAYOUT_COLOR_ATTACHMENT_OPTIMAL => "ColorAttachmentOptimal",
+        vk::IMAGE_LAYOUT_DEPTH_STENCIL_ATTACHMENT_OPTIMAL => "DepthStencilAttachmentOptimal",
+        vk::IMAGE_LAYOUT_DEPTH_STENCIL_READ_ONLY_OPTIMAL => "DepthStencilReadOnlyOptimal",
+        vk::IMAGE_LAYOUT_SHADER_READ_ONLY_OPTIMAL => "ShaderReadOnlyOptimal",
+        vk::IMAGE_LAYOUT_TRANSFER_SRC_OPTIMAL => "TransferSrcOptimal",
+        vk::IMAGE_LAYOUT_TRANSFER_DST_OPTIMAL => "TransferDstOptimal",
+        vk::IMAGE_LAYOUT_PREINITIALIZED => "Preinitialized",
+        vk::IMAGE_LAYOUT_PRESENT_SRC_KHR => "PresentSrc",
+        vk::IMAGE_LAYOUT_SHARED_PRESENT_KHR => "SharedPresent",
+        _ => "Unknown",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp;
+    use std::collections::HashMap;
+    use std::env;
+    use std::ffi::CStr;
+    use std::fs::File;
+    use std::io::Write;
+    use std::iter;
+    use std::sync::Arc;
+
+    use buffer::BufferAccess;
+    use buffer::BufferUsage;
+    use buffer::CpuAccessibleBuffer;
+    use command_buffer::AutoCommandBufferBuilder;
+    use command_buffer::DrawIndirectCommand;
+    use command_buffer::DynamicState;
+    use command_buffer::sys::UnsafeCommandBufferBuilderPipelineBarrier;
+    use descriptor::descriptor::DescriptorBufferContentDesc;
+    use descriptor::descriptor::DescriptorBufferDesc;
+    use descriptor::descriptor::DescriptorDesc;
+    use descriptor::descriptor::DescriptorDescTy;
+    use descriptor::descriptor::DescriptorImageDesc;
+    use descriptor::descriptor::DescriptorImageDescArray;
+    use descriptor::descriptor::DescriptorImageDescDimensions;
+    use descriptor::descriptor::ShaderStages;
+    use descriptor::descriptor_set::SimpleDescriptorSetBufferExt;
+    use descriptor::descriptor_set::SimpleDescriptorSetBuilder;
+    use descriptor::descriptor_set::SimpleDescriptorSetImageExt;
+    use descriptor::pipeline_layout::EmptyPipelineDesc;
+    use descriptor::pipeline_layout::PipelineLayout;
+    use descriptor::pipeline_layout::PipelineLayoutDesc;
+    use descriptor::pipeline_layout::PipelineLayoutDescNames;
+    use descriptor::pipeline_layout::PipelineLayoutDescPcRange;
+    use device::Device;
+    use device::Queue;
+    use format::ClearValue;
+    use format::Format;
+    use framebuffer::Framebuffer;
+    use framebuffer::FramebufferAbstract;
+    use framebuffer::RenderPassAbstract;
+    use framebuffer::Subpass;
+    use image::AttachmentImage;
+    use image::Dimensions;
+    use image::ImageLayout;
+    use image::ImmutableImage;
+    use image::MipmapsCount;
+    use image::immutable::ImmutableImageInitialization;
+    use image::StorageImage;
+    use pipeline::ComputePipeline;
+    use pipeline::GraphicsPipeline;
+    use pipeline::shader::EmptyShaderInterfaceDef;
+    use pipeline::shader::ShaderModule;
+    use pipeline::vertex::Vertex;
+    use pipeline::vertex::VertexMemberInfo;
+    use pipeline::viewport::Viewport;
+    use sampler::Sampler;
+    use sync::AccessFlagBits;
+    use sync::PipelineStages;
+    use tests::FULL_SCREEN_VS;
+    use vk;
+
+    use super::CommandCapture;
+
+    #[test]
+    fn barrier_text() {
+        let mut capture = CommandCapture::new();
+
+        let mut barrier = UnsafeCommandBufferBuilderPipelineBarrier::new();
+        barrier.src_stage_mask = vk::PIPELINE_STAGE_TRANSFER_BIT;
+        barrier.dst_stage_mask = vk::PIPELINE_STAGE_VERTEX_INPUT_BIT |
+            vk::PIPELINE_STAGE_FRAGMENT_SHADER_BIT;
+        for &handle in [7, 3].iter() {
+            barrier.buffer_barriers.push(vk::BufferMemoryBarrier {
+                                             sType: vk::STRUCTURE_TYPE_BUFFER_MEMORY_BARRIER,
+                                             pNext: ::std::ptr::null(),
+                                             srcAccessMask: vk::ACCESS_TRANSFER_WRITE_BIT,
+                                             dstAccessMask: vk::ACCESS_VERTEX_ATTRIBUTE_READ_BIT,
+                                             srcQueueFamilyIndex: vk::QUEUE_FAMILY_IGNORED,
+                                             dstQueueFamilyIndex: vk::QUEUE_FAMILY_IGNORED,
+                                             buffer: handle,
+                                             offset: 0,
+                                             size: 256,
+                                         });
+        }
+
+        capture.command(0, "copy_buffer");
+        capture.pipeline_barrier(&barrier);
+        capture.pipeline_barrier(&UnsafeCommandBufferBuilderPipelineBarrier::new());
+
+        // The buffers are numbered in the order of their first use, and the lines of a barrier
+        // are sorted.
+        let expected = "copy_buffer\n\
+                        pipeline_barrier transfer -> vertex_input|fragment_shader by_region\n    \
+                        buffer buf0 0..256 transfer_write -> vertex_attribute_read\n    \
+                        buffer buf1 0..256 transfer_write -> vertex_attribute_read\n";
+        assert_eq!(capture.into_text(), expected);
+    }
+
+    // Builds a render pass that loads and stores a single color attachment, and leaves it in
+    // `final_layout`.
+    fn color_render_pass(device: &Arc<Device>, final_layout: ImageLayout)
+                         -> Arc<RenderPassAbstract + Send + Sync> {
+        Arc::new(single_pass_renderpass!(device.clone(),
+            attachments: {
+                color: {
+                    load: Load,
+                    store: Store,
+                    format: Format::R8G8B8A8Unorm,
+                    samples: 1,
+                    final_layout: final_layout,
+                }
+            },
+            pass: { color: [color], depth_stencil: {} }
+        ).unwrap())
+    }
+
+    fn framebuffer(render_pass: &Arc<RenderPassAbstract + Send + Sync>,
+                   image: Arc<AttachmentImage<Format>>)
+                   -> Arc<FramebufferAbstract + Send + Sync> {
+        Arc::new(Framebuffer::start(render_pass.clone())
+                     .add(image)
+                     .unwrap()
+                     .build()
+                     .unwrap())
+    }
+
+    // Vertex of the buffers created by the scenarios. The vertex shader of the draws doesn't read
+    // any attribute, but the buffers are still bound and synchronized as vertex buffers.
+    #[derive(Debug, Copy, Clone)]
+    struct Word(u32);
+
+    unsafe impl Vertex for Word {
+        #[inline]
+        fn member(_: &str) -> Option<VertexMemberInfo> {
+            None
+        }
+    }
+
+    // Resource created by a scenario.
+    enum Resource {
+        Buffer(Arc<CpuAccessibleBuffer<[Word]>>),
+        Indirect(Arc<CpuAccessibleBuffer<[DrawIndirectCommand]>>),
+        // Immutable images are only ever written, through their initialization access.
+        Immutable(ImmutableImageInitialization<Format>),
+        Storage(Arc<StorageImage<Format>>),
+        Attachment(Arc<AttachmentImage<Format>>),
+    }
+
+    // Replays a scenario and returns the captures of its command buffers, one after the other.
+    //
+    // Each line of a scenario is a command followed by its parameters, separated with spaces.
+    // Empty lines and lines that start with `#` are ignored. The commands are:
+    //
+    // - `buffer <name> <size>` creates a buffer of `size` bytes. The size must be a multiple of 4.
+    // - `indirect_buffer <name> <count>` creates a buffer of `count` indirect draw commands.
+    // - `image <name> immutable|storage|attachment <width> <height>` creates an image with the
+    //   `R8G8B8A8Unorm` format.
+    // - `copy_buffer <src> <dest>`, `copy_buffer_to_image <src> <dest>` and
+    //   `fill_buffer <buffer> <data>` add the corresponding command.
+    // - `dispatch <resource>` dispatches a compute shader that writes to a buffer, bound as a
+    //   storage buffer, or to a storage image.
+    // - `draw <attachment> <vertices> <texture>` begins a render pass that loads and stores the
+    //   attachment, draws a triangle whose fragment shader samples the storage image `texture`,
+    //   and ends the render pass. `draw_indirect <attachment> <vertices> <indirect> <texture>`
+    //   does the same with an indirect draw.
+    // - `compute_write_buffer <buffer> <stages> <access>` and
+    //   `compute_write_image <image> <layout> <stages> <access>` add the corresponding barrier.
+    //   The stages and the access are lists of names separated with `|`.
+    // - `render_pass <attachment> <layout>` begins and ends a render pass that loads and stores
+    //   the attachment, and leaves it in the given layout.
+    // - `command_buffer` builds the current command buffer and starts a new one.
+    fn replay(device: &Arc<Device>, queue: &Arc<Queue>, scenario: &str) -> String {
+        let new_builder = || {
+            AutoCommandBufferBuilder::new(device.clone(), queue.family())
+                .unwrap()
+                .enable_capture()
+        };
+
+        let mut resources = HashMap::new();
+        let mut captures = Vec::new();
+        let mut builder = new_builder();
+
+        for line in scenario.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let words = line.split_whitespace().collect::<Vec<_>>();
+            builder = match words[0] {
+                "buffer" => {
+                    let size: u32 = words[2].parse().unwrap();
+                    assert_eq!(size % 4, 0);
+                    let buffer = CpuAccessibleBuffer::from_iter(device.clone(),
+                                                                BufferUsage::all(),
+                                                                Some(queue.family()),
+                                                                (0 .. size / 4).map(|_| Word(0)))
+                        .unwrap();
+                    resources.insert(words[1], Resource::Buffer(buffer));
+                    builder
+                },
+                "indirect_buffer" => {
+                    let count: u32 = words[2].parse().unwrap();
+                    let command = DrawIndirectCommand {
+                        vertex_count: 3,
+                        instance_count: 1,
+                        first_vertex: 0,
+                        first_instance: 0,
+                    };
+                    let buffer = CpuAccessibleBuffer::from_iter(device.clone(),
+                                                                BufferUsage::all(),
+                                                                Some(queue.family()),
+                                                                (0 .. count).map(|_| command))
+                        .unwrap();
+                    resources.insert(words[1], Resource::Indirect(buffer));
+                    builder
+                },
+                "image" => {
+                    let dimensions = Dimensions::Dim2d {
+                        width: words[3].parse().unwrap(),
+                        height: words[4].parse().unwrap(),
+                    };
+                    let format = Format::R8G8B8A8Unorm;
+                    let image = match words[2] {
+                        "immutable" => {
+                            let (_, init) = unsafe {
+                                ImmutableImage::uninitialized(device.clone(), dimensions, format,
+                                                              MipmapsCount::One,
+                                                              Some(queue.family()))
+                                    .unwrap()
+                            };
+                            Resource::Immutable(init)
+                        },
+                        "storage" => {
+                            Resource::Storage(StorageImage::new(device.clone(), dimensions, format,
+                                                                Some(queue.family()))
+                                                  .unwrap())
+                        },
+                        "attachment" => {
+                            Resource::Attachment(AttachmentImage::new(device.clone(),
+                                                                      [dimensions.width(),
+                                                                       dimensions.height()],
+                                                                      format)
+                                                     .unwrap())
+                        },
+                        kind => panic!("unknown image kind `{}`", kind),
+                    };
+                    resources.insert(words[1], image);
+                    builder
+                },
+                "copy_buffer" => {
+                    builder
+                        .copy_buffer(buffer(&resources, words[1]), buffer(&resources, words[2]))
+                        .unwrap()
+                },
+                "copy_buffer_to_image" => {
+                    let src = buffer(&resources, words[1]);
+                    match resources[words[2]] {
+                        Resource::Immutable(ref i) => builder.copy_buffer_to_image(src, i.clone()),
+                        Resource::Storage(ref i) => builder.copy_buffer_to_image(src, i.clone()),
+                        _ => panic!("`{}` can't be a transfer destination", words[2]),
+                    }.unwrap()
+                },
+                "fill_buffer" => {
+                    builder
+                        .fill_buffer(buffer(&resources, words[1]), words[2].parse().unwrap())
+                        .unwrap()
+                },
+                "dispatch" => {
+                    match resources[words[1]] {
+                        Resource::Storage(ref i) => dispatch_image(builder, device, i.clone()),
+                        _ => dispatch_buffer(builder, device, any_buffer(&resources, words[1])),
+                    }
+                },
+                "draw" => {
+                    draw(builder,
+                         device,
+                         attachment(&resources, words[1]),
+                         buffer(&resources, words[2]),
+                         None,
+                         storage_image(&resources, words[3]))
+                },
+                "draw_indirect" => {
+                    let indirect = match resources[words[3]] {
+                        Resource::Indirect(ref b) => b.clone(),
+                        _ => panic!("`{}` isn't an indirect buffer", words[3]),
+                    };
+                    draw(builder,
+                         device,
+                         attachment(&resources, words[1]),
+                         buffer(&resources, words[2]),
+                         Some(indirect),
+                         storage_image(&resources, words[4]))
+                },
+                "compute_write_buffer" => {
+                    builder
+                        .compute_write_buffer_barrier(any_buffer(&resources, words[1]),
+                                                      stages(words[2]),
+                                                      access(words[3]))
+                        .unwrap()
+                },
+                "compute_write_image" => {
+                    let layout = layout(words[2]);
+                    let stages = stages(words[3]);
+                    let access = access(words[4]);
+                    match resources[words[1]] {
+                        Resource::Immutable(ref i) => {
+                            builder.compute_write_image_barrier(i.clone(), layout, stages, access)
+                        },
+                        Resource::Storage(ref i) => {
+                            builder.compute_write_image_barrier(i.clone(), layout, stages, access)
+                        },
+                        Resource::Attachment(ref i) => {
+                            builder.compute_write_image_barrier(i.clone(), layout, stages, access)
+                        },
+                        _ => panic!("`{}` isn't an image", words[1]),
+                    }.unwrap()
+                },
+                "render_pass" => {
+                    let render_pass = color_render_pass(device, layout(words[2]));
+                    builder
+                        .begin_render_pass(framebuffer(&render_pass,
+                                                       attachment(&resources, words[1])),
+                                           false,
+                                           vec![ClearValue::None])
+                        .unwrap()
+                        .end_render_pass()
+                        .unwrap()
+                },
+                "command_buffer" => {
+                    captures.push(builder.build().unwrap().capture().unwrap().to_owned());
+                    new_builder()
+                },
+                command => panic!("unknown command `{}`", command),
+            };
+        }
+
+        captures.push(builder.build().unwrap().capture().unwrap().to_owned());
+
+        let mut output = String::new();
+        for (num, capture) in captures.iter().enumerate() {
+            output.push_str(&format!("command_buffer {}\n", num));
+            output.push_str(capture);
+        }
+        output
+    }
+
+    fn buffer(resources: &HashMap<&str, Resource>, name: &str) -> Arc<CpuAccessibleBuffer<[Word]>> {
+        match resources[name] {
+            Resource::Buffer(ref b) => b.clone(),
+            _ => panic!("`{}` isn't a buffer", name),
+        }
+    }
+
+    // Same as `buffer`, but also accepts indirect buffers.
+    fn any_buffer(resources: &HashMap<&str, Resource>, name: &str)
+                  -> Arc<BufferAccess + Send + Sync> {
+        match resources[name] {
+            Resource::Buffer(ref b) => b.clone() as Arc<_>,
+            Resource::Indirect(ref b) => b.clone() as Arc<_>,
+            _ => panic!("`{}` isn't a buffer", name),
+        }
+    }
+
+    fn storage_image(resources: &HashMap<&str, Resource>, name: &str)
+                     -> Arc<StorageImage<Format>> {
+        match resources[name] {
+            Resource::Storage(ref i) => i.clone(),
+            _ => panic!("`{}` isn't a storage image", name),
+        }
+    }
+
+    fn attachment(resources: &HashMap<&str, Resource>, name: &str)
+                  -> Arc<AttachmentImage<Format>> {
+        match resources[name] {
+            Resource::Attachment(ref i) => i.clone(),
+            _ => panic!("`{}` isn't an attachment image", name),
+        }
+    }
+
+    // Dispatches `WRITE_BUFFER_CS`, with one invocation for each 32-bits word of `buffer`.
+    fn dispatch_buffer(builder: AutoCommandBufferBuilder, device: &Arc<Device>,
+                       buffer: Arc<BufferAccess + Send + Sync>)
+                       -> AutoCommandBufferBuilder {
+        let desc = SingleDescriptorDesc {
+            ty: DescriptorDescTy::Buffer(DescriptorBufferDesc {
+                dynamic: Some(false),
+                storage: true,
+                content: DescriptorBufferContentDesc::F32,
+            }),
+            stages: ShaderStages::compute(),
+            readonly: false,
+        };
+        let pipeline = compute_pipeline(device, &WRITE_BUFFER_CS, desc);
+
+        let len = buffer.size() / 4;
+        let set = {
+            let builder = SimpleDescriptorSetBuilder::new(pipeline.clone(), 0);
+            Arc::new(SimpleDescriptorSetBufferExt::add_me(buffer, builder, "data")
+                         .unwrap()
+                         .build().unwrap())
+        };
+
+        builder.dispatch([len as u32, 1, 1], pipeline, set, ()).unwrap()
+    }
+
+    // Dispatches `WRITE_IMAGE_CS`, with one invocation for each texel of `image`.
+    fn dispatch_image(builder: AutoCommandBufferBuilder, device: &Arc<Device>,
+                      image: Arc<StorageImage<Format>>)
+                      -> AutoCommandBufferBuilder {
+        let desc = SingleDescriptorDesc {
+            ty: DescriptorDescTy::Image(DescriptorImageDesc {
+                sampled: false,
+                dimensions: DescriptorImageDescDimensions::TwoDimensional,
+                format: None,
+                multisampled: false,
+                array_layers: DescriptorImageDescArray::NonArrayed,
+            }),
+            stages: ShaderStages::compute(),
+            readonly: false,
+        };
+        let pipeline = compute_pipeline(device, &WRITE_IMAGE_CS, desc);
+
+        let dimensions = image.dimensions();
+        let set = {
+            let builder = SimpleDescriptorSetBuilder::new(pipeline.clone(), 0);
+            Arc::new(SimpleDescriptorSetImageExt::add_me(image, builder, "img")
+                         .unwrap()
+                         .build().unwrap())
+        };
+
+        builder
+            .dispatch([dimensions.width(), dimensions.height(), 1], pipeline, set, ())
+            .unwrap()
+    }
+
+    fn compute_pipeline(device: &Arc<Device>, spirv: &[u8], desc: SingleDescriptorDesc)
+                        -> Arc<ComputePipeline<PipelineLayout<SingleDescriptorDesc>>> {
+        let module = unsafe { ShaderModule::new(device.clone(), spirv) }.unwrap();
+        let main = unsafe {
+            let name = CStr::from_bytes_with_nul_unchecked(b"main\0");
+            module.compute_shader_entry_point::<(), _>(name, desc)
+        };
+        Arc::new(ComputePipeline::new(device.clone(), &main, &()).unwrap())
+    }
+
+    // Begins a render pass on `target`, draws a triangle whose fragment shader samples `texture`,
+    // and ends the render pass. The draw is indirect if `indirect` is `Some`.
+    fn draw(builder: AutoCommandBufferBuilder, device: &Arc<Device>,
+            target: Arc<AttachmentImage<Format>>, vertices: Arc<CpuAccessibleBuffer<[Word]>>,
+            indirect: Option<Arc<CpuAccessibleBuffer<[DrawIndirectCommand]>>>,
+            texture: Arc<StorageImage<Format>>)
+            -> AutoCommandBufferBuilder {
+        let render_pass = color_render_pass(device, ImageLayout::ColorAttachmentOptimal);
+        let dimensions = target.dimensions();
+
+        let vs = unsafe { ShaderModule::new(device.clone(), &FULL_SCREEN_VS) }.unwrap();
+        let fs = unsafe { ShaderModule::new(device.clone(), &SAMPLE_FS) }.unwrap();
+        let desc = SingleDescriptorDesc {
+            ty: DescriptorDescTy::CombinedImageSampler(DescriptorImageDesc {
+                sampled: true,
+                dimensions: DescriptorImageDescDimensions::TwoDimensional,
+                format: None,
+                multisampled: false,
+                array_layers: DescriptorImageDescArray::NonArrayed,
+            }),
+            stages: ShaderStages {
+                fragment: true,
+                ..ShaderStages::none()
+            },
+            readonly: true,
+        };
+        let (vs_main, fs_main) = unsafe {
+            let name = CStr::from_bytes_with_nul_unchecked(b"main\0");
+            (vs.vertex_shader_entry_point::<(), _, _, _>(name,
+                                                         EmptyShaderInterfaceDef,
+                                                         EmptyShaderInterfaceDef,
+                                                         EmptyPipelineDesc),
+             fs.fragment_shader_entry_point::<(), _, _, _>(name,
+                                                           EmptyShaderInterfaceDef,
+                                                           EmptyShaderInterfaceDef,
+                                                           desc))
+        };
+
+        let viewport = Viewport {
+            origin: [0.0, 0.0],
+            dimensions: [dimensions[0] as f32, dimensions[1] as f32],
+            depth_range: 0.0 .. 1.0,
+        };
+        let pipeline = Arc::new(GraphicsPipeline::start()
+                                    .vertex_input_single_buffer::<Word>()
+                                    .vertex_shader(vs_main, ())
+                                    .triangle_list()
+                                    .viewports(iter::once(viewport))
+                                    .fragment_shader(fs_main, ())
+                                    .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+                                    .build(device.clone())
+                                    .unwrap());
+
+        let set = {
+            let sampler = Sampler::simple_repeat_linear_no_mipmap(device.clone());
+            let builder = SimpleDescriptorSetBuilder::new(pipeline.clone(), 0);
+            Arc::new(SimpleDescriptorSetImageExt::add_me((texture, sampler), builder, "tex")
+                         .unwrap()
+                         .build().unwrap())
+        };
+
+        let builder = builder
+            .begin_render_pass(framebuffer(&render_pass, target), false, vec![ClearValue::None])
+            .unwrap();
+        let builder = match indirect {
+            Some(indirect) => {
+                builder
+                    .draw_indirect(pipeline, DynamicState::none(), vertices, indirect, set, ())
+                    .unwrap()
+            },
+            None => builder.draw(pipeline, DynamicState::none(), vertices, set, ()).unwrap(),
+        };
+        builder.end_render_pass().unwrap()
+    }
+
+    // Pipeline layout with a single descriptor, at binding 0 of set 0. Any name designates it.
+    #[derive(Debug, Clone)]
+    struct SingleDescriptorDesc {
+        ty: DescriptorDescTy,
+        stages: ShaderStages,
+        readonly: bool,
+    }
+
+    unsafe impl PipelineLayoutDesc for SingleDescriptorDesc {
+        fn num_sets(&self) -> usize {
+            1
+        }
+
+        fn num_bindings_in_set(&self, set: usize) -> Option<usize> {
+            if set == 0 { Some(1) } else { None }
+        }
+
+        fn descriptor(&self, set: usize, binding: usize) -> Option<DescriptorDesc> {
+            if set != 0 || binding != 0 {
+                return None;
+            }
+
+            Some(DescriptorDesc {
+                     ty: self.ty.clone(),
+                     array_count: 1,
+                     stages: self.stages,
+                     readonly: self.readonly,
+                 })
+        }
+
+        fn num_push_constants_ranges(&self) -> usize {
+            0
+        }
+
+        fn push_constants_range(&self, _: usize) -> Option<PipelineLayoutDescPcRange> {
+            None
+        }
+    }
+
+    unsafe impl PipelineLayoutDescNames for SingleDescriptorDesc {
+        fn descriptor_by_name(&self, _: &str) -> Option<(usize, usize)> {
+            Some((0, 0))
+        }
+    }
+
+    fn stages(names: &str) -> PipelineStages {
+        let mut stages = PipelineStages::none();
+        for name in names.split('|') {
+            match name {
+                "draw_indirect" => stages.draw_indirect = true,
+                "vertex_input" => stages.vertex_input = true,
+                "vertex_shader" => stages.vertex_shader = true,
+                "fragment_shader" => stages.fragment_shader = true,
+                "compute_shader" => stages.compute_shader = true,
+                "transfer" => stages.transfer = true,
+                "host" => stages.host = true,
+                _ => panic!("unknown stage `{}`", name),
+            }
+        }
+        stages
+    }
+
+    fn access(names: &str) -> AccessFlagBits {
+        let mut access = AccessFlagBits::none();
+        for name in names.split('|') {
+            match name {
+                "indirect_command_read" => access.indirect_command_read = true,
+                "index_read" => access.index_read = true,
+                "vertex_attribute_read" => access.vertex_attribute_read = true,
+                "uniform_read" => access.uniform_read = true,
+                "shader_read" => access.shader_read = true,
+                "shader_write" => access.shader_write = true,
+                "transfer_read" => access.transfer_read = true,
+                "transfer_write" => access.transfer_write = true,
+                "host_read" => access.host_read = true,
+                _ => panic!("unknown access `{}`", name),
+            }
+        }
+        access
+    }
+
+    fn layout(name: &str) -> ImageLayout {
+        match name {
+            "General" => ImageLayout::General,
+            "ColorAttachmentOptimal" => ImageLayout::ColorAttachmentOptimal,
+            "ShaderReadOnlyOptimal" => ImageLayout::ShaderReadOnlyOptimal,
+            "TransferSrcOptimal" => ImageLayout::TransferSrcOptimal,
+            "TransferDstOptimal" => ImageLayout::TransferDstOptimal,
+            _ => panic!("unknown layout `{}`", name),
+        }
+    }
+
+    // Compares a capture with the content of a golden file, and panics with the lines that
+    // differ if they don't match.
+    //
+    // If the `VULKANO_BLESS_CAPTURES` environment variable is set, the golden file is instead
+    // overwritten with the capture.
+    fn check(name: &str, capture: &str, golden: &str) {
+        if env::var_os("VULKANO_BLESS_CAPTURES").is_some() {
+            let path = format!("{}/src/command_buffer/capture_fixtures/{}.golden",
+                               env!("CARGO_MANIFEST_DIR"),
+                               name);
+            File::create(path)
+                .unwrap()
+                .write_all(capture.as_bytes())
+                .unwrap();
+            return;
+        }
+
+        let expected = golden.lines().collect::<Vec<_>>();
+        let obtained = capture.lines().collect::<Vec<_>>();
+        if expected == obtained {
+            return;
+        }
+
+        let mut diff = String::new();
+        for num in 0 .. cmp::max(expected.len(), obtained.len()) {
+            match (expected.get(num), obtained.get(num)) {
+                (Some(e), Some(o)) if e == o => {
+                    diff.push_str(&format!("  {}\n", e));
+                },
+                (e, o) => {
+                    if let Some(e) = e {
+                        diff.push_str(&format!("- {}\n", e));
+                    }
+                    if let Some(o) = o {
+                        diff.push_str(&format!("+ {}\n", o));
+                    }
+                },
+            }
+        }
+
+        panic!("the capture of `{}` doesn't match its golden file:\n{}", name, diff);
+    }
+
+    // Replays the `.scenario` file of the `capture_fixtures` directory that has the name of the
+    // test, and compares the result with the `.golden` file.
+    macro_rules! scenario_tests {
+        ($($name:ident,)+) => (
+            $(
+                #[test]
+                fn $name() {
+                    let (device, queue) = gfx_dev_and_queue!();
+                    let scenario = include_str!(concat!("capture_fixtures/", stringify!($name),
+                                                        ".scenario"));
+                    let golden = include_str!(concat!("capture_fixtures/", stringify!($name),
+                                                      ".golden"));
+                    let capture = replay(&device, &queue, scenario);
+                    check(stringify!($name), &capture, golden);
+                }
+            )+
+        );
+    }
+
+    scenario_tests! {
+        compute_then_draw,
+        render_pass_attachment,
+        copy_then_sample,
+        cross_command_buffer,
+    }
+
+    // Builds a scenario where `count` dispatches each write to a buffer, then each buffer is
+    // copied to another buffer.
+    fn compute_chain(count: usize) -> String {
+        let mut scenario = String::new();
+        for n in 0 .. count {
+            scenario.push_str(&format!("buffer src{} 64\nbuffer dest{} 64\n", n, n));
+        }
+        for n in 0 .. count {
+            scenario.push_str(&format!("dispatch src{}\n", n));
+        }
+        for n in 0 .. count {
+            scenario.push_str(&format!("copy_buffer src{} dest{}\n", n, n));
+        }
+        scenario
+    }
+
+    #[test]
+    fn compute_chain_barrier_count() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let count = |capture: &str, prefix: &str| {
+            capture
+                .lines()
+                .filter(|line| line.trim_left().starts_with(prefix))
+                .count()
+        };
+
+        // The barriers needed by all the copies are recorded with a single call.
+        let capture = replay(&device, &queue, &compute_chain(4));
+        assert_eq!(count(&capture, "pipeline_barrier "), 1);
+        assert_eq!(count(&capture, "buffer "), 4);
+        assert_eq!(count(&capture, "memory "), 0);
+
+        // Above 8 buffer barriers, they are replaced with a single global memory barrier.
+        let capture = replay(&device, &queue, &compute_chain(12));
+        assert_eq!(count(&capture, "pipeline_barrier "), 1);
+        assert_eq!(count(&capture, "buffer "), 0);
+        assert_eq!(count(&capture, "memory "), 1);
+    }
+
+    /*
+        SPIR-V 1.0 module, assembled by hand.
+
+        layout(local_size_x = 1) in;
+        layout(set = 0, binding = 0) buffer Data { uint data[]; } buf;
+
+        void main() {
+            buf.data[gl_GlobalInvocationID.x] = 0;
+        }
+    */
+    const WRITE_BUFFER_CS: [u8; 496] = [3, 2, 35, 7, 0, 0, 1, 0, 0, 0, 0, 0, 20, 0, 0, 0, 0, 0, 0,
+                                        0, 17, 0, 2, 0, 1, 0, 0, 0, 14, 0, 3, 0, 0, 0, 0, 0, 1, 0,
+                                        0, 0, 15, 0, 6, 0, 5, 0, 0, 0, 1, 0, 0, 0, 109, 97, 105,
+                                        110, 0, 0, 0, 0, 2, 0, 0, 0, 16, 0, 6, 0, 1, 0, 0, 0, 17, 0,
+                                        0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 71, 0, 4, 0, 2, 0,
+                                        0, 0, 11, 0, 0, 0, 28, 0, 0, 0, 71, 0, 4, 0, 3, 0, 0, 0, 6,
+                                        0, 0, 0, 4, 0, 0, 0, 72, 0, 5, 0, 4, 0, 0, 0, 0, 0, 0, 0,
+                                        35, 0, 0, 0, 0, 0, 0, 0, 71, 0, 3, 0, 4, 0, 0, 0, 3, 0, 0,
+                                        0, 71, 0, 4, 0, 5, 0, 0, 0, 34, 0, 0, 0, 0, 0, 0, 0, 71, 0,
+                                        4, 0, 5, 0, 0, 0, 33, 0, 0, 0, 0, 0, 0, 0, 19, 0, 2, 0, 6,
+                                        0, 0, 0, 33, 0, 3, 0, 7, 0, 0, 0, 6, 0, 0, 0, 21, 0, 4, 0,
+                                        8, 0, 0, 0, 32, 0, 0, 0, 0, 0, 0, 0, 21, 0, 4, 0, 9, 0, 0,
+                                        0, 32, 0, 0, 0, 1, 0, 0, 0, 23, 0, 4, 0, 10, 0, 0, 0, 8, 0,
+                                        0, 0, 3, 0, 0, 0, 29, 0, 3, 0, 3, 0, 0, 0, 8, 0, 0, 0, 30,
+                                        0, 3, 0, 4, 0, 0, 0, 3, 0, 0, 0, 32, 0, 4, 0, 11, 0, 0, 0,
+                                        2, 0, 0, 0, 4, 0, 0, 0, 32, 0, 4, 0, 12, 0, 0, 0, 1, 0, 0,
+                                        0, 10, 0, 0, 0, 32, 0, 4, 0, 13, 0, 0, 0, 2, 0, 0, 0, 8, 0,
+                                        0, 0, 59, 0, 4, 0, 11, 0, 0, 0, 5, 0, 0, 0, 2, 0, 0, 0, 59,
+                                        0, 4, 0, 12, 0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 0, 43, 0, 4, 0,
+                                        9, 0, 0, 0, 14, 0, 0, 0, 0, 0, 0, 0, 43, 0, 4, 0, 8, 0, 0,
+                                        0, 15, 0, 0, 0, 0, 0, 0, 0, 54, 0, 5, 0, 6, 0, 0, 0, 1, 0,
+                                        0, 0, 0, 0, 0, 0, 7, 0, 0, 0, 248, 0, 2, 0, 16, 0, 0, 0, 61,
+                                        0, 4, 0, 10, 0, 0, 0, 17, 0, 0, 0, 2, 0, 0, 0, 81, 0, 5, 0,
+                                        8, 0, 0, 0, 18, 0, 0, 0, 17, 0, 0, 0, 0, 0, 0, 0, 65, 0, 6,
+                                        0, 13, 0, 0, 0, 19, 0, 0, 0, 5, 0, 0, 0, 14, 0, 0, 0, 18, 0,
+                                        0, 0, 62, 0, 3, 0, 19, 0, 0, 0, 15, 0, 0, 0, 253, 0, 1, 0,
+                                        56, 0, 1, 0];
+
+    /*
+        SPIR-V 1.0 module, assembled by hand.
+
+        layout(local_size_x = 1) in;
+        layout(set = 0, binding = 0, rgba8) uniform image2D img;
+
+        void main() {
+            imageStore(img, ivec2(gl_GlobalInvocationID.xy), vec4(1.0));
+        }
+    */
+    const WRITE_IMAGE_CS: [u8; 488] = [3, 2, 35, 7, 0, 0, 1, 0, 0, 0, 0, 0, 20, 0, 0, 0, 0, 0, 0, 0,
+                                       17, 0, 2, 0, 1, 0, 0, 0, 14, 0, 3, 0, 0, 0, 0, 0, 1, 0, 0, 0,
+                                       15, 0, 6, 0, 5, 0, 0, 0, 1, 0, 0, 0, 109, 97, 105, 110, 0, 0,
+                                       0, 0, 2, 0, 0, 0, 16, 0, 6, 0, 1, 0, 0, 0, 17, 0, 0, 0, 1, 0,
+                                       0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 71, 0, 4, 0, 2, 0, 0, 0, 11, 0,
+                                       0, 0, 28, 0, 0, 0, 71, 0, 4, 0, 3, 0, 0, 0, 34, 0, 0, 0, 0,
+                                       0, 0, 0, 71, 0, 4, 0, 3, 0, 0, 0, 33, 0, 0, 0, 0, 0, 0, 0,
+                                       19, 0, 2, 0, 4, 0, 0, 0, 33, 0, 3, 0, 5, 0, 0, 0, 4, 0, 0, 0,
+                                       22, 0, 3, 0, 6, 0, 0, 0, 32, 0, 0, 0, 23, 0, 4, 0, 7, 0, 0,
+                                       0, 6, 0, 0, 0, 4, 0, 0, 0, 21, 0, 4, 0, 8, 0, 0, 0, 32, 0, 0,
+                                       0, 0, 0, 0, 0, 23, 0, 4, 0, 9, 0, 0, 0, 8, 0, 0, 0, 2, 0, 0,
+                                       0, 23, 0, 4, 0, 10, 0, 0, 0, 8, 0, 0, 0, 3, 0, 0, 0, 25, 0,
+                                       9, 0, 11, 0, 0, 0, 6, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+                                       0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 4, 0, 0, 0, 32, 0, 4, 0, 12, 0,
+                                       0, 0, 0, 0, 0, 0, 11, 0, 0, 0, 32, 0, 4, 0, 13, 0, 0, 0, 1,
+                                       0, 0, 0, 10, 0, 0, 0, 59, 0, 4, 0, 12, 0, 0, 0, 3, 0, 0, 0,
+                                       0, 0, 0, 0, 59, 0, 4, 0, 13, 0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 0,
+                                       43, 0, 4, 0, 6, 0, 0, 0, 14, 0, 0, 0, 0, 0, 128, 63, 44, 0,
+                                       7, 0, 7, 0, 0, 0, 15, 0, 0, 0, 14, 0, 0, 0, 14, 0, 0, 0, 14,
+                                       0, 0, 0, 14, 0, 0, 0, 54, 0, 5, 0, 4, 0, 0, 0, 1, 0, 0, 0, 0,
+                                       0, 0, 0, 5, 0, 0, 0, 248, 0, 2, 0, 16, 0, 0, 0, 61, 0, 4, 0,
+                                       10, 0, 0, 0, 17, 0, 0, 0, 2, 0, 0, 0, 79, 0, 7, 0, 9, 0, 0,
+                                       0, 18, 0, 0, 0, 17, 0, 0, 0, 17, 0, 0, 0, 0, 0, 0, 0, 1, 0,
+                                       0, 0, 61, 0, 4, 0, 11, 0, 0, 0, 19, 0, 0, 0, 3, 0, 0, 0, 99,
+                                       0, 4, 0, 19, 0, 0, 0, 18, 0, 0, 0, 15, 0, 0, 0, 253, 0, 1, 0,
+                                       56, 0, 1, 0];
+
+    /*
+        SPIR-V 1.0 module, assembled by hand.
+
+        layout(set = 0, binding = 0) uniform sampler2D tex;
+        layout(location = 0) out vec4 f_color;
+
+        void main() {
+            f_color = texture(tex, vec2(0.5));
+        }
+    */
+    const SAMPLE_FS: [u8; 420] = [3, 2, 35, 7, 0, 0, 1, 0, 0, 0, 0, 0, 18, 0, 0, 0, 0, 0, 0, 0, 17,
+                                  0, 2, 0, 1, 0, 0, 0, 14, 0, 3, 0, 0, 0, 0, 0, 1, 0, 0, 0, 15, 0,
+                                  6, 0, 4, 0, 0, 0, 1, 0, 0, 0, 109, 97, 105, 110, 0, 0, 0, 0, 2, 0,
+                                  0, 0, 16, 0, 3, 0, 1, 0, 0, 0, 7, 0, 0, 0, 71, 0, 4, 0, 2, 0, 0,
+                                  0, 30, 0, 0, 0, 0, 0, 0, 0, 71, 0, 4, 0, 3, 0, 0, 0, 34, 0, 0, 0,
+                                  0, 0, 0, 0, 71, 0, 4, 0, 3, 0, 0, 0, 33, 0, 0, 0, 0, 0, 0, 0, 19,
+                                  0, 2, 0, 4, 0, 0, 0, 33, 0, 3, 0, 5, 0, 0, 0, 4, 0, 0, 0, 22, 0,
+                                  3, 0, 6, 0, 0, 0, 32, 0, 0, 0, 23, 0, 4, 0, 7, 0, 0, 0, 6, 0, 0,
+                                  0, 4, 0, 0, 0, 23, 0, 4, 0, 8, 0, 0, 0, 6, 0, 0, 0, 2, 0, 0, 0,
+                                  25, 0, 9, 0, 9, 0, 0, 0, 6, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+                                  0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 27, 0, 3, 0, 10, 0, 0,
+                                  0, 9, 0, 0, 0, 32, 0, 4, 0, 11, 0, 0, 0, 0, 0, 0, 0, 10, 0, 0, 0,
+                                  32, 0, 4, 0, 12, 0, 0, 0, 3, 0, 0, 0, 7, 0, 0, 0, 59, 0, 4, 0, 11,
+                                  0, 0, 0, 3, 0, 0, 0, 0, 0, 0, 0, 59, 0, 4, 0, 12, 0, 0, 0, 2, 0,
+                                  0, 0, 3, 0, 0, 0, 43, 0, 4, 0, 6, 0, 0, 0, 13, 0, 0, 0, 0, 0, 0,
+                                  63, 44, 0, 5, 0, 8, 0, 0, 0, 14, 0, 0, 0, 13, 0, 0, 0, 13, 0, 0,
+                                  0, 54, 0, 5, 0, 4, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 5, 0, 0, 0,
+                                  248, 0, 2, 0, 15, 0, 0, 0, 61, 0, 4, 0, 10, 0, 0, 0, 16, 0, 0, 0,
+                                  3, 0, 0, 0, 87, 0, 5, 0, 7, 0, 0, 0, 17, 0, 0, 0, 16, 0, 0, 0, 14,
+                                  0, 0, 0, 62, 0, 3, 0, 2, 0, 0, 0, 17, 0, 0, 0, 253, 0, 1, 0, 56,
+                                  0, 1, 0];
+}
@@ -0,0 +1,1058 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Commands generated by the GPU itself.
+//!
+//! With the `VK_NV_device_generated_commands` extension, the GPU can read a list of commands
+//! from buffers instead of having them recorded by the CPU in the command buffer. This is
+//! similar to indirect drawing, except that each *sequence* can also push constants or bind
+//! buffers before drawing.
+//!
+//! The content of each sequence is described by an `IndirectCommandsLayout`, which is a list of
+//! *tokens*. Each token reads its data at a given offset in one of the *streams*, and each stream
+//! is a buffer containing one element of a fixed stride per sequence. The last token of a layout
+//! must be a draw command.
+//!
+//! The implementation needs some scratch memory, called the preprocess buffer, in order to
+//! execute the generated commands. Its size can be queried with
+//! `IndirectCommandsLayout::preprocess_requirements`. The commands are then executed with
+//! `AutoCommandBufferBuilder::execute_generated_commands`.
+//!
+//! Only the NV extension is supported at the moment. The tokens, the validation of the layouts
+//! and the command buffer commands don't depend on it, so that other extensions providing the
+//! same functionality can reuse them.
+//!
+//! Graphics pipelines must be built with `GraphicsPipelineBuilder::indirect_bindable` in order
+//! to be used with generated commands.
+
+use smallvec::SmallVec;
+use std::error;
+use std::fmt;
+use std::mem;
+use std::ptr;
+use std::sync::Arc;
+
+use Error;
+use OomError;
+use VulkanObject;
+use check_errors;
+use descriptor::descriptor::ShaderStages;
+use descriptor::pipeline_layout::PipelineLayoutAbstract;
+use device::Device;
+use device::DeviceOwned;
+use instance::PhysicalDevice;
+use memory::MemoryRequirements;
+use pipeline::GraphicsPipelineAbstract;
+use vk;
+
+/// Data read from a stream by a `VertexBuffer` token.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct BindVertexBufferIndirectCommand {
+    /// Device address of the start of the vertex buffer.
+    pub buffer_address: u64,
+    /// Size in bytes of the vertex buffer.
+    pub size: u32,
+    /// Stride of the vertex buffer. Ignored unless the token uses a dynamic stride.
+    pub stride: u32,
+}
+
+/// Data read from a stream by an `IndexBuffer` token.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct BindIndexBufferIndirectCommand {
+    /// Device address of the start of the index buffer.
+    pub buffer_address: u64,
+    /// Size in bytes of the index buffer.
+    pub size: u32,
+    /// Raw Vulkan value of the type of the indices.
+    pub index_type: u32,
+}
+
+/// A command of a sequence of generated commands.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum IndirectCommandsToken {
+    /// Binds a vertex buffer. The stream contains a `BindVertexBufferIndirectCommand`.
+    ///
+    /// Vulkano doesn't provide a way to obtain the device address of a buffer yet, so this
+    /// token can only be used with addresses obtained by other means.
+    VertexBuffer {
+        /// The vertex buffer binding to update.
+        binding: u32,
+        /// If true, the stride of the vertex buffer is read from the stream instead of being
+        /// the one of the pipeline.
+        dynamic_stride: bool,
+    },
+
+    /// Binds an index buffer. The stream contains a `BindIndexBufferIndirectCommand`.
+    ///
+    /// The same restriction as `VertexBuffer` applies to the address of the buffer.
+    IndexBuffer,
+
+    /// Updates push constants. The stream contains `size` bytes of data.
+    PushConstant {
+        /// The stages whose push constants are updated.
+        stages: ShaderStages,
+        /// Offset of the push constants to update.
+        offset: u32,
+        /// Number of bytes to update.
+        size: u32,
+    },
+
+    /// Draws. The stream contains a `DrawIndirectCommand`.
+    Draw,
+
+    /// Draws with indices. The stream contains a `DrawIndexedIndirectCommand`.
+    DrawIndexed,
+}
+
+impl IndirectCommandsToken {
+    /// Returns the number of bytes read from the stream for this token.
+    #[inline]
+    pub fn data_size(&self) -> u32 {
+        match *self {
+            IndirectCommandsToken::VertexBuffer { .. } => {
+                mem::size_of::<BindVertexBufferIndirectCommand>() as u32
+            },
+            IndirectCommandsToken::IndexBuffer => {
+                mem::size_of::<BindIndexBufferIndirectCommand>() as u32
+            },
+            IndirectCommandsToken::PushConstant { size, .. } => size,
+            IndirectCommandsToken::Draw => mem::size_of::<vk::DrawIndirectCommand>() as u32,
+            IndirectCommandsToken::DrawIndexed => {
+                mem::size_of::<vk::DrawIndexedIndirectCommand>() as u32
+            },
+        }
+    }
+
+    /// Returns true if the token is a draw command. There must be exactly one in a layout, at
+    /// the end.
+    #[inline]
+    pub fn is_action(&self) -> bool {
+        match *self {
+            IndirectCommandsToken::Draw | IndirectCommandsToken::DrawIndexed => true,
+            _ => false,
+        }
+    }
+}
+
+/// A token of an `IndirectCommandsLayout` and where its data is located.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct IndirectCommandsTokenDesc {
+    /// The token.
+    pub token: IndirectCommandsToken,
+    /// Index of the stream that contains the data of the token.
+    pub stream: u32,
+    /// Offset of the data of the token within each element of the stream.
+    pub offset: u32,
+}
+
+/// Limits of the implementation regarding generated commands.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DeviceGeneratedCommandsProperties {
+    /// Maximum number of shader groups of a pipeline.
+    pub max_graphics_shader_group_count: u32,
+    /// Maximum number of sequences that can be executed at once.
+    pub max_indirect_sequence_count: u32,
+    /// Maximum number of tokens in a layout.
+    pub max_indirect_commands_token_count: u32,
+    /// Maximum number of streams in a layout.
+    pub max_indirect_commands_stream_count: u32,
+    /// Maximum offset of a token within an element of a stream.
+    pub max_indirect_commands_token_offset: u32,
+    /// Maximum stride of a stream.
+    pub max_indirect_commands_stream_stride: u32,
+    /// Alignment of the offset of the buffer that contains the number of sequences.
+    pub min_sequences_count_buffer_offset_alignment: u32,
+    /// Alignment of the offset of the buffer that contains the indices of the sequences.
+    pub min_sequences_index_buffer_offset_alignment: u32,
+    /// Alignment of the offset of the streams.
+    pub min_indirect_commands_buffer_offset_alignment: u32,
+}
+
+impl DeviceGeneratedCommandsProperties {
+    /// Returns the limits that all the implementations supporting the extension guarantee.
+    #[inline]
+    pub fn minimum() -> DeviceGeneratedCommandsProperties {
+        DeviceGeneratedCommandsProperties {
+            max_graphics_shader_group_count: 4096,
+            max_indirect_sequence_count: 1 << 20,
+            max_indirect_commands_token_count: 16,
+            max_indirect_commands_stream_count: 16,
+            max_indirect_commands_token_offset: 2047,
+            max_indirect_commands_stream_stride: 2048,
+            min_sequences_count_buffer_offset_alignment: 256,
+            min_sequences_index_buffer_offset_alignment: 256,
+            min_indirect_commands_buffer_offset_alignment: 256,
+        }
+    }
+
+    /// Queries the limits of a physical device.
+    ///
+    /// The limits can only be queried if the `khr_get_physical_device_properties2` extension
+    /// is enabled on the instance. Otherwise the guaranteed limits returned by `minimum()` are
+    /// returned.
+    pub fn from_physical_device(physical_device: PhysicalDevice)
+                                -> DeviceGeneratedCommandsProperties {
+        let instance = physical_device.instance();
+        if !instance.loaded_extensions().khr_get_physical_device_properties2 {
+            return DeviceGeneratedCommandsProperties::minimum();
+        }
+
+        unsafe {
+            let mut generated = vk::PhysicalDeviceDeviceGeneratedCommandsPropertiesNV {
+                sType: vk::STRUCTURE_TYPE_PHYSICAL_DEVICE_DEVICE_GENERATED_COMMANDS_PROPERTIES_NV,
+                pNext: ptr::null_mut(),
+                maxGraphicsShaderGroupCount: 0,
+                maxIndirectSequenceCount: 0,
+                maxIndirectCommandsTokenCount: 0,
+                maxIndirectCommandsStreamCount: 0,
+                maxIndirectCommandsTokenOffset: 0,
+                maxIndirectCommandsStreamStride: 0,
+                minSequencesCountBufferOffsetAlignment: 0,
+                minSequencesIndexBufferOffsetAlignment: 0,
+                minIndirectCommandsBufferOffsetAlignment: 0,
+            };
+
+            let mut output = vk::PhysicalDeviceProperties2KHR {
+                sType: vk::STRUCTURE_TYPE_PHYSICAL_DEVICE_PROPERTIES_2_KHR,
+                pNext: &mut generated as *mut _ as *const _,
+                properties: mem::uninitialized(),
+            };
+
+            let vk = instance.pointers();
+            vk.GetPhysicalDeviceProperties2KHR(physical_device.internal_object(), &mut output);
+
+            DeviceGeneratedCommandsProperties {
+                max_graphics_shader_group_count: generated.maxGraphicsShaderGroupCount,
+                max_indirect_sequence_count: generated.maxIndirectSequenceCount,
+                max_indirect_commands_token_count: generated.maxIndirectCommandsTokenCount,
+                max_indirect_commands_stream_count: generated.maxIndirectCommandsStreamCount,
+                max_indirect_commands_token_offset: generated.maxIndirectCommandsTokenOffset,
+                max_indirect_commands_stream_stride: generated.maxIndirectCommandsStreamStride,
+                min_sequences_count_buffer_offset_alignment:
+                    generated.minSequencesCountBufferOffsetAlignment,
+                min_sequences_index_buffer_offset_alignment:
+                    generated.minSequencesIndexBufferOffsetAlignment,
+                min_indirect_commands_buffer_offset_alignment:
+                    generated.minIndirectCommandsBufferOffsetAlignment,
+            }
+        }
+    }
+}
+
+/// Describes the content of the sequences of generated commands.
+pub struct IndirectCommandsLayout<L> {
+    layout: vk::IndirectCommandsLayoutNV,
+    device: Arc<Device>,
+    pipeline_layout: L,
+    tokens: SmallVec<[IndirectCommandsTokenDesc; 8]>,
+    stream_strides: SmallVec<[u32; 4]>,
+    properties: DeviceGeneratedCommandsProperties,
+}
+
+impl IndirectCommandsLayout<()> {
+    /// Starts building an `IndirectCommandsLayout`.
+    #[inline]
+    pub fn start() -> IndirectCommandsLayoutBuilder {
+        IndirectCommandsLayoutBuilder {
+            tokens: SmallVec::new(),
+            stream_strides: SmallVec::new(),
+            unordered_sequences: false,
+        }
+    }
+}
+
+impl<L> IndirectCommandsLayout<L> {
+    /// Returns the tokens of the layout.
+    #[inline]
+    pub fn tokens(&self) -> &[IndirectCommandsTokenDesc] {
+        &self.tokens
+    }
+
+    /// Returns the stride of each stream of the layout.
+    #[inline]
+    pub fn stream_strides(&self) -> &[u32] {
+        &self.stream_strides
+    }
+
+    /// Returns the pipeline layout used by the push constant tokens.
+    #[inline]
+    pub fn pipeline_layout(&self) -> &L {
+        &self.pipeline_layout
+    }
+
+    /// Returns the limits of the device that the layout was checked against.
+    #[inline]
+    pub fn properties(&self) -> &DeviceGeneratedCommandsProperties {
+        &self.properties
+    }
+
+    /// Returns the memory requirements of the preprocess buffer needed to execute up to
+    /// `max_sequences` sequences with the given pipeline.
+    ///
+    /// The buffer must be created with the `indirect_buffer` usage.
+    pub fn preprocess_requirements<Gp>(&self, pipeline: &Gp, max_sequences: u32)
+                                       -> MemoryRequirements
+        where Gp: ?Sized + GraphicsPipelineAbstract
+    {
+        assert_eq!(self.device.internal_object(),
+                   pipeline.device().internal_object());
+
+        unsafe {
+            let infos = vk::GeneratedCommandsMemoryRequirementsInfoNV {
+                sType: vk::STRUCTURE_TYPE_GENERATED_COMMANDS_MEMORY_REQUIREMENTS_INFO_NV,
+                pNext: ptr::null(),
+                pipelineBindPoint: vk::PIPELINE_BIND_POINT_GRAPHICS,
+                pipeline: GraphicsPipelineAbstract::inner(pipeline).internal_object(),
+                indirectCommandsLayout: self.layout,
+                maxSequencesCount: max_sequences,
+            };
+
+            let mut output = vk::MemoryRequirements2KHR {
+                sType: vk::STRUCTURE_TYPE_MEMORY_REQUIREMENTS_2_KHR,
+                pNext: ptr::null_mut(),
+                memoryRequirements: mem::uninitialized(),
+            };
+
+            let vk = self.device.pointers();
+            vk.GetGeneratedCommandsMemoryRequirementsNV(self.device.internal_object(),
+                                                        &infos,
+                                                        &mut output);
+            output.memoryRequirements.into()
+        }
+    }
+}
+
+unsafe impl<L> VulkanObject for IndirectCommandsLayout<L> {
+    type Object = vk::IndirectCommandsLayoutNV;
+
+    #[inline]
+    fn internal_object(&self) -> vk::IndirectCommandsLayoutNV {
+        self.layout
+    }
+}
+
+unsafe impl<L> DeviceOwned for IndirectCommandsLayout<L> {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+}
+
+impl<L> fmt::Debug for IndirectCommandsLayout<L> {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "<Vulkan indirect commands layout {:?}>", self.layout)
+    }
+}
+
+impl<L> Drop for IndirectCommandsLayout<L> {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe {
+            let vk = self.device.pointers();
+            vk.DestroyIndirectCommandsLayoutNV(self.device.internal_object(),
+                                               self.layout,
+                                               ptr::null());
+        }
+    }
+}
+
+/// Prototype of an `IndirectCommandsLayout`.
+#[derive(Debug, Clone)]
+pub struct IndirectCommandsLayoutBuilder {
+    tokens: SmallVec<[IndirectCommandsTokenDesc; 8]>,
+    stream_strides: SmallVec<[u32; 4]>,
+    unordered_sequences: bool,
+}
+
+impl IndirectCommandsLayoutBuilder {
+    /// Adds a stream whose elements are `stride` bytes apart. Streams are numbered in the
+    /// order in which they are added.
+    #[inline]
+    pub fn stream(mut self, stride: u32) -> Self {
+        self.stream_strides.push(stride);
+        self
+    }
+
+    /// Adds a token whose data is at `offset` within each element of `stream`.
+    #[inline]
+    pub fn token(mut self, stream: u32, offset: u32, token: IndirectCommandsToken) -> Self {
+        self.tokens.push(IndirectCommandsTokenDesc {
+                             token: token,
+                             stream: stream,
+                             offset: offset,
+                         });
+        self
+    }
+
+    /// Allows the implementation to execute the sequences in any order.
+    #[inline]
+    pub fn unordered_sequences(mut self) -> Self {
+        self.unordered_sequences = true;
+        self
+    }
+
+    /// Builds the layout. `pipeline_layout` is used by the push constant tokens, and must be
+    /// compatible with the pipelines the layout is used with.
+    pub fn build<L>(self, pipeline_layout: L)
+                    -> Result<Arc<IndirectCommandsLayout<L>>, IndirectCommandsLayoutCreationError>
+        where L: PipelineLayoutAbstract
+    {
+        let device = pipeline_layout.device().clone();
+
+        if !device.loaded_extensions().nv_device_generated_commands {
+            return Err(IndirectCommandsLayoutCreationError::ExtensionNotEnabled);
+        }
+
+        let properties = DeviceGeneratedCommandsProperties::from_physical_device(
+            device.physical_device());
+        check_layout(&self.tokens, &self.stream_strides, &properties)?;
+
+        for desc in self.tokens.iter() {
+            if let IndirectCommandsToken::PushConstant { stages, offset, size } = desc.token {
+                let covered = (0 .. pipeline_layout.num_push_constants_ranges())
+                    .filter_map(|num| pipeline_layout.push_constants_range(num))
+                    .any(|range| {
+                        range.stages.is_superset_of(&stages) &&
+                            range.offset <= offset as usize &&
+                            offset as usize + size as usize <= range.offset + range.size
+                    });
+
+                if !covered || (offset % 4) != 0 || (size % 4) != 0 || size == 0 {
+                    return Err(IndirectCommandsLayoutCreationError::PushConstantRangeNotInLayout);
+                }
+            }
+        }
+
+        let layout = unsafe {
+            let pipeline_layout_raw = pipeline_layout.sys().internal_object();
+
+            let tokens = self.tokens
+                .iter()
+                .map(|desc| {
+                    let mut raw = vk::IndirectCommandsLayoutTokenNV {
+                        sType: vk::STRUCTURE_TYPE_INDIRECT_COMMANDS_LAYOUT_TOKEN_NV,
+                        pNext: ptr::null(),
+                        tokenType: 0,
+                        stream: desc.stream,
+                        offset: desc.offset,
+                        vertexBindingUnit: 0,
+                        vertexDynamicStride: vk::FALSE,
+                        pushconstantPipelineLayout: 0,
+                        pushconstantShaderStageFlags: 0,
+                        pushconstantOffset: 0,
+                        pushconstantSize: 0,
+                        indirectStateFlags: 0,
+                        indexTypeCount: 0,
+                        pIndexTypes: ptr::null(),
+                        pIndexTypeValues: ptr::null(),
+                    };
+
+                    match desc.token {
+                        IndirectCommandsToken::VertexBuffer { binding, dynamic_stride } => {
+                            raw.tokenType = vk::INDIRECT_COMMANDS_TOKEN_TYPE_VERTEX_BUFFER_NV;
+                            raw.vertexBindingUnit = binding;
+                            raw.vertexDynamicStride = if dynamic_stride {
+                                vk::TRUE
+                            } else {
+                                vk::FALSE
+                            };
+                        },
+                        IndirectCommandsToken::IndexBuffer => {
+                            raw.tokenType = vk::INDIRECT_COMMANDS_TOKEN_TYPE_INDEX_BUFFER_NV;
+                        },
+                        IndirectCommandsToken::PushConstant { stages, offset, size } => {
+                            raw.tokenType = vk::INDIRECT_COMMANDS_TOKEN_TYPE_PUSH_CONSTANT_NV;
+                            raw.pushconstantPipelineLayout = pipeline_layout_raw;
+                            raw.pushconstantShaderStageFlags = stages.into();
+                            raw.pushconstantOffset = offset;
+                            raw.pushconstantSize = size;
+                        },
+                        IndirectCommandsToken::Draw => {
+                            raw.tokenType = vk::INDIRECT_COMMANDS_TOKEN_TYPE_DRAW_NV;
+                        },
+                        IndirectCommandsToken::DrawIndexed => {
+                            raw.tokenType = vk::INDIRECT_COMMANDS_TOKEN_TYPE_DRAW_INDEXED_NV;
+                        },
+                    }
+
+                    raw
+                })
+                .collect::<SmallVec<[_; 8]>>();
+
+            let flags = if self.unordered_sequences {
+                vk::INDIRECT_COMMANDS_LAYOUT_USAGE_UNORDERED_SEQUENCES_BIT_NV
+            } else {
+                0
+            };
+
+            let infos = vk::IndirectCommandsLayoutCreateInfoNV {
+                sType: vk::STRUCTURE_TYPE_INDIRECT_COMMANDS_LAYOUT_CREATE_INFO_NV,
+                pNext: ptr::null(),
+                flags: flags,
+                pipelineBindPoint: vk::PIPELINE_BIND_POINT_GRAPHICS,
+                tokenCount: tokens.len() as u32,
+                pTokens: tokens.as_ptr(),
+                streamCount: self.stream_strides.len() as u32,
+                pStreamStrides: self.stream_strides.as_ptr(),
+            };
+
+            let vk = device.pointers();
+            let mut output = mem::uninitialized();
+            check_errors(vk.CreateIndirectCommandsLayoutNV(device.internal_object(),
+                                                           &infos,
+                                                           ptr::null(),
+                                                           &mut output),
+                         "vkCreateIndirectCommandsLayoutNV")?;
+            output
+        };
+
+        Ok(Arc::new(IndirectCommandsLayout {
+                        layout: layout,
+                        device: device,
+                        pipeline_layout: pipeline_layout,
+                        tokens: self.tokens,
+                        stream_strides: self.stream_strides,
+                        properties: properties,
+                    }))
+    }
+}
+
+/// Checks the tokens and the streams of a layout against the limits of the implementation.
+fn check_layout(tokens: &[IndirectCommandsTokenDesc], stream_strides: &[u32],
+                properties: &DeviceGeneratedCommandsProperties)
+                -> Result<(), IndirectCommandsLayoutCreationError> {
+    if tokens.is_empty() {
+        return Err(IndirectCommandsLayoutCreationError::NoTokens);
+    }
+
+    if tokens.len() > properties.max_indirect_commands_token_count as usize {
+        return Err(IndirectCommandsLayoutCreationError::MaxTokensExceeded {
+                       max: properties.max_indirect_commands_token_count,
+                       obtained: tokens.len() as u32,
+                   });
+    }
+
+    if stream_strides.len() > properties.max_indirect_commands_stream_count as usize {
+        return Err(IndirectCommandsLayoutCreationError::MaxStreamsExceeded {
+                       max: properties.max_indirect_commands_stream_count,
+                       obtained: stream_strides.len() as u32,
+                   });
+    }
+
+    for &stride in stream_strides {
+        if stride > properties.max_indirect_commands_stream_stride {
+            return Err(IndirectCommandsLayoutCreationError::MaxStreamStrideExceeded {
+                           max: properties.max_indirect_commands_stream_stride,
+                           obtained: stride,
+                       });
+        }
+    }
+
+    let last = tokens.len() - 1;
+    for (num, desc) in tokens.iter().enumerate() {
+        if desc.token.is_action() != (num == last) {
+            return Err(IndirectCommandsLayoutCreationError::DrawTokenNotLast);
+        }
+
+        let stride = match stream_strides.get(desc.stream as usize) {
+            Some(&s) => s,
+            None => {
+                return Err(IndirectCommandsLayoutCreationError::StreamOutOfRange {
+                               stream: desc.stream,
+                               num_streams: stream_strides.len() as u32,
+                           });
+            },
+        };
+
+        if desc.offset > properties.max_indirect_commands_token_offset {
+            return Err(IndirectCommandsLayoutCreationError::MaxTokenOffsetExceeded {
+                           max: properties.max_indirect_commands_token_offset,
+                           obtained: desc.offset,
+                       });
+        }
+
+        if (desc.offset % 4) != 0 {
+            return Err(IndirectCommandsLayoutCreationError::TokenOffsetNotAligned);
+        }
+
+        if desc.offset + desc.token.data_size() > stride {
+            return Err(IndirectCommandsLayoutCreationError::TokenOutsideStride);
+        }
+    }
+
+    Ok(())
+}
+
+/// Error that can happen when creating an `IndirectCommandsLayout`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IndirectCommandsLayoutCreationError {
+    /// Not enough memory.
+    OomError(OomError),
+    /// The `nv_device_generated_commands` extension must be enabled in order to create layouts.
+    ExtensionNotEnabled,
+    /// The layout doesn't have any token.
+    NoTokens,
+    /// The last token must be a draw, and it must be the only one.
+    DrawTokenNotLast,
+    /// The maximum number of tokens was exceeded.
+    MaxTokensExceeded { max: u32, obtained: u32 },
+    /// The maximum number of streams was exceeded.
+    MaxStreamsExceeded { max: u32, obtained: u32 },
+    /// The maximum stride of a stream was exceeded.
+    MaxStreamStrideExceeded { max: u32, obtained: u32 },
+    /// The maximum offset of a token was exceeded.
+    MaxTokenOffsetExceeded { max: u32, obtained: u32 },
+    /// A token refers to a stream that doesn't exist.
+    StreamOutOfRange { stream: u32, num_streams: u32 },
+    /// The offset of a token is not a multiple of 4.
+    TokenOffsetNotAligned,
+    /// The data of a token goes beyond the stride of its stream.
+    TokenOutsideStride,
+    /// A push constant token updates push constants that aren't in the pipeline layout, or
+    /// its offset or size isn't a non-zero multiple of 4.
+    PushConstantRangeNotInLayout,
+}
+
+impl error::Error for IndirectCommandsLayoutCreationError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            IndirectCommandsLayoutCreationError::OomError(_) => "not enough memory available",
+            IndirectCommandsLayoutCreationError::ExtensionNotEnabled => {
+                "the `nv_device_generated_commands` extension must be enabled in order to create \
+                 indirect commands layouts"
+            },
+            IndirectCommandsLayoutCreationError::NoTokens => {
+                "the layout doesn't have any token"
+            },
+            IndirectCommandsLayoutCreationError::DrawTokenNotLast => {
+                "the last token must be a draw, and it must be the only one"
+            },
+            IndirectCommandsLayoutCreationError::MaxTokensExceeded { .. } => {
+                "the maximum number of tokens was exceeded"
+            },
+            IndirectCommandsLayoutCreationError::MaxStreamsExceeded { .. } => {
+                "the maximum number of streams was exceeded"
+            },
+            IndirectCommandsLayoutCreationError::MaxStreamStrideExceeded { .. } => {
+                "the maximum stride of a stream was exceeded"
+            },
+            IndirectCommandsLayoutCreationError::MaxTokenOffsetExceeded { .. } => {
+                "the maximum offset of a token was exceeded"
+            },
+            IndirectCommandsLayoutCreationError::StreamOutOfRange { .. } => {
+                "a token refers to a stream that doesn't exist"
+            },
+            IndirectCommandsLayoutCreationError::TokenOffsetNotAligned => {
+                "the offset of a token is not a multiple of 4"
+            },
+            IndirectCommandsLayoutCreationError::TokenOutsideStride => {
+                "the data of a token goes beyond the stride of its stream"
+            },
+            IndirectCommandsLayoutCreationError::PushConstantRangeNotInLayout => {
+                "a push constant token updates push constants that aren't in the pipeline layout"
+            },
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            IndirectCommandsLayoutCreationError::OomError(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for IndirectCommandsLayoutCreationError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl From<OomError> for IndirectCommandsLayoutCreationError {
+    #[inline]
+    fn from(err: OomError) -> IndirectCommandsLayoutCreationError {
+        IndirectCommandsLayoutCreationError::OomError(err)
+    }
+}
+
+impl From<Error> for IndirectCommandsLayoutCreationError {
+    #[inline]
+    fn from(err: Error) -> IndirectCommandsLayoutCreationError {
+        match err {
+            err @ Error::OutOfHostMemory => {
+                IndirectCommandsLayoutCreationError::OomError(OomError::from(err))
+            },
+            err @ Error::OutOfDeviceMemory => {
+                IndirectCommandsLayoutCreationError::OomError(OomError::from(err))
+            },
+            _ => panic!("unexpected error: {:?}", err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CStr;
+    use std::sync::Arc;
+
+    use buffer::BufferAccess;
+    use buffer::BufferUsage;
+    use buffer::CpuAccessibleBuffer;
+    use buffer::DeviceLocalBuffer;
+    use command_buffer::AutoCommandBufferBuilder;
+    use command_buffer::DrawIndirectCommand;
+    use command_buffer::DynamicState;
+    use command_buffer::auto::ExecuteGeneratedCommandsError;
+    use command_buffer::indirect_commands::DeviceGeneratedCommandsProperties;
+    use command_buffer::indirect_commands::IndirectCommandsLayout;
+    use command_buffer::indirect_commands::IndirectCommandsLayoutCreationError;
+    use command_buffer::indirect_commands::IndirectCommandsToken;
+    use command_buffer::indirect_commands::IndirectCommandsTokenDesc;
+    use command_buffer::indirect_commands::check_layout;
+    use command_buffer::read_image_region;
+    use command_buffer::validity::CheckGeneratedCommandsError;
+    use descriptor::descriptor::ShaderStages;
+    use descriptor::pipeline_layout::EmptyPipelineDesc;
+    use descriptor::pipeline_layout::PipelineLayoutDesc;
+    use device::Device;
+    use device::DeviceExtensions;
+    use format::ClearValue;
+    use format::Format;
+    use framebuffer::Framebuffer;
+    use framebuffer::Subpass;
+    use image::AttachmentImage;
+    use image::ImageUsage;
+    use instance::Features;
+    use instance::PhysicalDevice;
+    use pipeline::GraphicsPipeline;
+    use pipeline::blend::AttachmentBlend;
+    use pipeline::blend::BlendFactor;
+    use pipeline::shader::EmptyShaderInterfaceDef;
+    use pipeline::shader::ShaderModule;
+    use pipeline::vertex::Vertex;
+    use pipeline::vertex::VertexMemberInfo;
+    use pipeline::viewport::Viewport;
+    use sync::GpuFuture;
+    use sync::now;
+    use tests::BASIC_FS;
+    use tests::FULL_SCREEN_VS;
+
+    fn desc(stream: u32, offset: u32, token: IndirectCommandsToken) -> IndirectCommandsTokenDesc {
+        IndirectCommandsTokenDesc {
+            token: token,
+            stream: stream,
+            offset: offset,
+        }
+    }
+
+    fn push_constant(size: u32) -> IndirectCommandsToken {
+        IndirectCommandsToken::PushConstant {
+            stages: ShaderStages::all_graphics(),
+            offset: 0,
+            size: size,
+        }
+    }
+
+    #[test]
+    fn push_constant_then_draw() {
+        let tokens = [desc(0, 0, push_constant(4)), desc(0, 4, IndirectCommandsToken::Draw)];
+        let props = DeviceGeneratedCommandsProperties::minimum();
+        assert_eq!(check_layout(&tokens, &[20], &props), Ok(()));
+    }
+
+    #[test]
+    fn draw_must_be_last() {
+        let props = DeviceGeneratedCommandsProperties::minimum();
+
+        let tokens = [desc(0, 0, IndirectCommandsToken::Draw), desc(0, 16, push_constant(4))];
+        assert_eq!(check_layout(&tokens, &[20], &props),
+                   Err(IndirectCommandsLayoutCreationError::DrawTokenNotLast));
+
+        let tokens = [desc(0, 0, push_constant(4))];
+        assert_eq!(check_layout(&tokens, &[4], &props),
+                   Err(IndirectCommandsLayoutCreationError::DrawTokenNotLast));
+
+        assert_eq!(check_layout(&[], &[4], &props),
+                   Err(IndirectCommandsLayoutCreationError::NoTokens));
+    }
+
+    #[test]
+    fn token_outside_stride() {
+        let tokens = [desc(0, 8, IndirectCommandsToken::Draw)];
+        let props = DeviceGeneratedCommandsProperties::minimum();
+        assert_eq!(check_layout(&tokens, &[16], &props),
+                   Err(IndirectCommandsLayoutCreationError::TokenOutsideStride));
+    }
+
+    #[test]
+    fn stream_out_of_range() {
+        let tokens = [desc(1, 0, IndirectCommandsToken::Draw)];
+        let props = DeviceGeneratedCommandsProperties::minimum();
+        assert_eq!(check_layout(&tokens, &[16], &props),
+                   Err(IndirectCommandsLayoutCreationError::StreamOutOfRange {
+                           stream: 1,
+                           num_streams: 1,
+                       }));
+    }
+
+    #[test]
+    fn limits_checked() {
+        let mut props = DeviceGeneratedCommandsProperties::minimum();
+        props.max_indirect_commands_stream_stride = 16;
+
+        let tokens = [desc(0, 0, IndirectCommandsToken::Draw)];
+        assert_eq!(check_layout(&tokens, &[32], &props),
+                   Err(IndirectCommandsLayoutCreationError::MaxStreamStrideExceeded {
+                           max: 16,
+                           obtained: 32,
+                       }));
+
+        props.max_indirect_commands_token_count = 1;
+        let tokens = [desc(0, 0, push_constant(4)), desc(0, 4, IndirectCommandsToken::Draw)];
+        assert_eq!(check_layout(&tokens, &[16], &props),
+                   Err(IndirectCommandsLayoutCreationError::MaxTokensExceeded {
+                           max: 1,
+                           obtained: 2,
+                       }));
+    }
+
+    #[test]
+    fn unaligned_offset() {
+        let tokens = [desc(0, 2, IndirectCommandsToken::Draw)];
+        let props = DeviceGeneratedCommandsProperties::minimum();
+        assert_eq!(check_layout(&tokens, &[32], &props),
+                   Err(IndirectCommandsLayoutCreationError::TokenOffsetNotAligned));
+    }
+
+    #[test]
+    fn data_size() {
+        assert_eq!(IndirectCommandsToken::Draw.data_size(), 16);
+        assert_eq!(IndirectCommandsToken::DrawIndexed.data_size(), 20);
+        assert_eq!(IndirectCommandsToken::IndexBuffer.data_size(), 16);
+        let vertex_buffer = IndirectCommandsToken::VertexBuffer {
+            binding: 0,
+            dynamic_stride: false,
+        };
+        assert_eq!(vertex_buffer.data_size(), 16);
+        assert_eq!(push_constant(12).data_size(), 12);
+    }
+
+    #[test]
+    fn layout_requires_extension() {
+        let (device, _) = gfx_dev_and_queue!();
+        let pipeline_layout = EmptyPipelineDesc.build(device.clone()).unwrap();
+
+        let result = IndirectCommandsLayout::start()
+            .stream(16)
+            .token(0, 0, IndirectCommandsToken::Draw)
+            .build(pipeline_layout);
+        assert_eq!(result.err(), Some(IndirectCommandsLayoutCreationError::ExtensionNotEnabled));
+    }
+
+    #[test]
+    fn hundred_generated_draws() {
+        let instance = instance!();
+        let physical = match PhysicalDevice::enumerate(&instance).next() {
+            Some(p) => p,
+            None => return,
+        };
+        let queue = match physical.queue_families().find(|q| q.supports_graphics()) {
+            Some(q) => q,
+            None => return,
+        };
+        let extensions = DeviceExtensions {
+            nv_device_generated_commands: true,
+            ..DeviceExtensions::none()
+        };
+        let (device, mut queues) = match Device::new(&physical,
+                                                     &Features::none(),
+                                                     &extensions,
+                                                     [(queue, 0.5)].iter().cloned()) {
+            Ok(r) => r,
+            Err(_) => return,
+        };
+        let queue = queues.next().unwrap();
+
+        // Each sequence draws a full-screen triangle, and the draws are added together by the
+        // blending. The red channel of the image therefore counts the sequences that ran.
+        let vs = unsafe { ShaderModule::new(device.clone(), &FULL_SCREEN_VS) }.unwrap();
+        let fs = unsafe { ShaderModule::new(device.clone(), &BASIC_FS) }.unwrap();
+        let render_pass = Arc::new(single_pass_renderpass!(device.clone(),
+            attachments: {
+                color: {
+                    load: Clear,
+                    store: Store,
+                    format: Format::R16G16B16A16Sfloat,
+                    samples: 1,
+                }
+            },
+            pass: { color: [color], depth_stencil: {} }
+        ).unwrap());
+
+        let build = |indirect_bindable: bool| {
+            let (vs_main, fs_main) = unsafe {
+                let name = CStr::from_bytes_with_nul_unchecked(b"main\0");
+                (vs.vertex_shader_entry_point::<(), _, _, _>(name,
+                                                             EmptyShaderInterfaceDef,
+                                                             EmptyShaderInterfaceDef,
+                                                             EmptyPipelineDesc),
+                 fs.fragment_shader_entry_point::<(), _, _, _>(name,
+                                                               EmptyShaderInterfaceDef,
+                                                               EmptyShaderInterfaceDef,
+                                                               EmptyPipelineDesc))
+            };
+
+            let builder = GraphicsPipeline::start()
+                .vertex_input_single_buffer::<NoVertex>()
+                .vertex_shader(vs_main, ())
+                .triangle_list()
+                .viewports_dynamic_scissors_irrelevant(1)
+                .fragment_shader(fs_main, ())
+                .blend_collective(AttachmentBlend {
+                                      enabled: true,
+                                      color_src: BlendFactor::One,
+                                      color_dst: BlendFactor::One,
+                                      alpha_src: BlendFactor::One,
+                                      alpha_dst: BlendFactor::One,
+                                      ..AttachmentBlend::pass_through()
+                                  })
+                .render_pass(Subpass::from(render_pass.clone(), 0).unwrap());
+            let builder = if indirect_bindable {
+                builder.indirect_bindable()
+            } else {
+                builder
+            };
+            Arc::new(builder.build(device.clone()).unwrap())
+        };
+        let pipeline = build(true);
+        let not_bindable = build(false);
+        assert!(pipeline.is_indirect_bindable());
+
+        let layout = IndirectCommandsLayout::start()
+            .stream(16)
+            .token(0, 0, IndirectCommandsToken::Draw)
+            .build(pipeline.clone())
+            .unwrap();
+
+        let draw = DrawIndirectCommand {
+            vertex_count: 3,
+            instance_count: 1,
+            first_vertex: 0,
+            first_instance: 0,
+        };
+        let stream = CpuAccessibleBuffer::from_iter(device.clone(),
+                                                    BufferUsage::indirect_buffer(),
+                                                    Some(queue.family()),
+                                                    (0 .. 100).map(|_| draw))
+            .unwrap();
+        let preprocess_size = layout.preprocess_requirements(&pipeline, 100).size;
+        let preprocess = DeviceLocalBuffer::<[u8]>::array(device.clone(),
+                                                          preprocess_size,
+                                                          BufferUsage::indirect_buffer(),
+                                                          Some(queue.family()))
+            .unwrap();
+        let vertices = CpuAccessibleBuffer::from_iter(device.clone(),
+                                                      BufferUsage::vertex_buffer(),
+                                                      Some(queue.family()),
+                                                      (0 .. 3).map(|_| NoVertex { _dummy: 0 }))
+            .unwrap();
+
+        let usage = ImageUsage {
+            transfer_source: true,
+            color_attachment: true,
+            ..ImageUsage::none()
+        };
+        let image = AttachmentImage::with_usage(device.clone(), [1, 1], Format::R16G16B16A16Sfloat,
+                                                usage)
+            .unwrap();
+        let framebuffer = Arc::new(Framebuffer::start(render_pass.clone())
+                                       .add(image.clone())
+                                       .unwrap()
+                                       .build()
+                                       .unwrap());
+        let dynamic = DynamicState {
+            viewports: Some(vec![Viewport {
+                                     origin: [0.0, 0.0],
+                                     dimensions: [1.0, 1.0],
+                                     depth_range: 0.0 .. 1.0,
+                                 }]),
+            ..DynamicState::none()
+        };
+        let streams = || vec![Box::new(stream.clone()) as Box<BufferAccess + Send + Sync>];
+
+        let builder = AutoCommandBufferBuilder::new(device.clone(), queue.family())
+            .unwrap()
+            .begin_render_pass(framebuffer.clone(),
+                               false,
+                               vec![ClearValue::Float([0.0, 0.0, 0.0, 0.0])])
+            .unwrap();
+
+        // Pipelines must opt in to be used with generated commands.
+        match builder.execute_generated_commands(not_bindable.clone(), dynamic.clone(),
+                                                 vertices.clone(), layout.clone(), streams(),
+                                                 100, preprocess.clone(), (), ()) {
+            Err(ExecuteGeneratedCommandsError::CheckGeneratedCommandsError(
+                CheckGeneratedCommandsError::PipelineNotIndirectBindable)) => (),
+            _ => panic!(),
+        }
+
+        let builder = AutoCommandBufferBuilder::new(device.clone(), queue.family())
+            .unwrap()
+            .begin_render_pass(framebuffer.clone(),
+                               false,
+                               vec![ClearValue::Float([0.0, 0.0, 0.0, 0.0])])
+            .unwrap();
+        match builder.execute_generated_commands(pipeline.clone(), dynamic.clone(),
+                                                 vertices.clone(), layout.clone(), Vec::new(),
+                                                 100, preprocess.clone(), (), ()) {
+            Err(ExecuteGeneratedCommandsError::CheckGeneratedCommandsError(
+                CheckGeneratedCommandsError::WrongNumberOfStreams { .. })) => (),
+            _ => panic!(),
+        }
+
+        let cb = AutoCommandBufferBuilder::new(device.clone(), queue.family())
+            .unwrap()
+            .begin_render_pass(framebuffer.clone(),
+                               false,
+                               vec![ClearValue::Float([0.0, 0.0, 0.0, 0.0])])
+            .unwrap()
+            .execute_generated_commands(pipeline.clone(), dynamic, vertices, layout, streams(),
+                                        100, preprocess, (), ())
+            .unwrap()
+            .end_render_pass()
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let future = now(device.clone()).then_execute(queue.clone(), cb).unwrap();
+        let texels: Vec<[u16; 4]> =
+            read_image_region(future, queue.clone(), image, [0, 0, 0], [1, 1, 1], 0, 0).unwrap();
+        // 100.0 as a half-precision float.
+        assert_eq!(texels[0][0], 0x5640);
+    }
+
+    // Vertex type of a vertex buffer that isn't read by the vertex shader.
+    #[derive(Debug, Copy, Clone)]
+    struct NoVertex {
+        _dummy: u32,
+    }
+
+    unsafe impl Vertex for NoVertex {
+        #[inline]
+        fn member(_: &str) -> Option<VertexMemberInfo> {
+            None
+        }
+    }
+}
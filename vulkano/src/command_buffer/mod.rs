@@ -75,16 +75,36 @@
 
 pub use self::auto::AutoCommandBuffer;
 pub use self::auto::AutoCommandBufferBuilder;
+pub use self::auto::BeginRenderingError;
+pub use self::auto::BuildError;
+pub use self::auto::CopyBufferError;
+pub use self::auto::CopyBufferToImageError;
+pub use self::auto::CopyImageToBufferError;
+pub use self::auto::DebugLabelError;
+pub use self::auto::ResetQueryPoolError;
+pub use self::auto::WriteTimestampError;
+pub use self::readback::ReadbackError;
+pub use self::readback::read_image_region;
+pub use self::readback::read_texel;
+pub use self::replay::ReplayError;
+pub use self::replay::ReplayFuture;
+pub use self::replay::ReplayableCommandBuffer;
 pub use self::state_cacher::StateCacher;
 pub use self::state_cacher::StateCacherOutcome;
+pub use self::synced::DebugLabelScope;
 pub use self::traits::CommandBuffer;
 pub use self::traits::CommandBufferBuild;
 pub use self::traits::CommandBufferExecError;
 pub use self::traits::CommandBufferExecFuture;
 
+use pipeline::depth_stencil::Compare;
+use pipeline::depth_stencil::DynamicStencilValue;
+use pipeline::raster::DepthBias;
 use pipeline::viewport::Scissor;
 use pipeline::viewport::Viewport;
+use std::ops::Range;
 
+pub mod indirect_commands;
 pub mod pool;
 pub mod submit;
 pub mod synced;
@@ -92,6 +112,9 @@ pub mod sys;
 pub mod validity;
 
 mod auto;
+mod capture;
+mod readback;
+mod replay;
 mod state_cacher;
 mod traits;
 
@@ -129,6 +152,23 @@ pub struct DynamicState {
     pub line_width: Option<f32>,
     pub viewports: Option<Vec<Viewport>>,
     pub scissors: Option<Vec<Scissor>>,
+    pub stencil_compare_mask: Option<DynamicStencilValue>,
+    pub stencil_write_mask: Option<DynamicStencilValue>,
+    pub stencil_reference: Option<DynamicStencilValue>,
+    /// Whether the depth test is enabled. Requires the `ext_extended_dynamic_state` extension.
+    pub depth_test_enable: Option<bool>,
+    /// Whether depth writes are enabled. Requires the `ext_extended_dynamic_state` extension.
+    pub depth_write_enable: Option<bool>,
+    /// Comparison of the depth test. Requires the `ext_extended_dynamic_state` extension.
+    pub depth_compare_op: Option<Compare>,
+    /// Whether the stencil test is enabled. Requires the `ext_extended_dynamic_state` extension.
+    pub stencil_test_enable: Option<bool>,
+    /// Range of depth values that pass the depth bounds test. Both values must be between 0.0
+    /// and 1.0.
+    pub depth_bounds: Option<Range<f32>>,
+    /// Depth bias added to the depth of the fragments. A `clamp` different from 0.0 requires the
+    /// `depth_bias_clamp` feature.
+    pub depth_bias: Option<DepthBias>,
     // TODO: missing fields
 }
 
@@ -139,6 +179,15 @@ impl DynamicState {
             line_width: None,
             viewports: None,
             scissors: None,
+            stencil_compare_mask: None,
+            stencil_write_mask: None,
+            stencil_reference: None,
+            depth_test_enable: None,
+            depth_write_enable: None,
+            depth_compare_op: None,
+            stencil_test_enable: None,
+            depth_bounds: None,
+            depth_bias: None,
         }
     }
 }
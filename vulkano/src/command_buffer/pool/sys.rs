@@ -97,7 +97,8 @@ impl UnsafeCommandPool {
             check_errors(vk.CreateCommandPool(device.internal_object(),
                                               &infos,
                                               ptr::null(),
-                                              &mut output))?;
+                                              &mut output),
+                         "vkCreateCommandPool")?;
             output
         };
 
@@ -126,7 +127,8 @@ impl UnsafeCommandPool {
         };
 
         let vk = self.device.pointers();
-        check_errors(vk.ResetCommandPool(self.device.internal_object(), self.pool, flags))?;
+        check_errors(vk.ResetCommandPool(self.device.internal_object(), self.pool, flags),
+                     "vkResetCommandPool")?;
         Ok(())
     }
 
@@ -136,12 +138,12 @@ impl UnsafeCommandPool {
     /// Command buffers allocated from the pool are not affected by trimming.
     ///
     /// This function is supported only if the `VK_KHR_maintenance1` extension was enabled at
-    /// device creation. Otherwise an error is returned.
+    /// device creation or if the device supports Vulkan 1.1. Otherwise an error is returned.
     /// Since this operation is purely an optimization it is legitimate to call this function and
     /// simply ignore any possible error.
     pub fn trim(&self) -> Result<(), CommandPoolTrimError> {
         unsafe {
-            if !self.device.loaded_extensions().khr_maintenance1 {
+            if !self.device.effective_extensions().khr_maintenance1 {
                 return Err(CommandPoolTrimError::Maintenance1ExtensionNotEnabled);
             }
 
@@ -180,7 +182,8 @@ impl UnsafeCommandPool {
             let mut out = Vec::with_capacity(count);
             check_errors(vk.AllocateCommandBuffers(self.device.internal_object(),
                                                    &infos,
-                                                   out.as_mut_ptr()))?;
+                                                   out.as_mut_ptr()),
+                         "vkAllocateCommandBuffers")?;
 
             out.set_len(count);
 
@@ -285,7 +288,8 @@ impl ExactSizeIterator for UnsafeCommandPoolAllocIter {
 /// Error that can happen when trimming command pools.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum CommandPoolTrimError {
-    /// The `KHR_maintenance1` extension was not enabled.
+    /// The `KHR_maintenance1` extension was not enabled and the device doesn't support
+    /// Vulkan 1.1.
     Maintenance1ExtensionNotEnabled,
 }
 
@@ -0,0 +1,312 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::error;
+use std::fmt;
+use std::mem;
+use std::sync::Arc;
+
+use OomError;
+use buffer::BufferUsage;
+use buffer::CpuAccessibleBuffer;
+use command_buffer::AutoCommandBufferBuilder;
+use command_buffer::CommandBufferExecError;
+use command_buffer::CopyImageToBufferError;
+use command_buffer::auto::BuildError;
+use device::Queue;
+use image::ImageAccess;
+use memory::Content;
+use sync::FlushError;
+use sync::GpuFuture;
+
+/// Reads a region of an image from the CPU.
+///
+/// The region is copied to a host-visible buffer that is just large enough to contain it, after
+/// the commands of `before` (usually the commands that wrote the image). This function then
+/// blocks until the copy is finished and returns the texels of the region, row by row. The image
+/// is transitioned to the layout required for the copy if needed.
+///
+/// `T` is the type of one texel, for example `u32` for an `R32Uint` image or `[u8; 4]` for an
+/// `R8G8B8A8Unorm` image. Its size must be the size of a texel of the format of the image,
+/// otherwise `TexelSizeMismatch` is returned. Compressed, depth and stencil formats are not
+/// supported. The region must contain at least one texel.
+///
+/// This is much cheaper than copying the whole image when only a few texels are needed, for
+/// example in order to read the identifier of the object below the cursor.
+pub fn read_image_region<F, I, T>(before: F, queue: Arc<Queue>, image: I, offset: [u32; 3],
+                                  size: [u32; 3], layer: u32, mipmap: u32)
+                                  -> Result<Vec<T>, ReadbackError>
+    where F: GpuFuture,
+          I: ImageAccess + Send + Sync + 'static,
+          T: Content + Copy + Send + Sync + 'static
+{
+    if size[0] == 0 || size[1] == 0 || size[2] == 0 {
+        return Err(ReadbackError::EmptyRegion);
+    }
+
+    let format = image.format();
+    if !image.has_color() {
+        return Err(ReadbackError::UnsupportedFormat);
+    }
+
+    match format.block_size() {
+        Some(texel_size) if format.block_dimensions() == [1, 1] => {
+            if texel_size != mem::size_of::<T>() {
+                return Err(ReadbackError::TexelSizeMismatch {
+                               texel_size: texel_size,
+                               type_size: mem::size_of::<T>(),
+                           });
+            }
+        },
+        _ => return Err(ReadbackError::UnsupportedFormat),
+    }
+
+    let device = queue.device().clone();
+    let len = size[0] as usize * size[1] as usize * size[2] as usize;
+
+    let buffer = unsafe {
+        CpuAccessibleBuffer::<[T]>::uninitialized_array(device.clone(),
+                                                        len,
+                                                        BufferUsage::transfer_dest(),
+                                                        Some(queue.family()))?
+    };
+
+    let cb = AutoCommandBufferBuilder::new(device, queue.family())?
+        .copy_image_to_buffer_dimensions(image, buffer.clone(), offset, size, layer, 1, mipmap)?
+        .host_read_barrier(buffer.clone())
+        .unwrap() // Can't fail, as the builder is new and outside of a render pass.
+        .build()
+        .map_err(|err| match err {
+                     BuildError::OomError(err) => ReadbackError::OomError(err),
+                     BuildError::AutoCommandBufferBuilderContextError(_) => unreachable!(),
+                 })?;
+
+    before
+        .then_execute(queue, cb)?
+        .then_signal_fence_and_flush()?
+        .wait(None)?;
+
+    let content = buffer.read().unwrap(); // The buffer is no longer used by the GPU.
+    Ok(content.to_vec())
+}
+
+/// Reads one texel of the first layer and first mipmap level of an image from the CPU.
+///
+/// This is the same as `read_image_region` with a region of one texel.
+#[inline]
+pub fn read_texel<F, I, T>(before: F, queue: Arc<Queue>, image: I, position: [u32; 2])
+                           -> Result<T, ReadbackError>
+    where F: GpuFuture,
+          I: ImageAccess + Send + Sync + 'static,
+          T: Content + Copy + Send + Sync + 'static
+{
+    let texels = read_image_region(before,
+                                   queue,
+                                   image,
+                                   [position[0], position[1], 0],
+                                   [1, 1, 1],
+                                   0,
+                                   0)?;
+    Ok(texels[0])
+}
+
+/// Error that can happen when reading back an image.
+#[derive(Debug, Clone)]
+pub enum ReadbackError {
+    /// Not enough memory.
+    OomError(OomError),
+
+    /// The format of the image is compressed, is a depth or stencil format, or doesn't have a
+    /// known texel size.
+    UnsupportedFormat,
+
+    /// The region doesn't contain any texel.
+    EmptyRegion,
+
+    /// The size of the type of the texels doesn't match the size of a texel of the image.
+    TexelSizeMismatch {
+        /// Size in bytes of a texel of the image.
+        texel_size: usize,
+        /// Size in bytes of the type of the texels.
+        type_size: usize,
+    },
+
+    /// The region couldn't be copied.
+    CopyError(CopyImageToBufferError),
+
+    /// The command buffer couldn't be executed.
+    ExecError(CommandBufferExecError),
+
+    /// Error while flushing the submission or while waiting for it.
+    FlushError(FlushError),
+}
+
+impl error::Error for ReadbackError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            ReadbackError::OomError(_) => "not enough memory",
+            ReadbackError::UnsupportedFormat => {
+                "the format of the image is compressed, is a depth or stencil format, or doesn't \
+                 have a known texel size"
+            },
+            ReadbackError::EmptyRegion => "the region doesn't contain any texel",
+            ReadbackError::TexelSizeMismatch { .. } => {
+                "the size of the type of the texels doesn't match the size of a texel of the image"
+            },
+            ReadbackError::CopyError(_) => "the region couldn't be copied",
+            ReadbackError::ExecError(_) => "the command buffer couldn't be executed",
+            ReadbackError::FlushError(_) => "error while flushing or waiting for the submission",
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            ReadbackError::OomError(ref err) => Some(err),
+            ReadbackError::CopyError(ref err) => Some(err),
+            ReadbackError::ExecError(ref err) => Some(err),
+            ReadbackError::FlushError(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ReadbackError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl From<OomError> for ReadbackError {
+    #[inline]
+    fn from(err: OomError) -> ReadbackError {
+        ReadbackError::OomError(err)
+    }
+}
+
+impl From<CopyImageToBufferError> for ReadbackError {
+    #[inline]
+    fn from(err: CopyImageToBufferError) -> ReadbackError {
+        ReadbackError::CopyError(err)
+    }
+}
+
+impl From<CommandBufferExecError> for ReadbackError {
+    #[inline]
+    fn from(err: CommandBufferExecError) -> ReadbackError {
+        ReadbackError::ExecError(err)
+    }
+}
+
+impl From<FlushError> for ReadbackError {
+    #[inline]
+    fn from(err: FlushError) -> ReadbackError {
+        ReadbackError::FlushError(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buffer::BufferUsage;
+    use buffer::CpuAccessibleBuffer;
+    use command_buffer::AutoCommandBufferBuilder;
+    use command_buffer::ReadbackError;
+    use command_buffer::read_image_region;
+    use command_buffer::read_texel;
+    use format::Format;
+    use image::AttachmentImage;
+    use image::Dimensions;
+    use image::ImageUsage;
+    use image::StorageImage;
+    use sync::GpuFuture;
+    use sync::now;
+
+    #[test]
+    fn read_texels() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let image = StorageImage::new(device.clone(),
+                                      Dimensions::Dim2d {
+                                          width: 4,
+                                          height: 4,
+                                      },
+                                      Format::R32Uint,
+                                      Some(queue.family()))
+            .unwrap();
+        let source = CpuAccessibleBuffer::from_iter(device.clone(),
+                                                    BufferUsage::all(),
+                                                    Some(queue.family()),
+                                                    (0 .. 16).map(|n| n * 10u32))
+            .unwrap();
+
+        let upload = || {
+            let cb = AutoCommandBufferBuilder::new(device.clone(), queue.family())
+                .unwrap()
+                .copy_buffer_to_image(source.clone(), image.clone())
+                .unwrap()
+                .build()
+                .unwrap();
+            now(device.clone()).then_execute(queue.clone(), cb).unwrap()
+        };
+
+        let texel: u32 = read_texel(upload(), queue.clone(), image.clone(), [2, 1]).unwrap();
+        assert_eq!(texel, 60);
+
+        let region: Vec<u32> =
+            read_image_region(upload(), queue.clone(), image.clone(), [1, 1, 0], [2, 2, 1], 0, 0)
+                .unwrap();
+        assert_eq!(region, vec![50, 60, 90, 100]);
+
+        match read_texel::<_, _, u8>(upload(), queue.clone(), image.clone(), [0, 0]) {
+            Err(ReadbackError::TexelSizeMismatch {
+                    texel_size: 4,
+                    type_size: 1,
+                }) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn invalid_regions() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let image = StorageImage::new(device.clone(),
+                                      Dimensions::Dim2d {
+                                          width: 4,
+                                          height: 4,
+                                      },
+                                      Format::R32Uint,
+                                      Some(queue.family()))
+            .unwrap();
+        match read_image_region::<_, _, u32>(now(device.clone()),
+                                             queue.clone(),
+                                             image.clone(),
+                                             [0, 0, 0],
+                                             [2, 0, 1],
+                                             0,
+                                             0) {
+            Err(ReadbackError::EmptyRegion) => (),
+            _ => panic!(),
+        }
+
+        let usage = ImageUsage {
+            transfer_source: true,
+            depth_stencil_attachment: true,
+            ..ImageUsage::none()
+        };
+        let depth = AttachmentImage::with_usage(device.clone(), [4, 4], Format::D16Unorm, usage)
+            .unwrap();
+        match read_texel::<_, _, u16>(now(device.clone()), queue.clone(), depth, [0, 0]) {
+            Err(ReadbackError::UnsupportedFormat) => (),
+            _ => panic!(),
+        }
+    }
+}
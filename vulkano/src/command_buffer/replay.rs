@@ -0,0 +1,313 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::collections::VecDeque;
+use std::error;
+use std::fmt;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use command_buffer::AutoCommandBuffer;
+use command_buffer::CommandBuffer;
+use command_buffer::CommandBufferExecError;
+use command_buffer::CommandBufferExecFuture;
+use command_buffer::pool::standard::StandardCommandPoolAlloc;
+use device::Device;
+use device::DeviceOwned;
+use device::Queue;
+use sync::FenceSignalFuture;
+use sync::FlushError;
+use sync::GpuFuture;
+use sync::NowFuture;
+use sync::now;
+
+/// Command buffer that is recorded once and then submitted again and again, typically once per
+/// frame.
+///
+/// The `ReplayableCommandBuffer` holds the `AutoCommandBuffer`, and therefore all the resources
+/// that it uses, for as long as it exists. Each submission is immediately flushed and followed
+/// by a fence. At most `max_in_flight` submissions can be executing at the same time: once this
+/// number is reached, a new submission first blocks until the oldest one has finished.
+///
+/// The locks on the resources used by the command buffer are released whenever a submission
+/// finishes, so that a command buffer that writes to a buffer can be submitted again once the
+/// previous execution is over.
+///
+/// # Example
+///
+/// ```ignore
+/// let cb = AutoCommandBufferBuilder::primary_simultaneous_use(device.clone(), queue.family())
+///     .unwrap()
+///     // ...
+///     .build().unwrap();
+/// let cb = ReplayableCommandBuffer::new(cb, 2);
+///
+/// loop {
+///     let future = cb.execute(queue.clone()).unwrap();
+///     // ...
+/// }
+/// ```
+pub struct ReplayableCommandBuffer<P = StandardCommandPoolAlloc> {
+    command_buffer: Arc<AutoCommandBuffer<P>>,
+    max_in_flight: usize,
+    // Fences of the submissions that may still be executing, the oldest first.
+    in_flight: Mutex<VecDeque<Box<InFlight + Send + Sync>>>,
+}
+
+/// Future returned when submitting a `ReplayableCommandBuffer`.
+pub type ReplayFuture<F, P = StandardCommandPoolAlloc> =
+    FenceSignalFuture<CommandBufferExecFuture<F, Arc<AutoCommandBuffer<P>>>>;
+
+impl<P> ReplayableCommandBuffer<P>
+    where P: Send + Sync + 'static
+{
+    /// Wraps around a command buffer so that it can be submitted multiple times.
+    ///
+    /// If the command buffer wasn't created with `primary_simultaneous_use`, it can't be executing
+    /// twice at the same time and `max_in_flight` is ignored and set to 1.
+    ///
+    /// A command buffer created with `primary_one_time_submit` can be wrapped, but submitting it
+    /// a second time will return an error.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `max_in_flight` is 0.
+    ///
+    pub fn new(command_buffer: AutoCommandBuffer<P>, max_in_flight: usize)
+               -> ReplayableCommandBuffer<P> {
+        assert!(max_in_flight >= 1);
+
+        let max_in_flight = if command_buffer.simultaneous_use() {
+            max_in_flight
+        } else {
+            1
+        };
+
+        ReplayableCommandBuffer {
+            command_buffer: Arc::new(command_buffer),
+            max_in_flight: max_in_flight,
+            in_flight: Mutex::new(VecDeque::with_capacity(max_in_flight)),
+        }
+    }
+
+    /// Returns the command buffer that is being replayed.
+    #[inline]
+    pub fn command_buffer(&self) -> &Arc<AutoCommandBuffer<P>> {
+        &self.command_buffer
+    }
+
+    /// Returns the maximum number of submissions that can be executing at the same time.
+    #[inline]
+    pub fn max_in_flight(&self) -> usize {
+        self.max_in_flight
+    }
+
+    /// Returns the number of submissions that haven't been waited upon yet. Some of them may
+    /// already have finished executing.
+    #[inline]
+    pub fn num_in_flight(&self) -> usize {
+        self.in_flight.lock().unwrap().len()
+    }
+
+    /// Submits the command buffer to a queue.
+    ///
+    /// > **Note**: This is just a shortcut for `execute_after(vulkano::sync::now(), queue)`.
+    #[inline]
+    pub fn execute(&self, queue: Arc<Queue>)
+                   -> Result<Arc<ReplayFuture<NowFuture, P>>, ReplayError> {
+        let device = queue.device().clone();
+        self.execute_after(now(device), queue)
+    }
+
+    /// Submits the command buffer to a queue after an existing future.
+    ///
+    /// Contrary to `CommandBuffer::execute_after`, the submission is immediately flushed and
+    /// followed by a fence. The returned future can be used to chain more operations, or can be
+    /// waited upon. If `max_in_flight` submissions are still in progress, this function first
+    /// blocks until the oldest one has finished.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the device of the command buffer is not the same as the device of the future.
+    ///
+    pub fn execute_after<F>(&self, future: F, queue: Arc<Queue>)
+                            -> Result<Arc<ReplayFuture<F, P>>, ReplayError>
+        where F: GpuFuture + Send + Sync + 'static
+    {
+        let mut in_flight = self.in_flight.lock().unwrap();
+
+        while in_flight.len() >= self.max_in_flight {
+            in_flight.pop_front().unwrap().wait()?;
+        }
+
+        let future = self.command_buffer
+            .clone()
+            .execute_after(future, queue)?
+            .then_signal_fence_and_flush()?;
+        let future = Arc::new(future);
+
+        in_flight.push_back(Box::new(future.clone()) as Box<_>);
+        Ok(future)
+    }
+
+    /// Blocks until all the submissions have finished executing.
+    pub fn wait_idle(&self) -> Result<(), FlushError> {
+        let mut in_flight = self.in_flight.lock().unwrap();
+
+        while let Some(submission) = in_flight.pop_front() {
+            submission.wait()?;
+        }
+
+        Ok(())
+    }
+}
+
+unsafe impl<P> DeviceOwned for ReplayableCommandBuffer<P> {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        self.command_buffer.device()
+    }
+}
+
+// A submission whose end can be waited upon, regardless of the future it was submitted after.
+trait InFlight {
+    fn wait(&self) -> Result<(), FlushError>;
+}
+
+impl<F> InFlight for Arc<FenceSignalFuture<F>>
+    where F: GpuFuture
+{
+    #[inline]
+    fn wait(&self) -> Result<(), FlushError> {
+        FenceSignalFuture::wait(self, None)
+    }
+}
+
+/// Error that can happen when submitting a `ReplayableCommandBuffer`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplayError {
+    /// The command buffer couldn't be executed.
+    ExecError(CommandBufferExecError),
+
+    /// Error while flushing the submission or while waiting for a previous one.
+    FlushError(FlushError),
+}
+
+impl error::Error for ReplayError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            ReplayError::ExecError(_) => "the command buffer couldn't be executed",
+            ReplayError::FlushError(_) => "error while flushing or waiting for a submission",
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            ReplayError::ExecError(ref err) => Some(err),
+            ReplayError::FlushError(ref err) => Some(err),
+        }
+    }
+}
+
+impl fmt::Display for ReplayError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl From<CommandBufferExecError> for ReplayError {
+    #[inline]
+    fn from(err: CommandBufferExecError) -> ReplayError {
+        ReplayError::ExecError(err)
+    }
+}
+
+impl From<FlushError> for ReplayError {
+    #[inline]
+    fn from(err: FlushError) -> ReplayError {
+        ReplayError::FlushError(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::iter;
+
+    use buffer::BufferUsage;
+    use buffer::CpuAccessibleBuffer;
+    use command_buffer::AutoCommandBufferBuilder;
+    use command_buffer::ReplayableCommandBuffer;
+
+    #[test]
+    fn max_in_flight() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let cb = AutoCommandBufferBuilder::primary_simultaneous_use(device, queue.family())
+            .unwrap()
+            .build()
+            .unwrap();
+        let cb = ReplayableCommandBuffer::new(cb, 2);
+
+        for _ in 0 .. 5 {
+            cb.execute(queue.clone()).unwrap();
+            assert!(cb.num_in_flight() <= 2);
+        }
+
+        cb.wait_idle().unwrap();
+        assert_eq!(cb.num_in_flight(), 0);
+    }
+
+    #[test]
+    fn exclusive_command_buffer() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let cb = AutoCommandBufferBuilder::new(device, queue.family())
+            .unwrap()
+            .build()
+            .unwrap();
+        let cb = ReplayableCommandBuffer::new(cb, 3);
+        assert_eq!(cb.max_in_flight(), 1);
+
+        for _ in 0 .. 3 {
+            cb.execute(queue.clone()).unwrap();
+        }
+    }
+
+    #[test]
+    fn replay_writes_to_buffer() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let source = CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::all(),
+                                                    iter::once(queue.family()), 0 .. 128u32)
+            .unwrap();
+        let destination = CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::all(),
+                                                         iter::once(queue.family()),
+                                                         (0 .. 128).map(|_| 0u32))
+            .unwrap();
+
+        let cb = AutoCommandBufferBuilder::new(device, queue.family())
+            .unwrap()
+            .copy_buffer(source, destination.clone())
+            .unwrap()
+            .build()
+            .unwrap();
+        let cb = ReplayableCommandBuffer::new(cb, 1);
+
+        // Each submission must release its exclusive lock on `destination` once it has finished.
+        for _ in 0 .. 3 {
+            cb.execute(queue.clone()).unwrap();
+        }
+
+        cb.wait_idle().unwrap();
+        assert_eq!(destination.read().unwrap()[5], 5);
+    }
+}
@@ -15,6 +15,7 @@ use std::ptr;
 
 use buffer::sys::UnsafeBuffer;
 use device::Queue;
+use format::FormatTy;
 use image::sys::UnsafeImage;
 use memory::DeviceMemory;
 use sync::Fence;
@@ -142,7 +143,6 @@ impl<'a> SubmitBindSparseBuilder<'a> {
             debug_assert!(queue.family().supports_sparse_binding());
 
             let vk = queue.device().pointers();
-            let queue = queue.internal_object_guard();
 
             // We start by storing all the `VkSparseBufferMemoryBindInfo`s of the whole command
             // in the same collection.
@@ -240,11 +240,14 @@ impl<'a> SubmitBindSparseBuilder<'a> {
                 bs_infos
             };
 
-            // Finally executing the command.
+            // Finally executing the command. The queue is only locked for the duration of the
+            // call.
+            let queue = queue.internal_object_guard();
             check_errors(vk.QueueBindSparse(*queue,
                                             bs_infos.len() as u32,
                                             bs_infos.as_ptr(),
-                                            self.fence))?;
+                                            self.fence),
+                         "vkQueueBindSparse")?;
             Ok(())
         }
     }
@@ -432,6 +435,8 @@ impl<'a> SubmitBindSparseImageOpaqueBindBuilder<'a> {
 
 pub struct SubmitBindSparseImageBindBuilder<'a> {
     image: vk::Image,
+    // Aspect of the image whose tiles are bound.
+    aspect: vk::ImageAspectFlags,
     binds: SmallVec<[vk::SparseImageMemoryBind; 1]>,
     marker: PhantomData<&'a ()>,
 }
@@ -442,14 +447,86 @@ impl<'a> SubmitBindSparseImageBindBuilder<'a> {
     ///
     /// - `image` must be an image with sparse binding enabled.
     pub unsafe fn new(image: &'a UnsafeImage) -> SubmitBindSparseImageBindBuilder {
+        // TODO: allow binding the stencil aspect of depth-stencil images
+        let aspect = match image.format().ty() {
+            FormatTy::Depth | FormatTy::DepthStencil => vk::IMAGE_ASPECT_DEPTH_BIT,
+            FormatTy::Stencil => vk::IMAGE_ASPECT_STENCIL_BIT,
+            _ => vk::IMAGE_ASPECT_COLOR_BIT,
+        };
+
         SubmitBindSparseImageBindBuilder {
             image: image.internal_object(),
+            aspect: aspect,
             binds: SmallVec::new(),
             marker: PhantomData,
         }
     }
 
-    // TODO: finish
+    /// Binds the region of `extent` texels starting at `offset` of the given mipmap level and
+    /// array layer to `memory`, starting at `memory_offset` bytes.
+    ///
+    /// # Safety
+    ///
+    /// - The mipmap level must not be part of the mip tail of the image.
+    /// - `offset` must be a multiple of the sparse image granularity. `extent` must be a multiple
+    ///   of the granularity as well, unless the region reaches the edge of the mipmap level.
+    /// - The memory must satisfy the requirements of the image, and be kept alive for as long as
+    ///   it is bound.
+    ///
+    pub unsafe fn add_bind(&mut self, mip_level: u32, array_layer: u32, offset: [i32; 3],
+                           extent: [u32; 3], memory: &DeviceMemory, memory_offset: usize) {
+        self.binds.push(vk::SparseImageMemoryBind {
+                            subresource: vk::ImageSubresource {
+                                aspectMask: self.aspect,
+                                mipLevel: mip_level,
+                                arrayLayer: array_layer,
+                            },
+                            offset: vk::Offset3D {
+                                x: offset[0],
+                                y: offset[1],
+                                z: offset[2],
+                            },
+                            extent: vk::Extent3D {
+                                width: extent[0],
+                                height: extent[1],
+                                depth: extent[2],
+                            },
+                            memory: memory.internal_object(),
+                            memoryOffset: memory_offset as vk::DeviceSize,
+                            flags: 0,
+                        });
+    }
+
+    /// Unbinds the memory of the region of `extent` texels starting at `offset` of the given
+    /// mipmap level and array layer.
+    ///
+    /// # Safety
+    ///
+    /// Same as `add_bind`, except for the memory.
+    ///
+    pub unsafe fn add_unbind(&mut self, mip_level: u32, array_layer: u32, offset: [i32; 3],
+                             extent: [u32; 3]) {
+        self.binds.push(vk::SparseImageMemoryBind {
+                            subresource: vk::ImageSubresource {
+                                aspectMask: self.aspect,
+                                mipLevel: mip_level,
+                                arrayLayer: array_layer,
+                            },
+                            offset: vk::Offset3D {
+                                x: offset[0],
+                                y: offset[1],
+                                z: offset[2],
+                            },
+                            extent: vk::Extent3D {
+                                width: extent[0],
+                                height: extent[1],
+                                depth: extent[2],
+                            },
+                            memory: 0,
+                            memoryOffset: 0,
+                            flags: 0,
+                        });
+    }
 }
 
 /// Error that can happen when submitting the present prototype.
@@ -23,11 +23,13 @@ pub use self::queue_present::SubmitPresentBuilder;
 pub use self::queue_present::SubmitPresentError;
 pub use self::queue_submit::SubmitCommandBufferBuilder;
 pub use self::queue_submit::SubmitCommandBufferError;
+pub use self::queue_submit2::SubmitCommandBuffer2Builder;
 pub use self::semaphores_wait::SubmitSemaphoresWaitBuilder;
 
 mod bind_sparse;
 mod queue_present;
 mod queue_submit;
+mod queue_submit2;
 mod semaphores_wait;
 
 /// Contains all the possible submission builders.
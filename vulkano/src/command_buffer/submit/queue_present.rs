@@ -11,11 +11,15 @@ use smallvec::SmallVec;
 use std::error;
 use std::fmt;
 use std::marker::PhantomData;
-use std::mem;
 use std::ptr;
+use std::sync::Arc;
+use std::sync::Mutex;
 
+use device::DeviceOwned;
 use device::Queue;
+use instrumentation;
 use swapchain::Swapchain;
+use sync::Fence;
 use sync::Semaphore;
 
 use Error;
@@ -32,6 +36,11 @@ pub struct SubmitPresentBuilder<'a> {
     wait_semaphores: SmallVec<[vk::Semaphore; 8]>,
     swapchains: SmallVec<[vk::SwapchainKHR; 4]>,
     image_indices: SmallVec<[u32; 4]>,
+    present_fences: SmallVec<[vk::Fence; 4]>,
+    // The swapchains that have been added, with the fence that tracks their present if any.
+    // Their presents are reported to them once the command has been successfully submitted.
+    presented: SmallVec<[(&'a Swapchain, Option<Arc<Fence>>); 4]>,
+    results: Option<&'a Mutex<Vec<Result<(), SubmitPresentError>>>>,
     marker: PhantomData<&'a ()>,
 }
 
@@ -43,6 +52,9 @@ impl<'a> SubmitPresentBuilder<'a> {
             wait_semaphores: SmallVec::new(),
             swapchains: SmallVec::new(),
             image_indices: SmallVec::new(),
+            present_fences: SmallVec::new(),
+            presented: SmallVec::new(),
+            results: None,
             marker: PhantomData,
         }
     }
@@ -83,6 +95,52 @@ impl<'a> SubmitPresentBuilder<'a> {
         debug_assert!(image_num < swapchain.num_images());
         self.swapchains.push(swapchain.internal_object());
         self.image_indices.push(image_num);
+        self.present_fences.push(0);
+        self.presented.push((swapchain, None));
+    }
+
+    /// Sets a fence that is signaled when the presentation engine no longer needs the semaphores
+    /// and the image of the swapchain that was added last.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if no swapchain has been added yet.
+    /// - Panics if the `ext_swapchain_maintenance1` extension isn't enabled on the device of the
+    ///   fence.
+    ///
+    /// # Safety
+    ///
+    /// - If you submit this builder, the fence must be kept alive until it is signaled.
+    ///
+    /// - The fence must be unsignaled and must not be used by any other pending operation.
+    ///
+    #[inline]
+    pub unsafe fn set_present_fence(&mut self, fence: &'a Fence) {
+        assert!(fence.device().loaded_extensions().ext_swapchain_maintenance1,
+                "the ext_swapchain_maintenance1 extension must be enabled to use present fences");
+        let last = self.present_fences
+            .last_mut()
+            .expect("a swapchain must be added before setting its present fence");
+        *last = fence.internal_object();
+    }
+
+    // Same as `set_present_fence`, except that the fence is also handed to the swapchain once
+    // the present has been submitted, so that it can track the presents in flight.
+    #[inline]
+    pub(crate) unsafe fn set_tracked_present_fence(&mut self, fence: &'a Arc<Fence>) {
+        self.set_present_fence(fence);
+        self.presented.last_mut().unwrap().1 = Some(fence.clone());
+    }
+
+    /// Asks for the result of each individual swapchain to be written to `results` on submit,
+    /// in the order in which the swapchains were added.
+    ///
+    /// When this is set, `submit` only returns an error if the whole present command failed
+    /// (for example because the device was lost). Errors that only concern some of the
+    /// swapchains, such as `OutOfDate` or `SurfaceLost`, are only reported through `results`.
+    #[inline]
+    pub fn set_results(&mut self, results: &'a Mutex<Vec<Result<(), SubmitPresentError>>>) {
+        self.results = Some(results);
     }
 
     /// Submits the command. Calls `vkQueuePresentKHR`.
@@ -98,13 +156,24 @@ impl<'a> SubmitPresentBuilder<'a> {
                     "Tried to submit a present command without any swapchain");
 
             let vk = queue.device().pointers();
-            let queue = queue.internal_object_guard();
 
-            let mut results = vec![mem::uninitialized(); self.swapchains.len()]; // TODO: alloca
+            let mut results: SmallVec<[vk::Result; 4]> =
+                self.swapchains.iter().map(|_| vk::SUCCESS).collect();
+
+            let fence_infos = vk::SwapchainPresentFenceInfoEXT {
+                sType: vk::STRUCTURE_TYPE_SWAPCHAIN_PRESENT_FENCE_INFO_EXT,
+                pNext: ptr::null(),
+                swapchainCount: self.present_fences.len() as u32,
+                pFences: self.present_fences.as_ptr(),
+            };
 
             let infos = vk::PresentInfoKHR {
                 sType: vk::STRUCTURE_TYPE_PRESENT_INFO_KHR,
-                pNext: ptr::null(),
+                pNext: if self.present_fences.iter().any(|&f| f != 0) {
+                    &fence_infos as *const _ as *const _
+                } else {
+                    ptr::null()
+                },
                 waitSemaphoreCount: self.wait_semaphores.len() as u32,
                 pWaitSemaphores: self.wait_semaphores.as_ptr(),
                 swapchainCount: self.swapchains.len() as u32,
@@ -113,16 +182,81 @@ impl<'a> SubmitPresentBuilder<'a> {
                 pResults: results.as_mut_ptr(),
             };
 
-            check_errors(vk.QueuePresentKHR(*queue, &infos))?;
+            // The queue is only locked for the duration of the call.
+            let raw_queue = queue.internal_object_guard();
+            let result = vk.QueuePresentKHR(*raw_queue, &infos);
+            drop(raw_queue);
+
+            let presented = self.presented.iter().zip(self.image_indices.iter());
+            let result = match self.results {
+                Some(sink) => {
+                    split_present_results(result, &results).map(|per_swapchain| {
+                        for ((&(swapchain, ref fence), &image), r) in
+                            presented.zip(per_swapchain.iter())
+                        {
+                            if r.is_ok() {
+                                swapchain.mark_presented(image as usize, fence.clone());
+                            }
+                        }
+                        *sink.lock().unwrap() = per_swapchain;
+                    })
+                },
+                None => {
+                    let result = check_errors(result, "vkQueuePresentKHR");
+                    if result.is_ok() {
+                        for (&(swapchain, ref fence), &image) in presented {
+                            swapchain.mark_presented(image as usize, fence.clone());
+                        }
+                    }
+                    result.map(|_| ()).map_err(From::from)
+                },
+            };
 
-            for result in results {
-                // TODO: AMD driver initially didn't write the results ; check that it's been fixed
-                //try!(check_errors(result));
+            if result.is_ok() {
+                if let Some(observer) = queue.device().submission_observer() {
+                    let swapchains = self.swapchains.clone();
+                    let image_indices = self.image_indices.clone();
+                    instrumentation::notify(move || {
+                        observer.swapchain_present(&swapchains, &image_indices);
+                    });
+                }
             }
 
-            Ok(())
+            result
+        }
+    }
+}
+
+// Turns the result of `vkQueuePresentKHR` and the individual result of each swapchain into
+// either an error that concerns the whole command, or one result per swapchain.
+fn split_present_results(result: vk::Result, per_swapchain: &[vk::Result])
+                         -> Result<Vec<Result<(), SubmitPresentError>>, SubmitPresentError> {
+    let convert = |r| {
+        check_errors(r, "vkQueuePresentKHR")
+            .map(|_| ())
+            .map_err(SubmitPresentError::from)
+    };
+
+    let overall = convert(result);
+    match overall {
+        Err(err @ SubmitPresentError::OomError(_)) => return Err(err),
+        Err(err @ SubmitPresentError::DeviceLost) => return Err(err),
+        _ => (),
+    }
+
+    let mut results = per_swapchain.iter().map(|&r| convert(r)).collect::<Vec<_>>();
+
+    // Some drivers don't write the individual results. If the command failed but no swapchain
+    // reports an error, we have no choice but to attribute the error to all of them.
+    if let Err(err) = overall {
+        if results.iter().all(|r| r.is_ok()) {
+            for r in results.iter_mut() {
+                *r = Err(err);
+            }
         }
     }
+
+    Ok(results)
 }
 
 /// Error that can happen when submitting the present prototype.
@@ -149,7 +283,9 @@ impl error::Error for SubmitPresentError {
         match *self {
             SubmitPresentError::OomError(_) => "not enough memory",
             SubmitPresentError::DeviceLost => "the connection to the device has been lost",
-            SubmitPresentError::SurfaceLost => "the surface of this swapchain is no longer valid",
+            SubmitPresentError::SurfaceLost => {
+                "the surface of this swapchain is no longer valid"
+            },
             SubmitPresentError::OutOfDate => "the swapchain needs to be recreated",
         }
     }
@@ -187,6 +323,7 @@ impl From<Error> for SubmitPresentError {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use ErrorContext;
 
     #[test]
     #[should_panic(expected = "Tried to submit a present command without any swapchain")]
@@ -194,4 +331,40 @@ mod tests {
         let (_, queue) = gfx_dev_and_queue!();
         let _ = SubmitPresentBuilder::new().submit(&queue);
     }
+
+    #[test]
+    fn per_swapchain_results() {
+        let results = split_present_results(vk::ERROR_OUT_OF_DATE_KHR,
+                                            &[vk::SUCCESS, vk::ERROR_OUT_OF_DATE_KHR,
+                                              vk::SUBOPTIMAL_KHR])
+            .unwrap();
+        assert_eq!(results,
+                   vec![Ok(()), Err(SubmitPresentError::OutOfDate), Ok(())]);
+    }
+
+    #[test]
+    fn unreported_error_applies_to_all() {
+        let results = split_present_results(vk::ERROR_SURFACE_LOST_KHR,
+                                            &[vk::SUCCESS, vk::SUCCESS])
+            .unwrap();
+        assert_eq!(results.len(), 2);
+        for result in results {
+            match result {
+                Err(SubmitPresentError::SurfaceLost) => {
+                    assert_eq!(ErrorContext::last().unwrap().function(), "vkQueuePresentKHR");
+                },
+                _ => panic!(),
+            }
+        }
+    }
+
+    #[test]
+    fn device_lost_fails_whole_present() {
+        match split_present_results(vk::ERROR_DEVICE_LOST, &[vk::SUCCESS, vk::SUCCESS]) {
+            Err(SubmitPresentError::DeviceLost) => {
+                assert_eq!(ErrorContext::last().unwrap().function(), "vkQueuePresentKHR");
+            },
+            _ => panic!(),
+        }
+    }
 }
@@ -15,6 +15,8 @@ use std::ptr;
 
 use command_buffer::sys::UnsafeCommandBuffer;
 use device::Queue;
+use instrumentation::QueueSubmitInfo;
+use instrumentation;
 use sync::Fence;
 use sync::PipelineStages;
 use sync::Semaphore;
@@ -203,7 +205,6 @@ impl<'a> SubmitCommandBufferBuilder<'a> {
     pub fn submit(self, queue: &Queue) -> Result<(), SubmitCommandBufferError> {
         unsafe {
             let vk = queue.device().pointers();
-            let queue = queue.internal_object_guard();
 
             debug_assert_eq!(self.wait_semaphores.len(), self.dest_stages.len());
 
@@ -219,7 +220,31 @@ impl<'a> SubmitCommandBufferBuilder<'a> {
                 pSignalSemaphores: self.signal_semaphores.as_ptr(),
             };
 
-            check_errors(vk.QueueSubmit(*queue, 1, &batch, self.fence))?;
+            // The queue is only locked for the duration of the call.
+            let raw_queue = queue.internal_object_guard();
+            check_errors(vk.QueueSubmit(*raw_queue, 1, &batch, self.fence), "vkQueueSubmit")?;
+            drop(raw_queue);
+
+            if let Some(observer) = queue.device().submission_observer() {
+                let queue_family = queue.family().id();
+                let queue_index = queue.id_within_family();
+                let command_buffers = self.command_buffers.clone();
+                let num_wait_semaphores = self.wait_semaphores.len();
+                let num_signal_semaphores = self.signal_semaphores.len();
+                let fence = if self.fence == 0 { None } else { Some(self.fence) };
+
+                instrumentation::notify(move || {
+                    observer.queue_submit(&QueueSubmitInfo {
+                                              queue_family: queue_family,
+                                              queue_index: queue_index,
+                                              command_buffers: &command_buffers,
+                                              num_wait_semaphores: num_wait_semaphores,
+                                              num_signal_semaphores: num_signal_semaphores,
+                                              fence: fence,
+                                          });
+                });
+            }
+
             Ok(())
         }
     }
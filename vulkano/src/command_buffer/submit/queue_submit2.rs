@@ -0,0 +1,228 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use smallvec::SmallVec;
+use std::marker::PhantomData;
+use std::ptr;
+
+use command_buffer::submit::SubmitCommandBufferError;
+use command_buffer::sys::UnsafeCommandBuffer;
+use device::Queue;
+use sync::Fence;
+use sync::PipelineStages;
+use sync::TimelineSemaphore;
+
+use SynchronizedVulkanObject;
+use VulkanObject;
+use check_errors;
+use vk;
+
+/// Prototype for a submission that executes command buffers with `vkQueueSubmit2`.
+///
+/// Contrary to `SubmitCommandBufferBuilder`, the semaphores waited upon and signaled by this
+/// submission are timeline semaphores, each associated with a counter value.
+///
+/// Submitting requires the `VK_KHR_synchronization2` extension to be enabled on the device, or
+/// the device to support Vulkan 1.3.
+#[derive(Debug)]
+pub struct SubmitCommandBuffer2Builder<'a> {
+    // Semaphore, counter value and stages to wait for.
+    wait_semaphores: SmallVec<[(vk::Semaphore, u64, vk::PipelineStageFlags2KHR); 8]>,
+    // Semaphore and counter value to signal.
+    signal_semaphores: SmallVec<[(vk::Semaphore, u64); 8]>,
+    command_buffers: SmallVec<[vk::CommandBuffer; 4]>,
+    fence: vk::Fence,
+    marker: PhantomData<&'a ()>,
+}
+
+impl<'a> SubmitCommandBuffer2Builder<'a> {
+    /// Builds a new empty `SubmitCommandBuffer2Builder`.
+    #[inline]
+    pub fn new() -> SubmitCommandBuffer2Builder<'a> {
+        SubmitCommandBuffer2Builder {
+            wait_semaphores: SmallVec::new(),
+            signal_semaphores: SmallVec::new(),
+            command_buffers: SmallVec::new(),
+            fence: 0,
+            marker: PhantomData,
+        }
+    }
+
+    /// Returns true if this builder will signal a fence when submitted.
+    #[inline]
+    pub fn has_fence(&self) -> bool {
+        self.fence != 0
+    }
+
+    /// Adds an operation that signals a fence after this submission ends.
+    ///
+    /// # Safety
+    ///
+    /// Same as `SubmitCommandBufferBuilder::set_fence_signal`.
+    ///
+    #[inline]
+    pub unsafe fn set_fence_signal(&mut self, fence: &'a Fence) {
+        self.fence = fence.internal_object();
+    }
+
+    /// Adds a timeline semaphore to be waited upon before the command buffers are executed.
+    ///
+    /// Only the given `stages` of the command buffers will wait until the counter of the
+    /// semaphore is superior or equal to `value`.
+    ///
+    /// # Safety
+    ///
+    /// - The stages must be supported by the device.
+    ///
+    /// - If you submit this builder, the semaphore must be kept alive until you are guaranteed
+    ///   that the GPU has at least started executing the command buffers.
+    ///
+    /// - If you submit this builder, there must be a submission or a host operation that is
+    ///   going to set the counter to at least `value`. In other words, you must not block the
+    ///   queue with semaphores that can't get signaled.
+    ///
+    /// - The fence, command buffers, and semaphores must all belong to the same device.
+    ///
+    #[inline]
+    pub unsafe fn add_wait_semaphore(&mut self, semaphore: &'a TimelineSemaphore, value: u64,
+                                     stages: PipelineStages) {
+        let stages: vk::PipelineStageFlagBits = stages.into();
+        debug_assert!(stages != 0);
+        self.wait_semaphores
+            .push((semaphore.internal_object(), value, stages as vk::PipelineStageFlags2KHR));
+    }
+
+    /// Adds a command buffer that is executed as part of this command.
+    ///
+    /// The command buffers are submitted in the order in which they are added.
+    ///
+    /// # Safety
+    ///
+    /// Same as `SubmitCommandBufferBuilder::add_command_buffer`.
+    ///
+    #[inline]
+    pub unsafe fn add_command_buffer<P>(&mut self, command_buffer: &'a UnsafeCommandBuffer<P>) {
+        self.command_buffers.push(command_buffer.internal_object());
+    }
+
+    /// Returns the number of semaphores to signal.
+    #[inline]
+    pub fn num_signal_semaphores(&self) -> usize {
+        self.signal_semaphores.len()
+    }
+
+    /// Adds a timeline semaphore whose counter is set to `value` at the end of the submission.
+    ///
+    /// # Safety
+    ///
+    /// - If you submit this builder, the semaphore must be kept alive until you are guaranteed
+    ///   that the GPU has finished executing this submission.
+    ///
+    /// - `value` must be strictly superior to the value of the counter when queue execution
+    ///   reaches this submission, and to the value of any other pending signal operation.
+    ///
+    /// - The fence, command buffers, and semaphores must all belong to the same device.
+    ///
+    #[inline]
+    pub unsafe fn add_signal_semaphore(&mut self, semaphore: &'a TimelineSemaphore, value: u64) {
+        self.signal_semaphores.push((semaphore.internal_object(), value));
+    }
+
+    /// Submits the command buffers to the given queue.
+    ///
+    /// > **Note**: This is an expensive operation, so you may want to merge as many builders as
+    /// > possible together and avoid submitting them one by one.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the `VK_KHR_synchronization2` extension was not enabled on the device and the
+    ///   device doesn't support Vulkan 1.3.
+    ///
+    pub fn submit(self, queue: &Queue) -> Result<(), SubmitCommandBufferError> {
+        assert!(queue.device().effective_extensions().khr_synchronization2,
+                "The VK_KHR_synchronization2 extension must be enabled to use \
+                 SubmitCommandBuffer2Builder");
+
+        unsafe {
+            let vk = queue.device().pointers();
+
+            let wait_semaphores = self.wait_semaphores
+                .iter()
+                .map(|&(semaphore, value, stages)| {
+                    vk::SemaphoreSubmitInfoKHR {
+                        sType: vk::STRUCTURE_TYPE_SEMAPHORE_SUBMIT_INFO_KHR,
+                        pNext: ptr::null(),
+                        semaphore: semaphore,
+                        value: value,
+                        stageMask: stages,
+                        deviceIndex: 0,
+                    }
+                })
+                .collect::<SmallVec<[_; 8]>>();
+
+            let command_buffers = self.command_buffers
+                .iter()
+                .map(|&command_buffer| {
+                    vk::CommandBufferSubmitInfoKHR {
+                        sType: vk::STRUCTURE_TYPE_COMMAND_BUFFER_SUBMIT_INFO_KHR,
+                        pNext: ptr::null(),
+                        commandBuffer: command_buffer,
+                        deviceMask: 0,
+                    }
+                })
+                .collect::<SmallVec<[_; 4]>>();
+
+            // The signal operations happen once all the commands of the batch have completed.
+            let signal_semaphores = self.signal_semaphores
+                .iter()
+                .map(|&(semaphore, value)| {
+                    vk::SemaphoreSubmitInfoKHR {
+                        sType: vk::STRUCTURE_TYPE_SEMAPHORE_SUBMIT_INFO_KHR,
+                        pNext: ptr::null(),
+                        semaphore: semaphore,
+                        value: value,
+                        stageMask: vk::PIPELINE_STAGE_ALL_COMMANDS_BIT as
+                            vk::PipelineStageFlags2KHR,
+                        deviceIndex: 0,
+                    }
+                })
+                .collect::<SmallVec<[_; 8]>>();
+
+            let batch = vk::SubmitInfo2KHR {
+                sType: vk::STRUCTURE_TYPE_SUBMIT_INFO_2_KHR,
+                pNext: ptr::null(),
+                flags: 0,
+                waitSemaphoreInfoCount: wait_semaphores.len() as u32,
+                pWaitSemaphoreInfos: wait_semaphores.as_ptr(),
+                commandBufferInfoCount: command_buffers.len() as u32,
+                pCommandBufferInfos: command_buffers.as_ptr(),
+                signalSemaphoreInfoCount: signal_semaphores.len() as u32,
+                pSignalSemaphoreInfos: signal_semaphores.as_ptr(),
+            };
+
+            // The queue is only locked for the duration of the call.
+            let queue = queue.internal_object_guard();
+            check_errors(vk.QueueSubmit2KHR(*queue, 1, &batch, self.fence), "vkQueueSubmit2KHR")?;
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[should_panic(expected = "The VK_KHR_synchronization2 extension must be enabled")]
+    fn missing_extension() {
+        let (_, queue) = gfx_dev_and_queue!();
+        let builder = SubmitCommandBuffer2Builder::new();
+        let _ = builder.submit(&queue);
+    }
+}
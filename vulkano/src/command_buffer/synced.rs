@@ -12,16 +12,24 @@ use smallvec::SmallVec;
 use std::collections::hash_map::Entry;
 use std::error;
 use std::fmt;
+use std::ffi::CString;
 use std::hash::{Hash, Hasher};
+use std::cmp;
+use std::marker::PhantomData;
 use std::mem;
+use std::ops::Range;
 use std::ptr;
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
 
 use OomError;
 use buffer::BufferAccess;
 use command_buffer::CommandBuffer;
 use command_buffer::CommandBufferExecError;
+use command_buffer::capture::CommandCapture;
+use command_buffer::indirect_commands::IndirectCommandsLayout;
 use command_buffer::pool::CommandPool;
 use command_buffer::pool::CommandPoolAlloc;
 use command_buffer::pool::CommandPoolBuilderAlloc;
@@ -29,10 +37,12 @@ use command_buffer::sys::Flags;
 use command_buffer::sys::Kind;
 use command_buffer::sys::UnsafeCommandBuffer;
 use command_buffer::sys::UnsafeCommandBufferBuilder;
+use command_buffer::sys::UnsafeCommandBufferBuilderBindShaders;
 use command_buffer::sys::UnsafeCommandBufferBuilderBindVertexBuffer;
 use command_buffer::sys::UnsafeCommandBufferBuilderBufferImageCopy;
 use command_buffer::sys::UnsafeCommandBufferBuilderPipelineBarrier;
 use descriptor::descriptor::ShaderStages;
+use descriptor::descriptor_set::DescriptorResourceAccess;
 use descriptor::descriptor_set::DescriptorSet;
 use descriptor::pipeline_layout::PipelineLayoutAbstract;
 use device::Device;
@@ -44,11 +54,19 @@ use framebuffer::RenderPassAbstract;
 use framebuffer::SubpassContents;
 use image::ImageAccess;
 use image::ImageLayout;
+use image::ImageViewAccess;
 use pipeline::ComputePipelineAbstract;
 use pipeline::GraphicsPipelineAbstract;
+use pipeline::depth_stencil::Compare;
+use pipeline::depth_stencil::StencilFaceFlags;
 use pipeline::input_assembly::IndexType;
+use pipeline::raster::DepthBiasControl;
+use pipeline::shader_object::ShaderObject;
+use pipeline::shader_object::ShaderObjectStage;
+use pipeline::shader_object::ShaderObjectState;
 use pipeline::viewport::Scissor;
 use pipeline::viewport::Viewport;
+use query::UnsafeQueryPool;
 use sync::AccessCheckError;
 use sync::AccessError;
 use sync::AccessFlagBits;
@@ -87,6 +105,10 @@ pub struct SyncCommandBufferBuilder<P> {
     // Stores all the commands that were submitted or are going to be submitted to the inner
     // builder. A copy of this `Arc` is stored in each `BuilderKey`.
     commands: Arc<Mutex<Commands<P>>>,
+
+    // If `Some`, the commands and pipeline barriers are written to it as they are sent to the
+    // inner builder.
+    capture: Option<CommandCapture>,
 }
 
 impl<P> fmt::Debug for SyncCommandBufferBuilder<P> {
@@ -102,6 +124,10 @@ pub enum SyncCommandBufferBuilderError {
     /// Unsolvable conflict.
     // TODO: add details
     Conflict,
+
+    /// A buffer or an image whose content hasn't been initialized is used before any other
+    /// command of the command buffer has written it.
+    UninitializedResource,
 }
 
 impl error::Error for SyncCommandBufferBuilderError {
@@ -111,6 +137,9 @@ impl error::Error for SyncCommandBufferBuilderError {
             SyncCommandBufferBuilderError::Conflict => {
                 "unsolvable conflict"
             },
+            SyncCommandBufferBuilderError::UninitializedResource => {
+                "a resource whose content hasn't been initialized is used before being written"
+            },
         }
     }
 }
@@ -122,18 +151,65 @@ impl fmt::Display for SyncCommandBufferBuilderError {
     }
 }
 
+// Maximum number of buffer memory barriers in a single pipeline barrier. Above this number, they
+// are replaced with a global memory barrier.
+const MAX_BUFFER_BARRIERS: usize = 8;
+
+// Sends a pending pipeline barrier to the inner builder and replaces it with an empty one.
+//
+// All the barriers needed before a batch of commands are accumulated in the pending barrier, so
+// that they are submitted with a single `vkCmdPipelineBarrier` call.
+unsafe fn submit_pending_barrier<P>(inner: &mut UnsafeCommandBufferBuilder<P>,
+                                    pending: &mut UnsafeCommandBufferBuilderPipelineBarrier,
+                                    capture: &mut Option<CommandCapture>) {
+    let mut barrier = mem::replace(pending, UnsafeCommandBufferBuilderPipelineBarrier::new());
+    barrier.collapse_buffer_barriers(MAX_BUFFER_BARRIERS);
+    if let Some(ref mut capture) = *capture {
+        capture.pipeline_barrier(&barrier);
+    }
+    inner.pipeline_barrier(&barrier);
+}
+
+// Sends the commands between `start` and `end` to the inner builder.
+unsafe fn send_commands<P>(inner: &mut UnsafeCommandBufferBuilder<P>,
+                           commands: &mut Commands<P>, start: usize, end: usize,
+                           capture: &mut Option<CommandCapture>) {
+    for command_id in start .. end {
+        let command = &mut commands.commands[command_id];
+        if let Some(ref mut capture) = *capture {
+            capture.command(command_id, command.name());
+        }
+        command.send(inner);
+    }
+}
+
 // List of commands of a `SyncCommandBufferBuilder`.
 struct Commands<P> {
     // Only the commands before `first_unflushed` have already been sent to the inner
     // `UnsafeCommandBufferBuilder`.
     first_unflushed: usize,
 
+    // Index of the `begin_render_pass` command of the render pass that is being recorded, if any.
+    // Pipeline barriers can't be recorded inside a render pass, therefore the commands starting
+    // from this one are never flushed before the render pass ends.
+    render_pass_start: Option<usize>,
+
     // The actual list.
     commands: Vec<Box<Command<P> + Send + Sync>>,
+
+    // Number of debug labels that have been opened and not closed yet.
+    open_debug_labels: u32,
+
+    // True once the command buffer has been built. `DebugLabelScope`s that are destroyed
+    // afterwards don't add any command.
+    built: bool,
 }
 
 // A single command within the list of commands.
 trait Command<P> {
+    // Name of the command, used when capturing the command buffer.
+    fn name(&self) -> &'static str;
+
     // Sends the command to the `UnsafeCommandBufferBuilder`. Calling this method twice on the same
     // object may lead to a panic.
     unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>);
@@ -151,6 +227,55 @@ trait Command<P> {
     }
 }
 
+// Command that closes the latest debug label. Shared between `end_debug_utils_label`,
+// `DebugLabelScope` and `build`.
+struct EndDebugUtilsLabelCmd;
+
+impl<P> Command<P> for EndDebugUtilsLabelCmd {
+    fn name(&self) -> &'static str {
+        "end_debug_utils_label"
+    }
+
+    unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
+        out.end_debug_utils_label();
+    }
+
+    fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
+        Box::new(())
+    }
+}
+
+/// Debug label of a `SyncCommandBufferBuilder` that is closed when this object is destroyed.
+///
+/// Scopes are expected to be destroyed in the reverse order of their creation, which is what
+/// happens when they are stored in local variables.
+pub struct DebugLabelScope<P> {
+    // Same `Arc` as the `SyncCommandBufferBuilder`.
+    commands: Arc<Mutex<Commands<P>>>,
+    // The scope adds a command to the list when it is destroyed, which must not happen from
+    // another thread while the builder is in the middle of adding a command.
+    marker: PhantomData<*const ()>,
+}
+
+impl<P> Drop for DebugLabelScope<P> {
+    fn drop(&mut self) {
+        let mut commands_lock = self.commands.lock().unwrap();
+        if commands_lock.built || commands_lock.open_debug_labels == 0 {
+            return;
+        }
+
+        commands_lock.commands.push(Box::new(EndDebugUtilsLabelCmd));
+        commands_lock.open_debug_labels -= 1;
+    }
+}
+
+impl<P> fmt::Debug for DebugLabelScope<P> {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "<Debug label scope>")
+    }
+}
+
 // Key that identifies a resource. Implements `PartialEq`, `Eq` and `Hash` so that two resources
 // that conflict with each other compare equal.
 //
@@ -260,6 +385,9 @@ impl<P> Hash for BuilderKey<P> {
 // Current state of a resource during the building of the command buffer.
 #[derive(Debug, Clone)]
 struct ResourceState {
+    // Index of the command that last used this resource.
+    command_id: usize,
+
     // Stages of the command that last used this resource.
     stages: PipelineStages,
     // Access for the command that last used this resource.
@@ -276,8 +404,9 @@ struct ResourceState {
     // don't care.
     initial_layout: ImageLayout,
 
-    // Current layout at this stage of the building.
-    current_layout: ImageLayout,
+    // Current layout of each subresource at this stage of the building. Always `Undefined` for
+    // buffers.
+    current_layouts: ImageLayouts,
 }
 
 impl ResourceState {
@@ -285,16 +414,201 @@ impl ResourceState {
     // being built.
     #[inline]
     fn finalize(self) -> ResourceFinalState {
+        // All the subresources of an image are transitioned to the same final layout by `build`.
+        let final_layout = self.current_layouts.uniform().unwrap();
+
         ResourceFinalState {
             final_stages: self.stages,
             final_access: self.access,
             exclusive: self.exclusive_any,
             initial_layout: self.initial_layout,
-            final_layout: self.current_layout,
+            final_layout: final_layout,
+        }
+    }
+}
+
+// Layouts of the subresources of an image, stored as non-overlapping rectangles of mipmap levels
+// and array layers that together cover the whole image. Neighbouring rectangles that end up in
+// the same layout are merged back together.
+//
+// Mipmap levels and array layers are those of the `UnsafeImage`, not relative to the range of an
+// `ImageAccess`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ImageLayouts {
+    ranges: Vec<SubresourceLayout>,
+}
+
+// Layout of a rectangle of subresources of an image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SubresourceLayout {
+    mipmaps: Range<u32>,
+    layers: Range<u32>,
+    layout: ImageLayout,
+}
+
+impl ImageLayouts {
+    // Builds an `ImageLayouts` where all the subresources are in the same layout.
+    #[inline]
+    fn new(mipmaps: Range<u32>, layers: Range<u32>, layout: ImageLayout) -> ImageLayouts {
+        debug_assert!(mipmaps.start < mipmaps.end);
+        debug_assert!(layers.start < layers.end);
+
+        ImageLayouts {
+            ranges: vec![SubresourceLayout {
+                mipmaps: mipmaps,
+                layers: layers,
+                layout: layout,
+            }],
+        }
+    }
+
+    // Returns the layout of the image if all of its subresources are in the same layout.
+    #[inline]
+    fn uniform(&self) -> Option<ImageLayout> {
+        let layout = self.ranges[0].layout;
+        if self.ranges.iter().all(|r| r.layout == layout) {
+            Some(layout)
+        } else {
+            None
+        }
+    }
+
+    // Returns the layouts of the subresources within the given range, split into rectangles that
+    // each have a single layout.
+    fn get(&self, mipmaps: Range<u32>, layers: Range<u32>) -> Vec<SubresourceLayout> {
+        self.ranges
+            .iter()
+            .filter_map(|r| {
+                let m = cmp::max(r.mipmaps.start, mipmaps.start) ..
+                    cmp::min(r.mipmaps.end, mipmaps.end);
+                let l = cmp::max(r.layers.start, layers.start) ..
+                    cmp::min(r.layers.end, layers.end);
+
+                if m.start < m.end && l.start < l.end {
+                    Some(SubresourceLayout {
+                        mipmaps: m,
+                        layers: l,
+                        layout: r.layout,
+                    })
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    // Sets the layout of the subresources within the given range.
+    fn set(&mut self, mipmaps: Range<u32>, layers: Range<u32>, layout: ImageLayout) {
+        debug_assert!(mipmaps.start < mipmaps.end);
+        debug_assert!(layers.start < layers.end);
+
+        // Cut the rectangles that overlap the range into up to four pieces that surround it.
+        let mut ranges = Vec::with_capacity(self.ranges.len() + 4);
+        for r in self.ranges.drain(..) {
+            let m = cmp::max(r.mipmaps.start, mipmaps.start) ..
+                cmp::min(r.mipmaps.end, mipmaps.end);
+            let l = cmp::max(r.layers.start, layers.start) ..
+                cmp::min(r.layers.end, layers.end);
+
+            if m.start >= m.end || l.start >= l.end {
+                ranges.push(r);
+                continue;
+            }
+
+            let pieces = [
+                (r.mipmaps.start .. m.start, r.layers.clone()),
+                (m.end .. r.mipmaps.end, r.layers.clone()),
+                (m.clone(), r.layers.start .. l.start),
+                (m.clone(), l.end .. r.layers.end),
+            ];
+
+            for &(ref pm, ref pl) in pieces.iter() {
+                if pm.start < pm.end && pl.start < pl.end {
+                    ranges.push(SubresourceLayout {
+                        mipmaps: pm.clone(),
+                        layers: pl.clone(),
+                        layout: r.layout,
+                    });
+                }
+            }
+        }
+
+        ranges.push(SubresourceLayout {
+            mipmaps: mipmaps,
+            layers: layers,
+            layout: layout,
+        });
+
+        self.ranges = ranges;
+        self.merge();
+    }
+
+    // Merges the rectangles that are in the same layout.
+    fn merge(&mut self) {
+        // If all the subresources converged to the same layout, go back to a single rectangle.
+        if let Some(layout) = self.uniform() {
+            let mipmaps = self.ranges.iter().map(|r| r.mipmaps.start).min().unwrap() ..
+                self.ranges.iter().map(|r| r.mipmaps.end).max().unwrap();
+            let layers = self.ranges.iter().map(|r| r.layers.start).min().unwrap() ..
+                self.ranges.iter().map(|r| r.layers.end).max().unwrap();
+            *self = ImageLayouts::new(mipmaps, layers, layout);
+            return;
+        }
+
+        // Otherwise merge neighbours that share a whole edge, until there is none left.
+        'outer: loop {
+            for i in 0 .. self.ranges.len() {
+                for j in i + 1 .. self.ranges.len() {
+                    let merged = {
+                        let a = &self.ranges[i];
+                        let b = &self.ranges[j];
+
+                        if a.layout != b.layout {
+                            None
+                        } else if a.layers == b.layers && (a.mipmaps.end == b.mipmaps.start ||
+                                                           b.mipmaps.end == a.mipmaps.start)
+                        {
+                            let m = cmp::min(a.mipmaps.start, b.mipmaps.start) ..
+                                cmp::max(a.mipmaps.end, b.mipmaps.end);
+                            Some((m, a.layers.clone()))
+                        } else if a.mipmaps == b.mipmaps && (a.layers.end == b.layers.start ||
+                                                             b.layers.end == a.layers.start)
+                        {
+                            let l = cmp::min(a.layers.start, b.layers.start) ..
+                                cmp::max(a.layers.end, b.layers.end);
+                            Some((a.mipmaps.clone(), l))
+                        } else {
+                            None
+                        }
+                    };
+
+                    if let Some((mipmaps, layers)) = merged {
+                        self.ranges[i].mipmaps = mipmaps;
+                        self.ranges[i].layers = layers;
+                        self.ranges.swap_remove(j);
+                        continue 'outer;
+                    }
+                }
+            }
+
+            break;
         }
     }
 }
 
+// Converts the mipmap levels and array layers of `range`, which are those of the `UnsafeImage`,
+// into ranges relative to `img`.
+#[inline]
+fn image_relative_range(img: &ImageAccess, range: &SubresourceLayout) -> (Range<u32>, Range<u32>) {
+    let inner = img.inner();
+    let first_mipmap = inner.first_mipmap_level as u32;
+    let first_layer = inner.first_layer as u32;
+    debug_assert!(range.mipmaps.start >= first_mipmap);
+    debug_assert!(range.layers.start >= first_layer);
+    (range.mipmaps.start - first_mipmap .. range.mipmaps.end - first_mipmap,
+     range.layers.start - first_layer .. range.layers.end - first_layer)
+}
+
 impl<P> SyncCommandBufferBuilder<P> {
     /// Builds a new `SyncCommandBufferBuilder`. The parameters are the same as the
     /// `UnsafeCommandBufferBuilder::new` function.
@@ -332,11 +646,28 @@ impl<P> SyncCommandBufferBuilder<P> {
             pending_barrier: UnsafeCommandBufferBuilderPipelineBarrier::new(),
             commands: Arc::new(Mutex::new(Commands {
                                               first_unflushed: 0,
+                                              render_pass_start: None,
                                               commands: Vec::new(),
+                                              open_debug_labels: 0,
+                                              built: false,
                                           })),
+            capture: None,
         }
     }
 
+    /// Starts capturing the commands and the pipeline barriers that are sent to the Vulkan
+    /// command buffer. The capture can be retrieved with `SyncCommandBuffer::capture` once the
+    /// command buffer is built.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if commands have already been added to the builder.
+    ///
+    pub fn enable_capture(&mut self) {
+        assert_eq!(self.num_commands(), 0);
+        self.capture = Some(CommandCapture::new());
+    }
+
     // After a command is added to the list of pending commands, this function must be called for
     // each resource used by the command that has just been added.
     // The function will take care of handling the pipeline barrier or flushing.
@@ -344,6 +675,28 @@ impl<P> SyncCommandBufferBuilder<P> {
                          stages: PipelineStages, access: AccessFlagBits,
                          start_layout: ImageLayout, end_layout: ImageLayout)
                          -> Result<(), SyncCommandBufferBuilderError> {
+        let (mipmaps, layers) = match resource_ty {
+            KeyTy::Buffer => (0 .. 1, 0 .. 1),
+            KeyTy::Image => {
+                let commands_lock = self.commands.lock().unwrap();
+                let img = commands_lock.commands.last().unwrap().image(resource_index);
+                let inner = img.inner();
+                (0 .. inner.num_mipmap_levels as u32, 0 .. inner.num_layers as u32)
+            },
+        };
+
+        self.prev_cmd_resource_range(resource_ty, resource_index, mipmaps, layers, exclusive,
+                                     stages, access, start_layout, end_layout)
+    }
+
+    // Same as `prev_cmd_resource`, except that only the given mipmap levels and array layers of
+    // the image are used by the command. They are relative to the `ImageAccess`, and are ignored
+    // for buffers.
+    fn prev_cmd_resource_range(&mut self, resource_ty: KeyTy, resource_index: usize,
+                               mipmaps: Range<u32>, layers: Range<u32>, exclusive: bool,
+                               stages: PipelineStages, access: AccessFlagBits,
+                               start_layout: ImageLayout, end_layout: ImageLayout)
+                               -> Result<(), SyncCommandBufferBuilderError> {
         debug_assert!(exclusive || start_layout == end_layout);
         debug_assert!(access.is_compatible_with(&stages));
         debug_assert!(resource_ty != KeyTy::Image || end_layout != ImageLayout::Undefined);
@@ -351,10 +704,38 @@ impl<P> SyncCommandBufferBuilder<P> {
         debug_assert!(resource_ty != KeyTy::Buffer || end_layout == ImageLayout::Undefined);
         debug_assert_ne!(end_layout, ImageLayout::Preinitialized);
 
-        let (first_unflushed, latest_command_id) = {
+        let (first_unflushed, render_pass_start, latest_command_id) = {
             let commands_lock = self.commands.lock().unwrap();
             debug_assert!(commands_lock.commands.len() >= 1);
-            (commands_lock.first_unflushed, commands_lock.commands.len() - 1)
+            (commands_lock.first_unflushed,
+             commands_lock.render_pass_start,
+             commands_lock.commands.len() - 1)
+        };
+
+        if let Some(ref mut capture) = self.capture {
+            let commands_lock = self.commands.lock().unwrap();
+            let command = &commands_lock.commands[latest_command_id];
+            match resource_ty {
+                KeyTy::Buffer => capture.buffer_use(latest_command_id,
+                                                    command.buffer(resource_index)),
+                KeyTy::Image => capture.image_use(latest_command_id,
+                                                  command.image(resource_index)),
+            }
+        }
+
+        // Mipmap levels and array layers of the `UnsafeImage` that are used by the command.
+        let (mipmaps, layers) = match resource_ty {
+            KeyTy::Buffer => (0 .. 1, 0 .. 1),
+            KeyTy::Image => {
+                let commands_lock = self.commands.lock().unwrap();
+                let inner = commands_lock.commands[latest_command_id].image(resource_index).inner();
+                debug_assert!(mipmaps.end as usize <= inner.num_mipmap_levels);
+                debug_assert!(layers.end as usize <= inner.num_layers);
+                let first_mipmap = inner.first_mipmap_level as u32;
+                let first_layer = inner.first_layer as u32;
+                (mipmaps.start + first_mipmap .. mipmaps.end + first_mipmap,
+                 layers.start + first_layer .. layers.end + first_layer)
+            },
         };
 
         let key = BuilderKey {
@@ -366,16 +747,40 @@ impl<P> SyncCommandBufferBuilder<P> {
 
         match self.resources.entry(key) {
             Entry::Occupied(entry) => {
-                let collision_command_id = entry.key().command_id;
+                let mut entry = entry.into_mut();
+                let collision_command_id = entry.command_id;
                 debug_assert!(collision_command_id <= latest_command_id);
+
+                // Layouts of the subresources used by the command.
+                let current_layouts = entry.current_layouts.get(mipmaps.clone(), layers.clone());
+
+                // A command can read the same resource multiple times, for example when a texture
+                // is written to two descriptors, but can't write it through one of its uses.
                 if collision_command_id == latest_command_id {
-                    return Err(SyncCommandBufferBuilderError::Conflict);
+                    if exclusive || entry.exclusive ||
+                        current_layouts.iter().any(|r| r.layout != start_layout)
+                    {
+                        return Err(SyncCommandBufferBuilderError::Conflict);
+                    }
+
+                    entry.stages = entry.stages | stages;
+                    entry.access = entry.access | access;
+                    return Ok(());
                 }
 
-                let mut entry = entry.into_mut();
+                // The accesses to the attachments of the render pass being recorded, for example
+                // through input attachments, are synchronized by the subpass dependencies.
+                if render_pass_start.map_or(false, |start| collision_command_id >= start) {
+                    entry.command_id = latest_command_id;
+                    entry.stages = entry.stages | stages;
+                    entry.access = entry.access | access;
+                    return Ok(());
+                }
 
                 // Find out if we have a collision with the pending commands.
-                if exclusive || entry.exclusive || entry.current_layout != start_layout {
+                if exclusive || entry.exclusive ||
+                    current_layouts.iter().any(|r| r.layout != start_layout)
+                {
                     // Collision found.
 
                     // We now want to modify the current pipeline barrier in order to include the
@@ -383,17 +788,18 @@ impl<P> SyncCommandBufferBuilder<P> {
                     // the flushed commands, it would be a mistake if the command we transition
                     // from hasn't been flushed yet.
                     if collision_command_id >= first_unflushed {
-                        // Flush.
+                        // Flush. The commands of the render pass being recorded stay unflushed,
+                        // so that the barrier is recorded before the render pass.
+                        let end = render_pass_start.unwrap_or(latest_command_id);
                         unsafe {
-                            self.inner.pipeline_barrier(&self.pending_barrier);
-                            self.pending_barrier = UnsafeCommandBufferBuilderPipelineBarrier::new();
+                            submit_pending_barrier(&mut self.inner, &mut self.pending_barrier,
+                                                   &mut self.capture);
                             {
                                 let mut commands_lock = self.commands.lock().unwrap();
                                 let f = commands_lock.first_unflushed;
-                                for command in &mut commands_lock.commands[f .. latest_command_id] {
-                                    command.send(&mut self.inner);
-                                }
-                                commands_lock.first_unflushed = latest_command_id;
+                                send_commands(&mut self.inner, &mut commands_lock, f, end,
+                                              &mut self.capture);
+                                commands_lock.first_unflushed = end;
                             }
                         }
                     }
@@ -422,23 +828,29 @@ impl<P> SyncCommandBufferBuilder<P> {
                                 let img = commands_lock.commands[latest_command_id]
                                     .image(resource_index);
 
-                                let b = &mut self.pending_barrier;
-                                b.add_image_memory_barrier(img,
-                                                           0 .. img.mipmap_levels(),
-                                                           0 .. img.dimensions().array_layers(),
-                                                           entry.stages,
-                                                           entry.access,
-                                                           stages,
-                                                           access,
-                                                           true,
-                                                           None,
-                                                           entry.current_layout,
-                                                           start_layout);
+                                // One barrier for each group of subresources that are in the
+                                // same layout.
+                                for range in current_layouts {
+                                    let (m, l) = image_relative_range(img, &range);
+                                    let b = &mut self.pending_barrier;
+                                    b.add_image_memory_barrier(img,
+                                                               m,
+                                                               l,
+                                                               entry.stages,
+                                                               entry.access,
+                                                               stages,
+                                                               access,
+                                                               true,
+                                                               None,
+                                                               range.layout,
+                                                               start_layout);
+                                }
                             },
                         };
                     }
 
                     // Update state.
+                    entry.command_id = latest_command_id;
                     entry.stages = stages;
                     entry.access = access;
                     entry.exclusive_any = true;
@@ -447,18 +859,48 @@ impl<P> SyncCommandBufferBuilder<P> {
                         // Only modify the layout in case of a write, because buffer operations
                         // pass `Undefined` for the layout. While a buffer write *must* set the
                         // layout to `Undefined`, a buffer read must not touch it.
-                        entry.current_layout = end_layout;
+                        entry.current_layouts.set(mipmaps, layers, end_layout);
                     }
 
                 } else {
+                    entry.command_id = latest_command_id;
                     entry.stages = entry.stages | stages;
                     entry.access = entry.access | access;
                 }
             },
 
             Entry::Vacant(entry) => {
+                // This is the first use of the resource in the command buffer. If its content
+                // isn't initialized yet, there is no earlier command that could be writing it.
+                {
+                    let commands_lock = self.commands.lock().unwrap();
+                    let command = &commands_lock.commands[latest_command_id];
+                    let initialized = match resource_ty {
+                        KeyTy::Buffer => command.buffer(resource_index).initialized(),
+                        KeyTy::Image => command.image(resource_index).initialized(),
+                    };
+                    if !initialized {
+                        return Err(SyncCommandBufferBuilderError::UninitializedResource);
+                    }
+                }
+
                 let mut actually_exclusive = exclusive;
 
+                // The subresources that aren't used by the command stay in the layout that the
+                // image has at the start of the command buffer.
+                let mut current_layouts = match resource_ty {
+                    KeyTy::Buffer => ImageLayouts::new(0 .. 1, 0 .. 1, ImageLayout::Undefined),
+                    KeyTy::Image => {
+                        let commands_lock = self.commands.lock().unwrap();
+                        let img = commands_lock.commands[latest_command_id].image(resource_index);
+                        let image = img.inner().image;
+                        ImageLayouts::new(0 .. image.mipmap_levels(),
+                                          0 .. image.dimensions().array_layers(),
+                                          img.initial_layout_requirement())
+                    },
+                };
+                current_layouts.set(mipmaps.clone(), layers.clone(), end_layout);
+
                 // Handle the case when the initial layout requirement of the image is different
                 // from the first layout usage.
                 if resource_ty == KeyTy::Image && start_layout != ImageLayout::Undefined &&
@@ -471,15 +913,23 @@ impl<P> SyncCommandBufferBuilder<P> {
                         actually_exclusive = true;
 
                         unsafe {
+                            let (m, l) = image_relative_range(img, &SubresourceLayout {
+                                mipmaps: mipmaps,
+                                layers: layers,
+                                layout: start_layout,
+                            });
+                            // No earlier command of this command buffer uses the image, and
+                            // the previous submissions are waited upon with semaphores that
+                            // cover all the commands, so there is nothing to wait for.
                             let b = &mut self.pending_barrier;
                             b.add_image_memory_barrier(img,
-                                                       0 .. img.mipmap_levels(),
-                                                       0 .. img.dimensions().array_layers(),
+                                                       m,
+                                                       l,
                                                        PipelineStages {
-                                                           bottom_of_pipe: true,
+                                                           top_of_pipe: true,
                                                            ..PipelineStages::none()
-                                                       }, // TODO:?
-                                                       AccessFlagBits::none(), // TODO: ?
+                                                       },
+                                                       AccessFlagBits::none(),
                                                        stages,
                                                        access,
                                                        true,
@@ -491,12 +941,13 @@ impl<P> SyncCommandBufferBuilder<P> {
                 }
 
                 entry.insert(ResourceState {
+                    command_id: latest_command_id,
                     stages: stages,
                     access: access,
                     exclusive_any: actually_exclusive,
                     exclusive: actually_exclusive,
                     initial_layout: start_layout,
-                    current_layout: end_layout,     // TODO: what if we reach the end with Undefined? that's not correct?
+                    current_layouts: current_layouts,
                 });
             },
         }
@@ -504,6 +955,19 @@ impl<P> SyncCommandBufferBuilder<P> {
         Ok(())
     }
 
+    /// Returns the number of commands that have been added to the builder, not counting the
+    /// pipeline barriers.
+    #[inline]
+    pub fn num_commands(&self) -> usize {
+        self.commands.lock().unwrap().commands.len()
+    }
+
+    /// Returns the number of debug labels that have been opened and not closed yet.
+    #[inline]
+    pub fn num_open_debug_labels(&self) -> u32 {
+        self.commands.lock().unwrap().open_debug_labels
+    }
+
     /// Builds the command buffer.
     #[inline]
     pub fn build(mut self) -> Result<SyncCommandBuffer<P::Alloc>, OomError>
@@ -511,13 +975,21 @@ impl<P> SyncCommandBufferBuilder<P> {
     {
         let mut commands_lock = self.commands.lock().unwrap();
 
+        // Close the debug labels that are still open, for example because a `DebugLabelScope`
+        // is still alive.
+        while commands_lock.open_debug_labels > 0 {
+            commands_lock.commands.push(Box::new(EndDebugUtilsLabelCmd));
+            commands_lock.open_debug_labels -= 1;
+        }
+        commands_lock.built = true;
+
         // Flush the commands that haven't been flushed yet.
         unsafe {
-            self.inner.pipeline_barrier(&self.pending_barrier);
+            submit_pending_barrier(&mut self.inner, &mut self.pending_barrier,
+                                   &mut self.capture);
             let f = commands_lock.first_unflushed;
-            for command in &mut commands_lock.commands[f ..] {
-                command.send(&mut self.inner);
-            }
+            let end = commands_lock.commands.len();
+            send_commands(&mut self.inner, &mut commands_lock, f, end, &mut self.capture);
         }
 
         // Transition images to their desired final layout.
@@ -530,30 +1002,47 @@ impl<P> SyncCommandBufferBuilder<P> {
                 }
 
                 let img = commands_lock.commands[key.command_id].image(key.resource_index);
-                if img.final_layout_requirement() == state.current_layout {
-                    continue;
+                let final_layout = img.final_layout_requirement();
+
+                // Each group of subresources is transitioned from its own layout.
+                for range in state.current_layouts.ranges.iter() {
+                    if range.layout == final_layout {
+                        continue;
+                    }
+
+                    state.exclusive_any = true;
+                    let (m, l) = image_relative_range(img, range);
+                    barrier.add_image_memory_barrier(img,
+                                                     m,
+                                                     l,
+                                                     state.stages,
+                                                     state.access,
+                                                     PipelineStages {
+                                                         bottom_of_pipe: true,
+                                                         ..PipelineStages::none()
+                                                     }, // TODO:?
+                                                     AccessFlagBits::none(),
+                                                     true,
+                                                     None, // TODO: access?
+                                                     range.layout,
+                                                     final_layout);
                 }
 
-                state.exclusive_any = true;
-                barrier.add_image_memory_barrier(img,
-                                                 0 .. img.mipmap_levels(),
-                                                 0 .. img.dimensions().array_layers(),
-                                                 state.stages,
-                                                 state.access,
-                                                 PipelineStages {
-                                                     bottom_of_pipe: true,
-                                                     ..PipelineStages::none()
-                                                 }, // TODO:?
-                                                 AccessFlagBits::none(),
-                                                 true,
-                                                 None, // TODO: access?
-                                                 state.current_layout,
-                                                 img.final_layout_requirement());
+                let image = img.inner().image;
+                state.current_layouts = ImageLayouts::new(0 .. image.mipmap_levels(),
+                                                          0 .. image.dimensions().array_layers(),
+                                                          final_layout);
             }
 
+            barrier.collapse_buffer_barriers(MAX_BUFFER_BARRIERS);
+            if let Some(ref mut capture) = self.capture {
+                capture.pipeline_barrier(&barrier);
+            }
             self.inner.pipeline_barrier(&barrier);
         }
 
+        let num_commands = commands_lock.commands.len();
+
         // Fill the `commands` list.
         let final_commands = {
             let mut final_commands = Vec::new();
@@ -575,36 +1064,105 @@ impl<P> SyncCommandBufferBuilder<P> {
 
         Ok(SyncCommandBuffer {
                inner: self.inner.build()?,
-               resources: final_resources_states,
+               resources: Mutex::new(final_resources_states),
                commands: final_commands,
+               num_commands: num_commands,
+               pending_unlocks: AtomicUsize::new(0),
+               capture: self.capture.map(CommandCapture::into_text),
            })
     }
 
+    /// Calls `vkCmdBeginDebugUtilsLabelEXT` on the builder.
+    #[inline]
+    pub unsafe fn begin_debug_utils_label(&mut self, name: CString, color: [f32; 4]) {
+        struct Cmd {
+            name: CString,
+            color: [f32; 4],
+        }
+
+        impl<P> Command<P> for Cmd {
+            fn name(&self) -> &'static str {
+                "begin_debug_utils_label"
+            }
+
+            unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
+                out.begin_debug_utils_label(&self.name, self.color);
+            }
+
+            fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
+                Box::new(())
+            }
+        }
+
+        let mut commands_lock = self.commands.lock().unwrap();
+        commands_lock.commands.push(Box::new(Cmd { name, color }));
+        commands_lock.open_debug_labels += 1;
+    }
+
+    /// Same as `begin_debug_utils_label`, but returns an object that calls
+    /// `vkCmdEndDebugUtilsLabelEXT` on the builder when it is destroyed.
+    ///
+    /// If the scope is still alive when the command buffer is built, the label is closed at the
+    /// end of the command buffer.
+    #[inline]
+    pub unsafe fn debug_utils_label_scope(&mut self, name: CString, color: [f32; 4])
+                                          -> DebugLabelScope<P> {
+        self.begin_debug_utils_label(name, color);
+        DebugLabelScope {
+            commands: self.commands.clone(),
+            marker: PhantomData,
+        }
+    }
+
     /// Calls `vkBeginRenderPass` on the builder.
     // TODO: it shouldn't be possible to get an error if the framebuffer checked conflicts already
-    // TODO: after begin_render_pass has been called, flushing should be forbidden and an error
-    //       returned if conflict
     #[inline]
     pub unsafe fn begin_render_pass<F, I>(&mut self, framebuffer: F,
                                           subpass_contents: SubpassContents, clear_values: I)
                                           -> Result<(), SyncCommandBufferBuilderError>
         where F: FramebufferAbstract + Send + Sync + 'static,
               I: Iterator<Item = ClearValue> + Send + Sync + 'static
+    {
+        let dimensions = framebuffer.dimensions();
+        self.begin_render_pass_with_area(framebuffer,
+                                         subpass_contents,
+                                         clear_values,
+                                         [0, 0],
+                                         [dimensions[0], dimensions[1]])
+    }
+
+    /// Calls `vkBeginRenderPass` on the builder, with a render area of `extent` pixels starting
+    /// at `offset`.
+    pub unsafe fn begin_render_pass_with_area<F, I>(&mut self, framebuffer: F,
+                                                    subpass_contents: SubpassContents,
+                                                    clear_values: I, offset: [u32; 2],
+                                                    extent: [u32; 2])
+                                                    -> Result<(), SyncCommandBufferBuilderError>
+        where F: FramebufferAbstract + Send + Sync + 'static,
+              I: Iterator<Item = ClearValue> + Send + Sync + 'static
     {
         struct Cmd<F, I> {
             framebuffer: F,
             subpass_contents: SubpassContents,
             clear_values: Option<I>,
+            offset: [u32; 2],
+            extent: [u32; 2],
         }
 
         impl<P, F, I> Command<P> for Cmd<F, I>
             where F: FramebufferAbstract + Send + Sync + 'static,
                   I: Iterator<Item = ClearValue>
         {
+            fn name(&self) -> &'static str {
+                "begin_render_pass"
+            }
+
             unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
-                out.begin_render_pass(&self.framebuffer,
-                                      self.subpass_contents,
-                                      self.clear_values.take().unwrap());
+                out.begin_render_pass_with_area(&self.framebuffer,
+                                                self.subpass_contents,
+                                                self.clear_values.take().unwrap(),
+                                                self.offset,
+                                                self.extent);
             }
 
             fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
@@ -634,6 +1192,8 @@ impl<P> SyncCommandBufferBuilder<P> {
                                                                  framebuffer,
                                                                  subpass_contents,
                                                                  clear_values: Some(clear_values),
+                                                                 offset,
+                                                                 extent,
                                                              }));
 
         for (atch, desc) in atch_desc.into_iter().enumerate() {
@@ -653,6 +1213,84 @@ impl<P> SyncCommandBufferBuilder<P> {
                                    desc.initial_layout, desc.final_layout)?;
         }
 
+        let mut commands_lock = self.commands.lock().unwrap();
+        commands_lock.render_pass_start = Some(commands_lock.commands.len() - 1);
+        Ok(())
+    }
+
+    /// Calls `vkCmdBeginRenderingKHR` on the builder, with a render area of `extent` pixels
+    /// starting at `offset`.
+    ///
+    /// The color attachments are transitioned to the `ColorAttachmentOptimal` layout beforehand.
+    pub unsafe fn begin_rendering<V>(&mut self, color_attachments: Vec<(V, ClearValue)>,
+                                     offset: [u32; 2], extent: [u32; 2])
+                                     -> Result<(), SyncCommandBufferBuilderError>
+        where V: ImageViewAccess + Send + Sync + 'static
+    {
+        struct Cmd<V> {
+            color_attachments: Vec<(V, ClearValue)>,
+            offset: [u32; 2],
+            extent: [u32; 2],
+        }
+
+        impl<P, V> Command<P> for Cmd<V>
+            where V: ImageViewAccess + Send + Sync + 'static
+        {
+            fn name(&self) -> &'static str {
+                "begin_rendering"
+            }
+
+            unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
+                let attachments = self.color_attachments.iter().map(|&(ref view, clear_value)| {
+                    (view.inner(), ImageLayout::ColorAttachmentOptimal, clear_value)
+                });
+                out.begin_rendering(attachments, self.offset, self.extent);
+            }
+
+            fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
+                struct Fin<V>(Vec<(V, ClearValue)>);
+                impl<V> FinalCommand for Fin<V>
+                    where V: ImageViewAccess + Send + Sync + 'static
+                {
+                    fn image(&self, num: usize) -> &ImageAccess {
+                        self.0[num].0.parent()
+                    }
+                }
+                Box::new(Fin(self.color_attachments))
+            }
+
+            fn image(&self, num: usize) -> &ImageAccess {
+                self.color_attachments[num].0.parent()
+            }
+        }
+
+        let num_attachments = color_attachments.len();
+
+        self.commands.lock().unwrap().commands.push(Box::new(Cmd {
+                                                                 color_attachments,
+                                                                 offset,
+                                                                 extent,
+                                                             }));
+
+        for atch in 0 .. num_attachments {
+            self.prev_cmd_resource(KeyTy::Image,
+                                   atch,
+                                   true,
+                                   PipelineStages {
+                                       color_attachment_output: true,
+                                       ..PipelineStages::none()
+                                   },
+                                   AccessFlagBits {
+                                       color_attachment_read: true,
+                                       color_attachment_write: true,
+                                       ..AccessFlagBits::none()
+                                   },
+                                   ImageLayout::ColorAttachmentOptimal,
+                                   ImageLayout::ColorAttachmentOptimal)?;
+        }
+
+        let mut commands_lock = self.commands.lock().unwrap();
+        commands_lock.render_pass_start = Some(commands_lock.commands.len() - 1);
         Ok(())
     }
 
@@ -670,6 +1308,10 @@ impl<P> SyncCommandBufferBuilder<P> {
         impl<P, B> Command<P> for Cmd<B>
             where B: BufferAccess + Send + Sync + 'static
         {
+            fn name(&self) -> &'static str {
+                "bind_index_buffer"
+            }
+
             unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
                 out.bind_index_buffer(&self.buffer, self.index_ty);
             }
@@ -679,6 +1321,10 @@ impl<P> SyncCommandBufferBuilder<P> {
                 impl<B> FinalCommand for Fin<B>
                     where B: BufferAccess + Send + Sync + 'static
                 {
+                    fn execution_only(&self) -> bool {
+                        true
+                    }
+
                     fn buffer(&self, num: usize) -> &BufferAccess {
                         assert_eq!(num, 0);
                         &self.0
@@ -726,6 +1372,10 @@ impl<P> SyncCommandBufferBuilder<P> {
         impl<P, Gp> Command<P> for Cmd<Gp>
             where Gp: GraphicsPipelineAbstract + Send + Sync + 'static
         {
+            fn name(&self) -> &'static str {
+                "bind_pipeline_graphics"
+            }
+
             unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
                 out.bind_pipeline_graphics(&self.pipeline);
             }
@@ -759,6 +1409,10 @@ impl<P> SyncCommandBufferBuilder<P> {
         impl<P, Gp> Command<P> for Cmd<Gp>
             where Gp: ComputePipelineAbstract + Send + Sync + 'static
         {
+            fn name(&self) -> &'static str {
+                "bind_pipeline_compute"
+            }
+
             unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
                 out.bind_pipeline_compute(&self.pipeline);
             }
@@ -780,6 +1434,55 @@ impl<P> SyncCommandBufferBuilder<P> {
             .push(Box::new(Cmd { pipeline }));
     }
 
+    /// Calls `vkCmdBindShadersEXT` on the builder.
+    ///
+    /// Each shader is bound to the stage it was created for, and the stages of `unbound` have
+    /// no shader bound.
+    #[inline]
+    pub unsafe fn bind_shaders<L>(&mut self, shaders: Vec<Arc<ShaderObject<L>>>,
+                                  unbound: Vec<ShaderObjectStage>)
+        where L: Send + Sync + 'static
+    {
+        struct Cmd<L> {
+            shaders: Vec<Arc<ShaderObject<L>>>,
+            unbound: Vec<ShaderObjectStage>,
+        }
+
+        impl<P, L> Command<P> for Cmd<L>
+            where L: Send + Sync + 'static
+        {
+            fn name(&self) -> &'static str {
+                "bind_shaders"
+            }
+
+            unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
+                let mut params = UnsafeCommandBufferBuilderBindShaders::new();
+                for shader in self.shaders.iter() {
+                    params.add(shader);
+                }
+                for &stage in self.unbound.iter() {
+                    params.add_unbound(stage);
+                }
+                out.bind_shaders(&params);
+            }
+
+            fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
+                struct Fin<L>(Vec<Arc<ShaderObject<L>>>);
+                impl<L> FinalCommand for Fin<L>
+                    where L: Send + Sync + 'static
+                {
+                }
+                Box::new(Fin(self.shaders))
+            }
+        }
+
+        self.commands
+            .lock()
+            .unwrap()
+            .commands
+            .push(Box::new(Cmd { shaders, unbound }));
+    }
+
     /// Starts the process of binding descriptor sets. Returns an intermediate struct which can be
     /// used to add the sets.
     #[inline]
@@ -823,6 +1526,10 @@ impl<P> SyncCommandBufferBuilder<P> {
                   D: BufferAccess + Send + Sync + 'static,
                   R: Iterator<Item = (usize, usize, usize)>
         {
+            fn name(&self) -> &'static str {
+                "copy_buffer"
+            }
+
             unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
                 out.copy_buffer(self.source.as_ref().unwrap(),
                                 self.destination.as_ref().unwrap(),
@@ -835,6 +1542,10 @@ impl<P> SyncCommandBufferBuilder<P> {
                     where S: BufferAccess + Send + Sync + 'static,
                           D: BufferAccess + Send + Sync + 'static
                 {
+                    fn execution_only(&self) -> bool {
+                        true
+                    }
+
                     fn buffer(&self, num: usize) -> &BufferAccess {
                         match num {
                             0 => &self.0,
@@ -916,6 +1627,10 @@ impl<P> SyncCommandBufferBuilder<P> {
                   D: ImageAccess + Send + Sync + 'static,
                   R: Iterator<Item = UnsafeCommandBufferBuilderBufferImageCopy>
         {
+            fn name(&self) -> &'static str {
+                "copy_buffer_to_image"
+            }
+
             unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
                 out.copy_buffer_to_image(self.source.as_ref().unwrap(),
                                          self.destination.as_ref().unwrap(),
@@ -929,6 +1644,10 @@ impl<P> SyncCommandBufferBuilder<P> {
                     where S: BufferAccess + Send + Sync + 'static,
                           D: ImageAccess + Send + Sync + 'static
                 {
+                    fn execution_only(&self) -> bool {
+                        true
+                    }
+
                     fn buffer(&self, num: usize) -> &BufferAccess {
                         assert_eq!(num, 0);
                         &self.0
@@ -957,11 +1676,28 @@ impl<P> SyncCommandBufferBuilder<P> {
             }
         }
 
+        // Only the mipmap levels and array layers that are written by the regions are transitioned.
+        let regions = regions.collect::<Vec<_>>();
+        let (mipmaps, layers) = if regions.is_empty() {
+            let inner = destination.inner();
+            (0 .. inner.num_mipmap_levels as u32, 0 .. inner.num_layers as u32)
+        } else {
+            let mipmaps = regions.iter().map(|r| r.image_mip_level).min().unwrap() ..
+                regions.iter().map(|r| r.image_mip_level + 1).max().unwrap();
+            let layers = regions.iter().map(|r| r.image_base_array_layer).min().unwrap() ..
+                regions
+                    .iter()
+                    .map(|r| r.image_base_array_layer + r.image_layer_count)
+                    .max()
+                    .unwrap();
+            (mipmaps, layers)
+        };
+
         self.commands.lock().unwrap().commands.push(Box::new(Cmd {
                                                                  source: Some(source),
                                                                  destination: Some(destination),
                                                                  dest_layout,
-                                                                 regions: Some(regions),
+                                                                 regions: Some(regions.into_iter()),
                                                              }));
         self.prev_cmd_resource(KeyTy::Buffer,
                                0,
@@ -976,7 +1712,138 @@ impl<P> SyncCommandBufferBuilder<P> {
                                },
                                ImageLayout::Undefined,
                                ImageLayout::Undefined)?;
-        self.prev_cmd_resource(KeyTy::Image,
+        self.prev_cmd_resource_range(KeyTy::Image,
+                                     0,
+                                     mipmaps,
+                                     layers,
+                                     true,
+                                     PipelineStages {
+                                         transfer: true,
+                                         ..PipelineStages::none()
+                                     },
+                                     AccessFlagBits {
+                                         transfer_write: true,
+                                         ..AccessFlagBits::none()
+                                     },
+                                     dest_layout,
+                                     dest_layout)?;
+        Ok(())
+    }
+
+    /// Calls `vkCmdCopyImageToBuffer` on the builder.
+    ///
+    /// Does nothing if the list of regions is empty, as it would be a no-op and isn't a valid
+    /// usage of the command anyway.
+    #[inline]
+    pub unsafe fn copy_image_to_buffer<S, D, R>(&mut self, source: S, source_layout: ImageLayout,
+                                                destination: D, regions: R)
+                                                -> Result<(), SyncCommandBufferBuilderError>
+        where S: ImageAccess + Send + Sync + 'static,
+              D: BufferAccess + Send + Sync + 'static,
+              R: Iterator<Item = UnsafeCommandBufferBuilderBufferImageCopy> + Send + Sync + 'static
+    {
+        struct Cmd<S, D, R> {
+            source: Option<S>,
+            source_layout: ImageLayout,
+            destination: Option<D>,
+            regions: Option<R>,
+        }
+
+        impl<P, S, D, R> Command<P> for Cmd<S, D, R>
+            where S: ImageAccess + Send + Sync + 'static,
+                  D: BufferAccess + Send + Sync + 'static,
+                  R: Iterator<Item = UnsafeCommandBufferBuilderBufferImageCopy>
+        {
+            fn name(&self) -> &'static str {
+                "copy_image_to_buffer"
+            }
+
+            unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
+                out.copy_image_to_buffer(self.source.as_ref().unwrap(),
+                                         self.source_layout,
+                                         self.destination.as_ref().unwrap(),
+                                         self.regions.take().unwrap());
+            }
+
+            fn into_final_command(mut self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
+                struct Fin<S, D>(S, D);
+                impl<S, D> FinalCommand for Fin<S, D>
+                    where S: ImageAccess + Send + Sync + 'static,
+                          D: BufferAccess + Send + Sync + 'static
+                {
+                    fn execution_only(&self) -> bool {
+                        true
+                    }
+
+                    fn buffer(&self, num: usize) -> &BufferAccess {
+                        assert_eq!(num, 0);
+                        &self.1
+                    }
+
+                    fn image(&self, num: usize) -> &ImageAccess {
+                        assert_eq!(num, 0);
+                        &self.0
+                    }
+                }
+
+                // Note: borrow checker somehow doesn't accept `self.source` and `self.destination`
+                // without using an Option.
+                Box::new(Fin(self.source.take().unwrap(),
+                             self.destination.take().unwrap()))
+            }
+
+            fn buffer(&self, num: usize) -> &BufferAccess {
+                assert_eq!(num, 0);
+                self.destination.as_ref().unwrap()
+            }
+
+            fn image(&self, num: usize) -> &ImageAccess {
+                assert_eq!(num, 0);
+                self.source.as_ref().unwrap()
+            }
+        }
+
+        // Only the mipmap levels and array layers that are read by the regions are transitioned.
+        let regions = regions.collect::<Vec<_>>();
+        let (mipmaps, layers) = if regions.is_empty() {
+            let inner = source.inner();
+            (0 .. inner.num_mipmap_levels as u32, 0 .. inner.num_layers as u32)
+        } else {
+            let mipmaps = regions.iter().map(|r| r.image_mip_level).min().unwrap() ..
+                regions.iter().map(|r| r.image_mip_level + 1).max().unwrap();
+            let layers = regions.iter().map(|r| r.image_base_array_layer).min().unwrap() ..
+                regions
+                    .iter()
+                    .map(|r| r.image_base_array_layer + r.image_layer_count)
+                    .max()
+                    .unwrap();
+            (mipmaps, layers)
+        };
+
+        self.commands.lock().unwrap().commands.push(Box::new(Cmd {
+                                                                 source: Some(source),
+                                                                 source_layout,
+                                                                 destination: Some(destination),
+                                                                 regions: Some(regions.into_iter()),
+                                                             }));
+        // The image is only read, but the access is declared as exclusive so that the layout
+        // transition to `source_layout` is recorded in the state of the image.
+        self.prev_cmd_resource_range(KeyTy::Image,
+                                     0,
+                                     mipmaps,
+                                     layers,
+                                     true,
+                                     PipelineStages {
+                                         transfer: true,
+                                         ..PipelineStages::none()
+                                     },
+                                     AccessFlagBits {
+                                         transfer_read: true,
+                                         ..AccessFlagBits::none()
+                                     },
+                                     source_layout,
+                                     source_layout)?;
+        self.prev_cmd_resource(KeyTy::Buffer,
                                0,
                                true,
                                PipelineStages {
@@ -987,8 +1854,129 @@ impl<P> SyncCommandBufferBuilder<P> {
                                    transfer_write: true,
                                    ..AccessFlagBits::none()
                                },
-                               dest_layout,
-                               dest_layout)?;
+                               ImageLayout::Undefined,
+                               ImageLayout::Undefined)?;
+        Ok(())
+    }
+
+    /// Declares that the commands that have been added to the builder so far have accessed
+    /// `buffer` with the given stages and access.
+    ///
+    /// No Vulkan command is recorded, but the builder takes the usage into account when it
+    /// determines which pipeline barriers are needed. This is useful for resources that are
+    /// accessed through descriptor sets, as they are not yet tracked automatically.
+    #[inline]
+    pub unsafe fn declare_buffer_usage<B>(&mut self, buffer: B, exclusive: bool,
+                                          stages: PipelineStages, access: AccessFlagBits)
+                                          -> Result<(), SyncCommandBufferBuilderError>
+        where B: BufferAccess + Send + Sync + 'static
+    {
+        struct Cmd<B> {
+            buffer: B,
+        }
+
+        impl<P, B> Command<P> for Cmd<B>
+            where B: BufferAccess + Send + Sync + 'static
+        {
+            fn name(&self) -> &'static str {
+                "declare_buffer_usage"
+            }
+
+            unsafe fn send(&mut self, _: &mut UnsafeCommandBufferBuilder<P>) {
+            }
+
+            fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
+                struct Fin<B>(B);
+                impl<B> FinalCommand for Fin<B>
+                    where B: BufferAccess + Send + Sync + 'static
+                {
+                    fn execution_only(&self) -> bool {
+                        true
+                    }
+
+                    fn buffer(&self, num: usize) -> &BufferAccess {
+                        assert_eq!(num, 0);
+                        &self.0
+                    }
+                }
+                Box::new(Fin(self.buffer))
+            }
+
+            fn buffer(&self, num: usize) -> &BufferAccess {
+                assert_eq!(num, 0);
+                &self.buffer
+            }
+        }
+
+        self.commands
+            .lock()
+            .unwrap()
+            .commands
+            .push(Box::new(Cmd { buffer }));
+        self.prev_cmd_resource(KeyTy::Buffer,
+                               0,
+                               exclusive,
+                               stages,
+                               access,
+                               ImageLayout::Undefined,
+                               ImageLayout::Undefined)?;
+        Ok(())
+    }
+
+    /// Declares that the commands that have been added to the builder so far have accessed
+    /// `image` in the given layout with the given stages and access.
+    ///
+    /// See `declare_buffer_usage`.
+    #[inline]
+    pub unsafe fn declare_image_usage<I>(&mut self, image: I, layout: ImageLayout,
+                                         exclusive: bool, stages: PipelineStages,
+                                         access: AccessFlagBits)
+                                         -> Result<(), SyncCommandBufferBuilderError>
+        where I: ImageAccess + Send + Sync + 'static
+    {
+        struct Cmd<I> {
+            image: I,
+        }
+
+        impl<P, I> Command<P> for Cmd<I>
+            where I: ImageAccess + Send + Sync + 'static
+        {
+            fn name(&self) -> &'static str {
+                "declare_image_usage"
+            }
+
+            unsafe fn send(&mut self, _: &mut UnsafeCommandBufferBuilder<P>) {
+            }
+
+            fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
+                struct Fin<I>(I);
+                impl<I> FinalCommand for Fin<I>
+                    where I: ImageAccess + Send + Sync + 'static
+                {
+                    fn execution_only(&self) -> bool {
+                        true
+                    }
+
+                    fn image(&self, num: usize) -> &ImageAccess {
+                        assert_eq!(num, 0);
+                        &self.0
+                    }
+                }
+                Box::new(Fin(self.image))
+            }
+
+            fn image(&self, num: usize) -> &ImageAccess {
+                assert_eq!(num, 0);
+                &self.image
+            }
+        }
+
+        self.commands
+            .lock()
+            .unwrap()
+            .commands
+            .push(Box::new(Cmd { image }));
+        self.prev_cmd_resource(KeyTy::Image, 0, exclusive, stages, access, layout, layout)?;
         Ok(())
     }
 
@@ -1000,6 +1988,10 @@ impl<P> SyncCommandBufferBuilder<P> {
         }
 
         impl<P> Command<P> for Cmd {
+            fn name(&self) -> &'static str {
+                "dispatch"
+            }
+
             unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
                 out.dispatch(self.dimensions);
             }
@@ -1029,6 +2021,10 @@ impl<P> SyncCommandBufferBuilder<P> {
         impl<P, B> Command<P> for Cmd<B>
             where B: BufferAccess + Send + Sync + 'static
         {
+            fn name(&self) -> &'static str {
+                "dispatch_indirect"
+            }
+
             unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
                 out.dispatch_indirect(&self.buffer);
             }
@@ -1038,6 +2034,10 @@ impl<P> SyncCommandBufferBuilder<P> {
                 impl<B> FinalCommand for Fin<B>
                     where B: BufferAccess + Send + Sync + 'static
                 {
+                    fn execution_only(&self) -> bool {
+                        true
+                    }
+
                     fn buffer(&self, num: usize) -> &BufferAccess {
                         assert_eq!(num, 0);
                         &self.0
@@ -1085,6 +2085,10 @@ impl<P> SyncCommandBufferBuilder<P> {
         }
 
         impl<P> Command<P> for Cmd {
+            fn name(&self) -> &'static str {
+                "draw"
+            }
+
             unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
                 out.draw(self.vertex_count,
                          self.instance_count,
@@ -1119,6 +2123,10 @@ impl<P> SyncCommandBufferBuilder<P> {
         }
 
         impl<P> Command<P> for Cmd {
+            fn name(&self) -> &'static str {
+                "draw_indexed"
+            }
+
             unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
                 out.draw_indexed(self.index_count,
                                  self.instance_count,
@@ -1156,6 +2164,10 @@ impl<P> SyncCommandBufferBuilder<P> {
         impl<P, B> Command<P> for Cmd<B>
             where B: BufferAccess + Send + Sync + 'static
         {
+            fn name(&self) -> &'static str {
+                "draw_indirect"
+            }
+
             unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
                 out.draw_indirect(&self.buffer, self.draw_count, self.stride);
             }
@@ -1165,6 +2177,10 @@ impl<P> SyncCommandBufferBuilder<P> {
                 impl<B> FinalCommand for Fin<B>
                     where B: BufferAccess + Send + Sync + 'static
                 {
+                    fn execution_only(&self) -> bool {
+                        true
+                    }
+
                     fn buffer(&self, num: usize) -> &BufferAccess {
                         assert_eq!(num, 0);
                         &self.0
@@ -1200,6 +2216,125 @@ impl<P> SyncCommandBufferBuilder<P> {
         Ok(())
     }
 
+    /// Calls `vkCmdExecuteGeneratedCommandsNV` on the builder.
+    ///
+    /// The streams are read like indirect buffers. Since the implementation both reads and
+    /// writes the preprocess buffer at a stage that vulkano doesn't expose, the preprocess buffer
+    /// is conservatively considered as being written by all the commands.
+    #[inline]
+    pub unsafe fn execute_generated_commands<Gp, L, Pb>(&mut self, pipeline: Gp,
+                                                        layout: Arc<IndirectCommandsLayout<L>>,
+                                                        streams: Vec<Box<BufferAccess + Send + Sync>>,
+                                                        sequences_count: u32, preprocess: Pb)
+                                                        -> Result<(), SyncCommandBufferBuilderError>
+        where Gp: GraphicsPipelineAbstract + Send + Sync + 'static,
+              L: Send + Sync + 'static,
+              Pb: BufferAccess + Send + Sync + 'static
+    {
+        struct Cmd<Gp, L, Pb> {
+            pipeline: Gp,
+            layout: Arc<IndirectCommandsLayout<L>>,
+            streams: Vec<Box<BufferAccess + Send + Sync>>,
+            sequences_count: u32,
+            preprocess: Pb,
+        }
+
+        impl<P, Gp, L, Pb> Command<P> for Cmd<Gp, L, Pb>
+            where Gp: GraphicsPipelineAbstract + Send + Sync + 'static,
+                  L: Send + Sync + 'static,
+                  Pb: BufferAccess + Send + Sync + 'static
+        {
+            fn name(&self) -> &'static str {
+                "execute_generated_commands"
+            }
+
+            unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
+                let streams = self.streams
+                    .iter()
+                    .map(|s| &**s as &BufferAccess)
+                    .collect::<SmallVec<[_; 4]>>();
+                out.execute_generated_commands(&self.pipeline,
+                                               &self.layout,
+                                               &streams,
+                                               self.sequences_count,
+                                               &self.preprocess);
+            }
+
+            fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
+                struct Fin<Gp, L, Pb>(Gp,
+                                      Arc<IndirectCommandsLayout<L>>,
+                                      Vec<Box<BufferAccess + Send + Sync>>,
+                                      Pb);
+                impl<Gp, L, Pb> FinalCommand for Fin<Gp, L, Pb>
+                    where Gp: Send + Sync + 'static,
+                          L: Send + Sync + 'static,
+                          Pb: BufferAccess + Send + Sync + 'static
+                {
+                    fn buffer(&self, num: usize) -> &BufferAccess {
+                        if num < self.2.len() {
+                            &self.2[num]
+                        } else {
+                            assert_eq!(num, self.2.len());
+                            &self.3
+                        }
+                    }
+                }
+                Box::new(Fin(self.pipeline, self.layout, self.streams, self.preprocess))
+            }
+
+            fn buffer(&self, num: usize) -> &BufferAccess {
+                if num < self.streams.len() {
+                    &self.streams[num]
+                } else {
+                    assert_eq!(num, self.streams.len());
+                    &self.preprocess
+                }
+            }
+        }
+
+        let num_streams = streams.len();
+
+        self.commands.lock().unwrap().commands.push(Box::new(Cmd {
+                                                                 pipeline,
+                                                                 layout,
+                                                                 streams,
+                                                                 sequences_count,
+                                                                 preprocess,
+                                                             }));
+
+        for n in 0 .. num_streams {
+            self.prev_cmd_resource(KeyTy::Buffer,
+                                   n,
+                                   false,
+                                   PipelineStages {
+                                       draw_indirect: true,
+                                       ..PipelineStages::none()
+                                   },
+                                   AccessFlagBits {
+                                       indirect_command_read: true,
+                                       ..AccessFlagBits::none()
+                                   },
+                                   ImageLayout::Undefined,
+                                   ImageLayout::Undefined)?;
+        }
+
+        self.prev_cmd_resource(KeyTy::Buffer,
+                               num_streams,
+                               true,
+                               PipelineStages {
+                                   all_commands: true,
+                                   ..PipelineStages::none()
+                               },
+                               AccessFlagBits {
+                                   memory_read: true,
+                                   memory_write: true,
+                                   ..AccessFlagBits::none()
+                               },
+                               ImageLayout::Undefined,
+                               ImageLayout::Undefined)?;
+        Ok(())
+    }
+
     /// Calls `vkCmdDrawIndexedIndirect` on the builder.
     #[inline]
     pub unsafe fn draw_indexed_indirect<B>(&mut self, buffer: B, draw_count: u32, stride: u32)
@@ -1215,6 +2350,10 @@ impl<P> SyncCommandBufferBuilder<P> {
         impl<P, B> Command<P> for Cmd<B>
             where B: BufferAccess + Send + Sync + 'static
         {
+            fn name(&self) -> &'static str {
+                "draw_indexed_indirect"
+            }
+
             unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
                 out.draw_indexed_indirect(&self.buffer, self.draw_count, self.stride);
             }
@@ -1224,6 +2363,10 @@ impl<P> SyncCommandBufferBuilder<P> {
                 impl<B> FinalCommand for Fin<B>
                     where B: BufferAccess + Send + Sync + 'static
                 {
+                    fn execution_only(&self) -> bool {
+                        true
+                    }
+
                     fn buffer(&self, num: usize) -> &BufferAccess {
                         assert_eq!(num, 0);
                         &self.0
@@ -1259,12 +2402,30 @@ impl<P> SyncCommandBufferBuilder<P> {
         Ok(())
     }
 
+    /// Calls `vkCmdEndDebugUtilsLabelEXT` on the builder.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if there is no open debug label.
+    ///
+    #[inline]
+    pub unsafe fn end_debug_utils_label(&mut self) {
+        let mut commands_lock = self.commands.lock().unwrap();
+        assert!(commands_lock.open_debug_labels >= 1);
+        commands_lock.commands.push(Box::new(EndDebugUtilsLabelCmd));
+        commands_lock.open_debug_labels -= 1;
+    }
+
     /// Calls `vkCmdEndRenderPass` on the builder.
     #[inline]
     pub unsafe fn end_render_pass(&mut self) {
         struct Cmd;
 
         impl<P> Command<P> for Cmd {
+            fn name(&self) -> &'static str {
+                "end_render_pass"
+            }
+
             unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
                 out.end_render_pass();
             }
@@ -1274,7 +2435,33 @@ impl<P> SyncCommandBufferBuilder<P> {
             }
         }
 
-        self.commands.lock().unwrap().commands.push(Box::new(Cmd));
+        let mut commands_lock = self.commands.lock().unwrap();
+        commands_lock.commands.push(Box::new(Cmd));
+        commands_lock.render_pass_start = None;
+    }
+
+    /// Calls `vkCmdEndRenderingKHR` on the builder.
+    #[inline]
+    pub unsafe fn end_rendering(&mut self) {
+        struct Cmd;
+
+        impl<P> Command<P> for Cmd {
+            fn name(&self) -> &'static str {
+                "end_rendering"
+            }
+
+            unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
+                out.end_rendering();
+            }
+
+            fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
+                Box::new(())
+            }
+        }
+
+        let mut commands_lock = self.commands.lock().unwrap();
+        commands_lock.commands.push(Box::new(Cmd));
+        commands_lock.render_pass_start = None;
     }
 
     /// Calls `vkCmdFillBuffer` on the builder.
@@ -1290,6 +2477,10 @@ impl<P> SyncCommandBufferBuilder<P> {
         impl<P, B> Command<P> for Cmd<B>
             where B: BufferAccess + Send + Sync + 'static
         {
+            fn name(&self) -> &'static str {
+                "fill_buffer"
+            }
+
             unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
                 out.fill_buffer(&self.buffer, self.data);
             }
@@ -1299,6 +2490,10 @@ impl<P> SyncCommandBufferBuilder<P> {
                 impl<B> FinalCommand for Fin<B>
                     where B: BufferAccess + Send + Sync + 'static
                 {
+                    fn execution_only(&self) -> bool {
+                        true
+                    }
+
                     fn buffer(&self, num: usize) -> &BufferAccess {
                         assert_eq!(num, 0);
                         &self.0
@@ -1334,6 +2529,35 @@ impl<P> SyncCommandBufferBuilder<P> {
             .unwrap();
     }
 
+    /// Calls `vkCmdInsertDebugUtilsLabelEXT` on the builder.
+    #[inline]
+    pub unsafe fn insert_debug_utils_label(&mut self, name: CString, color: [f32; 4]) {
+        struct Cmd {
+            name: CString,
+            color: [f32; 4],
+        }
+
+        impl<P> Command<P> for Cmd {
+            fn name(&self) -> &'static str {
+                "insert_debug_utils_label"
+            }
+
+            unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
+                out.insert_debug_utils_label(&self.name, self.color);
+            }
+
+            fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
+                Box::new(())
+            }
+        }
+
+        self.commands
+            .lock()
+            .unwrap()
+            .commands
+            .push(Box::new(Cmd { name, color }));
+    }
+
     /// Calls `vkCmdNextSubpass` on the builder.
     #[inline]
     pub unsafe fn next_subpass(&mut self, subpass_contents: SubpassContents) {
@@ -1342,6 +2566,10 @@ impl<P> SyncCommandBufferBuilder<P> {
         }
 
         impl<P> Command<P> for Cmd {
+            fn name(&self) -> &'static str {
+                "next_subpass"
+            }
+
             unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
                 out.next_subpass(self.subpass_contents);
             }
@@ -1376,6 +2604,10 @@ impl<P> SyncCommandBufferBuilder<P> {
         impl<P, Pl> Command<P> for Cmd<Pl>
             where Pl: PipelineLayoutAbstract + Send + Sync + 'static
         {
+            fn name(&self) -> &'static str {
+                "push_constants"
+            }
+
             unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
                 out.push_constants::<_, [u8]>(&self.pipeline_layout,
                                               self.stages,
@@ -1411,17 +2643,256 @@ impl<P> SyncCommandBufferBuilder<P> {
                                                              }));
     }
 
-    /// Calls `vkCmdResetEvent` on the builder.
+    /// Calls `vkCmdResetEvent` on the builder.
+    #[inline]
+    pub unsafe fn reset_event(&mut self, event: Arc<Event>, stages: PipelineStages) {
+        struct Cmd {
+            event: Arc<Event>,
+            stages: PipelineStages,
+        }
+
+        impl<P> Command<P> for Cmd {
+            fn name(&self) -> &'static str {
+                "reset_event"
+            }
+
+            unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
+                out.reset_event(&self.event, self.stages);
+            }
+
+            fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
+                struct Fin(Arc<Event>);
+                impl FinalCommand for Fin {
+                }
+                Box::new(Fin(self.event))
+            }
+        }
+
+        self.commands
+            .lock()
+            .unwrap()
+            .commands
+            .push(Box::new(Cmd { event, stages }));
+    }
+
+    /// Calls `vkCmdResetQueryPool` on the builder.
+    #[inline]
+    pub unsafe fn reset_query_pool(&mut self, pool: Arc<UnsafeQueryPool>, queries: Range<u32>) {
+        struct Cmd {
+            pool: Arc<UnsafeQueryPool>,
+            queries: Range<u32>,
+        }
+
+        impl<P> Command<P> for Cmd {
+            fn name(&self) -> &'static str {
+                "reset_query_pool"
+            }
+
+            unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
+                out.reset_query_pool(&self.pool, self.queries.clone());
+            }
+
+            fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
+                struct Fin(Arc<UnsafeQueryPool>);
+                impl FinalCommand for Fin {
+                }
+                Box::new(Fin(self.pool))
+            }
+        }
+
+        self.commands
+            .lock()
+            .unwrap()
+            .commands
+            .push(Box::new(Cmd { pool, queries }));
+    }
+
+    /// Calls `vkCmdSetBlendConstants` on the builder.
+    #[inline]
+    pub unsafe fn set_blend_constants(&mut self, constants: [f32; 4]) {
+        struct Cmd {
+            constants: [f32; 4],
+        }
+
+        impl<P> Command<P> for Cmd {
+            fn name(&self) -> &'static str {
+                "set_blend_constants"
+            }
+
+            unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
+                out.set_blend_constants(self.constants);
+            }
+
+            fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
+                Box::new(())
+            }
+        }
+
+        self.commands
+            .lock()
+            .unwrap()
+            .commands
+            .push(Box::new(Cmd { constants }));
+    }
+
+    /// Calls `vkCmdSetDepthBias` on the builder.
+    #[inline]
+    pub unsafe fn set_depth_bias(&mut self, constant_factor: f32, clamp: f32, slope_factor: f32) {
+        struct Cmd {
+            constant_factor: f32,
+            clamp: f32,
+            slope_factor: f32,
+        }
+
+        impl<P> Command<P> for Cmd {
+            fn name(&self) -> &'static str {
+                "set_depth_bias"
+            }
+
+            unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
+                out.set_depth_bias(self.constant_factor, self.clamp, self.slope_factor);
+            }
+
+            fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
+                Box::new(())
+            }
+        }
+
+        self.commands.lock().unwrap().commands.push(Box::new(Cmd {
+                                                                 constant_factor,
+                                                                 clamp,
+                                                                 slope_factor,
+                                                             }));
+    }
+
+    /// Calls `vkCmdSetDepthBounds` on the builder.
+    #[inline]
+    pub unsafe fn set_depth_bounds(&mut self, min: f32, max: f32) {
+        struct Cmd {
+            min: f32,
+            max: f32,
+        }
+
+        impl<P> Command<P> for Cmd {
+            fn name(&self) -> &'static str {
+                "set_depth_bounds"
+            }
+
+            unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
+                out.set_depth_bounds(self.min, self.max);
+            }
+
+            fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
+                Box::new(())
+            }
+        }
+
+        self.commands
+            .lock()
+            .unwrap()
+            .commands
+            .push(Box::new(Cmd { min, max }));
+    }
+
+    /// Calls `vkCmdSetDepthCompareOpEXT` on the builder.
+    #[inline]
+    pub unsafe fn set_depth_compare_op(&mut self, compare: Compare) {
+        struct Cmd {
+            compare: Compare,
+        }
+
+        impl<P> Command<P> for Cmd {
+            fn name(&self) -> &'static str {
+                "set_depth_compare_op"
+            }
+
+            unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
+                out.set_depth_compare_op(self.compare);
+            }
+
+            fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
+                Box::new(())
+            }
+        }
+
+        self.commands
+            .lock()
+            .unwrap()
+            .commands
+            .push(Box::new(Cmd { compare }));
+    }
+
+    /// Calls `vkCmdSetDepthTestEnableEXT` on the builder.
+    #[inline]
+    pub unsafe fn set_depth_test_enable(&mut self, enable: bool) {
+        struct Cmd {
+            enable: bool,
+        }
+
+        impl<P> Command<P> for Cmd {
+            fn name(&self) -> &'static str {
+                "set_depth_test_enable"
+            }
+
+            unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
+                out.set_depth_test_enable(self.enable);
+            }
+
+            fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
+                Box::new(())
+            }
+        }
+
+        self.commands
+            .lock()
+            .unwrap()
+            .commands
+            .push(Box::new(Cmd { enable }));
+    }
+
+    /// Calls `vkCmdSetDepthWriteEnableEXT` on the builder.
+    #[inline]
+    pub unsafe fn set_depth_write_enable(&mut self, enable: bool) {
+        struct Cmd {
+            enable: bool,
+        }
+
+        impl<P> Command<P> for Cmd {
+            fn name(&self) -> &'static str {
+                "set_depth_write_enable"
+            }
+
+            unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
+                out.set_depth_write_enable(self.enable);
+            }
+
+            fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
+                Box::new(())
+            }
+        }
+
+        self.commands
+            .lock()
+            .unwrap()
+            .commands
+            .push(Box::new(Cmd { enable }));
+    }
+
+    /// Calls `vkCmdSetEvent` on the builder.
     #[inline]
-    pub unsafe fn reset_event(&mut self, event: Arc<Event>, stages: PipelineStages) {
+    pub unsafe fn set_event(&mut self, event: Arc<Event>, stages: PipelineStages) {
         struct Cmd {
             event: Arc<Event>,
             stages: PipelineStages,
         }
 
         impl<P> Command<P> for Cmd {
+            fn name(&self) -> &'static str {
+                "set_event"
+            }
+
             unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
-                out.reset_event(&self.event, self.stages);
+                out.set_event(&self.event, self.stages);
             }
 
             fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
@@ -1439,16 +2910,72 @@ impl<P> SyncCommandBufferBuilder<P> {
             .push(Box::new(Cmd { event, stages }));
     }
 
-    /// Calls `vkCmdSetBlendConstants` on the builder.
+    /// Sets all the dynamic state that is required in order to draw with shader objects.
+    ///
+    /// This calls the various `vkCmdSet*EXT` functions on the builder.
     #[inline]
-    pub unsafe fn set_blend_constants(&mut self, constants: [f32; 4]) {
+    pub unsafe fn set_shader_object_state(&mut self, state: ShaderObjectState) {
         struct Cmd {
-            constants: [f32; 4],
+            state: ShaderObjectState,
         }
 
         impl<P> Command<P> for Cmd {
+            fn name(&self) -> &'static str {
+                "set_shader_object_state"
+            }
+
             unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
-                out.set_blend_constants(self.constants);
+                let state = &self.state;
+                let features = out.device().enabled_features().clone();
+
+                out.set_vertex_input(state.vertex_bindings.iter().cloned(),
+                                     state.vertex_attributes.iter().cloned());
+                out.set_primitive_topology(state.input_assembly.topology);
+                out.set_primitive_restart_enable(state.input_assembly.primitive_restart_enable);
+                out.set_viewport_with_count(state.viewports.iter().cloned());
+                out.set_scissor_with_count(state.scissors.iter().cloned());
+
+                out.set_rasterizer_discard_enable(state.raster.rasterizer_discard);
+                if features.depth_clamp {
+                    out.set_depth_clamp_enable(state.raster.depth_clamp);
+                }
+                out.set_polygon_mode(state.raster.polygon_mode);
+                out.set_cull_mode(state.raster.cull_mode);
+                out.set_front_face(state.raster.front_face);
+                if let Some(line_width) = state.raster.line_width {
+                    out.set_line_width(line_width);
+                }
+                match state.raster.depth_bias {
+                    DepthBiasControl::Disabled => out.set_depth_bias_enable(false),
+                    DepthBiasControl::Dynamic => out.set_depth_bias_enable(true),
+                    DepthBiasControl::Static(bias) => {
+                        out.set_depth_bias_enable(true);
+                        out.set_depth_bias(bias.constant_factor, bias.clamp, bias.slope_factor);
+                    },
+                }
+
+                out.set_rasterization_samples(state.rasterization_samples);
+                out.set_sample_mask(state.rasterization_samples, state.sample_mask);
+                out.set_alpha_to_coverage_enable(state.alpha_to_coverage);
+                if features.alpha_to_one {
+                    out.set_alpha_to_one_enable(state.alpha_to_one);
+                }
+
+                out.set_depth_test_enable(state.depth_test_enabled());
+                out.set_depth_write_enable(state.depth_write);
+                out.set_depth_compare_op(state.depth_compare);
+                if features.depth_bounds {
+                    out.set_depth_bounds_test_enable(false);
+                }
+                out.set_stencil_test_enable(false);
+
+                if features.logic_op {
+                    out.set_logic_op_enable(false);
+                }
+                out.set_blend_constants(state.blend_constants);
+                out.set_color_blend_enable(0, state.attachments.iter().map(|a| a.enabled));
+                out.set_color_blend_equation(0, state.attachments.iter().cloned());
+                out.set_color_write_mask(0, state.attachments.iter().cloned());
             }
 
             fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
@@ -1460,21 +2987,23 @@ impl<P> SyncCommandBufferBuilder<P> {
             .lock()
             .unwrap()
             .commands
-            .push(Box::new(Cmd { constants }));
+            .push(Box::new(Cmd { state }));
     }
 
-    /// Calls `vkCmdSetDepthBias` on the builder.
+    /// Calls `vkCmdSetLineWidth` on the builder.
     #[inline]
-    pub unsafe fn set_depth_bias(&mut self, constant_factor: f32, clamp: f32, slope_factor: f32) {
+    pub unsafe fn set_line_width(&mut self, line_width: f32) {
         struct Cmd {
-            constant_factor: f32,
-            clamp: f32,
-            slope_factor: f32,
+            line_width: f32,
         }
 
         impl<P> Command<P> for Cmd {
+            fn name(&self) -> &'static str {
+                "set_line_width"
+            }
+
             unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
-                out.set_depth_bias(self.constant_factor, self.clamp, self.slope_factor);
+                out.set_line_width(self.line_width);
             }
 
             fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
@@ -1482,24 +3011,28 @@ impl<P> SyncCommandBufferBuilder<P> {
             }
         }
 
-        self.commands.lock().unwrap().commands.push(Box::new(Cmd {
-                                                                 constant_factor,
-                                                                 clamp,
-                                                                 slope_factor,
-                                                             }));
+        self.commands
+            .lock()
+            .unwrap()
+            .commands
+            .push(Box::new(Cmd { line_width }));
     }
 
-    /// Calls `vkCmdSetDepthBounds` on the builder.
+    /// Calls `vkCmdSetStencilCompareMask` on the builder.
     #[inline]
-    pub unsafe fn set_depth_bounds(&mut self, min: f32, max: f32) {
+    pub unsafe fn set_stencil_compare_mask(&mut self, face_mask: StencilFaceFlags, compare_mask: u32) {
         struct Cmd {
-            min: f32,
-            max: f32,
+            face_mask: StencilFaceFlags,
+            compare_mask: u32,
         }
 
         impl<P> Command<P> for Cmd {
+            fn name(&self) -> &'static str {
+                "set_stencil_compare_mask"
+            }
+
             unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
-                out.set_depth_bounds(self.min, self.max);
+                out.set_stencil_compare_mask(self.face_mask, self.compare_mask);
             }
 
             fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
@@ -1511,27 +3044,28 @@ impl<P> SyncCommandBufferBuilder<P> {
             .lock()
             .unwrap()
             .commands
-            .push(Box::new(Cmd { min, max }));
+            .push(Box::new(Cmd { face_mask, compare_mask }));
     }
 
-    /// Calls `vkCmdSetEvent` on the builder.
+    /// Calls `vkCmdSetStencilReference` on the builder.
     #[inline]
-    pub unsafe fn set_event(&mut self, event: Arc<Event>, stages: PipelineStages) {
+    pub unsafe fn set_stencil_reference(&mut self, face_mask: StencilFaceFlags, reference: u32) {
         struct Cmd {
-            event: Arc<Event>,
-            stages: PipelineStages,
+            face_mask: StencilFaceFlags,
+            reference: u32,
         }
 
         impl<P> Command<P> for Cmd {
+            fn name(&self) -> &'static str {
+                "set_stencil_reference"
+            }
+
             unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
-                out.set_event(&self.event, self.stages);
+                out.set_stencil_reference(self.face_mask, self.reference);
             }
 
             fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
-                struct Fin(Arc<Event>);
-                impl FinalCommand for Fin {
-                }
-                Box::new(Fin(self.event))
+                Box::new(())
             }
         }
 
@@ -1539,19 +3073,23 @@ impl<P> SyncCommandBufferBuilder<P> {
             .lock()
             .unwrap()
             .commands
-            .push(Box::new(Cmd { event, stages }));
+            .push(Box::new(Cmd { face_mask, reference }));
     }
 
-    /// Calls `vkCmdSetLineWidth` on the builder.
+    /// Calls `vkCmdSetStencilTestEnableEXT` on the builder.
     #[inline]
-    pub unsafe fn set_line_width(&mut self, line_width: f32) {
+    pub unsafe fn set_stencil_test_enable(&mut self, enable: bool) {
         struct Cmd {
-            line_width: f32,
+            enable: bool,
         }
 
         impl<P> Command<P> for Cmd {
+            fn name(&self) -> &'static str {
+                "set_stencil_test_enable"
+            }
+
             unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
-                out.set_line_width(self.line_width);
+                out.set_stencil_test_enable(self.enable);
             }
 
             fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
@@ -1563,10 +3101,37 @@ impl<P> SyncCommandBufferBuilder<P> {
             .lock()
             .unwrap()
             .commands
-            .push(Box::new(Cmd { line_width }));
+            .push(Box::new(Cmd { enable }));
     }
 
-    // TODO: stencil states
+    /// Calls `vkCmdSetStencilWriteMask` on the builder.
+    #[inline]
+    pub unsafe fn set_stencil_write_mask(&mut self, face_mask: StencilFaceFlags, write_mask: u32) {
+        struct Cmd {
+            face_mask: StencilFaceFlags,
+            write_mask: u32,
+        }
+
+        impl<P> Command<P> for Cmd {
+            fn name(&self) -> &'static str {
+                "set_stencil_write_mask"
+            }
+
+            unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
+                out.set_stencil_write_mask(self.face_mask, self.write_mask);
+            }
+
+            fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
+                Box::new(())
+            }
+        }
+
+        self.commands
+            .lock()
+            .unwrap()
+            .commands
+            .push(Box::new(Cmd { face_mask, write_mask }));
+    }
 
     /// Calls `vkCmdSetScissor` on the builder.
     ///
@@ -1583,6 +3148,10 @@ impl<P> SyncCommandBufferBuilder<P> {
         impl<P, I> Command<P> for Cmd<I>
             where I: Iterator<Item = Scissor>
         {
+            fn name(&self) -> &'static str {
+                "set_scissor"
+            }
+
             unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
                 out.set_scissor(self.first_scissor, self.scissors.take().unwrap());
             }
@@ -1613,6 +3182,10 @@ impl<P> SyncCommandBufferBuilder<P> {
         impl<P, I> Command<P> for Cmd<I>
             where I: Iterator<Item = Viewport>
         {
+            fn name(&self) -> &'static str {
+                "set_viewport"
+            }
+
             unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
                 out.set_viewport(self.first_viewport, self.viewports.take().unwrap());
             }
@@ -1643,6 +3216,10 @@ impl<P> SyncCommandBufferBuilder<P> {
             where B: BufferAccess + Send + Sync + 'static,
                   D: Send + Sync + 'static
         {
+            fn name(&self) -> &'static str {
+                "update_buffer"
+            }
+
             unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
                 out.update_buffer(&self.buffer, &self.data);
             }
@@ -1652,6 +3229,10 @@ impl<P> SyncCommandBufferBuilder<P> {
                 impl<B> FinalCommand for Fin<B>
                     where B: BufferAccess + Send + Sync + 'static
                 {
+                    fn execution_only(&self) -> bool {
+                        true
+                    }
+
                     fn buffer(&self, num: usize) -> &BufferAccess {
                         assert_eq!(num, 0);
                         &self.0
@@ -1686,6 +3267,40 @@ impl<P> SyncCommandBufferBuilder<P> {
                                ImageLayout::Undefined)
             .unwrap();
     }
+
+    /// Calls `vkCmdWriteTimestamp` on the builder.
+    #[inline]
+    pub unsafe fn write_timestamp(&mut self, pool: Arc<UnsafeQueryPool>, query: u32,
+                                  stage: PipelineStages) {
+        struct Cmd {
+            pool: Arc<UnsafeQueryPool>,
+            query: u32,
+            stage: PipelineStages,
+        }
+
+        impl<P> Command<P> for Cmd {
+            fn name(&self) -> &'static str {
+                "write_timestamp"
+            }
+
+            unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
+                out.write_timestamp(&self.pool, self.query, self.stage);
+            }
+
+            fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
+                struct Fin(Arc<UnsafeQueryPool>);
+                impl FinalCommand for Fin {
+                }
+                Box::new(Fin(self.pool))
+            }
+        }
+
+        self.commands
+            .lock()
+            .unwrap()
+            .commands
+            .push(Box::new(Cmd { pool, query, stage }));
+    }
 }
 
 unsafe impl<P> DeviceOwned for SyncCommandBufferBuilder<P> {
@@ -1728,6 +3343,10 @@ impl<'b, P> SyncCommandBufferBuilderBindDescriptorSets<'b, P> {
             where Pl: PipelineLayoutAbstract,
                   I: Iterator<Item = u32>
         {
+            fn name(&self) -> &'static str {
+                "bind_descriptor_sets"
+            }
+
             unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
                 out.bind_descriptor_sets(self.graphics,
                                          &self.pipeline_layout,
@@ -1739,12 +3358,67 @@ impl<'b, P> SyncCommandBufferBuilderBindDescriptorSets<'b, P> {
             fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
                 struct Fin(SmallVec<[Box<DescriptorSet + Send + Sync>; 12]>);
                 impl FinalCommand for Fin {
-                    /* TODO: implement buffer() and image() */
+                    fn buffer(&self, num: usize) -> &BufferAccess {
+                        sets_buffer(&self.0, num).0
+                    }
+
+                    fn image(&self, num: usize) -> &ImageAccess {
+                        sets_image(&self.0, num).0
+                    }
                 }
                 Box::new(Fin(self.inner))
             }
+
+            fn buffer(&self, num: usize) -> &BufferAccess {
+                sets_buffer(&self.inner, num).0
+            }
+
+            fn image(&self, num: usize) -> &ImageAccess {
+                sets_image(&self.inner, num).0
+            }
+        }
+
+        // Returns the `num`th buffer of the sets, counting the buffers of all the sets one after
+        // the other.
+        fn sets_buffer(sets: &[Box<DescriptorSet + Send + Sync>], mut num: usize)
+                       -> (&BufferAccess, DescriptorResourceAccess) {
+            for set in sets {
+                if num < set.num_buffers() {
+                    return set.buffer(num).unwrap();
+                }
+                num -= set.num_buffers();
+            }
+            panic!()
+        }
+
+        // Same as `sets_buffer`, but for images.
+        fn sets_image(sets: &[Box<DescriptorSet + Send + Sync>], mut num: usize)
+                      -> (&ImageAccess, DescriptorResourceAccess) {
+            for set in sets {
+                if num < set.num_images() {
+                    return set.image(num).unwrap();
+                }
+                num -= set.num_images();
+            }
+            panic!()
+        }
+
+        // Binding zero descriptor sets is forbidden by the Vulkan specs.
+        if self.inner.is_empty() {
+            return Ok(());
         }
 
+        // The resources are registered with the stages of the shaders that can see their
+        // descriptor, instead of all the stages of the pipeline.
+        let buffers = (0 .. self.inner.len())
+            .flat_map(|s| (0 .. self.inner[s].num_buffers()).map(move |b| (s, b)))
+            .map(|(s, b)| self.inner[s].buffer(b).unwrap().1)
+            .collect::<Vec<_>>();
+        let images = (0 .. self.inner.len())
+            .flat_map(|s| (0 .. self.inner[s].num_images()).map(move |i| (s, i)))
+            .map(|(s, i)| self.inner[s].image(i).unwrap().1)
+            .collect::<Vec<_>>();
+
         self.builder
             .commands
             .lock()
@@ -1758,7 +3432,27 @@ impl<'b, P> SyncCommandBufferBuilderBindDescriptorSets<'b, P> {
                                dynamic_offsets: Some(dynamic_offsets),
                            }));
 
-        // FIXME: add resources
+        for (n, usage) in buffers.into_iter().enumerate() {
+            self.builder
+                .prev_cmd_resource(KeyTy::Buffer,
+                                   n,
+                                   usage.write,
+                                   usage.stages,
+                                   usage.access,
+                                   ImageLayout::Undefined,
+                                   ImageLayout::Undefined)?;
+        }
+
+        for (n, usage) in images.into_iter().enumerate() {
+            self.builder
+                .prev_cmd_resource(KeyTy::Image,
+                                   n,
+                                   usage.write,
+                                   usage.stages,
+                                   usage.access,
+                                   usage.layout,
+                                   usage.layout)?;
+        }
 
         Ok(())
     }
@@ -1790,6 +3484,10 @@ impl<'a, P> SyncCommandBufferBuilderBindVertexBuffer<'a, P> {
         }
 
         impl<P> Command<P> for Cmd {
+            fn name(&self) -> &'static str {
+                "bind_vertex_buffers"
+            }
+
             unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
                 out.bind_vertex_buffers(self.first_binding, self.inner.take().unwrap());
             }
@@ -1797,6 +3495,10 @@ impl<'a, P> SyncCommandBufferBuilderBindVertexBuffer<'a, P> {
             fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
                 struct Fin(Vec<Box<BufferAccess + Send + Sync>>);
                 impl FinalCommand for Fin {
+                    fn execution_only(&self) -> bool {
+                        true
+                    }
+
                     fn buffer(&self, num: usize) -> &BufferAccess {
                         &self.0[num]
                     }
@@ -1847,13 +3549,24 @@ pub struct SyncCommandBuffer<P> {
     // The actual Vulkan command buffer.
     inner: UnsafeCommandBuffer<P>,
 
-    // State of all the resources used by this command buffer.
-    resources: FnvHashMap<CbKey<'static>, ResourceFinalState>,
+    // State of all the resources used by this command buffer. Must always be locked before
+    // `commands` when both are needed.
+    resources: Mutex<FnvHashMap<CbKey<'static>, ResourceFinalState>>,
 
     // List of commands used by the command buffer. Used to hold the various resources that are
     // being used. Each element of `resources` has a copy of this `Arc`, but we need to keep one
     // here in case `resources` is empty.
     commands: Arc<Mutex<Vec<Box<FinalCommand + Send + Sync>>>>,
+
+    // Number of commands that were recorded, not counting the pipeline barriers.
+    num_commands: usize,
+
+    // Number of submissions that have locked the resources with `prepare_submit` and whose locks
+    // haven't been released with `unlock` yet.
+    pending_unlocks: AtomicUsize,
+
+    // Capture of the commands and pipeline barriers, if it was enabled on the builder.
+    capture: Option<String>,
 }
 
 // Usage of a resource in a finished command buffer.
@@ -1878,6 +3591,12 @@ struct ResourceFinalState {
 /// Equivalent to `Command`, but with less methods. Typically contains less things than the
 /// `Command` it comes from.
 trait FinalCommand {
+    // Returns true if the command only holds resources that must be kept alive while the
+    // command buffer is executing. Such commands are dropped by `release_execution_resources`.
+    fn execution_only(&self) -> bool {
+        false
+    }
+
     // Gives access to the `num`th buffer used by the command.
     fn buffer(&self, num: usize) -> &BufferAccess {
         panic!()
@@ -2076,9 +3795,10 @@ unsafe impl<P> CommandBuffer for SyncCommandBuffer<P> {
                       -> Result<(), CommandBufferExecError> {
         // TODO: if at any point we return an error, we can't recover
 
+        let resources = self.resources.lock().unwrap();
         let commands_lock = self.commands.lock().unwrap();
 
-        for (key, entry) in self.resources.iter() {
+        for (key, entry) in resources.iter() {
             let (commands, command_id, resource_ty, resource_index) = match *key {
                 CbKey::Command {
                     ref commands,
@@ -2137,16 +3857,24 @@ unsafe impl<P> CommandBuffer for SyncCommandBuffer<P> {
 
         // TODO: pipeline barriers if necessary?
 
+        self.pending_unlocks.fetch_add(1, Ordering::SeqCst);
         Ok(())
     }
 
+    #[inline]
+    unsafe fn unlock(&self) {
+        let prev = self.pending_unlocks.fetch_sub(1, Ordering::SeqCst);
+        debug_assert!(prev >= 1);
+        self.unlock_resources();
+    }
+
     #[inline]
     fn check_buffer_access(
         &self, buffer: &BufferAccess, exclusive: bool, queue: &Queue)
         -> Result<Option<(PipelineStages, AccessFlagBits)>, AccessCheckError> {
         // TODO: check the queue family
 
-        if let Some(value) = self.resources.get(&CbKey::BufferRef(buffer)) {
+        if let Some(value) = self.resources.lock().unwrap().get(&CbKey::BufferRef(buffer)) {
             if !value.exclusive && exclusive {
                 return Err(AccessCheckError::Denied(AccessError::ExclusiveDenied));
             }
@@ -2163,7 +3891,7 @@ unsafe impl<P> CommandBuffer for SyncCommandBuffer<P> {
                           -> Result<Option<(PipelineStages, AccessFlagBits)>, AccessCheckError> {
         // TODO: check the queue family
 
-        if let Some(value) = self.resources.get(&CbKey::ImageRef(image)) {
+        if let Some(value) = self.resources.lock().unwrap().get(&CbKey::ImageRef(image)) {
             if layout != ImageLayout::Undefined && value.final_layout != layout {
                 return Err(AccessCheckError::Denied(AccessError::UnexpectedImageLayout {
                                                         allowed: value.final_layout,
@@ -2189,37 +3917,216 @@ unsafe impl<P> DeviceOwned for SyncCommandBuffer<P> {
     }
 }
 
+impl<P> SyncCommandBuffer<P> {
+    /// Returns the capture of the command buffer, or `None` if `enable_capture` wasn't called on
+    /// the builder.
+    ///
+    /// The capture contains one line for each command that was sent to the Vulkan command
+    /// buffer, followed by the buffers and images that the command uses. Each pipeline barrier
+    /// that was inserted by the builder appears as a `pipeline_barrier` line with its source and
+    /// destination stages, followed by one indented line for each memory, buffer or image
+    /// barrier that it contains. Buffers and images are numbered in the order in which the
+    /// command buffer first uses them, so that the capture doesn't change from one run to the
+    /// next.
+    #[inline]
+    pub fn capture(&self) -> Option<&str> {
+        self.capture.as_ref().map(|c| &c[..])
+    }
+
+    /// Returns the number of commands that were recorded in the command buffer, not counting the
+    /// pipeline barriers that were inserted automatically.
+    #[inline]
+    pub fn recorded_command_count(&self) -> usize {
+        self.num_commands
+    }
+
+    /// Returns the number of distinct buffers and images that the command buffer uses.
+    ///
+    /// Resources released by `release_execution_resources` are no longer counted.
+    #[inline]
+    pub fn resource_reference_count(&self) -> usize {
+        self.resources.lock().unwrap().len()
+    }
+
+    /// Drops the buffers and images that only need to be kept alive while the command buffer is
+    /// executing, such as the source and destination of a copy, or the vertex buffers.
+    ///
+    /// Pipelines, framebuffers, descriptor sets and events are kept alive until the command
+    /// buffer is destroyed. The command buffer no longer tracks the accesses to the released
+    /// resources afterwards.
+    ///
+    /// # Safety
+    ///
+    /// - The command buffer must not be executing, and must never be submitted again.
+    ///
+    pub unsafe fn release_execution_resources(&self) {
+        debug_assert_eq!(self.pending_unlocks.load(Ordering::SeqCst), 0);
+
+        let mut resources = self.resources.lock().unwrap();
+        let mut commands_lock = self.commands.lock().unwrap();
+
+        let released = commands_lock
+            .iter()
+            .map(|command| command.execution_only())
+            .collect::<Vec<_>>();
+
+        // The keys of the resources that belong to the released commands must be removed first,
+        // as they refer to the commands by index.
+        resources.retain(|key, _| match *key {
+                             CbKey::Command { command_id, .. } => !released[command_id],
+                             _ => unreachable!(),
+                         });
+
+        for (command, released) in commands_lock.iter_mut().zip(released) {
+            if released {
+                *command = Box::new(());
+            }
+        }
+    }
+
+    // Releases the locks on the resources that were acquired by one call to `prepare_submit`.
+    unsafe fn unlock_resources(&self) {
+        let resources = self.resources.lock().unwrap();
+        let commands_lock = self.commands.lock().unwrap();
+
+        for (key, entry) in resources.iter() {
+            let (command_id, resource_ty, resource_index) = match *key {
+                CbKey::Command {
+                    command_id,
+                    resource_ty,
+                    resource_index,
+                    ..
+                } => {
+                    (command_id, resource_ty, resource_index)
+                },
+                _ => unreachable!(),
+            };
+
+            match resource_ty {
+                KeyTy::Buffer => {
+                    let cmd = &commands_lock[command_id];
+                    let buf = cmd.buffer(resource_index);
+                    buf.unlock();
+                },
+                KeyTy::Image => {
+                    let cmd = &commands_lock[command_id];
+                    let img = cmd.image(resource_index);
+                    img.unlock();
+                },
+            }
+        }
+    }
+}
+
 impl<P> Drop for SyncCommandBuffer<P> {
     fn drop(&mut self) {
-        unsafe {
-            let commands_lock = self.commands.lock().unwrap();
+        // Submissions whose end was never signaled with `unlock` still hold their locks.
+        for _ in 0 .. *self.pending_unlocks.get_mut() {
+            unsafe {
+                self.unlock_resources();
+            }
+        }
+    }
+}
 
-            for (key, entry) in self.resources.iter() {
-                let (command_id, resource_ty, resource_index) = match *key {
-                    CbKey::Command {
-                        command_id,
-                        resource_ty,
-                        resource_index,
-                        ..
-                    } => {
-                        (command_id, resource_ty, resource_index)
-                    },
-                    _ => unreachable!(),
-                };
+#[cfg(test)]
+mod tests {
+    use super::ImageLayouts;
+    use image::ImageLayout;
+
+    #[test]
+    fn image_layouts_single_level() {
+        let mut layouts = ImageLayouts::new(0 .. 4, 0 .. 1, ImageLayout::ShaderReadOnlyOptimal);
+        layouts.set(1 .. 2, 0 .. 1, ImageLayout::TransferDstOptimal);
+
+        assert_eq!(layouts.uniform(), None);
+        assert_eq!(layouts.ranges.len(), 3);
+
+        let used = layouts.get(0 .. 4, 0 .. 1);
+        assert_eq!(used.len(), 3);
+        for range in used {
+            if range.mipmaps == (1 .. 2) {
+                assert_eq!(range.layout, ImageLayout::TransferDstOptimal);
+            } else {
+                assert_eq!(range.layout, ImageLayout::ShaderReadOnlyOptimal);
+            }
+        }
 
-                match resource_ty {
-                    KeyTy::Buffer => {
-                        let cmd = &commands_lock[command_id];
-                        let buf = cmd.buffer(resource_index);
-                        buf.unlock();
-                    },
-                    KeyTy::Image => {
-                        let cmd = &commands_lock[command_id];
-                        let img = cmd.image(resource_index);
-                        img.unlock();
-                    },
+        let level = layouts.get(1 .. 2, 0 .. 1);
+        assert_eq!(level.len(), 1);
+        assert_eq!(level[0].layout, ImageLayout::TransferDstOptimal);
+    }
+
+    #[test]
+    fn image_layouts_interleaved_levels() {
+        // Simulates a mipmap generation chain, where each level is written as a transfer
+        // destination and then read as a transfer source for the next level.
+        let mut layouts = ImageLayouts::new(0 .. 5, 0 .. 2, ImageLayout::TransferDstOptimal);
+
+        for level in 0 .. 4 {
+            layouts.set(level .. level + 1, 0 .. 2, ImageLayout::TransferSrcOptimal);
+
+            let src = layouts.get(0 .. level + 1, 0 .. 2);
+            assert!(src.iter().all(|r| r.layout == ImageLayout::TransferSrcOptimal));
+            let dest = layouts.get(level + 1 .. 5, 0 .. 2);
+            assert!(dest.iter().all(|r| r.layout == ImageLayout::TransferDstOptimal));
+
+            // Neighbouring levels in the same layout are merged.
+            assert_eq!(layouts.ranges.len(), 2);
+        }
+
+        // The levels converge to the same layout.
+        layouts.set(4 .. 5, 0 .. 2, ImageLayout::TransferSrcOptimal);
+        assert_eq!(layouts.uniform(), Some(ImageLayout::TransferSrcOptimal));
+        assert_eq!(layouts.ranges.len(), 1);
+        assert_eq!(layouts.ranges[0].mipmaps, 0 .. 5);
+        assert_eq!(layouts.ranges[0].layers, 0 .. 2);
+    }
+
+    #[test]
+    fn image_layouts_levels_and_layers() {
+        let mut layouts = ImageLayouts::new(0 .. 3, 0 .. 3, ImageLayout::General);
+
+        // Checkerboard of levels and layers.
+        for level in 0 .. 3 {
+            for layer in 0 .. 3 {
+                if (level + layer) % 2 == 0 {
+                    layouts.set(level .. level + 1, layer .. layer + 1,
+                                ImageLayout::TransferDstOptimal);
                 }
             }
         }
+
+        for level in 0 .. 3 {
+            for layer in 0 .. 3 {
+                let ranges = layouts.get(level .. level + 1, layer .. layer + 1);
+                assert_eq!(ranges.len(), 1);
+                let expected = if (level + layer) % 2 == 0 {
+                    ImageLayout::TransferDstOptimal
+                } else {
+                    ImageLayout::General
+                };
+                assert_eq!(ranges[0].layout, expected);
+            }
+        }
+
+        // Covering a range that overlaps multiple rectangles.
+        layouts.set(0 .. 2, 1 .. 3, ImageLayout::ShaderReadOnlyOptimal);
+        assert!(layouts
+                    .get(0 .. 2, 1 .. 3)
+                    .iter()
+                    .all(|r| r.layout == ImageLayout::ShaderReadOnlyOptimal));
+        assert_eq!(layouts.get(2 .. 3, 0 .. 1)[0].layout, ImageLayout::TransferDstOptimal);
+
+        let total: u32 = layouts
+            .ranges
+            .iter()
+            .map(|r| (r.mipmaps.end - r.mipmaps.start) * (r.layers.end - r.layers.start))
+            .sum();
+        assert_eq!(total, 9);
+
+        layouts.set(0 .. 3, 0 .. 3, ImageLayout::General);
+        assert_eq!(layouts.uniform(), Some(ImageLayout::General));
+        assert_eq!(layouts.ranges.len(), 1);
     }
 }
@@ -8,6 +8,8 @@
 // according to those terms.
 
 use smallvec::SmallVec;
+use std::cmp;
+use std::ffi::CStr;
 use std::fmt;
 use std::mem;
 use std::ops::Range;
@@ -21,6 +23,7 @@ use buffer::BufferAccess;
 use buffer::BufferInner;
 use check_errors;
 use command_buffer::CommandBuffer;
+use command_buffer::indirect_commands::IndirectCommandsLayout;
 use command_buffer::pool::CommandPool;
 use command_buffer::pool::CommandPoolAlloc;
 use command_buffer::pool::CommandPoolBuilderAlloc;
@@ -39,12 +42,25 @@ use framebuffer::Subpass;
 use framebuffer::SubpassContents;
 use image::ImageAccess;
 use image::ImageLayout;
+use image::sys::UnsafeImageView;
 use instance::QueueFamily;
 use pipeline::ComputePipelineAbstract;
 use pipeline::GraphicsPipelineAbstract;
+use pipeline::blend::AttachmentBlend;
+use pipeline::depth_stencil::Compare;
+use pipeline::depth_stencil::StencilFaceFlags;
 use pipeline::input_assembly::IndexType;
+use pipeline::input_assembly::PrimitiveTopology;
+use pipeline::raster::CullMode;
+use pipeline::raster::FrontFace;
+use pipeline::raster::PolygonMode;
+use pipeline::shader_object::ShaderObject;
+use pipeline::shader_object::ShaderObjectStage;
+use pipeline::shader_object::VertexInputAttribute;
+use pipeline::shader_object::VertexInputBinding;
 use pipeline::viewport::Scissor;
 use pipeline::viewport::Viewport;
+use query::UnsafeQueryPool;
 use sync::AccessFlagBits;
 use sync::Event;
 use sync::PipelineStages;
@@ -254,7 +270,7 @@ impl<P> UnsafeCommandBufferBuilder<P> {
             pInheritanceInfo: &inheritance,
         };
 
-        check_errors(vk.BeginCommandBuffer(cmd, &infos))?;
+        check_errors(vk.BeginCommandBuffer(cmd, &infos), "vkBeginCommandBuffer")?;
 
         Ok(UnsafeCommandBufferBuilder {
                cmd: Some(alloc),
@@ -280,7 +296,8 @@ impl<P> UnsafeCommandBufferBuilder<P> {
         unsafe {
             let cmd = self.cmd.take().unwrap();
             let vk = self.device.pointers();
-            check_errors(vk.EndCommandBuffer(cmd.inner().internal_object()))?;
+            check_errors(vk.EndCommandBuffer(cmd.inner().internal_object()),
+                         "vkEndCommandBuffer")?;
             let cmd_raw = cmd.inner().internal_object();
 
             Ok(UnsafeCommandBuffer {
@@ -293,12 +310,47 @@ impl<P> UnsafeCommandBufferBuilder<P> {
         }
     }
 
+    /// Calls `vkCmdBeginDebugUtilsLabelEXT` on the builder.
+    ///
+    /// The `ext_debug_utils` extension must be enabled on the instance.
+    #[inline]
+    pub unsafe fn begin_debug_utils_label(&mut self, name: &CStr, color: [f32; 4]) {
+        debug_assert!(self.device().instance().loaded_extensions().ext_debug_utils);
+        let info = vk::DebugUtilsLabelEXT {
+            sType: vk::STRUCTURE_TYPE_DEBUG_UTILS_LABEL_EXT,
+            pNext: ptr::null(),
+            pLabelName: name.as_ptr(),
+            color: color,
+        };
+
+        let vk = self.device().pointers();
+        let cmd = self.internal_object();
+        vk.CmdBeginDebugUtilsLabelEXT(cmd, &info);
+    }
+
     /// Calls `vkCmdBeginRenderPass` on the builder.
     #[inline]
     pub unsafe fn begin_render_pass<F, I>(&mut self, framebuffer: &F,
                                           subpass_contents: SubpassContents, clear_values: I)
         where F: ?Sized + FramebufferAbstract,
               I: Iterator<Item = ClearValue>
+    {
+        let dimensions = framebuffer.dimensions();
+        self.begin_render_pass_with_area(framebuffer,
+                                         subpass_contents,
+                                         clear_values,
+                                         [0, 0],
+                                         [dimensions[0], dimensions[1]])
+    }
+
+    /// Calls `vkCmdBeginRenderPass` on the builder, with a render area of `extent` pixels
+    /// starting at `offset`.
+    pub unsafe fn begin_render_pass_with_area<F, I>(&mut self, framebuffer: &F,
+                                                    subpass_contents: SubpassContents,
+                                                    clear_values: I, offset: [u32; 2],
+                                                    extent: [u32; 2])
+        where F: ?Sized + FramebufferAbstract,
+              I: Iterator<Item = ClearValue>
     {
         let vk = self.device().pointers();
         let cmd = self.internal_object();
@@ -342,12 +394,6 @@ impl<P> UnsafeCommandBufferBuilder<P> {
                  })
             .collect();
 
-        // TODO: allow customizing
-        let rect = [
-            0 .. framebuffer.dimensions()[0],
-            0 .. framebuffer.dimensions()[1],
-        ];
-
         let begin = vk::RenderPassBeginInfo {
             sType: vk::STRUCTURE_TYPE_RENDER_PASS_BEGIN_INFO,
             pNext: ptr::null(),
@@ -355,12 +401,12 @@ impl<P> UnsafeCommandBufferBuilder<P> {
             framebuffer: raw_framebuffer,
             renderArea: vk::Rect2D {
                 offset: vk::Offset2D {
-                    x: rect[0].start as i32,
-                    y: rect[1].start as i32,
+                    x: offset[0] as i32,
+                    y: offset[1] as i32,
                 },
                 extent: vk::Extent2D {
-                    width: rect[0].end - rect[0].start,
-                    height: rect[1].end - rect[1].start,
+                    width: extent[0],
+                    height: extent[1],
                 },
             },
             clearValueCount: raw_clear_values.len() as u32,
@@ -370,6 +416,81 @@ impl<P> UnsafeCommandBufferBuilder<P> {
         vk.CmdBeginRenderPass(cmd, &begin, subpass_contents as u32);
     }
 
+    /// Calls `vkCmdBeginRenderingKHR` on the builder, with a render area of `extent` pixels
+    /// starting at `offset`.
+    ///
+    /// Each color attachment is cleared with its clear value, or keeps its content if the clear
+    /// value is `ClearValue::None`. The content of the attachments is always stored.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if one of the clear values is a depth or stencil value.
+    ///
+    pub unsafe fn begin_rendering<'a, I>(&mut self, color_attachments: I, offset: [u32; 2],
+                                         extent: [u32; 2])
+        where I: Iterator<Item = (&'a UnsafeImageView, ImageLayout, ClearValue)>
+    {
+        let vk = self.device().pointers();
+        let cmd = self.internal_object();
+
+        let attachments: SmallVec<[_; 8]> = color_attachments
+            .map(|(view, layout, clear_value)| {
+                let (load_op, clear_value) = match clear_value {
+                    ClearValue::None => {
+                        (vk::ATTACHMENT_LOAD_OP_LOAD, vk::ClearColorValue::float32([0.0; 4]))
+                    },
+                    ClearValue::Float(val) => {
+                        (vk::ATTACHMENT_LOAD_OP_CLEAR, vk::ClearColorValue::float32(val))
+                    },
+                    ClearValue::Int(val) => {
+                        (vk::ATTACHMENT_LOAD_OP_CLEAR, vk::ClearColorValue::int32(val))
+                    },
+                    ClearValue::Uint(val) => {
+                        (vk::ATTACHMENT_LOAD_OP_CLEAR, vk::ClearColorValue::uint32(val))
+                    },
+                    _ => panic!("depth or stencil clear value for a color attachment"),
+                };
+
+                vk::RenderingAttachmentInfoKHR {
+                    sType: vk::STRUCTURE_TYPE_RENDERING_ATTACHMENT_INFO_KHR,
+                    pNext: ptr::null(),
+                    imageView: view.internal_object(),
+                    imageLayout: layout as u32,
+                    resolveMode: vk::RESOLVE_MODE_NONE_KHR,
+                    resolveImageView: 0,
+                    resolveImageLayout: vk::IMAGE_LAYOUT_UNDEFINED,
+                    loadOp: load_op,
+                    storeOp: vk::ATTACHMENT_STORE_OP_STORE,
+                    clearValue: vk::ClearValue::color(clear_value),
+                }
+            })
+            .collect();
+
+        let infos = vk::RenderingInfoKHR {
+            sType: vk::STRUCTURE_TYPE_RENDERING_INFO_KHR,
+            pNext: ptr::null(),
+            flags: 0,
+            renderArea: vk::Rect2D {
+                offset: vk::Offset2D {
+                    x: offset[0] as i32,
+                    y: offset[1] as i32,
+                },
+                extent: vk::Extent2D {
+                    width: extent[0],
+                    height: extent[1],
+                },
+            },
+            layerCount: 1,
+            viewMask: 0,
+            colorAttachmentCount: attachments.len() as u32,
+            pColorAttachments: attachments.as_ptr(),
+            pDepthAttachment: ptr::null(),
+            pStencilAttachment: ptr::null(),
+        };
+
+        vk.CmdBeginRenderingKHR(cmd, &infos);
+    }
+
     /// Calls `vkCmdBindDescriptorSets` on the builder.
     ///
     /// Does nothing if the list of descriptor sets is empty, as it would be a no-op and isn't a
@@ -451,6 +572,26 @@ impl<P> UnsafeCommandBufferBuilder<P> {
         vk.CmdBindPipeline(cmd, vk::PIPELINE_BIND_POINT_GRAPHICS, inner);
     }
 
+    /// Calls `vkCmdBindShadersEXT` on the builder.
+    ///
+    /// Does nothing if the list of stages is empty.
+    #[inline]
+    pub unsafe fn bind_shaders(&mut self, params: &UnsafeCommandBufferBuilderBindShaders) {
+        debug_assert_eq!(params.stages.len(), params.shaders.len());
+        debug_assert!(self.device().loaded_extensions().ext_shader_object);
+
+        if params.stages.is_empty() {
+            return;
+        }
+
+        let vk = self.device().pointers();
+        let cmd = self.internal_object();
+        vk.CmdBindShadersEXT(cmd,
+                             params.stages.len() as u32,
+                             params.stages.as_ptr(),
+                             params.shaders.as_ptr());
+    }
+
     /// Calls `vkCmdBindVertexBuffers` on the builder.
     ///
     /// Does nothing if the list of buffers is empty, as it would be a no-op and isn't a valid
@@ -614,6 +755,70 @@ impl<P> UnsafeCommandBufferBuilder<P> {
                                 regions.as_ptr());
     }
 
+    /// Calls `vkCmdCopyImageToBuffer` on the builder.
+    ///
+    /// Does nothing if the list of regions is empty, as it would be a no-op and isn't a valid
+    /// usage of the command anyway.
+    #[inline]
+    pub unsafe fn copy_image_to_buffer<S, D, R>(&mut self, source: &S, source_layout: ImageLayout,
+                                                destination: &D, regions: R)
+        where S: ?Sized + ImageAccess,
+              D: ?Sized + BufferAccess,
+              R: Iterator<Item = UnsafeCommandBufferBuilderBufferImageCopy>
+    {
+        debug_assert_eq!(source.samples(), 1);
+        let source = source.inner();
+        debug_assert!(source.image.usage_transfer_src());
+        debug_assert!(source_layout == ImageLayout::General ||
+                      source_layout == ImageLayout::TransferSrcOptimal);
+
+        let destination = destination.inner();
+        debug_assert!(destination.offset < destination.buffer.size());
+        debug_assert!(destination.buffer.usage_transfer_dest());
+
+        let regions: SmallVec<[_; 8]> = regions
+            .map(|copy| {
+                debug_assert!(copy.image_layer_count <= source.num_layers as u32);
+                debug_assert!(copy.image_mip_level < source.num_mipmap_levels as u32);
+
+                vk::BufferImageCopy {
+                    bufferOffset: (destination.offset + copy.buffer_offset) as vk::DeviceSize,
+                    bufferRowLength: copy.buffer_row_length,
+                    bufferImageHeight: copy.buffer_image_height,
+                    imageSubresource: vk::ImageSubresourceLayers {
+                        aspectMask: copy.image_aspect.to_vk_bits(),
+                        mipLevel: copy.image_mip_level + source.first_mipmap_level as u32,
+                        baseArrayLayer: copy.image_base_array_layer + source.first_layer as u32,
+                        layerCount: copy.image_layer_count,
+                    },
+                    imageOffset: vk::Offset3D {
+                        x: copy.image_offset[0],
+                        y: copy.image_offset[1],
+                        z: copy.image_offset[2],
+                    },
+                    imageExtent: vk::Extent3D {
+                        width: copy.image_extent[0],
+                        height: copy.image_extent[1],
+                        depth: copy.image_extent[2],
+                    },
+                }
+            })
+            .collect();
+
+        if regions.is_empty() {
+            return;
+        }
+
+        let vk = self.device().pointers();
+        let cmd = self.internal_object();
+        vk.CmdCopyImageToBuffer(cmd,
+                                source.image.internal_object(),
+                                source_layout as u32,
+                                destination.buffer.internal_object(),
+                                regions.len() as u32,
+                                regions.as_ptr());
+    }
+
     /// Calls `vkCmdDispatch` on the builder.
     #[inline]
     pub unsafe fn dispatch(&mut self, dimensions: [u32; 3]) {
@@ -699,6 +904,60 @@ impl<P> UnsafeCommandBufferBuilder<P> {
                            stride);
     }
 
+    /// Calls `vkCmdExecuteGeneratedCommandsNV` on the builder.
+    ///
+    /// The commands are preprocessed as part of the execution, using `preprocess` as scratch
+    /// memory. The pipeline must be bound beforehand.
+    #[inline]
+    pub unsafe fn execute_generated_commands<Gp, L>(&mut self, pipeline: &Gp,
+                                                    layout: &IndirectCommandsLayout<L>,
+                                                    streams: &[&BufferAccess],
+                                                    sequences_count: u32,
+                                                    preprocess: &BufferAccess)
+        where Gp: ?Sized + GraphicsPipelineAbstract
+    {
+        debug_assert!(self.device().loaded_extensions().nv_device_generated_commands);
+        debug_assert_eq!(streams.len(), layout.stream_strides().len());
+
+        let vk = self.device().pointers();
+        let cmd = self.internal_object();
+
+        let streams = streams
+            .iter()
+            .map(|stream| {
+                let inner = stream.inner();
+                debug_assert!(inner.buffer.usage_indirect_buffer());
+                vk::IndirectCommandsStreamNV {
+                    buffer: inner.buffer.internal_object(),
+                    offset: inner.offset as vk::DeviceSize,
+                }
+            })
+            .collect::<SmallVec<[_; 4]>>();
+
+        let preprocess_inner = preprocess.inner();
+        debug_assert!(preprocess_inner.buffer.usage_indirect_buffer());
+
+        let infos = vk::GeneratedCommandsInfoNV {
+            sType: vk::STRUCTURE_TYPE_GENERATED_COMMANDS_INFO_NV,
+            pNext: ptr::null(),
+            pipelineBindPoint: vk::PIPELINE_BIND_POINT_GRAPHICS,
+            pipeline: GraphicsPipelineAbstract::inner(pipeline).internal_object(),
+            indirectCommandsLayout: layout.internal_object(),
+            streamCount: streams.len() as u32,
+            pStreams: streams.as_ptr(),
+            sequencesCount: sequences_count,
+            preprocessBuffer: preprocess_inner.buffer.internal_object(),
+            preprocessOffset: preprocess_inner.offset as vk::DeviceSize,
+            preprocessSize: preprocess.size() as vk::DeviceSize,
+            sequencesCountBuffer: 0,
+            sequencesCountOffset: 0,
+            sequencesIndexBuffer: 0,
+            sequencesIndexOffset: 0,
+        };
+
+        vk.CmdExecuteGeneratedCommandsNV(cmd, vk::FALSE, &infos);
+    }
+
     /// Calls `vkCmdDrawIndexedIndirect` on the builder.
     #[inline]
     pub unsafe fn draw_indexed_indirect<B>(&mut self, buffer: &B, draw_count: u32, stride: u32)
@@ -718,6 +977,17 @@ impl<P> UnsafeCommandBufferBuilder<P> {
                                   stride);
     }
 
+    /// Calls `vkCmdEndDebugUtilsLabelEXT` on the builder.
+    ///
+    /// The `ext_debug_utils` extension must be enabled on the instance.
+    #[inline]
+    pub unsafe fn end_debug_utils_label(&mut self) {
+        debug_assert!(self.device().instance().loaded_extensions().ext_debug_utils);
+        let vk = self.device().pointers();
+        let cmd = self.internal_object();
+        vk.CmdEndDebugUtilsLabelEXT(cmd);
+    }
+
     /// Calls `vkCmdEndRenderPass` on the builder.
     #[inline]
     pub unsafe fn end_render_pass(&mut self) {
@@ -726,6 +996,14 @@ impl<P> UnsafeCommandBufferBuilder<P> {
         vk.CmdEndRenderPass(cmd);
     }
 
+    /// Calls `vkCmdEndRenderingKHR` on the builder.
+    #[inline]
+    pub unsafe fn end_rendering(&mut self) {
+        let vk = self.device().pointers();
+        let cmd = self.internal_object();
+        vk.CmdEndRenderingKHR(cmd);
+    }
+
     /// Calls `vkCmdExecuteCommands` on the builder.
     ///
     /// Does nothing if the list of command buffers is empty, as it would be a no-op and isn't a
@@ -768,6 +1046,24 @@ impl<P> UnsafeCommandBufferBuilder<P> {
                          data);
     }
 
+    /// Calls `vkCmdInsertDebugUtilsLabelEXT` on the builder.
+    ///
+    /// The `ext_debug_utils` extension must be enabled on the instance.
+    #[inline]
+    pub unsafe fn insert_debug_utils_label(&mut self, name: &CStr, color: [f32; 4]) {
+        debug_assert!(self.device().instance().loaded_extensions().ext_debug_utils);
+        let info = vk::DebugUtilsLabelEXT {
+            sType: vk::STRUCTURE_TYPE_DEBUG_UTILS_LABEL_EXT,
+            pNext: ptr::null(),
+            pLabelName: name.as_ptr(),
+            color: color,
+        };
+
+        let vk = self.device().pointers();
+        let cmd = self.internal_object();
+        vk.CmdInsertDebugUtilsLabelEXT(cmd, &info);
+    }
+
     /// Calls `vkCmdNextSubpass` on the builder.
     #[inline]
     pub unsafe fn next_subpass(&mut self, subpass_contents: SubpassContents) {
@@ -841,6 +1137,37 @@ impl<P> UnsafeCommandBufferBuilder<P> {
         vk.CmdResetEvent(cmd, event.internal_object(), stages.into());
     }
 
+    /// Calls `vkCmdResetQueryPool` on the builder.
+    #[inline]
+    pub unsafe fn reset_query_pool(&mut self, pool: &UnsafeQueryPool, queries: Range<u32>) {
+        let vk = self.device().pointers();
+        let cmd = self.internal_object();
+
+        debug_assert!(queries.start <= queries.end);
+        debug_assert!(queries.end <= pool.num_slots());
+
+        vk.CmdResetQueryPool(cmd,
+                             pool.internal_object(),
+                             queries.start,
+                             queries.end - queries.start);
+    }
+
+    /// Calls `vkCmdSetAlphaToCoverageEnableEXT` on the builder.
+    #[inline]
+    pub unsafe fn set_alpha_to_coverage_enable(&mut self, enable: bool) {
+        let vk = self.device().pointers();
+        let cmd = self.internal_object();
+        vk.CmdSetAlphaToCoverageEnableEXT(cmd, if enable { vk::TRUE } else { vk::FALSE });
+    }
+
+    /// Calls `vkCmdSetAlphaToOneEnableEXT` on the builder.
+    #[inline]
+    pub unsafe fn set_alpha_to_one_enable(&mut self, enable: bool) {
+        let vk = self.device().pointers();
+        let cmd = self.internal_object();
+        vk.CmdSetAlphaToOneEnableEXT(cmd, if enable { vk::TRUE } else { vk::FALSE });
+    }
+
     /// Calls `vkCmdSetBlendConstants` on the builder.
     #[inline]
     pub unsafe fn set_blend_constants(&mut self, constants: [f32; 4]) {
@@ -849,6 +1176,88 @@ impl<P> UnsafeCommandBufferBuilder<P> {
         vk.CmdSetBlendConstants(cmd, constants); // TODO: correct to pass array?
     }
 
+    /// Calls `vkCmdSetColorBlendEnableEXT` on the builder.
+    ///
+    /// If the list is empty then the command is automatically ignored.
+    #[inline]
+    pub unsafe fn set_color_blend_enable<I>(&mut self, first_attachment: u32, enables: I)
+        where I: Iterator<Item = bool>
+    {
+        let enables = enables
+            .map(|e| if e { vk::TRUE } else { vk::FALSE })
+            .collect::<SmallVec<[_; 8]>>();
+        if enables.is_empty() {
+            return;
+        }
+
+        let vk = self.device().pointers();
+        let cmd = self.internal_object();
+        vk.CmdSetColorBlendEnableEXT(cmd, first_attachment, enables.len() as u32,
+                                     enables.as_ptr());
+    }
+
+    /// Calls `vkCmdSetColorBlendEquationEXT` on the builder. Only the blend factors and
+    /// operations of each `AttachmentBlend` are used.
+    ///
+    /// If the list is empty then the command is automatically ignored.
+    #[inline]
+    pub unsafe fn set_color_blend_equation<I>(&mut self, first_attachment: u32, attachments: I)
+        where I: Iterator<Item = AttachmentBlend>
+    {
+        let equations = attachments
+            .map(|a| {
+                let state: vk::PipelineColorBlendAttachmentState = a.into();
+                vk::ColorBlendEquationEXT {
+                    srcColorBlendFactor: state.srcColorBlendFactor,
+                    dstColorBlendFactor: state.dstColorBlendFactor,
+                    colorBlendOp: state.colorBlendOp,
+                    srcAlphaBlendFactor: state.srcAlphaBlendFactor,
+                    dstAlphaBlendFactor: state.dstAlphaBlendFactor,
+                    alphaBlendOp: state.alphaBlendOp,
+                }
+            })
+            .collect::<SmallVec<[_; 8]>>();
+        if equations.is_empty() {
+            return;
+        }
+
+        let vk = self.device().pointers();
+        let cmd = self.internal_object();
+        vk.CmdSetColorBlendEquationEXT(cmd, first_attachment, equations.len() as u32,
+                                       equations.as_ptr());
+    }
+
+    /// Calls `vkCmdSetColorWriteMaskEXT` on the builder. Only the masks of each
+    /// `AttachmentBlend` are used.
+    ///
+    /// If the list is empty then the command is automatically ignored.
+    #[inline]
+    pub unsafe fn set_color_write_mask<I>(&mut self, first_attachment: u32, attachments: I)
+        where I: Iterator<Item = AttachmentBlend>
+    {
+        let masks = attachments
+            .map(|a| {
+                let state: vk::PipelineColorBlendAttachmentState = a.into();
+                state.colorWriteMask
+            })
+            .collect::<SmallVec<[_; 8]>>();
+        if masks.is_empty() {
+            return;
+        }
+
+        let vk = self.device().pointers();
+        let cmd = self.internal_object();
+        vk.CmdSetColorWriteMaskEXT(cmd, first_attachment, masks.len() as u32, masks.as_ptr());
+    }
+
+    /// Calls `vkCmdSetCullModeEXT` on the builder.
+    #[inline]
+    pub unsafe fn set_cull_mode(&mut self, cull_mode: CullMode) {
+        let vk = self.device().pointers();
+        let cmd = self.internal_object();
+        vk.CmdSetCullModeEXT(cmd, cull_mode as u32);
+    }
+
     /// Calls `vkCmdSetDepthBias` on the builder.
     #[inline]
     pub unsafe fn set_depth_bias(&mut self, constant_factor: f32, clamp: f32, slope_factor: f32) {
@@ -858,6 +1267,14 @@ impl<P> UnsafeCommandBufferBuilder<P> {
         vk.CmdSetDepthBias(cmd, constant_factor, clamp, slope_factor);
     }
 
+    /// Calls `vkCmdSetDepthBiasEnableEXT` on the builder.
+    #[inline]
+    pub unsafe fn set_depth_bias_enable(&mut self, enable: bool) {
+        let vk = self.device().pointers();
+        let cmd = self.internal_object();
+        vk.CmdSetDepthBiasEnableEXT(cmd, if enable { vk::TRUE } else { vk::FALSE });
+    }
+
     /// Calls `vkCmdSetDepthBounds` on the builder.
     #[inline]
     pub unsafe fn set_depth_bounds(&mut self, min: f32, max: f32) {
@@ -868,6 +1285,48 @@ impl<P> UnsafeCommandBufferBuilder<P> {
         vk.CmdSetDepthBounds(cmd, min, max);
     }
 
+    /// Calls `vkCmdSetDepthBoundsTestEnableEXT` on the builder.
+    #[inline]
+    pub unsafe fn set_depth_bounds_test_enable(&mut self, enable: bool) {
+        let vk = self.device().pointers();
+        let cmd = self.internal_object();
+        debug_assert!(!enable || self.device().enabled_features().depth_bounds);
+        vk.CmdSetDepthBoundsTestEnableEXT(cmd, if enable { vk::TRUE } else { vk::FALSE });
+    }
+
+    /// Calls `vkCmdSetDepthClampEnableEXT` on the builder.
+    #[inline]
+    pub unsafe fn set_depth_clamp_enable(&mut self, enable: bool) {
+        let vk = self.device().pointers();
+        let cmd = self.internal_object();
+        debug_assert!(!enable || self.device().enabled_features().depth_clamp);
+        vk.CmdSetDepthClampEnableEXT(cmd, if enable { vk::TRUE } else { vk::FALSE });
+    }
+
+    /// Calls `vkCmdSetDepthCompareOpEXT` on the builder.
+    #[inline]
+    pub unsafe fn set_depth_compare_op(&mut self, compare: Compare) {
+        let vk = self.device().pointers();
+        let cmd = self.internal_object();
+        vk.CmdSetDepthCompareOpEXT(cmd, compare as u32);
+    }
+
+    /// Calls `vkCmdSetDepthTestEnableEXT` on the builder.
+    #[inline]
+    pub unsafe fn set_depth_test_enable(&mut self, enable: bool) {
+        let vk = self.device().pointers();
+        let cmd = self.internal_object();
+        vk.CmdSetDepthTestEnableEXT(cmd, if enable { vk::TRUE } else { vk::FALSE });
+    }
+
+    /// Calls `vkCmdSetDepthWriteEnableEXT` on the builder.
+    #[inline]
+    pub unsafe fn set_depth_write_enable(&mut self, enable: bool) {
+        let vk = self.device().pointers();
+        let cmd = self.internal_object();
+        vk.CmdSetDepthWriteEnableEXT(cmd, if enable { vk::TRUE } else { vk::FALSE });
+    }
+
     /// Calls `vkCmdSetEvent` on the builder.
     #[inline]
     pub unsafe fn set_event(&mut self, event: &Event, stages: PipelineStages) {
@@ -880,6 +1339,14 @@ impl<P> UnsafeCommandBufferBuilder<P> {
         vk.CmdSetEvent(cmd, event.internal_object(), stages.into());
     }
 
+    /// Calls `vkCmdSetFrontFaceEXT` on the builder.
+    #[inline]
+    pub unsafe fn set_front_face(&mut self, front_face: FrontFace) {
+        let vk = self.device().pointers();
+        let cmd = self.internal_object();
+        vk.CmdSetFrontFaceEXT(cmd, front_face as u32);
+    }
+
     /// Calls `vkCmdSetLineWidth` on the builder.
     #[inline]
     pub unsafe fn set_line_width(&mut self, line_width: f32) {
@@ -889,32 +1356,99 @@ impl<P> UnsafeCommandBufferBuilder<P> {
         vk.CmdSetLineWidth(cmd, line_width);
     }
 
-    // TODO: missing enum for face_mask
-    /*/// Calls `vkCmdSetStencilCompareMask` on the builder.
+    /// Calls `vkCmdSetLogicOpEnableEXT` on the builder.
     #[inline]
-    pub unsafe fn set_stencil_compare_mask(&mut self, face_mask: , compare_mask: u32) {
+    pub unsafe fn set_logic_op_enable(&mut self, enable: bool) {
         let vk = self.device().pointers();
         let cmd = self.internal_object();
-        vk.CmdSetStencilCompareMask(cmd, face_mask, compare_mask);
-    }*/
+        vk.CmdSetLogicOpEnableEXT(cmd, if enable { vk::TRUE } else { vk::FALSE });
+    }
 
-    // TODO: missing enum for face_mask
-    /*/// Calls `vkCmdSetStencilReference` on the builder.
+    /// Calls `vkCmdSetPolygonModeEXT` on the builder.
     #[inline]
-    pub unsafe fn set_stencil_reference(&mut self, face_mask: , reference: u32) {
+    pub unsafe fn set_polygon_mode(&mut self, polygon_mode: PolygonMode) {
         let vk = self.device().pointers();
         let cmd = self.internal_object();
-        vk.CmdSetStencilReference(cmd, face_mask, reference);
-    }*/
+        debug_assert!(polygon_mode == PolygonMode::Fill ||
+                          self.device().enabled_features().fill_mode_non_solid);
+        vk.CmdSetPolygonModeEXT(cmd, polygon_mode as u32);
+    }
 
-    // TODO: missing enum for face_mask
-    /*/// Calls `vkCmdSetStencilWriteMask` on the builder.
+    /// Calls `vkCmdSetPrimitiveRestartEnableEXT` on the builder.
     #[inline]
-    pub unsafe fn set_stencil_write_mask(&mut self, face_mask: , write_mask: u32) {
+    pub unsafe fn set_primitive_restart_enable(&mut self, enable: bool) {
         let vk = self.device().pointers();
         let cmd = self.internal_object();
-        vk.CmdSetStencilWriteMask(cmd, face_mask, write_mask);
-    }*/
+        vk.CmdSetPrimitiveRestartEnableEXT(cmd, if enable { vk::TRUE } else { vk::FALSE });
+    }
+
+    /// Calls `vkCmdSetPrimitiveTopologyEXT` on the builder.
+    // TODO: the number of control points of patch lists isn't set
+    #[inline]
+    pub unsafe fn set_primitive_topology(&mut self, topology: PrimitiveTopology) {
+        let vk = self.device().pointers();
+        let cmd = self.internal_object();
+        vk.CmdSetPrimitiveTopologyEXT(cmd, topology.into());
+    }
+
+    /// Calls `vkCmdSetRasterizationSamplesEXT` on the builder.
+    #[inline]
+    pub unsafe fn set_rasterization_samples(&mut self, samples: u32) {
+        let vk = self.device().pointers();
+        let cmd = self.internal_object();
+        debug_assert!(samples.is_power_of_two() && samples <= 64);
+        vk.CmdSetRasterizationSamplesEXT(cmd, samples);
+    }
+
+    /// Calls `vkCmdSetRasterizerDiscardEnableEXT` on the builder.
+    #[inline]
+    pub unsafe fn set_rasterizer_discard_enable(&mut self, enable: bool) {
+        let vk = self.device().pointers();
+        let cmd = self.internal_object();
+        vk.CmdSetRasterizerDiscardEnableEXT(cmd, if enable { vk::TRUE } else { vk::FALSE });
+    }
+
+    /// Calls `vkCmdSetSampleMaskEXT` on the builder.
+    #[inline]
+    pub unsafe fn set_sample_mask(&mut self, samples: u32, sample_mask: [u32; 4]) {
+        let vk = self.device().pointers();
+        let cmd = self.internal_object();
+        debug_assert!(samples.is_power_of_two() && samples <= 64);
+        vk.CmdSetSampleMaskEXT(cmd, samples, sample_mask.as_ptr());
+    }
+
+    /// Calls `vkCmdSetStencilCompareMask` on the builder.
+    #[inline]
+    pub unsafe fn set_stencil_compare_mask(&mut self, face_mask: StencilFaceFlags,
+                                           compare_mask: u32) {
+        let vk = self.device().pointers();
+        let cmd = self.internal_object();
+        vk.CmdSetStencilCompareMask(cmd, face_mask as u32, compare_mask);
+    }
+
+    /// Calls `vkCmdSetStencilReference` on the builder.
+    #[inline]
+    pub unsafe fn set_stencil_reference(&mut self, face_mask: StencilFaceFlags, reference: u32) {
+        let vk = self.device().pointers();
+        let cmd = self.internal_object();
+        vk.CmdSetStencilReference(cmd, face_mask as u32, reference);
+    }
+
+    /// Calls `vkCmdSetStencilWriteMask` on the builder.
+    #[inline]
+    pub unsafe fn set_stencil_write_mask(&mut self, face_mask: StencilFaceFlags, write_mask: u32) {
+        let vk = self.device().pointers();
+        let cmd = self.internal_object();
+        vk.CmdSetStencilWriteMask(cmd, face_mask as u32, write_mask);
+    }
+
+    /// Calls `vkCmdSetStencilTestEnableEXT` on the builder.
+    #[inline]
+    pub unsafe fn set_stencil_test_enable(&mut self, enable: bool) {
+        let vk = self.device().pointers();
+        let cmd = self.internal_object();
+        vk.CmdSetStencilTestEnableEXT(cmd, if enable { vk::TRUE } else { vk::FALSE });
+    }
 
     /// Calls `vkCmdSetScissor` on the builder.
     ///
@@ -943,6 +1477,72 @@ impl<P> UnsafeCommandBufferBuilder<P> {
         vk.CmdSetScissor(cmd, first_scissor, scissors.len() as u32, scissors.as_ptr());
     }
 
+    /// Calls `vkCmdSetScissorWithCountEXT` on the builder.
+    ///
+    /// If the list is empty then the command is automatically ignored.
+    #[inline]
+    pub unsafe fn set_scissor_with_count<I>(&mut self, scissors: I)
+        where I: Iterator<Item = Scissor>
+    {
+        let scissors = scissors
+            .map(|v| v.clone().into())
+            .collect::<SmallVec<[_; 16]>>();
+        if scissors.is_empty() {
+            return;
+        }
+
+        debug_assert!(scissors.len() == 1 || self.device().enabled_features().multi_viewport);
+        debug_assert!({
+                          let max = self.device().physical_device().limits().max_viewports();
+                          scissors.len() as u32 <= max
+                      });
+
+        let vk = self.device().pointers();
+        let cmd = self.internal_object();
+        vk.CmdSetScissorWithCountEXT(cmd, scissors.len() as u32, scissors.as_ptr());
+    }
+
+    /// Calls `vkCmdSetVertexInputEXT` on the builder.
+    #[inline]
+    pub unsafe fn set_vertex_input<B, A>(&mut self, bindings: B, attributes: A)
+        where B: Iterator<Item = VertexInputBinding>,
+              A: Iterator<Item = VertexInputAttribute>
+    {
+        let bindings = bindings
+            .map(|b| {
+                vk::VertexInputBindingDescription2EXT {
+                    sType: vk::STRUCTURE_TYPE_VERTEX_INPUT_BINDING_DESCRIPTION_2_EXT,
+                    pNext: ptr::null_mut(),
+                    binding: b.binding,
+                    stride: b.stride,
+                    inputRate: b.input_rate as u32,
+                    divisor: 1,
+                }
+            })
+            .collect::<SmallVec<[_; 8]>>();
+
+        let attributes = attributes
+            .map(|a| {
+                vk::VertexInputAttributeDescription2EXT {
+                    sType: vk::STRUCTURE_TYPE_VERTEX_INPUT_ATTRIBUTE_DESCRIPTION_2_EXT,
+                    pNext: ptr::null_mut(),
+                    location: a.location,
+                    binding: a.binding,
+                    format: a.format as u32,
+                    offset: a.offset,
+                }
+            })
+            .collect::<SmallVec<[_; 16]>>();
+
+        let vk = self.device().pointers();
+        let cmd = self.internal_object();
+        vk.CmdSetVertexInputEXT(cmd,
+                                bindings.len() as u32,
+                                bindings.as_ptr(),
+                                attributes.len() as u32,
+                                attributes.as_ptr());
+    }
+
     /// Calls `vkCmdSetViewport` on the builder.
     ///
     /// If the list is empty then the command is automatically ignored.
@@ -972,6 +1572,31 @@ impl<P> UnsafeCommandBufferBuilder<P> {
                           viewports.as_ptr());
     }
 
+    /// Calls `vkCmdSetViewportWithCountEXT` on the builder.
+    ///
+    /// If the list is empty then the command is automatically ignored.
+    #[inline]
+    pub unsafe fn set_viewport_with_count<I>(&mut self, viewports: I)
+        where I: Iterator<Item = Viewport>
+    {
+        let viewports = viewports
+            .map(|v| v.clone().into())
+            .collect::<SmallVec<[_; 16]>>();
+        if viewports.is_empty() {
+            return;
+        }
+
+        debug_assert!(viewports.len() == 1 || self.device().enabled_features().multi_viewport);
+        debug_assert!({
+                          let max = self.device().physical_device().limits().max_viewports();
+                          viewports.len() as u32 <= max
+                      });
+
+        let vk = self.device().pointers();
+        let cmd = self.internal_object();
+        vk.CmdSetViewportWithCountEXT(cmd, viewports.len() as u32, viewports.as_ptr());
+    }
+
     /// Calls `vkCmdUpdateBuffer` on the builder.
     #[inline]
     pub unsafe fn update_buffer<B, D>(&mut self, buffer: &B, data: &D)
@@ -1002,6 +1627,22 @@ impl<P> UnsafeCommandBufferBuilder<P> {
                            size as vk::DeviceSize,
                            data as *const D as *const _);
     }
+
+    /// Calls `vkCmdWriteTimestamp` on the builder.
+    ///
+    /// `stage` must contain exactly one stage.
+    #[inline]
+    pub unsafe fn write_timestamp(&mut self, pool: &UnsafeQueryPool, query: u32,
+                                  stage: PipelineStages) {
+        let vk = self.device().pointers();
+        let cmd = self.internal_object();
+
+        let stage: vk::PipelineStageFlags = stage.into();
+        debug_assert_eq!(stage.count_ones(), 1);
+        debug_assert!(query < pool.num_slots());
+
+        vk.CmdWriteTimestamp(cmd, stage, pool.internal_object(), query);
+    }
 }
 
 unsafe impl<P> DeviceOwned for UnsafeCommandBufferBuilder<P> {
@@ -1051,6 +1692,39 @@ impl UnsafeCommandBufferBuilderBindVertexBuffer {
     }
 }
 
+/// Prototype for a `vkCmdBindShadersEXT`.
+pub struct UnsafeCommandBufferBuilderBindShaders {
+    // Stages to bind.
+    stages: SmallVec<[vk::ShaderStageFlagBits; 6]>,
+    // Raw handles of the shaders to bind, or null to unbind a stage.
+    shaders: SmallVec<[vk::ShaderEXT; 6]>,
+}
+
+impl UnsafeCommandBufferBuilderBindShaders {
+    /// Builds a new empty list.
+    #[inline]
+    pub fn new() -> UnsafeCommandBufferBuilderBindShaders {
+        UnsafeCommandBufferBuilderBindShaders {
+            stages: SmallVec::new(),
+            shaders: SmallVec::new(),
+        }
+    }
+
+    /// Adds a shader to the list. It will be bound to the stage it was created for.
+    #[inline]
+    pub fn add<L>(&mut self, shader: &ShaderObject<L>) {
+        self.stages.push(shader.stage().into());
+        self.shaders.push(shader.internal_object());
+    }
+
+    /// Adds a stage that will have no shader bound.
+    #[inline]
+    pub fn add_unbound(&mut self, stage: ShaderObjectStage) {
+        self.stages.push(stage.into());
+        self.shaders.push(0);
+    }
+}
+
 /// Prototype for a `vkCmdExecuteCommands`.
 pub struct UnsafeCommandBufferBuilderExecuteCommands {
     // Raw handles of the command buffers to execute.
@@ -1126,12 +1800,12 @@ pub struct UnsafeCommandBufferBuilderBufferImageCopy {
 /// > multiple different types. Doing so with a single function would be very tedious in terms of
 /// > API.
 pub struct UnsafeCommandBufferBuilderPipelineBarrier {
-    src_stage_mask: vk::PipelineStageFlags,
-    dst_stage_mask: vk::PipelineStageFlags,
-    dependency_flags: vk::DependencyFlags,
-    memory_barriers: SmallVec<[vk::MemoryBarrier; 2]>,
-    buffer_barriers: SmallVec<[vk::BufferMemoryBarrier; 8]>,
-    image_barriers: SmallVec<[vk::ImageMemoryBarrier; 8]>,
+    pub(super) src_stage_mask: vk::PipelineStageFlags,
+    pub(super) dst_stage_mask: vk::PipelineStageFlags,
+    pub(super) dependency_flags: vk::DependencyFlags,
+    pub(super) memory_barriers: SmallVec<[vk::MemoryBarrier; 2]>,
+    pub(super) buffer_barriers: SmallVec<[vk::BufferMemoryBarrier; 8]>,
+    pub(super) image_barriers: SmallVec<[vk::ImageMemoryBarrier; 8]>,
 }
 
 impl UnsafeCommandBufferBuilderPipelineBarrier {
@@ -1163,9 +1837,73 @@ impl UnsafeCommandBufferBuilderPipelineBarrier {
 
         self.memory_barriers
             .extend(other.memory_barriers.into_iter());
-        self.buffer_barriers
-            .extend(other.buffer_barriers.into_iter());
-        self.image_barriers.extend(other.image_barriers.into_iter());
+        for barrier in other.buffer_barriers {
+            push_buffer_barrier(&mut self.buffer_barriers, barrier);
+        }
+        for barrier in other.image_barriers {
+            push_image_barrier(&mut self.image_barriers, barrier);
+        }
+    }
+
+    /// Returns the number of buffer memory barriers that have been added.
+    ///
+    /// Barriers that target the same buffer are merged together and count as one.
+    #[inline]
+    pub fn num_buffer_barriers(&self) -> usize {
+        self.buffer_barriers.len()
+    }
+
+    /// Returns the number of image memory barriers that have been added.
+    ///
+    /// Barriers that target the same subresources with the same layout transition are merged
+    /// together and count as one.
+    #[inline]
+    pub fn num_image_barriers(&self) -> usize {
+        self.image_barriers.len()
+    }
+
+    /// Returns the number of global memory barriers that have been added.
+    #[inline]
+    pub fn num_memory_barriers(&self) -> usize {
+        self.memory_barriers.len()
+    }
+
+    /// If there are more than `max` buffer memory barriers, replaces all of them with a single
+    /// global memory barrier that covers the union of their accesses.
+    ///
+    /// A global memory barrier covers all the buffers, and the implementation usually handles it
+    /// more efficiently than a long list of buffer barriers. The execution dependency doesn't
+    /// change. Buffer barriers that transfer ownership between queues are always kept, and don't
+    /// count towards `max`.
+    #[inline]
+    pub fn collapse_buffer_barriers(&mut self, max: usize) {
+        let num_collapsible = self.buffer_barriers
+            .iter()
+            .filter(|b| !is_queue_transfer(b))
+            .count();
+        if num_collapsible <= max {
+            return;
+        }
+
+        let mut src_access = 0;
+        let mut dst_access = 0;
+
+        self.buffer_barriers.retain(|b| {
+            if is_queue_transfer(b) {
+                return true;
+            }
+
+            src_access |= b.srcAccessMask;
+            dst_access |= b.dstAccessMask;
+            false
+        });
+
+        self.memory_barriers.push(vk::MemoryBarrier {
+                                      sType: vk::STRUCTURE_TYPE_MEMORY_BARRIER,
+                                      pNext: ptr::null(),
+                                      srcAccessMask: src_access,
+                                      dstAccessMask: dst_access,
+                                  });
     }
 
     /// Adds an execution dependency. This means that all the stages in `source` of the previous
@@ -1258,17 +1996,18 @@ impl UnsafeCommandBufferBuilderPipelineBarrier {
             (vk::QUEUE_FAMILY_IGNORED, vk::QUEUE_FAMILY_IGNORED)
         };
 
-        self.buffer_barriers.push(vk::BufferMemoryBarrier {
-                                      sType: vk::STRUCTURE_TYPE_BUFFER_MEMORY_BARRIER,
-                                      pNext: ptr::null(),
-                                      srcAccessMask: source_access.into(),
-                                      dstAccessMask: dest_access.into(),
-                                      srcQueueFamilyIndex: src_queue,
-                                      dstQueueFamilyIndex: dest_queue,
-                                      buffer: buffer.internal_object(),
-                                      offset: offset as vk::DeviceSize,
-                                      size: size as vk::DeviceSize,
-                                  });
+        push_buffer_barrier(&mut self.buffer_barriers,
+                            vk::BufferMemoryBarrier {
+                                sType: vk::STRUCTURE_TYPE_BUFFER_MEMORY_BARRIER,
+                                pNext: ptr::null(),
+                                srcAccessMask: source_access.into(),
+                                dstAccessMask: dest_access.into(),
+                                srcQueueFamilyIndex: src_queue,
+                                dstQueueFamilyIndex: dest_queue,
+                                buffer: buffer.internal_object(),
+                                offset: offset as vk::DeviceSize,
+                                size: size as vk::DeviceSize,
+                            });
     }
 
     /// Adds an image memory barrier. This is the equivalent of `add_buffer_memory_barrier` but
@@ -1330,24 +2069,93 @@ impl UnsafeCommandBufferBuilderPipelineBarrier {
 
         let image = image.inner();
 
-        self.image_barriers.push(vk::ImageMemoryBarrier {
-                                     sType: vk::STRUCTURE_TYPE_IMAGE_MEMORY_BARRIER,
-                                     pNext: ptr::null(),
-                                     srcAccessMask: source_access.into(),
-                                     dstAccessMask: dest_access.into(),
-                                     oldLayout: current_layout as u32,
-                                     newLayout: new_layout as u32,
-                                     srcQueueFamilyIndex: src_queue,
-                                     dstQueueFamilyIndex: dest_queue,
-                                     image: image.image.internal_object(),
-                                     subresourceRange: vk::ImageSubresourceRange {
-                                         aspectMask: aspect_mask,
-                                         baseMipLevel: mipmaps.start + image.first_mipmap_level as u32,
-                                         levelCount: mipmaps.end - mipmaps.start,
-                                         baseArrayLayer: layers.start + image.first_layer as u32,
-                                         layerCount: layers.end - layers.start,
-                                     },
-                                 });
+        push_image_barrier(&mut self.image_barriers,
+                           vk::ImageMemoryBarrier {
+                               sType: vk::STRUCTURE_TYPE_IMAGE_MEMORY_BARRIER,
+                               pNext: ptr::null(),
+                               srcAccessMask: source_access.into(),
+                               dstAccessMask: dest_access.into(),
+                               oldLayout: current_layout as u32,
+                               newLayout: new_layout as u32,
+                               srcQueueFamilyIndex: src_queue,
+                               dstQueueFamilyIndex: dest_queue,
+                               image: image.image.internal_object(),
+                               subresourceRange: vk::ImageSubresourceRange {
+                                   aspectMask: aspect_mask,
+                                   baseMipLevel: mipmaps.start + image.first_mipmap_level as u32,
+                                   levelCount: mipmaps.end - mipmaps.start,
+                                   baseArrayLayer: layers.start + image.first_layer as u32,
+                                   layerCount: layers.end - layers.start,
+                               },
+                           });
+    }
+}
+
+// Adds a buffer barrier to a list, or merges it with a barrier of the list that targets the same
+// buffer with the same queue families.
+//
+// The merged barrier covers the union of the two ranges and of the two access masks. This is
+// more conservative than the two original barriers, and therefore always correct.
+fn push_buffer_barrier(barriers: &mut SmallVec<[vk::BufferMemoryBarrier; 8]>,
+                       barrier: vk::BufferMemoryBarrier) {
+    let existing = barriers.iter_mut().find(|b| {
+        b.buffer == barrier.buffer && b.srcQueueFamilyIndex == barrier.srcQueueFamilyIndex &&
+            b.dstQueueFamilyIndex == barrier.dstQueueFamilyIndex
+    });
+
+    match existing {
+        Some(existing) => {
+            let start = cmp::min(existing.offset, barrier.offset);
+            existing.size = match (buffer_barrier_end(existing), buffer_barrier_end(&barrier)) {
+                (Some(e1), Some(e2)) => cmp::max(e1, e2) - start,
+                _ => vk::WHOLE_SIZE,
+            };
+            existing.srcAccessMask |= barrier.srcAccessMask;
+            existing.dstAccessMask |= barrier.dstAccessMask;
+            existing.offset = start;
+        },
+        None => barriers.push(barrier),
+    }
+}
+
+// Returns the end of the range covered by a buffer barrier, or `None` if the barrier goes until
+// the end of the buffer.
+fn buffer_barrier_end(barrier: &vk::BufferMemoryBarrier) -> Option<u64> {
+    if barrier.size == vk::WHOLE_SIZE {
+        None
+    } else {
+        barrier.offset.checked_add(barrier.size)
+    }
+}
+
+// Returns true if a buffer barrier transfers the ownership of the buffer between queue families.
+fn is_queue_transfer(barrier: &vk::BufferMemoryBarrier) -> bool {
+    barrier.srcQueueFamilyIndex != vk::QUEUE_FAMILY_IGNORED ||
+        barrier.dstQueueFamilyIndex != vk::QUEUE_FAMILY_IGNORED
+}
+
+// Adds an image barrier to a list, or merges it with a barrier of the list that performs exactly
+// the same transition on the same subresources.
+fn push_image_barrier(barriers: &mut SmallVec<[vk::ImageMemoryBarrier; 8]>,
+                      barrier: vk::ImageMemoryBarrier) {
+    let existing = barriers.iter_mut().find(|b| {
+        let r1 = &b.subresourceRange;
+        let r2 = &barrier.subresourceRange;
+        b.image == barrier.image && b.oldLayout == barrier.oldLayout &&
+            b.newLayout == barrier.newLayout &&
+            b.srcQueueFamilyIndex == barrier.srcQueueFamilyIndex &&
+            b.dstQueueFamilyIndex == barrier.dstQueueFamilyIndex &&
+            r1.aspectMask == r2.aspectMask && r1.baseMipLevel == r2.baseMipLevel &&
+            r1.levelCount == r2.levelCount && r1.baseArrayLayer == r2.baseArrayLayer &&
+            r1.layerCount == r2.layerCount
+    });
+
+    match existing {
+        Some(existing) => {
+            existing.srcAccessMask |= barrier.srcAccessMask;
+            existing.dstAccessMask |= barrier.dstAccessMask;
+        },
+        None => barriers.push(barrier),
     }
 }
 
@@ -1390,3 +2198,119 @@ unsafe impl<P> VulkanObject for UnsafeCommandBuffer<P> {
         self.cmd_raw
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use smallvec::SmallVec;
+    use std::ptr;
+
+    use super::UnsafeCommandBufferBuilderPipelineBarrier;
+    use super::push_buffer_barrier;
+    use vk;
+
+    fn buffer_barrier(buffer: vk::Buffer, offset: u64, size: u64, src: u32, dst: u32)
+                      -> vk::BufferMemoryBarrier {
+        vk::BufferMemoryBarrier {
+            sType: vk::STRUCTURE_TYPE_BUFFER_MEMORY_BARRIER,
+            pNext: ptr::null(),
+            srcAccessMask: src,
+            dstAccessMask: dst,
+            srcQueueFamilyIndex: vk::QUEUE_FAMILY_IGNORED,
+            dstQueueFamilyIndex: vk::QUEUE_FAMILY_IGNORED,
+            buffer: buffer,
+            offset: offset,
+            size: size,
+        }
+    }
+
+    #[test]
+    fn same_buffer_merged() {
+        let mut barriers = SmallVec::new();
+        push_buffer_barrier(&mut barriers,
+                            buffer_barrier(1, 0, 64, vk::ACCESS_SHADER_WRITE_BIT,
+                                           vk::ACCESS_SHADER_READ_BIT));
+        push_buffer_barrier(&mut barriers,
+                            buffer_barrier(1, 128, 64, vk::ACCESS_TRANSFER_WRITE_BIT,
+                                           vk::ACCESS_SHADER_READ_BIT));
+        push_buffer_barrier(&mut barriers,
+                            buffer_barrier(2, 0, 64, vk::ACCESS_SHADER_WRITE_BIT,
+                                           vk::ACCESS_SHADER_READ_BIT));
+
+        assert_eq!(barriers.len(), 2);
+        assert_eq!(barriers[0].offset, 0);
+        assert_eq!(barriers[0].size, 192);
+        assert_eq!(barriers[0].srcAccessMask,
+                   vk::ACCESS_SHADER_WRITE_BIT | vk::ACCESS_TRANSFER_WRITE_BIT);
+        assert_eq!(barriers[1].buffer, 2);
+    }
+
+    #[test]
+    fn collapse_into_global_barrier() {
+        let mut barrier = UnsafeCommandBufferBuilderPipelineBarrier::new();
+        for buffer in 0 .. 10 {
+            push_buffer_barrier(&mut barrier.buffer_barriers,
+                                buffer_barrier(buffer, 0, 64, vk::ACCESS_SHADER_WRITE_BIT,
+                                               vk::ACCESS_SHADER_READ_BIT));
+        }
+
+        barrier.collapse_buffer_barriers(16);
+        assert_eq!(barrier.num_buffer_barriers(), 10);
+        assert_eq!(barrier.num_memory_barriers(), 0);
+
+        barrier.collapse_buffer_barriers(8);
+        assert_eq!(barrier.num_buffer_barriers(), 0);
+        assert_eq!(barrier.num_memory_barriers(), 1);
+        assert_eq!(barrier.memory_barriers[0].srcAccessMask, vk::ACCESS_SHADER_WRITE_BIT);
+        assert_eq!(barrier.memory_barriers[0].dstAccessMask, vk::ACCESS_SHADER_READ_BIT);
+    }
+
+    #[test]
+    fn collapse_keeps_queue_transfers() {
+        let mut barrier = UnsafeCommandBufferBuilderPipelineBarrier::new();
+        for buffer in 0 .. 4 {
+            push_buffer_barrier(&mut barrier.buffer_barriers,
+                                buffer_barrier(buffer, 0, 64, vk::ACCESS_SHADER_WRITE_BIT,
+                                               vk::ACCESS_SHADER_READ_BIT));
+        }
+        let mut transfer = buffer_barrier(4, 0, 64, 0, 0);
+        transfer.srcQueueFamilyIndex = 0;
+        transfer.dstQueueFamilyIndex = 1;
+        push_buffer_barrier(&mut barrier.buffer_barriers, transfer);
+
+        barrier.collapse_buffer_barriers(2);
+        assert_eq!(barrier.num_buffer_barriers(), 1);
+        assert_eq!(barrier.buffer_barriers[0].buffer, 4);
+        assert_eq!(barrier.num_memory_barriers(), 1);
+    }
+
+    #[test]
+    fn collapse_only_queue_transfers() {
+        let mut barrier = UnsafeCommandBufferBuilderPipelineBarrier::new();
+        for buffer in 0 .. 4 {
+            let mut transfer = buffer_barrier(buffer, 0, 64, 0, 0);
+            transfer.srcQueueFamilyIndex = 0;
+            transfer.dstQueueFamilyIndex = 1;
+            push_buffer_barrier(&mut barrier.buffer_barriers, transfer);
+        }
+
+        // Queue transfers can't be collapsed, so no global barrier is added.
+        barrier.collapse_buffer_barriers(2);
+        assert_eq!(barrier.num_buffer_barriers(), 4);
+        assert_eq!(barrier.num_memory_barriers(), 0);
+    }
+
+    #[test]
+    fn merge_whole_size() {
+        let mut barriers = SmallVec::new();
+        push_buffer_barrier(&mut barriers,
+                            buffer_barrier(1, 128, vk::WHOLE_SIZE, vk::ACCESS_SHADER_WRITE_BIT,
+                                           vk::ACCESS_SHADER_READ_BIT));
+        push_buffer_barrier(&mut barriers,
+                            buffer_barrier(1, 64, 32, vk::ACCESS_SHADER_WRITE_BIT,
+                                           vk::ACCESS_SHADER_READ_BIT));
+
+        assert_eq!(barriers.len(), 1);
+        assert_eq!(barriers[0].offset, 64);
+        assert_eq!(barriers[0].size, vk::WHOLE_SIZE);
+    }
+}
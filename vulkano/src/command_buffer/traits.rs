@@ -25,6 +25,7 @@ use device::DeviceOwned;
 use device::Queue;
 use image::ImageAccess;
 use image::ImageLayout;
+use instrumentation::DeferNotifications;
 use sync::AccessCheckError;
 use sync::AccessError;
 use sync::AccessFlagBits;
@@ -54,14 +55,29 @@ pub unsafe trait CommandBuffer: DeviceOwned {
     ///
     /// Calling this function means that at some point you will submit the command buffer to the
     /// GPU. Once the function has returned `Ok`, the resources used by the command buffer will
-    /// likely be in a locked state until the command buffer is destroyed.
+    /// likely be in a locked state until `unlock` is called or the command buffer is destroyed.
     ///
     /// **You should not call this function directly**, otherwise any further attempt to submit
     /// will return a runtime error.
-    // TODO: what about command buffers that are submitted multiple times?
+    ///
+    /// This function is also where the usage of the command buffer is enforced. It must return
+    /// an error if the command buffer was created for one-time submission and has already been
+    /// submitted, or if it wasn't created for simultaneous use and is still in use by the GPU.
     fn prepare_submit(&self, future: &GpuFuture, queue: &Queue)
                       -> Result<(), CommandBufferExecError>;
 
+    /// Signals that an execution of the command buffer that was prepared with `prepare_submit`
+    /// has finished.
+    ///
+    /// This releases the locks on the resources that this submission acquired, and command
+    /// buffers that can't be submitted while already in use can be submitted again afterwards.
+    /// The default implementation does nothing.
+    ///
+    /// **You should not call this function directly.**
+    #[inline]
+    unsafe fn unlock(&self) {
+    }
+
     /// Executes this command buffer on a queue.
     ///
     /// This function returns an object that implements the `GpuFuture` trait. See the
@@ -173,6 +189,11 @@ unsafe impl<T> CommandBuffer for T
         (**self).prepare_submit(future, queue)
     }
 
+    #[inline]
+    unsafe fn unlock(&self) {
+        (**self).unlock()
+    }
+
     #[inline]
     fn check_buffer_access(
         &self, buffer: &BufferAccess, exclusive: bool, queue: &Queue)
@@ -245,6 +266,7 @@ unsafe impl<F, Cb> GpuFuture for CommandBufferExecFuture<F, Cb>
     #[inline]
     fn flush(&self) -> Result<(), FlushError> {
         unsafe {
+            let _defer = DeferNotifications::new(self.device());
             let mut submitted = self.submitted.lock().unwrap();
             if *submitted {
                 return Ok(());
@@ -268,7 +290,9 @@ unsafe impl<F, Cb> GpuFuture for CommandBufferExecFuture<F, Cb>
 
     #[inline]
     unsafe fn signal_finished(&self) {
-        self.finished.store(true, Ordering::SeqCst);
+        if !self.finished.swap(true, Ordering::SeqCst) {
+            self.command_buffer.unlock();
+        }
         self.previous.signal_finished();
     }
 
@@ -333,6 +357,7 @@ impl<F, Cb> Drop for CommandBufferExecFuture<F, Cb>
                 self.flush().unwrap();
                 // Block until the queue finished.
                 self.queue.wait().unwrap();
+                self.command_buffer.unlock();
                 self.previous.signal_finished();
             }
         }
@@ -345,6 +370,12 @@ pub enum CommandBufferExecError {
     /// Access to a resource has been denied.
     AccessError(AccessError),
 
+    /// The command buffer was created for one-time submission and has already been submitted.
+    OneTimeSubmitAlreadySubmitted,
+
+    /// The command buffer wasn't created for simultaneous use and is already in use by the GPU.
+    ExclusiveAlreadyInUse,
+
     // TODO: missing entries (eg. wrong queue family, secondary command buffer)
 }
 
@@ -353,6 +384,14 @@ impl error::Error for CommandBufferExecError {
     fn description(&self) -> &str {
         match *self {
             CommandBufferExecError::AccessError(_) => "access to a resource has been denied",
+            CommandBufferExecError::OneTimeSubmitAlreadySubmitted => {
+                "the command buffer was created for one-time submission and has already been \
+                 submitted"
+            },
+            CommandBufferExecError::ExclusiveAlreadyInUse => {
+                "the command buffer wasn't created for simultaneous use and is already in use by \
+                 the GPU"
+            },
         }
     }
 
@@ -360,6 +399,7 @@ impl error::Error for CommandBufferExecError {
     fn cause(&self) -> Option<&error::Error> {
         match *self {
             CommandBufferExecError::AccessError(ref err) => Some(err),
+            _ => None,
         }
     }
 }
@@ -0,0 +1,115 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::error;
+use std::fmt;
+
+use device::Device;
+use format::ClearValue;
+use image::ImageViewAccess;
+
+/// Checks whether rendering can begin without a render pass, with the given color attachments
+/// and the render area described by its `offset` and `extent` in pixels.
+///
+/// The `khr_dynamic_rendering` extension must be enabled, or be part of the version of Vulkan
+/// of the device. Each attachment must have the color attachment usage, its clear value must be
+/// a color or `ClearValue::None`, and the render area must be within its dimensions.
+pub fn check_begin_rendering<V>(device: &Device, color_attachments: &[(V, ClearValue)],
+                                offset: [u32; 2], extent: [u32; 2])
+                                -> Result<(), CheckBeginRenderingError>
+    where V: ImageViewAccess
+{
+    if !device.effective_extensions().khr_dynamic_rendering {
+        return Err(CheckBeginRenderingError::ExtensionNotEnabled);
+    }
+
+    if extent[0] == 0 || extent[1] == 0 {
+        return Err(CheckBeginRenderingError::EmptyRenderArea);
+    }
+
+    for (num, &(ref view, clear_value)) in color_attachments.iter().enumerate() {
+        if !view.inner().usage_color_attachment() {
+            return Err(CheckBeginRenderingError::MissingColorAttachmentUsage { attachment: num });
+        }
+
+        match clear_value {
+            ClearValue::None | ClearValue::Float(_) | ClearValue::Int(_) | ClearValue::Uint(_) => (),
+            _ => return Err(CheckBeginRenderingError::WrongClearValue { attachment: num }),
+        }
+
+        let dimensions = view.dimensions().width_height();
+        for i in 0 .. 2 {
+            if offset[i] as u64 + extent[i] as u64 > dimensions[i] as u64 {
+                return Err(CheckBeginRenderingError::OutOfBounds { attachment: num });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Error that can happen when beginning to render without a render pass.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CheckBeginRenderingError {
+    /// The `khr_dynamic_rendering` extension must be enabled in order to render without a render
+    /// pass.
+    ExtensionNotEnabled,
+
+    /// The width or the height of the render area is 0.
+    EmptyRenderArea,
+
+    /// An attachment wasn't created with the color attachment usage.
+    MissingColorAttachmentUsage {
+        /// Index of the attachment.
+        attachment: usize,
+    },
+
+    /// The clear value of an attachment is a depth or stencil value.
+    WrongClearValue {
+        /// Index of the attachment.
+        attachment: usize,
+    },
+
+    /// The render area is not within the dimensions of an attachment.
+    OutOfBounds {
+        /// Index of the attachment.
+        attachment: usize,
+    },
+}
+
+impl error::Error for CheckBeginRenderingError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            CheckBeginRenderingError::ExtensionNotEnabled => {
+                "the `khr_dynamic_rendering` extension must be enabled in order to render without \
+                 a render pass"
+            },
+            CheckBeginRenderingError::EmptyRenderArea => {
+                "the width or the height of the render area is 0"
+            },
+            CheckBeginRenderingError::MissingColorAttachmentUsage { .. } => {
+                "an attachment wasn't created with the color attachment usage"
+            },
+            CheckBeginRenderingError::WrongClearValue { .. } => {
+                "the clear value of an attachment is a depth or stencil value"
+            },
+            CheckBeginRenderingError::OutOfBounds { .. } => {
+                "the render area is not within the dimensions of an attachment"
+            },
+        }
+    }
+}
+
+impl fmt::Display for CheckBeginRenderingError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
@@ -0,0 +1,122 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::error;
+use std::fmt;
+
+use format::ClearValue;
+use framebuffer::RenderPassDesc;
+
+/// Checks whether a list of clear values can be used to begin a render pass.
+///
+/// There must be exactly one value per attachment of the render pass, and each value must be
+/// valid for its attachment as described in `LayoutAttachmentDescription::is_valid_clear_value`.
+pub fn check_clear_values<R>(render_pass: &R, values: &[ClearValue])
+                             -> Result<(), CheckClearValuesError>
+    where R: ?Sized + RenderPassDesc
+{
+    let num_attachments = render_pass.num_attachments();
+    if values.len() != num_attachments {
+        return Err(CheckClearValuesError::WrongNumberOfClearValues {
+                       expected: num_attachments,
+                       obtained: values.len(),
+                   });
+    }
+
+    for (num, value) in values.iter().enumerate() {
+        let desc = render_pass.attachment_desc(num).unwrap();
+        if !desc.is_valid_clear_value(value) {
+            return Err(CheckClearValuesError::InvalidClearValue { attachment: num });
+        }
+    }
+
+    Ok(())
+}
+
+/// Error that can happen when checking the clear values of a render pass.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CheckClearValuesError {
+    /// The number of clear values doesn't match the number of attachments.
+    WrongNumberOfClearValues {
+        /// Number of attachments of the render pass.
+        expected: usize,
+        /// Number of clear values that were passed.
+        obtained: usize,
+    },
+
+    /// The clear value of an attachment doesn't match its format, or doesn't provide a value for
+    /// a component that is cleared.
+    InvalidClearValue {
+        /// Index of the attachment.
+        attachment: usize,
+    },
+}
+
+impl error::Error for CheckClearValuesError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            CheckClearValuesError::WrongNumberOfClearValues { .. } => {
+                "the number of clear values doesn't match the number of attachments"
+            },
+            CheckClearValuesError::InvalidClearValue { .. } => {
+                "the clear value of an attachment doesn't match its format or doesn't provide a \
+                 value for a component that is cleared"
+            },
+        }
+    }
+}
+
+impl fmt::Display for CheckClearValuesError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use command_buffer::validity;
+    use format::ClearValue;
+    use format::Format;
+
+    #[test]
+    fn clear_values() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let rp = single_pass_renderpass! {
+            device.clone(),
+            attachments: {
+                a: { load: Clear, store: Store, format: Format::R8G8B8A8Unorm, samples: 1, },
+                b: { load: Clear, store: DontCare, format: Format::D16Unorm, samples: 1, }
+            },
+            pass: {
+                color: [a],
+                depth_stencil: {b}
+            }
+        }.unwrap();
+
+        let valid = [ClearValue::Float([0.0, 0.0, 1.0, 1.0]), ClearValue::Depth(1.0)];
+        validity::check_clear_values(&rp, &valid).unwrap();
+
+        match validity::check_clear_values(&rp, &valid[.. 1]) {
+            Err(validity::CheckClearValuesError::WrongNumberOfClearValues {
+                    expected: 2,
+                    obtained: 1,
+                }) => (),
+            _ => panic!(),
+        }
+
+        let invalid = [ClearValue::Float([0.0, 0.0, 1.0, 1.0]), ClearValue::Stencil(0)];
+        match validity::check_clear_values(&rp, &invalid) {
+            Err(validity::CheckClearValuesError::InvalidClearValue { attachment: 1 }) => (),
+            _ => panic!(),
+        }
+    }
+}
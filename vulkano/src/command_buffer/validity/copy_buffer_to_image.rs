@@ -0,0 +1,223 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::error;
+use std::fmt;
+
+use VulkanObject;
+use buffer::BufferAccess;
+use device::Device;
+use device::DeviceOwned;
+use image::ImageAccess;
+use image::region_buffer_len;
+
+/// Checks whether a copy buffer to image command is valid.
+///
+/// The region of the image is described by `offset` and `size` in texels, within the mipmap
+/// level `mipmap`. If the format of the image is compressed, the region must be made of whole
+/// blocks, except that it can end on the edge of the mipmap level.
+///
+/// # Panic
+///
+/// - Panics if the buffer or the image were not created with `device`.
+///
+pub fn check_copy_buffer_to_image<S, D>(device: &Device, source: &S, destination: &D,
+                                        offset: [u32; 3], size: [u32; 3], first_layer: u32,
+                                        num_layers: u32, mipmap: u32)
+                                        -> Result<(), CheckCopyBufferToImageError>
+    where S: ?Sized + BufferAccess,
+          D: ?Sized + ImageAccess
+{
+    assert_eq!(source.inner().buffer.device().internal_object(),
+               device.internal_object());
+    assert_eq!(destination.inner().image.device().internal_object(),
+               device.internal_object());
+
+    if !source.inner().buffer.usage_transfer_src() {
+        return Err(CheckCopyBufferToImageError::SourceMissingTransferUsage);
+    }
+
+    if !destination.inner().image.usage_transfer_dest() {
+        return Err(CheckCopyBufferToImageError::DestinationMissingTransferUsage);
+    }
+
+    if mipmap >= destination.mipmap_levels() {
+        return Err(CheckCopyBufferToImageError::MipmapOutOfRange);
+    }
+
+    let dimensions = destination.dimensions();
+
+    match first_layer.checked_add(num_layers) {
+        Some(end) if end <= dimensions.array_layers() => (),
+        _ => return Err(CheckCopyBufferToImageError::ArrayLayersOutOfRange),
+    }
+
+    let mip_dimensions = match dimensions.mip_level_dimensions(mipmap) {
+        Some(d) => d.width_height_depth(),
+        None => return Err(CheckCopyBufferToImageError::MipmapOutOfRange),
+    };
+
+    for i in 0 .. 3 {
+        match offset[i].checked_add(size[i]) {
+            Some(end) if end <= mip_dimensions[i] => (),
+            _ => return Err(CheckCopyBufferToImageError::RegionOutOfImageBounds),
+        }
+    }
+
+    let block_dims = destination.format().block_dimensions();
+    for i in 0 .. 2 {
+        if offset[i] % block_dims[i] != 0 ||
+            (size[i] % block_dims[i] != 0 && offset[i] + size[i] != mip_dimensions[i])
+        {
+            return Err(CheckCopyBufferToImageError::RegionNotAlignedToBlocks);
+        }
+    }
+
+    if let Some(required) = region_buffer_len(destination.format(), size, num_layers) {
+        if source.size() < required {
+            return Err(CheckCopyBufferToImageError::SourceTooSmall {
+                           required: required,
+                           actual: source.size(),
+                       });
+        }
+    }
+
+    Ok(())
+}
+
+/// Error that can happen from `check_copy_buffer_to_image`.
+#[derive(Debug, Copy, Clone)]
+pub enum CheckCopyBufferToImageError {
+    /// The source buffer is missing the transfer source usage.
+    SourceMissingTransferUsage,
+    /// The destination image is missing the transfer destination usage.
+    DestinationMissingTransferUsage,
+    /// The mipmap level is out of range of the image.
+    MipmapOutOfRange,
+    /// The array layers are out of range of the image.
+    ArrayLayersOutOfRange,
+    /// The region goes beyond the dimensions of the mipmap level.
+    RegionOutOfImageBounds,
+    /// The format of the image is compressed, and the region doesn't start or end on the edge of
+    /// a block.
+    RegionNotAlignedToBlocks,
+    /// The source buffer is too small to contain the data of the region.
+    SourceTooSmall {
+        /// Number of bytes required by the region.
+        required: usize,
+        /// Size of the source buffer.
+        actual: usize,
+    },
+}
+
+impl error::Error for CheckCopyBufferToImageError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            CheckCopyBufferToImageError::SourceMissingTransferUsage => {
+                "the source buffer is missing the transfer source usage"
+            },
+            CheckCopyBufferToImageError::DestinationMissingTransferUsage => {
+                "the destination image is missing the transfer destination usage"
+            },
+            CheckCopyBufferToImageError::MipmapOutOfRange => {
+                "the mipmap level is out of range of the image"
+            },
+            CheckCopyBufferToImageError::ArrayLayersOutOfRange => {
+                "the array layers are out of range of the image"
+            },
+            CheckCopyBufferToImageError::RegionOutOfImageBounds => {
+                "the region goes beyond the dimensions of the mipmap level"
+            },
+            CheckCopyBufferToImageError::RegionNotAlignedToBlocks => {
+                "the format of the image is compressed, and the region doesn't start or end on the \
+                 edge of a block"
+            },
+            CheckCopyBufferToImageError::SourceTooSmall { .. } => {
+                "the source buffer is too small to contain the data of the region"
+            },
+        }
+    }
+}
+
+impl fmt::Display for CheckCopyBufferToImageError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::iter;
+    use buffer::BufferUsage;
+    use buffer::CpuAccessibleBuffer;
+    use format::Format;
+    use image::Dimensions;
+    use image::ImmutableImage;
+    use super::*;
+
+    #[test]
+    fn overflowing_region() {
+        let (device, queue) = gfx_dev_and_queue!();
+        let image = ImmutableImage::new(device.clone(),
+                                        Dimensions::Dim2d { width: 16, height: 16 },
+                                        Format::R8Unorm,
+                                        iter::once(queue.family()))
+            .unwrap();
+        let buffer = CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::all(),
+                                                    iter::once(queue.family()),
+                                                    (0 .. 16 * 16).map(|_| 0u8)).unwrap();
+
+        match check_copy_buffer_to_image(&device, &buffer, &image, [1, 0, 0], [!0, 1, 1], 0, 1,
+                                         0) {
+            Err(CheckCopyBufferToImageError::RegionOutOfImageBounds) => (),
+            _ => panic!()
+        }
+
+        match check_copy_buffer_to_image(&device, &buffer, &image, [0, 0, 0], [1, 1, 1], 1, !0,
+                                         0) {
+            Err(CheckCopyBufferToImageError::ArrayLayersOutOfRange) => (),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn compressed_region_alignment() {
+        let (device, queue) = gfx_dev_and_queue!(texture_compression_bc);
+        let image = ImmutableImage::new(device.clone(),
+                                        Dimensions::Dim2d { width: 18, height: 16 },
+                                        Format::BC1_RGBAUnormBlock,
+                                        iter::once(queue.family()))
+            .unwrap();
+        // 5 by 4 blocks of 8 bytes.
+        let buffer = CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::all(),
+                                                    iter::once(queue.family()),
+                                                    (0 .. 5 * 4 * 8).map(|_| 0u8)).unwrap();
+
+        assert!(check_copy_buffer_to_image(&device, &buffer, &image, [4, 8, 0], [8, 8, 1], 0, 1,
+                                           0).is_ok());
+
+        // The region can end in the middle of a block on the edge of the image.
+        assert!(check_copy_buffer_to_image(&device, &buffer, &image, [16, 0, 0], [2, 16, 1], 0,
+                                           1, 0).is_ok());
+
+        match check_copy_buffer_to_image(&device, &buffer, &image, [2, 0, 0], [4, 4, 1], 0, 1,
+                                         0) {
+            Err(CheckCopyBufferToImageError::RegionNotAlignedToBlocks) => (),
+            _ => panic!()
+        }
+
+        match check_copy_buffer_to_image(&device, &buffer, &image, [0, 0, 0], [4, 6, 1], 0, 1,
+                                         0) {
+            Err(CheckCopyBufferToImageError::RegionNotAlignedToBlocks) => (),
+            _ => panic!()
+        }
+    }
+}
@@ -0,0 +1,215 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::error;
+use std::fmt;
+
+use VulkanObject;
+use buffer::BufferAccess;
+use device::Device;
+use device::DeviceOwned;
+use image::ImageAccess;
+use image::region_buffer_len;
+
+/// Checks whether a copy image to buffer command is valid.
+///
+/// The region of the image is described by `offset` and `size` in texels, within the mipmap
+/// level `mipmap`.
+///
+/// # Panic
+///
+/// - Panics if the image or the buffer were not created with `device`.
+///
+pub fn check_copy_image_to_buffer<S, D>(device: &Device, source: &S, destination: &D,
+                                        offset: [u32; 3], size: [u32; 3], first_layer: u32,
+                                        num_layers: u32, mipmap: u32)
+                                        -> Result<(), CheckCopyImageToBufferError>
+    where S: ?Sized + ImageAccess,
+          D: ?Sized + BufferAccess
+{
+    assert_eq!(source.inner().image.device().internal_object(),
+               device.internal_object());
+    assert_eq!(destination.inner().buffer.device().internal_object(),
+               device.internal_object());
+
+    if !source.inner().image.usage_transfer_src() {
+        return Err(CheckCopyImageToBufferError::SourceMissingTransferUsage);
+    }
+
+    if !destination.inner().buffer.usage_transfer_dest() {
+        return Err(CheckCopyImageToBufferError::DestinationMissingTransferUsage);
+    }
+
+    if source.samples() != 1 {
+        return Err(CheckCopyImageToBufferError::SourceMultisampled);
+    }
+
+    if !source.has_color() {
+        return Err(CheckCopyImageToBufferError::SourceNotColor);
+    }
+
+    if mipmap >= source.mipmap_levels() {
+        return Err(CheckCopyImageToBufferError::MipmapOutOfRange);
+    }
+
+    let dimensions = source.dimensions();
+
+    match first_layer.checked_add(num_layers) {
+        Some(end) if end <= dimensions.array_layers() => (),
+        _ => return Err(CheckCopyImageToBufferError::ArrayLayersOutOfRange),
+    }
+
+    let mip_dimensions = match dimensions.mip_level_dimensions(mipmap) {
+        Some(d) => d.width_height_depth(),
+        None => return Err(CheckCopyImageToBufferError::MipmapOutOfRange),
+    };
+
+    for i in 0 .. 3 {
+        match offset[i].checked_add(size[i]) {
+            Some(end) if end <= mip_dimensions[i] => (),
+            _ => return Err(CheckCopyImageToBufferError::RegionOutOfImageBounds),
+        }
+    }
+
+    if let Some(required) = region_buffer_len(source.format(), size, num_layers) {
+        if destination.size() < required {
+            return Err(CheckCopyImageToBufferError::DestinationTooSmall {
+                           required: required,
+                           actual: destination.size(),
+                       });
+        }
+    }
+
+    Ok(())
+}
+
+/// Error that can happen from `check_copy_image_to_buffer`.
+#[derive(Debug, Copy, Clone)]
+pub enum CheckCopyImageToBufferError {
+    /// The source image is missing the transfer source usage.
+    SourceMissingTransferUsage,
+    /// The destination buffer is missing the transfer destination usage.
+    DestinationMissingTransferUsage,
+    /// The source image has more than one sample per pixel.
+    SourceMultisampled,
+    /// The source image doesn't have a color aspect. Copying from depth or stencil images isn't
+    /// supported.
+    SourceNotColor,
+    /// The mipmap level is out of range of the image.
+    MipmapOutOfRange,
+    /// The array layers are out of range of the image.
+    ArrayLayersOutOfRange,
+    /// The region goes beyond the dimensions of the mipmap level.
+    RegionOutOfImageBounds,
+    /// The destination buffer is too small to contain the data of the region.
+    DestinationTooSmall {
+        /// Number of bytes required by the region.
+        required: usize,
+        /// Size of the destination buffer.
+        actual: usize,
+    },
+}
+
+impl error::Error for CheckCopyImageToBufferError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            CheckCopyImageToBufferError::SourceMissingTransferUsage => {
+                "the source image is missing the transfer source usage"
+            },
+            CheckCopyImageToBufferError::DestinationMissingTransferUsage => {
+                "the destination buffer is missing the transfer destination usage"
+            },
+            CheckCopyImageToBufferError::SourceMultisampled => {
+                "the source image has more than one sample per pixel"
+            },
+            CheckCopyImageToBufferError::SourceNotColor => {
+                "the source image doesn't have a color aspect"
+            },
+            CheckCopyImageToBufferError::MipmapOutOfRange => {
+                "the mipmap level is out of range of the image"
+            },
+            CheckCopyImageToBufferError::ArrayLayersOutOfRange => {
+                "the array layers are out of range of the image"
+            },
+            CheckCopyImageToBufferError::RegionOutOfImageBounds => {
+                "the region goes beyond the dimensions of the mipmap level"
+            },
+            CheckCopyImageToBufferError::DestinationTooSmall { .. } => {
+                "the destination buffer is too small to contain the data of the region"
+            },
+        }
+    }
+}
+
+impl fmt::Display for CheckCopyImageToBufferError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::iter;
+    use buffer::BufferUsage;
+    use buffer::CpuAccessibleBuffer;
+    use format::Format;
+    use image::AttachmentImage;
+    use image::ImageUsage;
+    use super::*;
+
+    #[test]
+    fn depth_source() {
+        let (device, queue) = gfx_dev_and_queue!();
+        let usage = ImageUsage {
+            transfer_source: true,
+            depth_stencil_attachment: true,
+            ..ImageUsage::none()
+        };
+        let image = AttachmentImage::with_usage(device.clone(), [16, 16], Format::D16Unorm, usage)
+            .unwrap();
+        let buffer = CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::all(),
+                                                    iter::once(queue.family()),
+                                                    (0 .. 16 * 16).map(|_| 0u16)).unwrap();
+
+        match check_copy_image_to_buffer(&device, &image, &buffer, [0, 0, 0], [16, 16, 1], 0, 1,
+                                         0) {
+            Err(CheckCopyImageToBufferError::SourceNotColor) => (),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn overflowing_region() {
+        let (device, queue) = gfx_dev_and_queue!();
+        let usage = ImageUsage {
+            transfer_source: true,
+            color_attachment: true,
+            ..ImageUsage::none()
+        };
+        let image = AttachmentImage::with_usage(device.clone(), [16, 16], Format::R8Unorm, usage)
+            .unwrap();
+        let buffer = CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::all(),
+                                                    iter::once(queue.family()),
+                                                    (0 .. 16 * 16).map(|_| 0u8)).unwrap();
+
+        match check_copy_image_to_buffer(&device, &image, &buffer, [1, 0, 0], [!0, 1, 1], 0, 1,
+                                         0) {
+            Err(CheckCopyImageToBufferError::RegionOutOfImageBounds) => (),
+            _ => panic!()
+        }
+
+        match check_copy_image_to_buffer(&device, &image, &buffer, [0, 0, 0], [1, 1, 1], 1, !0,
+                                         0) {
+            Err(CheckCopyImageToBufferError::ArrayLayersOutOfRange) => (),
+            _ => panic!()
+        }
+    }
+}
@@ -0,0 +1,79 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::error;
+use std::ffi::CString;
+use std::fmt;
+
+use device::Device;
+
+/// Checks whether a debug label can be opened or inserted, and returns its name as a `CString`.
+pub fn check_debug_label(device: &Device, name: &str)
+                         -> Result<CString, CheckDebugLabelError> {
+    if !device.instance().loaded_extensions().ext_debug_utils {
+        return Err(CheckDebugLabelError::ExtensionNotEnabled);
+    }
+
+    match CString::new(name) {
+        Ok(name) => Ok(name),
+        Err(_) => Err(CheckDebugLabelError::NulInName),
+    }
+}
+
+/// Checks whether a debug label can be closed.
+///
+/// `num_open_labels` is the number of labels that have been opened in the command buffer and
+/// that haven't been closed yet.
+pub fn check_end_debug_label(device: &Device, num_open_labels: u32)
+                             -> Result<(), CheckDebugLabelError> {
+    if !device.instance().loaded_extensions().ext_debug_utils {
+        return Err(CheckDebugLabelError::ExtensionNotEnabled);
+    }
+
+    if num_open_labels == 0 {
+        return Err(CheckDebugLabelError::NoOpenLabel);
+    }
+
+    Ok(())
+}
+
+/// Error that can happen when recording a debug label command.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CheckDebugLabelError {
+    /// The `ext_debug_utils` extension wasn't enabled on the instance.
+    ExtensionNotEnabled,
+    /// The name of the label contains a nul character.
+    NulInName,
+    /// There is no open label to close.
+    NoOpenLabel,
+}
+
+impl error::Error for CheckDebugLabelError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            CheckDebugLabelError::ExtensionNotEnabled => {
+                "the `ext_debug_utils` extension wasn't enabled on the instance"
+            },
+            CheckDebugLabelError::NulInName => {
+                "the name of the label contains a nul character"
+            },
+            CheckDebugLabelError::NoOpenLabel => {
+                "there is no open label to close"
+            },
+        }
+    }
+}
+
+impl fmt::Display for CheckDebugLabelError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
@@ -7,27 +7,292 @@
 // notice may not be copied, modified, or distributed except
 // according to those terms.
 
+use std::collections::HashMap;
 use std::error;
 use std::fmt;
 
+use VulkanObject;
+use descriptor::descriptor::DescriptorType;
+use descriptor::descriptor_set::DescriptorSetsCollection;
 use descriptor::pipeline_layout::PipelineLayoutAbstract;
+use descriptor::pipeline_layout::PipelineLayoutDesc;
+use vk;
 
 /// Checks whether descriptor sets are compatible with the pipeline.
+///
+/// A pipeline layout without any descriptor set is compatible with an empty collection, such as
+/// `()`.
 pub fn check_descriptor_sets_validity<Pl, D>(pipeline: &Pl, descriptor_sets: &D)
                                              -> Result<(), CheckDescriptorSetsValidityError>
-    where Pl: ?Sized + PipelineLayoutAbstract,
-          D: ?Sized,
+    where Pl: ?Sized + PipelineLayoutDesc,
+          D: ?Sized + DescriptorSetsCollection,
 {
-    // TODO: implement
+    let mut num_dynamic = 0;
+    let mut set = 0;
+    while descriptor_sets.num_bindings_in_set(set).is_some() {
+        num_dynamic += check_set(pipeline, descriptor_sets, set)?;
+        set += 1;
+    }
+
+    check_dynamic_offsets(descriptor_sets, num_dynamic)
+}
+
+/// Same as `check_descriptor_sets_validity`, but remembers the result for each descriptor set so
+/// that an unchanged set used with a compatible pipeline layout isn't validated again.
+///
+/// Results are keyed by the pipeline layout, by the index of the set in the collection, and by
+/// the generation of the descriptor set, as returned by `DescriptorSetsCollection::generation`.
+/// Generations are never shared between two sets, even if a set reuses the handle of a destroyed
+/// set, and writing to a set changes its generation, which invalidates the results that concern
+/// it. Sets whose generation is unknown are validated every time.
+///
+/// The cache keeps a clone of every pipeline layout passed to `check`, so that the Vulkan handle
+/// of a layout can't be reused by another layout while the cache is alive. The first time a
+/// layout is seen, its description is compared with the layouts already known, and compatible
+/// layouts share their results.
+#[derive(Default)]
+pub struct DescriptorSetsValidityCache {
+    // Pipeline layouts that have been seen by Vulkan handle, with the identifier of their
+    // results. Compatible layouts have the same identifier.
+    layouts: HashMap<vk::PipelineLayout, (Box<PipelineLayoutAbstract + Send + Sync>, usize)>,
+    // Number of identifiers given to layouts so far.
+    num_layout_ids: usize,
+    // Number of dynamic buffer descriptors of each set that has been validated, or the error.
+    // Keyed by layout identifier, index of the set and generation of the set.
+    results: HashMap<(usize, usize, usize), Result<usize, CheckDescriptorSetsValidityError>>,
+    hits: usize,
+    misses: usize,
+}
+
+impl DescriptorSetsValidityCache {
+    /// Builds a new empty cache.
+    #[inline]
+    pub fn new() -> DescriptorSetsValidityCache {
+        DescriptorSetsValidityCache {
+            layouts: HashMap::new(),
+            num_layout_ids: 0,
+            results: HashMap::new(),
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// Checks whether descriptor sets are compatible with the pipeline, reusing the previous
+    /// results when possible.
+    pub fn check<Pl, D>(&mut self, pipeline: &Pl, descriptor_sets: &D)
+                        -> Result<(), CheckDescriptorSetsValidityError>
+        where Pl: PipelineLayoutAbstract + Clone + Send + Sync + 'static,
+              D: ?Sized + DescriptorSetsCollection
+    {
+        let layout_id = self.layout_id(pipeline);
+        self.check_with_layout_id(layout_id, pipeline, descriptor_sets)
+    }
+
+    // Returns the identifier of the results of `pipeline`, and keeps the layout alive if it
+    // hasn't been seen yet.
+    fn layout_id<Pl>(&mut self, pipeline: &Pl) -> usize
+        where Pl: PipelineLayoutAbstract + Clone + Send + Sync + 'static
+    {
+        let handle = pipeline.sys().internal_object();
+        if let Some(&(_, id)) = self.layouts.get(&handle) {
+            return id;
+        }
+
+        let id = match self.layouts
+            .values()
+            .find(|&&(ref layout, _)| layouts_compatible(&**layout, pipeline))
+        {
+            Some(&(_, id)) => id,
+            None => {
+                self.num_layout_ids += 1;
+                self.num_layout_ids - 1
+            },
+        };
+
+        self.layouts.insert(handle, (Box::new(pipeline.clone()) as Box<_>, id));
+        id
+    }
+
+    fn check_with_layout_id<Pl, D>(&mut self, layout_id: usize, pipeline: &Pl,
+                                   descriptor_sets: &D)
+                                   -> Result<(), CheckDescriptorSetsValidityError>
+        where Pl: ?Sized + PipelineLayoutDesc,
+              D: ?Sized + DescriptorSetsCollection
+    {
+        let mut num_dynamic = 0;
+        let mut set = 0;
+        while descriptor_sets.num_bindings_in_set(set).is_some() {
+            let key = descriptor_sets
+                .generation(set)
+                .map(|generation| (layout_id, set, generation));
+
+            let cached = key.and_then(|key| self.results.get(&key).cloned());
+            let result = match cached {
+                Some(result) => {
+                    self.hits += 1;
+                    result
+                },
+                None => {
+                    self.misses += 1;
+                    let result = check_set(pipeline, descriptor_sets, set);
+                    if let Some(key) = key {
+                        self.results.insert(key, result);
+                    }
+                    result
+                },
+            };
+
+            num_dynamic += result?;
+            set += 1;
+        }
+
+        check_dynamic_offsets(descriptor_sets, num_dynamic)
+    }
+
+    /// Returns the number of descriptor sets whose validation was skipped thanks to the cache.
+    #[inline]
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+
+    /// Returns the number of descriptor sets that had to be validated.
+    #[inline]
+    pub fn misses(&self) -> usize {
+        self.misses
+    }
+}
+
+impl fmt::Debug for DescriptorSetsValidityCache {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        fmt.debug_struct("DescriptorSetsValidityCache")
+            .field("num_layouts", &self.layouts.len())
+            .field("num_results", &self.results.len())
+            .field("hits", &self.hits)
+            .field("misses", &self.misses)
+            .finish()
+    }
+}
+
+// Checks a single set of the collection and returns its number of dynamic buffer descriptors.
+fn check_set<Pl, D>(pipeline: &Pl, descriptor_sets: &D, set: usize)
+                    -> Result<usize, CheckDescriptorSetsValidityError>
+    where Pl: ?Sized + PipelineLayoutDesc,
+          D: ?Sized + DescriptorSetsCollection
+{
+    if set >= pipeline.num_sets() {
+        return Err(CheckDescriptorSetsValidityError::IncompatibleDescriptorSets);
+    }
+
+    // Every descriptor of the pipeline layout must be provided by the set.
+    let num_layout_bindings = pipeline.num_bindings_in_set(set).unwrap_or(0);
+    for binding in 0 .. num_layout_bindings {
+        let layout_desc = match pipeline.descriptor(set, binding) {
+            Some(d) => d,
+            None => continue,
+        };
+
+        match descriptor_sets.descriptor(set, binding) {
+            Some(ref desc) if desc.is_superset_of(&layout_desc) => (),
+            _ => return Err(CheckDescriptorSetsValidityError::IncompatibleDescriptorSets),
+        }
+    }
+
+    let mut num_dynamic = 0;
+    let num_bindings = descriptor_sets.num_bindings_in_set(set).unwrap();
+    for binding in 0 .. num_bindings {
+        let desc = match descriptor_sets.descriptor(set, binding) {
+            Some(d) => d,
+            None => continue,
+        };
+
+        match desc.ty.ty() {
+            Some(DescriptorType::UniformBufferDynamic) |
+            Some(DescriptorType::StorageBufferDynamic) => {
+                num_dynamic += desc.array_count as usize;
+            },
+            _ => (),
+        }
+    }
+
+    Ok(num_dynamic)
+}
+
+// There must be exactly one dynamic offset per dynamic buffer descriptor.
+fn check_dynamic_offsets<D>(descriptor_sets: &D, num_dynamic: usize)
+                            -> Result<(), CheckDescriptorSetsValidityError>
+    where D: ?Sized + DescriptorSetsCollection
+{
+    let num_offsets = descriptor_sets.dynamic_offsets().len();
+    if num_offsets != num_dynamic {
+        return Err(CheckDescriptorSetsValidityError::WrongNumberOfDynamicOffsets {
+                       expected: num_dynamic,
+                       obtained: num_offsets,
+                   });
+    }
 
     Ok(())
 }
 
+// Returns true if the descriptor sets and push constant ranges of `a` and `b` are the same, in
+// which case the same descriptor sets are valid with both layouts.
+fn layouts_compatible<A, B>(a: &A, b: &B) -> bool
+    where A: ?Sized + PipelineLayoutDesc,
+          B: ?Sized + PipelineLayoutDesc
+{
+    if a.num_sets() != b.num_sets() {
+        return false;
+    }
+
+    for set in 0 .. a.num_sets() {
+        let num_bindings = a.num_bindings_in_set(set).unwrap_or(0);
+        if b.num_bindings_in_set(set).unwrap_or(0) != num_bindings {
+            return false;
+        }
+
+        for binding in 0 .. num_bindings {
+            match (a.descriptor(set, binding), b.descriptor(set, binding)) {
+                (None, None) => (),
+                (Some(a), Some(b)) => {
+                    if a.ty != b.ty || a.array_count != b.array_count || a.stages != b.stages ||
+                        a.readonly != b.readonly
+                    {
+                        return false;
+                    }
+                },
+                _ => return false,
+            }
+        }
+    }
+
+    if a.num_push_constants_ranges() != b.num_push_constants_ranges() {
+        return false;
+    }
+
+    (0 .. a.num_push_constants_ranges()).all(|n| {
+        match (a.push_constants_range(n), b.push_constants_range(n)) {
+            (None, None) => true,
+            (Some(a), Some(b)) => {
+                a.offset == b.offset && a.size == b.size &&
+                    a.stages == b.stages
+            },
+            _ => false,
+        }
+    })
+}
+
 /// Error that can happen when checking descriptor sets validity.
 #[derive(Debug, Copy, Clone)]
 pub enum CheckDescriptorSetsValidityError {
     /// The descriptor sets are incompatible with the pipeline layout.
     IncompatibleDescriptorSets,
+
+    /// The number of dynamic offsets doesn't match the number of dynamic buffer descriptors.
+    WrongNumberOfDynamicOffsets {
+        /// Number of dynamic uniform and storage buffer descriptors in the sets.
+        expected: usize,
+        /// Number of dynamic offsets that were passed.
+        obtained: usize,
+    },
 }
 
 impl error::Error for CheckDescriptorSetsValidityError {
@@ -37,6 +302,10 @@ impl error::Error for CheckDescriptorSetsValidityError {
             CheckDescriptorSetsValidityError::IncompatibleDescriptorSets => {
                 "the descriptor sets are incompatible with the pipeline layout"
             },
+            CheckDescriptorSetsValidityError::WrongNumberOfDynamicOffsets { .. } => {
+                "the number of dynamic offsets doesn't match the number of dynamic buffer \
+                 descriptors"
+            },
         }
     }
 }
@@ -47,3 +316,281 @@ impl fmt::Display for CheckDescriptorSetsValidityError {
         write!(fmt, "{}", error::Error::description(self))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::iter;
+
+    use buffer::BufferAccess;
+    use descriptor::descriptor::DescriptorBufferContentDesc;
+    use descriptor::descriptor::DescriptorBufferDesc;
+    use descriptor::descriptor::DescriptorDesc;
+    use descriptor::descriptor::DescriptorDescTy;
+    use descriptor::descriptor::ShaderStages;
+    use descriptor::descriptor_set::DescriptorSet;
+    use descriptor::descriptor_set::DescriptorSetsCollection;
+    use descriptor::descriptor_set::DynamicOffsets;
+    use descriptor::pipeline_layout::EmptyPipelineDesc;
+    use descriptor::pipeline_layout::PipelineLayoutDescPcRange;
+    use image::ImageAccess;
+    use super::*;
+
+    // Collection of descriptor sets that only has the descriptions of the sets, so that the
+    // checks can be tested without creating any Vulkan object.
+    struct FakeSets {
+        // Descriptor of each binding of each set.
+        sets: Vec<Vec<Option<DescriptorDesc>>>,
+        // Generation of each set, if known.
+        generations: Vec<Option<usize>>,
+    }
+
+    impl FakeSets {
+        fn new(sets: Vec<Vec<Option<DescriptorDesc>>>) -> FakeSets {
+            FakeSets {
+                sets: sets,
+                generations: Vec::new(),
+            }
+        }
+    }
+
+    unsafe impl DescriptorSetsCollection for FakeSets {
+        fn into_vec(self) -> Vec<Box<DescriptorSet + Send + Sync>> {
+            // There is no actual set behind the descriptions.
+            Vec::new()
+        }
+
+        fn num_bindings_in_set(&self, set: usize) -> Option<usize> {
+            self.sets.get(set).map(|bindings| bindings.len())
+        }
+
+        fn descriptor(&self, set: usize, binding: usize) -> Option<DescriptorDesc> {
+            self.sets
+                .get(set)
+                .and_then(|bindings| bindings.get(binding))
+                .and_then(|desc| desc.clone())
+        }
+
+        fn buffers_list<'a>(&'a self) -> Box<Iterator<Item = &'a BufferAccess> + 'a> {
+            Box::new(iter::empty())
+        }
+
+        fn images_list<'a>(&'a self) -> Box<Iterator<Item = &'a ImageAccess> + 'a> {
+            Box::new(iter::empty())
+        }
+
+        fn generation(&self, set: usize) -> Option<usize> {
+            self.generations.get(set).and_then(|generation| *generation)
+        }
+    }
+
+    // Collection that pretends to contain one set with a dynamic uniform buffer.
+    fn one_dynamic_buffer_set() -> FakeSets {
+        let desc = DescriptorDesc {
+            ty: DescriptorDescTy::Buffer(DescriptorBufferDesc {
+                                             dynamic: Some(true),
+                                             storage: false,
+                                             content: DescriptorBufferContentDesc::F32,
+                                         }),
+            array_count: 1,
+            stages: ShaderStages::all_graphics(),
+            readonly: true,
+        };
+
+        FakeSets::new(vec![vec![Some(desc)]])
+    }
+
+    // Pipeline layout with one set and no push constants.
+    struct OneSetPipelineDesc;
+
+    unsafe impl PipelineLayoutDesc for OneSetPipelineDesc {
+        fn num_sets(&self) -> usize {
+            1
+        }
+
+        fn num_bindings_in_set(&self, set: usize) -> Option<usize> {
+            one_dynamic_buffer_set().num_bindings_in_set(set)
+        }
+
+        fn descriptor(&self, set: usize, binding: usize) -> Option<DescriptorDesc> {
+            one_dynamic_buffer_set().descriptor(set, binding)
+        }
+
+        fn num_push_constants_ranges(&self) -> usize {
+            0
+        }
+
+        fn push_constants_range(&self, _: usize) -> Option<PipelineLayoutDescPcRange> {
+            None
+        }
+    }
+
+    // Same as `one_dynamic_buffer_set`, with a generation.
+    fn tracked_set(generation: usize) -> FakeSets {
+        let mut sets = one_dynamic_buffer_set();
+        sets.generations = vec![Some(generation)];
+        sets
+    }
+
+    #[test]
+    fn no_sets() {
+        assert!(check_descriptor_sets_validity(&EmptyPipelineDesc, &()).is_ok());
+    }
+
+    #[test]
+    fn too_many_sets() {
+        let one_empty_set = FakeSets::new(vec![vec![]]);
+        match check_descriptor_sets_validity(&EmptyPipelineDesc, &one_empty_set) {
+            Err(CheckDescriptorSetsValidityError::IncompatibleDescriptorSets) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn dynamic_offsets() {
+        let sets = DynamicOffsets::new(one_dynamic_buffer_set(), iter::once(256));
+        assert!(check_descriptor_sets_validity(&OneSetPipelineDesc, &sets).is_ok());
+
+        match check_descriptor_sets_validity(&OneSetPipelineDesc, &one_dynamic_buffer_set()) {
+            Err(CheckDescriptorSetsValidityError::WrongNumberOfDynamicOffsets {
+                    expected: 1,
+                    obtained: 0,
+                }) => (),
+            _ => panic!(),
+        }
+
+        let sets = DynamicOffsets::new(one_dynamic_buffer_set(), vec![0, 256]);
+        match check_descriptor_sets_validity(&OneSetPipelineDesc, &sets) {
+            Err(CheckDescriptorSetsValidityError::WrongNumberOfDynamicOffsets {
+                    expected: 1,
+                    obtained: 2,
+                }) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn cache_skips_unchanged_sets() {
+        let mut cache = DescriptorSetsValidityCache::new();
+        let layout_id = 0;
+
+        for _ in 0 .. 1000 {
+            let sets = DynamicOffsets::new(tracked_set(3), iter::once(0));
+            cache.check_with_layout_id(layout_id, &OneSetPipelineDesc, &sets).unwrap();
+        }
+        assert_eq!(cache.misses(), 1);
+        assert_eq!(cache.hits(), 999);
+
+        // The set has been written to.
+        let sets = DynamicOffsets::new(tracked_set(4), iter::once(0));
+        cache.check_with_layout_id(layout_id, &OneSetPipelineDesc, &sets).unwrap();
+        assert_eq!(cache.misses(), 2);
+
+        // Another layout.
+        let sets = DynamicOffsets::new(tracked_set(4), iter::once(0));
+        cache.check_with_layout_id(1, &OneSetPipelineDesc, &sets).unwrap();
+        assert_eq!(cache.misses(), 3);
+        assert_eq!(cache.hits(), 999);
+    }
+
+    #[test]
+    fn cache_keeps_errors() {
+        let mut cache = DescriptorSetsValidityCache::new();
+        let layout_id = 0;
+
+        for _ in 0 .. 2 {
+            match cache.check_with_layout_id(layout_id, &EmptyPipelineDesc, &tracked_set(0)) {
+                Err(CheckDescriptorSetsValidityError::IncompatibleDescriptorSets) => (),
+                _ => panic!(),
+            }
+        }
+        assert_eq!(cache.misses(), 1);
+        assert_eq!(cache.hits(), 1);
+    }
+
+    #[test]
+    fn cache_depends_on_set_index() {
+        let mut cache = DescriptorSetsValidityCache::new();
+        let layout_id = 0;
+
+        let sets = DynamicOffsets::new(tracked_set(7), iter::once(0));
+        cache.check_with_layout_id(layout_id, &OneSetPipelineDesc, &sets).unwrap();
+
+        // The same set used as the second set of the collection, which the layout doesn't have.
+        let mut sets = one_dynamic_buffer_set();
+        sets.sets.insert(0, vec![]);
+        sets.generations = vec![Some(6), Some(7)];
+        let sets = DynamicOffsets::new(sets, iter::once(0));
+        match cache.check_with_layout_id(layout_id, &OneSetPipelineDesc, &sets) {
+            Err(CheckDescriptorSetsValidityError::IncompatibleDescriptorSets) => (),
+            _ => panic!(),
+        }
+        assert_eq!(cache.hits(), 0);
+    }
+
+    #[test]
+    fn cache_checks_dynamic_offsets_every_time() {
+        let mut cache = DescriptorSetsValidityCache::new();
+        let layout_id = 0;
+
+        let sets = DynamicOffsets::new(tracked_set(0), iter::once(0));
+        cache.check_with_layout_id(layout_id, &OneSetPipelineDesc, &sets).unwrap();
+
+        let sets = DynamicOffsets::new(tracked_set(0), vec![0, 256]);
+        match cache.check_with_layout_id(layout_id, &OneSetPipelineDesc, &sets) {
+            Err(CheckDescriptorSetsValidityError::WrongNumberOfDynamicOffsets {
+                    expected: 1,
+                    obtained: 2,
+                }) => (),
+            _ => panic!(),
+        }
+        assert_eq!(cache.hits(), 1);
+    }
+
+    #[test]
+    fn cache_without_generation() {
+        let mut cache = DescriptorSetsValidityCache::new();
+        let layout_id = 0;
+
+        for _ in 0 .. 2 {
+            let sets = DynamicOffsets::new(one_dynamic_buffer_set(), iter::once(0));
+            cache.check_with_layout_id(layout_id, &OneSetPipelineDesc, &sets).unwrap();
+        }
+        assert_eq!(cache.misses(), 2);
+        assert_eq!(cache.hits(), 0);
+    }
+
+    #[test]
+    fn layouts_compatible_compares_bindings() {
+        assert!(layouts_compatible(&OneSetPipelineDesc, &OneSetPipelineDesc));
+        assert!(layouts_compatible(&EmptyPipelineDesc, &EmptyPipelineDesc));
+        assert!(!layouts_compatible(&OneSetPipelineDesc, &EmptyPipelineDesc));
+        assert!(!layouts_compatible(&EmptyPipelineDesc, &OneSetPipelineDesc));
+    }
+
+    #[test]
+    fn missing_descriptor() {
+        let sets = FakeSets::new(vec![vec![None]]);
+        match check_descriptor_sets_validity(&OneSetPipelineDesc, &sets) {
+            Err(CheckDescriptorSetsValidityError::IncompatibleDescriptorSets) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn incompatible_descriptor() {
+        // A storage buffer where the layout has a dynamic uniform buffer.
+        let mut sets = one_dynamic_buffer_set();
+        if let Some(ref mut desc) = sets.sets[0][0] {
+            desc.ty = DescriptorDescTy::Buffer(DescriptorBufferDesc {
+                                                   dynamic: Some(true),
+                                                   storage: true,
+                                                   content: DescriptorBufferContentDesc::F32,
+                                               });
+        }
+        let sets = DynamicOffsets::new(sets, iter::once(0));
+        match check_descriptor_sets_validity(&OneSetPipelineDesc, &sets) {
+            Err(CheckDescriptorSetsValidityError::IncompatibleDescriptorSets) => (),
+            _ => panic!(),
+        }
+    }
+}
@@ -26,6 +26,44 @@ pub fn check_dispatch(device: &Device, dimensions: [u32; 3]) -> Result<(), Check
     Ok(())
 }
 
+/// Computes the number of workgroups to dispatch in order to run at least `elements` invocations
+/// in each dimension, and checks whether it is supported by the device.
+///
+/// `local_size` is the number of invocations in each dimension of a workgroup, as returned by
+/// `ComputePipelineAbstract::local_size`. The last workgroup of a dimension is included even if
+/// it is only partially covered by the elements, so the shader must ignore the invocations that
+/// are out of range. An error is returned if `local_size` is `None` or contains a zero.
+pub fn check_dispatch_elements(device: &Device, local_size: Option<[u32; 3]>,
+                               elements: [u32; 3])
+                               -> Result<[u32; 3], CheckDispatchError> {
+    let local_size = match local_size {
+        Some(local_size) => local_size,
+        None => return Err(CheckDispatchError::UnknownLocalSize),
+    };
+
+    if local_size.iter().any(|&size| size == 0) {
+        return Err(CheckDispatchError::InvalidLocalSize { local_size: local_size });
+    }
+
+    let dimensions = dispatch_group_counts(local_size, elements);
+    check_dispatch(device, dimensions)?;
+    Ok(dimensions)
+}
+
+// Divides each number of elements by the workgroup size, rounding up. Written so that it can't
+// overflow even if the number of elements is close to `u32::max_value()`. The workgroup size
+// must not contain a zero.
+fn dispatch_group_counts(local_size: [u32; 3], elements: [u32; 3]) -> [u32; 3] {
+    let mut counts = [0; 3];
+    for i in 0 .. 3 {
+        counts[i] = elements[i] / local_size[i];
+        if elements[i] % local_size[i] != 0 {
+            counts[i] += 1;
+        }
+    }
+    counts
+}
+
 /// Error that can happen when checking dispatch command validity.
 #[derive(Debug, Copy, Clone)]
 pub enum CheckDispatchError {
@@ -36,6 +74,15 @@ pub enum CheckDispatchError {
         /// The actual supported dimensions.
         max_supported: [u32; 3],
     },
+    /// The workgroup size of the pipeline is unknown, so the number of workgroups can't be
+    /// computed.
+    UnknownLocalSize,
+    /// The workgroup size of the pipeline contains a zero, so the number of workgroups can't be
+    /// computed.
+    InvalidLocalSize {
+        /// The workgroup size of the pipeline.
+        local_size: [u32; 3],
+    },
 }
 
 impl error::Error for CheckDispatchError {
@@ -45,6 +92,12 @@ impl error::Error for CheckDispatchError {
             CheckDispatchError::UnsupportedDimensions { .. } => {
                 "the dimensions are too large for the device's limits"
             },
+            CheckDispatchError::UnknownLocalSize => {
+                "the workgroup size of the pipeline is unknown"
+            },
+            CheckDispatchError::InvalidLocalSize { .. } => {
+                "the workgroup size of the pipeline contains a zero"
+            },
         }
     }
 }
@@ -58,6 +111,7 @@ impl fmt::Display for CheckDispatchError {
 
 #[cfg(test)]
 mod tests {
+    use super::dispatch_group_counts;
     use command_buffer::validity;
 
     #[test]
@@ -78,4 +132,39 @@ mod tests {
             _ => panic!()
         }
     }
+
+    #[test]
+    fn elements_unknown_local_size() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        match validity::check_dispatch_elements(&device, None, [64, 1, 1]) {
+            Err(validity::CheckDispatchError::UnknownLocalSize) => (),
+            _ => panic!()
+        }
+
+        assert_eq!(validity::check_dispatch_elements(&device, Some([64, 1, 1]), [65, 1, 1])
+                       .unwrap(),
+                   [2, 1, 1]);
+    }
+
+    #[test]
+    fn elements_zero_local_size() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        match validity::check_dispatch_elements(&device, Some([64, 0, 1]), [64, 1, 1]) {
+            Err(validity::CheckDispatchError::InvalidLocalSize { local_size }) => {
+                assert_eq!(local_size, [64, 0, 1]);
+            },
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn group_counts_round_up() {
+        assert_eq!(dispatch_group_counts([64, 1, 1], [128, 1, 1]), [2, 1, 1]);
+        assert_eq!(dispatch_group_counts([64, 1, 1], [129, 1, 1]), [3, 1, 1]);
+        assert_eq!(dispatch_group_counts([8, 8, 1], [1, 17, 0]), [1, 3, 0]);
+        assert_eq!(dispatch_group_counts([2, 1, 1], [u32::max_value(), 1, 1]),
+                   [u32::max_value() / 2 + 1, 1, 1]);
+    }
 }
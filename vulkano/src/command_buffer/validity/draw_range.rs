@@ -0,0 +1,171 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::error;
+use std::fmt;
+
+use pipeline::input_assembly::Index;
+
+/// Checks whether the range of indices of an indexed draw fits in the index buffer.
+///
+/// `num_indices` is the number of indices in the bound index buffer, as returned by
+/// `check_index_buffer`.
+#[inline]
+pub fn check_index_range(num_indices: usize, first_index: u32, index_count: u32)
+                         -> Result<(), CheckDrawRangeError> {
+    if first_index as u64 + index_count as u64 > num_indices as u64 {
+        return Err(CheckDrawRangeError::IndexRangeOutOfBounds {
+                       first_index: first_index,
+                       index_count: index_count,
+                       num_indices: num_indices,
+                   });
+    }
+
+    Ok(())
+}
+
+/// Checks whether every index of `indices` refers to a vertex that exists in the vertex buffers.
+///
+/// `num_vertices` is the number of vertices available in the vertex buffers, as returned by
+/// `check_vertex_buffers`. Contrary to `check_index_range`, this has to look at each index and
+/// is therefore more expensive.
+pub fn check_indices_vertex_range<I>(indices: &[I], vertex_offset: i32, num_vertices: u32)
+                                     -> Result<(), CheckDrawRangeError>
+    where I: Index
+{
+    for (position, index) in indices.iter().enumerate() {
+        let vertex = index.to_u32() as i64 + vertex_offset as i64;
+
+        if vertex < 0 || vertex >= num_vertices as i64 {
+            return Err(CheckDrawRangeError::VertexOutOfBounds {
+                           position: position,
+                           vertex: vertex,
+                           num_vertices: num_vertices,
+                       });
+        }
+    }
+
+    Ok(())
+}
+
+/// Error that can happen when checking the range of a draw command.
+#[derive(Debug, Copy, Clone)]
+pub enum CheckDrawRangeError {
+    /// The range of indices goes past the end of the index buffer.
+    IndexRangeOutOfBounds {
+        /// Index of the first index to read.
+        first_index: u32,
+        /// Number of indices to read.
+        index_count: u32,
+        /// Number of indices in the index buffer.
+        num_indices: usize,
+    },
+
+    /// An index refers to a vertex that isn't in the vertex buffers.
+    VertexOutOfBounds {
+        /// Position of the offending index within the range of indices that is drawn.
+        position: usize,
+        /// Vertex the index refers to, after the vertex offset has been added.
+        vertex: i64,
+        /// Number of vertices available in the vertex buffers.
+        num_vertices: u32,
+    },
+}
+
+impl error::Error for CheckDrawRangeError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            CheckDrawRangeError::IndexRangeOutOfBounds { .. } => {
+                "the range of indices goes past the end of the index buffer"
+            },
+            CheckDrawRangeError::VertexOutOfBounds { .. } => {
+                "an index refers to a vertex that isn't in the vertex buffers"
+            },
+        }
+    }
+}
+
+impl fmt::Display for CheckDrawRangeError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            CheckDrawRangeError::IndexRangeOutOfBounds {
+                first_index,
+                index_count,
+                num_indices,
+            } => {
+                write!(fmt,
+                       "{} (first index: {}, index count: {}, indices in the buffer: {})",
+                       error::Error::description(self),
+                       first_index,
+                       index_count,
+                       num_indices)
+            },
+            CheckDrawRangeError::VertexOutOfBounds {
+                position,
+                vertex,
+                num_vertices,
+            } => {
+                write!(fmt,
+                       "{} (index #{} refers to vertex {}, vertices available: {})",
+                       error::Error::description(self),
+                       position,
+                       vertex,
+                       num_vertices)
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn index_range() {
+        assert!(check_index_range(12, 0, 12).is_ok());
+        assert!(check_index_range(12, 6, 6).is_ok());
+
+        match check_index_range(12, 6, 7) {
+            Err(CheckDrawRangeError::IndexRangeOutOfBounds {
+                    first_index: 6,
+                    index_count: 7,
+                    num_indices: 12,
+                }) => (),
+            _ => panic!(),
+        }
+
+        match check_index_range(12, u32::max_value(), 2) {
+            Err(CheckDrawRangeError::IndexRangeOutOfBounds { .. }) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn indices_vertex_range() {
+        let indices: [u16; 6] = [0, 1, 2, 2, 3, 0];
+        assert!(check_indices_vertex_range(&indices, 0, 4).is_ok());
+        assert!(check_indices_vertex_range(&indices, 2, 6).is_ok());
+
+        match check_indices_vertex_range(&indices, 0, 3) {
+            Err(CheckDrawRangeError::VertexOutOfBounds {
+                    position: 4,
+                    vertex: 3,
+                    num_vertices: 3,
+                }) => (),
+            _ => panic!(),
+        }
+
+        match check_indices_vertex_range(&[5u32], -6, 10) {
+            Err(CheckDrawRangeError::VertexOutOfBounds { vertex: -1, .. }) => (),
+            _ => panic!(),
+        }
+    }
+}
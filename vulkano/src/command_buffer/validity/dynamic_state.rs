@@ -71,6 +71,104 @@ pub fn check_dynamic_state_validity<Pl>(pipeline: &Pl, state: &DynamicState)
         }
     }
 
+    if pipeline.has_dynamic_stencil_compare_mask() {
+        if state.stencil_compare_mask.is_none() {
+            return Err(CheckDynamicStateValidityError::StencilCompareMaskMissing);
+        }
+    } else {
+        if state.stencil_compare_mask.is_some() {
+            return Err(CheckDynamicStateValidityError::StencilCompareMaskNotDynamic);
+        }
+    }
+
+    if pipeline.has_dynamic_stencil_write_mask() {
+        if state.stencil_write_mask.is_none() {
+            return Err(CheckDynamicStateValidityError::StencilWriteMaskMissing);
+        }
+    } else {
+        if state.stencil_write_mask.is_some() {
+            return Err(CheckDynamicStateValidityError::StencilWriteMaskNotDynamic);
+        }
+    }
+
+    if pipeline.has_dynamic_stencil_reference() {
+        if state.stencil_reference.is_none() {
+            return Err(CheckDynamicStateValidityError::StencilReferenceMissing);
+        }
+    } else {
+        if state.stencil_reference.is_some() {
+            return Err(CheckDynamicStateValidityError::StencilReferenceNotDynamic);
+        }
+    }
+
+    if pipeline.has_dynamic_depth_test_enable() {
+        if state.depth_test_enable.is_none() {
+            return Err(CheckDynamicStateValidityError::DepthTestEnableMissing);
+        }
+    } else {
+        if state.depth_test_enable.is_some() {
+            return Err(CheckDynamicStateValidityError::DepthTestEnableNotDynamic);
+        }
+    }
+
+    if pipeline.has_dynamic_depth_write_enable() {
+        if state.depth_write_enable.is_none() {
+            return Err(CheckDynamicStateValidityError::DepthWriteEnableMissing);
+        }
+    } else {
+        if state.depth_write_enable.is_some() {
+            return Err(CheckDynamicStateValidityError::DepthWriteEnableNotDynamic);
+        }
+    }
+
+    if pipeline.has_dynamic_depth_compare_op() {
+        if state.depth_compare_op.is_none() {
+            return Err(CheckDynamicStateValidityError::DepthCompareOpMissing);
+        }
+    } else {
+        if state.depth_compare_op.is_some() {
+            return Err(CheckDynamicStateValidityError::DepthCompareOpNotDynamic);
+        }
+    }
+
+    if pipeline.has_dynamic_stencil_test_enable() {
+        if state.stencil_test_enable.is_none() {
+            return Err(CheckDynamicStateValidityError::StencilTestEnableMissing);
+        }
+    } else {
+        if state.stencil_test_enable.is_some() {
+            return Err(CheckDynamicStateValidityError::StencilTestEnableNotDynamic);
+        }
+    }
+
+    if pipeline.has_dynamic_depth_bounds() {
+        if let Some(ref range) = state.depth_bounds {
+            if !(range.start >= 0.0 && range.start <= range.end && range.end <= 1.0) {
+                return Err(CheckDynamicStateValidityError::DepthBoundsInvalidRange);
+            }
+        } else {
+            return Err(CheckDynamicStateValidityError::DepthBoundsMissing);
+        }
+    } else {
+        if state.depth_bounds.is_some() {
+            return Err(CheckDynamicStateValidityError::DepthBoundsNotDynamic);
+        }
+    }
+
+    if pipeline.has_dynamic_depth_bias() {
+        if let Some(bias) = state.depth_bias {
+            if bias.clamp != 0.0 && !device.enabled_features().depth_bias_clamp {
+                return Err(CheckDynamicStateValidityError::DepthBiasClampFeatureNotEnabled);
+            }
+        } else {
+            return Err(CheckDynamicStateValidityError::DepthBiasMissing);
+        }
+    } else {
+        if state.depth_bias.is_some() {
+            return Err(CheckDynamicStateValidityError::DepthBiasNotDynamic);
+        }
+    }
+
     // TODO: don't forget to implement the rest
 
     Ok(())
@@ -108,6 +206,55 @@ pub enum CheckDynamicStateValidityError {
         /// Number of scissors that were passed.
         obtained: usize,
     },
+    /// Passed a dynamic stencil compare mask, while the pipeline doesn't have the compare mask
+    /// set as dynamic.
+    StencilCompareMaskNotDynamic,
+    /// The pipeline has a dynamic stencil compare mask, but no compare mask was passed.
+    StencilCompareMaskMissing,
+    /// Passed a dynamic stencil write mask, while the pipeline doesn't have the write mask set as
+    /// dynamic.
+    StencilWriteMaskNotDynamic,
+    /// The pipeline has a dynamic stencil write mask, but no write mask was passed.
+    StencilWriteMaskMissing,
+    /// Passed a dynamic stencil reference, while the pipeline doesn't have the reference set as
+    /// dynamic.
+    StencilReferenceNotDynamic,
+    /// The pipeline has a dynamic stencil reference, but no reference was passed.
+    StencilReferenceMissing,
+    /// Passed a dynamic depth test enable value, while the pipeline doesn't have it set as
+    /// dynamic.
+    DepthTestEnableNotDynamic,
+    /// The pipeline has a dynamic depth test enable value, but no value was passed.
+    DepthTestEnableMissing,
+    /// Passed a dynamic depth write enable value, while the pipeline doesn't have it set as
+    /// dynamic.
+    DepthWriteEnableNotDynamic,
+    /// The pipeline has a dynamic depth write enable value, but no value was passed.
+    DepthWriteEnableMissing,
+    /// Passed a dynamic depth compare op value, while the pipeline doesn't have it set as
+    /// dynamic.
+    DepthCompareOpNotDynamic,
+    /// The pipeline has a dynamic depth compare op value, but no value was passed.
+    DepthCompareOpMissing,
+    /// Passed a dynamic stencil test enable value, while the pipeline doesn't have it set as
+    /// dynamic.
+    StencilTestEnableNotDynamic,
+    /// The pipeline has a dynamic stencil test enable value, but no value was passed.
+    StencilTestEnableMissing,
+    /// Passed dynamic depth bounds, while the pipeline doesn't have them set as dynamic.
+    DepthBoundsNotDynamic,
+    /// The pipeline has dynamic depth bounds, but no depth bounds were passed.
+    DepthBoundsMissing,
+    /// The depth bounds must be between 0.0 and 1.0, and the minimum must not be greater than the
+    /// maximum.
+    DepthBoundsInvalidRange,
+    /// Passed a dynamic depth bias, while the pipeline doesn't have it set as dynamic.
+    DepthBiasNotDynamic,
+    /// The pipeline has a dynamic depth bias, but no depth bias was passed.
+    DepthBiasMissing,
+    /// The `depth_bias_clamp` feature must be enabled in order to use a depth bias clamp
+    /// different from 0.0.
+    DepthBiasClampFeatureNotEnabled,
 }
 
 impl error::Error for CheckDynamicStateValidityError {
@@ -144,6 +291,75 @@ impl error::Error for CheckDynamicStateValidityError {
             CheckDynamicStateValidityError::ScissorsCountMismatch { .. } => {
                 "the number of dynamic scissors doesn't match the expected number of scissors"
             },
+            CheckDynamicStateValidityError::StencilCompareMaskNotDynamic => {
+                "passed a dynamic stencil compare mask, while the pipeline doesn't have the \
+                 compare mask set as dynamic"
+            },
+            CheckDynamicStateValidityError::StencilCompareMaskMissing => {
+                "the pipeline has a dynamic stencil compare mask, but no compare mask was passed"
+            },
+            CheckDynamicStateValidityError::StencilWriteMaskNotDynamic => {
+                "passed a dynamic stencil write mask, while the pipeline doesn't have the write \
+                 mask set as dynamic"
+            },
+            CheckDynamicStateValidityError::StencilWriteMaskMissing => {
+                "the pipeline has a dynamic stencil write mask, but no write mask was passed"
+            },
+            CheckDynamicStateValidityError::StencilReferenceNotDynamic => {
+                "passed a dynamic stencil reference, while the pipeline doesn't have the \
+                 reference set as dynamic"
+            },
+            CheckDynamicStateValidityError::StencilReferenceMissing => {
+                "the pipeline has a dynamic stencil reference, but no reference was passed"
+            },
+            CheckDynamicStateValidityError::DepthTestEnableNotDynamic => {
+                "passed a dynamic depth test enable value, while the pipeline doesn\'t have \
+                 it set as dynamic"
+            },
+            CheckDynamicStateValidityError::DepthTestEnableMissing => {
+                "the pipeline has a dynamic depth test enable value, but no value was passed"
+            },
+            CheckDynamicStateValidityError::DepthWriteEnableNotDynamic => {
+                "passed a dynamic depth write enable value, while the pipeline doesn\'t have \
+                 it set as dynamic"
+            },
+            CheckDynamicStateValidityError::DepthWriteEnableMissing => {
+                "the pipeline has a dynamic depth write enable value, but no value was passed"
+            },
+            CheckDynamicStateValidityError::DepthCompareOpNotDynamic => {
+                "passed a dynamic depth compare op value, while the pipeline doesn\'t have \
+                 it set as dynamic"
+            },
+            CheckDynamicStateValidityError::DepthCompareOpMissing => {
+                "the pipeline has a dynamic depth compare op value, but no value was passed"
+            },
+            CheckDynamicStateValidityError::StencilTestEnableNotDynamic => {
+                "passed a dynamic stencil test enable value, while the pipeline doesn\'t have \
+                 it set as dynamic"
+            },
+            CheckDynamicStateValidityError::StencilTestEnableMissing => {
+                "the pipeline has a dynamic stencil test enable value, but no value was passed"
+            },
+            CheckDynamicStateValidityError::DepthBoundsNotDynamic => {
+                "passed dynamic depth bounds, while the pipeline doesn't have them set as dynamic"
+            },
+            CheckDynamicStateValidityError::DepthBoundsMissing => {
+                "the pipeline has dynamic depth bounds, but no depth bounds were passed"
+            },
+            CheckDynamicStateValidityError::DepthBoundsInvalidRange => {
+                "the depth bounds must be between 0.0 and 1.0, and the minimum must not be \
+                 greater than the maximum"
+            },
+            CheckDynamicStateValidityError::DepthBiasNotDynamic => {
+                "passed a dynamic depth bias, while the pipeline doesn't have it set as dynamic"
+            },
+            CheckDynamicStateValidityError::DepthBiasMissing => {
+                "the pipeline has a dynamic depth bias, but no depth bias was passed"
+            },
+            CheckDynamicStateValidityError::DepthBiasClampFeatureNotEnabled => {
+                "the `depth_bias_clamp` feature must be enabled in order to use a depth bias \
+                 clamp different from 0.0"
+            },
         }
     }
 }
@@ -156,3 +372,369 @@ impl fmt::Display for CheckDynamicStateValidityError {
 }
 
 // TODO: tests
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CStr;
+    use std::iter;
+    use std::sync::Arc;
+
+    use command_buffer::DynamicState;
+    use descriptor::pipeline_layout::EmptyPipelineDesc;
+    use format::Format;
+    use framebuffer::Subpass;
+    use pipeline::GraphicsPipeline;
+    use pipeline::depth_stencil::Compare;
+    use pipeline::depth_stencil::DynamicStencilValue;
+    use pipeline::raster::DepthBias;
+    use pipeline::shader::EmptyShaderInterfaceDef;
+    use pipeline::shader::ShaderModule;
+    use pipeline::viewport::Viewport;
+    use tests::BASIC_FS;
+    use tests::FULL_SCREEN_VS;
+    use super::*;
+
+    // Builds a pipeline without any other dynamic state than the ones enabled by calling
+    // `$method` on the builder.
+    macro_rules! pipeline {
+        ($device:expr, $method:ident) => ({
+            let vs = unsafe { ShaderModule::new($device.clone(), &FULL_SCREEN_VS) }.unwrap();
+            let fs = unsafe { ShaderModule::new($device.clone(), &BASIC_FS) }.unwrap();
+            let render_pass = Arc::new(single_pass_renderpass!($device.clone(),
+                attachments: {
+                    color: { load: Clear, store: Store, format: Format::R8G8B8A8Unorm, samples: 1, }
+                },
+                pass: { color: [color], depth_stencil: {} }
+            ).unwrap());
+
+            let (vs_main, fs_main) = unsafe {
+                let name = CStr::from_bytes_with_nul_unchecked(b"main\0");
+                (vs.vertex_shader_entry_point::<(), _, _, _>(name,
+                                                             EmptyShaderInterfaceDef,
+                                                             EmptyShaderInterfaceDef,
+                                                             EmptyPipelineDesc),
+                 fs.fragment_shader_entry_point::<(), _, _, _>(name,
+                                                               EmptyShaderInterfaceDef,
+                                                               EmptyShaderInterfaceDef,
+                                                               EmptyPipelineDesc))
+            };
+
+            GraphicsPipeline::start()
+                .vertex_shader(vs_main, ())
+                .triangle_list()
+                .viewports(iter::once(Viewport {
+                                          origin: [0.0, 0.0],
+                                          dimensions: [16.0, 16.0],
+                                          depth_range: 0.0 .. 1.0,
+                                      }))
+                .fragment_shader(fs_main, ())
+                .$method()
+                .render_pass(Subpass::from(render_pass, 0).unwrap())
+                .build($device.clone())
+                .unwrap()
+        })
+    }
+
+    // A depth bias with the given clamp.
+    fn bias(clamp: f32) -> DynamicState {
+        DynamicState {
+            depth_bias: Some(DepthBias {
+                                 constant_factor: 1.0,
+                                 clamp: clamp,
+                                 slope_factor: 1.0,
+                             }),
+            ..DynamicState::none()
+        }
+    }
+
+    #[test]
+    fn depth_bias_missing() {
+        let (device, _) = gfx_dev_and_queue!();
+        let pipeline = pipeline!(device, depth_bias_dynamic);
+
+        match check_dynamic_state_validity(&pipeline, &DynamicState::none()) {
+            Err(CheckDynamicStateValidityError::DepthBiasMissing) => (),
+            _ => panic!(),
+        }
+        assert!(check_dynamic_state_validity(&pipeline, &bias(0.0)).is_ok());
+    }
+
+    #[test]
+    fn depth_bias_not_dynamic() {
+        let (device, _) = gfx_dev_and_queue!();
+        let pipeline = pipeline!(device, depth_bias_disabled);
+
+        assert!(check_dynamic_state_validity(&pipeline, &DynamicState::none()).is_ok());
+        match check_dynamic_state_validity(&pipeline, &bias(0.0)) {
+            Err(CheckDynamicStateValidityError::DepthBiasNotDynamic) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn depth_bias_clamp_feature_not_enabled() {
+        let (device, _) = gfx_dev_and_queue!();
+        let pipeline = pipeline!(device, depth_bias_dynamic);
+
+        match check_dynamic_state_validity(&pipeline, &bias(0.5)) {
+            Err(CheckDynamicStateValidityError::DepthBiasClampFeatureNotEnabled) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn depth_bias_clamp_feature_enabled() {
+        let (device, _) = gfx_dev_and_queue!(depth_bias_clamp);
+        let pipeline = pipeline!(device, depth_bias_dynamic);
+
+        assert!(check_dynamic_state_validity(&pipeline, &bias(0.5)).is_ok());
+    }
+
+    #[test]
+    fn stencil_not_dynamic() {
+        let (device, _) = gfx_dev_and_queue!();
+        let pipeline = pipeline!(device, stencil_disabled);
+
+        assert!(check_dynamic_state_validity(&pipeline, &DynamicState::none()).is_ok());
+
+        let state = DynamicState {
+            stencil_compare_mask: Some(DynamicStencilValue::both(1)),
+            ..DynamicState::none()
+        };
+        match check_dynamic_state_validity(&pipeline, &state) {
+            Err(CheckDynamicStateValidityError::StencilCompareMaskNotDynamic) => (),
+            _ => panic!(),
+        }
+
+        let state = DynamicState {
+            stencil_write_mask: Some(DynamicStencilValue::both(1)),
+            ..DynamicState::none()
+        };
+        match check_dynamic_state_validity(&pipeline, &state) {
+            Err(CheckDynamicStateValidityError::StencilWriteMaskNotDynamic) => (),
+            _ => panic!(),
+        }
+
+        let state = DynamicState {
+            stencil_reference: Some(DynamicStencilValue::both(1)),
+            ..DynamicState::none()
+        };
+        match check_dynamic_state_validity(&pipeline, &state) {
+            Err(CheckDynamicStateValidityError::StencilReferenceNotDynamic) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn stencil_compare_mask_missing() {
+        let (device, _) = gfx_dev_and_queue!();
+        let pipeline = pipeline!(device, stencil_compare_mask_dynamic);
+        assert!(pipeline.has_dynamic_stencil_compare_mask());
+
+        match check_dynamic_state_validity(&pipeline, &DynamicState::none()) {
+            Err(CheckDynamicStateValidityError::StencilCompareMaskMissing) => (),
+            _ => panic!(),
+        }
+
+        let state = DynamicState {
+            stencil_compare_mask: Some(DynamicStencilValue::both(0xff)),
+            ..DynamicState::none()
+        };
+        assert!(check_dynamic_state_validity(&pipeline, &state).is_ok());
+    }
+
+    #[test]
+    fn stencil_write_mask_missing() {
+        let (device, _) = gfx_dev_and_queue!();
+        let pipeline = pipeline!(device, stencil_write_mask_dynamic);
+        assert!(pipeline.has_dynamic_stencil_write_mask());
+
+        match check_dynamic_state_validity(&pipeline, &DynamicState::none()) {
+            Err(CheckDynamicStateValidityError::StencilWriteMaskMissing) => (),
+            _ => panic!(),
+        }
+
+        let state = DynamicState {
+            stencil_write_mask: Some(DynamicStencilValue::both(0xff)),
+            ..DynamicState::none()
+        };
+        assert!(check_dynamic_state_validity(&pipeline, &state).is_ok());
+    }
+
+    #[test]
+    fn stencil_reference_missing() {
+        let (device, _) = gfx_dev_and_queue!();
+        let pipeline = pipeline!(device, stencil_reference_dynamic);
+        assert!(pipeline.has_dynamic_stencil_reference());
+
+        match check_dynamic_state_validity(&pipeline, &DynamicState::none()) {
+            Err(CheckDynamicStateValidityError::StencilReferenceMissing) => (),
+            _ => panic!(),
+        }
+
+        let state = DynamicState {
+            stencil_reference: Some(DynamicStencilValue::both(1)),
+            ..DynamicState::none()
+        };
+        assert!(check_dynamic_state_validity(&pipeline, &state).is_ok());
+    }
+
+    #[test]
+    fn depth_bounds_missing() {
+        let (device, _) = gfx_dev_and_queue!(depth_bounds);
+        let pipeline = pipeline!(device, depth_bounds_dynamic);
+
+        match check_dynamic_state_validity(&pipeline, &DynamicState::none()) {
+            Err(CheckDynamicStateValidityError::DepthBoundsMissing) => (),
+            _ => panic!(),
+        }
+
+        let state = DynamicState {
+            depth_bounds: Some(0.25 .. 0.75),
+            ..DynamicState::none()
+        };
+        assert!(check_dynamic_state_validity(&pipeline, &state).is_ok());
+    }
+
+    #[test]
+    fn depth_bounds_invalid_range() {
+        let (device, _) = gfx_dev_and_queue!(depth_bounds);
+        let pipeline = pipeline!(device, depth_bounds_dynamic);
+
+        for range in vec![0.75 .. 0.25, -0.5 .. 0.5, 0.5 .. 1.5] {
+            let state = DynamicState {
+                depth_bounds: Some(range),
+                ..DynamicState::none()
+            };
+            match check_dynamic_state_validity(&pipeline, &state) {
+                Err(CheckDynamicStateValidityError::DepthBoundsInvalidRange) => (),
+                _ => panic!(),
+            }
+        }
+    }
+
+    #[test]
+    fn depth_bounds_not_dynamic() {
+        let (device, _) = gfx_dev_and_queue!();
+        let pipeline = pipeline!(device, depth_bounds_disabled);
+
+        let state = DynamicState {
+            depth_bounds: Some(0.0 .. 1.0),
+            ..DynamicState::none()
+        };
+        match check_dynamic_state_validity(&pipeline, &state) {
+            Err(CheckDynamicStateValidityError::DepthBoundsNotDynamic) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn extended_dynamic_state_not_dynamic() {
+        let (device, _) = gfx_dev_and_queue!();
+        let pipeline = pipeline!(device, depth_stencil_disabled);
+
+        let state = DynamicState {
+            depth_test_enable: Some(true),
+            ..DynamicState::none()
+        };
+        match check_dynamic_state_validity(&pipeline, &state) {
+            Err(CheckDynamicStateValidityError::DepthTestEnableNotDynamic) => (),
+            _ => panic!(),
+        }
+
+        let state = DynamicState {
+            depth_write_enable: Some(true),
+            ..DynamicState::none()
+        };
+        match check_dynamic_state_validity(&pipeline, &state) {
+            Err(CheckDynamicStateValidityError::DepthWriteEnableNotDynamic) => (),
+            _ => panic!(),
+        }
+
+        let state = DynamicState {
+            depth_compare_op: Some(Compare::Less),
+            ..DynamicState::none()
+        };
+        match check_dynamic_state_validity(&pipeline, &state) {
+            Err(CheckDynamicStateValidityError::DepthCompareOpNotDynamic) => (),
+            _ => panic!(),
+        }
+
+        let state = DynamicState {
+            stencil_test_enable: Some(true),
+            ..DynamicState::none()
+        };
+        match check_dynamic_state_validity(&pipeline, &state) {
+            Err(CheckDynamicStateValidityError::StencilTestEnableNotDynamic) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn depth_test_enable_missing() {
+        let (device, _) = gfx_dev_and_queue!(extensions: [ext_extended_dynamic_state]);
+        let pipeline = pipeline!(device, depth_test_enable_dynamic);
+
+        match check_dynamic_state_validity(&pipeline, &DynamicState::none()) {
+            Err(CheckDynamicStateValidityError::DepthTestEnableMissing) => (),
+            _ => panic!(),
+        }
+
+        let state = DynamicState {
+            depth_test_enable: Some(false),
+            ..DynamicState::none()
+        };
+        assert!(check_dynamic_state_validity(&pipeline, &state).is_ok());
+    }
+
+    #[test]
+    fn depth_write_enable_missing() {
+        let (device, _) = gfx_dev_and_queue!(extensions: [ext_extended_dynamic_state]);
+        let pipeline = pipeline!(device, depth_write_enable_dynamic);
+
+        match check_dynamic_state_validity(&pipeline, &DynamicState::none()) {
+            Err(CheckDynamicStateValidityError::DepthWriteEnableMissing) => (),
+            _ => panic!(),
+        }
+
+        let state = DynamicState {
+            depth_write_enable: Some(false),
+            ..DynamicState::none()
+        };
+        assert!(check_dynamic_state_validity(&pipeline, &state).is_ok());
+    }
+
+    #[test]
+    fn depth_compare_op_missing() {
+        let (device, _) = gfx_dev_and_queue!(extensions: [ext_extended_dynamic_state]);
+        let pipeline = pipeline!(device, depth_compare_op_dynamic);
+
+        match check_dynamic_state_validity(&pipeline, &DynamicState::none()) {
+            Err(CheckDynamicStateValidityError::DepthCompareOpMissing) => (),
+            _ => panic!(),
+        }
+
+        let state = DynamicState {
+            depth_compare_op: Some(Compare::Always),
+            ..DynamicState::none()
+        };
+        assert!(check_dynamic_state_validity(&pipeline, &state).is_ok());
+    }
+
+    #[test]
+    fn stencil_test_enable_missing() {
+        let (device, _) = gfx_dev_and_queue!(extensions: [ext_extended_dynamic_state]);
+        let pipeline = pipeline!(device, stencil_test_enable_dynamic);
+
+        match check_dynamic_state_validity(&pipeline, &DynamicState::none()) {
+            Err(CheckDynamicStateValidityError::StencilTestEnableMissing) => (),
+            _ => panic!(),
+        }
+
+        let state = DynamicState {
+            stencil_test_enable: Some(false),
+            ..DynamicState::none()
+        };
+        assert!(check_dynamic_state_validity(&pipeline, &state).is_ok());
+    }
+}
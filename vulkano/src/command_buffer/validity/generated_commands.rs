@@ -0,0 +1,153 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::error;
+use std::fmt;
+
+use VulkanObject;
+use buffer::BufferAccess;
+use command_buffer::indirect_commands::IndirectCommandsLayout;
+use device::Device;
+use device::DeviceOwned;
+use pipeline::GraphicsPipelineAbstract;
+
+/// Checks whether the execution of generated commands is valid.
+///
+/// # Panic
+///
+/// - Panics if the layout, the pipeline or the buffers were not created with `device`.
+///
+pub fn check_generated_commands<Gp, L>(device: &Device, pipeline: &Gp,
+                                       layout: &IndirectCommandsLayout<L>,
+                                       streams: &[Box<BufferAccess + Send + Sync>],
+                                       sequences_count: u32, preprocess: &BufferAccess)
+                                       -> Result<(), CheckGeneratedCommandsError>
+    where Gp: ?Sized + GraphicsPipelineAbstract
+{
+    assert_eq!(layout.device().internal_object(), device.internal_object());
+    assert_eq!(pipeline.device().internal_object(), device.internal_object());
+    assert_eq!(preprocess.inner().buffer.device().internal_object(),
+               device.internal_object());
+
+    if !pipeline.is_indirect_bindable() {
+        return Err(CheckGeneratedCommandsError::PipelineNotIndirectBindable);
+    }
+
+    let properties = layout.properties();
+
+    if streams.len() != layout.stream_strides().len() {
+        return Err(CheckGeneratedCommandsError::WrongNumberOfStreams {
+                       expected: layout.stream_strides().len() as u32,
+                       obtained: streams.len() as u32,
+                   });
+    }
+
+    if sequences_count > properties.max_indirect_sequence_count {
+        return Err(CheckGeneratedCommandsError::MaxSequencesExceeded {
+                       max: properties.max_indirect_sequence_count,
+                       obtained: sequences_count,
+                   });
+    }
+
+    let alignment = properties.min_indirect_commands_buffer_offset_alignment as usize;
+    for (num, (stream, &stride)) in streams.iter().zip(layout.stream_strides()).enumerate() {
+        let inner = stream.inner();
+        assert_eq!(inner.buffer.device().internal_object(), device.internal_object());
+
+        if !inner.buffer.usage_indirect_buffer() {
+            return Err(CheckGeneratedCommandsError::BufferMissingUsage);
+        }
+
+        if alignment != 0 && (inner.offset % alignment) != 0 {
+            return Err(CheckGeneratedCommandsError::StreamOffsetNotAligned { stream: num as u32 });
+        }
+
+        let required = stride as usize * sequences_count as usize;
+        if stream.size() < required {
+            return Err(CheckGeneratedCommandsError::StreamTooSmall {
+                           stream: num as u32,
+                           required: required,
+                           obtained: stream.size(),
+                       });
+        }
+    }
+
+    if !preprocess.inner().buffer.usage_indirect_buffer() {
+        return Err(CheckGeneratedCommandsError::BufferMissingUsage);
+    }
+
+    let required = layout.preprocess_requirements(pipeline, sequences_count).size;
+    if preprocess.size() < required {
+        return Err(CheckGeneratedCommandsError::PreprocessBufferTooSmall {
+                       required: required,
+                       obtained: preprocess.size(),
+                   });
+    }
+
+    Ok(())
+}
+
+/// Error that can happen when checking the execution of generated commands.
+#[derive(Debug, Copy, Clone)]
+pub enum CheckGeneratedCommandsError {
+    /// The pipeline wasn't created with `indirect_bindable`.
+    PipelineNotIndirectBindable,
+    /// The number of streams doesn't match the layout.
+    WrongNumberOfStreams { expected: u32, obtained: u32 },
+    /// The maximum number of sequences was exceeded.
+    MaxSequencesExceeded { max: u32, obtained: u32 },
+    /// A stream or the preprocess buffer is missing the indirect buffer usage.
+    BufferMissingUsage,
+    /// The offset of a stream within its buffer is not properly aligned.
+    StreamOffsetNotAligned { stream: u32 },
+    /// A stream doesn't contain enough data for all the sequences.
+    StreamTooSmall {
+        stream: u32,
+        required: usize,
+        obtained: usize,
+    },
+    /// The preprocess buffer is smaller than what the implementation requires.
+    PreprocessBufferTooSmall { required: usize, obtained: usize },
+}
+
+impl error::Error for CheckGeneratedCommandsError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            CheckGeneratedCommandsError::PipelineNotIndirectBindable => {
+                "the pipeline wasn't created with `indirect_bindable`"
+            },
+            CheckGeneratedCommandsError::WrongNumberOfStreams { .. } => {
+                "the number of streams doesn't match the layout"
+            },
+            CheckGeneratedCommandsError::MaxSequencesExceeded { .. } => {
+                "the maximum number of sequences was exceeded"
+            },
+            CheckGeneratedCommandsError::BufferMissingUsage => {
+                "a stream or the preprocess buffer is missing the indirect buffer usage"
+            },
+            CheckGeneratedCommandsError::StreamOffsetNotAligned { .. } => {
+                "the offset of a stream within its buffer is not properly aligned"
+            },
+            CheckGeneratedCommandsError::StreamTooSmall { .. } => {
+                "a stream doesn't contain enough data for all the sequences"
+            },
+            CheckGeneratedCommandsError::PreprocessBufferTooSmall { .. } => {
+                "the preprocess buffer is smaller than what the implementation requires"
+            },
+        }
+    }
+}
+
+impl fmt::Display for CheckGeneratedCommandsError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
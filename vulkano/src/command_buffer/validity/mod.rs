@@ -9,22 +9,40 @@
 
 //! Functions that check the validity of commands.
 
+pub use self::begin_rendering::{check_begin_rendering, CheckBeginRenderingError};
+pub use self::clear_values::{check_clear_values, CheckClearValuesError};
 pub use self::copy_buffer::{CheckCopyBufferError, check_copy_buffer, CheckCopyBuffer};
-pub use self::descriptor_sets::{check_descriptor_sets_validity, CheckDescriptorSetsValidityError};
-pub use self::dispatch::{check_dispatch, CheckDispatchError};
+pub use self::copy_buffer_to_image::{CheckCopyBufferToImageError, check_copy_buffer_to_image};
+pub use self::copy_image_to_buffer::{CheckCopyImageToBufferError, check_copy_image_to_buffer};
+pub use self::debug_label::{check_debug_label, check_end_debug_label, CheckDebugLabelError};
+pub use self::descriptor_sets::{check_descriptor_sets_validity, CheckDescriptorSetsValidityError, DescriptorSetsValidityCache};
+pub use self::dispatch::{check_dispatch, check_dispatch_elements, CheckDispatchError};
+pub use self::draw_range::{check_index_range, check_indices_vertex_range, CheckDrawRangeError};
 pub use self::dynamic_state::{CheckDynamicStateValidityError, check_dynamic_state_validity};
 pub use self::fill_buffer::{CheckFillBufferError, check_fill_buffer};
+pub use self::generated_commands::{check_generated_commands, CheckGeneratedCommandsError};
 pub use self::index_buffer::{check_index_buffer, CheckIndexBuffer, CheckIndexBufferError};
 pub use self::push_constants::{check_push_constants_validity, CheckPushConstantsValidityError};
+pub use self::query::{check_reset_query_pool, check_write_timestamp, CheckQueryError};
+pub use self::render_area::{check_render_area, CheckRenderAreaError};
 pub use self::update_buffer::{CheckUpdateBufferError, check_update_buffer};
 pub use self::vertex_buffers::{check_vertex_buffers, CheckVertexBuffer, CheckVertexBufferError};
 
+mod begin_rendering;
+mod clear_values;
 mod copy_buffer;
+mod copy_buffer_to_image;
+mod copy_image_to_buffer;
+mod debug_label;
 mod descriptor_sets;
 mod dispatch;
+mod draw_range;
 mod dynamic_state;
 mod fill_buffer;
+mod generated_commands;
 mod index_buffer;
 mod push_constants;
+mod query;
+mod render_area;
 mod update_buffer;
 mod vertex_buffers;
@@ -0,0 +1,98 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::error;
+use std::fmt;
+use std::ops::Range;
+
+use VulkanObject;
+use device::Device;
+use query::QueryType;
+use query::UnsafeQueryPool;
+use sync::PipelineStages;
+
+/// Checks whether a reset query pool command is valid.
+///
+/// # Panic
+///
+/// - Panics if the query pool was not created with `device`.
+///
+pub fn check_reset_query_pool(device: &Device, pool: &UnsafeQueryPool, queries: Range<u32>)
+                              -> Result<(), CheckQueryError> {
+    assert_eq!(pool.device().internal_object(), device.internal_object());
+
+    if queries.start > queries.end || queries.end > pool.num_slots() {
+        return Err(CheckQueryError::OutOfRange);
+    }
+
+    Ok(())
+}
+
+/// Checks whether a write timestamp command is valid.
+///
+/// # Panic
+///
+/// - Panics if the query pool was not created with `device`.
+///
+pub fn check_write_timestamp(device: &Device, pool: &UnsafeQueryPool, query: u32,
+                             stage: PipelineStages)
+                             -> Result<(), CheckQueryError> {
+    assert_eq!(pool.device().internal_object(), device.internal_object());
+
+    match pool.ty() {
+        QueryType::Timestamp => (),
+        _ => return Err(CheckQueryError::NotTimestampPool),
+    }
+
+    if query >= pool.num_slots() {
+        return Err(CheckQueryError::OutOfRange);
+    }
+
+    let bits: u32 = stage.into();
+    if bits.count_ones() != 1 || stage.host {
+        return Err(CheckQueryError::InvalidStage);
+    }
+
+    Ok(())
+}
+
+/// Error that can happen when attempting to add a command that uses queries.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CheckQueryError {
+    /// The queries are out of range of the slots of the query pool.
+    OutOfRange,
+    /// The query pool doesn't contain timestamp queries.
+    NotTimestampPool,
+    /// The pipeline stage of a timestamp must contain exactly one stage, other than `host`.
+    InvalidStage,
+}
+
+impl error::Error for CheckQueryError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            CheckQueryError::OutOfRange => {
+                "the queries are out of range of the slots of the query pool"
+            },
+            CheckQueryError::NotTimestampPool => {
+                "the query pool doesn't contain timestamp queries"
+            },
+            CheckQueryError::InvalidStage => {
+                "the pipeline stage of a timestamp must contain exactly one stage, other than host"
+            },
+        }
+    }
+}
+
+impl fmt::Display for CheckQueryError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
@@ -0,0 +1,192 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::error;
+use std::fmt;
+
+use framebuffer::FramebufferAbstract;
+use image::ImageLayout;
+
+/// Checks whether a render area can be used to begin a render pass with a framebuffer.
+///
+/// The area, described by its `offset` and `extent` in pixels, must be non-empty and must be
+/// within the dimensions of the framebuffer.
+///
+/// If the area doesn't cover the whole framebuffer, no attachment must have the `Undefined`
+/// initial layout. Transitioning from this layout discards the content of the whole attachment,
+/// including outside of the render area. Attachments whose load operation is `Clear` or
+/// `DontCare` get this layout by default; give them an explicit `initial_layout` in order to keep
+/// the content outside of the render area.
+pub fn check_render_area<F>(framebuffer: &F, offset: [u32; 2], extent: [u32; 2])
+                            -> Result<(), CheckRenderAreaError>
+    where F: ?Sized + FramebufferAbstract
+{
+    if extent[0] == 0 || extent[1] == 0 {
+        return Err(CheckRenderAreaError::EmptyRenderArea);
+    }
+
+    let dimensions = framebuffer.dimensions();
+    for i in 0 .. 2 {
+        if offset[i] as u64 + extent[i] as u64 > dimensions[i] as u64 {
+            return Err(CheckRenderAreaError::OutOfBounds {
+                           offset: offset,
+                           extent: extent,
+                           framebuffer_dimensions: [dimensions[0], dimensions[1]],
+                       });
+        }
+    }
+
+    let full = offset == [0, 0] && extent == [dimensions[0], dimensions[1]];
+    if !full {
+        for num in 0 .. framebuffer.num_attachments() {
+            let desc = framebuffer.attachment_desc(num).unwrap();
+            if desc.initial_layout == ImageLayout::Undefined {
+                return Err(CheckRenderAreaError::AttachmentContentDiscarded { attachment: num });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Error that can happen when checking the render area of a render pass.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CheckRenderAreaError {
+    /// The width or the height of the render area is 0.
+    EmptyRenderArea,
+
+    /// The render area is not within the dimensions of the framebuffer.
+    OutOfBounds {
+        /// Offset of the render area.
+        offset: [u32; 2],
+        /// Extent of the render area.
+        extent: [u32; 2],
+        /// Width and height of the framebuffer.
+        framebuffer_dimensions: [u32; 2],
+    },
+
+    /// The render area doesn't cover the whole framebuffer, but an attachment has the `Undefined`
+    /// initial layout and would lose its content outside of the render area.
+    AttachmentContentDiscarded {
+        /// Index of the attachment.
+        attachment: usize,
+    },
+}
+
+impl error::Error for CheckRenderAreaError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            CheckRenderAreaError::EmptyRenderArea => {
+                "the width or the height of the render area is 0"
+            },
+            CheckRenderAreaError::OutOfBounds { .. } => {
+                "the render area is not within the dimensions of the framebuffer"
+            },
+            CheckRenderAreaError::AttachmentContentDiscarded { .. } => {
+                "the render area doesn't cover the whole framebuffer, but an attachment has the \
+                 `Undefined` initial layout and would lose its content outside of the render area"
+            },
+        }
+    }
+}
+
+impl fmt::Display for CheckRenderAreaError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use command_buffer::validity;
+    use format::Format;
+    use framebuffer::Framebuffer;
+    use image::AttachmentImage;
+    use image::ImageLayout;
+    use {ordered_passes_renderpass, single_pass_renderpass};
+    use std::sync::Arc;
+
+    #[test]
+    fn render_area_bounds() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let rp = Arc::new(single_pass_renderpass! {
+            device.clone(),
+            attachments: {
+                atlas: {
+                    load: DontCare,
+                    store: Store,
+                    format: Format::D16Unorm,
+                    samples: 1,
+                    initial_layout: ImageLayout::DepthStencilAttachmentOptimal,
+                }
+            },
+            pass: {
+                color: [],
+                depth_stencil: {atlas}
+            }
+        }.unwrap());
+
+        let image = AttachmentImage::new(device.clone(), [1024, 1024], Format::D16Unorm).unwrap();
+        let framebuffer = Framebuffer::start(rp)
+            .add(image)
+            .unwrap()
+            .build()
+            .unwrap();
+
+        validity::check_render_area(&framebuffer, [0, 0], [1024, 1024]).unwrap();
+        validity::check_render_area(&framebuffer, [512, 256], [256, 256]).unwrap();
+        validity::check_render_area(&framebuffer, [768, 768], [256, 256]).unwrap();
+
+        match validity::check_render_area(&framebuffer, [768, 768], [512, 256]) {
+            Err(validity::CheckRenderAreaError::OutOfBounds { framebuffer_dimensions, .. }) => {
+                assert_eq!(framebuffer_dimensions, [1024, 1024]);
+            },
+            _ => panic!(),
+        }
+
+        match validity::check_render_area(&framebuffer, [0, 0], [0, 256]) {
+            Err(validity::CheckRenderAreaError::EmptyRenderArea) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn partial_area_discards_content() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let rp = Arc::new(single_pass_renderpass! {
+            device.clone(),
+            attachments: {
+                color: { load: Clear, store: Store, format: Format::R8G8B8A8Unorm, samples: 1, }
+            },
+            pass: {
+                color: [color],
+                depth_stencil: {}
+            }
+        }.unwrap());
+
+        let image = AttachmentImage::new(device.clone(), [64, 64], Format::R8G8B8A8Unorm)
+            .unwrap();
+        let framebuffer = Framebuffer::start(rp)
+            .add(image)
+            .unwrap()
+            .build()
+            .unwrap();
+
+        validity::check_render_area(&framebuffer, [0, 0], [64, 64]).unwrap();
+
+        match validity::check_render_area(&framebuffer, [0, 0], [32, 32]) {
+            Err(validity::CheckRenderAreaError::AttachmentContentDiscarded { attachment: 0 }) => (),
+            _ => panic!(),
+        }
+    }
+}
@@ -0,0 +1,113 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Debug names of Vulkan objects.
+//!
+//! The name given to an object is attached to the `ErrorContext` of the errors returned by the
+//! functions called on this object, and is passed to the implementation if the `ext_debug_utils`
+//! extension is enabled so that it shows up in the messages of the validation layers.
+
+use std::ffi::CString;
+use std::ptr;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use device::Device;
+
+use OomError;
+use VulkanObject;
+use check_errors;
+use vk;
+
+/// Storage for the debug name of an object.
+#[derive(Debug)]
+pub(crate) struct DebugName {
+    name: Mutex<Option<Arc<str>>>,
+}
+
+impl DebugName {
+    /// Builds a `DebugName` with no name.
+    #[inline]
+    pub fn new() -> DebugName {
+        DebugName { name: Mutex::new(None) }
+    }
+
+    /// Returns the current name.
+    #[inline]
+    pub fn get(&self) -> Option<Arc<str>> {
+        self.name.lock().unwrap().clone()
+    }
+
+    /// Changes the name of the object of type `ty` whose handle is `handle`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `name` contains a nul character.
+    ///
+    pub fn set(&self, device: &Device, ty: vk::ObjectType, handle: u64, name: &str)
+               -> Result<(), OomError> {
+        if device.instance().loaded_extensions().ext_debug_utils {
+            let c_name = CString::new(name).expect("debug names can't contain nul characters");
+
+            unsafe {
+                let infos = vk::DebugUtilsObjectNameInfoEXT {
+                    sType: vk::STRUCTURE_TYPE_DEBUG_UTILS_OBJECT_NAME_INFO_EXT,
+                    pNext: ptr::null(),
+                    objectType: ty,
+                    objectHandle: handle,
+                    pObjectName: c_name.as_ptr(),
+                };
+
+                let vk = device.pointers();
+                check_errors(vk.SetDebugUtilsObjectNameEXT(device.internal_object(), &infos),
+                             "vkSetDebugUtilsObjectNameEXT")?;
+            }
+        } else {
+            assert!(!name.contains('\0'), "debug names can't contain nul characters");
+        }
+
+        *self.name.lock().unwrap() = Some(Arc::from(name));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use ErrorContext;
+    use OomError;
+    use check_errors;
+    use vk;
+
+    #[test]
+    fn error_context_name() {
+        let err = check_errors(vk::ERROR_OUT_OF_HOST_MEMORY, "vkWaitForFences").unwrap_err();
+        let err = OomError::from(err.with_object_name(Some(Arc::from("frame fence"))));
+        assert_eq!(err, OomError::OutOfHostMemory);
+
+        let context = ErrorContext::last().unwrap();
+        assert_eq!(context.function(), "vkWaitForFences");
+        assert_eq!(context.object_name(), Some("frame fence"));
+        assert!(context.to_string().contains("`vkWaitForFences` on `frame fence`"));
+    }
+
+    #[test]
+    fn error_context_replaced() {
+        let _ = check_errors(vk::ERROR_OUT_OF_HOST_MEMORY, "vkWaitForFences")
+            .unwrap_err()
+            .with_object_name(Some(Arc::from("frame fence")));
+        let _ = check_errors(vk::ERROR_DEVICE_LOST, "vkQueueSubmit").unwrap_err();
+
+        // The name of the object isn't kept from one error to the next.
+        let context = ErrorContext::last().unwrap();
+        assert_eq!(context.function(), "vkQueueSubmit");
+        assert_eq!(context.object_name(), None);
+    }
+}
@@ -44,6 +44,7 @@
 use format::Format;
 use std::cmp;
 use std::ops::BitOr;
+use sync::PipelineStages;
 use vk;
 
 /// Contains the exact description of a single descriptor.
@@ -99,6 +100,183 @@ impl DescriptorDesc {
                  readonly: self.readonly && other.readonly,
              })
     }
+
+    /// Builds the description of a uniform buffer accessed by the given stages.
+    ///
+    /// The descriptor has one array element and is read-only.
+    #[inline]
+    pub fn uniform_buffer(stages: ShaderStages) -> DescriptorDesc {
+        DescriptorDesc::buffer(stages, false)
+    }
+
+    /// Builds the description of a storage buffer accessed by the given stages.
+    ///
+    /// The descriptor has one array element and can be written by the shader.
+    #[inline]
+    pub fn storage_buffer(stages: ShaderStages) -> DescriptorDesc {
+        DescriptorDesc::buffer(stages, true).readonly(false)
+    }
+
+    #[inline]
+    fn buffer(stages: ShaderStages, storage: bool) -> DescriptorDesc {
+        DescriptorDesc {
+            ty: DescriptorDescTy::Buffer(DescriptorBufferDesc {
+                                             dynamic: Some(false),
+                                             storage: storage,
+                                             content: DescriptorBufferContentDesc::F32,
+                                         }),
+            array_count: 1,
+            stages: stages,
+            readonly: true,
+        }
+    }
+
+    /// Builds the description of a uniform texel buffer (if `storage` is false) or of a storage
+    /// texel buffer (if `storage` is true) accessed by the given stages.
+    ///
+    /// Storage texel buffers can be written by the shader.
+    #[inline]
+    pub fn texel_buffer(stages: ShaderStages, storage: bool, format: Option<Format>)
+                        -> DescriptorDesc {
+        DescriptorDesc {
+            ty: DescriptorDescTy::TexelBuffer {
+                storage: storage,
+                format: format,
+            },
+            array_count: 1,
+            stages: stages,
+            readonly: !storage,
+        }
+    }
+
+    /// Builds the description of a sampler accessed by the given stages.
+    #[inline]
+    pub fn sampler(stages: ShaderStages) -> DescriptorDesc {
+        DescriptorDesc {
+            ty: DescriptorDescTy::Sampler,
+            array_count: 1,
+            stages: stages,
+            readonly: true,
+        }
+    }
+
+    /// Builds the description of a non-multisampled and non-arrayed sampled image of any format.
+    ///
+    /// Use `multisampled` and `array_layers` to change these properties.
+    #[inline]
+    pub fn sampled_image(stages: ShaderStages, dimensions: DescriptorImageDescDimensions)
+                         -> DescriptorDesc {
+        DescriptorDesc {
+            ty: DescriptorDescTy::Image(DescriptorImageDesc::new(true, dimensions, None)),
+            array_count: 1,
+            stages: stages,
+            readonly: true,
+        }
+    }
+
+    /// Builds the description of a non-multisampled and non-arrayed storage image.
+    ///
+    /// If `format` is `None`, images of any format can be attached. The descriptor can be
+    /// written by the shader.
+    #[inline]
+    pub fn storage_image(stages: ShaderStages, dimensions: DescriptorImageDescDimensions,
+                         format: Option<Format>)
+                         -> DescriptorDesc {
+        DescriptorDesc {
+            ty: DescriptorDescTy::Image(DescriptorImageDesc::new(false, dimensions, format)),
+            array_count: 1,
+            stages: stages,
+            readonly: false,
+        }
+    }
+
+    /// Builds the description of a non-multisampled and non-arrayed image combined with a
+    /// sampler.
+    #[inline]
+    pub fn combined_image_sampler(stages: ShaderStages,
+                                  dimensions: DescriptorImageDescDimensions)
+                                  -> DescriptorDesc {
+        DescriptorDesc {
+            ty: DescriptorDescTy::CombinedImageSampler(DescriptorImageDesc::new(true,
+                                                                                dimensions,
+                                                                                None)),
+            array_count: 1,
+            stages: stages,
+            readonly: true,
+        }
+    }
+
+    /// Builds the description of a non-arrayed input attachment accessed by the given stages.
+    ///
+    /// Note that input attachments can only be accessed from fragment shaders.
+    #[inline]
+    pub fn input_attachment(stages: ShaderStages, multisampled: bool) -> DescriptorDesc {
+        DescriptorDesc {
+            ty: DescriptorDescTy::InputAttachment {
+                multisampled: multisampled,
+                array_layers: DescriptorImageDescArray::NonArrayed,
+            },
+            array_count: 1,
+            stages: stages,
+            readonly: true,
+        }
+    }
+
+    /// Turns the descriptor into an array of `count` elements.
+    #[inline]
+    pub fn array(mut self, count: u32) -> DescriptorDesc {
+        self.array_count = count;
+        self
+    }
+
+    /// Sets whether the descriptor is only ever read by the shader.
+    #[inline]
+    pub fn readonly(mut self, readonly: bool) -> DescriptorDesc {
+        self.readonly = readonly;
+        self
+    }
+
+    /// Sets whether the image of the descriptor is multisampled.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the descriptor doesn't contain an image.
+    ///
+    #[inline]
+    pub fn multisampled(mut self, multisampled: bool) -> DescriptorDesc {
+        match self.ty {
+            DescriptorDescTy::Image(ref mut desc) |
+            DescriptorDescTy::CombinedImageSampler(ref mut desc) => {
+                desc.multisampled = multisampled;
+            },
+            DescriptorDescTy::InputAttachment { multisampled: ref mut m, .. } => {
+                *m = multisampled;
+            },
+            _ => panic!("the descriptor doesn't contain an image"),
+        }
+        self
+    }
+
+    /// Sets whether the descriptor contains one or more array layers of an image.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the descriptor doesn't contain an image.
+    ///
+    #[inline]
+    pub fn array_layers(mut self, array_layers: DescriptorImageDescArray) -> DescriptorDesc {
+        match self.ty {
+            DescriptorDescTy::Image(ref mut desc) |
+            DescriptorDescTy::CombinedImageSampler(ref mut desc) => {
+                desc.array_layers = array_layers;
+            },
+            DescriptorDescTy::InputAttachment { array_layers: ref mut a, .. } => {
+                *a = array_layers;
+            },
+            _ => panic!("the descriptor doesn't contain an image"),
+        }
+        self
+    }
 }
 
 /// Describes the content and layout of each array element of a descriptor.
@@ -245,6 +423,18 @@ pub struct DescriptorImageDesc {
 }
 
 impl DescriptorImageDesc {
+    #[inline]
+    fn new(sampled: bool, dimensions: DescriptorImageDescDimensions, format: Option<Format>)
+           -> DescriptorImageDesc {
+        DescriptorImageDesc {
+            sampled: sampled,
+            dimensions: dimensions,
+            format: format,
+            multisampled: false,
+            array_layers: DescriptorImageDescArray::NonArrayed,
+        }
+    }
+
     /// Checks whether we are a superset of another image.
     // TODO: add example
     #[inline]
@@ -416,6 +606,18 @@ impl ShaderStages {
         }
     }
 
+    #[inline]
+    pub(crate) fn from_bits(val: vk::ShaderStageFlags) -> ShaderStages {
+        ShaderStages {
+            vertex: (val & vk::SHADER_STAGE_VERTEX_BIT) != 0,
+            tessellation_control: (val & vk::SHADER_STAGE_TESSELLATION_CONTROL_BIT) != 0,
+            tessellation_evaluation: (val & vk::SHADER_STAGE_TESSELLATION_EVALUATION_BIT) != 0,
+            geometry: (val & vk::SHADER_STAGE_GEOMETRY_BIT) != 0,
+            fragment: (val & vk::SHADER_STAGE_FRAGMENT_BIT) != 0,
+            compute: (val & vk::SHADER_STAGE_COMPUTE_BIT) != 0,
+        }
+    }
+
     /// Checks whether we have more stages enabled than `other`.
     // TODO: add example
     #[inline]
@@ -481,3 +683,22 @@ impl Into<vk::ShaderStageFlags> for ShaderStages {
         result
     }
 }
+
+/// Returns the pipeline stages during which shaders of the given stages are executed.
+///
+/// This is the narrowest stage mask that can be used to synchronize accesses to a descriptor
+/// that is only visible to the given stages.
+impl From<ShaderStages> for PipelineStages {
+    #[inline]
+    fn from(stages: ShaderStages) -> PipelineStages {
+        PipelineStages {
+            vertex_shader: stages.vertex,
+            tessellation_control_shader: stages.tessellation_control,
+            tessellation_evaluation_shader: stages.tessellation_evaluation,
+            geometry_shader: stages.geometry,
+            fragment_shader: stages.fragment,
+            compute_shader: stages.compute,
+            ..PipelineStages::none()
+        }
+    }
+}
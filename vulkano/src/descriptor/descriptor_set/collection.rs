@@ -33,6 +33,95 @@ pub unsafe trait DescriptorSetsCollection {
 
     /// Returns the list of images used by this descriptor set. Includes image views.
     fn images_list<'a>(&'a self) -> Box<Iterator<Item = &'a ImageAccess> + 'a>;
+
+    /// Returns the offsets to apply to the dynamic uniform and storage buffers of the sets, in
+    /// the order of the sets and then of the bindings.
+    ///
+    /// The default implementation returns an empty list.
+    #[inline]
+    fn dynamic_offsets(&self) -> Vec<u32> {
+        Vec::new()
+    }
+
+    /// Returns the generation of the given set, as returned by `UnsafeDescriptorSet::generation`.
+    ///
+    /// This is used to avoid validating an unchanged set multiple times. The default
+    /// implementation returns `None`, in which case the set is validated every time it is used.
+    #[inline]
+    fn generation(&self, _: usize) -> Option<usize> {
+        None
+    }
+}
+
+/// Wraps around a collection of descriptor sets and attaches dynamic offsets to it.
+///
+/// There must be one offset for each dynamic uniform buffer or dynamic storage buffer of the
+/// sets, in the order of the sets and then of the bindings.
+///
+/// # Example
+///
+/// ```ignore
+/// let (chunk, offset) = dynamic_uniform_buffer.push(data)?;
+/// let set = Arc::new(simple_descriptor_set!(pipeline.clone(), 0, { data: chunk })?);
+/// builder.draw(pipeline, dynamic, vertices, DynamicOffsets::new(set, iter::once(offset)), ())
+/// ```
+pub struct DynamicOffsets<S> {
+    sets: S,
+    offsets: Vec<u32>,
+}
+
+impl<S> DynamicOffsets<S>
+    where S: DescriptorSetsCollection
+{
+    /// Attaches `offsets` to `sets`.
+    #[inline]
+    pub fn new<I>(sets: S, offsets: I) -> DynamicOffsets<S>
+        where I: IntoIterator<Item = u32>
+    {
+        DynamicOffsets {
+            sets: sets,
+            offsets: offsets.into_iter().collect(),
+        }
+    }
+}
+
+unsafe impl<S> DescriptorSetsCollection for DynamicOffsets<S>
+    where S: DescriptorSetsCollection
+{
+    #[inline]
+    fn into_vec(self) -> Vec<Box<DescriptorSet + Send + Sync>> {
+        self.sets.into_vec()
+    }
+
+    #[inline]
+    fn num_bindings_in_set(&self, set: usize) -> Option<usize> {
+        self.sets.num_bindings_in_set(set)
+    }
+
+    #[inline]
+    fn descriptor(&self, set: usize, binding: usize) -> Option<DescriptorDesc> {
+        self.sets.descriptor(set, binding)
+    }
+
+    #[inline]
+    fn buffers_list<'a>(&'a self) -> Box<Iterator<Item = &'a BufferAccess> + 'a> {
+        self.sets.buffers_list()
+    }
+
+    #[inline]
+    fn images_list<'a>(&'a self) -> Box<Iterator<Item = &'a ImageAccess> + 'a> {
+        self.sets.images_list()
+    }
+
+    #[inline]
+    fn dynamic_offsets(&self) -> Vec<u32> {
+        self.offsets.clone()
+    }
+
+    #[inline]
+    fn generation(&self, set: usize) -> Option<usize> {
+        self.sets.generation(set)
+    }
 }
 
 unsafe impl DescriptorSetsCollection for () {
@@ -95,6 +184,14 @@ unsafe impl<T> DescriptorSetsCollection for T
     fn images_list<'a>(&'a self) -> Box<Iterator<Item = &'a ImageAccess> + 'a> {
         DescriptorSet::images_list(self)
     }
+
+    #[inline]
+    fn generation(&self, set: usize) -> Option<usize> {
+        match set {
+            0 => Some(DescriptorSet::inner(self).generation()),
+            _ => None,
+        }
+    }
 }
 
 macro_rules! impl_collection {
@@ -177,6 +274,27 @@ macro_rules! impl_collection {
                 )*
                 Box::new(output.into_iter())
             }
+
+            #[inline]
+            fn generation(&self, mut set: usize) -> Option<usize> {
+                #![allow(non_snake_case)]
+                #![allow(unused_mut)]       // For the `set` parameter.
+
+                if set == 0 {
+                    return Some(DescriptorSet::inner(&self.0).generation());
+                }
+
+                let &(_, $(ref $others,)*) = self;
+
+                $(
+                    set -= 1;
+                    if set == 0 {
+                        return Some(DescriptorSet::inner($others).generation());
+                    }
+                )*
+
+                None
+            }
         }
 
         impl_collection!($($others),+);
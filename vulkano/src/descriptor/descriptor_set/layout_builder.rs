@@ -0,0 +1,425 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::error;
+use std::fmt;
+use std::sync::Arc;
+
+use OomError;
+use descriptor::descriptor::DescriptorDesc;
+use descriptor::descriptor::DescriptorType;
+use descriptor::descriptor::ShaderStages;
+use descriptor::descriptor_set::UnsafeDescriptorSetLayout;
+use device::Device;
+
+/// Builds an `UnsafeDescriptorSetLayout` from descriptors written by hand.
+///
+/// Contrary to `UnsafeDescriptorSetLayout::new`, the descriptors can be added in any order and
+/// the layout is checked against the limits of the device before it is created.
+///
+/// # Example
+///
+/// ```
+/// # use vulkano::device::Device;
+/// # use std::sync::Arc;
+/// # let device: Arc<Device> = return;
+/// use vulkano::descriptor::descriptor::DescriptorDesc;
+/// use vulkano::descriptor::descriptor::DescriptorImageDescDimensions;
+/// use vulkano::descriptor::descriptor::ShaderStages;
+/// use vulkano::descriptor::descriptor_set::DescriptorSetLayoutBuilder;
+///
+/// let stages = ShaderStages::all_graphics();
+/// let dimensions = DescriptorImageDescDimensions::TwoDimensional;
+/// let textures = DescriptorDesc::combined_image_sampler(stages, dimensions).array(4);
+///
+/// let layout = DescriptorSetLayoutBuilder::new(device.clone())
+///     .binding(0, DescriptorDesc::uniform_buffer(stages))
+///     .binding(1, textures)
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct DescriptorSetLayoutBuilder {
+    device: Arc<Device>,
+    descriptors: Vec<(u32, DescriptorDesc)>,
+}
+
+impl DescriptorSetLayoutBuilder {
+    /// Starts building a layout with no descriptor.
+    #[inline]
+    pub fn new(device: Arc<Device>) -> DescriptorSetLayoutBuilder {
+        DescriptorSetLayoutBuilder {
+            device: device,
+            descriptors: Vec::new(),
+        }
+    }
+
+    /// Adds a descriptor at the given binding point.
+    ///
+    /// Adding two descriptors at the same binding point is an error that is reported by `build`.
+    #[inline]
+    pub fn binding(mut self, binding: u32, desc: DescriptorDesc) -> DescriptorSetLayoutBuilder {
+        self.descriptors.push((binding, desc));
+        self
+    }
+
+    /// Checks the descriptors and builds the layout.
+    pub fn build(mut self) -> Result<UnsafeDescriptorSetLayout, DescriptorSetLayoutBuilderError> {
+        // The binding points can be sparse, so the descriptors are kept as a sorted list instead
+        // of a list of slots.
+        self.descriptors.sort_by_key(|&(binding, _)| binding);
+
+        for (num, &(binding, ref desc)) in self.descriptors.iter().enumerate() {
+            if desc.array_count == 0 {
+                return Err(DescriptorSetLayoutBuilderError::EmptyArray { binding: binding });
+            }
+
+            if desc.ty.ty().is_none() {
+                return Err(DescriptorSetLayoutBuilderError::UnknownDescriptorType {
+                               binding: binding,
+                           });
+            }
+
+            if num >= 1 && self.descriptors[num - 1].0 == binding {
+                return Err(DescriptorSetLayoutBuilderError::DuplicateBinding { binding: binding });
+            }
+        }
+
+        check_per_stage_limits(&self.device, self.descriptors.iter().map(|&(_, ref d)| d))?;
+
+        Ok(UnsafeDescriptorSetLayout::with_bindings(self.device, self.descriptors)?)
+    }
+}
+
+// Checks that no shader stage has access to more descriptors of a kind than what the device
+// supports.
+fn check_per_stage_limits<'a, I>(device: &Device, descriptors: I)
+                                 -> Result<(), DescriptorSetLayoutBuilderError>
+    where I: Iterator<Item = &'a DescriptorDesc> + Clone
+{
+    let stages = [
+        ShaderStages { vertex: true, ..ShaderStages::none() },
+        ShaderStages { tessellation_control: true, ..ShaderStages::none() },
+        ShaderStages { tessellation_evaluation: true, ..ShaderStages::none() },
+        ShaderStages { geometry: true, ..ShaderStages::none() },
+        ShaderStages { fragment: true, ..ShaderStages::none() },
+        ShaderStages::compute(),
+    ];
+
+    let limits = device.physical_device().limits();
+
+    for stage in stages.iter() {
+        let mut samplers = 0;
+        let mut uniform_buffers = 0;
+        let mut storage_buffers = 0;
+        let mut sampled_images = 0;
+        let mut storage_images = 0;
+        let mut input_attachments = 0;
+
+        // The counts saturate, as some devices report limits of `u32::MAX`.
+        for desc in descriptors.clone().filter(|d| d.stages.intersects(stage)) {
+            let count = desc.array_count;
+            let counter: &mut u32 = match desc.ty.ty().unwrap() {
+                DescriptorType::Sampler => &mut samplers,
+                DescriptorType::CombinedImageSampler => {
+                    samplers = samplers.saturating_add(count);
+                    &mut sampled_images
+                },
+                DescriptorType::SampledImage |
+                DescriptorType::UniformTexelBuffer => &mut sampled_images,
+                DescriptorType::StorageImage |
+                DescriptorType::StorageTexelBuffer => &mut storage_images,
+                DescriptorType::UniformBuffer |
+                DescriptorType::UniformBufferDynamic => &mut uniform_buffers,
+                DescriptorType::StorageBuffer |
+                DescriptorType::StorageBufferDynamic => &mut storage_buffers,
+                DescriptorType::InputAttachment => &mut input_attachments,
+            };
+            *counter = counter.saturating_add(count);
+        }
+
+        // Samplers are the only descriptors that aren't resources. The color attachments of the
+        // fragment shader also count, but they aren't known here.
+        let resources = uniform_buffers
+            .saturating_add(storage_buffers)
+            .saturating_add(sampled_images)
+            .saturating_add(storage_images)
+            .saturating_add(input_attachments);
+
+        let checks = [
+            (samplers, limits.max_per_stage_descriptor_samplers(), PerStageLimit::Samplers),
+            (uniform_buffers,
+             limits.max_per_stage_descriptor_uniform_buffers(),
+             PerStageLimit::UniformBuffers),
+            (storage_buffers,
+             limits.max_per_stage_descriptor_storage_buffers(),
+             PerStageLimit::StorageBuffers),
+            (sampled_images,
+             limits.max_per_stage_descriptor_sampled_images(),
+             PerStageLimit::SampledImages),
+            (storage_images,
+             limits.max_per_stage_descriptor_storage_images(),
+             PerStageLimit::StorageImages),
+            (input_attachments,
+             limits.max_per_stage_descriptor_input_attachments(),
+             PerStageLimit::InputAttachments),
+            (resources, limits.max_per_stage_resources(), PerStageLimit::Resources),
+        ];
+
+        for &(requested, max, limit) in checks.iter() {
+            if requested > max {
+                return Err(DescriptorSetLayoutBuilderError::PerStageLimitExceeded {
+                               limit: limit,
+                               requested: requested,
+                               max: max,
+                           });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Per-stage limit of the device on the number of descriptors of a kind.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PerStageLimit {
+    /// The `max_per_stage_descriptor_samplers` limit.
+    Samplers,
+    /// The `max_per_stage_descriptor_uniform_buffers` limit.
+    UniformBuffers,
+    /// The `max_per_stage_descriptor_storage_buffers` limit.
+    StorageBuffers,
+    /// The `max_per_stage_descriptor_sampled_images` limit.
+    SampledImages,
+    /// The `max_per_stage_descriptor_storage_images` limit.
+    StorageImages,
+    /// The `max_per_stage_descriptor_input_attachments` limit.
+    InputAttachments,
+    /// The `max_per_stage_resources` limit, which applies to all the descriptors except samplers.
+    Resources,
+}
+
+/// Error that can happen when building a descriptor set layout with a
+/// `DescriptorSetLayoutBuilder`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DescriptorSetLayoutBuilderError {
+    /// Not enough memory.
+    OomError(OomError),
+
+    /// Two descriptors were added at the same binding point.
+    DuplicateBinding {
+        /// The binding point.
+        binding: u32,
+    },
+
+    /// A descriptor has an `array_count` of 0.
+    EmptyArray {
+        /// The binding point of the descriptor.
+        binding: u32,
+    },
+
+    /// The type of a descriptor can't be determined, for example because it is a buffer whose
+    /// `dynamic` field is `None`.
+    UnknownDescriptorType {
+        /// The binding point of the descriptor.
+        binding: u32,
+    },
+
+    /// A shader stage has access to more descriptors of a kind than what the device supports.
+    PerStageLimitExceeded {
+        /// The limit that is exceeded.
+        limit: PerStageLimit,
+        /// Number of descriptors of that kind accessible from the stage.
+        requested: u32,
+        /// Value of the limit.
+        max: u32,
+    },
+}
+
+impl error::Error for DescriptorSetLayoutBuilderError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            DescriptorSetLayoutBuilderError::OomError(_) => {
+                "not enough memory available"
+            },
+            DescriptorSetLayoutBuilderError::DuplicateBinding { .. } => {
+                "two descriptors were added at the same binding point"
+            },
+            DescriptorSetLayoutBuilderError::EmptyArray { .. } => {
+                "a descriptor has an array count of 0"
+            },
+            DescriptorSetLayoutBuilderError::UnknownDescriptorType { .. } => {
+                "the type of a descriptor can't be determined"
+            },
+            DescriptorSetLayoutBuilderError::PerStageLimitExceeded { .. } => {
+                "a shader stage has access to more descriptors of a kind than what the device \
+                 supports"
+            },
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            DescriptorSetLayoutBuilderError::OomError(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for DescriptorSetLayoutBuilderError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl From<OomError> for DescriptorSetLayoutBuilderError {
+    #[inline]
+    fn from(err: OomError) -> DescriptorSetLayoutBuilderError {
+        DescriptorSetLayoutBuilderError::OomError(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp;
+    use std::u32;
+
+    use descriptor::descriptor::DescriptorDesc;
+    use descriptor::descriptor::DescriptorImageDescDimensions;
+    use descriptor::descriptor::ShaderStages;
+    use descriptor::descriptor_set::DescriptorSetLayoutBuilder;
+    use descriptor::descriptor_set::DescriptorSetLayoutBuilderError;
+    use descriptor::descriptor_set::DescriptorsCount;
+    use descriptor::descriptor_set::PerStageLimit;
+
+    #[test]
+    fn out_of_order_bindings() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let layout = DescriptorSetLayoutBuilder::new(device)
+            .binding(2, DescriptorDesc::uniform_buffer(ShaderStages::all_graphics()))
+            .binding(0,
+                     DescriptorDesc::sampled_image(ShaderStages::all_graphics(),
+                                                   DescriptorImageDescDimensions::TwoDimensional)
+                         .array(2))
+            .build()
+            .unwrap();
+
+        assert_eq!(layout.descriptors_count(),
+                   &DescriptorsCount {
+                       uniform_buffer: 1,
+                       sampled_image: 1,
+                       ..DescriptorsCount::zero()
+                   });
+    }
+
+    #[test]
+    fn duplicate_binding() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let result = DescriptorSetLayoutBuilder::new(device)
+            .binding(1, DescriptorDesc::uniform_buffer(ShaderStages::compute()))
+            .binding(1, DescriptorDesc::storage_buffer(ShaderStages::compute()))
+            .build();
+
+        match result {
+            Err(DescriptorSetLayoutBuilderError::DuplicateBinding { binding: 1 }) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn per_stage_limit() {
+        let (device, _) = gfx_dev_and_queue!();
+        let max = device.physical_device().limits().max_per_stage_descriptor_samplers();
+
+        let result = DescriptorSetLayoutBuilder::new(device)
+            .binding(0, DescriptorDesc::sampler(ShaderStages::compute()).array(max))
+            .binding(1, DescriptorDesc::sampler(ShaderStages::compute()))
+            .build();
+
+        // The limit can't be exceeded if it is `u32::MAX`, since the counts saturate.
+        if max == u32::MAX {
+            return;
+        }
+
+        match result {
+            Err(DescriptorSetLayoutBuilderError::PerStageLimitExceeded {
+                    limit: PerStageLimit::Samplers,
+                    ..
+                }) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn per_stage_resources_limit() {
+        let (device, _) = gfx_dev_and_queue!();
+        let limits = device.physical_device().limits();
+        let max = limits.max_per_stage_resources();
+        if max == u32::MAX {
+            return;
+        }
+
+        // Uses as many uniform and storage buffers as allowed individually, which must exceed
+        // the limit on the total.
+        let uniform = cmp::min(limits.max_per_stage_descriptor_uniform_buffers(), max);
+        let storage = cmp::min(limits.max_per_stage_descriptor_storage_buffers(),
+                               max - uniform + 1);
+        if uniform + storage <= max {
+            return;
+        }
+
+        let result = DescriptorSetLayoutBuilder::new(device.clone())
+            .binding(0, DescriptorDesc::uniform_buffer(ShaderStages::compute()).array(uniform))
+            .binding(1, DescriptorDesc::storage_buffer(ShaderStages::compute()).array(storage))
+            .build();
+
+        match result {
+            Err(DescriptorSetLayoutBuilderError::PerStageLimitExceeded {
+                    limit: PerStageLimit::Resources,
+                    requested,
+                    max: m,
+                }) => {
+                assert_eq!(requested, uniform + storage);
+                assert_eq!(m, max);
+            },
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn sparse_bindings() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        // The builder must not allocate one slot per binding point up to the largest one.
+        let result = DescriptorSetLayoutBuilder::new(device.clone())
+            .binding(u32::MAX, DescriptorDesc::uniform_buffer(ShaderStages::compute()).array(0))
+            .build();
+        match result {
+            Err(DescriptorSetLayoutBuilderError::EmptyArray { binding: u32::MAX }) => (),
+            _ => panic!(),
+        }
+
+        let layout = DescriptorSetLayoutBuilder::new(device)
+            .binding(1000, DescriptorDesc::uniform_buffer(ShaderStages::compute()))
+            .binding(3, DescriptorDesc::storage_buffer(ShaderStages::compute()))
+            .build()
+            .unwrap();
+        assert_eq!(layout.descriptors_count(),
+                   &DescriptorsCount {
+                       uniform_buffer: 1,
+                       storage_buffer: 1,
+                       ..DescriptorsCount::zero()
+                   });
+    }
+}
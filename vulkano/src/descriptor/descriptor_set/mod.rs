@@ -39,11 +39,21 @@ use SafeDeref;
 use buffer::BufferAccess;
 use descriptor::descriptor::DescriptorDesc;
 use image::ImageAccess;
+use image::ImageLayout;
+use sync::AccessFlagBits;
+use sync::PipelineStages;
 
 pub use self::collection::DescriptorSetsCollection;
+pub use self::collection::DynamicOffsets;
+pub use self::layout_builder::DescriptorSetLayoutBuilder;
+pub use self::layout_builder::DescriptorSetLayoutBuilderError;
+pub use self::layout_builder::PerStageLimit;
 pub use self::simple::*;
 pub use self::std_pool::StdDescriptorPool;
 pub use self::std_pool::StdDescriptorPoolAlloc;
+pub use self::std_pool::StdDescriptorPoolAllocError;
+pub use self::std_pool::StdDescriptorPoolPolicy;
+pub use self::std_pool::StdDescriptorPoolStats;
 pub use self::sys::DescriptorPool;
 pub use self::sys::DescriptorPoolAlloc;
 pub use self::sys::DescriptorPoolAllocError;
@@ -56,6 +66,7 @@ pub use self::unsafe_layout::UnsafeDescriptorSetLayout;
 
 pub mod collection;
 
+mod layout_builder;
 mod simple;
 mod std_pool;
 mod sys;
@@ -75,6 +86,20 @@ pub unsafe trait DescriptorSet: DescriptorSetDesc {
     /// Returns the list of images used by this descriptor set. Includes image views.
     // TODO: meh for boxing
     fn images_list<'a>(&'a self) -> Box<Iterator<Item = &'a ImageAccess> + 'a>;
+
+    /// Returns the number of buffers within this descriptor set. Includes buffer views.
+    fn num_buffers(&self) -> usize;
+
+    /// Returns the `num`th buffer of this descriptor set and how the shaders access it, or `None`
+    /// if out of range.
+    fn buffer(&self, num: usize) -> Option<(&BufferAccess, DescriptorResourceAccess)>;
+
+    /// Returns the number of images within this descriptor set. Includes image views.
+    fn num_images(&self) -> usize;
+
+    /// Returns the image of the `num`th image view of this descriptor set and how the shaders
+    /// access it, or `None` if out of range.
+    fn image(&self, num: usize) -> Option<(&ImageAccess, DescriptorResourceAccess)>;
 }
 
 unsafe impl<T> DescriptorSet for T
@@ -95,6 +120,42 @@ unsafe impl<T> DescriptorSet for T
     fn images_list<'a>(&'a self) -> Box<Iterator<Item = &'a ImageAccess> + 'a> {
         (**self).images_list()
     }
+
+    #[inline]
+    fn num_buffers(&self) -> usize {
+        (**self).num_buffers()
+    }
+
+    #[inline]
+    fn buffer(&self, num: usize) -> Option<(&BufferAccess, DescriptorResourceAccess)> {
+        (**self).buffer(num)
+    }
+
+    #[inline]
+    fn num_images(&self) -> usize {
+        (**self).num_images()
+    }
+
+    #[inline]
+    fn image(&self, num: usize) -> Option<(&ImageAccess, DescriptorResourceAccess)> {
+        (**self).image(num)
+    }
+}
+
+/// Describes how the shaders of a pipeline access a buffer or an image of a descriptor set.
+///
+/// The stages only include the shaders that can see the descriptor, so that command buffers
+/// don't have to wait for the other stages of the pipeline.
+#[derive(Debug, Copy, Clone)]
+pub struct DescriptorResourceAccess {
+    /// Stages of the pipeline whose shaders access the resource.
+    pub stages: PipelineStages,
+    /// Kind of accesses performed by these shaders.
+    pub access: AccessFlagBits,
+    /// True if the shaders can write to the resource.
+    pub write: bool,
+    /// Layout the image must be in while it is accessed. Always `Undefined` for buffers.
+    pub layout: ImageLayout,
 }
 
 /// Trait for objects that describe the layout of the descriptors of a set.
@@ -7,14 +7,19 @@
 // notice may not be copied, modified, or distributed except
 // according to those terms.
 
+use std::error;
+use std::fmt;
 use std::sync::Arc;
 
 use buffer::BufferAccess;
 use buffer::BufferViewRef;
 use descriptor::descriptor::DescriptorDesc;
+use descriptor::descriptor::DescriptorDescTy;
 use descriptor::descriptor::DescriptorType;
 use descriptor::descriptor_set::DescriptorPool;
 use descriptor::descriptor_set::DescriptorPoolAlloc;
+use descriptor::descriptor_set::DescriptorPoolAllocError;
+use descriptor::descriptor_set::DescriptorResourceAccess;
 use descriptor::descriptor_set::DescriptorSet;
 use descriptor::descriptor_set::DescriptorSetDesc;
 use descriptor::descriptor_set::DescriptorWrite;
@@ -66,7 +71,8 @@ impl<R, P> SimpleDescriptorSet<R, P>
 }
 
 unsafe impl<R, P> DescriptorSet for SimpleDescriptorSet<R, P>
-    where P: DescriptorPool
+    where P: DescriptorPool,
+          R: SimpleDescriptorSetResourcesCollection
 {
     #[inline]
     fn inner(&self) -> &UnsafeDescriptorSet {
@@ -75,12 +81,36 @@ unsafe impl<R, P> DescriptorSet for SimpleDescriptorSet<R, P>
 
     #[inline]
     fn buffers_list<'a>(&'a self) -> Box<Iterator<Item = &'a BufferAccess> + 'a> {
-        unimplemented!()
+        Box::new((0 .. self.resources.num_buffers()).map(move |n| {
+            self.resources.buffer(n).unwrap().0
+        }))
     }
 
     #[inline]
     fn images_list<'a>(&'a self) -> Box<Iterator<Item = &'a ImageAccess> + 'a> {
-        unimplemented!()
+        Box::new((0 .. self.resources.num_images()).map(move |n| {
+            self.resources.image(n).unwrap().0
+        }))
+    }
+
+    #[inline]
+    fn num_buffers(&self) -> usize {
+        self.resources.num_buffers()
+    }
+
+    #[inline]
+    fn buffer(&self, num: usize) -> Option<(&BufferAccess, DescriptorResourceAccess)> {
+        self.resources.buffer(num)
+    }
+
+    #[inline]
+    fn num_images(&self) -> usize {
+        self.resources.num_images()
+    }
+
+    #[inline]
+    fn image(&self, num: usize) -> Option<(&ImageAccess, DescriptorResourceAccess)> {
+        self.resources.image(num)
     }
 }
 
@@ -99,6 +129,9 @@ unsafe impl<R, P> DescriptorSetDesc for SimpleDescriptorSet<R, P>
 }
 
 /// Builds a descriptor set in the form of a `SimpleDescriptorSet` object.
+///
+/// Returns a `Result<SimpleDescriptorSet, DescriptorSetUpdateError>`. An error is returned if one
+/// of the resources doesn't match the descriptor it is written to.
 // TODO: more doc
 #[macro_export]
 macro_rules! simple_descriptor_set {
@@ -113,7 +146,8 @@ macro_rules! simple_descriptor_set {
         // We build an empty `SimpleDescriptorSetBuilder` struct, then adds each element one by
         // one. When done, we call `build()` on the builder.
 
-        let builder = SimpleDescriptorSetBuilder::new($layout, $set_num);
+        let builder: Result<_, $crate::descriptor::descriptor_set::DescriptorSetUpdateError> =
+            Ok(SimpleDescriptorSetBuilder::new($layout, $set_num));
 
         $(
             // Here `$val` can be either a buffer or an image. However we can't create an extension
@@ -125,10 +159,13 @@ macro_rules! simple_descriptor_set {
             // that both have a method named `add_me`. We import these two traits in scope and
             // call `add_me` on the value, letting Rust dispatch to the right trait. A compilation
             // error will happen if `$val` is both a buffer and an image.
-            let builder = $val.add_me(builder, stringify!($name));
+            let builder = match builder {
+                Ok(builder) => $val.add_me(builder, stringify!($name)),
+                Err(err) => Err(err),
+            };
         )*
 
-        builder.build()
+        builder.and_then(|builder| builder.build())
     });
 }
 
@@ -181,7 +218,11 @@ impl<L, R> SimpleDescriptorSetBuilder<L, R>
     where L: PipelineLayoutAbstract
 {
     /// Builds a `SimpleDescriptorSet` from the builder.
-    pub fn build(self) -> SimpleDescriptorSet<R, Arc<StdDescriptorPool>> {
+    ///
+    /// Returns `DescriptorSetUpdateError::AllocError` if the set can't be allocated from the
+    /// standard descriptor pool of the device.
+    pub fn build(self) -> Result<SimpleDescriptorSet<R, Arc<StdDescriptorPool>>,
+                                 DescriptorSetUpdateError> {
         // TODO: check that we filled everything
         let pool = Device::standard_descriptor_pool(self.layout.device());
         let set_layout = self.layout
@@ -190,17 +231,17 @@ impl<L, R> SimpleDescriptorSetBuilder<L, R>
             .clone(); // FIXME: error
 
         let set = unsafe {
-            let mut set = pool.alloc(&set_layout).unwrap(); // FIXME: error
+            let mut set = pool.alloc(&set_layout)?;
             set.inner_mut()
                 .write(pool.device(), self.writes.into_iter());
             set
         };
 
-        SimpleDescriptorSet {
-            inner: set,
-            resources: self.resources,
-            layout: set_layout,
-        }
+        Ok(SimpleDescriptorSet {
+               inner: set,
+               resources: self.resources,
+               layout: set_layout,
+           })
     }
 }
 
@@ -210,9 +251,8 @@ pub unsafe trait SimpleDescriptorSetBufferExt<L, R> {
     type Out;
 
     /// Appends the buffer to the `SimpleDescriptorSetBuilder`.
-    // TODO: return Result
     fn add_me(self, i: SimpleDescriptorSetBuilder<L, R>, name: &str)
-              -> SimpleDescriptorSetBuilder<L, Self::Out>;
+              -> Result<SimpleDescriptorSetBuilder<L, Self::Out>, DescriptorSetUpdateError>;
 }
 
 unsafe impl<L, R, T> SimpleDescriptorSetBufferExt<L, R> for T
@@ -222,12 +262,14 @@ unsafe impl<L, R, T> SimpleDescriptorSetBufferExt<L, R> for T
     type Out = (R, SimpleDescriptorSetBuf<T>);
 
     fn add_me(self, mut i: SimpleDescriptorSetBuilder<L, R>, name: &str)
-              -> SimpleDescriptorSetBuilder<L, Self::Out> {
+              -> Result<SimpleDescriptorSetBuilder<L, Self::Out>, DescriptorSetUpdateError> {
         let (set_id, binding_id) = i.layout.descriptor_by_name(name).unwrap(); // TODO: Result instead
         assert_eq!(set_id, i.set_id); // TODO: Result instead
         let desc = i.layout.descriptor(set_id, binding_id).unwrap(); // TODO: Result instead
 
-        assert!(desc.array_count == 1); // not implemented
+        check_descriptor_count(&desc, binding_id, 1)?;
+        check_initialized(self.initialized(), binding_id)?;
+        let (stage, access) = shader_access(&desc);
         i.writes.push(match desc.ty.ty().unwrap() {
                           DescriptorType::UniformBuffer => unsafe {
                               DescriptorWrite::uniform_buffer(binding_id as u32, 0, &self)
@@ -235,10 +277,16 @@ unsafe impl<L, R, T> SimpleDescriptorSetBufferExt<L, R> for T
                           DescriptorType::StorageBuffer => unsafe {
                               DescriptorWrite::storage_buffer(binding_id as u32, 0, &self)
                           },
+                          DescriptorType::UniformBufferDynamic => unsafe {
+                              DescriptorWrite::dynamic_uniform_buffer(binding_id as u32, 0, &self)
+                          },
+                          DescriptorType::StorageBufferDynamic => unsafe {
+                              DescriptorWrite::dynamic_storage_buffer(binding_id as u32, 0, &self)
+                          },
                           _ => panic!(),
                       });
 
-        SimpleDescriptorSetBuilder {
+        Ok(SimpleDescriptorSetBuilder {
             layout: i.layout,
             set_id: i.set_id,
             writes: i.writes,
@@ -246,10 +294,10 @@ unsafe impl<L, R, T> SimpleDescriptorSetBufferExt<L, R> for T
                         SimpleDescriptorSetBuf {
                             buffer: self,
                             write: !desc.readonly,
-                            stage: PipelineStages::none(), // FIXME:
-                            access: AccessFlagBits::none(), // FIXME:
+                            stage: stage,
+                            access: access,
                         }),
-        }
+        })
     }
 }
 
@@ -259,9 +307,8 @@ pub unsafe trait SimpleDescriptorSetImageExt<L, R> {
     type Out;
 
     /// Appends the image to the `SimpleDescriptorSetBuilder`.
-    // TODO: return Result
     fn add_me(self, i: SimpleDescriptorSetBuilder<L, R>, name: &str)
-              -> SimpleDescriptorSetBuilder<L, Self::Out>;
+              -> Result<SimpleDescriptorSetBuilder<L, Self::Out>, DescriptorSetUpdateError>;
 }
 
 unsafe impl<L, R, T> SimpleDescriptorSetImageExt<L, R> for T
@@ -271,26 +318,33 @@ unsafe impl<L, R, T> SimpleDescriptorSetImageExt<L, R> for T
     type Out = (R, SimpleDescriptorSetImg<T>);
 
     fn add_me(self, mut i: SimpleDescriptorSetBuilder<L, R>, name: &str)
-              -> SimpleDescriptorSetBuilder<L, Self::Out> {
+              -> Result<SimpleDescriptorSetBuilder<L, Self::Out>, DescriptorSetUpdateError> {
         let (set_id, binding_id) = i.layout.descriptor_by_name(name).unwrap(); // TODO: Result instead
         assert_eq!(set_id, i.set_id); // TODO: Result instead
         let desc = i.layout.descriptor(set_id, binding_id).unwrap(); // TODO: Result instead
 
-        assert!(desc.array_count == 1); // not implemented
+        check_descriptor_count(&desc, binding_id, 1)?;
+        check_initialized(self.parent().initialized(), binding_id)?;
+        check_multisampled(&desc, binding_id, self.samples())?;
+        let (stage, access) = shader_access(&desc);
+        let layout;
         i.writes.push(match desc.ty.ty().unwrap() {
                           DescriptorType::SampledImage => {
+                              layout = self.descriptor_set_sampled_image_layout();
                               DescriptorWrite::sampled_image(binding_id as u32, 0, &self)
                           },
                           DescriptorType::StorageImage => {
+                              layout = self.descriptor_set_storage_image_layout();
                               DescriptorWrite::storage_image(binding_id as u32, 0, &self)
                           },
                           DescriptorType::InputAttachment => {
+                              layout = self.descriptor_set_input_attachment_layout();
                               DescriptorWrite::input_attachment(binding_id as u32, 0, &self)
                           },
                           _ => panic!(),
                       });
 
-        SimpleDescriptorSetBuilder {
+        Ok(SimpleDescriptorSetBuilder {
             layout: i.layout,
             set_id: i.set_id,
             writes: i.writes,
@@ -303,11 +357,11 @@ unsafe impl<L, R, T> SimpleDescriptorSetImageExt<L, R> for T
                             num_mipmaps: 1, // FIXME:
                             first_layer: 0, // FIXME:
                             num_layers: 1, // FIXME:
-                            layout: ImageLayout::General, // FIXME:
-                            stage: PipelineStages::none(), // FIXME:
-                            access: AccessFlagBits::none(), // FIXME:
+                            layout: layout,
+                            stage: stage,
+                            access: access,
                         }),
-        }
+        })
     }
 }
 
@@ -318,14 +372,18 @@ unsafe impl<L, R, T> SimpleDescriptorSetImageExt<L, R> for (T, Arc<Sampler>)
     type Out = (R, SimpleDescriptorSetImg<T>);
 
     fn add_me(self, mut i: SimpleDescriptorSetBuilder<L, R>, name: &str)
-              -> SimpleDescriptorSetBuilder<L, Self::Out> {
+              -> Result<SimpleDescriptorSetBuilder<L, Self::Out>, DescriptorSetUpdateError> {
         let image_view = self.0;
 
         let (set_id, binding_id) = i.layout.descriptor_by_name(name).unwrap(); // TODO: Result instead
         assert_eq!(set_id, i.set_id); // TODO: Result instead
         let desc = i.layout.descriptor(set_id, binding_id).unwrap(); // TODO: Result instead
 
-        assert!(desc.array_count == 1); // not implemented
+        check_descriptor_count(&desc, binding_id, 1)?;
+        check_initialized(image_view.parent().initialized(), binding_id)?;
+        check_multisampled(&desc, binding_id, image_view.samples())?;
+        let (stage, access) = shader_access(&desc);
+        let layout = image_view.descriptor_set_combined_image_sampler_layout();
         i.writes.push(match desc.ty.ty().unwrap() {
                           DescriptorType::CombinedImageSampler => {
                               DescriptorWrite::combined_image_sampler(binding_id as u32,
@@ -336,7 +394,7 @@ unsafe impl<L, R, T> SimpleDescriptorSetImageExt<L, R> for (T, Arc<Sampler>)
                           _ => panic!(),
                       });
 
-        SimpleDescriptorSetBuilder {
+        Ok(SimpleDescriptorSetBuilder {
             layout: i.layout,
             set_id: i.set_id,
             writes: i.writes,
@@ -349,11 +407,11 @@ unsafe impl<L, R, T> SimpleDescriptorSetImageExt<L, R> for (T, Arc<Sampler>)
                             num_mipmaps: 1, // FIXME:
                             first_layer: 0, // FIXME:
                             num_layers: 1, // FIXME:
-                            layout: ImageLayout::General, // FIXME:
-                            stage: PipelineStages::none(), // FIXME:
-                            access: AccessFlagBits::none(), // FIXME:
+                            layout: layout,
+                            stage: stage,
+                            access: access,
                         }),
-        }
+        })
     }
 }
 
@@ -365,13 +423,18 @@ unsafe impl<L, R, T> SimpleDescriptorSetImageExt<L, R> for Vec<(T, Arc<Sampler>)
     type Out = (R, Vec<SimpleDescriptorSetImg<T>>);
 
     fn add_me(self, mut i: SimpleDescriptorSetBuilder<L, R>, name: &str)
-              -> SimpleDescriptorSetBuilder<L, Self::Out> {
+              -> Result<SimpleDescriptorSetBuilder<L, Self::Out>, DescriptorSetUpdateError> {
         let (set_id, binding_id) = i.layout.descriptor_by_name(name).unwrap(); // TODO: Result instead
         assert_eq!(set_id, i.set_id); // TODO: Result instead
         let desc = i.layout.descriptor(set_id, binding_id).unwrap(); // TODO: Result instead
 
-        assert_eq!(desc.array_count as usize, self.len()); // not implemented
+        check_descriptor_count(&desc, binding_id, self.len())?;
+        for &(ref img, _) in self.iter() {
+            check_initialized(img.parent().initialized(), binding_id)?;
+            check_multisampled(&desc, binding_id, img.samples())?;
+        }
 
+        let (stage, access) = shader_access(&desc);
         let mut imgs = Vec::new();
         for (num, (img, sampler)) in self.into_iter().enumerate() {
             i.writes.push(match desc.ty.ty().unwrap() {
@@ -384,6 +447,7 @@ unsafe impl<L, R, T> SimpleDescriptorSetImageExt<L, R> for Vec<(T, Arc<Sampler>)
                               _ => panic!(),
                           });
 
+            let layout = img.descriptor_set_combined_image_sampler_layout();
             imgs.push(SimpleDescriptorSetImg {
                           image: img,
                           sampler: Some(sampler),
@@ -392,33 +456,228 @@ unsafe impl<L, R, T> SimpleDescriptorSetImageExt<L, R> for Vec<(T, Arc<Sampler>)
                           num_mipmaps: 1, // FIXME:
                           first_layer: 0, // FIXME:
                           num_layers: 1, // FIXME:
-                          layout: ImageLayout::General, // FIXME:
-                          stage: PipelineStages::none(), // FIXME:
-                          access: AccessFlagBits::none(), // FIXME:
+                          layout: layout,
+                          stage: stage,
+                          access: access,
                       });
         }
 
-        SimpleDescriptorSetBuilder {
+        Ok(SimpleDescriptorSetBuilder {
             layout: i.layout,
             set_id: i.set_id,
             writes: i.writes,
             resources: (i.resources, imgs),
+        })
+    }
+}
+
+// Returns the stages whose shaders can access the resources written to the descriptor, and the
+// kind of accesses they perform.
+fn shader_access(desc: &DescriptorDesc) -> (PipelineStages, AccessFlagBits) {
+    let mut stages = PipelineStages::from(desc.stages);
+    if stages == PipelineStages::none() {
+        // Should never happen with a valid layout, but a barrier needs at least one stage.
+        stages.all_commands = true;
+    }
+
+    let write = !desc.readonly;
+    let access = match desc.ty.ty() {
+        Some(DescriptorType::UniformBuffer) |
+        Some(DescriptorType::UniformBufferDynamic) => {
+            AccessFlagBits {
+                uniform_read: true,
+                ..AccessFlagBits::none()
+            }
+        },
+        Some(DescriptorType::InputAttachment) => {
+            AccessFlagBits {
+                input_attachment_read: true,
+                ..AccessFlagBits::none()
+            }
+        },
+        _ => {
+            AccessFlagBits {
+                shader_read: true,
+                shader_write: write,
+                ..AccessFlagBits::none()
+            }
+        },
+    };
+
+    (stages, access)
+}
+
+// Checks that the number of descriptors being written matches the number of descriptors declared
+// in the layout for this binding.
+#[inline]
+fn check_descriptor_count(desc: &DescriptorDesc, binding: usize, actual: usize)
+                          -> Result<(), DescriptorSetUpdateError> {
+    if desc.array_count as usize != actual {
+        return Err(DescriptorSetUpdateError::WrongDescriptorCount {
+                       binding: binding as u32,
+                       expected: desc.array_count,
+                       actual: actual as u32,
+                   });
+    }
+
+    Ok(())
+}
+
+// Checks that a resource being written has been initialized.
+#[inline]
+fn check_initialized(initialized: bool, binding: usize) -> Result<(), DescriptorSetUpdateError> {
+    if !initialized {
+        return Err(DescriptorSetUpdateError::UninitializedResource { binding: binding as u32 });
+    }
+
+    Ok(())
+}
+
+// Checks that an image being written is multisampled if and only if the descriptor expects a
+// multisampled image.
+#[inline]
+fn check_multisampled(desc: &DescriptorDesc, binding: usize, samples: u32)
+                      -> Result<(), DescriptorSetUpdateError> {
+    let multisampled = match desc.ty {
+        DescriptorDescTy::Image(ref img) |
+        DescriptorDescTy::CombinedImageSampler(ref img) => img.multisampled,
+        DescriptorDescTy::InputAttachment { multisampled, .. } => multisampled,
+        _ => return Ok(()),
+    };
+
+    if multisampled != (samples > 1) {
+        return Err(DescriptorSetUpdateError::MultisampledMismatch {
+                       binding: binding as u32,
+                       multisampled: multisampled,
+                       samples: samples,
+                   });
+    }
+
+    Ok(())
+}
+
+/// Error that can happen when writing resources in a descriptor set or when building it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DescriptorSetUpdateError {
+    /// The number of descriptors written to a binding doesn't match the number of descriptors
+    /// declared in the layout.
+    ///
+    /// Array bindings must be written with exactly as many elements as the layout declares.
+    WrongDescriptorCount {
+        /// The binding that was written.
+        binding: u32,
+        /// Number of descriptors declared in the layout.
+        expected: u32,
+        /// Number of descriptors that were provided.
+        actual: u32,
+    },
+
+    /// The content of a buffer or an image written to a binding hasn't been initialized yet.
+    ///
+    /// This happens when writing an `ImmutableBuffer` or an `ImmutableImage` whose initial upload
+    /// hasn't finished executing.
+    UninitializedResource {
+        /// The binding that was written.
+        binding: u32,
+    },
+
+    /// The descriptor expects a multisampled image and the image written to it has only one
+    /// sample, or the opposite.
+    MultisampledMismatch {
+        /// The binding that was written.
+        binding: u32,
+        /// True if the descriptor expects a multisampled image.
+        multisampled: bool,
+        /// Number of samples of the image that was provided.
+        samples: u32,
+    },
+
+    /// The descriptor set couldn't be allocated from the pool.
+    AllocError(DescriptorPoolAllocError),
+}
+
+impl error::Error for DescriptorSetUpdateError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            DescriptorSetUpdateError::WrongDescriptorCount { .. } => {
+                "the number of descriptors written doesn't match the number of descriptors in the \
+                 layout"
+            },
+            DescriptorSetUpdateError::UninitializedResource { .. } => {
+                "the content of a resource written to a binding hasn't been initialized yet"
+            },
+            DescriptorSetUpdateError::MultisampledMismatch { .. } => {
+                "the number of samples of an image doesn't match whether the descriptor is \
+                 multisampled"
+            },
+            DescriptorSetUpdateError::AllocError(_) => {
+                "the descriptor set couldn't be allocated from the pool"
+            },
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            DescriptorSetUpdateError::AllocError(ref err) => Some(err),
+            _ => None,
         }
     }
 }
 
-/*
+impl fmt::Display for DescriptorSetUpdateError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl From<DescriptorPoolAllocError> for DescriptorSetUpdateError {
+    #[inline]
+    fn from(err: DescriptorPoolAllocError) -> DescriptorSetUpdateError {
+        DescriptorSetUpdateError::AllocError(err)
+    }
+}
+
 /// Internal trait related to the `SimpleDescriptorSet` system.
+///
+/// Implemented on the list of resources of a `SimpleDescriptorSet`.
 pub unsafe trait SimpleDescriptorSetResourcesCollection {
-    #[inline]
-    fn add_transition<'a>(&'a self, sink: &mut CommandsListSink<'a>);
+    /// Returns the number of buffers within the collection.
+    fn num_buffers(&self) -> usize;
+
+    /// Returns the `num`th buffer of the collection, or `None` if out of range.
+    fn buffer(&self, num: usize) -> Option<(&BufferAccess, DescriptorResourceAccess)>;
+
+    /// Returns the number of images within the collection.
+    fn num_images(&self) -> usize;
+
+    /// Returns the `num`th image of the collection, or `None` if out of range.
+    fn image(&self, num: usize) -> Option<(&ImageAccess, DescriptorResourceAccess)>;
 }
 
 unsafe impl SimpleDescriptorSetResourcesCollection for () {
     #[inline]
-    fn add_transition<'a>(&'a self, _: &mut CommandsListSink<'a>) {
+    fn num_buffers(&self) -> usize {
+        0
     }
-}*/
+
+    #[inline]
+    fn buffer(&self, _: usize) -> Option<(&BufferAccess, DescriptorResourceAccess)> {
+        None
+    }
+
+    #[inline]
+    fn num_images(&self) -> usize {
+        0
+    }
+
+    #[inline]
+    fn image(&self, _: usize) -> Option<(&ImageAccess, DescriptorResourceAccess)> {
+        None
+    }
+}
 
 /// Internal object related to the `SimpleDescriptorSet` system.
 pub struct SimpleDescriptorSetBuf<B> {
@@ -428,28 +687,39 @@ pub struct SimpleDescriptorSetBuf<B> {
     access: AccessFlagBits,
 }
 
-/*unsafe impl<B> SimpleDescriptorSetResourcesCollection for SimpleDescriptorSetBuf<B>
+unsafe impl<B> SimpleDescriptorSetResourcesCollection for SimpleDescriptorSetBuf<B>
     where B: BufferAccess
 {
     #[inline]
-    fn add_transition<'a>(&'a self, sink: &mut CommandsListSink<'a>) {
-        // TODO: wrong values
-        let stages = PipelineStages {
-            compute_shader: true,
-            all_graphics: true,
-            .. PipelineStages::none()
-        };
-        
-        let access = AccessFlagBits {
-            uniform_read: true,
-            shader_read: true,
-            shader_write: true,
-            .. AccessFlagBits::none()
-        };
+    fn num_buffers(&self) -> usize {
+        1
+    }
+
+    #[inline]
+    fn buffer(&self, num: usize) -> Option<(&BufferAccess, DescriptorResourceAccess)> {
+        if num != 0 {
+            return None;
+        }
 
-        sink.add_buffer_transition(&self.buffer, 0, self.buffer.size(), self.write, stages, access);
+        Some((&self.buffer,
+              DescriptorResourceAccess {
+                  stages: self.stage,
+                  access: self.access,
+                  write: self.write,
+                  layout: ImageLayout::Undefined,
+              }))
     }
-}*/
+
+    #[inline]
+    fn num_images(&self) -> usize {
+        0
+    }
+
+    #[inline]
+    fn image(&self, _: usize) -> Option<(&ImageAccess, DescriptorResourceAccess)> {
+        None
+    }
+}
 
 /// Internal object related to the `SimpleDescriptorSet` system.
 pub struct SimpleDescriptorSetBufView<V>
@@ -461,29 +731,39 @@ pub struct SimpleDescriptorSetBufView<V>
     access: AccessFlagBits,
 }
 
-/*unsafe impl<V> SimpleDescriptorSetResourcesCollection for SimpleDescriptorSetBufView<V>
-    where V: BufferViewRef, V::BufferAccess: BufferAccess
+unsafe impl<V> SimpleDescriptorSetResourcesCollection for SimpleDescriptorSetBufView<V>
+    where V: BufferViewRef
 {
     #[inline]
-    fn add_transition<'a>(&'a self, sink: &mut CommandsListSink<'a>) {
-        // TODO: wrong values
-        let stages = PipelineStages {
-            compute_shader: true,
-            all_graphics: true,
-            .. PipelineStages::none()
-        };
-        
-        let access = AccessFlagBits {
-            uniform_read: true,
-            shader_read: true,
-            shader_write: true,
-            .. AccessFlagBits::none()
-        };
+    fn num_buffers(&self) -> usize {
+        1
+    }
 
-        sink.add_buffer_transition(self.view.view().buffer(), 0, self.view.view().buffer().size(),
-                                   self.write, stages, access);
+    #[inline]
+    fn buffer(&self, num: usize) -> Option<(&BufferAccess, DescriptorResourceAccess)> {
+        if num != 0 {
+            return None;
+        }
+
+        Some((self.view.view().buffer(),
+              DescriptorResourceAccess {
+                  stages: self.stage,
+                  access: self.access,
+                  write: self.write,
+                  layout: ImageLayout::Undefined,
+              }))
     }
-}*/
+
+    #[inline]
+    fn num_images(&self) -> usize {
+        0
+    }
+
+    #[inline]
+    fn image(&self, _: usize) -> Option<(&ImageAccess, DescriptorResourceAccess)> {
+        None
+    }
+}
 
 /// Internal object related to the `SimpleDescriptorSet` system.
 pub struct SimpleDescriptorSetImg<I> {
@@ -499,30 +779,76 @@ pub struct SimpleDescriptorSetImg<I> {
     access: AccessFlagBits,
 }
 
-/*unsafe impl<I> SimpleDescriptorSetResourcesCollection for SimpleDescriptorSetImg<I>
+unsafe impl<I> SimpleDescriptorSetResourcesCollection for SimpleDescriptorSetImg<I>
     where I: ImageViewAccess
 {
     #[inline]
-    fn add_transition<'a>(&'a self, sink: &mut CommandsListSink<'a>) {
-        // TODO: wrong values
-        let stages = PipelineStages {
-            compute_shader: true,
-            all_graphics: true,
-            .. PipelineStages::none()
-        };
-        
-        let access = AccessFlagBits {
-            uniform_read: true,
-            input_attachment_read: true,
-            shader_read: true,
-            shader_write: true,
-            .. AccessFlagBits::none()
-        };
+    fn num_buffers(&self) -> usize {
+        0
+    }
+
+    #[inline]
+    fn buffer(&self, _: usize) -> Option<(&BufferAccess, DescriptorResourceAccess)> {
+        None
+    }
+
+    #[inline]
+    fn num_images(&self) -> usize {
+        1
+    }
+
+    #[inline]
+    fn image(&self, num: usize) -> Option<(&ImageAccess, DescriptorResourceAccess)> {
+        if num != 0 {
+            return None;
+        }
 
-        // FIXME: adjust layers & mipmaps with the view's parameters
-        sink.add_image_transition(self.image.parent(), self.first_layer, self.num_layers,
-                                  self.first_mipmap, self.num_mipmaps, self.write,
-                                  self.layout, stages, access);
+        // FIXME: only the mipmaps and layers of the view are accessed
+        Some((self.image.parent(),
+              DescriptorResourceAccess {
+                  stages: self.stage,
+                  access: self.access,
+                  write: self.write,
+                  layout: self.layout,
+              }))
+    }
+}
+
+unsafe impl<T> SimpleDescriptorSetResourcesCollection for Vec<T>
+    where T: SimpleDescriptorSetResourcesCollection
+{
+    #[inline]
+    fn num_buffers(&self) -> usize {
+        self.iter().map(|r| r.num_buffers()).sum()
+    }
+
+    fn buffer(&self, mut num: usize) -> Option<(&BufferAccess, DescriptorResourceAccess)> {
+        for r in self.iter() {
+            let n = r.num_buffers();
+            if num < n {
+                return r.buffer(num);
+            }
+            num -= n;
+        }
+
+        None
+    }
+
+    #[inline]
+    fn num_images(&self) -> usize {
+        self.iter().map(|r| r.num_images()).sum()
+    }
+
+    fn image(&self, mut num: usize) -> Option<(&ImageAccess, DescriptorResourceAccess)> {
+        for r in self.iter() {
+            let n = r.num_images();
+            if num < n {
+                return r.image(num);
+            }
+            num -= n;
+        }
+
+        None
     }
 }
 
@@ -531,8 +857,280 @@ unsafe impl<A, B> SimpleDescriptorSetResourcesCollection for (A, B)
           B: SimpleDescriptorSetResourcesCollection
 {
     #[inline]
-    fn add_transition<'a>(&'a self, sink: &mut CommandsListSink<'a>) {
-        self.0.add_transition(sink);
-        self.1.add_transition(sink);
+    fn num_buffers(&self) -> usize {
+        self.0.num_buffers() + self.1.num_buffers()
     }
-}*/
+
+    #[inline]
+    fn buffer(&self, num: usize) -> Option<(&BufferAccess, DescriptorResourceAccess)> {
+        let first = self.0.num_buffers();
+        if num < first {
+            self.0.buffer(num)
+        } else {
+            self.1.buffer(num - first)
+        }
+    }
+
+    #[inline]
+    fn num_images(&self) -> usize {
+        self.0.num_images() + self.1.num_images()
+    }
+
+    #[inline]
+    fn image(&self, num: usize) -> Option<(&ImageAccess, DescriptorResourceAccess)> {
+        let first = self.0.num_images();
+        if num < first {
+            self.0.image(num)
+        } else {
+            self.1.image(num - first)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use descriptor::descriptor::DescriptorDesc;
+    use descriptor::descriptor::DescriptorDescTy;
+    use descriptor::descriptor::DescriptorImageDesc;
+    use descriptor::descriptor::DescriptorImageDescArray;
+    use descriptor::descriptor::DescriptorImageDescDimensions;
+    use descriptor::descriptor::ShaderStages;
+    use descriptor::descriptor_set::DescriptorSet;
+    use descriptor::descriptor_set::DescriptorSetUpdateError;
+    use descriptor::descriptor_set::SimpleDescriptorSetBuilder;
+    use descriptor::descriptor_set::SimpleDescriptorSetImageExt;
+    use descriptor::pipeline_layout::PipelineLayoutDesc;
+    use descriptor::pipeline_layout::PipelineLayoutDescNames;
+    use descriptor::pipeline_layout::PipelineLayoutDescPcRange;
+    use format::R8G8B8A8Unorm;
+    use image::AttachmentImage;
+    use image::Dimensions;
+    use image::ImageLayout;
+    use image::ImageUsage;
+    use image::MipmapsCount;
+    use image::StorageImage;
+    use image::immutable::ImmutableImage;
+    use sampler::Sampler;
+    use sync::PipelineStages;
+
+    // Layout with a single binding of `array_count` combined image samplers, named `tex`, in
+    // set 0.
+    struct SampledImageDesc {
+        array_count: u32,
+        multisampled: bool,
+    }
+
+    impl SampledImageDesc {
+        fn new(array_count: u32) -> SampledImageDesc {
+            SampledImageDesc {
+                array_count: array_count,
+                multisampled: false,
+            }
+        }
+    }
+
+    unsafe impl PipelineLayoutDesc for SampledImageDesc {
+        fn num_sets(&self) -> usize {
+            1
+        }
+
+        fn num_bindings_in_set(&self, set: usize) -> Option<usize> {
+            if set == 0 { Some(1) } else { None }
+        }
+
+        fn descriptor(&self, set: usize, binding: usize) -> Option<DescriptorDesc> {
+            if set != 0 || binding != 0 {
+                return None;
+            }
+
+            Some(DescriptorDesc {
+                     ty: DescriptorDescTy::CombinedImageSampler(DescriptorImageDesc {
+                         sampled: true,
+                         dimensions: DescriptorImageDescDimensions::TwoDimensional,
+                         format: None,
+                         multisampled: self.multisampled,
+                         array_layers: DescriptorImageDescArray::NonArrayed,
+                     }),
+                     array_count: self.array_count,
+                     stages: ShaderStages {
+                         fragment: true,
+                         ..ShaderStages::none()
+                     },
+                     readonly: true,
+                 })
+        }
+
+        fn num_push_constants_ranges(&self) -> usize {
+            0
+        }
+
+        fn push_constants_range(&self, _: usize) -> Option<PipelineLayoutDescPcRange> {
+            None
+        }
+    }
+
+    unsafe impl PipelineLayoutDescNames for SampledImageDesc {
+        fn descriptor_by_name(&self, name: &str) -> Option<(usize, usize)> {
+            if name == "tex" { Some((0, 0)) } else { None }
+        }
+    }
+
+    #[test]
+    fn uninitialized_image() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let layout = Arc::new(SampledImageDesc::new(1).build(device.clone()).unwrap());
+        let sampler = Sampler::simple_repeat_linear(device.clone());
+
+        // The initialization access is never used, so the image stays uninitialized.
+        let (image, _init) = unsafe {
+            ImmutableImage::uninitialized(device.clone(),
+                                          Dimensions::Dim2d {
+                                              width: 16,
+                                              height: 16,
+                                          },
+                                          R8G8B8A8Unorm,
+                                          MipmapsCount::One,
+                                          Some(queue.family()))
+                .unwrap()
+        };
+
+        let builder = SimpleDescriptorSetBuilder::new(layout, 0);
+        match SimpleDescriptorSetImageExt::add_me((image, sampler), builder, "tex") {
+            Err(DescriptorSetUpdateError::UninitializedResource { binding: 0 }) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn resource_access_narrowed_to_descriptor_stages() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let layout = Arc::new(SampledImageDesc::new(1).build(device.clone()).unwrap());
+        let sampler = Sampler::simple_repeat_linear(device.clone());
+        let image = StorageImage::new(device.clone(),
+                                      Dimensions::Dim2d {
+                                          width: 16,
+                                          height: 16,
+                                      },
+                                      R8G8B8A8Unorm,
+                                      Some(queue.family()))
+            .unwrap();
+
+        let builder = SimpleDescriptorSetBuilder::new(layout, 0);
+        let set = SimpleDescriptorSetImageExt::add_me((image, sampler), builder, "tex")
+            .unwrap()
+            .build()
+            .unwrap();
+
+        assert_eq!(set.num_buffers(), 0);
+        assert_eq!(set.num_images(), 1);
+        assert!(set.image(1).is_none());
+
+        // The texture is only read by the fragment shader.
+        let (_, usage) = set.image(0).unwrap();
+        assert_eq!(usage.stages,
+                   PipelineStages {
+                       fragment_shader: true,
+                       ..PipelineStages::none()
+                   });
+        assert!(usage.access.shader_read);
+        assert!(!usage.access.shader_write);
+        assert!(!usage.write);
+        assert_eq!(usage.layout, ImageLayout::General);
+    }
+
+    #[test]
+    fn wrong_descriptor_count() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let layout = Arc::new(SampledImageDesc::new(2).build(device.clone()).unwrap());
+        let sampler = Sampler::simple_repeat_linear(device.clone());
+        let image = StorageImage::new(device.clone(),
+                                      Dimensions::Dim2d {
+                                          width: 16,
+                                          height: 16,
+                                      },
+                                      R8G8B8A8Unorm,
+                                      Some(queue.family()))
+            .unwrap();
+
+        let builder = SimpleDescriptorSetBuilder::new(layout.clone(), 0);
+        let single = (image.clone(), sampler.clone());
+        match SimpleDescriptorSetImageExt::add_me(single, builder, "tex") {
+            Err(DescriptorSetUpdateError::WrongDescriptorCount {
+                    binding: 0,
+                    expected: 2,
+                    actual: 1,
+                }) => (),
+            _ => panic!(),
+        }
+
+        let images = vec![(image.clone(), sampler.clone()); 3];
+        let builder = SimpleDescriptorSetBuilder::new(layout.clone(), 0);
+        match SimpleDescriptorSetImageExt::add_me(images, builder, "tex") {
+            Err(DescriptorSetUpdateError::WrongDescriptorCount {
+                    binding: 0,
+                    expected: 2,
+                    actual: 3,
+                }) => (),
+            _ => panic!(),
+        }
+
+        // An array of exactly the declared size is accepted.
+        let images = vec![(image, sampler); 2];
+        let builder = SimpleDescriptorSetBuilder::new(layout, 0);
+        assert!(SimpleDescriptorSetImageExt::add_me(images, builder, "tex").is_ok());
+    }
+
+    #[test]
+    fn multisampled_mismatch() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let sampler = Sampler::simple_repeat_linear(device.clone());
+        let usage = ImageUsage {
+            sampled: true,
+            ..ImageUsage::none()
+        };
+        let single = AttachmentImage::with_usage(device.clone(), [16, 16], R8G8B8A8Unorm, usage)
+            .unwrap();
+        let multi = AttachmentImage::multisampled_with_usage(device.clone(),
+                                                             [16, 16],
+                                                             4,
+                                                             R8G8B8A8Unorm,
+                                                             usage)
+            .unwrap();
+
+        let layout = Arc::new(SampledImageDesc::new(1).build(device.clone()).unwrap());
+        let builder = SimpleDescriptorSetBuilder::new(layout, 0);
+        let multi_sampled = (multi.clone(), sampler.clone());
+        match SimpleDescriptorSetImageExt::add_me(multi_sampled, builder, "tex") {
+            Err(DescriptorSetUpdateError::MultisampledMismatch {
+                    binding: 0,
+                    multisampled: false,
+                    samples: 4,
+                }) => (),
+            _ => panic!(),
+        }
+
+        let ms_layout = SampledImageDesc {
+            array_count: 1,
+            multisampled: true,
+        };
+        let ms_layout = Arc::new(ms_layout.build(device.clone()).unwrap());
+        let builder = SimpleDescriptorSetBuilder::new(ms_layout.clone(), 0);
+        match SimpleDescriptorSetImageExt::add_me((single, sampler.clone()), builder, "tex") {
+            Err(DescriptorSetUpdateError::MultisampledMismatch {
+                    binding: 0,
+                    multisampled: true,
+                    samples: 1,
+                }) => (),
+            _ => panic!(),
+        }
+
+        let builder = SimpleDescriptorSetBuilder::new(ms_layout, 0);
+        assert!(SimpleDescriptorSetImageExt::add_me((multi, sampler), builder, "tex").is_ok());
+    }
+}
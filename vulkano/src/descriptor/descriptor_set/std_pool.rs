@@ -7,6 +7,8 @@
 // notice may not be copied, modified, or distributed except
 // according to those terms.
 
+use std::error;
+use std::fmt;
 use std::sync::Arc;
 use std::sync::Mutex;
 
@@ -24,45 +26,172 @@ use device::DeviceOwned;
 /// Standard implementation of a descriptor pool.
 ///
 /// Whenever a set is allocated, this implementation will try to find a pool that has some space
-/// for it. If there is one, allocate from it. If there is none, create a new pool whose capacity
-/// is 40 sets and 40 times the requested descriptors. This number is arbitrary.
+/// for it. If there is one, allocate from it. If there is none, create a new pool. The size of
+/// the new pool and the maximum number of pools are controlled by a `StdDescriptorPoolPolicy`.
+///
+/// Each set remembers the pool it was allocated from and is freed to it when dropped. A pool
+/// whose sets have all been freed is reset, which makes its whole capacity available again even
+/// if it was fragmented.
 pub struct StdDescriptorPool {
     device: Arc<Device>,
+    policy: StdDescriptorPoolPolicy,
     pools: Mutex<Vec<Arc<Mutex<Pool>>>>,
 }
 
+/// Controls how a `StdDescriptorPool` creates its Vulkan pools.
+///
+/// The default policy creates pools of 40 sets, with no maximum number of pools. Each pool can
+/// hold 40 times the descriptors of the set whose allocation created it.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct StdDescriptorPoolPolicy {
+    /// Number of sets of the first pool. Must be at least 1.
+    pub initial_sets: u32,
+    /// Number of descriptors of each type of the first pool. The following pools get these
+    /// numbers multiplied by the same factor as their number of sets, rounded up.
+    ///
+    /// If `None`, each pool can hold as many times the descriptors of the set whose allocation
+    /// created it as it can hold sets. In both cases, the numbers are raised if needed so that
+    /// this set fits.
+    pub initial_descriptors: Option<DescriptorsCount>,
+    /// Factor by which the number of sets is multiplied each time a new pool is created. Must be
+    /// at least 1.0. A factor of 1.0 means that all the pools have the same size.
+    pub growth_factor: f32,
+    /// Maximum number of pools. Once this number is reached, allocating a set that doesn't fit
+    /// in any of the existing pools returns `StdDescriptorPoolAllocError::OutOfPoolMemory` or
+    /// `StdDescriptorPoolAllocError::FragmentedPool`.
+    pub max_pools: Option<u32>,
+}
+
+impl StdDescriptorPoolPolicy {
+    /// Returns the number of sets of the pool at index `pool_index`, with the first pool at
+    /// index 0.
+    #[inline]
+    pub fn sets_of_pool(&self, pool_index: u32) -> u32 {
+        let sets = self.initial_sets as f64 * (self.growth_factor as f64).powi(pool_index as i32);
+        if sets >= u32::max_value() as f64 {
+            u32::max_value()
+        } else {
+            sets as u32
+        }
+    }
+
+    /// Returns the number of descriptors of each type of the pool at index `pool_index`, when
+    /// this pool is created in order to allocate a set with the descriptors `set_descriptors`.
+    pub fn descriptors_of_pool(&self, pool_index: u32, set_descriptors: &DescriptorsCount)
+                               -> DescriptorsCount {
+        let num_sets = self.sets_of_pool(pool_index);
+
+        match self.initial_descriptors {
+            Some(initial) => {
+                let ratio = (num_sets - 1) / self.initial_sets + 1;
+                initial.saturating_mul(ratio).max(set_descriptors)
+            },
+            None => set_descriptors.saturating_mul(num_sets),
+        }
+    }
+}
+
+impl Default for StdDescriptorPoolPolicy {
+    #[inline]
+    fn default() -> StdDescriptorPoolPolicy {
+        StdDescriptorPoolPolicy {
+            initial_sets: 40,
+            initial_descriptors: None,
+            growth_factor: 1.0,
+            max_pools: None,
+        }
+    }
+}
+
 struct Pool {
     pool: UnsafeDescriptorPool,
+    // Index of the pool in the order of creation.
+    index: u32,
+    capacity: DescriptorsCount,
+    sets_count: u32,
     remaining_capacity: DescriptorsCount,
     remaining_sets_count: u32,
+    // Number of sets and descriptors that are currently allocated from this pool.
+    allocated_sets: u32,
+    allocated_descriptors: DescriptorsCount,
+}
+
+/// Statistics about the sets allocated from a `StdDescriptorPool`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct StdDescriptorPoolStats {
+    /// Number of Vulkan pools that have been created so far.
+    pub pools_created: u32,
+    /// Number of sets that are currently allocated.
+    pub sets_allocated: u32,
+    /// Number of descriptors of each type that are currently allocated.
+    pub descriptors_allocated: DescriptorsCount,
 }
 
 impl StdDescriptorPool {
-    /// Builds a new `StdDescriptorPool`.
+    /// Builds a new `StdDescriptorPool` with the default policy.
+    #[inline]
     pub fn new(device: Arc<Device>) -> StdDescriptorPool {
+        StdDescriptorPool::with_policy(device, StdDescriptorPoolPolicy::default())
+    }
+
+    /// Builds a new `StdDescriptorPool` with the given policy.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `policy.initial_sets` is 0 or if `policy.growth_factor` is less than 1.0.
+    ///
+    pub fn with_policy(device: Arc<Device>, policy: StdDescriptorPoolPolicy) -> StdDescriptorPool {
+        assert!(policy.initial_sets >= 1);
+        assert!(policy.growth_factor >= 1.0);
+
         StdDescriptorPool {
             device: device,
+            policy: policy,
             pools: Mutex::new(Vec::new()),
         }
     }
-}
 
-/// A descriptor set allocated from a `StdDescriptorPool`.
-pub struct StdDescriptorPoolAlloc {
-    pool: Arc<Mutex<Pool>>,
-    // The set. Inside an option so that we can extract it in the destructor.
-    set: Option<UnsafeDescriptorSet>,
-    // We need to keep track of this count in order to add it back to the capacity when freeing.
-    descriptors: DescriptorsCount,
-}
+    /// Returns the policy that was passed when creating the pool.
+    #[inline]
+    pub fn policy(&self) -> &StdDescriptorPoolPolicy {
+        &self.policy
+    }
 
-unsafe impl DescriptorPool for Arc<StdDescriptorPool> {
-    type Alloc = StdDescriptorPoolAlloc;
+    /// Returns the number of Vulkan pools that have been created so far.
+    #[inline]
+    pub fn num_pools(&self) -> usize {
+        self.pools.lock().unwrap().len()
+    }
+
+    /// Returns statistics about the sets that are currently allocated.
+    pub fn statistics(&self) -> StdDescriptorPoolStats {
+        let pools = self.pools.lock().unwrap();
+
+        let mut stats = StdDescriptorPoolStats {
+            pools_created: pools.len() as u32,
+            sets_allocated: 0,
+            descriptors_allocated: DescriptorsCount::zero(),
+        };
+
+        for pool in pools.iter() {
+            let pool = pool.lock().unwrap();
+            stats.sets_allocated += pool.allocated_sets;
+            stats.descriptors_allocated += pool.allocated_descriptors;
+        }
 
+        stats
+    }
+
+    /// Allocates a descriptor set with the given layout.
+    ///
+    /// Same as `DescriptorPool::alloc`, except that reaching the maximum number of pools of the
+    /// policy is reported with distinct errors.
     // TODO: eventually use a lock-free algorithm?
-    fn alloc(&self, layout: &UnsafeDescriptorSetLayout)
-             -> Result<StdDescriptorPoolAlloc, OomError> {
-        let mut pools = self.pools.lock().unwrap();
+    pub fn try_alloc(me: &Arc<Self>, layout: &UnsafeDescriptorSetLayout)
+                     -> Result<StdDescriptorPoolAlloc, StdDescriptorPoolAllocError> {
+        let mut pools = me.pools.lock().unwrap();
+        // True if one of the pools tried by this call reported that it is fragmented.
+        let mut fragmented = false;
 
         // Try find an existing pool with some free space.
         for pool_arc in pools.iter_mut() {
@@ -88,10 +217,17 @@ unsafe impl DescriptorPool for Arc<StdDescriptorPool> {
                     Ok(mut sets) => sets.next().unwrap(),
                     // An error can happen if we're out of memory, or if the pool is fragmented.
                     // We handle these errors by just ignoring this pool and trying the next ones.
+                    Err(DescriptorPoolAllocError::FragmentedPool) => {
+                        fragmented = true;
+                        continue;
+                    },
                     Err(_) => continue,
                 }
             };
 
+            pool.allocated_sets += 1;
+            pool.allocated_descriptors += *layout.descriptors_count();
+
             return Ok(StdDescriptorPoolAlloc {
                           pool: pool_arc.clone(),
                           set: Some(alloc),
@@ -100,20 +236,31 @@ unsafe impl DescriptorPool for Arc<StdDescriptorPool> {
         }
 
         // No existing pool can be used. Create a new one.
-        // We use an arbitrary number of 40 sets and 40 times the requested descriptors.
-        let count = layout.descriptors_count().clone() * 40;
+        if let Some(max) = me.policy.max_pools {
+            if pools.len() as u32 >= max {
+                return Err(if fragmented {
+                               StdDescriptorPoolAllocError::FragmentedPool { max: max }
+                           } else {
+                               StdDescriptorPoolAllocError::OutOfPoolMemory { max: max }
+                           });
+            }
+        }
+
+        let index = pools.len() as u32;
+        let num_sets = me.policy.sets_of_pool(index);
+        let count = me.policy.descriptors_of_pool(index, layout.descriptors_count());
         // Failure to allocate a new pool results in an error for the whole function because
         // there's no way we can recover from that.
-        let mut new_pool = UnsafeDescriptorPool::new(self.device.clone(), &count, 40, true)?;
+        let mut new_pool = UnsafeDescriptorPool::new(me.device.clone(), &count, num_sets, true)?;
 
         let alloc = unsafe {
             match new_pool.alloc(Some(layout)) {
                 Ok(mut sets) => sets.next().unwrap(),
                 Err(DescriptorPoolAllocError::OutOfHostMemory) => {
-                    return Err(OomError::OutOfHostMemory);
+                    return Err(OomError::OutOfHostMemory.into());
                 },
                 Err(DescriptorPoolAllocError::OutOfDeviceMemory) => {
-                    return Err(OomError::OutOfDeviceMemory);
+                    return Err(OomError::OutOfDeviceMemory.into());
                 },
                 // A fragmented pool error can't happen at the first ever allocation.
                 Err(DescriptorPoolAllocError::FragmentedPool) => unreachable!(),
@@ -124,9 +271,14 @@ unsafe impl DescriptorPool for Arc<StdDescriptorPool> {
 
         let pool_obj = Arc::new(Mutex::new(Pool {
                                                pool: new_pool,
+                                               index: index,
+                                               capacity: count,
+                                               sets_count: num_sets,
                                                remaining_capacity: count -
                                                    *layout.descriptors_count(),
-                                               remaining_sets_count: 40 - 1,
+                                               remaining_sets_count: num_sets - 1,
+                                               allocated_sets: 1,
+                                               allocated_descriptors: *layout.descriptors_count(),
                                            }));
 
         pools.push(pool_obj.clone());
@@ -139,6 +291,101 @@ unsafe impl DescriptorPool for Arc<StdDescriptorPool> {
     }
 }
 
+/// A descriptor set allocated from a `StdDescriptorPool`.
+pub struct StdDescriptorPoolAlloc {
+    pool: Arc<Mutex<Pool>>,
+    // The set. Inside an option so that we can extract it in the destructor.
+    set: Option<UnsafeDescriptorSet>,
+    // We need to keep track of this count in order to add it back to the capacity when freeing.
+    descriptors: DescriptorsCount,
+}
+
+impl StdDescriptorPoolAlloc {
+    /// Returns the index of the Vulkan pool the set was allocated from, in the order in which the
+    /// pools were created.
+    #[inline]
+    pub fn pool_index(&self) -> u32 {
+        self.pool.lock().unwrap().index
+    }
+}
+
+unsafe impl DescriptorPool for Arc<StdDescriptorPool> {
+    type Alloc = StdDescriptorPoolAlloc;
+
+    // Use `StdDescriptorPool::try_alloc` to also get the maximum number of pools when it is
+    // reached.
+    #[inline]
+    fn alloc(&self, layout: &UnsafeDescriptorSetLayout)
+             -> Result<StdDescriptorPoolAlloc, DescriptorPoolAllocError> {
+        match StdDescriptorPool::try_alloc(self, layout) {
+            Ok(alloc) => Ok(alloc),
+            Err(StdDescriptorPoolAllocError::OomError(OomError::OutOfHostMemory)) => {
+                Err(DescriptorPoolAllocError::OutOfHostMemory)
+            },
+            Err(StdDescriptorPoolAllocError::OomError(OomError::OutOfDeviceMemory)) => {
+                Err(DescriptorPoolAllocError::OutOfDeviceMemory)
+            },
+            Err(StdDescriptorPoolAllocError::OutOfPoolMemory { .. }) => {
+                Err(DescriptorPoolAllocError::OutOfPoolMemory)
+            },
+            Err(StdDescriptorPoolAllocError::FragmentedPool { .. }) => {
+                Err(DescriptorPoolAllocError::FragmentedPool)
+            },
+        }
+    }
+}
+
+/// Error that can happen when allocating a set from a `StdDescriptorPool`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StdDescriptorPoolAllocError {
+    /// Not enough memory.
+    OomError(OomError),
+    /// None of the existing pools has enough space left for the set, and the maximum number of
+    /// pools of the policy has been reached.
+    OutOfPoolMemory { max: u32 },
+    /// Some of the existing pools have enough space left for the set, but it is too fragmented
+    /// to be used. The maximum number of pools of the policy has been reached.
+    FragmentedPool { max: u32 },
+}
+
+impl error::Error for StdDescriptorPoolAllocError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            StdDescriptorPoolAllocError::OomError(_) => "not enough memory available",
+            StdDescriptorPoolAllocError::OutOfPoolMemory { .. } => {
+                "the descriptor pools are full and the maximum number of pools has been reached"
+            },
+            StdDescriptorPoolAllocError::FragmentedPool { .. } => {
+                "the descriptor pools are fragmented and the maximum number of pools has been \
+                 reached"
+            },
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            StdDescriptorPoolAllocError::OomError(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for StdDescriptorPoolAllocError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl From<OomError> for StdDescriptorPoolAllocError {
+    #[inline]
+    fn from(err: OomError) -> StdDescriptorPoolAllocError {
+        StdDescriptorPoolAllocError::OomError(err)
+    }
+}
+
 unsafe impl DeviceOwned for StdDescriptorPool {
     #[inline]
     fn device(&self) -> &Arc<Device> {
@@ -167,6 +414,223 @@ impl Drop for StdDescriptorPoolAlloc {
             // Add back the capacity only after freeing, in case of a panic during the free.
             pool.remaining_sets_count += 1;
             pool.remaining_capacity += self.descriptors;
+            pool.allocated_sets -= 1;
+            pool.allocated_descriptors -= self.descriptors;
+
+            // Once the pool is empty, resetting it gets rid of the fragmentation and of the
+            // capacity that was removed after failed allocations.
+            if pool.allocated_sets == 0 && pool.pool.reset().is_ok() {
+                pool.remaining_sets_count = pool.sets_count;
+                pool.remaining_capacity = pool.capacity;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use descriptor::descriptor::DescriptorBufferContentDesc;
+    use descriptor::descriptor::DescriptorBufferDesc;
+    use descriptor::descriptor::DescriptorDesc;
+    use descriptor::descriptor::DescriptorDescTy;
+    use descriptor::descriptor::ShaderStages;
+    use descriptor::descriptor_set::DescriptorPool;
+    use descriptor::descriptor_set::DescriptorPoolAllocError;
+    use descriptor::descriptor_set::DescriptorsCount;
+    use descriptor::descriptor_set::StdDescriptorPool;
+    use descriptor::descriptor_set::StdDescriptorPoolAllocError;
+    use descriptor::descriptor_set::StdDescriptorPoolPolicy;
+    use descriptor::descriptor_set::StdDescriptorPoolStats;
+    use descriptor::descriptor_set::UnsafeDescriptorSetLayout;
+    use device::Device;
+    use std::iter;
+    use std::sync::Arc;
+
+    fn buffer_layout(device: Arc<Device>, storage: bool) -> UnsafeDescriptorSetLayout {
+        let layout = DescriptorDesc {
+            ty: DescriptorDescTy::Buffer(DescriptorBufferDesc {
+                                             dynamic: Some(false),
+                                             storage: storage,
+                                             content: DescriptorBufferContentDesc::F32,
+                                         }),
+            array_count: 1,
+            stages: ShaderStages::all_graphics(),
+            readonly: true,
+        };
+        UnsafeDescriptorSetLayout::new(device, iter::once(Some(layout))).unwrap()
+    }
+
+    #[test]
+    fn policy_growth() {
+        let policy = StdDescriptorPoolPolicy {
+            initial_sets: 8,
+            initial_descriptors: None,
+            growth_factor: 2.0,
+            max_pools: None,
+        };
+        assert_eq!(policy.sets_of_pool(0), 8);
+        assert_eq!(policy.sets_of_pool(3), 64);
+        assert_eq!(policy.sets_of_pool(100), u32::max_value());
+
+        assert_eq!(StdDescriptorPoolPolicy::default().sets_of_pool(5), 40);
+    }
+
+    #[test]
+    fn policy_descriptors_saturate() {
+        let set = DescriptorsCount {
+            uniform_buffer: 2,
+            ..DescriptorsCount::zero()
+        };
+
+        let policy = StdDescriptorPoolPolicy {
+            initial_sets: 8,
+            initial_descriptors: None,
+            growth_factor: 2.0,
+            max_pools: None,
+        };
+        assert_eq!(policy.descriptors_of_pool(100, &set).uniform_buffer, u32::max_value());
+
+        let policy = StdDescriptorPoolPolicy { initial_descriptors: Some(set), ..policy };
+        assert_eq!(policy.descriptors_of_pool(100, &set).uniform_buffer, u32::max_value());
+    }
+
+    #[test]
+    fn policy_descriptors() {
+        let set = DescriptorsCount {
+            uniform_buffer: 2,
+            sampler: 1,
+            ..DescriptorsCount::zero()
+        };
+
+        let policy = StdDescriptorPoolPolicy {
+            initial_sets: 4,
+            initial_descriptors: None,
+            growth_factor: 1.5,
+            max_pools: None,
+        };
+        assert_eq!(policy.descriptors_of_pool(1, &set), set * 6);
+
+        let policy = StdDescriptorPoolPolicy {
+            initial_descriptors: Some(DescriptorsCount {
+                                          uniform_buffer: 10,
+                                          ..DescriptorsCount::zero()
+                                      }),
+            ..policy
+        };
+        assert_eq!(policy.descriptors_of_pool(0, &set),
+                   DescriptorsCount {
+                       uniform_buffer: 10,
+                       sampler: 1,
+                       ..DescriptorsCount::zero()
+                   });
+        // The second pool has 6 sets, which is rounded up to twice the 4 sets of the first one.
+        assert_eq!(policy.descriptors_of_pool(1, &set),
+                   DescriptorsCount {
+                       uniform_buffer: 20,
+                       sampler: 1,
+                       ..DescriptorsCount::zero()
+                   });
+    }
+
+    #[test]
+    fn max_pools_reached() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let set_layout = buffer_layout(device.clone(), false);
+
+        let pool = Arc::new(StdDescriptorPool::with_policy(device,
+                                                           StdDescriptorPoolPolicy {
+                                                               initial_sets: 2,
+                                                               initial_descriptors: None,
+                                                               growth_factor: 2.0,
+                                                               max_pools: Some(2),
+                                                           }));
+
+        // The two pools hold 2 and 4 sets.
+        let sets = (0 .. 6)
+            .map(|_| StdDescriptorPool::try_alloc(&pool, &set_layout).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(pool.num_pools(), 2);
+
+        match StdDescriptorPool::try_alloc(&pool, &set_layout) {
+            Err(StdDescriptorPoolAllocError::OutOfPoolMemory { max: 2 }) => (),
+            _ => panic!(),
+        }
+
+        // The error is kept when going through the `DescriptorPool` trait.
+        match pool.alloc(&set_layout) {
+            Err(DescriptorPoolAllocError::OutOfPoolMemory) => (),
+            _ => panic!(),
         }
+
+        // Freeing a set makes room again.
+        drop(sets);
+        assert!(StdDescriptorPool::try_alloc(&pool, &set_layout).is_ok());
+        assert_eq!(pool.num_pools(), 2);
+    }
+
+    #[test]
+    fn mixed_sets_chain_pools() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let layouts = [buffer_layout(device.clone(), false), buffer_layout(device.clone(), true)];
+
+        let pool = Arc::new(StdDescriptorPool::with_policy(device,
+                                                           StdDescriptorPoolPolicy {
+                                                               initial_sets: 4,
+                                                               initial_descriptors:
+                                                                   Some(DescriptorsCount {
+                                                                       uniform_buffer: 4,
+                                                                       storage_buffer: 4,
+                                                                       ..DescriptorsCount::zero()
+                                                                   }),
+                                                               growth_factor: 2.0,
+                                                               max_pools: Some(3),
+                                                           }));
+
+        // Allocate sets of both types until the pools of 4, 8 and 16 sets are full.
+        let mut sets = Vec::new();
+        let err;
+        loop {
+            match StdDescriptorPool::try_alloc(&pool, &layouts[sets.len() % 2]) {
+                Ok(set) => sets.push(set),
+                Err(e) => {
+                    err = e;
+                    break;
+                },
+            }
+        }
+        assert_eq!(err, StdDescriptorPoolAllocError::OutOfPoolMemory { max: 3 });
+        assert_eq!(sets.len(), 28);
+
+        assert_eq!(sets[0].pool_index(), 0);
+        assert_eq!(sets[4].pool_index(), 1);
+        assert_eq!(sets[27].pool_index(), 2);
+
+        assert_eq!(pool.statistics(),
+                   StdDescriptorPoolStats {
+                       pools_created: 3,
+                       sets_allocated: 28,
+                       descriptors_allocated: DescriptorsCount {
+                           uniform_buffer: 14,
+                           storage_buffer: 14,
+                           ..DescriptorsCount::zero()
+                       },
+                   });
+
+        // Freeing sets of the first pool makes room in it for sets of the other type.
+        sets.drain(0 .. 2);
+        let set = StdDescriptorPool::try_alloc(&pool, &layouts[1]).unwrap();
+        assert_eq!(set.pool_index(), 0);
+        assert_eq!(pool.statistics().sets_allocated, 27);
+
+        drop(set);
+        drop(sets);
+        assert_eq!(pool.statistics(),
+                   StdDescriptorPoolStats {
+                       pools_created: 3,
+                       sets_allocated: 0,
+                       descriptors_allocated: DescriptorsCount::zero(),
+                   });
     }
 }
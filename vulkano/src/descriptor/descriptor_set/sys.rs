@@ -15,6 +15,8 @@ use std::mem;
 use std::ops;
 use std::ptr;
 use std::sync::Arc;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
 use std::vec::IntoIter as VecIntoIter;
 
 use buffer::BufferAccess;
@@ -30,6 +32,7 @@ use sampler::Sampler;
 use OomError;
 use VulkanObject;
 use check_errors;
+use record_error_context;
 use vk;
 
 /// A pool from which descriptor sets can be allocated.
@@ -43,7 +46,11 @@ pub unsafe trait DescriptorPool: DeviceOwned {
     type Alloc: DescriptorPoolAlloc;
 
     /// Allocates a descriptor set.
-    fn alloc(&self, layout: &UnsafeDescriptorSetLayout) -> Result<Self::Alloc, OomError>;
+    ///
+    /// Returns `OutOfPoolMemory` or `FragmentedPool` if the set can't be allocated because the
+    /// pool is full or fragmented and can't grow.
+    fn alloc(&self, layout: &UnsafeDescriptorSetLayout)
+             -> Result<Self::Alloc, DescriptorPoolAllocError>;
 }
 
 /// An allocated descriptor set.
@@ -89,6 +96,26 @@ macro_rules! descriptors_count {
                 }
             }
 
+            /// Returns, for each type of descriptor, the largest of the two counts.
+            #[inline]
+            pub fn max(&self, other: &DescriptorsCount) -> DescriptorsCount {
+                DescriptorsCount {
+                    $(
+                        $name: cmp::max(self.$name, other.$name),
+                    )+
+                }
+            }
+
+            /// Multiplies each count by `rhs`, saturating at `u32::MAX` instead of overflowing.
+            #[inline]
+            pub fn saturating_mul(&self, rhs: u32) -> DescriptorsCount {
+                DescriptorsCount {
+                    $(
+                        $name: self.$name.saturating_mul(rhs),
+                    )+
+                }
+            }
+
             /// Adds one descriptor of the given type to the count.
             #[inline]
             pub fn add_one(&mut self, ty: DescriptorType) {
@@ -120,12 +147,14 @@ macro_rules! descriptors_count {
 
         impl cmp::PartialOrd for DescriptorsCount {
             fn partial_cmp(&self, other: &DescriptorsCount) -> Option<cmp::Ordering> {
-                if $(self.$name > other.$name)&&+ {
+                // A count is greater than another if it is greater or equal for every type of
+                // descriptor, so that `a >= b` means that `b` fits in `a`.
+                if $(self.$name == other.$name)&&+ {
+                    Some(cmp::Ordering::Equal)
+                } else if $(self.$name >= other.$name)&&+ {
                     Some(cmp::Ordering::Greater)
-                } else if $(self.$name < other.$name)&&+ {
+                } else if $(self.$name <= other.$name)&&+ {
                     Some(cmp::Ordering::Less)
-                } else if $(self.$name == other.$name)&&+ {
-                    Some(cmp::Ordering::Equal)
                 } else {
                     None
                 }
@@ -295,7 +324,8 @@ impl UnsafeDescriptorPool {
             check_errors(vk.CreateDescriptorPool(device.internal_object(),
                                                  &infos,
                                                  ptr::null(),
-                                                 &mut output))?;
+                                                 &mut output),
+                         "vkCreateDescriptorPool")?;
             output
         };
 
@@ -369,6 +399,9 @@ impl UnsafeDescriptorPool {
         // According to the specs, because `VK_ERROR_FRAGMENTED_POOL` was added after version
         // 1.0 of Vulkan, any negative return value except out-of-memory errors must be
         // considered as a fragmented pool error.
+        if (ret as i32) < 0 {
+            record_error_context("vkAllocateDescriptorSets");
+        }
         match ret {
             vk::ERROR_OUT_OF_HOST_MEMORY => {
                 return Err(DescriptorPoolAllocError::OutOfHostMemory);
@@ -421,7 +454,8 @@ impl UnsafeDescriptorPool {
         check_errors(vk.FreeDescriptorSets(self.device.internal_object(),
                                            self.pool,
                                            sets.len() as u32,
-                                           sets.as_ptr()))?;
+                                           sets.as_ptr()),
+                     "vkFreeDescriptorSets")?;
         Ok(())
     }
 
@@ -432,7 +466,8 @@ impl UnsafeDescriptorPool {
         let vk = self.device.pointers();
         check_errors(vk.ResetDescriptorPool(self.device.internal_object(),
                                             self.pool,
-                                            0 /* reserved flags */))?;
+                                            0 /* reserved flags */),
+                     "vkResetDescriptorPool")?;
         Ok(())
     }
 }
@@ -514,7 +549,12 @@ impl Iterator for UnsafeDescriptorPoolAllocIter {
 
     #[inline]
     fn next(&mut self) -> Option<UnsafeDescriptorSet> {
-        self.sets.next().map(|s| UnsafeDescriptorSet { set: s })
+        self.sets.next().map(|s| {
+                                 UnsafeDescriptorSet {
+                                     set: s,
+                                     generation: next_generation(),
+                                 }
+                             })
     }
 
     #[inline]
@@ -533,9 +573,31 @@ impl ExactSizeIterator for UnsafeDescriptorPoolAllocIter {
 /// Instead it is an object meant to be used with the `UnsafeDescriptorPool`.
 pub struct UnsafeDescriptorSet {
     set: vk::DescriptorSet,
+    // Changes every time the set is written to. See `generation()`.
+    generation: usize,
+}
+
+// Source of the generations of all the descriptor sets. Sharing a single counter guarantees that
+// a set that reuses the handle of a freed set doesn't get the same generation.
+static NEXT_GENERATION: AtomicUsize = AtomicUsize::new(0);
+
+#[inline]
+fn next_generation() -> usize {
+    NEXT_GENERATION.fetch_add(1, Ordering::Relaxed)
 }
 
 impl UnsafeDescriptorSet {
+    /// Returns a number that changes every time the set is written to.
+    ///
+    /// Generations increase monotonically and are never shared between two descriptor sets, even
+    /// when a descriptor set is allocated with the handle of a descriptor set that has been freed.
+    /// An unchanged generation therefore identifies the same set with the same content, which is
+    /// used to avoid validating the same set multiple times.
+    #[inline]
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
     // TODO: add copying from other descriptor sets
     //       add a `copy` method that just takes a copy, and an `update` method that takes both
     //       writes and copies and that actually performs the operation
@@ -560,6 +622,7 @@ impl UnsafeDescriptorSet {
         where I: Iterator<Item = DescriptorWrite>
     {
         let vk = device.pointers();
+        self.generation = next_generation();
 
         // In this function, we build 4 arrays: one array of image descriptors (image_descriptors),
         // one for buffer descriptors (buffer_descriptors), one for buffer view descriptors
@@ -980,6 +1043,34 @@ mod tests {
     use descriptor::descriptor_set::UnsafeDescriptorSetLayout;
     use std::iter;
 
+    #[test]
+    fn count_ordering() {
+        let small = DescriptorsCount {
+            uniform_buffer: 1,
+            ..DescriptorsCount::zero()
+        };
+        let large = DescriptorsCount {
+            uniform_buffer: 3,
+            sampler: 2,
+            ..DescriptorsCount::zero()
+        };
+        let other = DescriptorsCount {
+            storage_buffer: 1,
+            ..DescriptorsCount::zero()
+        };
+
+        assert!(large >= small);
+        assert!(small < large);
+        assert!(!(large >= other));
+        assert!(!(other >= large));
+        assert_eq!(small.max(&other),
+                   DescriptorsCount {
+                       uniform_buffer: 1,
+                       storage_buffer: 1,
+                       ..DescriptorsCount::zero()
+                   });
+    }
+
     #[test]
     fn pool_create() {
         let (device, _) = gfx_dev_and_queue!();
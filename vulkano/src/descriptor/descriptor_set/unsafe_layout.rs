@@ -46,31 +46,41 @@ impl UnsafeDescriptorSetLayout {
     pub fn new<I>(device: Arc<Device>, descriptors: I)
                   -> Result<UnsafeDescriptorSetLayout, OomError>
         where I: IntoIterator<Item = Option<DescriptorDesc>>
+    {
+        let descriptors = descriptors
+            .into_iter()
+            .enumerate()
+            .filter_map(|(binding, desc)| desc.map(|d| (binding as u32, d)));
+        UnsafeDescriptorSetLayout::with_bindings(device, descriptors)
+    }
+
+    /// Builds a new `UnsafeDescriptorSetLayout` with the given descriptors and their binding
+    /// points.
+    ///
+    /// Contrary to `new`, the descriptors can be passed in any order and the binding points don't
+    /// need to be contiguous. Each binding point must appear only once.
+    pub fn with_bindings<I>(device: Arc<Device>, descriptors: I)
+                            -> Result<UnsafeDescriptorSetLayout, OomError>
+        where I: IntoIterator<Item = (u32, DescriptorDesc)>
     {
         let mut descriptors_count = DescriptorsCount::zero();
 
         let bindings = descriptors
             .into_iter()
-            .enumerate()
-            .filter_map(|(binding, desc)| {
-                let desc = match desc {
-                    Some(d) => d,
-                    None => return None,
-                };
-
+            .map(|(binding, desc)| {
                 // FIXME: it is not legal to pass eg. the TESSELLATION_SHADER bit when the device
                 //        doesn't have tess shaders enabled
 
                 let ty = desc.ty.ty().unwrap(); // TODO: shouldn't panic
                 descriptors_count.add_one(ty);
 
-                Some(vk::DescriptorSetLayoutBinding {
-                         binding: binding as u32,
-                         descriptorType: ty as u32,
-                         descriptorCount: desc.array_count,
-                         stageFlags: desc.stages.into(),
-                         pImmutableSamplers: ptr::null(), // FIXME: not yet implemented
-                     })
+                vk::DescriptorSetLayoutBinding {
+                    binding: binding,
+                    descriptorType: ty as u32,
+                    descriptorCount: desc.array_count,
+                    stageFlags: desc.stages.into(),
+                    pImmutableSamplers: ptr::null(), // FIXME: not yet implemented
+                }
             })
             .collect::<SmallVec<[_; 32]>>();
 
@@ -90,7 +100,8 @@ impl UnsafeDescriptorSetLayout {
             check_errors(vk.CreateDescriptorSetLayout(device.internal_object(),
                                                       &infos,
                                                       ptr::null(),
-                                                      &mut output))?;
+                                                      &mut output),
+                         "vkCreateDescriptorSetLayout")?;
             output
         };
 
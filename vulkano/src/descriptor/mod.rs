@@ -72,7 +72,7 @@
 //! let set0 = simple_descriptor_set!(&graphics_pipeline, 0, {
 //!     u_texture: &my_buffer1,
 //!     u_buffer: &my_buffer2,
-//! });
+//! }).unwrap();
 //! ```
 //!
 //! ## Passing the descriptor set when drawing
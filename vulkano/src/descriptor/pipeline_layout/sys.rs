@@ -108,7 +108,7 @@ impl<L> PipelineLayout<L>
                     return Err(PipelineLayoutCreationError::InvalidPushConstant);
                 }
 
-                if offset + size > limits.max_push_constants_size() as usize {
+                if offset + size > limits.max_push_constants_size() {
                     return Err(PipelineLayoutCreationError::MaxPushConstantsSizeExceeded);
                 }
 
@@ -157,7 +157,8 @@ impl<L> PipelineLayout<L>
             check_errors(vk.CreatePipelineLayout(device.internal_object(),
                                                  &infos,
                                                  ptr::null(),
-                                                 &mut output))?;
+                                                 &mut output),
+                         "vkCreatePipelineLayout")?;
             output
         };
 
@@ -364,6 +365,13 @@ mod tests {
         let _layout = PipelineLayout::new(&device, iter::empty(), iter::empty()).unwrap();
     }
 
+    #[test]
+    fn push_constants_only() {
+        let (device, _) = gfx_dev_and_queue!();
+        let push_constant = (0, 16, ShaderStages::all_graphics());
+        let _layout = PipelineLayout::new(&device, iter::empty(), Some(push_constant)).unwrap();
+    }
+
     #[test]
     #[should_panic]
     fn wrong_device_panic() {
@@ -91,26 +91,63 @@
 
 use fnv::FnvHasher;
 use smallvec::SmallVec;
+use std::cmp;
 use std::collections::HashMap;
 use std::collections::hash_map::Entry;
 use std::error;
+use std::ffi::CStr;
+use std::ffi::CString;
 use std::fmt;
 use std::hash::BuildHasherDefault;
+use std::iter::Empty;
 use std::mem;
 use std::ops::Deref;
+use std::os::raw::c_void;
 use std::ptr;
 use std::sync::Arc;
 use std::sync::Mutex;
 use std::sync::MutexGuard;
+use std::sync::RwLock;
 use std::sync::Weak;
-
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+
+use buffer::BufferCreationError;
+use buffer::BufferUsage;
+use buffer::sys::SparseLevel;
+use buffer::sys::UnsafeBuffer;
+use command_buffer::CommandBuffer;
 use command_buffer::pool::StandardCommandPool;
 use descriptor::descriptor_set::StdDescriptorPool;
+use format::Format;
+use image::ImageAccess;
+use image::ImageCreationError;
+use image::ImageDimensions;
+use image::ImageUsage;
+use image::MipmapsCount;
+use image::sys::ImageCreationOptions;
+use image::sys::UnsafeImage;
 use instance::Features;
 use instance::Instance;
 use instance::PhysicalDevice;
+use instrumentation::SubmissionObserver;
 use instance::QueueFamily;
+use memory::DeviceMemory;
+use memory::MemoryRequirements;
 use memory::pool::StdMemoryPool;
+use pipeline::subgroup_size::SubgroupSizeFeatures;
+use sync;
+use sync::Fence;
+use sync::FenceWaitError;
+use sync::GpuFuture;
+use sync::PipelineStages;
+use sync::SparseBindError;
+use sync::SparseBindFuture;
+use sync::SparseImageBind;
+use sync::SubmitTimelineError;
+use sync::TimelineSemaphore;
+use sync::TimelineSubmitFuture;
+use version::Version;
 
 use Error;
 use OomError;
@@ -133,6 +170,14 @@ pub struct Device {
         Mutex<HashMap<u32, Weak<StandardCommandPool>, BuildHasherDefault<FnvHasher>>>,
     features: Features,
     extensions: DeviceExtensions,
+    effective_extensions: DeviceExtensions,
+    api_version: Version,
+    subgroup_size_features: SubgroupSizeFeatures,
+    depth_clip_enable: bool,
+    // Observer registered with `set_submission_observer`. `has_observer` is true if it is `Some`,
+    // so that we don't need to lock when there is no observer.
+    observer: RwLock<Option<Arc<SubmissionObserver>>>,
+    has_observer: AtomicBool,
 }
 
 // The `StandardCommandPool` type doesn't implement Send/Sync, so we have to manually reimplement
@@ -199,6 +244,24 @@ impl Device {
             .iter()
             .map(|extension| extension.as_ptr())
             .collect::<SmallVec<[_; 16]>>();
+        let loaded_extensions: DeviceExtensions = (&extensions).into();
+
+        // The version of Vulkan usable with the device is limited both by the version requested
+        // by the instance and by the version supported by the physical device.
+        let api_version = cmp::min(phys.instance().api_version(), phys.api_version());
+        let effective_extensions =
+            loaded_extensions.union(&DeviceExtensions::promoted_to_core(api_version));
+
+        let subgroup_size_features = if effective_extensions.ext_subgroup_size_control {
+            SubgroupSizeFeatures::from_physical_device(*phys).unwrap_or_default()
+        } else {
+            SubgroupSizeFeatures::default()
+        };
+
+        // `depthClipEnable` is the only feature of `VK_EXT_depth_clip_enable`, but the physical
+        // device may still not support it.
+        let depth_clip_enable = effective_extensions.ext_depth_clip_enable &&
+            depth_clip_enable_supported(*phys);
 
         // device creation
         let device = unsafe {
@@ -261,9 +324,169 @@ impl Device {
                 features
             };
 
+            // The features of some extensions must be enabled through structs chained to
+            // `pNext`. These features are guaranteed to be supported whenever the corresponding
+            // extension is, so we enable them automatically when the extension is requested.
+            // The same goes for the versions of Vulkan in which these extensions were promoted
+            // to core, where the features are mandatory.
+            let mut next: *const c_void = ptr::null();
+
+            let mut timeline_semaphore_features = vk::PhysicalDeviceTimelineSemaphoreFeaturesKHR {
+                sType: vk::STRUCTURE_TYPE_PHYSICAL_DEVICE_TIMELINE_SEMAPHORE_FEATURES_KHR,
+                pNext: ptr::null_mut(),
+                timelineSemaphore: vk::TRUE,
+            };
+            if effective_extensions.khr_timeline_semaphore {
+                timeline_semaphore_features.pNext = next as *mut _;
+                next = &timeline_semaphore_features as *const _ as *const _;
+            }
+
+            let mut synchronization2_features = vk::PhysicalDeviceSynchronization2FeaturesKHR {
+                sType: vk::STRUCTURE_TYPE_PHYSICAL_DEVICE_SYNCHRONIZATION_2_FEATURES_KHR,
+                pNext: ptr::null_mut(),
+                synchronization2: vk::TRUE,
+            };
+            if effective_extensions.khr_synchronization2 {
+                synchronization2_features.pNext = next as *mut _;
+                next = &synchronization2_features as *const _ as *const _;
+            }
+
+            let mut dynamic_rendering_features = vk::PhysicalDeviceDynamicRenderingFeaturesKHR {
+                sType: vk::STRUCTURE_TYPE_PHYSICAL_DEVICE_DYNAMIC_RENDERING_FEATURES_KHR,
+                pNext: ptr::null_mut(),
+                dynamicRendering: vk::TRUE,
+            };
+            if effective_extensions.khr_dynamic_rendering {
+                dynamic_rendering_features.pNext = next as *mut _;
+                next = &dynamic_rendering_features as *const _ as *const _;
+            }
+
+            // Makes the point size default to 1.0 when it isn't written, which graphics pipelines
+            // rely on when the extension is enabled.
+            let mut maintenance5_features = vk::PhysicalDeviceMaintenance5FeaturesKHR {
+                sType: vk::STRUCTURE_TYPE_PHYSICAL_DEVICE_MAINTENANCE_5_FEATURES_KHR,
+                pNext: ptr::null_mut(),
+                maintenance5: vk::TRUE,
+            };
+            if effective_extensions.khr_maintenance5 {
+                maintenance5_features.pNext = next as *mut _;
+                next = &maintenance5_features as *const _ as *const _;
+            }
+
+            let mut private_data_features = vk::PhysicalDevicePrivateDataFeaturesEXT {
+                sType: vk::STRUCTURE_TYPE_PHYSICAL_DEVICE_PRIVATE_DATA_FEATURES_EXT,
+                pNext: ptr::null_mut(),
+                privateData: vk::TRUE,
+            };
+            if effective_extensions.ext_private_data {
+                private_data_features.pNext = next as *mut _;
+                next = &private_data_features as *const _ as *const _;
+            }
+
+            // Contrary to the other features above, `computeFullSubgroups` is optional even when
+            // the extension is supported. Only the features that are supported are enabled.
+            let mut subgroup_size_control_features =
+                vk::PhysicalDeviceSubgroupSizeControlFeaturesEXT {
+                    sType: vk::STRUCTURE_TYPE_PHYSICAL_DEVICE_SUBGROUP_SIZE_CONTROL_FEATURES_EXT,
+                    pNext: ptr::null_mut(),
+                    subgroupSizeControl: if subgroup_size_features.subgroup_size_control {
+                        vk::TRUE
+                    } else {
+                        vk::FALSE
+                    },
+                    computeFullSubgroups: if subgroup_size_features.compute_full_subgroups {
+                        vk::TRUE
+                    } else {
+                        vk::FALSE
+                    },
+                };
+            if subgroup_size_features != SubgroupSizeFeatures::default() {
+                subgroup_size_control_features.pNext = next as *mut _;
+                next = &subgroup_size_control_features as *const _ as *const _;
+            }
+
+            let mut image_view_min_lod_features = vk::PhysicalDeviceImageViewMinLodFeaturesEXT {
+                sType: vk::STRUCTURE_TYPE_PHYSICAL_DEVICE_IMAGE_VIEW_MIN_LOD_FEATURES_EXT,
+                pNext: ptr::null_mut(),
+                minLod: vk::TRUE,
+            };
+            if effective_extensions.ext_image_view_min_lod {
+                image_view_min_lod_features.pNext = next as *mut _;
+                next = &image_view_min_lod_features as *const _ as *const _;
+            }
+
+            let mut device_generated_commands_features =
+                vk::PhysicalDeviceDeviceGeneratedCommandsFeaturesNV {
+                    sType:
+                        vk::STRUCTURE_TYPE_PHYSICAL_DEVICE_DEVICE_GENERATED_COMMANDS_FEATURES_NV,
+                    pNext: ptr::null_mut(),
+                    deviceGeneratedCommands: vk::TRUE,
+                };
+            if effective_extensions.nv_device_generated_commands {
+                device_generated_commands_features.pNext = next as *mut _;
+                next = &device_generated_commands_features as *const _ as *const _;
+            }
+
+            // Unlike the other extensions above, the features of `VK_EXT_extended_dynamic_state`
+            // were made unconditional when it was promoted to core, and its features struct can
+            // only be passed when the extension itself is enabled.
+            let mut extended_dynamic_state_features =
+                vk::PhysicalDeviceExtendedDynamicStateFeaturesEXT {
+                    sType: vk::STRUCTURE_TYPE_PHYSICAL_DEVICE_EXTENDED_DYNAMIC_STATE_FEATURES_EXT,
+                    pNext: ptr::null_mut(),
+                    extendedDynamicState: vk::TRUE,
+                };
+            if loaded_extensions.ext_extended_dynamic_state {
+                extended_dynamic_state_features.pNext = next as *mut _;
+                next = &extended_dynamic_state_features as *const _ as *const _;
+            }
+
+            let mut depth_clip_enable_features = vk::PhysicalDeviceDepthClipEnableFeaturesEXT {
+                sType: vk::STRUCTURE_TYPE_PHYSICAL_DEVICE_DEPTH_CLIP_ENABLE_FEATURES_EXT,
+                pNext: ptr::null_mut(),
+                depthClipEnable: vk::TRUE,
+            };
+            if depth_clip_enable {
+                depth_clip_enable_features.pNext = next as *mut _;
+                next = &depth_clip_enable_features as *const _ as *const _;
+            }
+
+            let mut swapchain_maintenance1_features =
+                vk::PhysicalDeviceSwapchainMaintenance1FeaturesEXT {
+                    sType: vk::STRUCTURE_TYPE_PHYSICAL_DEVICE_SWAPCHAIN_MAINTENANCE_1_FEATURES_EXT,
+                    pNext: ptr::null_mut(),
+                    swapchainMaintenance1: vk::TRUE,
+                };
+            if effective_extensions.ext_swapchain_maintenance1 {
+                swapchain_maintenance1_features.pNext = next as *mut _;
+                next = &swapchain_maintenance1_features as *const _ as *const _;
+            }
+
+            let mut shader_object_features = vk::PhysicalDeviceShaderObjectFeaturesEXT {
+                sType: vk::STRUCTURE_TYPE_PHYSICAL_DEVICE_SHADER_OBJECT_FEATURES_EXT,
+                pNext: ptr::null_mut(),
+                shaderObject: vk::TRUE,
+            };
+            if effective_extensions.ext_shader_object {
+                shader_object_features.pNext = next as *mut _;
+                next = &shader_object_features as *const _ as *const _;
+            }
+
+            // `cooperativeMatrixRobustBufferAccess` is optional, and is left disabled.
+            let mut cooperative_matrix_features = vk::PhysicalDeviceCooperativeMatrixFeaturesKHR {
+                sType: vk::STRUCTURE_TYPE_PHYSICAL_DEVICE_COOPERATIVE_MATRIX_FEATURES_KHR,
+                pNext: ptr::null_mut(),
+                cooperativeMatrix: vk::TRUE,
+                cooperativeMatrixRobustBufferAccess: vk::FALSE,
+            };
+            if effective_extensions.khr_cooperative_matrix {
+                cooperative_matrix_features.pNext = next as *mut _;
+                next = &cooperative_matrix_features as *const _ as *const _;
+            }
+
             let infos = vk::DeviceCreateInfo {
                 sType: vk::STRUCTURE_TYPE_DEVICE_CREATE_INFO,
-                pNext: ptr::null(),
+                pNext: next,
                 flags: 0, // reserved
                 queueCreateInfoCount: queues.len() as u32,
                 pQueueCreateInfos: queues.as_ptr(),
@@ -278,15 +501,23 @@ impl Device {
             check_errors(vk_i.CreateDevice(phys.internal_object(),
                                            &infos,
                                            ptr::null(),
-                                           &mut output))?;
+                                           &mut output),
+                         "vkCreateDevice")?;
             output
         };
 
         // loading the function pointers of the newly-created device
+        // If an extension hasn't been enabled but has been promoted to core, the suffixed name
+        // of its functions isn't available and we load the core name instead.
         let vk = vk::DevicePointers::load(|name| unsafe {
-                                              vk_i.GetDeviceProcAddr(device, name.as_ptr()) as
-                                                  *const _
-                                          });
+            let ptr = vk_i.GetDeviceProcAddr(device, name.as_ptr()) as *const c_void;
+            match core_name(name) {
+                Some(ref core) if ptr.is_null() => {
+                    vk_i.GetDeviceProcAddr(device, core.as_ptr()) as *const _
+                },
+                _ => ptr,
+            }
+        });
 
         let device = Arc::new(Device {
                                   instance: phys.instance().clone(),
@@ -297,7 +528,13 @@ impl Device {
                                   standard_descriptor_pool: Mutex::new(Weak::new()),
                                   standard_command_pools: Mutex::new(Default::default()),
                                   features: requested_features.clone(),
-                                  extensions: (&extensions).into(),
+                                  extensions: loaded_extensions,
+                                  effective_extensions: effective_extensions,
+                                  api_version: api_version,
+                                  subgroup_size_features: subgroup_size_features,
+                                  depth_clip_enable: depth_clip_enable,
+                                  observer: RwLock::new(None),
+                                  has_observer: AtomicBool::new(false),
                               });
 
         // Iterator for the produced queues.
@@ -328,7 +565,7 @@ impl Device {
     /// while this function is waiting.
     ///
     pub unsafe fn wait(&self) -> Result<(), OomError> {
-        check_errors(self.vk.DeviceWaitIdle(self.device))?;
+        check_errors(self.vk.DeviceWaitIdle(self.device), "vkDeviceWaitIdle")?;
         Ok(())
     }
 
@@ -356,6 +593,70 @@ impl Device {
         &self.extensions
     }
 
+    /// Returns the list of extensions whose functionalities can be used with this device.
+    ///
+    /// This contains the extensions that have been loaded, plus the extensions that are part of
+    /// the core API in the version returned by `api_version()`.
+    #[inline]
+    pub fn effective_extensions(&self) -> &DeviceExtensions {
+        &self.effective_extensions
+    }
+
+    /// Returns the features of `ext_subgroup_size_control` that are enabled on this device.
+    ///
+    /// They are enabled automatically when the extension is requested and the physical device
+    /// supports them. None of them is enabled if the extension isn't.
+    #[inline]
+    pub fn subgroup_size_features(&self) -> SubgroupSizeFeatures {
+        self.subgroup_size_features
+    }
+
+    /// Returns true if the `depthClipEnable` feature of `ext_depth_clip_enable` is enabled on
+    /// this device.
+    ///
+    /// It is enabled automatically when the extension is requested and the physical device
+    /// supports it.
+    #[inline]
+    pub fn depth_clip_enable(&self) -> bool {
+        self.depth_clip_enable
+    }
+
+    /// Returns the version of Vulkan that can be used with this device.
+    ///
+    /// This is the minimum between the version requested by the instance and the version
+    /// supported by the physical device.
+    #[inline]
+    pub fn api_version(&self) -> Version {
+        self.api_version
+    }
+
+    /// Registers an observer that is notified about the work sent to this device, for example in
+    /// order to forward it to a profiler. Replaces the previous observer, if any. Pass `None` to
+    /// remove the observer.
+    ///
+    /// See the [`instrumentation`](../instrumentation/index.html) module for more information.
+    pub fn set_submission_observer(&self, observer: Option<Arc<SubmissionObserver>>) {
+        let mut current = self.observer.write().unwrap();
+        self.has_observer.store(observer.is_some(), Ordering::Release);
+        *current = observer;
+    }
+
+    /// Returns the observer registered with `set_submission_observer`, if any.
+    #[inline]
+    pub fn submission_observer(&self) -> Option<Arc<SubmissionObserver>> {
+        if !self.has_observer.load(Ordering::Acquire) {
+            return None;
+        }
+
+        self.observer.read().unwrap().clone()
+    }
+
+    // Returns true if an observer is registered. Cheaper than `submission_observer`.
+    #[inline]
+    pub(crate) fn has_submission_observer(&self) -> bool {
+        self.has_observer.load(Ordering::Acquire)
+    }
+
     /// Returns the standard memory pool used by default if you don't provide any other pool.
     pub fn standard_pool(me: &Arc<Self>) -> Arc<StdMemoryPool> {
         let mut pool = me.standard_pool.lock().unwrap();
@@ -411,6 +712,58 @@ impl Device {
             },
         }
     }
+
+    /// Returns the memory requirements of a buffer of `size` bytes with the given usage, without
+    /// having to create the buffer yourself.
+    ///
+    /// The returned requirements are the same as the ones of a buffer created with
+    /// `UnsafeBuffer::new` with the same parameters, including the additional alignment that
+    /// vulkano enforces for uniform, storage and texel buffers. This is useful if you want to
+    /// pack multiple resources into one allocation before creating them.
+    ///
+    /// > **Note**: Vulkan doesn't provide a way to query this without an object, so a temporary
+    /// > buffer is created and destroyed.
+    pub fn buffer_memory_requirements(me: &Arc<Self>, usage: BufferUsage, size: usize)
+                                      -> Result<MemoryRequirements, BufferCreationError> {
+        unsafe {
+            let (_buffer, reqs) = UnsafeBuffer::new(me.clone(),
+                                                    size,
+                                                    usage,
+                                                    sync::Sharing::Exclusive::<Empty<_>>,
+                                                    SparseLevel::none())?;
+            Ok(reqs)
+        }
+    }
+
+    /// Returns the memory requirements of an image created with the given parameters, without
+    /// having to create the image yourself.
+    ///
+    /// The parameters have the same meaning as the ones of `UnsafeImage::new`. This function
+    /// returns the same errors as `UnsafeImage::new` if they aren't supported.
+    ///
+    /// > **Note**: Vulkan doesn't provide a way to query this without an object, so a temporary
+    /// > image is created and destroyed.
+    pub fn image_memory_requirements<Mi>(me: &Arc<Self>, usage: ImageUsage, format: Format,
+                                         dimensions: ImageDimensions, num_samples: u32,
+                                         mipmaps: Mi, linear_tiling: bool)
+                                         -> Result<MemoryRequirements, ImageCreationError>
+        where Mi: Into<MipmapsCount>
+    {
+        unsafe {
+            let (_image, reqs) = UnsafeImage::new(me.clone(),
+                                                  usage,
+                                                  format,
+                                                  dimensions,
+                                                  num_samples,
+                                                  mipmaps,
+                                                  sync::Sharing::Exclusive::<Empty<_>>,
+                                                  ImageCreationOptions {
+                                                      linear_tiling: linear_tiling,
+                                                      ..ImageCreationOptions::none()
+                                                  })?;
+            Ok(reqs)
+        }
+    }
 }
 
 impl fmt::Debug for Device {
@@ -625,16 +978,118 @@ impl Queue {
 
     /// Waits until all work on this queue has finished.
     ///
+    /// This is the same as `wait_idle()`.
+    ///
     /// Just like `Device::wait()`, you shouldn't have to call this function in a typical program.
     #[inline]
     pub fn wait(&self) -> Result<(), OomError> {
+        self.wait_idle()
+    }
+
+    /// Waits until all work that has been submitted to this queue so far has finished.
+    ///
+    /// Contrary to `vkQueueWaitIdle`, this function doesn't prevent other threads from using the
+    /// queue while it waits. It submits an empty batch that signals a fence, and waits for that
+    /// fence with the queue unlocked. Work submitted by other threads in the meantime isn't
+    /// waited upon.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the device has been lost.
+    ///
+    pub fn wait_idle(&self) -> Result<(), OomError> {
+        let fence = Fence::new(self.device.clone())?;
+
         unsafe {
             let vk = self.device.pointers();
+            // The queue must only be locked for the duration of the `vkQueue*` call.
             let queue = self.queue.lock().unwrap();
-            check_errors(vk.QueueWaitIdle(*queue))?;
-            Ok(())
+            check_errors(vk.QueueSubmit(*queue, 0, ptr::null(), fence.internal_object()),
+                         "vkQueueSubmit")?;
+        }
+
+        match fence.wait(None) {
+            Ok(()) => Ok(()),
+            Err(FenceWaitError::OomError(err)) => Err(err),
+            Err(err) => panic!("unexpected error: {:?}", err),
         }
     }
+
+    /// Submits a command buffer to the queue with `vkQueueSubmit2`, waiting on and signaling
+    /// timeline semaphores.
+    ///
+    /// Each element of `wait` blocks the given stages of the command buffer until the counter of
+    /// the semaphore is superior or equal to the given value. Each element of `signal` sets the
+    /// counter of the semaphore to the given value once the command buffer has finished
+    /// executing. Signal values must be strictly superior to the `pending_value()` of their
+    /// semaphore.
+    ///
+    /// The submission happens immediately. The returned future represents the execution of the
+    /// command buffer and can be chained with other futures, for example to signal a fence.
+    ///
+    /// Requires the `VK_KHR_synchronization2` extension to be enabled on the device.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the command buffer or one of the semaphores doesn't belong to the same device
+    ///   as the queue.
+    ///
+    pub fn submit_timeline<Cb>(me: &Arc<Queue>, command_buffer: Cb,
+                               wait: &[(Arc<TimelineSemaphore>, u64, PipelineStages)],
+                               signal: &[(Arc<TimelineSemaphore>, u64)])
+                               -> Result<TimelineSubmitFuture<Cb>, SubmitTimelineError>
+        where Cb: CommandBuffer + 'static
+    {
+        sync::submit_timeline(me.clone(), command_buffer, wait, signal)
+    }
+
+    /// Binds or unbinds memory to regions of a sparse image, tile by tile.
+    ///
+    /// Each element of `binds` describes a region of a mipmap level of the image, in texels, and
+    /// the memory to bind to it. The regions must be aligned to the granularity returned by
+    /// `UnsafeImage::sparse_memory_requirements`, and the memory must satisfy the memory
+    /// requirements of the image. Each tile uses `alignment` bytes of memory.
+    ///
+    /// The operation is submitted when the returned future is flushed, after the submission of
+    /// `previous`, and sees its effects. Futures chained after it wait until the memory is bound.
+    /// Reading a region that isn't bound returns undefined values, unless the device guarantees
+    /// that non-resident reads return zero.
+    ///
+    /// The memory of the mip tail must be bound separately with `bind_sparse_image_mip_tail`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the image or the memory doesn't belong to the same device as the queue.
+    ///
+    #[inline]
+    pub fn bind_sparse_image_memory<F, I>(me: &Arc<Queue>, previous: F, image: I,
+                                          binds: Vec<SparseImageBind>)
+                                          -> Result<SparseBindFuture<F, I>, SparseBindError>
+        where F: GpuFuture,
+              I: ImageAccess
+    {
+        sync::bind_sparse_image_memory(previous, me.clone(), image, binds)
+    }
+
+    /// Binds `memory` to the mip tail of a sparse image, starting at `memory_offset` bytes, after
+    /// `previous`.
+    ///
+    /// The memory must contain at least `sparse_image_mip_tail_size(&image)` bytes from the
+    /// offset.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the image or the memory doesn't belong to the same device as the queue.
+    ///
+    #[inline]
+    pub fn bind_sparse_image_mip_tail<F, I>(me: &Arc<Queue>, previous: F, image: I,
+                                            memory: Arc<DeviceMemory>, memory_offset: usize)
+                                            -> Result<SparseBindFuture<F, I>, SparseBindError>
+        where F: GpuFuture,
+              I: ImageAccess
+    {
+        sync::bind_sparse_image_mip_tail(previous, me.clone(), image, memory, memory_offset)
+    }
 }
 
 unsafe impl SynchronizedVulkanObject for Queue {
@@ -646,14 +1101,188 @@ unsafe impl SynchronizedVulkanObject for Queue {
     }
 }
 
+// Returns true if the physical device supports the `depthClipEnable` feature. Always returns false
+// if the `khr_get_physical_device_properties2` extension isn't enabled on the instance, since the
+// feature can't be queried.
+fn depth_clip_enable_supported(phys: PhysicalDevice) -> bool {
+    let instance = phys.instance();
+    if !instance.loaded_extensions().khr_get_physical_device_properties2 {
+        return false;
+    }
+
+    unsafe {
+        let mut depth_clip = vk::PhysicalDeviceDepthClipEnableFeaturesEXT {
+            sType: vk::STRUCTURE_TYPE_PHYSICAL_DEVICE_DEPTH_CLIP_ENABLE_FEATURES_EXT,
+            pNext: ptr::null_mut(),
+            depthClipEnable: vk::FALSE,
+        };
+
+        let mut output = vk::PhysicalDeviceFeatures2KHR {
+            sType: vk::STRUCTURE_TYPE_PHYSICAL_DEVICE_FEATURES_2_KHR,
+            pNext: &mut depth_clip as *mut _ as *const _,
+            features: mem::uninitialized(),
+        };
+
+        let vk = instance.pointers();
+        vk.GetPhysicalDeviceFeatures2KHR(phys.internal_object(), &mut output);
+        depth_clip.depthClipEnable != vk::FALSE
+    }
+}
+
+// Returns the name of the core function corresponding to an extension function, by removing the
+// vendor suffix. Returns `None` if the name doesn't have a suffix.
+fn core_name(name: &CStr) -> Option<CString> {
+    let bytes = name.to_bytes();
+    let suffixes: [&[u8]; 2] = [b"KHR", b"EXT"];
+
+    suffixes
+        .iter()
+        .find(|suffix| bytes.ends_with(suffix))
+        .map(|suffix| CString::new(&bytes[.. bytes.len() - suffix.len()]).unwrap())
+}
+
 #[cfg(test)]
 mod tests {
+    use buffer::BufferUsage;
+    use command_buffer::AutoCommandBufferBuilder;
+    use command_buffer::CommandBuffer;
+    use command_buffer::pool::CommandPoolTrimError;
+    use command_buffer::pool::UnsafeCommandPool;
     use device::Device;
     use device::DeviceCreationError;
     use device::DeviceExtensions;
+    use device::Queue;
     use features::Features;
     use instance;
+    use std::ffi::CString;
     use std::sync::Arc;
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::Duration;
+    use super::core_name;
+    use sync::GpuFuture;
+    use sync::PipelineStages;
+    use sync::TimelineSemaphore;
+    use sync::TimelineSemaphoreCreationError;
+    use version::Version;
+
+    #[test]
+    fn buffer_memory_requirements() {
+        let (device, _) = gfx_dev_and_queue!();
+        let reqs = Device::buffer_memory_requirements(&device, BufferUsage::all(), 128).unwrap();
+
+        assert!(reqs.size >= 128);
+        assert_ne!(reqs.memory_type_ids().len(), 0);
+        for id in reqs.memory_type_ids() {
+            let ty = device.physical_device().memory_type_by_id(id).unwrap();
+            assert!(reqs.supports_memory_type(ty));
+        }
+    }
+
+    #[test]
+    fn api_version() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        assert!(device.api_version() <= device.physical_device().api_version());
+        assert!(device.api_version() <= device.instance().api_version());
+
+        // Extensions that are part of the core API in the device's version must be usable
+        // whether or not they have been enabled.
+        let promoted = DeviceExtensions::promoted_to_core(device.api_version());
+        assert_eq!(device.effective_extensions().intersection(&promoted), promoted);
+        assert_eq!(device.effective_extensions().intersection(device.loaded_extensions()),
+                   *device.loaded_extensions());
+    }
+
+    // Promoted functionalities are usable through the extension on a Vulkan 1.0 instance.
+    #[test]
+    fn promoted_extension_path() {
+        let instance = instance!();
+        let physical = match instance::PhysicalDevice::enumerate(&instance).next() {
+            Some(p) => p,
+            None => return,
+        };
+        let family = physical.queue_families().next().unwrap();
+
+        let supported = DeviceExtensions::supported_by_device(&physical);
+        let extensions = DeviceExtensions {
+            khr_maintenance1: supported.khr_maintenance1,
+            khr_timeline_semaphore: supported.khr_timeline_semaphore,
+            ..DeviceExtensions::none()
+        };
+        let device = match Device::new(&physical, &Features::none(), &extensions,
+                                       Some((family, 1.0))) {
+            Ok((d, _)) => d,
+            Err(_) => return,
+        };
+
+        assert_eq!(device.api_version(), Version { major: 1, minor: 0, patch: 0 });
+        assert_eq!(*device.effective_extensions(), extensions);
+
+        let pool = UnsafeCommandPool::new(device.clone(), family, false, false).unwrap();
+        match pool.trim() {
+            Ok(()) => assert!(extensions.khr_maintenance1),
+            Err(CommandPoolTrimError::Maintenance1ExtensionNotEnabled) => {
+                assert!(!extensions.khr_maintenance1)
+            },
+        }
+
+        match TimelineSemaphore::new(device.clone(), 0) {
+            Ok(_) => assert!(extensions.khr_timeline_semaphore),
+            Err(TimelineSemaphoreCreationError::TimelineSemaphoreExtensionNotEnabled) => {
+                assert!(!extensions.khr_timeline_semaphore)
+            },
+            Err(err) => panic!("{:?}", err),
+        }
+    }
+
+    // Promoted functionalities are usable without their extension on a Vulkan 1.2 instance and
+    // device.
+    #[test]
+    fn promoted_core_path() {
+        let version = Version { major: 1, minor: 2, patch: 0 };
+        let extensions = instance::InstanceExtensions::none();
+        let instance = match instance::Instance::with_api_version(None, &extensions, None,
+                                                                  version) {
+            Ok(i) => i,
+            Err(_) => return,
+        };
+        let physical = match instance::PhysicalDevice::enumerate(&instance).next() {
+            Some(p) => p,
+            None => return,
+        };
+        if physical.api_version() < version {
+            return;
+        }
+        let family = physical.queue_families().next().unwrap();
+
+        let device = match Device::new(&physical, &Features::none(), &DeviceExtensions::none(),
+                                       Some((family, 1.0))) {
+            Ok((d, _)) => d,
+            Err(_) => return,
+        };
+
+        assert!(device.api_version() >= version);
+        assert_eq!(*device.loaded_extensions(), DeviceExtensions::none());
+        assert!(device.effective_extensions().khr_maintenance1);
+        assert!(device.effective_extensions().khr_timeline_semaphore);
+
+        let pool = UnsafeCommandPool::new(device.clone(), family, false, false).unwrap();
+        assert_eq!(pool.trim(), Ok(()));
+
+        let semaphore = TimelineSemaphore::new(device.clone(), 3).unwrap();
+        assert_eq!(semaphore.value().unwrap(), 3);
+    }
+
+    #[test]
+    fn core_names() {
+        let name = |s: &str| CString::new(s).unwrap();
+
+        assert_eq!(core_name(&name("vkQueueSubmit2KHR")), Some(name("vkQueueSubmit2")));
+        assert_eq!(core_name(&name("vkCmdSetDepthTestEnableEXT")),
+                   Some(name("vkCmdSetDepthTestEnable")));
+        assert_eq!(core_name(&name("vkQueueSubmit")), None);
+    }
 
     #[test]
     fn one_ref() {
@@ -706,6 +1335,53 @@ mod tests {
         };
     }
 
+    #[test]
+    fn wait_idle_doesnt_block_submissions() {
+        let (device, queue) =
+            gfx_dev_and_queue!(extensions: [khr_timeline_semaphore, khr_synchronization2]);
+        let semaphore = Arc::new(TimelineSemaphore::new(device.clone(), 0).unwrap());
+
+        // The queue stays busy until the semaphore is signaled from the host.
+        let cb = AutoCommandBufferBuilder::new(device.clone(), queue.family())
+            .unwrap()
+            .build()
+            .unwrap();
+        let stages = PipelineStages { all_commands: true, ..PipelineStages::none() };
+        let busy = Queue::submit_timeline(&queue, cb, &[(semaphore.clone(), 1, stages)], &[])
+            .unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        let waiter = {
+            let queue = queue.clone();
+            thread::spawn(move || {
+                queue.wait_idle().unwrap();
+                tx.send(()).unwrap();
+            })
+        };
+        // Gives the other thread the time to start waiting. The checks below don't depend on it.
+        thread::sleep(Duration::from_millis(20));
+
+        // The submission goes through while the queue is busy, and therefore while `wait_idle`
+        // can't have returned yet.
+        let cb = AutoCommandBufferBuilder::new(device.clone(), queue.family())
+            .unwrap()
+            .build()
+            .unwrap();
+        let future = cb.execute(queue.clone())
+            .unwrap()
+            .then_signal_fence_and_flush()
+            .unwrap();
+        assert_eq!(semaphore.value().unwrap(), 0);
+        assert_eq!(rx.try_recv(), Err(mpsc::TryRecvError::Empty));
+
+        // `wait_idle` returns once the queue is no longer busy.
+        semaphore.signal(1).unwrap();
+        rx.recv().unwrap();
+        waiter.join().unwrap();
+        future.wait(None).unwrap();
+        drop(busy);
+    }
+
     #[test]
     fn priority_out_of_range() {
         let instance = instance!();
@@ -552,6 +552,157 @@ formats! {
     ASTC_12x12SrgbBlock => FORMAT_ASTC_12x12_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
 }
 
+impl Format {
+    /// Returns the dimensions in texels of a block of this format.
+    ///
+    /// Block-compressed formats store their data in blocks of several texels. For all the other
+    /// formats, this returns `[1, 1]`.
+    pub fn block_dimensions(&self) -> [u32; 2] {
+        match *self {
+            Format::ASTC_5x4UnormBlock | Format::ASTC_5x4SrgbBlock => [5, 4],
+            Format::ASTC_5x5UnormBlock | Format::ASTC_5x5SrgbBlock => [5, 5],
+            Format::ASTC_6x5UnormBlock | Format::ASTC_6x5SrgbBlock => [6, 5],
+            Format::ASTC_6x6UnormBlock | Format::ASTC_6x6SrgbBlock => [6, 6],
+            Format::ASTC_8x5UnormBlock | Format::ASTC_8x5SrgbBlock => [8, 5],
+            Format::ASTC_8x6UnormBlock | Format::ASTC_8x6SrgbBlock => [8, 6],
+            Format::ASTC_8x8UnormBlock | Format::ASTC_8x8SrgbBlock => [8, 8],
+            Format::ASTC_10x5UnormBlock | Format::ASTC_10x5SrgbBlock => [10, 5],
+            Format::ASTC_10x6UnormBlock | Format::ASTC_10x6SrgbBlock => [10, 6],
+            Format::ASTC_10x8UnormBlock | Format::ASTC_10x8SrgbBlock => [10, 8],
+            Format::ASTC_10x10UnormBlock | Format::ASTC_10x10SrgbBlock => [10, 10],
+            Format::ASTC_12x10UnormBlock | Format::ASTC_12x10SrgbBlock => [12, 10],
+            Format::ASTC_12x12UnormBlock | Format::ASTC_12x12SrgbBlock => [12, 12],
+            f if f.ty() == FormatTy::Compressed => [4, 4],
+            _ => [1, 1],
+        }
+    }
+
+    /// Returns the size in bytes of a block of this format, as described by `block_dimensions`.
+    ///
+    /// For formats that aren't compressed, this is the same as `size()`. Returns `None` if the
+    /// size is irrelevant (for example for combined depth-stencil formats).
+    pub fn block_size(&self) -> Option<usize> {
+        match *self {
+            Format::BC1_RGBUnormBlock | Format::BC1_RGBSrgbBlock |
+            Format::BC1_RGBAUnormBlock | Format::BC1_RGBASrgbBlock |
+            Format::BC4UnormBlock | Format::BC4SnormBlock |
+            Format::ETC2_R8G8B8UnormBlock | Format::ETC2_R8G8B8SrgbBlock |
+            Format::ETC2_R8G8B8A1UnormBlock | Format::ETC2_R8G8B8A1SrgbBlock |
+            Format::EAC_R11UnormBlock | Format::EAC_R11SnormBlock => Some(8),
+            f if f.ty() == FormatTy::Compressed => Some(16),
+            f => f.size(),
+        }
+    }
+
+    /// Returns the number of locations of a shader interface consumed by one element of this
+    /// format.
+    ///
+    /// A location holds up to four 32-bit components, therefore 64-bit formats with three or four
+    /// components consume two locations. All the other formats consume one location.
+    #[inline]
+    pub fn num_locations(&self) -> u32 {
+        match *self {
+            Format::R64G64B64Uint | Format::R64G64B64Sint | Format::R64G64B64Sfloat |
+            Format::R64G64B64A64Uint | Format::R64G64B64A64Sint | Format::R64G64B64A64Sfloat => 2,
+            _ => 1,
+        }
+    }
+
+    /// Returns true if the format stores its color components in the sRGB color space.
+    ///
+    /// When sampling from or rendering to such a format, the implementation automatically
+    /// converts between sRGB and linear values.
+    #[inline]
+    pub fn is_srgb(&self) -> bool {
+        match self.srgb_pair() {
+            Some((_, srgb)) => srgb == *self,
+            None => false,
+        }
+    }
+
+    /// Returns the sRGB format that has the same memory layout as this format.
+    ///
+    /// Returns `self` if the format is already an sRGB format, and `None` if there is no such
+    /// format. Viewing the same data through both formats only changes whether the values are
+    /// converted from sRGB to linear when they are read.
+    #[inline]
+    pub fn srgb_equivalent(&self) -> Option<Format> {
+        self.srgb_pair().map(|(_, srgb)| srgb)
+    }
+
+    /// Returns the `Unorm` format that has the same memory layout as this format.
+    ///
+    /// Returns `self` if the format is a `Unorm` format that has an sRGB equivalent, and `None` if
+    /// the format has no sRGB equivalent.
+    #[inline]
+    pub fn unorm_equivalent(&self) -> Option<Format> {
+        self.srgb_pair().map(|(unorm, _)| unorm)
+    }
+
+    // Returns the `(unorm, srgb)` pair of formats this format belongs to, if any.
+    fn srgb_pair(&self) -> Option<(Format, Format)> {
+        match *self {
+            Format::R8Unorm | Format::R8Srgb => Some((Format::R8Unorm, Format::R8Srgb)),
+            Format::R8G8Unorm | Format::R8G8Srgb => Some((Format::R8G8Unorm, Format::R8G8Srgb)),
+            Format::R8G8B8Unorm | Format::R8G8B8Srgb => Some((Format::R8G8B8Unorm, Format::R8G8B8Srgb)),
+            Format::B8G8R8Unorm | Format::B8G8R8Srgb => Some((Format::B8G8R8Unorm, Format::B8G8R8Srgb)),
+            Format::R8G8B8A8Unorm | Format::R8G8B8A8Srgb => Some((Format::R8G8B8A8Unorm, Format::R8G8B8A8Srgb)),
+            Format::B8G8R8A8Unorm | Format::B8G8R8A8Srgb => Some((Format::B8G8R8A8Unorm, Format::B8G8R8A8Srgb)),
+            Format::A8B8G8R8UnormPack32 | Format::A8B8G8R8SrgbPack32 => Some((Format::A8B8G8R8UnormPack32, Format::A8B8G8R8SrgbPack32)),
+            Format::BC1_RGBUnormBlock | Format::BC1_RGBSrgbBlock => Some((Format::BC1_RGBUnormBlock, Format::BC1_RGBSrgbBlock)),
+            Format::BC1_RGBAUnormBlock | Format::BC1_RGBASrgbBlock => Some((Format::BC1_RGBAUnormBlock, Format::BC1_RGBASrgbBlock)),
+            Format::BC2UnormBlock | Format::BC2SrgbBlock => Some((Format::BC2UnormBlock, Format::BC2SrgbBlock)),
+            Format::BC3UnormBlock | Format::BC3SrgbBlock => Some((Format::BC3UnormBlock, Format::BC3SrgbBlock)),
+            Format::BC7UnormBlock | Format::BC7SrgbBlock => Some((Format::BC7UnormBlock, Format::BC7SrgbBlock)),
+            Format::ETC2_R8G8B8UnormBlock | Format::ETC2_R8G8B8SrgbBlock => Some((Format::ETC2_R8G8B8UnormBlock, Format::ETC2_R8G8B8SrgbBlock)),
+            Format::ETC2_R8G8B8A1UnormBlock | Format::ETC2_R8G8B8A1SrgbBlock => Some((Format::ETC2_R8G8B8A1UnormBlock, Format::ETC2_R8G8B8A1SrgbBlock)),
+            Format::ETC2_R8G8B8A8UnormBlock | Format::ETC2_R8G8B8A8SrgbBlock => Some((Format::ETC2_R8G8B8A8UnormBlock, Format::ETC2_R8G8B8A8SrgbBlock)),
+            Format::ASTC_4x4UnormBlock | Format::ASTC_4x4SrgbBlock => Some((Format::ASTC_4x4UnormBlock, Format::ASTC_4x4SrgbBlock)),
+            Format::ASTC_5x4UnormBlock | Format::ASTC_5x4SrgbBlock => Some((Format::ASTC_5x4UnormBlock, Format::ASTC_5x4SrgbBlock)),
+            Format::ASTC_5x5UnormBlock | Format::ASTC_5x5SrgbBlock => Some((Format::ASTC_5x5UnormBlock, Format::ASTC_5x5SrgbBlock)),
+            Format::ASTC_6x5UnormBlock | Format::ASTC_6x5SrgbBlock => Some((Format::ASTC_6x5UnormBlock, Format::ASTC_6x5SrgbBlock)),
+            Format::ASTC_6x6UnormBlock | Format::ASTC_6x6SrgbBlock => Some((Format::ASTC_6x6UnormBlock, Format::ASTC_6x6SrgbBlock)),
+            Format::ASTC_8x5UnormBlock | Format::ASTC_8x5SrgbBlock => Some((Format::ASTC_8x5UnormBlock, Format::ASTC_8x5SrgbBlock)),
+            Format::ASTC_8x6UnormBlock | Format::ASTC_8x6SrgbBlock => Some((Format::ASTC_8x6UnormBlock, Format::ASTC_8x6SrgbBlock)),
+            Format::ASTC_8x8UnormBlock | Format::ASTC_8x8SrgbBlock => Some((Format::ASTC_8x8UnormBlock, Format::ASTC_8x8SrgbBlock)),
+            Format::ASTC_10x5UnormBlock | Format::ASTC_10x5SrgbBlock => Some((Format::ASTC_10x5UnormBlock, Format::ASTC_10x5SrgbBlock)),
+            Format::ASTC_10x6UnormBlock | Format::ASTC_10x6SrgbBlock => Some((Format::ASTC_10x6UnormBlock, Format::ASTC_10x6SrgbBlock)),
+            Format::ASTC_10x8UnormBlock | Format::ASTC_10x8SrgbBlock => Some((Format::ASTC_10x8UnormBlock, Format::ASTC_10x8SrgbBlock)),
+            Format::ASTC_10x10UnormBlock | Format::ASTC_10x10SrgbBlock => Some((Format::ASTC_10x10UnormBlock, Format::ASTC_10x10SrgbBlock)),
+            Format::ASTC_12x10UnormBlock | Format::ASTC_12x10SrgbBlock => Some((Format::ASTC_12x10UnormBlock, Format::ASTC_12x10SrgbBlock)),
+            Format::ASTC_12x12UnormBlock | Format::ASTC_12x12SrgbBlock => Some((Format::ASTC_12x12UnormBlock, Format::ASTC_12x12SrgbBlock)),
+            _ => None,
+        }
+    }
+
+    /// Returns true if an image of this format can be viewed with `other`, provided that the
+    /// image was created with a mutable format.
+    ///
+    /// Uncompressed color formats are compatible if they have the same size. Compressed formats
+    /// are only compatible with their sRGB or `Unorm` equivalent, and depth and stencil formats
+    /// are only compatible with themselves.
+    pub fn is_compatible_with(&self, other: Format) -> bool {
+        if *self == other {
+            return true;
+        }
+
+        match (self.ty(), other.ty()) {
+            (FormatTy::Compressed, FormatTy::Compressed) => {
+                self.srgb_pair().is_some() && self.srgb_pair() == other.srgb_pair()
+            },
+            (FormatTy::Float, _) | (FormatTy::Uint, _) | (FormatTy::Sint, _) => {
+                match other.ty() {
+                    FormatTy::Float | FormatTy::Uint | FormatTy::Sint => {
+                        self.size().is_some() && self.size() == other.size()
+                    },
+                    _ => false,
+                }
+            },
+            _ => false,
+        }
+    }
+}
+
 pub unsafe trait FormatDesc {
     type ClearValue;
 
@@ -856,3 +1007,39 @@ macro_rules! impl_clear_values_tuple {
 }
 
 impl_clear_values_tuple!(A B C D E F G H I J K L M N O P Q R S T U V W X Y Z);
+
+#[cfg(test)]
+mod tests {
+    use format::Format;
+
+    #[test]
+    fn srgb_equivalents() {
+        assert_eq!(Format::R8G8B8A8Unorm.srgb_equivalent(), Some(Format::R8G8B8A8Srgb));
+        assert_eq!(Format::R8G8B8A8Srgb.unorm_equivalent(), Some(Format::R8G8B8A8Unorm));
+        assert_eq!(Format::R8G8B8A8Srgb.srgb_equivalent(), Some(Format::R8G8B8A8Srgb));
+        assert_eq!(Format::BC7UnormBlock.srgb_equivalent(), Some(Format::BC7SrgbBlock));
+        assert_eq!(Format::R16G16B16A16Sfloat.srgb_equivalent(), None);
+        assert_eq!(Format::R8G8B8A8Snorm.unorm_equivalent(), None);
+
+        assert!(Format::B8G8R8A8Srgb.is_srgb());
+        assert!(!Format::B8G8R8A8Unorm.is_srgb());
+    }
+
+    #[test]
+    fn compatible_formats() {
+        assert!(Format::R8G8B8A8Unorm.is_compatible_with(Format::R8G8B8A8Srgb));
+        assert!(Format::R8G8B8A8Unorm.is_compatible_with(Format::R32Uint));
+        assert!(!Format::R8G8B8A8Unorm.is_compatible_with(Format::R16Sfloat));
+        assert!(Format::BC1_RGBUnormBlock.is_compatible_with(Format::BC1_RGBSrgbBlock));
+        assert!(!Format::BC1_RGBUnormBlock.is_compatible_with(Format::BC1_RGBAUnormBlock));
+        assert!(!Format::D32Sfloat.is_compatible_with(Format::R32Sfloat));
+    }
+
+    #[test]
+    fn num_locations() {
+        assert_eq!(Format::R32G32B32A32Sfloat.num_locations(), 1);
+        assert_eq!(Format::R64G64Sfloat.num_locations(), 1);
+        assert_eq!(Format::R64G64B64Sfloat.num_locations(), 2);
+        assert_eq!(Format::R64G64B64A64Uint.num_locations(), 2);
+    }
+}
@@ -0,0 +1,357 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! This module contains the `ensure_render_pass_compatible` function, which verifies whether
+//! two render passes are compatible.
+
+use format::Format;
+use framebuffer::RenderPassDesc;
+use image::ImageLayout;
+use std::error;
+use std::fmt;
+use vk;
+
+/// Checks whether two render passes are compatible, as defined in the `Render Pass Compatibility`
+/// section of the Vulkan specs.
+///
+/// Two render passes are compatible if they have the same number of subpasses, and if each
+/// subpass references attachments that have the same formats and number of samples in the same
+/// order. The load and store operations and the layouts don't matter. If the render passes have
+/// more than one subpass, their dependencies must also be identical. If they have only one
+/// subpass, the resolve attachments are ignored.
+///
+/// In the returned error, "expected" refers to `reference` and "obtained" refers to `other`.
+pub fn ensure_render_pass_compatible<A, B>(reference: &A, other: &B)
+                                           -> Result<(), IncompatibleRenderPassError>
+    where A: ?Sized + RenderPassDesc,
+          B: ?Sized + RenderPassDesc
+{
+    if reference.num_subpasses() != other.num_subpasses() {
+        return Err(IncompatibleRenderPassError::SubpassCountMismatch {
+                       expected: reference.num_subpasses(),
+                       obtained: other.num_subpasses(),
+                   });
+    }
+
+    let single_subpass = reference.num_subpasses() == 1;
+
+    for subpass in 0 .. reference.num_subpasses() {
+        let ref_desc = reference.subpass_desc(subpass).unwrap();
+        let other_desc = other.subpass_desc(subpass).unwrap();
+
+        compare_references(reference,
+                           other,
+                           subpass,
+                           AttachmentKind::Color,
+                           &ref_desc.color_attachments,
+                           &other_desc.color_attachments)?;
+        compare_references(reference,
+                           other,
+                           subpass,
+                           AttachmentKind::Input,
+                           &ref_desc.input_attachments,
+                           &other_desc.input_attachments)?;
+
+        let ref_depth = ref_desc.depth_stencil.into_iter().collect::<Vec<_>>();
+        let other_depth = other_desc.depth_stencil.into_iter().collect::<Vec<_>>();
+        compare_references(reference,
+                           other,
+                           subpass,
+                           AttachmentKind::DepthStencil,
+                           &ref_depth,
+                           &other_depth)?;
+
+        if !single_subpass {
+            compare_references(reference,
+                               other,
+                               subpass,
+                               AttachmentKind::Resolve,
+                               &ref_desc.resolve_attachments,
+                               &other_desc.resolve_attachments)?;
+        }
+    }
+
+    if !single_subpass {
+        if reference.num_dependencies() != other.num_dependencies() {
+            return Err(IncompatibleRenderPassError::DependenciesMismatch);
+        }
+
+        for num in 0 .. reference.num_dependencies() {
+            let a = reference.dependency_desc(num).unwrap();
+            let b = other.dependency_desc(num).unwrap();
+
+            let a_src_stages: vk::PipelineStageFlagBits = a.src_stages.into();
+            let a_dst_stages: vk::PipelineStageFlagBits = a.dst_stages.into();
+            let a_src_access: vk::AccessFlagBits = a.src_access.into();
+            let a_dst_access: vk::AccessFlagBits = a.dst_access.into();
+            let b_src_stages: vk::PipelineStageFlagBits = b.src_stages.into();
+            let b_dst_stages: vk::PipelineStageFlagBits = b.dst_stages.into();
+            let b_src_access: vk::AccessFlagBits = b.src_access.into();
+            let b_dst_access: vk::AccessFlagBits = b.dst_access.into();
+
+            let identical = a.source_subpass == b.source_subpass &&
+                a.destination_subpass == b.destination_subpass &&
+                a_src_stages == b_src_stages && a_dst_stages == b_dst_stages &&
+                a_src_access == b_src_access && a_dst_access == b_dst_access &&
+                a.by_region == b.by_region;
+
+            if !identical {
+                return Err(IncompatibleRenderPassError::DependenciesMismatch);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Compares one kind of attachment references of a subpass of both render passes.
+fn compare_references<A, B>(reference: &A, other: &B, subpass: usize, kind: AttachmentKind,
+                            ref_list: &[(usize, ImageLayout)],
+                            other_list: &[(usize, ImageLayout)])
+                            -> Result<(), IncompatibleRenderPassError>
+    where A: ?Sized + RenderPassDesc,
+          B: ?Sized + RenderPassDesc
+{
+    if ref_list.len() != other_list.len() {
+        return Err(IncompatibleRenderPassError::AttachmentCountMismatch {
+                       subpass: subpass,
+                       kind: kind,
+                       expected: ref_list.len(),
+                       obtained: other_list.len(),
+                   });
+    }
+
+    for (index, (&(ref_num, _), &(other_num, _))) in
+        ref_list.iter().zip(other_list.iter()).enumerate()
+    {
+        let ref_atch = reference.attachment_desc(ref_num).unwrap();
+        let other_atch = other.attachment_desc(other_num).unwrap();
+
+        if ref_atch.format != other_atch.format {
+            return Err(IncompatibleRenderPassError::FormatMismatch {
+                           subpass: subpass,
+                           kind: kind,
+                           index: index,
+                           expected: ref_atch.format,
+                           obtained: other_atch.format,
+                       });
+        }
+
+        if ref_atch.samples != other_atch.samples {
+            return Err(IncompatibleRenderPassError::SamplesMismatch {
+                           subpass: subpass,
+                           kind: kind,
+                           index: index,
+                           expected: ref_atch.samples,
+                           obtained: other_atch.samples,
+                       });
+        }
+    }
+
+    Ok(())
+}
+
+/// The way a subpass uses an attachment.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AttachmentKind {
+    /// Color attachment.
+    Color,
+    /// Input attachment.
+    Input,
+    /// Resolve attachment.
+    Resolve,
+    /// Depth-stencil attachment.
+    DepthStencil,
+}
+
+/// Error that can happen when checking whether two render passes, or a pipeline and a subpass,
+/// are compatible.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum IncompatibleRenderPassError {
+    /// The render passes don't have the same number of subpasses.
+    SubpassCountMismatch {
+        /// Number of subpasses of the reference render pass.
+        expected: usize,
+        /// Number of subpasses of the other render pass.
+        obtained: usize,
+    },
+
+    /// The pipeline was created for a different subpass than the one it is used in.
+    SubpassIndexMismatch {
+        /// Index of the subpass the pipeline was created for.
+        expected: u32,
+        /// Index of the subpass the pipeline is used in.
+        obtained: u32,
+    },
+
+    /// A subpass doesn't use the same number of attachments of a kind in both render passes.
+    AttachmentCountMismatch {
+        /// Index of the subpass.
+        subpass: usize,
+        /// Kind of the attachments.
+        kind: AttachmentKind,
+        /// Number of attachments in the reference render pass.
+        expected: usize,
+        /// Number of attachments in the other render pass.
+        obtained: usize,
+    },
+
+    /// Two corresponding attachments don't have the same format.
+    FormatMismatch {
+        /// Index of the subpass.
+        subpass: usize,
+        /// Kind of the attachment.
+        kind: AttachmentKind,
+        /// Position of the attachment within the attachments of this kind of the subpass.
+        index: usize,
+        /// Format of the attachment in the reference render pass.
+        expected: Format,
+        /// Format of the attachment in the other render pass.
+        obtained: Format,
+    },
+
+    /// Two corresponding attachments don't have the same number of samples.
+    SamplesMismatch {
+        /// Index of the subpass.
+        subpass: usize,
+        /// Kind of the attachment.
+        kind: AttachmentKind,
+        /// Position of the attachment within the attachments of this kind of the subpass.
+        index: usize,
+        /// Number of samples of the attachment in the reference render pass.
+        expected: u32,
+        /// Number of samples of the attachment in the other render pass.
+        obtained: u32,
+    },
+
+    /// The render passes have more than one subpass and their dependencies are different.
+    DependenciesMismatch,
+}
+
+impl error::Error for IncompatibleRenderPassError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            IncompatibleRenderPassError::SubpassCountMismatch { .. } => {
+                "the render passes don't have the same number of subpasses"
+            },
+            IncompatibleRenderPassError::SubpassIndexMismatch { .. } => {
+                "the pipeline was created for a different subpass than the one it is used in"
+            },
+            IncompatibleRenderPassError::AttachmentCountMismatch { .. } => {
+                "a subpass doesn't use the same number of attachments in both render passes"
+            },
+            IncompatibleRenderPassError::FormatMismatch { .. } => {
+                "two corresponding attachments don't have the same format"
+            },
+            IncompatibleRenderPassError::SamplesMismatch { .. } => {
+                "two corresponding attachments don't have the same number of samples"
+            },
+            IncompatibleRenderPassError::DependenciesMismatch => {
+                "the render passes have more than one subpass and their dependencies are different"
+            },
+        }
+    }
+}
+
+impl fmt::Display for IncompatibleRenderPassError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AttachmentKind;
+    use super::IncompatibleRenderPassError;
+    use super::ensure_render_pass_compatible;
+    use format::Format;
+
+    #[test]
+    fn load_ops_ignored() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let rp1 = single_pass_renderpass!(device.clone(),
+            attachments: {
+                color: { load: Clear, store: Store, format: Format::R8G8B8A8Unorm, samples: 1, }
+            },
+            pass: { color: [color], depth_stencil: {} }
+        ).unwrap();
+
+        let rp2 = single_pass_renderpass!(device.clone(),
+            attachments: {
+                color: { load: Load, store: DontCare, format: Format::R8G8B8A8Unorm, samples: 1, }
+            },
+            pass: { color: [color], depth_stencil: {} }
+        ).unwrap();
+
+        ensure_render_pass_compatible(&rp1, &rp2).unwrap();
+    }
+
+    #[test]
+    fn format_mismatch() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let rp1 = single_pass_renderpass!(device.clone(),
+            attachments: {
+                color: { load: Clear, store: Store, format: Format::R8G8B8A8Unorm, samples: 1, }
+            },
+            pass: { color: [color], depth_stencil: {} }
+        ).unwrap();
+
+        let rp2 = single_pass_renderpass!(device.clone(),
+            attachments: {
+                color: { load: Clear, store: Store, format: Format::B8G8R8A8Srgb, samples: 1, }
+            },
+            pass: { color: [color], depth_stencil: {} }
+        ).unwrap();
+
+        match ensure_render_pass_compatible(&rp1, &rp2) {
+            Err(IncompatibleRenderPassError::FormatMismatch {
+                    subpass: 0,
+                    kind: AttachmentKind::Color,
+                    index: 0,
+                    expected: Format::R8G8B8A8Unorm,
+                    obtained: Format::B8G8R8A8Srgb,
+                }) => (),
+            e => panic!("{:?}", e),
+        }
+    }
+
+    #[test]
+    fn missing_depth_stencil() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let rp1 = single_pass_renderpass!(device.clone(),
+            attachments: {
+                color: { load: Clear, store: Store, format: Format::R8G8B8A8Unorm, samples: 1, },
+                depth: { load: Clear, store: DontCare, format: Format::D16Unorm, samples: 1, }
+            },
+            pass: { color: [color], depth_stencil: {depth} }
+        ).unwrap();
+
+        let rp2 = single_pass_renderpass!(device.clone(),
+            attachments: {
+                color: { load: Clear, store: Store, format: Format::R8G8B8A8Unorm, samples: 1, }
+            },
+            pass: { color: [color], depth_stencil: {} }
+        ).unwrap();
+
+        match ensure_render_pass_compatible(&rp1, &rp2) {
+            Err(IncompatibleRenderPassError::AttachmentCountMismatch {
+                    kind: AttachmentKind::DepthStencil,
+                    expected: 1,
+                    obtained: 0,
+                    ..
+                }) => (),
+            e => panic!("{:?}", e),
+        }
+    }
+}
@@ -457,6 +457,47 @@ impl LayoutAttachmentDescription {
     pub fn is_compatible_with(&self, other: &LayoutAttachmentDescription) -> bool {
         self.format == other.format && self.samples == other.samples
     }
+
+    /// Returns true if `value` can be used as the clear value of this attachment when beginning
+    /// the render pass.
+    ///
+    /// The value must provide a clear value for each component that is loaded with
+    /// `LoadOp::Clear`. For depth-stencil formats, `ClearValue::Depth` and `ClearValue::Stencil`
+    /// can be used when only one of the two components is cleared. Attachments that aren't
+    /// cleared at all accept any value, but `ClearValue::None` is recommended.
+    pub fn is_valid_clear_value(&self, value: &ClearValue) -> bool {
+        let clear_main = self.load == LoadOp::Clear;
+        let clear_stencil = self.stencil_load == LoadOp::Clear;
+
+        match self.format.ty() {
+            FormatTy::Float | FormatTy::Compressed => match *value {
+                ClearValue::Float(_) => true,
+                _ => !clear_main,
+            },
+            FormatTy::Uint => match *value {
+                ClearValue::Uint(_) => true,
+                _ => !clear_main,
+            },
+            FormatTy::Sint => match *value {
+                ClearValue::Int(_) => true,
+                _ => !clear_main,
+            },
+            FormatTy::Depth => match *value {
+                ClearValue::Depth(_) | ClearValue::DepthStencil(_) => true,
+                _ => !clear_main,
+            },
+            FormatTy::Stencil => match *value {
+                ClearValue::Stencil(_) | ClearValue::DepthStencil(_) => true,
+                _ => !clear_stencil,
+            },
+            FormatTy::DepthStencil => match *value {
+                ClearValue::DepthStencil(_) => true,
+                ClearValue::Depth(_) => !clear_stencil,
+                ClearValue::Stencil(_) => !clear_main,
+                _ => !clear_main && !clear_stencil,
+            },
+        }
+    }
 }
 
 /// Describes one of the passes of a render pass.
@@ -116,8 +116,8 @@ unsafe impl RenderPassDesc for EmptySinglePassRenderPassDesc {
 unsafe impl RenderPassDescClearValues<Vec<ClearValue>> for EmptySinglePassRenderPassDesc {
     #[inline]
     fn convert_clear_values(&self, values: Vec<ClearValue>) -> Box<Iterator<Item = ClearValue>> {
-        assert!(values.is_empty()); // TODO: error instead
-        Box::new(iter::empty())
+        // The number of values is checked when beginning the render pass.
+        Box::new(values.into_iter())
     }
 }
 
@@ -110,7 +110,16 @@ impl<Rp> Framebuffer<Rp, ()> {
         }
     }
 
-    /// Starts building a framebuffer.
+    /// Starts building a framebuffer with the given width, height and number of layers.
+    ///
+    /// The attachments must be at least as large as the dimensions. Use a number of layers
+    /// greater than 1 with attachments that have multiple array layers in order to perform
+    /// layered rendering.
+    ///
+    /// > **Note**: The framebuffer always has exactly these dimensions. Previously, they were
+    /// > replaced with the dimensions of the last attachment that was added, which made it
+    /// > impossible to render to fewer layers than an attachment has. Use `start()` or
+    /// > `with_intersecting_dimensions()` to get the dimensions from the attachments instead.
     pub fn with_dimensions(render_pass: Rp, dimensions: [u32; 3]) -> FramebufferBuilder<Rp, ()> {
         FramebufferBuilder {
             render_pass: render_pass,
@@ -216,9 +225,7 @@ impl<Rp, A> FramebufferBuilder<Rp, A>
                     });
                 }
 
-                FramebufferBuilderDimensions::Specific(
-                    [img_dims.width(), img_dims.height(), img_dims.array_layers()],
-                )
+                FramebufferBuilderDimensions::Specific(current)
             },
         };
 
@@ -309,7 +316,8 @@ impl<Rp, A> FramebufferBuilder<Rp, A>
             check_errors(vk.CreateFramebuffer(device.internal_object(),
                                               &infos,
                                               ptr::null(),
-                                              &mut output))?;
+                                              &mut output),
+                         "vkCreateFramebuffer")?;
             output
         };
 
@@ -545,6 +553,9 @@ impl From<Error> for FramebufferCreationError {
 
 #[cfg(test)]
 mod tests {
+    use ErrorContext;
+    use OomError;
+    use check_errors;
     use format::Format;
     use framebuffer::EmptySinglePassRenderPassDesc;
     use framebuffer::Framebuffer;
@@ -552,6 +563,7 @@ mod tests {
     use framebuffer::RenderPassDesc;
     use image::attachment::AttachmentImage;
     use std::sync::Arc;
+    use vk;
 
     #[test]
     fn simple_create() {
@@ -693,6 +705,45 @@ mod tests {
         }
     }
 
+    #[test]
+    fn layered_attachment() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let render_pass = Arc::new(
+            single_pass_renderpass!(device.clone(),
+            attachments: {
+                color: {
+                    load: Clear,
+                    store: DontCare,
+                    format: Format::R8G8B8A8Unorm,
+                    samples: 1,
+                }
+            },
+            pass: {
+                color: [color],
+                depth_stencil: {}
+            }
+        ).unwrap(),
+        );
+
+        let img = AttachmentImage::array(device.clone(), [256, 256], 4, Format::R8G8B8A8Unorm)
+            .unwrap();
+
+        let fb = Framebuffer::start(render_pass.clone())
+            .add(img.clone())
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(fb.dimensions(), [256, 256, 4]);
+
+        let fb = Framebuffer::with_dimensions(render_pass, [128, 128, 2])
+            .add(img)
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(fb.dimensions(), [128, 128, 2]);
+    }
+
     #[test]
     fn multi_attachments_dims_not_identical() {
         let (device, _) = gfx_dev_and_queue!();
@@ -899,4 +950,18 @@ mod tests {
             _ => panic!(),
         }
     }
+
+    #[test]
+    fn error_context() {
+        let err = check_errors(vk::ERROR_OUT_OF_DEVICE_MEMORY, "vkCreateFramebuffer").unwrap_err();
+        let err = FramebufferCreationError::from(err);
+
+        match err {
+            FramebufferCreationError::OomError(OomError::OutOfDeviceMemory) => (),
+            _ => panic!(),
+        }
+
+        assert_eq!(ErrorContext::last().unwrap().function(), "vkCreateFramebuffer");
+        assert_eq!(ErrorContext::last().unwrap().object_name(), None);
+    }
 }
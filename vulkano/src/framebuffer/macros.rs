@@ -7,6 +7,16 @@
 // notice may not be copied, modified, or distributed except
 // according to those terms.
 
+// Expands to the load or store operation of the stencil component of an attachment, which
+// defaults to the operation of its main component. An operation that is given more than once
+// doesn't match any rule and is a compilation error.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! renderpass_stencil_op {
+    ($ty:ident, $default:ident) => ($crate::framebuffer::$ty::$default);
+    ($ty:ident, $default:ident, $op:ident) => ($crate::framebuffer::$ty::$op);
+}
+
 /// Builds a `RenderPass` object whose template parameter is of undeterminate type.
 #[macro_export]
 macro_rules! single_pass_renderpass {
@@ -33,6 +43,47 @@ macro_rules! single_pass_renderpass {
 }
 
 /// Builds a `RenderPass` object whose template parameter is of undeterminate type.
+///
+/// The `load` and `store` operations of an attachment apply to its color or depth component.
+/// For depth-stencil attachments, the operations of the stencil component default to the same
+/// values and can be overridden with `stencil_load` and `stencil_store`, which must come right
+/// after `store`:
+///
+/// ```ignore
+/// depth: {
+///     load: Load,
+///     store: DontCare,
+///     stencil_load: Clear,
+///     stencil_store: Store,
+///     format: Format::D24Unorm_S8Uint,
+///     samples: 1,
+/// }
+/// ```
+///
+/// Each of `stencil_load` and `stencil_store` can only be given once. When beginning the render
+/// pass, the clear value of such an attachment must contain a value for each component that is
+/// cleared. In the example above, the clear value would be `ClearValue::Stencil(0)`.
+///
+/// A subpass can read the content that it has itself written to an attachment, for example for
+/// programmable blending, by using the attachment both as a color or depth-stencil attachment
+/// and as an input attachment, and by adding `self_dependency: true` after its input
+/// attachments:
+///
+/// ```ignore
+/// passes: [
+///     {
+///         color: [color],
+///         depth_stencil: {},
+///         input: [color],
+///         self_dependency: true
+///     }
+/// ]
+/// ```
+///
+/// Such an attachment uses the `General` layout during the subpass. The self-dependency makes the
+/// color and depth-stencil writes of the subpass available to its input attachment reads, and
+/// only applies to the same pixel (`by_region` is true). A pipeline barrier is still needed
+/// between the draw that writes the attachment and the draw that reads it.
 #[macro_export]
 macro_rules! ordered_passes_renderpass {
     (
@@ -42,6 +93,8 @@ macro_rules! ordered_passes_renderpass {
                 $atch_name:ident: {
                     load: $load:ident,
                     store: $store:ident,
+                    $(stencil_load: $stencil_load:ident,)*
+                    $(stencil_store: $stencil_store:ident,)*
                     format: $format:expr,
                     samples: $samples:expr,
                     $(initial_layout: $init_layout:expr,)*
@@ -55,6 +108,7 @@ macro_rules! ordered_passes_renderpass {
                     color: [$($color_atch:ident),*],
                     depth_stencil: {$($depth_atch:ident)*},
                     input: [$($input_atch:ident),*]
+                    $(, self_dependency: $self_dep:expr)*
                 }
             ),*
         ]
@@ -117,7 +171,8 @@ macro_rules! ordered_passes_renderpass {
 
             unsafe impl RenderPassDescClearValues<Vec<ClearValue>> for CustomRenderPassDesc {
                 fn convert_clear_values(&self, values: Vec<ClearValue>) -> Box<Iterator<Item = ClearValue>> {
-                    // FIXME: safety checks
+                    // The values are checked against the attachments when beginning the render
+                    // pass.
                     Box::new(values.into_iter())
                 }
             }
@@ -143,13 +198,18 @@ macro_rules! ordered_passes_renderpass {
                     if id == num {
                         let (initial_layout, final_layout) = attachment_layouts(num);
 
+                        let stencil_load =
+                            renderpass_stencil_op!(LoadOp, $load $(, $stencil_load)*);
+                        let stencil_store =
+                            renderpass_stencil_op!(StoreOp, $store $(, $stencil_store)*);
+
                         return Some($crate::framebuffer::LayoutAttachmentDescription {
                             format: desc.$atch_name.0,
                             samples: desc.$atch_name.1,
                             load: $crate::framebuffer::LoadOp::$load,
                             store: $crate::framebuffer::StoreOp::$store,
-                            stencil_load: $crate::framebuffer::LoadOp::$load,
-                            stencil_store: $crate::framebuffer::StoreOp::$store,
+                            stencil_load: stencil_load,
+                            stencil_store: stencil_store,
                             initial_layout: initial_layout,
                             final_layout: final_layout,
                         });
@@ -187,21 +247,43 @@ macro_rules! ordered_passes_renderpass {
 
                 $({
                     if id == cur_pass_num {
+                        // Attachments that are both written and read by the subpass must use the
+                        // `General` layout.
+                        let inputs: &[usize] = &[$($input_atch),*];
+                        let mut outputs: Vec<usize> = Vec::new();
+                        $(outputs.push($color_atch);)*
+                        $(outputs.push($depth_atch);)*
+
+                        let output_layout = |a: usize, layout: ImageLayout| {
+                            if inputs.contains(&a) { ImageLayout::General } else { layout }
+                        };
+
+                        let input_layout = |a: usize| {
+                            if outputs.contains(&a) {
+                                ImageLayout::General
+                            } else {
+                                ImageLayout::ShaderReadOnlyOptimal
+                            }
+                        };
+
                         let mut depth = None;
                         $(
-                            depth = Some(($depth_atch, ImageLayout::DepthStencilAttachmentOptimal));
+                            depth = Some(($depth_atch,
+                                          output_layout($depth_atch,
+                                                        ImageLayout::DepthStencilAttachmentOptimal)));
                         )*
 
                         return Some(LayoutPassDescription {
                             color_attachments: vec![
                                 $(
-                                    ($color_atch, ImageLayout::ColorAttachmentOptimal)
+                                    ($color_atch,
+                                     output_layout($color_atch, ImageLayout::ColorAttachmentOptimal))
                                 ),*
                             ],
                             depth_stencil: depth,
                             input_attachments: vec![
                                 $(
-                                    ($input_atch, ImageLayout::ShaderReadOnlyOptimal)
+                                    ($input_atch, input_layout($input_atch))
                                 ),*
                             ],
                             resolve_attachments: vec![],
@@ -220,9 +302,30 @@ macro_rules! ordered_passes_renderpass {
                 None
             }
 
+            /// Returns the list of subpasses that have a self-dependency.
+            fn self_dependencies() -> Vec<usize> {
+                #![allow(unused_assignments)]
+                #![allow(unused_mut)]
+
+                let mut out = Vec::new();
+                let mut cur_pass_num = 0;
+
+                $({
+                    $(
+                        if $self_dep {
+                            out.push(cur_pass_num);
+                        }
+                    )*
+
+                    cur_pass_num += 1;
+                })*
+
+                out
+            }
+
             #[inline]
             fn num_dependencies() -> usize {
-                num_subpasses().saturating_sub(1)
+                num_subpasses().saturating_sub(1) + self_dependencies().len()
             }
 
             #[inline]
@@ -230,7 +333,37 @@ macro_rules! ordered_passes_renderpass {
                 let num_passes = num_subpasses();
 
                 if id + 1 >= num_passes {
-                    return None;
+                    let subpass = match self_dependencies()
+                        .get(id - num_passes.saturating_sub(1))
+                    {
+                        Some(&s) => s,
+                        None => return None,
+                    };
+
+                    return Some(LayoutPassDependencyDescription {
+                        source_subpass: subpass,
+                        destination_subpass: subpass,
+                        src_stages: PipelineStages {
+                            early_fragment_tests: true,
+                            late_fragment_tests: true,
+                            color_attachment_output: true,
+                            .. PipelineStages::none()
+                        },
+                        dst_stages: PipelineStages {
+                            fragment_shader: true,
+                            .. PipelineStages::none()
+                        },
+                        src_access: AccessFlagBits {
+                            color_attachment_write: true,
+                            depth_stencil_attachment_write: true,
+                            .. AccessFlagBits::none()
+                        },
+                        dst_access: AccessFlagBits {
+                            input_attachment_read: true,
+                            .. AccessFlagBits::none()
+                        },
+                        by_region: true,
+                    });
                 }
 
                 Some(LayoutPassDependencyDescription {
@@ -262,41 +395,51 @@ macro_rules! ordered_passes_renderpass {
                 let mut final_layout = None;
 
                 $({
+                    let mut layout = None;
+
                     $(
                         if $depth_atch == num {
-                            if initial_layout.is_none() {
-                                initial_layout = Some(ImageLayout::DepthStencilAttachmentOptimal);
-                            }
-                            final_layout = Some(ImageLayout::DepthStencilAttachmentOptimal);
+                            layout = Some(ImageLayout::DepthStencilAttachmentOptimal);
                         }
                     )*
 
                     $(
                         if $color_atch == num {
-                            if initial_layout.is_none() {
-                                initial_layout = Some(ImageLayout::ColorAttachmentOptimal);
-                            }
-                            final_layout = Some(ImageLayout::ColorAttachmentOptimal);
+                            layout = Some(ImageLayout::ColorAttachmentOptimal);
                         }
                     )*
 
                     $(
                         if $input_atch == num {
-                            if initial_layout.is_none() {
-                                initial_layout = Some(ImageLayout::ShaderReadOnlyOptimal);
-                            }
-                            final_layout = Some(ImageLayout::ShaderReadOnlyOptimal);
+                            // An attachment that is both written and read by the same subpass
+                            // uses the `General` layout.
+                            layout = Some(if layout.is_some() {
+                                ImageLayout::General
+                            } else {
+                                ImageLayout::ShaderReadOnlyOptimal
+                            });
                         }
                     )*
+
+                    if let Some(layout) = layout {
+                        if initial_layout.is_none() {
+                            initial_layout = Some(layout);
+                        }
+                        final_layout = Some(layout);
+                    }
                 })*
 
                 $(if $atch_name == num {
-                    // If the clear OP is Clear or DontCare, default to the Undefined layout.
+                    let stencil_load = renderpass_stencil_op!(LoadOp, $load $(, $stencil_load)*);
+
+                    // If neither the main component nor the stencil component is loaded, default
+                    // to the Undefined layout.
                     if initial_layout == Some(ImageLayout::DepthStencilAttachmentOptimal) ||
-                        initial_layout == Some(ImageLayout::ColorAttachmentOptimal)
+                        initial_layout == Some(ImageLayout::ColorAttachmentOptimal) ||
+                        initial_layout == Some(ImageLayout::General)
                     {
-                        if $crate::framebuffer::LoadOp::$load == $crate::framebuffer::LoadOp::Clear ||
-                            $crate::framebuffer::LoadOp::$load == $crate::framebuffer::LoadOp::DontCare
+                        if $crate::framebuffer::LoadOp::$load != $crate::framebuffer::LoadOp::Load &&
+                            stencil_load != $crate::framebuffer::LoadOp::Load
                         {
                             initial_layout = Some(ImageLayout::Undefined);
                         }
@@ -93,6 +93,9 @@
 pub use self::attachments_list::AttachmentsList;
 pub use self::compat_atch::IncompatibleRenderPassAttachmentError;
 pub use self::compat_atch::ensure_image_view_compatible;
+pub use self::compat_rp::AttachmentKind;
+pub use self::compat_rp::IncompatibleRenderPassError;
+pub use self::compat_rp::ensure_render_pass_compatible;
 pub use self::desc::LayoutAttachmentDescription;
 pub use self::desc::LayoutPassDependencyDescription;
 pub use self::desc::LayoutPassDescription;
@@ -107,6 +110,10 @@ pub use self::framebuffer::Framebuffer;
 pub use self::framebuffer::FramebufferBuilder;
 pub use self::framebuffer::FramebufferCreationError;
 pub use self::framebuffer::FramebufferSys;
+pub use self::msaa::MsaaAttachments;
+pub use self::msaa::MsaaAttachmentsCreationError;
+pub use self::msaa::MsaaFramebuffer;
+pub use self::msaa::MsaaRenderPassDesc;
 pub use self::sys::RenderPass;
 pub use self::sys::RenderPassCreationError;
 pub use self::sys::RenderPassSys;
@@ -123,9 +130,11 @@ use vk;
 mod macros;
 mod attachments_list;
 mod compat_atch;
+mod compat_rp;
 mod desc;
 mod empty;
 mod framebuffer;
+mod msaa;
 mod sys;
 mod traits;
 
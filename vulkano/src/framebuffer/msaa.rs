@@ -0,0 +1,458 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::error;
+use std::fmt;
+use std::sync::Arc;
+
+use device::Device;
+use device::DeviceOwned;
+use format::ClearValue;
+use format::Format;
+use format::FormatTy;
+use framebuffer::Framebuffer;
+use framebuffer::FramebufferCreationError;
+use framebuffer::LayoutAttachmentDescription;
+use framebuffer::LayoutPassDependencyDescription;
+use framebuffer::LayoutPassDescription;
+use framebuffer::LoadOp;
+use framebuffer::RenderPass;
+use framebuffer::RenderPassCreationError;
+use framebuffer::RenderPassDesc;
+use framebuffer::RenderPassDescClearValues;
+use framebuffer::StoreOp;
+use framebuffer::Subpass;
+use image::AttachmentImage;
+use image::ImageLayout;
+use image::ImageViewAccess;
+use image::sys::ImageCreationError;
+use pipeline::multisample::Multisample;
+
+/// Description of a render pass with a single subpass that draws on a multisampled color
+/// attachment and a multisampled depth attachment, and resolves the color attachment into a
+/// single-sampled image at the end of the subpass.
+///
+/// The attachments are, in this order:
+///
+/// - The multisampled color attachment, cleared at the start of the render pass.
+/// - The single-sampled image that receives the resolved color. This is usually a swapchain
+///   image. Its content is stored.
+/// - The multisampled depth attachment, cleared at the start of the render pass.
+///
+/// The clear values can be passed either as a `Vec<ClearValue>` with one entry per attachment,
+/// or as a `([f32; 4], f32)` tuple containing the clear color and the clear depth. Beginning the
+/// render pass returns an error if the `Vec` doesn't contain exactly three valid values.
+#[derive(Debug, Copy, Clone)]
+pub struct MsaaRenderPassDesc {
+    color_format: Format,
+    depth_format: Format,
+    samples: u32,
+}
+
+impl MsaaRenderPassDesc {
+    /// Builds a new description.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `samples` is less than 2.
+    /// - Panics if `color_format` is not a color format or `depth_format` doesn't have a depth
+    ///   component.
+    ///
+    #[inline]
+    pub fn new(color_format: Format, depth_format: Format, samples: u32) -> MsaaRenderPassDesc {
+        assert!(samples >= 2);
+        match color_format.ty() {
+            FormatTy::Float | FormatTy::Uint | FormatTy::Sint => (),
+            _ => panic!("the color format of a MSAA render pass must be a color format"),
+        }
+        match depth_format.ty() {
+            FormatTy::Depth | FormatTy::DepthStencil => (),
+            _ => panic!("the depth format of a MSAA render pass must have a depth component"),
+        }
+
+        MsaaRenderPassDesc {
+            color_format: color_format,
+            depth_format: depth_format,
+            samples: samples,
+        }
+    }
+
+    /// Returns the format of the color attachment and of the resolve target.
+    #[inline]
+    pub fn color_format(&self) -> Format {
+        self.color_format
+    }
+
+    /// Returns the format of the depth attachment.
+    #[inline]
+    pub fn depth_format(&self) -> Format {
+        self.depth_format
+    }
+
+    /// Returns the number of samples of the color and depth attachments.
+    #[inline]
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+}
+
+unsafe impl RenderPassDesc for MsaaRenderPassDesc {
+    #[inline]
+    fn num_attachments(&self) -> usize {
+        3
+    }
+
+    #[inline]
+    fn attachment_desc(&self, num: usize) -> Option<LayoutAttachmentDescription> {
+        let (format, samples, load, store, final_layout) = match num {
+            0 => (self.color_format,
+                  self.samples,
+                  LoadOp::Clear,
+                  StoreOp::DontCare,
+                  ImageLayout::ColorAttachmentOptimal),
+            1 => (self.color_format,
+                  1,
+                  LoadOp::DontCare,
+                  StoreOp::Store,
+                  ImageLayout::ColorAttachmentOptimal),
+            2 => (self.depth_format,
+                  self.samples,
+                  LoadOp::Clear,
+                  StoreOp::DontCare,
+                  ImageLayout::DepthStencilAttachmentOptimal),
+            _ => return None,
+        };
+
+        Some(LayoutAttachmentDescription {
+                 format: format,
+                 samples: samples,
+                 load: load,
+                 store: store,
+                 stencil_load: load,
+                 stencil_store: store,
+                 initial_layout: ImageLayout::Undefined,
+                 final_layout: final_layout,
+             })
+    }
+
+    #[inline]
+    fn num_subpasses(&self) -> usize {
+        1
+    }
+
+    #[inline]
+    fn subpass_desc(&self, num: usize) -> Option<LayoutPassDescription> {
+        if num != 0 {
+            return None;
+        }
+
+        Some(LayoutPassDescription {
+                 color_attachments: vec![(0, ImageLayout::ColorAttachmentOptimal)],
+                 depth_stencil: Some((2, ImageLayout::DepthStencilAttachmentOptimal)),
+                 input_attachments: vec![],
+                 resolve_attachments: vec![(1, ImageLayout::ColorAttachmentOptimal)],
+                 preserve_attachments: vec![],
+             })
+    }
+
+    #[inline]
+    fn num_dependencies(&self) -> usize {
+        0
+    }
+
+    #[inline]
+    fn dependency_desc(&self, _: usize) -> Option<LayoutPassDependencyDescription> {
+        None
+    }
+}
+
+unsafe impl RenderPassDescClearValues<Vec<ClearValue>> for MsaaRenderPassDesc {
+    #[inline]
+    fn convert_clear_values(&self, values: Vec<ClearValue>) -> Box<Iterator<Item = ClearValue>> {
+        // The values are checked against the attachments when beginning the render pass.
+        Box::new(values.into_iter())
+    }
+}
+
+unsafe impl RenderPassDescClearValues<([f32; 4], f32)> for MsaaRenderPassDesc {
+    #[inline]
+    fn convert_clear_values(&self, (color, depth): ([f32; 4], f32))
+                            -> Box<Iterator<Item = ClearValue>> {
+        let depth = match self.depth_format.ty() {
+            FormatTy::DepthStencil => ClearValue::DepthStencil((depth, 0)),
+            _ => ClearValue::Depth(depth),
+        };
+
+        Box::new(vec![ClearValue::Float(color), ClearValue::None, depth].into_iter())
+    }
+}
+
+/// Framebuffer created by `MsaaAttachments::framebuffer`.
+pub type MsaaFramebuffer = Framebuffer<Arc<RenderPass<MsaaRenderPassDesc>>,
+                                       ((((), Arc<AttachmentImage>),
+                                         Arc<ImageViewAccess + Send + Sync>),
+                                        Arc<AttachmentImage>)>;
+
+/// Multisampled color and depth images, and the render pass that draws on them and resolves the
+/// result into a single-sampled image.
+///
+/// All the objects agree on the number of samples. Use `multisample` to obtain the multisample
+/// state to pass when creating a graphics pipeline for `subpass`.
+///
+/// # Example
+///
+/// ```ignore
+/// let msaa = MsaaAttachments::new(device.clone(), dimensions, 4, swapchain.format(),
+///                                 Format::D16Unorm)?;
+///
+/// let framebuffers = images.iter()
+///     .map(|image| msaa.framebuffer(image.clone()).map(Arc::new))
+///     .collect::<Result<Vec<_>, _>>()?;
+/// ```
+#[derive(Debug)]
+pub struct MsaaAttachments {
+    render_pass: Arc<RenderPass<MsaaRenderPassDesc>>,
+    color: Arc<AttachmentImage>,
+    depth: Arc<AttachmentImage>,
+}
+
+impl MsaaAttachments {
+    /// Creates the multisampled images and the render pass.
+    ///
+    /// The images are transient, as their content is only needed during the render pass.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `samples` is less than 2.
+    /// - Panics if `color_format` is not a color format or `depth_format` doesn't have a depth
+    ///   component.
+    ///
+    pub fn new(device: Arc<Device>, dimensions: [u32; 2], samples: u32, color_format: Format,
+               depth_format: Format)
+               -> Result<MsaaAttachments, MsaaAttachmentsCreationError> {
+        let desc = MsaaRenderPassDesc::new(color_format, depth_format, samples);
+        let render_pass = Arc::new(RenderPass::new(device.clone(), desc)?);
+        let (color, depth) = MsaaAttachments::create_images(&device, &desc, dimensions)?;
+
+        Ok(MsaaAttachments {
+               render_pass: render_pass,
+               color: color,
+               depth: depth,
+           })
+    }
+
+    fn create_images(device: &Arc<Device>, desc: &MsaaRenderPassDesc, dimensions: [u32; 2])
+                     -> Result<(Arc<AttachmentImage>, Arc<AttachmentImage>), ImageCreationError> {
+        let color = AttachmentImage::transient_multisampled(device.clone(),
+                                                            dimensions,
+                                                            desc.samples,
+                                                            desc.color_format)?;
+        let depth = AttachmentImage::transient_multisampled(device.clone(),
+                                                            dimensions,
+                                                            desc.samples,
+                                                            desc.depth_format)?;
+        Ok((color, depth))
+    }
+
+    /// Recreates the multisampled images with new dimensions, for example after the window has
+    /// been resized. The render pass is kept.
+    ///
+    /// The framebuffers that were created before must be created again.
+    pub fn resize(&mut self, dimensions: [u32; 2]) -> Result<(), ImageCreationError> {
+        let (color, depth) = MsaaAttachments::create_images(self.render_pass.device(),
+                                                            self.render_pass.desc(),
+                                                            dimensions)?;
+        self.color = color;
+        self.depth = depth;
+        Ok(())
+    }
+
+    /// Returns the render pass.
+    #[inline]
+    pub fn render_pass(&self) -> &Arc<RenderPass<MsaaRenderPassDesc>> {
+        &self.render_pass
+    }
+
+    /// Returns the only subpass of the render pass.
+    #[inline]
+    pub fn subpass(&self) -> Subpass<Arc<RenderPass<MsaaRenderPassDesc>>> {
+        Subpass::from(self.render_pass.clone(), 0).unwrap()
+    }
+
+    /// Returns the number of samples of the images.
+    #[inline]
+    pub fn samples(&self) -> u32 {
+        self.render_pass.desc().samples
+    }
+
+    /// Returns the multisample state to use for the graphics pipelines that draw in the subpass.
+    #[inline]
+    pub fn multisample(&self) -> Multisample {
+        Multisample {
+            rasterization_samples: self.samples(),
+            ..Multisample::disabled()
+        }
+    }
+
+    /// Returns the multisampled color image.
+    #[inline]
+    pub fn color(&self) -> &Arc<AttachmentImage> {
+        &self.color
+    }
+
+    /// Returns the multisampled depth image.
+    #[inline]
+    pub fn depth(&self) -> &Arc<AttachmentImage> {
+        &self.depth
+    }
+
+    /// Builds a framebuffer that resolves the color into `resolve_target`.
+    ///
+    /// The resolve target must have the same dimensions as the multisampled images, the same
+    /// format as the color image, and only one sample.
+    pub fn framebuffer<I>(&self, resolve_target: I)
+                          -> Result<MsaaFramebuffer, FramebufferCreationError>
+        where I: ImageViewAccess + Send + Sync + 'static
+    {
+        Framebuffer::start(self.render_pass.clone())
+            .add(self.color.clone())?
+            .add(Arc::new(resolve_target) as Arc<ImageViewAccess + Send + Sync>)?
+            .add(self.depth.clone())?
+            .build()
+    }
+}
+
+/// Error that can happen when creating a `MsaaAttachments`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MsaaAttachmentsCreationError {
+    /// Error while creating one of the images. This happens in particular if the number of
+    /// samples isn't supported by the implementation.
+    ImageCreationError(ImageCreationError),
+
+    /// Error while creating the render pass.
+    RenderPassCreationError(RenderPassCreationError),
+}
+
+impl error::Error for MsaaAttachmentsCreationError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            MsaaAttachmentsCreationError::ImageCreationError(_) => {
+                "error while creating one of the images"
+            },
+            MsaaAttachmentsCreationError::RenderPassCreationError(_) => {
+                "error while creating the render pass"
+            },
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            MsaaAttachmentsCreationError::ImageCreationError(ref err) => Some(err),
+            MsaaAttachmentsCreationError::RenderPassCreationError(ref err) => Some(err),
+        }
+    }
+}
+
+impl fmt::Display for MsaaAttachmentsCreationError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl From<ImageCreationError> for MsaaAttachmentsCreationError {
+    #[inline]
+    fn from(err: ImageCreationError) -> MsaaAttachmentsCreationError {
+        MsaaAttachmentsCreationError::ImageCreationError(err)
+    }
+}
+
+impl From<RenderPassCreationError> for MsaaAttachmentsCreationError {
+    #[inline]
+    fn from(err: RenderPassCreationError) -> MsaaAttachmentsCreationError {
+        MsaaAttachmentsCreationError::RenderPassCreationError(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use command_buffer::validity;
+    use format::ClearValue;
+    use format::Format;
+    use framebuffer::MsaaAttachments;
+    use framebuffer::MsaaRenderPassDesc;
+    use framebuffer::RenderPassDesc;
+    use framebuffer::RenderPassDescClearValues;
+    use image::AttachmentImage;
+
+    #[test]
+    fn desc_samples() {
+        let desc = MsaaRenderPassDesc::new(Format::R8G8B8A8Unorm, Format::D16Unorm, 4);
+
+        assert_eq!(desc.attachment_desc(0).unwrap().samples, 4);
+        assert_eq!(desc.attachment_desc(1).unwrap().samples, 1);
+        assert_eq!(desc.attachment_desc(2).unwrap().samples, 4);
+        assert_eq!(desc.num_samples(0), Some(4));
+
+        let subpass = desc.subpass_desc(0).unwrap();
+        assert_eq!(subpass.resolve_attachments.len(), subpass.color_attachments.len());
+    }
+
+    #[test]
+    #[should_panic]
+    fn single_sample() {
+        MsaaRenderPassDesc::new(Format::R8G8B8A8Unorm, Format::D16Unorm, 1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn depth_format_as_color() {
+        MsaaRenderPassDesc::new(Format::D16Unorm, Format::D16Unorm, 4);
+    }
+
+    #[test]
+    fn create_framebuffer() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let msaa = MsaaAttachments::new(device.clone(), [64, 64], 4, Format::R8G8B8A8Unorm,
+                                        Format::D16Unorm)
+            .unwrap();
+        assert_eq!(msaa.multisample().rasterization_samples, 4);
+        assert_eq!(msaa.subpass().num_samples(), Some(4));
+
+        let target = AttachmentImage::new(device, [64, 64], Format::R8G8B8A8Unorm).unwrap();
+        msaa.framebuffer(target).unwrap();
+    }
+
+    #[test]
+    fn clear_values() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let msaa = MsaaAttachments::new(device.clone(), [64, 64], 4, Format::R8G8B8A8Unorm,
+                                        Format::D16Unorm)
+            .unwrap();
+        let render_pass = msaa.render_pass();
+
+        let values = render_pass.convert_clear_values(([0.0, 0.0, 1.0, 1.0], 1.0))
+            .collect::<Vec<_>>();
+        validity::check_clear_values(render_pass, &values).unwrap();
+
+        let values = render_pass.convert_clear_values(vec![ClearValue::Float([0.0; 4])])
+            .collect::<Vec<_>>();
+        match validity::check_clear_values(render_pass, &values) {
+            Err(validity::CheckClearValuesError::WrongNumberOfClearValues {
+                    expected: 3,
+                    obtained: 1,
+                }) => (),
+            _ => panic!(),
+        }
+    }
+}
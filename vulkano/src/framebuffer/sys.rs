@@ -27,6 +27,7 @@ use framebuffer::LoadOp;
 use framebuffer::RenderPassAbstract;
 use framebuffer::RenderPassDesc;
 use framebuffer::RenderPassDescClearValues;
+use sync::PipelineStages;
 
 use Error;
 use OomError;
@@ -296,6 +297,12 @@ impl<D> RenderPass<D>
             out
         };
 
+        for dependency in description.dependency_descs() {
+            if dependency.source_subpass == dependency.destination_subpass {
+                check_self_dependency(&dependency)?;
+            }
+        }
+
         let dependencies = description
             .dependency_descs()
             .map(|dependency| {
@@ -347,7 +354,8 @@ impl<D> RenderPass<D>
             check_errors(vk.CreateRenderPass(device.internal_object(),
                                              &infos,
                                              ptr::null(),
-                                             &mut output))?;
+                                             &mut output),
+                         "vkCreateRenderPass")?;
             output
         };
 
@@ -502,6 +510,90 @@ unsafe impl<'a> VulkanObject for RenderPassSys<'a> {
     }
 }
 
+// Checks the restrictions that apply to a dependency of a subpass on itself.
+fn check_self_dependency(dependency: &LayoutPassDependencyDescription)
+                         -> Result<(), RenderPassCreationError> {
+    let subpass = dependency.source_subpass;
+
+    let (src_earliest, src_latest) = match graphics_stages_range(&dependency.src_stages) {
+        Some(r) => r,
+        None => return Err(RenderPassCreationError::SelfDependencyInvalidStages { subpass: subpass }),
+    };
+    let (dst_earliest, dst_latest) = match graphics_stages_range(&dependency.dst_stages) {
+        Some(r) => r,
+        None => return Err(RenderPassCreationError::SelfDependencyInvalidStages { subpass: subpass }),
+    };
+
+    // Framebuffer-space stages are ordered per pixel, and can therefore depend on each other in
+    // any order. Otherwise the source stages must come before the destination stages.
+    let all_framebuffer_space = is_framebuffer_space(src_earliest) &&
+        is_framebuffer_space(src_latest) && is_framebuffer_space(dst_earliest) &&
+        is_framebuffer_space(dst_latest);
+    if !all_framebuffer_space && src_latest > dst_earliest {
+        return Err(RenderPassCreationError::SelfDependencyInvalidStages { subpass: subpass });
+    }
+
+    if has_framebuffer_space_stage(&dependency.src_stages) &&
+        has_framebuffer_space_stage(&dependency.dst_stages) && !dependency.by_region
+    {
+        return Err(RenderPassCreationError::SelfDependencyNotByRegion { subpass: subpass });
+    }
+
+    if !dependency.src_access.is_compatible_with(&dependency.src_stages) ||
+        !dependency.dst_access.is_compatible_with(&dependency.dst_stages)
+    {
+        return Err(RenderPassCreationError::SelfDependencyAccessNotSupported { subpass: subpass });
+    }
+
+    Ok(())
+}
+
+// Returns the position in the graphics pipeline of the earliest and latest stages of `stages`,
+// or `None` if `stages` is empty or contains a stage that isn't part of the graphics pipeline.
+fn graphics_stages_range(stages: &PipelineStages) -> Option<(usize, usize)> {
+    if stages.compute_shader || stages.transfer || stages.host {
+        return None;
+    }
+
+    let ordered = [
+        stages.top_of_pipe,
+        stages.draw_indirect,
+        stages.vertex_input,
+        stages.vertex_shader,
+        stages.tessellation_control_shader,
+        stages.tessellation_evaluation_shader,
+        stages.geometry_shader,
+        stages.early_fragment_tests,
+        stages.fragment_shader,
+        stages.late_fragment_tests,
+        stages.color_attachment_output,
+        stages.bottom_of_pipe,
+    ];
+
+    if stages.all_graphics || stages.all_commands {
+        return Some((0, ordered.len() - 1));
+    }
+
+    match (ordered.iter().position(|&s| s), ordered.iter().rposition(|&s| s)) {
+        (Some(earliest), Some(latest)) => Some((earliest, latest)),
+        _ => None,
+    }
+}
+
+// Returns true if the stage at the given position in the graphics pipeline is a
+// framebuffer-space stage.
+#[inline]
+fn is_framebuffer_space(stage: usize) -> bool {
+    stage >= 7 && stage <= 10
+}
+
+// Returns true if `stages` contains at least one framebuffer-space stage.
+#[inline]
+fn has_framebuffer_space_stage(stages: &PipelineStages) -> bool {
+    stages.early_fragment_tests || stages.fragment_shader || stages.late_fragment_tests ||
+        stages.color_attachment_output || stages.all_graphics || stages.all_commands
+}
+
 /// Error that can happen when creating a compute pipeline.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum RenderPassCreationError {
@@ -509,6 +601,23 @@ pub enum RenderPassCreationError {
     OomError(OomError),
     /// The maximum number of color attachments has been exceeded.
     ColorAttachmentsLimitExceeded,
+    /// A self-dependency of a subpass has an empty stage mask or a stage that isn't part of the
+    /// graphics pipeline, or its source stages are not earlier than its destination stages.
+    SelfDependencyInvalidStages {
+        /// Index of the subpass.
+        subpass: usize,
+    },
+    /// A self-dependency of a subpass involves framebuffer-space stages on both sides, but
+    /// `by_region` is false.
+    SelfDependencyNotByRegion {
+        /// Index of the subpass.
+        subpass: usize,
+    },
+    /// A self-dependency of a subpass has an access that isn't supported by its stages.
+    SelfDependencyAccessNotSupported {
+        /// Index of the subpass.
+        subpass: usize,
+    },
 }
 
 impl error::Error for RenderPassCreationError {
@@ -519,6 +628,16 @@ impl error::Error for RenderPassCreationError {
             RenderPassCreationError::ColorAttachmentsLimitExceeded => {
                 "the maximum number of color attachments has been exceeded"
             },
+            RenderPassCreationError::SelfDependencyInvalidStages { .. } => {
+                "a self-dependency of a subpass has invalid stages"
+            },
+            RenderPassCreationError::SelfDependencyNotByRegion { .. } => {
+                "a self-dependency of a subpass involves framebuffer-space stages but isn't by \
+                 region"
+            },
+            RenderPassCreationError::SelfDependencyAccessNotSupported { .. } => {
+                "a self-dependency of a subpass has an access that isn't supported by its stages"
+            },
         }
     }
 
@@ -562,9 +681,19 @@ impl From<Error> for RenderPassCreationError {
 
 #[cfg(test)]
 mod tests {
+    use format::ClearValue;
     use format::Format;
+    use framebuffer::LayoutPassDependencyDescription;
+    use framebuffer::LoadOp;
     use framebuffer::RenderPass;
     use framebuffer::RenderPassCreationError;
+    use framebuffer::RenderPassDesc;
+    use framebuffer::RenderPassDescClearValues;
+    use framebuffer::StoreOp;
+    use image::ImageLayout;
+    use sync::AccessFlagBits;
+    use sync::PipelineStages;
+    use super::check_self_dependency;
 
     #[test]
     fn empty() {
@@ -626,4 +755,131 @@ mod tests {
         assert_ne!(granularity[0], 0);
         assert_ne!(granularity[1], 0);
     }
+    #[test]
+    fn separate_stencil_ops() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let rp = single_pass_renderpass! {
+            device.clone(),
+            attachments: {
+                a: {
+                    load: Load,
+                    store: DontCare,
+                    stencil_load: Clear,
+                    stencil_store: Store,
+                    format: Format::D24Unorm_S8Uint,
+                    samples: 1,
+                }
+            },
+            pass: {
+                color: [],
+                depth_stencil: {a}
+            }
+        }.unwrap();
+
+        let desc = rp.attachment_desc(0).unwrap();
+        assert_eq!(desc.load, LoadOp::Load);
+        assert_eq!(desc.store, StoreOp::DontCare);
+        assert_eq!(desc.stencil_load, LoadOp::Clear);
+        assert_eq!(desc.stencil_store, StoreOp::Store);
+
+        // Only the stencil component is cleared.
+        assert!(desc.is_valid_clear_value(&ClearValue::Stencil(0)));
+        assert!(desc.is_valid_clear_value(&ClearValue::DepthStencil((1.0, 0))));
+        assert!(!desc.is_valid_clear_value(&ClearValue::Depth(1.0)));
+        assert!(!desc.is_valid_clear_value(&ClearValue::None));
+    }
+
+    #[test]
+    fn clear_values_check() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let rp = single_pass_renderpass! {
+            device.clone(),
+            attachments: {
+                a: { load: Clear, store: Store, format: Format::R8G8B8A8Unorm, samples: 1, },
+                b: { load: Clear, store: DontCare, format: Format::D16Unorm, samples: 1, }
+            },
+            pass: {
+                color: [a],
+                depth_stencil: {b}
+            }
+        }.unwrap();
+
+        let valid = vec![[0.0, 0.0, 1.0, 1.0].into(), 1.0f32.into()];
+        assert_eq!(rp.convert_clear_values(valid).count(), 2);
+
+        let desc = rp.attachment_desc(1).unwrap();
+        assert!(!desc.is_valid_clear_value(&ClearValue::Float([1.0; 4])));
+        assert!(!desc.is_valid_clear_value(&ClearValue::Stencil(0)));
+    }
+
+    #[test]
+    fn self_dependency() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let rp = ordered_passes_renderpass! {
+            device.clone(),
+            attachments: {
+                a: { load: Clear, store: Store, format: Format::R8G8B8A8Unorm, samples: 1, }
+            },
+            passes: [
+                {
+                    color: [a],
+                    depth_stencil: {},
+                    input: [a],
+                    self_dependency: true
+                }
+            ]
+        }.unwrap();
+
+        assert_eq!(rp.num_dependencies(), 1);
+        let dependency = rp.dependency_desc(0).unwrap();
+        assert_eq!(dependency.source_subpass, 0);
+        assert_eq!(dependency.destination_subpass, 0);
+        assert!(dependency.by_region);
+
+        let subpass = rp.subpass_desc(0).unwrap();
+        assert_eq!(subpass.color_attachments, vec![(0, ImageLayout::General)]);
+        assert_eq!(subpass.input_attachments, vec![(0, ImageLayout::General)]);
+
+        let attachment = rp.attachment_desc(0).unwrap();
+        assert_eq!(attachment.initial_layout, ImageLayout::Undefined);
+        assert_eq!(attachment.final_layout, ImageLayout::General);
+    }
+
+    #[test]
+    fn self_dependency_checks() {
+        let valid = LayoutPassDependencyDescription {
+            source_subpass: 1,
+            destination_subpass: 1,
+            src_stages: PipelineStages { color_attachment_output: true, .. PipelineStages::none() },
+            dst_stages: PipelineStages { fragment_shader: true, .. PipelineStages::none() },
+            src_access: AccessFlagBits { color_attachment_write: true, .. AccessFlagBits::none() },
+            dst_access: AccessFlagBits { input_attachment_read: true, .. AccessFlagBits::none() },
+            by_region: true,
+        };
+        assert_eq!(check_self_dependency(&valid), Ok(()));
+
+        let mut dependency = valid.clone();
+        dependency.by_region = false;
+        assert_eq!(check_self_dependency(&dependency),
+                   Err(RenderPassCreationError::SelfDependencyNotByRegion { subpass: 1 }));
+
+        let mut dependency = valid.clone();
+        dependency.dst_stages = PipelineStages { vertex_shader: true, .. PipelineStages::none() };
+        dependency.dst_access = AccessFlagBits { shader_read: true, .. AccessFlagBits::none() };
+        assert_eq!(check_self_dependency(&dependency),
+                   Err(RenderPassCreationError::SelfDependencyInvalidStages { subpass: 1 }));
+
+        let mut dependency = valid.clone();
+        dependency.src_stages = PipelineStages { transfer: true, .. PipelineStages::none() };
+        assert_eq!(check_self_dependency(&dependency),
+                   Err(RenderPassCreationError::SelfDependencyInvalidStages { subpass: 1 }));
+
+        let mut dependency = valid.clone();
+        dependency.dst_access = AccessFlagBits { host_read: true, .. AccessFlagBits::none() };
+        assert_eq!(check_self_dependency(&dependency),
+                   Err(RenderPassCreationError::SelfDependencyAccessNotSupported { subpass: 1 }));
+    }
 }
@@ -10,8 +10,10 @@
 use device::DeviceOwned;
 use format::ClearValue;
 use framebuffer::FramebufferSys;
+use framebuffer::IncompatibleRenderPassError;
 use framebuffer::RenderPassDesc;
 use framebuffer::RenderPassSys;
+use framebuffer::ensure_render_pass_compatible;
 use image::ImageViewAccess;
 use pipeline::shader::ShaderInterfaceDef;
 
@@ -220,25 +222,23 @@ pub unsafe trait RenderPassCompatible<Other: ?Sized>: RenderPassDesc
 {
     /// Returns `true` if this layout is compatible with the other layout, as defined in the
     /// `Render Pass Compatibility` section of the Vulkan specs.
-    // TODO: return proper error
-    fn is_compatible_with(&self, other: &Other) -> bool;
+    #[inline]
+    fn is_compatible_with(&self, other: &Other) -> bool {
+        self.ensure_compatible_with(other).is_ok()
+    }
+
+    /// Same as `is_compatible_with`, but returns an error that describes the first difference
+    /// between the two layouts.
+    fn ensure_compatible_with(&self, other: &Other) -> Result<(), IncompatibleRenderPassError>;
 }
 
 unsafe impl<A, B: ?Sized> RenderPassCompatible<B> for A
     where A: RenderPassDesc,
           B: RenderPassDesc
 {
-    fn is_compatible_with(&self, other: &B) -> bool {
-        // FIXME:
-        /*for (atch1, atch2) in (&self).attachments().zip(other.attachments()) {
-            if !atch1.is_compatible_with(&atch2) {
-                return false;
-            }
-        }*/
-
-        return true;
-
-        // FIXME: finish
+    #[inline]
+    fn ensure_compatible_with(&self, other: &B) -> Result<(), IncompatibleRenderPassError> {
+        ensure_render_pass_compatible(self, other)
     }
 }
 
@@ -271,6 +271,16 @@ impl<L> Subpass<L>
         }
     }
 
+    // Same as `from`, but doesn't check that `id` is in range. The other methods panic if it
+    // isn't, so the index must be checked before they are called.
+    #[inline]
+    pub(crate) fn from_unchecked(render_pass: L, id: u32) -> Subpass<L> {
+        Subpass {
+            render_pass: render_pass,
+            subpass_id: id,
+        }
+    }
+
     /// Returns the number of color attachments in this subpass.
     #[inline]
     pub fn num_color_attachments(&self) -> u32 {
@@ -26,6 +26,7 @@ use image::ImageLayout;
 use image::ImageUsage;
 use image::ViewType;
 use image::sys::ImageCreationError;
+use image::sys::ImageCreationOptions;
 use image::sys::UnsafeImage;
 use image::sys::UnsafeImageView;
 use image::traits::ImageAccess;
@@ -42,9 +43,11 @@ use sync::Sharing;
 /// ImageAccess whose purpose is to be used as a framebuffer attachment.
 ///
 /// The image is always two-dimensional and has only one mipmap, but it can have any kind of
-/// format. Trying to use a format that the backend doesn't support for rendering will result in
-/// an error being returned when creating the image. Once you have an `AttachmentImage`, you are
-/// guaranteed that you will be able to draw on it.
+/// format. It can also have multiple array layers, in which case it can be used for layered
+/// rendering by creating a framebuffer with multiple layers. Trying to use a format that the
+/// backend doesn't support for rendering will result in an error being returned when creating
+/// the image. Once you have an `AttachmentImage`, you are guaranteed that you will be able to
+/// draw on it.
 ///
 /// The template parameter of `AttachmentImage` is a type that describes the format of the image.
 ///
@@ -100,7 +103,7 @@ impl<F> AttachmentImage<F> {
                -> Result<Arc<AttachmentImage<F>>, ImageCreationError>
         where F: FormatDesc
     {
-        AttachmentImage::new_impl(device, dimensions, format, ImageUsage::none(), 1)
+        AttachmentImage::new_impl(device, dimensions, 1, format, ImageUsage::none(), 1)
     }
 
     /// Same as `new`, but creates a multisampled image.
@@ -112,7 +115,7 @@ impl<F> AttachmentImage<F> {
                         -> Result<Arc<AttachmentImage<F>>, ImageCreationError>
         where F: FormatDesc
     {
-        AttachmentImage::new_impl(device, dimensions, format, ImageUsage::none(), samples)
+        AttachmentImage::new_impl(device, dimensions, 1, format, ImageUsage::none(), samples)
     }
 
     /// Same as `new`, but lets you specify additional usages.
@@ -121,7 +124,7 @@ impl<F> AttachmentImage<F> {
                       -> Result<Arc<AttachmentImage<F>>, ImageCreationError>
         where F: FormatDesc
     {
-        AttachmentImage::new_impl(device, dimensions, format, usage, 1)
+        AttachmentImage::new_impl(device, dimensions, 1, format, usage, 1)
     }
 
     /// Same as `with_usage`, but creates a multisampled image.
@@ -134,7 +137,40 @@ impl<F> AttachmentImage<F> {
                                    -> Result<Arc<AttachmentImage<F>>, ImageCreationError>
         where F: FormatDesc
     {
-        AttachmentImage::new_impl(device, dimensions, format, usage, samples)
+        AttachmentImage::new_impl(device, dimensions, 1, format, usage, samples)
+    }
+
+    /// Same as `new`, but creates an image with multiple array layers.
+    ///
+    /// This is useful for layered rendering, for example to draw all the cascades of a shadow map
+    /// or all the faces of a cube shadow map in a single pass. The geometry shader selects the
+    /// layer to draw to by writing to `gl_Layer`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `array_layers` is 0.
+    ///
+    #[inline]
+    pub fn array(device: Arc<Device>, dimensions: [u32; 2], array_layers: u32, format: F)
+                 -> Result<Arc<AttachmentImage<F>>, ImageCreationError>
+        where F: FormatDesc
+    {
+        AttachmentImage::new_impl(device, dimensions, array_layers, format, ImageUsage::none(), 1)
+    }
+
+    /// Same as `array`, but lets you specify additional usages.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `array_layers` is 0.
+    ///
+    #[inline]
+    pub fn array_with_usage(device: Arc<Device>, dimensions: [u32; 2], array_layers: u32,
+                            format: F, usage: ImageUsage)
+                            -> Result<Arc<AttachmentImage<F>>, ImageCreationError>
+        where F: FormatDesc
+    {
+        AttachmentImage::new_impl(device, dimensions, array_layers, format, usage, 1)
     }
 
     /// Same as `new`, except that the image will be transient.
@@ -151,7 +187,7 @@ impl<F> AttachmentImage<F> {
             ..ImageUsage::none()
         };
 
-        AttachmentImage::new_impl(device, dimensions, format, base_usage, 1)
+        AttachmentImage::new_impl(device, dimensions, 1, format, base_usage, 1)
     }
 
     /// Same as `transient`, but creates a multisampled image.
@@ -169,14 +205,15 @@ impl<F> AttachmentImage<F> {
             ..ImageUsage::none()
         };
 
-        AttachmentImage::new_impl(device, dimensions, format, base_usage, samples)
+        AttachmentImage::new_impl(device, dimensions, 1, format, base_usage, samples)
     }
 
-    fn new_impl(device: Arc<Device>, dimensions: [u32; 2], format: F, base_usage: ImageUsage,
-                samples: u32)
+    fn new_impl(device: Arc<Device>, dimensions: [u32; 2], array_layers: u32, format: F,
+                base_usage: ImageUsage, samples: u32)
                 -> Result<Arc<AttachmentImage<F>>, ImageCreationError>
         where F: FormatDesc
     {
+        assert!(array_layers >= 1);
         // TODO: check dimensions against the max_framebuffer_width/height/layers limits
 
         let is_depth = match format.format().ty() {
@@ -197,7 +234,7 @@ impl<F> AttachmentImage<F> {
             let dims = ImageDimensions::Dim2d {
                 width: dimensions[0],
                 height: dimensions[1],
-                array_layers: 1,
+                array_layers: array_layers,
                 cubemap_compatible: false,
             };
 
@@ -208,8 +245,7 @@ impl<F> AttachmentImage<F> {
                              samples,
                              1,
                              Sharing::Exclusive::<Empty<u32>>,
-                             false,
-                             false)?
+                             ImageCreationOptions::none())?
         };
 
         let mem_ty = {
@@ -235,7 +271,13 @@ impl<F> AttachmentImage<F> {
             image.bind_memory(mem.memory(), mem.offset())?;
         }
 
-        let view = unsafe { UnsafeImageView::raw(&image, ViewType::Dim2d, 0 .. 1, 0 .. 1)? };
+        let view_ty = if array_layers == 1 {
+            ViewType::Dim2d
+        } else {
+            ViewType::Dim2dArray
+        };
+
+        let view = unsafe { UnsafeImageView::raw(&image, view_ty, 0 .. 1, 0 .. array_layers)? };
 
         Ok(Arc::new(AttachmentImage {
                         image: image,
@@ -259,6 +301,12 @@ impl<F, A> AttachmentImage<F, A> {
         let dims = self.image.dimensions();
         [dims.width(), dims.height()]
     }
+
+    /// Returns the number of array layers of the image.
+    #[inline]
+    pub fn array_layers(&self) -> u32 {
+        self.image.dimensions().array_layers()
+    }
 }
 
 unsafe impl<F, A> ImageAccess for AttachmentImage<F, A>
@@ -285,11 +333,6 @@ unsafe impl<F, A> ImageAccess for AttachmentImage<F, A>
         self.attachment_layout
     }
 
-    #[inline]
-    fn conflict_key(&self, _: u32, _: u32, _: u32, _: u32) -> u64 {
-        self.image.key()
-    }
-
     #[inline]
     fn try_gpu_lock(&self, _: bool, _: &Queue) -> Result<(), AccessError> {
         if self.gpu_lock.compare_and_swap(0, 1, Ordering::SeqCst) == 0 {
@@ -341,9 +384,18 @@ unsafe impl<F, A> ImageViewAccess for AttachmentImage<F, A>
     #[inline]
     fn dimensions(&self) -> Dimensions {
         let dims = self.image.dimensions();
-        Dimensions::Dim2d {
-            width: dims.width(),
-            height: dims.height(),
+
+        if dims.array_layers() == 1 {
+            Dimensions::Dim2d {
+                width: dims.width(),
+                height: dims.height(),
+            }
+        } else {
+            Dimensions::Dim2dArray {
+                width: dims.width(),
+                height: dims.height(),
+                array_layers: dims.array_layers(),
+            }
         }
     }
 
@@ -395,6 +447,13 @@ mod tests {
         let _img = AttachmentImage::transient(device, [32, 32], Format::R8G8B8A8Unorm).unwrap();
     }
 
+    #[test]
+    fn create_array() {
+        let (device, _) = gfx_dev_and_queue!();
+        let img = AttachmentImage::array(device, [32, 32], 6, Format::D16Unorm).unwrap();
+        assert_eq!(img.array_layers(), 6);
+    }
+
     #[test]
     fn d16_unorm_always_supported() {
         let (device, _) = gfx_dev_and_queue!();
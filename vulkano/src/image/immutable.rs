@@ -8,9 +8,26 @@
 // according to those terms.
 
 use smallvec::SmallVec;
+use std::error;
+use std::fmt;
+use std::iter;
 use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
 
+use OomError;
+use buffer::BufferAccess;
+use buffer::BufferUsage;
+use buffer::CpuAccessibleBuffer;
+use command_buffer::AutoCommandBuffer;
+use command_buffer::AutoCommandBufferBuilder;
+use command_buffer::BuildError;
+use command_buffer::CommandBuffer;
+use command_buffer::CommandBufferExecError;
+use command_buffer::CommandBufferExecFuture;
+use command_buffer::CopyBufferToImageError;
 use device::Device;
+use device::DeviceOwned;
 use device::Queue;
 use format::Format;
 use format::FormatDesc;
@@ -21,6 +38,9 @@ use image::ImageLayout;
 use image::ImageUsage;
 use image::MipmapsCount;
 use image::sys::ImageCreationError;
+use image::sys::ImageCreationOptions;
+use image::sys::ImageViewFormatError;
+use image::sys::ImageViewMinLodError;
 use image::sys::UnsafeImage;
 use image::sys::UnsafeImageView;
 use image::traits::ImageAccess;
@@ -32,10 +52,17 @@ use memory::pool::MemoryPool;
 use memory::pool::MemoryPoolAlloc;
 use memory::pool::StdMemoryPool;
 use sync::AccessError;
+use sync::NowFuture;
 use sync::Sharing;
 
 /// Image whose purpose is to be used for read-only purposes. You can write to the image once,
 /// but then you must only ever read from it. TODO: clarify because of blit operations
+///
+/// The initial content of the image is written through the `ImmutableImageInitialization` that
+/// is returned alongside it. Until the command buffer that performs this upload has finished
+/// executing, the image can't be put in a descriptor set and can't be used by a command buffer,
+/// except after the upload in the same command buffer. Trying to do so returns an
+/// `UninitializedResource` error.
 // TODO: type (2D, 3D, array, etc.) as template parameter
 #[derive(Debug)]
 pub struct ImmutableImage<F, A = Arc<StdMemoryPool>>
@@ -46,10 +73,19 @@ pub struct ImmutableImage<F, A = Arc<StdMemoryPool>>
     dimensions: Dimensions,
     memory: A::Alloc,
     format: F,
+    // True if the `ImmutableImageInitialization` object was used by the GPU then unlocked.
+    initialized: AtomicBool,
 }
 
+// TODO: make this prettier
+type ImmutableImageFromBufferFuture = CommandBufferExecFuture<NowFuture, AutoCommandBuffer>;
+
 impl<F> ImmutableImage<F> {
     /// Builds a new immutable image.
+    ///
+    /// The content of the image is undefined and the image isn't tracked as uninitialized. It is
+    /// up to you to fill it before reading from it. Prefer `from_iter`, `from_buffer` or
+    /// `uninitialized`, which check that the image isn't used before its initial upload.
     // TODO: one mipmap is probably not a great default
     #[inline]
     pub fn new<'a, I>(device: Arc<Device>, dimensions: Dimensions, format: F, queue_families: I)
@@ -65,12 +101,132 @@ impl<F> ImmutableImage<F> {
     }
 
     /// Builds a new immutable image with the given number of mipmaps.
+    ///
+    /// Same remarks as `new` about the content of the image.
     pub fn with_mipmaps<'a, I, M>(device: Arc<Device>, dimensions: Dimensions, format: F,
                                   mipmaps: M, queue_families: I)
                                   -> Result<Arc<ImmutableImage<F>>, ImageCreationError>
         where F: FormatDesc,
               I: IntoIterator<Item = QueueFamily<'a>>,
               M: Into<MipmapsCount>
+    {
+        unsafe {
+            let (image, _) = ImmutableImage::uninitialized(device,
+                                                           dimensions,
+                                                           format,
+                                                           mipmaps,
+                                                           queue_families)?;
+            // These images weren't tracked before the initialization checks were introduced.
+            image.initialized.store(true, Ordering::Relaxed);
+            Ok(image)
+        }
+    }
+
+    /// Builds an `ImmutableImage` from some pixels.
+    ///
+    /// This function builds a memory-mapped intermediate buffer, writes the pixels to it, builds
+    /// a command buffer that copies from this intermediate buffer to the image, and finally
+    /// submits the command buffer as a future.
+    ///
+    /// This function returns two objects: the newly-created image, and a future representing
+    /// the initial upload operation. In order to be allowed to use the `ImmutableImage`, you must
+    /// execute this future and wait for it to be finished.
+    pub fn from_iter<'a, P, I, J>(data: I, dimensions: Dimensions, format: F, queue_families: J,
+                                  queue: Arc<Queue>)
+                                  -> Result<(Arc<ImmutableImage<F>>,
+                                             ImmutableImageFromBufferFuture),
+                                            ImmutableImageUploadError>
+        where P: Send + Sync + 'static,
+              I: ExactSizeIterator<Item = P>,
+              J: IntoIterator<Item = QueueFamily<'a>>,
+              F: FormatDesc + 'static + Send + Sync
+    {
+        let source = CpuAccessibleBuffer::from_iter(queue.device().clone(),
+                                                    BufferUsage::transfer_source(),
+                                                    iter::once(queue.family()),
+                                                    data)?;
+        ImmutableImage::from_buffer(source, dimensions, format, queue_families, queue)
+    }
+
+    /// Builds an `ImmutableImage` that copies its pixels from a buffer.
+    ///
+    /// This function returns two objects: the newly-created image, and a future representing
+    /// the initial upload operation. In order to be allowed to use the `ImmutableImage`, you must
+    /// execute this future and wait for it to be finished.
+    pub fn from_buffer<'a, B, I>(source: B, dimensions: Dimensions, format: F, queue_families: I,
+                                 queue: Arc<Queue>)
+                                 -> Result<(Arc<ImmutableImage<F>>,
+                                            ImmutableImageFromBufferFuture),
+                                           ImmutableImageUploadError>
+        where B: BufferAccess + 'static + Send + Sync,
+              I: IntoIterator<Item = QueueFamily<'a>>,
+              F: FormatDesc + 'static + Send + Sync
+    {
+        ImmutableImage::from_buffer_impl(source, dimensions, format, queue_families, queue, false)
+    }
+
+    fn from_buffer_impl<'a, B, I>(source: B, dimensions: Dimensions, format: F,
+                                  queue_families: I, queue: Arc<Queue>, mutable_format: bool)
+                                  -> Result<(Arc<ImmutableImage<F>>,
+                                             ImmutableImageFromBufferFuture),
+                                            ImmutableImageUploadError>
+        where B: BufferAccess + 'static + Send + Sync,
+              I: IntoIterator<Item = QueueFamily<'a>>,
+              F: FormatDesc + 'static + Send + Sync
+    {
+        unsafe {
+            let (image, init) = ImmutableImage::uninitialized_impl(source.device().clone(),
+                                                                   dimensions,
+                                                                   format,
+                                                                   MipmapsCount::One,
+                                                                   queue_families,
+                                                                   mutable_format)?;
+
+            let cb = AutoCommandBufferBuilder::new(source.device().clone(), queue.family())?
+                .copy_buffer_to_image(source, init)?
+                .build()?;
+
+            let future = cb.execute(queue)?;
+            Ok((image, future))
+        }
+    }
+
+    /// Builds a new immutable image with the given number of mipmaps and uninitialized content.
+    ///
+    /// Returns two things: the image, and a special access that should be used for the initial
+    /// upload to the image.
+    ///
+    /// You will get an error if you try to use the image before the initial upload access has
+    /// been used. However this function doesn't check whether you actually used this initial
+    /// upload to fill the image like you're supposed to do.
+    ///
+    /// # Safety
+    ///
+    /// - The `ImmutableImageInitialization` should be used to fill the image with some initial
+    ///   data, otherwise the content is undefined.
+    ///
+    pub unsafe fn uninitialized<'a, I, M>(device: Arc<Device>, dimensions: Dimensions, format: F,
+                                          mipmaps: M, queue_families: I)
+                                          -> Result<(Arc<ImmutableImage<F>>,
+                                                     ImmutableImageInitialization<F>),
+                                                    ImageCreationError>
+        where F: FormatDesc,
+              I: IntoIterator<Item = QueueFamily<'a>>,
+              M: Into<MipmapsCount>
+    {
+        ImmutableImage::uninitialized_impl(device, dimensions, format, mipmaps, queue_families,
+                                           false)
+    }
+
+    unsafe fn uninitialized_impl<'a, I, M>(device: Arc<Device>, dimensions: Dimensions,
+                                           format: F, mipmaps: M, queue_families: I,
+                                           mutable_format: bool)
+                                           -> Result<(Arc<ImmutableImage<F>>,
+                                                      ImmutableImageInitialization<F>),
+                                                     ImageCreationError>
+        where F: FormatDesc,
+              I: IntoIterator<Item = QueueFamily<'a>>,
+              M: Into<MipmapsCount>
     {
         let usage = ImageUsage {
             transfer_source: true, // for blits
@@ -84,7 +240,7 @@ impl<F> ImmutableImage<F> {
             .map(|f| f.id())
             .collect::<SmallVec<[u32; 4]>>();
 
-        let (image, mem_reqs) = unsafe {
+        let (image, mem_reqs) = {
             let sharing = if queue_families.len() >= 2 {
                 Sharing::Concurrent(queue_families.iter().cloned())
             } else {
@@ -98,8 +254,10 @@ impl<F> ImmutableImage<F> {
                              1,
                              mipmaps,
                              sharing,
-                             false,
-                             false)?
+                             ImageCreationOptions {
+                                 mutable_format: mutable_format,
+                                 ..ImageCreationOptions::none()
+                             })?
         };
 
         let mem_ty = {
@@ -121,27 +279,86 @@ impl<F> ImmutableImage<F> {
                                     mem_reqs.alignment,
                                     AllocLayout::Optimal)?;
         debug_assert!((mem.offset() % mem_reqs.alignment) == 0);
-        unsafe {
-            image.bind_memory(mem.memory(), mem.offset())?;
-        }
+        image.bind_memory(mem.memory(), mem.offset())?;
 
-        let view = unsafe {
-            UnsafeImageView::raw(&image,
-                                 dimensions.to_view_type(),
-                                 0 .. image.mipmap_levels(),
-                                 0 .. image.dimensions().array_layers())?
+        let view = UnsafeImageView::raw(&image,
+                                        dimensions.to_view_type(),
+                                        0 .. image.mipmap_levels(),
+                                        0 .. image.dimensions().array_layers())?;
+
+        let final_image = Arc::new(ImmutableImage {
+                                       image: image,
+                                       view: view,
+                                       memory: mem,
+                                       dimensions: dimensions,
+                                       format: format,
+                                       initialized: AtomicBool::new(false),
+                                   });
+
+        let initialization = ImmutableImageInitialization {
+            image: final_image.clone(),
+            used: Arc::new(AtomicBool::new(false)),
         };
 
-        Ok(Arc::new(ImmutableImage {
-                        image: image,
-                        view: view,
-                        memory: mem,
-                        dimensions: dimensions,
-                        format: format,
-                    }))
+        Ok((final_image, initialization))
+    }
+}
+
+impl ImmutableImage<Format> {
+    /// Same as `from_iter`, but chooses between the sRGB and the `Unorm` variant of `format`
+    /// depending on whether the pixels are already sRGB-encoded.
+    ///
+    /// If `srgb_encoded` is true, the image uses the sRGB equivalent of `format` so that
+    /// sampling it returns linear values. Otherwise it uses the `Unorm` equivalent of `format`,
+    /// or `format` itself if it has no sRGB equivalent. The returned `ColorEncoding` tells which
+    /// decision was made.
+    ///
+    /// If `format` has an sRGB equivalent, the image is created with a mutable format so that
+    /// `format_view` can view it with the other format of the pair.
+    pub fn from_iter_with_encoding<'a, P, I, J>(data: I, dimensions: Dimensions, format: Format,
+                                                srgb_encoded: bool, queue_families: J,
+                                                queue: Arc<Queue>)
+                                                -> Result<(Arc<ImmutableImage<Format>>,
+                                                           ColorEncoding,
+                                                           ImmutableImageFromBufferFuture),
+                                                          ImmutableImageUploadError>
+        where P: Send + Sync + 'static,
+              I: ExactSizeIterator<Item = P>,
+              J: IntoIterator<Item = QueueFamily<'a>>
+    {
+        let (format, encoding) = match (srgb_encoded, format.srgb_equivalent()) {
+            (true, Some(srgb)) => (srgb, ColorEncoding::Srgb),
+            (true, None) => (format, ColorEncoding::UndecodedSrgb),
+            (false, _) => (format.unorm_equivalent().unwrap_or(format), ColorEncoding::Linear),
+        };
+
+        let source = CpuAccessibleBuffer::from_iter(queue.device().clone(),
+                                                    BufferUsage::transfer_source(),
+                                                    iter::once(queue.family()),
+                                                    data)?;
+        let mutable_format = format.srgb_equivalent().is_some();
+        let (image, future) = ImmutableImage::from_buffer_impl(source, dimensions, format,
+                                                               queue_families, queue,
+                                                               mutable_format)?;
+        Ok((image, encoding, future))
     }
 }
 
+/// How the pixels given to `ImmutableImage::from_iter_with_encoding` are stored.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ColorEncoding {
+    /// The pixels are linear and the image has a format that isn't an sRGB format.
+    Linear,
+
+    /// The pixels are sRGB-encoded and the image has an sRGB format. Sampling the image returns
+    /// linear values.
+    Srgb,
+
+    /// The pixels are sRGB-encoded, but the requested format has no sRGB equivalent. Sampling the
+    /// image returns the sRGB-encoded values, that shaders must convert themselves.
+    UndecodedSrgb,
+}
+
 impl<F, A> ImmutableImage<F, A>
     where A: MemoryPool
 {
@@ -156,6 +373,52 @@ impl<F, A> ImmutableImage<F, A>
     pub fn mipmap_levels(&self) -> u32 {
         self.image.mipmap_levels()
     }
+
+    /// Builds a view of the whole image whose sampling is clamped to `min_lod`. The mipmap
+    /// levels below `min_lod` are never sampled through this view, which makes it possible to
+    /// stream the content of a texture starting from its smallest levels.
+    ///
+    /// Requires the `VK_EXT_image_view_min_lod` extension. `min_lod` must be between 0 and the
+    /// index of the last mipmap level of the image.
+    ///
+    /// The view can be used in a descriptor set like the image itself. Since its parent is the
+    /// `ImmutableImage`, accesses through the view are checked against the image.
+    pub fn min_lod_view(me: &Arc<ImmutableImage<F, A>>, min_lod: f32)
+                        -> Result<Arc<ImmutableImageView<F, A>>, ImageViewMinLodError> {
+        let view = unsafe {
+            UnsafeImageView::with_min_lod(&me.image,
+                                          me.dimensions.to_view_type(),
+                                          0 .. me.image.mipmap_levels(),
+                                          0 .. me.image.dimensions().array_layers(),
+                                          min_lod)?
+        };
+
+        Ok(Arc::new(ImmutableImageView {
+                        image: me.clone(),
+                        view: view,
+                    }))
+    }
+
+    /// Builds a view of the whole image that interprets its content with `format`.
+    ///
+    /// Unless `format` is the format of the image, the image must have been created with a
+    /// mutable format, for example by `from_iter_with_encoding`, and `format` must be compatible
+    /// with the format of the image. See `UnsafeImageView::with_format`.
+    pub fn format_view(me: &Arc<ImmutableImage<F, A>>, format: Format)
+                       -> Result<Arc<ImmutableImageView<F, A>>, ImageViewFormatError> {
+        let view = unsafe {
+            UnsafeImageView::with_format(&me.image,
+                                         format,
+                                         me.dimensions.to_view_type(),
+                                         0 .. me.image.mipmap_levels(),
+                                         0 .. me.image.dimensions().array_layers())?
+        };
+
+        Ok(Arc::new(ImmutableImageView {
+                        image: me.clone(),
+                        view: view,
+                    }))
+    }
 }
 
 unsafe impl<F, A> ImageAccess for ImmutableImage<F, A>
@@ -184,13 +447,19 @@ unsafe impl<F, A> ImageAccess for ImmutableImage<F, A>
     }
 
     #[inline]
-    fn conflict_key(&self, _: u32, _: u32, _: u32, _: u32) -> u64 {
-        self.image.key()
+    fn initialized(&self) -> bool {
+        self.initialized.load(Ordering::Relaxed)
     }
 
     #[inline]
     fn try_gpu_lock(&self, exclusive_access: bool, queue: &Queue) -> Result<(), AccessError> {
-        Ok(()) // FIXME:
+        if !self.initialized.load(Ordering::Relaxed) {
+            return Err(AccessError::ImageNotInitialized {
+                           requested: ImageLayout::ShaderReadOnlyOptimal,
+                       });
+        }
+
+        Ok(()) // FIXME: forbid exclusive access
     }
 
     #[inline]
@@ -258,3 +527,526 @@ unsafe impl<F: 'static, A> ImageViewAccess for ImmutableImage<F, A>
         true
     }
 }
+
+/// A view of an `ImmutableImage` whose sampling is clamped to a minimum LOD, or that has another
+/// format than the image.
+///
+/// Created with `ImmutableImage::min_lod_view` or `ImmutableImage::format_view`. The view keeps
+/// the image alive, and is tracked as being the image itself when it comes to synchronization
+/// and layouts.
+#[derive(Debug)]
+pub struct ImmutableImageView<F, A = Arc<StdMemoryPool>>
+    where A: MemoryPool
+{
+    image: Arc<ImmutableImage<F, A>>,
+    view: UnsafeImageView,
+}
+
+impl<F, A> ImmutableImageView<F, A>
+    where A: MemoryPool
+{
+    /// Returns the image this view was created from.
+    #[inline]
+    pub fn image(&self) -> &Arc<ImmutableImage<F, A>> {
+        &self.image
+    }
+
+    /// Returns the minimum LOD of the view.
+    #[inline]
+    pub fn min_lod(&self) -> f32 {
+        self.view.min_lod()
+    }
+}
+
+unsafe impl<F, A> ImageViewAccess for ImmutableImageView<F, A>
+    where F: 'static + Send + Sync,
+          A: MemoryPool
+{
+    #[inline]
+    fn parent(&self) -> &ImageAccess {
+        &*self.image
+    }
+
+    #[inline]
+    fn dimensions(&self) -> Dimensions {
+        self.image.dimensions
+    }
+
+    #[inline]
+    fn inner(&self) -> &UnsafeImageView {
+        &self.view
+    }
+
+    #[inline]
+    fn descriptor_set_storage_image_layout(&self) -> ImageLayout {
+        ImageLayout::ShaderReadOnlyOptimal
+    }
+
+    #[inline]
+    fn descriptor_set_combined_image_sampler_layout(&self) -> ImageLayout {
+        ImageLayout::ShaderReadOnlyOptimal
+    }
+
+    #[inline]
+    fn descriptor_set_sampled_image_layout(&self) -> ImageLayout {
+        ImageLayout::ShaderReadOnlyOptimal
+    }
+
+    #[inline]
+    fn descriptor_set_input_attachment_layout(&self) -> ImageLayout {
+        ImageLayout::ShaderReadOnlyOptimal
+    }
+
+    #[inline]
+    fn identity_swizzle(&self) -> bool {
+        true
+    }
+}
+
+/// Error that can happen when creating an `ImmutableImage` with `from_iter` or `from_buffer`.
+#[derive(Debug, Clone)]
+pub enum ImmutableImageUploadError {
+    /// Error while creating the image.
+    ImageCreationError(ImageCreationError),
+
+    /// Not enough memory for the staging buffer or the command buffer.
+    OomError(OomError),
+
+    /// The pixels couldn't be copied to the image.
+    CopyError(CopyBufferToImageError),
+
+    /// The command buffer that uploads the pixels couldn't be built.
+    BuildError(BuildError),
+
+    /// The command buffer that uploads the pixels couldn't be executed.
+    ExecError(CommandBufferExecError),
+}
+
+impl error::Error for ImmutableImageUploadError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            ImmutableImageUploadError::ImageCreationError(_) => "error while creating the image",
+            ImmutableImageUploadError::OomError(_) => "not enough memory",
+            ImmutableImageUploadError::CopyError(_) => {
+                "the pixels couldn't be copied to the image"
+            },
+            ImmutableImageUploadError::BuildError(_) => {
+                "the command buffer that uploads the pixels couldn't be built"
+            },
+            ImmutableImageUploadError::ExecError(_) => {
+                "the command buffer that uploads the pixels couldn't be executed"
+            },
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            ImmutableImageUploadError::ImageCreationError(ref err) => Some(err),
+            ImmutableImageUploadError::OomError(ref err) => Some(err),
+            ImmutableImageUploadError::CopyError(ref err) => Some(err),
+            ImmutableImageUploadError::BuildError(ref err) => Some(err),
+            ImmutableImageUploadError::ExecError(ref err) => Some(err),
+        }
+    }
+}
+
+impl fmt::Display for ImmutableImageUploadError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl From<ImageCreationError> for ImmutableImageUploadError {
+    #[inline]
+    fn from(err: ImageCreationError) -> ImmutableImageUploadError {
+        ImmutableImageUploadError::ImageCreationError(err)
+    }
+}
+
+impl From<OomError> for ImmutableImageUploadError {
+    #[inline]
+    fn from(err: OomError) -> ImmutableImageUploadError {
+        ImmutableImageUploadError::OomError(err)
+    }
+}
+
+impl From<CopyBufferToImageError> for ImmutableImageUploadError {
+    #[inline]
+    fn from(err: CopyBufferToImageError) -> ImmutableImageUploadError {
+        ImmutableImageUploadError::CopyError(err)
+    }
+}
+
+impl From<BuildError> for ImmutableImageUploadError {
+    #[inline]
+    fn from(err: BuildError) -> ImmutableImageUploadError {
+        ImmutableImageUploadError::BuildError(err)
+    }
+}
+
+impl From<CommandBufferExecError> for ImmutableImageUploadError {
+    #[inline]
+    fn from(err: CommandBufferExecError) -> ImmutableImageUploadError {
+        ImmutableImageUploadError::ExecError(err)
+    }
+}
+
+/// Access to the immutable image that can be used for the initial upload.
+pub struct ImmutableImageInitialization<F, A = Arc<StdMemoryPool>>
+    where A: MemoryPool
+{
+    image: Arc<ImmutableImage<F, A>>,
+    used: Arc<AtomicBool>,
+}
+
+unsafe impl<F, A> ImageAccess for ImmutableImageInitialization<F, A>
+    where F: 'static + Send + Sync,
+          A: MemoryPool
+{
+    #[inline]
+    fn inner(&self) -> ImageInner {
+        ImageAccess::inner(&*self.image)
+    }
+
+    #[inline]
+    fn initial_layout_requirement(&self) -> ImageLayout {
+        ImageLayout::Undefined
+    }
+
+    #[inline]
+    fn final_layout_requirement(&self) -> ImageLayout {
+        ImageLayout::ShaderReadOnlyOptimal
+    }
+
+    #[inline]
+    fn try_gpu_lock(&self, exclusive_access: bool, queue: &Queue) -> Result<(), AccessError> {
+        if self.image.initialized.load(Ordering::Relaxed) {
+            return Err(AccessError::AlreadyInUse);
+        }
+
+        if !self.used.compare_and_swap(false, true, Ordering::Relaxed) {
+            Ok(())
+        } else {
+            Err(AccessError::AlreadyInUse)
+        }
+    }
+
+    #[inline]
+    unsafe fn increase_gpu_lock(&self) {
+        debug_assert!(self.used.load(Ordering::Relaxed));
+    }
+
+    #[inline]
+    unsafe fn unlock(&self) {
+        self.image.initialized.store(true, Ordering::Relaxed);
+    }
+}
+
+impl<F, A> Clone for ImmutableImageInitialization<F, A>
+    where A: MemoryPool
+{
+    #[inline]
+    fn clone(&self) -> ImmutableImageInitialization<F, A> {
+        ImmutableImageInitialization {
+            image: self.image.clone(),
+            used: self.used.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::iter;
+    use std::time::Duration;
+
+    use buffer::BufferUsage;
+    use buffer::CpuAccessibleBuffer;
+    use command_buffer::AutoCommandBufferBuilder;
+    use command_buffer::CommandBuffer;
+    use command_buffer::CopyBufferToImageError;
+    use command_buffer::synced::SyncCommandBufferBuilderError;
+    use format::Format;
+    use format::R8G8B8A8Unorm;
+    use device::Device;
+    use device::DeviceExtensions;
+    use features::Features;
+    use image::Dimensions;
+    use image::ImageAccess;
+    use image::MipmapsCount;
+    use image::immutable::ColorEncoding;
+    use image::immutable::ImmutableImage;
+    use image::sys::ImageViewFormatError;
+    use image::sys::ImageViewMinLodError;
+    use instance;
+    use sampler::Filter;
+    use sampler::MipmapMode;
+    use sampler::Sampler;
+    use sampler::SamplerAddressMode;
+    use sync::GpuFuture;
+    use sync::now;
+    use tests::sample_image;
+
+    #[test]
+    fn from_iter_initialized_after_upload() {
+        let (_, queue) = gfx_dev_and_queue!();
+
+        let (image, future) = ImmutableImage::from_iter((0 .. 64 * 64).map(|_| [0u8; 4]),
+                                                        Dimensions::Dim2d {
+                                                            width: 64,
+                                                            height: 64,
+                                                        },
+                                                        R8G8B8A8Unorm,
+                                                        Some(queue.family()),
+                                                        queue.clone())
+            .unwrap();
+        assert!(!image.initialized());
+
+        future
+            .then_signal_fence_and_flush()
+            .unwrap()
+            .wait(Some(Duration::from_secs(5)))
+            .unwrap();
+        assert!(image.initialized());
+    }
+
+    #[test]
+    fn new_not_tracked() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let image = ImmutableImage::new(device.clone(),
+                                        Dimensions::Dim2d {
+                                            width: 64,
+                                            height: 64,
+                                        },
+                                        R8G8B8A8Unorm,
+                                        Some(queue.family()))
+            .unwrap();
+        assert!(image.initialized());
+    }
+
+    #[test]
+    fn use_uninitialized_forbidden() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let (image, _) = unsafe {
+            ImmutableImage::uninitialized(device.clone(),
+                                          Dimensions::Dim2d {
+                                              width: 64,
+                                              height: 64,
+                                          },
+                                          R8G8B8A8Unorm,
+                                          MipmapsCount::One,
+                                          Some(queue.family()))
+                .unwrap()
+        };
+
+        let src = CpuAccessibleBuffer::from_iter(device.clone(),
+                                                 BufferUsage::all(),
+                                                 iter::once(queue.family()),
+                                                 (0 .. 64 * 64).map(|_| [0u8; 4]))
+            .unwrap();
+
+        let result = AutoCommandBufferBuilder::new(device.clone(), queue.family())
+            .unwrap()
+            .copy_buffer_to_image(src, image);
+
+        match result {
+            Err(CopyBufferToImageError::SyncCommandBufferBuilderError(
+                SyncCommandBufferBuilderError::UninitializedResource)) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn min_lod_view_sampling() {
+        let instance = instance!();
+
+        let physical = match instance::PhysicalDevice::enumerate(&instance).next() {
+            Some(p) => p,
+            None => return,
+        };
+
+        if !DeviceExtensions::supported_by_device(&physical).ext_image_view_min_lod {
+            return;
+        }
+
+        let queue = match physical.queue_families().find(|q| q.supports_graphics()) {
+            Some(q) => q,
+            None => return,
+        };
+
+        let extensions = DeviceExtensions {
+            ext_image_view_min_lod: true,
+            ..DeviceExtensions::none()
+        };
+
+        let (device, mut queues) = match Device::new(&physical,
+                                                     &Features::none(),
+                                                     &extensions,
+                                                     [(queue, 0.5)].iter().cloned()) {
+            Ok(r) => r,
+            Err(_) => return,
+        };
+        let queue = queues.next().unwrap();
+
+        let (image, init) = unsafe {
+            ImmutableImage::uninitialized(device.clone(),
+                                          Dimensions::Dim2d {
+                                              width: 4,
+                                              height: 4,
+                                          },
+                                          R8G8B8A8Unorm,
+                                          MipmapsCount::Specific(3),
+                                          Some(queue.family()))
+                .unwrap()
+        };
+
+        // Each level is filled with a different color: red, green, then blue.
+        let mut cb = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap();
+        for &(level, color) in [(0, [255u8, 0, 0, 255]), (1, [0, 255, 0, 255]),
+                                (2, [0, 0, 255, 255])]
+            .iter()
+        {
+            let size = 4 >> level;
+            let data = CpuAccessibleBuffer::from_iter(device.clone(),
+                                                      BufferUsage::all(),
+                                                      Some(queue.family()),
+                                                      iter::repeat(color)
+                                                          .take((size * size) as usize))
+                .unwrap();
+            cb = cb.copy_buffer_to_image_dimensions(data,
+                                                    init.clone(),
+                                                    [0, 0, 0],
+                                                    [size, size, 1],
+                                                    0,
+                                                    1,
+                                                    level)
+                .unwrap();
+        }
+        cb.build()
+            .unwrap()
+            .execute(queue.clone())
+            .unwrap()
+            .then_signal_fence_and_flush()
+            .unwrap()
+            .wait(None)
+            .unwrap();
+        assert!(image.initialized());
+
+        let view = ImmutableImage::min_lod_view(&image, 2.0).unwrap();
+        assert_eq!(view.min_lod(), 2.0);
+
+        let sampler = Sampler::new(device.clone(),
+                                   Filter::Nearest,
+                                   Filter::Nearest,
+                                   MipmapMode::Nearest,
+                                   SamplerAddressMode::ClampToEdge,
+                                   SamplerAddressMode::ClampToEdge,
+                                   SamplerAddressMode::ClampToEdge,
+                                   0.0,
+                                   1.0,
+                                   0.0,
+                                   1000.0)
+            .unwrap();
+
+        // Whatever the LOD that is requested, only the last level is sampled.
+        let values = sample_image(now(device.clone()),
+                                  queue.clone(),
+                                  view,
+                                  sampler,
+                                  &[[0.5, 0.5, 0.0], [0.5, 0.5, 1.0], [0.5, 0.5, 2.0]]);
+        assert_eq!(values, vec![[0.0, 0.0, 1.0, 1.0]; 3]);
+
+        match ImmutableImage::min_lod_view(&image, 3.0) {
+            Err(ImageViewMinLodError::MinLodOutOfRange { min_lod, max }) => {
+                assert_eq!(min_lod, 3.0);
+                assert_eq!(max, 2.0);
+            },
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn from_iter_with_encoding_sampling() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let (image, encoding, future) =
+            ImmutableImage::from_iter_with_encoding((0 .. 4 * 4).map(|_| [128u8, 128, 128, 255]),
+                                                    Dimensions::Dim2d {
+                                                        width: 4,
+                                                        height: 4,
+                                                    },
+                                                    Format::R8G8B8A8Unorm,
+                                                    true,
+                                                    Some(queue.family()),
+                                                    queue.clone())
+                .unwrap();
+        assert_eq!(encoding, ColorEncoding::Srgb);
+        assert_eq!(image.format, Format::R8G8B8A8Srgb);
+
+        let unorm_view = ImmutableImage::format_view(&image, Format::R8G8B8A8Unorm).unwrap();
+
+        let sampler = Sampler::new(device.clone(),
+                                   Filter::Nearest,
+                                   Filter::Nearest,
+                                   MipmapMode::Nearest,
+                                   SamplerAddressMode::ClampToEdge,
+                                   SamplerAddressMode::ClampToEdge,
+                                   SamplerAddressMode::ClampToEdge,
+                                   0.0,
+                                   1.0,
+                                   0.0,
+                                   0.0)
+            .unwrap();
+
+        // The sRGB view decodes the value, the `Unorm` view returns it as it is stored.
+        let srgb = sample_image(future, queue.clone(), image.clone(), sampler.clone(),
+                                &[[0.5, 0.5, 0.0]]);
+        let unorm = sample_image(now(device.clone()), queue.clone(), unorm_view, sampler,
+                                 &[[0.5, 0.5, 0.0]]);
+
+        assert!((srgb[0][0] - 0.2158).abs() < 0.01);
+        assert!((unorm[0][0] - 128.0 / 255.0).abs() < 0.01);
+        assert_eq!(srgb[0][3], 1.0);
+        assert_eq!(unorm[0][3], 1.0);
+    }
+
+    #[test]
+    fn from_iter_with_encoding_decision() {
+        let (_, queue) = gfx_dev_and_queue!();
+
+        let dimensions = Dimensions::Dim2d {
+            width: 1,
+            height: 1,
+        };
+
+        let (image, encoding, _) =
+            ImmutableImage::from_iter_with_encoding(iter::once([0u8; 4]),
+                                                    dimensions,
+                                                    Format::R8G8B8A8Srgb,
+                                                    false,
+                                                    Some(queue.family()),
+                                                    queue.clone())
+                .unwrap();
+        assert_eq!(encoding, ColorEncoding::Linear);
+        assert_eq!(image.format, Format::R8G8B8A8Unorm);
+
+        let (image, encoding, _) =
+            ImmutableImage::from_iter_with_encoding(iter::once([0u16; 4]),
+                                                    dimensions,
+                                                    Format::R16G16B16A16Sfloat,
+                                                    true,
+                                                    Some(queue.family()),
+                                                    queue.clone())
+                .unwrap();
+        assert_eq!(encoding, ColorEncoding::UndecodedSrgb);
+        assert_eq!(image.format, Format::R16G16B16A16Sfloat);
+        match ImmutableImage::format_view(&image, Format::R16G16B16A16Uint) {
+            Err(ImageViewFormatError::ImageFormatNotMutable) => (),
+            _ => panic!(),
+        }
+    }
+}
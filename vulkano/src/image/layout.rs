@@ -39,4 +39,5 @@ pub enum ImageLayout {
     TransferDstOptimal = vk::IMAGE_LAYOUT_TRANSFER_DST_OPTIMAL,
     Preinitialized = vk::IMAGE_LAYOUT_PREINITIALIZED,
     PresentSrc = vk::IMAGE_LAYOUT_PRESENT_SRC_KHR,
+    SharedPresent = vk::IMAGE_LAYOUT_SHARED_PRESENT_KHR,
 }
@@ -40,30 +40,52 @@
 //! - An `AttachmentImage` can be used when you want to draw to an image.
 //! - An `ImmutableImage` stores data which never need be changed after the initial upload,
 //!   like a texture.
+//! - A `SparseImage` is a texture whose memory is bound tile per tile, for example by a
+//!   `ResidencyManager` that streams the visible tiles.
+//! - An `UpdatableImage` is a texture whose content is updated region by region, like a glyph
+//!   atlas.
 //!
 //! # Low-level informations
 //!
 //! To be written.
 //!
 
+use format::Format;
+use std::cmp;
+use std::mem;
+
 pub use self::attachment::AttachmentImage;
+pub use self::immutable::ColorEncoding;
 pub use self::immutable::ImmutableImage;
+pub use self::immutable::ImmutableImageUploadError;
+pub use self::immutable::ImmutableImageView;
 pub use self::layout::ImageLayout;
+pub use self::sparse::ResidencyError;
+pub use self::sparse::ResidencyManager;
+pub use self::sparse::ResidencyUpdate;
+pub use self::sparse::SparseImage;
+pub use self::sparse::TileId;
 pub use self::storage::StorageImage;
 pub use self::swapchain::SwapchainImage;
+pub use self::swapchain::SwapchainImageView;
 pub use self::sys::ImageCreationError;
 pub use self::traits::ImageAccess;
 pub use self::traits::ImageInner;
 pub use self::traits::ImageViewAccess;
+pub use self::updatable::UpdatableImage;
+pub use self::updatable::UpdatableImageError;
+pub use self::updatable::UpdatableImageRegion;
 pub use self::usage::ImageUsage;
 
 pub mod attachment; // TODO: make private
 pub mod immutable; // TODO: make private
 mod layout;
+mod sparse;
 mod storage;
 pub mod swapchain; // TODO: make private
 pub mod sys;
 pub mod traits;
+mod updatable;
 mod usage;
 
 /// Specifies how many mipmaps must be allocated.
@@ -384,4 +406,252 @@ impl ImageDimensions {
             ImageDimensions::Dim3d { .. } => 1,
         }
     }
+
+    /// Returns the total number of texels of the image, including all the array layers but
+    /// excluding the mipmaps.
+    #[inline]
+    pub fn texel_count(&self) -> u64 {
+        self.width() as u64 * self.height() as u64 * self.depth() as u64 *
+            self.array_layers() as u64
+    }
+
+    /// Returns the maximum number of mipmap levels that an image with these dimensions can
+    /// have, including the main level.
+    ///
+    /// Each mipmap level is half the size of the previous one, rounded down and with a minimum
+    /// of 1 in each dimension. The last level is the one where all the dimensions are equal to 1.
+    #[inline]
+    pub fn max_mip_levels(&self) -> u32 {
+        let max_dim = cmp::max(cmp::max(self.width(), self.height()), self.depth());
+        32 - max_dim.leading_zeros()
+    }
+
+    /// Returns the dimensions of the given mipmap level. Level 0 is the main level.
+    ///
+    /// The number of array layers and the cubemap compatibility are the same as the main level.
+    /// Returns `None` if `level` is superior or equal to `max_mip_levels()`.
+    pub fn mip_level_dimensions(&self, level: u32) -> Option<ImageDimensions> {
+        if level >= self.max_mip_levels() {
+            return None;
+        }
+
+        let shrink = |dim: u32| cmp::max(1, dim >> level);
+
+        Some(match *self {
+            ImageDimensions::Dim1d { width, array_layers } => {
+                ImageDimensions::Dim1d {
+                    width: shrink(width),
+                    array_layers: array_layers,
+                }
+            },
+            ImageDimensions::Dim2d {
+                width,
+                height,
+                array_layers,
+                cubemap_compatible,
+            } => {
+                ImageDimensions::Dim2d {
+                    width: shrink(width),
+                    height: shrink(height),
+                    array_layers: array_layers,
+                    cubemap_compatible: cubemap_compatible,
+                }
+            },
+            ImageDimensions::Dim3d {
+                width,
+                height,
+                depth,
+            } => {
+                ImageDimensions::Dim3d {
+                    width: shrink(width),
+                    height: shrink(height),
+                    depth: shrink(depth),
+                }
+            },
+        })
+    }
+
+    /// Returns the number of bytes that a buffer must contain in order to hold the whole content
+    /// of the given mipmap level, for all array layers, when tightly packed.
+    ///
+    /// For block-compressed formats, the dimensions are rounded up to a multiple of the block
+    /// dimensions.
+    ///
+    /// Returns `None` if `level` is out of range, or if the size of a texel of `format` is
+    /// irrelevant (for example for combined depth-stencil formats).
+    pub fn required_buffer_len_for_level(&self, format: Format, level: u32) -> Option<usize> {
+        let dims = match self.mip_level_dimensions(level) {
+            Some(d) => d,
+            None => return None,
+        };
+
+        region_buffer_len(format, dims.width_height_depth(), dims.array_layers())
+    }
+}
+
+// Returns the number of bytes required to hold a region of `extent` texels and `layers` array
+// layers of an image of the given format, tightly packed.
+pub(crate) fn region_buffer_len(format: Format, extent: [u32; 3], layers: u32) -> Option<usize> {
+    let block_size = match format.block_size() {
+        Some(s) => s,
+        None => return None,
+    };
+
+    let block_dims = format.block_dimensions();
+    let blocks_x = (extent[0] + block_dims[0] - 1) / block_dims[0];
+    let blocks_y = (extent[1] + block_dims[1] - 1) / block_dims[1];
+
+    Some(blocks_x as usize * blocks_y as usize * extent[2] as usize * layers as usize * block_size)
+}
+
+// Returns true if pixels of type `P` have the size of a texel of `format`. Always false for
+// compressed formats and for formats whose texel size is irrelevant.
+pub(crate) fn matches_pixel_format<P>(format: Format) -> bool {
+    format.block_dimensions() == [1, 1] && format.block_size() == Some(mem::size_of::<P>())
+}
+
+#[cfg(test)]
+mod tests {
+    use format::Format;
+    use image::ImageDimensions;
+    use image::matches_pixel_format;
+
+    #[test]
+    fn max_mip_levels() {
+        let dims = ImageDimensions::Dim2d {
+            width: 1,
+            height: 1,
+            array_layers: 1,
+            cubemap_compatible: false,
+        };
+        assert_eq!(dims.max_mip_levels(), 1);
+
+        let dims = ImageDimensions::Dim2d {
+            width: 256,
+            height: 256,
+            array_layers: 1,
+            cubemap_compatible: false,
+        };
+        assert_eq!(dims.max_mip_levels(), 9);
+
+        // The largest dimension determines the number of levels.
+        let dims = ImageDimensions::Dim2d {
+            width: 300,
+            height: 7,
+            array_layers: 1,
+            cubemap_compatible: false,
+        };
+        assert_eq!(dims.max_mip_levels(), 9);
+
+        let dims = ImageDimensions::Dim3d {
+            width: 3,
+            height: 5,
+            depth: 17,
+        };
+        assert_eq!(dims.max_mip_levels(), 5);
+    }
+
+    #[test]
+    fn mip_level_dimensions_npot() {
+        let dims = ImageDimensions::Dim2d {
+            width: 300,
+            height: 7,
+            array_layers: 3,
+            cubemap_compatible: false,
+        };
+
+        let expected = [[300, 7], [150, 3], [75, 1], [37, 1], [18, 1], [9, 1], [4, 1], [2, 1],
+                        [1, 1]];
+        for (level, &exp) in expected.iter().enumerate() {
+            let mip = dims.mip_level_dimensions(level as u32).unwrap();
+            assert_eq!(mip.width_height(), exp);
+            assert_eq!(mip.array_layers(), 3);
+        }
+
+        assert!(dims.mip_level_dimensions(expected.len() as u32).is_none());
+    }
+
+    #[test]
+    fn texel_count() {
+        let dims = ImageDimensions::Dim2d {
+            width: 5,
+            height: 3,
+            array_layers: 2,
+            cubemap_compatible: false,
+        };
+        assert_eq!(dims.texel_count(), 30);
+
+        let dims = ImageDimensions::Dim3d {
+            width: 5,
+            height: 3,
+            depth: 7,
+        };
+        assert_eq!(dims.texel_count(), 105);
+    }
+
+    #[test]
+    fn buffer_len_uncompressed() {
+        let dims = ImageDimensions::Dim2d {
+            width: 13,
+            height: 5,
+            array_layers: 2,
+            cubemap_compatible: false,
+        };
+
+        let format = Format::R8G8B8A8Unorm;
+        assert_eq!(dims.required_buffer_len_for_level(format, 0), Some(13 * 5 * 2 * 4));
+        assert_eq!(dims.required_buffer_len_for_level(format, 1), Some(6 * 2 * 2 * 4));
+        assert_eq!(dims.required_buffer_len_for_level(format, 3), Some(1 * 1 * 2 * 4));
+        assert_eq!(dims.required_buffer_len_for_level(format, 4), None);
+
+        assert_eq!(dims.required_buffer_len_for_level(Format::D24Unorm_S8Uint, 0), None);
+    }
+
+    #[test]
+    fn buffer_len_bc() {
+        let dims = ImageDimensions::Dim2d {
+            width: 10,
+            height: 6,
+            array_layers: 1,
+            cubemap_compatible: false,
+        };
+
+        // BC1 stores 4x4 blocks of 8 bytes.
+        let format = Format::BC1_RGBUnormBlock;
+        assert_eq!(dims.required_buffer_len_for_level(format, 0), Some(3 * 2 * 8));
+        assert_eq!(dims.required_buffer_len_for_level(format, 1), Some(2 * 1 * 8));
+        // 2x2 and 1x1 mipmaps still occupy a full block.
+        assert_eq!(dims.required_buffer_len_for_level(format, 2), Some(8));
+        assert_eq!(dims.required_buffer_len_for_level(format, 3), Some(8));
+
+        // BC3 stores 4x4 blocks of 16 bytes.
+        let format = Format::BC3UnormBlock;
+        assert_eq!(dims.required_buffer_len_for_level(format, 0), Some(3 * 2 * 16));
+        assert_eq!(dims.required_buffer_len_for_level(format, 2), Some(16));
+        assert_eq!(dims.required_buffer_len_for_level(format, 3), Some(16));
+    }
+
+    #[test]
+    fn buffer_len_astc() {
+        let dims = ImageDimensions::Dim2d {
+            width: 13,
+            height: 13,
+            array_layers: 1,
+            cubemap_compatible: false,
+        };
+
+        let format = Format::ASTC_12x10UnormBlock;
+        assert_eq!(format.block_dimensions(), [12, 10]);
+        assert_eq!(dims.required_buffer_len_for_level(format, 0), Some(2 * 2 * 16));
+        assert_eq!(dims.required_buffer_len_for_level(format, 1), Some(16));
+    }
+
+    #[test]
+    fn pixel_format() {
+        assert!(matches_pixel_format::<[u8; 4]>(Format::R8G8B8A8Unorm));
+        assert!(matches_pixel_format::<u32>(Format::R8G8B8A8Unorm));
+        assert!(matches_pixel_format::<[f32; 4]>(Format::R32G32B32A32Sfloat));
+        assert!(!matches_pixel_format::<u8>(Format::R8G8B8A8Unorm));
+        assert!(!matches_pixel_format::<[u8; 16]>(Format::BC7UnormBlock));
+    }
 }
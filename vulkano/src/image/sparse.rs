@@ -0,0 +1,951 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use smallvec::SmallVec;
+use std::cmp;
+use std::collections::HashMap;
+use std::error;
+use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+
+use device::Device;
+use device::DeviceOwned;
+use device::Queue;
+use format::FormatDesc;
+use image::Dimensions;
+use image::ImageInner;
+use image::ImageLayout;
+use image::ImageUsage;
+use image::MipmapsCount;
+use image::matches_pixel_format;
+use image::sys::ImageCreationError;
+use image::sys::SparseImageMemoryRequirements;
+use image::sys::SparseImageMipTail;
+use image::sys::UnsafeImage;
+use image::sys::UnsafeImageView;
+use image::traits::ImageAccess;
+use image::traits::ImageContent;
+use image::traits::ImageViewAccess;
+use instance::QueueFamily;
+use memory::DeviceMemory;
+use memory::MemoryRequirements;
+use sync::AccessError;
+use sync::GpuFuture;
+use sync::NowFuture;
+use sync::Sharing;
+use sync::SparseBindError;
+use sync::SparseBindFuture;
+use sync::SparseImageBind;
+use sync::bind_sparse_image_memory;
+use sync::bind_sparse_image_mip_tail;
+use sync::now;
+use sync::sparse_image_mip_tail_size;
+
+use OomError;
+
+/// Two-dimensional image with a full mipmap chain whose memory is bound tile per tile.
+///
+/// Creating a `SparseImage` requires the `sparse_binding` and `sparse_residency_image2d`
+/// features. No memory is bound to the image at creation. Use a `ResidencyManager` to bind and
+/// evict tiles, or `Queue::bind_sparse_image_memory` to bind them manually.
+///
+/// The image stays in the `General` layout, so that tiles can be uploaded and sampled without
+/// layout transitions.
+#[derive(Debug)]
+pub struct SparseImage<F> {
+    // Inner implementation.
+    image: UnsafeImage,
+
+    // We maintain a view of the whole image.
+    view: UnsafeImageView,
+
+    // Dimensions of the image view.
+    dimensions: Dimensions,
+
+    // Format.
+    format: F,
+
+    // Sparse requirements of the color aspect.
+    sparse_requirements: SparseImageMemoryRequirements,
+
+    // Memory requirements of the image. The alignment is the size of a tile.
+    memory_requirements: MemoryRequirements,
+
+    // Number of times this image is locked on the GPU side.
+    gpu_lock: AtomicUsize,
+}
+
+impl<F> SparseImage<F> {
+    /// Creates a new sparse image with the given dimensions and format, and all its mipmaps.
+    ///
+    /// The dimensions must be `Dim2d` or `Dim2dArray`.
+    pub fn new<'a, I>(device: Arc<Device>, dimensions: Dimensions, format: F, queue_families: I)
+                      -> Result<Arc<SparseImage<F>>, ImageCreationError>
+        where F: FormatDesc,
+              I: IntoIterator<Item = QueueFamily<'a>>
+    {
+        let usage = ImageUsage {
+            transfer_source: true,
+            transfer_dest: true,
+            sampled: true,
+            ..ImageUsage::none()
+        };
+
+        let queue_families = queue_families
+            .into_iter()
+            .map(|f| f.id())
+            .collect::<SmallVec<[u32; 4]>>();
+
+        let (image, mem_reqs) = unsafe {
+            let sharing = if queue_families.len() >= 2 {
+                Sharing::Concurrent(queue_families.iter().cloned())
+            } else {
+                Sharing::Exclusive
+            };
+
+            UnsafeImage::new_sparse(device.clone(),
+                                    usage,
+                                    format.format(),
+                                    dimensions.to_image_dimensions(),
+                                    MipmapsCount::Log2,
+                                    sharing)?
+        };
+
+        let sparse_requirements = image
+            .sparse_memory_requirements()
+            .into_iter()
+            .find(|req| req.color)
+            .ok_or(ImageCreationError::FormatNotSupported)?;
+
+        let view = unsafe {
+            UnsafeImageView::raw(&image,
+                                 dimensions.to_view_type(),
+                                 0 .. image.mipmap_levels(),
+                                 0 .. image.dimensions().array_layers())?
+        };
+
+        Ok(Arc::new(SparseImage {
+                        image: image,
+                        view: view,
+                        dimensions: dimensions,
+                        format: format,
+                        sparse_requirements: sparse_requirements,
+                        memory_requirements: mem_reqs,
+                        gpu_lock: AtomicUsize::new(0),
+                    }))
+    }
+
+    /// Returns the dimensions of the image.
+    #[inline]
+    pub fn dimensions(&self) -> Dimensions {
+        self.dimensions
+    }
+
+    /// Returns the number of mipmap levels of the image.
+    #[inline]
+    pub fn mipmap_levels(&self) -> u32 {
+        self.image.mipmap_levels()
+    }
+
+    /// Returns the sparse memory requirements of the image, which include the size of a tile in
+    /// texels and the location of the mip tail.
+    #[inline]
+    pub fn sparse_memory_requirements(&self) -> SparseImageMemoryRequirements {
+        self.sparse_requirements
+    }
+
+    /// Returns the description of the mip tail of the image, or `None` if all of its mipmap
+    /// levels are divided in tiles.
+    #[inline]
+    pub fn mip_tail(&self) -> Option<SparseImageMipTail> {
+        self.sparse_requirements
+            .mip_tail(self.image.mipmap_levels(), self.dimensions.array_layers())
+    }
+
+    /// Returns the size in bytes of the memory of a tile.
+    #[inline]
+    pub fn tile_size(&self) -> usize {
+        self.memory_requirements.alignment
+    }
+
+    /// Returns the number of tiles horizontally and vertically in the given mipmap level.
+    ///
+    /// Returns `None` if the level is out of range or is part of the mip tail.
+    pub fn num_tiles(&self, mip_level: u32) -> Option<[u32; 2]> {
+        if mip_level >= self.sparse_requirements.mip_tail_first_lod {
+            return None;
+        }
+
+        let level = match self.image.dimensions().mip_level_dimensions(mip_level) {
+            Some(d) if mip_level < self.image.mipmap_levels() => d,
+            _ => return None,
+        };
+
+        let granularity = self.sparse_requirements.image_granularity;
+        Some([(level.width() + granularity[0] - 1) / granularity[0],
+              (level.height() + granularity[1] - 1) / granularity[1]])
+    }
+
+    /// Returns the offset and the extent in texels of a tile, or `None` if the tile is out of
+    /// range.
+    ///
+    /// The tiles at the right and bottom edges of a mipmap level can be smaller than the others.
+    pub fn tile_region(&self, tile: TileId) -> Option<([u32; 3], [u32; 3])> {
+        let num_tiles = match self.num_tiles(tile.mip_level) {
+            Some(n) => n,
+            None => return None,
+        };
+
+        if tile.x >= num_tiles[0] || tile.y >= num_tiles[1] ||
+            tile.array_layer >= self.dimensions.array_layers()
+        {
+            return None;
+        }
+
+        let level = self.image
+            .dimensions()
+            .mip_level_dimensions(tile.mip_level)
+            .unwrap();
+        let granularity = self.sparse_requirements.image_granularity;
+
+        let offset = [tile.x * granularity[0], tile.y * granularity[1], 0];
+        let extent = [cmp::min(granularity[0], level.width() - offset[0]),
+                      cmp::min(granularity[1], level.height() - offset[1]),
+                      1];
+        Some((offset, extent))
+    }
+}
+
+unsafe impl<F> ImageAccess for SparseImage<F>
+    where F: 'static + Send + Sync
+{
+    #[inline]
+    fn inner(&self) -> ImageInner {
+        ImageInner {
+            image: &self.image,
+            first_layer: 0,
+            num_layers: self.dimensions.array_layers() as usize,
+            first_mipmap_level: 0,
+            num_mipmap_levels: self.image.mipmap_levels() as usize,
+        }
+    }
+
+    #[inline]
+    fn initial_layout_requirement(&self) -> ImageLayout {
+        ImageLayout::General
+    }
+
+    #[inline]
+    fn final_layout_requirement(&self) -> ImageLayout {
+        ImageLayout::General
+    }
+
+    #[inline]
+    fn try_gpu_lock(&self, _: bool, _: &Queue) -> Result<(), AccessError> {
+        if self.gpu_lock.compare_and_swap(0, 1, Ordering::SeqCst) == 0 {
+            Ok(())
+        } else {
+            Err(AccessError::AlreadyInUse)
+        }
+    }
+
+    #[inline]
+    unsafe fn increase_gpu_lock(&self) {
+        let val = self.gpu_lock.fetch_add(1, Ordering::SeqCst);
+        debug_assert!(val >= 1);
+    }
+
+    #[inline]
+    unsafe fn unlock(&self) {
+        let prev_val = self.gpu_lock.fetch_sub(1, Ordering::SeqCst);
+        debug_assert!(prev_val >= 1);
+    }
+}
+
+unsafe impl<P, F> ImageContent<P> for SparseImage<F>
+    where F: 'static + Send + Sync
+{
+    #[inline]
+    fn matches_format(&self) -> bool {
+        matches_pixel_format::<P>(self.image.format())
+    }
+}
+
+unsafe impl<F> ImageViewAccess for SparseImage<F>
+    where F: 'static + Send + Sync
+{
+    #[inline]
+    fn parent(&self) -> &ImageAccess {
+        self
+    }
+
+    #[inline]
+    fn dimensions(&self) -> Dimensions {
+        self.dimensions
+    }
+
+    #[inline]
+    fn inner(&self) -> &UnsafeImageView {
+        &self.view
+    }
+
+    #[inline]
+    fn descriptor_set_storage_image_layout(&self) -> ImageLayout {
+        ImageLayout::General
+    }
+
+    #[inline]
+    fn descriptor_set_combined_image_sampler_layout(&self) -> ImageLayout {
+        ImageLayout::General
+    }
+
+    #[inline]
+    fn descriptor_set_sampled_image_layout(&self) -> ImageLayout {
+        ImageLayout::General
+    }
+
+    #[inline]
+    fn descriptor_set_input_attachment_layout(&self) -> ImageLayout {
+        ImageLayout::General
+    }
+
+    #[inline]
+    fn identity_swizzle(&self) -> bool {
+        true
+    }
+}
+
+/// Identifies a tile of a `SparseImage`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct TileId {
+    /// Mipmap level of the tile. Must not be part of the mip tail.
+    pub mip_level: u32,
+    /// Array layer of the tile.
+    pub array_layer: u32,
+    /// Horizontal position of the tile, in number of tiles.
+    pub x: u32,
+    /// Vertical position of the tile, in number of tiles.
+    pub y: u32,
+}
+
+/// Keeps tiles of a `SparseImage` resident within a memory budget.
+///
+/// At creation, the manager allocates its budget of memory and binds the mip tail of the image,
+/// which stays resident. Each call to `make_resident` then binds the requested tiles that aren't
+/// resident yet. When the budget is full, the least recently requested tiles are evicted to make
+/// room for the new ones.
+///
+/// The content of a newly bound tile is undefined. You are expected to upload it, for example
+/// with `copy_buffer_to_image_dimensions` using the region returned by `tile_region`, before
+/// sampling it.
+///
+/// # Example
+///
+/// ```ignore
+/// let (mut residency, future) = ResidencyManager::new(queue.clone(), image.clone(), 64 << 20)?;
+///
+/// // At each frame, after the commands of the previous frame:
+/// let update = residency.make_resident(previous_frame, visible_tiles)?;
+/// let mut cb = AutoCommandBufferBuilder::new(device.clone(), queue.family())?;
+/// for tile in update.bound {
+///     let (offset, extent) = image.tile_region(tile).unwrap();
+///     cb = cb.copy_buffer_to_image_dimensions(tile_data(tile), image.clone(), offset, extent,
+///                                            tile.array_layer, 1, tile.mip_level)?;
+/// }
+/// let future = update.future.then_execute(queue.clone(), cb.build()?)?;
+/// ```
+pub struct ResidencyManager<F>
+    where F: 'static + Send + Sync
+{
+    queue: Arc<Queue>,
+    image: Arc<SparseImage<F>>,
+    // Memory in which the tiles are stored, one slot of `tile_size` bytes per tile.
+    memory: Arc<DeviceMemory>,
+    // Memory of the mip tail, kept alive for as long as the image is used.
+    mip_tail_memory: Option<Arc<DeviceMemory>>,
+    cache: TileCache,
+}
+
+impl<F> ResidencyManager<F>
+    where F: 'static + Send + Sync
+{
+    /// Builds a new `ResidencyManager` that can keep `budget` bytes of tiles resident.
+    ///
+    /// Returns the manager and a future representing the binding of the mip tail. The first update
+    /// should be chained after this future.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `budget` is smaller than the size of a tile.
+    /// - Panics if the image doesn't belong to the same device as the queue.
+    ///
+    pub fn new(queue: Arc<Queue>, image: Arc<SparseImage<F>>, budget: usize)
+               -> Result<(ResidencyManager<F>, SparseBindFuture<NowFuture, Arc<SparseImage<F>>>),
+                         ResidencyError> {
+        let tile_size = image.tile_size();
+        let capacity = budget / tile_size;
+        assert!(capacity >= 1, "The budget is smaller than the size of a tile");
+
+        let device = queue.device().clone();
+        let memory_type = {
+            let type_bits = image.memory_requirements.memory_type_bits;
+            let device_local = device
+                .physical_device()
+                .memory_types()
+                .filter(|t| (type_bits & (1 << t.id())) != 0)
+                .filter(|t| t.is_device_local());
+            let any = device
+                .physical_device()
+                .memory_types()
+                .filter(|t| (type_bits & (1 << t.id())) != 0);
+            device_local.chain(any).next().unwrap()
+        };
+
+        let memory = Arc::new(DeviceMemory::alloc(device.clone(),
+                                                  memory_type,
+                                                  capacity * tile_size)?);
+
+        let mip_tail_size = sparse_image_mip_tail_size(&image);
+        let (mip_tail_memory, future) = if mip_tail_size != 0 {
+            let mip_tail_memory =
+                Arc::new(DeviceMemory::alloc(device.clone(), memory_type, mip_tail_size)?);
+            let future = bind_sparse_image_mip_tail(now(device.clone()),
+                                                    queue.clone(),
+                                                    image.clone(),
+                                                    mip_tail_memory.clone(),
+                                                    0)?;
+            (Some(mip_tail_memory), future)
+        } else {
+            let future = bind_sparse_image_memory(now(device.clone()),
+                                                  queue.clone(),
+                                                  image.clone(),
+                                                  Vec::new())?;
+            (None, future)
+        };
+
+        let manager = ResidencyManager {
+            queue: queue,
+            image: image,
+            memory: memory,
+            mip_tail_memory: mip_tail_memory,
+            cache: TileCache::new(capacity),
+        };
+
+        Ok((manager, future))
+    }
+
+    /// Returns the image whose tiles are managed.
+    #[inline]
+    pub fn image(&self) -> &Arc<SparseImage<F>> {
+        &self.image
+    }
+
+    /// Returns the maximum number of tiles that can be resident at the same time.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.cache.capacity
+    }
+
+    /// Returns the number of tiles that are currently resident, not counting the mip tail.
+    #[inline]
+    pub fn num_resident(&self) -> usize {
+        self.cache.tiles.len()
+    }
+
+    /// Returns true if `tile` is resident.
+    #[inline]
+    pub fn is_resident(&self, tile: TileId) -> bool {
+        self.cache.tiles.contains_key(&tile)
+    }
+
+    /// Makes the given tiles resident, evicting the least recently requested tiles if the budget
+    /// is full.
+    ///
+    /// All the tiles that are requested count as used, even if they were already resident.
+    /// Returns an error if more tiles are requested than the capacity of the manager, in which
+    /// case nothing changes.
+    ///
+    /// The bind operation is submitted when the returned future is flushed, after `previous`.
+    /// Evicted tiles are unbound and their memory is reused by other tiles, therefore `previous`
+    /// must include all the commands that read from the evicted tiles.
+    ///
+    /// If an error is returned, the resident tiles are left untouched.
+    pub fn make_resident<P, I>(&mut self, previous: P, tiles: I)
+                               -> Result<ResidencyUpdate<P, F>, ResidencyError>
+        where P: GpuFuture,
+              I: IntoIterator<Item = TileId>
+    {
+        let tiles = tiles.into_iter().collect::<Vec<_>>();
+        for &tile in tiles.iter() {
+            if self.image.tile_region(tile).is_none() {
+                return Err(ResidencyError::TileOutOfRange);
+            }
+        }
+
+        let plan = self.cache.plan(&tiles)?;
+
+        let mut binds = Vec::with_capacity(plan.bound.len() + plan.evicted.len());
+        for &tile in plan.evicted.iter() {
+            let (offset, extent) = self.image.tile_region(tile).unwrap();
+            binds.push(SparseImageBind {
+                           mip_level: tile.mip_level,
+                           array_layer: tile.array_layer,
+                           offset: offset,
+                           extent: extent,
+                           memory: None,
+                       });
+        }
+        for &(tile, slot) in plan.bound.iter() {
+            let (offset, extent) = self.image.tile_region(tile).unwrap();
+            binds.push(SparseImageBind {
+                           mip_level: tile.mip_level,
+                           array_layer: tile.array_layer,
+                           offset: offset,
+                           extent: extent,
+                           memory: Some((self.memory.clone(), slot * self.image.tile_size())),
+                       });
+        }
+
+        let future =
+            bind_sparse_image_memory(previous, self.queue.clone(), self.image.clone(), binds)?;
+
+        // Only update the bookkeeping once the bind operation has been successfully created.
+        let bound = plan.bound.iter().map(|&(tile, _)| tile).collect();
+        let evicted = plan.evicted.clone();
+        self.cache.apply(plan);
+
+        Ok(ResidencyUpdate {
+               bound: bound,
+               evicted: evicted,
+               future: future,
+           })
+    }
+}
+
+unsafe impl<F> DeviceOwned for ResidencyManager<F>
+    where F: 'static + Send + Sync
+{
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        self.queue.device()
+    }
+}
+
+/// Result of `ResidencyManager::make_resident`.
+pub struct ResidencyUpdate<P, F>
+    where P: GpuFuture,
+          F: 'static + Send + Sync
+{
+    /// Tiles that have been bound by this update. Their content is undefined.
+    pub bound: Vec<TileId>,
+    /// Tiles that have been evicted to make room for the new ones.
+    pub evicted: Vec<TileId>,
+    /// Represents the bind operation. Futures chained after it see the new bindings.
+    pub future: SparseBindFuture<P, Arc<SparseImage<F>>>,
+}
+
+// Bookkeeping of the resident tiles and of the slots of memory they use.
+struct TileCache {
+    capacity: usize,
+    // Slots of memory that aren't used by any tile.
+    free_slots: Vec<usize>,
+    // Resident tiles, with their slot and the value of `clock` when they were last requested.
+    tiles: HashMap<TileId, (usize, u64)>,
+    // Incremented at each request.
+    clock: u64,
+}
+
+impl TileCache {
+    fn new(capacity: usize) -> TileCache {
+        TileCache {
+            capacity: capacity,
+            free_slots: (0 .. capacity).rev().collect(),
+            tiles: HashMap::new(),
+            clock: 0,
+        }
+    }
+
+    // Computes the changes required to make `tiles` resident, without modifying the cache.
+    fn plan(&self, tiles: &[TileId]) -> Result<TilePlan, ResidencyError> {
+        let mut requested = tiles.to_vec();
+        requested.sort_by_key(key);
+        requested.dedup();
+
+        if requested.len() > self.capacity {
+            return Err(ResidencyError::TooManyTiles {
+                           requested: requested.len(),
+                           capacity: self.capacity,
+                       });
+        }
+
+        let missing = requested
+            .iter()
+            .cloned()
+            .filter(|tile| !self.tiles.contains_key(tile))
+            .collect::<Vec<_>>();
+
+        // Resident tiles that aren't requested this time, from the least recently used one.
+        // Since there are no more tiles requested than the capacity, there are always enough of
+        // them when the free slots run out.
+        let mut lru = self.tiles
+            .iter()
+            .filter(|&(tile, _)| requested.binary_search_by_key(&key(tile), key).is_err())
+            .map(|(&tile, &(slot, last_use))| (last_use, key(&tile), tile, slot))
+            .collect::<Vec<_>>();
+        lru.sort_by_key(|&(last_use, key, _, _)| (last_use, key));
+        let mut lru = lru.into_iter();
+
+        let mut free_slots = self.free_slots.iter().rev();
+        let mut bound = Vec::with_capacity(missing.len());
+        let mut evicted = Vec::new();
+
+        for tile in missing {
+            let slot = match free_slots.next() {
+                Some(&slot) => slot,
+                None => {
+                    let (_, _, lru_tile, slot) = lru.next().unwrap();
+                    evicted.push(lru_tile);
+                    slot
+                },
+            };
+
+            bound.push((tile, slot));
+        }
+
+        Ok(TilePlan {
+               requested: requested,
+               bound: bound,
+               evicted: evicted,
+           })
+    }
+
+    // Applies the changes computed by `plan`, marking all the requested tiles as used.
+    fn apply(&mut self, plan: TilePlan) {
+        self.clock += 1;
+        let clock = self.clock;
+
+        for tile in plan.evicted.iter() {
+            self.tiles.remove(tile);
+        }
+
+        let num_free_slots_used = plan.bound.len() - plan.evicted.len();
+        let remaining = self.free_slots.len() - num_free_slots_used;
+        self.free_slots.truncate(remaining);
+
+        for (tile, slot) in plan.bound {
+            self.tiles.insert(tile, (slot, clock));
+        }
+
+        for tile in plan.requested.iter() {
+            if let Some(&mut (_, ref mut last_use)) = self.tiles.get_mut(tile) {
+                *last_use = clock;
+            }
+        }
+    }
+}
+
+// Changes to make to a `TileCache`, computed by `TileCache::plan`.
+struct TilePlan {
+    // All the requested tiles, sorted and without duplicates.
+    requested: Vec<TileId>,
+    // Tiles that become resident, with their slot.
+    bound: Vec<(TileId, usize)>,
+    // Tiles that are evicted to make room for the new ones.
+    evicted: Vec<TileId>,
+}
+
+// Key by which the tiles are sorted.
+#[inline]
+fn key(tile: &TileId) -> (u32, u32, u32, u32) {
+    (tile.mip_level, tile.array_layer, tile.y, tile.x)
+}
+
+/// Error that can happen when making tiles resident.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ResidencyError {
+    /// More tiles were requested than the capacity of the manager.
+    TooManyTiles {
+        /// Number of distinct tiles that were requested.
+        requested: usize,
+        /// Maximum number of resident tiles.
+        capacity: usize,
+    },
+
+    /// A tile is outside of the image, or is part of the mip tail.
+    TileOutOfRange,
+
+    /// Error while binding memory to the image.
+    SparseBindError(SparseBindError),
+
+    /// Not enough memory.
+    OomError(OomError),
+}
+
+impl error::Error for ResidencyError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            ResidencyError::TooManyTiles { .. } => {
+                "more tiles were requested than the capacity of the manager"
+            },
+            ResidencyError::TileOutOfRange => {
+                "a tile is outside of the image, or is part of the mip tail"
+            },
+            ResidencyError::SparseBindError(_) => "error while binding memory to the image",
+            ResidencyError::OomError(_) => "not enough memory",
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            ResidencyError::SparseBindError(ref err) => Some(err),
+            ResidencyError::OomError(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ResidencyError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl From<SparseBindError> for ResidencyError {
+    #[inline]
+    fn from(err: SparseBindError) -> ResidencyError {
+        ResidencyError::SparseBindError(err)
+    }
+}
+
+impl From<OomError> for ResidencyError {
+    #[inline]
+    fn from(err: OomError) -> ResidencyError {
+        ResidencyError::OomError(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::iter;
+
+    use super::ResidencyError;
+    use super::ResidencyManager;
+    use super::SparseImage;
+    use super::TileCache;
+    use super::TileId;
+    use buffer::BufferUsage;
+    use buffer::CpuAccessibleBuffer;
+    use command_buffer::AutoCommandBufferBuilder;
+    use format::Format;
+    use image::Dimensions;
+    use image::ImageCreationError;
+    use sampler::Filter;
+    use sampler::MipmapMode;
+    use sampler::Sampler;
+    use sampler::SamplerAddressMode;
+    use sync::GpuFuture;
+    use tests::sample_image;
+
+    fn tile(x: u32) -> TileId {
+        TileId {
+            mip_level: 0,
+            array_layer: 0,
+            x: x,
+            y: 0,
+        }
+    }
+
+    // Plans and applies a request, like `make_resident` does when binding succeeds.
+    fn request(cache: &mut TileCache, tiles: &[TileId])
+               -> Result<(Vec<(TileId, usize)>, Vec<TileId>), ResidencyError> {
+        let plan = cache.plan(tiles)?;
+        let result = (plan.bound.clone(), plan.evicted.clone());
+        cache.apply(plan);
+        Ok(result)
+    }
+
+    #[test]
+    fn lru_eviction() {
+        let mut cache = TileCache::new(2);
+
+        let (bound, evicted) = request(&mut cache, &[tile(0)]).unwrap();
+        assert_eq!(bound, vec![(tile(0), 0)]);
+        assert!(evicted.is_empty());
+
+        let (bound, evicted) = request(&mut cache, &[tile(1)]).unwrap();
+        assert_eq!(bound, vec![(tile(1), 1)]);
+        assert!(evicted.is_empty());
+
+        // Requesting a resident tile doesn't bind anything but marks it as used.
+        let (bound, evicted) = request(&mut cache, &[tile(0)]).unwrap();
+        assert!(bound.is_empty());
+        assert!(evicted.is_empty());
+
+        // Tile 1 is the least recently used one, so its slot is reused.
+        let (bound, evicted) = request(&mut cache, &[tile(2)]).unwrap();
+        assert_eq!(bound, vec![(tile(2), 1)]);
+        assert_eq!(evicted, vec![tile(1)]);
+        assert!(cache.tiles.contains_key(&tile(0)));
+        assert!(!cache.tiles.contains_key(&tile(1)));
+    }
+
+    #[test]
+    fn requested_tiles_are_not_evicted() {
+        let mut cache = TileCache::new(2);
+        request(&mut cache, &[tile(0), tile(1)]).unwrap();
+
+        let (bound, evicted) = request(&mut cache, &[tile(1), tile(2), tile(2)]).unwrap();
+        assert_eq!(bound, vec![(tile(2), 0)]);
+        assert_eq!(evicted, vec![tile(0)]);
+    }
+
+    #[test]
+    fn plan_doesnt_modify_cache() {
+        let mut cache = TileCache::new(1);
+        request(&mut cache, &[tile(0)]).unwrap();
+
+        // If binding fails, the plan is dropped and tile 0 stays resident in its slot.
+        let plan = cache.plan(&[tile(1)]).unwrap();
+        assert_eq!(plan.bound, vec![(tile(1), 0)]);
+        assert_eq!(plan.evicted, vec![tile(0)]);
+        drop(plan);
+        assert_eq!(cache.tiles.len(), 1);
+        assert!(cache.tiles.contains_key(&tile(0)));
+
+        let (bound, evicted) = request(&mut cache, &[tile(1)]).unwrap();
+        assert_eq!(bound, vec![(tile(1), 0)]);
+        assert_eq!(evicted, vec![tile(0)]);
+        assert!(!cache.tiles.contains_key(&tile(0)));
+    }
+
+    #[test]
+    fn too_many_tiles() {
+        let mut cache = TileCache::new(1);
+        match request(&mut cache, &[tile(0), tile(1)]) {
+            Err(ResidencyError::TooManyTiles {
+                    requested: 2,
+                    capacity: 1,
+                }) => (),
+            _ => panic!(),
+        }
+        assert!(cache.tiles.is_empty());
+    }
+
+    #[test]
+    fn sparse_feature_not_enabled() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let dimensions = Dimensions::Dim2d {
+            width: 1024,
+            height: 1024,
+        };
+        match SparseImage::new(device, dimensions, Format::R8G8B8A8Unorm, Some(queue.family())) {
+            Err(ImageCreationError::SparseBindingFeatureNotEnabled) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn sample_resident_tiles() {
+        let (device, queue) = gfx_dev_and_queue!(sparse_binding, sparse_residency_image2d);
+        if !queue.family().supports_sparse_binding() {
+            return;
+        }
+
+        // The size of a tile depends on the implementation, so the image is made of 2x2 tiles.
+        let granularity = {
+            let dimensions = Dimensions::Dim2d {
+                width: 256,
+                height: 256,
+            };
+            let probe = match SparseImage::new(device.clone(),
+                                               dimensions,
+                                               Format::R8G8B8A8Unorm,
+                                               Some(queue.family())) {
+                Ok(i) => i,
+                Err(_) => return,
+            };
+            probe.sparse_memory_requirements().image_granularity
+        };
+        let dimensions = Dimensions::Dim2d {
+            width: granularity[0] * 2,
+            height: granularity[1] * 2,
+        };
+        let image = SparseImage::new(device.clone(),
+                                     dimensions,
+                                     Format::R8G8B8A8Unorm,
+                                     Some(queue.family()))
+            .unwrap();
+
+        let tile_size = image.tile_size();
+        let (mut residency, future) =
+            ResidencyManager::new(queue.clone(), image.clone(), 2 * tile_size).unwrap();
+
+        let top_left = TileId {
+            mip_level: 0,
+            array_layer: 0,
+            x: 0,
+            y: 0,
+        };
+        let bottom_right = TileId { x: 1, y: 1, ..top_left };
+        let update = residency.make_resident(future, vec![top_left, bottom_right]).unwrap();
+        assert_eq!(update.bound, vec![top_left, bottom_right]);
+        assert!(update.evicted.is_empty());
+        assert_eq!(residency.num_resident(), 2);
+
+        let num_texels = (granularity[0] * granularity[1]) as usize;
+        let mut cb = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap();
+        for &(tile, color) in [(top_left, [255u8, 0, 0, 255]), (bottom_right, [0, 255, 0, 255])]
+            .iter()
+        {
+            let data = CpuAccessibleBuffer::from_iter(device.clone(),
+                                                      BufferUsage::all(),
+                                                      Some(queue.family()),
+                                                      iter::repeat(color).take(num_texels))
+                .unwrap();
+            let (offset, extent) = image.tile_region(tile).unwrap();
+            cb = cb.copy_buffer_to_image_dimensions(data,
+                                                    image.clone(),
+                                                    offset,
+                                                    extent,
+                                                    tile.array_layer,
+                                                    1,
+                                                    tile.mip_level)
+                .unwrap();
+        }
+        let upload = update
+            .future
+            .then_execute(queue.clone(), cb.build().unwrap())
+            .unwrap();
+
+        let sampler = Sampler::new(device.clone(),
+                                   Filter::Nearest,
+                                   Filter::Nearest,
+                                   MipmapMode::Nearest,
+                                   SamplerAddressMode::ClampToEdge,
+                                   SamplerAddressMode::ClampToEdge,
+                                   SamplerAddressMode::ClampToEdge,
+                                   0.0,
+                                   1.0,
+                                   0.0,
+                                   0.0)
+            .unwrap();
+        let values = sample_image(upload,
+                                  queue.clone(),
+                                  image.clone(),
+                                  sampler,
+                                  &[[0.25, 0.25, 0.0], [0.75, 0.75, 0.0]]);
+        assert_eq!(values, vec![[1.0, 0.0, 0.0, 1.0], [0.0, 1.0, 0.0, 1.0]]);
+    }
+}
@@ -25,12 +25,14 @@ use image::ImageInner;
 use image::ImageLayout;
 use image::ImageUsage;
 use image::sys::ImageCreationError;
+use image::sys::ImageCreationOptions;
 use image::sys::UnsafeImage;
 use image::sys::UnsafeImageView;
 use image::traits::ImageAccess;
 use image::traits::ImageClearValue;
 use image::traits::ImageContent;
 use image::traits::ImageViewAccess;
+use instance::MemoryType;
 use instance::QueueFamily;
 use memory::pool::AllocLayout;
 use memory::pool::MemoryPool;
@@ -39,6 +41,8 @@ use memory::pool::StdMemoryPool;
 use sync::AccessError;
 use sync::Sharing;
 
+use VulkanObject;
+
 /// General-purpose image in device memory. Can be used for any usage, but will be slower than a
 /// specialized image.
 #[derive(Debug)]
@@ -69,10 +73,66 @@ pub struct StorageImage<F, A = Arc<StdMemoryPool>>
 
 impl<F> StorageImage<F> {
     /// Creates a new image with the given dimensions and format.
+    #[inline]
     pub fn new<'a, I>(device: Arc<Device>, dimensions: Dimensions, format: F, queue_families: I)
                       -> Result<Arc<StorageImage<F>>, ImageCreationError>
         where F: FormatDesc,
               I: IntoIterator<Item = QueueFamily<'a>>
+    {
+        StorageImage::new_impl(device, dimensions, format, 1, queue_families, None)
+    }
+
+    /// Same as `new`, but creates a two-dimensional multisampled image.
+    ///
+    /// Shaders can read and write the individual samples of the image. The
+    /// `shader_storage_image_multisample` feature must be enabled on the device, otherwise
+    /// `ShaderStorageImageMultisampleFeatureNotEnabled` is returned.
+    #[inline]
+    pub fn multisampled<'a, I>(device: Arc<Device>, dimensions: [u32; 2], samples: u32,
+                               format: F, queue_families: I)
+                               -> Result<Arc<StorageImage<F>>, ImageCreationError>
+        where F: FormatDesc,
+              I: IntoIterator<Item = QueueFamily<'a>>
+    {
+        let dimensions = Dimensions::Dim2d {
+            width: dimensions[0],
+            height: dimensions[1],
+        };
+        StorageImage::new_impl(device, dimensions, format, samples, queue_families, None)
+    }
+
+    /// Creates a new image with the given dimensions and format, whose memory is allocated from
+    /// the given memory type.
+    ///
+    /// Contrary to `new`, the memory type isn't automatically chosen. Returns
+    /// `MemoryTypeNotAllowed` if the image can't be put in memory of this type.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the memory type doesn't belong to the physical device of `device`.
+    ///
+    #[inline]
+    pub fn with_memory_type<'a, I>(device: Arc<Device>, dimensions: Dimensions, format: F,
+                                   queue_families: I, memory_type: MemoryType)
+                                   -> Result<Arc<StorageImage<F>>, ImageCreationError>
+        where F: FormatDesc,
+              I: IntoIterator<Item = QueueFamily<'a>>
+    {
+        assert_eq!(memory_type.physical_device().internal_object(),
+                   device.physical_device().internal_object());
+        StorageImage::new_impl(device,
+                               dimensions,
+                               format,
+                               1,
+                               queue_families,
+                               Some(memory_type))
+    }
+
+    fn new_impl<'a, I>(device: Arc<Device>, dimensions: Dimensions, format: F, samples: u32,
+                       queue_families: I, memory_type: Option<MemoryType>)
+                       -> Result<Arc<StorageImage<F>>, ImageCreationError>
+        where F: FormatDesc,
+              I: IntoIterator<Item = QueueFamily<'a>>
     {
         let is_depth = match format.format().ty() {
             FormatTy::Depth => true,
@@ -109,24 +169,31 @@ impl<F> StorageImage<F> {
                              usage,
                              format.format(),
                              dimensions.to_image_dimensions(),
-                             1,
+                             samples,
                              1,
                              Sharing::Exclusive::<Empty<u32>>,
-                             false,
-                             false)?
+                             ImageCreationOptions::none())?
         };
 
-        let mem_ty = {
-            let device_local = device
-                .physical_device()
-                .memory_types()
-                .filter(|t| (mem_reqs.memory_type_bits & (1 << t.id())) != 0)
-                .filter(|t| t.is_device_local());
-            let any = device
-                .physical_device()
-                .memory_types()
-                .filter(|t| (mem_reqs.memory_type_bits & (1 << t.id())) != 0);
-            device_local.chain(any).next().unwrap()
+        let mem_ty = match memory_type {
+            Some(ty) => {
+                if (mem_reqs.memory_type_bits & (1 << ty.id())) == 0 {
+                    return Err(ImageCreationError::MemoryTypeNotAllowed);
+                }
+                ty
+            },
+            None => {
+                let device_local = device
+                    .physical_device()
+                    .memory_types()
+                    .filter(|t| (mem_reqs.memory_type_bits & (1 << t.id())) != 0)
+                    .filter(|t| t.is_device_local());
+                let any = device
+                    .physical_device()
+                    .memory_types()
+                    .filter(|t| (mem_reqs.memory_type_bits & (1 << t.id())) != 0);
+                device_local.chain(any).next().unwrap()
+            },
         };
 
         let mem = MemoryPool::alloc(&Device::standard_pool(&device),
@@ -193,11 +260,6 @@ unsafe impl<F, A> ImageAccess for StorageImage<F, A>
         ImageLayout::General
     }
 
-    #[inline]
-    fn conflict_key(&self, _: u32, _: u32, _: u32, _: u32) -> u64 {
-        self.image.key()
-    }
-
     #[inline]
     fn try_gpu_lock(&self, _: bool, _: &Queue) -> Result<(), AccessError> {
         let val = self.gpu_lock.fetch_add(1, Ordering::SeqCst);
@@ -291,6 +353,14 @@ mod tests {
     use super::StorageImage;
     use format::Format;
     use image::Dimensions;
+    use image::ImageAccess;
+    use image::ImageCreationError;
+    use image::ImageDimensions;
+    use image::ImageUsage;
+    use image::sys::ImageCreationOptions;
+    use image::sys::UnsafeImage;
+    use std::iter::Empty;
+    use sync::Sharing;
 
     #[test]
     fn create() {
@@ -304,4 +374,117 @@ mod tests {
                                      Some(queue.family()))
             .unwrap();
     }
+
+    #[test]
+    fn aliasing_wrappers_conflict() {
+        let (device, queue) = gfx_dev_and_queue!();
+        let img = StorageImage::new(device.clone(),
+                                    Dimensions::Dim2d {
+                                        width: 32,
+                                        height: 32,
+                                    },
+                                    Format::R8G8B8A8Unorm,
+                                    Some(queue.family()))
+            .unwrap();
+        let other = StorageImage::new(device,
+                                      Dimensions::Dim2d {
+                                          width: 32,
+                                          height: 32,
+                                      },
+                                      Format::R8G8B8A8Unorm,
+                                      Some(queue.family()))
+            .unwrap();
+
+        let wrapped = unsafe { img.clone().forced_undefined_initial_layout(false) };
+
+        assert!(img.conflicts_image_all(&img.clone()));
+        assert!(img.conflicts_image_all(&wrapped));
+        assert!(wrapped.conflicts_image_all(&img));
+        assert_eq!(img.conflict_key_all(), wrapped.conflict_key_all());
+        assert!(!img.conflicts_image_all(&other));
+    }
+
+    #[test]
+    fn disjoint_layers_dont_conflict() {
+        let (device, queue) = gfx_dev_and_queue!();
+        let img = StorageImage::new(device,
+                                    Dimensions::Dim2dArray {
+                                        width: 32,
+                                        height: 32,
+                                        array_layers: 4,
+                                    },
+                                    Format::R8G8B8A8Unorm,
+                                    Some(queue.family()))
+            .unwrap();
+
+        assert!(!img.conflicts_image(0, 2, 0, 1, &img, 2, 2, 0, 1));
+        assert!(img.conflicts_image(0, 3, 0, 1, &img, 2, 2, 0, 1));
+    }
+
+    #[test]
+    fn fixed_memory_type() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        // Same parameters as the ones `StorageImage` uses for a color image.
+        let usage = ImageUsage {
+            transient_attachment: false,
+            depth_stencil_attachment: false,
+            ..ImageUsage::all()
+        };
+        let (_, mem_reqs) = unsafe {
+            UnsafeImage::new(device.clone(),
+                             usage,
+                             Format::R8G8B8A8Unorm,
+                             ImageDimensions::Dim2d {
+                                 width: 32,
+                                 height: 32,
+                                 array_layers: 1,
+                                 cubemap_compatible: false,
+                             },
+                             1,
+                             1,
+                             Sharing::Exclusive::<Empty<u32>>,
+                             ImageCreationOptions::none())
+                .unwrap()
+        };
+
+        for memory_type in device.physical_device().memory_types() {
+            let allowed = (mem_reqs.memory_type_bits & (1 << memory_type.id())) != 0;
+
+            match StorageImage::with_memory_type(device.clone(),
+                                                 Dimensions::Dim2d {
+                                                     width: 32,
+                                                     height: 32,
+                                                 },
+                                                 Format::R8G8B8A8Unorm,
+                                                 Some(queue.family()),
+                                                 memory_type) {
+                Ok(_) => assert!(allowed),
+                Err(ImageCreationError::MemoryTypeNotAllowed) => assert!(!allowed),
+                Err(_) => panic!(),
+            }
+        }
+    }
+
+    #[test]
+    fn multisampled_requires_feature() {
+        let (device, queue) = gfx_dev_and_queue!();
+        match StorageImage::multisampled(device, [32, 32], 4, Format::R8G8B8A8Unorm,
+                                         Some(queue.family())) {
+            Err(ImageCreationError::ShaderStorageImageMultisampleFeatureNotEnabled) => (),
+            Err(ImageCreationError::UnsupportedSamplesCount { .. }) => (), // unlikely but possible
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn multisampled() {
+        let (device, queue) = gfx_dev_and_queue!(shader_storage_image_multisample);
+        match StorageImage::multisampled(device, [32, 32], 4, Format::R8G8B8A8Unorm,
+                                         Some(queue.family())) {
+            Ok(img) => assert_eq!(img.samples(), 4),
+            Err(ImageCreationError::UnsupportedSamplesCount { .. }) => (),
+            Err(_) => panic!(),
+        }
+    }
 }
@@ -7,6 +7,7 @@
 // notice may not be copied, modified, or distributed except
 // according to those terms.
 
+use std::ops::Range;
 use std::sync::Arc;
 
 use device::Queue;
@@ -17,7 +18,9 @@ use image::Dimensions;
 use image::ImageDimensions;
 use image::ImageInner;
 use image::ImageLayout;
+use image::ImageUsage;
 use image::ViewType;
+use image::sys::ImageViewFormatError;
 use image::sys::UnsafeImage;
 use image::sys::UnsafeImageView;
 use image::traits::ImageAccess;
@@ -80,6 +83,78 @@ impl SwapchainImage {
         &self.swapchain
     }
 
+    /// Returns the usage the image was created with, as passed to `Swapchain::new`.
+    #[inline]
+    pub fn usage(&self) -> ImageUsage {
+        self.swapchain.usage()
+    }
+
+    /// Builds a view of some of the layers of the image.
+    ///
+    /// The view can be used as a framebuffer attachment or in a descriptor set like the image
+    /// itself. Since its parent is the `SwapchainImage`, accesses through the view and through
+    /// any other view of the same image are checked against each other.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if `array_layers` is empty or out of the range of the layers of the swapchain.
+    ///
+    #[inline]
+    pub fn layers_view(me: &Arc<SwapchainImage>, array_layers: Range<u32>)
+                       -> Result<Arc<SwapchainImageView>, OomError> {
+        let ty = SwapchainImage::layers_view_type(me, &array_layers);
+
+        let view = unsafe {
+            let image = me.my_image();
+            UnsafeImageView::raw(&image.image, ty, 0 .. 1, array_layers.clone())?
+        };
+
+        Ok(Arc::new(SwapchainImageView {
+                        image: me.clone(),
+                        view: view,
+                        array_layers: array_layers,
+                    }))
+    }
+
+    /// Same as `layers_view`, but the view interprets the content of the image with `format`.
+    ///
+    /// Unless `format` is the format of the swapchain, the swapchain images must have been
+    /// created with a mutable format and `format` must be compatible with the format of the
+    /// swapchain. Vulkano doesn't create mutable-format swapchains at the moment, so an error is
+    /// returned in that situation.
+    ///
+    /// # Panics
+    ///
+    /// - Panics if `array_layers` is empty or out of the range of the layers of the swapchain.
+    ///
+    pub fn view_with_format(me: &Arc<SwapchainImage>, format: Format, array_layers: Range<u32>)
+                            -> Result<Arc<SwapchainImageView>, ImageViewFormatError> {
+        let ty = SwapchainImage::layers_view_type(me, &array_layers);
+
+        let view = unsafe {
+            let image = me.my_image();
+            UnsafeImageView::with_format(&image.image, format, ty, 0 .. 1, array_layers.clone())?
+        };
+
+        Ok(Arc::new(SwapchainImageView {
+                        image: me.clone(),
+                        view: view,
+                        array_layers: array_layers,
+                    }))
+    }
+
+    // Checks the layers of a view and returns the type of the view.
+    fn layers_view_type(me: &SwapchainImage, array_layers: &Range<u32>) -> ViewType {
+        assert!(array_layers.start < array_layers.end);
+        assert!(array_layers.end <= me.swapchain.layers());
+
+        if array_layers.end - array_layers.start == 1 {
+            ViewType::Dim2d
+        } else {
+            ViewType::Dim2dArray
+        }
+    }
+
     #[inline]
     fn my_image(&self) -> ImageInner {
         self.swapchain.raw_image(self.image_offset).unwrap()
@@ -94,17 +169,12 @@ unsafe impl ImageAccess for SwapchainImage {
 
     #[inline]
     fn initial_layout_requirement(&self) -> ImageLayout {
-        ImageLayout::PresentSrc
+        self.swapchain.present_layout()
     }
 
     #[inline]
     fn final_layout_requirement(&self) -> ImageLayout {
-        ImageLayout::PresentSrc
-    }
-
-    #[inline]
-    fn conflict_key(&self, _: u32, _: u32, _: u32, _: u32) -> u64 {
-        self.my_image().image.key()
+        self.swapchain.present_layout()
     }
 
     #[inline]
@@ -158,9 +228,90 @@ unsafe impl ImageViewAccess for SwapchainImage {
 
     #[inline]
     fn descriptor_set_storage_image_layout(&self) -> ImageLayout {
+        ImageLayout::General
+    }
+
+    #[inline]
+    fn descriptor_set_combined_image_sampler_layout(&self) -> ImageLayout {
+        ImageLayout::ShaderReadOnlyOptimal
+    }
+
+    #[inline]
+    fn descriptor_set_sampled_image_layout(&self) -> ImageLayout {
         ImageLayout::ShaderReadOnlyOptimal
     }
 
+    #[inline]
+    fn descriptor_set_input_attachment_layout(&self) -> ImageLayout {
+        ImageLayout::ShaderReadOnlyOptimal
+    }
+
+    #[inline]
+    fn identity_swizzle(&self) -> bool {
+        true
+    }
+}
+
+/// A view of some layers of a `SwapchainImage`, possibly with a different format.
+///
+/// Created with `SwapchainImage::layers_view` or `SwapchainImage::view_with_format`. The view
+/// keeps the image alive, and is tracked as being the image itself when it comes to
+/// synchronization and layouts.
+pub struct SwapchainImageView {
+    image: Arc<SwapchainImage>,
+    view: UnsafeImageView,
+    array_layers: Range<u32>,
+}
+
+impl SwapchainImageView {
+    /// Returns the image this view was created from.
+    #[inline]
+    pub fn image(&self) -> &Arc<SwapchainImage> {
+        &self.image
+    }
+
+    /// Returns the layers of the image covered by the view.
+    #[inline]
+    pub fn array_layers(&self) -> Range<u32> {
+        self.array_layers.clone()
+    }
+}
+
+unsafe impl ImageViewAccess for SwapchainImageView {
+    #[inline]
+    fn parent(&self) -> &ImageAccess {
+        &*self.image
+    }
+
+    #[inline]
+    fn dimensions(&self) -> Dimensions {
+        let dims = self.image.dimensions();
+        let num_layers = self.array_layers.end - self.array_layers.start;
+
+        if num_layers == 1 {
+            Dimensions::Dim2d {
+                width: dims[0],
+                height: dims[1],
+            }
+        } else {
+            Dimensions::Dim2dArray {
+                width: dims[0],
+                height: dims[1],
+                array_layers: num_layers,
+            }
+        }
+    }
+
+    #[inline]
+    fn inner(&self) -> &UnsafeImageView {
+        &self.view
+    }
+
+    #[inline]
+    fn descriptor_set_storage_image_layout(&self) -> ImageLayout {
+        ImageLayout::General
+    }
+
     #[inline]
     fn descriptor_set_combined_image_sampler_layout(&self) -> ImageLayout {
         ImageLayout::ShaderReadOnlyOptimal
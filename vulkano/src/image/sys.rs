@@ -20,14 +20,20 @@ use std::mem;
 use std::ops::Range;
 use std::ptr;
 use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
 
 use device::Device;
+use device::DeviceOwned;
 use format::Format;
 use format::FormatTy;
 use image::ImageDimensions;
 use image::ImageUsage;
 use image::MipmapsCount;
 use image::ViewType;
+use instance::Limits;
+use instance::PhysicalDevice;
+use instance::SampleCounts;
 use memory::DeviceMemory;
 use memory::MemoryRequirements;
 use sync::Sharing;
@@ -64,12 +70,52 @@ pub struct UnsafeImage {
     // Features that are supported for this particular format.
     format_features: vk::FormatFeatureFlagBits,
 
+    // True if the image was created with `VK_IMAGE_CREATE_MUTABLE_FORMAT_BIT`.
+    mutable_format: bool,
+
+    // True if the image was created with sparse binding and sparse residency.
+    sparse: bool,
+
     // `vkDestroyImage` is called only if `needs_destruction` is true.
     needs_destruction: bool,
+
+    // True if memory has been bound to the image.
+    memory_bound: AtomicBool,
+}
+
+/// Options of an image created with `UnsafeImage::new`.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct ImageCreationOptions {
+    /// If true, the texels of the image are stored in a linear layout instead of an optimal
+    /// implementation-defined layout.
+    pub linear_tiling: bool,
+
+    /// If true, the image starts in the `Preinitialized` layout instead of `Undefined`, which
+    /// preserves the content of its memory on the first layout transition.
+    pub preinitialized_layout: bool,
+
+    /// If true, views of the image can be created with a format other than the format of the
+    /// image, as long as it is compatible. See `UnsafeImageView::with_format`.
+    pub mutable_format: bool,
+}
+
+impl ImageCreationOptions {
+    /// Builds an `ImageCreationOptions` with all the options disabled.
+    #[inline]
+    pub fn none() -> ImageCreationOptions {
+        ImageCreationOptions::default()
+    }
 }
 
 impl UnsafeImage {
-    /// Creates a new image and allocates memory for it.
+    /// Creates a new image.
+    ///
+    /// No memory is bound to the returned image. You can allocate or reuse memory that satisfies
+    /// the returned requirements whenever you want, then call `bind_memory`. The image must not
+    /// be used before that.
+    ///
+    /// See `ImageCreationOptions` for the tiling, the initial layout and whether views of the
+    /// image can have another format.
     ///
     /// # Panic
     ///
@@ -80,8 +126,7 @@ impl UnsafeImage {
     #[inline]
     pub unsafe fn new<'a, Mi, I>(device: Arc<Device>, usage: ImageUsage, format: Format,
                                  dimensions: ImageDimensions, num_samples: u32, mipmaps: Mi,
-                                 sharing: Sharing<I>, linear_tiling: bool,
-                                 preinitialized_layout: bool)
+                                 sharing: Sharing<I>, options: ImageCreationOptions)
                                  -> Result<(UnsafeImage, MemoryRequirements), ImageCreationError>
         where Mi: Into<MipmapsCount>,
               I: Iterator<Item = u32>
@@ -98,17 +143,115 @@ impl UnsafeImage {
                               num_samples,
                               mipmaps.into(),
                               sharing,
-                              linear_tiling,
-                              preinitialized_layout)
+                              options.linear_tiling,
+                              options.preinitialized_layout,
+                              options.mutable_format,
+                              false)
+    }
+
+    /// Creates a new two-dimensional image with sparse binding and sparse residency.
+    ///
+    /// Contrary to `new`, no memory must be bound to the whole image with `bind_memory`. Instead
+    /// memory is bound to individual tiles of the image by submitting sparse bind operations to a
+    /// queue. See `sparse_memory_requirements` for the size of the tiles. The memory type and the
+    /// alignment of the memory bound to the tiles must match the returned requirements.
+    ///
+    /// The image always uses optimal tiling, one sample and no mutable format.
+    ///
+    /// Returns an error if the `sparse_binding` or the `sparse_residency_image2d` feature is not
+    /// enabled on the device, if `dimensions` are not two-dimensional, or if the format doesn't
+    /// support sparse residency.
+    pub unsafe fn new_sparse<'a, Mi, I>(device: Arc<Device>, usage: ImageUsage, format: Format,
+                                        dimensions: ImageDimensions, mipmaps: Mi,
+                                        sharing: Sharing<I>)
+                                        -> Result<(UnsafeImage, MemoryRequirements),
+                                                  ImageCreationError>
+        where Mi: Into<MipmapsCount>,
+              I: Iterator<Item = u32>
+    {
+        match dimensions {
+            ImageDimensions::Dim2d { cubemap_compatible: false, .. } => (),
+            _ => return Err(ImageCreationError::UnsupportedDimensions { dimensions: dimensions }),
+        };
+
+        if !device.enabled_features().sparse_binding {
+            return Err(ImageCreationError::SparseBindingFeatureNotEnabled);
+        }
+        if !device.enabled_features().sparse_residency_image2d {
+            return Err(ImageCreationError::SparseResidencyImage2dFeatureNotEnabled);
+        }
+
+        // An empty list of sparse properties means that the format doesn't support sparse
+        // residency with these parameters.
+        if UnsafeImage::sparse_format_properties(device.physical_device(), format, usage)
+            .is_empty()
+        {
+            return Err(ImageCreationError::FormatNotSupported);
+        }
+
+        let sharing = match sharing {
+            Sharing::Exclusive => (vk::SHARING_MODE_EXCLUSIVE, SmallVec::<[u32; 8]>::new()),
+            Sharing::Concurrent(ids) => (vk::SHARING_MODE_CONCURRENT, ids.collect()),
+        };
+
+        UnsafeImage::new_impl(device,
+                              usage,
+                              format,
+                              dimensions,
+                              1,
+                              mipmaps.into(),
+                              sharing,
+                              false,
+                              false,
+                              false,
+                              true)
+    }
+
+    /// Returns the sparse properties of the given format when used by an image created with
+    /// `new_sparse`, with one entry per aspect.
+    ///
+    /// This can be called before creating the image in order to know the size of a tile.
+    /// Returns an empty list if the format doesn't support sparse residency with this usage.
+    pub fn sparse_format_properties(physical_device: PhysicalDevice, format: Format,
+                                    usage: ImageUsage)
+                                    -> Vec<SparseImageFormatProperties> {
+        unsafe {
+            let vk_i = physical_device.instance().pointers();
+
+            let mut num = 0;
+            vk_i.GetPhysicalDeviceSparseImageFormatProperties(physical_device.internal_object(),
+                                                              format as u32,
+                                                              vk::IMAGE_TYPE_2D,
+                                                              vk::SAMPLE_COUNT_1_BIT,
+                                                              usage.to_usage_bits(),
+                                                              vk::IMAGE_TILING_OPTIMAL,
+                                                              &mut num,
+                                                              ptr::null_mut());
+
+            let mut output = Vec::with_capacity(num as usize);
+            vk_i.GetPhysicalDeviceSparseImageFormatProperties(physical_device.internal_object(),
+                                                              format as u32,
+                                                              vk::IMAGE_TYPE_2D,
+                                                              vk::SAMPLE_COUNT_1_BIT,
+                                                              usage.to_usage_bits(),
+                                                              vk::IMAGE_TILING_OPTIMAL,
+                                                              &mut num,
+                                                              output.as_mut_ptr());
+            output.set_len(num as usize);
+
+            output.into_iter().map(SparseImageFormatProperties::from).collect()
+        }
     }
 
     // Non-templated version to avoid inlining and improve compile times.
     unsafe fn new_impl(device: Arc<Device>, usage: ImageUsage, format: Format,
                        dimensions: ImageDimensions, num_samples: u32, mipmaps: MipmapsCount,
                        (sh_mode, sh_indices): (vk::SharingMode, SmallVec<[u32; 8]>),
-                       linear_tiling: bool, preinitialized_layout: bool)
+                       linear_tiling: bool, preinitialized_layout: bool, mutable_format: bool,
+                       sparse: bool)
                        -> Result<(UnsafeImage, MemoryRequirements), ImageCreationError> {
-        // TODO: doesn't check that the proper features are enabled
+        // TODO: doesn't check that the proper features are enabled, except for sparse images
+        //       whose features are checked by `new_sparse`
 
         let vk = device.pointers();
         let vk_i = device.instance().pointers();
@@ -151,7 +294,7 @@ impl UnsafeImage {
             {
                 return Err(ImageCreationError::UnsupportedUsage);
             }
-            if device.loaded_extensions().khr_maintenance1 {
+            if device.effective_extensions().khr_maintenance1 {
                 if usage.transfer_source &&
                     (features & vk::FORMAT_FEATURE_TRANSFER_SRC_BIT_KHR == 0)
                 {
@@ -191,28 +334,7 @@ impl UnsafeImage {
         let mut capabilities_error = None;
 
         // Compute the maximum number of mipmaps.
-        // TODO: only compte if necessary?
-        let max_mipmaps = {
-            let smallest_dim: u32 = match dimensions {
-                ImageDimensions::Dim1d { width, .. } => width,
-                ImageDimensions::Dim2d { width, height, .. } => {
-                    if width < height { width } else { height }
-                },
-                ImageDimensions::Dim3d {
-                    width,
-                    height,
-                    depth,
-                } => {
-                    if width < height {
-                        if depth < width { depth } else { width }
-                    } else {
-                        if depth < height { depth } else { height }
-                    }
-                },
-            };
-
-            32 - smallest_dim.leading_zeros()
-        };
+        let max_mipmaps = dimensions.max_mip_levels();
 
         // Compute the number of mipmaps.
         let mipmaps = match mipmaps.into() {
@@ -243,90 +365,10 @@ impl UnsafeImage {
             return Err(ImageCreationError::UnsupportedSamplesCount { obtained: num_samples });
 
         } else {
-            let mut supported_samples = 0x7f; // all bits up to VK_SAMPLE_COUNT_64_BIT
-
-            if usage.sampled {
-                match format.ty() {
-                    FormatTy::Float | FormatTy::Compressed => {
-                        supported_samples &= device
-                            .physical_device()
-                            .limits()
-                            .sampled_image_color_sample_counts();
-                    },
-                    FormatTy::Uint | FormatTy::Sint => {
-                        supported_samples &= device
-                            .physical_device()
-                            .limits()
-                            .sampled_image_integer_sample_counts();
-                    },
-                    FormatTy::Depth => {
-                        supported_samples &= device
-                            .physical_device()
-                            .limits()
-                            .sampled_image_depth_sample_counts();
-                    },
-                    FormatTy::Stencil => {
-                        supported_samples &= device
-                            .physical_device()
-                            .limits()
-                            .sampled_image_stencil_sample_counts();
-                    },
-                    FormatTy::DepthStencil => {
-                        supported_samples &= device
-                            .physical_device()
-                            .limits()
-                            .sampled_image_depth_sample_counts();
-                        supported_samples &= device
-                            .physical_device()
-                            .limits()
-                            .sampled_image_stencil_sample_counts();
-                    },
-                }
-            }
-
-            if usage.storage {
-                supported_samples &= device
-                    .physical_device()
-                    .limits()
-                    .storage_image_sample_counts();
-            }
-
-            if usage.color_attachment || usage.depth_stencil_attachment ||
-                usage.input_attachment || usage.transient_attachment
-            {
-                match format.ty() {
-                    FormatTy::Float | FormatTy::Compressed | FormatTy::Uint | FormatTy::Sint => {
-                        supported_samples &= device
-                            .physical_device()
-                            .limits()
-                            .framebuffer_color_sample_counts();
-                    },
-                    FormatTy::Depth => {
-                        supported_samples &= device
-                            .physical_device()
-                            .limits()
-                            .framebuffer_depth_sample_counts();
-                    },
-                    FormatTy::Stencil => {
-                        supported_samples &= device
-                            .physical_device()
-                            .limits()
-                            .framebuffer_stencil_sample_counts();
-                    },
-                    FormatTy::DepthStencil => {
-                        supported_samples &= device
-                            .physical_device()
-                            .limits()
-                            .framebuffer_depth_sample_counts();
-                        supported_samples &= device
-                            .physical_device()
-                            .limits()
-                            .framebuffer_stencil_sample_counts();
-                    },
-                }
-            }
+            let supported_samples =
+                limits_sample_counts(device.physical_device().limits(), format, usage);
 
-            if (num_samples & supported_samples) == 0 {
+            if !supported_samples.supports(num_samples) {
                 let err = ImageCreationError::UnsupportedSamplesCount { obtained: num_samples };
                 capabilities_error = Some(err);
             }
@@ -444,6 +486,18 @@ impl UnsafeImage {
             _ => unreachable!(),
         };
 
+        let flags = if mutable_format {
+            flags | vk::IMAGE_CREATE_MUTABLE_FORMAT_BIT
+        } else {
+            flags
+        };
+
+        let flags = if sparse {
+            flags | vk::IMAGE_CREATE_SPARSE_BINDING_BIT | vk::IMAGE_CREATE_SPARSE_RESIDENCY_BIT
+        } else {
+            flags
+        };
+
         let usage = usage.to_usage_bits();
 
         // Now that all checks have been performed, if any of the check failed we query the Vulkan
@@ -462,10 +516,10 @@ impl UnsafeImage {
                                                                 ty,
                                                                 tiling,
                                                                 usage,
-                                                                0, /* TODO */
+                                                                flags,
                                                                 &mut output);
 
-            match check_errors(r) {
+            match check_errors(r, "vkGetPhysicalDeviceImageFormatProperties") {
                 Ok(_) => (),
                 Err(Error::FormatNotSupported) =>
                     return Err(ImageCreationError::FormatNotSupported),
@@ -514,7 +568,8 @@ impl UnsafeImage {
             check_errors(vk.CreateImage(device.internal_object(),
                                         &infos,
                                         ptr::null(),
-                                        &mut output))?;
+                                        &mut output),
+                         "vkCreateImage")?;
             output
         };
 
@@ -534,7 +589,10 @@ impl UnsafeImage {
             samples: num_samples,
             mipmaps: mipmaps,
             format_features: format_features,
+            mutable_format: mutable_format,
+            sparse: sparse,
             needs_destruction: true,
+            memory_bound: AtomicBool::new(false),
         };
 
         Ok((image, mem_reqs.into()))
@@ -563,29 +621,124 @@ impl UnsafeImage {
             samples: samples,
             mipmaps: mipmaps,
             format_features: output.optimalTilingFeatures,
+            mutable_format: false,
+            sparse: false,
             needs_destruction: false, // TODO: pass as parameter
+            memory_bound: AtomicBool::new(true),
+        }
+    }
+
+    /// Returns the memory requirements of the image.
+    ///
+    /// These are the same requirements as the ones returned by `new`.
+    pub fn memory_requirements(&self) -> MemoryRequirements {
+        unsafe {
+            let vk = self.device.pointers();
+            let mut output: vk::MemoryRequirements = mem::uninitialized();
+            vk.GetImageMemoryRequirements(self.device.internal_object(), self.image, &mut output);
+            output.into()
         }
     }
 
-    pub unsafe fn bind_memory(&self, memory: &DeviceMemory, offset: usize) -> Result<(), OomError> {
+    /// Returns the sparse memory requirements of the image, with one entry per aspect.
+    ///
+    /// Returns an empty list if the image wasn't created with `new_sparse`.
+    pub fn sparse_memory_requirements(&self) -> Vec<SparseImageMemoryRequirements> {
+        if !self.sparse {
+            return Vec::new();
+        }
+
+        unsafe {
+            let vk = self.device.pointers();
+
+            let mut num = 0;
+            vk.GetImageSparseMemoryRequirements(self.device.internal_object(),
+                                                self.image,
+                                                &mut num,
+                                                ptr::null_mut());
+
+            let mut output = Vec::with_capacity(num as usize);
+            vk.GetImageSparseMemoryRequirements(self.device.internal_object(),
+                                                self.image,
+                                                &mut num,
+                                                output.as_mut_ptr());
+            output.set_len(num as usize);
+
+            output.into_iter().map(SparseImageMemoryRequirements::from).collect()
+        }
+    }
+
+    /// Returns the mip tails of the image, with one entry per aspect that has a mip tail.
+    ///
+    /// Returns an empty list if the image wasn't created with `new_sparse` or if all of its
+    /// mipmap levels are divided in tiles.
+    pub fn sparse_mip_tails(&self) -> Vec<SparseImageMipTail> {
+        let array_layers = self.dimensions().array_layers();
+
+        self.sparse_memory_requirements()
+            .into_iter()
+            .filter_map(|req| req.mip_tail(self.mipmap_levels(), array_layers))
+            .collect()
+    }
+
+    /// Returns true if memory has been bound to the image.
+    ///
+    /// Always returns false for sparse images, as their memory is bound tile per tile.
+    #[inline]
+    pub fn is_memory_bound(&self) -> bool {
+        self.memory_bound.load(Ordering::SeqCst)
+    }
+
+    /// Binds `memory` to the image, starting at `offset` bytes.
+    ///
+    /// Returns an error if the memory doesn't satisfy the requirements of the image, or if memory
+    /// has already been bound to it. Several images can be bound to the same memory.
+    ///
+    /// # Safety
+    ///
+    /// - The memory must be kept alive for as long as the image is used.
+    /// - If the memory is shared with other resources, you must ensure that their accesses are
+    ///   synchronized with the accesses to this image.
+    ///
+    pub unsafe fn bind_memory(&self, memory: &DeviceMemory, offset: usize)
+                              -> Result<(), ImageBindMemoryError> {
         let vk = self.device.pointers();
 
-        // We check for correctness in debug mode.
-        debug_assert!({
-                          let mut mem_reqs = mem::uninitialized();
-                          vk.GetImageMemoryRequirements(self.device.internal_object(),
-                                                        self.image,
-                                                        &mut mem_reqs);
-                          mem_reqs.size <= (memory.size() - offset) as u64 &&
-                              (offset as u64 % mem_reqs.alignment) == 0 &&
-                              mem_reqs.memoryTypeBits & (1 << memory.memory_type().id()) != 0
-                      });
+        assert_eq!(memory.device().internal_object(), self.device.internal_object());
+        assert!(!self.sparse, "Can't bind memory to a sparse image with bind_memory");
+
+        let mem_reqs = self.memory_requirements();
+        if mem_reqs.memory_type_bits & (1 << memory.memory_type().id()) == 0 {
+            return Err(ImageBindMemoryError::MemoryTypeNotAllowed);
+        }
+        if offset % mem_reqs.alignment != 0 {
+            return Err(ImageBindMemoryError::OffsetNotAligned {
+                           offset: offset,
+                           alignment: mem_reqs.alignment,
+                       });
+        }
+        if offset > memory.size() || mem_reqs.size > memory.size() - offset {
+            return Err(ImageBindMemoryError::MemoryTooSmall {
+                           required: mem_reqs.size,
+                           available: memory.size().saturating_sub(offset),
+                       });
+        }
+
+        if self.memory_bound.swap(true, Ordering::SeqCst) {
+            return Err(ImageBindMemoryError::AlreadyBound);
+        }
 
-        check_errors(vk.BindImageMemory(self.device.internal_object(),
-                                        self.image,
-                                        memory.internal_object(),
-                                        offset as vk::DeviceSize))?;
-        Ok(())
+        match check_errors(vk.BindImageMemory(self.device.internal_object(),
+                                              self.image,
+                                              memory.internal_object(),
+                                              offset as vk::DeviceSize),
+                           "vkBindImageMemory") {
+            Ok(_) => Ok(()),
+            Err(err) => {
+                self.memory_bound.store(false, Ordering::SeqCst);
+                Err(err.into())
+            },
+        }
     }
 
     #[inline]
@@ -603,6 +756,18 @@ impl UnsafeImage {
         self.mipmaps
     }
 
+    /// Returns true if views of the image can use a format other than the image's format.
+    #[inline]
+    pub fn mutable_format(&self) -> bool {
+        self.mutable_format
+    }
+
+    /// Returns true if the image was created with `new_sparse`.
+    #[inline]
+    pub fn is_sparse(&self) -> bool {
+        self.sparse
+    }
+
     #[inline]
     pub fn dimensions(&self) -> ImageDimensions {
         self.dimensions
@@ -794,6 +959,138 @@ impl Drop for UnsafeImage {
     }
 }
 
+/// Error that can happen when binding memory to an image.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ImageBindMemoryError {
+    /// Not enough memory.
+    OomError(OomError),
+    /// Memory has already been bound to the image.
+    AlreadyBound,
+    /// The memory type of the memory is not allowed by the requirements of the image.
+    MemoryTypeNotAllowed,
+    /// The offset is not a multiple of the alignment required by the image.
+    OffsetNotAligned { offset: usize, alignment: usize },
+    /// The memory after the offset is smaller than the size required by the image.
+    MemoryTooSmall { required: usize, available: usize },
+}
+
+impl error::Error for ImageBindMemoryError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            ImageBindMemoryError::OomError(_) => "not enough memory available",
+            ImageBindMemoryError::AlreadyBound => "memory has already been bound to the image",
+            ImageBindMemoryError::MemoryTypeNotAllowed =>
+                "the memory type of the memory is not allowed by the requirements of the image",
+            ImageBindMemoryError::OffsetNotAligned { .. } =>
+                "the offset is not a multiple of the alignment required by the image",
+            ImageBindMemoryError::MemoryTooSmall { .. } =>
+                "the memory after the offset is smaller than the size required by the image",
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            ImageBindMemoryError::OomError(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ImageBindMemoryError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl From<OomError> for ImageBindMemoryError {
+    #[inline]
+    fn from(err: OomError) -> ImageBindMemoryError {
+        ImageBindMemoryError::OomError(err)
+    }
+}
+
+impl From<Error> for ImageBindMemoryError {
+    #[inline]
+    fn from(err: Error) -> ImageBindMemoryError {
+        match err {
+            err @ Error::OutOfHostMemory => ImageBindMemoryError::OomError(OomError::from(err)),
+            err @ Error::OutOfDeviceMemory => ImageBindMemoryError::OomError(OomError::from(err)),
+            _ => panic!("unexpected error: {:?}", err),
+        }
+    }
+}
+
+/// Returns the numbers of samples per pixel allowed by the limits of the physical device for an
+/// image with the given format and usage.
+///
+/// The actual list of supported sample counts can be more restrictive, see
+/// `PhysicalDevice::supported_sample_counts`.
+pub(crate) fn limits_sample_counts(limits: Limits, format: Format, usage: ImageUsage)
+                                   -> SampleCounts {
+    let mut supported_samples = SampleCounts::all();
+
+    if usage.sampled {
+        match format.ty() {
+            FormatTy::Float | FormatTy::Compressed => {
+                supported_samples = supported_samples
+                    .intersection(&limits.sampled_image_color_sample_counts());
+            },
+            FormatTy::Uint | FormatTy::Sint => {
+                supported_samples = supported_samples
+                    .intersection(&limits.sampled_image_integer_sample_counts());
+            },
+            FormatTy::Depth => {
+                supported_samples = supported_samples
+                    .intersection(&limits.sampled_image_depth_sample_counts());
+            },
+            FormatTy::Stencil => {
+                supported_samples = supported_samples
+                    .intersection(&limits.sampled_image_stencil_sample_counts());
+            },
+            FormatTy::DepthStencil => {
+                supported_samples = supported_samples
+                    .intersection(&limits.sampled_image_depth_sample_counts());
+                supported_samples = supported_samples
+                    .intersection(&limits.sampled_image_stencil_sample_counts());
+            },
+        }
+    }
+
+    if usage.storage {
+        supported_samples = supported_samples.intersection(&limits.storage_image_sample_counts());
+    }
+
+    if usage.color_attachment || usage.depth_stencil_attachment || usage.input_attachment ||
+        usage.transient_attachment
+    {
+        match format.ty() {
+            FormatTy::Float | FormatTy::Compressed | FormatTy::Uint | FormatTy::Sint => {
+                supported_samples = supported_samples
+                    .intersection(&limits.framebuffer_color_sample_counts());
+            },
+            FormatTy::Depth => {
+                supported_samples = supported_samples
+                    .intersection(&limits.framebuffer_depth_sample_counts());
+            },
+            FormatTy::Stencil => {
+                supported_samples = supported_samples
+                    .intersection(&limits.framebuffer_stencil_sample_counts());
+            },
+            FormatTy::DepthStencil => {
+                supported_samples = supported_samples
+                    .intersection(&limits.framebuffer_depth_sample_counts());
+                supported_samples = supported_samples
+                    .intersection(&limits.framebuffer_stencil_sample_counts());
+            },
+        }
+    }
+
+    supported_samples
+}
+
 /// Error that can happen when creating an instance.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum ImageCreationError {
@@ -814,6 +1111,14 @@ pub enum ImageCreationError {
     UnsupportedUsage,
     /// The `shader_storage_image_multisample` feature must be enabled to create such an image.
     ShaderStorageImageMultisampleFeatureNotEnabled,
+    /// The `sparse_binding` feature must be enabled to create a sparse image.
+    SparseBindingFeatureNotEnabled,
+    /// The `sparse_residency_image2d` feature must be enabled to create a sparse 2D image.
+    SparseResidencyImage2dFeatureNotEnabled,
+    /// The memory type that was requested isn't in the memory types supported by the image.
+    MemoryTypeNotAllowed,
+    /// Error while binding memory to the image.
+    BindMemoryError(ImageBindMemoryError),
 }
 
 impl error::Error for ImageCreationError {
@@ -836,6 +1141,15 @@ impl error::Error for ImageCreationError {
                 "the `shader_storage_image_multisample` feature must be enabled to create such \
                  an image"
             },
+            ImageCreationError::SparseBindingFeatureNotEnabled =>
+                "the `sparse_binding` feature must be enabled to create a sparse image",
+            ImageCreationError::SparseResidencyImage2dFeatureNotEnabled =>
+                "the `sparse_residency_image2d` feature must be enabled to create a sparse 2D \
+                 image",
+            ImageCreationError::MemoryTypeNotAllowed =>
+                "the memory type that was requested isn't in the memory types supported by the \
+                 image",
+            ImageCreationError::BindMemoryError(_) => "error while binding memory to the image",
         }
     }
 
@@ -843,6 +1157,7 @@ impl error::Error for ImageCreationError {
     fn cause(&self) -> Option<&error::Error> {
         match *self {
             ImageCreationError::OomError(ref err) => Some(err),
+            ImageCreationError::BindMemoryError(ref err) => Some(err),
             _ => None,
         }
     }
@@ -862,6 +1177,17 @@ impl From<OomError> for ImageCreationError {
     }
 }
 
+impl From<ImageBindMemoryError> for ImageCreationError {
+    #[inline]
+    fn from(err: ImageBindMemoryError) -> ImageCreationError {
+        match err {
+            ImageBindMemoryError::OomError(err) => ImageCreationError::OomError(err),
+            ImageBindMemoryError::MemoryTypeNotAllowed => ImageCreationError::MemoryTypeNotAllowed,
+            err => ImageCreationError::BindMemoryError(err),
+        }
+    }
+}
+
 impl From<Error> for ImageCreationError {
     #[inline]
     fn from(err: Error) -> ImageCreationError {
@@ -897,19 +1223,407 @@ pub struct LinearLayout {
     pub depth_pitch: usize,
 }
 
+/// Describes how memory must be bound to an aspect of a sparse image.
+///
+/// Obtained by calling `sparse_memory_requirements` on the image.
+///
+/// Each mipmap level whose index is inferior to `mip_tail_first_lod` is divided in tiles of
+/// `image_granularity` texels, whose memory can be bound individually. The size in bytes of each
+/// tile is the alignment of the memory requirements of the image. The remaining mipmap levels
+/// form the *mip tail*, which is bound as a whole with an opaque bind operation.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SparseImageMemoryRequirements {
+    /// True if these requirements apply to the color aspect.
+    pub color: bool,
+    /// True if these requirements apply to the depth aspect.
+    pub depth: bool,
+    /// True if these requirements apply to the stencil aspect.
+    pub stencil: bool,
+    /// True if these requirements apply to the metadata aspect.
+    pub metadata: bool,
+    /// Width, height and depth of a tile in texels.
+    pub image_granularity: [u32; 3],
+    /// If true, all the array layers share a single mip tail.
+    pub single_mip_tail: bool,
+    /// If true, the first mipmap level whose dimensions are not a multiple of the granularity is
+    /// part of the mip tail.
+    pub aligned_mip_size: bool,
+    /// If true, the tiles don't use the standard block shapes.
+    pub nonstandard_block_size: bool,
+    /// First mipmap level of the mip tail.
+    pub mip_tail_first_lod: u32,
+    /// Size in bytes of the mip tail of an array layer.
+    pub mip_tail_size: usize,
+    /// Opaque offset of the mip tail of the first array layer.
+    pub mip_tail_offset: usize,
+    /// Distance between the mip tails of two consecutive array layers, if `single_mip_tail` is
+    /// false.
+    pub mip_tail_stride: usize,
+}
+
+#[doc(hidden)]
+impl From<vk::SparseImageMemoryRequirements> for SparseImageMemoryRequirements {
+    #[inline]
+    fn from(val: vk::SparseImageMemoryRequirements) -> SparseImageMemoryRequirements {
+        let aspects = val.formatProperties.aspectMask;
+        let flags = val.formatProperties.flags;
+        let granularity = val.formatProperties.imageGranularity;
+
+        SparseImageMemoryRequirements {
+            color: (aspects & vk::IMAGE_ASPECT_COLOR_BIT) != 0,
+            depth: (aspects & vk::IMAGE_ASPECT_DEPTH_BIT) != 0,
+            stencil: (aspects & vk::IMAGE_ASPECT_STENCIL_BIT) != 0,
+            metadata: (aspects & vk::IMAGE_ASPECT_METADATA_BIT) != 0,
+            image_granularity: [granularity.width, granularity.height, granularity.depth],
+            single_mip_tail: (flags & vk::SPARSE_IMAGE_FORMAT_SINGLE_MIPTAIL_BIT) != 0,
+            aligned_mip_size: (flags & vk::SPARSE_IMAGE_FORMAT_ALIGNED_MIP_SIZE_BIT) != 0,
+            nonstandard_block_size: (flags & vk::SPARSE_IMAGE_FORMAT_NONSTANDARD_BLOCK_SIZE_BIT) !=
+                0,
+            mip_tail_first_lod: val.imageMipTailFirstLod,
+            mip_tail_size: val.imageMipTailSize as usize,
+            mip_tail_offset: val.imageMipTailOffset as usize,
+            mip_tail_stride: val.imageMipTailStride as usize,
+        }
+    }
+}
+
+impl SparseImageMemoryRequirements {
+    /// Returns true if the given mipmap level is part of the mip tail.
+    #[inline]
+    pub fn is_in_mip_tail(&self, mip_level: u32) -> bool {
+        mip_level >= self.mip_tail_first_lod
+    }
+
+    /// Returns the description of the mip tail of an image with these requirements and the
+    /// given number of mipmap levels and array layers.
+    ///
+    /// Returns `None` if the image doesn't have a mip tail.
+    pub fn mip_tail(&self, mipmap_levels: u32, array_layers: u32) -> Option<SparseImageMipTail> {
+        if self.mip_tail_first_lod >= mipmap_levels {
+            return None;
+        }
+
+        let num_tails = if self.single_mip_tail {
+            1
+        } else {
+            array_layers
+        };
+
+        let regions = (0 .. num_tails)
+            .map(|layer| {
+                SparseImageMipTailRegion {
+                    array_layer: if self.single_mip_tail {
+                        None
+                    } else {
+                        Some(layer)
+                    },
+                    offset: self.mip_tail_offset + layer as usize * self.mip_tail_stride,
+                    size: self.mip_tail_size,
+                }
+            })
+            .collect();
+
+        Some(SparseImageMipTail {
+                 first_lod: self.mip_tail_first_lod,
+                 metadata: self.metadata,
+                 regions: regions,
+             })
+    }
+}
+
+/// Describes the mip tail of an aspect of a sparse image.
+///
+/// The mip tail is made of one or more opaque regions, each of which must be bound to memory as
+/// a whole. Contrary to the other mipmap levels, its content can't be bound tile by tile.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SparseImageMipTail {
+    /// First mipmap level that is part of the mip tail. All the levels after this one are part
+    /// of the mip tail as well.
+    pub first_lod: u32,
+    /// True if this is the mip tail of the metadata aspect.
+    pub metadata: bool,
+    /// The opaque regions that form the mip tail. There is a single region if all the array
+    /// layers share the same mip tail, and one region per array layer otherwise.
+    pub regions: Vec<SparseImageMipTailRegion>,
+}
+
+impl SparseImageMipTail {
+    /// Returns the number of bytes of memory needed to bind all the regions of the mip tail,
+    /// if each region starts at a multiple of `alignment`.
+    ///
+    /// `alignment` is the alignment of the memory requirements of the image.
+    #[inline]
+    pub fn memory_size(&self, alignment: usize) -> usize {
+        self.regions
+            .iter()
+            .map(|region| (region.size + alignment - 1) / alignment * alignment)
+            .sum()
+    }
+}
+
+/// An opaque region of the mip tail of a sparse image.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SparseImageMipTailRegion {
+    /// The array layer whose mip tail this is, or `None` if the region is shared by all the
+    /// array layers.
+    pub array_layer: Option<u32>,
+    /// Opaque offset of the region within the image.
+    pub offset: usize,
+    /// Size in bytes of the region.
+    pub size: usize,
+}
+
+/// Describes how a format can be used by a sparse image.
+///
+/// Obtained by calling `UnsafeImage::sparse_format_properties`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SparseImageFormatProperties {
+    /// True if these properties apply to the color aspect.
+    pub color: bool,
+    /// True if these properties apply to the depth aspect.
+    pub depth: bool,
+    /// True if these properties apply to the stencil aspect.
+    pub stencil: bool,
+    /// True if these properties apply to the metadata aspect.
+    pub metadata: bool,
+    /// Width, height and depth of a tile in texels.
+    pub image_granularity: [u32; 3],
+    /// If true, all the array layers share a single mip tail.
+    pub single_mip_tail: bool,
+    /// If true, the first mipmap level whose dimensions are not a multiple of the granularity is
+    /// part of the mip tail.
+    pub aligned_mip_size: bool,
+    /// If true, the tiles don't use the standard block shapes.
+    pub nonstandard_block_size: bool,
+}
+
+#[doc(hidden)]
+impl From<vk::SparseImageFormatProperties> for SparseImageFormatProperties {
+    #[inline]
+    fn from(val: vk::SparseImageFormatProperties) -> SparseImageFormatProperties {
+        let aspects = val.aspectMask;
+        let flags = val.flags;
+        let granularity = val.imageGranularity;
+
+        SparseImageFormatProperties {
+            color: (aspects & vk::IMAGE_ASPECT_COLOR_BIT) != 0,
+            depth: (aspects & vk::IMAGE_ASPECT_DEPTH_BIT) != 0,
+            stencil: (aspects & vk::IMAGE_ASPECT_STENCIL_BIT) != 0,
+            metadata: (aspects & vk::IMAGE_ASPECT_METADATA_BIT) != 0,
+            image_granularity: [granularity.width, granularity.height, granularity.depth],
+            single_mip_tail: (flags & vk::SPARSE_IMAGE_FORMAT_SINGLE_MIPTAIL_BIT) != 0,
+            aligned_mip_size: (flags & vk::SPARSE_IMAGE_FORMAT_ALIGNED_MIP_SIZE_BIT) != 0,
+            nonstandard_block_size: (flags & vk::SPARSE_IMAGE_FORMAT_NONSTANDARD_BLOCK_SIZE_BIT) !=
+                0,
+        }
+    }
+}
+
+/// Error that can happen when creating a view with a format other than the image's format.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ImageViewFormatError {
+    /// Not enough memory.
+    OomError(OomError),
+    /// The image wasn't created with a mutable format.
+    ImageFormatNotMutable,
+    /// The format is not compatible with the format of the image.
+    IncompatibleFormat,
+}
+
+impl error::Error for ImageViewFormatError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            ImageViewFormatError::OomError(_) => "not enough memory available",
+            ImageViewFormatError::ImageFormatNotMutable =>
+                "the image wasn't created with a mutable format",
+            ImageViewFormatError::IncompatibleFormat =>
+                "the format is not compatible with the format of the image",
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            ImageViewFormatError::OomError(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ImageViewFormatError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl From<OomError> for ImageViewFormatError {
+    #[inline]
+    fn from(err: OomError) -> ImageViewFormatError {
+        ImageViewFormatError::OomError(err)
+    }
+}
+
+/// Error that can happen when creating a view with a minimum LOD.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ImageViewMinLodError {
+    /// Not enough memory.
+    OomError(OomError),
+    /// The `VK_EXT_image_view_min_lod` extension was not enabled on the device.
+    ExtensionNotEnabled,
+    /// The minimum LOD is negative, or higher than the last mipmap level of the view.
+    MinLodOutOfRange {
+        /// The minimum LOD that was requested, relative to the first mipmap level of the view.
+        min_lod: f32,
+        /// The highest minimum LOD that is allowed for this view.
+        max: f32,
+    },
+}
+
+impl error::Error for ImageViewMinLodError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            ImageViewMinLodError::OomError(_) => "not enough memory available",
+            ImageViewMinLodError::ExtensionNotEnabled =>
+                "the `VK_EXT_image_view_min_lod` extension was not enabled",
+            ImageViewMinLodError::MinLodOutOfRange { .. } =>
+                "the minimum LOD is out of range of the mipmap levels of the view",
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            ImageViewMinLodError::OomError(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ImageViewMinLodError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl From<OomError> for ImageViewMinLodError {
+    #[inline]
+    fn from(err: OomError) -> ImageViewMinLodError {
+        ImageViewMinLodError::OomError(err)
+    }
+}
+
+// Checks that `min_lod`, relative to the first level of a view of `num_levels` mipmap levels,
+// doesn't go past the last level of the view.
+fn check_min_lod(min_lod: f32, num_levels: u32) -> Result<(), ImageViewMinLodError> {
+    let max = (num_levels - 1) as f32;
+    if !(min_lod >= 0.0 && min_lod <= max) {
+        return Err(ImageViewMinLodError::MinLodOutOfRange {
+                       min_lod: min_lod,
+                       max: max,
+                   });
+    }
+
+    Ok(())
+}
+
 pub struct UnsafeImageView {
     view: vk::ImageView,
     device: Arc<Device>,
     usage: vk::ImageUsageFlagBits,
     identity_swizzle: bool,
     format: Format,
+    min_lod: f32,
 }
 
 impl UnsafeImageView {
     /// See the docs of new().
+    #[inline]
     pub unsafe fn raw(image: &UnsafeImage, ty: ViewType, mipmap_levels: Range<u32>,
                       array_layers: Range<u32>)
                       -> Result<UnsafeImageView, OomError> {
+        UnsafeImageView::raw_impl(image, image.format, ty, mipmap_levels, array_layers, 0.0)
+    }
+
+    /// Creates a new view of the image that interprets its content with the given format.
+    ///
+    /// Unless `format` is the format of the image, the image must have been created with
+    /// `mutable_format` and `format` must be compatible with the image's format as defined by
+    /// `Format::is_compatible_with`. For example this can be used to read the same data both
+    /// as `R8G8B8A8Unorm` and as `R8G8B8A8Srgb`.
+    ///
+    /// See the docs of new() for the other parameters.
+    pub unsafe fn with_format(image: &UnsafeImage, format: Format, ty: ViewType,
+                              mipmap_levels: Range<u32>, array_layers: Range<u32>)
+                              -> Result<UnsafeImageView, ImageViewFormatError> {
+        if format != image.format {
+            if !image.mutable_format {
+                return Err(ImageViewFormatError::ImageFormatNotMutable);
+            }
+
+            if !image.format.is_compatible_with(format) {
+                return Err(ImageViewFormatError::IncompatibleFormat);
+            }
+        }
+
+        Ok(UnsafeImageView::raw_impl(image, format, ty, mipmap_levels, array_layers, 0.0)?)
+    }
+
+    /// Creates a new view of the image whose sampling is clamped to `min_lod`. This is typically
+    /// used when streaming textures, in order to prevent sampling the mipmap levels that
+    /// haven't been uploaded yet without having to create new samplers.
+    ///
+    /// `min_lod` is relative to the first level of `mipmap_levels`. For example if
+    /// `mipmap_levels` is `2 .. 8` and `min_lod` is `1.0`, the levels 2 and 3 of the image are
+    /// never sampled. It must be between 0 and the index of the last level of the view.
+    ///
+    /// Requires the `VK_EXT_image_view_min_lod` extension. On devices that don't support it,
+    /// `resident_level_views` can be used instead.
+    ///
+    /// See the docs of new() for the other parameters.
+    pub unsafe fn with_min_lod(image: &UnsafeImage, ty: ViewType, mipmap_levels: Range<u32>,
+                               array_layers: Range<u32>, min_lod: f32)
+                               -> Result<UnsafeImageView, ImageViewMinLodError> {
+        if !image.device.loaded_extensions().ext_image_view_min_lod {
+            return Err(ImageViewMinLodError::ExtensionNotEnabled);
+        }
+
+        assert!(mipmap_levels.end > mipmap_levels.start);
+        check_min_lod(min_lod, mipmap_levels.end - mipmap_levels.start)?;
+
+        Ok(UnsafeImageView::raw_impl(image, image.format, ty, mipmap_levels, array_layers,
+                                     min_lod)?)
+    }
+
+    /// Creates one view for each level of `mipmap_levels`. The view at index `n` starts at the
+    /// level `mipmap_levels.start + n` and ends at `mipmap_levels.end`.
+    ///
+    /// This is the fallback of `with_min_lod` for the devices that don't support
+    /// `VK_EXT_image_view_min_lod`: when only the levels starting from `mipmap_levels.start + n`
+    /// are resident, bind the view at index `n`. Contrary to a minimum LOD, this changes the
+    /// dimensions of the view and therefore the LOD that is computed when sampling it, which
+    /// means that the selected level may differ from the one that `with_min_lod` would select.
+    ///
+    /// See the docs of new() for the other parameters.
+    pub unsafe fn resident_level_views(image: &UnsafeImage, ty: ViewType,
+                                       mipmap_levels: Range<u32>, array_layers: Range<u32>)
+                                       -> Result<Vec<UnsafeImageView>, OomError> {
+        mipmap_levels
+            .clone()
+            .map(|first| {
+                UnsafeImageView::raw(image, ty, first .. mipmap_levels.end, array_layers.clone())
+            })
+            .collect()
+    }
+
+    // `min_lod` is relative to `mipmap_levels.start` and has already been checked. It is only
+    // passed to Vulkan if it is not 0.
+    unsafe fn raw_impl(image: &UnsafeImage, format: Format, ty: ViewType,
+                       mipmap_levels: Range<u32>, array_layers: Range<u32>, min_lod: f32)
+                       -> Result<UnsafeImageView, OomError> {
         let vk = image.device.pointers();
 
         assert!(mipmap_levels.end > mipmap_levels.start);
@@ -917,7 +1631,7 @@ impl UnsafeImageView {
         assert!(array_layers.end > array_layers.start);
         assert!(array_layers.end <= image.dimensions.array_layers());
 
-        let aspect_mask = match image.format.ty() {
+        let aspect_mask = match format.ty() {
             FormatTy::Float | FormatTy::Uint | FormatTy::Sint | FormatTy::Compressed => {
                 vk::IMAGE_ASPECT_COLOR_BIT
             },
@@ -948,13 +1662,24 @@ impl UnsafeImageView {
         };
 
         let view = {
+            // The value passed to Vulkan is relative to the first level of the image.
+            let min_lod_infos = vk::ImageViewMinLodCreateInfoEXT {
+                sType: vk::STRUCTURE_TYPE_IMAGE_VIEW_MIN_LOD_CREATE_INFO_EXT,
+                pNext: ptr::null(),
+                minLod: mipmap_levels.start as f32 + min_lod,
+            };
+
             let infos = vk::ImageViewCreateInfo {
                 sType: vk::STRUCTURE_TYPE_IMAGE_VIEW_CREATE_INFO,
-                pNext: ptr::null(),
+                pNext: if min_lod != 0.0 {
+                    &min_lod_infos as *const _ as *const _
+                } else {
+                    ptr::null()
+                },
                 flags: 0, // reserved
                 image: image.internal_object(),
                 viewType: view_type,
-                format: image.format as u32,
+                format: format as u32,
                 components: vk::ComponentMapping {
                     r: 0,
                     g: 0,
@@ -974,7 +1699,8 @@ impl UnsafeImageView {
             check_errors(vk.CreateImageView(image.device.internal_object(),
                                             &infos,
                                             ptr::null(),
-                                            &mut output))?;
+                                            &mut output),
+                         "vkCreateImageView")?;
             output
         };
 
@@ -983,7 +1709,8 @@ impl UnsafeImageView {
                device: image.device.clone(),
                usage: image.usage,
                identity_swizzle: true, // FIXME:
-               format: image.format,
+               format: format,
+               min_lod: min_lod,
            })
     }
 
@@ -1014,6 +1741,13 @@ impl UnsafeImageView {
         self.format
     }
 
+    /// Returns the minimum LOD of the view, relative to its first mipmap level. This is 0 unless
+    /// the view was created with `with_min_lod`.
+    #[inline]
+    pub fn min_lod(&self) -> f32 {
+        self.min_lod
+    }
+
     #[inline]
     pub fn usage_transfer_src(&self) -> bool {
         (self.usage & vk::IMAGE_USAGE_TRANSFER_SRC_BIT) != 0
@@ -1086,14 +1820,66 @@ mod tests {
     use std::iter::Empty;
     use std::u32;
 
+    use super::ImageBindMemoryError;
     use super::ImageCreationError;
+    use super::ImageCreationOptions;
     use super::ImageUsage;
+    use super::ImageViewFormatError;
+    use super::ImageViewMinLodError;
+    use super::SparseImageMemoryRequirements;
+    use super::SparseImageMipTailRegion;
     use super::UnsafeImage;
+    use super::check_min_lod;
+    use super::UnsafeImageView;
 
     use format::Format;
     use image::ImageDimensions;
+    use image::ViewType;
+    use memory::DeviceMemory;
     use sync::Sharing;
 
+    #[test]
+    fn sparse_mip_tail() {
+        let mut reqs = SparseImageMemoryRequirements {
+            color: true,
+            depth: false,
+            stencil: false,
+            metadata: false,
+            image_granularity: [128, 128, 1],
+            single_mip_tail: false,
+            aligned_mip_size: false,
+            nonstandard_block_size: false,
+            mip_tail_first_lod: 3,
+            mip_tail_size: 1000,
+            mip_tail_offset: 4096,
+            mip_tail_stride: 8192,
+        };
+
+        assert!(!reqs.is_in_mip_tail(2));
+        assert!(reqs.is_in_mip_tail(3));
+        assert!(reqs.mip_tail(3, 2).is_none());
+
+        let tail = reqs.mip_tail(5, 2).unwrap();
+        assert_eq!(tail.first_lod, 3);
+        assert_eq!(tail.regions,
+                   vec![SparseImageMipTailRegion {
+                            array_layer: Some(0),
+                            offset: 4096,
+                            size: 1000,
+                        },
+                        SparseImageMipTailRegion {
+                            array_layer: Some(1),
+                            offset: 12288,
+                            size: 1000,
+                        }]);
+        assert_eq!(tail.memory_size(512), 2048);
+
+        reqs.single_mip_tail = true;
+        let tail = reqs.mip_tail(5, 2).unwrap();
+        assert_eq!(tail.regions.len(), 1);
+        assert_eq!(tail.regions[0].array_layer, None);
+    }
+
     #[test]
     fn create_sampled() {
         let (device, _) = gfx_dev_and_queue!();
@@ -1116,9 +1902,165 @@ mod tests {
                              1,
                              1,
                              Sharing::Exclusive::<Empty<_>>,
-                             false,
-                             false)
+                             ImageCreationOptions::none())
+        }.unwrap();
+    }
+
+    #[test]
+    fn bind_memory_checks() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let usage = ImageUsage {
+            sampled: true,
+            ..ImageUsage::none()
+        };
+
+        let (img, reqs) = unsafe {
+            UnsafeImage::new(device.clone(),
+                             usage,
+                             Format::R8G8B8A8Unorm,
+                             ImageDimensions::Dim2d {
+                                 width: 32,
+                                 height: 32,
+                                 array_layers: 1,
+                                 cubemap_compatible: false,
+                             },
+                             1,
+                             1,
+                             Sharing::Exclusive::<Empty<_>>,
+                             ImageCreationOptions::none())
+        }.unwrap();
+
+        assert!(!img.is_memory_bound());
+        let mem_ty = device
+            .physical_device()
+            .memory_types()
+            .find(|t| reqs.supports_memory_type(*t))
+            .unwrap();
+
+        let small = DeviceMemory::alloc(device.clone(), mem_ty, reqs.size - 1).unwrap();
+        match unsafe { img.bind_memory(&small, 0) } {
+            Err(ImageBindMemoryError::MemoryTooSmall { .. }) => (),
+            _ => panic!(),
+        }
+
+        let mem = DeviceMemory::alloc(device.clone(), mem_ty, reqs.size).unwrap();
+        unsafe { img.bind_memory(&mem, 0) }.unwrap();
+        assert!(img.is_memory_bound());
+        match unsafe { img.bind_memory(&mem, 0) } {
+            Err(ImageBindMemoryError::AlreadyBound) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn view_with_format() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let usage = ImageUsage {
+            sampled: true,
+            ..ImageUsage::none()
+        };
+
+        let create = |mutable_format| unsafe {
+            UnsafeImage::new(device.clone(),
+                             usage,
+                             Format::R8G8B8A8Unorm,
+                             ImageDimensions::Dim2d {
+                                 width: 32,
+                                 height: 32,
+                                 array_layers: 1,
+                                 cubemap_compatible: false,
+                             },
+                             1,
+                             1,
+                             Sharing::Exclusive::<Empty<_>>,
+                             ImageCreationOptions {
+                                 mutable_format: mutable_format,
+                                 ..ImageCreationOptions::none()
+                             })
+        }.unwrap().0;
+
+        let img = create(false);
+        match unsafe {
+            UnsafeImageView::with_format(&img, Format::R8G8B8A8Srgb, ViewType::Dim2d, 0 .. 1,
+                                         0 .. 1)
+        } {
+            Err(ImageViewFormatError::ImageFormatNotMutable) => (),
+            _ => panic!(),
+        };
+
+        let img = create(true);
+        let view = unsafe {
+            UnsafeImageView::with_format(&img, Format::R8G8B8A8Srgb, ViewType::Dim2d, 0 .. 1,
+                                         0 .. 1)
+        }.unwrap();
+        assert_eq!(view.format(), Format::R8G8B8A8Srgb);
+        match unsafe {
+            UnsafeImageView::with_format(&img, Format::R16Sfloat, ViewType::Dim2d, 0 .. 1, 0 .. 1)
+        } {
+            Err(ImageViewFormatError::IncompatibleFormat) => (),
+            _ => panic!(),
+        };
+    }
+
+    #[test]
+    fn min_lod_range() {
+        assert!(check_min_lod(0.0, 1).is_ok());
+        assert!(check_min_lod(2.0, 6).is_ok());
+        assert!(check_min_lod(5.0, 6).is_ok());
+
+        match check_min_lod(5.5, 6) {
+            Err(ImageViewMinLodError::MinLodOutOfRange { min_lod, max }) => {
+                assert_eq!(min_lod, 5.5);
+                assert_eq!(max, 5.0);
+            },
+            _ => panic!(),
+        };
+        match check_min_lod(-0.5, 6) {
+            Err(ImageViewMinLodError::MinLodOutOfRange { .. }) => (),
+            _ => panic!(),
+        };
+    }
+
+    #[test]
+    fn min_lod_needs_extension() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let usage = ImageUsage {
+            sampled: true,
+            ..ImageUsage::none()
+        };
+
+        let (img, _) = unsafe {
+            UnsafeImage::new(device,
+                             usage,
+                             Format::R8G8B8A8Unorm,
+                             ImageDimensions::Dim2d {
+                                 width: 32,
+                                 height: 32,
+                                 array_layers: 1,
+                                 cubemap_compatible: false,
+                             },
+                             1,
+                             6,
+                             Sharing::Exclusive::<Empty<_>>,
+                             ImageCreationOptions::none())
+        }.unwrap();
+
+        match unsafe {
+            UnsafeImageView::with_min_lod(&img, ViewType::Dim2d, 0 .. 6, 0 .. 1, 2.0)
+        } {
+            Err(ImageViewMinLodError::ExtensionNotEnabled) => (),
+            _ => panic!(),
+        };
+
+        // The fallback never exposes the levels below the resident one.
+        let views = unsafe {
+            UnsafeImageView::resident_level_views(&img, ViewType::Dim2d, 0 .. 6, 0 .. 1)
         }.unwrap();
+        assert_eq!(views.len(), 6);
+        assert!(views.iter().all(|v| v.min_lod() == 0.0));
     }
 
     #[test]
@@ -1144,8 +2086,7 @@ mod tests {
                              1,
                              1,
                              Sharing::Exclusive::<Empty<_>>,
-                             false,
-                             false)
+                             ImageCreationOptions::none())
         }.unwrap();
     }
 
@@ -1171,8 +2112,7 @@ mod tests {
                              0,
                              1,
                              Sharing::Exclusive::<Empty<_>>,
-                             false,
-                             false)
+                             ImageCreationOptions::none())
         };
 
         match res {
@@ -1203,8 +2143,7 @@ mod tests {
                              5,
                              1,
                              Sharing::Exclusive::<Empty<_>>,
-                             false,
-                             false)
+                             ImageCreationOptions::none())
         };
 
         match res {
@@ -1235,8 +2174,7 @@ mod tests {
                              1,
                              0,
                              Sharing::Exclusive::<Empty<_>>,
-                             false,
-                             false)
+                             ImageCreationOptions::none())
         };
 
         match res {
@@ -1268,8 +2206,7 @@ mod tests {
                              1,
                              u32::MAX,
                              Sharing::Exclusive::<Empty<_>>,
-                             false,
-                             false)
+                             ImageCreationOptions::none())
         };
 
         match res {
@@ -1306,8 +2243,7 @@ mod tests {
                              2,
                              1,
                              Sharing::Exclusive::<Empty<_>>,
-                             false,
-                             false)
+                             ImageCreationOptions::none())
         };
 
         match res {
@@ -1339,8 +2275,7 @@ mod tests {
                              1,
                              u32::MAX,
                              Sharing::Exclusive::<Empty<_>>,
-                             false,
-                             false)
+                             ImageCreationOptions::none())
         };
 
         match res {
@@ -1373,8 +2308,7 @@ mod tests {
                              1,
                              1,
                              Sharing::Exclusive::<Empty<_>>,
-                             false,
-                             false)
+                             ImageCreationOptions::none())
         };
 
         match res {
@@ -1405,8 +2339,7 @@ mod tests {
                              1,
                              1,
                              Sharing::Exclusive::<Empty<_>>,
-                             false,
-                             false)
+                             ImageCreationOptions::none())
         };
 
         match res {
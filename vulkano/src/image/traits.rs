@@ -26,7 +26,6 @@ use sampler::Sampler;
 use sync::AccessError;
 
 use SafeDeref;
-use VulkanObject;
 
 /// Trait for types that represent the way a GPU can access an image.
 pub unsafe trait ImageAccess {
@@ -119,11 +118,14 @@ pub unsafe trait ImageAccess {
     ///
     /// If this function returns `false`, this means that we are allowed to access the offset/size
     /// of `self` at the same time as the offset/size of `other` without causing a data race.
+    ///
+    /// The default implementation always returns `false`, as buffers and images never share
+    /// memory unless you implement these traits yourself.
+    #[inline]
     fn conflicts_buffer(&self, self_first_layer: u32, self_num_layers: u32,
                         self_first_mipmap: u32, self_num_mipmaps: u32, other: &BufferAccess,
                         other_offset: usize, other_size: usize)
                         -> bool {
-        // TODO: should we really provide a default implementation?
         false
     }
 
@@ -134,20 +136,33 @@ pub unsafe trait ImageAccess {
     ///
     /// If this function returns `false`, this means that we are allowed to access the offset/size
     /// of `self` at the same time as the offset/size of `other` without causing a data race.
+    ///
+    /// The default implementation compares the underlying `UnsafeImage` objects returned by
+    /// `inner()` and the ranges of array layers and mipmap levels within them. Two different
+    /// objects that wrap around the same image are therefore correctly detected as conflicting.
     fn conflicts_image(&self, self_first_layer: u32, self_num_layers: u32,
                        self_first_mipmap: u32, self_num_mipmaps: u32, other: &ImageAccess,
                        other_first_layer: u32, other_num_layers: u32, other_first_mipmap: u32,
                        other_num_mipmaps: u32)
                        -> bool {
-        // TODO: should we really provide a default implementation?
+        let self_inner = self.inner();
+        let other_inner = other.inner();
 
-        // TODO: debug asserts to check for ranges
-
-        if self.inner().image.internal_object() != other.inner().image.internal_object() {
+        if self_inner.image.key() != other_inner.image.key() {
             return false;
         }
 
-        true
+        let self_first_layer = self_first_layer + self_inner.first_layer as u32;
+        let other_first_layer = other_first_layer + other_inner.first_layer as u32;
+        let self_first_mipmap = self_first_mipmap + self_inner.first_mipmap_level as u32;
+        let other_first_mipmap = other_first_mipmap + other_inner.first_mipmap_level as u32;
+
+        let layers_overlap = self_first_layer < other_first_layer + other_num_layers &&
+            other_first_layer < self_first_layer + self_num_layers;
+        let mipmaps_overlap = self_first_mipmap < other_first_mipmap + other_num_mipmaps &&
+            other_first_mipmap < self_first_mipmap + self_num_mipmaps;
+
+        layers_overlap && mipmaps_overlap
     }
 
     /// Returns a key that uniquely identifies the range given by
@@ -162,8 +177,14 @@ pub unsafe trait ImageAccess {
     /// Since it is possible to accidentally return the same key for memory ranges that don't
     /// overlap, the `conflicts_image` or `conflicts_buffer` function should always be called to
     /// verify whether they actually overlap.
+    ///
+    /// The default implementation returns the key of the underlying `UnsafeImage`, which is the
+    /// same for all the subresources of an image.
+    #[inline]
     fn conflict_key(&self, first_layer: u32, num_layers: u32, first_mipmap: u32, num_mipmaps: u32)
-                    -> u64;
+                    -> u64 {
+        self.inner().image.key()
+    }
 
     /// Shortcut for `conflicts_buffer` that compares the whole buffer to another.
     #[inline]
@@ -197,6 +218,18 @@ pub unsafe trait ImageAccess {
         self.conflict_key(0, self.dimensions().array_layers(), 0, self.mipmap_levels())
     }
 
+    /// Returns false if the content of the image hasn't been initialized yet.
+    ///
+    /// An uninitialized image can't be put in a descriptor set, and a command buffer can only
+    /// use it after a previous command of the same command buffer has used it. This is how
+    /// `ImmutableImage` prevents reading its content before the initial upload has finished.
+    ///
+    /// The default implementation returns true.
+    #[inline]
+    fn initialized(&self) -> bool {
+        true
+    }
+
     /// Locks the resource for usage on the GPU. Returns an error if the lock can't be acquired.
     ///
     /// This function exists to prevent the user from causing a data race by reading and writing
@@ -269,6 +302,11 @@ unsafe impl<T> ImageAccess for T
         (**self).conflict_key(first_layer, num_layers, first_mipmap, num_mipmaps)
     }
 
+    #[inline]
+    fn initialized(&self) -> bool {
+        (**self).initialized()
+    }
+
     #[inline]
     fn try_gpu_lock(&self, exclusive_access: bool, queue: &Queue) -> Result<(), AccessError> {
         (**self).try_gpu_lock(exclusive_access, queue)
@@ -322,6 +360,11 @@ unsafe impl<I> ImageAccess for ImageAccessFromUndefinedLayout<I>
             .conflict_key(first_layer, num_layers, first_mipmap, num_mipmaps)
     }
 
+    #[inline]
+    fn initialized(&self) -> bool {
+        self.image.initialized()
+    }
+
     #[inline]
     fn try_gpu_lock(&self, exclusive_access: bool, queue: &Queue) -> Result<(), AccessError> {
         self.image.try_gpu_lock(exclusive_access, queue)
@@ -0,0 +1,891 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use smallvec::SmallVec;
+use std::error;
+use std::fmt;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::Weak;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+
+use buffer::StagingArena;
+use buffer::sys::BufferCreationError;
+use command_buffer::AutoCommandBufferBuilder;
+use command_buffer::CopyBufferToImageError;
+use device::Device;
+use device::Queue;
+use format::FormatDesc;
+use format::FormatTy;
+use image::Dimensions;
+use image::ImageInner;
+use image::ImageLayout;
+use image::ImageUsage;
+use image::matches_pixel_format;
+use image::sys::ImageCreationError;
+use image::sys::ImageCreationOptions;
+use image::sys::UnsafeImage;
+use image::sys::UnsafeImageView;
+use image::traits::ImageAccess;
+use image::traits::ImageContent;
+use image::traits::ImageViewAccess;
+use instance::QueueFamily;
+use memory::pool::AllocLayout;
+use memory::pool::MemoryPool;
+use memory::pool::MemoryPoolAlloc;
+use memory::pool::StdMemoryPool;
+use sync::AccessError;
+use sync::Sharing;
+
+/// Two-dimensional image in device memory that is sampled by shaders and whose content is
+/// updated region by region, like a glyph atlas.
+///
+/// The image always stays in the `ShaderReadOnlyOptimal` layout between command buffers.
+/// `update_regions` records copies from staging buffers to parts of the image, and the command
+/// buffer inserts the transitions to and from the transfer layout around them.
+///
+/// The first update also fills the rest of the image with zeroes. Until the command buffer that
+/// contains this first update has been submitted, the image can't be put in a descriptor set and
+/// can't be used by a command buffer, except after the update in the same command buffer.
+///
+/// # Sampling and updates in the same command buffer
+///
+/// Commands are executed in the order in which they are recorded. If a draw command samples a
+/// region of the image and the same command buffer updates this region afterwards, the draw
+/// command sees the old content. To catch this mistake, call `mark_sampled` when recording a
+/// command that samples a region of the image. `update_regions` then returns an error if it is
+/// asked to update this region with the same builder. The regions are forgotten when the builder
+/// is built, and don't affect the other builders.
+pub struct UpdatableImage<F, A = Arc<StdMemoryPool>>
+    where A: MemoryPool
+{
+    image: UnsafeImage,
+    view: UnsafeImageView,
+    dimensions: [u32; 2],
+    memory: A::Alloc,
+    format: F,
+
+    // Size in bytes of a texel of the format.
+    texel_size: usize,
+
+    // Staging buffers used by `update_regions`.
+    staging: StagingArena,
+
+    // True once a command buffer that initializes the content of the image has been submitted.
+    initialized: AtomicBool,
+
+    // Number of times this image is locked on the GPU side, plus `EXCLUSIVE_LOCK` if the lock is
+    // exclusive.
+    gpu_lock: AtomicUsize,
+
+    // Regions passed to `mark_sampled` as `[x, y, width, height]`, alongside the token of the
+    // builder they were marked for.
+    sampled_regions: Mutex<Vec<(Weak<()>, [u32; 4])>>,
+}
+
+// Flag of `gpu_lock` that is set while a command buffer that writes the image is locking it.
+const EXCLUSIVE_LOCK: usize = !(!0 >> 1);
+
+impl<F> UpdatableImage<F> {
+    /// Creates a new image with the given dimensions and format.
+    ///
+    /// Returns `FormatNotSupported` if the format is a depth, stencil or compressed format.
+    pub fn new<'a, I>(device: Arc<Device>, dimensions: [u32; 2], format: F, queue_families: I)
+                      -> Result<Arc<UpdatableImage<F>>, ImageCreationError>
+        where F: FormatDesc,
+              I: IntoIterator<Item = QueueFamily<'a>>
+    {
+        let texel_size = match format.format().ty() {
+            FormatTy::Float | FormatTy::Uint | FormatTy::Sint => {
+                format.format().block_size().unwrap()
+            },
+            _ => return Err(ImageCreationError::FormatNotSupported),
+        };
+
+        let usage = ImageUsage {
+            transfer_source: true, // for readbacks and blits
+            transfer_dest: true,
+            sampled: true,
+            ..ImageUsage::none()
+        };
+
+        let queue_families = queue_families
+            .into_iter()
+            .map(|f| f.id())
+            .collect::<SmallVec<[u32; 4]>>();
+
+        let dims = Dimensions::Dim2d {
+            width: dimensions[0],
+            height: dimensions[1],
+        };
+
+        let (image, mem_reqs) = unsafe {
+            let sharing = if queue_families.len() >= 2 {
+                Sharing::Concurrent(queue_families.iter().cloned())
+            } else {
+                Sharing::Exclusive
+            };
+
+            UnsafeImage::new(device.clone(),
+                             usage,
+                             format.format(),
+                             dims.to_image_dimensions(),
+                             1,
+                             1,
+                             sharing,
+                             ImageCreationOptions::none())?
+        };
+
+        let mem_ty = {
+            let device_local = device
+                .physical_device()
+                .memory_types()
+                .filter(|t| (mem_reqs.memory_type_bits & (1 << t.id())) != 0)
+                .filter(|t| t.is_device_local());
+            let any = device
+                .physical_device()
+                .memory_types()
+                .filter(|t| (mem_reqs.memory_type_bits & (1 << t.id())) != 0);
+            device_local.chain(any).next().unwrap()
+        };
+
+        let mem = MemoryPool::alloc(&Device::standard_pool(&device),
+                                    mem_ty,
+                                    mem_reqs.size,
+                                    mem_reqs.alignment,
+                                    AllocLayout::Optimal)?;
+        debug_assert!((mem.offset() % mem_reqs.alignment) == 0);
+        unsafe {
+            image.bind_memory(mem.memory(), mem.offset())?;
+        }
+
+        let view = unsafe {
+            UnsafeImageView::raw(&image, dims.to_view_type(), 0 .. 1, 0 .. 1)?
+        };
+
+        Ok(Arc::new(UpdatableImage {
+                        image: image,
+                        view: view,
+                        dimensions: dimensions,
+                        memory: mem,
+                        format: format,
+                        texel_size: texel_size,
+                        staging: StagingArena::new(device),
+                        initialized: AtomicBool::new(false),
+                        gpu_lock: AtomicUsize::new(0),
+                        sampled_regions: Mutex::new(Vec::new()),
+                    }))
+    }
+
+    /// Adds to `builder` the commands that write `data` to the given regions of the image.
+    ///
+    /// Each element of `regions` is a region of the image and the texels to write in it, row
+    /// after row. The rows don't need to be tightly packed, see `UpdatableImageRegion`. The data
+    /// is copied to staging buffers before this function returns.
+    ///
+    /// If an error is returned, the builder is destroyed like with the other methods of
+    /// `AutoCommandBufferBuilder`.
+    pub fn update_regions<P, I, D>(image: &Arc<UpdatableImage<F>>,
+                                   mut builder: AutoCommandBufferBuilder<P>, regions: I)
+                                   -> Result<AutoCommandBufferBuilder<P>, UpdatableImageError>
+        where I: IntoIterator<Item = (UpdatableImageRegion, D)>,
+              D: AsRef<[u8]>,
+              F: 'static + Send + Sync
+    {
+        let regions = regions.into_iter().collect::<Vec<_>>();
+
+        for &(ref region, ref data) in regions.iter() {
+            image.check_region(region, data.as_ref().len(), builder.token())?;
+        }
+
+        if !image.initialized.load(Ordering::SeqCst) {
+            let len = image.dimensions[0] as usize * image.dimensions[1] as usize *
+                image.texel_size;
+            let zeroes = image.staging.upload_iter((0 .. len).map(|_| 0u8))?;
+            let init = UpdatableImageInitialization { image: image.clone() };
+            builder = builder
+                .copy_buffer_to_image_dimensions(zeroes,
+                                                 init,
+                                                 [0, 0, 0],
+                                                 [image.dimensions[0], image.dimensions[1], 1],
+                                                 0,
+                                                 1,
+                                                 0)?;
+        }
+
+        for (region, data) in regions {
+            let data = data.as_ref();
+            let row_size = region.extent[0] as usize * image.texel_size;
+            let pitch = region.row_pitch(image.texel_size);
+
+            // The rows are repacked so that the staging buffer contains exactly the texels of the
+            // region, which is what `copy_buffer_to_image_dimensions` expects.
+            let packed = (0 .. region.extent[1] as usize)
+                .flat_map(|row| data[row * pitch .. row * pitch + row_size].iter().cloned())
+                .collect::<Vec<u8>>();
+            let staging = image.staging.upload_iter(packed.into_iter())?;
+
+            builder = builder
+                .copy_buffer_to_image_dimensions(staging,
+                                                 image.clone(),
+                                                 [region.offset[0], region.offset[1], 0],
+                                                 [region.extent[0], region.extent[1], 1],
+                                                 0,
+                                                 1,
+                                                 0)?;
+        }
+
+        Ok(builder)
+    }
+}
+
+impl<F, A> UpdatableImage<F, A>
+    where A: MemoryPool
+{
+    /// Returns the dimensions of the image.
+    #[inline]
+    pub fn dimensions(&self) -> [u32; 2] {
+        self.dimensions
+    }
+
+    /// Declares that a command that samples the given region of the image has been recorded in
+    /// `builder`.
+    ///
+    /// Until `builder` is built, `update_regions` returns an error if it is asked to update a
+    /// region that overlaps this one with the same builder.
+    pub fn mark_sampled<P>(&self, builder: &AutoCommandBufferBuilder<P>, offset: [u32; 2],
+                           extent: [u32; 2]) {
+        let mut sampled = self.sampled_regions.lock().unwrap();
+        // Forgets the regions of the builders that have been built or destroyed.
+        sampled.retain(|&(ref token, _)| token.upgrade().is_some());
+        let region = [offset[0], offset[1], extent[0], extent[1]];
+        sampled.push((Arc::downgrade(builder.token()), region));
+    }
+
+    // Checks whether `region` can be updated with `data_len` bytes of data, by the builder whose
+    // token is `token`.
+    fn check_region(&self, region: &UpdatableImageRegion, data_len: usize, token: &Arc<()>)
+                    -> Result<(), UpdatableImageError> {
+        for i in 0 .. 2 {
+            match region.offset[i].checked_add(region.extent[i]) {
+                Some(end) if end <= self.dimensions[i] => (),
+                _ => return Err(UpdatableImageError::RegionOutOfBounds),
+            }
+        }
+
+        if region.row_length != 0 && region.row_length < region.extent[0] {
+            return Err(UpdatableImageError::RowLengthTooSmall);
+        }
+
+        let required = if region.extent[0] == 0 || region.extent[1] == 0 {
+            0
+        } else {
+            (region.extent[1] as usize - 1) * region.row_pitch(self.texel_size) +
+                region.extent[0] as usize * self.texel_size
+        };
+
+        if data_len < required {
+            return Err(UpdatableImageError::DataTooSmall {
+                           required: required,
+                           actual: data_len,
+                       });
+        }
+
+        let sampled = self.sampled_regions.lock().unwrap();
+        for &(ref s_token, ref s) in sampled.iter() {
+            match s_token.upgrade() {
+                Some(ref t) if Arc::ptr_eq(t, token) => (),
+                _ => continue,
+            }
+
+            let overlaps = region.offset[0] < s[0] + s[2] &&
+                s[0] < region.offset[0] + region.extent[0] &&
+                region.offset[1] < s[1] + s[3] &&
+                s[1] < region.offset[1] + region.extent[1];
+            if overlaps {
+                return Err(UpdatableImageError::RegionSampledBeforeUpdate {
+                               offset: region.offset,
+                               extent: region.extent,
+                           });
+            }
+        }
+
+        Ok(())
+    }
+
+    // Locks the image for a submission. Any number of submissions can read the image at the
+    // same time, but a submission that writes it must be the only one to use it.
+    fn lock_for_submission(&self, exclusive: bool) -> Result<(), AccessError> {
+        if exclusive {
+            if self.gpu_lock.compare_and_swap(0, EXCLUSIVE_LOCK | 1, Ordering::SeqCst) != 0 {
+                return Err(AccessError::AlreadyInUse);
+            }
+            return Ok(());
+        }
+
+        let mut current = self.gpu_lock.load(Ordering::SeqCst);
+        loop {
+            if current & EXCLUSIVE_LOCK != 0 {
+                return Err(AccessError::AlreadyInUse);
+            }
+
+            let prev = self.gpu_lock.compare_and_swap(current, current + 1, Ordering::SeqCst);
+            if prev == current {
+                return Ok(());
+            }
+            current = prev;
+        }
+    }
+
+    // Releases one lock acquired by `lock_for_submission` or `increase_gpu_lock`.
+    fn unlock_submission(&self) {
+        let mut current = self.gpu_lock.load(Ordering::SeqCst);
+        loop {
+            debug_assert!(current & !EXCLUSIVE_LOCK >= 1);
+            // The exclusive flag is cleared along with the last lock.
+            let new = if current & !EXCLUSIVE_LOCK == 1 { 0 } else { current - 1 };
+            let prev = self.gpu_lock.compare_and_swap(current, new, Ordering::SeqCst);
+            if prev == current {
+                return;
+            }
+            current = prev;
+        }
+    }
+}
+
+/// Region of an `UpdatableImage` to update.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct UpdatableImageRegion {
+    /// Coordinates in texels of the top-left corner of the region.
+    pub offset: [u32; 2],
+    /// Width and height in texels of the region.
+    pub extent: [u32; 2],
+    /// Number of texels between the start of a row of the data and the start of the next row.
+    /// Must be superior or equal to the width of the region, or 0 if the rows are tightly
+    /// packed.
+    ///
+    /// This lets you update a region from a sub-rectangle of a larger bitmap. The texels that
+    /// follow the last row of the region don't need to be present in the data.
+    pub row_length: u32,
+}
+
+impl UpdatableImageRegion {
+    /// Builds a region whose data is tightly packed.
+    #[inline]
+    pub fn new(offset: [u32; 2], extent: [u32; 2]) -> UpdatableImageRegion {
+        UpdatableImageRegion {
+            offset: offset,
+            extent: extent,
+            row_length: 0,
+        }
+    }
+
+    // Returns the number of bytes between the start of two rows of the data.
+    #[inline]
+    fn row_pitch(&self, texel_size: usize) -> usize {
+        if self.row_length == 0 {
+            self.extent[0] as usize * texel_size
+        } else {
+            self.row_length as usize * texel_size
+        }
+    }
+}
+
+unsafe impl<F, A> ImageAccess for UpdatableImage<F, A>
+    where F: 'static + Send + Sync,
+          A: MemoryPool
+{
+    #[inline]
+    fn inner(&self) -> ImageInner {
+        ImageInner {
+            image: &self.image,
+            first_layer: 0,
+            num_layers: 1,
+            first_mipmap_level: 0,
+            num_mipmap_levels: 1,
+        }
+    }
+
+    #[inline]
+    fn initial_layout_requirement(&self) -> ImageLayout {
+        ImageLayout::ShaderReadOnlyOptimal
+    }
+
+    #[inline]
+    fn final_layout_requirement(&self) -> ImageLayout {
+        ImageLayout::ShaderReadOnlyOptimal
+    }
+
+    #[inline]
+    fn initialized(&self) -> bool {
+        self.initialized.load(Ordering::SeqCst)
+    }
+
+    #[inline]
+    fn try_gpu_lock(&self, exclusive: bool, _: &Queue) -> Result<(), AccessError> {
+        if !self.initialized.load(Ordering::SeqCst) {
+            return Err(AccessError::ImageNotInitialized {
+                           requested: ImageLayout::ShaderReadOnlyOptimal,
+                       });
+        }
+
+        self.lock_for_submission(exclusive)
+    }
+
+    #[inline]
+    unsafe fn increase_gpu_lock(&self) {
+        let val = self.gpu_lock.fetch_add(1, Ordering::SeqCst);
+        debug_assert!(val & !EXCLUSIVE_LOCK >= 1);
+    }
+
+    #[inline]
+    unsafe fn unlock(&self) {
+        self.unlock_submission();
+    }
+}
+
+unsafe impl<P, F, A> ImageContent<P> for UpdatableImage<F, A>
+    where F: 'static + Send + Sync,
+          A: MemoryPool
+{
+    #[inline]
+    fn matches_format(&self) -> bool {
+        matches_pixel_format::<P>(self.image.format())
+    }
+}
+
+unsafe impl<F, A> ImageViewAccess for UpdatableImage<F, A>
+    where F: 'static + Send + Sync,
+          A: MemoryPool
+{
+    #[inline]
+    fn parent(&self) -> &ImageAccess {
+        self
+    }
+
+    #[inline]
+    fn dimensions(&self) -> Dimensions {
+        Dimensions::Dim2d {
+            width: self.dimensions[0],
+            height: self.dimensions[1],
+        }
+    }
+
+    #[inline]
+    fn inner(&self) -> &UnsafeImageView {
+        &self.view
+    }
+
+    #[inline]
+    fn descriptor_set_storage_image_layout(&self) -> ImageLayout {
+        ImageLayout::ShaderReadOnlyOptimal
+    }
+
+    #[inline]
+    fn descriptor_set_combined_image_sampler_layout(&self) -> ImageLayout {
+        ImageLayout::ShaderReadOnlyOptimal
+    }
+
+    #[inline]
+    fn descriptor_set_sampled_image_layout(&self) -> ImageLayout {
+        ImageLayout::ShaderReadOnlyOptimal
+    }
+
+    #[inline]
+    fn descriptor_set_input_attachment_layout(&self) -> ImageLayout {
+        ImageLayout::ShaderReadOnlyOptimal
+    }
+
+    #[inline]
+    fn identity_swizzle(&self) -> bool {
+        true
+    }
+}
+
+// Access to the image used by the first update, which transitions it from the `Undefined`
+// layout.
+struct UpdatableImageInitialization<F, A = Arc<StdMemoryPool>>
+    where A: MemoryPool
+{
+    image: Arc<UpdatableImage<F, A>>,
+}
+
+unsafe impl<F, A> ImageAccess for UpdatableImageInitialization<F, A>
+    where F: 'static + Send + Sync,
+          A: MemoryPool
+{
+    #[inline]
+    fn inner(&self) -> ImageInner {
+        ImageAccess::inner(&*self.image)
+    }
+
+    #[inline]
+    fn initial_layout_requirement(&self) -> ImageLayout {
+        ImageLayout::Undefined
+    }
+
+    #[inline]
+    fn final_layout_requirement(&self) -> ImageLayout {
+        ImageLayout::ShaderReadOnlyOptimal
+    }
+
+    #[inline]
+    fn try_gpu_lock(&self, _: bool, _: &Queue) -> Result<(), AccessError> {
+        // The initialization always writes the whole image.
+        self.image.lock_for_submission(true)?;
+
+        // Another command buffer has initialized the image in the meantime. Submitting this one
+        // would erase the updates of the other one.
+        if self.image.initialized.swap(true, Ordering::SeqCst) {
+            self.image.unlock_submission();
+            return Err(AccessError::AlreadyInUse);
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    unsafe fn increase_gpu_lock(&self) {
+        self.image.increase_gpu_lock()
+    }
+
+    #[inline]
+    unsafe fn unlock(&self) {
+        self.image.unlock()
+    }
+}
+
+/// Error that can happen when updating an `UpdatableImage`.
+#[derive(Debug, Clone)]
+pub enum UpdatableImageError {
+    /// Error while creating the staging buffers.
+    StagingBufferCreationError(BufferCreationError),
+
+    /// Error while recording the copy commands.
+    CopyBufferToImageError(CopyBufferToImageError),
+
+    /// A region goes beyond the dimensions of the image.
+    RegionOutOfBounds,
+
+    /// The row length of a region is inferior to its width.
+    RowLengthTooSmall,
+
+    /// The data of a region is too small.
+    DataTooSmall {
+        /// Number of bytes required by the region.
+        required: usize,
+        /// Number of bytes that were provided.
+        actual: usize,
+    },
+
+    /// The region was passed to `mark_sampled` and the command buffer hasn't been submitted yet.
+    RegionSampledBeforeUpdate {
+        /// Offset of the region that was to be updated.
+        offset: [u32; 2],
+        /// Extent of the region that was to be updated.
+        extent: [u32; 2],
+    },
+}
+
+impl error::Error for UpdatableImageError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            UpdatableImageError::StagingBufferCreationError(_) => {
+                "error while creating the staging buffers"
+            },
+            UpdatableImageError::CopyBufferToImageError(_) => {
+                "error while recording the copy commands"
+            },
+            UpdatableImageError::RegionOutOfBounds => {
+                "a region goes beyond the dimensions of the image"
+            },
+            UpdatableImageError::RowLengthTooSmall => {
+                "the row length of a region is inferior to its width"
+            },
+            UpdatableImageError::DataTooSmall { .. } => "the data of a region is too small",
+            UpdatableImageError::RegionSampledBeforeUpdate { .. } => {
+                "the region is sampled by a command that was recorded before the update"
+            },
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            UpdatableImageError::StagingBufferCreationError(ref err) => Some(err),
+            UpdatableImageError::CopyBufferToImageError(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for UpdatableImageError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl From<BufferCreationError> for UpdatableImageError {
+    #[inline]
+    fn from(err: BufferCreationError) -> UpdatableImageError {
+        UpdatableImageError::StagingBufferCreationError(err)
+    }
+}
+
+impl From<CopyBufferToImageError> for UpdatableImageError {
+    #[inline]
+    fn from(err: CopyBufferToImageError) -> UpdatableImageError {
+        UpdatableImageError::CopyBufferToImageError(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::iter;
+
+    use buffer::BufferUsage;
+    use buffer::CpuAccessibleBuffer;
+    use command_buffer::AutoCommandBufferBuilder;
+    use command_buffer::CommandBuffer;
+    use format::Format;
+    use image::ImageAccess;
+    use image::ImageCreationError;
+    use image::traits::ImageContent;
+    use sync::AccessError;
+    use sync::GpuFuture;
+
+    use super::UpdatableImage;
+    use super::UpdatableImageError;
+    use super::UpdatableImageRegion;
+
+    #[test]
+    fn update_two_regions() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let image = UpdatableImage::new(device.clone(), [16, 16], Format::R8Unorm,
+                                        Some(queue.family()))
+            .unwrap();
+        assert!(!image.initialized());
+
+        // The first region is a 3x2 sub-rectangle of a bitmap that is 5 texels wide.
+        let first = UpdatableImageRegion {
+            offset: [1, 2],
+            extent: [3, 2],
+            row_length: 5,
+        };
+        let first_data = [1u8, 2, 3, 0xff, 0xff, 4, 5, 6];
+
+        // The second region isn't a multiple of 4 bytes wide.
+        let second = UpdatableImageRegion::new([9, 10], [5, 3]);
+        let second_data = (0 .. 15).map(|n| n + 10).collect::<Vec<u8>>();
+
+        let readback = CpuAccessibleBuffer::from_iter(device.clone(),
+                                                      BufferUsage::all(),
+                                                      iter::once(queue.family()),
+                                                      (0 .. 16 * 16).map(|_| 0xaau8))
+            .unwrap();
+
+        let builder = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap();
+        let builder = UpdatableImage::update_regions(&image,
+                                                     builder,
+                                                     vec![(first, &first_data[..]),
+                                                          (second, &second_data[..])])
+            .unwrap();
+        let cb = builder
+            .copy_image_to_buffer(image.clone(), readback.clone())
+            .unwrap()
+            .build()
+            .unwrap();
+
+        cb.execute(queue.clone())
+            .unwrap()
+            .then_signal_fence_and_flush()
+            .unwrap()
+            .wait(None)
+            .unwrap();
+        assert!(image.initialized());
+
+        let mut expected = vec![0u8; 16 * 16];
+        for row in 0 .. 2 {
+            for col in 0 .. 3 {
+                expected[(2 + row) * 16 + 1 + col] = first_data[row * 5 + col];
+            }
+        }
+        for row in 0 .. 3 {
+            for col in 0 .. 5 {
+                expected[(10 + row) * 16 + 9 + col] = second_data[row * 5 + col];
+            }
+        }
+
+        let content = readback.read().unwrap();
+        assert_eq!(&content[..], &expected[..]);
+    }
+
+    #[test]
+    fn invalid_regions() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let image = UpdatableImage::new(device.clone(), [16, 16], Format::R8Unorm,
+                                        Some(queue.family()))
+            .unwrap();
+
+        let builder = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap();
+        match UpdatableImage::update_regions(&image,
+                                             builder,
+                                             Some((UpdatableImageRegion::new([12, 0], [5, 1]),
+                                                   [0u8; 5]))) {
+            Err(UpdatableImageError::RegionOutOfBounds) => (),
+            _ => panic!(),
+        }
+
+        let builder = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap();
+        let region = UpdatableImageRegion {
+            offset: [0, 0],
+            extent: [4, 2],
+            row_length: 6,
+        };
+        match UpdatableImage::update_regions(&image, builder, Some((region, [0u8; 9]))) {
+            Err(UpdatableImageError::DataTooSmall {
+                    required: 10,
+                    actual: 9,
+                }) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn sampled_before_update() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let image = UpdatableImage::new(device.clone(), [16, 16], Format::R8Unorm,
+                                        Some(queue.family()))
+            .unwrap();
+
+        let builder = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap();
+        image.mark_sampled(&builder, [0, 0], [4, 4]);
+
+        // The regions marked for a builder don't affect the other builders.
+        let other = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap();
+        let other = UpdatableImage::update_regions(&image,
+                                                   other,
+                                                   Some((UpdatableImageRegion::new([3, 3],
+                                                                                   [2, 2]),
+                                                         [0u8; 4])))
+            .unwrap();
+        drop(other);
+
+        let builder = UpdatableImage::update_regions(&image,
+                                                     builder,
+                                                     Some((UpdatableImageRegion::new([4, 0],
+                                                                                     [2, 2]),
+                                                           [0u8; 4])))
+            .unwrap();
+
+        match UpdatableImage::update_regions(&image,
+                                             builder,
+                                             Some((UpdatableImageRegion::new([3, 3], [2, 2]),
+                                                   [0u8; 4]))) {
+            Err(UpdatableImageError::RegionSampledBeforeUpdate {
+                    offset: [3, 3],
+                    extent: [2, 2],
+                }) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn sampled_regions_forgotten_after_build() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let image = UpdatableImage::new(device.clone(), [16, 16], Format::R8Unorm,
+                                        Some(queue.family()))
+            .unwrap();
+
+        let builder = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap();
+        image.mark_sampled(&builder, [0, 0], [16, 16]);
+        let _cb = builder.build().unwrap();
+        assert!(image.sampled_regions.lock().unwrap()[0].0.upgrade().is_none());
+
+        let builder = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap();
+        image.mark_sampled(&builder, [0, 0], [1, 1]);
+        assert_eq!(image.sampled_regions.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn unsupported_format() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        match UpdatableImage::new(device.clone(), [16, 16], Format::D16Unorm,
+                                  Some(queue.family())) {
+            Err(ImageCreationError::FormatNotSupported) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn overflowing_region() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let image = UpdatableImage::new(device.clone(), [16, 16], Format::R8Unorm,
+                                        Some(queue.family()))
+            .unwrap();
+
+        let builder = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap();
+        match UpdatableImage::update_regions(&image,
+                                             builder,
+                                             Some((UpdatableImageRegion::new([1, 0], [!0, 0]),
+                                                   [0u8; 0]))) {
+            Err(UpdatableImageError::RegionOutOfBounds) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn shared_and_exclusive_locks() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let image = UpdatableImage::new(device.clone(), [16, 16], Format::R8Unorm,
+                                        Some(queue.family()))
+            .unwrap();
+
+        image.lock_for_submission(false).unwrap();
+        image.lock_for_submission(false).unwrap();
+        match image.lock_for_submission(true) {
+            Err(AccessError::AlreadyInUse) => (),
+            _ => panic!(),
+        }
+        image.unlock_submission();
+        image.unlock_submission();
+
+        image.lock_for_submission(true).unwrap();
+        match image.lock_for_submission(false) {
+            Err(AccessError::AlreadyInUse) => (),
+            _ => panic!(),
+        }
+        image.unlock_submission();
+
+        image.lock_for_submission(false).unwrap();
+        image.unlock_submission();
+    }
+
+    #[test]
+    fn matches_format() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let image = UpdatableImage::new(device.clone(), [16, 16], Format::R8G8B8A8Unorm,
+                                        Some(queue.family()))
+            .unwrap();
+        assert!(ImageContent::<[u8; 4]>::matches_format(&*image));
+        assert!(!ImageContent::<u8>::matches_format(&*image));
+    }
+}
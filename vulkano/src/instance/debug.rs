@@ -153,7 +153,8 @@ impl DebugCallback {
             check_errors(vk.CreateDebugReportCallbackEXT(instance.internal_object(),
                                                          &infos,
                                                          ptr::null(),
-                                                         &mut output))?;
+                                                         &mut output),
+                         "vkCreateDebugReportCallbackEXT")?;
             output
         };
 
@@ -21,6 +21,7 @@ use check_errors;
 use instance::PhysicalDevice;
 use instance::loader;
 use instance::loader::LoadingError;
+use version::Version;
 use vk;
 
 macro_rules! extensions {
@@ -71,6 +72,17 @@ macro_rules! extensions {
                     _unbuildable: Unbuildable(())
                 }
             }
+
+            /// Returns the union of this list and another list.
+            #[inline]
+            pub fn union(&self, other: &$sname) -> $sname {
+                $sname {
+                    $(
+                        $ext: self.$ext || other.$ext,
+                    )*
+                    _unbuildable: Unbuildable(())
+                }
+            }
         }
 
         impl fmt::Debug for $sname {
@@ -173,11 +185,13 @@ macro_rules! instance_extensions {
                 let properties: Vec<vk::ExtensionProperties> = unsafe {
                     let mut num = 0;
                     try!(check_errors(entry_points.EnumerateInstanceExtensionProperties(
-                        ptr::null(), &mut num, ptr::null_mut())));
+                        ptr::null(), &mut num, ptr::null_mut()),
+                                      "vkEnumerateInstanceExtensionProperties"));
 
                     let mut properties = Vec::with_capacity(num as usize);
                     try!(check_errors(entry_points.EnumerateInstanceExtensionProperties(
-                        ptr::null(), &mut num, properties.as_mut_ptr())));
+                        ptr::null(), &mut num, properties.as_mut_ptr()),
+                                      "vkEnumerateInstanceExtensionProperties"));
                     properties.set_len(num as usize);
                     properties
                 };
@@ -202,11 +216,13 @@ macro_rules! instance_extensions {
                 let properties: Vec<vk::ExtensionProperties> = unsafe {
                     let mut num = 0;
                     try!(check_errors(entry_points.EnumerateInstanceExtensionProperties(
-                        ptr::null(), &mut num, ptr::null_mut())));
+                        ptr::null(), &mut num, ptr::null_mut()),
+                                      "vkEnumerateInstanceExtensionProperties"));
 
                     let mut properties = Vec::with_capacity(num as usize);
                     try!(check_errors(entry_points.EnumerateInstanceExtensionProperties(
-                        ptr::null(), &mut num, properties.as_mut_ptr())));
+                        ptr::null(), &mut num, properties.as_mut_ptr()),
+                                      "vkEnumerateInstanceExtensionProperties"));
                     properties.set_len(num as usize);
                     properties
                 };
@@ -251,11 +267,13 @@ macro_rules! device_extensions {
                 let properties: Vec<vk::ExtensionProperties> = unsafe {
                     let mut num = 0;
                     try!(check_errors(vk.EnumerateDeviceExtensionProperties(
-                        physical_device.internal_object(), ptr::null(), &mut num, ptr::null_mut())));
+                        physical_device.internal_object(), ptr::null(), &mut num, ptr::null_mut()),
+                                      "vkEnumerateDeviceExtensionProperties"));
 
                     let mut properties = Vec::with_capacity(num as usize);
                     try!(check_errors(vk.EnumerateDeviceExtensionProperties(
-                        physical_device.internal_object(), ptr::null(), &mut num, properties.as_mut_ptr())));
+                        physical_device.internal_object(), ptr::null(), &mut num, properties.as_mut_ptr()),
+                                      "vkEnumerateDeviceExtensionProperties"));
                     properties.set_len(num as usize);
                     properties
                 };
@@ -280,11 +298,13 @@ macro_rules! device_extensions {
                 let properties: Vec<vk::ExtensionProperties> = unsafe {
                     let mut num = 0;
                     try!(check_errors(vk.EnumerateDeviceExtensionProperties(
-                        physical_device.internal_object(), ptr::null(), &mut num, ptr::null_mut())));
+                        physical_device.internal_object(), ptr::null(), &mut num, ptr::null_mut()),
+                                      "vkEnumerateDeviceExtensionProperties"));
 
                     let mut properties = Vec::with_capacity(num as usize);
                     try!(check_errors(vk.EnumerateDeviceExtensionProperties(
-                        physical_device.internal_object(), ptr::null(), &mut num, properties.as_mut_ptr())));
+                        physical_device.internal_object(), ptr::null(), &mut num, properties.as_mut_ptr()),
+                                      "vkEnumerateDeviceExtensionProperties"));
                     properties.set_len(num as usize);
                     properties
                 };
@@ -327,12 +347,16 @@ instance_extensions! {
     khr_android_surface => b"VK_KHR_android_surface",
     khr_win32_surface => b"VK_KHR_win32_surface",
     ext_debug_report => b"VK_EXT_debug_report",
+    ext_debug_utils => b"VK_EXT_debug_utils",
     mvk_ios_surface => b"VK_MVK_ios_surface",
     mvk_macos_surface => b"VK_MVK_macos_surface",
     mvk_moltenvk => b"VK_MVK_moltenvk",     // TODO: confirm that it's an instance extension
     nn_vi_surface => b"VK_NN_vi_surface",
     ext_swapchain_colorspace => b"VK_EXT_swapchain_colorspace",
     khr_get_physical_device_properties2 => b"VK_KHR_get_physical_device_properties2",
+    ext_surface_maintenance1 => b"VK_EXT_surface_maintenance1",
+    khr_get_surface_capabilities2 => b"VK_KHR_get_surface_capabilities2",
+    khr_surface_protected_capabilities => b"VK_KHR_surface_protected_capabilities",
 }
 
 device_extensions! {
@@ -342,6 +366,53 @@ device_extensions! {
     khr_display_swapchain => b"VK_KHR_display_swapchain",
     khr_sampler_mirror_clamp_to_edge => b"VK_KHR_sampler_mirror_clamp_to_edge",
     khr_maintenance1 => b"VK_KHR_maintenance1",
+    khr_maintenance5 => b"VK_KHR_maintenance5",
+    khr_timeline_semaphore => b"VK_KHR_timeline_semaphore",
+    khr_synchronization2 => b"VK_KHR_synchronization2",
+    khr_shared_presentable_image => b"VK_KHR_shared_presentable_image",
+    khr_dynamic_rendering => b"VK_KHR_dynamic_rendering",
+    ext_extended_dynamic_state => b"VK_EXT_extended_dynamic_state",
+    ext_depth_clip_enable => b"VK_EXT_depth_clip_enable",
+    ext_subgroup_size_control => b"VK_EXT_subgroup_size_control",
+    ext_swapchain_maintenance1 => b"VK_EXT_swapchain_maintenance1",
+    ext_shader_object => b"VK_EXT_shader_object",
+    khr_cooperative_matrix => b"VK_KHR_cooperative_matrix",
+    khr_video_queue => b"VK_KHR_video_queue",
+    ext_private_data => b"VK_EXT_private_data",
+    ext_image_view_min_lod => b"VK_EXT_image_view_min_lod",
+    ext_pipeline_creation_feedback => b"VK_EXT_pipeline_creation_feedback",
+    nv_device_generated_commands => b"VK_NV_device_generated_commands",
+}
+
+impl DeviceExtensions {
+    /// Returns the list of extensions whose functionality is part of the core API in the given
+    /// version of Vulkan.
+    ///
+    /// A device whose API version is at least `version` can use the functionalities of these
+    /// extensions without enabling them.
+    pub fn promoted_to_core(version: Version) -> DeviceExtensions {
+        let at_least = |major, minor| {
+            version >=
+                Version {
+                    major: major,
+                    minor: minor,
+                    patch: 0,
+                }
+        };
+
+        DeviceExtensions {
+            khr_maintenance1: at_least(1, 1),
+            khr_timeline_semaphore: at_least(1, 2),
+            khr_synchronization2: at_least(1, 3),
+            khr_dynamic_rendering: at_least(1, 3),
+            ext_extended_dynamic_state: at_least(1, 3),
+            ext_private_data: at_least(1, 3),
+            ext_pipeline_creation_feedback: at_least(1, 3),
+            ext_subgroup_size_control: at_least(1, 3),
+            khr_maintenance5: at_least(1, 4),
+            ..DeviceExtensions::none()
+        }
+    }
 }
 
 /// Error that can happen when loading the list of layers.
@@ -417,6 +488,7 @@ pub struct Unbuildable(());
 mod tests {
     use instance::{DeviceExtensions, RawDeviceExtensions};
     use instance::{InstanceExtensions, RawInstanceExtensions};
+    use version::Version;
 
     #[test]
     fn empty_extensions() {
@@ -426,4 +498,39 @@ mod tests {
         let d: RawDeviceExtensions = (&DeviceExtensions::none()).into();
         assert!(d.iter().next().is_none());
     }
+
+    #[test]
+    fn promoted_to_core() {
+        let version = |minor| Version { major: 1, minor: minor, patch: 0 };
+
+        assert_eq!(DeviceExtensions::promoted_to_core(version(0)), DeviceExtensions::none());
+
+        let ext = DeviceExtensions::promoted_to_core(version(1));
+        assert!(ext.khr_maintenance1);
+        assert!(!ext.khr_timeline_semaphore);
+
+        let ext = DeviceExtensions::promoted_to_core(version(2));
+        assert!(ext.khr_timeline_semaphore);
+        assert!(!ext.khr_synchronization2);
+
+        let ext = DeviceExtensions::promoted_to_core(version(3));
+        assert!(ext.khr_maintenance1 && ext.khr_timeline_semaphore);
+        assert!(ext.khr_synchronization2 && ext.khr_dynamic_rendering);
+        assert!(ext.ext_extended_dynamic_state && ext.ext_private_data);
+        assert!(ext.ext_pipeline_creation_feedback && ext.ext_subgroup_size_control);
+        assert!(!ext.khr_maintenance5);
+
+        let ext = DeviceExtensions::promoted_to_core(version(4));
+        assert!(ext.khr_maintenance5);
+        assert!(!ext.khr_swapchain && !ext.ext_shader_object);
+    }
+
+    #[test]
+    fn union() {
+        let a = DeviceExtensions { khr_swapchain: true, ..DeviceExtensions::none() };
+        let b = DeviceExtensions { khr_maintenance1: true, ..DeviceExtensions::none() };
+        let u = a.union(&b);
+        assert!(u.khr_swapchain && u.khr_maintenance1);
+        assert_eq!(u.difference(&b), a);
+    }
 }
@@ -14,6 +14,7 @@ use std::ffi::CStr;
 use std::ffi::CString;
 use std::fmt;
 use std::mem;
+use std::os::raw::c_void;
 use std::ptr;
 use std::slice;
 use std::sync::Arc;
@@ -26,8 +27,14 @@ use instance::loader;
 use instance::loader::LoadingError;
 use vk;
 
+use descriptor::descriptor::ShaderStages;
 use features::Features;
-use instance::{InstanceExtensions, RawInstanceExtensions};
+use format::Format;
+use image;
+use image::ImageUsage;
+use instance::{DeviceExtensions, InstanceExtensions, RawInstanceExtensions};
+use swapchain::CapabilitiesError;
+use swapchain::Surface;
 use version::Version;
 
 /// An instance of a Vulkan context. This is the main object that should be created by an
@@ -86,6 +93,7 @@ pub struct Instance {
     vk: vk::InstancePointers,
     extensions: InstanceExtensions,
     layers: SmallVec<[CString; 16]>,
+    api_version: Version,
 }
 
 impl Instance {
@@ -118,17 +126,43 @@ impl Instance {
                            -> Result<Arc<Instance>, InstanceCreationError>
         where L: IntoIterator<Item = &'a &'a str>,
               Ext: Into<RawInstanceExtensions>
+    {
+        let version = Version {
+            major: 1,
+            minor: 0,
+            patch: 0,
+        };
+
+        Instance::with_api_version(app_infos, extensions, layers, version)
+    }
+
+    /// Same as `new`, but requests the given version of Vulkan instead of 1.0.
+    ///
+    /// The version that is actually usable with a device is the minimum between this version
+    /// and the version of the physical device. See `Device::api_version()`.
+    ///
+    /// Returns `InstanceCreationError::IncompatibleDriver` if `api_version` is higher than 1.0
+    /// and the Vulkan loader only supports Vulkan 1.0.
+    ///
+    /// # Panic
+    ///
+    /// Same as `new`.
+    pub fn with_api_version<'a, L, Ext>(app_infos: Option<&ApplicationInfo>, extensions: Ext,
+                                        layers: L, api_version: Version)
+                                        -> Result<Arc<Instance>, InstanceCreationError>
+        where L: IntoIterator<Item = &'a &'a str>,
+              Ext: Into<RawInstanceExtensions>
     {
         let layers = layers
             .into_iter()
             .map(|&layer| CString::new(layer).unwrap())
             .collect::<SmallVec<[_; 16]>>();
 
-        Instance::new_inner(app_infos, extensions.into(), layers)
+        Instance::new_inner(app_infos, extensions.into(), layers, api_version)
     }
 
     fn new_inner(app_infos: Option<&ApplicationInfo>, extensions: RawInstanceExtensions,
-                 layers: SmallVec<[CString; 16]>)
+                 layers: SmallVec<[CString; 16]>, api_version: Version)
                  -> Result<Arc<Instance>, InstanceCreationError> {
         // TODO: For now there are still buggy drivers that will segfault if you don't pass any
         //       appinfos. Therefore for now we ensure that it can't be `None`.
@@ -153,6 +187,16 @@ impl Instance {
             None
         };
 
+        // Loaders that only support Vulkan 1.0 refuse any other version.
+        let version_1_0 = Version {
+            major: 1,
+            minor: 0,
+            patch: 0,
+        };
+        if api_version > version_1_0 && Instance::loader_api_version()? == version_1_0 {
+            return Err(InstanceCreationError::IncompatibleDriver);
+        }
+
         // Building the `vk::ApplicationInfo` if required.
         let app_infos = if let Some(app_infos) = app_infos {
             Some(vk::ApplicationInfo {
@@ -180,11 +224,7 @@ impl Instance {
                     .engine_version
                     .map(|v| v.into_vulkan_version())
                     .unwrap_or(0),
-                apiVersion: Version {
-                    major: 1,
-                    minor: 0,
-                    patch: 0,
-                }.into_vulkan_version(), // TODO:
+                apiVersion: api_version.into_vulkan_version(),
             })
 
         } else {
@@ -222,7 +262,8 @@ impl Instance {
                 ppEnabledExtensionNames: extensions_list.as_ptr(),
             };
 
-            check_errors(entry_points.CreateInstance(&infos, ptr::null(), &mut output))?;
+            check_errors(entry_points.CreateInstance(&infos, ptr::null(), &mut output),
+                         "vkCreateInstance")?;
             output
         };
 
@@ -238,10 +279,12 @@ impl Instance {
         // Enumerating all physical devices.
         let physical_devices: Vec<vk::PhysicalDevice> = unsafe {
             let mut num = 0;
-            check_errors(vk.EnumeratePhysicalDevices(instance, &mut num, ptr::null_mut()))?;
+            check_errors(vk.EnumeratePhysicalDevices(instance, &mut num, ptr::null_mut()),
+                         "vkEnumeratePhysicalDevices")?;
 
             let mut devices = Vec::with_capacity(num as usize);
-            check_errors(vk.EnumeratePhysicalDevices(instance, &mut num, devices.as_mut_ptr()))?;
+            check_errors(vk.EnumeratePhysicalDevices(instance, &mut num, devices.as_mut_ptr()),
+                         "vkEnumeratePhysicalDevices")?;
             devices.set_len(num as usize);
             devices
         };
@@ -264,9 +307,36 @@ impl Instance {
                         vk: vk,
                         extensions: extensions,
                         layers: layers,
+                        api_version: api_version,
                     }))
     }
 
+    /// Returns the highest version of Vulkan supported by the loader.
+    ///
+    /// Loaders that only support Vulkan 1.0 don't provide `vkEnumerateInstanceVersion`, in which
+    /// case 1.0 is returned.
+    fn loader_api_version() -> Result<Version, InstanceCreationError> {
+        let f = loader::static_functions()?;
+
+        unsafe {
+            let name = b"vkEnumerateInstanceVersion\0";
+            let ptr: *const c_void = mem::transmute(f.GetInstanceProcAddr(0, name.as_ptr() as
+                                                                             *const _));
+            if ptr.is_null() {
+                return Ok(Version {
+                              major: 1,
+                              minor: 0,
+                              patch: 0,
+                          });
+            }
+
+            let enumerate: extern "system" fn(*mut u32) -> vk::Result = mem::transmute(ptr);
+            let mut version = 0;
+            check_errors(enumerate(&mut version), "vkEnumerateInstanceVersion")?;
+            Ok(Version::from_vulkan_version(version))
+        }
+    }
+
     /// Initialize all physical devices
     fn init_physical_devices(vk: &vk::InstancePointers, physical_devices: Vec<vk::PhysicalDevice>)
                              -> Vec<PhysicalDeviceInfos> {
@@ -307,6 +377,7 @@ impl Instance {
                             memory: memory,
                             queue_families: queue_families,
                             available_features: Features::from(available_features),
+                            subgroup: None,
                         });
         }
         output
@@ -332,6 +403,34 @@ impl Instance {
                 output.properties
             };
 
+            // The subgroup properties are part of Vulkan 1.1 and can't be chained if the device
+            // only supports Vulkan 1.0.
+            let subgroup = if Version::from_vulkan_version(properties.apiVersion) >=
+                (Version { major: 1, minor: 1, patch: 0 })
+            {
+                unsafe {
+                    let mut subgroup = vk::PhysicalDeviceSubgroupProperties {
+                        sType: vk::STRUCTURE_TYPE_PHYSICAL_DEVICE_SUBGROUP_PROPERTIES,
+                        pNext: ptr::null_mut(),
+                        subgroupSize: 0,
+                        supportedStages: 0,
+                        supportedOperations: 0,
+                        quadOperationsInAllStages: vk::FALSE,
+                    };
+
+                    let mut output = vk::PhysicalDeviceProperties2KHR {
+                        sType: vk::STRUCTURE_TYPE_PHYSICAL_DEVICE_PROPERTIES_2_KHR,
+                        pNext: &mut subgroup as *mut _ as *const _,
+                        properties: mem::uninitialized(),
+                    };
+
+                    vk.GetPhysicalDeviceProperties2KHR(device, &mut output);
+                    Some(SubgroupProperties::from_vk(&subgroup))
+                }
+            } else {
+                None
+            };
+
             let queue_families = unsafe {
                 let mut num = 0;
                 vk.GetPhysicalDeviceQueueFamilyProperties2KHR(device, &mut num, ptr::null_mut());
@@ -381,6 +480,7 @@ impl Instance {
                             memory: memory,
                             queue_families: queue_families,
                             available_features: Features::from(available_features),
+                            subgroup: subgroup,
                         });
         }
         output
@@ -419,6 +519,15 @@ impl Instance {
         &self.extensions
     }
 
+    /// Returns the version of Vulkan that was requested when creating this instance.
+    ///
+    /// This is 1.0, unless the instance was created with `with_api_version`. Note that a physical
+    /// device may support a lower version, see `Device::api_version()`.
+    #[inline]
+    pub fn api_version(&self) -> Version {
+        self.api_version
+    }
+
     /// Returns the list of layers requested when creating this instance.
     #[doc(hidden)]
     #[inline]
@@ -581,6 +690,7 @@ struct PhysicalDeviceInfos {
     queue_families: Vec<vk::QueueFamilyProperties>,
     memory: vk::PhysicalDeviceMemoryProperties,
     available_features: Features,
+    subgroup: Option<SubgroupProperties>,
 }
 
 /// Represents one of the available devices on this machine.
@@ -763,6 +873,17 @@ impl<'a> PhysicalDevice<'a> {
         }
     }
 
+    /// Returns the first queue family that supports transfer operations and nothing else, in
+    /// other words whose queues are dedicated to transfers.
+    ///
+    /// Families that support video or optical flow operations are never returned, even if they
+    /// also advertise transfer operations. See `QueueFlags::is_dedicated_transfer`.
+    #[inline]
+    pub fn dedicated_transfer_queue_family(&self) -> Option<QueueFamily<'a>> {
+        self.queue_families()
+            .find(|family| family.queue_flags().is_dedicated_transfer())
+    }
+
     /// Builds an iterator that enumerates all the memory types on this physical device.
     #[inline]
     pub fn memory_types(&self) -> MemoryTypesIter<'a> {
@@ -809,6 +930,65 @@ impl<'a> PhysicalDevice<'a> {
         }
     }
 
+    /// Returns the subgroup size and the subgroup operations supported by this physical device.
+    ///
+    /// Returns `None` if the properties couldn't be queried, which is the case if the
+    /// `khr_get_physical_device_properties2` extension isn't enabled on the instance or if the
+    /// device doesn't support Vulkan 1.1.
+    #[inline]
+    pub fn subgroup_properties(&self) -> Option<SubgroupProperties> {
+        self.infos().subgroup
+    }
+
+    /// Returns the combinations of matrix sizes, component types and scopes that shaders can use
+    /// with cooperative matrix operations on this physical device.
+    ///
+    /// Returns an empty list if the device doesn't support the `khr_cooperative_matrix`
+    /// extension. Combinations that use component types or scopes unknown to vulkano are
+    /// skipped.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the device or host ran out of memory.
+    ///
+    pub fn cooperative_matrix_properties(&self) -> Vec<CooperativeMatrixProperties> {
+        if !DeviceExtensions::supported_by_device(self).khr_cooperative_matrix {
+            return Vec::new();
+        }
+
+        let vk = self.instance.pointers();
+        let properties: Vec<vk::CooperativeMatrixPropertiesKHR> = unsafe {
+            let mut num = 0;
+            let r = vk.GetPhysicalDeviceCooperativeMatrixPropertiesKHR(self.internal_object(),
+                                                                       &mut num,
+                                                                       ptr::null_mut());
+            if let Err(err) = check_errors(r, "vkGetPhysicalDeviceCooperativeMatrixPropertiesKHR") {
+                panic!("{:?}", OomError::from(err));
+            }
+
+            let mut properties = (0 .. num)
+                .map(|_| {
+                    let mut p: vk::CooperativeMatrixPropertiesKHR = mem::zeroed();
+                    p.sType = vk::STRUCTURE_TYPE_COOPERATIVE_MATRIX_PROPERTIES_KHR;
+                    p
+                })
+                .collect::<Vec<_>>();
+            let r = vk.GetPhysicalDeviceCooperativeMatrixPropertiesKHR(self.internal_object(),
+                                                                       &mut num,
+                                                                       properties.as_mut_ptr());
+            if let Err(err) = check_errors(r, "vkGetPhysicalDeviceCooperativeMatrixPropertiesKHR") {
+                panic!("{:?}", OomError::from(err));
+            }
+            properties.truncate(num as usize);
+            properties
+        };
+
+        properties
+            .iter()
+            .filter_map(CooperativeMatrixProperties::from_vk)
+            .collect()
+    }
+
     /// Gives access to the limits of the physical device.
     ///
     /// This function should be zero-cost in release mode. It only exists to not pollute the
@@ -818,6 +998,74 @@ impl<'a> PhysicalDevice<'a> {
         Limits { device: *self }
     }
 
+    /// Returns the numbers of samples per pixel that can be used when creating a two-dimensional
+    /// image with optimal tiling, the given format and the given usage.
+    ///
+    /// This is the intersection of the sample counts allowed by the limits of the device (for
+    /// example `framebuffer_color_sample_counts` or `framebuffer_depth_sample_counts`) and of the
+    /// sample counts supported for this specific format. The list is empty if the format can't
+    /// be used at all with this usage.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the device or host ran out of memory.
+    ///
+    pub fn supported_sample_counts(&self, format: Format, usage: ImageUsage) -> SampleCounts {
+        let limits = image::sys::limits_sample_counts(self.limits(), format, usage);
+
+        let vk = self.instance.pointers();
+        let output: vk::ImageFormatProperties = unsafe {
+            let mut output = mem::uninitialized();
+            let r = vk.GetPhysicalDeviceImageFormatProperties(self.internal_object(),
+                                                              format as u32,
+                                                              vk::IMAGE_TYPE_2D,
+                                                              vk::IMAGE_TILING_OPTIMAL,
+                                                              usage.to_usage_bits(),
+                                                              0,
+                                                              &mut output);
+
+            match check_errors(r, "vkGetPhysicalDeviceImageFormatProperties") {
+                Ok(_) => output,
+                Err(Error::FormatNotSupported) => return SampleCounts::none(),
+                Err(err) => panic!("{:?}", OomError::from(err)),
+            }
+        };
+
+        limits.intersection(&SampleCounts::from_bits(output.sampleCounts))
+    }
+
+    /// Returns the highest number of samples per pixel that can be used when creating a
+    /// two-dimensional image with optimal tiling, the given format and the given usage.
+    ///
+    /// Returns `None` if the format can't be used at all with this usage. Otherwise the returned
+    /// value is at least 1.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use vulkano::format::Format;
+    /// use vulkano::image::ImageUsage;
+    /// # use vulkano::instance::PhysicalDevice;
+    /// # let physical_device: PhysicalDevice = return;
+    ///
+    /// let usage = ImageUsage {
+    ///     color_attachment: true,
+    ///     transient_attachment: true,
+    ///     .. ImageUsage::none()
+    /// };
+    ///
+    /// let samples = physical_device.max_sample_count(Format::R8G8B8A8Unorm, usage).unwrap_or(1);
+    /// ```
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the device or host ran out of memory.
+    ///
+    #[inline]
+    pub fn max_sample_count(&self, format: Format, usage: ImageUsage) -> Option<u32> {
+        self.supported_sample_counts(format, usage).max_count()
+    }
+
     /// Returns an opaque number representing the version of the driver of this device.
     ///
     /// The meaning of this number is implementation-specific. It can be used in bug reports, for
@@ -977,6 +1225,104 @@ impl<'a> QueueFamily<'a> {
         (self.flags() & vk::QUEUE_SPARSE_BINDING_BIT) != 0
     }
 
+    /// Returns all the kinds of operations that queues of this family can execute.
+    ///
+    /// Contrary to `supports_transfers()`, the `transfer` flag is only set if the family
+    /// explicitly advertises it.
+    #[inline]
+    pub fn queue_flags(&self) -> QueueFlags {
+        QueueFlags::from_bits(self.flags())
+    }
+
+    /// Returns the number of meaningful bits of the timestamps written by queues of this family,
+    /// or `None` if they don't support timestamps.
+    #[inline]
+    pub fn timestamp_valid_bits(&self) -> Option<u32> {
+        match self.physical_device.infos().queue_families[self.id as usize].timestampValidBits {
+            0 => None,
+            bits => Some(bits),
+        }
+    }
+
+    /// Returns the granularity, in texels, of the image regions that transfer operations on
+    /// queues of this family can work with. The offset and the extent of these regions must be
+    /// multiples of this granularity, unless the region reaches the edge of the image.
+    ///
+    /// Returns `None` if only whole mipmap levels can be transferred.
+    #[inline]
+    pub fn min_image_transfer_granularity(&self) -> Option<[u32; 3]> {
+        let granularity = self.physical_device.infos().queue_families[self.id as usize]
+            .minImageTransferGranularity;
+        if granularity.width == 0 && granularity.height == 0 && granularity.depth == 0 {
+            None
+        } else {
+            Some([granularity.width, granularity.height, granularity.depth])
+        }
+    }
+
+    /// Returns the video codec operations that queues of this family support.
+    ///
+    /// Always empty if the device doesn't support the `khr_video_queue` extension or if the
+    /// `khr_get_physical_device_properties2` extension isn't enabled on the instance.
+    pub fn video_codec_operations(&self) -> VideoCodecOperations {
+        let physical_device = self.physical_device;
+        let instance = physical_device.instance();
+
+        if !instance.loaded_extensions().khr_get_physical_device_properties2 ||
+            !DeviceExtensions::supported_by_device(&physical_device).khr_video_queue
+        {
+            return VideoCodecOperations::none();
+        }
+
+        unsafe {
+            let vk = instance.pointers();
+
+            let mut num = 0;
+            vk.GetPhysicalDeviceQueueFamilyProperties2KHR(physical_device.internal_object(),
+                                                          &mut num,
+                                                          ptr::null_mut());
+
+            let mut video = (0 .. num)
+                .map(|_| {
+                    vk::QueueFamilyVideoPropertiesKHR {
+                        sType: vk::STRUCTURE_TYPE_QUEUE_FAMILY_VIDEO_PROPERTIES_KHR,
+                        pNext: ptr::null_mut(),
+                        videoCodecOperations: 0,
+                    }
+                })
+                .collect::<Vec<_>>();
+            let mut families = video
+                .iter_mut()
+                .map(|video| {
+                    vk::QueueFamilyProperties2KHR {
+                        sType: vk::STRUCTURE_TYPE_QUEUE_FAMILY_PROPERTIES_2_KHR,
+                        pNext: video as *mut _ as *const _,
+                        queueFamilyProperties: mem::zeroed(),
+                    }
+                })
+                .collect::<Vec<_>>();
+            vk.GetPhysicalDeviceQueueFamilyProperties2KHR(physical_device.internal_object(),
+                                                          &mut num,
+                                                          families.as_mut_ptr());
+
+            match video.get(self.id as usize) {
+                Some(video) => VideoCodecOperations::from_bits(video.videoCodecOperations),
+                None => VideoCodecOperations::none(),
+            }
+        }
+    }
+
+    /// Returns true if queues of this family can present images on the given surface.
+    ///
+    /// A queue family that supports graphics operations doesn't necessarily support presenting.
+    /// You should check this when choosing the queue that will present the images of a swapchain.
+    ///
+    /// > **Note**: This is the same as `Surface::is_supported`.
+    #[inline]
+    pub fn supports_surface(&self, surface: &Surface) -> Result<bool, CapabilitiesError> {
+        surface.is_supported(*self)
+    }
+
     /// Internal utility function that returns the flags of this queue family.
     #[inline]
     fn flags(&self) -> u32 {
@@ -1206,17 +1552,23 @@ impl<'a> ExactSizeIterator for MemoryHeapsIter<'a> {
 }
 
 /// Limits of a physical device.
+///
+/// Each method returns one of the values of `VkPhysicalDeviceLimits`. The Vulkan specifications
+/// guarantee a minimum (or maximum) value for most of them, but a device is free to be more
+/// permissive. You should only need to check a limit if you intend to go beyond that guaranteed
+/// value.
 pub struct Limits<'a> {
     device: PhysicalDevice<'a>,
 }
 
 macro_rules! limits_impl {
-    ($($name:ident: $t:ty => $target:ident,)*) => (
+    ($($(#[$attr:meta])* $name:ident: $t:ty => $target:ident,)*) => (
         impl<'a> Limits<'a> {
             $(
+                $(#[$attr])*
                 #[inline]
                 pub fn $name(&self) -> $t {
-                    self.device.infos().properties.limits.$target
+                    FromVkLimit::from_vk_limit(self.device.infos().properties.limits.$target)
                 }
             )*
         }
@@ -1224,123 +1576,871 @@ macro_rules! limits_impl {
 }
 
 limits_impl!{
+    /// Maximum width of a 1D image. Guaranteed to be at least 4096.
     max_image_dimension_1d: u32 => maxImageDimension1D,
+    /// Maximum width and height of a 2D image. Guaranteed to be at least 4096.
     max_image_dimension_2d: u32 => maxImageDimension2D,
+    /// Maximum width, height and depth of a 3D image. Guaranteed to be at least 256.
     max_image_dimension_3d: u32 => maxImageDimension3D,
+    /// Maximum width and height of a cubemap image. Guaranteed to be at least 4096.
     max_image_dimension_cube: u32 => maxImageDimensionCube,
+    /// Maximum number of array layers of an image. Guaranteed to be at least 256.
     max_image_array_layers: u32 => maxImageArrayLayers,
+    /// Maximum number of elements of a buffer view. Guaranteed to be at least 65536.
     max_texel_buffer_elements: u32 => maxTexelBufferElements,
-    max_uniform_buffer_range: u32 => maxUniformBufferRange,
-    max_storage_buffer_range: u32 => maxStorageBufferRange,
-    max_push_constants_size: u32 => maxPushConstantsSize,
+    /// Maximum size in bytes of the range of a uniform buffer descriptor. Guaranteed to be at
+    /// least 16384.
+    max_uniform_buffer_range: usize => maxUniformBufferRange,
+    /// Maximum size in bytes of the range of a storage buffer descriptor. Guaranteed to be at
+    /// least 2^27.
+    max_storage_buffer_range: usize => maxStorageBufferRange,
+    /// Maximum size in bytes of the push constants of a pipeline layout. Guaranteed to be at
+    /// least 128.
+    max_push_constants_size: usize => maxPushConstantsSize,
+    /// Maximum number of memory allocations that can exist simultaneously. Guaranteed to be at
+    /// least 4096.
+    ///
+    /// This value is usually low, which is why you should allocate large chunks of memory and
+    /// suballocate them.
     max_memory_allocation_count: u32 => maxMemoryAllocationCount,
+    /// Maximum number of samplers that can exist simultaneously. Guaranteed to be at least 4000.
     max_sampler_allocation_count: u32 => maxSamplerAllocationCount,
-    buffer_image_granularity: u64 => bufferImageGranularity,
+    /// Granularity in bytes at which a buffer and an optimal image that are bound to the same
+    /// memory object can be placed next to each other without aliasing.
+    buffer_image_granularity: usize => bufferImageGranularity,
+    /// Total size in bytes of the address space available for sparse resources.
+    ///
+    /// This is a `u64` because it can be larger than what a `usize` can hold on 32-bit
+    /// platforms.
     sparse_address_space_size: u64 => sparseAddressSpaceSize,
+    /// Maximum number of descriptor sets that a pipeline can use. Guaranteed to be at least 4.
     max_bound_descriptor_sets: u32 => maxBoundDescriptorSets,
+    /// Maximum number of samplers accessible by a single shader stage. Guaranteed to be at
+    /// least 16.
     max_per_stage_descriptor_samplers: u32 => maxPerStageDescriptorSamplers,
+    /// Maximum number of uniform buffers accessible by a single shader stage. Guaranteed to be
+    /// at least 12.
     max_per_stage_descriptor_uniform_buffers: u32 => maxPerStageDescriptorUniformBuffers,
+    /// Maximum number of storage buffers accessible by a single shader stage. Guaranteed to be
+    /// at least 4.
     max_per_stage_descriptor_storage_buffers: u32 => maxPerStageDescriptorStorageBuffers,
+    /// Maximum number of sampled images accessible by a single shader stage. Guaranteed to be
+    /// at least 16.
     max_per_stage_descriptor_sampled_images: u32 => maxPerStageDescriptorSampledImages,
+    /// Maximum number of storage images accessible by a single shader stage. Guaranteed to be
+    /// at least 4.
     max_per_stage_descriptor_storage_images: u32 => maxPerStageDescriptorStorageImages,
+    /// Maximum number of input attachments accessible by a single fragment shader. Guaranteed
+    /// to be at least 4.
     max_per_stage_descriptor_input_attachments: u32 => maxPerStageDescriptorInputAttachments,
+    /// Maximum number of resources of any kind accessible by a single shader stage, including
+    /// the color attachments of a fragment shader. Guaranteed to be at least 128.
     max_per_stage_resources: u32 => maxPerStageResources,
+    /// Maximum number of samplers in all the descriptor sets of a pipeline layout. Guaranteed to
+    /// be at least 96.
     max_descriptor_set_samplers: u32 => maxDescriptorSetSamplers,
+    /// Maximum number of uniform buffers in all the descriptor sets of a pipeline layout.
+    /// Guaranteed to be at least 72.
     max_descriptor_set_uniform_buffers: u32 => maxDescriptorSetUniformBuffers,
+    /// Maximum number of dynamic uniform buffers in all the descriptor sets of a pipeline
+    /// layout. Guaranteed to be at least 8.
     max_descriptor_set_uniform_buffers_dynamic: u32 => maxDescriptorSetUniformBuffersDynamic,
+    /// Maximum number of storage buffers in all the descriptor sets of a pipeline layout.
+    /// Guaranteed to be at least 24.
     max_descriptor_set_storage_buffers: u32 => maxDescriptorSetStorageBuffers,
+    /// Maximum number of dynamic storage buffers in all the descriptor sets of a pipeline
+    /// layout. Guaranteed to be at least 4.
     max_descriptor_set_storage_buffers_dynamic: u32 => maxDescriptorSetStorageBuffersDynamic,
+    /// Maximum number of sampled images in all the descriptor sets of a pipeline layout.
+    /// Guaranteed to be at least 96.
     max_descriptor_set_sampled_images: u32 => maxDescriptorSetSampledImages,
+    /// Maximum number of storage images in all the descriptor sets of a pipeline layout.
+    /// Guaranteed to be at least 24.
     max_descriptor_set_storage_images: u32 => maxDescriptorSetStorageImages,
+    /// Maximum number of input attachments in all the descriptor sets of a pipeline layout.
+    /// Guaranteed to be at least 4.
     max_descriptor_set_input_attachments: u32 => maxDescriptorSetInputAttachments,
+    /// Maximum number of vertex attributes of a graphics pipeline. Guaranteed to be at least 16.
     max_vertex_input_attributes: u32 => maxVertexInputAttributes,
+    /// Maximum number of vertex buffers of a graphics pipeline. Guaranteed to be at least 16.
     max_vertex_input_bindings: u32 => maxVertexInputBindings,
-    max_vertex_input_attribute_offset: u32 => maxVertexInputAttributeOffset,
-    max_vertex_input_binding_stride: u32 => maxVertexInputBindingStride,
+    /// Maximum offset in bytes of a vertex attribute within a vertex. Guaranteed to be at least
+    /// 2047.
+    max_vertex_input_attribute_offset: usize => maxVertexInputAttributeOffset,
+    /// Maximum stride in bytes of a vertex buffer. Guaranteed to be at least 2048.
+    max_vertex_input_binding_stride: usize => maxVertexInputBindingStride,
+    /// Maximum number of components of the outputs of a vertex shader. Guaranteed to be at
+    /// least 64.
     max_vertex_output_components: u32 => maxVertexOutputComponents,
+    /// Maximum tessellation generation level. Guaranteed to be at least 64 if the
+    /// `tessellation_shader` feature is supported.
     max_tessellation_generation_level: u32 => maxTessellationGenerationLevel,
+    /// Maximum number of vertices in a patch. Guaranteed to be at least 32 if the
+    /// `tessellation_shader` feature is supported.
     max_tessellation_patch_size: u32 => maxTessellationPatchSize,
+    /// Maximum number of per-vertex input components of a tessellation control shader.
     max_tessellation_control_per_vertex_input_components: u32 => maxTessellationControlPerVertexInputComponents,
+    /// Maximum number of per-vertex output components of a tessellation control shader.
     max_tessellation_control_per_vertex_output_components: u32 => maxTessellationControlPerVertexOutputComponents,
+    /// Maximum number of per-patch output components of a tessellation control shader.
     max_tessellation_control_per_patch_output_components: u32 => maxTessellationControlPerPatchOutputComponents,
+    /// Maximum total number of output components of a tessellation control shader.
     max_tessellation_control_total_output_components: u32 => maxTessellationControlTotalOutputComponents,
+    /// Maximum number of per-vertex input components of a tessellation evaluation shader.
     max_tessellation_evaluation_input_components: u32 => maxTessellationEvaluationInputComponents,
+    /// Maximum number of per-vertex output components of a tessellation evaluation shader.
     max_tessellation_evaluation_output_components: u32 => maxTessellationEvaluationOutputComponents,
+    /// Maximum invocation count of a geometry shader. Guaranteed to be at least 32 if the
+    /// `geometry_shader` feature is supported.
     max_geometry_shader_invocations: u32 => maxGeometryShaderInvocations,
+    /// Maximum number of input components of a geometry shader.
     max_geometry_input_components: u32 => maxGeometryInputComponents,
+    /// Maximum number of output components of a geometry shader.
     max_geometry_output_components: u32 => maxGeometryOutputComponents,
+    /// Maximum number of vertices that a geometry shader can emit.
     max_geometry_output_vertices: u32 => maxGeometryOutputVertices,
+    /// Maximum total number of components that a geometry shader can emit across all its
+    /// vertices.
     max_geometry_total_output_components: u32 => maxGeometryTotalOutputComponents,
+    /// Maximum number of input components of a fragment shader. Guaranteed to be at least 64.
     max_fragment_input_components: u32 => maxFragmentInputComponents,
+    /// Maximum number of color attachments that a fragment shader can write to. Guaranteed to
+    /// be at least 4.
     max_fragment_output_attachments: u32 => maxFragmentOutputAttachments,
+    /// Maximum number of attachments that a fragment shader can write to when dual-source
+    /// blending is used.
     max_fragment_dual_src_attachments: u32 => maxFragmentDualSrcAttachments,
+    /// Maximum total number of storage buffers, storage images and color attachments used by a
+    /// fragment shader. Guaranteed to be at least 4.
     max_fragment_combined_output_resources: u32 => maxFragmentCombinedOutputResources,
-    max_compute_shared_memory_size: u32 => maxComputeSharedMemorySize,
+    /// Maximum size in bytes of the shared memory of a compute shader. Guaranteed to be at least
+    /// 16384.
+    max_compute_shared_memory_size: usize => maxComputeSharedMemorySize,
+    /// Maximum number of work groups of a dispatch command in each dimension. Guaranteed to be
+    /// at least 65535 in each dimension.
     max_compute_work_group_count: [u32; 3] => maxComputeWorkGroupCount,
+    /// Maximum total number of invocations in a single work group. Guaranteed to be at least
+    /// 128.
     max_compute_work_group_invocations: u32 => maxComputeWorkGroupInvocations,
+    /// Maximum size of a work group in each dimension. Guaranteed to be at least
+    /// `[128, 128, 64]`.
     max_compute_work_group_size: [u32; 3] => maxComputeWorkGroupSize,
+    /// Number of bits of subpixel precision in the framebuffer coordinates.
     sub_pixel_precision_bits: u32 => subPixelPrecisionBits,
+    /// Number of bits of precision in the division along an axis of an image used for
+    /// filtering.
     sub_texel_precision_bits: u32 => subTexelPrecisionBits,
+    /// Number of bits of division that the LOD calculation for mipmap fetching gets snapped to.
     mipmap_precision_bits: u32 => mipmapPrecisionBits,
+    /// Maximum index value that can be used with an indexed draw command, when using 32-bit
+    /// indices. Guaranteed to be at least 2^24 - 1, or 2^32 - 1 if the `full_draw_index_uint32`
+    /// feature is supported.
     max_draw_indexed_index_value: u32 => maxDrawIndexedIndexValue,
+    /// Maximum draw count of an indirect draw command. Guaranteed to be at least 1, or 2^16 - 1
+    /// if the `multi_draw_indirect` feature is supported.
     max_draw_indirect_count: u32 => maxDrawIndirectCount,
+    /// Maximum absolute value of the LOD bias of a sampler. Guaranteed to be at least 2.0.
     max_sampler_lod_bias: f32 => maxSamplerLodBias,
+    /// Maximum anisotropy of a sampler. Guaranteed to be at least 16.0 if the
+    /// `sampler_anisotropy` feature is supported.
     max_sampler_anisotropy: f32 => maxSamplerAnisotropy,
+    /// Maximum number of viewports. Guaranteed to be at least 16 if the `multi_viewport`
+    /// feature is supported, or 1 otherwise.
     max_viewports: u32 => maxViewports,
+    /// Maximum width and height of a viewport. Guaranteed to be at least 4096.
     max_viewport_dimensions: [u32; 2] => maxViewportDimensions,
+    /// Range in which the corners of a viewport must be located.
     viewport_bounds_range: [f32; 2] => viewportBoundsRange,
+    /// Number of bits of subpixel precision of the viewport bounds.
     viewport_sub_pixel_bits: u32 => viewportSubPixelBits,
+    /// Alignment in bytes of the pointers returned when mapping memory. Guaranteed to be at
+    /// least 64.
     min_memory_map_alignment: usize => minMemoryMapAlignment,
-    min_texel_buffer_offset_alignment: u64 => minTexelBufferOffsetAlignment,
-    min_uniform_buffer_offset_alignment: u64 => minUniformBufferOffsetAlignment,
-    min_storage_buffer_offset_alignment: u64 => minStorageBufferOffsetAlignment,
+    /// Alignment in bytes of the offset of a buffer view. Guaranteed to be at most 256.
+    min_texel_buffer_offset_alignment: usize => minTexelBufferOffsetAlignment,
+    /// Alignment in bytes of the offset of a uniform buffer descriptor. Guaranteed to be at
+    /// most 256.
+    ///
+    /// You must take this value into account when putting multiple uniform buffers in the same
+    /// buffer.
+    min_uniform_buffer_offset_alignment: usize => minUniformBufferOffsetAlignment,
+    /// Alignment in bytes of the offset of a storage buffer descriptor. Guaranteed to be at
+    /// most 256.
+    min_storage_buffer_offset_alignment: usize => minStorageBufferOffsetAlignment,
+    /// Minimum offset value of the `ConstOffset` image operand of image sample instructions.
     min_texel_offset: i32 => minTexelOffset,
+    /// Maximum offset value of the `ConstOffset` image operand of image sample instructions.
     max_texel_offset: u32 => maxTexelOffset,
+    /// Minimum offset value of the `Offset` and `ConstOffsets` image operands of image gather
+    /// instructions.
     min_texel_gather_offset: i32 => minTexelGatherOffset,
+    /// Maximum offset value of the `Offset` and `ConstOffsets` image operands of image gather
+    /// instructions.
     max_texel_gather_offset: u32 => maxTexelGatherOffset,
+    /// Minimum offset value of the `InterpolateAtOffset` shader instruction.
     min_interpolation_offset: f32 => minInterpolationOffset,
+    /// Maximum offset value of the `InterpolateAtOffset` shader instruction.
     max_interpolation_offset: f32 => maxInterpolationOffset,
+    /// Number of bits of subpixel precision of the `InterpolateAtOffset` shader instruction.
     sub_pixel_interpolation_offset_bits: u32 => subPixelInterpolationOffsetBits,
+    /// Maximum width of a framebuffer. Guaranteed to be at least 4096.
     max_framebuffer_width: u32 => maxFramebufferWidth,
+    /// Maximum height of a framebuffer. Guaranteed to be at least 4096.
     max_framebuffer_height: u32 => maxFramebufferHeight,
+    /// Maximum number of layers of a framebuffer. Guaranteed to be at least 256.
     max_framebuffer_layers: u32 => maxFramebufferLayers,
-    framebuffer_color_sample_counts: u32 => framebufferColorSampleCounts,      // FIXME: SampleCountFlag
-    framebuffer_depth_sample_counts: u32 => framebufferDepthSampleCounts,      // FIXME: SampleCountFlag
-    framebuffer_stencil_sample_counts: u32 => framebufferStencilSampleCounts,      // FIXME: SampleCountFlag
-    framebuffer_no_attachments_sample_counts: u32 => framebufferNoAttachmentsSampleCounts,      // FIXME: SampleCountFlag
+    /// Sample counts supported by the color attachments of a framebuffer.
+    framebuffer_color_sample_counts: SampleCounts => framebufferColorSampleCounts,
+    /// Sample counts supported by the depth attachments of a framebuffer.
+    framebuffer_depth_sample_counts: SampleCounts => framebufferDepthSampleCounts,
+    /// Sample counts supported by the stencil attachments of a framebuffer.
+    framebuffer_stencil_sample_counts: SampleCounts => framebufferStencilSampleCounts,
+    /// Sample counts supported by subpasses that don't use any attachment.
+    framebuffer_no_attachments_sample_counts: SampleCounts => framebufferNoAttachmentsSampleCounts,
+    /// Maximum number of color attachments of a subpass. Guaranteed to be at least 4.
     max_color_attachments: u32 => maxColorAttachments,
-    sampled_image_color_sample_counts: u32 => sampledImageColorSampleCounts,        // FIXME: SampleCountFlag
-    sampled_image_integer_sample_counts: u32 => sampledImageIntegerSampleCounts,        // FIXME: SampleCountFlag
-    sampled_image_depth_sample_counts: u32 => sampledImageDepthSampleCounts,        // FIXME: SampleCountFlag
-    sampled_image_stencil_sample_counts: u32 => sampledImageStencilSampleCounts,        // FIXME: SampleCountFlag
-    storage_image_sample_counts: u32 => storageImageSampleCounts,      // FIXME: SampleCountFlag
+    /// Sample counts supported by sampled images with a non-integer color format.
+    sampled_image_color_sample_counts: SampleCounts => sampledImageColorSampleCounts,
+    /// Sample counts supported by sampled images with an integer color format.
+    sampled_image_integer_sample_counts: SampleCounts => sampledImageIntegerSampleCounts,
+    /// Sample counts supported by sampled images with a depth format.
+    sampled_image_depth_sample_counts: SampleCounts => sampledImageDepthSampleCounts,
+    /// Sample counts supported by sampled images with a stencil format.
+    sampled_image_stencil_sample_counts: SampleCounts => sampledImageStencilSampleCounts,
+    /// Sample counts supported by storage images.
+    storage_image_sample_counts: SampleCounts => storageImageSampleCounts,
+    /// Maximum number of 32-bit words of the `SampleMask` built-in variable.
     max_sample_mask_words: u32 => maxSampleMaskWords,
-    timestamp_compute_and_graphics: u32 => timestampComputeAndGraphics,        // TODO: these are booleans
+    /// True if timestamps are supported on all the graphics and compute queues.
+    timestamp_compute_and_graphics: bool => timestampComputeAndGraphics,
+    /// Number of nanoseconds required for a timestamp query to be incremented by 1.
     timestamp_period: f32 => timestampPeriod,
+    /// Maximum number of clip distances in a shader stage.
     max_clip_distances: u32 => maxClipDistances,
+    /// Maximum number of cull distances in a shader stage.
     max_cull_distances: u32 => maxCullDistances,
+    /// Maximum combined number of clip and cull distances in a shader stage.
     max_combined_clip_and_cull_distances: u32 => maxCombinedClipAndCullDistances,
+    /// Number of discrete priorities that can be assigned to a queue. Guaranteed to be at
+    /// least 2.
     discrete_queue_priorities: u32 => discreteQueuePriorities,
+    /// Range of supported sizes for points.
     point_size_range: [f32; 2] => pointSizeRange,
+    /// Range of supported widths for lines.
     line_width_range: [f32; 2] => lineWidthRange,
+    /// Granularity of the supported point sizes.
     point_size_granularity: f32 => pointSizeGranularity,
+    /// Granularity of the supported line widths.
     line_width_granularity: f32 => lineWidthGranularity,
-    strict_lines: u32 => strictLines,        // TODO: these are booleans
-    standard_sample_locations: u32 => standardSampleLocations,        // TODO: these are booleans
-    optimal_buffer_copy_offset_alignment: u64 => optimalBufferCopyOffsetAlignment,
-    optimal_buffer_copy_row_pitch_alignment: u64 => optimalBufferCopyRowPitchAlignment,
-    non_coherent_atom_size: u64 => nonCoherentAtomSize,
+    /// True if lines are rasterized according to the strict rules of the specifications.
+    strict_lines: bool => strictLines,
+    /// True if rasterization uses the standard sample locations.
+    standard_sample_locations: bool => standardSampleLocations,
+    /// Optimal alignment in bytes of the buffer offset of buffer-image copy operations.
+    optimal_buffer_copy_offset_alignment: usize => optimalBufferCopyOffsetAlignment,
+    /// Optimal alignment in bytes of the row pitch of buffer-image copy operations.
+    optimal_buffer_copy_row_pitch_alignment: usize => optimalBufferCopyRowPitchAlignment,
+    /// Size and alignment in bytes of the ranges that must be used when flushing or
+    /// invalidating non-coherent memory.
+    non_coherent_atom_size: usize => nonCoherentAtomSize,
+}
+
+impl<'a> Limits<'a> {
+    /// Returns the point size that the implementation will actually use if a shader writes
+    /// `size` to `gl_PointSize`.
+    ///
+    /// The size is clamped to `point_size_range()` and rounded to the nearest multiple of
+    /// `point_size_granularity()`.
+    pub fn clamp_point_size(&self, size: f32) -> f32 {
+        let range = self.point_size_range();
+        let (min, max) = (range[0], range[1]);
+        let granularity = self.point_size_granularity();
+
+        let size = if granularity > 0.0 {
+            min + ((size - min) / granularity).round() * granularity
+        } else {
+            size
+        };
+
+        size.max(min).min(max)
+    }
+}
+
+// Conversion from the raw type of a member of `vk::PhysicalDeviceLimits`.
+trait FromVkLimit<T> {
+    fn from_vk_limit(value: T) -> Self;
+}
+
+impl<T> FromVkLimit<T> for T {
+    #[inline]
+    fn from_vk_limit(value: T) -> T {
+        value
+    }
+}
+
+impl FromVkLimit<u32> for usize {
+    #[inline]
+    fn from_vk_limit(value: u32) -> usize {
+        value as usize
+    }
+}
+
+impl FromVkLimit<vk::DeviceSize> for usize {
+    #[inline]
+    fn from_vk_limit(value: vk::DeviceSize) -> usize {
+        value as usize
+    }
+}
+
+impl FromVkLimit<vk::Bool32> for bool {
+    #[inline]
+    fn from_vk_limit(value: vk::Bool32) -> bool {
+        value != 0
+    }
+}
+
+impl FromVkLimit<vk::SampleCountFlags> for SampleCounts {
+    #[inline]
+    fn from_vk_limit(value: vk::SampleCountFlags) -> SampleCounts {
+        SampleCounts::from_bits(value)
+    }
+}
+
+/// Properties of the subgroups of a physical device.
+///
+/// A subgroup is a set of invocations of a shader that execute together and that can
+/// communicate with each other with subgroup operations.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SubgroupProperties {
+    /// Default number of invocations in each subgroup. Always a power of two, and at least 1.
+    pub subgroup_size: u32,
+    /// Shader stages in which subgroup operations are supported. Always includes the compute
+    /// stage.
+    pub supported_stages: ShaderStages,
+    /// Categories of subgroup operations that are supported.
+    pub supported_operations: SubgroupFeatures,
+    /// If true, quad operations are supported in all the stages of `supported_stages`.
+    /// Otherwise they are only supported in the fragment and compute stages.
+    pub quad_operations_in_all_stages: bool,
+}
+
+impl SubgroupProperties {
+    #[inline]
+    fn from_vk(raw: &vk::PhysicalDeviceSubgroupProperties) -> SubgroupProperties {
+        SubgroupProperties {
+            subgroup_size: raw.subgroupSize,
+            supported_stages: ShaderStages::from_bits(raw.supportedStages),
+            supported_operations: SubgroupFeatures::from_bits(raw.supportedOperations),
+            quad_operations_in_all_stages: raw.quadOperationsInAllStages != 0,
+        }
+    }
+}
+
+/// Categories of subgroup operations.
+///
+/// Each category corresponds to a `GroupNonUniform*` capability in SPIR-V, and to a
+/// `GL_KHR_shader_subgroup_*` extension in GLSL.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SubgroupFeatures {
+    /// Elect and barrier operations. Always supported.
+    pub basic: bool,
+    /// `subgroupAll`, `subgroupAny` and `subgroupAllEqual`.
+    pub vote: bool,
+    /// Reductions and inclusive/exclusive scans, such as `subgroupAdd` or
+    /// `subgroupInclusiveAdd`.
+    pub arithmetic: bool,
+    /// `subgroupBallot` and broadcasts.
+    pub ballot: bool,
+    /// `subgroupShuffle` and `subgroupShuffleXor`.
+    pub shuffle: bool,
+    /// `subgroupShuffleUp` and `subgroupShuffleDown`.
+    pub shuffle_relative: bool,
+    /// Arithmetic operations on clusters of invocations, such as `subgroupClusteredAdd`.
+    pub clustered: bool,
+    /// Operations on quads of invocations, such as `subgroupQuadBroadcast`.
+    pub quad: bool,
+}
+
+impl SubgroupFeatures {
+    /// Builds a `SubgroupFeatures` with all the categories set to `false`.
+    #[inline]
+    pub fn none() -> SubgroupFeatures {
+        SubgroupFeatures {
+            basic: false,
+            vote: false,
+            arithmetic: false,
+            ballot: false,
+            shuffle: false,
+            shuffle_relative: false,
+            clustered: false,
+            quad: false,
+        }
+    }
+
+    #[inline]
+    pub(crate) fn from_bits(val: vk::SubgroupFeatureFlags) -> SubgroupFeatures {
+        SubgroupFeatures {
+            basic: (val & vk::SUBGROUP_FEATURE_BASIC_BIT) != 0,
+            vote: (val & vk::SUBGROUP_FEATURE_VOTE_BIT) != 0,
+            arithmetic: (val & vk::SUBGROUP_FEATURE_ARITHMETIC_BIT) != 0,
+            ballot: (val & vk::SUBGROUP_FEATURE_BALLOT_BIT) != 0,
+            shuffle: (val & vk::SUBGROUP_FEATURE_SHUFFLE_BIT) != 0,
+            shuffle_relative: (val & vk::SUBGROUP_FEATURE_SHUFFLE_RELATIVE_BIT) != 0,
+            clustered: (val & vk::SUBGROUP_FEATURE_CLUSTERED_BIT) != 0,
+            quad: (val & vk::SUBGROUP_FEATURE_QUAD_BIT) != 0,
+        }
+    }
+
+    /// Returns true if all the categories of `other` are also in `self`.
+    #[inline]
+    pub fn is_superset_of(&self, other: &SubgroupFeatures) -> bool {
+        (self.basic || !other.basic) && (self.vote || !other.vote) &&
+            (self.arithmetic || !other.arithmetic) && (self.ballot || !other.ballot) &&
+            (self.shuffle || !other.shuffle) &&
+            (self.shuffle_relative || !other.shuffle_relative) &&
+            (self.clustered || !other.clustered) && (self.quad || !other.quad)
+    }
+}
+
+/// A combination of matrix sizes, component types and scope supported by cooperative matrix
+/// operations.
+///
+/// A cooperative matrix multiply-add computes `Result = A * B + C`, where `A` is an `M x K`
+/// matrix, `B` is a `K x N` matrix, and `C` and `Result` are `M x N` matrices.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct CooperativeMatrixProperties {
+    /// Number of rows of `A`, `C` and `Result`.
+    pub m_size: u32,
+    /// Number of columns of `B`, `C` and `Result`.
+    pub n_size: u32,
+    /// Number of columns of `A` and rows of `B`.
+    pub k_size: u32,
+    /// Type of the components of `A`.
+    pub a_type: ComponentType,
+    /// Type of the components of `B`.
+    pub b_type: ComponentType,
+    /// Type of the components of `C`.
+    pub c_type: ComponentType,
+    /// Type of the components of `Result`.
+    pub result_type: ComponentType,
+    /// If true, the multiply-add must be performed with the `MatrixResultSaturatingAccumulation`
+    /// operand, in other words the result saturates instead of wrapping around.
+    pub saturating_accumulation: bool,
+    /// Set of invocations that operate on the matrices together.
+    pub scope: Scope,
+}
+
+impl CooperativeMatrixProperties {
+    #[inline]
+    fn from_vk(raw: &vk::CooperativeMatrixPropertiesKHR) -> Option<CooperativeMatrixProperties> {
+        Some(CooperativeMatrixProperties {
+                 m_size: raw.MSize,
+                 n_size: raw.NSize,
+                 k_size: raw.KSize,
+                 a_type: ComponentType::from_vk(raw.AType)?,
+                 b_type: ComponentType::from_vk(raw.BType)?,
+                 c_type: ComponentType::from_vk(raw.CType)?,
+                 result_type: ComponentType::from_vk(raw.ResultType)?,
+                 saturating_accumulation: raw.saturatingAccumulation != 0,
+                 scope: Scope::from_vk(raw.scope)?,
+             })
+    }
+}
+
+/// Type of the components of a cooperative matrix.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[repr(u32)]
+pub enum ComponentType {
+    Float16 = vk::COMPONENT_TYPE_FLOAT16_KHR,
+    Float32 = vk::COMPONENT_TYPE_FLOAT32_KHR,
+    Float64 = vk::COMPONENT_TYPE_FLOAT64_KHR,
+    SInt8 = vk::COMPONENT_TYPE_SINT8_KHR,
+    SInt16 = vk::COMPONENT_TYPE_SINT16_KHR,
+    SInt32 = vk::COMPONENT_TYPE_SINT32_KHR,
+    SInt64 = vk::COMPONENT_TYPE_SINT64_KHR,
+    UInt8 = vk::COMPONENT_TYPE_UINT8_KHR,
+    UInt16 = vk::COMPONENT_TYPE_UINT16_KHR,
+    UInt32 = vk::COMPONENT_TYPE_UINT32_KHR,
+    UInt64 = vk::COMPONENT_TYPE_UINT64_KHR,
+}
+
+impl ComponentType {
+    #[inline]
+    fn from_vk(val: vk::ComponentTypeKHR) -> Option<ComponentType> {
+        Some(match val {
+                 vk::COMPONENT_TYPE_FLOAT16_KHR => ComponentType::Float16,
+                 vk::COMPONENT_TYPE_FLOAT32_KHR => ComponentType::Float32,
+                 vk::COMPONENT_TYPE_FLOAT64_KHR => ComponentType::Float64,
+                 vk::COMPONENT_TYPE_SINT8_KHR => ComponentType::SInt8,
+                 vk::COMPONENT_TYPE_SINT16_KHR => ComponentType::SInt16,
+                 vk::COMPONENT_TYPE_SINT32_KHR => ComponentType::SInt32,
+                 vk::COMPONENT_TYPE_SINT64_KHR => ComponentType::SInt64,
+                 vk::COMPONENT_TYPE_UINT8_KHR => ComponentType::UInt8,
+                 vk::COMPONENT_TYPE_UINT16_KHR => ComponentType::UInt16,
+                 vk::COMPONENT_TYPE_UINT32_KHR => ComponentType::UInt32,
+                 vk::COMPONENT_TYPE_UINT64_KHR => ComponentType::UInt64,
+                 _ => return None,
+             })
+    }
+
+    /// Returns the size in bytes of a component of this type.
+    #[inline]
+    pub fn size(&self) -> usize {
+        match *self {
+            ComponentType::SInt8 | ComponentType::UInt8 => 1,
+            ComponentType::Float16 | ComponentType::SInt16 | ComponentType::UInt16 => 2,
+            ComponentType::Float32 | ComponentType::SInt32 | ComponentType::UInt32 => 4,
+            ComponentType::Float64 | ComponentType::SInt64 | ComponentType::UInt64 => 8,
+        }
+    }
+}
+
+/// Set of shader invocations that execute an operation together.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[repr(u32)]
+pub enum Scope {
+    Device = vk::SCOPE_DEVICE_KHR,
+    Workgroup = vk::SCOPE_WORKGROUP_KHR,
+    Subgroup = vk::SCOPE_SUBGROUP_KHR,
+    QueueFamily = vk::SCOPE_QUEUE_FAMILY_KHR,
+}
+
+impl Scope {
+    #[inline]
+    fn from_vk(val: vk::ScopeKHR) -> Option<Scope> {
+        Some(match val {
+                 vk::SCOPE_DEVICE_KHR => Scope::Device,
+                 vk::SCOPE_WORKGROUP_KHR => Scope::Workgroup,
+                 vk::SCOPE_SUBGROUP_KHR => Scope::Subgroup,
+                 vk::SCOPE_QUEUE_FAMILY_KHR => Scope::QueueFamily,
+                 _ => return None,
+             })
+    }
+}
+
+/// Kinds of operations that the queues of a queue family can execute.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct QueueFlags {
+    pub graphics: bool,
+    pub compute: bool,
+    pub transfer: bool,
+    pub sparse_binding: bool,
+    /// The queues can be created as protected queues.
+    pub protected: bool,
+    /// Requires the `khr_video_decode_queue` extension.
+    pub video_decode: bool,
+    /// Requires the `khr_video_encode_queue` extension.
+    pub video_encode: bool,
+    /// Requires the `nv_optical_flow` extension.
+    pub optical_flow: bool,
+}
+
+impl QueueFlags {
+    /// Builds a `QueueFlags` with all the flags set to `false`.
+    #[inline]
+    pub fn none() -> QueueFlags {
+        QueueFlags {
+            graphics: false,
+            compute: false,
+            transfer: false,
+            sparse_binding: false,
+            protected: false,
+            video_decode: false,
+            video_encode: false,
+            optical_flow: false,
+        }
+    }
+
+    #[inline]
+    pub(crate) fn from_bits(val: vk::QueueFlags) -> QueueFlags {
+        QueueFlags {
+            graphics: (val & vk::QUEUE_GRAPHICS_BIT) != 0,
+            compute: (val & vk::QUEUE_COMPUTE_BIT) != 0,
+            transfer: (val & vk::QUEUE_TRANSFER_BIT) != 0,
+            sparse_binding: (val & vk::QUEUE_SPARSE_BINDING_BIT) != 0,
+            protected: (val & vk::QUEUE_PROTECTED_BIT) != 0,
+            video_decode: (val & vk::QUEUE_VIDEO_DECODE_BIT_KHR) != 0,
+            video_encode: (val & vk::QUEUE_VIDEO_ENCODE_BIT_KHR) != 0,
+            optical_flow: (val & vk::QUEUE_OPTICAL_FLOW_BIT_NV) != 0,
+        }
+    }
+
+    /// Returns true if the queues support video decode or encode operations.
+    #[inline]
+    pub fn is_video(&self) -> bool {
+        self.video_decode || self.video_encode
+    }
+
+    /// Returns true if the queues support transfer operations, but neither graphics, compute,
+    /// video nor optical flow operations.
+    ///
+    /// Some implementations advertise transfer operations on their video queues. This function
+    /// lets you avoid picking such a queue when looking for a queue dedicated to transfers.
+    #[inline]
+    pub fn is_dedicated_transfer(&self) -> bool {
+        self.transfer && !self.graphics && !self.compute && !self.is_video() && !self.optical_flow
+    }
+
+    /// Returns true if all the flags of `other` are also in `self`.
+    #[inline]
+    pub fn is_superset_of(&self, other: &QueueFlags) -> bool {
+        (self.graphics || !other.graphics) && (self.compute || !other.compute) &&
+            (self.transfer || !other.transfer) &&
+            (self.sparse_binding || !other.sparse_binding) &&
+            (self.protected || !other.protected) &&
+            (self.video_decode || !other.video_decode) &&
+            (self.video_encode || !other.video_encode) &&
+            (self.optical_flow || !other.optical_flow)
+    }
+}
+
+/// Video codec operations supported by the queues of a queue family.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct VideoCodecOperations {
+    pub decode_h264: bool,
+    pub decode_h265: bool,
+    pub decode_av1: bool,
+    pub encode_h264: bool,
+    pub encode_h265: bool,
+    pub encode_av1: bool,
+}
+
+impl VideoCodecOperations {
+    /// Builds a `VideoCodecOperations` with all the operations set to `false`.
+    #[inline]
+    pub fn none() -> VideoCodecOperations {
+        VideoCodecOperations {
+            decode_h264: false,
+            decode_h265: false,
+            decode_av1: false,
+            encode_h264: false,
+            encode_h265: false,
+            encode_av1: false,
+        }
+    }
+
+    #[inline]
+    pub(crate) fn from_bits(val: vk::VideoCodecOperationFlagsKHR) -> VideoCodecOperations {
+        VideoCodecOperations {
+            decode_h264: (val & vk::VIDEO_CODEC_OPERATION_DECODE_H264_BIT_KHR) != 0,
+            decode_h265: (val & vk::VIDEO_CODEC_OPERATION_DECODE_H265_BIT_KHR) != 0,
+            decode_av1: (val & vk::VIDEO_CODEC_OPERATION_DECODE_AV1_BIT_KHR) != 0,
+            encode_h264: (val & vk::VIDEO_CODEC_OPERATION_ENCODE_H264_BIT_KHR) != 0,
+            encode_h265: (val & vk::VIDEO_CODEC_OPERATION_ENCODE_H265_BIT_KHR) != 0,
+            encode_av1: (val & vk::VIDEO_CODEC_OPERATION_ENCODE_AV1_BIT_KHR) != 0,
+        }
+    }
+}
+
+/// List of numbers of samples per pixel.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SampleCounts {
+    pub sample1: bool,
+    pub sample2: bool,
+    pub sample4: bool,
+    pub sample8: bool,
+    pub sample16: bool,
+    pub sample32: bool,
+    pub sample64: bool,
+}
+
+impl SampleCounts {
+    /// Builds a `SampleCounts` with all the counts set to `false`.
+    #[inline]
+    pub fn none() -> SampleCounts {
+        SampleCounts {
+            sample1: false,
+            sample2: false,
+            sample4: false,
+            sample8: false,
+            sample16: false,
+            sample32: false,
+            sample64: false,
+        }
+    }
+
+    /// Builds a `SampleCounts` with all the counts set to `true`.
+    #[inline]
+    pub fn all() -> SampleCounts {
+        SampleCounts {
+            sample1: true,
+            sample2: true,
+            sample4: true,
+            sample8: true,
+            sample16: true,
+            sample32: true,
+            sample64: true,
+        }
+    }
+
+    #[inline]
+    pub(crate) fn from_bits(val: vk::SampleCountFlags) -> SampleCounts {
+        SampleCounts {
+            sample1: (val & vk::SAMPLE_COUNT_1_BIT) != 0,
+            sample2: (val & vk::SAMPLE_COUNT_2_BIT) != 0,
+            sample4: (val & vk::SAMPLE_COUNT_4_BIT) != 0,
+            sample8: (val & vk::SAMPLE_COUNT_8_BIT) != 0,
+            sample16: (val & vk::SAMPLE_COUNT_16_BIT) != 0,
+            sample32: (val & vk::SAMPLE_COUNT_32_BIT) != 0,
+            sample64: (val & vk::SAMPLE_COUNT_64_BIT) != 0,
+        }
+    }
+
+    /// Returns true if `samples` is part of the list. Always returns false if `samples` is not
+    /// a power of two between 1 and 64.
+    #[inline]
+    pub fn supports(&self, samples: u32) -> bool {
+        match samples {
+            1 => self.sample1,
+            2 => self.sample2,
+            4 => self.sample4,
+            8 => self.sample8,
+            16 => self.sample16,
+            32 => self.sample32,
+            64 => self.sample64,
+            _ => false,
+        }
+    }
+
+    /// Returns the highest count of the list, or `None` if the list is empty.
+    #[inline]
+    pub fn max_count(&self) -> Option<u32> {
+        [64, 32, 16, 8, 4, 2, 1].iter().cloned().find(|&samples| self.supports(samples))
+    }
+
+    /// Returns the counts that are both in `self` and in `other`.
+    #[inline]
+    pub fn intersection(&self, other: &SampleCounts) -> SampleCounts {
+        SampleCounts {
+            sample1: self.sample1 && other.sample1,
+            sample2: self.sample2 && other.sample2,
+            sample4: self.sample4 && other.sample4,
+            sample8: self.sample8 && other.sample8,
+            sample16: self.sample16 && other.sample16,
+            sample32: self.sample32 && other.sample32,
+            sample64: self.sample64 && other.sample64,
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use format::Format;
+    use image::ImageUsage;
     use instance;
+    use vk;
 
     #[test]
     fn create_instance() {
         let _ = instance!();
     }
 
+    #[test]
+    fn subgroup_features_from_bits() {
+        let features = instance::SubgroupFeatures::from_bits(vk::SUBGROUP_FEATURE_BASIC_BIT |
+                                                                 vk::SUBGROUP_FEATURE_BALLOT_BIT |
+                                                                 vk::SUBGROUP_FEATURE_QUAD_BIT);
+        assert_eq!(features,
+                   instance::SubgroupFeatures {
+                       basic: true,
+                       ballot: true,
+                       quad: true,
+                       ..instance::SubgroupFeatures::none()
+                   });
+
+        let required = instance::SubgroupFeatures {
+            basic: true,
+            ballot: true,
+            ..instance::SubgroupFeatures::none()
+        };
+        assert!(features.is_superset_of(&required));
+        assert!(!required.is_superset_of(&features));
+    }
+
+    #[test]
+    fn cooperative_matrix_properties_from_vk() {
+        let mut raw = vk::CooperativeMatrixPropertiesKHR {
+            sType: vk::STRUCTURE_TYPE_COOPERATIVE_MATRIX_PROPERTIES_KHR,
+            pNext: ::std::ptr::null_mut(),
+            MSize: 16,
+            NSize: 16,
+            KSize: 16,
+            AType: vk::COMPONENT_TYPE_FLOAT16_KHR,
+            BType: vk::COMPONENT_TYPE_FLOAT16_KHR,
+            CType: vk::COMPONENT_TYPE_FLOAT32_KHR,
+            ResultType: vk::COMPONENT_TYPE_FLOAT32_KHR,
+            saturatingAccumulation: vk::FALSE,
+            scope: vk::SCOPE_SUBGROUP_KHR,
+        };
+
+        let properties = instance::CooperativeMatrixProperties::from_vk(&raw).unwrap();
+        assert_eq!(properties.k_size, 16);
+        assert_eq!(properties.a_type, instance::ComponentType::Float16);
+        assert_eq!(properties.result_type, instance::ComponentType::Float32);
+        assert_eq!(properties.scope, instance::Scope::Subgroup);
+        assert!(!properties.saturating_accumulation);
+
+        // Types introduced by other extensions are skipped.
+        raw.AType = 1000141000;
+        assert!(instance::CooperativeMatrixProperties::from_vk(&raw).is_none());
+    }
+
+    #[test]
+    fn queue_flags_dedicated_transfer() {
+        let transfer = instance::QueueFlags::from_bits(vk::QUEUE_TRANSFER_BIT |
+                                                           vk::QUEUE_SPARSE_BINDING_BIT);
+        assert!(transfer.is_dedicated_transfer());
+        assert!(!transfer.is_video());
+
+        // Some drivers advertise transfers on their video decode families.
+        let video = instance::QueueFlags::from_bits(vk::QUEUE_TRANSFER_BIT |
+                                                        vk::QUEUE_VIDEO_DECODE_BIT_KHR);
+        assert!(video.video_decode && video.is_video());
+        assert!(!video.is_dedicated_transfer());
+
+        let general = instance::QueueFlags::from_bits(vk::QUEUE_GRAPHICS_BIT |
+                                                          vk::QUEUE_COMPUTE_BIT |
+                                                          vk::QUEUE_TRANSFER_BIT);
+        assert!(!general.is_dedicated_transfer());
+        assert!(general.is_superset_of(&instance::QueueFlags {
+                                           transfer: true,
+                                           ..instance::QueueFlags::none()
+                                       }));
+        assert!(!general.is_superset_of(&video));
+    }
+
+    #[test]
+    fn sample_counts_max() {
+        assert_eq!(instance::SampleCounts::none().max_count(), None);
+        assert_eq!(instance::SampleCounts::all().max_count(), Some(64));
+
+        let counts = instance::SampleCounts {
+            sample1: true,
+            sample4: true,
+            ..instance::SampleCounts::none()
+        };
+        assert_eq!(counts.max_count(), Some(4));
+    }
+
+    #[test]
+    fn max_sample_count() {
+        let instance = instance!();
+
+        let phys = match instance::PhysicalDevice::enumerate(&instance).next() {
+            Some(p) => p,
+            None => return,
+        };
+
+        // Four samples are guaranteed to be supported for mandatory color attachment formats.
+        let usage = ImageUsage {
+            color_attachment: true,
+            ..ImageUsage::none()
+        };
+        let samples = phys.max_sample_count(Format::R8G8B8A8Unorm, usage).unwrap();
+        assert!(samples >= 4);
+        assert!(phys.limits().framebuffer_color_sample_counts().supports(samples));
+    }
+
     #[test]
     fn queue_family_by_id() {
         let instance = instance!();
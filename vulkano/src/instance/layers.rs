@@ -51,13 +51,15 @@ pub fn layers_list() -> Result<LayersIterator, LayersListError> {
         let mut num = 0;
         check_errors({
                          entry_points.EnumerateInstanceLayerProperties(&mut num, ptr::null_mut())
-                     })?;
+                     },
+                     "vkEnumerateInstanceLayerProperties")?;
 
         let mut layers: Vec<vk::LayerProperties> = Vec::with_capacity(num as usize);
         check_errors({
                          entry_points
                              .EnumerateInstanceLayerProperties(&mut num, layers.as_mut_ptr())
-                     })?;
+                     },
+                     "vkEnumerateInstanceLayerProperties")?;
         layers.set_len(num as usize);
 
         Ok(LayersIterator { iter: layers.into_iter() })
@@ -110,6 +110,8 @@ pub use self::extensions::InstanceExtensions;
 pub use self::extensions::RawDeviceExtensions;
 pub use self::extensions::RawInstanceExtensions;
 pub use self::instance::ApplicationInfo;
+pub use self::instance::ComponentType;
+pub use self::instance::CooperativeMatrixProperties;
 pub use self::instance::Instance;
 pub use self::instance::InstanceCreationError;
 pub use self::instance::Limits;
@@ -122,6 +124,12 @@ pub use self::instance::PhysicalDeviceType;
 pub use self::instance::PhysicalDevicesIter;
 pub use self::instance::QueueFamiliesIter;
 pub use self::instance::QueueFamily;
+pub use self::instance::QueueFlags;
+pub use self::instance::SampleCounts;
+pub use self::instance::Scope;
+pub use self::instance::SubgroupFeatures;
+pub use self::instance::SubgroupProperties;
+pub use self::instance::VideoCodecOperations;
 pub use self::layers::LayerProperties;
 pub use self::layers::LayersIterator;
 pub use self::layers::LayersListError;
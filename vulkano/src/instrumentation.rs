@@ -0,0 +1,364 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Hooks for external profilers.
+//!
+//! In order to see what vulkano is doing in a profiler such as Tracy, you can implement the
+//! `SubmissionObserver` trait and register it on a device with
+//! [`Device::set_submission_observer`](../device/struct.Device.html#method.set_submission_observer).
+//! The observer is then notified when command buffers are recorded, when work is submitted to a
+//! queue, when a fence is detected as signaled, and when swapchain images are acquired and
+//! presented.
+//!
+//! All the methods of `SubmissionObserver` have a default implementation that does nothing, so
+//! you only need to implement the ones you're interested in.
+//!
+//! # Overhead
+//!
+//! When no observer is registered, the only cost of these hooks is the load of an atomic boolean
+//! at each event and each time a future is flushed. The `observer-overhead` example measures it.
+//!
+//! Vulkano doesn't hold any lock while calling the observer, but the methods are called from the
+//! thread that triggers the event. They should therefore return quickly. It is however possible
+//! to call vulkano functions from within them.
+//!
+//! # Example
+//!
+//! ```
+//! use std::sync::Arc;
+//! use vulkano::instrumentation::CommandBufferBuildStats;
+//! use vulkano::instrumentation::SubmissionObserver;
+//!
+//! struct Logger;
+//!
+//! impl SubmissionObserver for Logger {
+//!     fn command_buffer_build_end(&self, stats: &CommandBufferBuildStats) {
+//!         println!("recorded {} commands in {:?}", stats.num_commands, stats.duration);
+//!     }
+//! }
+//!
+//! # let device: Arc<vulkano::device::Device> = return;
+//! device.set_submission_observer(Some(Arc::new(Logger)));
+//! ```
+
+use std::cell::RefCell;
+use std::marker::PhantomData;
+use std::mem;
+use std::time::Duration;
+
+use device::Device;
+
+/// Receives notifications about the work that vulkano sends to a device.
+///
+/// See [the module-level documentation](index.html) for more information.
+pub trait SubmissionObserver: Send + Sync {
+    /// Called when an `AutoCommandBufferBuilder` starts recording a command buffer for the given
+    /// queue family.
+    #[inline]
+    fn command_buffer_build_start(&self, queue_family: u32) {
+    }
+
+    /// Called when `build()` is called on an `AutoCommandBufferBuilder`, whether the build
+    /// succeeds or not.
+    ///
+    /// This is called on the same thread as the corresponding `command_buffer_build_start`. It is
+    /// not called if the builder is destroyed without `build()` being called.
+    #[inline]
+    fn command_buffer_build_end(&self, stats: &CommandBufferBuildStats) {
+    }
+
+    /// Called after work has been submitted to a queue.
+    #[inline]
+    fn queue_submit(&self, submit: &QueueSubmitInfo) {
+    }
+
+    /// Called when vulkano detects, while cleaning up the resources of a future, that a fence
+    /// has been signaled.
+    ///
+    /// `fence` is the Vulkan handle of the fence.
+    #[inline]
+    fn fence_signaled(&self, fence: u64) {
+    }
+
+    /// Called after an image of a swapchain has been acquired.
+    ///
+    /// `swapchain` is the Vulkan handle of the swapchain.
+    #[inline]
+    fn swapchain_acquire(&self, swapchain: u64, image_index: usize) {
+    }
+
+    /// Called after swapchain images have been presented.
+    ///
+    /// `swapchains` contains the Vulkan handles of the swapchains, and `image_indices` the index
+    /// of the image presented for each of them.
+    #[inline]
+    fn swapchain_present(&self, swapchains: &[u64], image_indices: &[u32]) {
+    }
+}
+
+/// Statistics about the recording of a command buffer.
+#[derive(Debug, Copy, Clone)]
+pub struct CommandBufferBuildStats {
+    /// Queue family the command buffer was recorded for.
+    pub queue_family: u32,
+    /// Number of commands that have been recorded, not counting the pipeline barriers that
+    /// vulkano inserted automatically.
+    pub num_commands: usize,
+    /// Number of times a descriptor set passed to a command didn't need to be validated, because
+    /// the same set had already been validated against a compatible pipeline layout.
+    pub descriptor_sets_validation_hits: usize,
+    /// Number of times a descriptor set passed to a command had to be validated.
+    pub descriptor_sets_validation_misses: usize,
+    /// Time between the creation of the builder and the end of the build.
+    pub duration: Duration,
+    /// True if the command buffer has been successfully built.
+    pub succeeded: bool,
+}
+
+/// Describes a submission of command buffers to a queue.
+#[derive(Debug, Copy, Clone)]
+pub struct QueueSubmitInfo<'a> {
+    /// Family of the queue.
+    pub queue_family: u32,
+    /// Index of the queue within its family.
+    pub queue_index: u32,
+    /// Vulkan handles of the command buffers that have been submitted.
+    pub command_buffers: &'a [usize],
+    /// Number of semaphores that the submission waits upon.
+    pub num_wait_semaphores: usize,
+    /// Number of semaphores that the submission signals.
+    pub num_signal_semaphores: usize,
+    /// Vulkan handle of the fence that the submission signals, if any.
+    pub fence: Option<u64>,
+}
+
+thread_local! {
+    // Number of `DeferNotifications` guards alive on this thread, and the notifications that have
+    // been delayed until the last of them is dropped.
+    static DEFERRED: RefCell<(usize, Vec<Box<Fn()>>)> = RefCell::new((0, Vec::new()));
+}
+
+/// Delays the notifications sent on this thread to the observers until the guard is dropped.
+///
+/// Must be created before locking anything that is held while submitting, so that it is dropped
+/// after the lock is released and the observer is never called while vulkano holds a lock.
+pub(crate) struct DeferNotifications {
+    // Prevents the guard from being sent to another thread.
+    marker: PhantomData<*const ()>,
+}
+
+impl DeferNotifications {
+    /// Starts delaying the notifications, or returns `None` if `device` doesn't have any
+    /// observer.
+    #[inline]
+    pub(crate) fn new(device: &Device) -> Option<DeferNotifications> {
+        if !device.has_submission_observer() {
+            return None;
+        }
+
+        DEFERRED.with(|deferred| deferred.borrow_mut().0 += 1);
+        Some(DeferNotifications { marker: PhantomData })
+    }
+}
+
+impl Drop for DeferNotifications {
+    fn drop(&mut self) {
+        let pending = DEFERRED.with(|deferred| {
+            let mut deferred = deferred.borrow_mut();
+            deferred.0 -= 1;
+            if deferred.0 == 0 {
+                mem::replace(&mut deferred.1, Vec::new())
+            } else {
+                Vec::new()
+            }
+        });
+
+        // The observer may call vulkano, so `DEFERRED` must not be borrowed at this point.
+        for notification in pending {
+            notification();
+        }
+    }
+}
+
+/// Calls `notification` now, or when the last `DeferNotifications` guard of the thread is
+/// dropped if there is one.
+pub(crate) fn notify<F>(notification: F)
+    where F: Fn() + 'static
+{
+    let notification = DEFERRED.with(|deferred| {
+        let mut deferred = deferred.borrow_mut();
+        if deferred.0 == 0 {
+            Some(notification)
+        } else {
+            deferred.1.push(Box::new(notification));
+            None
+        }
+    });
+
+    if let Some(notification) = notification {
+        notification();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use command_buffer::AutoCommandBufferBuilder;
+    use command_buffer::CommandBuffer;
+    use format::ClearValue;
+    use format::Format;
+    use framebuffer::Framebuffer;
+    use image::AttachmentImage;
+    use instrumentation::CommandBufferBuildStats;
+    use instrumentation::QueueSubmitInfo;
+    use instrumentation::SubmissionObserver;
+    use std::sync::Arc;
+    use std::sync::Mutex;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+    use sync::GpuFuture;
+
+    #[derive(Default)]
+    struct Counter {
+        build_starts: AtomicUsize,
+        build_ends: AtomicUsize,
+        submits: AtomicUsize,
+    }
+
+    impl SubmissionObserver for Counter {
+        fn command_buffer_build_start(&self, _: u32) {
+            self.build_starts.fetch_add(1, Ordering::SeqCst);
+        }
+
+        fn command_buffer_build_end(&self, stats: &CommandBufferBuildStats) {
+            assert_eq!(stats.num_commands, 0);
+            self.build_ends.fetch_add(1, Ordering::SeqCst);
+        }
+
+        fn queue_submit(&self, submit: &QueueSubmitInfo) {
+            assert_eq!(submit.command_buffers.len(), 1);
+            assert!(submit.fence.is_some());
+            self.submits.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn observer_notified() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let counter = Arc::new(Counter::default());
+        device.set_submission_observer(Some(counter.clone()));
+
+        let cb = AutoCommandBufferBuilder::new(device.clone(), queue.family())
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(counter.build_starts.load(Ordering::SeqCst), 1);
+        assert_eq!(counter.build_ends.load(Ordering::SeqCst), 1);
+
+        cb.execute(queue.clone())
+            .unwrap()
+            .then_signal_fence_and_flush()
+            .unwrap()
+            .wait(None)
+            .unwrap();
+        assert_eq!(counter.submits.load(Ordering::SeqCst), 1);
+
+        // No more notifications once the observer is removed.
+        device.set_submission_observer(None);
+        assert!(device.submission_observer().is_none());
+        let _ = AutoCommandBufferBuilder::new(device.clone(), queue.family())
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(counter.build_starts.load(Ordering::SeqCst), 1);
+    }
+
+    #[derive(Default)]
+    struct BuildResults {
+        results: Mutex<Vec<bool>>,
+    }
+
+    impl SubmissionObserver for BuildResults {
+        fn command_buffer_build_end(&self, stats: &CommandBufferBuildStats) {
+            self.results.lock().unwrap().push(stats.succeeded);
+        }
+    }
+
+    #[test]
+    fn build_end_on_error() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let render_pass = Arc::new(single_pass_renderpass!(device.clone(),
+            attachments: {
+                color: { load: Clear, store: Store, format: Format::R8G8B8A8Unorm, samples: 1, }
+            },
+            pass: { color: [color], depth_stencil: {} }
+        ).unwrap());
+        let image = AttachmentImage::new(device.clone(), [4, 4], Format::R8G8B8A8Unorm).unwrap();
+        let framebuffer = Arc::new(Framebuffer::start(render_pass)
+                                       .add(image)
+                                       .unwrap()
+                                       .build()
+                                       .unwrap());
+
+        let observer = Arc::new(BuildResults::default());
+        device.set_submission_observer(Some(observer.clone()));
+
+        // Building fails because the render pass is still in progress.
+        let result = AutoCommandBufferBuilder::new(device.clone(), queue.family())
+            .unwrap()
+            .begin_render_pass(framebuffer, false, vec![ClearValue::Float([0.0; 4])])
+            .unwrap()
+            .build();
+        assert!(result.is_err());
+        assert_eq!(*observer.results.lock().unwrap(), vec![false]);
+
+        device.set_submission_observer(None);
+    }
+
+    // Flushes `future` again from within the notification of its own submission, which would
+    // deadlock if the observer was called while the future is locked.
+    #[derive(Default)]
+    struct Reentrant {
+        future: Mutex<Option<Arc<GpuFuture + Send + Sync>>>,
+        submits: AtomicUsize,
+    }
+
+    impl SubmissionObserver for Reentrant {
+        fn queue_submit(&self, _: &QueueSubmitInfo) {
+            self.submits.fetch_add(1, Ordering::SeqCst);
+            let future = self.future.lock().unwrap().clone();
+            if let Some(future) = future {
+                future.flush().unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn no_lock_held() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let observer = Arc::new(Reentrant::default());
+        device.set_submission_observer(Some(observer.clone()));
+
+        let cb = AutoCommandBufferBuilder::new(device.clone(), queue.family())
+            .unwrap()
+            .build()
+            .unwrap();
+        let future = Arc::new(cb.execute(queue.clone()).unwrap().then_signal_fence());
+        *observer.future.lock().unwrap() = Some(future.clone() as Arc<GpuFuture + Send + Sync>);
+
+        future.flush().unwrap();
+        assert_eq!(observer.submits.load(Ordering::SeqCst), 1);
+
+        future.wait(None).unwrap();
+        *observer.future.lock().unwrap() = None;
+        device.set_submission_observer(None);
+    }
+}
@@ -72,25 +72,31 @@ extern crate vk_sys as vk;
 #[macro_use]
 mod tests;
 
+mod debug_name;
 mod features;
 mod version;
 
+// Declared first so that the render pass macros can be used in the tests of the other modules.
+#[macro_use]
+pub mod framebuffer;
+
 pub mod buffer;
 pub mod command_buffer;
 pub mod descriptor;
 pub mod device;
 pub mod format;
-#[macro_use]
-pub mod framebuffer;
 pub mod image;
 pub mod instance;
+pub mod instrumentation;
 pub mod memory;
 pub mod pipeline;
+pub mod private_data;
 pub mod query;
 pub mod sampler;
 pub mod swapchain;
 pub mod sync;
 
+use std::cell::RefCell;
 use std::error;
 use std::fmt;
 use std::ops::Deref;
@@ -162,6 +168,59 @@ impl From<Error> for OomError {
     }
 }
 
+thread_local! {
+    // Context of the last error returned by a Vulkan function on this thread.
+    static LAST_ERROR_CONTEXT: RefCell<Option<ErrorContext>> = RefCell::new(None);
+}
+
+/// Describes where an error returned by a Vulkan function comes from.
+///
+/// The error types of vulkano don't carry their context. Instead the context of the last error
+/// returned by a Vulkan function is kept for each thread, and can be retrieved with
+/// `ErrorContext::last()` right after a vulkano function has returned an error.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ErrorContext {
+    function: &'static str,
+    object_name: Option<Arc<str>>,
+}
+
+impl ErrorContext {
+    /// Returns the context of the last error returned by a Vulkan function on the current thread.
+    ///
+    /// Errors that are produced by vulkano itself, for example when a size computation
+    /// overflows, don't replace the context. Only call this right after getting an error that
+    /// comes from Vulkan.
+    #[inline]
+    pub fn last() -> Option<ErrorContext> {
+        LAST_ERROR_CONTEXT.with(|context| context.borrow().clone())
+    }
+
+    /// Returns the name of the Vulkan function that returned the error, for example
+    /// `vkAllocateMemory`.
+    #[inline]
+    pub fn function(&self) -> &'static str {
+        self.function
+    }
+
+    /// Returns the debug name of the object the function was called on, if the object was given
+    /// one.
+    #[inline]
+    pub fn object_name(&self) -> Option<&str> {
+        self.object_name.as_ref().map(|name| &**name)
+    }
+}
+
+impl fmt::Display for ErrorContext {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "returned by `{}`", self.function)?;
+        if let Some(ref name) = self.object_name {
+            write!(fmt, " on `{}`", name)?;
+        }
+        Ok(())
+    }
+}
+
 /// All possible success codes returned by any Vulkan function.
 #[derive(Debug, Copy, Clone)]
 #[repr(u32)]
@@ -179,7 +238,7 @@ enum Success {
 ///
 /// This type is not public. Instead all public error types should implement `From<Error>` and
 /// panic for error code that arent supposed to happen.
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[repr(u32)]
 #[doc(hidden)] // TODO: this is necessary because of the stupid visibility rules in rustc
 pub enum Error {
@@ -202,35 +261,67 @@ pub enum Error {
     OutOfPoolMemory = vk::ERROR_OUT_OF_POOL_MEMORY_KHR,
 }
 
+impl Error {
+    /// Attaches the debug name of the object the function was called on to the context of the
+    /// error.
+    #[inline]
+    fn with_object_name(self, name: Option<Arc<str>>) -> Error {
+        LAST_ERROR_CONTEXT.with(|context| {
+            if let Some(ref mut context) = *context.borrow_mut() {
+                context.object_name = name;
+            }
+        });
+        self
+    }
+}
+
 /// Checks whether the result returned correctly.
-fn check_errors(result: vk::Result) -> Result<Success, Error> {
-    match result {
-        vk::SUCCESS => Ok(Success::Success),
-        vk::NOT_READY => Ok(Success::NotReady),
-        vk::TIMEOUT => Ok(Success::Timeout),
-        vk::EVENT_SET => Ok(Success::EventSet),
-        vk::EVENT_RESET => Ok(Success::EventReset),
-        vk::INCOMPLETE => Ok(Success::Incomplete),
-        vk::ERROR_OUT_OF_HOST_MEMORY => Err(Error::OutOfHostMemory),
-        vk::ERROR_OUT_OF_DEVICE_MEMORY => Err(Error::OutOfDeviceMemory),
-        vk::ERROR_INITIALIZATION_FAILED => Err(Error::InitializationFailed),
-        vk::ERROR_DEVICE_LOST => Err(Error::DeviceLost),
-        vk::ERROR_MEMORY_MAP_FAILED => Err(Error::MemoryMapFailed),
-        vk::ERROR_LAYER_NOT_PRESENT => Err(Error::LayerNotPresent),
-        vk::ERROR_EXTENSION_NOT_PRESENT => Err(Error::ExtensionNotPresent),
-        vk::ERROR_FEATURE_NOT_PRESENT => Err(Error::FeatureNotPresent),
-        vk::ERROR_INCOMPATIBLE_DRIVER => Err(Error::IncompatibleDriver),
-        vk::ERROR_TOO_MANY_OBJECTS => Err(Error::TooManyObjects),
-        vk::ERROR_FORMAT_NOT_SUPPORTED => Err(Error::FormatNotSupported),
-        vk::ERROR_SURFACE_LOST_KHR => Err(Error::SurfaceLost),
-        vk::ERROR_NATIVE_WINDOW_IN_USE_KHR => Err(Error::NativeWindowInUse),
-        vk::SUBOPTIMAL_KHR => Ok(Success::Suboptimal),
-        vk::ERROR_OUT_OF_DATE_KHR => Err(Error::OutOfDate),
-        vk::ERROR_INCOMPATIBLE_DISPLAY_KHR => Err(Error::IncompatibleDisplay),
-        vk::ERROR_VALIDATION_FAILED_EXT => Err(Error::ValidationFailed),
-        vk::ERROR_OUT_OF_POOL_MEMORY_KHR => Err(Error::OutOfPoolMemory),
+///
+/// `function` is the name of the Vulkan function that returned `result`. If there is an error,
+/// it becomes the context returned by `ErrorContext::last()`.
+fn check_errors(result: vk::Result, function: &'static str) -> Result<Success, Error> {
+    let err = match result {
+        vk::SUCCESS => return Ok(Success::Success),
+        vk::NOT_READY => return Ok(Success::NotReady),
+        vk::TIMEOUT => return Ok(Success::Timeout),
+        vk::EVENT_SET => return Ok(Success::EventSet),
+        vk::EVENT_RESET => return Ok(Success::EventReset),
+        vk::INCOMPLETE => return Ok(Success::Incomplete),
+        vk::ERROR_OUT_OF_HOST_MEMORY => Error::OutOfHostMemory,
+        vk::ERROR_OUT_OF_DEVICE_MEMORY => Error::OutOfDeviceMemory,
+        vk::ERROR_INITIALIZATION_FAILED => Error::InitializationFailed,
+        vk::ERROR_DEVICE_LOST => Error::DeviceLost,
+        vk::ERROR_MEMORY_MAP_FAILED => Error::MemoryMapFailed,
+        vk::ERROR_LAYER_NOT_PRESENT => Error::LayerNotPresent,
+        vk::ERROR_EXTENSION_NOT_PRESENT => Error::ExtensionNotPresent,
+        vk::ERROR_FEATURE_NOT_PRESENT => Error::FeatureNotPresent,
+        vk::ERROR_INCOMPATIBLE_DRIVER => Error::IncompatibleDriver,
+        vk::ERROR_TOO_MANY_OBJECTS => Error::TooManyObjects,
+        vk::ERROR_FORMAT_NOT_SUPPORTED => Error::FormatNotSupported,
+        vk::ERROR_SURFACE_LOST_KHR => Error::SurfaceLost,
+        vk::ERROR_NATIVE_WINDOW_IN_USE_KHR => Error::NativeWindowInUse,
+        vk::SUBOPTIMAL_KHR => return Ok(Success::Suboptimal),
+        vk::ERROR_OUT_OF_DATE_KHR => Error::OutOfDate,
+        vk::ERROR_INCOMPATIBLE_DISPLAY_KHR => Error::IncompatibleDisplay,
+        vk::ERROR_VALIDATION_FAILED_EXT => Error::ValidationFailed,
+        vk::ERROR_OUT_OF_POOL_MEMORY_KHR => Error::OutOfPoolMemory,
         vk::ERROR_INVALID_SHADER_NV => panic!("Vulkan function returned \
                                                VK_ERROR_INVALID_SHADER_NV"),
         c => unreachable!("Unexpected error code returned by Vulkan: {}", c),
-    }
+    };
+
+    record_error_context(function);
+    Err(err)
+}
+
+/// Sets the context returned by `ErrorContext::last()` after `function` has returned an error.
+///
+/// Only needs to be called directly for result codes that aren't checked with `check_errors`.
+fn record_error_context(function: &'static str) {
+    LAST_ERROR_CONTEXT.with(|context| {
+        *context.borrow_mut() = Some(ErrorContext {
+                                         function: function,
+                                         object_name: None,
+                                     });
+    });
 }
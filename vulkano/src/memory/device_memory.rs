@@ -7,6 +7,7 @@
 // notice may not be copied, modified, or distributed except
 // according to those terms.
 
+use std::cmp;
 use std::fmt;
 use std::mem;
 use std::ops::Deref;
@@ -19,6 +20,7 @@ use std::sync::Arc;
 use OomError;
 use VulkanObject;
 use check_errors;
+use debug_name::DebugName;
 use device::Device;
 use device::DeviceOwned;
 use instance::MemoryType;
@@ -45,6 +47,8 @@ pub struct DeviceMemory {
     device: Arc<Device>,
     size: usize,
     memory_type_index: u32,
+    // Name given with `set_debug_name`.
+    name: DebugName,
 }
 
 impl DeviceMemory {
@@ -87,7 +91,8 @@ impl DeviceMemory {
             check_errors(vk.AllocateMemory(device.internal_object(),
                                            &infos,
                                            ptr::null(),
-                                           &mut output))?;
+                                           &mut output),
+                         "vkAllocateMemory")?;
             output
         };
 
@@ -96,6 +101,7 @@ impl DeviceMemory {
                device: device,
                size: size,
                memory_type_index: memory_type.id(),
+               name: DebugName::new(),
            })
     }
 
@@ -122,7 +128,8 @@ impl DeviceMemory {
                                       0,
                                       mem.size as vk::DeviceSize,
                                       0, /* reserved flags */
-                                      &mut output))?;
+                                      &mut output),
+                         "vkMapMemory")?;
             output
         };
 
@@ -147,6 +154,27 @@ impl DeviceMemory {
     pub fn size(&self) -> usize {
         self.size
     }
+
+    /// Gives a name to the memory.
+    ///
+    /// The name is attached to the errors returned when flushing or invalidating the memory. If
+    /// the `ext_debug_utils` extension is enabled on the instance, it is also passed to the
+    /// implementation and shows up in the messages of the validation layers.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `name` contains a nul character.
+    ///
+    #[inline]
+    pub fn set_debug_name(&self, name: &str) -> Result<(), OomError> {
+        self.name.set(&self.device, vk::OBJECT_TYPE_DEVICE_MEMORY, self.memory, name)
+    }
+
+    /// Returns the name given with `set_debug_name`, if any.
+    #[inline]
+    pub fn debug_name(&self) -> Option<Arc<str>> {
+        self.name.get()
+    }
 }
 
 unsafe impl DeviceOwned for DeviceMemory {
@@ -257,28 +285,161 @@ impl MappedDeviceMemory {
     pub unsafe fn read_write<T: ?Sized>(&self, range: Range<usize>) -> CpuAccess<T>
         where T: Content
     {
+        self.access(range, true, true)
+    }
+
+    /// Gives access to the content of the memory in order to read it.
+    ///
+    /// Same as `read_write`, except that `vkFlushMappedMemoryRanges` isn't called. Any
+    /// modification made through the returned object may therefore not be visible to the device.
+    ///
+    /// # Safety
+    ///
+    /// Same as `read_write`.
+    ///
+    #[inline]
+    pub unsafe fn read<T: ?Sized>(&self, range: Range<usize>) -> CpuAccess<T>
+        where T: Content
+    {
+        self.access(range, true, false)
+    }
+
+    /// Gives access to the content of the memory in order to overwrite it.
+    ///
+    /// Same as `read_write`, except that `vkInvalidateMappedMemoryRanges` isn't called. If the
+    /// memory isn't host-coherent, reading through the returned object may therefore return
+    /// outdated values instead of what the device wrote.
+    ///
+    /// # Safety
+    ///
+    /// Same as `read_write`.
+    ///
+    #[inline]
+    pub unsafe fn write_only<T: ?Sized>(&self, range: Range<usize>) -> CpuAccess<T>
+        where T: Content
+    {
+        self.access(range, false, true)
+    }
+
+    /// Makes the writes that the host made to the given range of the memory visible to the
+    /// device.
+    ///
+    /// Does nothing if the memory is host-coherent. Otherwise the range is extended so that it is
+    /// aligned to the `non_coherent_atom_size` limit of the physical device (see
+    /// `atom_aligned_range`), then `vkFlushMappedMemoryRanges` is called. The `CpuAccess`
+    /// objects returned by `read_write` and `write_only` already do this when they are
+    /// destroyed.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the range is out of the memory.
+    ///
+    pub fn flush_range(&self, range: Range<usize>) -> Result<(), OomError> {
+        if self.coherent {
+            return Ok(());
+        }
+
+        let vk = self.memory.device().pointers();
+        let range = self.mapped_memory_range(range);
+
+        unsafe {
+            check_errors(vk.FlushMappedMemoryRanges(self.memory.device().internal_object(),
+                                                    1,
+                                                    &range),
+                         "vkFlushMappedMemoryRanges")
+                .map_err(|err| err.with_object_name(self.memory.debug_name()))?;
+        }
+
+        Ok(())
+    }
+
+    /// Makes the writes that the device made to the given range of the memory visible to the
+    /// host.
+    ///
+    /// Does nothing if the memory is host-coherent. Otherwise the range is extended in the same
+    /// way as `flush_range`, then `vkInvalidateMappedMemoryRanges` is called. The `CpuAccess`
+    /// objects returned by `read_write` and `read` already do this when they are created.
+    ///
+    /// Note that any write that the host made to the extended range and that hasn't been flushed
+    /// yet is discarded.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the range is out of the memory.
+    ///
+    pub fn invalidate_range(&self, range: Range<usize>) -> Result<(), OomError> {
+        if self.coherent {
+            return Ok(());
+        }
+
         let vk = self.memory.device().pointers();
+        let range = self.mapped_memory_range(range);
+
+        unsafe {
+            check_errors(vk.InvalidateMappedMemoryRanges(self.memory.device().internal_object(),
+                                                         1,
+                                                         &range),
+                         "vkInvalidateMappedMemoryRanges")
+                .map_err(|err| err.with_object_name(self.memory.debug_name()))?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the range of the memory that is actually flushed or invalidated by `flush_range`
+    /// and `invalidate_range` when they are passed `range`.
+    ///
+    /// The returned range contains `range`. Its start is a multiple of the
+    /// `non_coherent_atom_size` limit of the physical device, and its end is either a multiple
+    /// of this limit or the end of the memory.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the range is out of the memory.
+    ///
+    pub fn atom_aligned_range(&self, range: Range<usize>) -> Range<usize> {
+        assert!(range.start <= range.end);
+        assert!(range.end <= self.memory.size());
+
+        let atom_size = self.memory
+            .device()
+            .physical_device()
+            .limits()
+            .non_coherent_atom_size();
+        align_to_atom_size(range, atom_size, self.memory.size())
+    }
+
+    // Builds the structure passed to `vkFlushMappedMemoryRanges` and
+    // `vkInvalidateMappedMemoryRanges` for the given range.
+    fn mapped_memory_range(&self, range: Range<usize>) -> vk::MappedMemoryRange {
+        let range = self.atom_aligned_range(range);
+
+        vk::MappedMemoryRange {
+            sType: vk::STRUCTURE_TYPE_MAPPED_MEMORY_RANGE,
+            pNext: ptr::null(),
+            memory: self.memory.internal_object(),
+            offset: range.start as u64,
+            size: (range.end - range.start) as u64,
+        }
+    }
+
+    unsafe fn access<T: ?Sized>(&self, range: Range<usize>, invalidate: bool, flush: bool)
+                                -> CpuAccess<T>
+        where T: Content
+    {
         let pointer = T::ref_from_ptr((self.pointer as usize + range.start) as *mut _,
                                       range.end - range.start)
             .unwrap(); // TODO: error
 
-        if !self.coherent {
-            let range = vk::MappedMemoryRange {
-                sType: vk::STRUCTURE_TYPE_MAPPED_MEMORY_RANGE,
-                pNext: ptr::null(),
-                memory: self.memory.internal_object(),
-                offset: range.start as u64,
-                size: (range.end - range.start) as u64,
-            };
-
-            // TODO: check result?
-            vk.InvalidateMappedMemoryRanges(self.memory.device().internal_object(), 1, &range);
+        if invalidate {
+            // TODO: report errors?
+            let _ = self.invalidate_range(range.clone());
         }
 
         CpuAccess {
             pointer: pointer,
             mem: self,
-            coherent: self.coherent,
+            flush: !self.coherent && flush,
             range: range,
         }
     }
@@ -324,7 +485,8 @@ impl fmt::Debug for MappedDeviceMemory {
 pub struct CpuAccess<'a, T: ?Sized + 'a> {
     pointer: *mut T,
     mem: &'a MappedDeviceMemory,
-    coherent: bool,
+    // True if the range must be flushed when the access ends.
+    flush: bool,
     range: Range<usize>,
 }
 
@@ -342,7 +504,7 @@ impl<'a, T: ?Sized + 'a> CpuAccess<'a, T> {
         CpuAccess {
             pointer: f(self.pointer),
             mem: self.mem,
-            coherent: self.coherent,
+            flush: self.flush,
             range: self.range.clone(), // TODO: ?
         }
     }
@@ -373,29 +535,30 @@ impl<'a, T: ?Sized + 'a> Drop for CpuAccess<'a, T> {
     #[inline]
     fn drop(&mut self) {
         // If the memory doesn't have the `coherent` flag, we need to flush the data.
-        if !self.coherent {
-            let vk = self.mem.as_ref().device().pointers();
-
-            let range = vk::MappedMemoryRange {
-                sType: vk::STRUCTURE_TYPE_MAPPED_MEMORY_RANGE,
-                pNext: ptr::null(),
-                memory: self.mem.as_ref().internal_object(),
-                offset: self.range.start as u64,
-                size: (self.range.end - self.range.start) as u64,
-            };
-
-            // TODO: check result?
-            unsafe {
-                vk.FlushMappedMemoryRanges(self.mem.as_ref().device().internal_object(), 1, &range);
-            }
+        if self.flush {
+            // TODO: report errors?
+            let _ = self.mem.flush_range(self.range.clone());
         }
     }
 }
 
+// Extends `range` so that its start and its end are multiples of `atom_size`, as required by
+// `vkFlushMappedMemoryRanges` and `vkInvalidateMappedMemoryRanges`. The end is clamped to
+// `memory_size`, which the specs allow even if it isn't a multiple of `atom_size`.
+fn align_to_atom_size(range: Range<usize>, atom_size: usize, memory_size: usize)
+                      -> Range<usize> {
+    debug_assert!(atom_size >= 1);
+    let start = range.start - range.start % atom_size;
+    let end = (range.end + atom_size - 1) / atom_size * atom_size;
+    start .. cmp::min(end, memory_size)
+}
+
 #[cfg(test)]
 mod tests {
+    use ErrorContext;
     use OomError;
     use memory::DeviceMemory;
+    use super::align_to_atom_size;
 
     #[test]
     fn create() {
@@ -424,11 +587,53 @@ mod tests {
             .unwrap();
 
         match DeviceMemory::alloc(device.clone(), mem_ty, 0xffffffffffffffff) {
-            Err(OomError::OutOfDeviceMemory) => (),
+            Err(OomError::OutOfDeviceMemory) => {
+                assert_eq!(ErrorContext::last().unwrap().function(), "vkAllocateMemory");
+            },
             _ => panic!(),
         }
     }
 
+    #[test]
+    fn atom_size_alignment() {
+        assert_eq!(align_to_atom_size(0 .. 256, 64, 1024), 0 .. 256);
+        assert_eq!(align_to_atom_size(70 .. 130, 64, 1024), 64 .. 192);
+        assert_eq!(align_to_atom_size(1000 .. 1010, 64, 1010), 960 .. 1010);
+        assert_eq!(align_to_atom_size(5 .. 9, 1, 16), 5 .. 9);
+    }
+
+    #[test]
+    fn flush_and_invalidate_aligned() {
+        let (device, _) = gfx_dev_and_queue!();
+        let mem_ty = device
+            .physical_device()
+            .memory_types()
+            .filter(|t| t.is_host_visible())
+            .next()
+            .unwrap();
+        let atom_size = device.physical_device().limits().non_coherent_atom_size();
+
+        let mem = DeviceMemory::alloc_and_map(device.clone(), mem_ty, 1024).unwrap();
+        let range = mem.atom_aligned_range(10 .. 20);
+        assert!(range.start <= 10 && range.end >= 20);
+        assert_eq!(range.start % atom_size, 0);
+        assert!(range.end % atom_size == 0 || range.end == 1024);
+
+        mem.flush_range(10 .. 20).unwrap();
+        mem.invalidate_range(10 .. 20).unwrap();
+    }
+
+    #[test]
+    fn debug_name() {
+        let (device, _) = gfx_dev_and_queue!();
+        let mem_ty = device.physical_device().memory_types().next().unwrap();
+
+        let mem = DeviceMemory::alloc(device.clone(), mem_ty, 256).unwrap();
+        assert_eq!(mem.debug_name(), None);
+        mem.set_debug_name("vertex pool").unwrap();
+        assert_eq!(mem.debug_name(), Some("vertex pool".into()));
+    }
+
     #[test]
     #[ignore] // TODO: test fails for now on Mesa+Intel
     fn oom_multi() {
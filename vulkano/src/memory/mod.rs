@@ -89,6 +89,7 @@ use std::mem;
 use std::os::raw::c_void;
 use std::slice;
 
+use instance::MemoryType;
 use vk;
 
 pub use self::device_memory::CpuAccess;
@@ -114,6 +115,25 @@ pub struct MemoryRequirements {
     pub memory_type_bits: u32,
 }
 
+impl MemoryRequirements {
+    /// Returns true if memory of the given type can be used.
+    #[inline]
+    pub fn supports_memory_type(&self, ty: MemoryType) -> bool {
+        self.memory_type_bits & (1 << ty.id()) != 0
+    }
+
+    /// Returns the ids of the memory types that can be used, in increasing order.
+    ///
+    /// Each id can be passed to `PhysicalDevice::memory_type_by_id`.
+    #[inline]
+    pub fn memory_type_ids(&self) -> MemoryTypeIds {
+        MemoryTypeIds {
+            bits: self.memory_type_bits,
+            next: 0,
+        }
+    }
+}
+
 #[doc(hidden)]
 impl From<vk::MemoryRequirements> for MemoryRequirements {
     #[inline]
@@ -126,6 +146,43 @@ impl From<vk::MemoryRequirements> for MemoryRequirements {
     }
 }
 
+/// Iterator to the ids of the memory types allowed by a `MemoryRequirements`.
+#[derive(Debug, Clone)]
+pub struct MemoryTypeIds {
+    bits: u32,
+    next: u32,
+}
+
+impl Iterator for MemoryTypeIds {
+    type Item = u32;
+
+    #[inline]
+    fn next(&mut self) -> Option<u32> {
+        while self.next < 32 {
+            let id = self.next;
+            self.next += 1;
+            if self.bits & (1 << id) != 0 {
+                return Some(id);
+            }
+        }
+
+        None
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = if self.next < 32 {
+            (self.bits >> self.next).count_ones() as usize
+        } else {
+            0
+        };
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for MemoryTypeIds {
+}
+
 /// Trait for types of data that can be mapped.
 // TODO: move to `buffer` module
 pub unsafe trait Content {
@@ -20,6 +20,9 @@ use memory::DeviceMemory;
 use memory::MappedDeviceMemory;
 
 /// Memory pool that operates on a given memory type.
+///
+/// Each chunk of memory is mapped when it is allocated and stays mapped until it is freed. All the
+/// allocations made in a chunk share this mapping.
 #[derive(Debug)]
 pub struct StdHostVisibleMemoryTypePool {
     device: Arc<Device>,
@@ -66,6 +69,17 @@ impl StdHostVisibleMemoryTypePool {
             al * (1 + (val - 1) / al)
         }
 
+        // Flushes and invalidates of non-coherent memory operate on whole atoms. Two allocations
+        // must never share an atom, otherwise invalidating the range of one of them could discard
+        // the writes made to the other. Both values are powers of two, so the larger one is a
+        // multiple of the other.
+        let (size, alignment) = if me.memory_type().is_host_coherent() {
+            (size, alignment)
+        } else {
+            let atom_size = me.device.physical_device().limits().non_coherent_atom_size();
+            (align(size, atom_size), cmp::max(alignment, atom_size))
+        };
+
         // Find a location.
         let mut occupied = me.occupied.lock().unwrap();
 
@@ -28,6 +28,7 @@ use std::ptr;
 use std::sync::Arc;
 
 use device::Device;
+use device::DeviceOwned;
 
 use OomError;
 use VulkanObject;
@@ -123,7 +124,8 @@ impl PipelineCache {
             check_errors(vk.CreatePipelineCache(device.internal_object(),
                                                 &infos,
                                                 ptr::null(),
-                                                &mut output))?;
+                                                &mut output),
+                         "vkCreatePipelineCache")?;
             output
         };
 
@@ -160,7 +162,8 @@ impl PipelineCache {
             check_errors(vk.MergePipelineCaches(self.device.internal_object(),
                                                 self.cache,
                                                 pipelines.len() as u32,
-                                                pipelines.as_ptr()))?;
+                                                pipelines.as_ptr()),
+                         "vkMergePipelineCaches")?;
 
             Ok(())
         }
@@ -202,13 +205,15 @@ impl PipelineCache {
             check_errors(vk.GetPipelineCacheData(self.device.internal_object(),
                                                  self.cache,
                                                  &mut num,
-                                                 ptr::null_mut()))?;
+                                                 ptr::null_mut()),
+                         "vkGetPipelineCacheData")?;
 
             let mut data: Vec<u8> = Vec::with_capacity(num as usize);
             check_errors(vk.GetPipelineCacheData(self.device.internal_object(),
                                                  self.cache,
                                                  &mut num,
-                                                 data.as_mut_ptr() as *mut _))?;
+                                                 data.as_mut_ptr() as *mut _),
+                         "vkGetPipelineCacheData")?;
             data.set_len(num as usize);
 
             Ok(data)
@@ -225,6 +230,13 @@ unsafe impl VulkanObject for PipelineCache {
     }
 }
 
+unsafe impl DeviceOwned for PipelineCache {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+}
+
 impl Drop for PipelineCache {
     #[inline]
     fn drop(&mut self) {
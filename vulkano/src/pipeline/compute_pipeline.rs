@@ -9,9 +9,11 @@
 
 use std::error;
 use std::fmt;
+use std::hash::Hasher;
 use std::marker::PhantomData;
 use std::mem;
 use std::ptr;
+use std::slice;
 use std::sync::Arc;
 
 use descriptor::descriptor::DescriptorDesc;
@@ -25,8 +27,15 @@ use descriptor::pipeline_layout::PipelineLayoutDescPcRange;
 use descriptor::pipeline_layout::PipelineLayoutNotSupersetError;
 use descriptor::pipeline_layout::PipelineLayoutSuperset;
 use descriptor::pipeline_layout::PipelineLayoutSys;
+use pipeline::cache::PipelineCache;
+use pipeline::feedback::FeedbackStorage;
+use pipeline::feedback::PipelineCreationFeedback;
+use pipeline::registry::StateHasher;
 use pipeline::shader::ComputeShaderEntryPoint;
 use pipeline::shader::SpecializationConstants;
+use pipeline::subgroup_size::SubgroupSizeControl;
+use pipeline::subgroup_size::SubgroupSizeControlError;
+use pipeline::subgroup_size::SubgroupSizeProperties;
 
 use Error;
 use OomError;
@@ -47,6 +56,8 @@ use vk;
 pub struct ComputePipeline<Pl> {
     inner: Inner,
     pipeline_layout: Pl,
+    local_size: Option<[u32; 3]>,
+    feedback: Option<PipelineCreationFeedback>,
 }
 
 struct Inner {
@@ -70,6 +81,105 @@ impl ComputePipeline<()> {
                                                             pipeline_layout)
         }
     }
+
+    /// Same as `new`, but passes a pipeline cache to the implementation, which can then skip
+    /// the compilation of the pipeline if it is already in the cache.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `cache` doesn't belong to `device`.
+    ///
+    pub fn with_cache<Css, Csl>(
+        device: Arc<Device>, shader: &ComputeShaderEntryPoint<Css, Csl>, specialization: &Css,
+        cache: &PipelineCache)
+        -> Result<ComputePipeline<PipelineLayout<Csl>>, ComputePipelineCreationError>
+        where Csl: PipelineLayoutDescNames + Clone,
+              Css: SpecializationConstants
+    {
+        assert_eq!(cache.device().internal_object(), device.internal_object());
+
+        unsafe {
+            let pipeline_layout = shader.layout().clone().build(device.clone())?;
+            ComputePipeline::create(device,
+                                    shader,
+                                    specialization,
+                                    pipeline_layout,
+                                    Some(cache),
+                                    None)
+        }
+    }
+
+    /// Same as `new`, but controls the size of the subgroups of the pipeline. Also passes a
+    /// pipeline cache to the implementation if `cache` is `Some`, like `with_cache`.
+    ///
+    /// The options are checked against the features and limits of the device and against the
+    /// workgroup size of the shader. See the documentation of the `subgroup_size` module. The
+    /// workgroup size must be known, otherwise `UnknownLocalSize` is returned.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `cache` doesn't belong to `device`.
+    ///
+    pub fn with_subgroup_size_control<Css, Csl>(
+        device: Arc<Device>, shader: &ComputeShaderEntryPoint<Css, Csl>, specialization: &Css,
+        subgroup_size: SubgroupSizeControl, cache: Option<&PipelineCache>)
+        -> Result<ComputePipeline<PipelineLayout<Csl>>, ComputePipelineCreationError>
+        where Csl: PipelineLayoutDescNames + Clone,
+              Css: SpecializationConstants
+    {
+        if let Some(cache) = cache {
+            assert_eq!(cache.device().internal_object(), device.internal_object());
+        }
+
+        if !subgroup_size.is_default() {
+            let local_size = match shader.local_size() {
+                Some(local_size) => local_size.specialize(specialization),
+                None => return Err(ComputePipelineCreationError::UnknownLocalSize),
+            };
+
+            let properties = SubgroupSizeProperties::for_device(&device)?;
+            subgroup_size.check_features(&device.subgroup_size_features())?;
+            subgroup_size.check(&properties, local_size)?;
+        }
+
+        unsafe {
+            let pipeline_layout = shader.layout().clone().build(device.clone())?;
+            ComputePipeline::create(device,
+                                    shader,
+                                    specialization,
+                                    pipeline_layout,
+                                    cache,
+                                    Some(&subgroup_size))
+        }
+    }
+
+    /// Returns a hash of the parameters that `new` would use to build a pipeline.
+    ///
+    /// This is the equivalent of `GraphicsPipelineBuilder::state_hash` for compute pipelines,
+    /// and can be used as the key of a `PipelineRegistry`. The specialization constants are
+    /// hashed by the value of each of the fields described by their `descriptors()`.
+    pub fn state_hash<Css, Csl>(shader: &ComputeShaderEntryPoint<Css, Csl>, specialization: &Css)
+                                -> u64
+        where Css: SpecializationConstants
+    {
+        let mut h = StateHasher::new();
+        h.write_u64(shader.module().spirv_hash());
+        h.write(shader.name().to_bytes_with_nul());
+
+        let data = unsafe {
+            slice::from_raw_parts(specialization as *const Css as *const u8,
+                                  mem::size_of_val(specialization))
+        };
+        let descriptors = <Css as SpecializationConstants>::descriptors();
+        h.write_usize(descriptors.len());
+        for entry in descriptors {
+            let offset = entry.offset as usize;
+            h.write_u32(entry.constant_id);
+            h.write(&data[offset .. offset + entry.size]);
+        }
+
+        h.finish()
+    }
 }
 
 impl<Pl> ComputePipeline<Pl> {
@@ -104,8 +214,29 @@ impl<Pl> ComputePipeline<Pl> {
               Css: SpecializationConstants,
               Pl: PipelineLayoutAbstract
     {
+        ComputePipeline::create(device, shader, specialization, pipeline_layout, None, None)
+    }
+
+    unsafe fn create<Css, Csl>(
+        device: Arc<Device>, shader: &ComputeShaderEntryPoint<Css, Csl>, specialization: &Css,
+        pipeline_layout: Pl, cache: Option<&PipelineCache>,
+        subgroup_size: Option<&SubgroupSizeControl>)
+        -> Result<ComputePipeline<Pl>, ComputePipelineCreationError>
+        where Csl: PipelineLayoutDescNames + Clone,
+              Css: SpecializationConstants,
+              Pl: PipelineLayoutAbstract
+    {
+        let local_size = shader.local_size().map(|l| l.specialize(specialization));
+
+        if shader.module().uses_cooperative_matrix() {
+            check_cooperative_matrix(&device, local_size, subgroup_size)?;
+        }
+
         let vk = device.pointers();
 
+        let mut feedback = FeedbackStorage::new(&device, 1);
+        let feedback_infos = feedback.as_mut().map(|f| f.create_info());
+
         let pipeline = {
             let spec_descriptors = <Css as SpecializationConstants>::descriptors();
             let specialization = vk::SpecializationInfo {
@@ -115,10 +246,24 @@ impl<Pl> ComputePipeline<Pl> {
                 pData: specialization as *const Css as *const _,
             };
 
+            let required_subgroup_size = subgroup_size
+                .and_then(|s| s.required_size)
+                .map(|size| {
+                    vk::PipelineShaderStageRequiredSubgroupSizeCreateInfoEXT {
+                        sType:
+                            vk::STRUCTURE_TYPE_PIPELINE_SHADER_STAGE_REQUIRED_SUBGROUP_SIZE_CREATE_INFO_EXT,
+                        pNext: ptr::null_mut(),
+                        requiredSubgroupSize: size,
+                    }
+                });
+
             let stage = vk::PipelineShaderStageCreateInfo {
                 sType: vk::STRUCTURE_TYPE_PIPELINE_SHADER_STAGE_CREATE_INFO,
-                pNext: ptr::null(),
-                flags: 0,
+                pNext: match required_subgroup_size {
+                    Some(ref s) => s as *const _ as *const _,
+                    None => ptr::null(),
+                },
+                flags: subgroup_size.map(|s| s.stage_flags()).unwrap_or(0),
                 stage: vk::SHADER_STAGE_COMPUTE_BIT,
                 module: shader.module().internal_object(),
                 pName: shader.name().as_ptr(),
@@ -131,7 +276,10 @@ impl<Pl> ComputePipeline<Pl> {
 
             let infos = vk::ComputePipelineCreateInfo {
                 sType: vk::STRUCTURE_TYPE_COMPUTE_PIPELINE_CREATE_INFO,
-                pNext: ptr::null(),
+                pNext: feedback_infos
+                    .as_ref()
+                    .map(|f| f as *const _ as *const _)
+                    .unwrap_or(ptr::null()),
                 flags: 0,
                 stage: stage,
                 layout: PipelineLayoutAbstract::sys(&pipeline_layout).internal_object(),
@@ -141,11 +289,13 @@ impl<Pl> ComputePipeline<Pl> {
 
             let mut output = mem::uninitialized();
             check_errors(vk.CreateComputePipelines(device.internal_object(),
-                                                   0,
+                                                   cache.map(|c| c.internal_object())
+                                                       .unwrap_or(0),
                                                    1,
                                                    &infos,
                                                    ptr::null(),
-                                                   &mut output))?;
+                                                   &mut output),
+                         "vkCreateComputePipelines")?;
             output
         };
 
@@ -155,10 +305,53 @@ impl<Pl> ComputePipeline<Pl> {
                    pipeline: pipeline,
                },
                pipeline_layout: pipeline_layout,
+               local_size: local_size,
+               feedback: feedback.map(|f| f.into_feedback()),
            })
     }
 }
 
+// Checks that a pipeline whose shader uses cooperative matrices can be created on `device`.
+//
+// Cooperative matrix operations are performed by all the invocations of a subgroup together,
+// therefore the subgroups of the pipeline must all be full. This can only be checked if the
+// workgroup size is known.
+fn check_cooperative_matrix(device: &Device, local_size: Option<[u32; 3]>,
+                            subgroup_size: Option<&SubgroupSizeControl>)
+                            -> Result<(), ComputePipelineCreationError> {
+    if !device.loaded_extensions().khr_cooperative_matrix {
+        return Err(ComputePipelineCreationError::CapabilityNotEnabled {
+                       capability: "CooperativeMatrixKHR",
+                   });
+    }
+
+    let subgroup_size = subgroup_size.cloned().unwrap_or_default();
+
+    let size = if let Some(size) = subgroup_size.required_size {
+        Some(size)
+    } else if subgroup_size.allow_varying_size {
+        // The size can't be known in advance. `SubgroupSizeControl::check` has already made sure
+        // that the workgroup size is compatible with full subgroups if they are required.
+        if !subgroup_size.require_full_subgroups {
+            return Err(ComputePipelineCreationError::CooperativeMatrixPartialSubgroups);
+        }
+        None
+    } else {
+        device
+            .physical_device()
+            .subgroup_properties()
+            .map(|p| p.subgroup_size)
+    };
+
+    if let (Some(size), Some(local_size)) = (size, local_size) {
+        if local_size[0] % size != 0 {
+            return Err(ComputePipelineCreationError::CooperativeMatrixPartialSubgroups);
+        }
+    }
+
+    Ok(())
+}
+
 impl<Pl> fmt::Debug for ComputePipeline<Pl> {
     #[inline]
     fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
@@ -178,12 +371,41 @@ impl<Pl> ComputePipeline<Pl> {
     pub fn layout(&self) -> &Pl {
         &self.pipeline_layout
     }
+
+    /// Returns the number of invocations in each dimension of a workgroup, or `None` if the
+    /// entry point that the pipeline was created with doesn't know its workgroup size.
+    ///
+    /// This takes into account the specialization constants that were passed when creating the
+    /// pipeline. The number of workgroups to dispatch in order to cover `width` elements in the
+    /// first dimension is `(width + local_size[0] - 1) / local_size[0]`.
+    /// `AutoCommandBufferBuilder::dispatch_elements` does this computation for you.
+    #[inline]
+    pub fn local_size(&self) -> Option<[u32; 3]> {
+        self.local_size
+    }
+
+    /// Returns what the implementation reported about the creation of this pipeline, or `None`
+    /// if the device supports neither Vulkan 1.3 nor `VK_EXT_pipeline_creation_feedback`.
+    ///
+    /// See the `feedback` module.
+    #[inline]
+    pub fn creation_feedback(&self) -> Option<&PipelineCreationFeedback> {
+        self.feedback.as_ref()
+    }
 }
 
 /// Trait implemented on all compute pipelines.
 pub unsafe trait ComputePipelineAbstract: PipelineLayoutAbstract {
     /// Returns an opaque object that represents the inside of the compute pipeline.
     fn inner(&self) -> ComputePipelineSys;
+
+    /// Returns the number of invocations in each dimension of a workgroup, or `None` if unknown.
+    ///
+    /// The default implementation returns `None`.
+    #[inline]
+    fn local_size(&self) -> Option<[u32; 3]> {
+        None
+    }
 }
 
 unsafe impl<Pl> ComputePipelineAbstract for ComputePipeline<Pl>
@@ -193,6 +415,11 @@ unsafe impl<Pl> ComputePipelineAbstract for ComputePipeline<Pl>
     fn inner(&self) -> ComputePipelineSys {
         ComputePipelineSys(self.inner.pipeline, PhantomData)
     }
+
+    #[inline]
+    fn local_size(&self) -> Option<[u32; 3]> {
+        self.local_size
+    }
 }
 
 unsafe impl<T> ComputePipelineAbstract for T
@@ -203,6 +430,11 @@ unsafe impl<T> ComputePipelineAbstract for T
     fn inner(&self) -> ComputePipelineSys {
         (**self).inner()
     }
+
+    #[inline]
+    fn local_size(&self) -> Option<[u32; 3]> {
+        (**self).local_size()
+    }
 }
 
 /// Opaque object that represents the inside of the compute pipeline. Can be made into a trait
@@ -307,6 +539,21 @@ pub enum ComputePipelineCreationError {
     PipelineLayoutCreationError(PipelineLayoutCreationError),
     /// The pipeline layout is not compatible with what the shader expects.
     IncompatiblePipelineLayout(PipelineLayoutNotSupersetError),
+    /// The subgroup size options are not supported.
+    SubgroupSizeControl(SubgroupSizeControlError),
+    /// The shader declares a SPIR-V capability whose extension or feature isn't enabled on the
+    /// device.
+    CapabilityNotEnabled {
+        /// Name of the capability in the SPIR-V specification.
+        capability: &'static str,
+    },
+    /// The shader uses cooperative matrices, but the subgroups of the pipeline may be partially
+    /// filled. The X dimension of the workgroup size must be a multiple of the subgroup size,
+    /// and if the subgroup size is allowed to vary, full subgroups must be required.
+    CooperativeMatrixPartialSubgroups,
+    /// The subgroup size options can't be checked because the workgroup size of the entry point
+    /// is unknown. See `ComputeShaderEntryPoint::with_local_size`.
+    UnknownLocalSize,
 }
 
 impl error::Error for ComputePipelineCreationError {
@@ -318,6 +565,16 @@ impl error::Error for ComputePipelineCreationError {
                 "error while creating the pipeline layout object",
             ComputePipelineCreationError::IncompatiblePipelineLayout(_) =>
                 "the pipeline layout is not compatible with what the shader expects",
+            ComputePipelineCreationError::SubgroupSizeControl(_) =>
+                "the subgroup size options are not supported",
+            ComputePipelineCreationError::CapabilityNotEnabled { .. } =>
+                "the shader declares a SPIR-V capability that isn't enabled on the device",
+            ComputePipelineCreationError::CooperativeMatrixPartialSubgroups =>
+                "the shader uses cooperative matrices, but the subgroups of the pipeline may be \
+                 partially filled",
+            ComputePipelineCreationError::UnknownLocalSize =>
+                "the subgroup size options can't be checked because the workgroup size of the \
+                 entry point is unknown",
         }
     }
 
@@ -327,6 +584,8 @@ impl error::Error for ComputePipelineCreationError {
             ComputePipelineCreationError::OomError(ref err) => Some(err),
             ComputePipelineCreationError::PipelineLayoutCreationError(ref err) => Some(err),
             ComputePipelineCreationError::IncompatiblePipelineLayout(ref err) => Some(err),
+            ComputePipelineCreationError::SubgroupSizeControl(ref err) => Some(err),
+            _ => None,
         }
     }
 }
@@ -334,7 +593,12 @@ impl error::Error for ComputePipelineCreationError {
 impl fmt::Display for ComputePipelineCreationError {
     #[inline]
     fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        write!(fmt, "{}", error::Error::description(self))
+        match *self {
+            ComputePipelineCreationError::CapabilityNotEnabled { capability } => {
+                write!(fmt, "{}: {}", error::Error::description(self), capability)
+            },
+            _ => write!(fmt, "{}", error::Error::description(self)),
+        }
     }
 }
 
@@ -359,6 +623,13 @@ impl From<PipelineLayoutNotSupersetError> for ComputePipelineCreationError {
     }
 }
 
+impl From<SubgroupSizeControlError> for ComputePipelineCreationError {
+    #[inline]
+    fn from(err: SubgroupSizeControlError) -> ComputePipelineCreationError {
+        ComputePipelineCreationError::SubgroupSizeControl(err)
+    }
+}
+
 impl From<Error> for ComputePipelineCreationError {
     #[inline]
     fn from(err: Error) -> ComputePipelineCreationError {
@@ -376,6 +647,461 @@ impl From<Error> for ComputePipelineCreationError {
 
 #[cfg(test)]
 mod tests {
+    use std::ffi::CStr;
+    use std::iter;
+    use std::sync::Arc;
+
+    use buffer::BufferUsage;
+    use buffer::CpuAccessibleBuffer;
+    use command_buffer::AutoCommandBufferBuilder;
+    use command_buffer::CommandBuffer;
+    use descriptor::descriptor::DescriptorBufferContentDesc;
+    use descriptor::descriptor::DescriptorBufferDesc;
+    use descriptor::descriptor::DescriptorDesc;
+    use descriptor::descriptor::DescriptorDescTy;
+    use descriptor::descriptor::ShaderStages;
+    use descriptor::descriptor_set::SimpleDescriptorSetBufferExt;
+    use descriptor::descriptor_set::SimpleDescriptorSetBuilder;
+    use descriptor::pipeline_layout::PipelineLayoutDesc;
+    use descriptor::pipeline_layout::PipelineLayoutDescNames;
+    use descriptor::pipeline_layout::PipelineLayoutDescPcRange;
+    use device::Device;
+    use device::DeviceExtensions;
+    use features::Features;
+    use instance;
+    use instance::ComponentType;
+    use instance::InstanceExtensions;
+    use instance::Scope;
+    use pipeline::ComputePipeline;
+    use pipeline::ComputePipelineCreationError;
+    use pipeline::cache::PipelineCache;
+    use pipeline::shader::ComputeShaderLocalSize;
+    use pipeline::shader::ShaderModule;
+    use pipeline::shader::SpecializationConstants;
+    use pipeline::shader::SpecializationMapEntry;
+    use pipeline::subgroup_size::SubgroupSizeControl;
+    use pipeline::subgroup_size::SubgroupSizeProperties;
+    use sync::GpuFuture;
+
     // TODO: test for basic creation
     // TODO: test for pipeline layout error
+
+    #[test]
+    fn cooperative_matrix_mul_add() {
+        let instance = instance!();
+
+        let physical = match instance::PhysicalDevice::enumerate(&instance).next() {
+            Some(p) => p,
+            None => return,
+        };
+
+        // The shader only uses 32-bit floats, so that no other capability is needed.
+        let properties = physical
+            .cooperative_matrix_properties()
+            .into_iter()
+            .find(|p| {
+                p.a_type == ComponentType::Float32 && p.b_type == ComponentType::Float32 &&
+                    p.c_type == ComponentType::Float32 &&
+                    p.result_type == ComponentType::Float32 &&
+                    p.scope == Scope::Subgroup
+            });
+        let properties = match properties {
+            Some(p) => p,
+            None => return,
+        };
+
+        let subgroup_size = match physical.subgroup_properties() {
+            Some(p) => p.subgroup_size,
+            None => return,
+        };
+
+        let queue = match physical.queue_families().find(|q| q.supports_compute()) {
+            Some(q) => q,
+            None => return,
+        };
+
+        let extensions = DeviceExtensions {
+            khr_cooperative_matrix: true,
+            ..DeviceExtensions::none()
+        };
+
+        let (device, mut queues) = match Device::new(&physical,
+                                                     &Features::none(),
+                                                     &extensions,
+                                                     [(queue, 0.5)].iter().cloned()) {
+            Ok(r) => r,
+            Err(_) => return,
+        };
+        let queue = queues.next().unwrap();
+
+        let module = unsafe { ShaderModule::new(device.clone(), &COOPERATIVE_MATRIX_CS) }
+            .unwrap();
+        assert!(module.uses_cooperative_matrix());
+
+        let local_size = ComputeShaderLocalSize {
+            size: [32, 1, 1],
+            specialization_ids: [Some(3), None, None],
+        };
+        let entry_point = unsafe {
+            let name = CStr::from_bytes_with_nul_unchecked(b"main\0");
+            module.compute_shader_entry_point::<Spec, _>(name, OneStorageBufferDesc)
+                .with_local_size(local_size)
+        };
+
+        let spec = Spec {
+            m: properties.m_size,
+            n: properties.n_size,
+            k: properties.k_size,
+            local_size_x: subgroup_size,
+        };
+
+        let pipeline = Arc::new(ComputePipeline::new(device.clone(), &entry_point, &spec)
+                                    .unwrap());
+
+        let num_results = (properties.m_size * properties.n_size) as usize;
+        let output = CpuAccessibleBuffer::from_iter(device.clone(),
+                                                    BufferUsage::all(),
+                                                    iter::once(queue.family()),
+                                                    (0 .. num_results).map(|_| 0.0f32))
+            .unwrap();
+
+        let builder = SimpleDescriptorSetBuilder::new(pipeline.clone(), 0);
+        let set = SimpleDescriptorSetBufferExt::add_me(output.clone(), builder, "out")
+            .unwrap()
+            .build().unwrap();
+
+        AutoCommandBufferBuilder::primary_one_time_submit(device.clone(), queue.family())
+            .unwrap()
+            .dispatch([1, 1, 1], pipeline.clone(), set, ())
+            .unwrap()
+            .build()
+            .unwrap()
+            .execute(queue.clone())
+            .unwrap()
+            .then_signal_fence_and_flush()
+            .unwrap()
+            .wait(None)
+            .unwrap();
+
+        // Each element of the result is the sum over K of 1.0 * 2.0, plus 3.0.
+        let expected = properties.k_size as f32 * 2.0 + 3.0;
+        let content = output.read().unwrap();
+        assert!(content.iter().all(|&v| v == expected));
+    }
+
+    #[test]
+    fn required_subgroup_size() {
+        let extensions = InstanceExtensions {
+            khr_get_physical_device_properties2: true,
+            ..InstanceExtensions::none()
+        };
+        let instance = match instance::Instance::new(None, &extensions, None) {
+            Ok(i) => i,
+            Err(_) => return,
+        };
+
+        let physical = match instance::PhysicalDevice::enumerate(&instance).next() {
+            Some(p) => p,
+            None => return,
+        };
+
+        let properties = match SubgroupSizeProperties::from_physical_device(physical) {
+            Some(p) => p,
+            None => return,
+        };
+        if !properties.required_subgroup_size_stages.compute {
+            return;
+        }
+
+        let queue = match physical.queue_families().find(|q| q.supports_compute()) {
+            Some(q) => q,
+            None => return,
+        };
+
+        let extensions = DeviceExtensions {
+            ext_subgroup_size_control: true,
+            ..DeviceExtensions::none()
+        };
+
+        let (device, mut queues) = match Device::new(&physical,
+                                                     &Features::none(),
+                                                     &extensions,
+                                                     [(queue, 0.5)].iter().cloned()) {
+            Ok(r) => r,
+            Err(_) => return,
+        };
+        let queue = queues.next().unwrap();
+        if !device.subgroup_size_features().subgroup_size_control {
+            return;
+        }
+
+        let module = unsafe { ShaderModule::new(device.clone(), &SUBGROUP_SIZE_CS) }.unwrap();
+        let local_size = ComputeShaderLocalSize {
+            size: [1, 1, 1],
+            specialization_ids: [None; 3],
+        };
+        let entry_point = unsafe {
+            let name = CStr::from_bytes_with_nul_unchecked(b"main\0");
+            module.compute_shader_entry_point::<(), _>(name, OneStorageBufferDesc)
+                .with_local_size(local_size)
+        };
+
+        // Pins the subgroup size to the smallest one, which is usually not the default one.
+        let control = SubgroupSizeControl {
+            required_size: Some(properties.min_subgroup_size),
+            ..SubgroupSizeControl::default()
+        };
+        let cache = PipelineCache::empty(device.clone()).unwrap();
+        let pipeline = Arc::new(ComputePipeline::with_subgroup_size_control(device.clone(),
+                                                                            &entry_point,
+                                                                            &(),
+                                                                            control,
+                                                                            Some(&cache))
+                                    .unwrap());
+
+        let output = CpuAccessibleBuffer::from_data(device.clone(),
+                                                    BufferUsage::all(),
+                                                    iter::once(queue.family()),
+                                                    0u32)
+            .unwrap();
+
+        let builder = SimpleDescriptorSetBuilder::new(pipeline.clone(), 0);
+        let set = SimpleDescriptorSetBufferExt::add_me(output.clone(), builder, "out")
+            .unwrap()
+            .build().unwrap();
+
+        AutoCommandBufferBuilder::primary_one_time_submit(device.clone(), queue.family())
+            .unwrap()
+            .dispatch([1, 1, 1], pipeline.clone(), set, ())
+            .unwrap()
+            .build()
+            .unwrap()
+            .execute(queue.clone())
+            .unwrap()
+            .then_signal_fence_and_flush()
+            .unwrap()
+            .wait(None)
+            .unwrap();
+
+        // The shader writes `gl_SubgroupSize`.
+        assert_eq!(*output.read().unwrap(), properties.min_subgroup_size);
+    }
+
+    #[test]
+    fn subgroup_size_control_unknown_local_size() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let module = unsafe { ShaderModule::new(device.clone(), &SUBGROUP_SIZE_CS) }.unwrap();
+        let entry_point = unsafe {
+            let name = CStr::from_bytes_with_nul_unchecked(b"main\0");
+            module.compute_shader_entry_point::<(), _>(name, OneStorageBufferDesc)
+        };
+
+        let control = SubgroupSizeControl {
+            require_full_subgroups: true,
+            ..SubgroupSizeControl::default()
+        };
+        match ComputePipeline::with_subgroup_size_control(device.clone(), &entry_point, &(),
+                                                          control, None) {
+            Err(ComputePipelineCreationError::UnknownLocalSize) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn state_hash() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let module = unsafe { ShaderModule::new(device.clone(), &SUBGROUP_SIZE_CS) }.unwrap();
+        let entry_point = unsafe {
+            let name = CStr::from_bytes_with_nul_unchecked(b"main\0");
+            module.compute_shader_entry_point::<Spec, _>(name, OneStorageBufferDesc)
+        };
+
+        let spec = Spec { m: 16, n: 16, k: 16, local_size_x: 32 };
+        let hash = ComputePipeline::state_hash(&entry_point, &spec);
+        assert_eq!(hash, ComputePipeline::state_hash(&entry_point, &spec));
+
+        // Each specialization constant is part of the hash.
+        let other = Spec { k: 8, ..spec };
+        assert!(hash != ComputePipeline::state_hash(&entry_point, &other));
+        let other = Spec { local_size_x: 64, ..spec };
+        assert!(hash != ComputePipeline::state_hash(&entry_point, &other));
+
+        // So is the code of the shader, even if the entry point has the same name.
+        let other_module = unsafe { ShaderModule::new(device.clone(), &COOPERATIVE_MATRIX_CS) }
+            .unwrap();
+        let other_entry_point = unsafe {
+            let name = CStr::from_bytes_with_nul_unchecked(b"main\0");
+            other_module.compute_shader_entry_point::<Spec, _>(name, OneStorageBufferDesc)
+        };
+        assert!(hash != ComputePipeline::state_hash(&other_entry_point, &spec));
+    }
+
+    #[repr(C)]
+    #[derive(Copy, Clone)]
+    struct Spec {
+        m: u32,
+        n: u32,
+        k: u32,
+        local_size_x: u32,
+    }
+
+    unsafe impl SpecializationConstants for Spec {
+        fn descriptors() -> &'static [SpecializationMapEntry] {
+            static DESCRIPTORS: [SpecializationMapEntry; 4] = [
+                SpecializationMapEntry { constant_id: 0, offset: 0, size: 4 },
+                SpecializationMapEntry { constant_id: 1, offset: 4, size: 4 },
+                SpecializationMapEntry { constant_id: 2, offset: 8, size: 4 },
+                SpecializationMapEntry { constant_id: 3, offset: 12, size: 4 },
+            ];
+            &DESCRIPTORS
+        }
+    }
+
+    // Pipeline layout with a single storage buffer named `out`.
+    #[derive(Debug, Copy, Clone)]
+    struct OneStorageBufferDesc;
+
+    unsafe impl PipelineLayoutDesc for OneStorageBufferDesc {
+        fn num_sets(&self) -> usize {
+            1
+        }
+
+        fn num_bindings_in_set(&self, set: usize) -> Option<usize> {
+            if set == 0 { Some(1) } else { None }
+        }
+
+        fn descriptor(&self, set: usize, binding: usize) -> Option<DescriptorDesc> {
+            if set != 0 || binding != 0 {
+                return None;
+            }
+
+            Some(DescriptorDesc {
+                     ty: DescriptorDescTy::Buffer(DescriptorBufferDesc {
+                                                      dynamic: Some(false),
+                                                      storage: true,
+                                                      content: DescriptorBufferContentDesc::F32,
+                                                  }),
+                     array_count: 1,
+                     stages: ShaderStages::compute(),
+                     readonly: false,
+                 })
+        }
+
+        fn num_push_constants_ranges(&self) -> usize {
+            0
+        }
+
+        fn push_constants_range(&self, _: usize) -> Option<PipelineLayoutDescPcRange> {
+            None
+        }
+    }
+
+    unsafe impl PipelineLayoutDescNames for OneStorageBufferDesc {
+        fn descriptor_by_name(&self, name: &str) -> Option<(usize, usize)> {
+            if name == "out" { Some((0, 0)) } else { None }
+        }
+    }
+
+    /*
+        SPIR-V 1.3 module, assembled by hand. The sizes of the matrices and the X dimension of
+        the workgroup are specialization constants 0 to 3.
+
+        void main() {
+            coopmat<float, gl_ScopeSubgroup, M, K, gl_MatrixUseA> a = coopmat<...>(1.0);
+            coopmat<float, gl_ScopeSubgroup, K, N, gl_MatrixUseB> b = coopmat<...>(2.0);
+            coopmat<float, gl_ScopeSubgroup, M, N, gl_MatrixUseAccumulator> c = coopmat<...>(3.0);
+            coopMatStore(coopMatMulAdd(a, b, c), out_buf.data, 0, N,
+                         gl_CooperativeMatrixLayoutRowMajor);
+        }
+    */
+    const COOPERATIVE_MATRIX_CS: [u8; 856] = [3, 2, 35, 7, 0, 3, 1, 0, 0, 0, 0, 0, 33, 0, 0, 0, 0,
+                                              0, 0, 0, 17, 0, 2, 0, 1, 0, 0, 0, 17, 0, 2, 0, 134,
+                                              23, 0, 0, 10, 0, 8, 0, 83, 80, 86, 95, 75, 72, 82,
+                                              95, 99, 111, 111, 112, 101, 114, 97, 116, 105, 118,
+                                              101, 95, 109, 97, 116, 114, 105, 120, 0, 0, 14, 0, 3,
+                                              0, 0, 0, 0, 0, 1, 0, 0, 0, 15, 0, 5, 0, 5, 0, 0, 0,
+                                              1, 0, 0, 0, 109, 97, 105, 110, 0, 0, 0, 0, 16, 0, 6,
+                                              0, 1, 0, 0, 0, 17, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0,
+                                              1, 0, 0, 0, 71, 0, 4, 0, 2, 0, 0, 0, 1, 0, 0, 0, 0,
+                                              0, 0, 0, 71, 0, 4, 0, 3, 0, 0, 0, 1, 0, 0, 0, 1, 0,
+                                              0, 0, 71, 0, 4, 0, 4, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0,
+                                              0, 71, 0, 4, 0, 5, 0, 0, 0, 1, 0, 0, 0, 3, 0, 0, 0,
+                                              71, 0, 4, 0, 6, 0, 0, 0, 11, 0, 0, 0, 25, 0, 0, 0,
+                                              71, 0, 4, 0, 7, 0, 0, 0, 6, 0, 0, 0, 4, 0, 0, 0, 72,
+                                              0, 5, 0, 8, 0, 0, 0, 0, 0, 0, 0, 35, 0, 0, 0, 0, 0,
+                                              0, 0, 71, 0, 3, 0, 8, 0, 0, 0, 2, 0, 0, 0, 71, 0, 4,
+                                              0, 9, 0, 0, 0, 34, 0, 0, 0, 0, 0, 0, 0, 71, 0, 4, 0,
+                                              9, 0, 0, 0, 33, 0, 0, 0, 0, 0, 0, 0, 19, 0, 2, 0, 10,
+                                              0, 0, 0, 33, 0, 3, 0, 11, 0, 0, 0, 10, 0, 0, 0, 22,
+                                              0, 3, 0, 12, 0, 0, 0, 32, 0, 0, 0, 21, 0, 4, 0, 13,
+                                              0, 0, 0, 32, 0, 0, 0, 0, 0, 0, 0, 50, 0, 4, 0, 13, 0,
+                                              0, 0, 2, 0, 0, 0, 16, 0, 0, 0, 50, 0, 4, 0, 13, 0, 0,
+                                              0, 3, 0, 0, 0, 16, 0, 0, 0, 50, 0, 4, 0, 13, 0, 0, 0,
+                                              4, 0, 0, 0, 16, 0, 0, 0, 50, 0, 4, 0, 13, 0, 0, 0, 5,
+                                              0, 0, 0, 32, 0, 0, 0, 43, 0, 4, 0, 13, 0, 0, 0, 14,
+                                              0, 0, 0, 0, 0, 0, 0, 43, 0, 4, 0, 13, 0, 0, 0, 15, 0,
+                                              0, 0, 1, 0, 0, 0, 43, 0, 4, 0, 13, 0, 0, 0, 16, 0, 0,
+                                              0, 2, 0, 0, 0, 43, 0, 4, 0, 13, 0, 0, 0, 17, 0, 0, 0,
+                                              3, 0, 0, 0, 23, 0, 4, 0, 18, 0, 0, 0, 13, 0, 0, 0, 3,
+                                              0, 0, 0, 51, 0, 6, 0, 18, 0, 0, 0, 6, 0, 0, 0, 5, 0,
+                                              0, 0, 15, 0, 0, 0, 15, 0, 0, 0, 104, 17, 7, 0, 19, 0,
+                                              0, 0, 12, 0, 0, 0, 17, 0, 0, 0, 2, 0, 0, 0, 4, 0, 0,
+                                              0, 14, 0, 0, 0, 104, 17, 7, 0, 20, 0, 0, 0, 12, 0, 0,
+                                              0, 17, 0, 0, 0, 4, 0, 0, 0, 3, 0, 0, 0, 15, 0, 0, 0,
+                                              104, 17, 7, 0, 21, 0, 0, 0, 12, 0, 0, 0, 17, 0, 0, 0,
+                                              2, 0, 0, 0, 3, 0, 0, 0, 16, 0, 0, 0, 43, 0, 4, 0, 12,
+                                              0, 0, 0, 22, 0, 0, 0, 0, 0, 128, 63, 43, 0, 4, 0, 12,
+                                              0, 0, 0, 23, 0, 0, 0, 0, 0, 0, 64, 43, 0, 4, 0, 12,
+                                              0, 0, 0, 24, 0, 0, 0, 0, 0, 64, 64, 29, 0, 3, 0, 7,
+                                              0, 0, 0, 12, 0, 0, 0, 30, 0, 3, 0, 8, 0, 0, 0, 7, 0,
+                                              0, 0, 32, 0, 4, 0, 25, 0, 0, 0, 12, 0, 0, 0, 8, 0, 0,
+                                              0, 59, 0, 4, 0, 25, 0, 0, 0, 9, 0, 0, 0, 12, 0, 0, 0,
+                                              32, 0, 4, 0, 26, 0, 0, 0, 12, 0, 0, 0, 12, 0, 0, 0,
+                                              54, 0, 5, 0, 10, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 11,
+                                              0, 0, 0, 248, 0, 2, 0, 27, 0, 0, 0, 80, 0, 4, 0, 19,
+                                              0, 0, 0, 28, 0, 0, 0, 22, 0, 0, 0, 80, 0, 4, 0, 20,
+                                              0, 0, 0, 29, 0, 0, 0, 23, 0, 0, 0, 80, 0, 4, 0, 21,
+                                              0, 0, 0, 30, 0, 0, 0, 24, 0, 0, 0, 107, 17, 6, 0, 21,
+                                              0, 0, 0, 31, 0, 0, 0, 28, 0, 0, 0, 29, 0, 0, 0, 30,
+                                              0, 0, 0, 65, 0, 6, 0, 26, 0, 0, 0, 32, 0, 0, 0, 9, 0,
+                                              0, 0, 14, 0, 0, 0, 14, 0, 0, 0, 106, 17, 5, 0, 32, 0,
+                                              0, 0, 31, 0, 0, 0, 14, 0, 0, 0, 3, 0, 0, 0, 253, 0,
+                                              1, 0, 56, 0, 1, 0];
+
+    /*
+        SPIR-V 1.3 module, assembled by hand. Writes the size of the subgroups to the buffer.
+
+        layout(local_size_x = 1) in;
+        layout(set = 0, binding = 0) buffer Out { uint data[]; } out_buf;
+
+        void main() {
+            out_buf.data[0] = gl_SubgroupSize;
+        }
+    */
+    const SUBGROUP_SIZE_CS: [u8; 436] = [3, 2, 35, 7, 0, 3, 1, 0, 0, 0, 0, 0, 16, 0, 0, 0, 0, 0, 0,
+                                         0, 17, 0, 2, 0, 1, 0, 0, 0, 17, 0, 2, 0, 61, 0, 0, 0, 14,
+                                         0, 3, 0, 0, 0, 0, 0, 1, 0, 0, 0, 15, 0, 6, 0, 5, 0, 0, 0,
+                                         1, 0, 0, 0, 109, 97, 105, 110, 0, 0, 0, 0, 2, 0, 0, 0, 16,
+                                         0, 6, 0, 1, 0, 0, 0, 17, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0,
+                                         1, 0, 0, 0, 71, 0, 4, 0, 2, 0, 0, 0, 11, 0, 0, 0, 36, 0, 0,
+                                         0, 71, 0, 4, 0, 3, 0, 0, 0, 6, 0, 0, 0, 4, 0, 0, 0, 72, 0,
+                                         5, 0, 4, 0, 0, 0, 0, 0, 0, 0, 35, 0, 0, 0, 0, 0, 0, 0, 71,
+                                         0, 3, 0, 4, 0, 0, 0, 2, 0, 0, 0, 71, 0, 4, 0, 5, 0, 0, 0,
+                                         34, 0, 0, 0, 0, 0, 0, 0, 71, 0, 4, 0, 5, 0, 0, 0, 33, 0, 0,
+                                         0, 0, 0, 0, 0, 19, 0, 2, 0, 6, 0, 0, 0, 33, 0, 3, 0, 7, 0,
+                                         0, 0, 6, 0, 0, 0, 21, 0, 4, 0, 8, 0, 0, 0, 32, 0, 0, 0, 0,
+                                         0, 0, 0, 29, 0, 3, 0, 3, 0, 0, 0, 8, 0, 0, 0, 30, 0, 3, 0,
+                                         4, 0, 0, 0, 3, 0, 0, 0, 32, 0, 4, 0, 9, 0, 0, 0, 12, 0, 0,
+                                         0, 4, 0, 0, 0, 59, 0, 4, 0, 9, 0, 0, 0, 5, 0, 0, 0, 12, 0,
+                                         0, 0, 32, 0, 4, 0, 10, 0, 0, 0, 1, 0, 0, 0, 8, 0, 0, 0, 59,
+                                         0, 4, 0, 10, 0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 0, 43, 0, 4, 0,
+                                         8, 0, 0, 0, 11, 0, 0, 0, 0, 0, 0, 0, 32, 0, 4, 0, 12, 0, 0,
+                                         0, 12, 0, 0, 0, 8, 0, 0, 0, 54, 0, 5, 0, 6, 0, 0, 0, 1, 0,
+                                         0, 0, 0, 0, 0, 0, 7, 0, 0, 0, 248, 0, 2, 0, 13, 0, 0, 0,
+                                         61, 0, 4, 0, 8, 0, 0, 0, 14, 0, 0, 0, 2, 0, 0, 0, 65, 0, 6,
+                                         0, 12, 0, 0, 0, 15, 0, 0, 0, 5, 0, 0, 0, 11, 0, 0, 0, 11,
+                                         0, 0, 0, 62, 0, 3, 0, 15, 0, 0, 0, 14, 0, 0, 0, 253, 0, 1,
+                                         0, 56, 0, 1, 0];
 }
@@ -0,0 +1,694 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::error;
+use std::fmt;
+use std::sync::Arc;
+
+use descriptor::pipeline_layout::PipelineLayout;
+use descriptor::pipeline_layout::PipelineLayoutDescNames;
+use device::Device;
+use pipeline::compute_pipeline::ComputePipeline;
+use pipeline::compute_pipeline::ComputePipelineCreationError;
+use pipeline::shader::ComputeShaderEntryPoint;
+use pipeline::shader::SpecializationConstants;
+
+/// Multiple compute pipelines created from the same shader, each with a different workgroup
+/// size.
+///
+/// The workgroup size of the shader must be overridable with specialization constants. Each
+/// candidate size is turned into a value of the shader's specialization constants by a closure,
+/// and a pipeline is created for each of them. You can then call `best_for` to get the pipeline
+/// that is the most appropriate to process a certain number of elements.
+///
+/// If only some of the sizes are going to be used, `ComputePipelineVariants::lazy` creates each
+/// pipeline the first time it is chosen instead.
+///
+/// # Example
+///
+/// ```ignore
+/// let variants = ComputePipelineVariants::new(device.clone(), &shader.main_entry_point(),
+///                                             [64, 128, 256].iter().cloned(),
+///                                             |size| cs::SpecializationConstants {
+///                                                 local_size_x: size,
+///                                             })?;
+///
+/// let pipeline = variants.best_for(num_elements);
+/// builder = builder.dispatch_elements(pipeline.clone(), [num_elements as u32, 1, 1], set, ())?;
+/// ```
+pub struct ComputePipelineVariants<Pl> {
+    // Sorted by increasing number of invocations per workgroup.
+    pipelines: Vec<Arc<ComputePipeline<Pl>>>,
+    // Number of invocations of each pipeline, in the same order.
+    invocations: Vec<u64>,
+    // Size of the subgroups of the physical device, or 1 if unknown.
+    subgroup_size: u32,
+}
+
+impl ComputePipelineVariants<()> {
+    /// Builds one compute pipeline for each of the candidate sizes.
+    ///
+    /// The `specialization` closure is called with each candidate and must return the
+    /// specialization constants to use for the corresponding pipeline. The actual workgroup size
+    /// of each pipeline is obtained from the specialization constants and is checked against the
+    /// `max_compute_work_group_size` and `max_compute_work_group_invocations` limits of the
+    /// physical device before the pipeline is created. Returns `UnknownLocalSize` if the
+    /// workgroup size of the entry point is unknown.
+    ///
+    /// The size of the subgroups of the physical device, if it can be queried, is taken into
+    /// account by `best_for`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `sizes` is empty.
+    ///
+    pub fn new<Css, Csl, I, S>(device: Arc<Device>, shader: &ComputeShaderEntryPoint<Css, Csl>,
+                               sizes: I, mut specialization: S)
+                               -> Result<ComputePipelineVariants<PipelineLayout<Csl>>,
+                                         ComputePipelineVariantsError>
+        where Csl: PipelineLayoutDescNames + Clone,
+              Css: SpecializationConstants,
+              I: IntoIterator<Item = u32>,
+              S: FnMut(u32) -> Css
+    {
+        let mut pipelines = Vec::new();
+
+        for (local_size, spec) in specialize_sizes(&device, shader, sizes, &mut specialization)? {
+            let pipeline = ComputePipeline::new(device.clone(), shader, &spec)?;
+            pipelines.push((invocations(local_size), Arc::new(pipeline)));
+        }
+
+        assert!(!pipelines.is_empty());
+        pipelines.sort_by_key(|&(invocations, _)| invocations);
+        let (sizes, pipelines) = pipelines.into_iter().unzip();
+
+        Ok(ComputePipelineVariants {
+               pipelines: pipelines,
+               invocations: sizes,
+               subgroup_size: subgroup_size(&device),
+           })
+    }
+
+    /// Same as `new`, except that the pipelines are only created the first time they are
+    /// returned by `best_for`.
+    ///
+    /// The workgroup sizes are still checked against the limits of the physical device
+    /// immediately.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `sizes` is empty.
+    ///
+    pub fn lazy<'a, Css, Csl, I, S>(device: Arc<Device>,
+                                    shader: &'a ComputeShaderEntryPoint<'a, Css, Csl>, sizes: I,
+                                    mut specialization: S)
+                                    -> Result<LazyComputePipelineVariants<'a, Css, Csl>,
+                                              ComputePipelineVariantsError>
+        where Csl: PipelineLayoutDescNames + Clone,
+              Css: SpecializationConstants,
+              I: IntoIterator<Item = u32>,
+              S: FnMut(u32) -> Css
+    {
+        let mut variants = specialize_sizes(&device, shader, sizes, &mut specialization)?
+            .into_iter()
+            .map(|(local_size, spec)| {
+                LazyVariant {
+                    invocations: invocations(local_size),
+                    specialization: spec,
+                    pipeline: None,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        assert!(!variants.is_empty());
+        variants.sort_by_key(|v| v.invocations);
+
+        Ok(LazyComputePipelineVariants {
+               subgroup_size: subgroup_size(&device),
+               device: device,
+               shader: shader,
+               variants: variants,
+           })
+    }
+}
+
+impl<Pl> ComputePipelineVariants<Pl> {
+    /// Returns the list of pipelines, by increasing number of invocations per workgroup.
+    #[inline]
+    pub fn pipelines(&self) -> &[Arc<ComputePipeline<Pl>>] {
+        &self.pipelines
+    }
+
+    /// Returns the pipeline to use in order to process `elements` elements, with one invocation
+    /// per element.
+    ///
+    /// The pipeline that wastes the least invocations is chosen. Since the invocations of a
+    /// workgroup are executed by whole subgroups, the invocations of a partially filled subgroup
+    /// count as wasted. If multiple pipelines waste the same number of invocations, the one with
+    /// the largest workgroups is chosen as it requires dispatching fewer of them.
+    #[inline]
+    pub fn best_for(&self, elements: u64) -> &Arc<ComputePipeline<Pl>> {
+        &self.pipelines[best_index(&self.invocations, self.subgroup_size, elements)]
+    }
+}
+
+/// Same as `ComputePipelineVariants`, except that each pipeline is created the first time it is
+/// chosen.
+///
+/// Created with `ComputePipelineVariants::lazy`. The pipelines that have been created are kept
+/// and are returned again by the next calls to `best_for`.
+pub struct LazyComputePipelineVariants<'a, Css, Csl>
+    where Csl: 'a,
+          Css: 'a
+{
+    device: Arc<Device>,
+    shader: &'a ComputeShaderEntryPoint<'a, Css, Csl>,
+    // Sorted by increasing number of invocations per workgroup.
+    variants: Vec<LazyVariant<Css, PipelineLayout<Csl>>>,
+    // Size of the subgroups of the physical device, or 1 if unknown.
+    subgroup_size: u32,
+}
+
+struct LazyVariant<Css, Pl> {
+    invocations: u64,
+    specialization: Css,
+    pipeline: Option<Arc<ComputePipeline<Pl>>>,
+}
+
+impl<'a, Css, Csl> LazyComputePipelineVariants<'a, Css, Csl>
+    where Csl: PipelineLayoutDescNames + Clone,
+          Css: SpecializationConstants
+{
+    /// Returns the pipeline to use in order to process `elements` elements, and creates it if
+    /// this is the first time it is chosen.
+    ///
+    /// The pipeline is chosen the same way as `ComputePipelineVariants::best_for`.
+    pub fn best_for(&mut self, elements: u64)
+                    -> Result<&Arc<ComputePipeline<PipelineLayout<Csl>>>,
+                              ComputePipelineCreationError> {
+        let index = {
+            let sizes = self.variants.iter().map(|v| v.invocations).collect::<Vec<_>>();
+            best_index(&sizes, self.subgroup_size, elements)
+        };
+
+        let variant = &mut self.variants[index];
+        if variant.pipeline.is_none() {
+            let pipeline =
+                ComputePipeline::new(self.device.clone(), self.shader, &variant.specialization)?;
+            variant.pipeline = Some(Arc::new(pipeline));
+        }
+
+        Ok(variant.pipeline.as_ref().unwrap())
+    }
+
+    /// Returns the number of pipelines that have been created so far.
+    #[inline]
+    pub fn num_created(&self) -> usize {
+        self.variants.iter().filter(|v| v.pipeline.is_some()).count()
+    }
+}
+
+// Turns each candidate size into specialization constants and checks the resulting workgroup
+// size against the limits of the physical device.
+fn specialize_sizes<Css, Csl, I, S>(device: &Arc<Device>,
+                                    shader: &ComputeShaderEntryPoint<Css, Csl>, sizes: I,
+                                    specialization: &mut S)
+                                    -> Result<Vec<([u32; 3], Css)>, ComputePipelineVariantsError>
+    where Css: SpecializationConstants,
+          I: IntoIterator<Item = u32>,
+          S: FnMut(u32) -> Css
+{
+    let (max_size, max_invocations) = {
+        let limits = device.physical_device().limits();
+        (limits.max_compute_work_group_size(), limits.max_compute_work_group_invocations())
+    };
+
+    let shader_local_size = match shader.local_size() {
+        Some(local_size) => local_size,
+        None => return Err(ComputePipelineVariantsError::UnknownLocalSize),
+    };
+
+    let mut output = Vec::new();
+
+    for size in sizes {
+        let spec = specialization(size);
+        let local_size = shader_local_size.specialize(&spec);
+        check_local_size(local_size, max_size, max_invocations)?;
+        output.push((local_size, spec));
+    }
+
+    Ok(output)
+}
+
+// Returns the size of the subgroups of the physical device, or 1 if it can't be queried.
+#[inline]
+fn subgroup_size(device: &Device) -> u32 {
+    device
+        .physical_device()
+        .subgroup_properties()
+        .map(|p| p.subgroup_size)
+        .unwrap_or(1)
+}
+
+// Returns the total number of invocations of a workgroup.
+#[inline]
+fn invocations(local_size: [u32; 3]) -> u64 {
+    local_size[0] as u64 * local_size[1] as u64 * local_size[2] as u64
+}
+
+// Checks that a workgroup size is within the limits of the physical device.
+fn check_local_size(size: [u32; 3], max_size: [u32; 3], max_invocations: u32)
+                    -> Result<(), ComputePipelineVariantsError> {
+    if size.iter().any(|&s| s == 0) ||
+        size.iter().zip(max_size.iter()).any(|(&s, &max)| s > max)
+    {
+        return Err(ComputePipelineVariantsError::MaxComputeWorkGroupSizeExceeded {
+                       size: size,
+                       max: max_size,
+                   });
+    }
+
+    if invocations(size) > max_invocations as u64 {
+        return Err(ComputePipelineVariantsError::MaxComputeWorkGroupInvocationsExceeded {
+                       invocations: invocations(size),
+                       max: max_invocations,
+                   });
+    }
+
+    Ok(())
+}
+
+// Returns the index of the workgroup size that wastes the least invocations for processing
+// `elements` elements. `sizes` must be sorted by increasing value.
+//
+// Each workgroup occupies a whole number of subgroups of `subgroup_size` invocations.
+fn best_index(sizes: &[u64], subgroup_size: u32, elements: u64) -> usize {
+    let subgroup_size = subgroup_size as u64;
+    let mut best = 0;
+    let mut best_waste = u64::max_value();
+
+    for (index, &size) in sizes.iter().enumerate() {
+        let groups = elements / size + if elements % size != 0 { 1 } else { 0 };
+        let occupied = (size + subgroup_size - 1) / subgroup_size * subgroup_size;
+        let waste = groups.saturating_mul(occupied).saturating_sub(elements);
+        if waste <= best_waste {
+            best = index;
+            best_waste = waste;
+        }
+    }
+
+    best
+}
+
+/// Error that can happen when creating a `ComputePipelineVariants`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ComputePipelineVariantsError {
+    /// Error while creating one of the pipelines.
+    CreationError(ComputePipelineCreationError),
+
+    /// A workgroup size is zero or exceeds the `max_compute_work_group_size` limit in one of its
+    /// dimensions.
+    MaxComputeWorkGroupSizeExceeded {
+        /// The workgroup size of the pipeline.
+        size: [u32; 3],
+        /// The maximum allowed size.
+        max: [u32; 3],
+    },
+
+    /// A workgroup size exceeds the `max_compute_work_group_invocations` limit.
+    MaxComputeWorkGroupInvocationsExceeded {
+        /// The number of invocations per workgroup of the pipeline.
+        invocations: u64,
+        /// The maximum allowed number of invocations.
+        max: u32,
+    },
+
+    /// The workgroup size of the entry point is unknown. See
+    /// `ComputeShaderEntryPoint::with_local_size`.
+    UnknownLocalSize,
+}
+
+impl error::Error for ComputePipelineVariantsError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            ComputePipelineVariantsError::CreationError(_) =>
+                "error while creating one of the pipelines",
+            ComputePipelineVariantsError::MaxComputeWorkGroupSizeExceeded { .. } =>
+                "a workgroup size exceeds the max_compute_work_group_size limit",
+            ComputePipelineVariantsError::MaxComputeWorkGroupInvocationsExceeded { .. } =>
+                "a workgroup size exceeds the max_compute_work_group_invocations limit",
+            ComputePipelineVariantsError::UnknownLocalSize =>
+                "the workgroup size of the entry point is unknown",
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            ComputePipelineVariantsError::CreationError(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ComputePipelineVariantsError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl From<ComputePipelineCreationError> for ComputePipelineVariantsError {
+    #[inline]
+    fn from(err: ComputePipelineCreationError) -> ComputePipelineVariantsError {
+        ComputePipelineVariantsError::CreationError(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CStr;
+    use std::sync::Arc;
+
+    use buffer::BufferUsage;
+    use buffer::CpuAccessibleBuffer;
+    use command_buffer::AutoCommandBufferBuilder;
+    use command_buffer::CommandBuffer;
+    use descriptor::descriptor::DescriptorBufferContentDesc;
+    use descriptor::descriptor::DescriptorBufferDesc;
+    use descriptor::descriptor::DescriptorDesc;
+    use descriptor::descriptor::DescriptorDescTy;
+    use descriptor::descriptor::ShaderStages;
+    use descriptor::descriptor_set::SimpleDescriptorSetBufferExt;
+    use descriptor::descriptor_set::SimpleDescriptorSetBuilder;
+    use descriptor::pipeline_layout::PipelineLayoutDesc;
+    use descriptor::pipeline_layout::PipelineLayoutDescNames;
+    use descriptor::pipeline_layout::PipelineLayoutDescPcRange;
+    use pipeline::ComputePipelineVariants;
+    use pipeline::ComputePipelineVariantsError;
+    use pipeline::shader::ComputeShaderEntryPoint;
+    use pipeline::shader::ComputeShaderLocalSize;
+    use pipeline::shader::ShaderModule;
+    use pipeline::shader::SpecializationConstants;
+    use pipeline::shader::SpecializationMapEntry;
+    use super::best_index;
+    use super::check_local_size;
+    use sync::GpuFuture;
+
+    // Number of elements processed by the dispatch tests. A multiple of every candidate size, so
+    // that no invocation writes past the end of the buffer.
+    const ELEMENTS: u32 = 1024;
+
+    #[test]
+    fn dispatch_each_size() {
+        let (device, queue) = gfx_dev_and_queue!();
+        let module = unsafe { ShaderModule::new(device.clone(), &LOCAL_SIZE_X_CS) }.unwrap();
+        let shader = entry_point(&module);
+
+        let variants = ComputePipelineVariants::new(device.clone(),
+                                                    &shader,
+                                                    vec![256, 64, 128],
+                                                    |size| Spec { local_size_x: size })
+            .unwrap();
+
+        let sizes = variants
+            .pipelines()
+            .iter()
+            .map(|p| p.local_size())
+            .collect::<Vec<_>>();
+        assert_eq!(sizes, vec![Some([64, 1, 1]), Some([128, 1, 1]), Some([256, 1, 1])]);
+        assert_eq!(variants.best_for(ELEMENTS as u64).local_size(), Some([256, 1, 1]));
+
+        for pipeline in variants.pipelines() {
+            let output = CpuAccessibleBuffer::from_iter(device.clone(),
+                                                        BufferUsage::all(),
+                                                        Some(queue.family()),
+                                                        (0 .. ELEMENTS).map(|_| 0u32))
+                .unwrap();
+
+            let builder = SimpleDescriptorSetBuilder::new(pipeline.clone(), 0);
+            let set = SimpleDescriptorSetBufferExt::add_me(output.clone(), builder, "out")
+                .unwrap()
+                .build()
+                .unwrap();
+
+            AutoCommandBufferBuilder::primary_one_time_submit(device.clone(), queue.family())
+                .unwrap()
+                .dispatch_elements(pipeline.clone(), [ELEMENTS, 1, 1], set, ())
+                .unwrap()
+                .build()
+                .unwrap()
+                .execute(queue.clone())
+                .unwrap()
+                .then_signal_fence_and_flush()
+                .unwrap()
+                .wait(None)
+                .unwrap();
+
+            // Each invocation writes the X dimension of its workgroup at its own index.
+            let size = pipeline.local_size().unwrap()[0];
+            assert!(output.read().unwrap().iter().all(|&v| v == size));
+        }
+    }
+
+    #[test]
+    fn lazy_creation() {
+        let (device, _) = gfx_dev_and_queue!();
+        let module = unsafe { ShaderModule::new(device.clone(), &LOCAL_SIZE_X_CS) }.unwrap();
+        let shader = entry_point(&module);
+
+        let mut variants = ComputePipelineVariants::lazy(device.clone(),
+                                                         &shader,
+                                                         vec![64, 128, 256],
+                                                         |size| Spec { local_size_x: size })
+            .unwrap();
+        assert_eq!(variants.num_created(), 0);
+
+        let first = variants.best_for(ELEMENTS as u64).unwrap().clone();
+        assert_eq!(first.local_size(), Some([256, 1, 1]));
+        assert_eq!(variants.num_created(), 1);
+
+        // The pipeline is created only once.
+        let again = variants.best_for(2 * ELEMENTS as u64).unwrap().clone();
+        assert!(Arc::ptr_eq(&first, &again));
+        assert_eq!(variants.num_created(), 1);
+    }
+
+    #[test]
+    fn lazy_checks_limits() {
+        let (device, _) = gfx_dev_and_queue!();
+        let module = unsafe { ShaderModule::new(device.clone(), &LOCAL_SIZE_X_CS) }.unwrap();
+        let shader = entry_point(&module);
+
+        let max = device.physical_device().limits().max_compute_work_group_size()[0];
+        let result = ComputePipelineVariants::lazy(device.clone(),
+                                                   &shader,
+                                                   vec![64, max + 1],
+                                                   |size| Spec { local_size_x: size });
+        match result {
+            Err(ComputePipelineVariantsError::MaxComputeWorkGroupSizeExceeded { .. }) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn unknown_local_size() {
+        let (device, _) = gfx_dev_and_queue!();
+        let module = unsafe { ShaderModule::new(device.clone(), &LOCAL_SIZE_X_CS) }.unwrap();
+        let shader = unsafe {
+            let name = CStr::from_bytes_with_nul_unchecked(b"main\0");
+            module.compute_shader_entry_point(name, OutDesc)
+        };
+
+        let result = ComputePipelineVariants::new(device.clone(),
+                                                  &shader,
+                                                  vec![64, 128],
+                                                  |size| Spec { local_size_x: size });
+        match result {
+            Err(ComputePipelineVariantsError::UnknownLocalSize) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn local_size_limits() {
+        let max = [1024, 1024, 64];
+
+        assert!(check_local_size([64, 1, 1], max, 256).is_ok());
+        assert!(check_local_size([256, 1, 1], max, 256).is_ok());
+
+        match check_local_size([512, 1, 1], max, 256) {
+            Err(ComputePipelineVariantsError::MaxComputeWorkGroupInvocationsExceeded {
+                    invocations: 512,
+                    max: 256,
+                }) => (),
+            _ => panic!(),
+        }
+
+        match check_local_size([1, 1, 128], max, 256) {
+            Err(ComputePipelineVariantsError::MaxComputeWorkGroupSizeExceeded { .. }) => (),
+            _ => panic!(),
+        }
+
+        match check_local_size([0, 1, 1], max, 256) {
+            Err(ComputePipelineVariantsError::MaxComputeWorkGroupSizeExceeded { .. }) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn best_size() {
+        let sizes = [64, 128, 256];
+
+        assert_eq!(best_index(&sizes, 1, 0), 2);
+        assert_eq!(best_index(&sizes, 1, 32), 0);
+        assert_eq!(best_index(&sizes, 1, 100), 1);
+        assert_eq!(best_index(&sizes, 1, 192), 0);
+        assert_eq!(best_index(&sizes, 1, 1 << 20), 2);
+        assert_eq!(best_index(&sizes, 1, (1 << 20) + 1), 0);
+
+        // Sizes that are multiples of the subgroup size aren't affected by it.
+        assert_eq!(best_index(&sizes, 32, 100), 1);
+        assert_eq!(best_index(&sizes, 32, 192), 0);
+    }
+
+    #[test]
+    fn best_size_subgroups() {
+        // A workgroup of 48 invocations occupies two subgroups of 32 invocations, just like a
+        // workgroup of 64 invocations.
+        let sizes = [48, 64];
+
+        assert_eq!(best_index(&sizes, 1, 96), 0);
+        assert_eq!(best_index(&sizes, 32, 96), 1);
+        assert_eq!(best_index(&sizes, 16, 96), 0);
+        assert_eq!(best_index(&sizes, u32::max_value(), 96), 1);
+    }
+
+    #[test]
+    fn single_size() {
+        assert_eq!(best_index(&[128], 1, 0), 0);
+        assert_eq!(best_index(&[128], 32, 1000), 0);
+    }
+
+    fn entry_point(module: &ShaderModule) -> ComputeShaderEntryPoint<Spec, OutDesc> {
+        let local_size = ComputeShaderLocalSize {
+            size: [1, 1, 1],
+            specialization_ids: [Some(0), None, None],
+        };
+
+        unsafe {
+            let name = CStr::from_bytes_with_nul_unchecked(b"main\0");
+            module.compute_shader_entry_point(name, OutDesc).with_local_size(local_size)
+        }
+    }
+
+    #[repr(C)]
+    #[derive(Copy, Clone)]
+    struct Spec {
+        local_size_x: u32,
+    }
+
+    unsafe impl SpecializationConstants for Spec {
+        fn descriptors() -> &'static [SpecializationMapEntry] {
+            static DESCRIPTORS: [SpecializationMapEntry; 1] = [
+                SpecializationMapEntry { constant_id: 0, offset: 0, size: 4 },
+            ];
+            &DESCRIPTORS
+        }
+    }
+
+    // Pipeline layout of `LOCAL_SIZE_X_CS`, with a single storage buffer named `out`.
+    #[derive(Debug, Copy, Clone)]
+    struct OutDesc;
+
+    unsafe impl PipelineLayoutDesc for OutDesc {
+        fn num_sets(&self) -> usize {
+            1
+        }
+
+        fn num_bindings_in_set(&self, set: usize) -> Option<usize> {
+            if set == 0 { Some(1) } else { None }
+        }
+
+        fn descriptor(&self, set: usize, binding: usize) -> Option<DescriptorDesc> {
+            if set != 0 || binding != 0 {
+                return None;
+            }
+
+            Some(DescriptorDesc {
+                     ty: DescriptorDescTy::Buffer(DescriptorBufferDesc {
+                                                      dynamic: Some(false),
+                                                      storage: true,
+                                                      content: DescriptorBufferContentDesc::F32,
+                                                  }),
+                     array_count: 1,
+                     stages: ShaderStages::compute(),
+                     readonly: false,
+                 })
+        }
+
+        fn num_push_constants_ranges(&self) -> usize {
+            0
+        }
+
+        fn push_constants_range(&self, _: usize) -> Option<PipelineLayoutDescPcRange> {
+            None
+        }
+    }
+
+    unsafe impl PipelineLayoutDescNames for OutDesc {
+        fn descriptor_by_name(&self, name: &str) -> Option<(usize, usize)> {
+            if name == "out" { Some((0, 0)) } else { None }
+        }
+    }
+
+    /*
+        SPIR-V 1.0 module, assembled by hand. The X dimension of the workgroup is the
+        specialization constant 0.
+
+        layout(local_size_x_id = 0) in;
+        layout(set = 0, binding = 0) buffer Out { uint data[]; } out_buf;
+
+        void main() {
+            out_buf.data[gl_GlobalInvocationID.x] = gl_WorkGroupSize.x;
+        }
+    */
+    const LOCAL_SIZE_X_CS: [u8; 568] = [3, 2, 35, 7, 0, 0, 1, 0, 0, 0, 0, 0, 22, 0, 0, 0, 0, 0, 0,
+                                        0, 17, 0, 2, 0, 1, 0, 0, 0, 14, 0, 3, 0, 0, 0, 0, 0, 1, 0,
+                                        0, 0, 15, 0, 6, 0, 5, 0, 0, 0, 1, 0, 0, 0, 109, 97, 105,
+                                        110, 0, 0, 0, 0, 2, 0, 0, 0, 16, 0, 6, 0, 1, 0, 0, 0, 17, 0,
+                                        0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 71, 0, 4, 0, 2, 0,
+                                        0, 0, 11, 0, 0, 0, 28, 0, 0, 0, 71, 0, 4, 0, 3, 0, 0, 0, 1,
+                                        0, 0, 0, 0, 0, 0, 0, 71, 0, 4, 0, 4, 0, 0, 0, 11, 0, 0, 0,
+                                        25, 0, 0, 0, 71, 0, 4, 0, 5, 0, 0, 0, 6, 0, 0, 0, 4, 0, 0,
+                                        0, 72, 0, 5, 0, 6, 0, 0, 0, 0, 0, 0, 0, 35, 0, 0, 0, 0, 0,
+                                        0, 0, 71, 0, 3, 0, 6, 0, 0, 0, 3, 0, 0, 0, 71, 0, 4, 0, 7,
+                                        0, 0, 0, 34, 0, 0, 0, 0, 0, 0, 0, 71, 0, 4, 0, 7, 0, 0, 0,
+                                        33, 0, 0, 0, 0, 0, 0, 0, 19, 0, 2, 0, 8, 0, 0, 0, 33, 0, 3,
+                                        0, 9, 0, 0, 0, 8, 0, 0, 0, 21, 0, 4, 0, 10, 0, 0, 0, 32, 0,
+                                        0, 0, 0, 0, 0, 0, 23, 0, 4, 0, 11, 0, 0, 0, 10, 0, 0, 0, 3,
+                                        0, 0, 0, 32, 0, 4, 0, 12, 0, 0, 0, 1, 0, 0, 0, 11, 0, 0, 0,
+                                        59, 0, 4, 0, 12, 0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 0, 50, 0, 4,
+                                        0, 10, 0, 0, 0, 3, 0, 0, 0, 1, 0, 0, 0, 43, 0, 4, 0, 10, 0,
+                                        0, 0, 13, 0, 0, 0, 1, 0, 0, 0, 51, 0, 6, 0, 11, 0, 0, 0, 4,
+                                        0, 0, 0, 3, 0, 0, 0, 13, 0, 0, 0, 13, 0, 0, 0, 43, 0, 4, 0,
+                                        10, 0, 0, 0, 14, 0, 0, 0, 0, 0, 0, 0, 29, 0, 3, 0, 5, 0, 0,
+                                        0, 10, 0, 0, 0, 30, 0, 3, 0, 6, 0, 0, 0, 5, 0, 0, 0, 32, 0,
+                                        4, 0, 15, 0, 0, 0, 2, 0, 0, 0, 6, 0, 0, 0, 59, 0, 4, 0, 15,
+                                        0, 0, 0, 7, 0, 0, 0, 2, 0, 0, 0, 32, 0, 4, 0, 16, 0, 0, 0,
+                                        1, 0, 0, 0, 10, 0, 0, 0, 32, 0, 4, 0, 17, 0, 0, 0, 2, 0, 0,
+                                        0, 10, 0, 0, 0, 54, 0, 5, 0, 8, 0, 0, 0, 1, 0, 0, 0, 0, 0,
+                                        0, 0, 9, 0, 0, 0, 248, 0, 2, 0, 18, 0, 0, 0, 65, 0, 5, 0,
+                                        16, 0, 0, 0, 19, 0, 0, 0, 2, 0, 0, 0, 14, 0, 0, 0, 61, 0, 4,
+                                        0, 10, 0, 0, 0, 20, 0, 0, 0, 19, 0, 0, 0, 65, 0, 6, 0, 17,
+                                        0, 0, 0, 21, 0, 0, 0, 7, 0, 0, 0, 14, 0, 0, 0, 20, 0, 0, 0,
+                                        62, 0, 3, 0, 21, 0, 0, 0, 3, 0, 0, 0, 253, 0, 1, 0, 56, 0,
+                                        1, 0];
+}
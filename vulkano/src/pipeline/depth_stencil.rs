@@ -20,6 +20,9 @@
 //! value in the stencil buffer at each fragment's location. Depending on the outcome of the
 //! depth and stencil tests, the value of the stencil buffer at that location can be updated.
 
+use pipeline::raster::DepthBias;
+use pipeline::raster::DepthBiasControl;
+use pipeline::raster::Rasterization;
 use std::ops::Range;
 use std::u32;
 use vk;
@@ -39,10 +42,26 @@ pub struct DepthStencil {
     pub depth_bounds_test: DepthBounds,
 
     /// Stencil operations to use for points, lines and triangles whose front is facing the user.
-    pub stencil_front: Stencil,
+    pub stencil_front: StencilOpState,
 
     /// Stencil operations to use for triangles whose back is facing the user.
-    pub stencil_back: Stencil,
+    pub stencil_back: StencilOpState,
+
+    /// If `true`, whether the depth test is enabled is set when drawing instead of being deduced
+    /// from `depth_compare` and `depth_write`.
+    ///
+    /// This and the other `dynamic_*` fields require the `ext_extended_dynamic_state` extension.
+    pub dynamic_depth_test_enable: bool,
+
+    /// If `true`, the value of `depth_write` is ignored and is set when drawing instead.
+    pub dynamic_depth_write_enable: bool,
+
+    /// If `true`, the value of `depth_compare` is ignored and is set when drawing instead.
+    pub dynamic_depth_compare_op: bool,
+
+    /// If `true`, whether the stencil test is enabled is set when drawing instead of being
+    /// deduced from `stencil_front` and `stencil_back`.
+    pub dynamic_stencil_test_enable: bool,
 }
 
 impl DepthStencil {
@@ -56,6 +75,10 @@ impl DepthStencil {
             depth_bounds_test: DepthBounds::Disabled,
             stencil_front: Default::default(),
             stencil_back: Default::default(),
+            dynamic_depth_test_enable: false,
+            dynamic_depth_write_enable: false,
+            dynamic_depth_compare_op: false,
+            dynamic_stencil_test_enable: false,
         }
     }
 
@@ -69,8 +92,26 @@ impl DepthStencil {
             depth_bounds_test: DepthBounds::Disabled,
             stencil_front: Default::default(),
             stencil_back: Default::default(),
+            dynamic_depth_test_enable: false,
+            dynamic_depth_write_enable: false,
+            dynamic_depth_compare_op: false,
+            dynamic_stencil_test_enable: false,
         }
     }
+
+    /// Returns true if the stencil test is enabled for at least one of the faces.
+    #[inline]
+    pub fn stencil_enabled(&self) -> bool {
+        !self.stencil_front.always_keep() || !self.stencil_back.always_keep()
+    }
+
+    /// Returns true if at least one of the states of the `ext_extended_dynamic_state` extension
+    /// is dynamic.
+    #[inline]
+    pub fn uses_extended_dynamic_state(&self) -> bool {
+        self.dynamic_depth_test_enable || self.dynamic_depth_write_enable ||
+            self.dynamic_depth_compare_op || self.dynamic_stencil_test_enable
+    }
 }
 
 impl Default for DepthStencil {
@@ -80,12 +121,153 @@ impl Default for DepthStencil {
     }
 }
 
-/// Configuration of a stencil test.
-#[derive(Debug, Copy, Clone)]
-pub struct Stencil {
+/// Configuration of everything related to the depth of fragments.
+///
+/// In Vulkan, the depth state of a graphics pipeline is split between the rasterization state
+/// (depth clamping, depth clipping and depth bias) and the depth/stencil state (depth test,
+/// depth writes and depth bounds). This struct groups all of them, so that they can be set at
+/// once with `GraphicsPipelineBuilder::depth_state` and checked together when the pipeline is
+/// built. The individual `depth_*` methods of the builder modify the same state.
+///
+/// # Depth clamping and depth clipping
+///
+/// By default primitives are clipped against the near and far planes, and fragments whose
+/// depth is outside of `[0.0 ; 1.0]` are discarded. Enabling `clamp` instead clamps the depth of
+/// the fragments to this range, which also disables clipping unless `clip` is `Some(true)`.
+/// Setting `clip` to `Some` requires the `ext_depth_clip_enable` extension.
+///
+/// # Strict mode
+///
+/// Some configurations are valid but are almost always a mistake. If `strict` is true, building
+/// a pipeline fails with `DepthStateHasNoEffect` if the subpass has a depth attachment but the
+/// depth state neither tests nor writes depth values (for example a `compare` of `Always` with
+/// `write` disabled).
+#[derive(Debug, Clone)]
+pub struct DepthState {
+    /// Comparison between the depth value of each fragment and the depth value currently in the
+    /// depth buffer.
+    pub compare: Compare,
+    /// If true, the depth buffer is updated when the depth test succeeds.
+    pub write: bool,
+    /// Depth bounds test, performed in addition to the regular depth test. Requires the
+    /// `depth_bounds` feature if not disabled.
+    pub bounds: DepthBounds,
+    /// If true, the depth of the fragments is clamped to `[0.0 ; 1.0]`. Requires the
+    /// `depth_clamp` feature.
+    pub clamp: bool,
+    /// Whether primitives are clipped against the near and far planes. If `None`, they are
+    /// clipped if and only if `clamp` is false.
+    pub clip: Option<bool>,
+    /// Depth bias added to the depth of the fragments. A clamp different from 0.0 requires the
+    /// `depth_bias_clamp` feature.
+    pub bias: DepthBiasControl,
+    /// If true, whether the depth test is enabled is set when drawing.
+    ///
+    /// This and the other `dynamic_*` fields require the `ext_extended_dynamic_state` extension.
+    pub dynamic_test_enable: bool,
+    /// If true, the value of `write` is ignored and is set when drawing instead.
+    pub dynamic_write_enable: bool,
+    /// If true, the value of `compare` is ignored and is set when drawing instead.
+    pub dynamic_compare_op: bool,
+    /// Enables the additional checks described above.
+    pub strict: bool,
+}
+
+impl DepthState {
+    /// Creates a `DepthState` where the depth test is disabled, and no clamping or bias is
+    /// applied.
+    #[inline]
+    pub fn disabled() -> DepthState {
+        DepthState {
+            compare: Compare::Always,
+            write: false,
+            bounds: DepthBounds::Disabled,
+            clamp: false,
+            clip: None,
+            bias: DepthBiasControl::Disabled,
+            dynamic_test_enable: false,
+            dynamic_write_enable: false,
+            dynamic_compare_op: false,
+            strict: false,
+        }
+    }
+
+    /// Creates a `DepthState` with a `Less` depth test and depth writes enabled.
+    #[inline]
+    pub fn simple_depth_test() -> DepthState {
+        DepthState {
+            compare: Compare::Less,
+            write: true,
+            ..DepthState::disabled()
+        }
+    }
+
+    /// Extracts the depth state from a rasterization state and a depth/stencil state.
+    ///
+    /// `strict` is always false in the returned value.
+    pub fn from_parts(raster: &Rasterization, depth_stencil: &DepthStencil) -> DepthState {
+        DepthState {
+            compare: depth_stencil.depth_compare,
+            write: depth_stencil.depth_write,
+            bounds: depth_stencil.depth_bounds_test.clone(),
+            clamp: raster.depth_clamp,
+            clip: raster.depth_clip,
+            bias: raster.depth_bias,
+            dynamic_test_enable: depth_stencil.dynamic_depth_test_enable,
+            dynamic_write_enable: depth_stencil.dynamic_depth_write_enable,
+            dynamic_compare_op: depth_stencil.dynamic_depth_compare_op,
+            strict: false,
+        }
+    }
+
+    /// Writes the depth state into a rasterization state and a depth/stencil state. The other
+    /// fields of `raster` and `depth_stencil`, including the stencil state, are left untouched.
+    pub fn apply(&self, raster: &mut Rasterization, depth_stencil: &mut DepthStencil) {
+        raster.depth_clamp = self.clamp;
+        raster.depth_clip = self.clip;
+        raster.depth_bias = self.bias;
+        depth_stencil.depth_compare = self.compare;
+        depth_stencil.depth_write = self.write;
+        depth_stencil.depth_bounds_test = self.bounds.clone();
+        depth_stencil.dynamic_depth_test_enable = self.dynamic_test_enable;
+        depth_stencil.dynamic_depth_write_enable = self.dynamic_write_enable;
+        depth_stencil.dynamic_depth_compare_op = self.dynamic_compare_op;
+    }
+
+    /// Sets a constant depth bias.
+    #[inline]
+    pub fn with_bias(self, bias: DepthBias) -> DepthState {
+        DepthState { bias: DepthBiasControl::Static(bias), ..self }
+    }
+
+    /// Returns true if the depth state neither tests nor writes the depth of the fragments,
+    /// whatever the dynamic states are set to when drawing.
+    #[inline]
+    pub fn has_no_effect(&self) -> bool {
+        self.compare == Compare::Always && !self.write && self.bounds == DepthBounds::Disabled &&
+            !self.dynamic_test_enable && !self.dynamic_write_enable && !self.dynamic_compare_op
+    }
+}
+
+impl Default for DepthState {
+    #[inline]
+    fn default() -> DepthState {
+        DepthState::disabled()
+    }
+}
+
+/// Configuration of the stencil test for one face.
+///
+/// The same state can be used for both faces with `GraphicsPipelineBuilder::stencil`, or
+/// different states can be used for front-facing and back-facing primitives with
+/// `stencil_front` and `stencil_back`. For example a two-sided shadow volume is rendered by
+/// using `IncrementAndWrap` as the `depth_fail_op` of the back face and `DecrementAndWrap` as the
+/// `depth_fail_op` of the front face.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct StencilOpState {
     /// The comparison to perform between the existing stencil value in the stencil buffer, and
     /// the reference value (given by `reference`).
-    pub compare: Compare,
+    pub compare_op: Compare,
 
     /// The operation to perform when both the depth test and the stencil test passed.
     pub pass_op: StencilOp,
@@ -98,15 +280,15 @@ pub struct Stencil {
 
     /// Selects the bits of the unsigned integer stencil values participating in the stencil test.
     ///
-    /// Ignored if `compare` is `Never` or `Always`.
+    /// Ignored if `compare_op` is `Never` or `Always`.
     ///
     /// If `None`, then this value is dynamic and will need to be set when drawing. Doesn't apply
-    /// if `compare` is `Never` or `Always`.
+    /// if `compare_op` is `Never` or `Always`.
     ///
     /// Note that if this value is `Some` in `stencil_front`, it must also be `Some` in
     /// `stencil_back` (but the content can be different). If this value is `None` in
     /// `stencil_front`, then it must also be `None` in `stencil_back`. This rule doesn't apply
-    /// if `compare` is `Never` or `Always`.
+    /// if `compare_op` is `Never` or `Always`.
     pub compare_mask: Option<u32>,
 
     /// Selects the bits of the unsigned integer stencil values updated by the stencil test in the
@@ -129,11 +311,11 @@ pub struct Stencil {
     pub reference: Option<u32>,
 }
 
-impl Stencil {
+impl StencilOpState {
     /// Returns true if the stencil operation will always result in `Keep`.
     #[inline]
     pub fn always_keep(&self) -> bool {
-        match self.compare {
+        match self.compare_op {
             Compare::Always => self.pass_op == StencilOp::Keep &&
                 self.depth_fail_op == StencilOp::Keep,
             Compare::Never => self.fail_op == StencilOp::Keep,
@@ -141,13 +323,27 @@ impl Stencil {
                 self.depth_fail_op == StencilOp::Keep,
         }
     }
+
+    /// Returns true if the stencil operation can modify the content of the stencil buffer.
+    ///
+    /// A dynamic write mask is assumed to be non-zero.
+    #[inline]
+    pub fn writes(&self) -> bool {
+        !self.always_keep() && self.write_mask != Some(0)
+    }
 }
 
-impl Default for Stencil {
+/// Former name of `StencilOpState`.
+///
+/// Note that its `compare` field is now named `compare_op`.
+#[deprecated = "Use StencilOpState instead"]
+pub type Stencil = StencilOpState;
+
+impl Default for StencilOpState {
     #[inline]
-    fn default() -> Stencil {
-        Stencil {
-            compare: Compare::Never,
+    fn default() -> StencilOpState {
+        StencilOpState {
+            compare_op: Compare::Never,
             pass_op: StencilOp::Keep,
             fail_op: StencilOp::Keep,
             depth_fail_op: StencilOp::Keep,
@@ -172,6 +368,38 @@ pub enum StencilOp {
     DecrementAndWrap = vk::STENCIL_OP_DECREMENT_AND_WRAP,
 }
 
+/// Faces that a dynamic stencil state applies to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u32)]
+pub enum StencilFaceFlags {
+    /// Only the state of the front face is set.
+    Front = vk::STENCIL_FACE_FRONT_BIT,
+    /// Only the state of the back face is set.
+    Back = vk::STENCIL_FACE_BACK_BIT,
+    /// The state of both faces is set.
+    FrontAndBack = vk::STENCIL_FRONT_AND_BACK,
+}
+
+/// Value of a dynamic stencil state, for both faces.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DynamicStencilValue {
+    /// Value for points, lines and triangles whose front is facing the user.
+    pub front: u32,
+    /// Value for triangles whose back is facing the user.
+    pub back: u32,
+}
+
+impl DynamicStencilValue {
+    /// Builds a `DynamicStencilValue` that uses the same value for both faces.
+    #[inline]
+    pub fn both(value: u32) -> DynamicStencilValue {
+        DynamicStencilValue {
+            front: value,
+            back: value,
+        }
+    }
+}
+
 /// Allows you to ask the GPU to exclude fragments that are outside of a certain range.
 #[derive(Debug, Clone, PartialEq)]
 pub enum DepthBounds {
@@ -221,3 +449,80 @@ pub enum Compare {
     /// The test always passes.
     Always = vk::COMPARE_OP_ALWAYS,
 }
+
+#[cfg(test)]
+mod tests {
+    use pipeline::depth_stencil::Compare;
+    use pipeline::depth_stencil::DepthBounds;
+    use pipeline::depth_stencil::DepthState;
+    use pipeline::depth_stencil::DepthStencil;
+    use pipeline::depth_stencil::StencilOp;
+    use pipeline::depth_stencil::StencilOpState;
+    use pipeline::raster::DepthBias;
+    use pipeline::raster::Rasterization;
+
+    #[test]
+    fn stencil_writes() {
+        assert!(!StencilOpState::default().writes());
+
+        let outline = StencilOpState {
+            compare_op: Compare::Always,
+            pass_op: StencilOp::Replace,
+            reference: Some(1),
+            ..StencilOpState::default()
+        };
+        assert!(outline.writes());
+        assert!(!StencilOpState { write_mask: Some(0), ..outline }.writes());
+        assert!(StencilOpState { write_mask: None, ..outline }.writes());
+
+        let test_only = StencilOpState {
+            compare_op: Compare::NotEqual,
+            reference: Some(1),
+            ..StencilOpState::default()
+        };
+        assert!(!test_only.writes());
+    }
+
+    #[test]
+    fn depth_state_round_trip() {
+        let mut raster = Rasterization::default();
+        let mut depth_stencil = DepthStencil::disabled();
+
+        let state = DepthState {
+            clamp: true,
+            clip: Some(true),
+            bounds: DepthBounds::Fixed(0.25 .. 0.75),
+            ..DepthState::simple_depth_test()
+        }.with_bias(DepthBias {
+                        constant_factor: 1.0,
+                        clamp: 0.0,
+                        slope_factor: 2.0,
+                    });
+        state.apply(&mut raster, &mut depth_stencil);
+
+        assert!(raster.depth_clamp);
+        assert!(raster.depth_clip_enabled());
+        assert!(depth_stencil.depth_write);
+        assert_eq!(depth_stencil.depth_compare, Compare::Less);
+
+        let back = DepthState::from_parts(&raster, &depth_stencil);
+        assert_eq!(back.compare, state.compare);
+        assert_eq!(back.bounds, state.bounds);
+        assert_eq!(back.clip, Some(true));
+        assert!(!back.has_no_effect());
+    }
+
+    #[test]
+    fn depth_state_no_effect() {
+        assert!(DepthState::disabled().has_no_effect());
+        assert!(!DepthState::simple_depth_test().has_no_effect());
+        assert!(!DepthState { dynamic_write_enable: true, ..DepthState::disabled() }
+                     .has_no_effect());
+
+        // Clamping is implied to disable clipping unless specified otherwise.
+        let mut raster = Rasterization::default();
+        DepthState { clamp: true, ..DepthState::disabled() }
+            .apply(&mut raster, &mut DepthStencil::disabled());
+        assert!(!raster.depth_clip_enabled());
+    }
+}
@@ -0,0 +1,198 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Information reported by the implementation about the creation of a pipeline.
+//!
+//! When the `VK_EXT_pipeline_creation_feedback` extension is enabled, or when the device
+//! supports Vulkan 1.3, vulkano asks the implementation how the creation of each pipeline went.
+//! The result can be obtained with `creation_feedback()` on the pipeline, and tells for example
+//! whether the pipeline was found in the pipeline cache or had to be compiled, and how long it
+//! took. This is mostly useful to find the pipelines that slow down the loading of an
+//! application.
+//!
+//! Implementations are allowed not to provide any feedback, in which case the `valid` field is
+//! false and all the other fields must be ignored.
+
+use smallvec::SmallVec;
+use std::ptr;
+use std::time::Duration;
+
+use device::Device;
+use vk;
+
+/// Feedback about the creation of a pipeline as a whole.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PipelineCreationFeedback {
+    /// True if the implementation provided the feedback. If false, the other fields must be
+    /// ignored.
+    pub valid: bool,
+    /// True if the pipeline was found in the pipeline cache passed by the application, and
+    /// therefore didn't need to be compiled.
+    pub application_pipeline_cache_hit: bool,
+    /// True if the base pipeline was used to speed up the creation.
+    pub base_pipeline_acceleration: bool,
+    /// Time spent creating the pipeline.
+    pub duration: Duration,
+    /// Feedback for each shader stage of the pipeline. For a graphics pipeline, the order is
+    /// vertex, fragment, geometry, tessellation control, tessellation evaluation, and the stages
+    /// that the pipeline doesn't use are skipped. For a compute pipeline, there is only one
+    /// entry. Empty if the implementation doesn't report per-stage feedback.
+    pub stages: Vec<PipelineStageCreationFeedback>,
+}
+
+/// Feedback about the creation of one shader stage of a pipeline.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PipelineStageCreationFeedback {
+    /// True if the implementation provided the feedback. If false, the other fields must be
+    /// ignored.
+    pub valid: bool,
+    /// True if the stage was found in the pipeline cache passed by the application.
+    pub application_pipeline_cache_hit: bool,
+    /// True if the base pipeline was used to speed up the creation of the stage.
+    pub base_pipeline_acceleration: bool,
+    /// Time spent creating the stage.
+    pub duration: Duration,
+}
+
+impl PipelineStageCreationFeedback {
+    #[inline]
+    fn from_vk(raw: &vk::PipelineCreationFeedbackEXT) -> PipelineStageCreationFeedback {
+        PipelineStageCreationFeedback {
+            valid: (raw.flags & vk::PIPELINE_CREATION_FEEDBACK_VALID_BIT_EXT) != 0,
+            application_pipeline_cache_hit:
+                (raw.flags &
+                     vk::PIPELINE_CREATION_FEEDBACK_APPLICATION_PIPELINE_CACHE_HIT_BIT_EXT) != 0,
+            base_pipeline_acceleration:
+                (raw.flags & vk::PIPELINE_CREATION_FEEDBACK_BASE_PIPELINE_ACCELERATION_BIT_EXT) !=
+                    0,
+            duration: Duration::new(raw.duration / 1_000_000_000,
+                                    (raw.duration % 1_000_000_000) as u32),
+        }
+    }
+}
+
+/// Storage that the implementation writes the feedback into during the creation of a pipeline.
+pub(crate) struct FeedbackStorage {
+    pipeline: vk::PipelineCreationFeedbackEXT,
+    stages: SmallVec<[vk::PipelineCreationFeedbackEXT; 5]>,
+}
+
+impl FeedbackStorage {
+    /// Returns a storage for a pipeline with `num_stages` shader stages, or `None` if the device
+    /// can't report feedback.
+    pub fn new(device: &Device, num_stages: usize) -> Option<FeedbackStorage> {
+        if !device.effective_extensions().ext_pipeline_creation_feedback {
+            return None;
+        }
+
+        let empty = || {
+            vk::PipelineCreationFeedbackEXT {
+                flags: 0,
+                duration: 0,
+            }
+        };
+
+        Some(FeedbackStorage {
+                 pipeline: empty(),
+                 stages: (0 .. num_stages).map(|_| empty()).collect(),
+             })
+    }
+
+    /// Builds the struct to chain to the create info of the pipeline. The storage must not be
+    /// moved while the returned struct is in use.
+    pub fn create_info(&mut self) -> vk::PipelineCreationFeedbackCreateInfoEXT {
+        vk::PipelineCreationFeedbackCreateInfoEXT {
+            sType: vk::STRUCTURE_TYPE_PIPELINE_CREATION_FEEDBACK_CREATE_INFO_EXT,
+            pNext: ptr::null(),
+            pPipelineCreationFeedback: &mut self.pipeline,
+            pipelineStageCreationFeedbackCount: self.stages.len() as u32,
+            pPipelineStageCreationFeedbacks: self.stages.as_mut_ptr(),
+        }
+    }
+
+    /// Turns what the implementation wrote into a `PipelineCreationFeedback`.
+    pub fn into_feedback(self) -> PipelineCreationFeedback {
+        let pipeline = PipelineStageCreationFeedback::from_vk(&self.pipeline);
+
+        // Implementations are allowed to only fill the feedback of the pipeline.
+        let stages = if self.stages
+            .iter()
+            .any(|s| (s.flags & vk::PIPELINE_CREATION_FEEDBACK_VALID_BIT_EXT) != 0)
+        {
+            self.stages.iter().map(PipelineStageCreationFeedback::from_vk).collect()
+        } else {
+            Vec::new()
+        };
+
+        PipelineCreationFeedback {
+            valid: pipeline.valid,
+            application_pipeline_cache_hit: pipeline.application_pipeline_cache_hit,
+            base_pipeline_acceleration: pipeline.base_pipeline_acceleration,
+            duration: pipeline.duration,
+            stages: stages,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use smallvec::SmallVec;
+    use std::time::Duration;
+
+    use pipeline::feedback::FeedbackStorage;
+    use vk;
+
+    fn storage(pipeline_flags: u32, stages_flags: &[u32]) -> FeedbackStorage {
+        FeedbackStorage {
+            pipeline: vk::PipelineCreationFeedbackEXT {
+                flags: pipeline_flags,
+                duration: 1_500_000_000,
+            },
+            stages: stages_flags
+                .iter()
+                .map(|&flags| {
+                         vk::PipelineCreationFeedbackEXT {
+                             flags: flags,
+                             duration: 250,
+                         }
+                     })
+                .collect::<SmallVec<_>>(),
+        }
+    }
+
+    #[test]
+    fn decode_flags() {
+        let feedback = storage(vk::PIPELINE_CREATION_FEEDBACK_VALID_BIT_EXT |
+                                   vk::PIPELINE_CREATION_FEEDBACK_APPLICATION_PIPELINE_CACHE_HIT_BIT_EXT,
+                               &[vk::PIPELINE_CREATION_FEEDBACK_VALID_BIT_EXT,
+                                 vk::PIPELINE_CREATION_FEEDBACK_VALID_BIT_EXT |
+                                     vk::PIPELINE_CREATION_FEEDBACK_BASE_PIPELINE_ACCELERATION_BIT_EXT])
+            .into_feedback();
+
+        assert!(feedback.valid);
+        assert!(feedback.application_pipeline_cache_hit);
+        assert!(!feedback.base_pipeline_acceleration);
+        assert_eq!(feedback.duration, Duration::new(1, 500_000_000));
+
+        assert_eq!(feedback.stages.len(), 2);
+        assert!(!feedback.stages[0].base_pipeline_acceleration);
+        assert!(feedback.stages[1].base_pipeline_acceleration);
+        assert_eq!(feedback.stages[1].duration, Duration::new(0, 250));
+    }
+
+    #[test]
+    fn no_stage_feedback() {
+        let feedback = storage(vk::PIPELINE_CREATION_FEEDBACK_VALID_BIT_EXT, &[0, 0]).into_feedback();
+        assert!(feedback.valid);
+        assert!(feedback.stages.is_empty());
+
+        let feedback = storage(0, &[]).into_feedback();
+        assert!(!feedback.valid);
+    }
+}
@@ -11,29 +11,40 @@
 // to avoid duplicating code, so we hide the warnings for now
 #![allow(deprecated)]
 
+use VulkanObject;
 use descriptor::pipeline_layout::EmptyPipelineDesc;
 use descriptor::pipeline_layout::PipelineLayoutAbstract;
 use descriptor::pipeline_layout::PipelineLayoutDescNames;
 use device::Device;
+use device::DeviceOwned;
 use framebuffer::RenderPassAbstract;
+use framebuffer::RenderPassDesc;
 use framebuffer::RenderPassSubpassInterface;
 use framebuffer::Subpass;
 use pipeline::blend::AttachmentBlend;
 use pipeline::blend::AttachmentsBlend;
 use pipeline::blend::Blend;
 use pipeline::blend::LogicOp;
+use pipeline::cache::PipelineCache;
+use pipeline::depth_stencil::DepthBounds;
+use pipeline::depth_stencil::DepthState;
 use pipeline::depth_stencil::DepthStencil;
+use pipeline::depth_stencil::StencilOpState;
+use pipeline::graphics_pipeline::CreateParams;
 use pipeline::graphics_pipeline::GraphicsPipeline;
 use pipeline::graphics_pipeline::GraphicsPipelineCreationError;
-use pipeline::graphics_pipeline::GraphicsPipelineParams;
 use pipeline::graphics_pipeline::GraphicsPipelineParamsTess;
+use pipeline::graphics_pipeline::state_hash;
 use pipeline::input_assembly::InputAssembly;
 use pipeline::input_assembly::PrimitiveTopology;
 use pipeline::multisample::Multisample;
 use pipeline::raster::CullMode;
+use pipeline::raster::DepthBias;
+use pipeline::raster::DepthBiasControl;
 use pipeline::raster::FrontFace;
 use pipeline::raster::PolygonMode;
 use pipeline::raster::Rasterization;
+use pipeline::registry::StateHasher;
 use pipeline::shader::EmptyShaderInterfaceDef;
 use pipeline::shader::FragmentShaderEntryPoint;
 use pipeline::shader::GeometryShaderEntryPoint;
@@ -47,6 +58,7 @@ use pipeline::vertex::VertexDefinition;
 use pipeline::viewport::Scissor;
 use pipeline::viewport::Viewport;
 use pipeline::viewport::ViewportsState;
+use std::hash::Hasher;
 use std::sync::Arc;
 
 /// Prototype for a `GraphicsPipeline`.
@@ -76,6 +88,8 @@ pub struct GraphicsPipelineBuilder<'a,
  Rp>
 {
     vertex_input: Vdef,
+    // True if the vertex input was set explicitly or comes from an existing pipeline.
+    vertex_input_set: bool,
     vertex_shader: Option<VertexShaderEntryPoint<'a, Vsp, Vi, Vo, Vl>>,
     input_assembly: InputAssembly,
     tessellation: Option<GraphicsPipelineParamsTess<'a, Tcs, Tci, Tco, Tcl, Tes, Tei, Teo, Tel>>,
@@ -85,8 +99,11 @@ pub struct GraphicsPipelineBuilder<'a,
     multisample: Multisample,
     fragment_shader: Option<FragmentShaderEntryPoint<'a, Fs, Fi, Fo, Fl>>,
     depth_stencil: DepthStencil,
+    // True if the depth state was set in strict mode.
+    depth_strict: bool,
     blend: Blend,
     render_pass: Option<Subpass<Rp>>,
+    indirect_bindable: bool,
 }
 
 impl<'a>
@@ -117,6 +134,7 @@ impl<'a>
     pub(super) fn new() -> Self {
         GraphicsPipelineBuilder {
             vertex_input: SingleBufferDefinition::new(), // TODO: should be empty attrs instead
+            vertex_input_set: false,
             vertex_shader: None,
             input_assembly: InputAssembly::triangle_list(),
             tessellation: None,
@@ -126,8 +144,60 @@ impl<'a>
             multisample: Multisample::disabled(),
             fragment_shader: None,
             depth_stencil: DepthStencil::disabled(),
+            depth_strict: false,
             blend: Blend::pass_through(),
             render_pass: None,
+            indirect_bindable: false,
+        }
+    }
+}
+
+impl<'a, Vdef, Rp>
+    GraphicsPipelineBuilder<'a,
+                            Vdef,
+                            (),
+                            (),
+                            (),
+                            (),
+                            (),
+                            EmptyShaderInterfaceDef,
+                            EmptyShaderInterfaceDef,
+                            EmptyPipelineDesc,
+                            (),
+                            EmptyShaderInterfaceDef,
+                            EmptyShaderInterfaceDef,
+                            EmptyPipelineDesc,
+                            (),
+                            EmptyShaderInterfaceDef,
+                            EmptyShaderInterfaceDef,
+                            EmptyPipelineDesc,
+                            (),
+                            EmptyShaderInterfaceDef,
+                            EmptyShaderInterfaceDef,
+                            EmptyPipelineDesc,
+                            Rp> {
+    /// Builds a builder without any shader, with the states of an existing pipeline.
+    pub(super) fn with_state(vertex_input: Vdef, input_assembly: InputAssembly,
+                             viewport: ViewportsState, raster: Rasterization,
+                             multisample: Multisample, depth_stencil: DepthStencil, blend: Blend,
+                             indirect_bindable: bool, subpass: Subpass<Rp>)
+                             -> Self {
+        GraphicsPipelineBuilder {
+            vertex_input: vertex_input,
+            vertex_input_set: true,
+            vertex_shader: None,
+            input_assembly: input_assembly,
+            tessellation: None,
+            geometry_shader: None,
+            viewport: Some(viewport),
+            raster: raster,
+            multisample: multisample,
+            fragment_shader: None,
+            depth_stencil: depth_stencil,
+            depth_strict: false,
+            blend: blend,
+            render_pass: Some(subpass),
+            indirect_bindable: indirect_bindable,
         }
     }
 }
@@ -198,45 +268,136 @@ impl<'a,
           Rp: RenderPassAbstract + RenderPassSubpassInterface<Fo>
 {
     /// Builds the graphics pipeline.
+    ///
+    /// Returns `GraphicsPipelineCreationError::IncompleteDefinition` listing all the required
+    /// states that haven't been set, if any.
     // TODO: replace Box<PipelineLayoutAbstract> with a PipelineUnion struct without template params
+    #[inline]
     pub fn build(self, device: Arc<Device>)
                  -> Result<GraphicsPipeline<Vdef, Box<PipelineLayoutAbstract + Send + Sync>, Rp>,
                            GraphicsPipelineCreationError> {
-        // TODO: return errors instead of panicking if missing param
-        GraphicsPipeline::with_tessellation_and_geometry(device,
-                                                         GraphicsPipelineParams {
-                                                             vertex_input: self.vertex_input,
-                                                             vertex_shader:
-                                                                 self.vertex_shader
-                                                                     .expect("Vertex shader not \
-                                                                              specified in the \
-                                                                              builder"),
-                                                             input_assembly: self.input_assembly,
-                                                             tessellation: self.tessellation,
-                                                             geometry_shader: self.geometry_shader,
-                                                             viewport:
-                                                                 self.viewport
-                                                                     .expect("Viewport state not \
-                                                                              specified in the \
-                                                                              builder"),
-                                                             raster: self.raster,
-                                                             multisample: self.multisample,
-                                                             fragment_shader:
-                                                                 self.fragment_shader
-                                                                     .expect("Fragment shader not \
-                                                                              specified in the \
-                                                                              builder"),
-                                                             depth_stencil: self.depth_stencil,
-                                                             blend: self.blend,
-                                                             render_pass:
-                                                                 self.render_pass
-                                                                     .expect("Render pass not \
-                                                                              specified in the \
-                                                                              builder"),
-                                                         })
-    }
-
-    // TODO: add build_with_cache method
+        self.build_inner(device, None)
+    }
+
+    /// Same as `build`, but passes a pipeline cache to the implementation, which can then skip
+    /// the compilation of the pipeline if it is already in the cache.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `cache` doesn't belong to `device`.
+    ///
+    #[inline]
+    pub fn build_with_cache(self, device: Arc<Device>, cache: &PipelineCache)
+                            -> Result<GraphicsPipeline<Vdef,
+                                                       Box<PipelineLayoutAbstract + Send + Sync>,
+                                                       Rp>,
+                                      GraphicsPipelineCreationError> {
+        assert_eq!(cache.device().internal_object(), device.internal_object());
+        self.build_inner(device, Some(cache))
+    }
+
+    fn build_inner(self, device: Arc<Device>, cache: Option<&PipelineCache>)
+                   -> Result<GraphicsPipeline<Vdef, Box<PipelineLayoutAbstract + Send + Sync>, Rp>,
+                             GraphicsPipelineCreationError> {
+        let vertex_input_set = self.vertex_input_set ||
+            match self.vertex_shader {
+                // The default vertex input is only suitable for shaders without any input.
+                Some(ref vs) => self.vertex_input.definition(vs.input_definition()).is_ok(),
+                None => true,
+            };
+
+        let missing = missing_states(self.vertex_shader.is_some(),
+                                     vertex_input_set,
+                                     self.viewport.is_some(),
+                                     self.fragment_shader.is_some(),
+                                     self.raster.rasterizer_discard,
+                                     self.render_pass.is_some());
+        if !missing.is_empty() {
+            return Err(GraphicsPipelineCreationError::IncompleteDefinition { missing: missing });
+        }
+
+        // `render_pass_index` doesn't check the index of the subpass, and the methods of
+        // `Subpass` panic if it is out of range.
+        {
+            let subpass = self.render_pass.as_ref().unwrap();
+            let available = subpass.render_pass().num_subpasses() as u32;
+            if subpass.index() >= available {
+                return Err(GraphicsPipelineCreationError::InvalidSubpass {
+                               requested: subpass.index(),
+                               available: available,
+                           });
+            }
+        }
+
+        if self.depth_strict && self.render_pass.as_ref().unwrap().has_depth() &&
+            DepthState::from_parts(&self.raster, &self.depth_stencil).has_no_effect()
+        {
+            return Err(GraphicsPipelineCreationError::DepthStateHasNoEffect);
+        }
+
+        GraphicsPipeline::with_cache_inner(device,
+                                           CreateParams {
+                                               vertex_input: self.vertex_input,
+                                               vertex_shader:
+                                                   self.vertex_shader.unwrap(),
+                                               input_assembly: self.input_assembly,
+                                               tessellation: self.tessellation,
+                                               geometry_shader: self.geometry_shader,
+                                               viewport: self.viewport.unwrap(),
+                                               raster: self.raster,
+                                               multisample: self.multisample,
+                                               fragment_shader: self.fragment_shader,
+                                               depth_stencil: self.depth_stencil,
+                                               blend: self.blend,
+                                               render_pass: self.render_pass.unwrap(),
+                                           },
+                                           cache,
+                                           self.indirect_bindable)
+    }
+
+    /// Returns a hash of all the parameters of the builder.
+    ///
+    /// Builders with the same parameters return the same hash, which can be used as the key of
+    /// a `PipelineRegistry`. Shaders are identified by the hash of their SPIR-V code and by the
+    /// name of their entry point, and the render pass by its description. Since the hash is
+    /// computed with a `StateHasher`, it doesn't change between runs of the same program.
+    pub fn state_hash(&self) -> u64 {
+        let mut h = StateHasher::new();
+
+        state_hash::hash_shader(&mut h,
+                                self.vertex_shader.as_ref().map(|s| (s.module(), s.name())));
+        if let Some(ref vs) = self.vertex_shader {
+            match self.vertex_input.definition(vs.input_definition()) {
+                Ok((buffers, attribs)) => {
+                    h.write_u8(1);
+                    state_hash::hash_vertex_definition(&mut h, buffers, attribs);
+                },
+                Err(_) => h.write_u8(0),
+            }
+        }
+
+        state_hash::hash_input_assembly(&mut h, &self.input_assembly);
+        {
+            let tcs = self.tessellation.as_ref().map(|t| &t.tessellation_control_shader);
+            let tes = self.tessellation.as_ref().map(|t| &t.tessellation_evaluation_shader);
+            state_hash::hash_shader(&mut h, tcs.map(|s| (s.module(), s.name())));
+            state_hash::hash_shader(&mut h, tes.map(|s| (s.module(), s.name())));
+        }
+        state_hash::hash_shader(&mut h,
+                                self.geometry_shader.as_ref().map(|s| (s.module(), s.name())));
+        state_hash::hash_viewports(&mut h, self.viewport.as_ref());
+        state_hash::hash_raster(&mut h, &self.raster);
+        state_hash::hash_multisample(&mut h, &self.multisample);
+        state_hash::hash_shader(&mut h,
+                                self.fragment_shader.as_ref().map(|s| (s.module(), s.name())));
+        state_hash::hash_depth_stencil(&mut h, &self.depth_stencil);
+        state_hash::hash_blend(&mut h, &self.blend);
+        state_hash::hash_subpass(&mut h,
+                                 self.render_pass.as_ref().map(|s| (s.render_pass(), s.index())));
+        h.write_u8(self.indirect_bindable as u8);
+
+        h.finish()
+    }
 }
 
 impl<'a,
@@ -315,6 +476,7 @@ impl<'a,
                                                       Rp> {
         GraphicsPipelineBuilder {
             vertex_input: vertex_input,
+            vertex_input_set: true,
             vertex_shader: self.vertex_shader,
             input_assembly: self.input_assembly,
             tessellation: self.tessellation,
@@ -324,8 +486,10 @@ impl<'a,
             multisample: self.multisample,
             fragment_shader: self.fragment_shader,
             depth_stencil: self.depth_stencil,
+            depth_strict: self.depth_strict,
             blend: self.blend,
             render_pass: self.render_pass,
+            indirect_bindable: self.indirect_bindable,
         }
     }
 
@@ -392,6 +556,7 @@ impl<'a,
                                                                    Rp> {
         GraphicsPipelineBuilder {
             vertex_input: self.vertex_input,
+            vertex_input_set: self.vertex_input_set,
             vertex_shader: Some(shader),
             input_assembly: self.input_assembly,
             tessellation: self.tessellation,
@@ -401,8 +566,10 @@ impl<'a,
             multisample: self.multisample,
             fragment_shader: self.fragment_shader,
             depth_stencil: self.depth_stencil,
+            depth_strict: self.depth_strict,
             blend: self.blend,
             render_pass: self.render_pass,
+            indirect_bindable: self.indirect_bindable,
         }
     }
 
@@ -533,6 +700,7 @@ impl<'a,
     {
         GraphicsPipelineBuilder {
             vertex_input: self.vertex_input,
+            vertex_input_set: self.vertex_input_set,
             vertex_shader: self.vertex_shader,
             input_assembly: self.input_assembly,
             tessellation: Some(GraphicsPipelineParamsTess {
@@ -545,8 +713,10 @@ impl<'a,
             multisample: self.multisample,
             fragment_shader: self.fragment_shader,
             depth_stencil: self.depth_stencil,
+            depth_strict: self.depth_strict,
             blend: self.blend,
             render_pass: self.render_pass,
+            indirect_bindable: self.indirect_bindable,
         }
     }
 
@@ -588,6 +758,7 @@ impl<'a,
                                                                      Rp> {
         GraphicsPipelineBuilder {
             vertex_input: self.vertex_input,
+            vertex_input_set: self.vertex_input_set,
             vertex_shader: self.vertex_shader,
             input_assembly: self.input_assembly,
             tessellation: self.tessellation,
@@ -597,8 +768,10 @@ impl<'a,
             multisample: self.multisample,
             fragment_shader: self.fragment_shader,
             depth_stencil: self.depth_stencil,
+            depth_strict: self.depth_strict,
             blend: self.blend,
             render_pass: self.render_pass,
+            indirect_bindable: self.indirect_bindable,
         }
     }
 
@@ -671,18 +844,56 @@ impl<'a,
     /// If false, fragments whose depth is outside of this range will be discarded before the
     /// fragment shader even runs.
     #[inline]
-    pub fn depth_clamp(mut self, clamp: bool) -> Self {
-        self.raster.depth_clamp = clamp;
-        self
+    pub fn depth_clamp(self, clamp: bool) -> Self {
+        self.update_depth_state(|state| state.clamp = clamp)
+    }
+
+    /// Sets whether primitives are clipped against the near and far planes, independently of
+    /// whether depth clamping is enabled.
+    ///
+    /// The `ext_depth_clip_enable` extension must be enabled on the device, and the physical device
+    /// must support its `depthClipEnable` feature. See `Device::depth_clip_enable`.
+    #[inline]
+    pub fn depth_clip(self, clip: bool) -> Self {
+        self.update_depth_state(|state| state.clip = Some(clip))
+    }
+
+    /// Adds a constant depth bias to the depth of the fragments.
+    ///
+    /// The bias is `constant_factor` times the smallest resolvable depth difference plus
+    /// `slope_factor` times the depth slope of the primitive, clamped to `clamp` if it is not
+    /// 0.0. The `depth_bias_clamp` feature must be enabled in order to use a non-zero `clamp`.
+    #[inline]
+    pub fn depth_bias(self, constant_factor: f32, clamp: f32, slope_factor: f32) -> Self {
+        self.update_depth_state(|state| {
+            state.bias = DepthBiasControl::Static(DepthBias {
+                                                      constant_factor: constant_factor,
+                                                      clamp: clamp,
+                                                      slope_factor: slope_factor,
+                                                  })
+        })
     }
 
-    // TODO: this won't work correctly
-    /*/// Disables the fragment shader stage.
+    /// Sets the depth bias as dynamic. It must then be passed when drawing, with the `depth_bias`
+    /// field of `DynamicState`.
+    #[inline]
+    pub fn depth_bias_dynamic(self) -> Self {
+        self.update_depth_state(|state| state.bias = DepthBiasControl::Dynamic)
+    }
+
+    /// Disables the depth bias. This is the default.
+    #[inline]
+    pub fn depth_bias_disabled(self) -> Self {
+        self.update_depth_state(|state| state.bias = DepthBiasControl::Disabled)
+    }
+
+    /// Discards the primitives right before rasterization. Nothing is drawn, and the fragment
+    /// shader is then optional.
     #[inline]
     pub fn rasterizer_discard(mut self) -> Self {
-        self.rasterization.rasterizer_discard. = true;
+        self.raster.rasterizer_discard = true;
         self
-    }*/
+    }
 
     /// Sets the front-facing faces to couner-clockwise faces. This is the default.
     ///
@@ -773,9 +984,16 @@ impl<'a,
         self
     }
 
-    // TODO: missing DepthBiasControl
-
-    // TODO: missing Multisample
+    /// Sets the multisample state.
+    ///
+    /// The number of rasterization samples must match the number of samples of the attachments
+    /// of the subpass, otherwise building the pipeline returns an error. When drawing with
+    /// `MsaaAttachments`, use the state returned by its `multisample()` method.
+    #[inline]
+    pub fn multisample(mut self, multisample: Multisample) -> Self {
+        self.multisample = multisample;
+        self
+    }
 
     /// Sets the fragment shader to use.
     ///
@@ -810,6 +1028,7 @@ impl<'a,
                                                                      Rp> {
         GraphicsPipelineBuilder {
             vertex_input: self.vertex_input,
+            vertex_input_set: self.vertex_input_set,
             vertex_shader: self.vertex_shader,
             input_assembly: self.input_assembly,
             tessellation: self.tessellation,
@@ -819,8 +1038,10 @@ impl<'a,
             multisample: self.multisample,
             fragment_shader: Some(shader),
             depth_stencil: self.depth_stencil,
+            depth_strict: self.depth_strict,
             blend: self.blend,
             render_pass: self.render_pass,
+            indirect_bindable: self.indirect_bindable,
         }
     }
 
@@ -851,10 +1072,169 @@ impl<'a,
         self
     }
 
+    /// Sets all the states related to the depth of fragments at once.
+    ///
+    /// This overrides the depth clamping, clipping and bias, the depth test and the depth bounds
+    /// test, but leaves the stencil test untouched. See the documentation of `DepthState` for
+    /// the checks that are performed when the pipeline is built.
+    #[inline]
+    pub fn depth_state(mut self, state: DepthState) -> Self {
+        state.apply(&mut self.raster, &mut self.depth_stencil);
+        self.depth_strict = state.strict;
+        self
+    }
+
+    // Modifies the current depth state.
+    #[inline]
+    fn update_depth_state<F>(self, f: F) -> Self
+        where F: FnOnce(&mut DepthState)
+    {
+        let mut state = DepthState::from_parts(&self.raster, &self.depth_stencil);
+        state.strict = self.depth_strict;
+        f(&mut state);
+        self.depth_state(state)
+    }
+
     /// Sets whether the depth buffer will be written.
     #[inline]
-    pub fn depth_write(mut self, write: bool) -> Self {
-        self.depth_stencil.depth_write = write;
+    pub fn depth_write(self, write: bool) -> Self {
+        self.update_depth_state(|state| state.write = write)
+    }
+
+    /// Enables the depth bounds test. Fragments whose depth value in the depth buffer is outside
+    /// of the range `min ..= max` are discarded.
+    ///
+    /// Both values must be between 0.0 and 1.0, and the `depth_bounds` feature must be enabled
+    /// on the device.
+    #[inline]
+    pub fn depth_bounds(self, min: f32, max: f32) -> Self {
+        self.update_depth_state(|state| state.bounds = DepthBounds::Fixed(min .. max))
+    }
+
+    /// Enables the depth bounds test, with the range of depth values to be passed when drawing.
+    ///
+    /// The `depth_bounds` feature must be enabled on the device.
+    #[inline]
+    pub fn depth_bounds_dynamic(self) -> Self {
+        self.update_depth_state(|state| state.bounds = DepthBounds::Dynamic)
+    }
+
+    /// Disables the depth bounds test. This is the default.
+    #[inline]
+    pub fn depth_bounds_disabled(self) -> Self {
+        self.update_depth_state(|state| state.bounds = DepthBounds::Disabled)
+    }
+
+    /// Sets the depth test enable as dynamic. Whether the depth test is enabled must then be
+    /// passed when drawing.
+    ///
+    /// The `ext_extended_dynamic_state` extension must be enabled on the device.
+    #[inline]
+    pub fn depth_test_enable_dynamic(self) -> Self {
+        self.update_depth_state(|state| state.dynamic_test_enable = true)
+    }
+
+    /// Sets the depth write enable as dynamic. Whether the depth buffer will be written must then
+    /// be passed when drawing.
+    ///
+    /// The `ext_extended_dynamic_state` extension must be enabled on the device.
+    #[inline]
+    pub fn depth_write_enable_dynamic(self) -> Self {
+        self.update_depth_state(|state| state.dynamic_write_enable = true)
+    }
+
+    /// Sets the depth compare operation as dynamic. It must then be passed when drawing.
+    ///
+    /// The `ext_extended_dynamic_state` extension must be enabled on the device.
+    #[inline]
+    pub fn depth_compare_op_dynamic(self) -> Self {
+        self.update_depth_state(|state| state.dynamic_compare_op = true)
+    }
+
+    /// Sets the stencil test enable as dynamic. Whether the stencil test is enabled must then be
+    /// passed when drawing.
+    ///
+    /// The `ext_extended_dynamic_state` extension must be enabled on the device.
+    #[inline]
+    pub fn stencil_test_enable_dynamic(mut self) -> Self {
+        self.depth_stencil.dynamic_stencil_test_enable = true;
+        self
+    }
+
+    /// Sets the stencil test configuration of both the front-facing and back-facing primitives.
+    ///
+    /// The subpass must have an attachment with a stencil aspect, unless the state always keeps
+    /// the stencil value.
+    /// If the state can modify the stencil value, this attachment must not be in the
+    /// `DepthStencilReadOnlyOptimal` layout.
+    #[inline]
+    pub fn stencil(mut self, state: StencilOpState) -> Self {
+        self.depth_stencil.stencil_front = state;
+        self.depth_stencil.stencil_back = state;
+        self
+    }
+
+    /// Sets the stencil test configuration of points, lines and front-facing triangles.
+    ///
+    /// The dynamic compare mask, write mask and reference must be the same for both faces. In
+    /// other words, if one of them is `None` in the front state it must also be `None` in the
+    /// back state.
+    #[inline]
+    pub fn stencil_front(mut self, state: StencilOpState) -> Self {
+        self.depth_stencil.stencil_front = state;
+        self
+    }
+
+    /// Sets the stencil test configuration of back-facing triangles.
+    ///
+    /// See the documentation of `stencil_front`.
+    #[inline]
+    pub fn stencil_back(mut self, state: StencilOpState) -> Self {
+        self.depth_stencil.stencil_back = state;
+        self
+    }
+
+    /// Sets the stencil compare mask of both faces as dynamic. It must then be passed when
+    /// drawing, with the `stencil_compare_mask` field of `DynamicState`.
+    ///
+    /// This overwrites the `compare_mask` of the states passed to `stencil`, `stencil_front` and
+    /// `stencil_back`, so it must be called after them.
+    #[inline]
+    pub fn stencil_compare_mask_dynamic(mut self) -> Self {
+        self.depth_stencil.stencil_front.compare_mask = None;
+        self.depth_stencil.stencil_back.compare_mask = None;
+        self
+    }
+
+    /// Sets the stencil write mask of both faces as dynamic. It must then be passed when
+    /// drawing, with the `stencil_write_mask` field of `DynamicState`.
+    ///
+    /// This overwrites the `write_mask` of the states passed to `stencil`, `stencil_front` and
+    /// `stencil_back`, so it must be called after them.
+    #[inline]
+    pub fn stencil_write_mask_dynamic(mut self) -> Self {
+        self.depth_stencil.stencil_front.write_mask = None;
+        self.depth_stencil.stencil_back.write_mask = None;
+        self
+    }
+
+    /// Sets the stencil reference of both faces as dynamic. It must then be passed when
+    /// drawing, with the `stencil_reference` field of `DynamicState`.
+    ///
+    /// This overwrites the `reference` of the states passed to `stencil`, `stencil_front` and
+    /// `stencil_back`, so it must be called after them.
+    #[inline]
+    pub fn stencil_reference_dynamic(mut self) -> Self {
+        self.depth_stencil.stencil_front.reference = None;
+        self.depth_stencil.stencil_back.reference = None;
+        self
+    }
+
+    /// Disables the stencil test. This is the default.
+    #[inline]
+    pub fn stencil_disabled(mut self) -> Self {
+        self.depth_stencil.stencil_front = Default::default();
+        self.depth_stencil.stencil_back = Default::default();
         self
     }
 
@@ -918,6 +1298,17 @@ impl<'a,
         self
     }
 
+    /// Allows the pipeline to be used with `AutoCommandBufferBuilder::execute_generated_commands`.
+    ///
+    /// The `nv_device_generated_commands` extension must be enabled on the device. Creating the
+    /// pipeline with this flag may make it slower to create or to use, so only enable it for
+    /// pipelines that are actually used with generated commands.
+    #[inline]
+    pub fn indirect_bindable(mut self) -> Self {
+        self.indirect_bindable = true;
+        self
+    }
+
     /// Sets the render pass subpass to use.
     #[inline]
     pub fn render_pass<Rp2>(self, subpass: Subpass<Rp2>)
@@ -946,6 +1337,7 @@ impl<'a,
                                                        Rp2> {
         GraphicsPipelineBuilder {
             vertex_input: self.vertex_input,
+            vertex_input_set: self.vertex_input_set,
             vertex_shader: self.vertex_shader,
             input_assembly: self.input_assembly,
             tessellation: self.tessellation,
@@ -955,10 +1347,73 @@ impl<'a,
             multisample: self.multisample,
             fragment_shader: self.fragment_shader,
             depth_stencil: self.depth_stencil,
+            depth_strict: self.depth_strict,
             blend: self.blend,
             render_pass: Some(subpass),
+            indirect_bindable: self.indirect_bindable,
         }
     }
+
+    /// Same as `render_pass`, but takes the render pass and the index of the subpass separately.
+    ///
+    /// The index is checked when building the pipeline, which returns `InvalidSubpass` if it is
+    /// out of range of the render pass.
+    #[inline]
+    pub fn render_pass_index<Rp2>(self, render_pass: Rp2, subpass: u32)
+                                  -> GraphicsPipelineBuilder<'a,
+                                                             Vdef,
+                                                             Vsp,
+                                                             Vi,
+                                                             Vo,
+                                                             Vl,
+                                                             Tcs,
+                                                             Tci,
+                                                             Tco,
+                                                             Tcl,
+                                                             Tes,
+                                                             Tei,
+                                                             Teo,
+                                                             Tel,
+                                                             Gs,
+                                                             Gi,
+                                                             Go,
+                                                             Gl,
+                                                             Fs,
+                                                             Fi,
+                                                             Fo,
+                                                             Fl,
+                                                             Rp2>
+        where Rp2: RenderPassDesc
+    {
+        self.render_pass(Subpass::from_unchecked(render_pass, subpass))
+    }
+}
+
+/// Returns the names of the required states of a builder that are missing. Each parameter is
+/// true if the corresponding state is set, except `rasterizer_discard` which is true if
+/// rasterization is discarded. The fragment shader isn't required in that case.
+fn missing_states(vertex_shader: bool, vertex_input: bool, viewport: bool, fragment_shader: bool,
+                  rasterizer_discard: bool, render_pass: bool)
+                  -> Vec<&'static str> {
+    let mut missing = Vec::new();
+
+    if !vertex_shader {
+        missing.push("vertex shader");
+    }
+    if !vertex_input {
+        missing.push("vertex input");
+    }
+    if !viewport {
+        missing.push("viewport state");
+    }
+    if !fragment_shader && !rasterizer_discard {
+        missing.push("fragment shader");
+    }
+    if !render_pass {
+        missing.push("render pass");
+    }
+
+    missing
 }
 
 // TODO:
@@ -979,6 +1434,7 @@ impl<'a, Vdef, Vsp, Vi, Vo, Vl, Tcs, Tci, Tco, Tcl, Tes, Tei, Teo, Tel, Gs, Gi,
     fn clone(&self) -> Self {
         GraphicsPipelineBuilder {
             vertex_input: self.vertex_input.clone(),
+            vertex_input_set: self.vertex_input_set,
             vertex_shader: self.vertex_shader,
             input_assembly: self.input_assembly,
             tessellation: self.tessellation,
@@ -988,8 +1444,846 @@ impl<'a, Vdef, Vsp, Vi, Vo, Vl, Tcs, Tci, Tco, Tcl, Tes, Tei, Teo, Tel, Gs, Gi,
             multisample: self.multisample,
             fragment_shader: self.fragment_shader,
             depth_stencil: self.depth_stencil,
+            depth_strict: self.depth_strict,
             blend: self.blend,
             render_pass: self.render_pass,
+            indirect_bindable: self.indirect_bindable,
         }
     }
 }*/
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+    use std::ffi::CStr;
+    use std::iter;
+    use std::sync::Arc;
+
+    use descriptor::pipeline_layout::EmptyPipelineDesc;
+    use format::Format;
+    use framebuffer::LayoutAttachmentDescription;
+    use framebuffer::LayoutPassDependencyDescription;
+    use framebuffer::LayoutPassDescription;
+    use framebuffer::LoadOp;
+    use framebuffer::MsaaAttachments;
+    use framebuffer::RenderPass;
+    use framebuffer::RenderPassDesc;
+    use framebuffer::StoreOp;
+    use framebuffer::Subpass;
+    use image::ImageLayout;
+    use instance::Features;
+    use pipeline::GraphicsPipeline;
+    use pipeline::GraphicsPipelineCreationError;
+    use pipeline::cache::PipelineCache;
+    use pipeline::depth_stencil::Compare;
+    use pipeline::depth_stencil::DepthState;
+    use pipeline::depth_stencil::StencilOp;
+    use pipeline::depth_stencil::StencilOpState;
+    use pipeline::graphics_pipeline::LastPreRasterizationStage;
+    use pipeline::graphics_pipeline::check_point_size;
+    use pipeline::input_assembly::PrimitiveTopology;
+    use pipeline::shader::EmptyShaderInterfaceDef;
+    use pipeline::shader::PointSizeOutput;
+    use pipeline::shader::ShaderInterfaceDef;
+    use pipeline::shader::ShaderInterfaceDefEntry;
+    use pipeline::shader::ShaderModule;
+    use pipeline::vertex::SingleBufferDefinition;
+    use tests::BASIC_FS;
+    use tests::FULL_SCREEN_VS;
+    use super::missing_states;
+
+    // Creates a render pass with one color attachment.
+    macro_rules! color_render_pass {
+        ($device:expr) => (
+            Arc::new(single_pass_renderpass!($device.clone(),
+                attachments: {
+                    color: { load: Clear, store: Store, format: Format::R8G8B8A8Unorm, samples: 1, }
+                },
+                pass: { color: [color], depth_stencil: {} }
+            ).unwrap())
+        )
+    }
+
+    // Starts a builder that draws a full-screen triangle in the first subpass of `render_pass`.
+    macro_rules! basic_builder {
+        ($vs:expr, $fs:expr, $render_pass:expr) => ({
+            let (vs_main, fs_main) = unsafe {
+                let name = CStr::from_bytes_with_nul_unchecked(b"main\0");
+                ($vs.vertex_shader_entry_point::<(), _, _, _>(name,
+                                                              EmptyShaderInterfaceDef,
+                                                              EmptyShaderInterfaceDef,
+                                                              EmptyPipelineDesc),
+                 $fs.fragment_shader_entry_point::<(), _, _, _>(name,
+                                                                EmptyShaderInterfaceDef,
+                                                                EmptyShaderInterfaceDef,
+                                                                EmptyPipelineDesc))
+            };
+
+            GraphicsPipeline::start()
+                .vertex_shader(vs_main, ())
+                .triangle_list()
+                .viewports_dynamic_scissors_irrelevant(1)
+                .fragment_shader(fs_main, ())
+                .render_pass(Subpass::from($render_pass.clone(), 0).unwrap())
+        })
+    }
+
+    // Render pass with a color attachment and a depth-stencil attachment in the
+    // `DepthStencilReadOnlyOptimal` layout.
+    #[derive(Debug)]
+    struct ReadOnlyStencilDesc;
+
+    unsafe impl RenderPassDesc for ReadOnlyStencilDesc {
+        fn num_attachments(&self) -> usize {
+            2
+        }
+
+        fn attachment_desc(&self, num: usize) -> Option<LayoutAttachmentDescription> {
+            let (format, layout) = match num {
+                0 => (Format::R8G8B8A8Unorm, ImageLayout::ColorAttachmentOptimal),
+                1 => (Format::D24Unorm_S8Uint, ImageLayout::DepthStencilReadOnlyOptimal),
+                _ => return None,
+            };
+
+            Some(LayoutAttachmentDescription {
+                     format: format,
+                     samples: 1,
+                     load: LoadOp::Load,
+                     store: StoreOp::Store,
+                     stencil_load: LoadOp::Load,
+                     stencil_store: StoreOp::Store,
+                     initial_layout: layout,
+                     final_layout: layout,
+                 })
+        }
+
+        fn num_subpasses(&self) -> usize {
+            1
+        }
+
+        fn subpass_desc(&self, num: usize) -> Option<LayoutPassDescription> {
+            if num != 0 {
+                return None;
+            }
+
+            Some(LayoutPassDescription {
+                     color_attachments: vec![(0, ImageLayout::ColorAttachmentOptimal)],
+                     depth_stencil: Some((1, ImageLayout::DepthStencilReadOnlyOptimal)),
+                     input_attachments: vec![],
+                     resolve_attachments: vec![],
+                     preserve_attachments: vec![],
+                 })
+        }
+
+        fn num_dependencies(&self) -> usize {
+            0
+        }
+
+        fn dependency_desc(&self, _: usize) -> Option<LayoutPassDependencyDescription> {
+            None
+        }
+    }
+
+    // A vertex shader input made of a single `vec2 position`.
+    #[derive(Debug, Copy, Clone)]
+    struct PositionInput;
+
+    unsafe impl ShaderInterfaceDef for PositionInput {
+        type Iter = iter::Once<ShaderInterfaceDefEntry>;
+
+        fn elements(&self) -> Self::Iter {
+            iter::once(ShaderInterfaceDefEntry {
+                           location: 0 .. 1,
+                           format: Format::R32G32Sfloat,
+                           name: Some(Cow::Borrowed("position")),
+                       })
+        }
+    }
+
+    #[test]
+    fn complete() {
+        assert!(missing_states(true, true, true, true, false, true).is_empty());
+    }
+
+    #[test]
+    fn each_missing() {
+        assert_eq!(missing_states(false, true, true, true, false, true), vec!["vertex shader"]);
+        assert_eq!(missing_states(true, false, true, true, false, true), vec!["vertex input"]);
+        assert_eq!(missing_states(true, true, false, true, false, true), vec!["viewport state"]);
+        assert_eq!(missing_states(true, true, true, false, false, true), vec!["fragment shader"]);
+        assert_eq!(missing_states(true, true, true, true, false, false), vec!["render pass"]);
+    }
+
+    #[test]
+    fn several_missing() {
+        assert_eq!(missing_states(false, true, false, true, false, false),
+                   vec!["vertex shader", "viewport state", "render pass"]);
+        assert_eq!(missing_states(false, false, false, false, false, false),
+                   vec!["vertex shader",
+                        "vertex input",
+                        "viewport state",
+                        "fragment shader",
+                        "render pass"]);
+    }
+
+    #[test]
+    fn discard_without_fragment_shader() {
+        assert!(missing_states(true, true, true, false, true, true).is_empty());
+        assert_eq!(missing_states(false, true, true, false, true, true), vec!["vertex shader"]);
+    }
+
+    #[test]
+    fn build_incomplete() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let vs = unsafe { ShaderModule::new(device.clone(), &FULL_SCREEN_VS) }.unwrap();
+        let fs = unsafe { ShaderModule::new(device.clone(), &BASIC_FS) }.unwrap();
+        let render_pass = color_render_pass!(device);
+
+        let (vs_main, fs_main) = unsafe {
+            let name = CStr::from_bytes_with_nul_unchecked(b"main\0");
+            (vs.vertex_shader_entry_point::<(), _, _, _>(name,
+                                                         EmptyShaderInterfaceDef,
+                                                         EmptyShaderInterfaceDef,
+                                                         EmptyPipelineDesc),
+             fs.fragment_shader_entry_point::<(), _, _, _>(name,
+                                                           EmptyShaderInterfaceDef,
+                                                           EmptyShaderInterfaceDef,
+                                                           EmptyPipelineDesc))
+        };
+
+        let result = GraphicsPipeline::start()
+            .vertex_shader(vs_main, ())
+            .triangle_list()
+            .viewports_dynamic_scissors_irrelevant(1)
+            .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+            .build(device.clone());
+        match result {
+            Err(GraphicsPipelineCreationError::IncompleteDefinition { missing }) => {
+                assert_eq!(missing, vec!["fragment shader"]);
+            },
+            _ => panic!(),
+        }
+
+        let result = GraphicsPipeline::start()
+            .vertex_shader(vs_main, ())
+            .triangle_list()
+            .fragment_shader(fs_main, ())
+            .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+            .build(device.clone());
+        match result {
+            Err(GraphicsPipelineCreationError::IncompleteDefinition { missing }) => {
+                assert_eq!(missing, vec!["viewport state"]);
+            },
+            _ => panic!(),
+        }
+
+        // All the missing states are reported at once.
+        let result = GraphicsPipeline::start()
+            .vertex_shader(vs_main, ())
+            .triangle_list()
+            .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+            .build(device.clone());
+        match result {
+            Err(GraphicsPipelineCreationError::IncompleteDefinition { missing }) => {
+                assert_eq!(missing, vec!["viewport state", "fragment shader"]);
+            },
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn build_detects_vertex_input() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let vs = unsafe { ShaderModule::new(device.clone(), &FULL_SCREEN_VS) }.unwrap();
+        let fs = unsafe { ShaderModule::new(device.clone(), &BASIC_FS) }.unwrap();
+        let render_pass = color_render_pass!(device);
+
+        let (vs_main, fs_main) = unsafe {
+            let name = CStr::from_bytes_with_nul_unchecked(b"main\0");
+            (vs.vertex_shader_entry_point::<(), _, _, _>(name,
+                                                         PositionInput,
+                                                         EmptyShaderInterfaceDef,
+                                                         EmptyPipelineDesc),
+             fs.fragment_shader_entry_point::<(), _, _, _>(name,
+                                                           EmptyShaderInterfaceDef,
+                                                           EmptyShaderInterfaceDef,
+                                                           EmptyPipelineDesc))
+        };
+
+        // The default vertex input has no attribute, so it can't feed a shader with inputs.
+        let result = GraphicsPipeline::start()
+            .vertex_shader(vs_main, ())
+            .triangle_list()
+            .viewports_dynamic_scissors_irrelevant(1)
+            .fragment_shader(fs_main, ())
+            .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+            .build(device.clone());
+        match result {
+            Err(GraphicsPipelineCreationError::IncompleteDefinition { missing }) => {
+                assert_eq!(missing, vec!["vertex input"]);
+            },
+            _ => panic!(),
+        }
+
+        // A vertex input that was set explicitly is never reported as missing, even if it
+        // doesn't fit the shader.
+        let result = GraphicsPipeline::start()
+            .vertex_input(SingleBufferDefinition::<()>::new())
+            .vertex_shader(vs_main, ())
+            .triangle_list()
+            .viewports_dynamic_scissors_irrelevant(1)
+            .fragment_shader(fs_main, ())
+            .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+            .build(device.clone());
+        match result {
+            Err(GraphicsPipelineCreationError::IncompatibleVertexDefinition(_)) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn build_discard_without_fragment_shader() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let vs = unsafe { ShaderModule::new(device.clone(), &FULL_SCREEN_VS) }.unwrap();
+        let render_pass = color_render_pass!(device);
+
+        let vs_main = unsafe {
+            let name = CStr::from_bytes_with_nul_unchecked(b"main\0");
+            vs.vertex_shader_entry_point::<(), _, _, _>(name,
+                                                        EmptyShaderInterfaceDef,
+                                                        EmptyShaderInterfaceDef,
+                                                        EmptyPipelineDesc)
+        };
+
+        GraphicsPipeline::start()
+            .vertex_shader(vs_main, ())
+            .triangle_list()
+            .viewports_dynamic_scissors_irrelevant(1)
+            .rasterizer_discard()
+            .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+            .build(device.clone())
+            .unwrap();
+    }
+
+    // What a stage that outputs points without writing their size reports.
+    const POINTS_NO_SIZE: PointSizeOutput = PointSizeOutput {
+        outputs_points: true,
+        writes_point_size: false,
+    };
+
+    #[test]
+    fn point_size_vertex() {
+        let features = Features::none();
+        let no_size = PointSizeOutput { outputs_points: false, .. POINTS_NO_SIZE };
+        let with_size = PointSizeOutput { writes_point_size: true, .. no_size };
+
+        // Vertex shaders output points if the topology is a list of points.
+        match check_point_size(LastPreRasterizationStage::Vertex(Some(no_size)),
+                               PrimitiveTopology::PointList, &features, false) {
+            Err(GraphicsPipelineCreationError::PointSizeNotWritten) => (),
+            _ => panic!(),
+        }
+        assert!(check_point_size(LastPreRasterizationStage::Vertex(Some(no_size)),
+                                 PrimitiveTopology::TriangleList, &features, false).is_ok());
+        assert!(check_point_size(LastPreRasterizationStage::Vertex(Some(with_size)),
+                                 PrimitiveTopology::PointList, &features, false).is_ok());
+        assert!(check_point_size(LastPreRasterizationStage::Vertex(None),
+                                 PrimitiveTopology::PointList, &features, false).is_ok());
+
+        // With `maintenance5`, the size defaults to 1.0.
+        assert!(check_point_size(LastPreRasterizationStage::Vertex(Some(no_size)),
+                                 PrimitiveTopology::PointList, &features, true).is_ok());
+    }
+
+    #[test]
+    fn point_size_tessellation_or_geometry() {
+        let features = Features {
+            shader_tessellation_and_geometry_point_size: true,
+            .. Features::none()
+        };
+
+        // The topology is irrelevant when the last stage isn't the vertex shader.
+        let stage = LastPreRasterizationStage::TessellationOrGeometry(Some(POINTS_NO_SIZE));
+        match check_point_size(stage, PrimitiveTopology::PatchList { vertices_per_patch: 3 },
+                               &features, false) {
+            Err(GraphicsPipelineCreationError::PointSizeNotWritten) => (),
+            _ => panic!(),
+        }
+        assert!(check_point_size(stage, PrimitiveTopology::PointList, &features, true).is_ok());
+
+        // Without the feature, points from these stages always have a size of 1.0.
+        assert!(check_point_size(stage, PrimitiveTopology::PointList, &Features::none(), false)
+                    .is_ok());
+
+        let triangles = PointSizeOutput { outputs_points: false, .. POINTS_NO_SIZE };
+        let stage = LastPreRasterizationStage::TessellationOrGeometry(Some(triangles));
+        assert!(check_point_size(stage, PrimitiveTopology::PointList, &features, false).is_ok());
+
+        let stage = LastPreRasterizationStage::TessellationOrGeometry(None);
+        assert!(check_point_size(stage, PrimitiveTopology::PointList, &features, false).is_ok());
+    }
+
+    #[test]
+    fn build_points_without_size() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let vs = unsafe { ShaderModule::new(device.clone(), &FULL_SCREEN_VS) }.unwrap();
+        let fs = unsafe { ShaderModule::new(device.clone(), &BASIC_FS) }.unwrap();
+        let render_pass = color_render_pass!(device);
+
+        let (vs_main, fs_main) = unsafe {
+            let name = CStr::from_bytes_with_nul_unchecked(b"main\0");
+            let no_size = PointSizeOutput { outputs_points: false, .. POINTS_NO_SIZE };
+            (vs.vertex_shader_entry_point::<(), _, _, _>(name,
+                                                         EmptyShaderInterfaceDef,
+                                                         EmptyShaderInterfaceDef,
+                                                         EmptyPipelineDesc)
+                 .with_point_size_output(no_size),
+             fs.fragment_shader_entry_point::<(), _, _, _>(name,
+                                                           EmptyShaderInterfaceDef,
+                                                           EmptyShaderInterfaceDef,
+                                                           EmptyPipelineDesc))
+        };
+
+        let result = GraphicsPipeline::start()
+            .vertex_shader(vs_main, ())
+            .point_list()
+            .viewports_dynamic_scissors_irrelevant(1)
+            .fragment_shader(fs_main, ())
+            .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+            .build(device.clone());
+
+        if device.effective_extensions().khr_maintenance5 {
+            assert!(result.is_ok());
+        } else {
+            match result {
+                Err(GraphicsPipelineCreationError::PointSizeNotWritten) => (),
+                _ => panic!(),
+            }
+        }
+    }
+
+    #[test]
+    fn build_with_cache_hits() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let vs = unsafe { ShaderModule::new(device.clone(), &FULL_SCREEN_VS) }.unwrap();
+        let fs = unsafe { ShaderModule::new(device.clone(), &BASIC_FS) }.unwrap();
+        let render_pass = color_render_pass!(device);
+        let cache = PipelineCache::empty(device.clone()).unwrap();
+
+        let _first = basic_builder!(vs, fs, render_pass)
+            .build_with_cache(device.clone(), &cache)
+            .unwrap();
+        assert!(!cache.get_data().unwrap().is_empty());
+
+        // The second creation must find the pipeline in the cache. Drivers are only required to
+        // report it through the creation feedback, if they support it.
+        let second = basic_builder!(vs, fs, render_pass)
+            .build_with_cache(device.clone(), &cache)
+            .unwrap();
+        if let Some(feedback) = second.creation_feedback() {
+            if feedback.valid {
+                assert!(feedback.application_pipeline_cache_hit);
+            }
+        }
+    }
+
+    #[test]
+    fn state_hash() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let vs = unsafe { ShaderModule::new(device.clone(), &FULL_SCREEN_VS) }.unwrap();
+        let fs = unsafe { ShaderModule::new(device.clone(), &BASIC_FS) }.unwrap();
+        let render_pass = color_render_pass!(device);
+
+        // Identical descriptions have the same hash, even with distinct shader modules.
+        let hash = basic_builder!(vs, fs, render_pass).state_hash();
+        assert_eq!(hash, basic_builder!(vs, fs, render_pass).state_hash());
+        let fs2 = unsafe { ShaderModule::new(device.clone(), &BASIC_FS) }.unwrap();
+        assert_eq!(hash, basic_builder!(vs, fs2, render_pass).state_hash());
+
+        // Changing any parameter changes the hash.
+        assert!(hash != basic_builder!(vs, fs, render_pass).point_list().state_hash());
+        assert!(hash !=
+                basic_builder!(vs, fs, render_pass).viewports_dynamic_scissors_irrelevant(2)
+                    .state_hash());
+        assert!(hash != basic_builder!(vs, fs, render_pass).cull_mode_back().state_hash());
+        assert!(hash != basic_builder!(vs, fs, render_pass).depth_clamp(true).state_hash());
+        assert!(hash != basic_builder!(vs, fs, render_pass).blend_alpha_blending().state_hash());
+
+        // The code of the shaders is part of the hash.
+        let other_fs = unsafe { ShaderModule::new(device.clone(), &FULL_SCREEN_VS) }.unwrap();
+        assert!(hash != basic_builder!(vs, other_fs, render_pass).state_hash());
+
+        // The render pass is identified by its description.
+        let other_render_pass = color_render_pass!(device);
+        assert_eq!(hash, basic_builder!(vs, fs, other_render_pass).state_hash());
+    }
+
+    #[test]
+    fn build_invalid_subpass() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let vs = unsafe { ShaderModule::new(device.clone(), &FULL_SCREEN_VS) }.unwrap();
+        let fs = unsafe { ShaderModule::new(device.clone(), &BASIC_FS) }.unwrap();
+        let render_pass = color_render_pass!(device);
+
+        // The index is checked before the strict depth state, which needs a valid subpass.
+        let strict = DepthState { strict: true, ..DepthState::disabled() };
+        match basic_builder!(vs, fs, render_pass)
+            .depth_state(strict)
+            .render_pass_index(render_pass.clone(), 1)
+            .build(device.clone())
+        {
+            Err(GraphicsPipelineCreationError::InvalidSubpass {
+                    requested: 1,
+                    available: 1,
+                }) => (),
+            _ => panic!(),
+        }
+
+        basic_builder!(vs, fs, render_pass)
+            .render_pass_index(render_pass.clone(), 0)
+            .build(device.clone())
+            .unwrap();
+    }
+
+    #[test]
+    fn build_samples_mismatch() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let vs = unsafe { ShaderModule::new(device.clone(), &FULL_SCREEN_VS) }.unwrap();
+        let fs = unsafe { ShaderModule::new(device.clone(), &BASIC_FS) }.unwrap();
+        let msaa = MsaaAttachments::new(device.clone(), [64, 64], 4, Format::R8G8B8A8Unorm,
+                                        Format::D16Unorm)
+            .unwrap();
+
+        match basic_builder!(vs, fs, msaa.render_pass()).build(device.clone()) {
+            Err(GraphicsPipelineCreationError::RasterizationSamplesMismatch {
+                    pipeline: 1,
+                    subpass: 4,
+                }) => (),
+            _ => panic!(),
+        }
+
+        basic_builder!(vs, fs, msaa.render_pass())
+            .multisample(msaa.multisample())
+            .build(device.clone())
+            .unwrap();
+    }
+
+    #[test]
+    fn build_depth_strict() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let vs = unsafe { ShaderModule::new(device.clone(), &FULL_SCREEN_VS) }.unwrap();
+        let fs = unsafe { ShaderModule::new(device.clone(), &BASIC_FS) }.unwrap();
+        let depth_render_pass = Arc::new(single_pass_renderpass!(device.clone(),
+            attachments: {
+                color: { load: Clear, store: Store, format: Format::R8G8B8A8Unorm, samples: 1, },
+                depth: { load: Clear, store: DontCare, format: Format::D16Unorm, samples: 1, }
+            },
+            pass: { color: [color], depth_stencil: {depth} }
+        ).unwrap());
+
+        let strict = DepthState { strict: true, ..DepthState::disabled() };
+        match basic_builder!(vs, fs, depth_render_pass)
+            .depth_state(strict.clone())
+            .build(device.clone())
+        {
+            Err(GraphicsPipelineCreationError::DepthStateHasNoEffect) => (),
+            _ => panic!(),
+        }
+
+        // The same state is accepted outside of strict mode, or without a depth attachment.
+        basic_builder!(vs, fs, depth_render_pass)
+            .depth_state(DepthState::disabled())
+            .build(device.clone())
+            .unwrap();
+        let render_pass = color_render_pass!(device);
+        basic_builder!(vs, fs, render_pass)
+            .depth_state(strict.clone())
+            .build(device.clone())
+            .unwrap();
+
+        // Strict mode is kept when the depth state is modified afterwards.
+        basic_builder!(vs, fs, depth_render_pass)
+            .depth_state(strict.clone())
+            .depth_write(true)
+            .build(device.clone())
+            .unwrap();
+    }
+
+    #[test]
+    fn build_depth_bias_clamp_feature() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let vs = unsafe { ShaderModule::new(device.clone(), &FULL_SCREEN_VS) }.unwrap();
+        let fs = unsafe { ShaderModule::new(device.clone(), &BASIC_FS) }.unwrap();
+        let render_pass = color_render_pass!(device);
+
+        match basic_builder!(vs, fs, render_pass).depth_bias(1.0, 0.5, 1.0).build(device.clone()) {
+            Err(GraphicsPipelineCreationError::DepthBiasClampFeatureNotEnabled) => (),
+            _ => panic!(),
+        }
+
+        // A bias that isn't clamped doesn't need the feature.
+        basic_builder!(vs, fs, render_pass)
+            .depth_bias(1.0, 0.0, 1.0)
+            .build(device.clone())
+            .unwrap();
+    }
+
+    #[test]
+    fn build_depth_bounds_feature() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let vs = unsafe { ShaderModule::new(device.clone(), &FULL_SCREEN_VS) }.unwrap();
+        let fs = unsafe { ShaderModule::new(device.clone(), &BASIC_FS) }.unwrap();
+        let render_pass = color_render_pass!(device);
+
+        match basic_builder!(vs, fs, render_pass).depth_bounds(0.0, 1.0).build(device.clone()) {
+            Err(GraphicsPipelineCreationError::DepthBoundsFeatureNotEnabled) => (),
+            _ => panic!(),
+        }
+
+        match basic_builder!(vs, fs, render_pass).depth_bounds_dynamic().build(device.clone()) {
+            Err(GraphicsPipelineCreationError::DepthBoundsFeatureNotEnabled) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn build_depth_bounds() {
+        let (device, _) = gfx_dev_and_queue!(depth_bounds);
+
+        let vs = unsafe { ShaderModule::new(device.clone(), &FULL_SCREEN_VS) }.unwrap();
+        let fs = unsafe { ShaderModule::new(device.clone(), &BASIC_FS) }.unwrap();
+        let render_pass = color_render_pass!(device);
+
+        for &(min, max) in &[(0.6, 0.4), (-0.5, 0.5), (0.5, 1.5)] {
+            match basic_builder!(vs, fs, render_pass).depth_bounds(min, max).build(device.clone()) {
+                Err(GraphicsPipelineCreationError::DepthBoundsInvalidRange) => (),
+                _ => panic!(),
+            }
+        }
+
+        let pipeline = basic_builder!(vs, fs, render_pass)
+            .depth_bounds(0.25, 0.75)
+            .build(device.clone())
+            .unwrap();
+        assert!(!pipeline.has_dynamic_depth_bounds());
+
+        let pipeline = basic_builder!(vs, fs, render_pass)
+            .depth_bounds_dynamic()
+            .build(device.clone())
+            .unwrap();
+        assert!(pipeline.has_dynamic_depth_bounds());
+
+        // The last depth bounds method that is called wins.
+        let pipeline = basic_builder!(vs, fs, render_pass)
+            .depth_bounds_dynamic()
+            .depth_bounds_disabled()
+            .build(device.clone())
+            .unwrap();
+        assert!(!pipeline.has_dynamic_depth_bounds());
+    }
+
+    #[test]
+    fn build_depth_clip_extension() {
+        let (device, _) = gfx_dev_and_queue!();
+        assert!(!device.depth_clip_enable());
+
+        let vs = unsafe { ShaderModule::new(device.clone(), &FULL_SCREEN_VS) }.unwrap();
+        let fs = unsafe { ShaderModule::new(device.clone(), &BASIC_FS) }.unwrap();
+        let render_pass = color_render_pass!(device);
+
+        match basic_builder!(vs, fs, render_pass).depth_clip(true).build(device.clone()) {
+            Err(GraphicsPipelineCreationError::DepthClipEnableExtensionNotEnabled) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn build_extended_dynamic_state_extension() {
+        let (device, _) = gfx_dev_and_queue!();
+        // The extension is part of Vulkan 1.3.
+        if device.effective_extensions().ext_extended_dynamic_state {
+            return;
+        }
+
+        let vs = unsafe { ShaderModule::new(device.clone(), &FULL_SCREEN_VS) }.unwrap();
+        let fs = unsafe { ShaderModule::new(device.clone(), &BASIC_FS) }.unwrap();
+        let render_pass = color_render_pass!(device);
+
+        match basic_builder!(vs, fs, render_pass)
+            .depth_test_enable_dynamic()
+            .build(device.clone()) {
+            Err(GraphicsPipelineCreationError::ExtendedDynamicStateExtensionNotEnabled) => (),
+            _ => panic!(),
+        }
+
+        match basic_builder!(vs, fs, render_pass)
+            .stencil_test_enable_dynamic()
+            .build(device.clone()) {
+            Err(GraphicsPipelineCreationError::ExtendedDynamicStateExtensionNotEnabled) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn build_extended_dynamic_state() {
+        let (device, _) = gfx_dev_and_queue!(extensions: [ext_extended_dynamic_state]);
+
+        let vs = unsafe { ShaderModule::new(device.clone(), &FULL_SCREEN_VS) }.unwrap();
+        let fs = unsafe { ShaderModule::new(device.clone(), &BASIC_FS) }.unwrap();
+        let render_pass = color_render_pass!(device);
+
+        let pipeline = basic_builder!(vs, fs, render_pass).build(device.clone()).unwrap();
+        assert!(!pipeline.has_dynamic_depth_test_enable());
+        assert!(!pipeline.has_dynamic_depth_write_enable());
+        assert!(!pipeline.has_dynamic_depth_compare_op());
+        assert!(!pipeline.has_dynamic_stencil_test_enable());
+
+        // The static depth write is ignored when it is dynamic, so the missing depth attachment
+        // isn't an error.
+        let pipeline = basic_builder!(vs, fs, render_pass)
+            .depth_write(true)
+            .depth_test_enable_dynamic()
+            .depth_write_enable_dynamic()
+            .depth_compare_op_dynamic()
+            .stencil_test_enable_dynamic()
+            .build(device.clone())
+            .unwrap();
+        assert!(pipeline.has_dynamic_depth_test_enable());
+        assert!(pipeline.has_dynamic_depth_write_enable());
+        assert!(pipeline.has_dynamic_depth_compare_op());
+        assert!(pipeline.has_dynamic_stencil_test_enable());
+    }
+
+    #[test]
+    fn build_stencil_attachment() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let vs = unsafe { ShaderModule::new(device.clone(), &FULL_SCREEN_VS) }.unwrap();
+        let fs = unsafe { ShaderModule::new(device.clone(), &BASIC_FS) }.unwrap();
+        let color_only = color_render_pass!(device);
+        let read_only = Arc::new(RenderPass::new(device.clone(), ReadOnlyStencilDesc).unwrap());
+
+        // The write mask prevents the operation from modifying the stencil value.
+        let test_only = StencilOpState {
+            compare_op: Compare::Equal,
+            pass_op: StencilOp::Replace,
+            write_mask: Some(0),
+            ..StencilOpState::default()
+        };
+        let replace = StencilOpState {
+            compare_op: Compare::Always,
+            pass_op: StencilOp::Replace,
+            ..StencilOpState::default()
+        };
+
+        match basic_builder!(vs, fs, color_only).stencil(test_only).build(device.clone()) {
+            Err(GraphicsPipelineCreationError::NoStencilAttachment) => (),
+            _ => panic!(),
+        }
+
+        match basic_builder!(vs, fs, color_only).stencil(replace).build(device.clone()) {
+            Err(GraphicsPipelineCreationError::NoStencilAttachment) => (),
+            _ => panic!(),
+        }
+
+        // Testing against a read-only stencil attachment is fine, but writing to it isn't.
+        assert!(basic_builder!(vs, fs, read_only).stencil(test_only).build(device.clone()).is_ok());
+
+        match basic_builder!(vs, fs, read_only).stencil(replace).build(device.clone()) {
+            Err(GraphicsPipelineCreationError::StencilAttachmentReadOnly) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn derive_with() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let vs = unsafe { ShaderModule::new(device.clone(), &FULL_SCREEN_VS) }.unwrap();
+        let fs = unsafe { ShaderModule::new(device.clone(), &BASIC_FS) }.unwrap();
+        let render_pass = color_render_pass!(device);
+        let other_render_pass = color_render_pass!(device);
+
+        let (vs_main, fs_main) = unsafe {
+            let name = CStr::from_bytes_with_nul_unchecked(b"main\0");
+            (vs.vertex_shader_entry_point::<(), _, _, _>(name,
+                                                         EmptyShaderInterfaceDef,
+                                                         EmptyShaderInterfaceDef,
+                                                         EmptyPipelineDesc),
+             fs.fragment_shader_entry_point::<(), _, _, _>(name,
+                                                           EmptyShaderInterfaceDef,
+                                                           EmptyShaderInterfaceDef,
+                                                           EmptyPipelineDesc))
+        };
+
+        let builder = basic_builder!(vs, fs, render_pass)
+            .line_list()
+            .line_width_dynamic()
+            .depth_bias_dynamic();
+        let hash = builder.state_hash();
+        let pipeline = builder.build(device.clone()).unwrap();
+
+        // `with_state` restores every state, so only the shaders have to be specified again.
+        let derived_hash = pipeline.derive_with(|builder| {
+            builder.vertex_shader(vs_main, ()).fragment_shader(fs_main, ()).state_hash()
+        });
+        assert_eq!(derived_hash, hash);
+
+        let derived = pipeline
+            .derive_with(|builder| {
+                builder
+                    .vertex_shader(vs_main, ())
+                    .fragment_shader(fs_main, ())
+                    .render_pass(Subpass::from(other_render_pass.clone(), 0).unwrap())
+                    .build(device.clone())
+            })
+            .unwrap();
+        assert!(Arc::ptr_eq(derived.render_pass(), &other_render_pass));
+        assert!(derived.has_dynamic_line_width());
+        assert!(derived.has_dynamic_depth_bias());
+        assert!(derived.has_dynamic_viewports());
+        assert_eq!(derived.num_viewports(), 1);
+    }
+
+    #[test]
+    fn indirect_bindable_is_opt_in() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let vs = unsafe { ShaderModule::new(device.clone(), &FULL_SCREEN_VS) }.unwrap();
+        let fs = unsafe { ShaderModule::new(device.clone(), &BASIC_FS) }.unwrap();
+        let render_pass = color_render_pass!(device);
+
+        let default_hash = basic_builder!(vs, fs, render_pass).state_hash();
+        let indirect_hash = basic_builder!(vs, fs, render_pass).indirect_bindable().state_hash();
+        assert!(default_hash != indirect_hash);
+
+        let pipeline = basic_builder!(vs, fs, render_pass).build(device.clone()).unwrap();
+        assert!(!pipeline.is_indirect_bindable());
+    }
+
+    #[test]
+    fn indirect_bindable_requires_extension() {
+        let (device, _) = gfx_dev_and_queue!();
+        assert!(!device.loaded_extensions().nv_device_generated_commands);
+
+        let vs = unsafe { ShaderModule::new(device.clone(), &FULL_SCREEN_VS) }.unwrap();
+        let fs = unsafe { ShaderModule::new(device.clone(), &BASIC_FS) }.unwrap();
+        let render_pass = color_render_pass!(device);
+
+        match basic_builder!(vs, fs, render_pass).indirect_bindable().build(device.clone()) {
+            Err(GraphicsPipelineCreationError::DeviceGeneratedCommandsExtensionNotEnabled) => (),
+            _ => panic!(),
+        }
+    }
+}
@@ -41,6 +41,8 @@ use descriptor::pipeline_layout::PipelineLayoutSys;
 use device::Device;
 use device::DeviceOwned;
 use format::ClearValue;
+use format::Format;
+use framebuffer::IncompatibleRenderPassError;
 use framebuffer::LayoutAttachmentDescription;
 use framebuffer::LayoutPassDependencyDescription;
 use framebuffer::LayoutPassDescription;
@@ -50,13 +52,18 @@ use framebuffer::RenderPassDescClearValues;
 use framebuffer::RenderPassSubpassInterface;
 use framebuffer::RenderPassSys;
 use framebuffer::Subpass;
+use framebuffer::ensure_render_pass_compatible;
+use instance::Features;
 use vk;
 
 use pipeline::blend::AttachmentsBlend;
 use pipeline::blend::Blend;
 use pipeline::depth_stencil::Compare;
 use pipeline::depth_stencil::DepthBounds;
+use pipeline::cache::PipelineCache;
 use pipeline::depth_stencil::DepthStencil;
+use pipeline::feedback::FeedbackStorage;
+use pipeline::feedback::PipelineCreationFeedback;
 use pipeline::input_assembly::InputAssembly;
 use pipeline::input_assembly::PrimitiveTopology;
 use pipeline::multisample::Multisample;
@@ -66,6 +73,7 @@ use pipeline::raster::Rasterization;
 use pipeline::shader::EmptyShaderInterfaceDef;
 use pipeline::shader::FragmentShaderEntryPoint;
 use pipeline::shader::GeometryShaderEntryPoint;
+use pipeline::shader::PointSizeOutput;
 use pipeline::shader::ShaderInterfaceDef;
 use pipeline::shader::ShaderInterfaceDefMatch;
 use pipeline::shader::ShaderInterfaceMismatchError;
@@ -81,6 +89,7 @@ use pipeline::viewport::ViewportsState;
 pub use self::builder::GraphicsPipelineBuilder;
 
 mod builder;
+mod state_hash;
 // FIXME: restore
 //mod tests;
 
@@ -169,6 +178,130 @@ pub struct GraphicsPipelineParamsTess<'a, Tcs, Tci, Tco, Tcl, Tes, Tei, Teo, Tel
     pub tessellation_evaluation_shader: TessEvaluationShaderEntryPoint<'a, Tes, Tei, Teo, Tel>,
 }
 
+// Same as `GraphicsPipelineParams`, except that the fragment shader is optional. It can only be
+// omitted if rasterization is discarded.
+struct CreateParams<'a,
+ Vdef,
+ Vsp,
+ Vi,
+ Vo,
+ Vl,
+ Tcs,
+ Tci,
+ Tco,
+ Tcl,
+ Tes,
+ Tei,
+ Teo,
+ Tel,
+ Gs,
+ Gi,
+ Go,
+ Gl,
+ Fs,
+ Fi,
+ Fo,
+ Fl,
+ Rp>
+{
+    vertex_input: Vdef,
+    vertex_shader: VertexShaderEntryPoint<'a, Vsp, Vi, Vo, Vl>,
+    input_assembly: InputAssembly,
+    tessellation: Option<GraphicsPipelineParamsTess<'a, Tcs, Tci, Tco, Tcl, Tes, Tei, Teo, Tel>>,
+    geometry_shader: Option<GeometryShaderEntryPoint<'a, Gs, Gi, Go, Gl>>,
+    viewport: ViewportsState,
+    raster: Rasterization,
+    multisample: Multisample,
+    fragment_shader: Option<FragmentShaderEntryPoint<'a, Fs, Fi, Fo, Fl>>,
+    depth_stencil: DepthStencil,
+    blend: Blend,
+    render_pass: Subpass<Rp>,
+}
+
+impl<'a, Vdef, Vsp, Vi, Vo, Vl, Tcs, Tci, Tco, Tcl, Tes, Tei, Teo, Tel, Gs, Gi, Go, Gl, Fs, Fi,
+     Fo, Fl, Rp>
+    CreateParams<'a, Vdef, Vsp, Vi, Vo, Vl, Tcs, Tci, Tco, Tcl, Tes, Tei, Teo, Tel, Gs, Gi, Go,
+                 Gl, Fs, Fi, Fo, Fl, Rp>
+{
+    // Returns the layout of the fragment shader, which is empty if there is none.
+    #[inline]
+    fn fragment_shader_layout(&self) -> OptionalStageLayout<Fl>
+        where Fl: Clone
+    {
+        OptionalStageLayout(self.fragment_shader.as_ref().map(|fs| fs.layout().clone()))
+    }
+}
+
+impl<'a, Vdef, Vsp, Vi, Vo, Vl, Tcs, Tci, Tco, Tcl, Tes, Tei, Teo, Tel, Gs, Gi, Go, Gl, Fs, Fi,
+     Fo, Fl, Rp>
+    From<GraphicsPipelineParams<'a, Vdef, Vsp, Vi, Vo, Vl, Tcs, Tci, Tco, Tcl, Tes, Tei, Teo, Tel,
+                                Gs, Gi, Go, Gl, Fs, Fi, Fo, Fl, Rp>>
+    for CreateParams<'a, Vdef, Vsp, Vi, Vo, Vl, Tcs, Tci, Tco, Tcl, Tes, Tei, Teo, Tel, Gs, Gi,
+                     Go, Gl, Fs, Fi, Fo, Fl, Rp>
+{
+    #[inline]
+    fn from(params: GraphicsPipelineParams<'a, Vdef, Vsp, Vi, Vo, Vl, Tcs, Tci, Tco, Tcl, Tes,
+                                           Tei, Teo, Tel, Gs, Gi, Go, Gl, Fs, Fi, Fo, Fl, Rp>)
+            -> Self {
+        CreateParams {
+            vertex_input: params.vertex_input,
+            vertex_shader: params.vertex_shader,
+            input_assembly: params.input_assembly,
+            tessellation: params.tessellation,
+            geometry_shader: params.geometry_shader,
+            viewport: params.viewport,
+            raster: params.raster,
+            multisample: params.multisample,
+            fragment_shader: Some(params.fragment_shader),
+            depth_stencil: params.depth_stencil,
+            blend: params.blend,
+            render_pass: params.render_pass,
+        }
+    }
+}
+
+// Layout of a shader stage that may be missing. Behaves like an empty layout if it is.
+#[derive(Debug, Clone)]
+struct OptionalStageLayout<L>(Option<L>);
+
+unsafe impl<L> PipelineLayoutDesc for OptionalStageLayout<L>
+    where L: PipelineLayoutDesc
+{
+    #[inline]
+    fn num_sets(&self) -> usize {
+        self.0.as_ref().map(|l| l.num_sets()).unwrap_or(0)
+    }
+
+    #[inline]
+    fn num_bindings_in_set(&self, set: usize) -> Option<usize> {
+        self.0.as_ref().and_then(|l| l.num_bindings_in_set(set))
+    }
+
+    #[inline]
+    fn descriptor(&self, set: usize, binding: usize) -> Option<DescriptorDesc> {
+        self.0.as_ref().and_then(|l| l.descriptor(set, binding))
+    }
+
+    #[inline]
+    fn num_push_constants_ranges(&self) -> usize {
+        self.0.as_ref().map(|l| l.num_push_constants_ranges()).unwrap_or(0)
+    }
+
+    #[inline]
+    fn push_constants_range(&self, num: usize) -> Option<PipelineLayoutDescPcRange> {
+        self.0.as_ref().and_then(|l| l.push_constants_range(num))
+    }
+}
+
+unsafe impl<L> PipelineLayoutDescNames for OptionalStageLayout<L>
+    where L: PipelineLayoutDescNames
+{
+    #[inline]
+    fn descriptor_by_name(&self, name: &str) -> Option<(usize, usize)> {
+        self.0.as_ref().and_then(|l| l.descriptor_by_name(name))
+    }
+}
+
 /// Defines how the implementation should perform a draw operation.
 ///
 /// This object contains the shaders and the various fixed states that describe how the
@@ -190,9 +323,19 @@ pub struct GraphicsPipeline<VertexDefinition, Layout, RenderP> {
     dynamic_stencil_compare_mask: bool,
     dynamic_stencil_write_mask: bool,
     dynamic_stencil_reference: bool,
+    dynamic_depth_test_enable: bool,
+    dynamic_depth_write_enable: bool,
+    dynamic_depth_compare_op: bool,
+    dynamic_stencil_test_enable: bool,
     dynamic_blend_constants: bool,
 
     num_viewports: u32,
+
+    indirect_bindable: bool,
+
+    fixed_state: FixedState,
+
+    feedback: Option<PipelineCreationFeedback>,
 }
 
 struct Inner {
@@ -200,6 +343,18 @@ struct Inner {
     device: Arc<Device>,
 }
 
+// The states that were passed when creating a pipeline, other than the shaders, the vertex input
+// and the render pass. Kept so that `derive_with` can start from them.
+struct FixedState {
+    input_assembly: InputAssembly,
+    viewport: ViewportsState,
+    raster: Rasterization,
+    multisample: Multisample,
+    depth_stencil: DepthStencil,
+    blend: Blend,
+    indirect_bindable: bool,
+}
+
 impl GraphicsPipeline<(), (), ()> {
     /// Starts the building process of a graphics pipeline. Returns a builder object that you can
     /// fill with the various parameters.
@@ -293,7 +448,7 @@ impl<Vdef, Rp> GraphicsPipeline<Vdef, (), Rp>
                                       _,
                                       _,
                                       _,
-                                      _>(device, params, pl)
+                                      _>(device, params.into(), pl, None, false)
     }
 
     /// Builds a new graphics pipeline object with a geometry shader.
@@ -352,7 +507,7 @@ impl<Vdef, Rp> GraphicsPipeline<Vdef, (), Rp>
                     .union(params.geometry_shader.as_ref().unwrap().layout().clone())    // FIXME: unwrap()
                     .build(device.clone()).unwrap(); // TODO: error
 
-        GraphicsPipeline::new_inner(device.clone(), params, pl)
+        GraphicsPipeline::new_inner(device.clone(), params.into(), pl, None, false)
     }
 
     /// Builds a new graphics pipeline object with tessellation shaders.
@@ -423,7 +578,7 @@ impl<Vdef, Rp> GraphicsPipeline<Vdef, (), Rp>
                     .union(params.tessellation.as_ref().unwrap().tessellation_evaluation_shader.layout().clone())    // FIXME: unwrap()
                     .build(device.clone()).unwrap(); // TODO: error
 
-        GraphicsPipeline::new_inner(device, params, pl)
+        GraphicsPipeline::new_inner(device, params.into(), pl, None, false)
     }
 
     /// Builds a new graphics pipeline object with a geometry and tessellation shaders.
@@ -502,6 +657,78 @@ impl<Vdef, Rp> GraphicsPipeline<Vdef, (), Rp>
                       + ShaderInterfaceDefMatch<Vo>,
               Fo: ShaderInterfaceDef,
               Rp: RenderPassAbstract + RenderPassSubpassInterface<Fo>
+    {
+        GraphicsPipeline::with_cache_inner(device, params.into(), None, false)
+    }
+
+    // Same as `with_tessellation_and_geometry`, but passes `cache` to the implementation if it is
+    // `Some`.
+    fn with_cache_inner<'a,
+                        Vsp,
+                        Vi,
+                        Vo,
+                        Vl,
+                        Tcs,
+                        Tci,
+                        Tco,
+                        Tcl,
+                        Tes,
+                        Tei,
+                        Teo,
+                        Tel,
+                        Gsp,
+                        Gi,
+                        Go,
+                        Gl,
+                        Fs,
+                        Fi,
+                        Fo,
+                        Fl>(
+        device: Arc<Device>,
+        params: CreateParams<'a,
+                             Vdef,
+                             Vsp,
+                             Vi,
+                             Vo,
+                             Vl,
+                             Tcs,
+                             Tci,
+                             Tco,
+                             Tcl,
+                             Tes,
+                             Tei,
+                             Teo,
+                             Tel,
+                             Gsp,
+                             Gi,
+                             Go,
+                             Gl,
+                             Fs,
+                             Fi,
+                             Fo,
+                             Fl,
+                             Rp>,
+        cache: Option<&PipelineCache>, indirect_bindable: bool)
+        -> Result<GraphicsPipeline<Vdef, Box<PipelineLayoutAbstract + Send + Sync>, Rp>,
+                  GraphicsPipelineCreationError>
+        where Vdef: VertexDefinition<Vi>,
+              Vl: PipelineLayoutDescNames + Clone + 'static + Send + Sync, // TODO: Clone + 'static + Send + Sync shouldn't be required
+              Fl: PipelineLayoutDescNames + Clone + 'static + Send + Sync, // TODO: Clone + 'static + Send + Sync shouldn't be required
+              Tcl: PipelineLayoutDescNames + Clone + 'static + Send + Sync, // TODO: Clone + 'static + Send + Sync shouldn't be required
+              Tel: PipelineLayoutDescNames + Clone + 'static + Send + Sync, // TODO: Clone + 'static + Send + Sync shouldn't be required
+              Gl: PipelineLayoutDescNames + Clone + 'static + Send + Sync, // TODO: Clone + 'static + Send + Sync shouldn't be required
+              Tci: ShaderInterfaceDefMatch<Vo>,
+              Tei: ShaderInterfaceDefMatch<Tco>,
+              Gi: ShaderInterfaceDefMatch<Teo> + ShaderInterfaceDefMatch<Vo>,
+              Vo: ShaderInterfaceDef,
+              Tco: ShaderInterfaceDef,
+              Teo: ShaderInterfaceDef,
+              Go: ShaderInterfaceDef,
+              Fi: ShaderInterfaceDefMatch<Go>
+                      + ShaderInterfaceDefMatch<Teo>
+                      + ShaderInterfaceDefMatch<Vo>,
+              Fo: ShaderInterfaceDef,
+              Rp: RenderPassAbstract + RenderPassSubpassInterface<Fo>
     {
         let pl;
 
@@ -524,12 +751,14 @@ impl<Vdef, Rp> GraphicsPipeline<Vdef, (), Rp>
                 {
                     return Err(GraphicsPipelineCreationError::TessEvalGeometryStagesMismatch(err));
                 }
-                if let Err(err) = params.fragment_shader.input().matches(gs.output()) {
-                    return Err(GraphicsPipelineCreationError::GeometryFragmentStagesMismatch(err));
+                if let Some(ref fs) = params.fragment_shader {
+                    fs.input()
+                        .matches(gs.output())
+                        .map_err(GraphicsPipelineCreationError::GeometryFragmentStagesMismatch)?;
                 }
 
                 pl = Box::new(params.vertex_shader.layout().clone()
-                    .union(params.fragment_shader.layout().clone())
+                    .union(params.fragment_shader_layout())
                     .union(params.tessellation.as_ref().unwrap().tessellation_control_shader.layout().clone())    // FIXME: unwrap()
                     .union(params.tessellation.as_ref().unwrap().tessellation_evaluation_shader.layout().clone())    // FIXME: unwrap()
                     .union(params.geometry_shader.as_ref().unwrap().layout().clone())    // FIXME: unwrap()
@@ -548,16 +777,14 @@ impl<Vdef, Rp> GraphicsPipeline<Vdef, (), Rp>
                 {
                     return Err(GraphicsPipelineCreationError::TessControlTessEvalStagesMismatch(err));
                 }
-                if let Err(err) = params
-                    .fragment_shader
-                    .input()
-                    .matches(tess.tessellation_evaluation_shader.output())
-                {
-                    return Err(GraphicsPipelineCreationError::TessEvalFragmentStagesMismatch(err));
+                if let Some(ref fs) = params.fragment_shader {
+                    fs.input()
+                        .matches(tess.tessellation_evaluation_shader.output())
+                        .map_err(GraphicsPipelineCreationError::TessEvalFragmentStagesMismatch)?;
                 }
 
                 pl = Box::new(params.vertex_shader.layout().clone()
-                    .union(params.fragment_shader.layout().clone())
+                    .union(params.fragment_shader_layout())
                     .union(params.tessellation.as_ref().unwrap().tessellation_control_shader.layout().clone())    // FIXME: unwrap()
                     .union(params.tessellation.as_ref().unwrap().tessellation_evaluation_shader.layout().clone())    // FIXME: unwrap()
                     .build(device.clone()).unwrap()) as Box<_>; // TODO: error
@@ -571,39 +798,35 @@ impl<Vdef, Rp> GraphicsPipeline<Vdef, (), Rp>
                 {
                     return Err(GraphicsPipelineCreationError::VertexGeometryStagesMismatch(err));
                 }
-                if let Err(err) = params
-                    .fragment_shader
-                    .input()
-                    .matches(geometry_shader.output())
-                {
-                    return Err(GraphicsPipelineCreationError::GeometryFragmentStagesMismatch(err));
+                if let Some(ref fs) = params.fragment_shader {
+                    fs.input()
+                        .matches(geometry_shader.output())
+                        .map_err(GraphicsPipelineCreationError::GeometryFragmentStagesMismatch)?;
                 }
 
                 pl = Box::new(params.vertex_shader.layout().clone()
-                    .union(params.fragment_shader.layout().clone())
+                    .union(params.fragment_shader_layout())
                     .union(params.geometry_shader.as_ref().unwrap().layout().clone())    // FIXME: unwrap()
                     .build(device.clone()).unwrap()) as Box<_>; // TODO: error
 
             } else {
-                if let Err(err) = params
-                    .fragment_shader
-                    .input()
-                    .matches(params.vertex_shader.output())
-                {
-                    return Err(GraphicsPipelineCreationError::VertexFragmentStagesMismatch(err));
+                if let Some(ref fs) = params.fragment_shader {
+                    fs.input()
+                        .matches(params.vertex_shader.output())
+                        .map_err(GraphicsPipelineCreationError::VertexFragmentStagesMismatch)?;
                 }
 
                 pl = Box::new(params
                                   .vertex_shader
                                   .layout()
                                   .clone()
-                                  .union(params.fragment_shader.layout().clone())
+                                  .union(params.fragment_shader_layout())
                                   .build(device.clone())
                                   .unwrap()) as Box<_>; // TODO: error
             }
         }
 
-        GraphicsPipeline::new_inner(device, params, pl)
+        GraphicsPipeline::new_inner(device, params, pl, cache, indirect_bindable)
     }
 }
 
@@ -632,30 +855,30 @@ impl<Vdef, L, Rp> GraphicsPipeline<Vdef, L, Rp>
                  Fo,
                  Fl>(
         device: Arc<Device>,
-        params: GraphicsPipelineParams<'a,
-                                       Vdef,
-                                       Vsp,
-                                       Vi,
-                                       Vo,
-                                       Vl,
-                                       Tcs,
-                                       Tci,
-                                       Tco,
-                                       Tcl,
-                                       Tes,
-                                       Tei,
-                                       Teo,
-                                       Tel,
-                                       Gsp,
-                                       Gi,
-                                       Go,
-                                       Gl,
-                                       Fs,
-                                       Fi,
-                                       Fo,
-                                       Fl,
-                                       Rp>,
-        pipeline_layout: L)
+        params: CreateParams<'a,
+                             Vdef,
+                             Vsp,
+                             Vi,
+                             Vo,
+                             Vl,
+                             Tcs,
+                             Tci,
+                             Tco,
+                             Tcl,
+                             Tes,
+                             Tei,
+                             Teo,
+                             Tel,
+                             Gsp,
+                             Gi,
+                             Go,
+                             Gl,
+                             Fs,
+                             Fi,
+                             Fo,
+                             Fl,
+                             Rp>,
+        pipeline_layout: L, cache: Option<&PipelineCache>, indirect_bindable: bool)
         -> Result<GraphicsPipeline<Vdef, L, Rp>, GraphicsPipelineCreationError>
         where Vdef: VertexDefinition<Vi>,
               Fo: ShaderInterfaceDef,
@@ -672,8 +895,9 @@ impl<Vdef, L, Rp> GraphicsPipeline<Vdef, L, Rp>
         // TODO: more details in the errors
         PipelineLayoutSuperset::ensure_superset_of(&pipeline_layout,
                                                    params.vertex_shader.layout())?;
-        PipelineLayoutSuperset::ensure_superset_of(&pipeline_layout,
-                                                   params.fragment_shader.layout())?;
+        if let Some(ref fs) = params.fragment_shader {
+            PipelineLayoutSuperset::ensure_superset_of(&pipeline_layout, fs.layout())?;
+        }
         if let Some(ref geometry_shader) = params.geometry_shader {
             PipelineLayoutSuperset::ensure_superset_of(&pipeline_layout, geometry_shader.layout())?;
         }
@@ -686,11 +910,13 @@ impl<Vdef, L, Rp> GraphicsPipeline<Vdef, L, Rp>
         }
 
         // Check that the subpass can accept the output of the fragment shader.
-        if !RenderPassSubpassInterface::is_compatible_with(&params.render_pass.render_pass(),
-                                                           params.render_pass.index(),
-                                                           params.fragment_shader.output())
-        {
-            return Err(GraphicsPipelineCreationError::FragmentShaderRenderPassIncompatible);
+        if let Some(ref fs) = params.fragment_shader {
+            if !RenderPassSubpassInterface::is_compatible_with(&params.render_pass.render_pass(),
+                                                               params.render_pass.index(),
+                                                               fs.output())
+            {
+                return Err(GraphicsPipelineCreationError::FragmentShaderRenderPassIncompatible);
+            }
         }
 
         // Will contain the list of dynamic states. Filled throughout this function.
@@ -710,15 +936,19 @@ impl<Vdef, L, Rp> GraphicsPipeline<Vdef, L, Rp>
                             pSpecializationInfo: ptr::null(), // TODO:
                         });
 
-            stages.push(vk::PipelineShaderStageCreateInfo {
-                            sType: vk::STRUCTURE_TYPE_PIPELINE_SHADER_STAGE_CREATE_INFO,
-                            pNext: ptr::null(),
-                            flags: 0, // reserved
-                            stage: vk::SHADER_STAGE_FRAGMENT_BIT,
-                            module: params.fragment_shader.module().internal_object(),
-                            pName: params.fragment_shader.name().as_ptr(),
-                            pSpecializationInfo: ptr::null(), // TODO:
-                        });
+            // The fragment shader can only be omitted if rasterization is discarded.
+            debug_assert!(params.fragment_shader.is_some() || params.raster.rasterizer_discard);
+            if let Some(ref fs) = params.fragment_shader {
+                stages.push(vk::PipelineShaderStageCreateInfo {
+                                sType: vk::STRUCTURE_TYPE_PIPELINE_SHADER_STAGE_CREATE_INFO,
+                                pNext: ptr::null(),
+                                flags: 0, // reserved
+                                stage: vk::SHADER_STAGE_FRAGMENT_BIT,
+                                module: fs.module().internal_object(),
+                                pName: fs.name().as_ptr(),
+                                pSpecializationInfo: ptr::null(), // TODO:
+                            });
+            }
 
             if let Some(ref gs) = params.geometry_shader {
                 if !device.enabled_features().geometry_shader {
@@ -782,11 +1012,11 @@ impl<Vdef, L, Rp> GraphicsPipeline<Vdef, L, Rp>
                     device
                         .physical_device()
                         .limits()
-                        .max_vertex_input_binding_stride() as usize
+                        .max_vertex_input_binding_stride()
                 {
                     return Err(GraphicsPipelineCreationError::MaxVertexInputBindingStrideExceeded {
                         binding: num as usize,
-                        max: device.physical_device().limits().max_vertex_input_binding_stride() as usize,
+                        max: device.physical_device().limits().max_vertex_input_binding_stride(),
                         obtained: stride,
                     });
                 }
@@ -802,14 +1032,31 @@ impl<Vdef, L, Rp> GraphicsPipeline<Vdef, L, Rp>
             for (loc, binding, info) in attribs_iter {
                 // TODO: check attribute format support
 
+                let (float64, int64) = match info.format {
+                    Format::R64Sfloat | Format::R64G64Sfloat | Format::R64G64B64Sfloat |
+                    Format::R64G64B64A64Sfloat => (true, false),
+                    Format::R64Uint | Format::R64G64Uint | Format::R64G64B64Uint |
+                    Format::R64G64B64A64Uint | Format::R64Sint | Format::R64G64Sint |
+                    Format::R64G64B64Sint | Format::R64G64B64A64Sint => (false, true),
+                    _ => (false, false),
+                };
+
+                if float64 && !device.enabled_features().shader_f3264 {
+                    return Err(GraphicsPipelineCreationError::ShaderFloat64FeatureNotEnabled);
+                }
+
+                if int64 && !device.enabled_features().shader_int64 {
+                    return Err(GraphicsPipelineCreationError::ShaderInt64FeatureNotEnabled);
+                }
+
                 if info.offset >
                     device
                         .physical_device()
                         .limits()
-                        .max_vertex_input_attribute_offset() as usize
+                        .max_vertex_input_attribute_offset()
                 {
                     return Err(GraphicsPipelineCreationError::MaxVertexInputAttributeOffsetExceeded {
-                        max: device.physical_device().limits().max_vertex_input_attribute_offset() as usize,
+                        max: device.physical_device().limits().max_vertex_input_attribute_offset(),
                         obtained: info.offset,
                     });
                 }
@@ -887,6 +1134,23 @@ impl<Vdef, L, Rp> GraphicsPipeline<Vdef, L, Rp>
             }
         }
 
+        // When points are rasterized, their size must be written by the last stage before the
+        // rasterizer.
+        {
+            let last_stage = if let Some(ref gs) = params.geometry_shader {
+                LastPreRasterizationStage::TessellationOrGeometry(gs.point_size_output())
+            } else if let Some(ref tess) = params.tessellation {
+                let tes = &tess.tessellation_evaluation_shader;
+                LastPreRasterizationStage::TessellationOrGeometry(tes.point_size_output())
+            } else {
+                LastPreRasterizationStage::Vertex(params.vertex_shader.point_size_output())
+            };
+
+            check_point_size(last_stage, params.input_assembly.topology,
+                             device.enabled_features(),
+                             device.effective_extensions().khr_maintenance5)?;
+        }
+
         let input_assembly = vk::PipelineInputAssemblyStateCreateInfo {
             sType: vk::STRUCTURE_TYPE_PIPELINE_INPUT_ASSEMBLY_STATE_CREATE_INFO,
             pNext: ptr::null(),
@@ -1036,6 +1300,25 @@ impl<Vdef, L, Rp> GraphicsPipeline<Vdef, L, Rp>
             return Err(GraphicsPipelineCreationError::DepthClampFeatureNotEnabled);
         }
 
+        // Without `VK_EXT_depth_clip_enable`, depth clipping is implicitly disabled when depth
+        // clamping is enabled.
+        let depth_clip_state = match params.raster.depth_clip {
+            Some(clip) => {
+                if !device.depth_clip_enable() {
+                    return Err(GraphicsPipelineCreationError::DepthClipEnableExtensionNotEnabled);
+                }
+
+                Some(vk::PipelineRasterizationDepthClipStateCreateInfoEXT {
+                         sType:
+                             vk::STRUCTURE_TYPE_PIPELINE_RASTERIZATION_DEPTH_CLIP_STATE_CREATE_INFO_EXT,
+                         pNext: ptr::null(),
+                         flags: 0, // reserved
+                         depthClipEnable: if clip { vk::TRUE } else { vk::FALSE },
+                     })
+            },
+            None => None,
+        };
+
         if params.raster.polygon_mode != PolygonMode::Fill &&
             !device.enabled_features().fill_mode_non_solid
         {
@@ -1044,7 +1327,10 @@ impl<Vdef, L, Rp> GraphicsPipeline<Vdef, L, Rp>
 
         let rasterization = vk::PipelineRasterizationStateCreateInfo {
             sType: vk::STRUCTURE_TYPE_PIPELINE_RASTERIZATION_STATE_CREATE_INFO,
-            pNext: ptr::null(),
+            pNext: match depth_clip_state {
+                Some(ref s) => s as *const _ as *const _,
+                None => ptr::null(),
+            },
             flags: 0, // reserved
             depthClampEnable: if params.raster.depth_clamp {
                 vk::TRUE
@@ -1067,7 +1353,14 @@ impl<Vdef, L, Rp> GraphicsPipeline<Vdef, L, Rp>
         };
 
         assert!(params.multisample.rasterization_samples >= 1);
-        // FIXME: check that rasterization_samples is equal to what's in the renderpass
+        if let Some(samples) = params.render_pass.num_samples() {
+            if samples != params.multisample.rasterization_samples {
+                return Err(GraphicsPipelineCreationError::RasterizationSamplesMismatch {
+                               pipeline: params.multisample.rasterization_samples,
+                               subpass: samples,
+                           });
+            }
+        }
         if let Some(s) = params.multisample.sample_shading {
             assert!(s >= 0.0 && s <= 1.0);
         }
@@ -1103,6 +1396,10 @@ impl<Vdef, L, Rp> GraphicsPipeline<Vdef, L, Rp>
                         return Err(GraphicsPipelineCreationError::DepthBoundsFeatureNotEnabled);
                     }
 
+                    if !(range.start >= 0.0 && range.start <= range.end && range.end <= 1.0) {
+                        return Err(GraphicsPipelineCreationError::DepthBoundsInvalidRange);
+                    }
+
                     (vk::TRUE, range.start, range.end)
                 },
                 DepthBounds::Dynamic => {
@@ -1143,24 +1440,56 @@ impl<Vdef, L, Rp> GraphicsPipeline<Vdef, L, Rp>
                 _ => return Err(GraphicsPipelineCreationError::WrongStencilState),
             };
 
-            if params.depth_stencil.depth_write && !params.render_pass.has_writable_depth() {
+            if params.depth_stencil.uses_extended_dynamic_state() {
+                if !device.effective_extensions().ext_extended_dynamic_state {
+                    return Err(
+                        GraphicsPipelineCreationError::ExtendedDynamicStateExtensionNotEnabled,
+                    );
+                }
+
+                if params.depth_stencil.dynamic_depth_test_enable {
+                    dynamic_states.push(vk::DYNAMIC_STATE_DEPTH_TEST_ENABLE_EXT);
+                }
+                if params.depth_stencil.dynamic_depth_write_enable {
+                    dynamic_states.push(vk::DYNAMIC_STATE_DEPTH_WRITE_ENABLE_EXT);
+                }
+                if params.depth_stencil.dynamic_depth_compare_op {
+                    dynamic_states.push(vk::DYNAMIC_STATE_DEPTH_COMPARE_OP_EXT);
+                }
+                if params.depth_stencil.dynamic_stencil_test_enable {
+                    dynamic_states.push(vk::DYNAMIC_STATE_STENCIL_TEST_ENABLE_EXT);
+                }
+            }
+
+            // When a state is dynamic, its static value is ignored and the checks below are
+            // up to the user.
+            if params.depth_stencil.depth_write &&
+                !params.depth_stencil.dynamic_depth_write_enable &&
+                !params.render_pass.has_writable_depth()
+            {
                 return Err(GraphicsPipelineCreationError::NoDepthAttachment);
             }
 
             if params.depth_stencil.depth_compare != Compare::Always &&
+                !params.depth_stencil.dynamic_depth_compare_op &&
                 !params.render_pass.has_depth()
             {
                 return Err(GraphicsPipelineCreationError::NoDepthAttachment);
             }
 
-            if (!params.depth_stencil.stencil_front.always_keep() ||
-                    !params.depth_stencil.stencil_back.always_keep()) &&
-                !params.render_pass.has_stencil()
-            {
+            if params.depth_stencil.stencil_enabled() && !params.render_pass.has_stencil() {
                 return Err(GraphicsPipelineCreationError::NoStencilAttachment);
             }
 
-            // FIXME: stencil writability
+            if (params.depth_stencil.stencil_front.writes() ||
+                    params.depth_stencil.stencil_back.writes()) &&
+                !params.render_pass.has_writable_stencil()
+            {
+                if params.render_pass.has_stencil() {
+                    return Err(GraphicsPipelineCreationError::StencilAttachmentReadOnly);
+                }
+                return Err(GraphicsPipelineCreationError::NoStencilAttachment);
+            }
 
             vk::PipelineDepthStencilStateCreateInfo {
                 sType: vk::STRUCTURE_TYPE_PIPELINE_DEPTH_STENCIL_STATE_CREATE_INFO,
@@ -1180,18 +1509,16 @@ impl<Vdef, L, Rp> GraphicsPipeline<Vdef, L, Rp>
                 },
                 depthCompareOp: params.depth_stencil.depth_compare as u32,
                 depthBoundsTestEnable: db.0,
-                stencilTestEnable: if params.depth_stencil.stencil_front.always_keep() &&
-                    params.depth_stencil.stencil_back.always_keep()
-                {
-                    vk::FALSE
-                } else {
+                stencilTestEnable: if params.depth_stencil.stencil_enabled() {
                     vk::TRUE
+                } else {
+                    vk::FALSE
                 },
                 front: vk::StencilOpState {
                     failOp: params.depth_stencil.stencil_front.fail_op as u32,
                     passOp: params.depth_stencil.stencil_front.pass_op as u32,
                     depthFailOp: params.depth_stencil.stencil_front.depth_fail_op as u32,
-                    compareOp: params.depth_stencil.stencil_front.compare as u32,
+                    compareOp: params.depth_stencil.stencil_front.compare_op as u32,
                     compareMask: params
                         .depth_stencil
                         .stencil_front
@@ -1208,7 +1535,7 @@ impl<Vdef, L, Rp> GraphicsPipeline<Vdef, L, Rp>
                     failOp: params.depth_stencil.stencil_back.fail_op as u32,
                     passOp: params.depth_stencil.stencil_back.pass_op as u32,
                     depthFailOp: params.depth_stencil.stencil_back.depth_fail_op as u32,
-                    compareOp: params.depth_stencil.stencil_back.compare as u32,
+                    compareOp: params.depth_stencil.stencil_back.compare_op as u32,
                     compareMask: params
                         .depth_stencil
                         .stencil_back
@@ -1230,10 +1557,10 @@ impl<Vdef, L, Rp> GraphicsPipeline<Vdef, L, Rp>
             let num_atch = params.render_pass.num_color_attachments();
 
             match params.blend.attachments {
-                AttachmentsBlend::Collective(blend) => {
+                AttachmentsBlend::Collective(ref blend) => {
                     (0 .. num_atch).map(|_| blend.clone().into()).collect()
                 },
-                AttachmentsBlend::Individual(blend) => {
+                AttachmentsBlend::Individual(ref blend) => {
                     if blend.len() != num_atch as usize {
                         return Err(GraphicsPipelineCreationError::MismatchBlendingAttachmentsCount);
                     }
@@ -1282,11 +1609,27 @@ impl<Vdef, L, Rp> GraphicsPipeline<Vdef, L, Rp>
             None
         };
 
+        let mut feedback = FeedbackStorage::new(&device, stages.len());
+        let feedback_infos = feedback.as_mut().map(|f| f.create_info());
+
+        if indirect_bindable && !device.loaded_extensions().nv_device_generated_commands {
+            return Err(GraphicsPipelineCreationError::DeviceGeneratedCommandsExtensionNotEnabled);
+        }
+
+        let flags = if indirect_bindable {
+            vk::PIPELINE_CREATE_INDIRECT_BINDABLE_BIT_NV
+        } else {
+            0
+        };
+
         let pipeline = unsafe {
             let infos = vk::GraphicsPipelineCreateInfo {
                 sType: vk::STRUCTURE_TYPE_GRAPHICS_PIPELINE_CREATE_INFO,
-                pNext: ptr::null(),
-                flags: 0, // TODO: some flags are available but none are critical
+                pNext: feedback_infos
+                    .as_ref()
+                    .map(|f| f as *const _ as *const _)
+                    .unwrap_or(ptr::null()),
+                flags: flags,
                 stageCount: stages.len() as u32,
                 pStages: stages.as_ptr(),
                 pVertexInputState: &vertex_input_state,
@@ -1313,16 +1656,28 @@ impl<Vdef, L, Rp> GraphicsPipeline<Vdef, L, Rp>
 
             let mut output = mem::uninitialized();
             check_errors(vk.CreateGraphicsPipelines(device.internal_object(),
-                                                    0,
+                                                    cache.map(|c| c.internal_object())
+                                                        .unwrap_or(0),
                                                     1,
                                                     &infos,
                                                     ptr::null(),
-                                                    &mut output))?;
+                                                    &mut output),
+                         "vkCreateGraphicsPipelines")?;
             output
         };
 
         let (render_pass, render_pass_subpass) = params.render_pass.into();
 
+        let fixed_state = FixedState {
+            input_assembly: params.input_assembly,
+            viewport: params.viewport.clone(),
+            raster: params.raster.clone(),
+            multisample: params.multisample.clone(),
+            depth_stencil: params.depth_stencil.clone(),
+            blend: params.blend.clone(),
+            indirect_bindable: indirect_bindable,
+        };
+
         Ok(GraphicsPipeline {
                inner: Inner {
                    device: device.clone(),
@@ -1342,14 +1697,24 @@ impl<Vdef, L, Rp> GraphicsPipeline<Vdef, L, Rp>
                dynamic_depth_bounds: params.depth_stencil.depth_bounds_test.is_dynamic(),
                dynamic_stencil_compare_mask: params
                    .depth_stencil
-                   .stencil_back
+                   .stencil_front
                    .compare_mask
                    .is_none(),
-               dynamic_stencil_write_mask: params.depth_stencil.stencil_back.write_mask.is_none(),
-               dynamic_stencil_reference: params.depth_stencil.stencil_back.reference.is_none(),
+               dynamic_stencil_write_mask: params.depth_stencil.stencil_front.write_mask.is_none(),
+               dynamic_stencil_reference: params.depth_stencil.stencil_front.reference.is_none(),
+               dynamic_depth_test_enable: params.depth_stencil.dynamic_depth_test_enable,
+               dynamic_depth_write_enable: params.depth_stencil.dynamic_depth_write_enable,
+               dynamic_depth_compare_op: params.depth_stencil.dynamic_depth_compare_op,
+               dynamic_stencil_test_enable: params.depth_stencil.dynamic_stencil_test_enable,
                dynamic_blend_constants: params.blend.blend_constants.is_none(),
 
                num_viewports: params.viewport.num_viewports(),
+
+               indirect_bindable: indirect_bindable,
+
+               fixed_state: fixed_state,
+
+               feedback: feedback.map(|f| f.into_feedback()),
            })
     }
 }
@@ -1366,6 +1731,15 @@ impl<Mv, L, Rp> GraphicsPipeline<Mv, L, Rp> {
     pub fn device(&self) -> &Arc<Device> {
         &self.inner.device
     }
+
+    /// Returns what the implementation reported about the creation of this pipeline, or `None`
+    /// if the device supports neither Vulkan 1.3 nor `VK_EXT_pipeline_creation_feedback`.
+    ///
+    /// See the `feedback` module.
+    #[inline]
+    pub fn creation_feedback(&self) -> Option<&PipelineCreationFeedback> {
+        self.feedback.as_ref()
+    }
 }
 
 impl<Mv, L, Rp> GraphicsPipeline<Mv, L, Rp>
@@ -1386,6 +1760,80 @@ impl<Mv, L, Rp> GraphicsPipeline<Mv, L, Rp>
     pub fn subpass(&self) -> Subpass<&Rp> {
         Subpass::from(&self.render_pass, self.render_pass_subpass).unwrap()
     }
+
+    /// Checks whether this pipeline can be used in the subpass `subpass` of `render_pass`.
+    ///
+    /// This is the case if `subpass` is the index of the subpass that the pipeline was created
+    /// for, and if `render_pass` is compatible with the render pass that the pipeline was created
+    /// with. The render passes don't need to be the same object. For example a pipeline can be
+    /// used with a render pass that only differs by the load and store operations of its
+    /// attachments. See `ensure_render_pass_compatible` for the exact rules.
+    pub fn is_compatible_with<R>(&self, render_pass: &R, subpass: u32)
+                                 -> Result<(), IncompatibleRenderPassError>
+        where R: ?Sized + RenderPassDesc
+    {
+        if subpass != self.render_pass_subpass {
+            return Err(IncompatibleRenderPassError::SubpassIndexMismatch {
+                           expected: self.render_pass_subpass,
+                           obtained: subpass,
+                       });
+        }
+
+        ensure_render_pass_compatible(&self.render_pass, render_pass)
+    }
+}
+
+impl<Mv, L, Rp> GraphicsPipeline<Mv, L, Rp>
+    where Mv: Clone,
+          Rp: RenderPassDesc + Clone
+{
+    /// Starts building a new graphics pipeline from the configuration of this one.
+    ///
+    /// `f` is passed a builder that already contains the vertex input, the fixed-function states
+    /// and the subpass of this pipeline, and only needs to change what is different before
+    /// calling `build`. For example, after the swapchain has been recreated with a different
+    /// format, `f` can pass the subpass of the new render pass to `render_pass`.
+    ///
+    /// The shaders must always be specified again, because a pipeline doesn't keep its shader
+    /// modules alive.
+    pub fn derive_with<'a, F, R>(&self, f: F) -> R
+        where F: FnOnce(GraphicsPipelineBuilder<'a,
+                                                Mv,
+                                                (),
+                                                (),
+                                                (),
+                                                (),
+                                                (),
+                                                EmptyShaderInterfaceDef,
+                                                EmptyShaderInterfaceDef,
+                                                EmptyPipelineDesc,
+                                                (),
+                                                EmptyShaderInterfaceDef,
+                                                EmptyShaderInterfaceDef,
+                                                EmptyPipelineDesc,
+                                                (),
+                                                EmptyShaderInterfaceDef,
+                                                EmptyShaderInterfaceDef,
+                                                EmptyPipelineDesc,
+                                                (),
+                                                EmptyShaderInterfaceDef,
+                                                EmptyShaderInterfaceDef,
+                                                EmptyPipelineDesc,
+                                                Rp>) -> R
+    {
+        let subpass = Subpass::from(self.render_pass.clone(), self.render_pass_subpass).unwrap();
+        let state = &self.fixed_state;
+
+        f(GraphicsPipelineBuilder::with_state(self.vertex_definition.clone(),
+                                              state.input_assembly,
+                                              state.viewport.clone(),
+                                              state.raster.clone(),
+                                              state.multisample.clone(),
+                                              state.depth_stencil.clone(),
+                                              state.blend.clone(),
+                                              state.indirect_bindable,
+                                              subpass))
+    }
 }
 
 impl<Mv, L, Rp> GraphicsPipeline<Mv, L, Rp> {
@@ -1425,6 +1873,12 @@ impl<Mv, L, Rp> GraphicsPipeline<Mv, L, Rp> {
         self.dynamic_depth_bounds
     }
 
+    /// Returns true if the depth bias used by this pipeline is dynamic.
+    #[inline]
+    pub fn has_dynamic_depth_bias(&self) -> bool {
+        self.dynamic_depth_bias
+    }
+
     /// Returns true if the stencil compare masks used by this pipeline are dynamic.
     #[inline]
     pub fn has_dynamic_stencil_compare_mask(&self) -> bool {
@@ -1442,6 +1896,36 @@ impl<Mv, L, Rp> GraphicsPipeline<Mv, L, Rp> {
     pub fn has_dynamic_stencil_reference(&self) -> bool {
         self.dynamic_stencil_reference
     }
+
+    /// Returns true if whether the depth test is enabled is dynamic for this pipeline.
+    #[inline]
+    pub fn has_dynamic_depth_test_enable(&self) -> bool {
+        self.dynamic_depth_test_enable
+    }
+
+    /// Returns true if whether depth writes are enabled is dynamic for this pipeline.
+    #[inline]
+    pub fn has_dynamic_depth_write_enable(&self) -> bool {
+        self.dynamic_depth_write_enable
+    }
+
+    /// Returns true if the depth compare operation is dynamic for this pipeline.
+    #[inline]
+    pub fn has_dynamic_depth_compare_op(&self) -> bool {
+        self.dynamic_depth_compare_op
+    }
+
+    /// Returns true if whether the stencil test is enabled is dynamic for this pipeline.
+    #[inline]
+    pub fn has_dynamic_stencil_test_enable(&self) -> bool {
+        self.dynamic_stencil_test_enable
+    }
+
+    /// Returns true if this pipeline can be used with generated commands.
+    #[inline]
+    pub fn is_indirect_bindable(&self) -> bool {
+        self.indirect_bindable
+    }
 }
 
 unsafe impl<Mv, L, Rp> PipelineLayoutAbstract for GraphicsPipeline<Mv, L, Rp>
@@ -1602,6 +2086,9 @@ pub unsafe trait GraphicsPipelineAbstract: PipelineLayoutAbstract + RenderPassAb
     /// Returns true if the depth bounds used by this pipeline are dynamic.
     fn has_dynamic_depth_bounds(&self) -> bool;
 
+    /// Returns true if the depth bias used by this pipeline is dynamic.
+    fn has_dynamic_depth_bias(&self) -> bool;
+
     /// Returns true if the stencil compare masks used by this pipeline are dynamic.
     fn has_dynamic_stencil_compare_mask(&self) -> bool;
 
@@ -1610,6 +2097,24 @@ pub unsafe trait GraphicsPipelineAbstract: PipelineLayoutAbstract + RenderPassAb
 
     /// Returns true if the stencil references used by this pipeline are dynamic.
     fn has_dynamic_stencil_reference(&self) -> bool;
+
+    /// Returns true if whether the depth test is enabled is dynamic for this pipeline.
+    fn has_dynamic_depth_test_enable(&self) -> bool;
+
+    /// Returns true if whether depth writes are enabled is dynamic for this pipeline.
+    fn has_dynamic_depth_write_enable(&self) -> bool;
+
+    /// Returns true if the depth compare operation is dynamic for this pipeline.
+    fn has_dynamic_depth_compare_op(&self) -> bool;
+
+    /// Returns true if whether the stencil test is enabled is dynamic for this pipeline.
+    fn has_dynamic_stencil_test_enable(&self) -> bool;
+
+    /// Returns true if this pipeline can be used with generated commands.
+    fn is_indirect_bindable(&self) -> bool;
+
+    /// Returns the index of the subpass that the pipeline was created for.
+    fn subpass_index(&self) -> u32;
 }
 
 unsafe impl<Mv, L, Rp> GraphicsPipelineAbstract for GraphicsPipeline<Mv, L, Rp>
@@ -1647,6 +2152,11 @@ unsafe impl<Mv, L, Rp> GraphicsPipelineAbstract for GraphicsPipeline<Mv, L, Rp>
         self.dynamic_depth_bounds
     }
 
+    #[inline]
+    fn has_dynamic_depth_bias(&self) -> bool {
+        self.dynamic_depth_bias
+    }
+
     #[inline]
     fn has_dynamic_stencil_compare_mask(&self) -> bool {
         self.dynamic_stencil_compare_mask
@@ -1661,6 +2171,36 @@ unsafe impl<Mv, L, Rp> GraphicsPipelineAbstract for GraphicsPipeline<Mv, L, Rp>
     fn has_dynamic_stencil_reference(&self) -> bool {
         self.dynamic_stencil_reference
     }
+
+    #[inline]
+    fn has_dynamic_depth_test_enable(&self) -> bool {
+        self.dynamic_depth_test_enable
+    }
+
+    #[inline]
+    fn has_dynamic_depth_write_enable(&self) -> bool {
+        self.dynamic_depth_write_enable
+    }
+
+    #[inline]
+    fn has_dynamic_depth_compare_op(&self) -> bool {
+        self.dynamic_depth_compare_op
+    }
+
+    #[inline]
+    fn has_dynamic_stencil_test_enable(&self) -> bool {
+        self.dynamic_stencil_test_enable
+    }
+
+    #[inline]
+    fn is_indirect_bindable(&self) -> bool {
+        self.indirect_bindable
+    }
+
+    #[inline]
+    fn subpass_index(&self) -> u32 {
+        self.render_pass_subpass
+    }
 }
 
 unsafe impl<T> GraphicsPipelineAbstract for T
@@ -1697,6 +2237,11 @@ unsafe impl<T> GraphicsPipelineAbstract for T
         (**self).has_dynamic_depth_bounds()
     }
 
+    #[inline]
+    fn has_dynamic_depth_bias(&self) -> bool {
+        (**self).has_dynamic_depth_bias()
+    }
+
     #[inline]
     fn has_dynamic_stencil_compare_mask(&self) -> bool {
         (**self).has_dynamic_stencil_compare_mask()
@@ -1711,6 +2256,36 @@ unsafe impl<T> GraphicsPipelineAbstract for T
     fn has_dynamic_stencil_reference(&self) -> bool {
         (**self).has_dynamic_stencil_reference()
     }
+
+    #[inline]
+    fn has_dynamic_depth_test_enable(&self) -> bool {
+        (**self).has_dynamic_depth_test_enable()
+    }
+
+    #[inline]
+    fn has_dynamic_depth_write_enable(&self) -> bool {
+        (**self).has_dynamic_depth_write_enable()
+    }
+
+    #[inline]
+    fn has_dynamic_depth_compare_op(&self) -> bool {
+        (**self).has_dynamic_depth_compare_op()
+    }
+
+    #[inline]
+    fn has_dynamic_stencil_test_enable(&self) -> bool {
+        (**self).has_dynamic_stencil_test_enable()
+    }
+
+    #[inline]
+    fn is_indirect_bindable(&self) -> bool {
+        (**self).is_indirect_bindable()
+    }
+
+    #[inline]
+    fn subpass_index(&self) -> u32 {
+        (**self).subpass_index()
+    }
 }
 
 /// Opaque object that represents the inside of the graphics pipeline.
@@ -1749,6 +2324,45 @@ unsafe impl<Mv, L, Rp, S> VertexSource<S> for GraphicsPipeline<Mv, L, Rp>
     }
 }
 
+// The last shader stage that runs before the rasterizer, and what it does with `PointSize`.
+#[derive(Debug, Copy, Clone)]
+enum LastPreRasterizationStage {
+    Vertex(Option<PointSizeOutput>),
+    TessellationOrGeometry(Option<PointSizeOutput>),
+}
+
+// Checks that the size of the points is written if the pipeline rasterizes points.
+//
+// A vertex shader outputs points if the topology is a list of points. Points whose size isn't
+// written have a size of 1.0 if `maintenance5` is enabled, or if they come from a tessellation or
+// geometry shader and the `shader_tessellation_and_geometry_point_size` feature is disabled.
+fn check_point_size(last_stage: LastPreRasterizationStage, topology: PrimitiveTopology,
+                    features: &Features, maintenance5: bool)
+                    -> Result<(), GraphicsPipelineCreationError> {
+    let (outputs_points, output) = match last_stage {
+        LastPreRasterizationStage::Vertex(output) => {
+            (topology == PrimitiveTopology::PointList, output)
+        },
+        LastPreRasterizationStage::TessellationOrGeometry(output) => {
+            if !features.shader_tessellation_and_geometry_point_size {
+                return Ok(());
+            }
+            (output.map(|o| o.outputs_points).unwrap_or(false), output)
+        },
+    };
+
+    let writes_point_size = match output {
+        Some(output) => output.writes_point_size,
+        None => return Ok(()),
+    };
+
+    if outputs_points && !writes_point_size && !maintenance5 {
+        return Err(GraphicsPipelineCreationError::PointSizeNotWritten);
+    }
+
+    Ok(())
+}
+
 /// Error that can happen when creating a graphics pipeline.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum GraphicsPipelineCreationError {
@@ -1786,6 +2400,15 @@ pub enum GraphicsPipelineCreationError {
     /// expects.
     FragmentShaderRenderPassIncompatible,
 
+    /// The subpass index passed to `GraphicsPipelineBuilder::render_pass_index` is out of range
+    /// of the render pass.
+    InvalidSubpass {
+        /// Index of the subpass that was requested.
+        requested: u32,
+        /// Number of subpasses in the render pass.
+        available: u32,
+    },
+
     /// The vertex definition is not compatible with the input of the vertex shader.
     IncompatibleVertexDefinition(IncompatibleVertexDefinitionError),
 
@@ -1854,6 +2477,19 @@ pub enum GraphicsPipelineCreationError {
     /// The `depth_clamp` feature must be enabled in order to use depth clamping.
     DepthClampFeatureNotEnabled,
 
+    /// The `ext_depth_clip_enable` extension must be enabled, and the physical device must
+    /// support its `depthClipEnable` feature, in order to control depth clipping independently of
+    /// depth clamping.
+    DepthClipEnableExtensionNotEnabled,
+
+    /// The `nv_device_generated_commands` extension must be enabled in order to create a pipeline
+    /// that can be used with generated commands.
+    DeviceGeneratedCommandsExtensionNotEnabled,
+
+    /// The depth state was built in strict mode and has no effect, even though the subpass has a
+    /// depth attachment.
+    DepthStateHasNoEffect,
+
     /// The `depth_bias_clamp` feature must be enabled in order to use a depth bias clamp different
     /// from 0.0.
     DepthBiasClampFeatureNotEnabled,
@@ -1865,18 +2501,38 @@ pub enum GraphicsPipelineCreationError {
     /// The `depth_bounds` feature must be enabled in order to use depth bounds testing.
     DepthBoundsFeatureNotEnabled,
 
+    /// The depth bounds must be between 0.0 and 1.0, and the minimum must not be greater than the
+    /// maximum.
+    DepthBoundsInvalidRange,
+
+    /// The `ext_extended_dynamic_state` extension must be enabled in order to set the depth test
+    /// enable, depth write enable, depth compare op or stencil test enable as dynamic.
+    ExtendedDynamicStateExtensionNotEnabled,
+
     /// The requested stencil test is invalid.
     WrongStencilState,
 
     /// The primitives topology does not match what the geometry shader expects.
     TopologyNotMatchingGeometryShader,
 
+    /// The pipeline draws points, but the last shader stage before rasterization doesn't write
+    /// `gl_PointSize`.
+    PointSizeNotWritten,
+
     /// The `geometry_shader` feature must be enabled in order to use geometry shaders.
     GeometryShaderFeatureNotEnabled,
 
     /// The `tessellation_shader` feature must be enabled in order to use tessellation shaders.
     TessellationShaderFeatureNotEnabled,
 
+    /// The `shader_f3264` feature must be enabled in order to use 64-bit floating-point vertex
+    /// attributes.
+    ShaderFloat64FeatureNotEnabled,
+
+    /// The `shader_int64` feature must be enabled in order to use 64-bit integer vertex
+    /// attributes.
+    ShaderInt64FeatureNotEnabled,
+
     /// The number of attachments specified in the blending does not match the number of
     /// attachments in the subpass.
     MismatchBlendingAttachmentsCount,
@@ -1892,16 +2548,35 @@ pub enum GraphicsPipelineCreationError {
     /// depth writing is enabled and the depth attachment is read-only.
     NoDepthAttachment,
 
-    /// The stencil test requires a stencil attachment but render pass has no stencil attachment, or
-    /// stencil writing is enabled and the stencil attachment is read-only.
+    /// The stencil test requires a stencil attachment but render pass has no stencil attachment.
     NoStencilAttachment,
 
+    /// The stencil operations can modify the stencil attachment, but the attachment is in the
+    /// `DepthStencilReadOnlyOptimal` layout.
+    StencilAttachmentReadOnly,
+
     /// Tried to use a patch list without a tessellation shader, or a non-patch-list with a
     /// tessellation shader.
     InvalidPrimitiveTopology,
 
     /// The `maxTessellationPatchSize` limit was exceeded.
     MaxTessellationPatchSizeExceeded,
+
+    /// The number of rasterization samples of the multisample state doesn't match the number of
+    /// samples of the attachments of the subpass.
+    RasterizationSamplesMismatch {
+        /// Number of samples in the multisample state of the pipeline.
+        pipeline: u32,
+        /// Number of samples of the attachments of the subpass.
+        subpass: u32,
+    },
+
+    /// Some states required to build the pipeline haven't been set on the
+    /// `GraphicsPipelineBuilder`.
+    IncompleteDefinition {
+        /// Names of all the missing states, for example `"vertex shader"` or `"render pass"`.
+        missing: Vec<&'static str>,
+    },
 }
 
 impl error::Error for GraphicsPipelineCreationError {
@@ -1942,6 +2617,9 @@ impl error::Error for GraphicsPipelineCreationError {
                 "the output of the fragment shader is not compatible with what the render pass \
                  subpass expects"
             },
+            GraphicsPipelineCreationError::InvalidSubpass { .. } => {
+                "the subpass index is out of range of the render pass"
+            },
             GraphicsPipelineCreationError::IncompatibleVertexDefinition(_) => {
                 "the vertex definition is not compatible with the input of the vertex shader"
             },
@@ -1982,6 +2660,18 @@ impl error::Error for GraphicsPipelineCreationError {
             GraphicsPipelineCreationError::DepthClampFeatureNotEnabled => {
                 "the `depth_clamp` feature must be enabled in order to use depth clamping"
             },
+            GraphicsPipelineCreationError::DepthClipEnableExtensionNotEnabled => {
+                "the `ext_depth_clip_enable` extension must be enabled in order to control depth \
+                 clipping independently of depth clamping"
+            },
+            GraphicsPipelineCreationError::DeviceGeneratedCommandsExtensionNotEnabled => {
+                "the `nv_device_generated_commands` extension must be enabled in order to create \
+                 a pipeline that can be used with generated commands"
+            },
+            GraphicsPipelineCreationError::DepthStateHasNoEffect => {
+                "the depth state was built in strict mode and has no effect, even though the \
+                 subpass has a depth attachment"
+            },
             GraphicsPipelineCreationError::DepthBiasClampFeatureNotEnabled => {
                 "the `depth_bias_clamp` feature must be enabled in order to use a depth bias \
                  clamp different from 0.0."
@@ -1993,12 +2683,25 @@ impl error::Error for GraphicsPipelineCreationError {
             GraphicsPipelineCreationError::DepthBoundsFeatureNotEnabled => {
                 "the `depth_bounds` feature must be enabled in order to use depth bounds testing"
             },
+            GraphicsPipelineCreationError::DepthBoundsInvalidRange => {
+                "the depth bounds must be between 0.0 and 1.0, and the minimum must not be \
+                 greater than the maximum"
+            },
+            GraphicsPipelineCreationError::ExtendedDynamicStateExtensionNotEnabled => {
+                "the `ext_extended_dynamic_state` extension must be enabled in order to set the \
+                 depth test enable, depth write enable, depth compare op or stencil test enable \
+                 as dynamic"
+            },
             GraphicsPipelineCreationError::WrongStencilState => {
                 "the requested stencil test is invalid"
             },
             GraphicsPipelineCreationError::TopologyNotMatchingGeometryShader => {
                 "the primitives topology does not match what the geometry shader expects"
             },
+            GraphicsPipelineCreationError::PointSizeNotWritten => {
+                "the pipeline draws points, but the last shader stage before rasterization \
+                 doesn't write `gl_PointSize`"
+            },
             GraphicsPipelineCreationError::GeometryShaderFeatureNotEnabled => {
                 "the `geometry_shader` feature must be enabled in order to use geometry shaders"
             },
@@ -2006,6 +2709,14 @@ impl error::Error for GraphicsPipelineCreationError {
                 "the `tessellation_shader` feature must be enabled in order to use tessellation \
                  shaders"
             },
+            GraphicsPipelineCreationError::ShaderFloat64FeatureNotEnabled => {
+                "the `shader_f3264` feature must be enabled in order to use 64-bit floating-point \
+                 vertex attributes"
+            },
+            GraphicsPipelineCreationError::ShaderInt64FeatureNotEnabled => {
+                "the `shader_int64` feature must be enabled in order to use 64-bit integer vertex \
+                 attributes"
+            },
             GraphicsPipelineCreationError::MismatchBlendingAttachmentsCount => {
                 "the number of attachments specified in the blending does not match the number of \
                  attachments in the subpass"
@@ -2023,6 +2734,10 @@ impl error::Error for GraphicsPipelineCreationError {
             GraphicsPipelineCreationError::NoStencilAttachment => {
                 "the stencil attachment of the render pass does not match the stencil test"
             },
+            GraphicsPipelineCreationError::StencilAttachmentReadOnly => {
+                "the stencil operations can modify the stencil attachment, but the attachment is \
+                 read-only"
+            },
             GraphicsPipelineCreationError::InvalidPrimitiveTopology => {
                 "trying to use a patch list without a tessellation shader, or a non-patch-list \
                  with a tessellation shader"
@@ -2030,6 +2745,13 @@ impl error::Error for GraphicsPipelineCreationError {
             GraphicsPipelineCreationError::MaxTessellationPatchSizeExceeded => {
                 "the maximum tessellation patch size was exceeded"
             },
+            GraphicsPipelineCreationError::RasterizationSamplesMismatch { .. } => {
+                "the number of rasterization samples doesn't match the number of samples of the \
+                 attachments of the subpass"
+            },
+            GraphicsPipelineCreationError::IncompleteDefinition { .. } => {
+                "some states required to build the pipeline haven't been set"
+            },
         }
     }
 
@@ -2054,7 +2776,15 @@ impl error::Error for GraphicsPipelineCreationError {
 impl fmt::Display for GraphicsPipelineCreationError {
     #[inline]
     fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        write!(fmt, "{}", error::Error::description(self))
+        match *self {
+            GraphicsPipelineCreationError::IncompleteDefinition { ref missing } => {
+                write!(fmt,
+                       "{}: {}",
+                       error::Error::description(self),
+                       missing.join(", "))
+            },
+            _ => write!(fmt, "{}", error::Error::description(self)),
+        }
     }
 }
 
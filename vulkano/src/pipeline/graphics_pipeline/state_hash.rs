@@ -0,0 +1,340 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Functions that feed the parameters of a graphics pipeline to a `StateHasher`.
+//!
+//! Floating-point values are hashed by their bits. Every variable-length list is preceded by its
+//! length, and every optional value by whether it is present, so that two different sets of
+//! parameters can't produce the same stream of bytes.
+
+use std::ffi::CStr;
+use std::hash::Hasher;
+
+use SafeDeref;
+use device::Device;
+use framebuffer::RenderPassDesc;
+use image::ImageLayout;
+use pipeline::blend::AttachmentBlend;
+use pipeline::blend::AttachmentsBlend;
+use pipeline::blend::Blend;
+use pipeline::depth_stencil::DepthBounds;
+use pipeline::depth_stencil::DepthStencil;
+use pipeline::depth_stencil::StencilOpState;
+use pipeline::input_assembly::InputAssembly;
+use pipeline::input_assembly::PrimitiveTopology;
+use pipeline::multisample::Multisample;
+use pipeline::raster::DepthBiasControl;
+use pipeline::raster::Rasterization;
+use pipeline::registry::StateHasher;
+use pipeline::shader::ShaderModule;
+use pipeline::vertex::AttributeInfo;
+use pipeline::vertex::InputRate;
+use pipeline::viewport::Scissor;
+use pipeline::viewport::Viewport;
+use pipeline::viewport::ViewportsState;
+use vk;
+
+#[inline]
+fn hash_bool(h: &mut StateHasher, value: bool) {
+    h.write_u8(value as u8);
+}
+
+#[inline]
+fn hash_f32(h: &mut StateHasher, value: f32) {
+    h.write_u32(value.to_bits());
+}
+
+#[inline]
+fn hash_option_u32(h: &mut StateHasher, value: Option<u32>) {
+    hash_bool(h, value.is_some());
+    if let Some(value) = value {
+        h.write_u32(value);
+    }
+}
+
+#[inline]
+fn hash_option_f32(h: &mut StateHasher, value: Option<f32>) {
+    hash_bool(h, value.is_some());
+    if let Some(value) = value {
+        hash_f32(h, value);
+    }
+}
+
+/// Hashes a shader stage. `None` means that the stage is absent.
+pub fn hash_shader<P>(h: &mut StateHasher, shader: Option<(&ShaderModule<P>, &CStr)>)
+    where P: SafeDeref<Target = Device>
+{
+    hash_bool(h, shader.is_some());
+    if let Some((module, name)) = shader {
+        h.write_u64(module.spirv_hash());
+        let name = name.to_bytes();
+        h.write_usize(name.len());
+        h.write(name);
+    }
+}
+
+/// Hashes the result of `VertexDefinition::definition`.
+pub fn hash_vertex_definition<B, A>(h: &mut StateHasher, buffers: B, attribs: A)
+    where B: ExactSizeIterator<Item = (u32, usize, InputRate)>,
+          A: ExactSizeIterator<Item = (u32, u32, AttributeInfo)>
+{
+    h.write_usize(buffers.len());
+    for (binding, stride, rate) in buffers {
+        h.write_u32(binding);
+        h.write_usize(stride);
+        h.write_u32(rate as u32);
+    }
+
+    h.write_usize(attribs.len());
+    for (location, binding, info) in attribs {
+        h.write_u32(location);
+        h.write_u32(binding);
+        h.write_usize(info.offset);
+        h.write_u32(info.format as u32);
+    }
+}
+
+pub fn hash_input_assembly(h: &mut StateHasher, input_assembly: &InputAssembly) {
+    let topology: vk::PrimitiveTopology = input_assembly.topology.into();
+    h.write_u32(topology);
+    if let PrimitiveTopology::PatchList { vertices_per_patch } = input_assembly.topology {
+        h.write_u32(vertices_per_patch);
+    }
+    hash_bool(h, input_assembly.primitive_restart_enable);
+}
+
+pub fn hash_viewports(h: &mut StateHasher, viewports: Option<&ViewportsState>) {
+    fn viewport(h: &mut StateHasher, viewport: &Viewport) {
+        hash_f32(h, viewport.origin[0]);
+        hash_f32(h, viewport.origin[1]);
+        hash_f32(h, viewport.dimensions[0]);
+        hash_f32(h, viewport.dimensions[1]);
+        hash_f32(h, viewport.depth_range.start);
+        hash_f32(h, viewport.depth_range.end);
+    }
+
+    fn scissor(h: &mut StateHasher, scissor: &Scissor) {
+        h.write_i32(scissor.origin[0]);
+        h.write_i32(scissor.origin[1]);
+        h.write_u32(scissor.dimensions[0]);
+        h.write_u32(scissor.dimensions[1]);
+    }
+
+    match viewports {
+        None => h.write_u8(0),
+        Some(&ViewportsState::Fixed { ref data }) => {
+            h.write_u8(1);
+            h.write_usize(data.len());
+            for &(ref v, ref s) in data.iter() {
+                viewport(h, v);
+                scissor(h, s);
+            }
+        },
+        Some(&ViewportsState::DynamicViewports { ref scissors }) => {
+            h.write_u8(2);
+            h.write_usize(scissors.len());
+            for s in scissors.iter() {
+                scissor(h, s);
+            }
+        },
+        Some(&ViewportsState::DynamicScissors { ref viewports }) => {
+            h.write_u8(3);
+            h.write_usize(viewports.len());
+            for v in viewports.iter() {
+                viewport(h, v);
+            }
+        },
+        Some(&ViewportsState::Dynamic { num }) => {
+            h.write_u8(4);
+            h.write_u32(num);
+        },
+    }
+}
+
+pub fn hash_raster(h: &mut StateHasher, raster: &Rasterization) {
+    hash_bool(h, raster.depth_clamp);
+    match raster.depth_clip {
+        None => h.write_u8(0),
+        Some(clip) => {
+            h.write_u8(1);
+            hash_bool(h, clip);
+        },
+    }
+    hash_bool(h, raster.rasterizer_discard);
+    h.write_u32(raster.polygon_mode as u32);
+    h.write_u32(raster.cull_mode as u32);
+    h.write_u32(raster.front_face as u32);
+    hash_option_f32(h, raster.line_width);
+
+    match raster.depth_bias {
+        DepthBiasControl::Disabled => h.write_u8(0),
+        DepthBiasControl::Dynamic => h.write_u8(1),
+        DepthBiasControl::Static(bias) => {
+            h.write_u8(2);
+            hash_f32(h, bias.constant_factor);
+            hash_f32(h, bias.clamp);
+            hash_f32(h, bias.slope_factor);
+        },
+    }
+}
+
+pub fn hash_multisample(h: &mut StateHasher, multisample: &Multisample) {
+    h.write_u32(multisample.rasterization_samples);
+    for &mask in multisample.sample_mask.iter() {
+        h.write_u32(mask);
+    }
+    hash_option_f32(h, multisample.sample_shading);
+    hash_bool(h, multisample.alpha_to_coverage);
+    hash_bool(h, multisample.alpha_to_one);
+}
+
+pub fn hash_depth_stencil(h: &mut StateHasher, depth_stencil: &DepthStencil) {
+    fn stencil(h: &mut StateHasher, state: &StencilOpState) {
+        h.write_u32(state.compare_op as u32);
+        h.write_u32(state.pass_op as u32);
+        h.write_u32(state.fail_op as u32);
+        h.write_u32(state.depth_fail_op as u32);
+        hash_option_u32(h, state.compare_mask);
+        hash_option_u32(h, state.write_mask);
+        hash_option_u32(h, state.reference);
+    }
+
+    h.write_u32(depth_stencil.depth_compare as u32);
+    hash_bool(h, depth_stencil.depth_write);
+
+    match depth_stencil.depth_bounds_test {
+        DepthBounds::Disabled => h.write_u8(0),
+        DepthBounds::Fixed(ref range) => {
+            h.write_u8(1);
+            hash_f32(h, range.start);
+            hash_f32(h, range.end);
+        },
+        DepthBounds::Dynamic => h.write_u8(2),
+    }
+
+    stencil(h, &depth_stencil.stencil_front);
+    stencil(h, &depth_stencil.stencil_back);
+
+    hash_bool(h, depth_stencil.dynamic_depth_test_enable);
+    hash_bool(h, depth_stencil.dynamic_depth_write_enable);
+    hash_bool(h, depth_stencil.dynamic_depth_compare_op);
+    hash_bool(h, depth_stencil.dynamic_stencil_test_enable);
+}
+
+pub fn hash_blend(h: &mut StateHasher, blend: &Blend) {
+    fn attachment(h: &mut StateHasher, blend: &AttachmentBlend) {
+        hash_bool(h, blend.enabled);
+        h.write_u32(blend.color_op as u32);
+        h.write_u32(blend.color_src as u32);
+        h.write_u32(blend.color_dst as u32);
+        h.write_u32(blend.alpha_op as u32);
+        h.write_u32(blend.alpha_src as u32);
+        h.write_u32(blend.alpha_dst as u32);
+        hash_bool(h, blend.mask_red);
+        hash_bool(h, blend.mask_green);
+        hash_bool(h, blend.mask_blue);
+        hash_bool(h, blend.mask_alpha);
+    }
+
+    hash_option_u32(h, blend.logic_op.map(|op| op as u32));
+
+    match blend.attachments {
+        AttachmentsBlend::Collective(ref blend) => {
+            h.write_u8(0);
+            attachment(h, blend);
+        },
+        AttachmentsBlend::Individual(ref blends) => {
+            h.write_u8(1);
+            h.write_usize(blends.len());
+            for blend in blends.iter() {
+                attachment(h, blend);
+            }
+        },
+    }
+
+    hash_bool(h, blend.blend_constants.is_some());
+    if let Some(constants) = blend.blend_constants {
+        for &c in constants.iter() {
+            hash_f32(h, c);
+        }
+    }
+}
+
+/// Hashes the description of a render pass and the index of a subpass within it.
+///
+/// Two render passes with the same description are compatible, so a pipeline created with one
+/// of them can be used with the other.
+pub fn hash_subpass<Rp>(h: &mut StateHasher, subpass: Option<(&Rp, u32)>)
+    where Rp: ?Sized + RenderPassDesc
+{
+    fn attachments(h: &mut StateHasher, list: &[(usize, ImageLayout)]) {
+        h.write_usize(list.len());
+        for &(num, layout) in list.iter() {
+            h.write_usize(num);
+            h.write_u32(layout as u32);
+        }
+    }
+
+    let (render_pass, index) = match subpass {
+        Some(s) => s,
+        None => {
+            h.write_u8(0);
+            return;
+        },
+    };
+
+    h.write_u8(1);
+    h.write_u32(index);
+
+    h.write_usize(render_pass.num_attachments());
+    for desc in (0 .. render_pass.num_attachments())
+        .filter_map(|n| render_pass.attachment_desc(n))
+    {
+        h.write_u32(desc.format as u32);
+        h.write_u32(desc.samples);
+        h.write_u32(desc.load as u32);
+        h.write_u32(desc.store as u32);
+        h.write_u32(desc.stencil_load as u32);
+        h.write_u32(desc.stencil_store as u32);
+        h.write_u32(desc.initial_layout as u32);
+        h.write_u32(desc.final_layout as u32);
+    }
+
+    h.write_usize(render_pass.num_subpasses());
+    for desc in (0 .. render_pass.num_subpasses()).filter_map(|n| render_pass.subpass_desc(n)) {
+        attachments(h, &desc.color_attachments);
+        hash_bool(h, desc.depth_stencil.is_some());
+        if let Some(depth_stencil) = desc.depth_stencil {
+            attachments(h, &[depth_stencil]);
+        }
+        attachments(h, &desc.input_attachments);
+        attachments(h, &desc.resolve_attachments);
+        h.write_usize(desc.preserve_attachments.len());
+        for &num in desc.preserve_attachments.iter() {
+            h.write_usize(num);
+        }
+    }
+
+    h.write_usize(render_pass.num_dependencies());
+    for desc in (0 .. render_pass.num_dependencies())
+        .filter_map(|n| render_pass.dependency_desc(n))
+    {
+        let src_stages: vk::PipelineStageFlagBits = desc.src_stages.into();
+        let dst_stages: vk::PipelineStageFlagBits = desc.dst_stages.into();
+        let src_access: vk::AccessFlagBits = desc.src_access.into();
+        let dst_access: vk::AccessFlagBits = desc.dst_access.into();
+        h.write_usize(desc.source_subpass);
+        h.write_usize(desc.destination_subpass);
+        h.write_u32(src_stages);
+        h.write_u32(dst_stages);
+        h.write_u32(src_access);
+        h.write_u32(dst_access);
+        hash_bool(h, desc.by_region);
+    }
+}
@@ -100,6 +100,9 @@ impl PrimitiveTopology {
 pub unsafe trait Index {
     /// Returns the type of data.
     fn ty() -> IndexType;
+
+    /// Returns the value of the index.
+    fn to_u32(&self) -> u32;
 }
 
 unsafe impl Index for u16 {
@@ -107,6 +110,11 @@ unsafe impl Index for u16 {
     fn ty() -> IndexType {
         IndexType::U16
     }
+
+    #[inline(always)]
+    fn to_u32(&self) -> u32 {
+        *self as u32
+    }
 }
 
 unsafe impl Index for u32 {
@@ -114,6 +122,11 @@ unsafe impl Index for u32 {
     fn ty() -> IndexType {
         IndexType::U32
     }
+
+    #[inline(always)]
+    fn to_u32(&self) -> u32 {
+        *self as u32
+    }
 }
 
 /// An enumeration of all valid index types.
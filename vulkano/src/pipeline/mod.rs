@@ -53,8 +53,8 @@
 //! - The output of the fragment shader is written to the framebuffer attachments, possibly by
 //!   mixing it with the existing values.
 //!
-//! All the sub-modules of this module (with the exception of `cache`) correspond to the various
-//! stages of graphical pipelines.
+//! All the sub-modules of this module (with the exception of `cache`, `feedback`, `registry` and
+//! `subgroup_size`) correspond to the various stages of graphical pipelines.
 //!
 //! > **Note**: With the exception of the addition of the tessellation shaders and the geometry
 //! > shader, these steps haven't changed in the past decade. If you are familiar with shaders in
@@ -79,6 +79,9 @@ pub use self::compute_pipeline::ComputePipeline;
 pub use self::compute_pipeline::ComputePipelineAbstract;
 pub use self::compute_pipeline::ComputePipelineCreationError;
 pub use self::compute_pipeline::ComputePipelineSys;
+pub use self::compute_variants::ComputePipelineVariants;
+pub use self::compute_variants::ComputePipelineVariantsError;
+pub use self::compute_variants::LazyComputePipelineVariants;
 pub use self::graphics_pipeline::GraphicsPipeline;
 pub use self::graphics_pipeline::GraphicsPipelineAbstract;
 pub use self::graphics_pipeline::GraphicsPipelineBuilder;
@@ -88,14 +91,19 @@ pub use self::graphics_pipeline::GraphicsPipelineParamsTess;
 pub use self::graphics_pipeline::GraphicsPipelineSys;
 
 mod compute_pipeline;
+mod compute_variants;
 mod graphics_pipeline;
 
 pub mod blend;
 pub mod cache;
 pub mod depth_stencil;
+pub mod feedback;
 pub mod input_assembly;
 pub mod multisample;
 pub mod raster;
+pub mod registry;
 pub mod shader;
+pub mod shader_object;
+pub mod subgroup_size;
 pub mod vertex;
 pub mod viewport;
@@ -20,8 +20,16 @@ use vk;
 pub struct Rasterization {
     /// If true, then the depth value of the vertices will be clamped to [0.0 ; 1.0]. If false,
     /// fragments whose depth is outside of this range will be discarded.
+    ///
+    /// Unless `depth_clip` says otherwise, enabling depth clamping also disables depth clipping.
     pub depth_clamp: bool,
 
+    /// Whether primitives are clipped against the near and far planes, independently of
+    /// `depth_clamp`. Requires the `ext_depth_clip_enable` extension if not `None`.
+    ///
+    /// If `None`, primitives are clipped if and only if `depth_clamp` is false.
+    pub depth_clip: Option<bool>,
+
     /// If true, all the fragments will be discarded. This is usually used when your vertex shader
     /// has some side effects and you don't need to run the fragment shader.
     pub rasterizer_discard: bool,
@@ -42,6 +50,7 @@ pub struct Rasterization {
     /// need to be set when you build the command buffer.
     pub line_width: Option<f32>,
 
+    /// Depth bias to add to the depth value of the fragments.
     pub depth_bias: DepthBiasControl,
 }
 
@@ -50,6 +59,7 @@ impl Default for Rasterization {
     fn default() -> Rasterization {
         Rasterization {
             depth_clamp: false,
+            depth_clip: None,
             rasterizer_discard: false,
             polygon_mode: Default::default(),
             cull_mode: Default::default(),
@@ -60,6 +70,14 @@ impl Default for Rasterization {
     }
 }
 
+impl Rasterization {
+    /// Returns true if primitives will be clipped against the near and far planes.
+    #[inline]
+    pub fn depth_clip_enabled(&self) -> bool {
+        self.depth_clip.unwrap_or(!self.depth_clamp)
+    }
+}
+
 #[derive(Copy, Clone, Debug)]
 pub enum DepthBiasControl {
     Disabled,
@@ -77,7 +95,7 @@ impl DepthBiasControl {
     }
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct DepthBias {
     pub constant_factor: f32,
     /// Requires the `depth_bias_clamp` feature to be enabled.
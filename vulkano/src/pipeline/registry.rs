@@ -0,0 +1,505 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! In-process registry of pipeline objects, indexed by a hash of their description.
+//!
+//! A `PipelineCache` lets the Vulkan implementation skip the compilation of pipelines that it
+//! has already seen, but each call to `vkCreateGraphicsPipelines` still has a cost. A
+//! `PipelineRegistry` goes one step further by memoizing the pipeline objects themselves. When
+//! asked for a pipeline whose key is already known, it returns a clone of the existing `Arc`
+//! without calling Vulkan at all.
+//!
+//! The key is a `u64` that must uniquely identify the description of the pipeline. You can
+//! compute it with your own content hashes, or use `GraphicsPipelineBuilder::state_hash` and
+//! `ComputePipeline::state_hash` which hash all the parameters of the pipeline, including the
+//! SPIR-V code of the shaders. The key doesn't need to identify the device, as the registry
+//! stores the pipelines of each device separately.
+//!
+//! # Example
+//!
+//! ```ignore
+//! let registry = PipelineRegistry::new(256);
+//!
+//! let builder = GraphicsPipeline::start()
+//!     .vertex_input_single_buffer::<Vertex>()
+//!     .vertex_shader(vs.main_entry_point(), ())
+//!     .triangle_list()
+//!     .viewports_dynamic_scissors_irrelevant(1)
+//!     .fragment_shader(fs.main_entry_point(), ())
+//!     .render_pass(Subpass::from(render_pass.clone(), 0).unwrap());
+//!
+//! let pipeline = registry.get_or_create(&device, builder.state_hash(),
+//!                                       || builder.build(device.clone()))?;
+//! ```
+//!
+//! # Bound and eviction
+//!
+//! The registry holds at most `capacity` pipelines. When a new pipeline is inserted in a full
+//! registry, the least recently used pipeline is removed from it. The pipeline itself is only
+//! destroyed once all the other `Arc`s that point to it have been dropped.
+//!
+//! Looking up a pipeline only updates its time of last use. The order of the pipelines is kept
+//! in a priority queue that is only updated when a pipeline is inserted or evicted, which makes
+//! finding the least recently used pipeline cost `O(log n)` on average.
+//!
+//! # Concurrency
+//!
+//! The entries are spread among several shards, each protected by a `RwLock`. Looking up a
+//! pipeline that is already in the registry only takes a read lock, so that threads that only
+//! read from the registry never block each other.
+//!
+//! Pipelines are created without any lock being held. If multiple threads ask for the same
+//! missing key at the same time, each of them may create a pipeline, but only the first one to
+//! finish is kept and returned to all of them.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::collections::HashMap;
+use std::hash::Hasher;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::RwLock;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+
+use device::Device;
+
+// Number of shards of the registry. Must be a power of two.
+const NUM_SHARDS: usize = 16;
+
+/// Memoizes pipelines of type `T` by a hash of their description.
+///
+/// See [the module-level documentation](index.html) for more information.
+pub struct PipelineRegistry<T> {
+    shards: Vec<RwLock<HashMap<Key, Entry<T>>>>,
+    // Contains one item per entry of the registry, whose `last_use` is at most the one of the
+    // entry. The items are only brought up to date when they reach the top of the queue.
+    queue: Mutex<BinaryHeap<Reverse<QueueItem>>>,
+    capacity: usize,
+    // Number of entries in all the shards.
+    len: AtomicUsize,
+    // Incremented each time an entry is accessed. Used to determine the least recently used
+    // entry.
+    clock: AtomicUsize,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+    evictions: AtomicUsize,
+}
+
+// The address of the device, and the key given by the user. The pipelines of the registry keep
+// their device alive, so the address of a device can't be reused while it has entries.
+type Key = (usize, u64);
+
+struct Entry<T> {
+    pipeline: Arc<T>,
+    last_use: AtomicUsize,
+    // Distinguishes this entry from the previous ones that had the same key.
+    id: usize,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct QueueItem {
+    // Must be the first field, as the items are ordered by it.
+    last_use: usize,
+    key: Key,
+    id: usize,
+}
+
+impl<T> PipelineRegistry<T> {
+    /// Builds a new empty registry that can hold up to `capacity` pipelines.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `capacity` is 0.
+    ///
+    pub fn new(capacity: usize) -> PipelineRegistry<T> {
+        assert_ne!(capacity, 0);
+
+        PipelineRegistry {
+            shards: (0 .. NUM_SHARDS).map(|_| RwLock::new(HashMap::new())).collect(),
+            queue: Mutex::new(BinaryHeap::new()),
+            capacity: capacity,
+            len: AtomicUsize::new(0),
+            clock: AtomicUsize::new(0),
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+            evictions: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns the maximum number of pipelines that the registry can hold.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of pipelines currently in the registry.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::Relaxed)
+    }
+
+    /// Returns the pipeline of `device` that corresponds to `key`, if it is in the registry.
+    ///
+    /// This counts as a use of the pipeline for the purpose of eviction, but doesn't modify the
+    /// statistics of the registry.
+    pub fn get(&self, device: &Device, key: u64) -> Option<Arc<T>> {
+        let key = (device_address(device), key);
+        let shard = self.shard(key).read().unwrap();
+        shard.get(&key).map(|entry| {
+            entry.last_use.store(self.tick(), Ordering::Relaxed);
+            entry.pipeline.clone()
+        })
+    }
+
+    /// Returns the pipeline of `device` that corresponds to `key`, or calls `create` and inserts
+    /// the pipeline it returns in the registry if there is none.
+    ///
+    /// Errors returned by `create` are passed through, and nothing is inserted in this case.
+    ///
+    /// It is your responsibility to ensure that `create` builds a pipeline that belongs to
+    /// `device`, and that two different pipeline descriptions never have the same key.
+    pub fn get_or_create<F, E>(&self, device: &Device, key: u64, create: F) -> Result<Arc<T>, E>
+        where F: FnOnce() -> Result<T, E>
+    {
+        if let Some(pipeline) = self.get(device, key) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(pipeline);
+        }
+
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let pipeline = Arc::new(create()?);
+        Ok(self.insert((device_address(device), key), pipeline))
+    }
+
+    /// Removes the pipeline of `device` that corresponds to `key` from the registry, and returns
+    /// it.
+    pub fn remove(&self, device: &Device, key: u64) -> Option<Arc<T>> {
+        self.remove_key((device_address(device), key))
+    }
+
+    // Removes an entry. Its item in the queue is discarded once it reaches the top.
+    fn remove_key(&self, key: Key) -> Option<Arc<T>> {
+        let mut shard = self.shard(key).write().unwrap();
+        shard.remove(&key).map(|entry| {
+            self.len.fetch_sub(1, Ordering::Relaxed);
+            entry.pipeline
+        })
+    }
+
+    /// Removes all the pipelines from the registry. The statistics are kept.
+    pub fn clear(&self) {
+        // The queue is cleared first, so that each entry inserted concurrently is either removed
+        // below or pushes its item after this point.
+        self.queue.lock().unwrap().clear();
+        for shard in self.shards.iter() {
+            let mut shard = shard.write().unwrap();
+            self.len.fetch_sub(shard.len(), Ordering::Relaxed);
+            shard.clear();
+        }
+    }
+
+    /// Returns statistics about the usage of the registry since its creation.
+    #[inline]
+    pub fn stats(&self) -> PipelineRegistryStats {
+        PipelineRegistryStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+            len: self.len(),
+        }
+    }
+
+    // Inserts a newly-created pipeline, unless another thread has inserted one with the same key
+    // in the meantime. Returns the pipeline that ends up in the registry.
+    fn insert(&self, key: Key, pipeline: Arc<T>) -> Arc<T> {
+        loop {
+            let item = {
+                let mut shard = self.shard(key).write().unwrap();
+
+                if let Some(entry) = shard.get(&key) {
+                    entry.last_use.store(self.tick(), Ordering::Relaxed);
+                    return entry.pipeline.clone();
+                }
+
+                // Reserve a slot, and only insert if doing so doesn't exceed the capacity.
+                if self.len.fetch_add(1, Ordering::Relaxed) < self.capacity {
+                    let tick = self.tick();
+                    shard.insert(key,
+                                 Entry {
+                                     pipeline: pipeline.clone(),
+                                     last_use: AtomicUsize::new(tick),
+                                     id: tick,
+                                 });
+                    Some(QueueItem {
+                             last_use: tick,
+                             key: key,
+                             id: tick,
+                         })
+                } else {
+                    self.len.fetch_sub(1, Ordering::Relaxed);
+                    None
+                }
+            };
+
+            // The queue is never locked while a shard is locked, in order to avoid deadlocks
+            // with `evict_one`.
+            if let Some(item) = item {
+                self.queue.lock().unwrap().push(Reverse(item));
+                return pipeline;
+            }
+
+            // The lock of the shard must be released before evicting, as the least recently used
+            // entry can be in any shard.
+            self.evict_one();
+        }
+    }
+
+    // Removes the least recently used entry of the registry.
+    //
+    // The item at the top of the queue has the smallest `last_use` of all the items, and the
+    // `last_use` of each entry is at least the one of its item. Therefore if the item is up to
+    // date, its entry is the least recently used one. Otherwise the item is brought up to date
+    // and pushed back.
+    fn evict_one(&self) {
+        loop {
+            let Reverse(item) = match self.queue.lock().unwrap().pop() {
+                Some(item) => item,
+                // The entries being inserted by other threads haven't pushed their item yet.
+                // Their insertion will free a slot or fail, so we can let the caller try again.
+                None => return,
+            };
+
+            let requeue = {
+                let mut shard = self.shard(item.key).write().unwrap();
+
+                let last_use = match shard.get(&item.key) {
+                    Some(entry) if entry.id == item.id => entry.last_use.load(Ordering::Relaxed),
+                    // The entry has been removed in the meantime.
+                    _ => continue,
+                };
+
+                if last_use == item.last_use {
+                    shard.remove(&item.key);
+                    self.len.fetch_sub(1, Ordering::Relaxed);
+                    self.evictions.fetch_add(1, Ordering::Relaxed);
+                    return;
+                }
+
+                QueueItem { last_use: last_use, ..item }
+            };
+
+            self.queue.lock().unwrap().push(Reverse(requeue));
+        }
+    }
+
+    #[inline]
+    fn shard(&self, key: Key) -> &RwLock<HashMap<Key, Entry<T>>> {
+        // The user keys are hashes, so their low bits are already evenly distributed.
+        &self.shards[(key.1 as usize) & (NUM_SHARDS - 1)]
+    }
+
+    #[inline]
+    fn tick(&self) -> usize {
+        self.clock.fetch_add(1, Ordering::Relaxed)
+    }
+}
+
+#[inline]
+fn device_address(device: &Device) -> usize {
+    device as *const Device as usize
+}
+
+/// Statistics about the usage of a `PipelineRegistry`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PipelineRegistryStats {
+    /// Number of calls to `get_or_create` that found the pipeline in the registry.
+    pub hits: usize,
+    /// Number of calls to `get_or_create` that had to create a pipeline.
+    pub misses: usize,
+    /// Number of pipelines that have been removed from the registry in order to respect its
+    /// capacity.
+    pub evictions: usize,
+    /// Number of pipelines currently in the registry.
+    pub len: usize,
+}
+
+/// Hasher used to compute the state hashes of pipelines.
+///
+/// This is the 64-bit FNV-1a hash function. Contrary to the `DefaultHasher` of the standard
+/// library, its output is fully specified and doesn't depend on the version of Rust, which makes
+/// it possible to store state hashes or to compare them between runs on the same platform.
+#[derive(Debug, Copy, Clone)]
+pub struct StateHasher {
+    state: u64,
+}
+
+impl StateHasher {
+    /// Builds a new hasher.
+    #[inline]
+    pub fn new() -> StateHasher {
+        StateHasher { state: 0xcbf29ce484222325 }
+    }
+}
+
+impl Default for StateHasher {
+    #[inline]
+    fn default() -> StateHasher {
+        StateHasher::new()
+    }
+}
+
+impl Hasher for StateHasher {
+    #[inline]
+    fn finish(&self) -> u64 {
+        self.state
+    }
+
+    #[inline]
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.state ^= byte as u64;
+            self.state = self.state.wrapping_mul(0x100000001b3);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pipeline::registry::PipelineRegistry;
+    use pipeline::registry::StateHasher;
+    use std::hash::Hasher;
+    use std::sync::Arc;
+
+    #[test]
+    fn same_key_same_arc() {
+        let (device, _) = gfx_dev_and_queue!();
+        let registry = PipelineRegistry::new(4);
+
+        let a = registry.get_or_create(&device, 12, || Ok::<_, ()>(5u32)).unwrap();
+        let b = registry.get_or_create(&device, 12, || -> Result<u32, ()> { panic!() }).unwrap();
+        assert!(Arc::ptr_eq(&a, &b));
+
+        let c = registry.get_or_create(&device, 13, || Ok::<_, ()>(5u32)).unwrap();
+        assert!(!Arc::ptr_eq(&a, &c));
+
+        let stats = registry.stats();
+        assert_eq!(stats.hits, 1);
+        assert_eq!(stats.misses, 2);
+        assert_eq!(stats.len, 2);
+    }
+
+    #[test]
+    fn keyed_by_device() {
+        let (device1, _) = gfx_dev_and_queue!();
+        let (device2, _) = gfx_dev_and_queue!();
+        let registry = PipelineRegistry::new(4);
+
+        let a = registry.get_or_create(&device1, 12, || Ok::<_, ()>(1u32)).unwrap();
+        let b = registry.get_or_create(&device2, 12, || Ok::<_, ()>(2u32)).unwrap();
+        assert_eq!((*a, *b), (1, 2));
+        assert_eq!(registry.stats().misses, 2);
+
+        assert_eq!(registry.remove(&device1, 12).map(|p| *p), Some(1));
+        assert_eq!(registry.get(&device2, 12).map(|p| *p), Some(2));
+    }
+
+    #[test]
+    fn error_not_inserted() {
+        let (device, _) = gfx_dev_and_queue!();
+        let registry = PipelineRegistry::<u32>::new(4);
+        assert_eq!(registry.get_or_create(&device, 1, || Err("oops")), Err("oops"));
+        assert_eq!(registry.len(), 0);
+        assert!(registry.get(&device, 1).is_none());
+    }
+
+    #[test]
+    fn eviction_respects_bound() {
+        let (device, _) = gfx_dev_and_queue!();
+        let registry = PipelineRegistry::new(3);
+
+        for key in 0 .. 3 {
+            registry.get_or_create(&device, key, || Ok::<_, ()>(key)).unwrap();
+        }
+        assert_eq!(registry.len(), 3);
+
+        // Use 0 so that 1 becomes the least recently used entry.
+        registry.get(&device, 0).unwrap();
+
+        for key in 3 .. 10 {
+            registry.get_or_create(&device, key, || Ok::<_, ()>(key)).unwrap();
+            assert_eq!(registry.len(), 3);
+        }
+
+        assert_eq!(registry.stats().evictions, 7);
+        assert!(registry.get(&device, 1).is_none());
+        assert!(registry.get(&device, 9).is_some());
+    }
+
+    #[test]
+    fn eviction_order() {
+        let (device, _) = gfx_dev_and_queue!();
+        let registry = PipelineRegistry::new(3);
+
+        for key in 0 .. 3 {
+            registry.get_or_create(&device, key, || Ok::<_, ()>(key)).unwrap();
+        }
+
+        // 1 is removed and inserted again, so its first insertion must not count.
+        registry.remove(&device, 1).unwrap();
+        registry.get_or_create(&device, 1, || Ok::<_, ()>(1)).unwrap();
+        registry.get(&device, 0).unwrap();
+
+        // The order of use is now 2, 1, 0.
+        registry.get_or_create(&device, 3, || Ok::<_, ()>(3)).unwrap();
+        assert!(registry.get(&device, 2).is_none());
+        registry.get_or_create(&device, 4, || Ok::<_, ()>(4)).unwrap();
+        assert!(registry.get(&device, 1).is_none());
+        registry.get_or_create(&device, 5, || Ok::<_, ()>(5)).unwrap();
+        assert!(registry.get(&device, 0).is_none());
+
+        assert_eq!(registry.stats().evictions, 3);
+        assert_eq!(registry.len(), 3);
+    }
+
+    #[test]
+    fn remove_and_clear() {
+        let (device, _) = gfx_dev_and_queue!();
+        let registry = PipelineRegistry::new(8);
+        for key in 0 .. 5 {
+            registry.get_or_create(&device, key, || Ok::<_, ()>(key)).unwrap();
+        }
+
+        assert_eq!(registry.remove(&device, 2).map(|p| *p), Some(2));
+        assert_eq!(registry.len(), 4);
+        registry.clear();
+        assert_eq!(registry.len(), 0);
+        assert_eq!(registry.stats().evictions, 0);
+
+        // The registry is still usable after being cleared.
+        for key in 0 .. 10 {
+            registry.get_or_create(&device, key, || Ok::<_, ()>(key)).unwrap();
+        }
+        assert_eq!(registry.len(), 8);
+        assert_eq!(registry.stats().evictions, 2);
+    }
+
+    #[test]
+    fn state_hasher_fnv() {
+        let hash = |bytes: &[u8]| {
+            let mut hasher = StateHasher::new();
+            hasher.write(bytes);
+            hasher.finish()
+        };
+
+        assert_eq!(hash(b""), 0xcbf29ce484222325);
+        assert_eq!(hash(b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(hash(b"foobar"), 0x85944171f73967e8);
+    }
+}
@@ -21,6 +21,7 @@ use std::borrow::Cow;
 use std::error;
 use std::ffi::CStr;
 use std::fmt;
+use std::hash::Hasher;
 use std::iter;
 use std::iter::Empty as EmptyIter;
 use std::marker::PhantomData;
@@ -31,6 +32,7 @@ use std::sync::Arc;
 
 use format::Format;
 use pipeline::input_assembly::PrimitiveTopology;
+use pipeline::registry::StateHasher;
 
 use OomError;
 use SafeDeref;
@@ -49,6 +51,10 @@ pub struct ShaderModule<P = Arc<Device>>
 {
     // The module.
     module: vk::ShaderModule,
+    // Hash of the SPIR-V code, computed with a `StateHasher`.
+    spirv_hash: u64,
+    // Capabilities declared by the SPIR-V code with `OpCapability`.
+    capabilities: Vec<u32>,
     // Pointer to the device.
     device: P,
 }
@@ -81,16 +87,43 @@ impl<P> ShaderModule<P>
             check_errors(vk.CreateShaderModule(device.internal_object(),
                                                &infos,
                                                ptr::null(),
-                                               &mut output))?;
+                                               &mut output),
+                         "vkCreateShaderModule")?;
             output
         };
 
+        let spirv_hash = {
+            let mut hasher = StateHasher::new();
+            hasher.write(spirv);
+            hasher.finish()
+        };
+
         Ok(Arc::new(ShaderModule {
                         module: module,
+                        spirv_hash: spirv_hash,
+                        capabilities: spirv_capabilities(spirv),
                         device: device,
                     }))
     }
 
+    /// Returns a hash of the SPIR-V code of this module.
+    ///
+    /// Two modules built from the same code have the same hash. This is used to compute the
+    /// state hashes of pipelines.
+    #[inline]
+    pub fn spirv_hash(&self) -> u64 {
+        self.spirv_hash
+    }
+
+    /// Returns true if the SPIR-V code declares the `CooperativeMatrixKHR` capability.
+    ///
+    /// Pipelines can only be created from such a module if the `khr_cooperative_matrix`
+    /// extension is enabled on the device.
+    #[inline]
+    pub fn uses_cooperative_matrix(&self) -> bool {
+        self.capabilities.contains(&CAPABILITY_COOPERATIVE_MATRIX_KHR)
+    }
+
     /// Gets access to an entry point contained in this module.
     ///
     /// This is purely a *logical* operation. It returns a struct that *represents* the entry
@@ -112,6 +145,7 @@ impl<P> ShaderModule<P>
             input: input,
             output: output,
             layout: layout,
+            point_size_output: None,
             marker: PhantomData,
         }
     }
@@ -162,6 +196,7 @@ impl<P> ShaderModule<P>
             layout: layout,
             input: input,
             output: output,
+            point_size_output: None,
             marker: PhantomData,
         }
     }
@@ -189,6 +224,7 @@ impl<P> ShaderModule<P>
             primitives: primitives,
             input: input,
             output: output,
+            point_size_output: None,
             marker: PhantomData,
         }
     }
@@ -236,11 +272,59 @@ impl<P> ShaderModule<P>
             module: self,
             name: name,
             layout: layout,
+            local_size: None,
             marker: PhantomData,
         }
     }
 }
 
+// Value of the `CooperativeMatrixKHR` capability in SPIR-V.
+const CAPABILITY_COOPERATIVE_MATRIX_KHR: u32 = 6022;
+
+// Returns the capabilities declared with `OpCapability` by a SPIR-V module.
+//
+// Returns an empty list if the code doesn't start with the SPIR-V magic number.
+fn spirv_capabilities(spirv: &[u8]) -> Vec<u32> {
+    const MAGIC: u32 = 0x07230203;
+    const HEADER_LEN: usize = 5;
+    const OP_CAPABILITY: u32 = 17;
+
+    if spirv.len() % 4 != 0 {
+        return Vec::new();
+    }
+
+    let mut words: Vec<u32> = spirv
+        .chunks(4)
+        .map(|w| w[0] as u32 | (w[1] as u32) << 8 | (w[2] as u32) << 16 | (w[3] as u32) << 24)
+        .collect();
+
+    match words.first().cloned() {
+        Some(MAGIC) => (),
+        Some(w) if w.swap_bytes() == MAGIC => {
+            for w in words.iter_mut() {
+                *w = w.swap_bytes();
+            }
+        },
+        _ => return Vec::new(),
+    }
+
+    // The capabilities are always the first instructions after the header.
+    let mut capabilities = Vec::new();
+    let mut pos = HEADER_LEN;
+    while pos + 1 < words.len() {
+        let word_count = (words[pos] >> 16) as usize;
+        let opcode = words[pos] & 0xffff;
+        if opcode != OP_CAPABILITY || word_count < 2 {
+            break;
+        }
+
+        capabilities.push(words[pos + 1]);
+        pos += word_count;
+    }
+
+    capabilities
+}
+
 unsafe impl<P> VulkanObject for ShaderModule<P>
     where P: SafeDeref<Target = Device>
 {
@@ -276,6 +360,7 @@ pub struct VertexShaderEntryPoint<'a, S, I, O, L, P = Arc<Device>>
     input: I,
     layout: L,
     output: O,
+    point_size_output: Option<PointSizeOutput>,
     marker: PhantomData<S>,
 }
 
@@ -312,6 +397,24 @@ impl<'a, S, I, O, L, P> VertexShaderEntryPoint<'a, S, I, O, L, P>
     pub fn output(&self) -> &O {
         &self.output
     }
+
+    /// Returns what the shader stage does with the `PointSize` built-in, or `None` if unknown.
+    #[inline]
+    pub fn point_size_output(&self) -> Option<PointSizeOutput> {
+        self.point_size_output
+    }
+
+    /// Sets what the shader stage does with the `PointSize` built-in.
+    ///
+    /// # Safety
+    ///
+    /// - The value must match what the shader actually does.
+    ///
+    #[inline]
+    pub unsafe fn with_point_size_output(mut self, output: PointSizeOutput) -> Self {
+        self.point_size_output = Some(output);
+        self
+    }
 }
 
 /// Represents the entry point of a tessellation control shader in a shader module.
@@ -375,6 +478,7 @@ pub struct TessEvaluationShaderEntryPoint<'a, S, I, O, L, P = Arc<Device>>
     layout: L,
     input: I,
     output: O,
+    point_size_output: Option<PointSizeOutput>,
     marker: PhantomData<S>,
 }
 
@@ -410,6 +514,24 @@ impl<'a, S, I, O, L, P> TessEvaluationShaderEntryPoint<'a, S, I, O, L, P>
     pub fn output(&self) -> &O {
         &self.output
     }
+
+    /// Returns what the shader stage does with the `PointSize` built-in, or `None` if unknown.
+    #[inline]
+    pub fn point_size_output(&self) -> Option<PointSizeOutput> {
+        self.point_size_output
+    }
+
+    /// Sets what the shader stage does with the `PointSize` built-in.
+    ///
+    /// # Safety
+    ///
+    /// - The value must match what the shader actually does.
+    ///
+    #[inline]
+    pub unsafe fn with_point_size_output(mut self, output: PointSizeOutput) -> Self {
+        self.point_size_output = Some(output);
+        self
+    }
 }
 
 /// Represents the entry point of a geometry shader in a shader module.
@@ -425,6 +547,7 @@ pub struct GeometryShaderEntryPoint<'a, S, I, O, L, P = Arc<Device>>
     primitives: GeometryShaderExecutionMode,
     input: I,
     output: O,
+    point_size_output: Option<PointSizeOutput>,
     marker: PhantomData<S>,
 }
 
@@ -466,6 +589,38 @@ impl<'a, S, I, O, L, P> GeometryShaderEntryPoint<'a, S, I, O, L, P>
     pub fn output(&self) -> &O {
         &self.output
     }
+
+    /// Returns what the shader stage does with the `PointSize` built-in, or `None` if unknown.
+    #[inline]
+    pub fn point_size_output(&self) -> Option<PointSizeOutput> {
+        self.point_size_output
+    }
+
+    /// Sets what the shader stage does with the `PointSize` built-in.
+    ///
+    /// # Safety
+    ///
+    /// - The value must match what the shader actually does.
+    ///
+    #[inline]
+    pub unsafe fn with_point_size_output(mut self, output: PointSizeOutput) -> Self {
+        self.point_size_output = Some(output);
+        self
+    }
+}
+
+/// Describes what a shader stage that can run right before rasterization does with the
+/// `PointSize` built-in.
+///
+/// When points are rasterized, the last of these stages must usually write their size.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PointSizeOutput {
+    /// True if the stage outputs points. This is the case of tessellation evaluation shaders in
+    /// point mode and of geometry shaders whose output primitives are points. Ignored for vertex
+    /// shaders, as they output points if the pipeline's topology is a list of points.
+    pub outputs_points: bool,
+    /// True if the stage writes the `PointSize` built-in.
+    pub writes_point_size: bool,
 }
 
 /// Declares which type of primitives are expected by the geometry shader.
@@ -562,6 +717,7 @@ pub struct ComputeShaderEntryPoint<'a, S, L, P = Arc<Device>>
     module: &'a ShaderModule<P>,
     name: &'a CStr,
     layout: L,
+    local_size: Option<ComputeShaderLocalSize>,
     marker: PhantomData<S>,
 }
 
@@ -585,6 +741,67 @@ impl<'a, S, L, P> ComputeShaderEntryPoint<'a, S, L, P>
     pub fn layout(&self) -> &L {
         &self.layout
     }
+
+    /// Returns the workgroup size declared by the shader stage, or `None` if unknown.
+    #[inline]
+    pub fn local_size(&self) -> Option<&ComputeShaderLocalSize> {
+        self.local_size.as_ref()
+    }
+
+    /// Sets the workgroup size declared by the shader stage.
+    ///
+    /// # Safety
+    ///
+    /// - The value must match the workgroup size declared in the SPIR-V code of the stage.
+    ///
+    #[inline]
+    pub unsafe fn with_local_size(mut self, local_size: ComputeShaderLocalSize) -> Self {
+        self.local_size = Some(local_size);
+        self
+    }
+}
+
+/// Workgroup size of a compute shader, as declared in its SPIR-V code.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ComputeShaderLocalSize {
+    /// Number of invocations in each dimension of a workgroup, if no specialization constant
+    /// overrides it.
+    pub size: [u32; 3],
+    /// For each dimension, the id of the specialization constant that overrides the size, if
+    /// any.
+    pub specialization_ids: [Option<u32>; 3],
+}
+
+impl ComputeShaderLocalSize {
+    /// Returns the workgroup size once the given specialization constants have been applied.
+    ///
+    /// Dimensions whose specialization constant isn't present in `specialization` keep their
+    /// default value.
+    pub fn specialize<S>(&self, specialization: &S) -> [u32; 3]
+        where S: SpecializationConstants
+    {
+        let mut size = self.size;
+
+        for (dim, id) in self.specialization_ids.iter().enumerate() {
+            let id = match *id {
+                Some(id) => id,
+                None => continue,
+            };
+
+            let entry = S::descriptors().iter().find(|e| e.constant_id == id);
+            if let Some(entry) = entry {
+                debug_assert_eq!(entry.size, mem::size_of::<u32>());
+                debug_assert!(entry.offset as usize + entry.size <= mem::size_of::<S>());
+                unsafe {
+                    let ptr = (specialization as *const S as *const u8)
+                        .offset(entry.offset as isize);
+                    size[dim] = ptr::read_unaligned(ptr as *const u32);
+                }
+            }
+        }
+
+        size
+    }
 }
 
 /// Types that contain the definition of an interface between two shader stages, or between
@@ -661,6 +878,13 @@ unsafe impl<T, I> ShaderInterfaceDefMatch<I> for T
                     return Err(ShaderInterfaceMismatchError::FormatMismatch);
                 }
 
+                // An element whose format consumes two locations must start at the same location
+                // in both interfaces, otherwise the halves of the elements don't line up.
+                let num_locs = a.format.num_locations();
+                if (loc - a.location.start) % num_locs != (loc - b.location.start) % num_locs {
+                    return Err(ShaderInterfaceMismatchError::MisalignedElement { location: loc });
+                }
+
                 // TODO: enforce this?
                 /*match (a.name, b.name) {
                     (Some(ref an), Some(ref bn)) => if an != bn { return false },
@@ -680,6 +904,7 @@ pub enum ShaderInterfaceMismatchError {
     ElementsCountMismatch,
     MissingElement { location: u32 },
     FormatMismatch,
+    MisalignedElement { location: u32 },
 }
 
 impl error::Error for ShaderInterfaceMismatchError {
@@ -691,6 +916,9 @@ impl error::Error for ShaderInterfaceMismatchError {
             ShaderInterfaceMismatchError::MissingElement { .. } => "an element is missing",
             ShaderInterfaceMismatchError::FormatMismatch =>
                 "the format of an element does not match",
+            ShaderInterfaceMismatchError::MisalignedElement { .. } =>
+                "an element that consumes two locations doesn't start at the same location in \
+                 both interfaces",
         }
     }
 }
@@ -0,0 +1,657 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Shader objects, an alternative to pipelines.
+//!
+//! With the `VK_EXT_shader_object` extension, each shader stage can be compiled on its own into a
+//! `ShaderObject` and bound directly on a command buffer without building a `GraphicsPipeline`.
+//! All the state that is normally baked into a pipeline must then be set dynamically when
+//! drawing. This state is described with a `ShaderObjectState`.
+//!
+//! Pipelines remain the default way to draw. Shader objects are mostly useful for tools and
+//! editors that would otherwise need to build a very large number of pipelines.
+//!
+//! In order to use shader objects, you must enable both the `ext_shader_object` and the
+//! `khr_dynamic_rendering` extensions when creating the device. Shader objects can't be used
+//! inside a render pass, so draws happen between `begin_rendering` and `end_rendering` on the
+//! command buffer builder.
+
+use smallvec::SmallVec;
+use std::error;
+use std::ffi::CStr;
+use std::fmt;
+use std::mem;
+use std::ptr;
+use std::sync::Arc;
+
+use Error;
+use OomError;
+use VulkanObject;
+use check_errors;
+use descriptor::descriptor::ShaderStages;
+use descriptor::pipeline_layout::PipelineLayoutAbstract;
+use descriptor::pipeline_layout::PipelineLayoutDescPcRange;
+use device::Device;
+use device::DeviceOwned;
+use format::Format;
+use pipeline::blend::AttachmentBlend;
+use pipeline::depth_stencil::Compare;
+use pipeline::input_assembly::InputAssembly;
+use pipeline::raster::Rasterization;
+use pipeline::vertex::InputRate;
+use pipeline::viewport::Scissor;
+use pipeline::viewport::Viewport;
+use vk;
+
+/// A single shader stage compiled on its own.
+///
+/// The layout of the descriptor sets and push constants is passed when the shader object is
+/// created, and is used later when binding descriptor sets and push constants for draw commands.
+pub struct ShaderObject<L> {
+    shader: vk::ShaderEXT,
+    device: Arc<Device>,
+    stage: ShaderObjectStage,
+    layout: L,
+}
+
+impl<L> ShaderObject<L>
+    where L: PipelineLayoutAbstract
+{
+    /// Builds a new shader object from SPIR-V.
+    ///
+    /// `next_stages` are the stages that may follow this one when drawing. It must be empty for
+    /// fragment and compute shaders.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `next_stages` isn't empty for a fragment or compute shader.
+    ///
+    /// # Safety
+    ///
+    /// - The SPIR-V code is not validated.
+    /// - The SPIR-V code must contain an entry point named `entry_point` of the given stage.
+    /// - The layout must correctly describe the descriptors and push constants used by the shader.
+    ///
+    pub unsafe fn new(layout: L, stage: ShaderObjectStage, next_stages: ShaderStages,
+                      spirv: &[u8], entry_point: &CStr)
+                      -> Result<Arc<ShaderObject<L>>, ShaderObjectCreationError> {
+        debug_assert!((spirv.len() % 4) == 0);
+
+        match stage {
+            ShaderObjectStage::Fragment | ShaderObjectStage::Compute => {
+                assert_eq!(next_stages, ShaderStages::none());
+            },
+            _ => (),
+        }
+
+        let device = layout.device().clone();
+
+        if !device.loaded_extensions().ext_shader_object {
+            return Err(ShaderObjectCreationError::ExtensionNotEnabled);
+        }
+
+        let layouts_ids = (0 .. layout.num_sets())
+            .filter_map(|num| layout.descriptor_set_layout(num))
+            .map(|l| l.internal_object())
+            .collect::<SmallVec<[_; 16]>>();
+
+        let push_constants = (0 .. layout.num_push_constants_ranges())
+            .filter_map(|num| layout.push_constants_range(num))
+            .map(|PipelineLayoutDescPcRange { offset, size, stages }| {
+                vk::PushConstantRange {
+                    stageFlags: stages.into(),
+                    offset: offset as u32,
+                    size: size as u32,
+                }
+            })
+            .collect::<SmallVec<[_; 8]>>();
+
+        let shader = {
+            let infos = vk::ShaderCreateInfoEXT {
+                sType: vk::STRUCTURE_TYPE_SHADER_CREATE_INFO_EXT,
+                pNext: ptr::null(),
+                flags: 0,
+                stage: stage.into(),
+                nextStage: next_stages.into(),
+                codeType: vk::SHADER_CODE_TYPE_SPIRV_EXT,
+                codeSize: spirv.len(),
+                pCode: spirv.as_ptr() as *const _,
+                pName: entry_point.as_ptr(),
+                setLayoutCount: layouts_ids.len() as u32,
+                pSetLayouts: layouts_ids.as_ptr(),
+                pushConstantRangeCount: push_constants.len() as u32,
+                pPushConstantRanges: push_constants.as_ptr(),
+                pSpecializationInfo: ptr::null(),
+            };
+
+            let vk = device.pointers();
+            let mut output = mem::uninitialized();
+            check_errors(vk.CreateShadersEXT(device.internal_object(),
+                                             1,
+                                             &infos,
+                                             ptr::null(),
+                                             &mut output),
+                         "vkCreateShadersEXT")?;
+            output
+        };
+
+        Ok(Arc::new(ShaderObject {
+                        shader: shader,
+                        device: device,
+                        stage: stage,
+                        layout: layout,
+                    }))
+    }
+}
+
+impl<L> ShaderObject<L> {
+    /// Returns the stage of the shader.
+    #[inline]
+    pub fn stage(&self) -> ShaderObjectStage {
+        self.stage
+    }
+
+    /// Returns the layout of the descriptor sets and push constants used by the shader.
+    #[inline]
+    pub fn layout(&self) -> &L {
+        &self.layout
+    }
+}
+
+unsafe impl<L> VulkanObject for ShaderObject<L> {
+    type Object = vk::ShaderEXT;
+
+    #[inline]
+    fn internal_object(&self) -> vk::ShaderEXT {
+        self.shader
+    }
+}
+
+unsafe impl<L> DeviceOwned for ShaderObject<L> {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+}
+
+impl<L> fmt::Debug for ShaderObject<L> {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "<Vulkan shader object {:?}>", self.shader)
+    }
+}
+
+impl<L> Drop for ShaderObject<L> {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe {
+            let vk = self.device.pointers();
+            vk.DestroyShaderEXT(self.device.internal_object(), self.shader, ptr::null());
+        }
+    }
+}
+
+/// Stage of a shader object.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ShaderObjectStage {
+    Vertex,
+    TessellationControl,
+    TessellationEvaluation,
+    Geometry,
+    Fragment,
+    Compute,
+}
+
+impl ShaderObjectStage {
+    /// Returns true if the stage is one of the stages of the graphics pipeline.
+    #[inline]
+    pub fn is_graphics(&self) -> bool {
+        *self != ShaderObjectStage::Compute
+    }
+}
+
+#[doc(hidden)]
+impl Into<vk::ShaderStageFlagBits> for ShaderObjectStage {
+    #[inline]
+    fn into(self) -> vk::ShaderStageFlagBits {
+        match self {
+            ShaderObjectStage::Vertex => vk::SHADER_STAGE_VERTEX_BIT,
+            ShaderObjectStage::TessellationControl => vk::SHADER_STAGE_TESSELLATION_CONTROL_BIT,
+            ShaderObjectStage::TessellationEvaluation => {
+                vk::SHADER_STAGE_TESSELLATION_EVALUATION_BIT
+            },
+            ShaderObjectStage::Geometry => vk::SHADER_STAGE_GEOMETRY_BIT,
+            ShaderObjectStage::Fragment => vk::SHADER_STAGE_FRAGMENT_BIT,
+            ShaderObjectStage::Compute => vk::SHADER_STAGE_COMPUTE_BIT,
+        }
+    }
+}
+
+/// State that is set dynamically before drawing with shader objects.
+///
+/// This is the equivalent of all the fixed states that are passed when building a
+/// `GraphicsPipeline`. The stencil test, the depth bounds test and the logic operation are
+/// always disabled.
+#[derive(Debug, Clone)]
+pub struct ShaderObjectState {
+    /// The vertex buffers that are read by the vertex shader.
+    pub vertex_bindings: Vec<VertexInputBinding>,
+    /// The attributes of the vertex shader, and where to read them from.
+    pub vertex_attributes: Vec<VertexInputAttribute>,
+    /// Describes how vertices are assembled into primitives.
+    pub input_assembly: InputAssembly,
+    /// The viewports. Must contain at least one element.
+    pub viewports: Vec<Viewport>,
+    /// The scissor boxes. Must contain as many elements as `viewports`.
+    pub scissors: Vec<Scissor>,
+    /// State of the rasterizer.
+    pub raster: Rasterization,
+    /// Number of samples per pixel.
+    pub rasterization_samples: u32,
+    /// Bitmask of the samples that are written.
+    pub sample_mask: [u32; 4],
+    /// If true, the alpha component of the first color output is used to compute the coverage.
+    pub alpha_to_coverage: bool,
+    /// If true, the alpha component of the color outputs is replaced with 1.0. Ignored if the
+    /// `alpha_to_one` feature isn't enabled.
+    pub alpha_to_one: bool,
+    /// Comparison to use for the depth test. The test is disabled if this is `Always` and
+    /// `depth_write` is false.
+    pub depth_compare: Compare,
+    /// If true, the depth buffer is updated when the depth test succeeds.
+    pub depth_write: bool,
+    /// Blending of each color attachment of the subpass.
+    pub attachments: Vec<AttachmentBlend>,
+    /// The constant color used by the blend factors that refer to a constant.
+    pub blend_constants: [f32; 4],
+}
+
+impl ShaderObjectState {
+    /// Builds a `ShaderObjectState` that draws a list of triangles to a single color attachment
+    /// covering the whole viewport, with blending and depth testing disabled.
+    ///
+    /// The vertex input is empty and must be filled if the vertex shader has inputs.
+    #[inline]
+    pub fn simple(dimensions: [u32; 2]) -> ShaderObjectState {
+        ShaderObjectState {
+            vertex_bindings: Vec::new(),
+            vertex_attributes: Vec::new(),
+            input_assembly: InputAssembly::triangle_list(),
+            viewports: vec![
+                Viewport {
+                    origin: [0.0, 0.0],
+                    dimensions: [dimensions[0] as f32, dimensions[1] as f32],
+                    depth_range: 0.0 .. 1.0,
+                },
+            ],
+            scissors: vec![
+                Scissor {
+                    origin: [0, 0],
+                    dimensions: dimensions,
+                },
+            ],
+            raster: Default::default(),
+            rasterization_samples: 1,
+            sample_mask: [0xffffffff; 4],
+            alpha_to_coverage: false,
+            alpha_to_one: false,
+            depth_compare: Compare::Always,
+            depth_write: false,
+            attachments: vec![AttachmentBlend::pass_through()],
+            blend_constants: [0.0; 4],
+        }
+    }
+
+    /// Returns true if the depth test is enabled.
+    #[inline]
+    pub fn depth_test_enabled(&self) -> bool {
+        self.depth_write || self.depth_compare != Compare::Always
+    }
+}
+
+/// Describes a vertex buffer binding when drawing with shader objects.
+#[derive(Debug, Copy, Clone)]
+pub struct VertexInputBinding {
+    /// Index of the binding.
+    pub binding: u32,
+    /// Number of bytes between two consecutive elements of the buffer.
+    pub stride: u32,
+    /// Whether each element corresponds to a vertex or to an instance.
+    pub input_rate: InputRate,
+}
+
+/// Describes a vertex attribute when drawing with shader objects.
+#[derive(Debug, Copy, Clone)]
+pub struct VertexInputAttribute {
+    /// Location of the attribute in the vertex shader.
+    pub location: u32,
+    /// Index of the binding the attribute is read from.
+    pub binding: u32,
+    /// Format of the attribute in the buffer.
+    pub format: Format,
+    /// Number of bytes between the start of an element and the attribute.
+    pub offset: u32,
+}
+
+/// Error that can happen when creating a shader object.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShaderObjectCreationError {
+    /// Not enough memory.
+    OomError(OomError),
+    /// The `ext_shader_object` extension must be enabled in order to create shader objects.
+    ExtensionNotEnabled,
+}
+
+impl error::Error for ShaderObjectCreationError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            ShaderObjectCreationError::OomError(_) => "not enough memory available",
+            ShaderObjectCreationError::ExtensionNotEnabled => {
+                "the `ext_shader_object` extension must be enabled in order to create shader \
+                 objects"
+            },
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            ShaderObjectCreationError::OomError(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ShaderObjectCreationError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl From<OomError> for ShaderObjectCreationError {
+    #[inline]
+    fn from(err: OomError) -> ShaderObjectCreationError {
+        ShaderObjectCreationError::OomError(err)
+    }
+}
+
+impl From<Error> for ShaderObjectCreationError {
+    #[inline]
+    fn from(err: Error) -> ShaderObjectCreationError {
+        match err {
+            err @ Error::OutOfHostMemory => {
+                ShaderObjectCreationError::OomError(OomError::from(err))
+            },
+            err @ Error::OutOfDeviceMemory => {
+                ShaderObjectCreationError::OomError(OomError::from(err))
+            },
+            _ => panic!("unexpected error: {:?}", err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CStr;
+    use std::iter;
+    use std::sync::Arc;
+
+    use buffer::BufferUsage;
+    use buffer::CpuAccessibleBuffer;
+    use command_buffer::AutoCommandBufferBuilder;
+    use command_buffer::BeginRenderingError;
+    use command_buffer::CommandBuffer;
+    use command_buffer::auto::AutoCommandBufferBuilderContextError;
+    use command_buffer::auto::DrawWithShadersError;
+    use command_buffer::validity::CheckBeginRenderingError;
+    use descriptor::descriptor::ShaderStages;
+    use descriptor::pipeline_layout::EmptyPipelineDesc;
+    use descriptor::pipeline_layout::PipelineLayout;
+    use descriptor::pipeline_layout::PipelineLayoutDesc;
+    use device::Device;
+    use device::DeviceExtensions;
+    use device::Queue;
+    use features::Features;
+    use format::ClearValue;
+    use format::Format;
+    use image::AttachmentImage;
+    use image::ImageUsage;
+    use instance;
+    use pipeline::shader_object::ShaderObject;
+    use pipeline::shader_object::ShaderObjectCreationError;
+    use pipeline::shader_object::ShaderObjectStage;
+    use pipeline::shader_object::ShaderObjectState;
+    use sync::GpuFuture;
+    use tests::BASIC_FS;
+    use tests::FULL_SCREEN_VS;
+
+    type Layout = Arc<PipelineLayout<EmptyPipelineDesc>>;
+
+    // Creates a device with the extensions required by shader objects, or returns `None` if
+    // they aren't supported.
+    fn shader_object_device(instance: &Arc<instance::Instance>)
+                            -> Option<(Arc<Device>, Arc<Queue>)> {
+        let physical = match instance::PhysicalDevice::enumerate(instance).next() {
+            Some(p) => p,
+            None => return None,
+        };
+
+        let supported = DeviceExtensions::supported_by_device(&physical);
+        if !supported.ext_shader_object {
+            return None;
+        }
+
+        let queue = match physical.queue_families().find(|q| q.supports_graphics()) {
+            Some(q) => q,
+            None => return None,
+        };
+
+        // `khr_dynamic_rendering` isn't necessarily listed on devices where it is part of core.
+        let extensions = DeviceExtensions {
+            ext_shader_object: true,
+            khr_dynamic_rendering: supported.khr_dynamic_rendering,
+            ..DeviceExtensions::none()
+        };
+
+        let (device, mut queues) = match Device::new(&physical,
+                                                     &Features::none(),
+                                                     &extensions,
+                                                     [(queue, 0.5)].iter().cloned()) {
+            Ok(r) => r,
+            Err(_) => return None,
+        };
+
+        if !device.effective_extensions().khr_dynamic_rendering {
+            return None;
+        }
+
+        Some((device, queues.next().unwrap()))
+    }
+
+    // Builds the vertex and fragment shader objects that draw a red full-screen triangle.
+    fn triangle_shaders(layout: &Layout) -> Vec<Arc<ShaderObject<Layout>>> {
+        unsafe {
+            let main = CStr::from_bytes_with_nul_unchecked(b"main\0");
+            let vs = ShaderObject::new(layout.clone(),
+                                       ShaderObjectStage::Vertex,
+                                       ShaderStages {
+                                           fragment: true,
+                                           ..ShaderStages::none()
+                                       },
+                                       &FULL_SCREEN_VS,
+                                       main)
+                .unwrap();
+            let fs = ShaderObject::new(layout.clone(),
+                                       ShaderObjectStage::Fragment,
+                                       ShaderStages::none(),
+                                       &BASIC_FS,
+                                       main)
+                .unwrap();
+            vec![vs, fs]
+        }
+    }
+
+    fn color_image(device: &Arc<Device>) -> Arc<AttachmentImage<Format>> {
+        AttachmentImage::with_usage(device.clone(),
+                                    [16, 16],
+                                    Format::R8G8B8A8Unorm,
+                                    ImageUsage {
+                                        transfer_source: true,
+                                        color_attachment: true,
+                                        ..ImageUsage::none()
+                                    })
+            .unwrap()
+    }
+
+    #[test]
+    fn extension_not_enabled() {
+        let (device, _) = gfx_dev_and_queue!();
+        let layout = Arc::new(EmptyPipelineDesc.build(device.clone()).unwrap());
+
+        let result = unsafe {
+            let main = CStr::from_bytes_with_nul_unchecked(b"main\0");
+            ShaderObject::new(layout,
+                              ShaderObjectStage::Fragment,
+                              ShaderStages::none(),
+                              &BASIC_FS,
+                              main)
+        };
+
+        assert_eq!(result.unwrap_err(), ShaderObjectCreationError::ExtensionNotEnabled);
+    }
+
+    #[test]
+    fn begin_rendering_extension_not_enabled() {
+        let (device, queue) = gfx_dev_and_queue!();
+        if device.effective_extensions().khr_dynamic_rendering {
+            return;
+        }
+
+        let image = color_image(&device);
+        let result = AutoCommandBufferBuilder::new(device.clone(), queue.family())
+            .unwrap()
+            .begin_rendering(vec![(image, ClearValue::None)], [0, 0], [16, 16]);
+
+        match result {
+            Err(BeginRenderingError::CheckBeginRenderingError(
+                CheckBeginRenderingError::ExtensionNotEnabled)) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn draw_requires_rendering() {
+        let instance = instance!();
+        let (device, queue) = match shader_object_device(&instance) {
+            Some(r) => r,
+            None => return,
+        };
+
+        let layout = Arc::new(EmptyPipelineDesc.build(device.clone()).unwrap());
+        let result = AutoCommandBufferBuilder::new(device.clone(), queue.family())
+            .unwrap()
+            .bind_shaders(triangle_shaders(&layout))
+            .draw_with_shaders(layout.clone(),
+                               ShaderObjectState::simple([16, 16]),
+                               Vec::new(),
+                               3,
+                               1,
+                               (),
+                               ());
+
+        match result {
+            Err(DrawWithShadersError::AutoCommandBufferBuilderContextError(
+                AutoCommandBufferBuilderContextError::ForbiddenOutsideRenderPass)) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn draw_requires_vertex_shader() {
+        let instance = instance!();
+        let (device, queue) = match shader_object_device(&instance) {
+            Some(r) => r,
+            None => return,
+        };
+
+        let layout = Arc::new(EmptyPipelineDesc.build(device.clone()).unwrap());
+        let fragment_only = triangle_shaders(&layout).pop();
+        let result = AutoCommandBufferBuilder::new(device.clone(), queue.family())
+            .unwrap()
+            .begin_rendering(vec![(color_image(&device), ClearValue::None)], [0, 0], [16, 16])
+            .unwrap()
+            .bind_shaders(fragment_only)
+            .draw_with_shaders(layout.clone(),
+                               ShaderObjectState::simple([16, 16]),
+                               Vec::new(),
+                               3,
+                               1,
+                               (),
+                               ());
+
+        match result {
+            Err(DrawWithShadersError::AutoCommandBufferBuilderContextError(
+                AutoCommandBufferBuilderContextError::NoShadersBound)) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn triangle() {
+        let instance = instance!();
+        let (device, queue) = match shader_object_device(&instance) {
+            Some(r) => r,
+            None => return,
+        };
+
+        let layout = Arc::new(EmptyPipelineDesc.build(device.clone()).unwrap());
+        let image = color_image(&device);
+        let output = CpuAccessibleBuffer::from_iter(device.clone(),
+                                                    BufferUsage::all(),
+                                                    iter::once(queue.family()),
+                                                    (0 .. 16 * 16).map(|_| [0u8; 4]))
+            .unwrap();
+
+        // No `GraphicsPipeline` and no render pass are involved.
+        let cb = AutoCommandBufferBuilder::new(device.clone(), queue.family())
+            .unwrap()
+            .begin_rendering(vec![(image.clone(), ClearValue::Float([0.0, 0.0, 1.0, 1.0]))],
+                             [0, 0],
+                             [16, 16])
+            .unwrap()
+            .bind_shaders(triangle_shaders(&layout))
+            .draw_with_shaders(layout.clone(),
+                               ShaderObjectState::simple([16, 16]),
+                               Vec::new(),
+                               3,
+                               1,
+                               (),
+                               ())
+            .unwrap()
+            .end_rendering()
+            .unwrap()
+            .copy_image_to_buffer(image.clone(), output.clone())
+            .unwrap()
+            .build()
+            .unwrap();
+
+        cb.execute(queue.clone())
+            .unwrap()
+            .then_signal_fence_and_flush()
+            .unwrap()
+            .wait(None)
+            .unwrap();
+
+        // The triangle covers the whole viewport, so the clear color must have been overwritten.
+        let content = output.read().unwrap();
+        assert!(content.iter().all(|&p| p == [255, 0, 0, 255]));
+    }
+}
@@ -0,0 +1,417 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Control over the size of the subgroups of a compute pipeline.
+//!
+//! By default the implementation is free to choose the number of invocations in each subgroup
+//! (see `PhysicalDevice::subgroup_properties`), and the last subgroup of a workgroup may be only
+//! partially filled. Some kernels, for example reductions that assume a specific wave size, need
+//! more control than that. With the `ext_subgroup_size_control` extension (or Vulkan 1.3), a
+//! compute pipeline can be created with `ComputePipeline::with_subgroup_size_control` in order to
+//! pin the subgroup size, or to require all the subgroups to be full.
+//!
+//! The range of sizes that can be requested is given by `SubgroupSizeProperties`, which can only
+//! be queried if the `khr_get_physical_device_properties2` extension is enabled on the instance.
+//! Requiring full subgroups is an optional feature of the extension, see `SubgroupSizeFeatures`.
+
+use std::error;
+use std::fmt;
+use std::mem;
+use std::ptr;
+
+use VulkanObject;
+use descriptor::descriptor::ShaderStages;
+use device::Device;
+use instance::PhysicalDevice;
+use vk;
+
+/// Options of the subgroup size of a compute pipeline.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct SubgroupSizeControl {
+    /// If `Some`, the pipeline will always use this number of invocations per subgroup. Must be
+    /// a power of two between `min_subgroup_size` and `max_subgroup_size`.
+    pub required_size: Option<u32>,
+    /// If true, the subgroup size may vary between `min_subgroup_size` and `max_subgroup_size`
+    /// instead of being the default subgroup size. Ignored if `required_size` is `Some`.
+    pub allow_varying_size: bool,
+    /// If true, all the subgroups of a workgroup are guaranteed to be full. The X dimension of
+    /// the workgroup size must then be a multiple of the maximum possible subgroup size.
+    pub require_full_subgroups: bool,
+}
+
+impl SubgroupSizeControl {
+    /// Returns true if the options are the default ones, in which case nothing needs to be passed
+    /// to the implementation.
+    #[inline]
+    pub fn is_default(&self) -> bool {
+        self.required_size.is_none() && !self.allow_varying_size && !self.require_full_subgroups
+    }
+
+    /// Returns the flags to put in the shader stage create info.
+    #[inline]
+    pub(crate) fn stage_flags(&self) -> vk::PipelineShaderStageCreateFlags {
+        let mut flags = 0;
+        if self.allow_varying_size && self.required_size.is_none() {
+            flags |= vk::PIPELINE_SHADER_STAGE_CREATE_ALLOW_VARYING_SUBGROUP_SIZE_BIT_EXT;
+        }
+        if self.require_full_subgroups {
+            flags |= vk::PIPELINE_SHADER_STAGE_CREATE_REQUIRE_FULL_SUBGROUPS_BIT_EXT;
+        }
+        flags
+    }
+
+    /// Checks that the features needed by the options are enabled.
+    pub fn check_features(&self, features: &SubgroupSizeFeatures)
+                          -> Result<(), SubgroupSizeControlError> {
+        if (self.required_size.is_some() || self.allow_varying_size) &&
+            !features.subgroup_size_control
+        {
+            return Err(SubgroupSizeControlError::FeatureNotEnabled {
+                           feature: "subgroupSizeControl",
+                       });
+        }
+
+        if self.require_full_subgroups && !features.compute_full_subgroups {
+            return Err(SubgroupSizeControlError::FeatureNotEnabled {
+                           feature: "computeFullSubgroups",
+                       });
+        }
+
+        Ok(())
+    }
+
+    /// Checks the options against the properties of the device and the workgroup size of the
+    /// shader.
+    pub fn check(&self, properties: &SubgroupSizeProperties, local_size: [u32; 3])
+                 -> Result<(), SubgroupSizeControlError> {
+        let invocations = local_size[0] as u64 * local_size[1] as u64 * local_size[2] as u64;
+
+        // The largest subgroup size that the implementation may use.
+        let max_size = if let Some(size) = self.required_size {
+            if !size.is_power_of_two() || size < properties.min_subgroup_size ||
+                size > properties.max_subgroup_size
+            {
+                return Err(SubgroupSizeControlError::RequiredSizeOutOfRange {
+                               requested: size,
+                               min: properties.min_subgroup_size,
+                               max: properties.max_subgroup_size,
+                           });
+            }
+
+            if !properties.required_subgroup_size_stages.compute {
+                return Err(SubgroupSizeControlError::RequiredSizeNotSupportedInComputeStage);
+            }
+
+            let max_invocations = size as u64 *
+                properties.max_compute_workgroup_subgroups as u64;
+            if invocations > max_invocations {
+                return Err(SubgroupSizeControlError::TooManySubgroups {
+                               invocations: invocations as u32,
+                               max: max_invocations as u32,
+                           });
+            }
+
+            size
+        } else if self.allow_varying_size {
+            properties.max_subgroup_size
+        } else {
+            properties.default_subgroup_size
+        };
+
+        if self.require_full_subgroups && local_size[0] % max_size != 0 {
+            return Err(SubgroupSizeControlError::LocalSizeNotMultipleOfSubgroupSize {
+                           local_size_x: local_size[0],
+                           subgroup_size: max_size,
+                       });
+        }
+
+        Ok(())
+    }
+}
+
+/// Features of the `ext_subgroup_size_control` extension.
+///
+/// Contrary to the properties, `compute_full_subgroups` may be missing even if the extension is
+/// supported.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct SubgroupSizeFeatures {
+    /// The subgroup size can be pinned or allowed to vary.
+    pub subgroup_size_control: bool,
+    /// Full subgroups can be required in compute shaders.
+    pub compute_full_subgroups: bool,
+}
+
+impl SubgroupSizeFeatures {
+    /// Queries the features supported by a physical device.
+    ///
+    /// Returns `None` if the `khr_get_physical_device_properties2` extension isn't enabled on the
+    /// instance. Both features are false if the physical device doesn't support the extension.
+    pub fn from_physical_device(physical_device: PhysicalDevice) -> Option<SubgroupSizeFeatures> {
+        let instance = physical_device.instance();
+        if !instance.loaded_extensions().khr_get_physical_device_properties2 {
+            return None;
+        }
+
+        unsafe {
+            let mut size_control = vk::PhysicalDeviceSubgroupSizeControlFeaturesEXT {
+                sType: vk::STRUCTURE_TYPE_PHYSICAL_DEVICE_SUBGROUP_SIZE_CONTROL_FEATURES_EXT,
+                pNext: ptr::null_mut(),
+                subgroupSizeControl: vk::FALSE,
+                computeFullSubgroups: vk::FALSE,
+            };
+
+            let mut output = vk::PhysicalDeviceFeatures2KHR {
+                sType: vk::STRUCTURE_TYPE_PHYSICAL_DEVICE_FEATURES_2_KHR,
+                pNext: &mut size_control as *mut _ as *const _,
+                features: mem::uninitialized(),
+            };
+
+            let vk = instance.pointers();
+            vk.GetPhysicalDeviceFeatures2KHR(physical_device.internal_object(), &mut output);
+
+            Some(SubgroupSizeFeatures {
+                     subgroup_size_control: size_control.subgroupSizeControl != vk::FALSE,
+                     compute_full_subgroups: size_control.computeFullSubgroups != vk::FALSE,
+                 })
+        }
+    }
+}
+
+/// Limits of the device regarding the control of the subgroup size.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SubgroupSizeProperties {
+    /// Subgroup size used when the size is neither pinned nor allowed to vary.
+    pub default_subgroup_size: u32,
+    /// Smallest subgroup size that can be requested.
+    pub min_subgroup_size: u32,
+    /// Largest subgroup size that can be requested.
+    pub max_subgroup_size: u32,
+    /// Maximum number of subgroups in a workgroup of a compute shader.
+    pub max_compute_workgroup_subgroups: u32,
+    /// Stages in which a specific subgroup size can be required.
+    pub required_subgroup_size_stages: ShaderStages,
+}
+
+impl SubgroupSizeProperties {
+    /// Queries the properties of a physical device.
+    ///
+    /// Returns `None` if the `khr_get_physical_device_properties2` extension isn't enabled on the
+    /// instance.
+    pub fn from_physical_device(physical_device: PhysicalDevice) -> Option<SubgroupSizeProperties> {
+        let instance = physical_device.instance();
+        if !instance.loaded_extensions().khr_get_physical_device_properties2 {
+            return None;
+        }
+
+        let default_subgroup_size = match physical_device.subgroup_properties() {
+            Some(subgroup) => subgroup.subgroup_size,
+            None => return None,
+        };
+
+        unsafe {
+            let mut size_control = vk::PhysicalDeviceSubgroupSizeControlPropertiesEXT {
+                sType: vk::STRUCTURE_TYPE_PHYSICAL_DEVICE_SUBGROUP_SIZE_CONTROL_PROPERTIES_EXT,
+                pNext: ptr::null_mut(),
+                minSubgroupSize: 0,
+                maxSubgroupSize: 0,
+                maxComputeWorkgroupSubgroups: 0,
+                requiredSubgroupSizeStages: 0,
+            };
+
+            let mut output = vk::PhysicalDeviceProperties2KHR {
+                sType: vk::STRUCTURE_TYPE_PHYSICAL_DEVICE_PROPERTIES_2_KHR,
+                pNext: &mut size_control as *mut _ as *const _,
+                properties: mem::uninitialized(),
+            };
+
+            let vk = instance.pointers();
+            vk.GetPhysicalDeviceProperties2KHR(physical_device.internal_object(), &mut output);
+
+            Some(SubgroupSizeProperties {
+                     default_subgroup_size: default_subgroup_size,
+                     min_subgroup_size: size_control.minSubgroupSize,
+                     max_subgroup_size: size_control.maxSubgroupSize,
+                     max_compute_workgroup_subgroups: size_control.maxComputeWorkgroupSubgroups,
+                     required_subgroup_size_stages:
+                         ShaderStages::from_bits(size_control.requiredSubgroupSizeStages),
+                 })
+        }
+    }
+
+    /// Same as `from_physical_device`, but also checks that subgroup size control is available
+    /// on `device`.
+    pub(crate) fn for_device(device: &Device)
+                             -> Result<SubgroupSizeProperties, SubgroupSizeControlError> {
+        if !device.effective_extensions().ext_subgroup_size_control {
+            return Err(SubgroupSizeControlError::ExtensionNotEnabled);
+        }
+
+        SubgroupSizeProperties::from_physical_device(device.physical_device())
+            .ok_or(SubgroupSizeControlError::ExtensionNotEnabled)
+    }
+}
+
+/// Error that can happen when checking the subgroup size options of a pipeline.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SubgroupSizeControlError {
+    /// The `ext_subgroup_size_control` extension must be enabled on the device, and the
+    /// `khr_get_physical_device_properties2` extension on the instance.
+    ExtensionNotEnabled,
+    /// A feature of the extension that the options need is not enabled on the device.
+    FeatureNotEnabled { feature: &'static str },
+    /// The required subgroup size is not a power of two within the range supported by the
+    /// device.
+    RequiredSizeOutOfRange { requested: u32, min: u32, max: u32 },
+    /// The device doesn't support requiring a subgroup size in compute shaders.
+    RequiredSizeNotSupportedInComputeStage,
+    /// The workgroup would contain more subgroups of the required size than the device supports.
+    TooManySubgroups { invocations: u32, max: u32 },
+    /// Full subgroups were required, but the X dimension of the workgroup size is not a multiple
+    /// of the subgroup size.
+    LocalSizeNotMultipleOfSubgroupSize { local_size_x: u32, subgroup_size: u32 },
+}
+
+impl error::Error for SubgroupSizeControlError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            SubgroupSizeControlError::ExtensionNotEnabled => {
+                "the `ext_subgroup_size_control` extension must be enabled on the device, and the \
+                 `khr_get_physical_device_properties2` extension on the instance"
+            },
+            SubgroupSizeControlError::FeatureNotEnabled { .. } => {
+                "a feature of the extension that the options need is not enabled on the device"
+            },
+            SubgroupSizeControlError::RequiredSizeOutOfRange { .. } => {
+                "the required subgroup size is not a power of two within the range supported by \
+                 the device"
+            },
+            SubgroupSizeControlError::RequiredSizeNotSupportedInComputeStage => {
+                "the device doesn't support requiring a subgroup size in compute shaders"
+            },
+            SubgroupSizeControlError::TooManySubgroups { .. } => {
+                "the workgroup would contain more subgroups of the required size than the device \
+                 supports"
+            },
+            SubgroupSizeControlError::LocalSizeNotMultipleOfSubgroupSize { .. } => {
+                "full subgroups were required, but the X dimension of the workgroup size is not a \
+                 multiple of the subgroup size"
+            },
+        }
+    }
+}
+
+impl fmt::Display for SubgroupSizeControlError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use descriptor::descriptor::ShaderStages;
+    use pipeline::subgroup_size::SubgroupSizeControl;
+    use pipeline::subgroup_size::SubgroupSizeControlError;
+    use pipeline::subgroup_size::SubgroupSizeFeatures;
+    use pipeline::subgroup_size::SubgroupSizeProperties;
+
+    fn properties() -> SubgroupSizeProperties {
+        SubgroupSizeProperties {
+            default_subgroup_size: 32,
+            min_subgroup_size: 16,
+            max_subgroup_size: 64,
+            max_compute_workgroup_subgroups: 16,
+            required_subgroup_size_stages: ShaderStages::compute(),
+        }
+    }
+
+    #[test]
+    fn required_size_range() {
+        let control = |size| SubgroupSizeControl {
+            required_size: Some(size),
+            ..SubgroupSizeControl::default()
+        };
+
+        assert!(control(16).check(&properties(), [64, 1, 1]).is_ok());
+        assert!(control(64).check(&properties(), [64, 1, 1]).is_ok());
+
+        for &size in [8, 48, 128].iter() {
+            match control(size).check(&properties(), [64, 1, 1]) {
+                Err(SubgroupSizeControlError::RequiredSizeOutOfRange { requested, .. }) => {
+                    assert_eq!(requested, size)
+                },
+                _ => panic!(),
+            }
+        }
+
+        match control(16).check(&properties(), [512, 1, 1]) {
+            Err(SubgroupSizeControlError::TooManySubgroups { invocations: 512, max: 256 }) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn full_subgroups() {
+        let control = SubgroupSizeControl {
+            require_full_subgroups: true,
+            ..SubgroupSizeControl::default()
+        };
+        assert!(control.check(&properties(), [32, 2, 1]).is_ok());
+        assert!(control.check(&properties(), [16, 4, 1]).is_err());
+
+        // When the size may vary, the workgroup must be a multiple of the largest size.
+        let varying = SubgroupSizeControl { allow_varying_size: true, ..control };
+        assert_eq!(varying.check(&properties(), [32, 1, 1]),
+                   Err(SubgroupSizeControlError::LocalSizeNotMultipleOfSubgroupSize {
+                           local_size_x: 32,
+                           subgroup_size: 64,
+                       }));
+
+        let pinned = SubgroupSizeControl { required_size: Some(16), ..control };
+        assert!(pinned.check(&properties(), [16, 4, 1]).is_ok());
+    }
+
+    #[test]
+    fn features() {
+        let no_full_subgroups = SubgroupSizeFeatures {
+            subgroup_size_control: true,
+            compute_full_subgroups: false,
+        };
+
+        let pinned = SubgroupSizeControl {
+            required_size: Some(32),
+            ..SubgroupSizeControl::default()
+        };
+        assert!(pinned.check_features(&no_full_subgroups).is_ok());
+        assert_eq!(pinned.check_features(&SubgroupSizeFeatures::default()),
+                   Err(SubgroupSizeControlError::FeatureNotEnabled {
+                           feature: "subgroupSizeControl",
+                       }));
+
+        let full = SubgroupSizeControl {
+            require_full_subgroups: true,
+            ..SubgroupSizeControl::default()
+        };
+        assert_eq!(full.check_features(&no_full_subgroups),
+                   Err(SubgroupSizeControlError::FeatureNotEnabled {
+                           feature: "computeFullSubgroups",
+                       }));
+
+        assert!(SubgroupSizeControl::default()
+                    .check_features(&SubgroupSizeFeatures::default())
+                    .is_ok());
+    }
+
+    #[test]
+    fn default_is_empty() {
+        assert!(SubgroupSizeControl::default().is_default());
+        assert_eq!(SubgroupSizeControl::default().stage_flags(), 0);
+    }
+}
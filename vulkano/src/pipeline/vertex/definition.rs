@@ -125,3 +125,79 @@ unsafe impl<L, T> VertexSource<L> for T
         (**self).decode(list)
     }
 }
+
+/// Shared implementation of `VertexSource` for lists of type-erased buffers, whether they are
+/// passed as a `Vec`, a slice or an array.
+///
+/// `element_sizes` contains the size of an element of each buffer. Returns the buffers to pass to
+/// the command buffer, and the number of elements in each buffer. If the size of a buffer is not
+/// a multiple of the size of its elements, the bytes at the end that don't make a whole element
+/// are ignored.
+///
+/// # Panic
+///
+/// - Panics if the number of buffers is not the same as the number of elements sizes.
+///
+pub(crate) fn decode_buffer_list(buffers: &[Arc<BufferAccess + Send + Sync>],
+                                 element_sizes: &[usize])
+                                 -> (Vec<Box<BufferAccess + Send + Sync>>, Vec<usize>) {
+    assert_eq!(buffers.len(), element_sizes.len());
+
+    let lengths = buffers
+        .iter()
+        .zip(element_sizes.iter())
+        .map(|(buffer, &element_size)| buffer.size() / element_size)
+        .collect();
+
+    let buffers = buffers
+        .iter()
+        .map(|buffer| Box::new(buffer.clone()) as Box<_>)
+        .collect();
+
+    (buffers, lengths)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use buffer::BufferAccess;
+    use buffer::BufferUsage;
+    use buffer::CpuAccessibleBuffer;
+    use pipeline::vertex::definition::decode_buffer_list;
+
+    #[test]
+    fn buffer_list_lengths() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let buffers = [16, 30].iter()
+            .map(|&len| {
+                CpuAccessibleBuffer::from_iter(device.clone(),
+                                               BufferUsage::vertex_buffer(),
+                                               Some(queue.family()),
+                                               (0 .. len).map(|_| 0u8))
+                    .unwrap() as Arc<BufferAccess + Send + Sync>
+            })
+            .collect::<Vec<_>>();
+
+        // The 2 bytes at the end of the second buffer are ignored.
+        let (boxed, lengths) = decode_buffer_list(&buffers, &[4, 8]);
+        assert_eq!(boxed.len(), 2);
+        assert_eq!(lengths, vec![4, 3]);
+        assert_eq!(boxed[1].inner().buffer.key(), buffers[1].inner().buffer.key());
+    }
+
+    #[test]
+    #[should_panic]
+    fn buffer_list_wrong_count() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let buffer = CpuAccessibleBuffer::from_iter(device.clone(),
+                                                    BufferUsage::vertex_buffer(),
+                                                    Some(queue.family()),
+                                                    (0 .. 16).map(|_| 0u8))
+            .unwrap() as Arc<BufferAccess + Send + Sync>;
+
+        decode_buffer_list(&[buffer], &[4, 4]);
+    }
+}
@@ -115,6 +115,20 @@ unsafe impl VertexMember for f64 {
     }
 }
 
+unsafe impl VertexMember for i64 {
+    #[inline]
+    fn format() -> (VertexMemberTy, usize) {
+        (VertexMemberTy::I64, 1)
+    }
+}
+
+unsafe impl VertexMember for u64 {
+    #[inline]
+    fn format() -> (VertexMemberTy, usize) {
+        (VertexMemberTy::U64, 1)
+    }
+}
+
 unsafe impl<T> VertexMember for (T,)
     where T: VertexMember
 {
@@ -21,6 +21,7 @@ use pipeline::vertex::InputRate;
 use pipeline::vertex::Vertex;
 use pipeline::vertex::VertexDefinition;
 use pipeline::vertex::VertexSource;
+use pipeline::vertex::definition::decode_buffer_list;
 
 /// Unstable.
 // TODO: bad way to do things
@@ -71,7 +72,8 @@ unsafe impl<T, U, I> VertexDefinition<I> for OneVertexOneInstanceDefinition<T, U
                 }
 
                 let mut offset = infos.offset;
-                for loc in e.location.clone() {
+                let mut loc = e.location.start;
+                while loc < e.location.end {
                     attribs.push((loc,
                                   buf_offset,
                                   AttributeInfo {
@@ -79,6 +81,7 @@ unsafe impl<T, U, I> VertexDefinition<I> for OneVertexOneInstanceDefinition<T, U
                                       format: e.format,
                                   }));
                     offset += e.format.size().unwrap();
+                    loc += e.format.num_locations();
                 }
             }
             attribs
@@ -99,15 +102,35 @@ unsafe impl<T, U> VertexSource<Vec<Arc<BufferAccess + Send + Sync>>>
           U: Vertex
 {
     #[inline]
-    fn decode(&self, mut source: Vec<Arc<BufferAccess + Send + Sync>>)
+    fn decode(&self, source: Vec<Arc<BufferAccess + Send + Sync>>)
               -> (Vec<Box<BufferAccess + Send + Sync>>, usize, usize) {
-        // FIXME: safety
-        assert_eq!(source.len(), 2);
-        let len = source[0].size() / mem::size_of::<T>();
-        let inst = source[0].size() / mem::size_of::<U>();
-        let s0 = source.remove(0);
-        let s1 = source.remove(0);
-        (vec![Box::new(s0) as Box<_>, Box::new(s1) as Box<_>], len, inst)
+        self.decode(&source[..])
+    }
+}
+
+unsafe impl<'a, T, U> VertexSource<&'a [Arc<BufferAccess + Send + Sync>]>
+    for OneVertexOneInstanceDefinition<T, U>
+    where T: Vertex,
+          U: Vertex
+{
+    #[inline]
+    fn decode(&self, source: &'a [Arc<BufferAccess + Send + Sync>])
+              -> (Vec<Box<BufferAccess + Send + Sync>>, usize, usize) {
+        let (buffers, lengths) =
+            decode_buffer_list(source, &[mem::size_of::<T>(), mem::size_of::<U>()]);
+        (buffers, lengths[0], lengths[1])
+    }
+}
+
+unsafe impl<T, U> VertexSource<[Arc<BufferAccess + Send + Sync>; 2]>
+    for OneVertexOneInstanceDefinition<T, U>
+    where T: Vertex,
+          U: Vertex
+{
+    #[inline]
+    fn decode(&self, source: [Arc<BufferAccess + Send + Sync>; 2])
+              -> (Vec<Box<BufferAccess + Send + Sync>>, usize, usize) {
+        self.decode(&source[..])
     }
 }
 
@@ -124,3 +147,59 @@ unsafe impl<'a, T, U, Bt, Bu> VertexSource<(Bt, Bu)> for OneVertexOneInstanceDef
         (vec![Box::new(source.0) as Box<_>, Box::new(source.1) as Box<_>], s1l, s2l)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use buffer::BufferAccess;
+    use buffer::BufferUsage;
+    use buffer::CpuAccessibleBuffer;
+    use pipeline::vertex::OneVertexOneInstanceDefinition;
+    use pipeline::vertex::Vertex;
+    use pipeline::vertex::VertexMemberInfo;
+    use pipeline::vertex::VertexSource;
+
+    #[allow(dead_code)]
+    struct Position([f32; 4]);
+    unsafe impl Vertex for Position {
+        fn member(_: &str) -> Option<VertexMemberInfo> {
+            None
+        }
+    }
+
+    #[allow(dead_code)]
+    struct Offset([f32; 2]);
+    unsafe impl Vertex for Offset {
+        fn member(_: &str) -> Option<VertexMemberInfo> {
+            None
+        }
+    }
+
+    #[test]
+    fn instance_count_from_second_buffer() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let buffer = |len| {
+            CpuAccessibleBuffer::from_iter(device.clone(),
+                                           BufferUsage::vertex_buffer(),
+                                           Some(queue.family()),
+                                           (0 .. len).map(|_| 0u8))
+                .unwrap() as Arc<BufferAccess + Send + Sync>
+        };
+        let buffers = [buffer(5 * 16), buffer(3 * 8)];
+
+        let definition = OneVertexOneInstanceDefinition::<Position, Offset>::new();
+        let from_slice = definition.decode(&buffers[..]);
+        let from_vec = definition.decode(buffers.to_vec());
+        let from_array = definition.decode([buffers[0].clone(), buffers[1].clone()]);
+
+        // The number of instances is computed from the size of the instance buffer, not from
+        // the size of the vertex buffer.
+        for &(ref boxed, vertices, instances) in [from_slice, from_vec, from_array].iter() {
+            assert_eq!(boxed.len(), 2);
+            assert_eq!(vertices, 5);
+            assert_eq!(instances, 3);
+        }
+    }
+}
@@ -22,6 +22,7 @@ use pipeline::vertex::InputRate;
 use pipeline::vertex::Vertex;
 use pipeline::vertex::VertexDefinition;
 use pipeline::vertex::VertexSource;
+use pipeline::vertex::definition::decode_buffer_list;
 
 /// Implementation of `VertexDefinition` for a single vertex buffer.
 pub struct SingleBufferDefinition<T>(pub PhantomData<T>);
@@ -66,8 +67,11 @@ unsafe impl<T, I> VertexDefinition<I> for SingleBufferDefinition<T>
                                });
                 }
 
+                // Three and four components 64-bit formats consume two locations per element,
+                // but only one attribute must be declared for each of them.
                 let mut offset = infos.offset;
-                for loc in e.location.clone() {
+                let mut loc = e.location.start;
+                while loc < e.location.end {
                     attribs.push((loc,
                                   0,
                                   AttributeInfo {
@@ -75,6 +79,7 @@ unsafe impl<T, I> VertexDefinition<I> for SingleBufferDefinition<T>
                                       format: e.format,
                                   }));
                     offset += e.format.size().unwrap();
+                    loc += e.format.num_locations();
                 }
             }
             attribs
@@ -89,12 +94,23 @@ unsafe impl<V> VertexSource<Vec<Arc<BufferAccess + Send + Sync>>> for SingleBuff
     where V: Vertex
 {
     #[inline]
-    fn decode(&self, mut source: Vec<Arc<BufferAccess + Send + Sync>>)
+    fn decode(&self, source: Vec<Arc<BufferAccess + Send + Sync>>)
               -> (Vec<Box<BufferAccess + Send + Sync>>, usize, usize) {
-        // FIXME: safety
-        assert_eq!(source.len(), 1);
-        let len = source[0].size() / mem::size_of::<V>();
-        (vec![Box::new(source.remove(0))], len, 1)
+        let (buffers, lengths) = decode_buffer_list(&source, &[mem::size_of::<V>()]);
+        (buffers, lengths[0], 1)
+    }
+}
+
+// Note: there is no implementation for `&[Arc<BufferAccess + Send + Sync>]`, as it would
+// conflict with the implementation below.
+unsafe impl<V> VertexSource<[Arc<BufferAccess + Send + Sync>; 1]> for SingleBufferDefinition<V>
+    where V: Vertex
+{
+    #[inline]
+    fn decode(&self, source: [Arc<BufferAccess + Send + Sync>; 1])
+              -> (Vec<Box<BufferAccess + Send + Sync>>, usize, usize) {
+        let (buffers, lengths) = decode_buffer_list(&source, &[mem::size_of::<V>()]);
+        (buffers, lengths[0], 1)
     }
 }
 
@@ -108,3 +124,81 @@ unsafe impl<'a, B, V> VertexSource<B> for SingleBufferDefinition<V>
         (vec![Box::new(source) as Box<_>], len, 1)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+    use std::vec::IntoIter as VecIntoIter;
+
+    use format::Format;
+    use pipeline::shader::ShaderInterfaceDef;
+    use pipeline::shader::ShaderInterfaceDefEntry;
+    use pipeline::vertex::SingleBufferDefinition;
+    use pipeline::vertex::Vertex;
+    use pipeline::vertex::VertexDefinition;
+    use pipeline::vertex::VertexMemberInfo;
+    use pipeline::vertex::VertexMemberTy;
+
+    #[allow(dead_code)]
+    struct DoubleVertex {
+        positions: [[f64; 3]; 2],
+        weight: f32,
+    }
+
+    unsafe impl Vertex for DoubleVertex {
+        fn member(name: &str) -> Option<VertexMemberInfo> {
+            match name {
+                "positions" => Some(VertexMemberInfo {
+                                        offset: 0,
+                                        ty: VertexMemberTy::F64,
+                                        array_size: 6,
+                                    }),
+                "weight" => Some(VertexMemberInfo {
+                                     offset: 48,
+                                     ty: VertexMemberTy::F32,
+                                     array_size: 1,
+                                 }),
+                _ => None,
+            }
+        }
+    }
+
+    struct Interface;
+
+    unsafe impl ShaderInterfaceDef for Interface {
+        type Iter = VecIntoIter<ShaderInterfaceDefEntry>;
+
+        fn elements(&self) -> Self::Iter {
+            // A `dvec3 positions[2]` consumes four locations.
+            vec![
+                ShaderInterfaceDefEntry {
+                    location: 0 .. 4,
+                    format: Format::R64G64B64Sfloat,
+                    name: Some(Cow::Borrowed("positions")),
+                },
+                ShaderInterfaceDefEntry {
+                    location: 4 .. 5,
+                    format: Format::R32Sfloat,
+                    name: Some(Cow::Borrowed("weight")),
+                },
+            ].into_iter()
+        }
+    }
+
+    #[test]
+    fn dvec3_attributes_span_two_locations() {
+        let definition = SingleBufferDefinition::<DoubleVertex>::new();
+        let (_, attribs) = definition.definition(&Interface).unwrap();
+
+        let attribs = attribs
+            .map(|(loc, binding, info)| (loc, binding, info.offset, info.format))
+            .collect::<Vec<_>>();
+
+        assert_eq!(attribs,
+                   vec![
+                       (0, 0, 0, Format::R64G64B64Sfloat),
+                       (2, 0, 24, Format::R64G64B64Sfloat),
+                       (4, 0, 48, Format::R32Sfloat),
+                   ]);
+    }
+}
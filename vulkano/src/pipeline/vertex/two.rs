@@ -7,6 +7,7 @@
 // notice may not be copied, modified, or distributed except
 // according to those terms.
 
+use std::cmp;
 use std::marker::PhantomData;
 use std::mem;
 use std::sync::Arc;
@@ -21,6 +22,7 @@ use pipeline::vertex::InputRate;
 use pipeline::vertex::Vertex;
 use pipeline::vertex::VertexDefinition;
 use pipeline::vertex::VertexSource;
+use pipeline::vertex::definition::decode_buffer_list;
 
 /// Unstable.
 // TODO: shouldn't be just `Two` but `Multi`
@@ -71,7 +73,8 @@ unsafe impl<T, U, I> VertexDefinition<I> for TwoBuffersDefinition<T, U>
                 }
 
                 let mut offset = infos.offset;
-                for loc in e.location.clone() {
+                let mut loc = e.location.start;
+                while loc < e.location.end {
                     attribs.push((loc,
                                   buf_offset,
                                   AttributeInfo {
@@ -79,6 +82,7 @@ unsafe impl<T, U, I> VertexDefinition<I> for TwoBuffersDefinition<T, U>
                                       format: e.format,
                                   }));
                     offset += e.format.size().unwrap();
+                    loc += e.format.num_locations();
                 }
             }
             attribs
@@ -100,7 +104,33 @@ unsafe impl<T, U> VertexSource<Vec<Arc<BufferAccess + Send + Sync>>> for TwoBuff
     #[inline]
     fn decode(&self, source: Vec<Arc<BufferAccess + Send + Sync>>)
               -> (Vec<Box<BufferAccess + Send + Sync>>, usize, usize) {
-        unimplemented!() // FIXME: implement
+        self.decode(&source[..])
+    }
+}
+
+unsafe impl<'a, T, U> VertexSource<&'a [Arc<BufferAccess + Send + Sync>]>
+    for TwoBuffersDefinition<T, U>
+    where T: Vertex,
+          U: Vertex
+{
+    #[inline]
+    fn decode(&self, source: &'a [Arc<BufferAccess + Send + Sync>])
+              -> (Vec<Box<BufferAccess + Send + Sync>>, usize, usize) {
+        let (buffers, lengths) =
+            decode_buffer_list(source, &[mem::size_of::<T>(), mem::size_of::<U>()]);
+        let vertices = cmp::min(lengths[0], lengths[1]);
+        (buffers, vertices, 1)
+    }
+}
+
+unsafe impl<T, U> VertexSource<[Arc<BufferAccess + Send + Sync>; 2]> for TwoBuffersDefinition<T, U>
+    where T: Vertex,
+          U: Vertex
+{
+    #[inline]
+    fn decode(&self, source: [Arc<BufferAccess + Send + Sync>; 2])
+              -> (Vec<Box<BufferAccess + Send + Sync>>, usize, usize) {
+        self.decode(&source[..])
     }
 }
 
@@ -120,3 +150,57 @@ unsafe impl<'a, T, U, Bt, Bu> VertexSource<(Bt, Bu)> for TwoBuffersDefinition<T,
         (vec![Box::new(source.0) as Box<_>, Box::new(source.1) as Box<_>], vertices, 1)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use buffer::BufferAccess;
+    use buffer::BufferUsage;
+    use buffer::CpuAccessibleBuffer;
+    use pipeline::vertex::TwoBuffersDefinition;
+    use pipeline::vertex::Vertex;
+    use pipeline::vertex::VertexMemberInfo;
+    use pipeline::vertex::VertexSource;
+
+    #[allow(dead_code)]
+    struct Position([f32; 4]);
+    unsafe impl Vertex for Position {
+        fn member(_: &str) -> Option<VertexMemberInfo> {
+            None
+        }
+    }
+
+    #[allow(dead_code)]
+    struct Color([f32; 2]);
+    unsafe impl Vertex for Color {
+        fn member(_: &str) -> Option<VertexMemberInfo> {
+            None
+        }
+    }
+
+    #[test]
+    fn decode_vec_slice_array() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let buffer = |len| {
+            CpuAccessibleBuffer::from_iter(device.clone(),
+                                           BufferUsage::vertex_buffer(),
+                                           Some(queue.family()),
+                                           (0 .. len).map(|_| 0u8))
+                .unwrap() as Arc<BufferAccess + Send + Sync>
+        };
+        let buffers = [buffer(5 * 16), buffer(3 * 8)];
+
+        let definition = TwoBuffersDefinition::<Position, Color>::new();
+        let from_slice = definition.decode(&buffers[..]);
+        let from_vec = definition.decode(buffers.to_vec());
+        let from_array = definition.decode([buffers[0].clone(), buffers[1].clone()]);
+
+        for &(ref boxed, vertices, instances) in [from_slice, from_vec, from_array].iter() {
+            assert_eq!(boxed.len(), 2);
+            assert_eq!(vertices, 3);
+            assert_eq!(instances, 1);
+        }
+    }
+}
@@ -48,10 +48,15 @@ pub enum VertexMemberTy {
     U32,
     F32,
     F64,
+    I64,
+    U64,
 }
 
 impl VertexMemberTy {
     /// Returns true if a combination of `(type, array_size)` matches a format.
+    ///
+    /// `num_locs` is the number of locations of the shader interface element. Formats that
+    /// consume two locations per element, like `R64G64B64Sfloat`, are taken into account.
     #[inline]
     pub fn matches(&self, array_size: usize, format: Format, num_locs: u32) -> bool {
         // TODO: implement correctly
@@ -64,6 +69,8 @@ impl VertexMemberTy {
             VertexMemberTy::U32 => 4,
             VertexMemberTy::F32 => 4,
             VertexMemberTy::F64 => 8,
+            VertexMemberTy::I64 => 8,
+            VertexMemberTy::U64 => 8,
         };
 
         let format_size = match format.size() {
@@ -71,6 +78,11 @@ impl VertexMemberTy {
             Some(s) => s,
         };
 
-        array_size * my_size == format_size * num_locs as usize
+        if num_locs % format.num_locations() != 0 {
+            return false;
+        }
+
+        let num_elements = (num_locs / format.num_locations()) as usize;
+        array_size * my_size == format_size * num_elements
     }
 }
@@ -0,0 +1,329 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Attaching user data to Vulkan objects.
+//!
+//! A `PrivateDataSlot` is a key that can be used to associate a 64-bit value with any buffer,
+//! image or pipeline of a device. This is useful for example to attach an identifier of your own
+//! to an object you receive from a library, without having to maintain a separate map.
+//!
+//! Private data slots require the `VK_EXT_private_data` extension, or Vulkan 1.3.
+
+use std::error;
+use std::fmt;
+use std::mem;
+use std::ptr;
+use std::sync::Arc;
+
+use buffer::sys::UnsafeBuffer;
+use device::Device;
+use device::DeviceOwned;
+use image::sys::UnsafeImage;
+use pipeline::ComputePipeline;
+use pipeline::GraphicsPipeline;
+
+use Error;
+use OomError;
+use VulkanObject;
+use check_errors;
+use vk;
+
+/// A key that associates a 64-bit value with Vulkan objects.
+///
+/// Each object has exactly one value per slot. The value of an object that was never set is 0.
+#[derive(Debug)]
+pub struct PrivateDataSlot {
+    slot: vk::PrivateDataSlotEXT,
+    device: Arc<Device>,
+}
+
+impl PrivateDataSlot {
+    /// Creates a new private data slot.
+    ///
+    /// Returns an error if the `ext_private_data` extension isn't enabled on the device and the
+    /// device doesn't support Vulkan 1.3.
+    pub fn new(device: Arc<Device>) -> Result<PrivateDataSlot, PrivateDataSlotCreationError> {
+        if !device.effective_extensions().ext_private_data {
+            return Err(PrivateDataSlotCreationError::ExtensionNotEnabled);
+        }
+
+        let slot = unsafe {
+            let infos = vk::PrivateDataSlotCreateInfoEXT {
+                sType: vk::STRUCTURE_TYPE_PRIVATE_DATA_SLOT_CREATE_INFO_EXT,
+                pNext: ptr::null(),
+                flags: 0, // reserved
+            };
+
+            let mut output = mem::uninitialized();
+            let vk = device.pointers();
+            check_errors(vk.CreatePrivateDataSlotEXT(device.internal_object(),
+                                                     &infos,
+                                                     ptr::null(),
+                                                     &mut output),
+                         "vkCreatePrivateDataSlotEXT")?;
+            output
+        };
+
+        Ok(PrivateDataSlot {
+               slot: slot,
+               device: device,
+           })
+    }
+
+    /// Sets the value associated with `object` in this slot.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the object doesn't belong to the same device as the slot.
+    ///
+    pub fn set<O>(&self, object: &O, data: u64) -> Result<(), OomError>
+        where O: ?Sized + PrivateDataObject
+    {
+        assert_eq!(object.object_device().internal_object(),
+                   self.device.internal_object());
+
+        let (ty, handle) = object.object_type_and_handle();
+
+        unsafe {
+            let vk = self.device.pointers();
+            check_errors(vk.SetPrivateDataEXT(self.device.internal_object(),
+                                              ty,
+                                              handle,
+                                              self.slot,
+                                              data),
+                         "vkSetPrivateDataEXT")?;
+            Ok(())
+        }
+    }
+
+    /// Returns the value associated with `object` in this slot.
+    ///
+    /// Returns 0 if no value has been set.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the object doesn't belong to the same device as the slot.
+    ///
+    pub fn get<O>(&self, object: &O) -> u64
+        where O: ?Sized + PrivateDataObject
+    {
+        assert_eq!(object.object_device().internal_object(),
+                   self.device.internal_object());
+
+        let (ty, handle) = object.object_type_and_handle();
+
+        unsafe {
+            let mut output = 0;
+            let vk = self.device.pointers();
+            vk.GetPrivateDataEXT(self.device.internal_object(),
+                                 ty,
+                                 handle,
+                                 self.slot,
+                                 &mut output);
+            output
+        }
+    }
+}
+
+unsafe impl DeviceOwned for PrivateDataSlot {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+}
+
+unsafe impl VulkanObject for PrivateDataSlot {
+    type Object = vk::PrivateDataSlotEXT;
+
+    #[inline]
+    fn internal_object(&self) -> vk::PrivateDataSlotEXT {
+        self.slot
+    }
+}
+
+impl Drop for PrivateDataSlot {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe {
+            let vk = self.device.pointers();
+            vk.DestroyPrivateDataSlotEXT(self.device.internal_object(), self.slot, ptr::null());
+        }
+    }
+}
+
+/// Vulkan object that private data can be attached to.
+///
+/// # Safety
+///
+/// The handle and object type returned by `object_type_and_handle` must be valid and must
+/// correspond to an object created from the device returned by `object_device`.
+pub unsafe trait PrivateDataObject {
+    /// Returns the device the object belongs to.
+    fn object_device(&self) -> &Arc<Device>;
+
+    /// Returns the type of the object and its raw handle.
+    fn object_type_and_handle(&self) -> (vk::ObjectType, u64);
+}
+
+unsafe impl PrivateDataObject for UnsafeBuffer {
+    #[inline]
+    fn object_device(&self) -> &Arc<Device> {
+        self.device()
+    }
+
+    #[inline]
+    fn object_type_and_handle(&self) -> (vk::ObjectType, u64) {
+        (vk::OBJECT_TYPE_BUFFER, self.internal_object())
+    }
+}
+
+unsafe impl PrivateDataObject for UnsafeImage {
+    #[inline]
+    fn object_device(&self) -> &Arc<Device> {
+        self.device()
+    }
+
+    #[inline]
+    fn object_type_and_handle(&self) -> (vk::ObjectType, u64) {
+        (vk::OBJECT_TYPE_IMAGE, self.internal_object())
+    }
+}
+
+unsafe impl<Mv, L, Rp> PrivateDataObject for GraphicsPipeline<Mv, L, Rp> {
+    #[inline]
+    fn object_device(&self) -> &Arc<Device> {
+        self.device()
+    }
+
+    #[inline]
+    fn object_type_and_handle(&self) -> (vk::ObjectType, u64) {
+        (vk::OBJECT_TYPE_PIPELINE, self.internal_object())
+    }
+}
+
+unsafe impl<Pl> PrivateDataObject for ComputePipeline<Pl> {
+    #[inline]
+    fn object_device(&self) -> &Arc<Device> {
+        self.device()
+    }
+
+    #[inline]
+    fn object_type_and_handle(&self) -> (vk::ObjectType, u64) {
+        (vk::OBJECT_TYPE_PIPELINE, self.internal_object())
+    }
+}
+
+/// Error that can happen when creating a private data slot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PrivateDataSlotCreationError {
+    /// Not enough memory.
+    OomError(OomError),
+    /// The `ext_private_data` extension must be enabled in order to create private data slots.
+    ExtensionNotEnabled,
+}
+
+impl error::Error for PrivateDataSlotCreationError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            PrivateDataSlotCreationError::OomError(_) => "not enough memory available",
+            PrivateDataSlotCreationError::ExtensionNotEnabled => {
+                "the `ext_private_data` extension must be enabled in order to create private \
+                 data slots"
+            },
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            PrivateDataSlotCreationError::OomError(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for PrivateDataSlotCreationError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl From<OomError> for PrivateDataSlotCreationError {
+    #[inline]
+    fn from(err: OomError) -> PrivateDataSlotCreationError {
+        PrivateDataSlotCreationError::OomError(err)
+    }
+}
+
+impl From<Error> for PrivateDataSlotCreationError {
+    #[inline]
+    fn from(err: Error) -> PrivateDataSlotCreationError {
+        match err {
+            err @ Error::OutOfHostMemory => {
+                PrivateDataSlotCreationError::OomError(OomError::from(err))
+            },
+            err @ Error::OutOfDeviceMemory => {
+                PrivateDataSlotCreationError::OomError(OomError::from(err))
+            },
+            _ => panic!("unexpected error: {:?}", err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::iter::Empty;
+
+    use buffer::BufferUsage;
+    use buffer::sys::SparseLevel;
+    use buffer::sys::UnsafeBuffer;
+    use private_data::PrivateDataSlot;
+    use private_data::PrivateDataSlotCreationError;
+    use sync::Sharing;
+
+    #[test]
+    fn extension_not_enabled() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        if device.effective_extensions().ext_private_data {
+            return;
+        }
+
+        match PrivateDataSlot::new(device) {
+            Err(PrivateDataSlotCreationError::ExtensionNotEnabled) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn set_get() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let slot = match PrivateDataSlot::new(device.clone()) {
+            Ok(s) => s,
+            Err(PrivateDataSlotCreationError::ExtensionNotEnabled) => return,
+            Err(e) => panic!("{:?}", e),
+        };
+
+        let (buffer, _) = unsafe {
+            UnsafeBuffer::new(device.clone(),
+                              128,
+                              BufferUsage::all(),
+                              Sharing::Exclusive::<Empty<_>>,
+                              SparseLevel::none())
+                .unwrap()
+        };
+
+        assert_eq!(slot.get(&buffer), 0);
+        slot.set(&buffer, 0xdeadbeef).unwrap();
+        assert_eq!(slot.get(&buffer), 0xdeadbeef);
+    }
+}
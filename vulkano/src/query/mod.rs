@@ -12,10 +12,14 @@
 //! In Vulkan, queries are not created individually. Instead you manipulate **query pools**, which
 //! represent a collection of queries. Whenever you use a query, you have to specify both the query
 //! pool and the slot id within that query pool.
+//!
+//! The `GpuProfiler` type uses timestamp queries to measure how much time the GPU spends on
+//! parts of a frame.
 
 use std::error;
 use std::fmt;
 use std::mem;
+use std::ops::Range;
 use std::ptr;
 use std::sync::Arc;
 
@@ -24,15 +28,23 @@ use device::Device;
 use Error;
 use OomError;
 use SafeDeref;
+use Success;
 use VulkanObject;
 use check_errors;
 use vk;
 
+pub use self::profiler::GpuProfiler;
+pub use self::profiler::GpuProfilerError;
+pub use self::profiler::GpuScopeTiming;
+
+mod profiler;
+
 pub struct UnsafeQueryPool<P = Arc<Device>>
     where P: SafeDeref<Target = Device>
 {
     pool: vk::QueryPool,
     device: P,
+    ty: QueryType,
     num_slots: u32,
 }
 
@@ -69,17 +81,25 @@ impl<P> UnsafeQueryPool<P>
             check_errors(vk.CreateQueryPool(device.internal_object(),
                                             &infos,
                                             ptr::null(),
-                                            &mut output))?;
+                                            &mut output),
+                         "vkCreateQueryPool")?;
             output
         };
 
         Ok(UnsafeQueryPool {
                pool: pool,
                device: device,
+               ty: ty,
                num_slots: num_slots,
            })
     }
 
+    /// Returns the type of the queries of that query pool.
+    #[inline]
+    pub fn ty(&self) -> QueryType {
+        self.ty
+    }
+
     /// Returns the number of slots of that query pool.
     #[inline]
     pub fn num_slots(&self) -> u32 {
@@ -91,6 +111,67 @@ impl<P> UnsafeQueryPool<P>
     pub fn device(&self) -> &P {
         &self.device
     }
+
+    /// Copies the results of the given range of queries to `destination`, as 64 bits values.
+    ///
+    /// If `wait` is true, blocks until the results are available. Otherwise returns `Ok(false)`
+    /// if the results of some queries aren't available yet, in which case the content of
+    /// `destination` is unspecified.
+    ///
+    /// # Safety
+    ///
+    /// - The queries must have been written by a command buffer that has been submitted, or
+    ///   `wait` would block forever.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `queries` is out of range of the slots of the pool.
+    /// - Panics if `destination` is shorter than the number of queries of `queries`.
+    ///
+    pub unsafe fn get_results_u64(&self, queries: Range<u32>, destination: &mut [u64], wait: bool)
+                                  -> Result<bool, QueryResultsError> {
+        assert!(queries.start <= queries.end);
+        assert!(queries.end <= self.num_slots);
+        let count = queries.end - queries.start;
+        assert!(destination.len() >= count as usize);
+
+        if count == 0 {
+            return Ok(true);
+        }
+
+        let mut flags = vk::QUERY_RESULT_64_BIT;
+        if wait {
+            flags |= vk::QUERY_RESULT_WAIT_BIT;
+        }
+
+        let vk = self.device.pointers();
+        let result = check_errors(vk.GetQueryPoolResults(self.device.internal_object(),
+                                                         self.pool,
+                                                         queries.start,
+                                                         count,
+                                                         count as usize * mem::size_of::<u64>(),
+                                                         destination.as_mut_ptr() as *mut _,
+                                                         mem::size_of::<u64>() as vk::DeviceSize,
+                                                         flags),
+                                  "vkGetQueryPoolResults")?;
+
+        match result {
+            Success::Success => Ok(true),
+            Success::NotReady => Ok(false),
+            s => panic!("unexpected success value: {:?}", s),
+        }
+    }
+}
+
+unsafe impl<P> VulkanObject for UnsafeQueryPool<P>
+    where P: SafeDeref<Target = Device>
+{
+    type Object = vk::QueryPool;
+
+    #[inline]
+    fn internal_object(&self) -> vk::QueryPool {
+        self.pool
+    }
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -230,6 +311,52 @@ impl From<OomError> for QueryPoolCreationError {
     }
 }
 
+/// Error that can happen when reading the results of queries.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum QueryResultsError {
+    /// Not enough memory.
+    OomError(OomError),
+    /// The connection to the device has been lost.
+    DeviceLost,
+}
+
+impl error::Error for QueryResultsError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            QueryResultsError::OomError(_) => "not enough memory available",
+            QueryResultsError::DeviceLost => "the connection to the device has been lost",
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            QueryResultsError::OomError(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for QueryResultsError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl From<Error> for QueryResultsError {
+    #[inline]
+    fn from(err: Error) -> QueryResultsError {
+        match err {
+            err @ Error::OutOfHostMemory => QueryResultsError::OomError(OomError::from(err)),
+            err @ Error::OutOfDeviceMemory => QueryResultsError::OomError(OomError::from(err)),
+            Error::DeviceLost => QueryResultsError::DeviceLost,
+            _ => panic!("unexpected error: {:?}", err),
+        }
+    }
+}
+
 impl From<Error> for QueryPoolCreationError {
     #[inline]
     fn from(err: Error) -> QueryPoolCreationError {
@@ -0,0 +1,501 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::error;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use command_buffer::AutoCommandBufferBuilder;
+use command_buffer::ResetQueryPoolError;
+use command_buffer::WriteTimestampError;
+use device::Device;
+use instance::QueueFamily;
+use query::QueryPoolCreationError;
+use query::QueryResultsError;
+use query::QueryType;
+use query::UnsafeQueryPool;
+use sync::PipelineStages;
+
+/// Measures how much time the GPU spends executing named parts of each frame.
+///
+/// At the start of each frame, call `begin_frame` on the command buffer builder of the frame.
+/// Then surround the commands to measure with `begin` and `end`. Once the frame has finished
+/// executing, its timings can be retrieved with `timings`.
+///
+/// The profiler uses one query pool per frame in flight, so that the results of a frame are only
+/// read once the GPU is done with it. The results of a frame are read when its query pool is
+/// about to be reused, which means that `timings` returns the timings of the frame that was
+/// started `frames_in_flight` frames ago.
+///
+/// # Example
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use vulkano::command_buffer::AutoCommandBufferBuilder;
+/// # use vulkano::device::Device;
+/// # use vulkano::device::Queue;
+/// use vulkano::query::GpuProfiler;
+///
+/// # let device: Arc<Device> = return;
+/// # let queue: Arc<Queue> = return;
+/// let mut profiler = GpuProfiler::new(device.clone(), queue.family(), 3, 16).unwrap();
+///
+/// loop {
+///     let builder = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap();
+///     let builder = profiler.begin_frame(builder).unwrap();
+///     let builder = profiler.begin("shadows", builder).unwrap();
+///     // ... draw the shadows ...
+///     let builder = profiler.end("shadows", builder).unwrap();
+///     // ... build and submit the command buffer ...
+///
+///     for timing in profiler.timings() {
+///         println!("{}: {:?}", timing.name, timing.duration);
+///     }
+///     # break;
+/// }
+/// ```
+pub struct GpuProfiler {
+    device: Arc<Device>,
+    // One entry per frame in flight.
+    frames: Vec<FrameQueries>,
+    // Index within `frames` of the frame being recorded, or `None` if `begin_frame` hasn't been
+    // called yet.
+    current: Option<usize>,
+    // Number of meaningful bits of the timestamps.
+    timestamp_valid_bits: u32,
+    // Timings of the last frame whose results have been read.
+    timings: Vec<GpuScopeTiming>,
+}
+
+// Queries of a single frame.
+struct FrameQueries {
+    pool: Arc<UnsafeQueryPool>,
+    scopes: Vec<Scope>,
+    // Index of the next query to allocate in the pool.
+    next_query: u32,
+}
+
+// A scope of a frame. The timestamp at the start of the scope is written to the query `begin`,
+// and the timestamp at the end to the query `begin + 1`.
+struct Scope {
+    name: String,
+    begin: u32,
+    ended: bool,
+}
+
+impl GpuProfiler {
+    /// Builds a new `GpuProfiler` for command buffers executed by queues of the given family.
+    ///
+    /// `frames_in_flight` must be at least the number of frames that the GPU can be working on
+    /// at the same time. `max_scopes` is the maximum number of scopes per frame.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `frames_in_flight` is 0.
+    /// - Panics if `max_scopes` is 0 or greater than `u32::MAX / 2`, as each scope uses two
+    ///   queries.
+    ///
+    pub fn new(device: Arc<Device>, queue_family: QueueFamily, frames_in_flight: usize,
+               max_scopes: u32)
+               -> Result<GpuProfiler, GpuProfilerError> {
+        assert!(frames_in_flight >= 1);
+        assert!(max_scopes >= 1, "max_scopes must be at least 1");
+        let num_queries = max_scopes
+            .checked_mul(2)
+            .expect("max_scopes is too large");
+
+        let timestamp_valid_bits = match queue_family.timestamp_valid_bits() {
+            Some(bits) => bits,
+            None => return Err(GpuProfilerError::TimestampsNotSupported),
+        };
+
+        let frames = (0 .. frames_in_flight)
+            .map(|_| {
+                let pool = UnsafeQueryPool::new(device.clone(), QueryType::Timestamp,
+                                                num_queries)?;
+                Ok(FrameQueries {
+                       pool: Arc::new(pool),
+                       scopes: Vec::new(),
+                       next_query: 0,
+                   })
+            })
+            .collect::<Result<Vec<_>, QueryPoolCreationError>>()?;
+
+        Ok(GpuProfiler {
+               device: device,
+               frames: frames,
+               current: None,
+               timestamp_valid_bits: timestamp_valid_bits,
+               timings: Vec::new(),
+           })
+    }
+
+    /// Starts a new frame and adds the commands that prepare its queries to `builder`.
+    ///
+    /// This reads the timings of the frame that previously used the same query pool. The scopes
+    /// of that frame that haven't been ended or whose results aren't available are ignored. The
+    /// results are unavailable if the frame has never been submitted, or if the GPU hasn't
+    /// finished executing it, in which case `frames_in_flight` is too small.
+    ///
+    /// Must be called outside of a render pass.
+    pub fn begin_frame<P>(&mut self, builder: AutoCommandBufferBuilder<P>)
+                          -> Result<AutoCommandBufferBuilder<P>, GpuProfilerError> {
+        let next = match self.current {
+            Some(current) => (current + 1) % self.frames.len(),
+            None => 0,
+        };
+
+        if !self.frames[next].scopes.is_empty() {
+            self.timings = self.read_timings(&self.frames[next])?;
+        }
+
+        let frame = &mut self.frames[next];
+        let num_slots = frame.pool.num_slots();
+        let builder = builder.reset_query_pool(frame.pool.clone(), 0 .. num_slots)?;
+        frame.scopes.clear();
+        frame.next_query = 0;
+        self.current = Some(next);
+        Ok(builder)
+    }
+
+    /// Adds to `builder` a command that marks the start of the scope named `name`.
+    ///
+    /// The scope must later be ended with `end`. Scopes can be nested, and multiple scopes can
+    /// have the same name.
+    pub fn begin<P>(&mut self, name: &str, builder: AutoCommandBufferBuilder<P>)
+                    -> Result<AutoCommandBufferBuilder<P>, GpuProfilerError> {
+        let frame = match self.current {
+            Some(current) => &mut self.frames[current],
+            None => return Err(GpuProfilerError::NoFrame),
+        };
+
+        if frame.next_query + 2 > frame.pool.num_slots() {
+            return Err(GpuProfilerError::TooManyScopes);
+        }
+
+        let stage = PipelineStages {
+            top_of_pipe: true,
+            ..PipelineStages::none()
+        };
+        let builder = builder.write_timestamp(frame.pool.clone(), frame.next_query, stage)?;
+
+        frame.scopes.push(Scope {
+                              name: name.to_owned(),
+                              begin: frame.next_query,
+                              ended: false,
+                          });
+        frame.next_query += 2;
+        Ok(builder)
+    }
+
+    /// Adds to `builder` a command that marks the end of the latest scope named `name` that
+    /// hasn't been ended yet.
+    pub fn end<P>(&mut self, name: &str, builder: AutoCommandBufferBuilder<P>)
+                  -> Result<AutoCommandBufferBuilder<P>, GpuProfilerError> {
+        let frame = match self.current {
+            Some(current) => &mut self.frames[current],
+            None => return Err(GpuProfilerError::NoFrame),
+        };
+
+        let scope = frame.scopes.iter_mut().rev().find(|s| !s.ended && s.name == name);
+        let scope = match scope {
+            Some(s) => s,
+            None => return Err(GpuProfilerError::UnknownScope),
+        };
+
+        let stage = PipelineStages {
+            bottom_of_pipe: true,
+            ..PipelineStages::none()
+        };
+        let builder = builder.write_timestamp(frame.pool.clone(), scope.begin + 1, stage)?;
+        scope.ended = true;
+        Ok(builder)
+    }
+
+    /// Returns the timings of the scopes of the last frame whose results have been read, in the
+    /// order in which the scopes were begun.
+    #[inline]
+    pub fn timings(&self) -> &[GpuScopeTiming] {
+        &self.timings
+    }
+
+    /// Returns the duration of the first scope named `name` in `timings`, if any.
+    #[inline]
+    pub fn timing(&self, name: &str) -> Option<Duration> {
+        self.timings.iter().find(|t| t.name == name).map(|t| t.duration)
+    }
+
+    // Reads the results of the scopes of `frame` that have been ended and whose results are
+    // available.
+    fn read_timings(&self, frame: &FrameQueries)
+                    -> Result<Vec<GpuScopeTiming>, GpuProfilerError> {
+        let mask = if self.timestamp_valid_bits >= 64 {
+            !0
+        } else {
+            (1u64 << self.timestamp_valid_bits) - 1
+        };
+        let period = self.device.physical_device().limits().timestamp_period() as f64;
+
+        let mut timings = Vec::with_capacity(frame.scopes.len());
+        for scope in frame.scopes.iter().filter(|s| s.ended) {
+            let mut results = [0u64; 2];
+            let available = unsafe {
+                frame.pool.get_results_u64(scope.begin .. scope.begin + 2, &mut results, false)?
+            };
+            if !available {
+                continue;
+            }
+
+            let ticks = results[1].wrapping_sub(results[0]) & mask;
+            let nanos = (ticks as f64 * period) as u64;
+            timings.push(GpuScopeTiming {
+                             name: scope.name.clone(),
+                             duration: Duration::new(nanos / 1_000_000_000,
+                                                     (nanos % 1_000_000_000) as u32),
+                         });
+        }
+
+        Ok(timings)
+    }
+}
+
+impl fmt::Debug for GpuProfiler {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        fmt.debug_struct("GpuProfiler")
+            .field("frames_in_flight", &self.frames.len())
+            .field("timings", &self.timings)
+            .finish()
+    }
+}
+
+/// Time that the GPU spent executing a scope of a `GpuProfiler`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GpuScopeTiming {
+    /// Name that was passed to `begin`.
+    pub name: String,
+    /// Time between the start and the end of the scope.
+    pub duration: Duration,
+}
+
+/// Error that can happen when using a `GpuProfiler`.
+#[derive(Debug, Clone)]
+pub enum GpuProfilerError {
+    /// The queue family doesn't support timestamps.
+    TimestampsNotSupported,
+    /// Error while creating a query pool.
+    QueryPoolCreationError(QueryPoolCreationError),
+    /// `begin_frame` hasn't been called yet.
+    NoFrame,
+    /// All the queries of the frame have been used. Create the profiler with a larger
+    /// `max_scopes`.
+    TooManyScopes,
+    /// There is no scope with this name that hasn't been ended yet.
+    UnknownScope,
+    /// Error while reading the results of the queries.
+    QueryResultsError(QueryResultsError),
+    /// Error while adding the command that resets the queries.
+    ResetQueryPoolError(ResetQueryPoolError),
+    /// Error while adding a command that writes a timestamp.
+    WriteTimestampError(WriteTimestampError),
+}
+
+impl error::Error for GpuProfilerError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            GpuProfilerError::TimestampsNotSupported => {
+                "the queue family doesn't support timestamps"
+            },
+            GpuProfilerError::QueryPoolCreationError(_) => {
+                "error while creating a query pool"
+            },
+            GpuProfilerError::NoFrame => {
+                "begin_frame hasn't been called yet"
+            },
+            GpuProfilerError::TooManyScopes => {
+                "all the queries of the frame have been used"
+            },
+            GpuProfilerError::UnknownScope => {
+                "there is no scope with this name that hasn't been ended yet"
+            },
+            GpuProfilerError::QueryResultsError(_) => {
+                "error while reading the results of the queries"
+            },
+            GpuProfilerError::ResetQueryPoolError(_) => {
+                "error while adding the command that resets the queries"
+            },
+            GpuProfilerError::WriteTimestampError(_) => {
+                "error while adding a command that writes a timestamp"
+            },
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            GpuProfilerError::QueryPoolCreationError(ref err) => Some(err),
+            GpuProfilerError::QueryResultsError(ref err) => Some(err),
+            GpuProfilerError::ResetQueryPoolError(ref err) => Some(err),
+            GpuProfilerError::WriteTimestampError(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for GpuProfilerError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl From<QueryPoolCreationError> for GpuProfilerError {
+    #[inline]
+    fn from(err: QueryPoolCreationError) -> GpuProfilerError {
+        GpuProfilerError::QueryPoolCreationError(err)
+    }
+}
+
+impl From<QueryResultsError> for GpuProfilerError {
+    #[inline]
+    fn from(err: QueryResultsError) -> GpuProfilerError {
+        GpuProfilerError::QueryResultsError(err)
+    }
+}
+
+impl From<ResetQueryPoolError> for GpuProfilerError {
+    #[inline]
+    fn from(err: ResetQueryPoolError) -> GpuProfilerError {
+        GpuProfilerError::ResetQueryPoolError(err)
+    }
+}
+
+impl From<WriteTimestampError> for GpuProfilerError {
+    #[inline]
+    fn from(err: WriteTimestampError) -> GpuProfilerError {
+        GpuProfilerError::WriteTimestampError(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::iter;
+
+    use buffer::BufferUsage;
+    use buffer::CpuAccessibleBuffer;
+    use command_buffer::AutoCommandBufferBuilder;
+    use command_buffer::CommandBuffer;
+    use query::GpuProfiler;
+    use query::GpuProfilerError;
+    use sync::GpuFuture;
+
+    #[test]
+    fn scope_timings() {
+        let (device, queue) = gfx_dev_and_queue!();
+        if queue.family().timestamp_valid_bits().is_none() {
+            return;
+        }
+
+        let buffer = CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::all(),
+                                                    iter::once(queue.family()),
+                                                    (0 .. 1024).map(|_| 0u32))
+            .unwrap();
+
+        let mut profiler = GpuProfiler::new(device.clone(), queue.family(), 1, 4).unwrap();
+
+        let builder = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap();
+        let builder = profiler.begin_frame(builder).unwrap();
+        let builder = profiler.begin("fill", builder).unwrap();
+        let builder = builder.fill_buffer(buffer.clone(), 7).unwrap();
+        let builder = profiler.end("fill", builder).unwrap();
+        builder
+            .build()
+            .unwrap()
+            .execute(queue.clone())
+            .unwrap()
+            .then_signal_fence_and_flush()
+            .unwrap()
+            .wait(None)
+            .unwrap();
+        assert!(profiler.timings().is_empty());
+
+        // Starting the next frame reads the results of the first one.
+        let builder = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap();
+        let _builder = profiler.begin_frame(builder).unwrap();
+        assert_eq!(profiler.timings().len(), 1);
+        assert_eq!(profiler.timings()[0].name, "fill");
+        assert!(profiler.timing("fill").is_some());
+    }
+
+    #[test]
+    fn unbalanced_scopes() {
+        let (device, queue) = gfx_dev_and_queue!();
+        if queue.family().timestamp_valid_bits().is_none() {
+            return;
+        }
+
+        let mut profiler = GpuProfiler::new(device.clone(), queue.family(), 2, 1).unwrap();
+
+        let builder = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap();
+        match profiler.begin("early", builder) {
+            Err(GpuProfilerError::NoFrame) => (),
+            _ => panic!(),
+        }
+
+        let builder = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap();
+        let builder = profiler.begin_frame(builder).unwrap();
+        let builder = profiler.begin("a", builder).unwrap();
+        match profiler.begin("b", builder) {
+            Err(GpuProfilerError::TooManyScopes) => (),
+            _ => panic!(),
+        }
+
+        let builder = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap();
+        match profiler.end("b", builder) {
+            Err(GpuProfilerError::UnknownScope) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn unsubmitted_frame() {
+        let (device, queue) = gfx_dev_and_queue!();
+        if queue.family().timestamp_valid_bits().is_none() {
+            return;
+        }
+
+        let mut profiler = GpuProfiler::new(device.clone(), queue.family(), 1, 4).unwrap();
+
+        let builder = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap();
+        let builder = profiler.begin_frame(builder).unwrap();
+        let builder = profiler.begin("never submitted", builder).unwrap();
+        let _builder = profiler.end("never submitted", builder).unwrap();
+
+        // The results of the frame are never available, and are dropped.
+        let builder = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap();
+        let _builder = profiler.begin_frame(builder).unwrap();
+        assert!(profiler.timings().is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "max_scopes must be at least 1")]
+    fn zero_scopes() {
+        let (device, queue) = gfx_dev_and_queue!();
+        let _ = GpuProfiler::new(device, queue.family(), 1, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "max_scopes is too large")]
+    fn too_many_scopes() {
+        let (device, queue) = gfx_dev_and_queue!();
+        let _ = GpuProfiler::new(device, queue.family(), 1, u32::max_value());
+    }
+}
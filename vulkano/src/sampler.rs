@@ -319,7 +319,8 @@ impl Sampler {
             check_errors(vk.CreateSampler(device.internal_object(),
                                           &infos,
                                           ptr::null(),
-                                          &mut output))?;
+                                          &mut output),
+                         "vkCreateSampler")?;
             output
         };
 
@@ -406,7 +407,8 @@ impl Sampler {
             check_errors(vk.CreateSampler(device.internal_object(),
                                           &infos,
                                           ptr::null(),
-                                          &mut output))?;
+                                          &mut output),
+                         "vkCreateSampler")?;
             output
         };
 
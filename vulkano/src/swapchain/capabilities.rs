@@ -7,8 +7,14 @@
 // notice may not be copied, modified, or distributed except
 // according to those terms.
 
+use std::cmp;
+use std::error;
+use std::fmt;
+
 use format::Format;
 use image::ImageUsage;
+use pipeline::viewport::Scissor;
+use pipeline::viewport::Viewport;
 use vk;
 
 /// The capabilities of a surface when used by a physical device.
@@ -57,6 +63,113 @@ pub struct Capabilities {
     pub present_modes: SupportedPresentModes,
 }
 
+impl Capabilities {
+    /// Returns the number of swapchain images that is the closest to `count` while being
+    /// supported by the surface.
+    ///
+    /// Compare the returned value with `count` in order to know whether it was adjusted.
+    #[inline]
+    pub fn clamp_image_count(&self, count: u32) -> u32 {
+        let count = cmp::max(count, self.min_image_count);
+        match self.max_image_count {
+            Some(max) => cmp::min(count, max),
+            None => count,
+        }
+    }
+
+    /// Returns the dimensions that are the closest to `dimensions` while being between
+    /// `min_image_extent` and `max_image_extent`.
+    ///
+    /// Compare the returned value with `dimensions` in order to know whether it was adjusted.
+    #[inline]
+    pub fn clamp_extent(&self, dimensions: [u32; 2]) -> [u32; 2] {
+        [
+            cmp::min(cmp::max(dimensions[0], self.min_image_extent[0]), self.max_image_extent[0]),
+            cmp::min(cmp::max(dimensions[1], self.min_image_extent[1]), self.max_image_extent[1]),
+        ]
+    }
+
+    /// Returns true if the surface currently has a width or a height of zero.
+    ///
+    /// This typically happens when a window is minimized. No swapchain can be created for the
+    /// surface in that situation, and you should skip rendering until the surface gets a
+    /// non-zero extent again.
+    #[inline]
+    pub fn is_zero_extent(&self) -> bool {
+        let current_zero = match self.current_extent {
+            Some(extent) => extent[0] == 0 || extent[1] == 0,
+            None => false,
+        };
+
+        current_zero || self.max_image_extent[0] == 0 || self.max_image_extent[1] == 0
+    }
+
+    /// Returns the transform to use when creating a swapchain if you don't have any particular
+    /// requirement.
+    ///
+    /// This is `SurfaceTransform::Identity` if it is supported, and `current_transform`
+    /// otherwise.
+    #[inline]
+    pub fn default_transform(&self) -> SurfaceTransform {
+        if self.supported_transforms.supports(SurfaceTransform::Identity) {
+            SurfaceTransform::Identity
+        } else {
+            self.current_transform
+        }
+    }
+
+    /// Returns the composite alpha mode to use when creating a swapchain if you don't have any
+    /// particular requirement.
+    ///
+    /// This is `CompositeAlpha::Opaque` if it is supported, and the first supported mode
+    /// otherwise.
+    #[inline]
+    pub fn default_composite_alpha(&self) -> CompositeAlpha {
+        if self.supported_composite_alpha.supports(CompositeAlpha::Opaque) {
+            CompositeAlpha::Opaque
+        } else {
+            // The Vulkan specs guarantee that at least one mode is supported.
+            self.supported_composite_alpha.iter().next().unwrap()
+        }
+    }
+
+    /// Returns the first composite alpha mode of `preferences` that is supported, or an error
+    /// containing the supported modes if none of them is.
+    ///
+    /// For example a transparent window can pass `[PostMultiplied, PreMultiplied]` and
+    /// premultiply its colors or not depending on the result. Note that the alpha values are
+    /// only meaningful if the format of the swapchain has an alpha component.
+    pub fn choose_composite_alpha<I>(&self, preferences: I)
+                                     -> Result<CompositeAlpha, UnsupportedCompositeAlphaError>
+        where I: IntoIterator<Item = CompositeAlpha>
+    {
+        let supported = self.supported_composite_alpha;
+        preferences
+            .into_iter()
+            .find(|&alpha| supported.supports(alpha))
+            .ok_or(UnsupportedCompositeAlphaError { supported: supported })
+    }
+
+    /// Returns the format and color space to use when creating a swapchain if you don't have any
+    /// particular requirement.
+    ///
+    /// This is the first supported sRGB format with the `SrgbNonLinear` color space if there is
+    /// one, so that the values written by shaders are treated as linear. Otherwise this is the
+    /// first supported format. Call `is_srgb()` on the returned format to know which one you got;
+    /// if it isn't an sRGB format, you have to encode the colors yourself before writing them.
+    #[inline]
+    pub fn default_format(&self) -> (Format, ColorSpace) {
+        self.supported_formats
+            .iter()
+            .find(|&&(format, color_space)| {
+                      format.is_srgb() && color_space == ColorSpace::SrgbNonLinear
+                  })
+            .or(self.supported_formats.first())
+            .cloned()
+            .unwrap() // The Vulkan specs guarantee that at least one format is supported.
+    }
+}
+
 /// The way presenting a swapchain is accomplished.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 #[repr(u32)]
@@ -82,6 +195,37 @@ pub enum PresentMode {
     ///
     /// This is the equivalent of OpenGL's `SwapInterval` with a value of -1.
     Relaxed = vk::PRESENT_MODE_FIFO_RELAXED_KHR,
+
+    /// The swapchain contains a single image that is shared between the application and the
+    /// presentation engine. The presentation engine only updates the screen after the image has
+    /// been presented, but the application can continue drawing on the image without acquiring
+    /// it again.
+    ///
+    /// Requires the `VK_KHR_shared_presentable_image` extension.
+    SharedDemandRefresh = vk::PRESENT_MODE_SHARED_DEMAND_REFRESH_KHR,
+
+    /// The swapchain contains a single image that is shared between the application and the
+    /// presentation engine. The presentation engine periodically updates the screen with the
+    /// content of the image, whether or not it has been presented again. Presenting the image
+    /// is still required once after it has been acquired.
+    ///
+    /// Requires the `VK_KHR_shared_presentable_image` extension.
+    SharedContinuousRefresh = vk::PRESENT_MODE_SHARED_CONTINUOUS_REFRESH_KHR,
+}
+
+impl PresentMode {
+    /// Returns true if this is one of the shared presentable image modes.
+    ///
+    /// A swapchain that uses a shared mode has exactly one image, which is in the
+    /// `SharedPresent` layout instead of the `PresentSrc` layout.
+    #[inline]
+    pub fn is_shared(&self) -> bool {
+        match *self {
+            PresentMode::SharedDemandRefresh |
+            PresentMode::SharedContinuousRefresh => true,
+            _ => false,
+        }
+    }
 }
 
 /// List of `PresentMode`s that are supported.
@@ -91,6 +235,8 @@ pub struct SupportedPresentModes {
     pub mailbox: bool,
     pub fifo: bool,
     pub relaxed: bool,
+    pub shared_demand_refresh: bool,
+    pub shared_continuous_refresh: bool,
 }
 
 pub fn supported_present_modes_from_list<I>(elem: I) -> SupportedPresentModes
@@ -103,6 +249,10 @@ pub fn supported_present_modes_from_list<I>(elem: I) -> SupportedPresentModes
             vk::PRESENT_MODE_MAILBOX_KHR => result.mailbox = true,
             vk::PRESENT_MODE_FIFO_KHR => result.fifo = true,
             vk::PRESENT_MODE_FIFO_RELAXED_KHR => result.relaxed = true,
+            vk::PRESENT_MODE_SHARED_DEMAND_REFRESH_KHR => result.shared_demand_refresh = true,
+            vk::PRESENT_MODE_SHARED_CONTINUOUS_REFRESH_KHR => {
+                result.shared_continuous_refresh = true
+            },
             _ => panic!("Wrong value for vk::PresentModeKHR"),
         }
     }
@@ -118,6 +268,8 @@ impl SupportedPresentModes {
             mailbox: false,
             fifo: false,
             relaxed: false,
+            shared_demand_refresh: false,
+            shared_continuous_refresh: false,
         }
     }
 
@@ -129,6 +281,8 @@ impl SupportedPresentModes {
             PresentMode::Mailbox => self.mailbox,
             PresentMode::Fifo => self.fifo,
             PresentMode::Relaxed => self.relaxed,
+            PresentMode::SharedDemandRefresh => self.shared_demand_refresh,
+            PresentMode::SharedContinuousRefresh => self.shared_continuous_refresh,
         }
     }
 
@@ -164,6 +318,14 @@ impl Iterator for SupportedPresentModesIter {
             self.0.relaxed = false;
             return Some(PresentMode::Relaxed);
         }
+        if self.0.shared_demand_refresh {
+            self.0.shared_demand_refresh = false;
+            return Some(PresentMode::SharedDemandRefresh);
+        }
+        if self.0.shared_continuous_refresh {
+            self.0.shared_continuous_refresh = false;
+            return Some(PresentMode::SharedContinuousRefresh);
+        }
         None
     }
 }
@@ -192,6 +354,145 @@ pub enum SurfaceTransform {
     Inherit = vk::SURFACE_TRANSFORM_INHERIT_BIT_KHR,
 }
 
+impl SurfaceTransform {
+    /// Returns true if the transform rotates the image by 90 or 270 degrees, in which case the
+    /// width and height of the swapchain images are swapped compared to what the user sees.
+    #[inline]
+    pub fn swaps_dimensions(&self) -> bool {
+        match *self {
+            SurfaceTransform::Rotate90 |
+            SurfaceTransform::Rotate270 |
+            SurfaceTransform::HorizontalMirrorRotate90 |
+            SurfaceTransform::HorizontalMirrorRotate270 => true,
+            _ => false,
+        }
+    }
+
+    /// Converts between the dimensions of the swapchain images and the dimensions as seen by the
+    /// user. The conversion is the same in both directions.
+    #[inline]
+    pub fn pre_rotated_dimensions(&self, dimensions: [u32; 2]) -> [u32; 2] {
+        if self.swaps_dimensions() {
+            [dimensions[1], dimensions[0]]
+        } else {
+            dimensions
+        }
+    }
+
+    /// Returns the column-major matrix to multiply the final clip-space positions with when
+    /// rendering to a swapchain created with this transform.
+    ///
+    /// When rendering with a transform other than `Identity`, the presentation engine rotates the
+    /// images before showing them. Rendering with this matrix pre-rotates the content so that it
+    /// is shown in the right orientation. It is usually applied as
+    /// `pre_rotation * projection * view * model`.
+    ///
+    /// `Inherit` is treated as `Identity`.
+    pub fn pre_rotation_matrix(&self) -> [[f32; 4]; 4] {
+        let (a, b, c, d) = self.pre_rotation_coefficients();
+        [
+            [a, c, 0.0, 0.0],
+            [b, d, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]
+    }
+
+    /// Converts a viewport expressed in the coordinates seen by the user into the coordinates
+    /// of the swapchain images.
+    ///
+    /// `dimensions` are the dimensions as seen by the user, in other words the swapchain
+    /// dimensions passed through `pre_rotated_dimensions`.
+    pub fn pre_rotate_viewport(&self, viewport: &Viewport, dimensions: [u32; 2]) -> Viewport {
+        let (origin, size) = self.pre_rotate_rect([viewport.origin[0] as f64,
+                                                   viewport.origin[1] as f64],
+                                                  [viewport.dimensions[0] as f64,
+                                                   viewport.dimensions[1] as f64],
+                                                  dimensions);
+        Viewport {
+            origin: [origin[0] as f32, origin[1] as f32],
+            dimensions: [size[0] as f32, size[1] as f32],
+            depth_range: viewport.depth_range.clone(),
+        }
+    }
+
+    /// Converts a scissor box expressed in the coordinates seen by the user into the coordinates
+    /// of the swapchain images.
+    ///
+    /// `dimensions` are the dimensions as seen by the user, in other words the swapchain
+    /// dimensions passed through `pre_rotated_dimensions`.
+    pub fn pre_rotate_scissor(&self, scissor: &Scissor, dimensions: [u32; 2]) -> Scissor {
+        let (origin, size) = self.pre_rotate_rect([scissor.origin[0] as f64,
+                                                   scissor.origin[1] as f64],
+                                                  [scissor.dimensions[0] as f64,
+                                                   scissor.dimensions[1] as f64],
+                                                  dimensions);
+        Scissor {
+            origin: [origin[0] as i32, origin[1] as i32],
+            dimensions: [size[0] as u32, size[1] as u32],
+        }
+    }
+
+    // Returns `(a, b, c, d)` so that a point `(x, y)` in the normalized coordinates seen by the
+    // user ends up at `(a * x + b * y, c * x + d * y)` in the swapchain image.
+    fn pre_rotation_coefficients(&self) -> (f32, f32, f32, f32) {
+        let (a, b, c, d) = match *self {
+            SurfaceTransform::Rotate90 |
+            SurfaceTransform::HorizontalMirrorRotate90 => (0.0, 1.0, -1.0, 0.0),
+            SurfaceTransform::Rotate180 |
+            SurfaceTransform::HorizontalMirrorRotate180 => (-1.0, 0.0, 0.0, -1.0),
+            SurfaceTransform::Rotate270 |
+            SurfaceTransform::HorizontalMirrorRotate270 => (0.0, -1.0, 1.0, 0.0),
+            SurfaceTransform::Identity |
+            SurfaceTransform::HorizontalMirror |
+            SurfaceTransform::Inherit => (1.0, 0.0, 0.0, 1.0),
+        };
+
+        if self.is_mirrored() {
+            (-a, -b, c, d)
+        } else {
+            (a, b, c, d)
+        }
+    }
+
+    #[inline]
+    fn is_mirrored(&self) -> bool {
+        match *self {
+            SurfaceTransform::HorizontalMirror |
+            SurfaceTransform::HorizontalMirrorRotate90 |
+            SurfaceTransform::HorizontalMirrorRotate180 |
+            SurfaceTransform::HorizontalMirrorRotate270 => true,
+            _ => false,
+        }
+    }
+
+    // Same as `pre_rotation_coefficients`, but for a rectangle in pixels.
+    fn pre_rotate_rect(&self, origin: [f64; 2], size: [f64; 2], dimensions: [u32; 2])
+                       -> ([f64; 2], [f64; 2]) {
+        let (w, h) = (dimensions[0] as f64, dimensions[1] as f64);
+        let ([x, y], [rw, rh]) = (origin, size);
+
+        let (origin, size) = match *self {
+            SurfaceTransform::Rotate90 |
+            SurfaceTransform::HorizontalMirrorRotate90 => ([y, w - x - rw], [rh, rw]),
+            SurfaceTransform::Rotate180 |
+            SurfaceTransform::HorizontalMirrorRotate180 => ([w - x - rw, h - y - rh], [rw, rh]),
+            SurfaceTransform::Rotate270 |
+            SurfaceTransform::HorizontalMirrorRotate270 => ([h - y - rh, x], [rh, rw]),
+            SurfaceTransform::Identity |
+            SurfaceTransform::HorizontalMirror |
+            SurfaceTransform::Inherit => ([x, y], [rw, rh]),
+        };
+
+        if self.is_mirrored() {
+            let image_width = if self.swaps_dimensions() { h } else { w };
+            ([image_width - origin[0] - size[0], origin[1]], size)
+        } else {
+            (origin, size)
+        }
+    }
+}
+
 /// How the alpha values of the pixels of the window are treated.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 #[repr(u32)]
@@ -300,6 +601,31 @@ impl Iterator for SupportedCompositeAlphaIter {
     }
 }
 
+/// Error returned by `Capabilities::choose_composite_alpha` when none of the requested modes is
+/// supported by the surface.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct UnsupportedCompositeAlphaError {
+    /// The modes that the surface supports.
+    pub supported: SupportedCompositeAlpha,
+}
+
+impl error::Error for UnsupportedCompositeAlphaError {
+    #[inline]
+    fn description(&self) -> &str {
+        "none of the requested composite alpha modes is supported by the surface"
+    }
+}
+
+impl fmt::Display for UnsupportedCompositeAlphaError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{} (supported modes:", error::Error::description(self))?;
+        for alpha in self.supported.iter() {
+            write!(fmt, " {:?}", alpha)?;
+        }
+        write!(fmt, ")")
+    }
+}
+
 /// List of supported composite alpha modes.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct SupportedSurfaceTransforms {
@@ -581,3 +907,218 @@ pub fn color_space_from_num(val: u32) -> ColorSpace {
         _ => panic!("Wrong value for color space enum"),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use swapchain::CompositeAlpha;
+    use swapchain::PresentMode;
+    use swapchain::SupportedCompositeAlpha;
+    use swapchain::SurfaceTransform;
+    use swapchain::capabilities::supported_composite_alpha_from_bits;
+    use swapchain::capabilities::supported_present_modes_from_list;
+    use swapchain::capabilities::UnsupportedCompositeAlphaError;
+    use vk;
+
+    fn capabilities(alpha: SupportedCompositeAlpha) -> ::swapchain::Capabilities {
+        ::swapchain::Capabilities {
+            min_image_count: 2,
+            max_image_count: None,
+            current_extent: None,
+            min_image_extent: [1, 1],
+            max_image_extent: [4096, 4096],
+            max_image_array_layers: 1,
+            supported_transforms: ::swapchain::capabilities::surface_transforms_from_bits(
+                vk::SURFACE_TRANSFORM_IDENTITY_BIT_KHR),
+            current_transform: ::swapchain::SurfaceTransform::Identity,
+            supported_composite_alpha: alpha,
+            supported_usage_flags: ::image::ImageUsage::none(),
+            supported_formats: Vec::new(),
+            present_modes: ::swapchain::SupportedPresentModes::none(),
+        }
+    }
+
+    #[test]
+    fn clamp_extent() {
+        let mut caps = capabilities(supported_composite_alpha_from_bits(
+            vk::COMPOSITE_ALPHA_OPAQUE_BIT_KHR));
+        caps.min_image_extent = [16, 8];
+        caps.max_image_extent = [1024, 512];
+
+        assert_eq!(caps.clamp_extent([640, 480]), [640, 480]);
+        assert_eq!(caps.clamp_extent([4, 4]), [16, 8]);
+        assert_eq!(caps.clamp_extent([2048, 100]), [1024, 100]);
+        assert_eq!(caps.clamp_extent([0, 0]), [16, 8]);
+    }
+
+    #[test]
+    fn clamp_image_count() {
+        let mut caps = capabilities(supported_composite_alpha_from_bits(
+            vk::COMPOSITE_ALPHA_OPAQUE_BIT_KHR));
+
+        // Minimum, maximum, requested count and expected result.
+        let tests = [
+            (2, Some(4), 1, 2),
+            (2, Some(4), 0, 2),
+            (2, Some(4), 3, 3),
+            (2, Some(4), 4, 4),
+            (2, Some(4), 5, 4),
+            (2, Some(4), u32::max_value(), 4),
+            (3, Some(3), 8, 3),
+            (2, None, 1, 2),
+            (2, None, 3, 3),
+            (2, None, u32::max_value(), u32::max_value()),
+        ];
+
+        for &(min, max, count, expected) in tests.iter() {
+            caps.min_image_count = min;
+            caps.max_image_count = max;
+            assert_eq!(caps.clamp_image_count(count), expected);
+        }
+    }
+
+    #[test]
+    fn zero_extent() {
+        let mut caps = capabilities(supported_composite_alpha_from_bits(
+            vk::COMPOSITE_ALPHA_OPAQUE_BIT_KHR));
+        assert!(!caps.is_zero_extent());
+
+        caps.current_extent = Some([800, 600]);
+        assert!(!caps.is_zero_extent());
+
+        // What Windows reports for a minimized window.
+        caps.current_extent = Some([0, 0]);
+        caps.min_image_extent = [0, 0];
+        caps.max_image_extent = [0, 0];
+        assert!(caps.is_zero_extent());
+
+        caps.current_extent = None;
+        caps.max_image_extent = [4096, 0];
+        assert!(caps.is_zero_extent());
+    }
+
+    #[test]
+    fn choose_composite_alpha_fallback() {
+        let caps = capabilities(supported_composite_alpha_from_bits(
+            vk::COMPOSITE_ALPHA_OPAQUE_BIT_KHR | vk::COMPOSITE_ALPHA_PRE_MULTIPLIED_BIT_KHR));
+
+        let chosen = caps.choose_composite_alpha(vec![CompositeAlpha::PostMultiplied,
+                                                      CompositeAlpha::PreMultiplied,
+                                                      CompositeAlpha::Opaque]);
+        assert_eq!(chosen, Ok(CompositeAlpha::PreMultiplied));
+    }
+
+    #[test]
+    fn choose_composite_alpha_unsupported() {
+        let supported = supported_composite_alpha_from_bits(vk::COMPOSITE_ALPHA_OPAQUE_BIT_KHR);
+        let caps = capabilities(supported);
+
+        let err = caps.choose_composite_alpha(vec![CompositeAlpha::PostMultiplied]).unwrap_err();
+        assert_eq!(err, UnsupportedCompositeAlphaError { supported: supported });
+        assert!(err.to_string().ends_with("(supported modes: Opaque)"));
+    }
+
+    const ALL_TRANSFORMS: [SurfaceTransform; 9] = [
+        SurfaceTransform::Identity,
+        SurfaceTransform::Rotate90,
+        SurfaceTransform::Rotate180,
+        SurfaceTransform::Rotate270,
+        SurfaceTransform::HorizontalMirror,
+        SurfaceTransform::HorizontalMirrorRotate90,
+        SurfaceTransform::HorizontalMirrorRotate180,
+        SurfaceTransform::HorizontalMirrorRotate270,
+        SurfaceTransform::Inherit,
+    ];
+
+    #[test]
+    fn pre_rotated_dimensions() {
+        for &transform in ALL_TRANSFORMS.iter() {
+            let dims = transform.pre_rotated_dimensions([1920, 1080]);
+            if transform.swaps_dimensions() {
+                assert_eq!(dims, [1080, 1920]);
+            } else {
+                assert_eq!(dims, [1920, 1080]);
+            }
+            assert_eq!(transform.pre_rotated_dimensions(dims), [1920, 1080]);
+        }
+    }
+
+    #[test]
+    fn pre_rotation_matrix_rotate90() {
+        let m = SurfaceTransform::Rotate90.pre_rotation_matrix();
+        // The right of the screen ends up at the top of the image.
+        let x = [1.0, 0.0];
+        assert_eq!([m[0][0] * x[0] + m[1][0] * x[1], m[0][1] * x[0] + m[1][1] * x[1]],
+                   [0.0, -1.0]);
+        assert_eq!(SurfaceTransform::Identity.pre_rotation_matrix(),
+                   [[1.0, 0.0, 0.0, 0.0],
+                    [0.0, 1.0, 0.0, 0.0],
+                    [0.0, 0.0, 1.0, 0.0],
+                    [0.0, 0.0, 0.0, 1.0]]);
+    }
+
+    #[test]
+    fn pre_rotation_matrix_matches_scissor() {
+        // The corners of a scissor box, once converted to normalized coordinates and multiplied
+        // by the matrix, must match the pre-rotated scissor box.
+        let dims = [200, 100];
+        let scissor = ::pipeline::viewport::Scissor {
+            origin: [20, 10],
+            dimensions: [60, 30],
+        };
+
+        for &transform in ALL_TRANSFORMS.iter() {
+            let m = transform.pre_rotation_matrix();
+            let image_dims = transform.pre_rotated_dimensions(dims);
+
+            let to_image = |px: i32, py: i32| -> [i32; 2] {
+                let x = px as f32 / dims[0] as f32 * 2.0 - 1.0;
+                let y = py as f32 / dims[1] as f32 * 2.0 - 1.0;
+                let u = m[0][0] * x + m[1][0] * y;
+                let v = m[0][1] * x + m[1][1] * y;
+                [((u + 1.0) * 0.5 * image_dims[0] as f32).round() as i32,
+                 ((v + 1.0) * 0.5 * image_dims[1] as f32).round() as i32]
+            };
+
+            let c1 = to_image(scissor.origin[0], scissor.origin[1]);
+            let c2 = to_image(scissor.origin[0] + scissor.dimensions[0] as i32,
+                              scissor.origin[1] + scissor.dimensions[1] as i32);
+
+            let rotated = transform.pre_rotate_scissor(&scissor, dims);
+            assert_eq!(rotated.origin, [::std::cmp::min(c1[0], c2[0]),
+                                        ::std::cmp::min(c1[1], c2[1])]);
+            assert_eq!(rotated.dimensions, [(c1[0] - c2[0]).abs() as u32,
+                                            (c1[1] - c2[1]).abs() as u32]);
+        }
+    }
+
+    #[test]
+    fn shared_present_modes_from_list() {
+        let modes = supported_present_modes_from_list(vec![
+            vk::PRESENT_MODE_FIFO_KHR,
+            vk::PRESENT_MODE_SHARED_DEMAND_REFRESH_KHR,
+            vk::PRESENT_MODE_SHARED_CONTINUOUS_REFRESH_KHR,
+        ].into_iter());
+
+        assert!(modes.fifo && !modes.mailbox);
+        assert!(modes.supports(PresentMode::SharedDemandRefresh));
+        assert!(modes.supports(PresentMode::SharedContinuousRefresh));
+        assert_eq!(modes.iter().collect::<Vec<_>>(),
+                   vec![PresentMode::Fifo,
+                        PresentMode::SharedDemandRefresh,
+                        PresentMode::SharedContinuousRefresh]);
+
+        let modes = supported_present_modes_from_list(vec![vk::PRESENT_MODE_FIFO_KHR].into_iter());
+        assert!(!modes.supports(PresentMode::SharedDemandRefresh));
+        assert!(!modes.supports(PresentMode::SharedContinuousRefresh));
+    }
+
+    #[test]
+    fn shared_present_modes_are_shared() {
+        assert!(PresentMode::SharedDemandRefresh.is_shared());
+        assert!(PresentMode::SharedContinuousRefresh.is_shared());
+        assert!(!PresentMode::Immediate.is_shared());
+        assert!(!PresentMode::Mailbox.is_shared());
+        assert!(!PresentMode::Fifo.is_shared());
+        assert!(!PresentMode::Relaxed.is_shared());
+    }
+}
@@ -67,7 +67,8 @@ impl DisplayPlane {
             let mut num: u32 = 0;
             check_errors(vk.GetPhysicalDeviceDisplayPlanePropertiesKHR(device.internal_object(),
                                                                        &mut num,
-                                                                       ptr::null_mut()))?;
+                                                                       ptr::null_mut()),
+                         "vkGetPhysicalDeviceDisplayPlanePropertiesKHR")?;
             num
         };
 
@@ -76,7 +77,8 @@ impl DisplayPlane {
             let mut num = num;
             check_errors(vk.GetPhysicalDeviceDisplayPlanePropertiesKHR(device.internal_object(),
                                                                        &mut num,
-                                                                       planes.as_mut_ptr()))?;
+                                                                       planes.as_mut_ptr()),
+                         "vkGetPhysicalDeviceDisplayPlanePropertiesKHR")?;
             planes.set_len(num as usize);
             planes
         };
@@ -85,7 +87,8 @@ impl DisplayPlane {
             let num = unsafe {
                 let mut num: u32 = 0;
                 check_errors(vk.GetDisplayPlaneSupportedDisplaysKHR(device.internal_object(), index as u32,
-                                                                    &mut num, ptr::null_mut())).unwrap();       // TODO: shouldn't unwrap
+                                                                    &mut num, ptr::null_mut()),
+                             "vkGetDisplayPlaneSupportedDisplaysKHR").unwrap();       // TODO: shouldn't unwrap
                 num
             };
 
@@ -94,7 +97,8 @@ impl DisplayPlane {
                 let mut num = num;
                 check_errors(vk.GetDisplayPlaneSupportedDisplaysKHR(device.internal_object(),
                                                                     index as u32, &mut num,
-                                                                    displays.as_mut_ptr())).unwrap();       // TODO: shouldn't unwrap
+                                                                    displays.as_mut_ptr()),
+                             "vkGetDisplayPlaneSupportedDisplaysKHR").unwrap();       // TODO: shouldn't unwrap
                 displays.set_len(num as usize);
                 displays
             };
@@ -167,7 +171,8 @@ impl Display {
             let mut num = 0;
             check_errors(vk.GetPhysicalDeviceDisplayPropertiesKHR(device.internal_object(),
                                                                   &mut num,
-                                                                  ptr::null_mut()))?;
+                                                                  ptr::null_mut()),
+                         "vkGetPhysicalDeviceDisplayPropertiesKHR")?;
             num
         };
 
@@ -176,7 +181,8 @@ impl Display {
             let mut num = num;
             check_errors(vk.GetPhysicalDeviceDisplayPropertiesKHR(device.internal_object(),
                                                                   &mut num,
-                                                                  displays.as_mut_ptr()))?;
+                                                                  displays.as_mut_ptr()),
+                         "vkGetPhysicalDeviceDisplayPropertiesKHR")?;
             displays.set_len(num as usize);
             displays
         };
@@ -266,7 +272,8 @@ impl Display {
             check_errors(vk.GetDisplayModePropertiesKHR(self.physical_device().internal_object(),
                                                         self.properties.display,
                                                         &mut num,
-                                                        ptr::null_mut()))?;
+                                                        ptr::null_mut()),
+                         "vkGetDisplayModePropertiesKHR")?;
             num
         };
 
@@ -276,7 +283,8 @@ impl Display {
             check_errors(vk.GetDisplayModePropertiesKHR(self.physical_device().internal_object(),
                                                         self.properties.display,
                                                         &mut num,
-                                                        modes.as_mut_ptr()))?;
+                                                        modes.as_mut_ptr()),
+                         "vkGetDisplayModePropertiesKHR")?;
             modes.set_len(num as usize);
             modes
         };
@@ -344,7 +352,8 @@ impl DisplayMode {
             let mut output = mem::uninitialized();
             try!(check_errors(vk.CreateDisplayModeKHR(display.device.internal_object(),
                                                       display.display, &infos, ptr::null(),
-                                                      &mut output)));
+                                                      &mut output),
+                              "vkCreateDisplayModeKHR"));
             output
         };
 
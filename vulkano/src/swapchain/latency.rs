@@ -0,0 +1,333 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Bookkeeping of the presents that the presentation engine hasn't finished processing.
+//!
+//! With the `ext_swapchain_maintenance1` extension, each present is given a fence that the
+//! presentation engine signals once it's done with the present. Without it, the only thing that
+//! Vulkan tells us is when an image is handed back to the application. A present is then
+//! considered finished when the fence passed to the next acquisition of the same image is
+//! signaled.
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use std::time::Instant;
+
+use sync::Fence;
+use sync::FenceWaitError;
+
+/// Object that gets signaled when the presentation engine is done with a present.
+pub trait LatencyFence {
+    /// Returns true if the fence is signaled.
+    fn ready(&self) -> Result<bool, FenceWaitError>;
+
+    /// Waits until the fence is signaled, or until the timeout has elapsed.
+    fn wait(&self, timeout: Option<Duration>) -> Result<(), FenceWaitError>;
+}
+
+impl LatencyFence for Fence {
+    #[inline]
+    fn ready(&self) -> Result<bool, FenceWaitError> {
+        Fence::ready(self).map_err(FenceWaitError::OomError)
+    }
+
+    #[inline]
+    fn wait(&self, timeout: Option<Duration>) -> Result<(), FenceWaitError> {
+        Fence::wait(self, timeout)
+    }
+}
+
+/// Keeps track of the presents of a swapchain that are still in flight.
+pub struct FrameLatency<F> {
+    // Nothing is tracked until the user has asked for it, so that acquiring doesn't need to
+    // create fences for nothing.
+    enabled: AtomicBool,
+    state: Mutex<State<F>>,
+}
+
+struct State<F> {
+    // The presents that may still be processed by the presentation engine, oldest first.
+    presents: VecDeque<InFlightPresent<F>>,
+    // Fences of acquisitions that don't correspond to any tracked present, for example the first
+    // acquisition of each image. They are kept alive until they are signaled, as it is forbidden
+    // to destroy a fence that is still in use.
+    orphans: Vec<Arc<F>>,
+}
+
+struct InFlightPresent<F> {
+    image_id: usize,
+    // `None` if we're waiting for the image to be acquired again in order to get a fence.
+    fence: Option<Arc<F>>,
+}
+
+impl<F> State<F>
+    where F: LatencyFence
+{
+    // Removes the presents whose fence has been signaled.
+    fn retire(&mut self) -> Result<(), FenceWaitError> {
+        let mut n = 0;
+        while n < self.presents.len() {
+            let done = match self.presents[n].fence {
+                Some(ref fence) => fence.ready()?,
+                None => false,
+            };
+
+            if done {
+                self.presents.remove(n);
+            } else {
+                n += 1;
+            }
+        }
+
+        self.orphans.retain(|fence| !fence.ready().unwrap_or(false));
+        Ok(())
+    }
+}
+
+impl<F> FrameLatency<F>
+    where F: LatencyFence
+{
+    /// Builds a new `FrameLatency` that doesn't track anything yet.
+    #[inline]
+    pub fn new() -> FrameLatency<F> {
+        FrameLatency {
+            enabled: AtomicBool::new(false),
+            state: Mutex::new(State {
+                                  presents: VecDeque::new(),
+                                  orphans: Vec::new(),
+                              }),
+        }
+    }
+
+    /// Starts tracking the presents and acquisitions that are reported from now on.
+    #[inline]
+    pub fn enable(&self) {
+        self.enabled.store(true, Ordering::SeqCst);
+    }
+
+    /// Returns true if `enable` has been called.
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::SeqCst)
+    }
+
+    /// Reports that an image has been presented. `fence` is the present fence, if any.
+    pub fn presented(&self, image_id: usize, fence: Option<Arc<F>>) {
+        let mut state = self.state.lock().unwrap();
+        state.presents.push_back(InFlightPresent {
+                                     image_id: image_id,
+                                     fence: fence,
+                                 });
+    }
+
+    /// Reports that an image has been acquired, and that `fence` will be signaled once the
+    /// presentation engine has handed it back.
+    pub fn acquired(&self, image_id: usize, fence: Arc<F>) {
+        let mut state = self.state.lock().unwrap();
+
+        let present = state
+            .presents
+            .iter_mut()
+            .find(|p| p.image_id == image_id && p.fence.is_none());
+
+        match present {
+            Some(present) => present.fence = Some(fence),
+            None => state.orphans.push(fence),
+        }
+    }
+
+    /// Reports that the swapchain is out of date. Images that haven't been acquired again will
+    /// never be, so we stop waiting for them.
+    pub fn out_of_date(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.presents.retain(|p| p.fence.is_some());
+    }
+
+    /// Returns the number of presents that are still in flight.
+    pub fn in_flight(&self) -> Result<usize, FenceWaitError> {
+        let mut state = self.state.lock().unwrap();
+        state.retire()?;
+        Ok(state.presents.len())
+    }
+
+    /// Blocks until fewer than `max_in_flight` presents are in flight.
+    ///
+    /// Returns early if the only presents left are waiting for their image to be acquired again,
+    /// as nothing but an acquisition can make them progress.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `max_in_flight` is 0.
+    ///
+    pub fn wait(&self, max_in_flight: usize, timeout: Option<Duration>)
+                -> Result<(), FenceWaitError> {
+        assert!(max_in_flight >= 1, "the maximum number of frames in flight must be at least 1");
+
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+
+        loop {
+            // The lock isn't held while waiting, so that other threads can keep presenting.
+            let fence = {
+                let mut state = self.state.lock().unwrap();
+                state.retire()?;
+
+                if state.presents.len() < max_in_flight {
+                    return Ok(());
+                }
+
+                match state.presents.iter().filter_map(|p| p.fence.clone()).next() {
+                    Some(fence) => fence,
+                    None => return Ok(()),
+                }
+            };
+
+            let remaining = deadline.map(|deadline| {
+                let now = Instant::now();
+                if now >= deadline {
+                    Duration::new(0, 0)
+                } else {
+                    deadline - now
+                }
+            });
+
+            fence.wait(remaining)?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+    use std::time::Instant;
+    use swapchain::latency::FrameLatency;
+    use swapchain::latency::LatencyFence;
+    use sync::FenceWaitError;
+
+    // Fence that gets signaled on its own at a given moment, in place of the presentation engine.
+    struct TimedFence {
+        signaled_at: Instant,
+    }
+
+    impl TimedFence {
+        fn after(ms: u64) -> Arc<TimedFence> {
+            Arc::new(TimedFence { signaled_at: Instant::now() + Duration::from_millis(ms) })
+        }
+    }
+
+    impl LatencyFence for TimedFence {
+        fn ready(&self) -> Result<bool, FenceWaitError> {
+            Ok(Instant::now() >= self.signaled_at)
+        }
+
+        fn wait(&self, timeout: Option<Duration>) -> Result<(), FenceWaitError> {
+            let now = Instant::now();
+            if now >= self.signaled_at {
+                return Ok(());
+            }
+
+            let left = self.signaled_at - now;
+            match timeout {
+                Some(timeout) if timeout < left => {
+                    thread::sleep(timeout);
+                    Err(FenceWaitError::Timeout)
+                },
+                _ => {
+                    thread::sleep(left);
+                    Ok(())
+                },
+            }
+        }
+    }
+
+    #[test]
+    fn counts_presents() {
+        let latency = FrameLatency::new();
+        latency.presented(0, Some(TimedFence::after(0)));
+        latency.presented(1, Some(TimedFence::after(10_000)));
+        latency.presented(2, None);
+        assert_eq!(latency.in_flight().unwrap(), 2);
+    }
+
+    #[test]
+    fn acquire_fence_finishes_present() {
+        let latency = FrameLatency::new();
+        latency.presented(0, None);
+        latency.presented(1, None);
+
+        // Image 2 was never presented, so this doesn't change anything.
+        latency.acquired(2, TimedFence::after(0));
+        assert_eq!(latency.in_flight().unwrap(), 2);
+
+        latency.acquired(1, TimedFence::after(0));
+        assert_eq!(latency.in_flight().unwrap(), 1);
+
+        latency.acquired(0, TimedFence::after(10_000));
+        assert_eq!(latency.in_flight().unwrap(), 1);
+    }
+
+    #[test]
+    fn wait_blocks_until_below_cap() {
+        let latency = FrameLatency::new();
+        let start = Instant::now();
+        latency.presented(0, Some(TimedFence::after(50)));
+        latency.presented(1, Some(TimedFence::after(100)));
+        latency.presented(2, Some(TimedFence::after(10_000)));
+
+        latency.wait(2, Some(Duration::from_secs(5))).unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(100));
+        assert_eq!(latency.in_flight().unwrap(), 1);
+
+        // Already below the cap.
+        latency.wait(3, Some(Duration::new(0, 0))).unwrap();
+    }
+
+    #[test]
+    fn wait_timeout() {
+        let latency = FrameLatency::new();
+        latency.presented(0, Some(TimedFence::after(10_000)));
+
+        match latency.wait(1, Some(Duration::from_millis(20))) {
+            Err(FenceWaitError::Timeout) => (),
+            r => panic!("{:?}", r),
+        }
+        assert_eq!(latency.in_flight().unwrap(), 1);
+    }
+
+    #[test]
+    fn wait_returns_when_only_unacquired_presents_are_left() {
+        let latency = FrameLatency::new();
+        latency.presented(0, None);
+        latency.presented(1, Some(TimedFence::after(20)));
+
+        latency.wait(1, Some(Duration::from_secs(5))).unwrap();
+        assert_eq!(latency.in_flight().unwrap(), 1);
+    }
+
+    #[test]
+    fn out_of_date_forgets_unacquired_presents() {
+        let latency = FrameLatency::new();
+        latency.presented(0, None);
+        latency.presented(1, Some(TimedFence::after(10_000)));
+        latency.out_of_date();
+        assert_eq!(latency.in_flight().unwrap(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "the maximum number of frames in flight must be at least 1")]
+    fn zero_cap() {
+        let latency = FrameLatency::<TimedFence>::new();
+        let _ = latency.wait(0, None);
+    }
+}
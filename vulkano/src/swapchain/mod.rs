@@ -141,6 +141,10 @@
 //!     swapchain::present(queue, index).unwrap();
 //! }
 //!
+//! If you render to multiple windows with the same device, `swapchain::present_multiple` presents
+//! images of multiple swapchains with a single present command. See the documentation of
+//! `MultiPresentFuture`.
+//!
 //! ## Recreating a swapchain
 //!
 //! In some situations, the swapchain will become invalid by itself. This includes for example when
@@ -152,6 +156,15 @@
 //! rendering, you will need to *recreate* the swapchain by creating a new swapchain and passing
 //! as last parameter the old swapchain.
 //!
+//! The same mechanism is used to change the parameters of a swapchain that is still valid. For
+//! example `recreate_with_present_mode` switches between `Fifo` and `Immediate` in order to
+//! turn vsync on or off from a settings menu.
+//!
+//! On some platforms, and notably on Windows, the surface of a minimized window has a width and
+//! a height of zero. No swapchain can be created in that situation: `Swapchain::new` returns
+//! `SwapchainCreationError::ZeroExtent`, and `recreate_for_surface` returns `Ok(None)`. Skip
+//! rendering until the window is restored.
+//!
 //! TODO: suboptimal stuff
 //!
 //! ```
@@ -204,7 +217,12 @@ pub use self::capabilities::SupportedPresentModesIter;
 pub use self::capabilities::SupportedSurfaceTransforms;
 pub use self::capabilities::SupportedSurfaceTransformsIter;
 pub use self::capabilities::SurfaceTransform;
+pub use self::capabilities::UnsupportedCompositeAlphaError;
+pub use self::multi_present::MultiPresentFuture;
+pub use self::multi_present::present_multiple;
 pub use self::surface::CapabilitiesError;
+pub use self::surface::GraphicsPresentFamilies;
+pub use self::surface::GraphicsPresentFamiliesError;
 pub use self::surface::Surface;
 pub use self::surface::SurfaceCreationError;
 pub use self::swapchain::AcquireError;
@@ -217,6 +235,8 @@ pub use self::swapchain::present;
 
 mod capabilities;
 pub mod display;
+mod latency;
+mod multi_present;
 mod surface;
 mod swapchain;
 
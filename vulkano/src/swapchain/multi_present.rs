@@ -0,0 +1,417 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Presenting images of multiple swapchains with a single present command.
+//!
+//! When an application renders to multiple windows with the same device, it can present all of
+//! them with one call to `vkQueuePresentKHR` instead of one call per window. This is more
+//! efficient, and the presentation engine may also display the images at the same time.
+//!
+//! Each swapchain keeps its own bookkeeping, so the windows can still be paced independently
+//! with `Swapchain::wait_for_latency`, and a window that is skipped during a frame doesn't need
+//! to be part of the present command.
+//!
+//! # Example
+//!
+//! ```ignore
+//! let (index_a, acquire_a) = swapchain::acquire_next_image(swapchain_a.clone(), None)?;
+//! let (index_b, acquire_b) = swapchain::acquire_next_image(swapchain_b.clone(), None)?;
+//!
+//! let future = acquire_a.join(acquire_b)
+//!     .then_execute(queue.clone(), command_buffer)?;
+//!
+//! let present = swapchain::present_multiple(future, queue.clone(),
+//!                                           vec![(swapchain_a.clone(), index_a),
+//!                                                (swapchain_b.clone(), index_b)]);
+//! present.flush()?;
+//!
+//! for result in present.results().unwrap() {
+//!     if result == Err(SubmitPresentError::OutOfDate) {
+//!         // Recreate the corresponding swapchain.
+//!     }
+//! }
+//! ```
+
+use smallvec::SmallVec;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+
+use buffer::BufferAccess;
+use command_buffer::pool::standard::StandardCommandPoolAlloc;
+use command_buffer::submit::SubmitAnyBuilder;
+use command_buffer::submit::SubmitCommandBufferBuilder;
+use command_buffer::submit::SubmitPresentBuilder;
+use command_buffer::submit::SubmitPresentError;
+use command_buffer::sys::UnsafeCommandBuffer;
+use device::Device;
+use device::DeviceOwned;
+use device::Queue;
+use image::ImageAccess;
+use image::ImageLayout;
+use swapchain::Swapchain;
+use swapchain::swapchain::build_present_transition;
+use swapchain::swapchain::check_present_support;
+use sync::AccessCheckError;
+use sync::AccessFlagBits;
+use sync::Fence;
+use sync::FlushError;
+use sync::GpuFuture;
+use sync::PipelineStages;
+use sync::Semaphore;
+
+use VulkanObject;
+
+/// Presents images of multiple swapchains with a single present command, after the `before`
+/// future.
+///
+/// `images` is a list of pairs of a swapchain and the index of the image of that swapchain to
+/// present. Each image must have been acquired. Contrary to `present`, an error concerning only
+/// some of the swapchains (such as `OutOfDate`) doesn't make the flush fail. Instead, the result
+/// of each swapchain can be obtained with `MultiPresentFuture::results`.
+///
+/// # Panic
+///
+/// - Panics if `images` is empty.
+/// - Panics if an index is out of range, or if the same swapchain appears twice.
+///
+pub fn present_multiple<F, I>(before: F, queue: Arc<Queue>, images: I) -> MultiPresentFuture<F>
+    where F: GpuFuture,
+          I: IntoIterator<Item = (Arc<Swapchain>, usize)>
+{
+    let mut targets: SmallVec<[PresentTarget; 4]> = SmallVec::new();
+
+    for (swapchain, image_id) in images {
+        assert!(image_id < swapchain.num_images() as usize);
+        assert!(!targets.iter().any(|t| {
+                                        t.swapchain.internal_object() ==
+                                            swapchain.internal_object()
+                                    }),
+                "the same swapchain can't be presented twice in one present command");
+
+        let present_fence = swapchain.new_present_fence(&queue);
+        targets.push(PresentTarget {
+                         swapchain: swapchain,
+                         image_id: image_id,
+                         present_fence: present_fence,
+                     });
+    }
+
+    assert!(!targets.is_empty(), "at least one swapchain image must be presented");
+
+    let mut present_support = Ok(());
+    for target in targets.iter() {
+        if let Err(err) = check_present_support(&queue, &target.swapchain) {
+            present_support = Err(err);
+            break;
+        }
+    }
+
+    let semaphore = Semaphore::new(queue.device().clone()).unwrap();
+
+    MultiPresentFuture {
+        previous: before,
+        queue: queue,
+        present_support: present_support,
+        targets: targets,
+        semaphore: semaphore,
+        transitions: Mutex::new(Vec::new()),
+        results: Mutex::new(Vec::new()),
+        flushed: AtomicBool::new(false),
+        finished: AtomicBool::new(false),
+    }
+}
+
+struct PresentTarget {
+    swapchain: Arc<Swapchain>,
+    image_id: usize,
+    // Fence signaled when the presentation engine is done with the present, if the latency of
+    // the swapchain is tracked.
+    present_fence: Option<Arc<Fence>>,
+}
+
+/// Represents images of multiple swapchains being presented on the screen.
+#[must_use = "Dropping this object will immediately block the thread until the GPU has finished processing the submission"]
+pub struct MultiPresentFuture<P>
+    where P: GpuFuture
+{
+    previous: P,
+    queue: Arc<Queue>,
+    // Whether the family of `queue` can present on the surfaces of all the targets. Checked once
+    // when the future is created.
+    present_support: Result<(), FlushError>,
+    targets: SmallVec<[PresentTarget; 4]>,
+    // Semaphore signaled by the submission of `previous` and waited upon by the present command.
+    semaphore: Semaphore,
+    // Command buffers that transition the images to the layout required for presenting.
+    // Kept alive until the future is destroyed.
+    transitions: Mutex<Vec<UnsafeCommandBuffer<StandardCommandPoolAlloc>>>,
+    // Result of each swapchain, written when the present command is submitted.
+    results: Mutex<Vec<Result<(), SubmitPresentError>>>,
+    // True if `flush()` has been called on the future, which means that the present command has
+    // been submitted.
+    flushed: AtomicBool,
+    // True if `signal_finished()` has been called on the future, which means that the future has
+    // been submitted and has already been processed by the GPU.
+    finished: AtomicBool,
+}
+
+impl<P> MultiPresentFuture<P>
+    where P: GpuFuture
+{
+    /// Returns the swapchains and image indices that are presented, in the order in which they
+    /// were passed to `present_multiple`.
+    #[inline]
+    pub fn images(&self) -> Vec<(&Arc<Swapchain>, usize)> {
+        self.targets
+            .iter()
+            .map(|t| (&t.swapchain, t.image_id))
+            .collect()
+    }
+
+    /// Returns the result of the present of each swapchain, in the same order as `images()`.
+    ///
+    /// Returns `None` if the present command hasn't been submitted yet.
+    pub fn results(&self) -> Option<Vec<Result<(), SubmitPresentError>>> {
+        let results = self.results.lock().unwrap();
+        if results.is_empty() {
+            None
+        } else {
+            Some(results.clone())
+        }
+    }
+
+    // Submits `builder` so that it signals the semaphore, and returns a present builder that
+    // waits on that semaphore.
+    unsafe fn submit_with_semaphore<'a, 'b>(&'a self, mut builder: SubmitCommandBufferBuilder<'b>,
+                                            queue: &Queue)
+                                            -> Result<SubmitPresentBuilder<'a>, FlushError>
+        where 'a: 'b
+    {
+        builder.add_signal_semaphore(&self.semaphore);
+        builder.submit(queue)?;
+
+        let mut present = SubmitPresentBuilder::new();
+        present.add_wait_semaphore(&self.semaphore);
+        Ok(present)
+    }
+}
+
+unsafe impl<P> GpuFuture for MultiPresentFuture<P>
+    where P: GpuFuture
+{
+    #[inline]
+    fn cleanup_finished(&mut self) {
+        self.previous.cleanup_finished();
+    }
+
+    unsafe fn build_submission(&self) -> Result<SubmitAnyBuilder, FlushError> {
+        if self.flushed.load(Ordering::SeqCst) {
+            return Ok(SubmitAnyBuilder::Empty);
+        }
+
+        let queue = self.previous.queue().map(|q| q.clone()).unwrap_or(self.queue.clone());
+
+        // All the targets are validated before anything is submitted, so that an error concerning
+        // one of them doesn't leave the others half-presented. The images are only released once
+        // the present command has been successfully submitted.
+        self.present_support.clone()?;
+        for target in self.targets.iter() {
+            target.swapchain.check_acquired_for_present(target.image_id)?;
+        }
+
+        let mut transitions = Vec::new();
+        for target in self.targets.iter() {
+            if let Some(transition) =
+                build_present_transition(&self.previous, &queue, &target.swapchain,
+                                         target.image_id)?
+            {
+                transitions.push(transition);
+            }
+        }
+
+        // Same as `PresentFuture`, except that there can be multiple transitions.
+        let mut builder = match self.previous.build_submission()? {
+            SubmitAnyBuilder::Empty => {
+                if transitions.is_empty() {
+                    SubmitPresentBuilder::new()
+                } else {
+                    let mut cb = SubmitCommandBufferBuilder::new();
+                    for transition in transitions.iter() {
+                        cb.add_command_buffer(transition);
+                    }
+                    self.submit_with_semaphore(cb, &queue)?
+                }
+            },
+            SubmitAnyBuilder::SemaphoresWait(sem) => {
+                if transitions.is_empty() {
+                    sem.into()
+                } else {
+                    let mut cb: SubmitCommandBufferBuilder = sem.into();
+                    for transition in transitions.iter() {
+                        cb.add_command_buffer(transition);
+                    }
+                    self.submit_with_semaphore(cb, &queue)?
+                }
+            },
+            SubmitAnyBuilder::CommandBuffer(mut cb) => {
+                for transition in transitions.iter() {
+                    cb.add_command_buffer(transition);
+                }
+                self.submit_with_semaphore(cb, &queue)?
+            },
+            SubmitAnyBuilder::BindSparse(cb) => {
+                cb.submit(&queue)?;
+                if transitions.is_empty() {
+                    SubmitPresentBuilder::new()
+                } else {
+                    let mut cb = SubmitCommandBufferBuilder::new();
+                    for transition in transitions.iter() {
+                        cb.add_command_buffer(transition);
+                    }
+                    self.submit_with_semaphore(cb, &queue)?
+                }
+            },
+            SubmitAnyBuilder::QueuePresent(present) => {
+                // The previous present is submitted on its own. Our transitions don't touch the
+                // images that it presents, so they don't need to wait for it.
+                present.submit(&queue)?;
+                if transitions.is_empty() {
+                    SubmitPresentBuilder::new()
+                } else {
+                    let mut cb = SubmitCommandBufferBuilder::new();
+                    for transition in transitions.iter() {
+                        cb.add_command_buffer(transition);
+                    }
+                    self.submit_with_semaphore(cb, &queue)?
+                }
+            },
+        };
+
+        for target in self.targets.iter() {
+            builder.add_swapchain(&target.swapchain, target.image_id as u32);
+            if let Some(ref fence) = target.present_fence {
+                builder.set_tracked_present_fence(fence);
+            }
+        }
+        builder.set_results(&self.results);
+
+        self.transitions.lock().unwrap().extend(transitions);
+
+        Ok(SubmitAnyBuilder::QueuePresent(builder))
+    }
+
+    #[inline]
+    fn flush(&self) -> Result<(), FlushError> {
+        unsafe {
+            // If `flushed` already contains `true`, then `build_submission` will return `Empty`.
+
+            match self.build_submission()? {
+                SubmitAnyBuilder::Empty => {},
+                SubmitAnyBuilder::QueuePresent(present) => {
+                    present.submit(&self.queue)?;
+                },
+                _ => unreachable!(),
+            }
+
+            let results = self.results.lock().unwrap();
+            for (target, result) in self.targets.iter().zip(results.iter()) {
+                if let Err(SubmitPresentError::OutOfDate) = *result {
+                    target.swapchain.mark_out_of_date();
+                }
+            }
+
+            self.flushed.store(true, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[inline]
+    unsafe fn signal_finished(&self) {
+        self.flushed.store(true, Ordering::SeqCst);
+        self.finished.store(true, Ordering::SeqCst);
+        self.previous.signal_finished();
+    }
+
+    #[inline]
+    fn queue_change_allowed(&self) -> bool {
+        false
+    }
+
+    #[inline]
+    fn queue(&self) -> Option<Arc<Queue>> {
+        debug_assert!(match self.previous.queue() {
+                          None => true,
+                          Some(q) => q.is_same(&self.queue),
+                      });
+
+        Some(self.queue.clone())
+    }
+
+    #[inline]
+    fn check_buffer_access(
+        &self, buffer: &BufferAccess, exclusive: bool, queue: &Queue)
+        -> Result<Option<(PipelineStages, AccessFlagBits)>, AccessCheckError> {
+        self.previous.check_buffer_access(buffer, exclusive, queue)
+    }
+
+    #[inline]
+    fn check_image_access(&self, image: &ImageAccess, layout: ImageLayout, exclusive: bool,
+                          queue: &Queue)
+                          -> Result<Option<(PipelineStages, AccessFlagBits)>, AccessCheckError> {
+        let presented = self.targets.iter().any(|target| {
+            let swapchain_image = target.swapchain.raw_image(target.image_id).unwrap();
+            swapchain_image.image.internal_object() == image.inner().image.internal_object()
+        });
+
+        if presented {
+            // See the equivalent in `PresentFuture`. Presenting unlocks the image.
+            Err(AccessCheckError::Unknown)
+        } else {
+            self.previous
+                .check_image_access(image, layout, exclusive, queue)
+        }
+    }
+}
+
+unsafe impl<P> DeviceOwned for MultiPresentFuture<P>
+    where P: GpuFuture
+{
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        self.queue.device()
+    }
+}
+
+impl<P> Drop for MultiPresentFuture<P>
+    where P: GpuFuture
+{
+    fn drop(&mut self) {
+        unsafe {
+            if !*self.finished.get_mut() {
+                match self.flush() {
+                    Ok(()) => (),
+                    // These errors are returned before anything is submitted, so `previous` is
+                    // flushed on its own.
+                    Err(FlushError::SwapchainImageNotAcquired) |
+                    Err(FlushError::PresentNotSupported { .. }) => {
+                        let _ = self.previous.flush();
+                    },
+                    // The commands of `previous` may have been submitted even though the present
+                    // failed. They are waited upon below in any case.
+                    Err(_) => (),
+                }
+                // Block until the queue finished.
+                let _ = self.queue().unwrap().wait();
+                self.previous.signal_finished();
+            }
+        }
+    }
+}
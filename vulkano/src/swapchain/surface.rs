@@ -25,6 +25,7 @@ use swapchain::SurfaceSwapchainLock;
 use swapchain::capabilities;
 use swapchain::display::DisplayMode;
 use swapchain::display::DisplayPlane;
+use sync::SharingMode;
 
 use Error;
 use OomError;
@@ -93,7 +94,8 @@ impl Surface {
             check_errors(vk.CreateDisplayPlaneSurfaceKHR(instance.internal_object(),
                                                          &infos,
                                                          ptr::null(),
-                                                         &mut output))?;
+                                                         &mut output),
+                         "vkCreateDisplayPlaneSurfaceKHR")?;
             output
         };
 
@@ -133,7 +135,8 @@ impl Surface {
             check_errors(vk.CreateWin32SurfaceKHR(instance.internal_object(),
                                                   &infos,
                                                   ptr::null(),
-                                                  &mut output))?;
+                                                  &mut output),
+                         "vkCreateWin32SurfaceKHR")?;
             output
         };
 
@@ -173,7 +176,8 @@ impl Surface {
             check_errors(vk.CreateXcbSurfaceKHR(instance.internal_object(),
                                                 &infos,
                                                 ptr::null(),
-                                                &mut output))?;
+                                                &mut output),
+                         "vkCreateXcbSurfaceKHR")?;
             output
         };
 
@@ -213,7 +217,8 @@ impl Surface {
             check_errors(vk.CreateXlibSurfaceKHR(instance.internal_object(),
                                                  &infos,
                                                  ptr::null(),
-                                                 &mut output))?;
+                                                 &mut output),
+                         "vkCreateXlibSurfaceKHR")?;
             output
         };
 
@@ -254,7 +259,8 @@ impl Surface {
             check_errors(vk.CreateWaylandSurfaceKHR(instance.internal_object(),
                                                     &infos,
                                                     ptr::null(),
-                                                    &mut output))?;
+                                                    &mut output),
+                         "vkCreateWaylandSurfaceKHR")?;
             output
         };
 
@@ -295,7 +301,8 @@ impl Surface {
             check_errors(vk.CreateMirSurfaceKHR(instance.internal_object(),
                                                 &infos,
                                                 ptr::null(),
-                                                &mut output))?;
+                                                &mut output),
+                         "vkCreateMirSurfaceKHR")?;
             output
         };
 
@@ -332,7 +339,8 @@ impl Surface {
             check_errors(vk.CreateAndroidSurfaceKHR(instance.internal_object(),
                                                     &infos,
                                                     ptr::null(),
-                                                    &mut output))?;
+                                                    &mut output),
+                         "vkCreateAndroidSurfaceKHR")?;
             output
         };
 
@@ -370,7 +378,8 @@ impl Surface {
             check_errors(vk.CreateIOSSurfaceMVK(instance.internal_object(),
                                                 &infos,
                                                 ptr::null(),
-                                                &mut output))?;
+                                                &mut output),
+                         "vkCreateIOSSurfaceMVK")?;
             output
         };
 
@@ -408,7 +417,8 @@ impl Surface {
             check_errors(vk.CreateMacOSSurfaceMVK(instance.internal_object(),
                                                   &infos,
                                                   ptr::null(),
-                                                  &mut output))?;
+                                                  &mut output),
+                         "vkCreateMacOSSurfaceMVK")?;
             output
         };
 
@@ -445,7 +455,8 @@ impl Surface {
             check_errors(vk.CreateViSurfaceNN(instance.internal_object(),
                                               &infos,
                                               ptr::null(),
-                                              &mut output))?;
+                                              &mut output),
+                         "vkCreateViSurfaceNN")?;
             output
         };
 
@@ -457,7 +468,6 @@ impl Surface {
     }
 
     /// Returns true if the given queue family can draw on this surface.
-    // FIXME: vulkano doesn't check this for the moment!
     pub fn is_supported(&self, queue: QueueFamily) -> Result<bool, CapabilitiesError> {
         unsafe {
             let vk = self.instance.pointers();
@@ -468,11 +478,62 @@ impl Surface {
                                                                    .internal_object(),
                                                                queue.id(),
                                                                self.surface,
-                                                               &mut output))?;
+                                                               &mut output),
+                         "vkGetPhysicalDeviceSurfaceSupportKHR")?;
             Ok(output != 0)
         }
     }
 
+    /// Finds the queue families of a physical device to use in order to draw on this surface
+    /// and present the result.
+    ///
+    /// A family that supports both graphics operations and presenting on the surface is chosen
+    /// if there is one. Otherwise separate families are returned, and you should use
+    /// `GraphicsPresentFamilies::swapchain_sharing` when creating the swapchain so that its
+    /// images can be used by both.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the device and the surface don't belong to the same instance.
+    ///
+    pub fn graphics_present_families<'a>(&self, device: PhysicalDevice<'a>)
+                                         -> Result<GraphicsPresentFamilies<'a>,
+                                                   GraphicsPresentFamiliesError> {
+        assert_eq!(&*self.instance as *const _, &**device.instance() as *const _);
+
+        let mut graphics = None;
+        let mut present = None;
+
+        for family in device.queue_families() {
+            let supports_present = self.is_supported(family)?;
+
+            if family.supports_graphics() && supports_present {
+                return Ok(GraphicsPresentFamilies {
+                              graphics: family,
+                              present: family,
+                          });
+            }
+
+            if family.supports_graphics() && graphics.is_none() {
+                graphics = Some(family);
+            }
+            if supports_present && present.is_none() {
+                present = Some(family);
+            }
+        }
+
+        match (graphics, present) {
+            (Some(graphics), Some(present)) => {
+                Ok(GraphicsPresentFamilies {
+                       graphics: graphics,
+                       present: present,
+                   })
+            },
+            (None, _) => Err(GraphicsPresentFamiliesError::NoGraphicsFamily),
+            (_, None) => Err(GraphicsPresentFamiliesError::NoPresentFamily),
+        }
+    }
+
     #[deprecated = "Renamed to `capabilities`"]
     #[inline]
     pub fn get_capabilities(&self, device: &PhysicalDevice) -> Result<Capabilities, OomError> {
@@ -497,7 +558,8 @@ impl Surface {
                 let mut out: vk::SurfaceCapabilitiesKHR = mem::uninitialized();
                 check_errors(vk.GetPhysicalDeviceSurfaceCapabilitiesKHR(device.internal_object(),
                                                                         self.surface,
-                                                                        &mut out))?;
+                                                                        &mut out),
+                             "vkGetPhysicalDeviceSurfaceCapabilitiesKHR")?;
                 out
             };
 
@@ -506,13 +568,15 @@ impl Surface {
                 check_errors(vk.GetPhysicalDeviceSurfaceFormatsKHR(device.internal_object(),
                                                                    self.surface,
                                                                    &mut num,
-                                                                   ptr::null_mut()))?;
+                                                                   ptr::null_mut()),
+                             "vkGetPhysicalDeviceSurfaceFormatsKHR")?;
 
                 let mut formats = Vec::with_capacity(num as usize);
                 check_errors(vk.GetPhysicalDeviceSurfaceFormatsKHR(device.internal_object(),
                                                                    self.surface,
                                                                    &mut num,
-                                                                   formats.as_mut_ptr()))?;
+                                                                   formats.as_mut_ptr()),
+                             "vkGetPhysicalDeviceSurfaceFormatsKHR")?;
                 formats.set_len(num as usize);
                 formats
             };
@@ -522,13 +586,15 @@ impl Surface {
                 check_errors(vk.GetPhysicalDeviceSurfacePresentModesKHR(device.internal_object(),
                                                                         self.surface,
                                                                         &mut num,
-                                                                        ptr::null_mut()))?;
+                                                                        ptr::null_mut()),
+                             "vkGetPhysicalDeviceSurfacePresentModesKHR")?;
 
                 let mut modes = Vec::with_capacity(num as usize);
                 check_errors(vk.GetPhysicalDeviceSurfacePresentModesKHR(device.internal_object(),
                                                                         self.surface,
                                                                         &mut num,
-                                                                        modes.as_mut_ptr()))?;
+                                                                        modes.as_mut_ptr()),
+                             "vkGetPhysicalDeviceSurfacePresentModesKHR")?;
                 modes.set_len(num as usize);
                 debug_assert!(modes
                                   .iter()
@@ -568,6 +634,54 @@ impl Surface {
         }
     }
 
+    /// Returns true if a swapchain created with this surface and a certain device can use
+    /// protected images, and therefore present protected content.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the device and the surface don't belong to the same instance.
+    /// - Panics if the `khr_get_surface_capabilities2` and `khr_surface_protected_capabilities`
+    ///   extensions were not enabled on the instance.
+    ///
+    pub fn supports_protected(&self, device: PhysicalDevice) -> Result<bool, CapabilitiesError> {
+        unsafe {
+            assert_eq!(&*self.instance as *const _,
+                       &**device.instance() as *const _,
+                       "Instance mismatch in Surface::supports_protected");
+            assert!(self.instance.loaded_extensions().khr_get_surface_capabilities2 &&
+                        self.instance.loaded_extensions().khr_surface_protected_capabilities,
+                    "the khr_get_surface_capabilities2 and khr_surface_protected_capabilities \
+                     extensions must be enabled on the instance");
+
+            let vk = self.instance.pointers();
+
+            let info = vk::PhysicalDeviceSurfaceInfo2KHR {
+                sType: vk::STRUCTURE_TYPE_PHYSICAL_DEVICE_SURFACE_INFO_2_KHR,
+                pNext: ptr::null(),
+                surface: self.surface,
+            };
+
+            let mut protected = vk::SurfaceProtectedCapabilitiesKHR {
+                sType: vk::STRUCTURE_TYPE_SURFACE_PROTECTED_CAPABILITIES_KHR,
+                pNext: ptr::null_mut(),
+                supportsProtected: vk::FALSE,
+            };
+
+            let mut caps = vk::SurfaceCapabilities2KHR {
+                sType: vk::STRUCTURE_TYPE_SURFACE_CAPABILITIES_2_KHR,
+                pNext: &mut protected as *mut _ as *mut _,
+                surfaceCapabilities: mem::uninitialized(),
+            };
+
+            check_errors(vk.GetPhysicalDeviceSurfaceCapabilities2KHR(device.internal_object(),
+                                                                     &info,
+                                                                     &mut caps),
+                         "vkGetPhysicalDeviceSurfaceCapabilities2KHR")?;
+
+            Ok(protected.supportsProtected != vk::FALSE)
+        }
+    }
+
     /// Returns the instance this surface was created with.
     #[inline]
     pub fn instance(&self) -> &Arc<Instance> {
@@ -665,6 +779,100 @@ impl From<Error> for SurfaceCreationError {
     }
 }
 
+/// Queue families to use in order to draw on a surface and present the result.
+///
+/// See `Surface::graphics_present_families`.
+#[derive(Debug, Copy, Clone)]
+pub struct GraphicsPresentFamilies<'a> {
+    /// Family of the queue that executes the graphics operations.
+    pub graphics: QueueFamily<'a>,
+    /// Family of the queue that presents on the surface. Can be the same as `graphics`.
+    pub present: QueueFamily<'a>,
+}
+
+impl<'a> GraphicsPresentFamilies<'a> {
+    /// Returns true if the same family is used for the graphics operations and for presenting.
+    #[inline]
+    pub fn is_single_family(&self) -> bool {
+        self.graphics.id() == self.present.id()
+    }
+
+    /// Returns the list of distinct families, to pass when creating the device.
+    #[inline]
+    pub fn families(&self) -> Vec<QueueFamily<'a>> {
+        if self.is_single_family() {
+            vec![self.graphics]
+        } else {
+            vec![self.graphics, self.present]
+        }
+    }
+
+    /// Returns the sharing mode to use when creating a swapchain.
+    ///
+    /// If the families are different, the images of the swapchain are shared between them so
+    /// that they don't need to be transferred from the graphics family to the present family.
+    #[inline]
+    pub fn swapchain_sharing(&self) -> SharingMode {
+        if self.is_single_family() {
+            SharingMode::Exclusive(self.graphics.id())
+        } else {
+            SharingMode::Concurrent(vec![self.graphics.id(), self.present.id()])
+        }
+    }
+}
+
+/// Error that can happen when looking for queue families to draw on a surface.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GraphicsPresentFamiliesError {
+    /// Error while querying whether a queue family can present on the surface.
+    CapabilitiesError(CapabilitiesError),
+
+    /// The physical device has no queue family that supports graphics operations.
+    NoGraphicsFamily,
+
+    /// The physical device has no queue family that can present on the surface.
+    NoPresentFamily,
+}
+
+impl error::Error for GraphicsPresentFamiliesError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            GraphicsPresentFamiliesError::CapabilitiesError(_) => {
+                "error while querying the support of the surface"
+            },
+            GraphicsPresentFamiliesError::NoGraphicsFamily => {
+                "the physical device has no queue family that supports graphics operations"
+            },
+            GraphicsPresentFamiliesError::NoPresentFamily => {
+                "the physical device has no queue family that can present on the surface"
+            },
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            GraphicsPresentFamiliesError::CapabilitiesError(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for GraphicsPresentFamiliesError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl From<CapabilitiesError> for GraphicsPresentFamiliesError {
+    #[inline]
+    fn from(err: CapabilitiesError) -> GraphicsPresentFamiliesError {
+        GraphicsPresentFamiliesError::CapabilitiesError(err)
+    }
+}
+
 /// Error that can happen when retreiving a surface's capabilities.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 #[repr(u32)]
@@ -723,8 +931,72 @@ impl From<Error> for CapabilitiesError {
 #[cfg(test)]
 mod tests {
     use std::ptr;
+    use instance::Instance;
+    use instance::InstanceExtensions;
+    use instance::PhysicalDevice;
     use swapchain::Surface;
     use swapchain::SurfaceCreationError;
+    use swapchain::display::Display;
+    use swapchain::display::DisplayPlane;
+
+    // Creates an instance with the given extensions in addition to `khr_surface` and
+    // `khr_display`, and a surface on the first display of the first physical device. Returns if
+    // there is no such display.
+    macro_rules! display_surface {
+        ($($ext:ident),*) => ({
+            let extensions = InstanceExtensions {
+                khr_surface: true,
+                khr_display: true,
+                $($ext: true,)*
+                ..InstanceExtensions::none()
+            };
+            let instance = match Instance::new(None, &extensions, None) {
+                Ok(i) => i,
+                Err(_) => return,
+            };
+            let surface = {
+                let physical = match PhysicalDevice::enumerate(&instance).next() {
+                    Some(p) => p,
+                    None => return,
+                };
+                let display = match Display::enumerate(&physical).next() {
+                    Some(d) => d,
+                    None => return,
+                };
+                let display_mode = match display.display_modes().next() {
+                    Some(m) => m,
+                    None => return,
+                };
+                let plane = match DisplayPlane::enumerate(&physical)
+                    .find(|p| p.supports(&display))
+                {
+                    Some(p) => p,
+                    None => return,
+                };
+                Surface::from_display_mode(&display_mode, &plane).unwrap()
+            };
+            (instance, surface)
+        })
+    }
+
+    #[test]
+    fn supports_protected() {
+        let (instance, surface) = display_surface!(khr_get_surface_capabilities2,
+                                                   khr_surface_protected_capabilities);
+        let physical = PhysicalDevice::enumerate(&instance).next().unwrap();
+
+        // Whether protected content is supported depends on the implementation, but the query
+        // itself must succeed.
+        surface.supports_protected(physical).unwrap();
+    }
+
+    #[test]
+    #[should_panic]
+    fn supports_protected_extensions_missing() {
+        let (instance, surface) = display_surface!();
+        let physical = PhysicalDevice::enumerate(&instance).next().unwrap();
+        let _ = surface.supports_protected(physical);
+    }
 
     #[test]
     fn khr_win32_surface_ext_missing() {
@@ -13,14 +13,24 @@ use std::mem;
 use std::ptr;
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::sync::Weak;
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering;
 use std::time::Duration;
 
 use buffer::BufferAccess;
+use command_buffer::pool::standard::StandardCommandPoolAlloc;
 use command_buffer::submit::SubmitAnyBuilder;
+use command_buffer::submit::SubmitCommandBufferBuilder;
 use command_buffer::submit::SubmitPresentBuilder;
+use command_buffer::submit::SubmitPresentError;
 use command_buffer::submit::SubmitSemaphoresWaitBuilder;
+use command_buffer::sys::Flags;
+use command_buffer::sys::Kind;
+use command_buffer::sys::UnsafeCommandBuffer;
+use command_buffer::sys::UnsafeCommandBufferBuilder;
+use command_buffer::sys::UnsafeCommandBufferBuilderPipelineBarrier;
+use debug_name::DebugName;
 use device::Device;
 use device::DeviceOwned;
 use device::Queue;
@@ -33,6 +43,7 @@ use image::ImageLayout;
 use image::ImageUsage;
 use image::swapchain::SwapchainImage;
 use image::sys::UnsafeImage;
+use swapchain::Capabilities;
 use swapchain::CapabilitiesError;
 use swapchain::ColorSpace;
 use swapchain::CompositeAlpha;
@@ -40,9 +51,12 @@ use swapchain::PresentMode;
 use swapchain::Surface;
 use swapchain::SurfaceSwapchainLock;
 use swapchain::SurfaceTransform;
+use swapchain::latency::FrameLatency;
 use sync::AccessCheckError;
 use sync::AccessError;
 use sync::AccessFlagBits;
+use sync::Fence;
+use sync::FenceWaitError;
 use sync::FlushError;
 use sync::GpuFuture;
 use sync::PipelineStages;
@@ -64,6 +78,10 @@ use vk;
 ///
 /// If you try to draw on an image without acquiring it first, the execution will block. (TODO
 /// behavior may change).
+///
+/// If the swapchain uses one of the shared present modes, the image only needs to be acquired
+/// from the presentation engine once. Subsequent calls immediately return the same image and a
+/// future that doesn't wait for anything.
 // TODO: has to make sure vkQueuePresent is called, because calling acquire_next_image many
 // times in a row is an error
 pub fn acquire_next_image(swapchain: Arc<Swapchain>, timeout: Option<Duration>)
@@ -74,13 +92,35 @@ pub fn acquire_next_image(swapchain: Arc<Swapchain>, timeout: Option<Duration>)
         // > VkSwapchainCreateInfoKHR::oldSwapchain value to vkCreateSwapchainKHR
         let stale = swapchain.stale.lock().unwrap();
         if *stale {
+            swapchain.latency.out_of_date();
             return Err(AcquireError::OutOfDate);
         }
 
+        // A shared presentable image stays acquired after it has been acquired for the first time.
+        if swapchain.mode.is_shared() && swapchain.shared_acquired.load(Ordering::SeqCst) {
+            return Ok((0,
+                       SwapchainAcquireFuture {
+                           swapchain: swapchain.clone(),
+                           semaphore: None,
+                           image_id: 0,
+                           finished: AtomicBool::new(false),
+                       }));
+        }
+
         let vk = swapchain.device.pointers();
 
         let semaphore = Semaphore::new(swapchain.device.clone())?;
 
+        // Without present fences, the fence signaled by the acquisition is what tells us that the
+        // previous present of the image is finished.
+        let latency_fence = if swapchain.latency.is_enabled() &&
+            !swapchain.device.loaded_extensions().ext_swapchain_maintenance1
+        {
+            Some(Fence::new(swapchain.device.clone())?)
+        } else {
+            None
+        };
+
         let timeout_ns = if let Some(timeout) = timeout {
             timeout
                 .as_secs()
@@ -95,8 +135,22 @@ pub fn acquire_next_image(swapchain: Arc<Swapchain>, timeout: Option<Duration>)
                                                     swapchain.swapchain,
                                                     timeout_ns,
                                                     semaphore.internal_object(),
-                                                    0,
-                                                    &mut out))?;
+                                                    latency_fence
+                                                        .as_ref()
+                                                        .map(|f| f.internal_object())
+                                                        .unwrap_or(0),
+                                                    &mut out),
+                             "vkAcquireNextImageKHR")
+            .map_err(|err| err.with_object_name(swapchain.name.get()));
+        let r = match r {
+            Ok(r) => r,
+            Err(err) => {
+                if err == Error::OutOfDate {
+                    swapchain.latency.out_of_date();
+                }
+                return Err(err.into());
+            },
+        };
 
         let id = match r {
             Success::Success => out as usize,
@@ -106,10 +160,25 @@ pub fn acquire_next_image(swapchain: Arc<Swapchain>, timeout: Option<Duration>)
             s => panic!("unexpected success value: {:?}", s),
         };
 
+        if swapchain.mode.is_shared() {
+            swapchain.shared_acquired.store(true, Ordering::SeqCst);
+        }
+
+        swapchain.images[id].acquired.store(true, Ordering::SeqCst);
+        drop(stale);
+
+        if let Some(fence) = latency_fence {
+            swapchain.latency.acquired(id, Arc::new(fence));
+        }
+
+        if let Some(observer) = swapchain.device.submission_observer() {
+            observer.swapchain_acquire(swapchain.swapchain, id);
+        }
+
         Ok((id,
             SwapchainAcquireFuture {
                 swapchain: swapchain.clone(), // TODO: don't clone
-                semaphore: semaphore,
+                semaphore: Some(semaphore),
                 image_id: id,
                 finished: AtomicBool::new(false),
             }))
@@ -119,28 +188,48 @@ pub fn acquire_next_image(swapchain: Arc<Swapchain>, timeout: Option<Duration>)
 /// Presents an image on the screen.
 ///
 /// The parameter is the same index as what `acquire_next_image` returned. The image must
-/// have been acquired first.
+/// have been acquired first, otherwise flushing the returned future produces a
+/// `FlushError::SwapchainImageNotAcquired` error. Except for the shared present modes, an image
+/// must be acquired again after it has been presented.
+///
+/// The queue must belong to a family that can present on the surface of the swapchain, otherwise
+/// flushing the returned future produces a `FlushError::PresentNotSupported` error. See
+/// `QueueFamily::supports_surface`. The queue doesn't need to support graphics operations, which
+/// makes it possible to present images that were written by a compute-only queue.
 ///
 /// The actual behavior depends on the present mode that you passed when creating the
 /// swapchain.
+///
+/// When the future is flushed, `before` is checked to make sure that it gives access to the
+/// image. If the image is not in the `PresentSrc` layout (or `SharedPresent` for the shared
+/// present modes) at the end of `before` (for example because it was written by a compute shader
+/// in the `General` layout), a transition is automatically added. The presentation always waits
+/// for the commands of `before` to be finished.
+///
+/// # Panic
+///
+/// - Panics if `index` is out of range.
+///
 pub fn present<F>(swapchain: Arc<Swapchain>, before: F, queue: Arc<Queue>, index: usize)
                   -> PresentFuture<F>
     where F: GpuFuture
 {
     assert!(index < swapchain.images.len());
 
-    // TODO: restore this check with a dummy ImageAccess implementation
-    /*let swapchain_image = me.images.lock().unwrap().get(index).unwrap().0.upgrade().unwrap();       // TODO: return error instead
-    // Normally if `check_image_access` returns false we're supposed to call the `gpu_access`
-    // function on the image instead. But since we know that this method on `SwapchainImage`
-    // always returns false anyway (by design), we don't need to do it.
-    assert!(before.check_image_access(&swapchain_image, ImageLayout::PresentSrc, true, &queue).is_ok());         // TODO: return error instead*/
+    let semaphore = Semaphore::new(queue.device().clone()).unwrap();
+
+    let present_fence = swapchain.new_present_fence(&queue);
+    let present_support = check_present_support(&queue, &swapchain);
 
     PresentFuture {
         previous: before,
         queue: queue,
+        present_support: present_support,
         swapchain: swapchain,
         image_id: index,
+        semaphore: semaphore,
+        present_fence: present_fence,
+        transition: Mutex::new(None),
         flushed: AtomicBool::new(false),
         finished: AtomicBool::new(false),
     }
@@ -158,6 +247,10 @@ pub struct Swapchain {
     // The images of this swapchain.
     images: Vec<ImageEntry>,
 
+    // The `SwapchainImage`s that were handed out for each image, so that `images()` can return
+    // them again.
+    image_handles: Mutex<Vec<Weak<SwapchainImage>>>,
+
     // If true, that means we have used this swapchain to recreate a new swapchain. The current
     // swapchain can no longer be used for anything except presenting already-acquired images.
     //
@@ -165,6 +258,21 @@ pub struct Swapchain {
     // we acquire the image.
     stale: Mutex<bool>,
 
+    // If the swapchain uses a shared present mode, true if its image has already been acquired.
+    // A shared image never needs to be acquired again afterwards.
+    shared_acquired: AtomicBool,
+
+    // The presents that the presentation engine may still be processing. Only used once
+    // `wait_for_latency` has been called.
+    latency: FrameLatency<Fence>,
+
+    // The queue families whose support of presentation on the surface has already been queried,
+    // and the result of the query.
+    present_support: Mutex<Vec<(u32, bool)>>,
+
+    // Name given with `set_debug_name`.
+    name: DebugName,
+
     // Parameters passed to the constructor.
     num_images: u32,
     format: Format,
@@ -183,6 +291,8 @@ struct ImageEntry {
     image: UnsafeImage,
     // If true, then the image is still in the undefined layout and must be transitionned.
     undefined_layout: AtomicBool,
+    // True between the moment the image is acquired and the moment it is presented.
+    acquired: AtomicBool,
 }
 
 impl Swapchain {
@@ -197,6 +307,29 @@ impl Swapchain {
     /// important to take into account if your fragment shader has side-effects or if you want to
     /// read back the content of the image afterwards.
     ///
+    /// The `transform` parameter is the transformation that the presentation engine applies to
+    /// the images before showing them. On some platforms, and notably on Android, passing a
+    /// value other than the `current_transform` of the surface makes the compositor rotate the
+    /// image itself, which has a performance cost. In that situation, pass `current_transform`
+    /// and rotate your rendering instead. The `alpha` parameter indicates how the alpha channel
+    /// of the images is composited with what is behind the surface. Use
+    /// `Capabilities::default_transform` and `Capabilities::default_composite_alpha` if you don't
+    /// have any particular requirement.
+    ///
+    /// The `dimensions` are clamped between the `min_image_extent` and the `max_image_extent` of
+    /// the surface's capabilities, and the actual dimensions can be obtained with
+    /// `dimensions()`. If the surface currently has a width or a height of zero, which typically
+    /// happens when a window is minimized, a `ZeroExtent` error is returned. You should then skip
+    /// rendering until the surface gets a non-zero extent again.
+    ///
+    /// The `num_images` parameter is the minimum number of images that the swapchain must
+    /// contain, and must be between the `min_image_count` and `max_image_count` of the surface's
+    /// capabilities. It is never adjusted automatically: if it is out of range, an
+    /// `UnsupportedMinImagesCount` or `UnsupportedMaxImagesCount` error is returned. Use
+    /// `Capabilities::clamp_image_count` if you want to pick the nearest supported value instead.
+    /// The implementation is allowed to create more images than requested, and the actual number
+    /// of images can be obtained with `num_images()` or from the length of the returned list.
+    ///
     /// This function returns the swapchain plus a list of the images that belong to the
     /// swapchain. The order in which the images are returned is important for the
     /// `acquire_next_image` and `present` functions.
@@ -255,6 +388,124 @@ impl Swapchain {
                              Some(self))
     }
 
+    /// Recreates the swapchain with a different present mode, for example in order to switch
+    /// vsync on or off at runtime. All the other parameters are kept.
+    ///
+    /// Returns `UnsupportedPresentMode` if the surface doesn't support `mode`, in which case
+    /// the current swapchain remains usable. Otherwise the current swapchain is retired: it can
+    /// no longer acquire images, but the images that are already acquired can still be
+    /// presented. The Vulkan swapchain is only destroyed once the last `Arc` to it (including
+    /// the ones held by the `SwapchainImage`s and by pending `PresentFuture`s) is gone. If
+    /// `wait_for_latency` has been called, its destruction also waits for the presents that are
+    /// still being processed by the presentation engine.
+    ///
+    /// Just like with `recreate_with_dimension`, the images returned by this function are new
+    /// images, and anything that referred to the old ones (such as framebuffers) must be
+    /// recreated.
+    ///
+    /// Switching from a shared present mode to a regular one requests the minimum number of
+    /// images supported by the surface, and the other way around requests a single image.
+    pub fn recreate_with_present_mode(
+        &self, mode: PresentMode)
+        -> Result<(Arc<Swapchain>, Vec<Arc<SwapchainImage>>), SwapchainCreationError> {
+        // Checked here rather than in `new_inner` so that the current swapchain isn't retired
+        // for nothing.
+        let capabilities = self.surface.capabilities(self.device.physical_device())?;
+        if !capabilities.present_modes.supports(mode) {
+            return Err(SwapchainCreationError::UnsupportedPresentMode);
+        }
+
+        let num_images = match (self.mode.is_shared(), mode.is_shared()) {
+            (false, true) => 1,
+            (true, false) => capabilities.min_image_count,
+            _ => self.num_images,
+        };
+
+        Swapchain::new_inner(self.device.clone(),
+                             self.surface.clone(),
+                             num_images,
+                             self.format,
+                             self.color_space,
+                             self.dimensions,
+                             self.layers,
+                             self.usage,
+                             self.sharing.clone(),
+                             self.transform,
+                             self.alpha,
+                             mode,
+                             self.clipped,
+                             Some(self))
+    }
+
+    /// Returns true if the surface has changed in a way that requires recreating the swapchain,
+    /// in other words if its current extent no longer matches the dimensions of the swapchain.
+    ///
+    /// If `follow_transform` is true, a change of the current transform of the surface is also
+    /// considered as requiring a recreation. Pass true if you pre-rotate your rendering, in which
+    /// case the swapchain must always be created with the current transform of the surface.
+    /// This typically happens on Android when the device is rotated, and is not necessarily
+    /// reported by `acquire_next_image` or `present`.
+    ///
+    /// While the window is minimized, this returns true but `recreate_for_surface` returns
+    /// `Ok(None)`.
+    pub fn needs_recreation(&self, follow_transform: bool) -> Result<bool, CapabilitiesError> {
+        let capabilities = self.surface.capabilities(self.device.physical_device())?;
+
+        if let Some(extent) = capabilities.current_extent {
+            if extent != self.dimensions {
+                return Ok(true);
+            }
+        }
+
+        Ok(follow_transform && capabilities.current_transform != self.transform)
+    }
+
+    /// Recreates the swapchain with the current extent of the surface. All the other parameters
+    /// are kept, except the transform if `follow_transform` is true, in which case the current
+    /// transform of the surface is used.
+    ///
+    /// Returns `Ok(None)` if the surface currently has a width or a height of zero, for example
+    /// because the window is minimized. In that situation the current swapchain is left
+    /// untouched, and you should skip rendering the frame and try again later.
+    ///
+    /// If the surface doesn't report a current extent, the dimensions of the swapchain are kept.
+    /// The dimensions of the new swapchain are those of the surface in its native orientation.
+    /// Use `SurfaceTransform::pre_rotated_dimensions` on them to obtain the dimensions as seen
+    /// by the user, and `SurfaceTransform::pre_rotation_matrix` to rotate your rendering.
+    ///
+    /// See also `needs_recreation`.
+    pub fn recreate_for_surface(
+        &self, follow_transform: bool)
+        -> Result<Option<(Arc<Swapchain>, Vec<Arc<SwapchainImage>>)>, SwapchainCreationError> {
+        let capabilities = self.surface.capabilities(self.device.physical_device())?;
+        if capabilities.is_zero_extent() {
+            return Ok(None);
+        }
+
+        let dimensions = capabilities.current_extent.unwrap_or(self.dimensions);
+        let transform = if follow_transform {
+            capabilities.current_transform
+        } else {
+            self.transform
+        };
+
+        let (swapchain, images) = Swapchain::new_inner(self.device.clone(),
+                                                       self.surface.clone(),
+                                                       self.num_images,
+                                                       self.format,
+                                                       self.color_space,
+                                                       dimensions,
+                                                       self.layers,
+                                                       self.usage,
+                                                       self.sharing.clone(),
+                                                       transform,
+                                                       self.alpha,
+                                                       self.mode,
+                                                       self.clipped,
+                                                       Some(self))?;
+        Ok(Some((swapchain, images)))
+    }
+
     fn new_inner(device: Arc<Device>, surface: Arc<Surface>, num_images: u32, format: Format,
                  color_space: ColorSpace, dimensions: [u32; 2], layers: u32, usage: ImageUsage,
                  sharing: SharingMode, transform: SurfaceTransform, alpha: CompositeAlpha,
@@ -265,14 +516,7 @@ impl Swapchain {
 
         // Checking that the requested parameters match the capabilities.
         let capabilities = surface.capabilities(device.physical_device())?;
-        if num_images < capabilities.min_image_count {
-            return Err(SwapchainCreationError::UnsupportedMinImagesCount);
-        }
-        if let Some(c) = capabilities.max_image_count {
-            if num_images > c {
-                return Err(SwapchainCreationError::UnsupportedMaxImagesCount);
-            }
-        }
+        check_image_count(&capabilities, mode, num_images)?;
         if !capabilities
             .supported_formats
             .iter()
@@ -280,18 +524,7 @@ impl Swapchain {
         {
             return Err(SwapchainCreationError::UnsupportedFormat);
         }
-        if dimensions[0] < capabilities.min_image_extent[0] {
-            return Err(SwapchainCreationError::UnsupportedDimensions);
-        }
-        if dimensions[1] < capabilities.min_image_extent[1] {
-            return Err(SwapchainCreationError::UnsupportedDimensions);
-        }
-        if dimensions[0] > capabilities.max_image_extent[0] {
-            return Err(SwapchainCreationError::UnsupportedDimensions);
-        }
-        if dimensions[1] > capabilities.max_image_extent[1] {
-            return Err(SwapchainCreationError::UnsupportedDimensions);
-        }
+        let dimensions = swapchain_dimensions(&capabilities, dimensions)?;
         if layers < 1 && layers > capabilities.max_image_array_layers {
             return Err(SwapchainCreationError::UnsupportedArrayLayers);
         }
@@ -329,11 +562,16 @@ impl Swapchain {
             return Err(SwapchainCreationError::MissingExtension);
         }
 
+        if mode.is_shared() && !device.loaded_extensions().khr_shared_presentable_image {
+            return Err(SwapchainCreationError::SharedPresentableImageExtensionNotEnabled);
+        }
+
         // Required by the specs.
         assert_ne!(usage, ImageUsage::none());
 
+        // The old swapchain is retired even if the creation fails.
         if let Some(ref old_swapchain) = old_swapchain {
-            *old_swapchain.stale.lock().unwrap() = false;
+            *old_swapchain.stale.lock().unwrap() = true;
         }
 
         let vk = device.pointers();
@@ -378,7 +616,8 @@ impl Swapchain {
             check_errors(vk.CreateSwapchainKHR(device.internal_object(),
                                                &infos,
                                                ptr::null(),
-                                               &mut output))?;
+                                               &mut output),
+                         "vkCreateSwapchainKHR")?;
             output
         };
 
@@ -387,13 +626,15 @@ impl Swapchain {
             check_errors(vk.GetSwapchainImagesKHR(device.internal_object(),
                                                   swapchain,
                                                   &mut num,
-                                                  ptr::null_mut()))?;
+                                                  ptr::null_mut()),
+                         "vkGetSwapchainImagesKHR")?;
 
             let mut images = Vec::with_capacity(num as usize);
             check_errors(vk.GetSwapchainImagesKHR(device.internal_object(),
                                                   swapchain,
                                                   &mut num,
-                                                  images.as_mut_ptr()))?;
+                                                  images.as_mut_ptr()),
+                         "vkGetSwapchainImagesKHR")?;
             images.set_len(num as usize);
             images
         };
@@ -420,6 +661,7 @@ impl Swapchain {
                 ImageEntry {
                     image: img,
                     undefined_layout: AtomicBool::new(true),
+                    acquired: AtomicBool::new(false),
                 }
             })
             .collect::<Vec<_>>();
@@ -429,7 +671,12 @@ impl Swapchain {
                                      surface: surface.clone(),
                                      swapchain: swapchain,
                                      images: images,
+                                     image_handles: Mutex::new(Vec::new()),
                                      stale: Mutex::new(false),
+                                     shared_acquired: AtomicBool::new(false),
+                                     latency: FrameLatency::new(),
+                                     present_support: Mutex::new(Vec::new()),
+                                     name: DebugName::new(),
                                      num_images: num_images,
                                      format: format,
                                      color_space: color_space,
@@ -451,9 +698,53 @@ impl Swapchain {
             swapchain_images
         };
 
+        *swapchain.image_handles.lock().unwrap() =
+            swapchain_images.iter().map(|img| Arc::downgrade(img)).collect();
+
         Ok((swapchain, swapchain_images))
     }
 
+    /// Returns the images of the swapchain, in the same order as the indices returned by
+    /// `acquire_next_image`.
+    ///
+    /// The images that were returned when creating the swapchain are returned again if they are
+    /// still alive. Otherwise new `SwapchainImage`s are created.
+    pub fn images(me: &Arc<Swapchain>) -> Result<Vec<Arc<SwapchainImage>>, OomError> {
+        let mut handles = me.image_handles.lock().unwrap();
+
+        let mut images = Vec::with_capacity(me.images.len());
+        for (n, handle) in handles.iter_mut().enumerate() {
+            let image = match handle.upgrade() {
+                Some(image) => image,
+                None => {
+                    let image = unsafe { SwapchainImage::from_raw(me.clone(), n)? };
+                    *handle = Arc::downgrade(&image);
+                    image
+                },
+            };
+
+            images.push(image);
+        }
+
+        Ok(images)
+    }
+
+    /// Returns true if the image at the given index is currently acquired by the application,
+    /// in other words if it has been returned by `acquire_next_image` and hasn't been presented
+    /// since.
+    ///
+    /// For the shared present modes, the image stays acquired once it has been acquired for
+    /// the first time.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `index` is out of range.
+    ///
+    #[inline]
+    pub fn is_image_acquired(&self, index: usize) -> bool {
+        self.images[index].acquired.load(Ordering::SeqCst)
+    }
+
     /// Returns of the images that belong to this swapchain.
     #[inline]
     pub fn raw_image(&self, offset: usize) -> Option<ImageInner> {
@@ -468,14 +759,29 @@ impl Swapchain {
         })
     }
 
+    /// Returns the surface that the swapchain presents on.
+    #[inline]
+    pub fn surface(&self) -> &Arc<Surface> {
+        &self.surface
+    }
+
     /// Returns the number of images of the swapchain.
     ///
-    /// See the documentation of `Swapchain::new`.
+    /// This is the number of images that were actually created, which can be higher than the
+    /// number that was requested. See the documentation of `Swapchain::new`.
     #[inline]
     pub fn num_images(&self) -> u32 {
         self.images.len() as u32
     }
 
+    /// Returns the number of images that was requested when creating the swapchain.
+    ///
+    /// See the documentation of `Swapchain::new`.
+    #[inline]
+    pub fn requested_num_images(&self) -> u32 {
+        self.num_images
+    }
+
     /// Returns the format of the images of the swapchain.
     ///
     /// See the documentation of `Swapchain::new`.
@@ -500,8 +806,20 @@ impl Swapchain {
         self.layers
     }
 
+    /// Returns the usage of the images of the swapchain.
+    ///
+    /// See the documentation of `Swapchain::new`.
+    #[inline]
+    pub fn usage(&self) -> ImageUsage {
+        self.usage
+    }
+
     /// Returns the transform that was passed when creating the swapchain.
     ///
+    /// The presentation engine applies this transform to the images before showing them. If it
+    /// is a rotation, your rendering must be rotated the other way round in order to appear
+    /// upright, for example with `SurfaceTransform::pre_rotation_matrix`.
+    ///
     /// See the documentation of `Swapchain::new`.
     #[inline]
     pub fn transform(&self) -> SurfaceTransform {
@@ -510,6 +828,10 @@ impl Swapchain {
 
     /// Returns the alpha mode that was passed when creating the swapchain.
     ///
+    /// If this is `CompositeAlpha::PreMultiplied`, the color values written to the images must
+    /// already be multiplied by their alpha value. See also
+    /// `Capabilities::choose_composite_alpha`.
+    ///
     /// See the documentation of `Swapchain::new`.
     #[inline]
     pub fn composite_alpha(&self) -> CompositeAlpha {
@@ -531,6 +853,146 @@ impl Swapchain {
     pub fn clipped(&self) -> bool {
         self.clipped
     }
+
+    /// Gives a name to the swapchain.
+    ///
+    /// The name is attached to the errors returned when acquiring an image or querying the
+    /// status of the swapchain. If the `ext_debug_utils` extension is enabled on the instance, it
+    /// is also passed to the implementation and shows up in the messages of the validation
+    /// layers.
+    ///
+    /// The name isn't transferred to the swapchains that are created by recreating this one.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `name` contains a nul character.
+    ///
+    #[inline]
+    pub fn set_debug_name(&self, name: &str) -> Result<(), OomError> {
+        self.name.set(&self.device, vk::OBJECT_TYPE_SWAPCHAIN_KHR, self.swapchain, name)
+    }
+
+    /// Returns the name given with `set_debug_name`, if any.
+    #[inline]
+    pub fn debug_name(&self) -> Option<Arc<str>> {
+        self.name.get()
+    }
+
+    /// Queries the status of the swapchain from the presentation engine.
+    ///
+    /// This is mostly useful for the shared present modes, where the application doesn't call
+    /// `acquire_next_image` regularly and therefore isn't notified when the swapchain needs to
+    /// be recreated. Returns `Ok` if the swapchain is still usable.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the `VK_KHR_shared_presentable_image` extension was not enabled on the device.
+    ///
+    pub fn status(&self) -> Result<(), AcquireError> {
+        assert!(self.device.loaded_extensions().khr_shared_presentable_image);
+
+        unsafe {
+            let vk = self.device.pointers();
+            match check_errors(vk.GetSwapchainStatusKHR(self.device.internal_object(),
+                                                        self.swapchain),
+                               "vkGetSwapchainStatusKHR")
+                .map_err(|err| err.with_object_name(self.name.get()))? {
+                Success::Success => Ok(()),
+                Success::Suboptimal => Ok(()),        // TODO: give that info to the user
+                s => panic!("unexpected success value: {:?}", s),
+            }
+        }
+    }
+
+    /// Blocks until fewer than `max_frames_in_flight` presents of this swapchain are still being
+    /// processed by the presentation engine, or until `timeout` has elapsed. This is the
+    /// equivalent of the frame latency waitable object of DXGI, and is typically called at the
+    /// start of each frame in order to cap the latency between input and display.
+    ///
+    /// Presents are only tracked once this function has been called for the first time, so the
+    /// first call always returns immediately.
+    ///
+    /// If the `ext_swapchain_maintenance1` extension is enabled on the device, each present is
+    /// given a fence that the presentation engine signals when it's done with it. Otherwise, a
+    /// present is considered finished once the presentation engine has handed its image back,
+    /// which is known thanks to a fence passed to `acquire_next_image`. In that situation a
+    /// present can't be waited upon until its image has been acquired again, and this function
+    /// returns early if it only has such presents left to wait for. Call it after
+    /// `acquire_next_image` rather than before in order to get the best results.
+    ///
+    /// Presents are not tracked for the shared present modes.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `max_frames_in_flight` is 0.
+    ///
+    #[inline]
+    pub fn wait_for_latency(&self, max_frames_in_flight: usize, timeout: Option<Duration>)
+                            -> Result<(), FenceWaitError> {
+        self.latency.enable();
+        self.latency.wait(max_frames_in_flight, timeout)
+    }
+
+    /// Returns the number of presents that the presentation engine may still be processing.
+    ///
+    /// Always returns 0 if `wait_for_latency` has never been called.
+    #[inline]
+    pub fn presents_in_flight(&self) -> Result<usize, FenceWaitError> {
+        self.latency.in_flight()
+    }
+
+    // Returns the fence to pass along with a present of this swapchain on `queue`, if its
+    // latency is tracked and the presentation engine can signal fences.
+    pub(crate) fn new_present_fence(&self, queue: &Queue) -> Option<Arc<Fence>> {
+        if self.latency.is_enabled() && !self.mode.is_shared() &&
+            queue.device().loaded_extensions().ext_swapchain_maintenance1
+        {
+            Some(Arc::new(Fence::new(queue.device().clone()).unwrap()))
+        } else {
+            None
+        }
+    }
+
+    // Called when an image is about to be added to a present command. Returns an error if the
+    // image isn't acquired. The image is only released by `mark_presented`, so that it stays
+    // acquired if the present command fails.
+    #[inline]
+    pub(crate) fn check_acquired_for_present(&self, image_id: usize) -> Result<(), FlushError> {
+        check_acquired_image(&self.images[image_id].acquired)
+    }
+
+    // Called by `SubmitPresentBuilder` once a present command of the image has been successfully
+    // submitted. Presents that are never submitted must not be recorded, otherwise waiting for
+    // their fence would block forever.
+    pub(crate) fn mark_presented(&self, image_id: usize, present_fence: Option<Arc<Fence>>) {
+        release_acquired_image(&self.images[image_id].acquired, self.mode.is_shared());
+
+        if self.latency.is_enabled() && !self.mode.is_shared() {
+            self.latency.presented(image_id, present_fence);
+        }
+
+        // Once presented, the image is no longer in the undefined layout.
+        self.images[image_id]
+            .undefined_layout
+            .store(false, Ordering::Relaxed);
+    }
+
+    // Called when a present of this swapchain has returned `OutOfDate`. The images that haven't
+    // been acquired again will never be, so their presents are no longer waited upon.
+    #[inline]
+    pub(crate) fn mark_out_of_date(&self) {
+        self.latency.out_of_date();
+    }
+
+    // Returns the layout that the images of the swapchain must be in when they are presented.
+    #[inline]
+    pub(crate) fn present_layout(&self) -> ImageLayout {
+        if self.mode.is_shared() {
+            ImageLayout::SharedPresent
+        } else {
+            ImageLayout::PresentSrc
+        }
+    }
 }
 
 unsafe impl VulkanObject for Swapchain {
@@ -552,6 +1014,14 @@ impl fmt::Debug for Swapchain {
 impl Drop for Swapchain {
     #[inline]
     fn drop(&mut self) {
+        // The presentation engine may still be reading from the images of presents that have
+        // already been flushed. Only the presents that have a fence can be waited upon. The wait
+        // is bounded, as a fence that never gets signaled (for example because the device has
+        // been lost) must not block the destruction forever.
+        if self.latency.is_enabled() {
+            let _ = self.latency.wait(1, Some(Duration::from_secs(1)));
+        }
+
         unsafe {
             let vk = self.device.pointers();
             vk.DestroySwapchainKHR(self.device.internal_object(), self.swapchain, ptr::null());
@@ -575,16 +1045,32 @@ pub enum SwapchainCreationError {
     NativeWindowInUse,
     /// The `VK_KHR_swapchain` extension was not enabled.
     MissingExtension,
+    /// A shared present mode was requested, but the `VK_KHR_shared_presentable_image` extension
+    /// was not enabled.
+    SharedPresentableImageExtensionNotEnabled,
     /// Surface mismatch between old and new swapchain.
     OldSwapchainSurfaceMismatch,
-    /// The requested number of swapchain images is not supported by the surface.
-    UnsupportedMinImagesCount,
-    /// The requested number of swapchain images is not supported by the surface.
-    UnsupportedMaxImagesCount,
+    /// The requested number of swapchain images is lower than the minimum supported by the
+    /// surface.
+    UnsupportedMinImagesCount {
+        /// The number of images that was requested.
+        requested: u32,
+        /// The minimum number of images supported by the surface.
+        min: u32,
+    },
+    /// The requested number of swapchain images is higher than the maximum supported by the
+    /// surface.
+    UnsupportedMaxImagesCount {
+        /// The number of images that was requested.
+        requested: u32,
+        /// The maximum number of images supported by the surface.
+        max: u32,
+    },
     /// The requested image format is not supported by the surface.
     UnsupportedFormat,
-    /// The requested dimensions are not supported by the surface.
-    UnsupportedDimensions,
+    /// The surface currently has a width or a height of zero, for example because the window is
+    /// minimized. No swapchain can be created until it gets a non-zero extent again.
+    ZeroExtent,
     /// The requested array layers count is not supported by the surface.
     UnsupportedArrayLayers,
     /// The requested image usage is not supported by the surface.
@@ -619,20 +1105,26 @@ impl error::Error for SwapchainCreationError {
             SwapchainCreationError::MissingExtension => {
                 "the `VK_KHR_swapchain` extension was not enabled"
             },
+            SwapchainCreationError::SharedPresentableImageExtensionNotEnabled => {
+                "a shared present mode was requested, but the `VK_KHR_shared_presentable_image` \
+                 extension was not enabled"
+            },
             SwapchainCreationError::OldSwapchainSurfaceMismatch => {
                 "surface mismatch between old and new swapchain"
             },
-            SwapchainCreationError::UnsupportedMinImagesCount => {
-                "the requested number of swapchain images is not supported by the surface"
+            SwapchainCreationError::UnsupportedMinImagesCount { .. } => {
+                "the requested number of swapchain images is lower than the minimum supported by \
+                 the surface"
             },
-            SwapchainCreationError::UnsupportedMaxImagesCount => {
-                "the requested number of swapchain images is not supported by the surface"
+            SwapchainCreationError::UnsupportedMaxImagesCount { .. } => {
+                "the requested number of swapchain images is higher than the maximum supported by \
+                 the surface"
             },
             SwapchainCreationError::UnsupportedFormat => {
                 "the requested image format is not supported by the surface"
             },
-            SwapchainCreationError::UnsupportedDimensions => {
-                "the requested dimensions are not supported by the surface"
+            SwapchainCreationError::ZeroExtent => {
+                "the surface currently has a width or a height of zero"
             },
             SwapchainCreationError::UnsupportedArrayLayers => {
                 "the requested array layers count is not supported by the surface"
@@ -678,15 +1170,9 @@ impl From<Error> for SwapchainCreationError {
             err @ Error::OutOfDeviceMemory => {
                 SwapchainCreationError::OomError(OomError::from(err))
             },
-            err @ Error::DeviceLost => {
-                SwapchainCreationError::DeviceLost
-            },
-            err @ Error::SurfaceLost => {
-                SwapchainCreationError::SurfaceLost
-            },
-            err @ Error::NativeWindowInUse => {
-                SwapchainCreationError::NativeWindowInUse
-            },
+            Error::DeviceLost => SwapchainCreationError::DeviceLost,
+            Error::SurfaceLost => SwapchainCreationError::SurfaceLost,
+            Error::NativeWindowInUse => SwapchainCreationError::NativeWindowInUse,
             _ => panic!("unexpected error: {:?}", err),
         }
     }
@@ -714,7 +1200,9 @@ impl From<CapabilitiesError> for SwapchainCreationError {
 pub struct SwapchainAcquireFuture {
     swapchain: Arc<Swapchain>,
     image_id: usize,
-    semaphore: Semaphore,
+    // Semaphore signaled when the image is available. `None` if the image is a shared presentable
+    // image that was already acquired previously.
+    semaphore: Option<Semaphore>,
     finished: AtomicBool,
 }
 
@@ -739,9 +1227,13 @@ unsafe impl GpuFuture for SwapchainAcquireFuture {
 
     #[inline]
     unsafe fn build_submission(&self) -> Result<SubmitAnyBuilder, FlushError> {
-        let mut sem = SubmitSemaphoresWaitBuilder::new();
-        sem.add_wait_semaphore(&self.semaphore);
-        Ok(SubmitAnyBuilder::SemaphoresWait(sem))
+        if let Some(ref semaphore) = self.semaphore {
+            let mut sem = SubmitSemaphoresWaitBuilder::new();
+            sem.add_wait_semaphore(semaphore);
+            Ok(SubmitAnyBuilder::SemaphoresWait(sem))
+        } else {
+            Ok(SubmitAnyBuilder::Empty)
+        }
     }
 
     #[inline]
@@ -789,9 +1281,10 @@ unsafe impl GpuFuture for SwapchainAcquireFuture {
                                                 }));
         }
 
-        if layout != ImageLayout::Undefined && layout != ImageLayout::PresentSrc {
+        let present_layout = self.swapchain.present_layout();
+        if layout != ImageLayout::Undefined && layout != present_layout {
             return Err(AccessCheckError::Denied(AccessError::UnexpectedImageLayout {
-                                                    allowed: ImageLayout::PresentSrc,
+                                                    allowed: present_layout,
                                                     requested: layout,
                                                 }));
         }
@@ -803,13 +1296,13 @@ unsafe impl GpuFuture for SwapchainAcquireFuture {
 unsafe impl DeviceOwned for SwapchainAcquireFuture {
     #[inline]
     fn device(&self) -> &Arc<Device> {
-        self.semaphore.device()
+        &self.swapchain.device
     }
 }
 
 impl Drop for SwapchainAcquireFuture {
     fn drop(&mut self) {
-        if !*self.finished.get_mut() {
+        if !*self.finished.get_mut() && self.semaphore.is_some() {
             panic!() // FIXME: what to do?
             /*// TODO: handle errors?
             let fence = Fence::new(self.device().clone()).unwrap();
@@ -892,6 +1385,43 @@ impl From<Error> for AcquireError {
     }
 }
 
+// Implementation of `ImageAccess` used to check whether the future that precedes a present
+// gives access to the swapchain image.
+struct PresentedImage<'a> {
+    swapchain: &'a Swapchain,
+    image_id: usize,
+}
+
+unsafe impl<'a> ImageAccess for PresentedImage<'a> {
+    #[inline]
+    fn inner(&self) -> ImageInner {
+        self.swapchain.raw_image(self.image_id).unwrap()
+    }
+
+    #[inline]
+    fn initial_layout_requirement(&self) -> ImageLayout {
+        self.swapchain.present_layout()
+    }
+
+    #[inline]
+    fn final_layout_requirement(&self) -> ImageLayout {
+        self.swapchain.present_layout()
+    }
+
+    #[inline]
+    fn try_gpu_lock(&self, _: bool, _: &Queue) -> Result<(), AccessError> {
+        Err(AccessError::SwapchainImageAcquireOnly)
+    }
+
+    #[inline]
+    unsafe fn increase_gpu_lock(&self) {
+    }
+
+    #[inline]
+    unsafe fn unlock(&self) {
+    }
+}
+
 /// Represents a swapchain image being presented on the screen.
 #[must_use = "Dropping this object will immediately block the thread until the GPU has finished processing the submission"]
 pub struct PresentFuture<P>
@@ -899,8 +1429,20 @@ pub struct PresentFuture<P>
 {
     previous: P,
     queue: Arc<Queue>,
+    // Whether the family of `queue` can present on the surface. Checked once when the future is
+    // created.
+    present_support: Result<(), FlushError>,
     swapchain: Arc<Swapchain>,
     image_id: usize,
+    // Semaphore signaled by the submission of `previous` and waited upon by the present command.
+    semaphore: Semaphore,
+    // Fence signaled when the presentation engine is done with the present. Only used if the
+    // latency of the swapchain is tracked and `ext_swapchain_maintenance1` is enabled.
+    present_fence: Option<Arc<Fence>>,
+    // Command buffer that transitions the image to the `PresentSrc` or `SharedPresent` layout, if
+    // one was needed.
+    // Kept alive until the future is destroyed.
+    transition: Mutex<Option<UnsafeCommandBuffer<StandardCommandPoolAlloc>>>,
     // True if `flush()` has been called on the future, which means that the present command has
     // been submitted.
     flushed: AtomicBool,
@@ -923,6 +1465,21 @@ impl<P> PresentFuture<P>
     pub fn swapchain(&self) -> &Arc<Swapchain> {
         &self.swapchain
     }
+
+    // Submits `builder` so that it signals the semaphore, and returns a present builder that
+    // waits on that semaphore.
+    unsafe fn submit_with_semaphore<'a, 'b>(&'a self, mut builder: SubmitCommandBufferBuilder<'b>,
+                                            queue: &Queue)
+                                            -> Result<SubmitPresentBuilder<'a>, FlushError>
+        where 'a: 'b
+    {
+        builder.add_signal_semaphore(&self.semaphore);
+        builder.submit(queue)?;
+
+        let mut present = SubmitPresentBuilder::new();
+        present.add_wait_semaphore(&self.semaphore);
+        Ok(present)
+    }
 }
 
 unsafe impl<P> GpuFuture for PresentFuture<P>
@@ -939,42 +1496,75 @@ unsafe impl<P> GpuFuture for PresentFuture<P>
             return Ok(SubmitAnyBuilder::Empty);
         }
 
-        let queue = self.previous.queue().map(|q| q.clone());
-
-        // TODO: if the swapchain image layout is not PRESENT, should add a transition command
-        // buffer
-
-        Ok(match self.previous.build_submission()? {
-               SubmitAnyBuilder::Empty => {
-                   let mut builder = SubmitPresentBuilder::new();
-                   builder.add_swapchain(&self.swapchain, self.image_id as u32);
-                   SubmitAnyBuilder::QueuePresent(builder)
-               },
-               SubmitAnyBuilder::SemaphoresWait(sem) => {
-                   let mut builder: SubmitPresentBuilder = sem.into();
-                   builder.add_swapchain(&self.swapchain, self.image_id as u32);
-                   SubmitAnyBuilder::QueuePresent(builder)
-               },
-               SubmitAnyBuilder::CommandBuffer(cb) => {
-                   cb.submit(&queue.unwrap())?; // FIXME: wrong because build_submission can be called multiple times
-                   let mut builder = SubmitPresentBuilder::new();
-                   builder.add_swapchain(&self.swapchain, self.image_id as u32);
-                   SubmitAnyBuilder::QueuePresent(builder)
-               },
-               SubmitAnyBuilder::BindSparse(cb) => {
-                   cb.submit(&queue.unwrap())?; // FIXME: wrong because build_submission can be called multiple times
-                   let mut builder = SubmitPresentBuilder::new();
-                   builder.add_swapchain(&self.swapchain, self.image_id as u32);
-                   SubmitAnyBuilder::QueuePresent(builder)
-               },
-               SubmitAnyBuilder::QueuePresent(present) => {
-                   unimplemented!() // TODO:
+        self.present_support.clone()?;
+        self.swapchain.check_acquired_for_present(self.image_id)?;
+
+        let queue = self.previous.queue().map(|q| q.clone()).unwrap_or(self.queue.clone());
+        let transition = build_present_transition(&self.previous,
+                                                  &queue,
+                                                  &self.swapchain,
+                                                  self.image_id)?;
+
+        // Presenting doesn't wait for the previous submissions of the queue to be finished, so
+        // any command buffer submitted by the previous futures must signal a semaphore that the
+        // present command waits upon.
+        let mut builder = match self.previous.build_submission()? {
+            SubmitAnyBuilder::Empty => {
+                match transition {
+                    Some(ref transition) => {
+                        let mut cb = SubmitCommandBufferBuilder::new();
+                        cb.add_command_buffer(transition);
+                        self.submit_with_semaphore(cb, &queue)?
+                    },
+                    None => SubmitPresentBuilder::new(),
+                }
+            },
+            SubmitAnyBuilder::SemaphoresWait(sem) => {
+                match transition {
+                    Some(ref transition) => {
+                        let mut cb: SubmitCommandBufferBuilder = sem.into();
+                        cb.add_command_buffer(transition);
+                        self.submit_with_semaphore(cb, &queue)?
+                    },
+                    None => sem.into(),
+                }
+            },
+            SubmitAnyBuilder::CommandBuffer(mut cb) => {
+                if let Some(ref transition) = transition {
+                    cb.add_command_buffer(transition);
+                }
+                self.submit_with_semaphore(cb, &queue)? // FIXME: wrong because build_submission can be called multiple times
+            },
+            SubmitAnyBuilder::BindSparse(cb) => {
+                cb.submit(&queue)?; // FIXME: wrong because build_submission can be called multiple times
+                match transition {
+                    Some(ref transition) => {
+                        let mut cb = SubmitCommandBufferBuilder::new();
+                        cb.add_command_buffer(transition);
+                        self.submit_with_semaphore(cb, &queue)?
+                    },
+                    None => SubmitPresentBuilder::new(),
+                }
+            },
+            SubmitAnyBuilder::QueuePresent(present) => {
+                unimplemented!() // TODO:
                 /*present.submit();
                 let mut builder = SubmitPresentBuilder::new();
                 builder.add_swapchain(self.command_buffer.inner(), self.image_id);
                 SubmitAnyBuilder::CommandBuffer(builder)*/
-               },
-           })
+            },
+        };
+
+        builder.add_swapchain(&self.swapchain, self.image_id as u32);
+        if let Some(ref fence) = self.present_fence {
+            builder.set_tracked_present_fence(fence);
+        }
+
+        if transition.is_some() {
+            *self.transition.lock().unwrap() = transition;
+        }
+
+        Ok(SubmitAnyBuilder::QueuePresent(builder))
     }
 
     #[inline]
@@ -985,7 +1575,14 @@ unsafe impl<P> GpuFuture for PresentFuture<P>
             match self.build_submission()? {
                 SubmitAnyBuilder::Empty => {},
                 SubmitAnyBuilder::QueuePresent(present) => {
-                    present.submit(&self.queue)?;
+                    match present.submit(&self.queue) {
+                        Ok(()) => (),
+                        Err(SubmitPresentError::OutOfDate) => {
+                            self.swapchain.latency.out_of_date();
+                            return Err(FlushError::OutOfDate);
+                        },
+                        Err(err) => return Err(err.into()),
+                    }
                 },
                 _ => unreachable!(),
             }
@@ -1057,12 +1654,619 @@ impl<P> Drop for PresentFuture<P>
     fn drop(&mut self) {
         unsafe {
             if !*self.finished.get_mut() {
-                // TODO: handle errors?
-                self.flush().unwrap();
+                match self.flush() {
+                    Ok(()) => (),
+                    // These errors are returned before anything is submitted, so `previous` is
+                    // flushed on its own.
+                    Err(FlushError::SwapchainImageNotAcquired) |
+                    Err(FlushError::PresentNotSupported { .. }) => {
+                        let _ = self.previous.flush();
+                    },
+                    // The commands of `previous` may have been submitted even though the present
+                    // failed. They are waited upon below in any case.
+                    Err(_) => (),
+                }
                 // Block until the queue finished.
-                self.queue().unwrap().wait().unwrap();
+                let _ = self.queue().unwrap().wait();
                 self.previous.signal_finished();
             }
         }
     }
 }
+
+// Checks that `previous` gives access to an image of a swapchain, and builds a command buffer
+// that transitions it to the layout required for presenting if it is in a different layout.
+pub(crate) unsafe fn build_present_transition<P>(
+    previous: &P, queue: &Arc<Queue>, swapchain: &Arc<Swapchain>, image_id: usize)
+    -> Result<Option<UnsafeCommandBuffer<StandardCommandPoolAlloc>>, FlushError>
+    where P: GpuFuture + ?Sized
+{
+    let image = PresentedImage {
+        swapchain: swapchain,
+        image_id: image_id,
+    };
+
+    let present_layout = swapchain.present_layout();
+    let current_layout =
+        match previous.check_image_access(&image, present_layout, false, queue) {
+            Ok(_) => return Ok(None),
+            Err(AccessCheckError::Denied(AccessError::UnexpectedImageLayout {
+                                             allowed, ..
+                                         })) => allowed,
+            Err(AccessCheckError::Denied(AccessError::ImageNotInitialized { .. })) => {
+                ImageLayout::Undefined
+            },
+            Err(AccessCheckError::Denied(err)) => return Err(err.into()),
+            Err(AccessCheckError::Unknown) => {
+                return Err(AccessError::SwapchainImageAcquireOnly.into());
+            },
+        };
+
+    let pool = Device::standard_command_pool(queue.device(), queue.family());
+    let mut cb = UnsafeCommandBufferBuilder::new(&pool, Kind::primary(), Flags::OneTimeSubmit)
+        .map_err(FlushError::OomError)?;
+
+    let family = queue.family();
+    let (source_stages, source_access) = present_transition_source(family.supports_graphics(),
+                                                                   family.supports_compute(),
+                                                                   family.supports_transfers());
+
+    let mut barrier = UnsafeCommandBufferBuilderPipelineBarrier::new();
+    barrier.add_image_memory_barrier(&image,
+                                     0 .. 1,
+                                     0 .. swapchain.layers(),
+                                     source_stages,
+                                     source_access,
+                                     PipelineStages {
+                                         bottom_of_pipe: true,
+                                         ..PipelineStages::none()
+                                     },
+                                     AccessFlagBits::none(),
+                                     false,
+                                     None,
+                                     current_layout,
+                                     present_layout);
+    cb.pipeline_barrier(&barrier);
+
+    Ok(Some(cb.build().map_err(FlushError::OomError)?))
+}
+
+// Returns the source stages and accesses of the barrier that transitions a swapchain image to its
+// present layout, for a queue family with the given capabilities.
+//
+// The stages of a barrier must be supported by the queue that it is submitted to. A family that
+// can't execute any command that writes to the image only needs to wait for the semaphores of the
+// submission, so the barrier doesn't wait for any stage.
+fn present_transition_source(graphics: bool, compute: bool, transfers: bool)
+                             -> (PipelineStages, AccessFlagBits) {
+    if graphics {
+        (PipelineStages {
+             all_commands: true,
+             ..PipelineStages::none()
+         },
+         AccessFlagBits {
+             shader_write: true,
+             color_attachment_write: true,
+             transfer_write: true,
+             ..AccessFlagBits::none()
+         })
+    } else if compute {
+        (PipelineStages {
+             compute_shader: true,
+             transfer: true,
+             ..PipelineStages::none()
+         },
+         AccessFlagBits {
+             shader_write: true,
+             transfer_write: true,
+             ..AccessFlagBits::none()
+         })
+    } else if transfers {
+        (PipelineStages {
+             transfer: true,
+             ..PipelineStages::none()
+         },
+         AccessFlagBits {
+             transfer_write: true,
+             ..AccessFlagBits::none()
+         })
+    } else {
+        (PipelineStages {
+             top_of_pipe: true,
+             ..PipelineStages::none()
+         },
+         AccessFlagBits::none())
+    }
+}
+
+// Returns an error if the family of `queue` can't present on the surface of `swapchain`.
+//
+// The support is only queried the first time a queue of a given family presents on the
+// swapchain.
+pub(crate) fn check_present_support(queue: &Queue, swapchain: &Swapchain)
+                                    -> Result<(), FlushError> {
+    let family = queue.family();
+
+    let supported = {
+        let mut cache = swapchain.present_support.lock().unwrap();
+        match cache.iter().find(|&&(id, _)| id == family.id()) {
+            Some(&(_, supported)) => supported,
+            None => {
+                match family.supports_surface(&swapchain.surface) {
+                    Ok(supported) => {
+                        cache.push((family.id(), supported));
+                        supported
+                    },
+                    // If the support can't be queried, the error will be reported when
+                    // presenting.
+                    Err(_) => true,
+                }
+            },
+        }
+    };
+
+    if supported {
+        Ok(())
+    } else {
+        Err(FlushError::PresentNotSupported { queue_family: family.id() })
+    }
+}
+
+// Returns the dimensions of a swapchain created with the requested `dimensions` on a surface
+// with the given capabilities.
+fn swapchain_dimensions(capabilities: &Capabilities, dimensions: [u32; 2])
+                        -> Result<[u32; 2], SwapchainCreationError> {
+    if capabilities.is_zero_extent() {
+        return Err(SwapchainCreationError::ZeroExtent);
+    }
+
+    Ok(capabilities.clamp_extent(dimensions))
+}
+
+// Checks that a swapchain with the given present mode can have `num_images` images on a surface
+// with the given capabilities.
+fn check_image_count(capabilities: &Capabilities, mode: PresentMode, num_images: u32)
+                     -> Result<(), SwapchainCreationError> {
+    // A shared presentable image swapchain always has exactly one image, regardless of the image
+    // count limits of the surface.
+    let (min, max) = if mode.is_shared() {
+        (1, Some(1))
+    } else {
+        (capabilities.min_image_count, capabilities.max_image_count)
+    };
+
+    if num_images < min {
+        return Err(SwapchainCreationError::UnsupportedMinImagesCount {
+                       requested: num_images,
+                       min: min,
+                   });
+    }
+    if let Some(max) = max {
+        if num_images > max {
+            return Err(SwapchainCreationError::UnsupportedMaxImagesCount {
+                           requested: num_images,
+                           max: max,
+                       });
+        }
+    }
+
+    Ok(())
+}
+
+// Called when an image is about to be presented. Returns an error if the image isn't acquired.
+fn check_acquired_image(acquired: &AtomicBool) -> Result<(), FlushError> {
+    if acquired.load(Ordering::SeqCst) {
+        Ok(())
+    } else {
+        Err(FlushError::SwapchainImageNotAcquired)
+    }
+}
+
+// Called once a present command of an image has been successfully submitted. The image is no
+// longer acquired afterwards, except for the shared present modes.
+fn release_acquired_image(acquired: &AtomicBool, shared: bool) {
+    if !shared {
+        acquired.store(false, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::atomic::Ordering;
+
+    use super::acquire_next_image;
+    use super::check_acquired_image;
+    use super::check_image_count;
+    use super::present_transition_source;
+    use super::release_acquired_image;
+    use super::swapchain_dimensions;
+    use device::Device;
+    use device::DeviceExtensions;
+    use features::Features;
+    use format::Format;
+    use framebuffer::Framebuffer;
+    use image::ImageAccess;
+    use image::ImageLayout;
+    use image::ImageUsage;
+    use image::ImageViewAccess;
+    use image::SwapchainImage;
+    use image::sys::ImageViewFormatError;
+    use instance::Instance;
+    use instance::InstanceExtensions;
+    use instance::PhysicalDevice;
+    use swapchain::Capabilities;
+    use swapchain::PresentMode;
+    use swapchain::SupportedCompositeAlpha;
+    use swapchain::SupportedPresentModes;
+    use swapchain::SupportedSurfaceTransforms;
+    use swapchain::Surface;
+    use swapchain::SurfaceTransform;
+    use swapchain::Swapchain;
+    use swapchain::SwapchainCreationError;
+    use swapchain::display::Display;
+    use swapchain::display::DisplayPlane;
+    use sync::FlushError;
+
+    fn capabilities(current_extent: Option<[u32; 2]>, min: [u32; 2], max: [u32; 2])
+                    -> Capabilities {
+        Capabilities {
+            min_image_count: 2,
+            max_image_count: None,
+            current_extent: current_extent,
+            min_image_extent: min,
+            max_image_extent: max,
+            max_image_array_layers: 1,
+            supported_transforms: SupportedSurfaceTransforms::none(),
+            current_transform: SurfaceTransform::Identity,
+            supported_composite_alpha: SupportedCompositeAlpha::none(),
+            supported_usage_flags: ImageUsage::none(),
+            supported_formats: Vec::new(),
+            present_modes: SupportedPresentModes::none(),
+        }
+    }
+
+    #[test]
+    fn dimensions_clamped() {
+        let caps = capabilities(None, [1, 1], [1920, 1080]);
+        assert_eq!(swapchain_dimensions(&caps, [800, 600]), Ok([800, 600]));
+        assert_eq!(swapchain_dimensions(&caps, [4000, 600]), Ok([1920, 600]));
+        assert_eq!(swapchain_dimensions(&caps, [0, 5000]), Ok([1, 1080]));
+    }
+
+    #[test]
+    fn minimized_window() {
+        let caps = capabilities(Some([0, 0]), [0, 0], [0, 0]);
+        assert_eq!(swapchain_dimensions(&caps, [800, 600]),
+                   Err(SwapchainCreationError::ZeroExtent));
+
+        let caps = capabilities(Some([800, 0]), [1, 1], [4096, 4096]);
+        assert_eq!(swapchain_dimensions(&caps, [800, 600]),
+                   Err(SwapchainCreationError::ZeroExtent));
+    }
+
+    #[test]
+    fn image_count_limits() {
+        let mut caps = capabilities(None, [1, 1], [1920, 1080]);
+        caps.min_image_count = 2;
+        caps.max_image_count = Some(4);
+
+        let tests = [
+            (PresentMode::Fifo, 1, Err(SwapchainCreationError::UnsupportedMinImagesCount {
+                                           requested: 1,
+                                           min: 2,
+                                       })),
+            (PresentMode::Fifo, 2, Ok(())),
+            (PresentMode::Fifo, 4, Ok(())),
+            (PresentMode::Fifo, 5, Err(SwapchainCreationError::UnsupportedMaxImagesCount {
+                                           requested: 5,
+                                           max: 4,
+                                       })),
+            // Shared present modes always need exactly one image.
+            (PresentMode::SharedDemandRefresh, 1, Ok(())),
+            (PresentMode::SharedDemandRefresh, 0,
+             Err(SwapchainCreationError::UnsupportedMinImagesCount {
+                     requested: 0,
+                     min: 1,
+                 })),
+            (PresentMode::SharedContinuousRefresh, 2,
+             Err(SwapchainCreationError::UnsupportedMaxImagesCount {
+                     requested: 2,
+                     max: 1,
+                 })),
+        ];
+
+        for &(mode, num_images, ref expected) in tests.iter() {
+            assert_eq!(&check_image_count(&caps, mode, num_images), expected);
+        }
+    }
+
+    #[test]
+    fn image_count_no_max() {
+        let mut caps = capabilities(None, [1, 1], [1920, 1080]);
+        caps.min_image_count = 3;
+        caps.max_image_count = None;
+
+        assert_eq!(check_image_count(&caps, PresentMode::Mailbox, 2),
+                   Err(SwapchainCreationError::UnsupportedMinImagesCount {
+                           requested: 2,
+                           min: 3,
+                       }));
+        assert_eq!(check_image_count(&caps, PresentMode::Mailbox, 3), Ok(()));
+        assert_eq!(check_image_count(&caps, PresentMode::Mailbox, 1000), Ok(()));
+    }
+
+    #[test]
+    fn present_without_acquire() {
+        let acquired = AtomicBool::new(false);
+        assert_eq!(check_acquired_image(&acquired),
+                   Err(FlushError::SwapchainImageNotAcquired));
+    }
+
+    #[test]
+    fn double_present() {
+        let acquired = AtomicBool::new(true);
+        assert_eq!(check_acquired_image(&acquired), Ok(()));
+        release_acquired_image(&acquired, false);
+        assert_eq!(check_acquired_image(&acquired),
+                   Err(FlushError::SwapchainImageNotAcquired));
+    }
+
+    #[test]
+    fn failed_present_keeps_image_acquired() {
+        // The image is only released once the present has been submitted.
+        let acquired = AtomicBool::new(true);
+        assert_eq!(check_acquired_image(&acquired), Ok(()));
+        assert_eq!(check_acquired_image(&acquired), Ok(()));
+    }
+
+    #[test]
+    fn shared_image_stays_acquired() {
+        let acquired = AtomicBool::new(true);
+        release_acquired_image(&acquired, true);
+        assert_eq!(check_acquired_image(&acquired), Ok(()));
+    }
+
+    #[test]
+    fn compute_only_present_transition() {
+        let (stages, access) = present_transition_source(false, true, true);
+        assert!(stages.compute_shader && !stages.all_commands && !stages.all_graphics);
+        assert!(access.shader_write && !access.color_attachment_write);
+
+        let (stages, access) = present_transition_source(false, false, false);
+        assert!(stages.top_of_pipe && !stages.compute_shader && !stages.transfer);
+        assert!(!access.shader_write && !access.transfer_write);
+    }
+
+    // Creates a swapchain on a display surface, as this doesn't require a window. Returns from
+    // the test if that's not possible. Additional device extensions can be passed.
+    macro_rules! display_swapchain {
+        ($($ext:ident),*) => ({
+            let extensions = InstanceExtensions {
+                khr_surface: true,
+                khr_display: true,
+                ..InstanceExtensions::none()
+            };
+            let instance = match Instance::new(None, &extensions, None) {
+                Ok(i) => i,
+                Err(_) => return,
+            };
+            let physical = match PhysicalDevice::enumerate(&instance).next() {
+                Some(p) => p,
+                None => return,
+            };
+            let display = match Display::enumerate(&physical).next() {
+                Some(d) => d,
+                None => return,
+            };
+            let display_mode = match display.display_modes().next() {
+                Some(m) => m,
+                None => return,
+            };
+            let plane = match DisplayPlane::enumerate(&physical).find(|p| p.supports(&display)) {
+                Some(p) => p,
+                None => return,
+            };
+            let surface = Surface::from_display_mode(&display_mode, &plane).unwrap();
+
+            let family = match physical
+                .queue_families()
+                .find(|&q| surface.is_supported(q).unwrap_or(false))
+            {
+                Some(q) => q,
+                None => return,
+            };
+            let extensions = DeviceExtensions {
+                khr_swapchain: true,
+                $($ext: true,)*
+                ..DeviceExtensions::none()
+            };
+            let (device, mut queues) = match Device::new(&physical,
+                                                         &Features::none(),
+                                                         &extensions,
+                                                         [(family, 0.5)].iter().cloned())
+            {
+                Ok(r) => r,
+                Err(_) => return,
+            };
+            let queue = queues.next().unwrap();
+
+            let caps = surface.capabilities(physical).unwrap();
+            let dimensions = caps.current_extent.unwrap_or(caps.min_image_extent);
+            let alpha = caps.supported_composite_alpha.iter().next().unwrap();
+            let usage = ImageUsage {
+                color_attachment: true,
+                ..ImageUsage::none()
+            };
+            let (swapchain, _) = Swapchain::new(device,
+                                                surface,
+                                                caps.min_image_count,
+                                                caps.supported_formats[0].0,
+                                                dimensions,
+                                                1,
+                                                usage,
+                                                &queue,
+                                                caps.current_transform,
+                                                alpha,
+                                                PresentMode::Fifo,
+                                                true,
+                                                None)
+                .unwrap();
+            (swapchain, caps)
+        })
+    }
+
+    #[test]
+    fn recreate_with_present_mode() {
+        let (swapchain, caps) = display_swapchain!();
+
+        // Switches to another regular mode if there is one. `Fifo` is always supported.
+        let mode = caps.present_modes
+            .iter()
+            .find(|&m| m != PresentMode::Fifo && !m.is_shared())
+            .unwrap_or(PresentMode::Fifo);
+
+        let (new_swapchain, images) = swapchain.recreate_with_present_mode(mode).unwrap();
+        assert!(!images.is_empty());
+        assert_eq!(new_swapchain.dimensions(), swapchain.dimensions());
+        assert_eq!(new_swapchain.format(), swapchain.format());
+
+        // The old swapchain is retired.
+        assert!(*swapchain.stale.lock().unwrap());
+    }
+
+    #[test]
+    fn unsupported_present_mode() {
+        let (swapchain, caps) = display_swapchain!();
+
+        let unsupported = [PresentMode::Immediate,
+                           PresentMode::Mailbox,
+                           PresentMode::Relaxed,
+                           PresentMode::SharedDemandRefresh,
+                           PresentMode::SharedContinuousRefresh]
+            .iter()
+            .cloned()
+            .find(|&m| !caps.present_modes.supports(m));
+        let mode = match unsupported {
+            Some(m) => m,
+            None => return,
+        };
+
+        match swapchain.recreate_with_present_mode(mode) {
+            Err(SwapchainCreationError::UnsupportedPresentMode) => (),
+            _ => panic!(),
+        }
+
+        // The swapchain isn't retired when the mode is rejected.
+        assert!(!*swapchain.stale.lock().unwrap());
+    }
+
+    #[test]
+    fn debug_name() {
+        let (swapchain, _) = display_swapchain!();
+
+        assert_eq!(swapchain.debug_name(), None);
+        swapchain.set_debug_name("main window").unwrap();
+        assert_eq!(swapchain.debug_name(), Some("main window".into()));
+
+        let (new_swapchain, _) = swapchain.recreate_with_dimension(swapchain.dimensions()).unwrap();
+        assert_eq!(new_swapchain.debug_name(), None);
+    }
+
+    #[test]
+    fn shared_mode_without_extension() {
+        let (swapchain, caps) = display_swapchain!();
+        if !caps.present_modes.supports(PresentMode::SharedDemandRefresh) {
+            return;
+        }
+
+        match swapchain.recreate_with_present_mode(PresentMode::SharedDemandRefresh) {
+            Err(SwapchainCreationError::SharedPresentableImageExtensionNotEnabled) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn shared_mode_single_image() {
+        let (swapchain, caps) = display_swapchain!(khr_shared_presentable_image);
+        if !caps.present_modes.supports(PresentMode::SharedDemandRefresh) {
+            return;
+        }
+
+        let (shared, images) = swapchain
+            .recreate_with_present_mode(PresentMode::SharedDemandRefresh)
+            .unwrap();
+        assert_eq!(images.len(), 1);
+        assert_eq!(shared.num_images(), 1);
+        assert_eq!(shared.present_layout(), ImageLayout::SharedPresent);
+        assert_eq!(images[0].initial_layout_requirement(), ImageLayout::SharedPresent);
+        assert_eq!(images[0].final_layout_requirement(), ImageLayout::SharedPresent);
+        assert_eq!(shared.status(), Ok(()));
+
+        // Going back to a regular mode requests the minimum number of images again.
+        let (regular, _) = shared.recreate_with_present_mode(PresentMode::Fifo).unwrap();
+        assert_eq!(regular.num_images(), caps.min_image_count);
+        assert_eq!(regular.present_layout(), ImageLayout::PresentSrc);
+    }
+
+    #[test]
+    fn shared_image_acquired_once() {
+        let (swapchain, caps) = display_swapchain!(khr_shared_presentable_image);
+        if !caps.present_modes.supports(PresentMode::SharedContinuousRefresh) {
+            return;
+        }
+
+        let (shared, _) = swapchain
+            .recreate_with_present_mode(PresentMode::SharedContinuousRefresh)
+            .unwrap();
+
+        // Once the image has been acquired, acquiring it again doesn't go through the
+        // presentation engine and the returned future doesn't wait for anything.
+        shared.shared_acquired.store(true, Ordering::SeqCst);
+        let (id, future) = acquire_next_image(shared.clone(), None).unwrap();
+        assert_eq!(id, 0);
+        assert!(future.semaphore.is_none());
+    }
+
+    #[test]
+    fn image_layers_view() {
+        let (swapchain, _) = display_swapchain!();
+        let images = Swapchain::images(&swapchain).unwrap();
+
+        assert_eq!(images[0].usage(), swapchain.usage());
+
+        let view = SwapchainImage::layers_view(&images[0], 0 .. 1).unwrap();
+        assert_eq!(view.array_layers(), 0 .. 1);
+        assert_eq!(view.dimensions().width_height(), swapchain.dimensions());
+        assert_eq!(view.descriptor_set_storage_image_layout(), ImageLayout::General);
+        assert_eq!(view.parent().inner().image.key(),
+                   ImageAccess::inner(&*images[0]).image.key());
+
+        // The view can be used as an attachment like the image itself.
+        let render_pass = Arc::new(single_pass_renderpass!(swapchain.device.clone(),
+            attachments: {
+                color: { load: Clear, store: Store, format: swapchain.format(), samples: 1, }
+            },
+            pass: { color: [color], depth_stencil: {} }
+        ).unwrap());
+        Framebuffer::start(render_pass).add(view).unwrap().build().unwrap();
+    }
+
+    #[test]
+    fn image_view_format_not_mutable() {
+        let (swapchain, _) = display_swapchain!();
+        let images = Swapchain::images(&swapchain).unwrap();
+
+        let format = if swapchain.format() == Format::B8G8R8A8Unorm {
+            Format::R8G8B8A8Unorm
+        } else {
+            Format::B8G8R8A8Unorm
+        };
+
+        match SwapchainImage::view_with_format(&images[0], format, 0 .. 1) {
+            Err(ImageViewFormatError::ImageFormatNotMutable) => (),
+            _ => panic!(),
+        }
+    }
+}
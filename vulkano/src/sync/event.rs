@@ -50,7 +50,8 @@ impl Event {
             check_errors(vk.CreateEvent(device.internal_object(),
                                         &INFOS,
                                         ptr::null(),
-                                        &mut output))?;
+                                        &mut output),
+                         "vkCreateEvent")?;
             output
         };
 
@@ -77,7 +78,8 @@ impl Event {
         unsafe {
             let vk = self.device.pointers();
             let result = check_errors(vk.GetEventStatus(self.device.internal_object(),
-                                                        self.event))?;
+                                                        self.event),
+                                      "vkGetEventStatus")?;
             match result {
                 Success::EventSet => Ok(true),
                 Success::EventReset => Ok(false),
@@ -91,7 +93,7 @@ impl Event {
     pub fn set_raw(&mut self) -> Result<(), OomError> {
         unsafe {
             let vk = self.device.pointers();
-            check_errors(vk.SetEvent(self.device.internal_object(), self.event))?;
+            check_errors(vk.SetEvent(self.device.internal_object(), self.event), "vkSetEvent")?;
             Ok(())
         }
     }
@@ -114,7 +116,8 @@ impl Event {
     pub fn reset_raw(&mut self) -> Result<(), OomError> {
         unsafe {
             let vk = self.device.pointers();
-            check_errors(vk.ResetEvent(self.device.internal_object(), self.event))?;
+            check_errors(vk.ResetEvent(self.device.internal_object(), self.event),
+                         "vkResetEvent")?;
             Ok(())
         }
     }
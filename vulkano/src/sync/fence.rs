@@ -23,6 +23,7 @@ use SafeDeref;
 use Success;
 use VulkanObject;
 use check_errors;
+use debug_name::DebugName;
 use device::Device;
 use device::DeviceOwned;
 use vk;
@@ -44,6 +45,9 @@ pub struct Fence<D = Arc<Device>>
     // This variable exists so that we don't need to call `vkGetFenceStatus` or `vkWaitForFences`
     // multiple times.
     signaled: AtomicBool,
+
+    // Name given with `set_debug_name`.
+    name: DebugName,
 }
 
 impl<D> Fence<D>
@@ -78,7 +82,8 @@ impl<D> Fence<D>
             check_errors(vk.CreateFence(device.internal_object(),
                                         &infos,
                                         ptr::null(),
-                                        &mut output))?;
+                                        &mut output),
+                         "vkCreateFence")?;
             output
         };
 
@@ -86,9 +91,31 @@ impl<D> Fence<D>
                fence: fence,
                device: device,
                signaled: AtomicBool::new(signaled),
+               name: DebugName::new(),
            })
     }
 
+    /// Gives a name to the fence.
+    ///
+    /// The name is attached to the errors returned by the methods of the fence. If the
+    /// `ext_debug_utils` extension is enabled on the instance, it is also passed to the
+    /// implementation and shows up in the messages of the validation layers.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `name` contains a nul character.
+    ///
+    #[inline]
+    pub fn set_debug_name(&self, name: &str) -> Result<(), OomError> {
+        self.name.set(&*self.device, vk::OBJECT_TYPE_FENCE, self.fence, name)
+    }
+
+    /// Returns the name given with `set_debug_name`, if any.
+    #[inline]
+    pub fn debug_name(&self) -> Option<Arc<str>> {
+        self.name.get()
+    }
+
     /// Returns true if the fence is signaled.
     #[inline]
     pub fn ready(&self) -> Result<bool, OomError> {
@@ -99,7 +126,9 @@ impl<D> Fence<D>
 
             let vk = self.device.pointers();
             let result = check_errors(vk.GetFenceStatus(self.device.internal_object(),
-                                                        self.fence))?;
+                                                        self.fence),
+                                      "vkGetFenceStatus")
+                .map_err(|err| err.with_object_name(self.name.get()))?;
             match result {
                 Success::Success => {
                     self.signaled.store(true, Ordering::Relaxed);
@@ -136,7 +165,9 @@ impl<D> Fence<D>
                                                   1,
                                                   &self.fence,
                                                   vk::TRUE,
-                                                  timeout_ns))?;
+                                                  timeout_ns),
+                                 "vkWaitForFences")
+                .map_err(|err| err.with_object_name(self.name.get()))?;
 
             match r {
                 Success::Success => {
@@ -196,7 +227,8 @@ impl<D> Fence<D>
                                               fences.len() as u32,
                                               fences.as_ptr(),
                                               vk::TRUE,
-                                              timeout_ns))?
+                                              timeout_ns),
+                             "vkWaitForFences")?
             }
         } else {
             return Ok(());
@@ -342,8 +374,15 @@ impl From<Error> for FenceWaitError {
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
     use std::time::Duration;
     use sync::Fence;
+    use sync::FenceWaitError;
+    use sync::FlushError;
+
+    use ErrorContext;
+    use check_errors;
+    use vk;
 
     #[test]
     fn fence_create() {
@@ -361,6 +400,31 @@ mod tests {
         assert!(fence.ready().unwrap());
     }
 
+    #[test]
+    fn debug_name() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let fence = Fence::new(device.clone()).unwrap();
+        assert_eq!(fence.debug_name(), None);
+        fence.set_debug_name("frame fence").unwrap();
+        assert_eq!(fence.debug_name(), Some("frame fence".into()));
+    }
+
+    #[test]
+    fn device_lost_context() {
+        let err = check_errors(vk::ERROR_DEVICE_LOST, "vkWaitForFences").unwrap_err();
+        let err = FenceWaitError::from(err.with_object_name(Some(Arc::from("frame fence"))));
+
+        match FlushError::from(err) {
+            FlushError::DeviceLost => (),
+            _ => panic!(),
+        }
+
+        let context = ErrorContext::last().unwrap();
+        assert_eq!(context.function(), "vkWaitForFences");
+        assert_eq!(context.object_name(), Some("frame fence"));
+    }
+
     #[test]
     fn fence_signaled_wait() {
         let (device, _) = gfx_dev_and_queue!();
@@ -0,0 +1,204 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+
+use VulkanObject;
+use buffer::BufferAccess;
+use command_buffer::submit::SubmitAnyBuilder;
+use command_buffer::submit::SubmitCommandBufferBuilder;
+use device::Device;
+use device::DeviceOwned;
+use device::Queue;
+use image::ImageAccess;
+use image::ImageLayout;
+use instrumentation::DeferNotifications;
+use sync::AccessCheckError;
+use sync::AccessFlagBits;
+use sync::FlushError;
+use sync::GpuFuture;
+use sync::PipelineStages;
+use sync::Semaphore;
+
+/// Builds a new external semaphore signal future.
+#[inline]
+pub fn then_signal_external_semaphore<F>(future: F, semaphore: Arc<Semaphore>)
+                                         -> ExternalSemaphoreSignalFuture<F>
+    where F: GpuFuture
+{
+    assert_eq!(future.device().internal_object(),
+               semaphore.device().internal_object());
+
+    assert!(future.queue().is_some()); // TODO: document
+
+    ExternalSemaphoreSignalFuture {
+        previous: future,
+        semaphore: semaphore,
+        signal_submitted: Mutex::new(false),
+        finished: AtomicBool::new(false),
+    }
+}
+
+/// Represents a semaphore owned by the user being signaled after a previous event.
+#[must_use = "Dropping this object will immediately block the thread until the GPU has finished \
+              processing the submission"]
+pub struct ExternalSemaphoreSignalFuture<F>
+    where F: GpuFuture
+{
+    previous: F,
+    semaphore: Arc<Semaphore>,
+    // True if the signaling command has already been submitted.
+    // If flush is called multiple times, we want to block so that only one flushing is executed.
+    // Therefore we use a `Mutex<bool>` and not an `AtomicBool`.
+    signal_submitted: Mutex<bool>,
+    finished: AtomicBool,
+}
+
+impl<F> ExternalSemaphoreSignalFuture<F>
+    where F: GpuFuture
+{
+    /// Returns the semaphore that is signaled by this future.
+    #[inline]
+    pub fn semaphore(&self) -> &Arc<Semaphore> {
+        &self.semaphore
+    }
+}
+
+unsafe impl<F> GpuFuture for ExternalSemaphoreSignalFuture<F>
+    where F: GpuFuture
+{
+    #[inline]
+    fn cleanup_finished(&mut self) {
+        self.previous.cleanup_finished();
+    }
+
+    #[inline]
+    unsafe fn build_submission(&self) -> Result<SubmitAnyBuilder, FlushError> {
+        // The semaphore is meant to be waited upon by someone else, so the next submission
+        // doesn't wait on it. It is only ordered after the signal by the queue.
+        self.flush()?;
+        Ok(SubmitAnyBuilder::Empty)
+    }
+
+    fn flush(&self) -> Result<(), FlushError> {
+        unsafe {
+            let _defer = DeferNotifications::new(self.device());
+            let mut signal_submitted = self.signal_submitted.lock().unwrap();
+
+            if *signal_submitted {
+                return Ok(());
+            }
+
+            if self.semaphore.is_wait_pending() {
+                return Err(FlushError::SemaphoreWaitPending);
+            }
+
+            let queue = self.previous.queue().unwrap().clone();
+
+            match self.previous.build_submission()? {
+                SubmitAnyBuilder::Empty => {
+                    let mut builder = SubmitCommandBufferBuilder::new();
+                    builder.add_signal_semaphore(&self.semaphore);
+                    builder.submit(&queue)?;
+                },
+                SubmitAnyBuilder::SemaphoresWait(sem) => {
+                    let mut builder: SubmitCommandBufferBuilder = sem.into();
+                    builder.add_signal_semaphore(&self.semaphore);
+                    builder.submit(&queue)?;
+                },
+                SubmitAnyBuilder::CommandBuffer(mut builder) => {
+                    debug_assert_eq!(builder.num_signal_semaphores(), 0);
+                    builder.add_signal_semaphore(&self.semaphore);
+                    builder.submit(&queue)?;
+                },
+                SubmitAnyBuilder::BindSparse(sparse) => {
+                    // The batches of a bind operation can finish in any order, so the semaphore
+                    // can't be signaled by the last of them. Instead the binds are flushed first,
+                    // and the semaphore is signaled by a submission that comes after them in
+                    // submission order.
+                    sparse.submit(&queue)?;
+                    let mut builder = SubmitCommandBufferBuilder::new();
+                    builder.add_signal_semaphore(&self.semaphore);
+                    builder.submit(&queue)?;
+                },
+                SubmitAnyBuilder::QueuePresent(present) => {
+                    present.submit(&queue)?;
+                    let mut builder = SubmitCommandBufferBuilder::new();
+                    builder.add_signal_semaphore(&self.semaphore);
+                    builder.submit(&queue)?; // FIXME: problematic because if we return an error and flush() is called again, then we'll submit the present twice
+                },
+            };
+
+            // Only write `true` here in order to try again next time if an error occurs.
+            *signal_submitted = true;
+            Ok(())
+        }
+    }
+
+    #[inline]
+    unsafe fn signal_finished(&self) {
+        debug_assert!(*self.signal_submitted.lock().unwrap());
+        self.finished.store(true, Ordering::SeqCst);
+        self.previous.signal_finished();
+    }
+
+    #[inline]
+    fn queue_change_allowed(&self) -> bool {
+        false
+    }
+
+    #[inline]
+    fn queue(&self) -> Option<Arc<Queue>> {
+        self.previous.queue()
+    }
+
+    #[inline]
+    fn check_buffer_access(
+        &self, buffer: &BufferAccess, exclusive: bool, queue: &Queue)
+        -> Result<Option<(PipelineStages, AccessFlagBits)>, AccessCheckError> {
+        self.previous
+            .check_buffer_access(buffer, exclusive, queue)
+    }
+
+    #[inline]
+    fn check_image_access(&self, image: &ImageAccess, layout: ImageLayout, exclusive: bool,
+                          queue: &Queue)
+                          -> Result<Option<(PipelineStages, AccessFlagBits)>, AccessCheckError> {
+        self.previous
+            .check_image_access(image, layout, exclusive, queue)
+    }
+}
+
+unsafe impl<F> DeviceOwned for ExternalSemaphoreSignalFuture<F>
+    where F: GpuFuture
+{
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        self.semaphore.device()
+    }
+}
+
+impl<F> Drop for ExternalSemaphoreSignalFuture<F>
+    where F: GpuFuture
+{
+    fn drop(&mut self) {
+        unsafe {
+            if !*self.finished.get_mut() {
+                // TODO: handle errors?
+                self.flush().unwrap();
+                // Block until the queue finished.
+                self.queue().unwrap().wait().unwrap();
+                self.previous.signal_finished();
+            }
+        }
+    }
+}
@@ -0,0 +1,143 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+
+use buffer::BufferAccess;
+use command_buffer::submit::SubmitAnyBuilder;
+use command_buffer::submit::SubmitSemaphoresWaitBuilder;
+use device::Device;
+use device::DeviceOwned;
+use device::Queue;
+use image::ImageAccess;
+use image::ImageLayout;
+use sync::AccessCheckError;
+use sync::AccessFlagBits;
+use sync::FlushError;
+use sync::GpuFuture;
+use sync::PipelineStages;
+use sync::Semaphore;
+
+/// Builds a future that makes the next submission wait on a semaphore owned by the user.
+///
+/// This is meant to be used when the semaphore is signaled by Vulkan code that isn't managed by
+/// vulkano, for example a hand-written module that uses the same device. The semaphore must
+/// have been signaled, or a signal operation must have been submitted, before the submission
+/// that waits on it is flushed.
+///
+/// The wait operation is submitted along with the next operation chained after this future.
+/// Until this wait has finished executing, which vulkano knows when the future is signaled as
+/// finished (for example after waiting on a fence), any attempt to wait on or signal the
+/// semaphore through vulkano returns `FlushError::SemaphoreWaitPending`.
+///
+/// > **Note**: Since a binary semaphore can only be waited upon once per signal operation, the
+/// > future can't be joined with another future that waits on the same semaphore.
+#[inline]
+pub fn wait_external_semaphore(semaphore: Arc<Semaphore>) -> ExternalSemaphoreWaitFuture {
+    ExternalSemaphoreWaitFuture {
+        semaphore: semaphore,
+        wait_reserved: AtomicBool::new(false),
+        finished: AtomicBool::new(false),
+    }
+}
+
+/// Represents the moment when a semaphore owned by the user is signaled.
+pub struct ExternalSemaphoreWaitFuture {
+    semaphore: Arc<Semaphore>,
+    // True if this future has marked the semaphore as being waited upon.
+    wait_reserved: AtomicBool,
+    finished: AtomicBool,
+}
+
+impl ExternalSemaphoreWaitFuture {
+    /// Returns the semaphore that is waited upon by this future.
+    #[inline]
+    pub fn semaphore(&self) -> &Arc<Semaphore> {
+        &self.semaphore
+    }
+}
+
+unsafe impl GpuFuture for ExternalSemaphoreWaitFuture {
+    #[inline]
+    fn cleanup_finished(&mut self) {
+    }
+
+    unsafe fn build_submission(&self) -> Result<SubmitAnyBuilder, FlushError> {
+        if !self.wait_reserved.load(Ordering::SeqCst) {
+            if !self.semaphore.try_reserve_wait() {
+                return Err(FlushError::SemaphoreWaitPending);
+            }
+            self.wait_reserved.store(true, Ordering::SeqCst);
+        }
+
+        let mut sem = SubmitSemaphoresWaitBuilder::new();
+        sem.add_wait_semaphore(&self.semaphore);
+        Ok(SubmitAnyBuilder::SemaphoresWait(sem))
+    }
+
+    #[inline]
+    fn flush(&self) -> Result<(), FlushError> {
+        // The wait operation is submitted along with the next submission.
+        Ok(())
+    }
+
+    #[inline]
+    unsafe fn signal_finished(&self) {
+        self.finished.store(true, Ordering::SeqCst);
+        if self.wait_reserved.swap(false, Ordering::SeqCst) {
+            self.semaphore.release_wait();
+        }
+    }
+
+    #[inline]
+    fn queue_change_allowed(&self) -> bool {
+        true
+    }
+
+    #[inline]
+    fn queue(&self) -> Option<Arc<Queue>> {
+        None
+    }
+
+    #[inline]
+    fn check_buffer_access(
+        &self, buffer: &BufferAccess, exclusive: bool, queue: &Queue)
+        -> Result<Option<(PipelineStages, AccessFlagBits)>, AccessCheckError> {
+        Err(AccessCheckError::Unknown)
+    }
+
+    #[inline]
+    fn check_image_access(&self, image: &ImageAccess, layout: ImageLayout, exclusive: bool,
+                          queue: &Queue)
+                          -> Result<Option<(PipelineStages, AccessFlagBits)>, AccessCheckError> {
+        Err(AccessCheckError::Unknown)
+    }
+}
+
+unsafe impl DeviceOwned for ExternalSemaphoreWaitFuture {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        self.semaphore.device()
+    }
+}
+
+impl Drop for ExternalSemaphoreWaitFuture {
+    fn drop(&mut self) {
+        if !*self.finished.get_mut() && *self.wait_reserved.get_mut() {
+            // The wait may have been submitted as part of a submission whose end we don't know
+            // about. Since we have no queue to wait upon, we wait for the whole device instead.
+            unsafe {
+                self.semaphore.device().wait().unwrap();
+            }
+            self.semaphore.release_wait();
+        }
+    }
+}
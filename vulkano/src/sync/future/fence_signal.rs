@@ -21,6 +21,8 @@ use device::DeviceOwned;
 use device::Queue;
 use image::ImageAccess;
 use image::ImageLayout;
+use instrumentation::DeferNotifications;
+use instrumentation;
 use sync::AccessCheckError;
 use sync::AccessFlagBits;
 use sync::Fence;
@@ -28,6 +30,8 @@ use sync::FlushError;
 use sync::GpuFuture;
 use sync::PipelineStages;
 
+use VulkanObject;
+
 /// Builds a new fence signal future.
 #[inline]
 pub fn then_signal_fence<F>(future: F, behavior: FenceSignalFutureBehavior) -> FenceSignalFuture<F>
@@ -135,6 +139,9 @@ impl<F> FenceSignalFuture<F>
     /// If the wait is successful, this function also cleans any resource locked by previous
     /// submissions.
     pub fn wait(&self, timeout: Option<Duration>) -> Result<(), FlushError> {
+        // Any notification sent to the observer while the state is locked is delivered after it
+        // has been unlocked.
+        let _defer = DeferNotifications::new(&self.device);
         let mut state = self.state.lock().unwrap();
 
         self.flush_impl(&mut state)?;
@@ -160,13 +167,15 @@ impl<F> FenceSignalFuture<F>
     // This is an external function so that we can also call it from an `Arc<FenceSignalFuture>`.
     #[inline]
     fn cleanup_finished_impl(&self) {
+        let _defer = DeferNotifications::new(&self.device);
         let mut state = self.state.lock().unwrap();
 
-        match *state {
+        let signaled_fence = match *state {
             FenceSignalFutureState::Flushed(ref mut prev, ref fence) => {
                 match fence.wait(Some(Duration::from_secs(0))) {
                     Ok(()) => unsafe {
-                        prev.signal_finished()
+                        prev.signal_finished();
+                        fence.internal_object()
                     },
                     Err(_) => {
                         prev.cleanup_finished();
@@ -188,6 +197,11 @@ impl<F> FenceSignalFuture<F>
         // This code can only be reached if we're already flushed and waiting on the fence
         // succeeded.
         *state = FenceSignalFutureState::Cleaned;
+        drop(state);
+
+        if let Some(observer) = self.device.submission_observer() {
+            instrumentation::notify(move || observer.fence_signaled(signaled_fence));
+        }
     }
 
     // Implementation of `flush`. You must lock the state and pass the mutex guard here.
@@ -320,6 +334,7 @@ unsafe impl<F> GpuFuture for FenceSignalFuture<F>
 
     #[inline]
     unsafe fn build_submission(&self) -> Result<SubmitAnyBuilder, FlushError> {
+        let _defer = DeferNotifications::new(&self.device);
         let mut state = self.state.lock().unwrap();
         self.flush_impl(&mut state)?;
 
@@ -343,6 +358,7 @@ unsafe impl<F> GpuFuture for FenceSignalFuture<F>
 
     #[inline]
     fn flush(&self) -> Result<(), FlushError> {
+        let _defer = DeferNotifications::new(&self.device);
         let mut state = self.state.lock().unwrap();
         self.flush_impl(&mut state)
     }
@@ -425,6 +441,7 @@ impl<F> Drop for FenceSignalFuture<F>
     where F: GpuFuture
 {
     fn drop(&mut self) {
+        let _defer = DeferNotifications::new(&self.device);
         let mut state = self.state.lock().unwrap();
 
         // We ignore any possible error while submitting for now. Problems are handled below.
@@ -30,16 +30,27 @@ use swapchain::Swapchain;
 use sync::AccessFlagBits;
 use sync::FenceWaitError;
 use sync::PipelineStages;
+use sync::Semaphore;
 
+pub use self::external_semaphore_signal::ExternalSemaphoreSignalFuture;
+pub use self::external_semaphore_wait::{ExternalSemaphoreWaitFuture, wait_external_semaphore};
 pub use self::fence_signal::{FenceSignalFuture, FenceSignalFutureBehavior};
 pub use self::join::JoinFuture;
 pub use self::now::{NowFuture, now};
 pub use self::semaphore_signal::SemaphoreSignalFuture;
+pub use self::sparse_bind::{SparseBindError, SparseBindFuture, SparseImageBind,
+                            bind_sparse_image_memory, bind_sparse_image_mip_tail,
+                            sparse_image_mip_tail_size};
+pub use self::timeline_submit::{SubmitTimelineError, TimelineSubmitFuture, submit_timeline};
 
 mod now;
+mod external_semaphore_signal;
+mod external_semaphore_wait;
 mod fence_signal;
 mod join;
 mod semaphore_signal;
+mod sparse_bind;
+mod timeline_submit;
 
 /// Represents an event that will happen on the GPU in the future.
 ///
@@ -202,6 +213,32 @@ pub unsafe trait GpuFuture: DeviceOwned {
         Ok(f)
     }
 
+    /// Signals a semaphore owned by the caller after this future. Returns another future that
+    /// represents the signal.
+    ///
+    /// This is meant to be used when the semaphore is waited upon by Vulkan code that isn't
+    /// managed by vulkano, for example a hand-written module that uses the same device. Contrary
+    /// to `then_signal_semaphore`, the operations that are chained after the returned future
+    /// don't wait on the semaphore.
+    ///
+    /// The semaphore is a binary semaphore, so every signal operation must be followed by
+    /// exactly one wait operation before it is signaled again. Vulkano can't know when code
+    /// outside of vulkano waits on the semaphore, so this is the responsibility of the caller.
+    /// However flushing returns `FlushError::SemaphoreWaitPending` if a wait operation submitted
+    /// through `wait_external_semaphore` hasn't finished executing yet.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the semaphore doesn't belong to the same device as this future.
+    ///
+    #[inline]
+    fn then_signal_external_semaphore(self, semaphore: &Arc<Semaphore>)
+                                      -> ExternalSemaphoreSignalFuture<Self>
+        where Self: Sized
+    {
+        external_semaphore_signal::then_signal_external_semaphore(self, semaphore.clone())
+    }
+
     /// Signals a fence after this future. Returns another future that represents the signal.
     ///
     /// > **Note**: More often than not you want to immediately flush the future after calling this
@@ -410,6 +447,20 @@ pub enum FlushError {
 
     /// The flush operation needed to block, but the timeout has elapsed.
     Timeout,
+
+    /// A semaphore is still being waited upon by a previous submission and can't be used yet.
+    SemaphoreWaitPending,
+
+    /// Attempted to present a swapchain image that isn't currently acquired, either because it
+    /// was never acquired or because it has already been presented since.
+    SwapchainImageNotAcquired,
+
+    /// Attempted to present a swapchain image with a queue whose family can't present on the
+    /// surface of the swapchain.
+    PresentNotSupported {
+        /// Id of the queue family.
+        queue_family: u32,
+    },
 }
 
 impl error::Error for FlushError {
@@ -423,6 +474,12 @@ impl error::Error for FlushError {
             FlushError::OutOfDate => "the swapchain needs to be recreated",
             FlushError::Timeout => "the flush operation needed to block, but the timeout has \
                                     elapsed",
+            FlushError::SemaphoreWaitPending => "a semaphore is still being waited upon by a \
+                                                 previous submission",
+            FlushError::SwapchainImageNotAcquired => "attempted to present a swapchain image \
+                                                      that isn't acquired",
+            FlushError::PresentNotSupported { .. } => "the queue family can't present on the \
+                                                       surface of the swapchain",
         }
     }
 
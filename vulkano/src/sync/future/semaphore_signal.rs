@@ -21,6 +21,7 @@ use device::DeviceOwned;
 use device::Queue;
 use image::ImageAccess;
 use image::ImageLayout;
+use instrumentation::DeferNotifications;
 use sync::AccessCheckError;
 use sync::AccessFlagBits;
 use sync::FlushError;
@@ -80,6 +81,7 @@ unsafe impl<F> GpuFuture for SemaphoreSignalFuture<F>
 
     fn flush(&self) -> Result<(), FlushError> {
         unsafe {
+            let _defer = DeferNotifications::new(self.device());
             let mut wait_submitted = self.wait_submitted.lock().unwrap();
 
             if *wait_submitted {
@@ -0,0 +1,578 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::error;
+use std::fmt;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+
+use OomError;
+use VulkanObject;
+use buffer::BufferAccess;
+use command_buffer::submit::SubmitAnyBuilder;
+use command_buffer::submit::SubmitBindSparseBatchBuilder;
+use command_buffer::submit::SubmitBindSparseBuilder;
+use command_buffer::submit::SubmitBindSparseImageBindBuilder;
+use command_buffer::submit::SubmitBindSparseImageOpaqueBindBuilder;
+use command_buffer::submit::SubmitCommandBufferBuilder;
+use command_buffer::submit::SubmitSemaphoresWaitBuilder;
+use device::Device;
+use device::DeviceOwned;
+use device::Queue;
+use image::ImageAccess;
+use image::ImageLayout;
+use image::sys::SparseImageMemoryRequirements;
+use instrumentation::DeferNotifications;
+use memory::DeviceMemory;
+use sync::AccessCheckError;
+use sync::AccessFlagBits;
+use sync::FlushError;
+use sync::GpuFuture;
+use sync::PipelineStages;
+use sync::Semaphore;
+
+/// Describes the memory to bind to a region of a mipmap level of a sparse image.
+#[derive(Debug, Clone)]
+pub struct SparseImageBind {
+    /// Mipmap level of the region. Must not be part of the mip tail.
+    pub mip_level: u32,
+    /// Array layer of the region.
+    pub array_layer: u32,
+    /// Offset in texels of the region. Must be a multiple of the sparse image granularity.
+    pub offset: [u32; 3],
+    /// Size in texels of the region. Must be a multiple of the sparse image granularity, unless
+    /// the region reaches the edge of the mipmap level.
+    pub extent: [u32; 3],
+    /// Memory to bind and offset in bytes within this memory, or `None` to unbind the region.
+    ///
+    /// Tiles are laid out contiguously in the memory, row by row.
+    pub memory: Option<(Arc<DeviceMemory>, usize)>,
+}
+
+/// Binds or unbinds memory to regions of a sparse image, after `previous`.
+///
+/// See the documentation of `Queue::bind_sparse_image_memory`.
+pub fn bind_sparse_image_memory<F, I>(previous: F, queue: Arc<Queue>, image: I,
+                                      binds: Vec<SparseImageBind>)
+                                      -> Result<SparseBindFuture<F, I>, SparseBindError>
+    where F: GpuFuture,
+          I: ImageAccess
+{
+    let requirements = check_sparse_image(&queue, &image)?;
+
+    {
+        let inner = image.inner().image;
+        let mem_reqs = inner.memory_requirements();
+
+        for bind in binds.iter() {
+            let level = match inner.dimensions().mip_level_dimensions(bind.mip_level) {
+                Some(d) if bind.mip_level < inner.mipmap_levels() => d,
+                _ => return Err(SparseBindError::RegionOutOfRange),
+            };
+            if bind.mip_level >= requirements.mip_tail_first_lod ||
+                bind.array_layer >= level.array_layers()
+            {
+                return Err(SparseBindError::RegionOutOfRange);
+            }
+
+            let level_extent = level.width_height_depth();
+            let mut num_tiles = 1;
+            for i in 0 .. 3 {
+                let granularity = requirements.image_granularity[i];
+                let end = bind.offset[i] as u64 + bind.extent[i] as u64;
+                if bind.extent[i] == 0 || end > level_extent[i] as u64 {
+                    return Err(SparseBindError::RegionOutOfRange);
+                }
+                if bind.offset[i] % granularity != 0 ||
+                    (bind.extent[i] % granularity != 0 && end != level_extent[i] as u64)
+                {
+                    return Err(SparseBindError::RegionNotAligned);
+                }
+                num_tiles *= (bind.extent[i] + granularity - 1) / granularity;
+            }
+
+            if let Some((ref memory, offset)) = bind.memory {
+                assert_eq!(memory.device().internal_object(),
+                           queue.device().internal_object());
+                if mem_reqs.memory_type_bits & (1 << memory.memory_type().id()) == 0 {
+                    return Err(SparseBindError::MemoryTypeNotAllowed);
+                }
+                if offset % mem_reqs.alignment != 0 {
+                    return Err(SparseBindError::RegionNotAligned);
+                }
+                let required = num_tiles as usize * mem_reqs.alignment;
+                if offset > memory.size() || required > memory.size() - offset {
+                    return Err(SparseBindError::MemoryTooSmall {
+                                   required: required,
+                                   available: memory.size().saturating_sub(offset),
+                               });
+                }
+            }
+        }
+    }
+
+    SparseBindFuture::new(previous, queue, image, binds, Vec::new())
+}
+
+/// Binds memory to the mip tail of a sparse image, after `previous`.
+///
+/// See the documentation of `Queue::bind_sparse_image_mip_tail`.
+pub fn bind_sparse_image_mip_tail<F, I>(previous: F, queue: Arc<Queue>, image: I,
+                                        memory: Arc<DeviceMemory>, memory_offset: usize)
+                                        -> Result<SparseBindFuture<F, I>, SparseBindError>
+    where F: GpuFuture,
+          I: ImageAccess
+{
+    check_sparse_image(&queue, &image)?;
+    assert_eq!(memory.device().internal_object(),
+               queue.device().internal_object());
+
+    let (required, opaque_binds) = {
+        let inner = image.inner().image;
+        let mem_reqs = inner.memory_requirements();
+
+        if mem_reqs.memory_type_bits & (1 << memory.memory_type().id()) == 0 {
+            return Err(SparseBindError::MemoryTypeNotAllowed);
+        }
+        if memory_offset % mem_reqs.alignment != 0 {
+            return Err(SparseBindError::RegionNotAligned);
+        }
+
+        let mut opaque_binds = Vec::new();
+        let mut next_offset = memory_offset;
+
+        for tail in inner.sparse_mip_tails() {
+            for region in tail.regions {
+                opaque_binds.push(OpaqueBind {
+                                      offset: region.offset,
+                                      size: region.size,
+                                      memory: memory.clone(),
+                                      memory_offset: next_offset,
+                                      metadata: tail.metadata,
+                                  });
+                next_offset += align(region.size, mem_reqs.alignment);
+            }
+        }
+
+        (next_offset - memory_offset, opaque_binds)
+    };
+
+    if memory_offset > memory.size() || required > memory.size() - memory_offset {
+        return Err(SparseBindError::MemoryTooSmall {
+                       required: required,
+                       available: memory.size().saturating_sub(memory_offset),
+                   });
+    }
+
+    SparseBindFuture::new(previous, queue, image, Vec::new(), opaque_binds)
+}
+
+/// Returns the number of bytes of memory that `bind_sparse_image_mip_tail` needs for `image`.
+///
+/// Returns 0 if the image isn't sparse or doesn't have a mip tail.
+pub fn sparse_image_mip_tail_size<I>(image: &I) -> usize
+    where I: ImageAccess
+{
+    let inner = image.inner().image;
+    let alignment = inner.memory_requirements().alignment;
+
+    inner
+        .sparse_mip_tails()
+        .iter()
+        .map(|tail| tail.memory_size(alignment))
+        .sum()
+}
+
+// Checks that `image` can be bound with `queue`, and returns the sparse requirements of its main
+// aspect.
+fn check_sparse_image<I>(queue: &Queue, image: &I)
+                         -> Result<SparseImageMemoryRequirements, SparseBindError>
+    where I: ImageAccess
+{
+    if !queue.family().supports_sparse_binding() {
+        return Err(SparseBindError::QueueFamilyNotSupported);
+    }
+
+    let inner = image.inner().image;
+    assert_eq!(inner.device().internal_object(),
+               queue.device().internal_object());
+
+    inner
+        .sparse_memory_requirements()
+        .into_iter()
+        .find(|req| !req.metadata)
+        .ok_or(SparseBindError::ImageNotSparse)
+}
+
+// Rounds `size` up to a multiple of `alignment`.
+#[inline]
+fn align(size: usize, alignment: usize) -> usize {
+    if alignment <= 1 {
+        return size;
+    }
+
+    (size + alignment - 1) / alignment * alignment
+}
+
+// Bind operation on the opaque memory of an image, used for the mip tail.
+struct OpaqueBind {
+    offset: usize,
+    size: usize,
+    memory: Arc<DeviceMemory>,
+    memory_offset: usize,
+    metadata: bool,
+}
+
+/// Represents memory being bound to a sparse image after a previous event.
+///
+/// The bind operation is submitted when the future is flushed, after the submission of the
+/// previous future, and signals a semaphore that subsequent submissions wait upon. In other words,
+/// the bind operation sees the effects of the previous future, and command buffers executed after
+/// this future see the new bindings.
+#[must_use = "Dropping this object will immediately block the thread until the GPU has finished \
+              processing the submission"]
+pub struct SparseBindFuture<F, I>
+    where F: GpuFuture,
+          I: ImageAccess
+{
+    previous: F,
+    queue: Arc<Queue>,
+    image: I,
+    binds: Vec<SparseImageBind>,
+    opaque_binds: Vec<OpaqueBind>,
+    // Signaled by the submission of the previous future, if it can't be waited upon directly.
+    wait_semaphore: Semaphore,
+    // Signaled by the bind operation.
+    semaphore: Semaphore,
+    // If flush is called multiple times, we want to block so that only one flushing is executed.
+    // Therefore we use a `Mutex` and not an atomic.
+    state: Mutex<SparseBindState>,
+    finished: AtomicBool,
+}
+
+// Progress of the submission of a `SparseBindFuture`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum SparseBindState {
+    // Nothing has been submitted yet.
+    Pending,
+    // The previous future has been submitted and signals `wait_semaphore`, but submitting the bind
+    // operation failed.
+    PreviousSubmitted,
+    // The bind operation has been submitted.
+    Submitted,
+}
+
+impl<F, I> SparseBindFuture<F, I>
+    where F: GpuFuture,
+          I: ImageAccess
+{
+    fn new(previous: F, queue: Arc<Queue>, image: I, binds: Vec<SparseImageBind>,
+           opaque_binds: Vec<OpaqueBind>)
+           -> Result<SparseBindFuture<F, I>, SparseBindError> {
+        let wait_semaphore = Semaphore::new(queue.device().clone())?;
+        let semaphore = Semaphore::new(queue.device().clone())?;
+
+        Ok(SparseBindFuture {
+               previous: previous,
+               queue: queue,
+               image: image,
+               binds: binds,
+               opaque_binds: opaque_binds,
+               wait_semaphore: wait_semaphore,
+               semaphore: semaphore,
+               state: Mutex::new(SparseBindState::Pending),
+               finished: AtomicBool::new(false),
+           })
+    }
+
+    /// Returns the image whose memory is bound.
+    #[inline]
+    pub fn image(&self) -> &I {
+        &self.image
+    }
+
+    // Returns the queue of the previous future, or the queue of the bind operation if the
+    // previous future doesn't have one.
+    #[inline]
+    fn previous_queue(&self) -> Arc<Queue> {
+        match self.previous.queue() {
+            Some(queue) => queue,
+            None => self.queue.clone(),
+        }
+    }
+}
+
+unsafe impl<F, I> GpuFuture for SparseBindFuture<F, I>
+    where F: GpuFuture,
+          I: ImageAccess
+{
+    #[inline]
+    fn cleanup_finished(&mut self) {
+        self.previous.cleanup_finished();
+    }
+
+    #[inline]
+    unsafe fn build_submission(&self) -> Result<SubmitAnyBuilder, FlushError> {
+        // The bind operation must always be submitted before the waiting part.
+        self.flush()?;
+
+        let mut sem = SubmitSemaphoresWaitBuilder::new();
+        sem.add_wait_semaphore(&self.semaphore);
+        Ok(SubmitAnyBuilder::SemaphoresWait(sem))
+    }
+
+    fn flush(&self) -> Result<(), FlushError> {
+        unsafe {
+            let _defer = DeferNotifications::new(self.device());
+            let mut state = self.state.lock().unwrap();
+
+            let mut builder = SubmitBindSparseBuilder::new();
+            let current_state = *state;
+            let mut batch = match current_state {
+                SparseBindState::Submitted => return Ok(()),
+                SparseBindState::PreviousSubmitted => {
+                    let mut batch = SubmitBindSparseBatchBuilder::new();
+                    batch.add_wait_semaphore(&self.wait_semaphore);
+                    batch
+                },
+                SparseBindState::Pending => {
+                    match self.previous.build_submission()? {
+                        SubmitAnyBuilder::Empty => SubmitBindSparseBatchBuilder::new(),
+                        SubmitAnyBuilder::SemaphoresWait(sem) => sem.into(),
+                        SubmitAnyBuilder::CommandBuffer(mut cb_builder) => {
+                            // Bind operations aren't ordered with the other submissions of the
+                            // queue, so the command buffers signal a semaphore that the bind
+                            // operation waits upon.
+                            debug_assert_eq!(cb_builder.num_signal_semaphores(), 0);
+                            cb_builder.add_signal_semaphore(&self.wait_semaphore);
+                            cb_builder.submit(&self.previous_queue())?;
+                            *state = SparseBindState::PreviousSubmitted;
+
+                            let mut batch = SubmitBindSparseBatchBuilder::new();
+                            batch.add_wait_semaphore(&self.wait_semaphore);
+                            batch
+                        },
+                        SubmitAnyBuilder::QueuePresent(present) => {
+                            let queue = self.previous_queue();
+                            present.submit(&queue)?;
+                            let mut signal = SubmitCommandBufferBuilder::new();
+                            signal.add_signal_semaphore(&self.wait_semaphore);
+                            signal.submit(&queue)?;
+                            *state = SparseBindState::PreviousSubmitted;
+
+                            let mut batch = SubmitBindSparseBatchBuilder::new();
+                            batch.add_wait_semaphore(&self.wait_semaphore);
+                            batch
+                        },
+                        SubmitAnyBuilder::BindSparse(previous_binds) => {
+                            // Batches start in order, so the previous bind operations are submitted
+                            // in the same call and the semaphores they wait upon apply to ours.
+                            builder = previous_binds;
+                            SubmitBindSparseBatchBuilder::new()
+                        },
+                    }
+                },
+            };
+
+            let image = self.image.inner().image;
+
+            if !self.binds.is_empty() {
+                let mut image_binds = SubmitBindSparseImageBindBuilder::new(image);
+                for bind in self.binds.iter() {
+                    let offset = [bind.offset[0] as i32,
+                                  bind.offset[1] as i32,
+                                  bind.offset[2] as i32];
+                    match bind.memory {
+                        Some((ref memory, memory_offset)) => {
+                            image_binds.add_bind(bind.mip_level,
+                                                 bind.array_layer,
+                                                 offset,
+                                                 bind.extent,
+                                                 memory,
+                                                 memory_offset)
+                        },
+                        None => {
+                            image_binds.add_unbind(bind.mip_level,
+                                                   bind.array_layer,
+                                                   offset,
+                                                   bind.extent)
+                        },
+                    }
+                }
+                batch.add_image(image_binds);
+            }
+
+            if !self.opaque_binds.is_empty() {
+                let mut opaque_binds = SubmitBindSparseImageOpaqueBindBuilder::new(image);
+                for bind in self.opaque_binds.iter() {
+                    opaque_binds.add_bind(bind.offset,
+                                          bind.size,
+                                          &bind.memory,
+                                          bind.memory_offset,
+                                          bind.metadata);
+                }
+                batch.add_image_opaque(opaque_binds);
+            }
+
+            batch.add_signal_semaphore(&self.semaphore);
+            builder.add(batch);
+            builder.submit(&self.queue)?;
+
+            // Only write `Submitted` here in order to try again next time if we failed to submit.
+            *state = SparseBindState::Submitted;
+            Ok(())
+        }
+    }
+
+    #[inline]
+    unsafe fn signal_finished(&self) {
+        debug_assert_eq!(*self.state.lock().unwrap(), SparseBindState::Submitted);
+        self.finished.store(true, Ordering::SeqCst);
+        self.previous.signal_finished();
+    }
+
+    #[inline]
+    fn queue_change_allowed(&self) -> bool {
+        true
+    }
+
+    #[inline]
+    fn queue(&self) -> Option<Arc<Queue>> {
+        Some(self.queue.clone())
+    }
+
+    #[inline]
+    fn check_buffer_access(
+        &self, buffer: &BufferAccess, exclusive: bool, queue: &Queue)
+        -> Result<Option<(PipelineStages, AccessFlagBits)>, AccessCheckError> {
+        self.previous
+            .check_buffer_access(buffer, exclusive, queue)
+            .map(|_| None)
+    }
+
+    #[inline]
+    fn check_image_access(&self, image: &ImageAccess, layout: ImageLayout, exclusive: bool,
+                          queue: &Queue)
+                          -> Result<Option<(PipelineStages, AccessFlagBits)>, AccessCheckError> {
+        self.previous
+            .check_image_access(image, layout, exclusive, queue)
+            .map(|_| None)
+    }
+}
+
+unsafe impl<F, I> DeviceOwned for SparseBindFuture<F, I>
+    where F: GpuFuture,
+          I: ImageAccess
+{
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        self.queue.device()
+    }
+}
+
+impl<F, I> Drop for SparseBindFuture<F, I>
+    where F: GpuFuture,
+          I: ImageAccess
+{
+    fn drop(&mut self) {
+        if *self.finished.get_mut() {
+            return;
+        }
+
+        // If the bind operation can't be submitted, there's nothing to wait for. Dropping the
+        // previous future then blocks until its own submission, if any, is finished.
+        if self.flush().is_err() {
+            return;
+        }
+
+        // Block until the queue finished. An error means that the device has been lost, in which
+        // case nothing is executing anymore.
+        let _ = self.queue.wait();
+        unsafe {
+            self.previous.signal_finished();
+        }
+    }
+}
+
+/// Error that can happen when binding memory to a sparse image.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SparseBindError {
+    /// The queue family of the queue doesn't support sparse binding operations.
+    QueueFamilyNotSupported,
+
+    /// The image wasn't created with sparse residency.
+    ImageNotSparse,
+
+    /// A region is outside of the image, or is part of the mip tail.
+    RegionOutOfRange,
+
+    /// A region or a memory offset isn't aligned to the sparse image granularity.
+    RegionNotAligned,
+
+    /// The type of the memory is not allowed by the image.
+    MemoryTypeNotAllowed,
+
+    /// The memory is too small for the region.
+    MemoryTooSmall {
+        /// Number of bytes needed from the memory offset.
+        required: usize,
+        /// Number of bytes available from the memory offset.
+        available: usize,
+    },
+
+    /// Not enough memory.
+    OomError(OomError),
+}
+
+impl error::Error for SparseBindError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            SparseBindError::QueueFamilyNotSupported => {
+                "the queue family of the queue doesn't support sparse binding operations"
+            },
+            SparseBindError::ImageNotSparse => "the image wasn't created with sparse residency",
+            SparseBindError::RegionOutOfRange => {
+                "a region is outside of the image, or is part of the mip tail"
+            },
+            SparseBindError::RegionNotAligned => {
+                "a region or a memory offset isn't aligned to the sparse image granularity"
+            },
+            SparseBindError::MemoryTypeNotAllowed => {
+                "the type of the memory is not allowed by the image"
+            },
+            SparseBindError::MemoryTooSmall { .. } => "the memory is too small for the region",
+            SparseBindError::OomError(_) => "not enough memory",
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            SparseBindError::OomError(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for SparseBindError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl From<OomError> for SparseBindError {
+    #[inline]
+    fn from(err: OomError) -> SparseBindError {
+        SparseBindError::OomError(err)
+    }
+}
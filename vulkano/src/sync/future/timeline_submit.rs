@@ -0,0 +1,346 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use smallvec::SmallVec;
+use std::error;
+use std::fmt;
+use std::sync::Arc;
+use std::sync::MutexGuard;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+
+use OomError;
+use VulkanObject;
+use buffer::BufferAccess;
+use command_buffer::CommandBuffer;
+use command_buffer::CommandBufferExecError;
+use command_buffer::submit::SubmitAnyBuilder;
+use command_buffer::submit::SubmitCommandBuffer2Builder;
+use command_buffer::submit::SubmitCommandBufferError;
+use device::Device;
+use device::DeviceOwned;
+use device::Queue;
+use image::ImageAccess;
+use image::ImageLayout;
+use sync::AccessCheckError;
+use sync::AccessFlagBits;
+use sync::FlushError;
+use sync::GpuFuture;
+use sync::PipelineStages;
+use sync::TimelineSemaphore;
+use sync::now;
+
+/// Submits a command buffer that waits on and signals timeline semaphores.
+///
+/// See the documentation of `Queue::submit_timeline`.
+pub fn submit_timeline<Cb>(queue: Arc<Queue>, command_buffer: Cb,
+                           wait: &[(Arc<TimelineSemaphore>, u64, PipelineStages)],
+                           signal: &[(Arc<TimelineSemaphore>, u64)])
+                           -> Result<TimelineSubmitFuture<Cb>, SubmitTimelineError>
+    where Cb: CommandBuffer + 'static
+{
+    let device = queue.device().clone();
+
+    if !device.effective_extensions().khr_synchronization2 {
+        return Err(SubmitTimelineError::Synchronization2ExtensionNotEnabled);
+    }
+
+    assert_eq!(command_buffer.device().internal_object(),
+               device.internal_object());
+    for &(ref semaphore, _, _) in wait {
+        assert_eq!(semaphore.device().internal_object(), device.internal_object());
+    }
+    for &(ref semaphore, _) in signal {
+        assert_eq!(semaphore.device().internal_object(), device.internal_object());
+    }
+
+    // Signal operations must strictly increase the counter. We check this before doing anything
+    // else, so that an error doesn't leave the command buffer in a locked state. The pending
+    // values stay locked until the submission is done, so that no other thread can signal the
+    // semaphores in the meantime. They are locked in a consistent order to avoid deadlocks, and
+    // the sort is stable so that a semaphore signaled several times keeps its order.
+    let mut signal_order = signal.iter().collect::<SmallVec<[_; 4]>>();
+    signal_order.sort_by_key(|&&(ref semaphore, _)| semaphore.internal_object());
+
+    // Each element is the locked pending value of a semaphore, and the value it will be set to.
+    let mut pending: SmallVec<[(MutexGuard<u64>, u64); 4]> = SmallVec::new();
+    for (num, &&(ref semaphore, value)) in signal_order.iter().enumerate() {
+        let already_locked = num != 0 &&
+            signal_order[num - 1].0.internal_object() == semaphore.internal_object();
+        if !already_locked {
+            let guard = semaphore.lock_pending_value();
+            let current = *guard;
+            pending.push((guard, current));
+        }
+
+        let entry = pending.last_mut().unwrap();
+        if value <= entry.1 {
+            return Err(SubmitTimelineError::SignalValueNotIncreasing {
+                           current: entry.1,
+                           requested: value,
+                       });
+        }
+        entry.1 = value;
+    }
+
+    command_buffer.prepare_submit(&now(device), &queue)?;
+
+    unsafe {
+        let mut builder = SubmitCommandBuffer2Builder::new();
+        for &(ref semaphore, value, stages) in wait {
+            builder.add_wait_semaphore(semaphore, value, stages);
+        }
+        builder.add_command_buffer(command_buffer.inner());
+        for &(ref semaphore, value) in signal {
+            builder.add_signal_semaphore(semaphore, value);
+        }
+        if let Err(err) = builder.submit(&queue) {
+            command_buffer.unlock();
+            return Err(err.into());
+        }
+    }
+
+    for &mut (ref mut guard, value) in pending.iter_mut() {
+        **guard = value;
+    }
+    drop(pending);
+
+    let semaphores = wait.iter()
+        .map(|&(ref s, _, _)| s.clone())
+        .chain(signal.iter().map(|&(ref s, _)| s.clone()))
+        .collect();
+
+    Ok(TimelineSubmitFuture {
+           command_buffer: command_buffer,
+           queue: queue,
+           semaphores: semaphores,
+           finished: AtomicBool::new(false),
+       })
+}
+
+/// Represents the execution of a command buffer submitted with timeline semaphores.
+///
+/// The submission has already been sent to the queue when this object is created. It can be
+/// chained with other futures, for example in order to signal a fence.
+#[must_use = "Dropping this object will immediately block the thread until the GPU has finished \
+              processing the submission"]
+pub struct TimelineSubmitFuture<Cb>
+    where Cb: CommandBuffer
+{
+    command_buffer: Cb,
+    queue: Arc<Queue>,
+    // The semaphores used by the submission, kept alive until it is finished.
+    semaphores: SmallVec<[Arc<TimelineSemaphore>; 4]>,
+    finished: AtomicBool,
+}
+
+unsafe impl<Cb> GpuFuture for TimelineSubmitFuture<Cb>
+    where Cb: CommandBuffer
+{
+    #[inline]
+    fn cleanup_finished(&mut self) {
+    }
+
+    #[inline]
+    unsafe fn build_submission(&self) -> Result<SubmitAnyBuilder, FlushError> {
+        Ok(SubmitAnyBuilder::Empty)
+    }
+
+    #[inline]
+    fn flush(&self) -> Result<(), FlushError> {
+        Ok(())
+    }
+
+    #[inline]
+    unsafe fn signal_finished(&self) {
+        if !self.finished.swap(true, Ordering::SeqCst) {
+            self.command_buffer.unlock();
+        }
+    }
+
+    #[inline]
+    fn queue_change_allowed(&self) -> bool {
+        false
+    }
+
+    #[inline]
+    fn queue(&self) -> Option<Arc<Queue>> {
+        Some(self.queue.clone())
+    }
+
+    #[inline]
+    fn check_buffer_access(
+        &self, buffer: &BufferAccess, exclusive: bool, queue: &Queue)
+        -> Result<Option<(PipelineStages, AccessFlagBits)>, AccessCheckError> {
+        self.command_buffer
+            .check_buffer_access(buffer, exclusive, queue)
+    }
+
+    #[inline]
+    fn check_image_access(&self, image: &ImageAccess, layout: ImageLayout, exclusive: bool,
+                          queue: &Queue)
+                          -> Result<Option<(PipelineStages, AccessFlagBits)>, AccessCheckError> {
+        self.command_buffer
+            .check_image_access(image, layout, exclusive, queue)
+    }
+}
+
+unsafe impl<Cb> DeviceOwned for TimelineSubmitFuture<Cb>
+    where Cb: CommandBuffer
+{
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        self.command_buffer.device()
+    }
+}
+
+impl<Cb> Drop for TimelineSubmitFuture<Cb>
+    where Cb: CommandBuffer
+{
+    fn drop(&mut self) {
+        if !*self.finished.get_mut() {
+            // Block until the queue finished.
+            self.queue.wait().unwrap();
+            unsafe {
+                self.command_buffer.unlock();
+            }
+        }
+    }
+}
+
+/// Error that can happen when submitting a command buffer with timeline semaphores.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SubmitTimelineError {
+    /// The `VK_KHR_synchronization2` extension was not enabled on the device and the device
+    /// doesn't support Vulkan 1.3.
+    Synchronization2ExtensionNotEnabled,
+
+    /// One of the signal values is not superior to the value that the semaphore already has or
+    /// is going to have.
+    SignalValueNotIncreasing {
+        /// The value of the semaphore once all pending signal operations have been executed.
+        current: u64,
+        /// The value that was requested.
+        requested: u64,
+    },
+
+    /// The command buffer can't be executed.
+    CommandBufferExecError(CommandBufferExecError),
+
+    /// Not enough memory.
+    OomError(OomError),
+
+    /// The connection to the device has been lost.
+    DeviceLost,
+}
+
+impl error::Error for SubmitTimelineError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            SubmitTimelineError::Synchronization2ExtensionNotEnabled => {
+                "the `VK_KHR_synchronization2` extension was not enabled on the device"
+            },
+            SubmitTimelineError::SignalValueNotIncreasing { .. } => {
+                "one of the signal values is not superior to the value of the semaphore"
+            },
+            SubmitTimelineError::CommandBufferExecError(_) => {
+                "the command buffer can't be executed"
+            },
+            SubmitTimelineError::OomError(_) => "not enough memory",
+            SubmitTimelineError::DeviceLost => "the connection to the device has been lost",
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            SubmitTimelineError::CommandBufferExecError(ref err) => Some(err),
+            SubmitTimelineError::OomError(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for SubmitTimelineError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl From<CommandBufferExecError> for SubmitTimelineError {
+    #[inline]
+    fn from(err: CommandBufferExecError) -> SubmitTimelineError {
+        SubmitTimelineError::CommandBufferExecError(err)
+    }
+}
+
+impl From<SubmitCommandBufferError> for SubmitTimelineError {
+    #[inline]
+    fn from(err: SubmitCommandBufferError) -> SubmitTimelineError {
+        match err {
+            SubmitCommandBufferError::OomError(err) => SubmitTimelineError::OomError(err),
+            SubmitCommandBufferError::DeviceLost => SubmitTimelineError::DeviceLost,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use command_buffer::AutoCommandBufferBuilder;
+    use sync::TimelineSemaphore;
+    use super::*;
+
+    #[test]
+    fn signal_value_not_increasing() {
+        let (device, queue) =
+            gfx_dev_and_queue!(extensions: [khr_timeline_semaphore, khr_synchronization2]);
+        let a = Arc::new(TimelineSemaphore::new(device.clone(), 5).unwrap());
+        let b = Arc::new(TimelineSemaphore::new(device.clone(), 0).unwrap());
+        let cb = || {
+            AutoCommandBufferBuilder::new(device.clone(), queue.family())
+                .unwrap()
+                .build()
+                .unwrap()
+        };
+
+        // A failed submission doesn't change any pending value, including the ones of the
+        // semaphores that were valid.
+        match submit_timeline(queue.clone(), cb(), &[], &[(b.clone(), 3), (a.clone(), 5)]) {
+            Err(SubmitTimelineError::SignalValueNotIncreasing {
+                    current: 5,
+                    requested: 5,
+                }) => (),
+            _ => panic!(),
+        }
+        assert_eq!(a.pending_value(), 5);
+        assert_eq!(b.pending_value(), 0);
+
+        // Signaling the same semaphore twice requires increasing values.
+        match submit_timeline(queue.clone(), cb(), &[], &[(b.clone(), 3), (b.clone(), 2)]) {
+            Err(SubmitTimelineError::SignalValueNotIncreasing {
+                    current: 3,
+                    requested: 2,
+                }) => (),
+            _ => panic!(),
+        }
+        assert_eq!(b.pending_value(), 0);
+
+        let future = submit_timeline(queue.clone(), cb(), &[], &[(b.clone(), 3), (a.clone(), 6)])
+            .unwrap();
+        assert_eq!(a.pending_value(), 6);
+        assert_eq!(b.pending_value(), 3);
+        a.wait(6, None).unwrap();
+        b.wait(3, None).unwrap();
+        drop(future);
+    }
+}
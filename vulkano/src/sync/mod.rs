@@ -111,22 +111,39 @@ pub use self::fence::Fence;
 pub use self::fence::FenceWaitError;
 pub use self::future::AccessCheckError;
 pub use self::future::AccessError;
+pub use self::future::ExternalSemaphoreSignalFuture;
+pub use self::future::ExternalSemaphoreWaitFuture;
 pub use self::future::FenceSignalFuture;
 pub use self::future::FlushError;
 pub use self::future::GpuFuture;
 pub use self::future::JoinFuture;
 pub use self::future::NowFuture;
 pub use self::future::SemaphoreSignalFuture;
+pub use self::future::SparseBindError;
+pub use self::future::SparseBindFuture;
+pub use self::future::SparseImageBind;
+pub use self::future::SubmitTimelineError;
+pub use self::future::TimelineSubmitFuture;
+pub use self::future::bind_sparse_image_memory;
+pub use self::future::bind_sparse_image_mip_tail;
 pub use self::future::now;
+pub use self::future::sparse_image_mip_tail_size;
+pub use self::future::submit_timeline;
+pub use self::future::wait_external_semaphore;
 pub use self::pipeline::AccessFlagBits;
 pub use self::pipeline::PipelineStages;
 pub use self::semaphore::Semaphore;
+pub use self::timeline_semaphore::TimelineSemaphore;
+pub use self::timeline_semaphore::TimelineSemaphoreCreationError;
+pub use self::timeline_semaphore::TimelineSemaphoreSignalError;
+pub use self::timeline_semaphore::TimelineSemaphoreWaitError;
 
 mod event;
 mod fence;
 mod future;
 mod pipeline;
 mod semaphore;
+mod timeline_semaphore;
 
 /// Declares in which queue(s) a resource can be used.
 ///
@@ -29,6 +29,15 @@ macro_rules! pipeline_stages {
                     )+
                 }
             }
+
+            // Returns the names of the stages whose bit is set in `bits`.
+            pub(crate) fn names_from_vk(bits: vk::PipelineStageFlagBits) -> Vec<&'static str> {
+                let mut names = Vec::new();
+                $(
+                    if (bits & $val) != 0 { names.push(stringify!($elem)); }
+                )+
+                names
+            }
         }
 
         impl ops::BitOr for PipelineStages {
@@ -115,6 +124,15 @@ macro_rules! access_flags {
                     )+
                 }
             }
+
+            // Returns the names of the access types whose bit is set in `bits`.
+            pub(crate) fn names_from_vk(bits: vk::AccessFlagBits) -> Vec<&'static str> {
+                let mut names = Vec::new();
+                $(
+                    if (bits & $val) != 0 { names.push(stringify!($elem)); }
+                )+
+                names
+            }
         }
 
         impl ops::BitOr for AccessFlagBits {
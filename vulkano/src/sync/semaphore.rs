@@ -10,6 +10,8 @@
 use std::mem;
 use std::ptr;
 use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
 
 use OomError;
 use SafeDeref;
@@ -29,6 +31,9 @@ pub struct Semaphore<D = Arc<Device>>
 {
     semaphore: vk::Semaphore,
     device: D,
+    // True if a wait operation submitted through `wait_external_semaphore` hasn't finished
+    // executing yet.
+    wait_pending: AtomicBool,
 }
 
 impl<D> Semaphore<D>
@@ -50,15 +55,38 @@ impl<D> Semaphore<D>
             check_errors(vk.CreateSemaphore(device.internal_object(),
                                             &INFOS,
                                             ptr::null(),
-                                            &mut output))?;
+                                            &mut output),
+                         "vkCreateSemaphore")?;
             output
         };
 
         Ok(Semaphore {
                device: device,
                semaphore: semaphore,
+               wait_pending: AtomicBool::new(false),
            })
     }
+
+    /// Returns true if a wait operation on this semaphore submitted through
+    /// `wait_external_semaphore` hasn't finished executing yet.
+    ///
+    /// While this is the case, vulkano refuses to submit another operation on this semaphore.
+    #[inline]
+    pub fn is_wait_pending(&self) -> bool {
+        self.wait_pending.load(Ordering::SeqCst)
+    }
+
+    /// Marks the semaphore as being waited upon. Returns false if it already was.
+    #[inline]
+    pub(crate) fn try_reserve_wait(&self) -> bool {
+        !self.wait_pending.swap(true, Ordering::SeqCst)
+    }
+
+    /// Marks the wait operation on this semaphore as finished.
+    #[inline]
+    pub(crate) fn release_wait(&self) {
+        self.wait_pending.store(false, Ordering::SeqCst);
+    }
 }
 
 unsafe impl DeviceOwned for Semaphore {
@@ -93,11 +121,86 @@ impl<D> Drop for Semaphore<D>
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+
+    use command_buffer::AutoCommandBufferBuilder;
+    use command_buffer::CommandBuffer;
+    use sync::FlushError;
+    use sync::GpuFuture;
     use sync::Semaphore;
+    use sync::now;
+    use sync::wait_external_semaphore;
 
     #[test]
     fn semaphore_create() {
         let (device, _) = gfx_dev_and_queue!();
         let _ = Semaphore::new(device.clone());
     }
+
+    #[test]
+    fn external_signal_then_wait() {
+        let (device, queue) = gfx_dev_and_queue!();
+        let semaphore = Arc::new(Semaphore::new(device.clone()).unwrap());
+
+        let cb1 = AutoCommandBufferBuilder::new(device.clone(), queue.family())
+            .unwrap()
+            .build()
+            .unwrap();
+        let cb2 = AutoCommandBufferBuilder::new(device.clone(), queue.family())
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let signal = cb1.execute(queue.clone())
+            .unwrap()
+            .then_signal_external_semaphore(&semaphore);
+        signal.flush().unwrap();
+
+        let wait = wait_external_semaphore(semaphore.clone())
+            .then_execute(queue.clone(), cb2)
+            .unwrap()
+            .then_signal_fence_and_flush()
+            .unwrap();
+        assert!(semaphore.is_wait_pending());
+        wait.wait(None).unwrap();
+        assert!(!semaphore.is_wait_pending());
+    }
+
+    #[test]
+    fn external_reuse_before_wait() {
+        let (device, queue) = gfx_dev_and_queue!();
+        let semaphore = Arc::new(Semaphore::new(device.clone()).unwrap());
+
+        let cb1 = AutoCommandBufferBuilder::new(device.clone(), queue.family())
+            .unwrap()
+            .build()
+            .unwrap();
+        let cb2 = AutoCommandBufferBuilder::new(device.clone(), queue.family())
+            .unwrap()
+            .build()
+            .unwrap();
+
+        // Pretend that a previous wait operation hasn't finished executing.
+        assert!(semaphore.try_reserve_wait());
+
+        let signal = cb1.execute(queue.clone())
+            .unwrap()
+            .then_signal_external_semaphore(&semaphore);
+        match signal.flush() {
+            Err(FlushError::SemaphoreWaitPending) => (),
+            _ => panic!(),
+        }
+
+        let wait = wait_external_semaphore(semaphore.clone())
+            .then_execute(queue.clone(), cb2)
+            .unwrap();
+        match wait.flush() {
+            Err(FlushError::SemaphoreWaitPending) => (),
+            _ => panic!(),
+        }
+
+        semaphore.release_wait();
+        signal.flush().unwrap();
+        wait.then_signal_fence_and_flush().unwrap().wait(None).unwrap();
+    }
 }
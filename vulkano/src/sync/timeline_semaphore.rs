@@ -0,0 +1,485 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::error;
+use std::fmt;
+use std::mem;
+use std::ptr;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::time::Duration;
+
+use Error;
+use OomError;
+use Success;
+use VulkanObject;
+use check_errors;
+use debug_name::DebugName;
+use device::Device;
+use device::DeviceOwned;
+use vk;
+
+/// A semaphore whose state is a monotonically increasing 64-bit counter.
+///
+/// Contrary to a regular `Semaphore`, a timeline semaphore is never reset. Instead, submissions
+/// wait until the counter reaches a certain value, and signal it by setting it to a higher value.
+/// The same value can be waited upon by any number of submissions.
+///
+/// Timeline semaphores require the `VK_KHR_timeline_semaphore` extension to be enabled on the
+/// device, or the device to support Vulkan 1.2.
+#[derive(Debug)]
+pub struct TimelineSemaphore {
+    semaphore: vk::Semaphore,
+    device: Arc<Device>,
+    // Highest value that a submission has been asked to signal. Signal operations must always
+    // strictly increase the value of the counter.
+    pending_value: Mutex<u64>,
+    // Name given with `set_debug_name`.
+    name: DebugName,
+}
+
+impl TimelineSemaphore {
+    /// Builds a new timeline semaphore whose counter starts at `initial_value`.
+    pub fn new(device: Arc<Device>, initial_value: u64)
+               -> Result<TimelineSemaphore, TimelineSemaphoreCreationError> {
+        if !device.effective_extensions().khr_timeline_semaphore {
+            return Err(TimelineSemaphoreCreationError::TimelineSemaphoreExtensionNotEnabled);
+        }
+
+        let semaphore = unsafe {
+            let type_infos = vk::SemaphoreTypeCreateInfoKHR {
+                sType: vk::STRUCTURE_TYPE_SEMAPHORE_TYPE_CREATE_INFO_KHR,
+                pNext: ptr::null(),
+                semaphoreType: vk::SEMAPHORE_TYPE_TIMELINE_KHR,
+                initialValue: initial_value,
+            };
+
+            let infos = vk::SemaphoreCreateInfo {
+                sType: vk::STRUCTURE_TYPE_SEMAPHORE_CREATE_INFO,
+                pNext: &type_infos as *const _ as *const _,
+                flags: 0, // reserved
+            };
+
+            let vk = device.pointers();
+            let mut output = mem::uninitialized();
+            check_errors(vk.CreateSemaphore(device.internal_object(),
+                                            &infos,
+                                            ptr::null(),
+                                            &mut output),
+                         "vkCreateSemaphore")?;
+            output
+        };
+
+        Ok(TimelineSemaphore {
+               semaphore: semaphore,
+               device: device,
+               pending_value: Mutex::new(initial_value),
+               name: DebugName::new(),
+           })
+    }
+
+    /// Gives a name to the semaphore.
+    ///
+    /// The name is attached to the errors returned by the methods of the semaphore. If the
+    /// `ext_debug_utils` extension is enabled on the instance, it is also passed to the
+    /// implementation and shows up in the messages of the validation layers.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `name` contains a nul character.
+    ///
+    #[inline]
+    pub fn set_debug_name(&self, name: &str) -> Result<(), OomError> {
+        self.name.set(&self.device, vk::OBJECT_TYPE_SEMAPHORE, self.semaphore, name)
+    }
+
+    /// Returns the name given with `set_debug_name`, if any.
+    #[inline]
+    pub fn debug_name(&self) -> Option<Arc<str>> {
+        self.name.get()
+    }
+
+    /// Returns the current value of the counter of the semaphore.
+    pub fn value(&self) -> Result<u64, OomError> {
+        unsafe {
+            let vk = self.device.pointers();
+            let mut output = 0;
+            check_errors(vk.GetSemaphoreCounterValueKHR(self.device.internal_object(),
+                                                        self.semaphore,
+                                                        &mut output),
+                         "vkGetSemaphoreCounterValueKHR")
+                .map_err(|err| err.with_object_name(self.name.get()))?;
+            Ok(output)
+        }
+    }
+
+    /// Sets the counter of the semaphore to `value` from the host.
+    ///
+    /// This unblocks the submissions and the host threads that are waiting for a value inferior
+    /// or equal to `value`. Just like for signal operations performed by the GPU, `value` must be
+    /// strictly superior to the `pending_value()` of the semaphore, otherwise an error is
+    /// returned. The pending value is left untouched if signaling fails.
+    pub fn signal(&self, value: u64) -> Result<(), TimelineSemaphoreSignalError> {
+        // The lock is held until the semaphore has been signaled, so that no submission can use
+        // a value between the current pending value and `value` in the meantime.
+        let mut pending = self.pending_value.lock().unwrap();
+        if value <= *pending {
+            return Err(TimelineSemaphoreSignalError::ValueNotIncreasing {
+                           value: value,
+                           pending: *pending,
+                       });
+        }
+
+        unsafe {
+            let infos = vk::SemaphoreSignalInfoKHR {
+                sType: vk::STRUCTURE_TYPE_SEMAPHORE_SIGNAL_INFO_KHR,
+                pNext: ptr::null(),
+                semaphore: self.semaphore,
+                value: value,
+            };
+
+            let vk = self.device.pointers();
+            check_errors(vk.SignalSemaphoreKHR(self.device.internal_object(), &infos),
+                         "vkSignalSemaphoreKHR")
+                .map_err(|err| err.with_object_name(self.name.get()))?;
+        }
+
+        *pending = value;
+        Ok(())
+    }
+
+    /// Blocks the current thread until the counter of the semaphore reaches at least `value`.
+    ///
+    /// If `timeout` is `None`, then the wait is infinite. Otherwise the thread will unblock after
+    /// the specified timeout has elapsed and an error will be returned.
+    pub fn wait(&self, value: u64, timeout: Option<Duration>)
+                -> Result<(), TimelineSemaphoreWaitError> {
+        unsafe {
+            let timeout_ns = if let Some(timeout) = timeout {
+                timeout
+                    .as_secs()
+                    .saturating_mul(1_000_000_000)
+                    .saturating_add(timeout.subsec_nanos() as u64)
+            } else {
+                u64::max_value()
+            };
+
+            let infos = vk::SemaphoreWaitInfoKHR {
+                sType: vk::STRUCTURE_TYPE_SEMAPHORE_WAIT_INFO_KHR,
+                pNext: ptr::null(),
+                flags: 0,
+                semaphoreCount: 1,
+                pSemaphores: &self.semaphore,
+                pValues: &value,
+            };
+
+            let vk = self.device.pointers();
+            let r = check_errors(vk.WaitSemaphoresKHR(self.device.internal_object(),
+                                                      &infos,
+                                                      timeout_ns),
+                                 "vkWaitSemaphoresKHR")
+                .map_err(|err| err.with_object_name(self.name.get()))?;
+
+            match r {
+                Success::Success => Ok(()),
+                Success::Timeout => Err(TimelineSemaphoreWaitError::Timeout),
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    /// Returns the highest value that a submission has been asked to signal, or the initial
+    /// value if nothing has been submitted yet.
+    ///
+    /// Any new signal operation must use a value strictly superior to this one.
+    #[inline]
+    pub fn pending_value(&self) -> u64 {
+        *self.pending_value.lock().unwrap()
+    }
+
+    /// Locks the pending value of the semaphore.
+    ///
+    /// Submissions check and update the pending value while holding the lock, so that no other
+    /// signal operation can be recorded between the check and the submission.
+    #[inline]
+    pub(crate) fn lock_pending_value(&self) -> MutexGuard<u64> {
+        self.pending_value.lock().unwrap()
+    }
+}
+
+unsafe impl DeviceOwned for TimelineSemaphore {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+}
+
+unsafe impl VulkanObject for TimelineSemaphore {
+    type Object = vk::Semaphore;
+
+    #[inline]
+    fn internal_object(&self) -> vk::Semaphore {
+        self.semaphore
+    }
+}
+
+impl Drop for TimelineSemaphore {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe {
+            let vk = self.device.pointers();
+            vk.DestroySemaphore(self.device.internal_object(), self.semaphore, ptr::null());
+        }
+    }
+}
+
+/// Error that can be returned when creating a timeline semaphore.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TimelineSemaphoreCreationError {
+    /// Not enough memory.
+    OomError(OomError),
+    /// The `VK_KHR_timeline_semaphore` extension was not enabled on the device and the device
+    /// doesn't support Vulkan 1.2.
+    TimelineSemaphoreExtensionNotEnabled,
+}
+
+impl error::Error for TimelineSemaphoreCreationError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            TimelineSemaphoreCreationError::OomError(_) => "not enough memory",
+            TimelineSemaphoreCreationError::TimelineSemaphoreExtensionNotEnabled => {
+                "the `VK_KHR_timeline_semaphore` extension was not enabled on the device"
+            },
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            TimelineSemaphoreCreationError::OomError(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for TimelineSemaphoreCreationError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl From<OomError> for TimelineSemaphoreCreationError {
+    #[inline]
+    fn from(err: OomError) -> TimelineSemaphoreCreationError {
+        TimelineSemaphoreCreationError::OomError(err)
+    }
+}
+
+impl From<Error> for TimelineSemaphoreCreationError {
+    #[inline]
+    fn from(err: Error) -> TimelineSemaphoreCreationError {
+        match err {
+            err @ Error::OutOfHostMemory => {
+                TimelineSemaphoreCreationError::OomError(OomError::from(err))
+            },
+            err @ Error::OutOfDeviceMemory => {
+                TimelineSemaphoreCreationError::OomError(OomError::from(err))
+            },
+            _ => panic!("unexpected error: {:?}", err),
+        }
+    }
+}
+
+/// Error that can be returned when signaling a timeline semaphore from the host.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TimelineSemaphoreSignalError {
+    /// Not enough memory.
+    OomError(OomError),
+    /// The value isn't strictly superior to the highest value that has already been signaled or
+    /// submitted for signaling.
+    ValueNotIncreasing {
+        /// The value that was passed.
+        value: u64,
+        /// The highest value signaled or submitted for signaling so far.
+        pending: u64,
+    },
+}
+
+impl error::Error for TimelineSemaphoreSignalError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            TimelineSemaphoreSignalError::OomError(_) => "not enough memory",
+            TimelineSemaphoreSignalError::ValueNotIncreasing { .. } => {
+                "the value doesn't strictly increase the counter of the semaphore"
+            },
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            TimelineSemaphoreSignalError::OomError(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for TimelineSemaphoreSignalError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl From<Error> for TimelineSemaphoreSignalError {
+    #[inline]
+    fn from(err: Error) -> TimelineSemaphoreSignalError {
+        match err {
+            err @ Error::OutOfHostMemory => {
+                TimelineSemaphoreSignalError::OomError(OomError::from(err))
+            },
+            err @ Error::OutOfDeviceMemory => {
+                TimelineSemaphoreSignalError::OomError(OomError::from(err))
+            },
+            _ => panic!("unexpected error: {:?}", err),
+        }
+    }
+}
+
+/// Error that can be returned when waiting for a timeline semaphore from the host.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TimelineSemaphoreWaitError {
+    /// Not enough memory to complete the wait.
+    OomError(OomError),
+
+    /// The specified timeout wasn't long enough.
+    Timeout,
+
+    /// The device has been lost.
+    DeviceLostError,
+}
+
+impl error::Error for TimelineSemaphoreWaitError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            TimelineSemaphoreWaitError::OomError(_) => "no memory available",
+            TimelineSemaphoreWaitError::Timeout => "the timeout has been reached",
+            TimelineSemaphoreWaitError::DeviceLostError => "the device was lost",
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            TimelineSemaphoreWaitError::OomError(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for TimelineSemaphoreWaitError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl From<Error> for TimelineSemaphoreWaitError {
+    #[inline]
+    fn from(err: Error) -> TimelineSemaphoreWaitError {
+        match err {
+            Error::OutOfHostMemory => TimelineSemaphoreWaitError::OomError(From::from(err)),
+            Error::OutOfDeviceMemory => TimelineSemaphoreWaitError::OomError(From::from(err)),
+            Error::DeviceLost => TimelineSemaphoreWaitError::DeviceLostError,
+            _ => panic!("Unexpected error value: {}", err as i32),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use sync::TimelineSemaphore;
+    use sync::TimelineSemaphoreCreationError;
+    use sync::TimelineSemaphoreSignalError;
+    use sync::TimelineSemaphoreWaitError;
+
+    #[test]
+    fn extension_not_enabled() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        match TimelineSemaphore::new(device.clone(), 0) {
+            Err(TimelineSemaphoreCreationError::TimelineSemaphoreExtensionNotEnabled) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn host_signal_and_wait() {
+        let (device, _) = gfx_dev_and_queue!(extensions: [khr_timeline_semaphore]);
+        let semaphore = TimelineSemaphore::new(device.clone(), 2).unwrap();
+        assert_eq!(semaphore.value().unwrap(), 2);
+
+        // Values that have already been reached don't block.
+        semaphore.wait(1, Some(Duration::from_millis(0))).unwrap();
+        match semaphore.wait(3, Some(Duration::from_millis(0))) {
+            Err(TimelineSemaphoreWaitError::Timeout) => (),
+            _ => panic!(),
+        }
+
+        semaphore.signal(5).unwrap();
+        assert_eq!(semaphore.pending_value(), 5);
+        assert_eq!(semaphore.value().unwrap(), 5);
+        semaphore.wait(3, Some(Duration::from_millis(0))).unwrap();
+        semaphore.wait(5, None).unwrap();
+    }
+
+    #[test]
+    fn host_signal_not_increasing() {
+        let (device, _) = gfx_dev_and_queue!(extensions: [khr_timeline_semaphore]);
+        let semaphore = TimelineSemaphore::new(device.clone(), 4).unwrap();
+
+        match semaphore.signal(4) {
+            Err(TimelineSemaphoreSignalError::ValueNotIncreasing {
+                    value: 4,
+                    pending: 4,
+                }) => (),
+            _ => panic!(),
+        }
+        match semaphore.signal(3) {
+            Err(TimelineSemaphoreSignalError::ValueNotIncreasing {
+                    value: 3,
+                    pending: 4,
+                }) => (),
+            _ => panic!(),
+        }
+
+        // The rejected signals didn't change anything.
+        assert_eq!(semaphore.pending_value(), 4);
+        assert_eq!(semaphore.value().unwrap(), 4);
+        semaphore.signal(6).unwrap();
+        assert_eq!(semaphore.pending_value(), 6);
+    }
+
+    #[test]
+    fn debug_name() {
+        let (device, _) = gfx_dev_and_queue!(extensions: [khr_timeline_semaphore]);
+        let semaphore = TimelineSemaphore::new(device.clone(), 0).unwrap();
+
+        assert_eq!(semaphore.debug_name(), None);
+        semaphore.set_debug_name("frame counter").unwrap();
+        assert_eq!(semaphore.debug_name(), Some("frame counter".into()));
+    }
+}
@@ -9,6 +9,33 @@
 
 #![cfg(test)]
 
+use std::ffi::CStr;
+use std::sync::Arc;
+
+use buffer::BufferUsage;
+use buffer::CpuAccessibleBuffer;
+use command_buffer::AutoCommandBufferBuilder;
+use descriptor::descriptor::DescriptorBufferContentDesc;
+use descriptor::descriptor::DescriptorBufferDesc;
+use descriptor::descriptor::DescriptorDesc;
+use descriptor::descriptor::DescriptorDescTy;
+use descriptor::descriptor::DescriptorImageDesc;
+use descriptor::descriptor::DescriptorImageDescArray;
+use descriptor::descriptor::DescriptorImageDescDimensions;
+use descriptor::descriptor::ShaderStages;
+use descriptor::descriptor_set::SimpleDescriptorSetBufferExt;
+use descriptor::descriptor_set::SimpleDescriptorSetBuilder;
+use descriptor::descriptor_set::SimpleDescriptorSetImageExt;
+use descriptor::pipeline_layout::PipelineLayoutDesc;
+use descriptor::pipeline_layout::PipelineLayoutDescNames;
+use descriptor::pipeline_layout::PipelineLayoutDescPcRange;
+use device::Queue;
+use image::ImageViewAccess;
+use pipeline::ComputePipeline;
+use pipeline::shader::ShaderModule;
+use sampler::Sampler;
+use sync::GpuFuture;
+
 /// Creates an instance or returns if initialization fails.
 macro_rules! instance {
     () => ({
@@ -22,8 +49,11 @@ macro_rules! instance {
 }
 
 /// Creates a device and a queue for graphics operations.
+///
+/// The extensions listed in `extensions: [...]` and the features that follow are enabled on the
+/// device. Returns if the physical device doesn't support them.
 macro_rules! gfx_dev_and_queue {
-    ($($feature:ident),*) => ({
+    (extensions: [$($extension:ident),*] $(, $feature:ident)*) => ({
         use instance;
         use device::Device;
         use device::DeviceExtensions;
@@ -41,7 +71,18 @@ macro_rules! gfx_dev_and_queue {
             None => return
         };
 
-        let extensions = DeviceExtensions::none();
+        let extensions = DeviceExtensions {
+            $(
+                $extension: true,
+            )*
+            .. DeviceExtensions::none()
+        };
+
+        // If the physical device doesn't support the requested extensions, just return.
+        let supported = DeviceExtensions::supported_by_device(&physical);
+        if extensions.difference(&supported) != DeviceExtensions::none() {
+            return;
+        }
 
         let features = Features {
             $(
@@ -64,4 +105,241 @@ macro_rules! gfx_dev_and_queue {
 
         (device, queues.next().unwrap())
     });
+
+    ($($feature:ident),*) => (gfx_dev_and_queue!(extensions: [] $(, $feature)*));
+}
+
+/// Samples `view` with `sampler` from a compute shader, after `before`, and returns the values.
+///
+/// Each point is made of the normalized coordinates and the level of detail to sample at. The
+/// view must be a two-dimensional, non-arrayed view of a floating-point image.
+pub fn sample_image<F, V>(before: F, queue: Arc<Queue>, view: V, sampler: Arc<Sampler>,
+                          points: &[[f32; 3]])
+                          -> Vec<[f32; 4]>
+    where F: GpuFuture,
+          V: ImageViewAccess + Send + Sync + 'static
+{
+    let device = queue.device().clone();
+
+    let module = unsafe { ShaderModule::new(device.clone(), &SAMPLE_CS) }.unwrap();
+    let main = unsafe {
+        module.compute_shader_entry_point::<(), _>(CStr::from_bytes_with_nul_unchecked(b"main\0"),
+                                                   SampleDesc)
+    };
+    let pipeline = Arc::new(ComputePipeline::new(device.clone(), &main, &()).unwrap());
+
+    let data = CpuAccessibleBuffer::from_iter(device.clone(),
+                                              BufferUsage::all(),
+                                              Some(queue.family()),
+                                              points.iter().map(|p| [p[0], p[1], p[2], 0.0]))
+        .unwrap();
+
+    let set = {
+        let builder = SimpleDescriptorSetBuilder::new(pipeline.clone(), 0);
+        let builder = SimpleDescriptorSetImageExt::add_me((view, sampler), builder, "tex")
+            .unwrap();
+        Arc::new(SimpleDescriptorSetBufferExt::add_me(data.clone(), builder, "data")
+                     .unwrap()
+                     .build().unwrap())
+    };
+
+    let cb = AutoCommandBufferBuilder::new(device.clone(), queue.family())
+        .unwrap()
+        .dispatch([points.len() as u32, 1, 1], pipeline.clone(), set, ())
+        .unwrap()
+        .build()
+        .unwrap();
+
+    before
+        .then_execute(queue, cb)
+        .unwrap()
+        .then_signal_fence_and_flush()
+        .unwrap()
+        .wait(None)
+        .unwrap();
+
+    let content = data.read().unwrap();
+    content.to_vec()
+}
+
+// Pipeline layout of `SAMPLE_CS`, with a sampled image named `tex` and a storage buffer named
+// `data`.
+#[derive(Debug, Copy, Clone)]
+struct SampleDesc;
+
+unsafe impl PipelineLayoutDesc for SampleDesc {
+    fn num_sets(&self) -> usize {
+        1
+    }
+
+    fn num_bindings_in_set(&self, set: usize) -> Option<usize> {
+        if set == 0 { Some(2) } else { None }
+    }
+
+    fn descriptor(&self, set: usize, binding: usize) -> Option<DescriptorDesc> {
+        let ty = match (set, binding) {
+            (0, 0) => {
+                DescriptorDescTy::CombinedImageSampler(DescriptorImageDesc {
+                    sampled: true,
+                    dimensions: DescriptorImageDescDimensions::TwoDimensional,
+                    format: None,
+                    multisampled: false,
+                    array_layers: DescriptorImageDescArray::NonArrayed,
+                })
+            },
+            (0, 1) => {
+                DescriptorDescTy::Buffer(DescriptorBufferDesc {
+                                             dynamic: Some(false),
+                                             storage: true,
+                                             content: DescriptorBufferContentDesc::F32,
+                                         })
+            },
+            _ => return None,
+        };
+
+        Some(DescriptorDesc {
+                 ty: ty,
+                 array_count: 1,
+                 stages: ShaderStages::compute(),
+                 readonly: binding == 0,
+             })
+    }
+
+    fn num_push_constants_ranges(&self) -> usize {
+        0
+    }
+
+    fn push_constants_range(&self, _: usize) -> Option<PipelineLayoutDescPcRange> {
+        None
+    }
+}
+
+unsafe impl PipelineLayoutDescNames for SampleDesc {
+    fn descriptor_by_name(&self, name: &str) -> Option<(usize, usize)> {
+        match name {
+            "tex" => Some((0, 0)),
+            "data" => Some((0, 1)),
+            _ => None,
+        }
+    }
 }
+
+/*
+    SPIR-V 1.0 module, assembled by hand.
+
+    layout(local_size_x = 1) in;
+    layout(set = 0, binding = 0) uniform sampler2D tex;
+    layout(set = 0, binding = 1) buffer Data { vec4 data[]; } buf;
+
+    void main() {
+        uint i = gl_GlobalInvocationID.x;
+        vec4 point = buf.data[i];
+        buf.data[i] = textureLod(tex, point.xy, point.z);
+    }
+*/
+const SAMPLE_CS: [u8; 744] = [3, 2, 35, 7, 0, 0, 1, 0, 0, 0, 0, 0, 31, 0, 0, 0, 0, 0, 0, 0, 17, 0,
+                              2, 0, 1, 0, 0, 0, 14, 0, 3, 0, 0, 0, 0, 0, 1, 0, 0, 0, 15, 0, 6, 0, 5,
+                              0, 0, 0, 1, 0, 0, 0, 109, 97, 105, 110, 0, 0, 0, 0, 2, 0, 0, 0, 16, 0,
+                              6, 0, 1, 0, 0, 0, 17, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 71,
+                              0, 4, 0, 2, 0, 0, 0, 11, 0, 0, 0, 28, 0, 0, 0, 71, 0, 4, 0, 3, 0, 0,
+                              0, 34, 0, 0, 0, 0, 0, 0, 0, 71, 0, 4, 0, 3, 0, 0, 0, 33, 0, 0, 0, 0,
+                              0, 0, 0, 71, 0, 4, 0, 4, 0, 0, 0, 6, 0, 0, 0, 16, 0, 0, 0, 72, 0, 5,
+                              0, 5, 0, 0, 0, 0, 0, 0, 0, 35, 0, 0, 0, 0, 0, 0, 0, 71, 0, 3, 0, 5, 0,
+                              0, 0, 3, 0, 0, 0, 71, 0, 4, 0, 6, 0, 0, 0, 34, 0, 0, 0, 0, 0, 0, 0,
+                              71, 0, 4, 0, 6, 0, 0, 0, 33, 0, 0, 0, 1, 0, 0, 0, 19, 0, 2, 0, 7, 0,
+                              0, 0, 33, 0, 3, 0, 8, 0, 0, 0, 7, 0, 0, 0, 22, 0, 3, 0, 9, 0, 0, 0,
+                              32, 0, 0, 0, 23, 0, 4, 0, 10, 0, 0, 0, 9, 0, 0, 0, 4, 0, 0, 0, 23, 0,
+                              4, 0, 11, 0, 0, 0, 9, 0, 0, 0, 2, 0, 0, 0, 21, 0, 4, 0, 12, 0, 0, 0,
+                              32, 0, 0, 0, 0, 0, 0, 0, 21, 0, 4, 0, 13, 0, 0, 0, 32, 0, 0, 0, 1, 0,
+                              0, 0, 23, 0, 4, 0, 14, 0, 0, 0, 12, 0, 0, 0, 3, 0, 0, 0, 25, 0, 9, 0,
+                              15, 0, 0, 0, 9, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+                              0, 1, 0, 0, 0, 0, 0, 0, 0, 27, 0, 3, 0, 16, 0, 0, 0, 15, 0, 0, 0, 32,
+                              0, 4, 0, 17, 0, 0, 0, 0, 0, 0, 0, 16, 0, 0, 0, 29, 0, 3, 0, 4, 0, 0,
+                              0, 10, 0, 0, 0, 30, 0, 3, 0, 5, 0, 0, 0, 4, 0, 0, 0, 32, 0, 4, 0, 18,
+                              0, 0, 0, 2, 0, 0, 0, 5, 0, 0, 0, 32, 0, 4, 0, 19, 0, 0, 0, 1, 0, 0, 0,
+                              14, 0, 0, 0, 32, 0, 4, 0, 20, 0, 0, 0, 2, 0, 0, 0, 10, 0, 0, 0, 59, 0,
+                              4, 0, 17, 0, 0, 0, 3, 0, 0, 0, 0, 0, 0, 0, 59, 0, 4, 0, 18, 0, 0, 0,
+                              6, 0, 0, 0, 2, 0, 0, 0, 59, 0, 4, 0, 19, 0, 0, 0, 2, 0, 0, 0, 1, 0, 0,
+                              0, 43, 0, 4, 0, 13, 0, 0, 0, 21, 0, 0, 0, 0, 0, 0, 0, 54, 0, 5, 0, 7,
+                              0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 8, 0, 0, 0, 248, 0, 2, 0, 22, 0, 0,
+                              0, 61, 0, 4, 0, 14, 0, 0, 0, 23, 0, 0, 0, 2, 0, 0, 0, 81, 0, 5, 0, 12,
+                              0, 0, 0, 24, 0, 0, 0, 23, 0, 0, 0, 0, 0, 0, 0, 65, 0, 6, 0, 20, 0, 0,
+                              0, 25, 0, 0, 0, 6, 0, 0, 0, 21, 0, 0, 0, 24, 0, 0, 0, 61, 0, 4, 0, 10,
+                              0, 0, 0, 26, 0, 0, 0, 25, 0, 0, 0, 79, 0, 7, 0, 11, 0, 0, 0, 27, 0, 0,
+                              0, 26, 0, 0, 0, 26, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 81, 0, 5, 0, 9,
+                              0, 0, 0, 28, 0, 0, 0, 26, 0, 0, 0, 2, 0, 0, 0, 61, 0, 4, 0, 16, 0, 0,
+                              0, 29, 0, 0, 0, 3, 0, 0, 0, 88, 0, 7, 0, 10, 0, 0, 0, 30, 0, 0, 0, 29,
+                              0, 0, 0, 27, 0, 0, 0, 2, 0, 0, 0, 28, 0, 0, 0, 62, 0, 3, 0, 25, 0, 0,
+                              0, 30, 0, 0, 0, 253, 0, 1, 0, 56, 0, 1, 0];
+
+/*
+    SPIR-V 1.0 module, assembled by hand. Draws a triangle that covers the whole viewport.
+
+    void main() {
+        gl_Position = vec4(gl_VertexIndex == 1 ? 3.0 : -1.0,
+                           gl_VertexIndex == 2 ? 3.0 : -1.0, 0.0, 1.0);
+    }
+*/
+pub const FULL_SCREEN_VS: [u8; 512] = [3, 2, 35, 7, 0, 0, 1, 0, 0, 0, 0, 0, 25, 0, 0, 0, 0, 0, 0, 0,
+                                       17, 0, 2, 0, 1, 0, 0, 0, 14, 0, 3, 0, 0, 0, 0, 0, 1, 0, 0, 0,
+                                       15, 0, 7, 0, 0, 0, 0, 0, 1, 0, 0, 0, 109, 97, 105, 110, 0, 0,
+                                       0, 0, 2, 0, 0, 0, 3, 0, 0, 0, 71, 0, 4, 0, 2, 0, 0, 0, 11, 0,
+                                       0, 0, 42, 0, 0, 0, 71, 0, 4, 0, 3, 0, 0, 0, 11, 0, 0, 0, 0,
+                                       0, 0, 0, 19, 0, 2, 0, 4, 0, 0, 0, 33, 0, 3, 0, 5, 0, 0, 0, 4,
+                                       0, 0, 0, 21, 0, 4, 0, 6, 0, 0, 0, 32, 0, 0, 0, 1, 0, 0, 0,
+                                       22, 0, 3, 0, 7, 0, 0, 0, 32, 0, 0, 0, 23, 0, 4, 0, 8, 0, 0,
+                                       0, 7, 0, 0, 0, 4, 0, 0, 0, 20, 0, 2, 0, 9, 0, 0, 0, 32, 0, 4,
+                                       0, 10, 0, 0, 0, 1, 0, 0, 0, 6, 0, 0, 0, 32, 0, 4, 0, 11, 0,
+                                       0, 0, 3, 0, 0, 0, 8, 0, 0, 0, 59, 0, 4, 0, 10, 0, 0, 0, 2, 0,
+                                       0, 0, 1, 0, 0, 0, 59, 0, 4, 0, 11, 0, 0, 0, 3, 0, 0, 0, 3, 0,
+                                       0, 0, 43, 0, 4, 0, 6, 0, 0, 0, 12, 0, 0, 0, 1, 0, 0, 0, 43,
+                                       0, 4, 0, 6, 0, 0, 0, 13, 0, 0, 0, 2, 0, 0, 0, 43, 0, 4, 0, 7,
+                                       0, 0, 0, 14, 0, 0, 0, 0, 0, 128, 191, 43, 0, 4, 0, 7, 0, 0,
+                                       0, 15, 0, 0, 0, 0, 0, 64, 64, 43, 0, 4, 0, 7, 0, 0, 0, 16, 0,
+                                       0, 0, 0, 0, 0, 0, 43, 0, 4, 0, 7, 0, 0, 0, 17, 0, 0, 0, 0, 0,
+                                       128, 63, 54, 0, 5, 0, 4, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 5,
+                                       0, 0, 0, 248, 0, 2, 0, 18, 0, 0, 0, 61, 0, 4, 0, 6, 0, 0, 0,
+                                       19, 0, 0, 0, 2, 0, 0, 0, 170, 0, 5, 0, 9, 0, 0, 0, 20, 0, 0,
+                                       0, 19, 0, 0, 0, 12, 0, 0, 0, 170, 0, 5, 0, 9, 0, 0, 0, 21, 0,
+                                       0, 0, 19, 0, 0, 0, 13, 0, 0, 0, 169, 0, 6, 0, 7, 0, 0, 0, 22,
+                                       0, 0, 0, 20, 0, 0, 0, 15, 0, 0, 0, 14, 0, 0, 0, 169, 0, 6, 0,
+                                       7, 0, 0, 0, 23, 0, 0, 0, 21, 0, 0, 0, 15, 0, 0, 0, 14, 0, 0,
+                                       0, 80, 0, 7, 0, 8, 0, 0, 0, 24, 0, 0, 0, 22, 0, 0, 0, 23, 0,
+                                       0, 0, 16, 0, 0, 0, 17, 0, 0, 0, 62, 0, 3, 0, 3, 0, 0, 0, 24,
+                                       0, 0, 0, 253, 0, 1, 0, 56, 0, 1, 0];
+
+/*
+    #version 450
+
+    #extension GL_ARB_separate_shader_objects : enable
+    #extension GL_ARB_shading_language_420pack : enable
+
+    layout(location = 0) out vec4 f_color;
+
+    void main() {
+        f_color = vec4(1.0, 0.0, 0.0, 1.0);
+    }
+*/
+pub const BASIC_FS: [u8; 420] = [3, 2, 35, 7, 0, 0, 1, 0, 1, 0, 8, 0, 13, 0, 0, 0, 0, 0, 0, 0, 17,
+                                 0, 2, 0, 1, 0, 0, 0, 11, 0, 6, 0, 1, 0, 0, 0, 71, 76, 83, 76, 46,
+                                 115, 116, 100, 46, 52, 53, 48, 0, 0, 0, 0, 14, 0, 3, 0, 0, 0, 0,
+                                 0, 1, 0, 0, 0, 15, 0, 6, 0, 4, 0, 0, 0, 4, 0, 0, 0, 109, 97,
+                                 105, 110, 0, 0, 0, 0, 9, 0, 0, 0, 16, 0, 3, 0, 4, 0, 0, 0, 7, 0,
+                                 0, 0, 3, 0, 3, 0, 2, 0, 0, 0, 194, 1, 0, 0, 4, 0, 9, 0, 71, 76,
+                                 95, 65, 82, 66, 95, 115, 101, 112, 97, 114, 97, 116, 101, 95,
+                                 115, 104, 97, 100, 101, 114, 95, 111, 98, 106, 101, 99, 116, 115,
+                                 0, 0, 4, 0, 9, 0, 71, 76, 95, 65, 82, 66, 95, 115, 104, 97, 100,
+                                 105, 110, 103, 95, 108, 97, 110, 103, 117, 97, 103, 101, 95, 52,
+                                 50, 48, 112, 97, 99, 107, 0, 5, 0, 4, 0, 4, 0, 0, 0, 109, 97,
+                                 105, 110, 0, 0, 0, 0, 5, 0, 4, 0, 9, 0, 0, 0, 102, 95, 99, 111,
+                                 108, 111, 114, 0, 71, 0, 4, 0, 9, 0, 0, 0, 30, 0, 0, 0, 0, 0,
+                                 0, 0, 19, 0, 2, 0, 2, 0, 0, 0, 33, 0, 3, 0, 3, 0, 0, 0, 2, 0, 0,
+                                 0, 22, 0, 3, 0, 6, 0, 0, 0, 32, 0, 0, 0, 23, 0, 4, 0, 7, 0, 0,
+                                 0, 6, 0, 0, 0, 4, 0, 0, 0, 32, 0, 4, 0, 8, 0, 0, 0, 3, 0, 0, 0,
+                                 7, 0, 0, 0, 59, 0, 4, 0, 8, 0, 0, 0, 9, 0, 0, 0, 3, 0, 0, 0, 43,
+                                 0, 4, 0, 6, 0, 0, 0, 10, 0, 0, 0, 0, 0, 128, 63, 43, 0, 4, 0, 6,
+                                 0, 0, 0, 11, 0, 0, 0, 0, 0, 0, 0, 44, 0, 7, 0, 7, 0, 0, 0, 12, 0,
+                                 0, 0, 10, 0, 0, 0, 11, 0, 0, 0, 11, 0, 0, 0, 10, 0, 0, 0, 54, 0,
+                                 5, 0, 2, 0, 0, 0, 4, 0, 0, 0, 0, 0, 0, 0, 3, 0, 0, 0, 248, 0, 2,
+                                 0, 5, 0, 0, 0, 62, 0, 3, 0, 9, 0, 0, 0, 12, 0, 0, 0, 253, 0, 1,
+                                 0, 56, 0, 1, 0];